@@ -0,0 +1,404 @@
+#![cfg_attr(
+    not(any(
+        feature = "vulkan",
+        feature = "gl",
+        feature = "dx11",
+        feature = "dx12",
+        feature = "metal",
+    )),
+    allow(dead_code, unused_extern_crates, unused_imports)
+)]
+
+#[cfg(feature = "dx11")]
+extern crate gfx_backend_dx11 as back;
+#[cfg(feature = "dx12")]
+extern crate gfx_backend_dx12 as back;
+#[cfg(not(any(
+    feature = "vulkan",
+    feature = "gl",
+    feature = "dx11",
+    feature = "dx12",
+    feature = "metal",
+)))]
+extern crate gfx_backend_empty as back;
+#[cfg(feature = "gl")]
+extern crate gfx_backend_gl as back;
+#[cfg(feature = "metal")]
+extern crate gfx_backend_metal as back;
+#[cfg(feature = "vulkan")]
+extern crate gfx_backend_vulkan as back;
+
+use std::{fs, iter, ops::Range, ptr, slice, str::FromStr};
+
+use hal::{
+    adapter::MemoryType,
+    buffer, command, memory, pool,
+    prelude::*,
+    pso,
+    queue::{QueueFamilyId, QueueType, Schedule, SemaphorePool},
+};
+
+/// Hands a buffer over from one queue to another, transferring the ownership
+/// if the queues belong to different families.
+struct Handover<'a> {
+    buffer: &'a <back::Backend as hal::Backend>::Buffer,
+    states: Range<buffer::State>,
+    stages: Range<pso::PipelineStage>,
+    transfer: Option<memory::OwnershipTransfer<'a, back::Backend>>,
+}
+
+impl<'a> Handover<'a> {
+    fn new(
+        buffer: &'a <back::Backend as hal::Backend>::Buffer,
+        states: Range<buffer::State>,
+        stages: Range<pso::PipelineStage>,
+        families: Range<QueueFamilyId>,
+    ) -> Self {
+        let transfer = memory::OwnershipTransfer::buffer(
+            buffer,
+            buffer::SubRange::WHOLE,
+            states.clone(),
+            stages.clone(),
+            families,
+        )
+        .ok();
+        Handover {
+            buffer,
+            states,
+            stages,
+            transfer,
+        }
+    }
+
+    unsafe fn release(&self, command_buffer: &mut <back::Backend as hal::Backend>::CommandBuffer) {
+        if let Some(ref transfer) = self.transfer {
+            transfer.release(command_buffer);
+        }
+    }
+
+    unsafe fn acquire(&self, command_buffer: &mut <back::Backend as hal::Backend>::CommandBuffer) {
+        match self.transfer {
+            Some(ref transfer) => transfer.acquire(command_buffer),
+            // Within a queue, the barrier covers the commands submitted before it.
+            None => command_buffer.pipeline_barrier(
+                self.stages.clone(),
+                memory::Dependencies::empty(),
+                iter::once(memory::Barrier::whole_buffer(
+                    self.buffer,
+                    self.states.clone(),
+                )),
+            ),
+        }
+    }
+}
+
+fn main() {
+    env_logger::init();
+
+    // For now this just panics if you didn't pass numbers. Could add proper error handling.
+    if std::env::args().len() == 1 {
+        panic!("You must pass a list of positive integers!")
+    }
+    let numbers: Vec<u32> = std::env::args()
+        .skip(1)
+        .map(|s| u32::from_str(&s).expect("You must pass a list of positive integers!"))
+        .collect();
+    let stride = std::mem::size_of::<u32>() as buffer::Stride;
+    let size = stride as u64 * numbers.len() as u64;
+
+    let instance =
+        back::Instance::create("gfx-rs async compute", 1).expect("Failed to create an instance!");
+
+    let adapter = instance
+        .enumerate_adapters()
+        .into_iter()
+        .find(|a| {
+            a.queue_families
+                .iter()
+                .any(|family| family.queue_type().supports_compute())
+        })
+        .expect("Failed to find a GPU with compute support!");
+
+    let memory_properties = adapter.physical_device.memory_properties();
+    // The transfers run on the main family, while the dispatch runs on
+    // a dedicated compute family, if the adapter has one.
+    let main_family = adapter
+        .queue_families
+        .iter()
+        .find(|family| family.queue_type().supports_compute())
+        .unwrap();
+    let compute_family = adapter
+        .queue_families
+        .iter()
+        .find(|family| family.queue_type() == QueueType::Compute)
+        .unwrap_or(main_family);
+    println!(
+        "Main queue family: {:?}, compute queue family: {:?}",
+        main_family.id(),
+        compute_family.id()
+    );
+
+    let mut families = vec![(main_family, &[1.0][..])];
+    if compute_family.id() != main_family.id() {
+        families.push((compute_family, &[1.0][..]));
+    }
+    let mut gpu = unsafe {
+        adapter
+            .physical_device
+            .open(&families, hal::Features::empty())
+            .unwrap()
+    };
+    let device = &gpu.device;
+    let main_index = gpu
+        .queue_groups
+        .iter()
+        .position(|group| group.family == main_family.id())
+        .unwrap();
+    let compute_index = gpu
+        .queue_groups
+        .iter()
+        .position(|group| group.family == compute_family.id())
+        .unwrap();
+
+    let glsl = fs::read_to_string("compute/shader/collatz.comp").unwrap();
+    let file = glsl_to_spirv::compile(&glsl, glsl_to_spirv::ShaderType::Compute).unwrap();
+    let spirv: Vec<u32> = auxil::read_spirv(file).unwrap();
+    let shader = unsafe { device.create_shader_module(&spirv) }.unwrap();
+
+    let storage_type = pso::DescriptorType::Buffer {
+        ty: pso::BufferDescriptorType::Storage { read_only: false },
+        format: pso::BufferDescriptorFormat::Structured {
+            dynamic_offset: false,
+        },
+    };
+    let set_layout = unsafe {
+        device.create_descriptor_set_layout(
+            iter::once(pso::DescriptorSetLayoutBinding {
+                binding: 0,
+                ty: storage_type,
+                count: 1,
+                stage_flags: pso::ShaderStageFlags::COMPUTE,
+                immutable_samplers: false,
+            }),
+            iter::empty(),
+        )
+    }
+    .expect("Can't create descriptor set layout");
+    let pipeline_layout =
+        unsafe { device.create_pipeline_layout(iter::once(&set_layout), iter::empty()) }
+            .expect("Can't create pipeline layout");
+    let entry_point = pso::EntryPoint {
+        entry: "main",
+        module: &shader,
+        specialization: pso::Specialization::default(),
+    };
+    let pipeline = unsafe {
+        device.create_compute_pipeline(
+            &pso::ComputePipelineDesc::new(entry_point, &pipeline_layout),
+            None,
+        )
+    }
+    .expect("Error creating compute pipeline!");
+    let mut desc_pool = unsafe {
+        device.create_descriptor_pool(
+            1,
+            iter::once(pso::DescriptorRangeDesc {
+                ty: storage_type,
+                count: 1,
+            }),
+            pso::DescriptorPoolCreateFlags::empty(),
+        )
+    }
+    .expect("Can't create descriptor pool");
+
+    let (mut staging_memory, staging_buffer) = unsafe {
+        create_buffer::<back::Backend>(
+            &device,
+            &memory_properties.memory_types,
+            memory::Properties::CPU_VISIBLE | memory::Properties::COHERENT,
+            buffer::Usage::TRANSFER_SRC | buffer::Usage::TRANSFER_DST,
+            size,
+        )
+    };
+    unsafe {
+        let mapping = device
+            .map_memory(&mut staging_memory, memory::Segment::ALL)
+            .unwrap();
+        ptr::copy_nonoverlapping(numbers.as_ptr() as *const u8, mapping, size as usize);
+        device.unmap_memory(&mut staging_memory);
+    }
+
+    let (device_memory, device_buffer) = unsafe {
+        create_buffer::<back::Backend>(
+            &device,
+            &memory_properties.memory_types,
+            memory::Properties::DEVICE_LOCAL,
+            buffer::Usage::TRANSFER_SRC | buffer::Usage::TRANSFER_DST | buffer::Usage::STORAGE,
+            size,
+        )
+    };
+
+    let desc_set = unsafe {
+        let mut desc_set = desc_pool.allocate_one(&set_layout).unwrap();
+        device.write_descriptor_set(pso::DescriptorSetWrite {
+            set: &mut desc_set,
+            binding: 0,
+            array_offset: 0,
+            descriptors: iter::once(pso::Descriptor::Buffer(
+                &device_buffer,
+                buffer::SubRange::WHOLE,
+            )),
+        });
+        desc_set
+    };
+
+    let to_compute = Handover::new(
+        &device_buffer,
+        buffer::Access::TRANSFER_WRITE..buffer::Access::SHADER_READ | buffer::Access::SHADER_WRITE,
+        pso::PipelineStage::TRANSFER..pso::PipelineStage::COMPUTE_SHADER,
+        main_family.id()..compute_family.id(),
+    );
+    let from_compute = Handover::new(
+        &device_buffer,
+        buffer::Access::SHADER_WRITE..buffer::Access::TRANSFER_READ,
+        pso::PipelineStage::COMPUTE_SHADER..pso::PipelineStage::TRANSFER,
+        compute_family.id()..main_family.id(),
+    );
+
+    let mut main_pool = unsafe {
+        device.create_command_pool(main_family.id(), pool::CommandPoolCreateFlags::empty())
+    }
+    .expect("Can't create command pool");
+    let mut compute_pool = unsafe {
+        device.create_command_pool(compute_family.id(), pool::CommandPoolCreateFlags::empty())
+    }
+    .expect("Can't create command pool");
+    let mut semaphores = SemaphorePool::<back::Backend>::new();
+    let mut fence = device.create_fence(false).unwrap();
+
+    unsafe {
+        let mut upload = main_pool.allocate_one(command::Level::Primary);
+        upload.begin_primary(command::CommandBufferFlags::ONE_TIME_SUBMIT);
+        upload.copy_buffer(
+            &staging_buffer,
+            &device_buffer,
+            iter::once(command::BufferCopy {
+                src: 0,
+                dst: 0,
+                size,
+            }),
+        );
+        to_compute.release(&mut upload);
+        upload.finish();
+
+        let mut dispatch = compute_pool.allocate_one(command::Level::Primary);
+        dispatch.begin_primary(command::CommandBufferFlags::ONE_TIME_SUBMIT);
+        to_compute.acquire(&mut dispatch);
+        dispatch.bind_compute_pipeline(&pipeline);
+        dispatch.bind_compute_descriptor_sets(
+            &pipeline_layout,
+            0,
+            iter::once(&desc_set),
+            iter::empty(),
+        );
+        dispatch.dispatch([numbers.len() as u32, 1, 1]);
+        from_compute.release(&mut dispatch);
+        dispatch.finish();
+
+        let mut readback = main_pool.allocate_one(command::Level::Primary);
+        readback.begin_primary(command::CommandBufferFlags::ONE_TIME_SUBMIT);
+        from_compute.acquire(&mut readback);
+        readback.copy_buffer(
+            &device_buffer,
+            &staging_buffer,
+            iter::once(command::BufferCopy {
+                src: 0,
+                dst: 0,
+                size,
+            }),
+        );
+        readback.finish();
+
+        // The schedule inserts the semaphores between the queues,
+        // and the submissions to the same queue rely on the barriers above.
+        let mut schedule = Schedule::<back::Backend>::new();
+        let upload_id = schedule.add(main_index, iter::once(&upload), &[]);
+        let dispatch_id = schedule.add(
+            compute_index,
+            iter::once(&dispatch),
+            &[(upload_id, pso::PipelineStage::COMPUTE_SHADER)],
+        );
+        let readback_id = schedule.add(
+            main_index,
+            iter::once(&readback),
+            &[(dispatch_id, pso::PipelineStage::TRANSFER)],
+        );
+        schedule.signal_fence(readback_id, &mut fence);
+
+        let mut queues = gpu
+            .queue_groups
+            .iter_mut()
+            .map(|group| &mut group.queues[0])
+            .collect::<Vec<_>>();
+        schedule
+            .submit(device, &mut queues, &mut semaphores)
+            .unwrap();
+
+        device.wait_for_fence(&fence, !0).unwrap();
+        semaphores.recycle();
+        main_pool.free(vec![upload, readback].into_iter());
+        compute_pool.free(iter::once(dispatch));
+    }
+
+    unsafe {
+        let mapping = device
+            .map_memory(&mut staging_memory, memory::Segment::ALL)
+            .unwrap();
+        println!(
+            "Times: {:?}",
+            slice::from_raw_parts::<u32>(mapping as *const u8 as *const u32, numbers.len()),
+        );
+        device.unmap_memory(&mut staging_memory);
+    }
+
+    unsafe {
+        semaphores.destroy(device);
+        device.destroy_command_pool(main_pool);
+        device.destroy_command_pool(compute_pool);
+        device.destroy_descriptor_pool(desc_pool);
+        device.destroy_descriptor_set_layout(set_layout);
+        device.destroy_shader_module(shader);
+        device.destroy_buffer(device_buffer);
+        device.destroy_buffer(staging_buffer);
+        device.destroy_fence(fence);
+        device.destroy_pipeline_layout(pipeline_layout);
+        device.free_memory(device_memory);
+        device.free_memory(staging_memory);
+        device.destroy_compute_pipeline(pipeline);
+    }
+}
+
+unsafe fn create_buffer<B: hal::Backend>(
+    device: &B::Device,
+    memory_types: &[MemoryType],
+    properties: memory::Properties,
+    usage: buffer::Usage,
+    size: u64,
+) -> (B::Memory, B::Buffer) {
+    let mut buffer = device.create_buffer(size, usage).unwrap();
+    let requirements = device.get_buffer_requirements(&buffer);
+
+    let ty = memory_types
+        .into_iter()
+        .enumerate()
+        .position(|(id, memory_type)| {
+            requirements.type_mask & (1 << id) != 0 && memory_type.properties.contains(properties)
+        })
+        .unwrap()
+        .into();
+
+    let memory = device.allocate_memory(ty, requirements.size).unwrap();
+    device.bind_buffer_memory(&memory, 0, &mut buffer).unwrap();
+
+    (memory, buffer)
+}
@@ -247,12 +247,14 @@ fn main() {
             );
             cmd_init.finish();
 
-            queue_group.queues[0].submit(
-                iter::once(&cmd_init),
-                iter::empty(),
-                iter::empty(),
-                Some(&mut fence),
-            );
+            queue_group.queues[0]
+                .submit(
+                    iter::once(&cmd_init),
+                    iter::empty(),
+                    iter::empty(),
+                    Some(&mut fence),
+                )
+                .unwrap();
             device.wait_for_fence(&fence, !0).unwrap();
         }
 
@@ -352,12 +354,14 @@ fn main() {
         let mut timings = vec![0u8; num_queries as usize * 8];
         for i in 0..RUNS {
             device.reset_fence(&mut fence).unwrap();
-            queue_group.queues[0].submit(
-                iter::once(&cmd_bench),
-                iter::empty(),
-                iter::empty(),
-                Some(&mut fence),
-            );
+            queue_group.queues[0]
+                .submit(
+                    iter::once(&cmd_bench),
+                    iter::empty(),
+                    iter::empty(),
+                    Some(&mut fence),
+                )
+                .unwrap();
             device.wait_for_fence(&fence, !0).unwrap();
 
             device
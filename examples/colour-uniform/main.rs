@@ -424,12 +424,14 @@ impl<B: Backend> RendererState<B> {
             cmd_buffer.insert_debug_marker("done", 0);
             cmd_buffer.finish();
 
-            self.device.borrow_mut().queues.queues[0].submit(
-                iter::once(&cmd_buffer),
-                iter::empty(),
-                iter::once(&*sem_image_present),
-                None,
-            );
+            self.device.borrow_mut().queues.queues[0]
+                .submit(
+                    iter::once(&cmd_buffer),
+                    iter::empty(),
+                    iter::once(&*sem_image_present),
+                    None,
+                )
+                .unwrap();
             command_buffers.push(cmd_buffer);
 
             // present frame
@@ -657,6 +659,8 @@ impl<B: Backend> RenderPassState<B> {
                 inputs: &[],
                 resolves: &[],
                 preserves: &[],
+                view_mask: 0,
+                shading_rate: None,
             };
 
             device
@@ -1142,12 +1146,14 @@ impl<B: Backend> ImageState<B> {
 
             cmd_buffer.finish();
 
-            device_state.queues.queues[0].submit(
-                iter::once(&cmd_buffer),
-                iter::empty(),
-                iter::empty(),
-                Some(&mut transfered_image_fence),
-            );
+            device_state.queues.queues[0]
+                .submit(
+                    iter::once(&cmd_buffer),
+                    iter::empty(),
+                    iter::empty(),
+                    Some(&mut transfered_image_fence),
+                )
+                .unwrap();
         }
 
         ImageState {
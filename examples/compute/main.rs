@@ -238,12 +238,14 @@ fn main() {
         );
         command_buffer.finish();
 
-        queue_group.queues[0].submit(
-            iter::once(&command_buffer),
-            iter::empty(),
-            iter::empty(),
-            Some(&mut fence),
-        );
+        queue_group.queues[0]
+            .submit(
+                iter::once(&command_buffer),
+                iter::empty(),
+                iter::empty(),
+                Some(&mut fence),
+            )
+            .unwrap();
 
         device.wait_for_fence(&fence, !0).unwrap();
         command_pool.free(iter::once(command_buffer));
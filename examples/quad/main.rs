@@ -517,12 +517,14 @@ where
 
             cmd_buffer.finish();
 
-            queue_group.queues[0].submit(
-                iter::once(&cmd_buffer),
-                iter::empty(),
-                iter::empty(),
-                Some(&mut copy_fence),
-            );
+            queue_group.queues[0]
+                .submit(
+                    iter::once(&cmd_buffer),
+                    iter::empty(),
+                    iter::empty(),
+                    Some(&mut copy_fence),
+                )
+                .unwrap();
 
             device
                 .wait_for_fence(&copy_fence, !0)
@@ -572,6 +574,8 @@ where
                 inputs: &[],
                 resolves: &[],
                 preserves: &[],
+                view_mask: 0,
+                shading_rate: None,
             };
 
             ManuallyDrop::new(
@@ -886,12 +890,14 @@ where
             cmd_buffer.end_render_pass();
             cmd_buffer.finish();
 
-            self.queue_group.queues[0].submit(
-                iter::once(&*cmd_buffer),
-                iter::empty(),
-                iter::once(&self.submission_complete_semaphores[frame_idx]),
-                Some(&mut self.submission_complete_fences[frame_idx]),
-            );
+            self.queue_group.queues[0]
+                .submit(
+                    iter::once(&*cmd_buffer),
+                    iter::empty(),
+                    iter::once(&self.submission_complete_semaphores[frame_idx]),
+                    Some(&mut self.submission_complete_fences[frame_idx]),
+                )
+                .unwrap();
 
             // present frame
             let result = self.queue_group.queues[0].present(
@@ -0,0 +1,256 @@
+//! Planning of device memory defragmentation.
+//!
+//! Resources can't be bound to another memory object once they are bound,
+//! so moving them to compact the device memory is done in several steps:
+//!   1. plan the moves of the movable allocations with `plan`,
+//!   2. create the new resources and bind them at the destination of each move,
+//!   3. record the copies from the old resources to the new ones, e.g. with
+//!      the regions from `buffer_copies`, using buffers spanning the whole memory objects,
+//!   4. switch the users to the new resources and destroy the old ones
+//!      once the copies are complete,
+//!   5. free the memory objects listed in `Plan::freed_blocks`.
+
+use hal::{buffer, command};
+
+use std::{cmp, ops::Range};
+
+/// A sub-allocation of a memory object.
+#[derive(Clone, Debug)]
+pub struct Allocation<K> {
+    /// User key identifying the allocation.
+    pub key: K,
+    /// Index of the memory object containing the allocation.
+    pub block: usize,
+    /// Byte range of the allocation within the memory object.
+    pub range: Range<buffer::Offset>,
+    /// Required alignment of the allocation offset.
+    pub alignment: buffer::Offset,
+    /// Whether the allocation can be moved, e.g. it's not mapped
+    /// and not in use by any pending command buffer.
+    pub movable: bool,
+}
+
+/// A planned move of an allocation to another memory object.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Move<K> {
+    /// User key identifying the allocation.
+    pub key: K,
+    /// Index of the memory object currently containing the allocation.
+    pub src_block: usize,
+    /// Current byte range of the allocation.
+    pub src: Range<buffer::Offset>,
+    /// Index of the memory object to move the allocation to.
+    pub dst_block: usize,
+    /// Byte range of the allocation after the move.
+    pub dst: Range<buffer::Offset>,
+}
+
+/// Outcome of the defragmentation planning.
+#[derive(Clone, Debug)]
+pub struct Plan<K> {
+    /// Moves to perform, each allocation being moved at most once.
+    pub moves: Vec<Move<K>>,
+    /// Memory objects that become empty once the moves are done.
+    pub freed_blocks: Vec<usize>,
+}
+
+fn align(offset: buffer::Offset, alignment: buffer::Offset) -> buffer::Offset {
+    let alignment = alignment.max(1);
+    (offset + alignment - 1) / alignment * alignment
+}
+
+/// Find the first free range of a memory object fitting the given size.
+fn find_gap(
+    occupied: &[Range<buffer::Offset>],
+    block_size: buffer::Offset,
+    size: buffer::Offset,
+    alignment: buffer::Offset,
+) -> Option<buffer::Offset> {
+    let mut start = 0;
+    for range in occupied {
+        let offset = align(start, alignment);
+        if offset + size <= range.start {
+            return Some(offset);
+        }
+        start = start.max(range.end);
+    }
+    let offset = align(start, alignment);
+    if offset + size <= block_size {
+        Some(offset)
+    } else {
+        None
+    }
+}
+
+/// Plan the moves compacting the allocations into fewer memory objects.
+///
+/// The allocations of the least used memory objects are moved into the free space
+/// of the most used ones, until no more allocations fit or `max_moved_bytes` would be
+/// exceeded. `block_sizes` gives the size of each memory object referred to by the allocations.
+pub fn plan<K: Clone>(
+    allocations: &[Allocation<K>],
+    block_sizes: &[buffer::Offset],
+    max_moved_bytes: buffer::Offset,
+) -> Plan<K> {
+    let mut occupied = vec![Vec::new(); block_sizes.len()];
+    for (index, allocation) in allocations.iter().enumerate() {
+        occupied[allocation.block].push((allocation.range.clone(), index));
+    }
+    for list in occupied.iter_mut() {
+        list.sort_by_key(|&(ref range, _)| range.start);
+    }
+    let initially_used = occupied
+        .iter()
+        .map(|list| !list.is_empty())
+        .collect::<Vec<_>>();
+
+    let used_bytes = |list: &[(Range<buffer::Offset>, usize)]| -> buffer::Offset {
+        list.iter().map(|&(ref range, _)| range.end - range.start).sum()
+    };
+    let mut order = (0..block_sizes.len()).collect::<Vec<_>>();
+    order.sort_by_key(|&block| cmp::Reverse(used_bytes(&occupied[block])));
+
+    let mut moves = Vec::new();
+    let mut moved_bytes = 0;
+    let mut moved = vec![false; allocations.len()];
+
+    for src_position in (1..order.len()).rev() {
+        let src_block = order[src_position];
+        let candidates = occupied[src_block]
+            .iter()
+            .map(|&(_, index)| index)
+            .filter(|&index| allocations[index].movable && !moved[index])
+            .collect::<Vec<_>>();
+
+        for index in candidates {
+            let allocation = &allocations[index];
+            let size = allocation.range.end - allocation.range.start;
+            if moved_bytes + size > max_moved_bytes {
+                continue;
+            }
+            for &dst_block in &order[..src_position] {
+                let dst_ranges = occupied[dst_block]
+                    .iter()
+                    .map(|&(ref range, _)| range.clone())
+                    .collect::<Vec<_>>();
+                let offset = match find_gap(
+                    &dst_ranges,
+                    block_sizes[dst_block],
+                    size,
+                    allocation.alignment,
+                ) {
+                    Some(offset) => offset,
+                    None => continue,
+                };
+
+                let dst = offset..offset + size;
+                let position = occupied[dst_block]
+                    .iter()
+                    .position(|&(ref range, _)| range.start > offset)
+                    .unwrap_or(occupied[dst_block].len());
+                occupied[dst_block].insert(position, (dst.clone(), index));
+                occupied[src_block].retain(|&(_, other)| other != index);
+
+                moves.push(Move {
+                    key: allocation.key.clone(),
+                    src_block,
+                    src: allocation.range.clone(),
+                    dst_block,
+                    dst,
+                });
+                moved_bytes += size;
+                moved[index] = true;
+                break;
+            }
+        }
+    }
+
+    let freed_blocks = occupied
+        .iter()
+        .enumerate()
+        .filter(|&(block, list)| initially_used[block] && list.is_empty())
+        .map(|(block, _)| block)
+        .collect();
+
+    Plan {
+        moves,
+        freed_blocks,
+    }
+}
+
+/// Group the copy regions of the moves by the pair of the source and destination memory objects.
+///
+/// The regions are relative to the start of the memory objects, so they are meant
+/// for the buffers spanning each of them.
+pub fn buffer_copies<K>(moves: &[Move<K>]) -> Vec<((usize, usize), Vec<command::BufferCopy>)> {
+    let mut groups: Vec<((usize, usize), Vec<command::BufferCopy>)> = Vec::new();
+    for mov in moves {
+        let region = command::BufferCopy {
+            src: mov.src.start,
+            dst: mov.dst.start,
+            size: mov.src.end - mov.src.start,
+        };
+        let blocks = (mov.src_block, mov.dst_block);
+        match groups.iter_mut().find(|&&mut (pair, _)| pair == blocks) {
+            Some(&mut (_, ref mut regions)) => regions.push(region),
+            None => groups.push((blocks, vec![region])),
+        }
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn allocation(key: u32, block: usize, range: Range<u64>) -> Allocation<u32> {
+        Allocation {
+            key,
+            block,
+            range,
+            alignment: 1,
+            movable: true,
+        }
+    }
+
+    #[test]
+    fn test_compact_into_fullest_block() {
+        let allocations = [
+            allocation(0, 0, 0..40),
+            allocation(1, 0, 60..100),
+            allocation(2, 1, 20..30),
+        ];
+        let plan = plan(&allocations, &[100, 100], !0);
+        assert_eq!(
+            plan.moves,
+            vec![Move {
+                key: 2,
+                src_block: 1,
+                src: 20..30,
+                dst_block: 0,
+                dst: 40..50,
+            }]
+        );
+        assert_eq!(plan.freed_blocks, vec![1]);
+    }
+
+    #[test]
+    fn test_respect_alignment_and_budget() {
+        let mut allocations = vec![
+            allocation(0, 0, 0..10),
+            allocation(1, 1, 0..20),
+            allocation(2, 2, 0..5),
+        ];
+        allocations[2].alignment = 16;
+        let plan = plan(&allocations, &[100, 100, 100], 5);
+        assert_eq!(plan.moves.len(), 1);
+        assert_eq!(plan.moves[0].key, 2);
+        assert_eq!(plan.moves[0].dst_block, 1);
+        assert_eq!(plan.moves[0].dst, 32..37);
+        assert_eq!(plan.freed_blocks, vec![2]);
+
+        let copies = buffer_copies(&plan.moves);
+        assert_eq!(copies.len(), 1);
+        assert_eq!(copies[0].0, (2, 1));
+    }
+}
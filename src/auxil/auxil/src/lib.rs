@@ -2,6 +2,11 @@
 use spirv_cross::spirv;
 use std::{io, slice};
 
+pub mod defrag;
+pub mod reflect;
+pub mod report;
+pub mod workers;
+
 /// Fast hash map used internally.
 pub type FastHashMap<K, V> =
     std::collections::HashMap<K, V, std::hash::BuildHasherDefault<fxhash::FxHasher>>;
@@ -38,6 +43,27 @@ impl ShaderStage {
     }
 }
 
+/// Total size of the memory heaps that have a memory type both local to the device
+/// and visible to the host, as reported in `Limits::device_local_host_visible_heap_size`.
+pub fn device_local_host_visible_heap_size(properties: &hal::adapter::MemoryProperties) -> u64 {
+    use hal::memory::Properties;
+    let mut heaps = 0u64;
+    for memory_type in properties.memory_types.iter() {
+        if memory_type
+            .properties
+            .contains(Properties::DEVICE_LOCAL | Properties::CPU_VISIBLE)
+        {
+            heaps |= 1 << memory_type.heap_index;
+        }
+    }
+    properties
+        .memory_heaps
+        .iter()
+        .enumerate()
+        .filter(|&(index, _)| heaps & (1 << index) != 0)
+        .fold(0, |size, (_, heap)| size.saturating_add(heap.size))
+}
+
 /// Safely read SPIR-V
 ///
 /// Converts to native endianness and returns correctly aligned storage without unnecessary
@@ -136,3 +162,54 @@ where
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use hal::{
+        adapter::{MemoryHeap, MemoryProperties, MemoryType},
+        memory::{HeapFlags, Properties},
+    };
+
+    #[test]
+    fn device_local_host_visible_heap_size() {
+        let mut properties = MemoryProperties {
+            memory_types: vec![
+                MemoryType {
+                    properties: Properties::DEVICE_LOCAL,
+                    heap_index: 0,
+                },
+                MemoryType {
+                    properties: Properties::CPU_VISIBLE | Properties::COHERENT,
+                    heap_index: 1,
+                },
+            ],
+            memory_heaps: vec![
+                MemoryHeap {
+                    size: 8 << 30,
+                    flags: HeapFlags::DEVICE_LOCAL,
+                },
+                MemoryHeap {
+                    size: 16 << 30,
+                    flags: HeapFlags::empty(),
+                },
+                MemoryHeap {
+                    size: 256 << 20,
+                    flags: HeapFlags::DEVICE_LOCAL,
+                },
+            ],
+        };
+        assert_eq!(super::device_local_host_visible_heap_size(&properties), 0);
+
+        // Resizable BAR exposed as a separate heap, referenced by two memory types
+        for &extra in &[Properties::empty(), Properties::CPU_CACHED] {
+            properties.memory_types.push(MemoryType {
+                properties: Properties::DEVICE_LOCAL | Properties::CPU_VISIBLE | extra,
+                heap_index: 2,
+            });
+        }
+        assert_eq!(
+            super::device_local_host_visible_heap_size(&properties),
+            256 << 20
+        );
+    }
+}
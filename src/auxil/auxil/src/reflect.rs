@@ -0,0 +1,684 @@
+//! Minimal SPIR-V reflection, producing the pipeline layout description of a shader.
+//!
+//! The descriptor set layouts, push constant ranges and vertex attributes
+//! are derived from the resource declarations of a module, so that they don't
+//! have to be written by hand and kept in sync with the shader code.
+//!
+//! The whole module is reflected at once: every binding is visible to the stages
+//! of all the entry points of the module. Use `Reflection::merge` to combine the
+//! reflections of the shaders of a pipeline into one layout.
+//!
+//! The user-defined inputs and outputs of each entry point are reflected too,
+//! and can be matched between the stages with `check_interface`.
+
+use hal::{
+    format::{ChannelType, Format},
+    pso,
+};
+
+use crate::FastHashMap;
+use std::ops::Range;
+
+const MAGIC_NUMBER: u32 = 0x0723_0203;
+const HEADER_SIZE: usize = 5;
+
+mod op {
+    pub const ENTRY_POINT: u32 = 15;
+    pub const TYPE_INT: u32 = 21;
+    pub const TYPE_FLOAT: u32 = 22;
+    pub const TYPE_VECTOR: u32 = 23;
+    pub const TYPE_MATRIX: u32 = 24;
+    pub const TYPE_IMAGE: u32 = 25;
+    pub const TYPE_SAMPLER: u32 = 26;
+    pub const TYPE_SAMPLED_IMAGE: u32 = 27;
+    pub const TYPE_ARRAY: u32 = 28;
+    pub const TYPE_RUNTIME_ARRAY: u32 = 29;
+    pub const TYPE_STRUCT: u32 = 30;
+    pub const TYPE_POINTER: u32 = 32;
+    pub const CONSTANT: u32 = 43;
+    pub const VARIABLE: u32 = 59;
+    pub const DECORATE: u32 = 71;
+    pub const MEMBER_DECORATE: u32 = 72;
+}
+
+mod decoration {
+    pub const BLOCK: u32 = 2;
+    pub const ARRAY_STRIDE: u32 = 6;
+    pub const MATRIX_STRIDE: u32 = 7;
+    pub const BUILT_IN: u32 = 11;
+    pub const NON_WRITABLE: u32 = 24;
+    pub const LOCATION: u32 = 30;
+    pub const BINDING: u32 = 33;
+    pub const DESCRIPTOR_SET: u32 = 34;
+    pub const OFFSET: u32 = 35;
+}
+
+mod storage {
+    pub const UNIFORM_CONSTANT: u32 = 0;
+    pub const INPUT: u32 = 1;
+    pub const UNIFORM: u32 = 2;
+    pub const OUTPUT: u32 = 3;
+    pub const PUSH_CONSTANT: u32 = 9;
+    pub const STORAGE_BUFFER: u32 = 12;
+}
+
+mod dim {
+    pub const BUFFER: u32 = 5;
+    pub const SUBPASS_DATA: u32 = 6;
+}
+
+mod model {
+    pub const VERTEX: u32 = 0;
+    pub const TESSELLATION_CONTROL: u32 = 1;
+    pub const TESSELLATION_EVALUATION: u32 = 2;
+    pub const GEOMETRY: u32 = 3;
+    pub const FRAGMENT: u32 = 4;
+    pub const GL_COMPUTE: u32 = 5;
+    pub const TASK: u32 = 5267;
+    pub const MESH: u32 = 5268;
+}
+
+/// Entry point of a reflected module.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EntryPoint {
+    /// Name of the entry point function.
+    pub name: String,
+    /// Stage executing the entry point.
+    pub stage: pso::ShaderStageFlags,
+    /// User-defined inputs of the entry point, sorted by location.
+    pub inputs: Vec<InterfaceVariable>,
+    /// User-defined outputs of the entry point, sorted by location.
+    pub outputs: Vec<InterfaceVariable>,
+}
+
+/// Input or output variable of an entry point.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct InterfaceVariable {
+    /// Location of the variable.
+    pub location: pso::Location,
+    /// Format matching the type of the variable, or `None` if the type
+    /// is not a 32-bit scalar or vector.
+    pub format: Option<Format>,
+}
+
+/// Vertex attribute expected by a vertex shader.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VertexAttribute {
+    /// Location of the attribute in the shader.
+    pub location: pso::Location,
+    /// Format matching the type of the attribute in the shader.
+    pub format: Format,
+}
+
+/// Pipeline layout description reflected from SPIR-V.
+#[derive(Clone, Debug, Default)]
+pub struct Reflection {
+    /// Entry points of the module.
+    pub entry_points: Vec<EntryPoint>,
+    /// Bindings of each descriptor set, sorted by binding. The sets
+    /// that aren't used by the shader are empty.
+    pub descriptor_sets: Vec<Vec<pso::DescriptorSetLayoutBinding>>,
+    /// Push constant ranges in bytes, with the stages accessing them.
+    pub push_constants: Vec<(pso::ShaderStageFlags, Range<u32>)>,
+    /// Vertex attributes of the vertex entry points, sorted by location.
+    pub vertex_attributes: Vec<VertexAttribute>,
+}
+
+impl Reflection {
+    /// Combine the reflection of another shader of the same pipeline into this one.
+    ///
+    /// Fails if both shaders declare a binding with a different type or count.
+    pub fn merge(&mut self, other: &Reflection) -> Result<(), String> {
+        self.entry_points.extend(other.entry_points.iter().cloned());
+        if self.descriptor_sets.len() < other.descriptor_sets.len() {
+            self.descriptor_sets.resize(other.descriptor_sets.len(), Vec::new());
+        }
+        for (set, (bindings, other_bindings)) in self
+            .descriptor_sets
+            .iter_mut()
+            .zip(other.descriptor_sets.iter())
+            .enumerate()
+        {
+            for other_binding in other_bindings {
+                match bindings
+                    .iter_mut()
+                    .find(|binding| binding.binding == other_binding.binding)
+                {
+                    Some(binding) => {
+                        if binding.ty != other_binding.ty || binding.count != other_binding.count {
+                            return Err(format!(
+                                "Binding {} of set {} is declared as {:?} x{} and {:?} x{}",
+                                binding.binding,
+                                set,
+                                binding.ty,
+                                binding.count,
+                                other_binding.ty,
+                                other_binding.count
+                            ));
+                        }
+                        binding.stage_flags |= other_binding.stage_flags;
+                    }
+                    None => bindings.push(other_binding.clone()),
+                }
+            }
+            bindings.sort_by_key(|binding| binding.binding);
+        }
+        self.push_constants.extend(other.push_constants.iter().cloned());
+        self.vertex_attributes.extend(other.vertex_attributes.iter().cloned());
+        self.vertex_attributes.sort_by_key(|attribute| attribute.location);
+        Ok(())
+    }
+}
+
+enum Type {
+    Int { width: u32, signed: bool },
+    Float { width: u32 },
+    Vector { component: u32, count: u32 },
+    Matrix { column: u32, count: u32 },
+    Image { dim: u32, sampled: u32 },
+    Sampler,
+    SampledImage,
+    Array { element: u32, length: u32 },
+    RuntimeArray,
+    Struct { members: Vec<u32> },
+    Pointer { ty: u32 },
+}
+
+struct Module {
+    types: FastHashMap<u32, Type>,
+    constants: FastHashMap<u32, u32>,
+    decorations: FastHashMap<(u32, u32), u32>,
+    member_decorations: FastHashMap<(u32, u32, u32), u32>,
+}
+
+impl Module {
+    /// Size of a type in bytes, following its explicit layout.
+    fn size_of(&self, ty: u32) -> Result<u32, String> {
+        Ok(match self.types.get(&ty) {
+            Some(&Type::Int { width, .. }) | Some(&Type::Float { width }) => width / 8,
+            Some(&Type::Vector { component, count }) => self.size_of(component)? * count,
+            Some(&Type::Array { element, length }) => {
+                let length = self.constants.get(&length).cloned().unwrap_or(1);
+                let stride = match self.decorations.get(&(ty, decoration::ARRAY_STRIDE)) {
+                    Some(&stride) => stride,
+                    None => self.size_of(element)?,
+                };
+                stride * length
+            }
+            Some(&Type::Struct { ref members }) => {
+                let mut size = 0;
+                for (index, &member) in members.iter().enumerate() {
+                    let index = index as u32;
+                    let offset = self
+                        .member_decorations
+                        .get(&(ty, index, decoration::OFFSET))
+                        .cloned()
+                        .unwrap_or(size);
+                    let member_size = match self.types.get(&member) {
+                        Some(&Type::Matrix { column, count }) => {
+                            let stride = match self
+                                .member_decorations
+                                .get(&(ty, index, decoration::MATRIX_STRIDE))
+                            {
+                                Some(&stride) => stride,
+                                None => self.size_of(column)?,
+                            };
+                            stride * count
+                        }
+                        _ => self.size_of(member)?,
+                    };
+                    size = size.max(offset + member_size);
+                }
+                size
+            }
+            _ => return Err(format!("Type {} has no explicit size", ty)),
+        })
+    }
+
+    fn vertex_format(&self, ty: u32) -> Option<Format> {
+        let (component, count) = match self.types.get(&ty) {
+            Some(&Type::Vector { component, count }) => (component, count),
+            _ => (ty, 1),
+        };
+        let formats = match self.types.get(&component) {
+            Some(&Type::Float { width: 32 }) => [
+                Format::R32Sfloat,
+                Format::Rg32Sfloat,
+                Format::Rgb32Sfloat,
+                Format::Rgba32Sfloat,
+            ],
+            Some(&Type::Int {
+                width: 32,
+                signed: true,
+            }) => [
+                Format::R32Sint,
+                Format::Rg32Sint,
+                Format::Rgb32Sint,
+                Format::Rgba32Sint,
+            ],
+            Some(&Type::Int {
+                width: 32,
+                signed: false,
+            }) => [
+                Format::R32Uint,
+                Format::Rg32Uint,
+                Format::Rgb32Uint,
+                Format::Rgba32Uint,
+            ],
+            _ => return None,
+        };
+        formats.get(count as usize - 1).cloned()
+    }
+
+    fn descriptor_type(
+        &self,
+        id: u32,
+        ty: u32,
+        storage_class: u32,
+        non_writable_members: &FastHashMap<u32, usize>,
+    ) -> Option<pso::DescriptorType> {
+        use hal::pso::{
+            BufferDescriptorFormat as Bdf, BufferDescriptorType as Bdt, DescriptorType as Dt,
+            ImageDescriptorType as Idt,
+        };
+        Some(match (self.types.get(&ty)?, storage_class) {
+            (Type::Sampler, _) => Dt::Sampler,
+            (Type::SampledImage, _) => Dt::Image {
+                ty: Idt::Sampled { with_sampler: true },
+            },
+            (&Type::Image { dim, sampled }, _) => match (dim, sampled) {
+                (dim::SUBPASS_DATA, _) => Dt::InputAttachment,
+                (dim::BUFFER, 2) => Dt::Buffer {
+                    ty: Bdt::Storage { read_only: false },
+                    format: Bdf::Texel,
+                },
+                (dim::BUFFER, _) => Dt::Buffer {
+                    ty: Bdt::Uniform,
+                    format: Bdf::Texel,
+                },
+                (_, 2) => Dt::Image {
+                    ty: Idt::Storage { read_only: false },
+                },
+                (_, _) => Dt::Image {
+                    ty: Idt::Sampled {
+                        with_sampler: false,
+                    },
+                },
+            },
+            (Type::Struct { .. }, storage::UNIFORM)
+                if self.decorations.contains_key(&(ty, decoration::BLOCK)) =>
+            {
+                Dt::Buffer {
+                    ty: Bdt::Uniform,
+                    format: Bdf::Structured {
+                        dynamic_offset: false,
+                    },
+                }
+            }
+            (&Type::Struct { ref members }, storage::UNIFORM)
+            | (&Type::Struct { ref members }, storage::STORAGE_BUFFER) => {
+                let read_only = self
+                    .decorations
+                    .contains_key(&(id, decoration::NON_WRITABLE))
+                    || non_writable_members.get(&ty) == Some(&members.len());
+                Dt::Buffer {
+                    ty: Bdt::Storage { read_only },
+                    format: Bdf::Structured {
+                        dynamic_offset: false,
+                    },
+                }
+            }
+            _ => return None,
+        })
+    }
+}
+
+fn parse_string(words: &[u32]) -> (String, usize) {
+    let bytes = words
+        .iter()
+        .flat_map(|word| word.to_le_bytes().to_vec())
+        .take_while(|&byte| byte != 0)
+        .collect::<Vec<_>>();
+    let word_count = bytes.len() / 4 + 1;
+    (String::from_utf8_lossy(&bytes).into_owned(), word_count)
+}
+
+fn stage(execution_model: u32) -> Result<pso::ShaderStageFlags, String> {
+    Ok(match execution_model {
+        model::VERTEX => pso::ShaderStageFlags::VERTEX,
+        model::TESSELLATION_CONTROL => pso::ShaderStageFlags::HULL,
+        model::TESSELLATION_EVALUATION => pso::ShaderStageFlags::DOMAIN,
+        model::GEOMETRY => pso::ShaderStageFlags::GEOMETRY,
+        model::FRAGMENT => pso::ShaderStageFlags::FRAGMENT,
+        model::GL_COMPUTE => pso::ShaderStageFlags::COMPUTE,
+        model::TASK => pso::ShaderStageFlags::TASK,
+        model::MESH => pso::ShaderStageFlags::MESH,
+        _ => return Err(format!("Unsupported execution model {}", execution_model)),
+    })
+}
+
+/// Reflect the pipeline layout description of a SPIR-V module.
+pub fn reflect_spirv(words: &[u32]) -> Result<Reflection, String> {
+    if words.len() < HEADER_SIZE || words[0] != MAGIC_NUMBER {
+        return Err("Not a SPIR-V module".to_string());
+    }
+
+    let mut module = Module {
+        types: FastHashMap::default(),
+        constants: FastHashMap::default(),
+        decorations: FastHashMap::default(),
+        member_decorations: FastHashMap::default(),
+    };
+    let mut entry_points = Vec::new();
+    let mut interfaces = Vec::new();
+    let mut variables = Vec::new();
+    let mut non_writable_members = FastHashMap::<u32, usize>::default();
+
+    let mut offset = HEADER_SIZE;
+    while offset < words.len() {
+        let word_count = (words[offset] >> 16) as usize;
+        let opcode = words[offset] & 0xFFFF;
+        if word_count == 0 || offset + word_count > words.len() {
+            return Err(format!("Malformed instruction at word {}", offset));
+        }
+        let operands = &words[offset + 1..offset + word_count];
+        offset += word_count;
+
+        let operand = |index: usize| {
+            operands
+                .get(index)
+                .cloned()
+                .ok_or_else(|| format!("Missing operands of the instruction {}", opcode))
+        };
+        match opcode {
+            op::ENTRY_POINT => {
+                let model = operand(0)?;
+                let (name, name_words) = parse_string(&operands[2.min(operands.len())..]);
+                let interface = operands.get(2 + name_words..).unwrap_or(&[]);
+                interfaces.push(interface.to_vec());
+                entry_points.push(EntryPoint {
+                    name,
+                    stage: stage(model)?,
+                    inputs: Vec::new(),
+                    outputs: Vec::new(),
+                });
+            }
+            op::TYPE_INT => {
+                let ty = Type::Int {
+                    width: operand(1)?,
+                    signed: operand(2)? != 0,
+                };
+                module.types.insert(operand(0)?, ty);
+            }
+            op::TYPE_FLOAT => {
+                let ty = Type::Float { width: operand(1)? };
+                module.types.insert(operand(0)?, ty);
+            }
+            op::TYPE_VECTOR => {
+                let ty = Type::Vector {
+                    component: operand(1)?,
+                    count: operand(2)?,
+                };
+                module.types.insert(operand(0)?, ty);
+            }
+            op::TYPE_MATRIX => {
+                let ty = Type::Matrix {
+                    column: operand(1)?,
+                    count: operand(2)?,
+                };
+                module.types.insert(operand(0)?, ty);
+            }
+            op::TYPE_IMAGE => {
+                let ty = Type::Image {
+                    dim: operand(2)?,
+                    sampled: operand(6)?,
+                };
+                module.types.insert(operand(0)?, ty);
+            }
+            op::TYPE_SAMPLER => {
+                module.types.insert(operand(0)?, Type::Sampler);
+            }
+            op::TYPE_SAMPLED_IMAGE => {
+                module.types.insert(operand(0)?, Type::SampledImage);
+            }
+            op::TYPE_ARRAY => {
+                let ty = Type::Array {
+                    element: operand(1)?,
+                    length: operand(2)?,
+                };
+                module.types.insert(operand(0)?, ty);
+            }
+            op::TYPE_RUNTIME_ARRAY => {
+                module.types.insert(operand(0)?, Type::RuntimeArray);
+            }
+            op::TYPE_STRUCT => {
+                let ty = Type::Struct {
+                    members: operands[1..].to_vec(),
+                };
+                module.types.insert(operand(0)?, ty);
+            }
+            op::TYPE_POINTER => {
+                module.types.insert(operand(0)?, Type::Pointer { ty: operand(2)? });
+            }
+            op::CONSTANT => {
+                module.constants.insert(operand(1)?, operand(2)?);
+            }
+            op::VARIABLE => variables.push((operand(0)?, operand(1)?, operand(2)?)),
+            op::DECORATE => {
+                let value = operands.get(2).cloned().unwrap_or(0);
+                module.decorations.insert((operand(0)?, operand(1)?), value);
+            }
+            op::MEMBER_DECORATE => {
+                let (ty, member, kind) = (operand(0)?, operand(1)?, operand(2)?);
+                if kind == decoration::NON_WRITABLE {
+                    *non_writable_members.entry(ty).or_insert(0) += 1;
+                }
+                let value = operands.get(3).cloned().unwrap_or(0);
+                module.member_decorations.insert((ty, member, kind), value);
+            }
+            _ => {}
+        }
+    }
+
+    let stages = entry_points
+        .iter()
+        .fold(pso::ShaderStageFlags::empty(), |stages, ep| stages | ep.stage);
+    let mut reflection = Reflection {
+        entry_points,
+        ..Reflection::default()
+    };
+
+    for (pointer, id, storage_class) in variables {
+        let mut ty = match module.types.get(&pointer) {
+            Some(&Type::Pointer { ty }) => ty,
+            _ => return Err(format!("Variable {} is not a pointer", id)),
+        };
+
+        match storage_class {
+            storage::PUSH_CONSTANT => {
+                let size = module.size_of(ty)?;
+                reflection.push_constants.push((stages, 0..size));
+                continue;
+            }
+            storage::INPUT | storage::OUTPUT => {
+                if module.decorations.contains_key(&(id, decoration::BUILT_IN)) {
+                    continue;
+                }
+                let location = module.decorations.get(&(id, decoration::LOCATION)).cloned();
+                for (ep, interface) in reflection.entry_points.iter_mut().zip(interfaces.iter()) {
+                    if !interface.contains(&id) {
+                        continue;
+                    }
+                    let vertex_input = storage_class == storage::INPUT
+                        && ep.stage == pso::ShaderStageFlags::VERTEX;
+                    let location = match location {
+                        Some(location) => location,
+                        None if vertex_input => {
+                            return Err(format!("Vertex input {} has no location", id))
+                        }
+                        // e.g. the blocks of built-ins
+                        None => continue,
+                    };
+                    let format = if vertex_input {
+                        let format = module.vertex_format(ty).ok_or_else(|| {
+                            format!(
+                                "Vertex input at location {} has an unsupported type",
+                                location
+                            )
+                        })?;
+                        if reflection
+                            .vertex_attributes
+                            .iter()
+                            .all(|attribute| attribute.location != location)
+                        {
+                            reflection
+                                .vertex_attributes
+                                .push(VertexAttribute { location, format });
+                        }
+                        Some(format)
+                    } else {
+                        // the inputs of the hull, domain and geometry stages are arrays of vertices
+                        module
+                            .vertex_format(ty)
+                            .or_else(|| match module.types.get(&ty) {
+                                Some(&Type::Array { element, .. }) => module.vertex_format(element),
+                                _ => None,
+                            })
+                    };
+                    let variable = InterfaceVariable { location, format };
+                    if storage_class == storage::INPUT {
+                        ep.inputs.push(variable);
+                    } else {
+                        ep.outputs.push(variable);
+                    }
+                }
+                continue;
+            }
+            _ => {}
+        }
+
+        let set = match module.decorations.get(&(id, decoration::DESCRIPTOR_SET)) {
+            Some(&set) => set,
+            None => continue,
+        };
+        let binding = module
+            .decorations
+            .get(&(id, decoration::BINDING))
+            .cloned()
+            .unwrap_or(0);
+
+        let mut count = 1;
+        loop {
+            match module.types.get(&ty) {
+                Some(&Type::Array { element, length }) => {
+                    count *= module.constants.get(&length).cloned().unwrap_or(1);
+                    ty = element;
+                }
+                Some(Type::RuntimeArray) if storage_class == storage::UNIFORM_CONSTANT => {
+                    return Err(format!(
+                        "Binding {} of set {} is an unsized array, which is not supported",
+                        binding, set
+                    ));
+                }
+                _ => break,
+            }
+        }
+
+        let descriptor_type = module
+            .descriptor_type(id, ty, storage_class, &non_writable_members)
+            .ok_or_else(|| format!("Binding {} of set {} has an unsupported type", binding, set))?;
+
+        if reflection.descriptor_sets.len() <= set as usize {
+            reflection.descriptor_sets.resize(set as usize + 1, Vec::new());
+        }
+        reflection.descriptor_sets[set as usize].push(pso::DescriptorSetLayoutBinding {
+            binding,
+            ty: descriptor_type,
+            count: count as pso::DescriptorArrayIndex,
+            stage_flags: stages,
+            immutable_samplers: false,
+        });
+    }
+
+    for bindings in reflection.descriptor_sets.iter_mut() {
+        bindings.sort_by_key(|binding| binding.binding);
+    }
+    for ep in reflection.entry_points.iter_mut() {
+        ep.inputs.sort_by_key(|variable| variable.location);
+        ep.outputs.sort_by_key(|variable| variable.location);
+    }
+    reflection.vertex_attributes.sort_by_key(|attribute| attribute.location);
+    Ok(reflection)
+}
+
+/// Numeric class of a format, as seen by the shaders.
+fn numeric_class(format: Format) -> ChannelType {
+    match format.base_format().1 {
+        ChannelType::Uint => ChannelType::Uint,
+        ChannelType::Sint => ChannelType::Sint,
+        _ => ChannelType::Sfloat,
+    }
+}
+
+/// Match the outputs of a shader stage with the inputs of the next stage,
+/// returning the descriptions of the mismatches.
+///
+/// Every input has to be written by an output of the same location and type.
+/// Extra outputs are allowed.
+pub fn check_interface(outputs: &[InterfaceVariable], inputs: &[InterfaceVariable]) -> Vec<String> {
+    let mut errors = Vec::new();
+    for input in inputs {
+        match outputs
+            .iter()
+            .find(|output| output.location == input.location)
+        {
+            None => errors.push(format!(
+                "location {} is read but not written by the previous stage",
+                input.location
+            )),
+            Some(output) => match (output.format, input.format) {
+                (Some(written), Some(read)) if written != read => errors.push(format!(
+                    "location {} is written as {:?} but read as {:?}",
+                    input.location, written, read
+                )),
+                _ => {}
+            },
+        }
+    }
+    errors
+}
+
+/// Match the vertex attributes of a pipeline with the inputs of its vertex shader,
+/// returning the descriptions of the mismatches.
+///
+/// Every input has to be provided by an attribute of the same numeric class
+/// (floating-point, signed or unsigned integer). Extra attributes are allowed.
+pub fn check_vertex_attributes(
+    inputs: &[InterfaceVariable],
+    attributes: &[pso::AttributeDesc],
+) -> Vec<String> {
+    let mut errors = Vec::new();
+    for input in inputs {
+        match attributes
+            .iter()
+            .find(|attribute| attribute.location == input.location)
+        {
+            None => errors.push(format!(
+                "location {} is not provided by any vertex attribute",
+                input.location
+            )),
+            Some(attribute) => match input.format {
+                Some(format)
+                    if numeric_class(format) != numeric_class(attribute.element.format) =>
+                {
+                    errors.push(format!(
+                        "location {} is read as {:?} from an attribute of {:?}",
+                        input.location, format, attribute.element.format
+                    ))
+                }
+                _ => {}
+            },
+        }
+    }
+    errors
+}
@@ -0,0 +1,103 @@
+//! Structured logging of the backends.
+//!
+//! Messages are logged with the [`report!`](crate::report!) macro under targets
+//! of the form `gfx::<backend>::<area>`, e.g. `gfx::metal::command` or `gfx::vulkan::queue`,
+//! so that they can be filtered by backend and area. Each message starts with
+//! a machine-readable code in brackets, e.g. `[depth-bounds-unsupported]`,
+//! which is kept stable so that tooling can aggregate the reports.
+//!
+//! Messages logged on every frame would flood the logs, so the repetitions of
+//! a message are rate limited: only the occurrences whose count is a power of two
+//! are logged, along with the count.
+
+use std::{
+    fmt,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+#[doc(hidden)]
+pub use log;
+
+/// Limits the logging of a repeating message.
+#[derive(Debug, Default)]
+pub struct RateLimit {
+    count: AtomicUsize,
+}
+
+impl RateLimit {
+    /// Create a new rate limit, with no occurrences.
+    pub const fn new() -> Self {
+        RateLimit {
+            count: AtomicUsize::new(0),
+        }
+    }
+
+    /// Count an occurrence of the message.
+    ///
+    /// Returns the number of occurrences so far if this one has to be logged.
+    pub fn check(&self) -> Option<usize> {
+        let count = self.count.fetch_add(1, Ordering::Relaxed) + 1;
+        if count.is_power_of_two() {
+            Some(count)
+        } else {
+            None
+        }
+    }
+}
+
+/// Suffix of a logged message, mentioning its number of occurrences.
+#[doc(hidden)]
+#[derive(Clone, Copy, Debug)]
+pub struct Repeats(pub usize);
+
+impl fmt::Display for Repeats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.0 > 1 {
+            write!(f, " (occurred {} times)", self.0)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Log a message at the given level, under a structured target and with a
+/// machine-readable code, limiting the rate of its repetitions.
+///
+/// ```
+/// # use gfx_auxil::report;
+/// report!(Warn, "gfx::gl::command", "depth-bounds-unsupported", "Depth bounds test is not supported");
+/// ```
+#[macro_export]
+macro_rules! report {
+    ($level:ident, $target:expr, $code:expr, $($arg:tt)+) => {{
+        static RATE_LIMIT: $crate::report::RateLimit = $crate::report::RateLimit::new();
+        if let Some(count) = RATE_LIMIT.check() {
+            $crate::report::log::log!(
+                target: $target,
+                $crate::report::log::Level::$level,
+                "[{}] {}{}",
+                $code,
+                format_args!($($arg)+),
+                $crate::report::Repeats(count)
+            );
+        }
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RateLimit, Repeats};
+
+    #[test]
+    fn rate_limit() {
+        let limit = RateLimit::new();
+        let logged = (0..20).filter_map(|_| limit.check()).collect::<Vec<_>>();
+        assert_eq!(logged, vec![1, 2, 4, 8, 16]);
+    }
+
+    #[test]
+    fn repeats() {
+        assert_eq!(Repeats(1).to_string(), "");
+        assert_eq!(Repeats(4).to_string(), " (occurred 4 times)");
+    }
+}
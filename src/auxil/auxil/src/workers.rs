@@ -0,0 +1,371 @@
+//! Background creation of the pipelines on a bounded pool of threads.
+
+use hal::{pass, pso, Backend};
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Condvar, Mutex},
+    thread,
+};
+
+/// Number of threads the backends create the pipelines with.
+pub const PIPELINE_THREADS: usize = 4;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+#[derive(Default)]
+struct Queue {
+    jobs: VecDeque<Job>,
+    threads: usize,
+    idle: usize,
+    closed: bool,
+}
+
+#[derive(Default)]
+struct Shared {
+    queue: Mutex<Queue>,
+    condvar: Condvar,
+}
+
+/// Pool of worker threads, spawned on demand up to a fixed count.
+///
+/// Dropping the pool blocks until the pending jobs are complete, which
+/// the owners of the objects used by the jobs can also do with `join`.
+pub struct Workers {
+    name: &'static str,
+    max_threads: usize,
+    shared: Arc<Shared>,
+    handles: Mutex<Vec<thread::JoinHandle<()>>>,
+}
+
+impl std::fmt::Debug for Workers {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fmt.debug_struct("Workers")
+            .field("name", &self.name)
+            .field("max_threads", &self.max_threads)
+            .finish()
+    }
+}
+
+impl Workers {
+    /// Create a pool of at most `max_threads` threads, named `name`.
+    pub fn new(name: &'static str, max_threads: usize) -> Self {
+        Workers {
+            name,
+            max_threads: max_threads.max(1),
+            shared: Arc::default(),
+            handles: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Run `job` on one of the threads, queueing it if they are all busy.
+    pub fn execute(&self, job: impl FnOnce() + Send + 'static) {
+        // Locked in the same order as in `join`
+        let mut handles = self.handles.lock().unwrap();
+        let mut queue = self.shared.queue.lock().unwrap();
+        queue.jobs.push_back(Box::new(job));
+        if queue.idle == 0 && queue.threads < self.max_threads {
+            let shared = Arc::clone(&self.shared);
+            let spawned = thread::Builder::new()
+                .name(self.name.to_string())
+                .spawn(move || Self::work(&shared));
+            match spawned {
+                Ok(handle) => {
+                    queue.threads += 1;
+                    handles.push(handle);
+                }
+                // The job runs on the existing threads, or right here if there are none
+                Err(_) if queue.threads == 0 => {
+                    let job = queue.jobs.pop_back().unwrap();
+                    drop(queue);
+                    drop(handles);
+                    job();
+                }
+                Err(_) => {}
+            }
+        } else {
+            self.shared.condvar.notify_one();
+        }
+    }
+
+    /// Wait for the pending jobs to complete and stop the threads.
+    ///
+    /// The jobs submitted afterwards are run on new threads.
+    pub fn join(&self) {
+        let mut handles = self.handles.lock().unwrap();
+        self.shared.queue.lock().unwrap().closed = true;
+        self.shared.condvar.notify_all();
+        for handle in handles.drain(..) {
+            let _ = handle.join();
+        }
+        let mut queue = self.shared.queue.lock().unwrap();
+        queue.threads = 0;
+        queue.closed = false;
+    }
+
+    fn work(shared: &Shared) {
+        let mut queue = shared.queue.lock().unwrap();
+        loop {
+            if let Some(job) = queue.jobs.pop_front() {
+                drop(queue);
+                job();
+                queue = shared.queue.lock().unwrap();
+            } else if queue.closed {
+                return;
+            } else {
+                queue.idle += 1;
+                queue = shared.condvar.wait(queue).unwrap();
+                queue.idle -= 1;
+            }
+        }
+    }
+}
+
+impl Drop for Workers {
+    fn drop(&mut self) {
+        self.join();
+    }
+}
+
+/// Pointer to an object that the caller of an asynchronous pipeline creation
+/// keeps alive until the task is complete.
+pub struct Detached<T>(*const T);
+
+unsafe impl<T: Sync> Send for Detached<T> {}
+
+impl<T> Detached<T> {
+    /// Detach the reference from its lifetime.
+    ///
+    /// # Safety
+    ///
+    /// The object has to outlive the returned pointer.
+    pub unsafe fn new(object: &T) -> Self {
+        Detached(object)
+    }
+
+    /// Get the object back.
+    ///
+    /// # Safety
+    ///
+    /// The object has to be still alive.
+    pub unsafe fn get(&self) -> &T {
+        &*self.0
+    }
+}
+
+/// Shader entry point owning its name and specialization.
+struct DetachedEntryPoint<B: Backend> {
+    entry: String,
+    module: Detached<B::ShaderModule>,
+    specialization: pso::Specialization<'static>,
+}
+
+impl<B: Backend> DetachedEntryPoint<B> {
+    unsafe fn new(ep: &pso::EntryPoint<B>) -> Self {
+        DetachedEntryPoint {
+            entry: ep.entry.to_string(),
+            module: Detached::new(ep.module),
+            specialization: pso::Specialization {
+                constants: ep.specialization.constants.to_vec().into(),
+                data: ep.specialization.data.to_vec().into(),
+            },
+        }
+    }
+
+    unsafe fn get(&self) -> pso::EntryPoint<'_, B> {
+        pso::EntryPoint {
+            entry: &self.entry,
+            module: self.module.get(),
+            specialization: self.specialization.clone(),
+        }
+    }
+}
+
+/// Graphics pipeline description detached from the lifetime of the caller,
+/// so that it can be moved to a worker thread.
+pub struct DetachedGraphicsPipelineDesc<B: Backend> {
+    label: Option<String>,
+    state: pso::GraphicsPipelineState,
+    vertex: DetachedEntryPoint<B>,
+    fragment: Option<DetachedEntryPoint<B>>,
+    layout: Detached<B::PipelineLayout>,
+    main_pass: Detached<B::RenderPass>,
+    subpass: pass::SubpassId,
+    parent: Option<Detached<B::GraphicsPipeline>>,
+}
+
+impl<B: Backend> DetachedGraphicsPipelineDesc<B> {
+    /// Detach the description, returning `None` for the pipelines that can't be
+    /// described this way: the ones with tessellation, geometry or mesh shaders,
+    /// and the ones deriving from another pipeline of the same creation call.
+    ///
+    /// # Safety
+    ///
+    /// The objects referenced by `desc` have to outlive the returned description.
+    pub unsafe fn new(desc: &pso::GraphicsPipelineDesc<B>) -> Option<Self> {
+        let vertex = match desc.primitive_assembler {
+            pso::PrimitiveAssemblerDesc::Vertex {
+                ref vertex,
+                tessellation: None,
+                geometry: None,
+                ..
+            } => vertex,
+            _ => return None,
+        };
+        let parent = match desc.parent {
+            pso::BasePipeline::None => None,
+            pso::BasePipeline::Pipeline(pipeline) => Some(Detached::new(pipeline)),
+            pso::BasePipeline::Index(_) => return None,
+        };
+        Some(DetachedGraphicsPipelineDesc {
+            label: desc.label.map(String::from),
+            state: desc.state(),
+            vertex: DetachedEntryPoint::new(vertex),
+            fragment: desc.fragment.as_ref().map(|ep| DetachedEntryPoint::new(ep)),
+            layout: Detached::new(desc.layout),
+            main_pass: Detached::new(desc.subpass.main_pass),
+            subpass: desc.subpass.index,
+            parent,
+        })
+    }
+
+    /// Call `fun` with the description reattached to the referenced objects.
+    ///
+    /// # Safety
+    ///
+    /// The referenced objects have to be still alive.
+    pub unsafe fn with<R>(&self, fun: impl FnOnce(&pso::GraphicsPipelineDesc<B>) -> R) -> R {
+        let mut desc = pso::GraphicsPipelineDesc::from_state(
+            &self.state,
+            self.vertex.get(),
+            self.fragment.as_ref().map(|ep| ep.get()),
+            self.layout.get(),
+            pass::Subpass {
+                index: self.subpass,
+                main_pass: self.main_pass.get(),
+            },
+        );
+        desc.label = self.label.as_deref();
+        if let Some(ref parent) = self.parent {
+            desc.parent = pso::BasePipeline::Pipeline(parent.get());
+        }
+        fun(&desc)
+    }
+}
+
+/// Compute pipeline description detached from the lifetime of the caller,
+/// so that it can be moved to a worker thread.
+pub struct DetachedComputePipelineDesc<B: Backend> {
+    label: Option<String>,
+    shader: DetachedEntryPoint<B>,
+    layout: Detached<B::PipelineLayout>,
+    flags: pso::PipelineCreationFlags,
+    parent: Option<Detached<B::ComputePipeline>>,
+}
+
+impl<B: Backend> DetachedComputePipelineDesc<B> {
+    /// Detach the description, returning `None` for the pipelines deriving
+    /// from another pipeline of the same creation call.
+    ///
+    /// # Safety
+    ///
+    /// The objects referenced by `desc` have to outlive the returned description.
+    pub unsafe fn new(desc: &pso::ComputePipelineDesc<B>) -> Option<Self> {
+        let parent = match desc.parent {
+            pso::BasePipeline::None => None,
+            pso::BasePipeline::Pipeline(pipeline) => Some(Detached::new(pipeline)),
+            pso::BasePipeline::Index(_) => return None,
+        };
+        Some(DetachedComputePipelineDesc {
+            label: desc.label.map(String::from),
+            shader: DetachedEntryPoint::new(&desc.shader),
+            layout: Detached::new(desc.layout),
+            flags: desc.flags,
+            parent,
+        })
+    }
+
+    /// Call `fun` with the description reattached to the referenced objects.
+    ///
+    /// # Safety
+    ///
+    /// The referenced objects have to be still alive.
+    pub unsafe fn with<R>(&self, fun: impl FnOnce(&pso::ComputePipelineDesc<B>) -> R) -> R {
+        let mut desc = pso::ComputePipelineDesc::new(self.shader.get(), self.layout.get());
+        desc.label = self.label.as_deref();
+        desc.flags = self.flags;
+        if let Some(ref parent) = self.parent {
+            desc.parent = pso::BasePipeline::Pipeline(parent.get());
+        }
+        fun(&desc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Workers;
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc, Arc,
+    };
+
+    #[test]
+    fn run_all_jobs() {
+        let workers = Workers::new("test", 2);
+        let (sender, receiver) = mpsc::channel();
+        for i in 0..16 {
+            let sender = sender.clone();
+            workers.execute(move || sender.send(i).unwrap());
+        }
+        let mut results = receiver.iter().take(16).collect::<Vec<_>>();
+        results.sort();
+        assert_eq!(results, (0..16).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn bounded_threads() {
+        let workers = Workers::new("test", 3);
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        for _ in 0..32 {
+            let (running, peak) = (Arc::clone(&running), Arc::clone(&peak));
+            workers.execute(move || {
+                let count = running.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(count, Ordering::SeqCst);
+                std::thread::sleep(std::time::Duration::from_millis(1));
+                running.fetch_sub(1, Ordering::SeqCst);
+            });
+        }
+        // Dropping waits for the queued jobs
+        drop(workers);
+        assert_eq!(running.load(Ordering::SeqCst), 0);
+        assert!(peak.load(Ordering::SeqCst) <= 3);
+    }
+
+    #[test]
+    fn spawn_on_demand() {
+        let workers = Workers::new("test", 4);
+        let (sender, receiver) = mpsc::channel();
+        workers.execute(move || sender.send(()).unwrap());
+        receiver.recv().unwrap();
+        assert_eq!(workers.shared.queue.lock().unwrap().threads, 1);
+    }
+
+    #[test]
+    fn join_and_reuse() {
+        let workers = Workers::new("test", 2);
+        let count = Arc::new(AtomicUsize::new(0));
+        for _ in 0..8 {
+            let count = Arc::clone(&count);
+            workers.execute(move || {
+                count.fetch_add(1, Ordering::SeqCst);
+            });
+        }
+        workers.join();
+        assert_eq!(count.load(Ordering::SeqCst), 8);
+
+        let (sender, receiver) = mpsc::channel();
+        workers.execute(move || sender.send(()).unwrap());
+        receiver.recv().unwrap();
+    }
+}
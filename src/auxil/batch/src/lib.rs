@@ -0,0 +1,700 @@
+//! Draw batching for gfx-hal.
+//!
+//! Scenes made of many small draws, like 2D sprites or UI widgets, are often
+//! bound by the CPU cost of recording them. A [`Batcher`] takes a stream of
+//! draws, each with the state it's recorded with and the data of its instances,
+//! and merges the consecutive draws sharing the same state and geometry into a
+//! single instanced draw.
+//!
+//! The instance data of all the draws is gathered into one array, which the
+//! caller uploads into a vertex buffer before recording the batches. The batches
+//! bind it at the instance binding, with per-instance vertex attributes read by
+//! the pipelines.
+//!
+//! Only consecutive draws get merged, so the order of the draws is preserved,
+//! which matters for blending.
+//!
+//! ```ignore
+//! let mut batcher = Batcher::new(mem::size_of::<Sprite>(), 1);
+//! for sprite in &sprites {
+//!     batcher.push(Draw {
+//!         state: state.clone(),
+//!         range: 0..6,
+//!         vertex_offset: 0,
+//!         instances: sprite.as_bytes(),
+//!     });
+//! }
+//! belt.upload_buffer(&device, &mut cmd_buffer, &instance_buffer, 0, batcher.instance_data())?;
+//! // begin the render pass
+//! batcher.record(&mut cmd_buffer, &instance_buffer, 0);
+//! ```
+#![warn(
+    trivial_casts,
+    trivial_numeric_casts,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications
+)]
+
+use hal::{
+    buffer,
+    command::{CommandBuffer as _, DescriptorSetOffset},
+    pso, Backend, IndexType, InstanceCount, VertexOffset,
+};
+
+use std::{iter, ops::Range, ptr};
+
+/// State a draw is recorded with.
+///
+/// Resources are compared by reference, so the draws have to refer
+/// to the same objects in order to be merged.
+#[derive(Debug)]
+pub struct DrawState<'a, B: Backend> {
+    /// Graphics pipeline.
+    pub pipeline: &'a B::GraphicsPipeline,
+    /// Layout of the pipeline.
+    pub layout: &'a B::PipelineLayout,
+    /// Descriptor sets, bound starting at set 0.
+    pub descriptor_sets: &'a [&'a B::DescriptorSet],
+    /// Dynamic offsets of the descriptor sets.
+    pub dynamic_offsets: &'a [DescriptorSetOffset],
+    /// Per-vertex buffers, bound starting at binding 0.
+    pub vertex_buffers: &'a [(&'a B::Buffer, buffer::SubRange)],
+    /// Index buffer of indexed draws.
+    pub index_buffer: Option<(&'a B::Buffer, buffer::SubRange, IndexType)>,
+    /// Shader stages of the push constants.
+    pub push_constant_stages: pso::ShaderStageFlags,
+    /// Push constants, written at offset 0.
+    pub push_constants: &'a [u32],
+}
+
+impl<B: Backend> Clone for DrawState<'_, B> {
+    fn clone(&self) -> Self {
+        DrawState {
+            pipeline: self.pipeline,
+            layout: self.layout,
+            descriptor_sets: self.descriptor_sets,
+            dynamic_offsets: self.dynamic_offsets,
+            vertex_buffers: self.vertex_buffers,
+            index_buffer: self.index_buffer.clone(),
+            push_constant_stages: self.push_constant_stages,
+            push_constants: self.push_constants,
+        }
+    }
+}
+
+impl<B: Backend> DrawState<'_, B> {
+    fn same_pipeline(&self, other: &Self) -> bool {
+        ptr::eq(self.pipeline, other.pipeline) && ptr::eq(self.layout, other.layout)
+    }
+
+    fn same_descriptor_sets(&self, other: &Self) -> bool {
+        self.descriptor_sets.len() == other.descriptor_sets.len()
+            && self
+                .descriptor_sets
+                .iter()
+                .zip(other.descriptor_sets)
+                .all(|(&a, &b)| ptr::eq(a, b))
+            && self.dynamic_offsets == other.dynamic_offsets
+    }
+
+    fn same_vertex_buffers(&self, other: &Self) -> bool {
+        self.vertex_buffers.len() == other.vertex_buffers.len()
+            && self
+                .vertex_buffers
+                .iter()
+                .zip(other.vertex_buffers)
+                .all(|((a, a_range), (b, b_range))| ptr::eq(*a, *b) && a_range == b_range)
+    }
+
+    fn same_index_buffer(&self, other: &Self) -> bool {
+        match (&self.index_buffer, &other.index_buffer) {
+            (None, None) => true,
+            (Some((a, a_range, a_type)), Some((b, b_range, b_type))) => {
+                ptr::eq(*a, *b) && a_range == b_range && a_type == b_type
+            }
+            _ => false,
+        }
+    }
+
+    fn same_push_constants(&self, other: &Self) -> bool {
+        self.push_constant_stages == other.push_constant_stages
+            && self.push_constants == other.push_constants
+    }
+
+    fn is_compatible(&self, other: &Self) -> bool {
+        self.same_pipeline(other)
+            && self.same_descriptor_sets(other)
+            && self.same_vertex_buffers(other)
+            && self.same_index_buffer(other)
+            && self.same_push_constants(other)
+    }
+}
+
+/// A draw of one or more instances.
+#[derive(Debug)]
+pub struct Draw<'a, B: Backend> {
+    /// State the draw is recorded with.
+    pub state: DrawState<'a, B>,
+    /// Range of vertices, or of indices if the state has an index buffer.
+    pub range: Range<u32>,
+    /// Value added to the indices before fetching the vertices, for indexed draws.
+    pub vertex_offset: VertexOffset,
+    /// Data of the instances, which has a multiple of the instance stride as size.
+    pub instances: &'a [u8],
+}
+
+impl<B: Backend> Clone for Draw<'_, B> {
+    fn clone(&self) -> Self {
+        Draw {
+            state: self.state.clone(),
+            range: self.range.clone(),
+            vertex_offset: self.vertex_offset,
+            instances: self.instances,
+        }
+    }
+}
+
+/// Statistics of the batching.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Stats {
+    /// Number of draws pushed.
+    pub draws: usize,
+    /// Number of draws recorded after merging.
+    pub batches: usize,
+    /// Number of instances of all the draws.
+    pub instances: InstanceCount,
+}
+
+/// Consecutive draws merged into one.
+#[derive(Debug)]
+struct Batch<'a, B: Backend> {
+    state: DrawState<'a, B>,
+    range: Range<u32>,
+    vertex_offset: VertexOffset,
+    /// Instances of the batch, in the instance data.
+    instances: Range<InstanceCount>,
+}
+
+/// Merges consecutive compatible draws into instanced draws.
+#[derive(Debug)]
+pub struct Batcher<'a, B: Backend> {
+    instance_stride: usize,
+    instance_binding: pso::BufferIndex,
+    batches: Vec<Batch<'a, B>>,
+    instance_data: Vec<u8>,
+    draws: usize,
+}
+
+impl<'a, B: Backend> Batcher<'a, B> {
+    /// Create a new batcher for instances of `instance_stride` bytes,
+    /// bound as a vertex buffer at `instance_binding`.
+    ///
+    /// The instance binding has to come after the per-vertex buffers of all the draws.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `instance_stride` is zero.
+    pub fn new(instance_stride: usize, instance_binding: pso::BufferIndex) -> Self {
+        assert_ne!(instance_stride, 0, "Instance stride must not be zero");
+        Batcher {
+            instance_stride,
+            instance_binding,
+            batches: Vec::new(),
+            instance_data: Vec::new(),
+            draws: 0,
+        }
+    }
+
+    /// Add a draw, merging it into the previous one if they only differ by the instances.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the size of the instance data is not a multiple of the instance stride.
+    pub fn push(&mut self, draw: Draw<'a, B>) {
+        assert_eq!(
+            draw.instances.len() % self.instance_stride,
+            0,
+            "Instance data size is not a multiple of the stride"
+        );
+        let count = (draw.instances.len() / self.instance_stride) as InstanceCount;
+        if count == 0 {
+            return;
+        }
+        self.draws += 1;
+        self.instance_data.extend_from_slice(draw.instances);
+
+        if let Some(batch) = self.batches.last_mut() {
+            if batch.range == draw.range
+                && batch.vertex_offset == draw.vertex_offset
+                && batch.state.is_compatible(&draw.state)
+            {
+                batch.instances.end += count;
+                return;
+            }
+        }
+        let start = self.batches.last().map_or(0, |batch| batch.instances.end);
+        self.batches.push(Batch {
+            state: draw.state,
+            range: draw.range,
+            vertex_offset: draw.vertex_offset,
+            instances: start..start + count,
+        });
+    }
+
+    /// Get the instance data of all the draws, to be uploaded into the instance buffer.
+    pub fn instance_data(&self) -> &[u8] {
+        &self.instance_data
+    }
+
+    /// Get the statistics of the batching.
+    pub fn stats(&self) -> Stats {
+        Stats {
+            draws: self.draws,
+            batches: self.batches.len(),
+            instances: self.batches.last().map_or(0, |batch| batch.instances.end),
+        }
+    }
+
+    /// Remove all the draws.
+    pub fn clear(&mut self) {
+        self.batches.clear();
+        self.instance_data.clear();
+        self.draws = 0;
+    }
+
+    /// Record the batches into `cmd_buffer`, reading the instances from
+    /// the instance data uploaded into `instance_buffer` at `instance_offset`.
+    ///
+    /// The state is only bound when it changes between the batches.
+    /// The command buffer has to be inside a render pass compatible
+    /// with the pipelines of the draws.
+    pub unsafe fn record(
+        &self,
+        cmd_buffer: &mut B::CommandBuffer,
+        instance_buffer: &B::Buffer,
+        instance_offset: buffer::Offset,
+    ) {
+        let mut previous: Option<&DrawState<'a, B>> = None;
+        for batch in &self.batches {
+            let state = &batch.state;
+            // Bindings are not assumed to persist across pipeline changes,
+            // as the layouts of the pipelines might differ.
+            let new_pipeline = previous.map_or(true, |prev| !prev.same_pipeline(state));
+            if new_pipeline {
+                cmd_buffer.bind_graphics_pipeline(state.pipeline);
+            }
+            if new_pipeline || !previous.unwrap().same_descriptor_sets(state) {
+                cmd_buffer.bind_graphics_descriptor_sets(
+                    state.layout,
+                    0,
+                    state.descriptor_sets.iter().cloned(),
+                    state.dynamic_offsets.iter().cloned(),
+                );
+            }
+            if (new_pipeline || !previous.unwrap().same_push_constants(state))
+                && !state.push_constants.is_empty()
+            {
+                cmd_buffer.push_graphics_constants(
+                    state.layout,
+                    state.push_constant_stages,
+                    0,
+                    state.push_constants,
+                );
+            }
+            if previous.map_or(true, |prev| !prev.same_vertex_buffers(state)) {
+                cmd_buffer.bind_vertex_buffers(
+                    0,
+                    state
+                        .vertex_buffers
+                        .iter()
+                        .map(|&(buffer, ref range)| (buffer, range.clone())),
+                );
+            }
+            if previous.map_or(true, |prev| !prev.same_index_buffer(state)) {
+                if let Some((buffer, ref range, index_type)) = state.index_buffer {
+                    cmd_buffer.bind_index_buffer(buffer, range.clone(), index_type);
+                }
+            }
+            previous = Some(state);
+
+            let offset = instance_offset
+                + batch.instances.start as buffer::Offset * self.instance_stride as buffer::Offset;
+            cmd_buffer.bind_vertex_buffers(
+                self.instance_binding,
+                iter::once((instance_buffer, buffer::SubRange { offset, size: None })),
+            );
+
+            let instances = 0..batch.instances.end - batch.instances.start;
+            if state.index_buffer.is_some() {
+                cmd_buffer.draw_indexed(batch.range.clone(), batch.vertex_offset, instances);
+            } else {
+                cmd_buffer.draw(batch.range.clone(), instances);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gfx_backend_empty as empty;
+    use gfx_backend_validation as validation;
+    use hal::{
+        adapter::PhysicalDevice as _, device::Device as _, pass, pso::DescriptorPool as _,
+        Instance as _,
+    };
+
+    type B = validation::Backend<empty::Backend>;
+
+    /// Objects the draw states refer to, with two distinct instances of each.
+    struct Objects {
+        layout: <B as Backend>::PipelineLayout,
+        pipelines: [<B as Backend>::GraphicsPipeline; 2],
+        sets: [<B as Backend>::DescriptorSet; 2],
+        buffers: [<B as Backend>::Buffer; 2],
+    }
+
+    impl Objects {
+        // The pipeline layouts of the empty backend are `()`.
+        #[allow(clippy::let_unit_value)]
+        fn new() -> Self {
+            let instance = validation::Instance::<empty::Backend>::create("batch", 1).unwrap();
+            let adapter = instance.enumerate_adapters().remove(0);
+            let family = &adapter.queue_families[0];
+            let gpu = unsafe {
+                adapter
+                    .physical_device
+                    .open(&[(family, &[1.0])], hal::Features::empty())
+                    .unwrap()
+            };
+            let device = gpu.device;
+            unsafe {
+                let set_layout = device
+                    .create_descriptor_set_layout(iter::empty(), iter::empty())
+                    .unwrap();
+                let layout = device
+                    .create_pipeline_layout(iter::once(&set_layout), iter::empty())
+                    .unwrap();
+                let mut pool = device
+                    .create_descriptor_pool(
+                        1,
+                        iter::empty(),
+                        pso::DescriptorPoolCreateFlags::empty(),
+                    )
+                    .unwrap();
+                let sets = [
+                    pool.allocate_one(&set_layout).unwrap(),
+                    pool.allocate_one(&set_layout).unwrap(),
+                ];
+                let render_pass = device
+                    .create_render_pass(
+                        iter::empty(),
+                        iter::once(pass::SubpassDesc {
+                            colors: &[],
+                            depth_stencil: None,
+                            inputs: &[],
+                            resolves: &[],
+                            preserves: &[],
+                            view_mask: 0,
+                            shading_rate: None,
+                        }),
+                        iter::empty(),
+                    )
+                    .unwrap();
+                let module = device.create_shader_module(&[]).unwrap();
+                let create_pipeline = || {
+                    let desc = pso::GraphicsPipelineDesc::new(
+                        pso::PrimitiveAssemblerDesc::Vertex {
+                            buffers: &[],
+                            attributes: &[],
+                            input_assembler: pso::InputAssemblerDesc::new(
+                                pso::Primitive::TriangleList,
+                            ),
+                            vertex: pso::EntryPoint {
+                                entry: "main",
+                                module: &module,
+                                specialization: pso::Specialization::default(),
+                            },
+                            tessellation: None,
+                            geometry: None,
+                        },
+                        pso::Rasterizer::FILL,
+                        None,
+                        &layout,
+                        pass::Subpass {
+                            index: 0,
+                            main_pass: &render_pass,
+                        },
+                    );
+                    device.create_graphics_pipeline(&desc, None).unwrap()
+                };
+                let pipelines = [create_pipeline(), create_pipeline()];
+                let create_buffer = || {
+                    device
+                        .create_buffer(256, buffer::Usage::VERTEX | buffer::Usage::INDEX)
+                        .unwrap()
+                };
+                let buffers = [create_buffer(), create_buffer()];
+                Objects {
+                    layout,
+                    pipelines,
+                    sets,
+                    buffers,
+                }
+            }
+        }
+
+        fn state(&self) -> DrawState<'_, B> {
+            DrawState {
+                pipeline: &self.pipelines[0],
+                layout: &self.layout,
+                descriptor_sets: &[],
+                dynamic_offsets: &[],
+                vertex_buffers: &[],
+                index_buffer: None,
+                push_constant_stages: pso::ShaderStageFlags::empty(),
+                push_constants: &[],
+            }
+        }
+    }
+
+    fn draw<'a>(state: DrawState<'a, B>, instances: &'a [u8]) -> Draw<'a, B> {
+        Draw {
+            state,
+            range: 0..6,
+            vertex_offset: 0,
+            instances,
+        }
+    }
+
+    fn batch_count(draws: &[Draw<'_, B>]) -> usize {
+        let mut batcher = Batcher::new(4, 1);
+        for draw in draws {
+            batcher.push(draw.clone());
+        }
+        batcher.stats().batches
+    }
+
+    #[test]
+    fn merge_same_state() {
+        let objects = Objects::new();
+        let data = [1u8, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12];
+        let mut batcher = Batcher::new(4, 1);
+        batcher.push(draw(objects.state(), &data[..4]));
+        batcher.push(draw(objects.state(), &data[4..]));
+        assert_eq!(
+            batcher.stats(),
+            Stats {
+                draws: 2,
+                batches: 1,
+                instances: 3,
+            }
+        );
+        assert_eq!(batcher.instance_data(), &data[..]);
+        assert_eq!(batcher.batches[0].instances, 0..3);
+    }
+
+    #[test]
+    fn split_on_geometry() {
+        let objects = Objects::new();
+        let data = [0u8; 4];
+        let mut other = draw(objects.state(), &data);
+        other.range = 0..3;
+        assert_eq!(batch_count(&[draw(objects.state(), &data), other]), 2);
+        let mut other = draw(objects.state(), &data);
+        other.vertex_offset = 1;
+        assert_eq!(batch_count(&[draw(objects.state(), &data), other]), 2);
+    }
+
+    #[test]
+    fn split_on_pipeline() {
+        let objects = Objects::new();
+        let data = [0u8; 4];
+        let state = DrawState {
+            pipeline: &objects.pipelines[1],
+            ..objects.state()
+        };
+        assert_eq!(
+            batch_count(&[draw(objects.state(), &data), draw(state, &data)]),
+            2
+        );
+    }
+
+    #[test]
+    fn split_on_descriptor_sets() {
+        let objects = Objects::new();
+        let data = [0u8; 4];
+        let sets = [&objects.sets[0], &objects.sets[1]];
+        let first = DrawState {
+            descriptor_sets: &sets[..1],
+            ..objects.state()
+        };
+        let same = DrawState {
+            descriptor_sets: &sets[..1],
+            ..objects.state()
+        };
+        assert_eq!(
+            batch_count(&[draw(first.clone(), &data), draw(same, &data)]),
+            1
+        );
+        let other = DrawState {
+            descriptor_sets: &sets[1..],
+            ..objects.state()
+        };
+        assert_eq!(
+            batch_count(&[draw(first.clone(), &data), draw(other, &data)]),
+            2
+        );
+        let offsets = [256];
+        let offset = DrawState {
+            dynamic_offsets: &offsets,
+            ..first.clone()
+        };
+        assert_eq!(batch_count(&[draw(first, &data), draw(offset, &data)]), 2);
+    }
+
+    #[test]
+    fn split_on_buffers() {
+        let objects = Objects::new();
+        let data = [0u8; 4];
+        let range = buffer::SubRange::WHOLE;
+        let vertex_buffers = [
+            (&objects.buffers[0], range.clone()),
+            (&objects.buffers[1], range.clone()),
+            (
+                &objects.buffers[0],
+                buffer::SubRange {
+                    offset: 64,
+                    size: None,
+                },
+            ),
+        ];
+        let first = DrawState {
+            vertex_buffers: &vertex_buffers[..1],
+            ..objects.state()
+        };
+        let same = DrawState {
+            vertex_buffers: &vertex_buffers[..1],
+            ..objects.state()
+        };
+        assert_eq!(
+            batch_count(&[draw(first.clone(), &data), draw(same, &data)]),
+            1
+        );
+        for other_buffers in &[&vertex_buffers[1..2], &vertex_buffers[2..]] {
+            let other = DrawState {
+                vertex_buffers: other_buffers,
+                ..objects.state()
+            };
+            assert_eq!(
+                batch_count(&[draw(first.clone(), &data), draw(other, &data)]),
+                2
+            );
+        }
+
+        let indexed = DrawState {
+            index_buffer: Some((&objects.buffers[0], range.clone(), IndexType::U16)),
+            ..objects.state()
+        };
+        assert_eq!(
+            batch_count(&[draw(indexed.clone(), &data), draw(indexed.clone(), &data)]),
+            1
+        );
+        assert_eq!(
+            batch_count(&[draw(objects.state(), &data), draw(indexed.clone(), &data)]),
+            2
+        );
+        let other = DrawState {
+            index_buffer: Some((&objects.buffers[1], range.clone(), IndexType::U16)),
+            ..objects.state()
+        };
+        assert_eq!(
+            batch_count(&[draw(indexed.clone(), &data), draw(other, &data)]),
+            2
+        );
+        let other = DrawState {
+            index_buffer: Some((&objects.buffers[0], range, IndexType::U32)),
+            ..objects.state()
+        };
+        assert_eq!(batch_count(&[draw(indexed, &data), draw(other, &data)]), 2);
+    }
+
+    #[test]
+    fn split_on_push_constants() {
+        let objects = Objects::new();
+        let data = [0u8; 4];
+        let constants = [1, 2];
+        let first = DrawState {
+            push_constant_stages: pso::ShaderStageFlags::VERTEX,
+            push_constants: &constants[..1],
+            ..objects.state()
+        };
+        let same_values = [1];
+        let same = DrawState {
+            push_constants: &same_values,
+            ..first.clone()
+        };
+        assert_eq!(
+            batch_count(&[draw(first.clone(), &data), draw(same, &data)]),
+            1
+        );
+        let other = DrawState {
+            push_constants: &constants[1..],
+            ..first.clone()
+        };
+        assert_eq!(
+            batch_count(&[draw(first.clone(), &data), draw(other, &data)]),
+            2
+        );
+        let other = DrawState {
+            push_constant_stages: pso::ShaderStageFlags::FRAGMENT,
+            ..first.clone()
+        };
+        assert_eq!(batch_count(&[draw(first, &data), draw(other, &data)]), 2);
+    }
+
+    #[test]
+    fn instance_data_of_batches() {
+        let objects = Objects::new();
+        let data = [1u8, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4];
+        let other = DrawState {
+            pipeline: &objects.pipelines[1],
+            ..objects.state()
+        };
+        let mut batcher = Batcher::new(4, 1);
+        batcher.push(draw(objects.state(), &data[..8]));
+        batcher.push(draw(objects.state(), &[]));
+        batcher.push(draw(other.clone(), &data[8..12]));
+        batcher.push(draw(other, &data[12..]));
+        batcher.push(draw(objects.state(), &data[..4]));
+        assert_eq!(
+            batcher.stats(),
+            Stats {
+                draws: 4,
+                batches: 3,
+                instances: 5,
+            }
+        );
+        assert_eq!(&batcher.instance_data()[..16], &data[..]);
+        assert_eq!(&batcher.instance_data()[16..], &data[..4]);
+        let instances = batcher
+            .batches
+            .iter()
+            .map(|batch| batch.instances.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(instances, vec![0..2, 2..4, 4..5]);
+
+        batcher.clear();
+        assert_eq!(batcher.stats(), Stats::default());
+        assert!(batcher.instance_data().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "multiple of the stride")]
+    fn unaligned_instance_data() {
+        let objects = Objects::new();
+        let mut batcher = Batcher::new(4, 1);
+        batcher.push(draw(objects.state(), &[0u8; 6]));
+    }
+}
@@ -0,0 +1,26 @@
+//! Compiles the GLSL kernels into SPIR-V, which the backends translate at runtime.
+
+use std::{env, fs, io::Read, path::Path};
+
+const SHADERS: &[&str] = &[
+    "reduce",
+    "scan",
+    "scan_add",
+    "radix_histogram",
+    "radix_scatter",
+];
+
+fn main() {
+    let out_dir = env::var("OUT_DIR").unwrap();
+    for name in SHADERS {
+        let path = format!("shaders/{}.comp", name);
+        println!("cargo:rerun-if-changed={}", path);
+
+        let source = fs::read_to_string(&path).unwrap();
+        let mut file = glsl_to_spirv::compile(&source, glsl_to_spirv::ShaderType::Compute)
+            .unwrap_or_else(|e| panic!("Failed to compile {}: {}", path, e));
+        let mut spirv = Vec::new();
+        file.read_to_end(&mut spirv).unwrap();
+        fs::write(Path::new(&out_dir).join(format!("{}.spv", name)), spirv).unwrap();
+    }
+}
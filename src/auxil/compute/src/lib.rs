@@ -0,0 +1,505 @@
+//! Reusable compute kernels for gfx-hal: radix sort, exclusive prefix sum and reduction.
+//!
+//! The kernels are written in GLSL, compiled to SPIR-V at build time and
+//! translated by each backend when the pipelines are created, so the same
+//! code runs everywhere compute shaders are supported.
+//!
+//! All operations work on tightly packed `u32` elements living in storage buffers
+//! (`buffer::Usage::STORAGE`). Some of them need a scratch buffer, whose size is
+//! given by the matching `*_scratch_size` method. Barriers between the internal
+//! dispatches are recorded by the kernels, while synchronizing the accesses
+//! before and after the recorded work is left to the caller.
+#![warn(
+    trivial_casts,
+    trivial_numeric_casts,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications
+)]
+
+use hal::{
+    buffer,
+    command::CommandBuffer as _,
+    device::{Device as _, OutOfMemory, ShaderError},
+    memory,
+    pso::{self, DescriptorPool as _},
+    Backend, Limits,
+};
+
+use std::{io, iter};
+
+#[cfg(test)]
+mod tests;
+
+/// Number of elements processed by a reduction or scan work group.
+const SCAN_BLOCK: u32 = 512;
+/// Number of keys processed by a radix sort work group.
+const SORT_BLOCK: u32 = 256;
+/// Number of bits sorted by each radix sort pass.
+const RADIX_BITS: u32 = 4;
+const RADIX_DIGITS: u32 = 1 << RADIX_BITS;
+const ELEMENT_SIZE: buffer::Offset = 4;
+
+/// Binding slots, shared by all the kernels.
+const BINDING_KEYS_IN: pso::DescriptorBinding = 0;
+const BINDING_VALUES_IN: pso::DescriptorBinding = 1;
+const BINDING_KEYS_OUT: pso::DescriptorBinding = 2;
+const BINDING_VALUES_OUT: pso::DescriptorBinding = 3;
+const BINDING_AUX: pso::DescriptorBinding = 4;
+const BINDING_COUNT: usize = 5;
+
+/// Error creating the compute kernels or recording their work.
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+pub enum Error {
+    /// Out of either host or device memory.
+    #[error(transparent)]
+    OutOfMemory(#[from] OutOfMemory),
+    /// A kernel failed to be translated for the backend.
+    #[error(transparent)]
+    Shader(#[from] ShaderError),
+    /// A compute pipeline failed to be created.
+    #[error(transparent)]
+    Pipeline(#[from] pso::CreationError),
+    /// The descriptor pool is exhausted, `Kernels::reset` needs to be called.
+    #[error(transparent)]
+    Allocation(#[from] pso::AllocationError),
+}
+
+/// A buffer range the kernels operate on.
+type BufferRange<'a, B> = (&'a <B as Backend>::Buffer, buffer::SubRange);
+
+/// Prepared levels of a multi-pass prefix sum.
+#[derive(Debug)]
+struct ScanPlan {
+    /// Number of elements at each level, starting with the input.
+    counts: Vec<u32>,
+    /// Descriptor set binding each level with the totals of its blocks.
+    sets: Vec<usize>,
+}
+
+/// Compute kernels along with the resources required to record them.
+#[derive(Debug)]
+pub struct Kernels<B: Backend> {
+    set_layout: B::DescriptorSetLayout,
+    pipeline_layout: B::PipelineLayout,
+    reduce: B::ComputePipeline,
+    scan: B::ComputePipeline,
+    scan_add: B::ComputePipeline,
+    radix_histogram: B::ComputePipeline,
+    radix_scatter: B::ComputePipeline,
+    pool: B::DescriptorPool,
+    sets: Vec<B::DescriptorSet>,
+    alignment: buffer::Offset,
+    max_groups_x: u32,
+}
+
+fn load_spirv(bytes: &[u8]) -> Vec<u32> {
+    auxil::read_spirv(io::Cursor::new(bytes)).unwrap()
+}
+
+fn div_ceil(value: u32, divisor: u32) -> u32 {
+    (value + divisor - 1) / divisor
+}
+
+fn align(offset: buffer::Offset, alignment: buffer::Offset) -> buffer::Offset {
+    (offset + alignment - 1) / alignment * alignment
+}
+
+fn elements(count: u32) -> buffer::Offset {
+    count as buffer::Offset * ELEMENT_SIZE
+}
+
+/// Number of elements of each level of a prefix sum, from the input down to a single total.
+fn scan_counts(count: u32) -> Vec<u32> {
+    let mut counts = vec![count];
+    let mut current = count;
+    loop {
+        let next = div_ceil(current, SCAN_BLOCK);
+        counts.push(next);
+        if current <= SCAN_BLOCK {
+            break counts;
+        }
+        current = next;
+    }
+}
+
+/// Number of elements of each level of a reduction, from the input down to the result.
+fn reduce_counts(count: u32) -> Vec<u32> {
+    let mut counts = vec![count];
+    let mut current = count;
+    loop {
+        current = div_ceil(current, SCAN_BLOCK);
+        counts.push(current);
+        if current == 1 {
+            break counts;
+        }
+    }
+}
+
+impl<B: Backend> Kernels<B> {
+    /// Create the kernels.
+    ///
+    /// `max_sets` is the number of descriptor sets available before a `reset`.
+    /// A prefix sum or a reduction of `n` elements uses `log512(n)` sets,
+    /// and a radix sort four more than the prefix sum of its histogram.
+    pub unsafe fn new(device: &B::Device, limits: &Limits, max_sets: usize) -> Result<Self, Error> {
+        let storage = pso::DescriptorType::Buffer {
+            ty: pso::BufferDescriptorType::Storage { read_only: false },
+            format: pso::BufferDescriptorFormat::Structured {
+                dynamic_offset: false,
+            },
+        };
+        let set_layout = device.create_descriptor_set_layout(
+            (0..BINDING_COUNT as pso::DescriptorBinding).map(|binding| {
+                pso::DescriptorSetLayoutBinding {
+                    binding,
+                    ty: storage,
+                    count: 1,
+                    stage_flags: pso::ShaderStageFlags::COMPUTE,
+                    immutable_samplers: false,
+                }
+            }),
+            iter::empty(),
+        )?;
+        let pipeline_layout = device.create_pipeline_layout(
+            iter::once(&set_layout),
+            iter::once((pso::ShaderStageFlags::COMPUTE, 0..8)),
+        )?;
+
+        let create_pipeline = |bytes: &[u8]| -> Result<B::ComputePipeline, Error> {
+            let module = device.create_shader_module(&load_spirv(bytes))?;
+            let desc = pso::ComputePipelineDesc::new(
+                pso::EntryPoint {
+                    entry: "main",
+                    module: &module,
+                    specialization: pso::Specialization::default(),
+                },
+                &pipeline_layout,
+            );
+            let pipeline = device.create_compute_pipeline(&desc, None);
+            device.destroy_shader_module(module);
+            Ok(pipeline?)
+        };
+        let reduce = create_pipeline(include_bytes!(concat!(env!("OUT_DIR"), "/reduce.spv")))?;
+        let scan = create_pipeline(include_bytes!(concat!(env!("OUT_DIR"), "/scan.spv")))?;
+        let scan_add = create_pipeline(include_bytes!(concat!(env!("OUT_DIR"), "/scan_add.spv")))?;
+        let radix_histogram = create_pipeline(include_bytes!(concat!(
+            env!("OUT_DIR"),
+            "/radix_histogram.spv"
+        )))?;
+        let radix_scatter = create_pipeline(include_bytes!(concat!(
+            env!("OUT_DIR"),
+            "/radix_scatter.spv"
+        )))?;
+
+        let pool = device.create_descriptor_pool(
+            max_sets,
+            iter::once(pso::DescriptorRangeDesc {
+                ty: storage,
+                count: max_sets * BINDING_COUNT,
+            }),
+            pso::DescriptorPoolCreateFlags::empty(),
+        )?;
+
+        Ok(Kernels {
+            set_layout,
+            pipeline_layout,
+            reduce,
+            scan,
+            scan_add,
+            radix_histogram,
+            radix_scatter,
+            pool,
+            sets: Vec::new(),
+            alignment: limits.min_storage_buffer_offset_alignment.max(ELEMENT_SIZE),
+            max_groups_x: limits.max_compute_work_group_count[0],
+        })
+    }
+
+    /// Release all the descriptor sets used by the recorded work.
+    ///
+    /// Must only be called once the command buffers referencing them have completed.
+    pub unsafe fn reset(&mut self) {
+        self.sets.clear();
+        self.pool.reset();
+    }
+
+    /// Destroy the kernels and their resources.
+    pub unsafe fn destroy(mut self, device: &B::Device) {
+        self.reset();
+        device.destroy_descriptor_pool(self.pool);
+        device.destroy_compute_pipeline(self.reduce);
+        device.destroy_compute_pipeline(self.scan);
+        device.destroy_compute_pipeline(self.scan_add);
+        device.destroy_compute_pipeline(self.radix_histogram);
+        device.destroy_compute_pipeline(self.radix_scatter);
+        device.destroy_pipeline_layout(self.pipeline_layout);
+        device.destroy_descriptor_set_layout(self.set_layout);
+    }
+
+    /// Size in bytes of the scratch buffer needed by `record_prefix_sum`.
+    pub fn prefix_sum_scratch_size(&self, count: u32) -> buffer::Offset {
+        scan_counts(count)[1..]
+            .iter()
+            .map(|&c| align(elements(c), self.alignment))
+            .sum()
+    }
+
+    /// Size in bytes of the scratch buffer needed by `record_reduce`.
+    pub fn reduce_scratch_size(&self, count: u32) -> buffer::Offset {
+        let counts = reduce_counts(count);
+        // The last level is written straight into the result.
+        counts[1..counts.len() - 1]
+            .iter()
+            .map(|&c| align(elements(c), self.alignment))
+            .sum()
+    }
+
+    /// Size in bytes of the scratch buffer needed by `record_radix_sort`.
+    pub fn radix_sort_scratch_size(&self, count: u32) -> buffer::Offset {
+        let histogram = RADIX_DIGITS * div_ceil(count, SORT_BLOCK);
+        2 * align(elements(count), self.alignment)
+            + align(elements(histogram), self.alignment)
+            + self.prefix_sum_scratch_size(histogram)
+    }
+
+    /// Record an in-place exclusive prefix sum of `count` elements at the start of `data`.
+    pub unsafe fn record_prefix_sum(
+        &mut self,
+        device: &B::Device,
+        cmd: &mut B::CommandBuffer,
+        data: &B::Buffer,
+        count: u32,
+        scratch: &B::Buffer,
+    ) -> Result<(), Error> {
+        if count == 0 {
+            return Ok(());
+        }
+        let plan = self.prepare_scan(device, (data, Self::whole(count)), count, scratch, 0)?;
+        self.record_scan(cmd, &plan);
+        Ok(())
+    }
+
+    /// Record the wrapping sum of `count` elements at the start of `data`,
+    /// writing it to `result` at `result_offset`.
+    ///
+    /// `result_offset` has to be aligned to `Limits::min_storage_buffer_offset_alignment`.
+    #[allow(clippy::too_many_arguments)]
+    pub unsafe fn record_reduce(
+        &mut self,
+        device: &B::Device,
+        cmd: &mut B::CommandBuffer,
+        data: &B::Buffer,
+        count: u32,
+        scratch: &B::Buffer,
+        result: &B::Buffer,
+        result_offset: buffer::Offset,
+    ) -> Result<(), Error> {
+        if count == 0 {
+            cmd.fill_buffer(
+                result,
+                buffer::SubRange {
+                    offset: result_offset,
+                    size: Some(ELEMENT_SIZE),
+                },
+                0,
+            );
+            return Ok(());
+        }
+
+        let counts = reduce_counts(count);
+        let mut ranges = vec![(data, Self::whole(count))];
+        let mut offset = 0;
+        for &c in &counts[1..counts.len() - 1] {
+            ranges.push((scratch, self.scratch_range(&mut offset, c)));
+        }
+        ranges.push((
+            result,
+            buffer::SubRange {
+                offset: result_offset,
+                size: Some(ELEMENT_SIZE),
+            },
+        ));
+
+        cmd.bind_compute_pipeline(&self.reduce);
+        for (level, pair) in ranges.windows(2).enumerate() {
+            let set = self.allocate_set(
+                device,
+                &[
+                    (BINDING_KEYS_IN, pair[0].clone()),
+                    (BINDING_KEYS_OUT, pair[1].clone()),
+                ],
+            )?;
+            self.dispatch(cmd, set, counts[level], 0, SCAN_BLOCK);
+        }
+        Ok(())
+    }
+
+    /// Record a stable ascending sort of `count` keys at the start of `keys`,
+    /// moving the values at the start of `values` along with them.
+    pub unsafe fn record_radix_sort(
+        &mut self,
+        device: &B::Device,
+        cmd: &mut B::CommandBuffer,
+        keys: &B::Buffer,
+        values: &B::Buffer,
+        count: u32,
+        scratch: &B::Buffer,
+    ) -> Result<(), Error> {
+        if count == 0 {
+            return Ok(());
+        }
+
+        let histogram_count = RADIX_DIGITS * div_ceil(count, SORT_BLOCK);
+        let mut offset = 0;
+        let alt_keys = self.scratch_range(&mut offset, count);
+        let alt_values = self.scratch_range(&mut offset, count);
+        let histogram = (scratch, self.scratch_range(&mut offset, histogram_count));
+        let plan =
+            self.prepare_scan(device, histogram.clone(), histogram_count, scratch, offset)?;
+
+        // Keys and values ping-pong between the user buffers and the scratch.
+        let primary = [(keys, Self::whole(count)), (values, Self::whole(count))];
+        let secondary = [(scratch, alt_keys), (scratch, alt_values)];
+        let mut histogram_sets = [0; 2];
+        let mut scatter_sets = [0; 2];
+        for (i, &(src, dst)) in [(&primary, &secondary), (&secondary, &primary)]
+            .iter()
+            .enumerate()
+        {
+            histogram_sets[i] = self.allocate_set(
+                device,
+                &[
+                    (BINDING_KEYS_IN, src[0].clone()),
+                    (BINDING_AUX, histogram.clone()),
+                ],
+            )?;
+            scatter_sets[i] = self.allocate_set(
+                device,
+                &[
+                    (BINDING_KEYS_IN, src[0].clone()),
+                    (BINDING_VALUES_IN, src[1].clone()),
+                    (BINDING_KEYS_OUT, dst[0].clone()),
+                    (BINDING_VALUES_OUT, dst[1].clone()),
+                    (BINDING_AUX, histogram.clone()),
+                ],
+            )?;
+        }
+
+        // An even number of passes leaves the result in the user buffers.
+        for pass in 0..32 / RADIX_BITS {
+            let shift = pass * RADIX_BITS;
+            let i = pass as usize % 2;
+            cmd.bind_compute_pipeline(&self.radix_histogram);
+            self.dispatch(cmd, histogram_sets[i], count, shift, SORT_BLOCK);
+            self.record_scan(cmd, &plan);
+            cmd.bind_compute_pipeline(&self.radix_scatter);
+            self.dispatch(cmd, scatter_sets[i], count, shift, SORT_BLOCK);
+        }
+        Ok(())
+    }
+
+    fn whole(count: u32) -> buffer::SubRange {
+        buffer::SubRange {
+            offset: 0,
+            size: Some(elements(count)),
+        }
+    }
+
+    /// Take the next aligned range of `count` elements from the scratch buffer.
+    fn scratch_range(&self, offset: &mut buffer::Offset, count: u32) -> buffer::SubRange {
+        let range = buffer::SubRange {
+            offset: *offset,
+            size: Some(elements(count)),
+        };
+        *offset += align(elements(count), self.alignment);
+        range
+    }
+
+    unsafe fn allocate_set(
+        &mut self,
+        device: &B::Device,
+        bindings: &[(pso::DescriptorBinding, BufferRange<B>)],
+    ) -> Result<usize, Error> {
+        let mut set = self.pool.allocate_one(&self.set_layout)?;
+        for &(binding, (buffer, ref range)) in bindings {
+            device.write_descriptor_set(pso::DescriptorSetWrite {
+                set: &mut set,
+                binding,
+                array_offset: 0,
+                descriptors: iter::once(pso::Descriptor::Buffer(buffer, range.clone())),
+            });
+        }
+        self.sets.push(set);
+        Ok(self.sets.len() - 1)
+    }
+
+    unsafe fn prepare_scan(
+        &mut self,
+        device: &B::Device,
+        data: BufferRange<B>,
+        count: u32,
+        scratch: &B::Buffer,
+        mut offset: buffer::Offset,
+    ) -> Result<ScanPlan, Error> {
+        let counts = scan_counts(count);
+        let mut ranges = vec![data];
+        for &c in &counts[1..] {
+            ranges.push((scratch, self.scratch_range(&mut offset, c)));
+        }
+        let mut sets = Vec::with_capacity(counts.len() - 1);
+        for pair in ranges.windows(2) {
+            sets.push(self.allocate_set(
+                device,
+                &[
+                    (BINDING_KEYS_IN, pair[0].clone()),
+                    (BINDING_AUX, pair[1].clone()),
+                ],
+            )?);
+        }
+        Ok(ScanPlan { counts, sets })
+    }
+
+    unsafe fn record_scan(&self, cmd: &mut B::CommandBuffer, plan: &ScanPlan) {
+        // Scan each level, producing the totals of its blocks as the next level.
+        cmd.bind_compute_pipeline(&self.scan);
+        for (level, &set) in plan.sets.iter().enumerate() {
+            self.dispatch(cmd, set, plan.counts[level], 0, SCAN_BLOCK);
+        }
+        // The last scanned level fits in a single block, add the others back top-down.
+        cmd.bind_compute_pipeline(&self.scan_add);
+        for (level, &set) in plan.sets.iter().enumerate().rev().skip(1) {
+            self.dispatch(cmd, set, plan.counts[level], 0, SCAN_BLOCK);
+        }
+    }
+
+    /// Dispatch the bound kernel over `count` elements, followed by a barrier.
+    unsafe fn dispatch(
+        &self,
+        cmd: &mut B::CommandBuffer,
+        set: usize,
+        count: u32,
+        shift: u32,
+        block: u32,
+    ) {
+        cmd.bind_compute_descriptor_sets(
+            &self.pipeline_layout,
+            0,
+            iter::once(&self.sets[set]),
+            iter::empty(),
+        );
+        cmd.push_compute_constants(&self.pipeline_layout, 0, &[count, shift]);
+        // Large inputs are spread over a second dimension of work groups.
+        let groups = div_ceil(count, block);
+        let groups_x = groups.min(self.max_groups_x);
+        cmd.dispatch([groups_x, div_ceil(groups, groups_x), 1]);
+        cmd.pipeline_barrier(
+            pso::PipelineStage::COMPUTE_SHADER..pso::PipelineStage::COMPUTE_SHADER,
+            memory::Dependencies::empty(),
+            iter::once(memory::Barrier::AllBuffers(
+                buffer::Access::SHADER_WRITE
+                    ..buffer::Access::SHADER_READ | buffer::Access::SHADER_WRITE,
+            )),
+        );
+    }
+}
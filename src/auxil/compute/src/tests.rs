@@ -0,0 +1,314 @@
+//! Models of the kernels on the CPU, driven by the same plans as the recorded work.
+//!
+//! Each model follows its shader phase by phase, a phase being the code
+//! between two barriers, with all the invocations of a work group running it
+//! before the next one starts.
+
+use crate::{div_ceil, reduce_counts, scan_counts, RADIX_BITS, SCAN_BLOCK, SORT_BLOCK};
+
+use std::mem;
+
+const SCAN_GROUP: usize = SCAN_BLOCK as usize / 2;
+const SORT_GROUP: usize = SORT_BLOCK as usize;
+
+/// Deterministic pseudo-random elements.
+fn random(count: u32, mut state: u32) -> Vec<u32> {
+    (0..count)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state
+        })
+        .collect()
+}
+
+/// `reduce.comp`
+fn reduce(src: &[u32], dst: &mut [u32], count: u32) {
+    let num_blocks = div_ceil(count, SCAN_BLOCK) as usize;
+    for (block, result) in dst.iter_mut().enumerate().take(num_blocks) {
+        let mut partial = [0u32; SCAN_GROUP];
+        for (i, value) in partial.iter_mut().enumerate() {
+            let index = block * SCAN_BLOCK as usize + i;
+            if index < count as usize {
+                *value = value.wrapping_add(src[index]);
+            }
+            if index + SCAN_GROUP < count as usize {
+                *value = value.wrapping_add(src[index + SCAN_GROUP]);
+            }
+        }
+        let mut stride = SCAN_GROUP / 2;
+        while stride > 0 {
+            for i in 0..stride {
+                partial[i] = partial[i].wrapping_add(partial[i + stride]);
+            }
+            stride >>= 1;
+        }
+        *result = partial[0];
+    }
+}
+
+/// `scan.comp`
+fn scan(data: &mut [u32], sums: &mut [u32], count: u32) {
+    let num_blocks = div_ceil(count, SCAN_BLOCK) as usize;
+    for (block, sum) in sums.iter_mut().enumerate().take(num_blocks) {
+        let base = block * SCAN_BLOCK as usize;
+        let mut temp = [0u32; SCAN_BLOCK as usize];
+        for (a, value) in temp.iter_mut().enumerate() {
+            if base + a < count as usize {
+                *value = data[base + a];
+            }
+        }
+
+        let mut offset = 1;
+        let mut d = SCAN_GROUP;
+        while d > 0 {
+            for i in 0..d {
+                let ai = offset * (2 * i + 1) - 1;
+                let bi = offset * (2 * i + 2) - 1;
+                temp[bi] = temp[bi].wrapping_add(temp[ai]);
+            }
+            offset <<= 1;
+            d >>= 1;
+        }
+
+        *sum = temp[SCAN_BLOCK as usize - 1];
+        temp[SCAN_BLOCK as usize - 1] = 0;
+
+        let mut d = 1;
+        while d < SCAN_BLOCK as usize {
+            offset >>= 1;
+            for i in 0..d {
+                let ai = offset * (2 * i + 1) - 1;
+                let bi = offset * (2 * i + 2) - 1;
+                let t = temp[ai];
+                temp[ai] = temp[bi];
+                temp[bi] = temp[bi].wrapping_add(t);
+            }
+            d <<= 1;
+        }
+
+        for (a, &value) in temp.iter().enumerate() {
+            if base + a < count as usize {
+                data[base + a] = value;
+            }
+        }
+    }
+}
+
+/// `scan_add.comp`
+fn scan_add(data: &mut [u32], sums: &[u32], count: u32) {
+    for (index, value) in data[..count as usize].iter_mut().enumerate() {
+        *value = value.wrapping_add(sums[index / SCAN_BLOCK as usize]);
+    }
+}
+
+/// `radix_histogram.comp`
+fn radix_histogram(keys: &[u32], histogram: &mut [u32], count: u32, shift: u32) {
+    let num_blocks = div_ceil(count, SORT_BLOCK) as usize;
+    for block in 0..num_blocks {
+        let mut counts = [0u32; 16];
+        for &key in keys[..count as usize]
+            .iter()
+            .skip(block * SORT_GROUP)
+            .take(SORT_GROUP)
+        {
+            counts[(key >> shift) as usize & 15] += 1;
+        }
+        for (digit, &c) in counts.iter().enumerate() {
+            histogram[digit * num_blocks + block] = c;
+        }
+    }
+}
+
+/// `radix_scatter.comp`
+fn radix_scatter(
+    src: (&[u32], &[u32]),
+    dst: (&mut [u32], &mut [u32]),
+    offsets: &[u32],
+    count: u32,
+    shift: u32,
+) {
+    fn add(a: [u32; 4], b: [u32; 4]) -> [u32; 4] {
+        [
+            a[0].wrapping_add(b[0]),
+            a[1].wrapping_add(b[1]),
+            a[2].wrapping_add(b[2]),
+            a[3].wrapping_add(b[3]),
+        ]
+    }
+
+    let num_blocks = div_ceil(count, SORT_BLOCK) as usize;
+    for block in 0..num_blocks {
+        let mut ranks_lo = [[0u32; 4]; SORT_GROUP];
+        let mut ranks_hi = [[0u32; 4]; SORT_GROUP];
+        let mut digits = [0; SORT_GROUP];
+        for i in 0..SORT_GROUP {
+            let index = block * SORT_GROUP + i;
+            let valid = index < count as usize;
+            let key = if valid { src.0[index] } else { 0 };
+            let digit = (key >> shift) & 15;
+            let mut flag = [0; 4];
+            if valid {
+                flag[(digit as usize >> 1) & 3] = 1 << ((digit & 1) * 16);
+            }
+            if digit < 8 {
+                ranks_lo[i] = flag;
+            } else {
+                ranks_hi[i] = flag;
+            }
+            digits[i] = digit;
+        }
+
+        let mut offset = 1;
+        let mut d = SORT_GROUP / 2;
+        while d > 0 {
+            for i in 0..d {
+                let ai = offset * (2 * i + 1) - 1;
+                let bi = offset * (2 * i + 2) - 1;
+                ranks_lo[bi] = add(ranks_lo[bi], ranks_lo[ai]);
+                ranks_hi[bi] = add(ranks_hi[bi], ranks_hi[ai]);
+            }
+            offset <<= 1;
+            d >>= 1;
+        }
+
+        ranks_lo[SORT_GROUP - 1] = [0; 4];
+        ranks_hi[SORT_GROUP - 1] = [0; 4];
+
+        let mut d = 1;
+        while d < SORT_GROUP {
+            offset >>= 1;
+            for i in 0..d {
+                let ai = offset * (2 * i + 1) - 1;
+                let bi = offset * (2 * i + 2) - 1;
+                let t = ranks_lo[ai];
+                ranks_lo[ai] = ranks_lo[bi];
+                ranks_lo[bi] = add(ranks_lo[bi], t);
+                let t = ranks_hi[ai];
+                ranks_hi[ai] = ranks_hi[bi];
+                ranks_hi[bi] = add(ranks_hi[bi], t);
+            }
+            d <<= 1;
+        }
+
+        for (i, &digit) in digits.iter().enumerate() {
+            let index = block * SORT_GROUP + i;
+            if index >= count as usize {
+                break;
+            }
+            let ranks = if digit < 8 { ranks_lo[i] } else { ranks_hi[i] };
+            let rank = (ranks[(digit as usize >> 1) & 3] >> ((digit & 1) * 16)) & 0xFFFF;
+            let position = (offsets[digit as usize * num_blocks + block] + rank) as usize;
+            dst.0[position] = src.0[index];
+            dst.1[position] = src.1[index];
+        }
+    }
+}
+
+/// `Kernels::record_prefix_sum`
+fn prefix_sum(data: &mut [u32], count: u32) {
+    let counts = scan_counts(count);
+    let mut levels = vec![data[..count as usize].to_vec()];
+    levels.extend(counts[1..].iter().map(|&c| vec![0; c as usize]));
+
+    for level in 0..counts.len() - 1 {
+        let (lower, upper) = levels.split_at_mut(level + 1);
+        scan(&mut lower[level], &mut upper[0], counts[level]);
+    }
+    for level in (0..counts.len() - 1).rev().skip(1) {
+        let (lower, upper) = levels.split_at_mut(level + 1);
+        scan_add(&mut lower[level], &upper[0], counts[level]);
+    }
+    data[..count as usize].copy_from_slice(&levels[0]);
+}
+
+/// `Kernels::record_reduce`
+fn sum(data: &[u32], count: u32) -> u32 {
+    let counts = reduce_counts(count);
+    let mut current = data[..count as usize].to_vec();
+    for level in 0..counts.len() - 1 {
+        let mut next = vec![0; counts[level + 1] as usize];
+        reduce(&current, &mut next, counts[level]);
+        current = next;
+    }
+    current[0]
+}
+
+/// `Kernels::record_radix_sort`
+fn radix_sort(keys: &mut [u32], values: &mut [u32], count: u32) {
+    let histogram_count = 16 * div_ceil(count, SORT_BLOCK);
+    let mut histogram = vec![0; histogram_count as usize];
+    let mut src = (keys.to_vec(), values.to_vec());
+    let mut dst = (vec![0; count as usize], vec![0; count as usize]);
+
+    for pass in 0..32 / RADIX_BITS {
+        let shift = pass * RADIX_BITS;
+        radix_histogram(&src.0, &mut histogram, count, shift);
+        prefix_sum(&mut histogram, histogram_count);
+        radix_scatter(
+            (&src.0, &src.1),
+            (&mut dst.0, &mut dst.1),
+            &histogram,
+            count,
+            shift,
+        );
+        mem::swap(&mut src, &mut dst);
+    }
+    keys.copy_from_slice(&src.0);
+    values.copy_from_slice(&src.1);
+}
+
+#[test]
+fn level_counts() {
+    assert_eq!(scan_counts(1), [1, 1]);
+    assert_eq!(scan_counts(512), [512, 1]);
+    assert_eq!(scan_counts(513), [513, 2, 1]);
+    assert_eq!(scan_counts(512 * 512 + 1), [512 * 512 + 1, 513, 2, 1]);
+    assert_eq!(reduce_counts(1), [1, 1]);
+    assert_eq!(reduce_counts(512), [512, 1]);
+    assert_eq!(reduce_counts(513), [513, 2, 1]);
+}
+
+#[test]
+fn prefix_sum_matches_sequential() {
+    for &count in &[1, 2, 511, 512, 513, 300_000] {
+        let mut data = random(count, count);
+        let mut expected = Vec::with_capacity(data.len());
+        let mut total = 0u32;
+        for &value in &data {
+            expected.push(total);
+            total = total.wrapping_add(value);
+        }
+        prefix_sum(&mut data, count);
+        assert_eq!(data, expected, "count {}", count);
+    }
+}
+
+#[test]
+fn reduce_matches_sequential() {
+    for &count in &[1, 2, 511, 512, 513, 300_000] {
+        let data = random(count, count);
+        let expected = data.iter().fold(0u32, |sum, &v| sum.wrapping_add(v));
+        assert_eq!(sum(&data, count), expected, "count {}", count);
+    }
+}
+
+#[test]
+fn radix_sort_is_stable() {
+    for &count in &[1, 255, 256, 257, 5000, 70_000] {
+        for &mask in &[!0, 0xF0F, 0] {
+            let mut keys = random(count, count)
+                .into_iter()
+                .map(|key| key & mask)
+                .collect::<Vec<_>>();
+            let mut values = (0..count).collect::<Vec<_>>();
+            let mut expected = keys.iter().cloned().zip(0..count).collect::<Vec<_>>();
+            expected.sort_by_key(|&(key, _)| key);
+
+            radix_sort(&mut keys, &mut values, count);
+            let sorted = keys.into_iter().zip(values).collect::<Vec<_>>();
+            assert_eq!(sorted, expected, "count {}, mask {:#x}", count, mask);
+        }
+    }
+}
@@ -0,0 +1,309 @@
+//! Descriptor set allocator for gfx-hal.
+//!
+//! `DescriptorPool::allocate` takes the pool by `&mut`, so sharing one pool
+//! between the threads loading materials would serialize all of them on a lock.
+//! `DescriptorAllocator` is a single logical pool made of several shards, each
+//! with its own lock and its own list of `DescriptorPool`s. A thread allocates
+//! from the shard it was assigned to, or from any other shard that isn't busy,
+//! and only waits when all of them are. A shard grows by another pool once the
+//! existing ones are exhausted.
+//!
+//! ```ignore
+//! let allocator = DescriptorAllocator::new(ranges, 256, 4);
+//! // from any thread
+//! let mut set = allocator.allocate(&device, &layout)?;
+//! device.write_descriptor_set(pso::DescriptorSetWrite {
+//!     set: set.raw_mut(),
+//!     binding: 0,
+//!     array_offset: 0,
+//!     descriptors: iter::once(pso::Descriptor::Buffer(&buffer, buffer::SubRange::WHOLE)),
+//! });
+//! allocator.free(set);
+//! ```
+#![warn(
+    trivial_casts,
+    trivial_numeric_casts,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications
+)]
+
+use hal::{
+    device::{Device as _, OutOfMemory},
+    pso::{self, AllocationError, DescriptorPool as _},
+    Backend,
+};
+use parking_lot::{Mutex, MutexGuard};
+
+use std::{
+    iter,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Error allocating a descriptor set.
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+pub enum Error {
+    /// A descriptor pool or set failed to be allocated.
+    #[error(transparent)]
+    OutOfMemory(#[from] OutOfMemory),
+    /// The layout needs more descriptors than a pool with the ranges of the allocator holds.
+    #[error("Descriptor set layout doesn't fit into a pool with the allocator ranges")]
+    OutOfPoolMemory,
+    /// The layout can't be allocated from the pools of the allocator.
+    #[error("Descriptor set layout is incompatible with the pools")]
+    IncompatibleLayout,
+}
+
+/// Returns the shard index preferred by the current thread.
+///
+/// The threads get consecutive hints, so that the first few of them don't
+/// compete for the same shard.
+fn shard_hint() -> usize {
+    static NEXT_HINT: AtomicUsize = AtomicUsize::new(0);
+    thread_local! {
+        static HINT: usize = NEXT_HINT.fetch_add(1, Ordering::Relaxed);
+    }
+    HINT.with(|&hint| hint)
+}
+
+/// A descriptor set allocated from a `DescriptorAllocator`.
+#[derive(Debug)]
+pub struct DescriptorSet<B: Backend> {
+    raw: B::DescriptorSet,
+    shard: usize,
+    pool: usize,
+}
+
+impl<B: Backend> DescriptorSet<B> {
+    /// Get the raw descriptor set, to bind it.
+    pub fn raw(&self) -> &B::DescriptorSet {
+        &self.raw
+    }
+
+    /// Get the raw descriptor set, to write into it.
+    pub fn raw_mut(&mut self) -> &mut B::DescriptorSet {
+        &mut self.raw
+    }
+}
+
+#[derive(Debug)]
+struct Shard<B: Backend> {
+    pools: Vec<B::DescriptorPool>,
+}
+
+impl<B: Backend> Shard<B> {
+    /// Allocate a set from the newest pool that has space for it,
+    /// creating a new pool if none does.
+    unsafe fn allocate(
+        &mut self,
+        device: &B::Device,
+        layout: &B::DescriptorSetLayout,
+        ranges: &[pso::DescriptorRangeDesc],
+        max_sets: usize,
+    ) -> Result<(usize, B::DescriptorSet), Error> {
+        for (index, pool) in self.pools.iter_mut().enumerate().rev() {
+            match pool.allocate_one(layout) {
+                Ok(set) => return Ok((index, set)),
+                Err(AllocationError::OutOfPoolMemory) | Err(AllocationError::FragmentedPool) => {}
+                Err(AllocationError::OutOfMemory(oom)) => return Err(oom.into()),
+                Err(AllocationError::IncompatibleLayout) => return Err(Error::IncompatibleLayout),
+            }
+        }
+
+        let mut pool = device.create_descriptor_pool(
+            max_sets,
+            ranges.iter().cloned(),
+            pso::DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET,
+        )?;
+        match pool.allocate_one(layout) {
+            Ok(set) => {
+                self.pools.push(pool);
+                Ok((self.pools.len() - 1, set))
+            }
+            Err(err) => {
+                device.destroy_descriptor_pool(pool);
+                Err(match err {
+                    AllocationError::OutOfMemory(oom) => oom.into(),
+                    // a fresh pool can't hold the set, so no pool of this allocator ever will
+                    AllocationError::OutOfPoolMemory | AllocationError::FragmentedPool => {
+                        Error::OutOfPoolMemory
+                    }
+                    AllocationError::IncompatibleLayout => Error::IncompatibleLayout,
+                })
+            }
+        }
+    }
+}
+
+/// Allocator of descriptor sets, usable from multiple threads at once.
+#[derive(Debug)]
+pub struct DescriptorAllocator<B: Backend> {
+    ranges: Vec<pso::DescriptorRangeDesc>,
+    max_sets: usize,
+    shards: Box<[Mutex<Shard<B>>]>,
+}
+
+impl<B: Backend> DescriptorAllocator<B> {
+    /// Create a new allocator with `shard_count` shards, growing by pools
+    /// of `max_sets` sets with the descriptors of `ranges`.
+    ///
+    /// The number of shards is best matched with the number of threads
+    /// allocating at the same time. No pools are created until the first allocation.
+    pub fn new<I>(ranges: I, max_sets: usize, shard_count: usize) -> Self
+    where
+        I: IntoIterator<Item = pso::DescriptorRangeDesc>,
+    {
+        DescriptorAllocator {
+            ranges: ranges.into_iter().collect(),
+            max_sets,
+            shards: (0..shard_count.max(1))
+                .map(|_| Mutex::new(Shard { pools: Vec::new() }))
+                .collect(),
+        }
+    }
+
+    /// Lock the shard of the current thread, or any other free one.
+    /// Only blocks if all the shards are locked.
+    fn lock_shard(&self) -> (usize, MutexGuard<Shard<B>>) {
+        let count = self.shards.len();
+        let home = shard_hint() % count;
+        (0..count)
+            .map(|i| (home + i) % count)
+            .find_map(|index| self.shards[index].try_lock().map(|guard| (index, guard)))
+            .unwrap_or_else(|| (home, self.shards[home].lock()))
+    }
+
+    /// Allocate a descriptor set with the given layout.
+    pub unsafe fn allocate(
+        &self,
+        device: &B::Device,
+        layout: &B::DescriptorSetLayout,
+    ) -> Result<DescriptorSet<B>, Error> {
+        let (shard, mut guard) = self.lock_shard();
+        let (pool, raw) = guard.allocate(device, layout, &self.ranges, self.max_sets)?;
+        Ok(DescriptorSet { raw, shard, pool })
+    }
+
+    /// Free a descriptor set, returning its descriptors to the pool.
+    ///
+    /// The set must not be in use by the device.
+    pub unsafe fn free(&self, set: DescriptorSet<B>) {
+        let mut shard = self.shards[set.shard].lock();
+        shard.pools[set.pool].free(iter::once(set.raw));
+    }
+
+    /// Number of the descriptor pools created by all the shards.
+    pub fn pool_count(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| shard.lock().pools.len())
+            .sum()
+    }
+
+    /// Reset all the pools, freeing all the descriptor sets at once.
+    ///
+    /// The pools are kept for the next allocations.
+    pub unsafe fn reset(&mut self) {
+        for shard in self.shards.iter_mut() {
+            for pool in shard.get_mut().pools.iter_mut() {
+                pool.reset();
+            }
+        }
+    }
+
+    /// Destroy the allocator with all of its pools.
+    ///
+    /// The descriptor sets allocated from it become invalid.
+    pub unsafe fn dispose(self, device: &B::Device) {
+        for shard in self.shards.into_vec() {
+            for pool in shard.into_inner().pools {
+                device.destroy_descriptor_pool(pool);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gfx_backend_software as software;
+    use hal::{adapter::PhysicalDevice as _, Instance as _};
+    use std::{sync::Arc, thread};
+
+    type B = software::Backend;
+
+    fn create_device() -> <B as Backend>::Device {
+        let instance = software::Instance::create("descriptor", 1).unwrap();
+        let adapter = instance.enumerate_adapters().remove(0);
+        let gpu = unsafe {
+            adapter
+                .physical_device
+                .open(
+                    &[(&adapter.queue_families[0], &[1.0])],
+                    hal::Features::empty(),
+                )
+                .unwrap()
+        };
+        gpu.device
+    }
+
+    #[test]
+    fn allocate_concurrently() {
+        const THREADS: usize = 8;
+        const SHARDS: usize = 4;
+        let device = Arc::new(create_device());
+        let layout = Arc::new(unsafe {
+            device
+                .create_descriptor_set_layout(
+                    iter::once(pso::DescriptorSetLayoutBinding {
+                        binding: 0,
+                        ty: pso::DescriptorType::Sampler,
+                        count: 1,
+                        stage_flags: pso::ShaderStageFlags::ALL,
+                        immutable_samplers: false,
+                    }),
+                    iter::empty(),
+                )
+                .unwrap()
+        });
+        let allocator = Arc::new(DescriptorAllocator::<B>::new(
+            iter::once(pso::DescriptorRangeDesc {
+                ty: pso::DescriptorType::Sampler,
+                count: 64,
+            }),
+            64,
+            SHARDS,
+        ));
+
+        let threads = (0..THREADS)
+            .map(|_| {
+                let device = Arc::clone(&device);
+                let layout = Arc::clone(&layout);
+                let allocator = Arc::clone(&allocator);
+                thread::spawn(move || {
+                    let mut sets = Vec::new();
+                    for i in 0..1000 {
+                        let set = unsafe { allocator.allocate(&*device, &*layout) }.unwrap();
+                        assert!(set.shard < SHARDS);
+                        sets.push(set);
+                        if i % 3 == 0 {
+                            unsafe { allocator.free(sets.swap_remove(i % sets.len())) };
+                        }
+                    }
+                    for set in sets {
+                        unsafe { allocator.free(set) };
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        // The pools of the software backend never run out, so each shard needs one at most.
+        let pool_count = allocator.pool_count();
+        assert!((1..=SHARDS).contains(&pool_count));
+        let allocator = Arc::try_unwrap(allocator).unwrap();
+        unsafe { allocator.dispose(&*device) };
+    }
+}
@@ -0,0 +1,602 @@
+//! Frame graph for gfx-hal.
+//!
+//! The passes of a frame are declared with the resources they read and write,
+//! along with the closures recording their commands. `GraphBuilder::build` then
+//! derives the rest:
+//!   - the passes that don't contribute to any imported resource are culled,
+//!   - the passes are ordered by their dependencies, spacing the dependent ones apart,
+//!   - the pipeline barriers and image layout transitions are inserted between the passes,
+//!   - the transient resources are created when recorded, and the ones of the same
+//!     description that aren't alive at the same time share an image or a buffer.
+//!
+//! ```ignore
+//! let mut builder = GraphBuilder::new();
+//! let back_buffer = builder.import_image(&frame, range, None, Some(ImageUsage::Present));
+//! let shadow = builder.create_image(ImageDesc { kind, mip_levels: 1, format: Format::D32Sfloat });
+//! builder
+//!     .add_pass("shadow")
+//!     .image(shadow, ImageUsage::DepthStencilAttachment)
+//!     .record(|cmd_buffer, resources| { /* draw into resources.image(shadow) */ });
+//! builder
+//!     .add_pass("main")
+//!     .image(shadow, ImageUsage::Sampled(PipelineStage::FRAGMENT_SHADER))
+//!     .image(back_buffer, ImageUsage::ColorAttachment)
+//!     .record(|cmd_buffer, resources| { /* ... */ });
+//! let transients = builder.build().record(&device, &mut allocator, &mut cmd_buffer)?;
+//! // once the command buffer is done executing
+//! transients.dispose(&device, &mut allocator);
+//! ```
+//!
+//! The images are in the layouts of `ImageUsage::state` during the passes, which
+//! the render passes created by the closures must use for their attachments.
+//!
+//! For the command buffers recorded without a graph, `ManagedImage` and
+//! `ManagedCommandBuffer` track the image layouts and insert the barriers instead.
+#![warn(
+    trivial_casts,
+    trivial_numeric_casts,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications
+)]
+
+mod managed;
+mod schedule;
+mod usage;
+
+pub use crate::{
+    managed::{ManagedCommandBuffer, ManagedImage},
+    usage::{BufferUsage, ImageUsage},
+};
+
+use crate::{
+    schedule::{PassNode, ResourceNode, Schedule, Transition},
+    usage::Use,
+};
+
+use hal::{
+    buffer,
+    command::CommandBuffer as _,
+    device::{BindError, Device as _},
+    format, image,
+    memory::{Barrier, Dependencies},
+    pso::PipelineStage,
+    Backend,
+};
+use memory_alloc::{Allocation, Allocator};
+
+/// Error recording a graph.
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+pub enum Error {
+    /// A transient image failed to be created.
+    #[error(transparent)]
+    ImageCreation(#[from] image::CreationError),
+    /// A transient buffer failed to be created.
+    #[error(transparent)]
+    BufferCreation(#[from] buffer::CreationError),
+    /// The memory of a transient resource failed to be allocated.
+    #[error(transparent)]
+    Allocation(#[from] memory_alloc::Error),
+    /// A transient resource failed to be bound to its memory.
+    #[error(transparent)]
+    Bind(#[from] BindError),
+}
+
+/// Handle of an image in a graph.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub struct ImageId(usize);
+
+/// Handle of a buffer in a graph.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub struct BufferId(usize);
+
+/// Description of a transient image.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImageDesc {
+    /// Kind of the image, with its size.
+    pub kind: image::Kind,
+    /// Number of the mipmap levels.
+    pub mip_levels: image::Level,
+    /// Format of the image.
+    pub format: format::Format,
+}
+
+impl ImageDesc {
+    fn range(&self) -> image::SubresourceRange {
+        image::SubresourceRange {
+            aspects: self.format.surface_desc().aspects,
+            ..image::SubresourceRange::default()
+        }
+    }
+}
+
+/// Description of a transient resource, the ones with equal descriptions can be shared.
+#[derive(Clone, Debug, PartialEq)]
+enum Key {
+    Image(ImageDesc),
+    Buffer(u64),
+}
+
+#[derive(Debug)]
+enum Source<'a, B: Backend> {
+    Transient,
+    Image(&'a B::Image, image::SubresourceRange),
+    Buffer(&'a B::Buffer),
+}
+
+type RecordFn<'a, B> = Box<dyn FnOnce(&mut <B as Backend>::CommandBuffer, &Resources<B>) + 'a>;
+
+struct Pass<'a, B: Backend> {
+    name: String,
+    record: Option<RecordFn<'a, B>>,
+}
+
+/// Builder of a frame graph.
+pub struct GraphBuilder<'a, B: Backend> {
+    resources: Vec<ResourceNode<Key>>,
+    sources: Vec<Source<'a, B>>,
+    nodes: Vec<PassNode>,
+    passes: Vec<Pass<'a, B>>,
+}
+
+impl<B: Backend> Default for GraphBuilder<'_, B> {
+    fn default() -> Self {
+        GraphBuilder {
+            resources: Vec::new(),
+            sources: Vec::new(),
+            nodes: Vec::new(),
+            passes: Vec::new(),
+        }
+    }
+}
+
+impl<'a, B: Backend> GraphBuilder<'a, B> {
+    /// Create an empty graph builder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn add_resource(&mut self, node: ResourceNode<Key>, source: Source<'a, B>) -> usize {
+        self.resources.push(node);
+        self.sources.push(source);
+        self.resources.len() - 1
+    }
+
+    /// Declare a transient image, created when the graph is recorded.
+    ///
+    /// Its contents are undefined before the first pass writing it.
+    pub fn create_image(&mut self, desc: ImageDesc) -> ImageId {
+        let node = ResourceNode {
+            key: Some(Key::Image(desc)),
+            initial: None,
+            final_use: None,
+        };
+        ImageId(self.add_resource(node, Source::Transient))
+    }
+
+    /// Declare a transient buffer of `size` bytes, created when the graph is recorded.
+    pub fn create_buffer(&mut self, size: u64) -> BufferId {
+        let node = ResourceNode {
+            key: Some(Key::Buffer(size)),
+            initial: None,
+            final_use: None,
+        };
+        BufferId(self.add_resource(node, Source::Transient))
+    }
+
+    /// Import an external image.
+    ///
+    /// `initial` is the use of the image before the graph, or `None` if its
+    /// contents can be discarded. The image is left in the `final_usage` state
+    /// after the graph, if any, or in the state of its last use.
+    pub fn import_image(
+        &mut self,
+        image: &'a B::Image,
+        range: image::SubresourceRange,
+        initial: Option<ImageUsage>,
+        final_usage: Option<ImageUsage>,
+    ) -> ImageId {
+        let node = ResourceNode {
+            key: None,
+            initial: initial.map(Use::Image),
+            final_use: final_usage.map(Use::Image),
+        };
+        ImageId(self.add_resource(node, Source::Image(image, range)))
+    }
+
+    /// Import an external buffer.
+    ///
+    /// See `import_image` for the meaning of `initial` and `final_usage`.
+    pub fn import_buffer(
+        &mut self,
+        buffer: &'a B::Buffer,
+        initial: Option<BufferUsage>,
+        final_usage: Option<BufferUsage>,
+    ) -> BufferId {
+        let node = ResourceNode {
+            key: None,
+            initial: initial.map(Use::Buffer),
+            final_use: final_usage.map(Use::Buffer),
+        };
+        BufferId(self.add_resource(node, Source::Buffer(buffer)))
+    }
+
+    /// Start declaring a pass.
+    ///
+    /// The passes are run in the order of the declaration, unless their dependencies
+    /// allow a better one. Writes to the imported resources are considered to be
+    /// the results of the graph, and the passes contributing to none of them are culled.
+    pub fn add_pass<'g>(&'g mut self, name: &str) -> PassBuilder<'g, 'a, B> {
+        PassBuilder {
+            builder: self,
+            name: name.to_string(),
+            node: PassNode {
+                uses: Vec::new(),
+                side_effects: false,
+            },
+        }
+    }
+
+    /// Order the passes and derive the transitions of the resources.
+    pub fn build(self) -> Graph<'a, B> {
+        let schedule = schedule::schedule(&self.nodes, &self.resources);
+        for (index, pass) in self.passes.iter().enumerate() {
+            if !schedule.order.contains(&index) {
+                log::debug!("Culled pass {:?}", pass.name);
+            }
+        }
+        Graph {
+            schedule,
+            resources: self.resources,
+            sources: self.sources,
+            nodes: self.nodes,
+            passes: self.passes,
+        }
+    }
+}
+
+/// Builder of a pass, declaring the resources it uses.
+pub struct PassBuilder<'g, 'a, B: Backend> {
+    builder: &'g mut GraphBuilder<'a, B>,
+    name: String,
+    node: PassNode,
+}
+
+impl<'g, 'a, B: Backend> PassBuilder<'g, 'a, B> {
+    fn add_use(mut self, resource: usize, usage: Use) -> Self {
+        assert!(
+            self.node.uses.iter().all(|&(r, _)| r != resource),
+            "Pass {:?} uses a resource twice",
+            self.name
+        );
+        self.node.uses.push((resource, usage));
+        self
+    }
+
+    /// Use an image in the pass.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the image is already used by the pass, or if `usage` is `ImageUsage::Present`.
+    pub fn image(self, id: ImageId, usage: ImageUsage) -> Self {
+        assert_ne!(
+            usage,
+            ImageUsage::Present,
+            "Images are presented after the graph"
+        );
+        self.add_use(id.0, Use::Image(usage))
+    }
+
+    /// Use a buffer in the pass.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer is already used by the pass.
+    pub fn buffer(self, id: BufferId, usage: BufferUsage) -> Self {
+        self.add_use(id.0, Use::Buffer(usage))
+    }
+
+    /// Never cull the pass, e.g. because it writes to a resource outside of the graph.
+    pub fn side_effects(mut self) -> Self {
+        self.node.side_effects = true;
+        self
+    }
+
+    /// Finish the pass with the closure recording its commands.
+    pub fn record<F>(self, record: F)
+    where
+        F: FnOnce(&mut B::CommandBuffer, &Resources<B>) + 'a,
+    {
+        self.builder.nodes.push(self.node);
+        self.builder.passes.push(Pass {
+            name: self.name,
+            record: Some(Box::new(record)),
+        });
+    }
+}
+
+#[derive(Debug)]
+enum Physical<'r, B: Backend> {
+    Image(&'r B::Image, image::SubresourceRange),
+    Buffer(&'r B::Buffer),
+}
+
+/// The resources of a graph, as seen by the passes.
+#[derive(Debug)]
+pub struct Resources<'r, B: Backend> {
+    physical: Vec<Physical<'r, B>>,
+    map: &'r [Option<usize>],
+}
+
+impl<'r, B: Backend> Resources<'r, B> {
+    fn get(&self, resource: usize) -> &Physical<'r, B> {
+        let p = self.map[resource].expect("Resource is not used by any pass");
+        &self.physical[p]
+    }
+
+    /// Get the image of an image handle.
+    pub fn image(&self, id: ImageId) -> &B::Image {
+        match *self.get(id.0) {
+            Physical::Image(image, _) => image,
+            Physical::Buffer(_) => unreachable!(),
+        }
+    }
+
+    /// Get the buffer of a buffer handle.
+    pub fn buffer(&self, id: BufferId) -> &B::Buffer {
+        match *self.get(id.0) {
+            Physical::Buffer(buffer) => buffer,
+            Physical::Image(..) => unreachable!(),
+        }
+    }
+}
+
+/// The transient resources created by recording a graph.
+///
+/// They must be kept alive until the command buffer is done executing.
+#[derive(Debug)]
+pub struct Transients<B: Backend> {
+    images: Vec<(B::Image, Allocation)>,
+    buffers: Vec<(B::Buffer, Allocation)>,
+}
+
+impl<B: Backend> Transients<B> {
+    /// Destroy the transient resources and free their memory.
+    pub unsafe fn dispose(self, device: &B::Device, allocator: &mut Allocator<B>) {
+        for (image, allocation) in self.images {
+            device.destroy_image(image);
+            allocator.free(device, allocation);
+        }
+        for (buffer, allocation) in self.buffers {
+            device.destroy_buffer(buffer);
+            allocator.free(device, allocation);
+        }
+    }
+}
+
+/// A frame graph, ready to be recorded.
+pub struct Graph<'a, B: Backend> {
+    schedule: Schedule,
+    resources: Vec<ResourceNode<Key>>,
+    sources: Vec<Source<'a, B>>,
+    nodes: Vec<PassNode>,
+    passes: Vec<Pass<'a, B>>,
+}
+
+impl<'a, B: Backend> Graph<'a, B> {
+    /// Names of the passes to run, in the order of execution.
+    pub fn pass_names(&self) -> impl Iterator<Item = &str> {
+        self.schedule
+            .order
+            .iter()
+            .map(move |&index| self.passes[index].name.as_str())
+    }
+
+    /// Usage flags of each physical resource, gathered from all the uses
+    /// of the resources sharing it.
+    fn usage_flags(&self) -> Vec<(image::Usage, buffer::Usage)> {
+        let mut flags =
+            vec![(image::Usage::empty(), buffer::Usage::empty()); self.schedule.aliases.len()];
+        for &pass in self.schedule.order.iter() {
+            for &(resource, usage) in self.nodes[pass].uses.iter() {
+                let p = self.schedule.physical[resource].unwrap();
+                match usage {
+                    Use::Image(usage) => flags[p].0 |= usage.usage(),
+                    Use::Buffer(usage) => flags[p].1 |= usage.usage(),
+                }
+            }
+        }
+        flags
+    }
+
+    unsafe fn create_transients(
+        &self,
+        device: &B::Device,
+        allocator: &mut Allocator<B>,
+        transients: &mut Transients<B>,
+    ) -> Result<Vec<Option<usize>>, Error> {
+        let mut slots = vec![None; self.schedule.aliases.len()];
+        for (p, (image_usage, buffer_usage)) in self.usage_flags().into_iter().enumerate() {
+            let key = match self.resources[self.schedule.aliases[p][0]].key {
+                Some(ref key) => key,
+                None => continue,
+            };
+            match *key {
+                Key::Image(ref desc) => {
+                    let mut image = device.create_image(
+                        desc.kind,
+                        desc.mip_levels,
+                        desc.format,
+                        image::Tiling::Optimal,
+                        image_usage,
+                        image::ViewCapabilities::empty(),
+                    )?;
+                    let requirements = device.get_image_requirements(&image);
+                    let allocation = match allocator.alloc(
+                        device,
+                        memory_alloc::Usage::DeviceLocal,
+                        memory_alloc::Resource::Image(&image),
+                        &requirements,
+                    ) {
+                        Ok(allocation) => allocation,
+                        Err(e) => {
+                            device.destroy_image(image);
+                            return Err(e.into());
+                        }
+                    };
+                    let result = device.bind_image_memory(
+                        allocator.memory(&allocation),
+                        allocation.offset(),
+                        &mut image,
+                    );
+                    slots[p] = Some(transients.images.len());
+                    transients.images.push((image, allocation));
+                    result?;
+                }
+                Key::Buffer(size) => {
+                    let mut buffer = device.create_buffer(size, buffer_usage)?;
+                    let requirements = device.get_buffer_requirements(&buffer);
+                    let allocation = match allocator.alloc(
+                        device,
+                        memory_alloc::Usage::DeviceLocal,
+                        memory_alloc::Resource::Buffer(&buffer),
+                        &requirements,
+                    ) {
+                        Ok(allocation) => allocation,
+                        Err(e) => {
+                            device.destroy_buffer(buffer);
+                            return Err(e.into());
+                        }
+                    };
+                    let result = device.bind_buffer_memory(
+                        allocator.memory(&allocation),
+                        allocation.offset(),
+                        &mut buffer,
+                    );
+                    slots[p] = Some(transients.buffers.len());
+                    transients.buffers.push((buffer, allocation));
+                    result?;
+                }
+            }
+        }
+        Ok(slots)
+    }
+
+    /// Record the passes into a command buffer, with the barriers between them.
+    ///
+    /// The transient resources are created, and returned to be disposed of
+    /// after the command buffer is done executing.
+    pub unsafe fn record(
+        self,
+        device: &B::Device,
+        allocator: &mut Allocator<B>,
+        command_buffer: &mut B::CommandBuffer,
+    ) -> Result<Transients<B>, Error> {
+        let mut transients = Transients {
+            images: Vec::new(),
+            buffers: Vec::new(),
+        };
+        let slots = match self.create_transients(device, allocator, &mut transients) {
+            Ok(slots) => slots,
+            Err(e) => {
+                transients.dispose(device, allocator);
+                return Err(e);
+            }
+        };
+
+        let Graph {
+            schedule,
+            resources,
+            sources,
+            nodes: _,
+            mut passes,
+        } = self;
+        let physical = schedule
+            .aliases
+            .iter()
+            .zip(slots)
+            .map(|(aliases, slot)| match sources[aliases[0]] {
+                Source::Image(image, ref range) => Physical::Image(image, range.clone()),
+                Source::Buffer(buffer) => Physical::Buffer(buffer),
+                Source::Transient => match resources[aliases[0]].key {
+                    Some(Key::Image(ref desc)) => {
+                        Physical::Image(&transients.images[slot.unwrap()].0, desc.range())
+                    }
+                    Some(Key::Buffer(_)) => Physical::Buffer(&transients.buffers[slot.unwrap()].0),
+                    None => unreachable!(),
+                },
+            })
+            .collect();
+        let view = Resources {
+            physical,
+            map: &schedule.physical,
+        };
+
+        for (&index, transitions) in schedule.order.iter().zip(schedule.transitions.iter()) {
+            record_barriers(command_buffer, transitions, &view.physical);
+            let record = passes[index].record.take().unwrap();
+            record(command_buffer, &view);
+        }
+        record_barriers(command_buffer, &schedule.final_transitions, &view.physical);
+
+        drop(view);
+        Ok(transients)
+    }
+}
+
+unsafe fn record_barriers<B: Backend>(
+    command_buffer: &mut B::CommandBuffer,
+    transitions: &[Transition],
+    physical: &[Physical<B>],
+) {
+    if transitions.is_empty() {
+        return;
+    }
+    let src_stages = transitions
+        .iter()
+        .filter_map(|t| t.before)
+        .fold(PipelineStage::empty(), |stages, usage| {
+            stages | usage.stages()
+        });
+    let dst_stages = transitions
+        .iter()
+        .fold(PipelineStage::empty(), |stages, t| {
+            stages | t.after.stages()
+        });
+    let src_stages = if src_stages.is_empty() {
+        PipelineStage::TOP_OF_PIPE
+    } else {
+        src_stages
+    };
+
+    let barriers = transitions
+        .iter()
+        .map(|t| match (&physical[t.resource], t.after) {
+            (&Physical::Image(target, ref range), Use::Image(after)) => {
+                let before = match t.before {
+                    Some(Use::Image(before)) => before.state(),
+                    _ => (image::Access::empty(), image::Layout::Undefined),
+                };
+                let before = if t.discard {
+                    (before.0, image::Layout::Undefined)
+                } else {
+                    before
+                };
+                Barrier::Image {
+                    states: before..after.state(),
+                    target,
+                    range: range.clone(),
+                    families: None,
+                }
+            }
+            (&Physical::Buffer(target), Use::Buffer(after)) => {
+                let before = match t.before {
+                    Some(Use::Buffer(before)) => before.state(),
+                    _ => buffer::Access::empty(),
+                };
+                Barrier::whole_buffer(target, before..after.state())
+            }
+            _ => unreachable!(),
+        });
+    command_buffer.pipeline_barrier(src_stages..dst_stages, Dependencies::empty(), barriers);
+}
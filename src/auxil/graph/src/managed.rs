@@ -0,0 +1,483 @@
+//! Image layout tracking for the command buffers recorded outside of a graph.
+//!
+//! `ManagedImage` keeps the layout and the last access of each of its subresources,
+//! and `ManagedCommandBuffer` transitions them as needed before the copies,
+//! the render passes, and the shader uses declared with `use_images`.
+//! The states are updated as the commands are recorded, so the command buffers
+//! using an image have to be submitted in the order of their recording.
+//!
+//! ```ignore
+//! let texture = ManagedImage::new(raw_image, kind, 1, format, Layout::Undefined);
+//! let mut cmd = ManagedCommandBuffer::new(&mut cmd_buffer);
+//! cmd.copy_buffer_to_image(&staging, &texture, iter::once(region));
+//! cmd.use_images(iter::once((&texture, range, ImageUsage::Sampled(PipelineStage::FRAGMENT_SHADER))));
+//! cmd.begin_render_pass(&render_pass, &framebuffer, area, iter::once((&target, range, ImageUsage::ColorAttachment)), attachments, contents);
+//! ```
+
+use crate::usage::ImageUsage;
+
+use hal::{
+    command::{self, CommandBuffer as _},
+    format, image,
+    memory::{Barrier, Dependencies},
+    pso::{self, PipelineStage},
+    Backend,
+};
+
+use std::{ops::Range, sync::Mutex};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct SubresourceState {
+    access: image::Access,
+    layout: image::Layout,
+    stages: PipelineStage,
+    write: bool,
+}
+
+impl SubresourceState {
+    fn new(usage: ImageUsage) -> Self {
+        let (access, layout) = usage.state();
+        SubresourceState {
+            access,
+            layout,
+            stages: usage.stages(),
+            write: usage.is_write(),
+        }
+    }
+
+    fn initial(layout: image::Layout) -> Self {
+        SubresourceState {
+            access: image::Access::empty(),
+            layout,
+            stages: PipelineStage::TOP_OF_PIPE,
+            write: false,
+        }
+    }
+}
+
+/// Moves the subresources of `range` to the `new` state, returning the runs of
+/// layers of each level that need a barrier, along with their previous state.
+///
+/// Consecutive reads in the same layout don't need barriers, their accesses are merged instead.
+fn transition(
+    states: &mut [SubresourceState],
+    layers: image::Layer,
+    levels: Range<image::Level>,
+    range_layers: Range<image::Layer>,
+    new: SubresourceState,
+) -> Vec<(image::Level, Range<image::Layer>, SubresourceState)> {
+    let mut runs: Vec<(image::Level, Range<image::Layer>, SubresourceState)> = Vec::new();
+    for level in levels {
+        for layer in range_layers.clone() {
+            let state = &mut states[level as usize * layers as usize + layer as usize];
+            let old = *state;
+            if old.layout == new.layout && !old.write && !new.write {
+                state.access |= new.access;
+                state.stages |= new.stages;
+                continue;
+            }
+            *state = new;
+            match runs.last_mut() {
+                Some(&mut (run_level, ref mut run, run_state))
+                    if run_level == level && run.end == layer && run_state == old =>
+                {
+                    run.end += 1
+                }
+                _ => runs.push((level, layer..layer + 1, old)),
+            }
+        }
+    }
+    runs
+}
+
+/// An image tracking the state of each of its subresources.
+///
+/// The aspects of a subresource share the same state.
+#[derive(Debug)]
+pub struct ManagedImage<B: Backend> {
+    raw: B::Image,
+    levels: image::Level,
+    layers: image::Layer,
+    aspects: format::Aspects,
+    states: Mutex<Vec<SubresourceState>>,
+}
+
+impl<B: Backend> ManagedImage<B> {
+    /// Start tracking an image, with all the subresources in the given layout.
+    pub fn new(
+        raw: B::Image,
+        kind: image::Kind,
+        levels: image::Level,
+        format: format::Format,
+        layout: image::Layout,
+    ) -> Self {
+        let layers = kind.num_layers();
+        ManagedImage {
+            raw,
+            levels,
+            layers,
+            aspects: format.surface_desc().aspects,
+            states: Mutex::new(vec![
+                SubresourceState::initial(layout);
+                levels as usize * layers as usize
+            ]),
+        }
+    }
+
+    /// Get the raw image.
+    pub fn raw(&self) -> &B::Image {
+        &self.raw
+    }
+
+    /// Stop tracking the image, returning the raw one.
+    pub fn into_raw(self) -> B::Image {
+        self.raw
+    }
+
+    /// Returns the range covering the whole image.
+    pub fn range(&self) -> image::SubresourceRange {
+        image::SubresourceRange {
+            aspects: self.aspects,
+            ..image::SubresourceRange::default()
+        }
+    }
+
+    /// Returns the current layout of a subresource.
+    pub fn layout(&self, level: image::Level, layer: image::Layer) -> image::Layout {
+        let states = self.states.lock().unwrap();
+        states[level as usize * self.layers as usize + layer as usize].layout
+    }
+
+    /// Tell the layout of all the subresources, after they were transitioned
+    /// outside of the managed command buffers.
+    pub fn assume_layout(&self, layout: image::Layout) {
+        let mut states = self.states.lock().unwrap();
+        for state in states.iter_mut() {
+            *state = SubresourceState::initial(layout);
+        }
+    }
+}
+
+/// A command buffer wrapper transitioning the managed images before their uses.
+#[derive(Debug)]
+pub struct ManagedCommandBuffer<'a, B: Backend> {
+    raw: &'a mut B::CommandBuffer,
+}
+
+impl<'a, B: Backend> ManagedCommandBuffer<'a, B> {
+    /// Wrap a command buffer in the recording state.
+    pub fn new(raw: &'a mut B::CommandBuffer) -> Self {
+        ManagedCommandBuffer { raw }
+    }
+
+    /// Get the raw command buffer, to record the commands that don't involve managed images.
+    pub fn raw(&mut self) -> &mut B::CommandBuffer {
+        self.raw
+    }
+
+    /// Transition the images to be used in the given ways, with a single pipeline barrier.
+    ///
+    /// The images accessed by the shaders, through descriptor sets, have to be
+    /// declared this way before the draw or dispatch commands, outside of render passes.
+    pub unsafe fn use_images<'i, I>(&mut self, uses: I)
+    where
+        I: IntoIterator<Item = (&'i ManagedImage<B>, image::SubresourceRange, ImageUsage)>,
+        B: 'i,
+    {
+        let mut src_stages = PipelineStage::empty();
+        let mut dst_stages = PipelineStage::empty();
+        let mut barriers = Vec::new();
+
+        for (image, range, usage) in uses {
+            let new = SubresourceState::new(usage);
+            let level_count = range.resolve_level_count(image.levels);
+            let layer_count = range.resolve_layer_count(image.layers);
+            let runs = transition(
+                &mut image.states.lock().unwrap(),
+                image.layers,
+                range.level_start..range.level_start + level_count,
+                range.layer_start..range.layer_start + layer_count,
+                new,
+            );
+            for (level, layers, old) in runs {
+                src_stages |= old.stages;
+                dst_stages |= new.stages;
+                barriers.push(Barrier::Image {
+                    states: (old.access, old.layout)..(new.access, new.layout),
+                    target: &image.raw,
+                    range: image::SubresourceRange {
+                        aspects: range.aspects,
+                        level_start: level,
+                        level_count: Some(1),
+                        layer_start: layers.start,
+                        layer_count: Some(layers.end - layers.start),
+                    },
+                    families: None,
+                });
+            }
+        }
+
+        if !barriers.is_empty() {
+            self.raw.pipeline_barrier(
+                src_stages..dst_stages,
+                Dependencies::empty(),
+                barriers.into_iter(),
+            );
+        }
+    }
+
+    /// Copy from a buffer into an image, transitioning it for the transfer.
+    pub unsafe fn copy_buffer_to_image<T>(
+        &mut self,
+        src: &B::Buffer,
+        dst: &ManagedImage<B>,
+        regions: T,
+    ) where
+        T: IntoIterator<Item = command::BufferImageCopy>,
+    {
+        let regions = regions.into_iter().collect::<Vec<_>>();
+        self.use_images(regions.iter().map(|r| {
+            let range = image::SubresourceRange::from(r.image_layers.clone());
+            (dst, range, ImageUsage::TransferDst)
+        }));
+        self.raw.copy_buffer_to_image(
+            src,
+            &dst.raw,
+            image::Layout::TransferDstOptimal,
+            regions.into_iter(),
+        );
+    }
+
+    /// Copy from an image into a buffer, transitioning it for the transfer.
+    pub unsafe fn copy_image_to_buffer<T>(
+        &mut self,
+        src: &ManagedImage<B>,
+        dst: &B::Buffer,
+        regions: T,
+    ) where
+        T: IntoIterator<Item = command::BufferImageCopy>,
+    {
+        let regions = regions.into_iter().collect::<Vec<_>>();
+        self.use_images(regions.iter().map(|r| {
+            let range = image::SubresourceRange::from(r.image_layers.clone());
+            (src, range, ImageUsage::TransferSrc)
+        }));
+        self.raw.copy_image_to_buffer(
+            &src.raw,
+            image::Layout::TransferSrcOptimal,
+            dst,
+            regions.into_iter(),
+        );
+    }
+
+    /// Copy between the images, transitioning them for the transfer.
+    pub unsafe fn copy_image<T>(&mut self, src: &ManagedImage<B>, dst: &ManagedImage<B>, regions: T)
+    where
+        T: IntoIterator<Item = command::ImageCopy>,
+    {
+        let regions = regions.into_iter().collect::<Vec<_>>();
+        self.use_images(regions.iter().flat_map(|r| {
+            vec![
+                (
+                    src,
+                    image::SubresourceRange::from(r.src_subresource.clone()),
+                    ImageUsage::TransferSrc,
+                ),
+                (
+                    dst,
+                    image::SubresourceRange::from(r.dst_subresource.clone()),
+                    ImageUsage::TransferDst,
+                ),
+            ]
+        }));
+        self.raw.copy_image(
+            &src.raw,
+            image::Layout::TransferSrcOptimal,
+            &dst.raw,
+            image::Layout::TransferDstOptimal,
+            regions.into_iter(),
+        );
+    }
+
+    /// Blit between the images, transitioning them for the transfer.
+    ///
+    /// The source and the destination can be the different levels of the same image,
+    /// e.g. to generate the mipmaps.
+    pub unsafe fn blit_image<T>(
+        &mut self,
+        src: &ManagedImage<B>,
+        dst: &ManagedImage<B>,
+        filter: image::Filter,
+        regions: T,
+    ) where
+        T: IntoIterator<Item = command::ImageBlit>,
+    {
+        let regions = regions.into_iter().collect::<Vec<_>>();
+        self.use_images(regions.iter().flat_map(|r| {
+            vec![
+                (
+                    src,
+                    image::SubresourceRange::from(r.src_subresource.clone()),
+                    ImageUsage::TransferSrc,
+                ),
+                (
+                    dst,
+                    image::SubresourceRange::from(r.dst_subresource.clone()),
+                    ImageUsage::TransferDst,
+                ),
+            ]
+        }));
+        self.raw.blit_image(
+            &src.raw,
+            image::Layout::TransferSrcOptimal,
+            &dst.raw,
+            image::Layout::TransferDstOptimal,
+            filter,
+            regions.into_iter(),
+        );
+    }
+
+    /// Resolve a multisampled image, transitioning the images for the transfer.
+    pub unsafe fn resolve_image<T>(
+        &mut self,
+        src: &ManagedImage<B>,
+        dst: &ManagedImage<B>,
+        regions: T,
+    ) where
+        T: IntoIterator<Item = command::ImageResolve>,
+    {
+        let regions = regions.into_iter().collect::<Vec<_>>();
+        self.use_images(regions.iter().flat_map(|r| {
+            vec![
+                (
+                    src,
+                    image::SubresourceRange::from(r.src_subresource.clone()),
+                    ImageUsage::TransferSrc,
+                ),
+                (
+                    dst,
+                    image::SubresourceRange::from(r.dst_subresource.clone()),
+                    ImageUsage::TransferDst,
+                ),
+            ]
+        }));
+        self.raw.resolve_image(
+            &src.raw,
+            image::Layout::TransferSrcOptimal,
+            &dst.raw,
+            image::Layout::TransferDstOptimal,
+            regions.into_iter(),
+        );
+    }
+
+    /// Clear the ranges of an image, transitioning it for the transfer.
+    pub unsafe fn clear_image<T>(
+        &mut self,
+        image: &ManagedImage<B>,
+        value: command::ClearValue,
+        subresource_ranges: T,
+    ) where
+        T: IntoIterator<Item = image::SubresourceRange>,
+    {
+        let ranges = subresource_ranges.into_iter().collect::<Vec<_>>();
+        self.use_images(
+            ranges
+                .iter()
+                .map(|range| (image, range.clone(), ImageUsage::TransferDst)),
+        );
+        self.raw.clear_image(
+            &image.raw,
+            image::Layout::TransferDstOptimal,
+            value,
+            ranges.into_iter(),
+        );
+    }
+
+    /// Begin a render pass, transitioning the attachment images first.
+    ///
+    /// The render pass must keep each attachment in the layout of its usage,
+    /// as both the initial and the final layout.
+    pub unsafe fn begin_render_pass<'i, 'v, I, T>(
+        &mut self,
+        render_pass: &B::RenderPass,
+        framebuffer: &B::Framebuffer,
+        render_area: pso::Rect,
+        images: I,
+        attachments: T,
+        first_subpass: command::SubpassContents,
+    ) where
+        I: IntoIterator<Item = (&'i ManagedImage<B>, image::SubresourceRange, ImageUsage)>,
+        T: IntoIterator<Item = command::RenderAttachmentInfo<'v, B>>,
+        B: 'i,
+    {
+        self.use_images(images);
+        self.raw.begin_render_pass(
+            render_pass,
+            framebuffer,
+            render_area,
+            attachments.into_iter(),
+            first_subpass,
+        );
+    }
+
+    /// End the current render pass.
+    pub unsafe fn end_render_pass(&mut self) {
+        self.raw.end_render_pass();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_after_read() {
+        let mut states = vec![SubresourceState::initial(image::Layout::Undefined); 1];
+        let vertex = SubresourceState::new(ImageUsage::Sampled(PipelineStage::VERTEX_SHADER));
+        let fragment = SubresourceState::new(ImageUsage::Sampled(PipelineStage::FRAGMENT_SHADER));
+        assert_eq!(transition(&mut states, 1, 0..1, 0..1, vertex).len(), 1);
+        assert!(transition(&mut states, 1, 0..1, 0..1, fragment).is_empty());
+        // the next write waits for both of the reads
+        let runs = transition(
+            &mut states,
+            1,
+            0..1,
+            0..1,
+            SubresourceState::new(ImageUsage::TransferDst),
+        );
+        assert_eq!(
+            runs[0].2.stages,
+            PipelineStage::VERTEX_SHADER | PipelineStage::FRAGMENT_SHADER
+        );
+    }
+
+    #[test]
+    fn test_write_after_write() {
+        let mut states = vec![SubresourceState::initial(image::Layout::Undefined); 1];
+        let dst = SubresourceState::new(ImageUsage::TransferDst);
+        assert_eq!(transition(&mut states, 1, 0..1, 0..1, dst).len(), 1);
+        assert_eq!(
+            transition(&mut states, 1, 0..1, 0..1, dst),
+            vec![(0, 0..1, dst)]
+        );
+    }
+
+    #[test]
+    fn test_layer_runs() {
+        // 2 levels of 4 layers
+        let mut states = vec![SubresourceState::initial(image::Layout::Undefined); 8];
+        let dst = SubresourceState::new(ImageUsage::TransferDst);
+        let src = SubresourceState::new(ImageUsage::TransferSrc);
+        transition(&mut states, 4, 0..1, 1..2, dst);
+        let undefined = SubresourceState::initial(image::Layout::Undefined);
+        assert_eq!(
+            transition(&mut states, 4, 0..2, 0..4, src),
+            vec![
+                (0, 0..1, undefined),
+                (0, 1..2, dst),
+                (0, 2..4, undefined),
+                (1, 0..4, undefined),
+            ]
+        );
+    }
+}
@@ -0,0 +1,467 @@
+//! Ordering of the passes, and the transitions and sharing of the resources between them.
+
+use crate::usage::Use;
+
+use std::ops::Range;
+
+/// A pass, with the resources it uses.
+#[derive(Debug)]
+pub(crate) struct PassNode {
+    /// Used resources, at most one use per resource.
+    pub uses: Vec<(usize, Use)>,
+    /// Run the pass even if none of its results are used.
+    pub side_effects: bool,
+}
+
+/// A resource, as declared by the user.
+#[derive(Debug)]
+pub(crate) struct ResourceNode<K> {
+    /// Description of a transient resource, to share it with the compatible ones.
+    /// `None` for the imported resources.
+    pub key: Option<K>,
+    /// Use of an imported resource before the graph, or `None` if its contents can be discarded.
+    pub initial: Option<Use>,
+    /// Use of an imported resource expected after the graph.
+    pub final_use: Option<Use>,
+}
+
+impl<K> ResourceNode<K> {
+    fn is_imported(&self) -> bool {
+        self.key.is_none()
+    }
+}
+
+/// A state change of a physical resource.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Transition {
+    /// Physical resource index.
+    pub resource: usize,
+    /// The last use of the resource, to synchronize with.
+    pub before: Option<Use>,
+    /// The next use of the resource.
+    pub after: Use,
+    /// The contents of the resource aren't preserved, e.g. the image can be
+    /// transitioned from `Layout::Undefined`.
+    pub discard: bool,
+}
+
+/// The passes in the order of execution, with the resource transitions before each of them.
+#[derive(Debug)]
+pub(crate) struct Schedule {
+    /// Indices of the passes to run, culled ones excluded.
+    pub order: Vec<usize>,
+    /// Physical resource of each declared resource, `None` for the unused transient ones.
+    pub physical: Vec<Option<usize>>,
+    /// Declared resources sharing each of the physical resources.
+    pub aliases: Vec<Vec<usize>>,
+    /// Transitions to do before each pass in `order`.
+    pub transitions: Vec<Vec<Transition>>,
+    /// Transitions of the imported resources to their final uses.
+    pub final_transitions: Vec<Transition>,
+}
+
+/// Returns the passes contributing to the imported resources, or having side effects.
+fn cull<K>(passes: &[PassNode], resources: &[ResourceNode<K>]) -> Vec<bool> {
+    let mut needed = vec![false; resources.len()];
+    let mut kept = vec![false; passes.len()];
+    for (index, pass) in passes.iter().enumerate().rev() {
+        kept[index] = pass.side_effects
+            || pass.uses.iter().any(|&(resource, usage)| {
+                usage.is_write() && (needed[resource] || resources[resource].is_imported())
+            });
+        if kept[index] {
+            for &(resource, _) in pass.uses.iter() {
+                needed[resource] = true;
+            }
+        }
+    }
+    kept
+}
+
+/// Returns the passes each kept pass depends on.
+fn dependencies(passes: &[PassNode], kept: &[bool], resource_count: usize) -> Vec<Vec<usize>> {
+    let mut last_writer = vec![None; resource_count];
+    let mut readers = vec![Vec::new(); resource_count];
+    let mut deps = vec![Vec::new(); passes.len()];
+    for (index, pass) in passes.iter().enumerate().filter(|&(i, _)| kept[i]) {
+        for &(resource, usage) in pass.uses.iter() {
+            deps[index].extend(last_writer[resource]);
+            if usage.is_write() {
+                deps[index].extend(readers[resource].drain(..));
+                last_writer[resource] = Some(index);
+            } else {
+                readers[resource].push(index);
+            }
+        }
+        deps[index].sort();
+        deps[index].dedup();
+    }
+    deps
+}
+
+/// Orders the kept passes, following the dependencies.
+///
+/// Out of the passes ready to run, the first declared one that doesn't depend on
+/// the previous pass is picked, leaving more room between the writes and the reads.
+fn order(kept: &[bool], deps: &[Vec<usize>]) -> Vec<usize> {
+    let mut scheduled = vec![false; kept.len()];
+    let mut order = Vec::new();
+    loop {
+        let ready = (0..kept.len())
+            .filter(|&i| kept[i] && !scheduled[i] && deps[i].iter().all(|&d| scheduled[d]));
+        let next = match order.last() {
+            Some(last) => ready
+                .clone()
+                .find(|&i| !deps[i].contains(last))
+                .or_else(|| ready.clone().next()),
+            None => ready.clone().next(),
+        };
+        match next {
+            Some(index) => {
+                scheduled[index] = true;
+                order.push(index);
+            }
+            None => break,
+        }
+    }
+    order
+}
+
+/// Assigns the physical resources, sharing them between the transient resources
+/// of the same description and disjoint lifetimes.
+fn assign<K: PartialEq>(
+    resources: &[ResourceNode<K>],
+    lifetimes: &[Option<Range<usize>>],
+) -> (Vec<Option<usize>>, Vec<Vec<usize>>) {
+    let mut physical = vec![None; resources.len()];
+    let mut aliases: Vec<Vec<usize>> = Vec::new();
+    // position after the last use of each physical resource
+    let mut free_at = Vec::new();
+
+    for (index, _) in resources
+        .iter()
+        .enumerate()
+        .filter(|&(_, r)| r.is_imported())
+    {
+        physical[index] = Some(aliases.len());
+        aliases.push(vec![index]);
+        free_at.push(usize::MAX);
+    }
+
+    let mut transient = (0..resources.len())
+        .filter(|&i| !resources[i].is_imported())
+        .filter_map(|i| lifetimes[i].clone().map(|lifetime| (i, lifetime)))
+        .collect::<Vec<_>>();
+    transient.sort_by_key(|&(_, ref lifetime)| lifetime.start);
+
+    for (index, lifetime) in transient {
+        let shared = (0..aliases.len()).find(|&p| {
+            free_at[p] <= lifetime.start && resources[aliases[p][0]].key == resources[index].key
+        });
+        let p = match shared {
+            Some(p) => p,
+            None => {
+                aliases.push(Vec::new());
+                free_at.push(0);
+                aliases.len() - 1
+            }
+        };
+        physical[index] = Some(p);
+        aliases[p].push(index);
+        free_at[p] = lifetime.end;
+    }
+
+    (physical, aliases)
+}
+
+/// Builds the schedule of the passes.
+pub(crate) fn schedule<K: PartialEq>(
+    passes: &[PassNode],
+    resources: &[ResourceNode<K>],
+) -> Schedule {
+    let kept = cull(passes, resources);
+    let deps = dependencies(passes, &kept, resources.len());
+    let order = order(&kept, &deps);
+
+    let mut lifetimes: Vec<Option<Range<usize>>> = vec![None; resources.len()];
+    for (position, &pass) in order.iter().enumerate() {
+        for &(resource, _) in passes[pass].uses.iter() {
+            let lifetime = lifetimes[resource].get_or_insert(position..position);
+            lifetime.end = position + 1;
+        }
+    }
+    let (physical, aliases) = assign(resources, &lifetimes);
+
+    // current use and occupant of each physical resource
+    let mut states = vec![None; aliases.len()];
+    let mut occupants = vec![None; aliases.len()];
+    let transitions = order
+        .iter()
+        .map(|&pass| {
+            let mut transitions = Vec::new();
+            for &(resource, after) in passes[pass].uses.iter() {
+                let p = physical[resource].unwrap();
+                let (before, discard) = if occupants[p] == Some(resource) {
+                    (states[p], false)
+                } else if resources[resource].is_imported() {
+                    let initial = resources[resource].initial;
+                    (initial, initial.is_none())
+                } else {
+                    (states[p], true)
+                };
+                let needed = match before {
+                    None => match after {
+                        Use::Image(_) => true,
+                        Use::Buffer(_) => false,
+                    },
+                    Some(before) => discard || before != after || after.is_write(),
+                };
+                if needed {
+                    transitions.push(Transition {
+                        resource: p,
+                        before,
+                        after,
+                        discard,
+                    });
+                }
+                states[p] = Some(after);
+                occupants[p] = Some(resource);
+            }
+            transitions
+        })
+        .collect();
+
+    let final_transitions = resources
+        .iter()
+        .enumerate()
+        .filter_map(|(index, resource)| {
+            let after = resource.final_use?;
+            let p = physical[index].unwrap();
+            let (before, discard) = match occupants[p] {
+                Some(_) => (states[p], false),
+                None => (resource.initial, resource.initial.is_none()),
+            };
+            if before == Some(after) {
+                None
+            } else {
+                Some(Transition {
+                    resource: p,
+                    before,
+                    after,
+                    discard,
+                })
+            }
+        })
+        .collect();
+
+    Schedule {
+        order,
+        physical,
+        aliases,
+        transitions,
+        final_transitions,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::usage::{BufferUsage, ImageUsage};
+    use hal::pso::PipelineStage;
+
+    const COLOR: Use = Use::Image(ImageUsage::ColorAttachment);
+    const SAMPLED: Use = Use::Image(ImageUsage::Sampled(PipelineStage::FRAGMENT_SHADER));
+    const PRESENT: Use = Use::Image(ImageUsage::Present);
+
+    fn pass(uses: &[(usize, Use)]) -> PassNode {
+        PassNode {
+            uses: uses.to_vec(),
+            side_effects: false,
+        }
+    }
+
+    fn transient(key: u32) -> ResourceNode<u32> {
+        ResourceNode {
+            key: Some(key),
+            initial: None,
+            final_use: None,
+        }
+    }
+
+    fn imported(initial: Option<Use>, final_use: Option<Use>) -> ResourceNode<u32> {
+        ResourceNode {
+            key: None,
+            initial,
+            final_use,
+        }
+    }
+
+    #[test]
+    fn test_culling() {
+        let resources = [imported(None, None), transient(0), transient(0)];
+        let passes = [
+            // nobody reads the result
+            pass(&[(1, COLOR)]),
+            pass(&[(2, COLOR)]),
+            pass(&[(2, SAMPLED), (0, COLOR)]),
+        ];
+        let schedule = schedule(&passes, &resources);
+        assert_eq!(schedule.order, vec![1, 2]);
+        assert_eq!(schedule.physical[1], None);
+    }
+
+    #[test]
+    fn test_side_effects() {
+        let resources = [transient(0)];
+        let mut passes = [pass(&[(0, COLOR)])];
+        assert!(schedule(&passes, &resources).order.is_empty());
+        passes[0].side_effects = true;
+        assert_eq!(schedule(&passes, &resources).order, vec![0]);
+    }
+
+    #[test]
+    fn test_interleaving() {
+        let resources = [
+            imported(None, None),
+            imported(None, None),
+            transient(0),
+            transient(0),
+        ];
+        let passes = [
+            pass(&[(2, COLOR)]),
+            pass(&[(2, SAMPLED), (0, COLOR)]),
+            pass(&[(3, COLOR)]),
+            pass(&[(3, SAMPLED), (1, COLOR)]),
+        ];
+        let schedule = schedule(&passes, &resources);
+        assert_eq!(schedule.order, vec![0, 2, 1, 3]);
+        // the transient images are alive at the same time
+        assert_ne!(schedule.physical[2], schedule.physical[3]);
+    }
+
+    #[test]
+    fn test_write_after_read() {
+        let resources = [imported(Some(SAMPLED), None), imported(None, None)];
+        let passes = [
+            pass(&[(0, SAMPLED), (1, COLOR)]),
+            // overwrites the image read by the first pass
+            pass(&[(0, COLOR)]),
+        ];
+        let schedule = schedule(&passes, &resources);
+        assert_eq!(schedule.order, vec![0, 1]);
+        assert_eq!(
+            schedule.transitions[1],
+            vec![Transition {
+                resource: 0,
+                before: Some(SAMPLED),
+                after: COLOR,
+                discard: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_transitions() {
+        let resources = [imported(None, Some(PRESENT)), transient(0)];
+        let passes = [
+            pass(&[(1, COLOR)]),
+            pass(&[(1, SAMPLED), (0, COLOR)]),
+            pass(&[(1, SAMPLED), (0, COLOR)]),
+        ];
+        let schedule = schedule(&passes, &resources);
+        let back = schedule.physical[0].unwrap();
+        let temp = schedule.physical[1].unwrap();
+        assert_eq!(
+            schedule.transitions,
+            vec![
+                vec![Transition {
+                    resource: temp,
+                    before: None,
+                    after: COLOR,
+                    discard: true,
+                }],
+                vec![
+                    Transition {
+                        resource: temp,
+                        before: Some(COLOR),
+                        after: SAMPLED,
+                        discard: false,
+                    },
+                    Transition {
+                        resource: back,
+                        before: None,
+                        after: COLOR,
+                        discard: true,
+                    },
+                ],
+                // the second read needs no barrier, unlike the second write
+                vec![Transition {
+                    resource: back,
+                    before: Some(COLOR),
+                    after: COLOR,
+                    discard: false,
+                }],
+            ]
+        );
+        assert_eq!(
+            schedule.final_transitions,
+            vec![Transition {
+                resource: back,
+                before: Some(COLOR),
+                after: PRESENT,
+                discard: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_transient_sharing() {
+        let resources = [
+            imported(None, None),
+            transient(0),
+            transient(0),
+            transient(1),
+        ];
+        let passes = [
+            pass(&[(1, COLOR)]),
+            pass(&[(1, SAMPLED), (0, COLOR)]),
+            // reading the results of the previous pass keeps it from being moved up
+            pass(&[(0, SAMPLED), (2, COLOR)]),
+            pass(&[(2, SAMPLED), (3, COLOR)]),
+            pass(&[(3, SAMPLED), (0, COLOR)]),
+        ];
+        let schedule = schedule(&passes, &resources);
+        assert_eq!(schedule.order, vec![0, 1, 2, 3, 4]);
+        assert_eq!(schedule.physical[1], schedule.physical[2]);
+        assert_ne!(schedule.physical[2], schedule.physical[3]);
+        assert_eq!(schedule.aliases.len(), 3);
+        // the second image waits for the reads of the first one, discarding the contents
+        assert!(schedule.transitions[2].contains(&Transition {
+            resource: schedule.physical[2].unwrap(),
+            before: Some(SAMPLED),
+            after: COLOR,
+            discard: true,
+        }));
+    }
+
+    #[test]
+    fn test_buffers() {
+        let storage = Use::Buffer(BufferUsage::Storage(PipelineStage::COMPUTE_SHADER));
+        let vertex = Use::Buffer(BufferUsage::Vertex);
+        let resources = [imported(None, None), transient(0)];
+        let passes = [pass(&[(1, storage)]), pass(&[(1, vertex), (0, COLOR)])];
+        let schedule = schedule(&passes, &resources);
+        let buffer = schedule.physical[1].unwrap();
+        // the first write has nothing to wait for
+        assert!(schedule.transitions[0].is_empty());
+        assert_eq!(
+            schedule.transitions[1][0],
+            Transition {
+                resource: buffer,
+                before: Some(storage),
+                after: vertex,
+                discard: false,
+            }
+        );
+    }
+}
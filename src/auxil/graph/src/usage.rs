@@ -0,0 +1,197 @@
+//! Uses of the resources by the passes, and their synchronization states.
+
+use hal::{buffer, image, pso::PipelineStage};
+
+/// How a pass uses an image.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ImageUsage {
+    /// Written, and possibly loaded, as a color attachment.
+    ColorAttachment,
+    /// Written, and possibly loaded, as a depth-stencil attachment.
+    DepthStencilAttachment,
+    /// Tested against as a read-only depth-stencil attachment.
+    DepthStencilReadOnly,
+    /// Read as an input attachment by the fragment shaders.
+    InputAttachment,
+    /// Sampled by the shaders of the given stages.
+    Sampled(PipelineStage),
+    /// Read and written as a storage image by the shaders of the given stages.
+    Storage(PipelineStage),
+    /// Source of a transfer command.
+    TransferSrc,
+    /// Destination of a transfer command.
+    TransferDst,
+    /// Presented to a surface. Only valid as the final usage of an imported image.
+    Present,
+}
+
+impl ImageUsage {
+    /// Returns the access flags and the layout of the image during the use.
+    pub fn state(self) -> image::State {
+        use hal::image::{Access as A, Layout as L};
+        match self {
+            ImageUsage::ColorAttachment => (
+                A::COLOR_ATTACHMENT_READ | A::COLOR_ATTACHMENT_WRITE,
+                L::ColorAttachmentOptimal,
+            ),
+            ImageUsage::DepthStencilAttachment => (
+                A::DEPTH_STENCIL_ATTACHMENT_READ | A::DEPTH_STENCIL_ATTACHMENT_WRITE,
+                L::DepthStencilAttachmentOptimal,
+            ),
+            ImageUsage::DepthStencilReadOnly => (
+                A::DEPTH_STENCIL_ATTACHMENT_READ,
+                L::DepthStencilReadOnlyOptimal,
+            ),
+            ImageUsage::InputAttachment => (A::INPUT_ATTACHMENT_READ, L::ShaderReadOnlyOptimal),
+            ImageUsage::Sampled(_) => (A::SHADER_READ, L::ShaderReadOnlyOptimal),
+            ImageUsage::Storage(_) => (A::SHADER_READ | A::SHADER_WRITE, L::General),
+            ImageUsage::TransferSrc => (A::TRANSFER_READ, L::TransferSrcOptimal),
+            ImageUsage::TransferDst => (A::TRANSFER_WRITE, L::TransferDstOptimal),
+            ImageUsage::Present => (A::empty(), L::Present),
+        }
+    }
+
+    /// Returns the pipeline stages accessing the image.
+    pub fn stages(self) -> PipelineStage {
+        match self {
+            ImageUsage::ColorAttachment => PipelineStage::COLOR_ATTACHMENT_OUTPUT,
+            ImageUsage::DepthStencilAttachment | ImageUsage::DepthStencilReadOnly => {
+                PipelineStage::EARLY_FRAGMENT_TESTS | PipelineStage::LATE_FRAGMENT_TESTS
+            }
+            ImageUsage::InputAttachment => PipelineStage::FRAGMENT_SHADER,
+            ImageUsage::Sampled(stages) | ImageUsage::Storage(stages) => stages,
+            ImageUsage::TransferSrc | ImageUsage::TransferDst => PipelineStage::TRANSFER,
+            ImageUsage::Present => PipelineStage::BOTTOM_OF_PIPE,
+        }
+    }
+
+    /// Returns the image usage flags needed for the use.
+    pub fn usage(self) -> image::Usage {
+        match self {
+            ImageUsage::ColorAttachment => image::Usage::COLOR_ATTACHMENT,
+            ImageUsage::DepthStencilAttachment | ImageUsage::DepthStencilReadOnly => {
+                image::Usage::DEPTH_STENCIL_ATTACHMENT
+            }
+            ImageUsage::InputAttachment => image::Usage::INPUT_ATTACHMENT,
+            ImageUsage::Sampled(_) => image::Usage::SAMPLED,
+            ImageUsage::Storage(_) => image::Usage::STORAGE,
+            ImageUsage::TransferSrc => image::Usage::TRANSFER_SRC,
+            ImageUsage::TransferDst => image::Usage::TRANSFER_DST,
+            ImageUsage::Present => image::Usage::empty(),
+        }
+    }
+
+    /// Returns true if the use modifies the image.
+    pub fn is_write(self) -> bool {
+        match self {
+            ImageUsage::ColorAttachment
+            | ImageUsage::DepthStencilAttachment
+            | ImageUsage::Storage(_)
+            | ImageUsage::TransferDst => true,
+            ImageUsage::DepthStencilReadOnly
+            | ImageUsage::InputAttachment
+            | ImageUsage::Sampled(_)
+            | ImageUsage::TransferSrc
+            | ImageUsage::Present => false,
+        }
+    }
+}
+
+/// How a pass uses a buffer.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BufferUsage {
+    /// Read as a vertex buffer.
+    Vertex,
+    /// Read as an index buffer.
+    Index,
+    /// Read as the arguments of the indirect commands.
+    Indirect,
+    /// Read as a uniform buffer by the shaders of the given stages.
+    Uniform(PipelineStage),
+    /// Read as a storage buffer by the shaders of the given stages.
+    StorageRead(PipelineStage),
+    /// Read and written as a storage buffer by the shaders of the given stages.
+    Storage(PipelineStage),
+    /// Source of a transfer command.
+    TransferSrc,
+    /// Destination of a transfer command.
+    TransferDst,
+}
+
+impl BufferUsage {
+    /// Returns the access flags of the buffer during the use.
+    pub fn state(self) -> buffer::State {
+        use hal::buffer::Access as A;
+        match self {
+            BufferUsage::Vertex => A::VERTEX_BUFFER_READ,
+            BufferUsage::Index => A::INDEX_BUFFER_READ,
+            BufferUsage::Indirect => A::INDIRECT_COMMAND_READ,
+            BufferUsage::Uniform(_) => A::UNIFORM_READ,
+            BufferUsage::StorageRead(_) => A::SHADER_READ,
+            BufferUsage::Storage(_) => A::SHADER_READ | A::SHADER_WRITE,
+            BufferUsage::TransferSrc => A::TRANSFER_READ,
+            BufferUsage::TransferDst => A::TRANSFER_WRITE,
+        }
+    }
+
+    /// Returns the pipeline stages accessing the buffer.
+    pub fn stages(self) -> PipelineStage {
+        match self {
+            BufferUsage::Vertex | BufferUsage::Index => PipelineStage::VERTEX_INPUT,
+            BufferUsage::Indirect => PipelineStage::DRAW_INDIRECT,
+            BufferUsage::Uniform(stages)
+            | BufferUsage::StorageRead(stages)
+            | BufferUsage::Storage(stages) => stages,
+            BufferUsage::TransferSrc | BufferUsage::TransferDst => PipelineStage::TRANSFER,
+        }
+    }
+
+    /// Returns the buffer usage flags needed for the use.
+    pub fn usage(self) -> buffer::Usage {
+        match self {
+            BufferUsage::Vertex => buffer::Usage::VERTEX,
+            BufferUsage::Index => buffer::Usage::INDEX,
+            BufferUsage::Indirect => buffer::Usage::INDIRECT,
+            BufferUsage::Uniform(_) => buffer::Usage::UNIFORM,
+            BufferUsage::StorageRead(_) | BufferUsage::Storage(_) => buffer::Usage::STORAGE,
+            BufferUsage::TransferSrc => buffer::Usage::TRANSFER_SRC,
+            BufferUsage::TransferDst => buffer::Usage::TRANSFER_DST,
+        }
+    }
+
+    /// Returns true if the use modifies the buffer.
+    pub fn is_write(self) -> bool {
+        match self {
+            BufferUsage::Storage(_) | BufferUsage::TransferDst => true,
+            BufferUsage::Vertex
+            | BufferUsage::Index
+            | BufferUsage::Indirect
+            | BufferUsage::Uniform(_)
+            | BufferUsage::StorageRead(_)
+            | BufferUsage::TransferSrc => false,
+        }
+    }
+}
+
+/// A use of either kind of resource.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Use {
+    Image(ImageUsage),
+    Buffer(BufferUsage),
+}
+
+impl Use {
+    pub fn is_write(self) -> bool {
+        match self {
+            Use::Image(usage) => usage.is_write(),
+            Use::Buffer(usage) => usage.is_write(),
+        }
+    }
+
+    pub fn stages(self) -> PipelineStage {
+        match self {
+            Use::Image(usage) => usage.stages(),
+            Use::Buffer(usage) => usage.stages(),
+        }
+    }
+}
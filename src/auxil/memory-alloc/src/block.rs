@@ -0,0 +1,151 @@
+//! Sub-allocation of a single memory object.
+
+use std::ops::Range;
+
+/// Layout of the resource occupying an allocation.
+///
+/// Linear and non-linear resources can't share a page of `buffer_image_granularity`
+/// bytes, otherwise they may alias each other on some hardware.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Tiling {
+    /// Buffers and linear images.
+    Linear,
+    /// Images with an optimal tiling.
+    NonLinear,
+}
+
+pub(crate) fn align(offset: u64, alignment: u64) -> u64 {
+    let alignment = alignment.max(1);
+    (offset + alignment - 1) / alignment * alignment
+}
+
+/// First fit allocator of the ranges of a memory object.
+#[derive(Debug)]
+pub(crate) struct Suballocator {
+    size: u64,
+    granularity: u64,
+    /// Allocated ranges, sorted by their start.
+    used: Vec<(Range<u64>, Tiling)>,
+}
+
+impl Suballocator {
+    pub fn new(size: u64, granularity: u64) -> Self {
+        Suballocator {
+            size,
+            granularity: granularity.max(1),
+            used: Vec::new(),
+        }
+    }
+
+    fn same_page(&self, a: u64, b: u64) -> bool {
+        a / self.granularity == b / self.granularity
+    }
+
+    pub fn allocate(&mut self, size: u64, alignment: u64, tiling: Tiling) -> Option<Range<u64>> {
+        for index in 0..=self.used.len() {
+            let prev = index.checked_sub(1).map(|i| &self.used[i]);
+            let next = self.used.get(index);
+            let gap_start = prev.map_or(0, |&(ref range, _)| range.end);
+            let gap_end = next.map_or(self.size, |&(ref range, _)| range.start);
+
+            let mut offset = align(gap_start, alignment);
+            if let Some(&(ref range, prev_tiling)) = prev {
+                if prev_tiling != tiling && self.same_page(range.end - 1, offset) {
+                    offset = align(offset, alignment.max(self.granularity));
+                }
+            }
+            let end = offset + size;
+            if end > gap_end {
+                continue;
+            }
+            if let Some(&(ref range, next_tiling)) = next {
+                if next_tiling != tiling && self.same_page(end - 1, range.start) {
+                    continue;
+                }
+            }
+            self.used.insert(index, (offset..end, tiling));
+            return Some(offset..end);
+        }
+        None
+    }
+
+    pub fn free(&mut self, range: &Range<u64>) {
+        match self
+            .used
+            .binary_search_by_key(&range.start, |&(ref r, _)| r.start)
+        {
+            Ok(index) => {
+                self.used.remove(index);
+            }
+            Err(_) => log::error!("Freeing an unknown range {:?}", range),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.used.is_empty()
+    }
+
+    pub fn used_bytes(&self) -> u64 {
+        self.used
+            .iter()
+            .map(|&(ref range, _)| range.end - range.start)
+            .sum()
+    }
+
+    /// Sizes of the free ranges between the allocations.
+    pub fn free_ranges<'a>(&'a self) -> impl Iterator<Item = u64> + 'a {
+        (0..=self.used.len())
+            .map(move |index| {
+                let start = match index {
+                    0 => 0,
+                    _ => self.used[index - 1].0.end,
+                };
+                let end = self.used.get(index).map_or(self.size, |r| r.0.start);
+                end - start
+            })
+            .filter(|&size| size != 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_fit_with_alignment() {
+        let mut sub = Suballocator::new(100, 1);
+        assert_eq!(sub.allocate(10, 1, Tiling::Linear), Some(0..10));
+        assert_eq!(sub.allocate(10, 16, Tiling::Linear), Some(16..26));
+        sub.free(&(0..10));
+        assert_eq!(sub.allocate(8, 4, Tiling::Linear), Some(0..8));
+        assert_eq!(sub.allocate(80, 1, Tiling::Linear), None);
+        assert_eq!(sub.free_ranges().collect::<Vec<_>>(), vec![8, 74]);
+        assert_eq!(sub.used_bytes(), 18);
+    }
+
+    #[test]
+    fn test_granularity_conflicts() {
+        let mut sub = Suballocator::new(256, 64);
+        assert_eq!(sub.allocate(10, 1, Tiling::Linear), Some(0..10));
+        // a non-linear resource can't share the page of the buffer
+        assert_eq!(sub.allocate(10, 1, Tiling::NonLinear), Some(64..74));
+        // while a linear one can
+        assert_eq!(sub.allocate(10, 1, Tiling::Linear), Some(10..20));
+        sub.free(&(64..74));
+        sub.free(&(0..10));
+        assert!(!sub.is_empty());
+        sub.free(&(10..20));
+        assert!(sub.is_empty());
+    }
+
+    #[test]
+    fn test_granularity_conflict_with_next() {
+        let mut sub = Suballocator::new(256, 64);
+        assert_eq!(sub.allocate(70, 1, Tiling::Linear), Some(0..70));
+        assert_eq!(sub.allocate(10, 1, Tiling::Linear), Some(70..80));
+        sub.free(&(0..70));
+        // the end of the first gap shares a page with the buffer at 70
+        assert_eq!(sub.allocate(66, 1, Tiling::NonLinear), Some(128..194));
+        assert_eq!(sub.allocate(10, 1, Tiling::NonLinear), Some(0..10));
+    }
+}
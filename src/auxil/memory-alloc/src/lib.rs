@@ -0,0 +1,490 @@
+//! Device memory allocator for gfx-hal.
+//!
+//! `Allocator` sub-allocates resources from large memory objects ("blocks"),
+//! one list per memory type, picking the memory type from the intended `Usage`.
+//! It takes care of the alignment of the resources, and keeps the linear and
+//! non-linear ones apart by `Limits::buffer_image_granularity`.
+//!
+//! The resources that require or prefer a dedicated allocation, as reported by
+//! their `memory::Requirements`, get their own memory object made with
+//! `Device::allocate_dedicated_memory`, and so do the ones too large for a block.
+//!
+//! ```ignore
+//! let requirements = device.get_buffer_requirements(&buffer);
+//! let allocation = allocator.alloc(&device, Usage::Upload, Resource::Buffer(&buffer), &requirements)?;
+//! device.bind_buffer_memory(allocator.memory(&allocation), allocation.offset(), &mut buffer)?;
+//! let ptr = allocator.map(&device, &allocation)?;
+//! ```
+#![warn(
+    trivial_casts,
+    trivial_numeric_casts,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications
+)]
+
+mod block;
+
+use crate::block::{align, Suballocator, Tiling};
+
+use hal::{
+    adapter::{MemoryProperties, MemoryType},
+    device::{AllocationError, Device as _, MapError},
+    memory::{self, DedicatedResource, Properties},
+    Backend, Limits, MemoryTypeId,
+};
+
+use std::ops::Range;
+
+/// Default size of the memory blocks, in bytes.
+pub const DEFAULT_BLOCK_SIZE: u64 = 64 << 20;
+
+/// Error allocating memory.
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+pub enum Error {
+    /// A memory object failed to be allocated.
+    #[error(transparent)]
+    Allocation(#[from] AllocationError),
+    /// A memory block failed to be mapped.
+    #[error(transparent)]
+    Map(#[from] MapError),
+    /// None of the memory types allowed by the requirements fits the usage.
+    #[error("No memory type is compatible with the resource and the usage")]
+    NoMemoryType,
+}
+
+/// Intended use of the memory, guiding the choice of its type.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Usage {
+    /// Only accessed by the device.
+    DeviceLocal,
+    /// Written by the host, and read by the device, e.g. staging buffers.
+    Upload,
+    /// Written by the device, and read back by the host.
+    Download,
+    /// Written by the host every frame and read by the device, e.g. uniform buffers.
+    Dynamic,
+}
+
+impl Usage {
+    /// Returns the required, preferred and avoided memory properties.
+    fn properties(self) -> (Properties, Properties, Properties) {
+        match self {
+            Usage::DeviceLocal => (
+                Properties::empty(),
+                Properties::DEVICE_LOCAL,
+                Properties::CPU_VISIBLE | Properties::LAZILY_ALLOCATED,
+            ),
+            Usage::Upload => (
+                Properties::CPU_VISIBLE,
+                Properties::COHERENT,
+                Properties::DEVICE_LOCAL | Properties::CPU_CACHED,
+            ),
+            Usage::Download => (
+                Properties::CPU_VISIBLE,
+                Properties::CPU_CACHED,
+                Properties::DEVICE_LOCAL,
+            ),
+            Usage::Dynamic => (
+                Properties::CPU_VISIBLE,
+                Properties::DEVICE_LOCAL | Properties::COHERENT,
+                Properties::CPU_CACHED,
+            ),
+        }
+    }
+}
+
+/// Resource that the memory is allocated for.
+#[derive(Debug)]
+pub enum Resource<'a, B: Backend> {
+    /// A buffer.
+    Buffer(&'a B::Buffer),
+    /// An image with `image::Tiling::Linear`.
+    LinearImage(&'a B::Image),
+    /// An image with `image::Tiling::Optimal`.
+    Image(&'a B::Image),
+}
+
+impl<B: Backend> Resource<'_, B> {
+    fn tiling(&self) -> Tiling {
+        match *self {
+            Resource::Buffer(_) | Resource::LinearImage(_) => Tiling::Linear,
+            Resource::Image(_) => Tiling::NonLinear,
+        }
+    }
+
+    fn dedicated(&self) -> DedicatedResource<B> {
+        match *self {
+            Resource::Buffer(buffer) => DedicatedResource::Buffer(buffer),
+            Resource::LinearImage(image) | Resource::Image(image) => {
+                DedicatedResource::Image(image)
+            }
+        }
+    }
+}
+
+/// A range of memory given to a resource.
+#[derive(Debug)]
+pub struct Allocation {
+    memory_type: MemoryTypeId,
+    block: usize,
+    range: Range<u64>,
+}
+
+impl Allocation {
+    /// Offset of the allocation in its memory object, to bind the resource at.
+    pub fn offset(&self) -> u64 {
+        self.range.start
+    }
+
+    /// Size of the allocation in bytes.
+    pub fn size(&self) -> u64 {
+        self.range.end - self.range.start
+    }
+
+    /// Memory type of the allocation.
+    pub fn memory_type(&self) -> MemoryTypeId {
+        self.memory_type
+    }
+}
+
+/// Statistics of the allocated memory, telling how fragmented it is.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Stats {
+    /// Number of memory blocks shared by the sub-allocations.
+    pub blocks: usize,
+    /// Number of dedicated memory objects.
+    pub dedicated: usize,
+    /// Total size of the memory objects, in bytes.
+    pub allocated_bytes: u64,
+    /// Total size of the live allocations, in bytes.
+    pub used_bytes: u64,
+    /// Number of the free ranges between the sub-allocations.
+    pub free_ranges: usize,
+    /// Size of the largest free range, in bytes.
+    pub largest_free_range: u64,
+}
+
+impl Stats {
+    /// Returns the fraction of the free block memory that can't be used
+    /// by an allocation of the size of all the free memory, from 0 to 1.
+    ///
+    /// A high value means that compacting the blocks, e.g. with
+    /// `gfx_auxil::defrag`, would help the large allocations.
+    pub fn fragmentation(&self) -> f32 {
+        let free = self.allocated_bytes - self.used_bytes;
+        if free == 0 {
+            0.0
+        } else {
+            1.0 - self.largest_free_range as f32 / free as f32
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Block<B: Backend> {
+    memory: B::Memory,
+    size: u64,
+    /// `None` for the dedicated allocations.
+    suballocator: Option<Suballocator>,
+    /// Pointer to the start of the block, if it's mapped.
+    mapped: Option<*mut u8>,
+}
+
+/// Allocator of device memory.
+#[derive(Debug)]
+pub struct Allocator<B: Backend> {
+    memory_types: Vec<MemoryType>,
+    /// Blocks of each memory type, with the slots of the freed ones left empty.
+    blocks: Vec<Vec<Option<Block<B>>>>,
+    block_size: u64,
+    granularity: u64,
+    non_coherent_atom_size: u64,
+}
+
+/// The mapped pointers are only dereferenced by the users of the allocations.
+unsafe impl<B: Backend> Send for Allocator<B> {}
+unsafe impl<B: Backend> Sync for Allocator<B> {}
+
+impl<B: Backend> Allocator<B> {
+    /// Create a new allocator, sub-allocating from blocks of `block_size` bytes.
+    pub fn new(memory_properties: &MemoryProperties, limits: &Limits, block_size: u64) -> Self {
+        Allocator {
+            memory_types: memory_properties.memory_types.clone(),
+            blocks: memory_properties
+                .memory_types
+                .iter()
+                .map(|_| Vec::new())
+                .collect(),
+            block_size,
+            granularity: limits.buffer_image_granularity.max(1),
+            non_coherent_atom_size: limits.non_coherent_atom_size.max(1) as u64,
+        }
+    }
+
+    /// Returns the memory types allowed by `type_mask`, from the best fit for `usage`.
+    fn memory_types(&self, usage: Usage, type_mask: u32) -> Vec<MemoryTypeId> {
+        let (required, preferred, avoided) = usage.properties();
+        let mut candidates = self
+            .memory_types
+            .iter()
+            .enumerate()
+            .filter(|&(id, ty)| type_mask & (1 << id) != 0 && ty.properties.contains(required))
+            .map(|(id, ty)| {
+                let score = (ty.properties & preferred).bits().count_ones() as i32
+                    - (ty.properties & avoided).bits().count_ones() as i32;
+                (MemoryTypeId(id), score)
+            })
+            .collect::<Vec<_>>();
+        // the sort is stable, keeping the order of the types with the same score
+        candidates.sort_by_key(|&(_, score)| -score);
+        candidates.into_iter().map(|(id, _)| id).collect()
+    }
+
+    /// Allocate memory for a resource with the given requirements.
+    ///
+    /// If a memory type runs out of memory, the next best one is tried.
+    pub unsafe fn alloc(
+        &mut self,
+        device: &B::Device,
+        usage: Usage,
+        resource: Resource<B>,
+        requirements: &memory::Requirements,
+    ) -> Result<Allocation, Error> {
+        let candidates = self.memory_types(usage, requirements.type_mask);
+        if candidates.is_empty() {
+            return Err(Error::NoMemoryType);
+        }
+
+        let dedicated = requirements.requires_dedicated
+            || requirements.prefers_dedicated
+            || requirements.size > self.block_size / 2;
+        let mut last_error = Error::NoMemoryType;
+        for memory_type in candidates {
+            let result = if dedicated {
+                self.alloc_dedicated(device, memory_type, &resource, requirements)
+            } else {
+                self.alloc_shared(device, memory_type, resource.tiling(), requirements)
+            };
+            match result {
+                Err(AllocationError::OutOfMemory(err)) => {
+                    log::warn!("Memory type {:?} is out of memory: {}", memory_type, err);
+                    last_error = AllocationError::OutOfMemory(err).into();
+                }
+                Err(err) => return Err(err.into()),
+                Ok(allocation) => return Ok(allocation),
+            }
+        }
+        Err(last_error)
+    }
+
+    unsafe fn alloc_dedicated(
+        &mut self,
+        device: &B::Device,
+        memory_type: MemoryTypeId,
+        resource: &Resource<B>,
+        requirements: &memory::Requirements,
+    ) -> Result<Allocation, AllocationError> {
+        let memory = if requirements.requires_dedicated || requirements.prefers_dedicated {
+            device.allocate_dedicated_memory(
+                memory_type,
+                requirements.size,
+                resource.dedicated(),
+            )?
+        } else {
+            device.allocate_memory(memory_type, requirements.size)?
+        };
+        let block = self.insert_block(
+            memory_type,
+            Block {
+                memory,
+                size: requirements.size,
+                suballocator: None,
+                mapped: None,
+            },
+        );
+        Ok(Allocation {
+            memory_type,
+            block,
+            range: 0..requirements.size,
+        })
+    }
+
+    unsafe fn alloc_shared(
+        &mut self,
+        device: &B::Device,
+        memory_type: MemoryTypeId,
+        tiling: Tiling,
+        requirements: &memory::Requirements,
+    ) -> Result<Allocation, AllocationError> {
+        // Host visible memory is flushed by atoms, which the allocations shouldn't share.
+        let coherent = self.memory_types[memory_type.0]
+            .properties
+            .contains(Properties::COHERENT);
+        let (size, alignment) = if coherent {
+            (requirements.size, requirements.alignment)
+        } else {
+            (
+                align(requirements.size, self.non_coherent_atom_size),
+                requirements.alignment.max(self.non_coherent_atom_size),
+            )
+        };
+
+        let blocks = &mut self.blocks[memory_type.0];
+        for (index, slot) in blocks.iter_mut().enumerate() {
+            if let Some(Block {
+                suballocator: Some(ref mut suballocator),
+                ..
+            }) = *slot
+            {
+                if let Some(range) = suballocator.allocate(size, alignment, tiling) {
+                    return Ok(Allocation {
+                        memory_type,
+                        block: index,
+                        range,
+                    });
+                }
+            }
+        }
+
+        log::debug!(
+            "Allocating a block of {} bytes of memory type {:?}",
+            self.block_size,
+            memory_type
+        );
+        let memory = device.allocate_memory(memory_type, self.block_size)?;
+        let mut suballocator = Suballocator::new(self.block_size, self.granularity);
+        let range = suballocator
+            .allocate(size, alignment, tiling)
+            .expect("Allocation doesn't fit into a new block");
+        let block = self.insert_block(
+            memory_type,
+            Block {
+                memory,
+                size: self.block_size,
+                suballocator: Some(suballocator),
+                mapped: None,
+            },
+        );
+        Ok(Allocation {
+            memory_type,
+            block,
+            range,
+        })
+    }
+
+    fn insert_block(&mut self, memory_type: MemoryTypeId, block: Block<B>) -> usize {
+        let blocks = &mut self.blocks[memory_type.0];
+        match blocks.iter().position(Option::is_none) {
+            Some(index) => {
+                blocks[index] = Some(block);
+                index
+            }
+            None => {
+                blocks.push(Some(block));
+                blocks.len() - 1
+            }
+        }
+    }
+
+    fn block(&self, allocation: &Allocation) -> &Block<B> {
+        self.blocks[allocation.memory_type.0][allocation.block]
+            .as_ref()
+            .expect("Allocation of a freed block")
+    }
+
+    /// Returns the memory object of an allocation, to bind the resource to.
+    pub fn memory(&self, allocation: &Allocation) -> &B::Memory {
+        &self.block(allocation).memory
+    }
+
+    /// Map the memory of an allocation, returning a pointer to its start.
+    ///
+    /// The whole block stays mapped until it's freed, so the allocations sharing it
+    /// can be mapped at the same time. The memory type must be `CPU_VISIBLE`.
+    pub unsafe fn map(
+        &mut self,
+        device: &B::Device,
+        allocation: &Allocation,
+    ) -> Result<*mut u8, Error> {
+        let block = self.blocks[allocation.memory_type.0][allocation.block]
+            .as_mut()
+            .expect("Allocation of a freed block");
+        let ptr = match block.mapped {
+            Some(ptr) => ptr,
+            None => {
+                let ptr = device.map_memory(&mut block.memory, memory::Segment::ALL)?;
+                block.mapped = Some(ptr);
+                ptr
+            }
+        };
+        Ok(ptr.add(allocation.range.start as usize))
+    }
+
+    /// Free an allocation.
+    ///
+    /// The blocks left without allocations are released to the device.
+    pub unsafe fn free(&mut self, device: &B::Device, allocation: Allocation) {
+        let slot = &mut self.blocks[allocation.memory_type.0][allocation.block];
+        let is_empty = match *slot {
+            Some(Block {
+                suballocator: Some(ref mut suballocator),
+                ..
+            }) => {
+                suballocator.free(&allocation.range);
+                suballocator.is_empty()
+            }
+            Some(Block {
+                suballocator: None, ..
+            }) => true,
+            None => panic!("Allocation of a freed block"),
+        };
+        if is_empty {
+            Self::free_block(device, slot.take().unwrap());
+        }
+    }
+
+    unsafe fn free_block(device: &B::Device, mut block: Block<B>) {
+        if block.mapped.is_some() {
+            device.unmap_memory(&mut block.memory);
+        }
+        device.free_memory(block.memory);
+    }
+
+    /// Get the statistics of the allocated memory.
+    pub fn stats(&self) -> Stats {
+        let mut stats = Stats::default();
+        for block in self.blocks.iter().flatten().flatten() {
+            stats.allocated_bytes += block.size;
+            match block.suballocator {
+                Some(ref suballocator) => {
+                    stats.blocks += 1;
+                    stats.used_bytes += suballocator.used_bytes();
+                    for size in suballocator.free_ranges() {
+                        stats.free_ranges += 1;
+                        stats.largest_free_range = stats.largest_free_range.max(size);
+                    }
+                }
+                None => {
+                    stats.dedicated += 1;
+                    stats.used_bytes += block.size;
+                }
+            }
+        }
+        stats
+    }
+
+    /// Destroy the allocator, freeing all the memory.
+    ///
+    /// The resources bound to the allocations must be destroyed.
+    pub unsafe fn dispose(self, device: &B::Device) {
+        let mut leaked = 0;
+        for block in self.blocks.into_iter().flatten().flatten() {
+            leaked += 1;
+            Self::free_block(device, block);
+        }
+        if leaked != 0 {
+            log::warn!("Freed {} memory blocks with live allocations", leaked);
+        }
+    }
+}
@@ -42,6 +42,27 @@ where
         &self.initial_range
     }
 
+    /// Extends the range covered by this allocator up to `new_end`.
+    ///
+    /// The added space becomes free, merging with the last free range if it touches the end.
+    pub fn grow_to(&mut self, new_end: T) {
+        assert!(self.initial_range.end <= new_end);
+        if self.initial_range.end == new_end {
+            return;
+        }
+        let initial_range_end = self.initial_range.end;
+        if let Some(last_range) = self
+            .free_ranges
+            .last_mut()
+            .filter(|last_range| last_range.end == initial_range_end)
+        {
+            last_range.end = new_end;
+        } else {
+            self.free_ranges.push(initial_range_end..new_end);
+        }
+        self.initial_range.end = new_end;
+    }
+
     pub fn allocate_range(&mut self, length: T) -> Result<Range<T>, RangeAllocationError<T>> {
         assert_ne!(length + length, length);
         let mut best_fit: Option<(usize, Range<T>)> = None;
@@ -306,4 +327,20 @@ mod tests {
         assert_eq!(alloc.free_ranges, vec![0..9]);
         assert!(alloc.allocated_ranges().eq(std::iter::empty()));
     }
+
+    #[test]
+    fn test_grow() {
+        let mut alloc = RangeAllocator::new(0..10);
+        assert_eq!(alloc.allocate_range(6), Ok(0..6));
+        // Growing extends the free range at the end.
+        alloc.grow_to(20);
+        assert_eq!(alloc.free_ranges, vec![6..20]);
+        assert_eq!(alloc.allocate_range(14), Ok(6..20));
+        // Growing a fully allocated range adds a new free range.
+        alloc.free_range(0..6);
+        alloc.grow_to(25);
+        assert_eq!(alloc.free_ranges, vec![0..6, 20..25]);
+        assert_eq!(alloc.initial_range(), &(0..25));
+        assert_eq!(alloc.allocate_range(5), Ok(20..25));
+    }
 }
@@ -0,0 +1,262 @@
+//! Compile-time shader embedding for gfx-hal.
+//!
+//! `gfx_shader!` compiles a GLSL shader to SPIR-V while the crate is being built,
+//! embeds the result into the binary and reflects its resource bindings into
+//! descriptor set layout constants, so that the layouts can't go out of sync with
+//! the shader code.
+//!
+//! ```ignore
+//! gfx_shader_macro::gfx_shader!(blur = "shaders/blur.comp");
+//!
+//! let module = device.create_shader_module(blur::SPIRV)?;
+//! let set_layout = device.create_descriptor_set_layout(
+//!     blur::SET_0.iter().cloned(),
+//!     iter::empty(),
+//! )?;
+//! ```
+//!
+//! The path is relative to the directory of the crate manifest. The stage is
+//! deduced from the file extension: `vert`, `tesc`, `tese`, `geom`, `frag` and `comp`
+//! are compiled from GLSL, while `spv` files are embedded as they are. Without an
+//! explicit name, the module is named after the file, with the characters that
+//! can't be a part of an identifier replaced by `_` (`shader.comp` becomes `shader_comp`).
+//!
+//! The generated module contains:
+//!   - `SPIRV`: the shader code, as `&[u32]`.
+//!   - `ENTRY`: the name of the entry point.
+//!   - `STAGE`: the `pso::ShaderStageFlags` of the entry point.
+//!   - `SET_<n>`: the `pso::DescriptorSetLayoutBinding` list of each descriptor set used.
+//!   - `SETS`: all the sets in order, with the unused ones being empty.
+//!
+//! The expansion refers to `gfx_hal` by its crate name, so it needs to be a dependency
+//! of the invoking crate.
+#![warn(
+    trivial_casts,
+    trivial_numeric_casts,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications
+)]
+
+extern crate proc_macro;
+
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::{
+    parse::{Parse, ParseStream},
+    parse_macro_input, Ident, LitStr, Token,
+};
+
+use std::{
+    env, fs,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+mod reflect;
+
+struct ShaderInput {
+    name: Option<Ident>,
+    path: LitStr,
+}
+
+impl Parse for ShaderInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name = if input.peek(Ident) {
+            let name = input.parse()?;
+            input.parse::<Token![=]>()?;
+            Some(name)
+        } else {
+            None
+        };
+        let path = input.parse()?;
+        Ok(ShaderInput { name, path })
+    }
+}
+
+/// Compiles a shader and generates a module with its SPIR-V code and reflected layouts.
+///
+/// See the crate documentation for the syntax and the generated items.
+#[proc_macro]
+pub fn gfx_shader(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as ShaderInput);
+    match expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(message) => syn::Error::new(input.path.span(), message)
+            .to_compile_error()
+            .into(),
+    }
+}
+
+fn module_name(path: &Path) -> String {
+    let file_name = path
+        .file_name()
+        .map_or(String::new(), |name| name.to_string_lossy().into_owned());
+    let mut name = file_name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    if name.chars().next().map_or(true, |c| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+    name
+}
+
+fn compile(path: &Path) -> Result<Vec<u32>, String> {
+    let extension = path
+        .extension()
+        .map_or(String::new(), |ext| ext.to_string_lossy().into_owned());
+    let ty = match extension.as_str() {
+        "spv" => {
+            let bytes = fs::read(path).map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+            return words(&bytes);
+        }
+        "vert" => glsl_to_spirv::ShaderType::Vertex,
+        "tesc" => glsl_to_spirv::ShaderType::TessellationControl,
+        "tese" => glsl_to_spirv::ShaderType::TessellationEvaluation,
+        "geom" => glsl_to_spirv::ShaderType::Geometry,
+        "frag" => glsl_to_spirv::ShaderType::Fragment,
+        "comp" => glsl_to_spirv::ShaderType::Compute,
+        _ => return Err(format!("Unknown shader extension {:?}", extension)),
+    };
+
+    let source =
+        fs::read_to_string(path).map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+    let mut file = glsl_to_spirv::compile(&source, ty)
+        .map_err(|e| format!("Failed to compile {:?}: {}", path, e))?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)
+        .map_err(|e| format!("Failed to read the SPIR-V of {:?}: {}", path, e))?;
+    words(&bytes)
+}
+
+fn words(bytes: &[u8]) -> Result<Vec<u32>, String> {
+    if bytes.len() % 4 != 0 {
+        return Err("SPIR-V size is not a multiple of 4".to_string());
+    }
+    Ok(bytes
+        .chunks(4)
+        .map(|chunk| u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        .collect())
+}
+
+fn stage(execution_model: u32) -> Result<TokenStream, String> {
+    use reflect::model;
+    let flag = match execution_model {
+        model::VERTEX => quote!(VERTEX),
+        model::TESSELLATION_CONTROL => quote!(HULL),
+        model::TESSELLATION_EVALUATION => quote!(DOMAIN),
+        model::GEOMETRY => quote!(GEOMETRY),
+        model::FRAGMENT => quote!(FRAGMENT),
+        model::COMPUTE => quote!(COMPUTE),
+        model::TASK => quote!(TASK),
+        model::MESH => quote!(MESH),
+        _ => return Err(format!("Unsupported execution model {}", execution_model)),
+    };
+    Ok(quote!(::gfx_hal::pso::ShaderStageFlags::#flag))
+}
+
+fn descriptor_type(resource: reflect::Resource) -> TokenStream {
+    use reflect::Resource as R;
+    let pso = quote!(::gfx_hal::pso);
+    match resource {
+        R::Sampler => quote!(#pso::DescriptorType::Sampler),
+        R::SampledImage { with_sampler } => quote!(#pso::DescriptorType::Image {
+            ty: #pso::ImageDescriptorType::Sampled { with_sampler: #with_sampler },
+        }),
+        R::StorageImage => quote!(#pso::DescriptorType::Image {
+            ty: #pso::ImageDescriptorType::Storage { read_only: false },
+        }),
+        R::UniformTexelBuffer => quote!(#pso::DescriptorType::Buffer {
+            ty: #pso::BufferDescriptorType::Uniform,
+            format: #pso::BufferDescriptorFormat::Texel,
+        }),
+        R::StorageTexelBuffer => quote!(#pso::DescriptorType::Buffer {
+            ty: #pso::BufferDescriptorType::Storage { read_only: false },
+            format: #pso::BufferDescriptorFormat::Texel,
+        }),
+        R::InputAttachment => quote!(#pso::DescriptorType::InputAttachment),
+        R::UniformBuffer => quote!(#pso::DescriptorType::Buffer {
+            ty: #pso::BufferDescriptorType::Uniform,
+            format: #pso::BufferDescriptorFormat::Structured { dynamic_offset: false },
+        }),
+        R::StorageBuffer { read_only } => quote!(#pso::DescriptorType::Buffer {
+            ty: #pso::BufferDescriptorType::Storage { read_only: #read_only },
+            format: #pso::BufferDescriptorFormat::Structured { dynamic_offset: false },
+        }),
+    }
+}
+
+fn expand(input: &ShaderInput) -> Result<TokenStream, String> {
+    let manifest_dir =
+        env::var("CARGO_MANIFEST_DIR").map_err(|_| "CARGO_MANIFEST_DIR is not set".to_string())?;
+    let path = PathBuf::from(manifest_dir).join(input.path.value());
+
+    let spirv = compile(&path)?;
+    let reflection = reflect::reflect(&spirv)?;
+    let entry_point = match reflection.entry_points.as_slice() {
+        [entry_point] => entry_point,
+        [] => return Err(format!("No entry points found in {:?}", path)),
+        _ => return Err(format!("Multiple entry points found in {:?}", path)),
+    };
+    let entry = &entry_point.name;
+    let stage = stage(entry_point.model)?;
+
+    let set_count = reflection
+        .bindings
+        .last()
+        .map_or(0, |binding| binding.set as usize + 1);
+    let set_names = (0..set_count)
+        .map(|set| format_ident!("SET_{}", set))
+        .collect::<Vec<_>>();
+    let sets = set_names.iter().enumerate().map(|(set, set_name)| {
+        let bindings = reflection
+            .bindings
+            .iter()
+            .filter(|binding| binding.set as usize == set)
+            .map(|binding| {
+                let index = binding.binding;
+                let ty = descriptor_type(binding.resource);
+                let count = binding.count as usize;
+                quote!(::gfx_hal::pso::DescriptorSetLayoutBinding {
+                    binding: #index,
+                    ty: #ty,
+                    count: #count,
+                    stage_flags: #stage,
+                    immutable_samplers: false,
+                })
+            });
+        quote! {
+            pub const #set_name: &[::gfx_hal::pso::DescriptorSetLayoutBinding] = &[#(#bindings),*];
+        }
+    });
+
+    let name = match input.name {
+        Some(ref name) => name.clone(),
+        None => Ident::new(&module_name(&path), Span::call_site()),
+    };
+    let source_path = path.to_string_lossy().into_owned();
+    let source = if path.extension().map_or(false, |ext| ext == "spv") {
+        quote!(
+            const _SOURCE: &[u8] = include_bytes!(#source_path);
+        )
+    } else {
+        quote!(
+            const _SOURCE: &str = include_str!(#source_path);
+        )
+    };
+
+    Ok(quote! {
+        #[allow(dead_code)]
+        pub mod #name {
+            // Makes the invoking crate rebuild when the shader changes.
+            #source
+
+            pub const SPIRV: &[u32] = &[#(#spirv),*];
+            pub const ENTRY: &str = #entry;
+            pub const STAGE: ::gfx_hal::pso::ShaderStageFlags = #stage;
+            #(#sets)*
+            pub const SETS: &[&[::gfx_hal::pso::DescriptorSetLayoutBinding]] = &[#(#set_names),*];
+        }
+    })
+}
@@ -0,0 +1,264 @@
+//! Minimal SPIR-V reflection: entry points and resource bindings.
+
+use std::collections::HashMap;
+
+const MAGIC_NUMBER: u32 = 0x0723_0203;
+const HEADER_SIZE: usize = 5;
+
+mod op {
+    pub const ENTRY_POINT: u32 = 15;
+    pub const TYPE_IMAGE: u32 = 25;
+    pub const TYPE_SAMPLER: u32 = 26;
+    pub const TYPE_SAMPLED_IMAGE: u32 = 27;
+    pub const TYPE_ARRAY: u32 = 28;
+    pub const TYPE_RUNTIME_ARRAY: u32 = 29;
+    pub const TYPE_STRUCT: u32 = 30;
+    pub const TYPE_POINTER: u32 = 32;
+    pub const CONSTANT: u32 = 43;
+    pub const VARIABLE: u32 = 59;
+    pub const DECORATE: u32 = 71;
+    pub const MEMBER_DECORATE: u32 = 72;
+}
+
+mod decoration {
+    pub const BLOCK: u32 = 2;
+    pub const NON_WRITABLE: u32 = 24;
+    pub const BINDING: u32 = 33;
+    pub const DESCRIPTOR_SET: u32 = 34;
+}
+
+mod storage {
+    pub const UNIFORM_CONSTANT: u32 = 0;
+    pub const UNIFORM: u32 = 2;
+    pub const STORAGE_BUFFER: u32 = 12;
+}
+
+mod dim {
+    pub const BUFFER: u32 = 5;
+    pub const SUBPASS_DATA: u32 = 6;
+}
+
+/// Execution models of the entry points.
+pub mod model {
+    pub const VERTEX: u32 = 0;
+    pub const TESSELLATION_CONTROL: u32 = 1;
+    pub const TESSELLATION_EVALUATION: u32 = 2;
+    pub const GEOMETRY: u32 = 3;
+    pub const FRAGMENT: u32 = 4;
+    pub const COMPUTE: u32 = 5;
+    pub const TASK: u32 = 5267;
+    pub const MESH: u32 = 5268;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Resource {
+    Sampler,
+    SampledImage { with_sampler: bool },
+    StorageImage,
+    UniformTexelBuffer,
+    StorageTexelBuffer,
+    InputAttachment,
+    UniformBuffer,
+    StorageBuffer { read_only: bool },
+}
+
+#[derive(Debug)]
+pub struct EntryPoint {
+    pub name: String,
+    pub model: u32,
+}
+
+#[derive(Debug)]
+pub struct Binding {
+    pub set: u32,
+    pub binding: u32,
+    pub resource: Resource,
+    pub count: u32,
+}
+
+#[derive(Debug, Default)]
+pub struct Reflection {
+    pub entry_points: Vec<EntryPoint>,
+    pub bindings: Vec<Binding>,
+}
+
+enum Type {
+    Image { dim: u32, sampled: u32 },
+    Sampler,
+    SampledImage,
+    Array { element: u32, length: u32 },
+    RuntimeArray,
+    Struct { members: usize },
+    Pointer { ty: u32 },
+}
+
+fn parse_string(words: &[u32]) -> String {
+    let bytes = words
+        .iter()
+        .flat_map(|word| word.to_le_bytes().to_vec())
+        .take_while(|&byte| byte != 0)
+        .collect::<Vec<_>>();
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+pub fn reflect(words: &[u32]) -> Result<Reflection, String> {
+    if words.len() < HEADER_SIZE || words[0] != MAGIC_NUMBER {
+        return Err("Not a SPIR-V module".to_string());
+    }
+
+    let mut reflection = Reflection::default();
+    let mut types = HashMap::new();
+    let mut constants = HashMap::new();
+    let mut variables = Vec::new();
+    let mut decorations = HashMap::<(u32, u32), u32>::new();
+    let mut non_writable_members = HashMap::<u32, usize>::new();
+
+    let mut offset = HEADER_SIZE;
+    while offset < words.len() {
+        let word_count = (words[offset] >> 16) as usize;
+        let opcode = words[offset] & 0xFFFF;
+        if word_count == 0 || offset + word_count > words.len() {
+            return Err(format!("Malformed instruction at word {}", offset));
+        }
+        let operands = &words[offset + 1..offset + word_count];
+        offset += word_count;
+
+        let operand = |index: usize| {
+            operands
+                .get(index)
+                .cloned()
+                .ok_or_else(|| format!("Missing operands of the instruction {}", opcode))
+        };
+        match opcode {
+            op::ENTRY_POINT => reflection.entry_points.push(EntryPoint {
+                model: operand(0)?,
+                name: parse_string(&operands[2.min(operands.len())..]),
+            }),
+            op::TYPE_IMAGE => {
+                types.insert(
+                    operand(0)?,
+                    Type::Image {
+                        dim: operand(2)?,
+                        sampled: operand(6)?,
+                    },
+                );
+            }
+            op::TYPE_SAMPLER => {
+                types.insert(operand(0)?, Type::Sampler);
+            }
+            op::TYPE_SAMPLED_IMAGE => {
+                types.insert(operand(0)?, Type::SampledImage);
+            }
+            op::TYPE_ARRAY => {
+                types.insert(
+                    operand(0)?,
+                    Type::Array {
+                        element: operand(1)?,
+                        length: operand(2)?,
+                    },
+                );
+            }
+            op::TYPE_RUNTIME_ARRAY => {
+                types.insert(operand(0)?, Type::RuntimeArray);
+            }
+            op::TYPE_STRUCT => {
+                types.insert(
+                    operand(0)?,
+                    Type::Struct {
+                        members: operands.len() - 1,
+                    },
+                );
+            }
+            op::TYPE_POINTER => {
+                types.insert(operand(0)?, Type::Pointer { ty: operand(2)? });
+            }
+            op::CONSTANT => {
+                constants.insert(operand(1)?, operand(2)?);
+            }
+            op::VARIABLE => variables.push((operand(0)?, operand(1)?, operand(2)?)),
+            op::DECORATE => {
+                let value = operands.get(2).cloned().unwrap_or(0);
+                decorations.insert((operand(0)?, operand(1)?), value);
+            }
+            op::MEMBER_DECORATE if operand(2)? == decoration::NON_WRITABLE => {
+                *non_writable_members.entry(operand(0)?).or_insert(0) += 1;
+            }
+            _ => {}
+        }
+    }
+
+    for (pointer, id, storage_class) in variables {
+        let set = match decorations.get(&(id, decoration::DESCRIPTOR_SET)) {
+            Some(&set) => set,
+            None => continue,
+        };
+        let binding = decorations
+            .get(&(id, decoration::BINDING))
+            .cloned()
+            .unwrap_or(0);
+
+        let mut ty = match types.get(&pointer) {
+            Some(&Type::Pointer { ty }) => ty,
+            _ => return Err(format!("Variable {} is not a pointer", id)),
+        };
+        let mut count = 1;
+        loop {
+            match types.get(&ty) {
+                Some(&Type::Array { element, length }) => {
+                    count *= constants.get(&length).cloned().unwrap_or(1);
+                    ty = element;
+                }
+                Some(Type::RuntimeArray) if storage_class == storage::UNIFORM_CONSTANT => {
+                    return Err(format!(
+                        "Binding {} of set {} is an unsized array, which is not supported",
+                        binding, set
+                    ));
+                }
+                _ => break,
+            }
+        }
+
+        let resource = match (types.get(&ty), storage_class) {
+            (Some(Type::Sampler), _) => Resource::Sampler,
+            (Some(Type::SampledImage), _) => Resource::SampledImage { with_sampler: true },
+            (Some(&Type::Image { dim, sampled }), _) => match (dim, sampled) {
+                (dim::SUBPASS_DATA, _) => Resource::InputAttachment,
+                (dim::BUFFER, 2) => Resource::StorageTexelBuffer,
+                (dim::BUFFER, _) => Resource::UniformTexelBuffer,
+                (_, 2) => Resource::StorageImage,
+                (_, _) => Resource::SampledImage {
+                    with_sampler: false,
+                },
+            },
+            (Some(Type::Struct { .. }), storage::UNIFORM)
+                if decorations.contains_key(&(ty, decoration::BLOCK)) =>
+            {
+                Resource::UniformBuffer
+            }
+            (Some(&Type::Struct { members }), storage::UNIFORM)
+            | (Some(&Type::Struct { members }), storage::STORAGE_BUFFER) => {
+                let read_only = decorations.contains_key(&(id, decoration::NON_WRITABLE))
+                    || non_writable_members.get(&ty) == Some(&members);
+                Resource::StorageBuffer { read_only }
+            }
+            _ => {
+                return Err(format!(
+                    "Binding {} of set {} has an unsupported type",
+                    binding, set
+                ))
+            }
+        };
+
+        reflection.bindings.push(Binding {
+            set,
+            binding,
+            resource,
+            count,
+        });
+    }
+
+    reflection
+        .bindings
+        .sort_by_key(|binding| (binding.set, binding.binding));
+    Ok(reflection)
+}
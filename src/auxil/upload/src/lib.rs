@@ -0,0 +1,631 @@
+//! Staging buffer uploads for gfx-hal.
+//!
+//! `StagingBelt` owns a set of host-visible staging buffers ("chunks"), which
+//! stay persistently mapped. Uploads copy the data into the current chunk and
+//! record a transfer into the destination resource on a command buffer
+//! supplied by the caller.
+//!
+//! The chunks written since the last `finish` form a batch, guarded by a fence
+//! owned by the belt: `finish` returns it, and it has to be passed to the
+//! submission of the command buffers with the recorded transfers. `recall`
+//! then recycles the chunks of every batch whose fence is signaled, so the
+//! staging memory is never overwritten while the device may still read it.
+//!
+//! ```
+//! # use gfx_backend_software as back;
+//! # use gfx_upload::StagingBelt;
+//! # use hal::{adapter::PhysicalDevice as _, buffer, command::{self, CommandBuffer as _}, device::Device as _, pool::CommandPool as _, queue::Queue as _, Instance as _};
+//! # use std::iter;
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> { unsafe {
+//! # let instance = back::Instance::create("upload", 1).unwrap();
+//! # let adapter = instance.enumerate_adapters().remove(0);
+//! # let mut gpu = adapter.physical_device.open(&[(&adapter.queue_families[0], &[1.0])], hal::Features::empty())?;
+//! # let family = gpu.queue_groups[0].family;
+//! # let (device, queue) = (&gpu.device, &mut gpu.queue_groups[0].queues[0]);
+//! # let mut pool = device.create_command_pool(family, hal::pool::CommandPoolCreateFlags::empty())?;
+//! # let mut cmd_buffer = pool.allocate_one(command::Level::Primary);
+//! # cmd_buffer.begin_primary(command::CommandBufferFlags::ONE_TIME_SUBMIT);
+//! # let vertices = [0u8; 64];
+//! # let mut vertex_buffer = device.create_buffer(64, buffer::Usage::TRANSFER_DST)?;
+//! # let memory = device.allocate_memory(hal::MemoryTypeId(0), 64)?;
+//! # device.bind_buffer_memory(&memory, 0, &mut vertex_buffer)?;
+//! let properties = adapter.physical_device.memory_properties();
+//! let mut belt = StagingBelt::<back::Backend>::new(&properties, &adapter.physical_device.limits(), 1 << 16);
+//! belt.upload_buffer(device, &mut cmd_buffer, &vertex_buffer, 0, &vertices)?;
+//! cmd_buffer.finish();
+//! let fence = belt.finish(device)?;
+//! queue.submit(iter::once(&cmd_buffer), iter::empty(), iter::empty(), Some(fence))?;
+//! // later on, typically once per frame
+//! belt.recall(device)?;
+//! # belt.destroy(device);
+//! # } Ok(()) }
+//! ```
+#![warn(
+    trivial_casts,
+    trivial_numeric_casts,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications
+)]
+
+use hal::{
+    adapter::{MemoryProperties, MemoryType},
+    buffer, command,
+    command::CommandBuffer as _,
+    device::{AllocationError, BindError, Device as _, DeviceLost, MapError, OutOfMemory},
+    image, memory, Backend, Limits, MemoryTypeId,
+};
+
+use std::{collections::VecDeque, iter, ptr};
+
+/// Alignment of the staging data of image uploads.
+///
+/// Copies between buffers and images require the buffer offset to be a multiple
+/// of the texel block size, which this covers for all the power of two sizes.
+const IMAGE_ALIGNMENT: buffer::Offset = 16;
+
+/// Error uploading data through the staging belt.
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+pub enum Error {
+    /// Out of either host or device memory.
+    #[error(transparent)]
+    OutOfMemory(#[from] OutOfMemory),
+    /// A staging chunk failed to be allocated.
+    #[error(transparent)]
+    Allocation(#[from] AllocationError),
+    /// A staging buffer failed to be created.
+    #[error(transparent)]
+    Buffer(#[from] buffer::CreationError),
+    /// A staging buffer failed to be bound to its memory.
+    #[error(transparent)]
+    Bind(#[from] BindError),
+    /// A staging chunk failed to be mapped.
+    #[error(transparent)]
+    Map(#[from] MapError),
+    /// The device was lost while checking the batch fences.
+    #[error(transparent)]
+    DeviceLost(#[from] DeviceLost),
+    /// None of the memory types compatible with the staging buffers is visible by the host.
+    #[error("No host visible memory type is compatible with the staging buffers")]
+    NoMemoryType,
+}
+
+/// Statistics of the staging memory.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Stats {
+    /// Number of chunks currently allocated.
+    pub chunks: usize,
+    /// Total size in bytes of the allocated chunks.
+    pub allocated: u64,
+    /// Number of submitted batches waiting for their fence.
+    pub pending_batches: usize,
+}
+
+/// A persistently mapped staging buffer.
+#[derive(Debug)]
+struct Chunk<B: Backend> {
+    memory: B::Memory,
+    buffer: B::Buffer,
+    ptr: *mut u8,
+    size: u64,
+    /// Offset of the free space of the chunk.
+    offset: u64,
+    coherent: bool,
+    /// Allocated for a single large upload, and freed once it completes.
+    dedicated: bool,
+}
+
+/// Chunks submitted together, guarded by a fence.
+#[derive(Debug)]
+struct Batch<B: Backend> {
+    chunks: Vec<Chunk<B>>,
+    fence: B::Fence,
+}
+
+/// A ring of staging buffers for uploading data to the device.
+#[derive(Debug)]
+pub struct StagingBelt<B: Backend> {
+    memory_types: Vec<MemoryType>,
+    chunk_size: u64,
+    copy_alignment: buffer::Offset,
+    non_coherent_atom_size: u64,
+    /// Chunks written since the last `finish`.
+    active: Vec<Chunk<B>>,
+    /// Batches submitted, in the order of submission.
+    batches: VecDeque<Batch<B>>,
+    /// Chunks ready to be reused.
+    free: Vec<Chunk<B>>,
+    /// Unsignaled fences ready to guard new batches.
+    free_fences: Vec<B::Fence>,
+}
+
+/// The chunks are only ever accessed by the thread owning the belt.
+unsafe impl<B: Backend> Send for StagingBelt<B> {}
+
+fn align(offset: u64, alignment: u64) -> u64 {
+    (offset + alignment - 1) / alignment * alignment
+}
+
+impl<B: Backend> StagingBelt<B> {
+    /// Create a new staging belt, allocating its chunks with `chunk_size` bytes.
+    ///
+    /// Uploads larger than `chunk_size` get a dedicated chunk, which is freed
+    /// instead of being recycled.
+    pub fn new(memory_properties: &MemoryProperties, limits: &Limits, chunk_size: u64) -> Self {
+        let non_coherent_atom_size = limits.non_coherent_atom_size.max(1) as u64;
+        StagingBelt {
+            memory_types: memory_properties.memory_types.clone(),
+            chunk_size: align(chunk_size.max(1), non_coherent_atom_size),
+            copy_alignment: limits.optimal_buffer_copy_offset_alignment.max(4),
+            non_coherent_atom_size,
+            active: Vec::new(),
+            batches: VecDeque::new(),
+            free: Vec::new(),
+            free_fences: Vec::new(),
+        }
+    }
+
+    /// Copy `data` into `dst` at `dst_offset`, recording the transfer into `cmd_buffer`.
+    ///
+    /// `dst` has to be created with `buffer::Usage::TRANSFER_DST`. The transfer is not
+    /// synchronized with the other commands of `cmd_buffer`: the caller is responsible
+    /// for the barriers protecting the accesses of `dst` around it.
+    pub unsafe fn upload_buffer(
+        &mut self,
+        device: &B::Device,
+        cmd_buffer: &mut B::CommandBuffer,
+        dst: &B::Buffer,
+        dst_offset: buffer::Offset,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        let (chunk, offset) = self.write(device, data, self.copy_alignment)?;
+        cmd_buffer.copy_buffer(
+            &chunk.buffer,
+            dst,
+            iter::once(command::BufferCopy {
+                src: offset,
+                dst: dst_offset,
+                size: data.len() as u64,
+            }),
+        );
+        Ok(())
+    }
+
+    /// Copy `data` into `dst`, recording the transfer into `cmd_buffer`.
+    ///
+    /// The regions are interpreted as in `CommandBuffer::copy_buffer_to_image`,
+    /// with `buffer_offset` being the offset into `data`. `dst` has to be created
+    /// with `image::Usage::TRANSFER_DST`, and be in `dst_layout` when the transfer
+    /// executes.
+    pub unsafe fn upload_image<I>(
+        &mut self,
+        device: &B::Device,
+        cmd_buffer: &mut B::CommandBuffer,
+        dst: &B::Image,
+        dst_layout: image::Layout,
+        data: &[u8],
+        regions: I,
+    ) -> Result<(), Error>
+    where
+        I: Iterator<Item = command::BufferImageCopy>,
+    {
+        if data.is_empty() {
+            return Ok(());
+        }
+        let alignment = self.copy_alignment.max(IMAGE_ALIGNMENT);
+        let (chunk, offset) = self.write(device, data, alignment)?;
+        cmd_buffer.copy_buffer_to_image(
+            &chunk.buffer,
+            dst,
+            dst_layout,
+            regions.map(|region| command::BufferImageCopy {
+                buffer_offset: offset + region.buffer_offset,
+                ..region
+            }),
+        );
+        Ok(())
+    }
+
+    /// Close the batch of the uploads recorded so far, returning its fence.
+    ///
+    /// The fence has to be passed to the submission of the last command buffer
+    /// with the recorded transfers. Until it's signaled, `recall` doesn't recycle
+    /// this batch, nor any of the batches finished after it.
+    pub unsafe fn finish(&mut self, device: &B::Device) -> Result<&mut B::Fence, Error> {
+        let ranges = self
+            .active
+            .iter()
+            .filter(|chunk| !chunk.coherent && chunk.offset != 0)
+            .map(|chunk| {
+                let size = align(chunk.offset, self.non_coherent_atom_size);
+                (
+                    &chunk.memory,
+                    memory::Segment {
+                        offset: 0,
+                        size: Some(size),
+                    },
+                )
+            })
+            .collect::<Vec<_>>();
+        if !ranges.is_empty() {
+            device.flush_mapped_memory_ranges(ranges.into_iter())?;
+        }
+
+        let fence = match self.free_fences.pop() {
+            Some(fence) => fence,
+            None => device.create_fence(false)?,
+        };
+        let chunks = self.active.drain(..).collect();
+        self.batches.push_back(Batch { chunks, fence });
+        Ok(&mut self.batches.back_mut().unwrap().fence)
+    }
+
+    /// Recycle the chunks of the batches that the device has finished reading.
+    ///
+    /// The batches are recycled in the order they were finished, stopping at the
+    /// first one with an unsignaled fence.
+    pub unsafe fn recall(&mut self, device: &B::Device) -> Result<(), Error> {
+        while let Some(batch) = self.batches.front() {
+            if !device.get_fence_status(&batch.fence)? {
+                break;
+            }
+            let Batch { chunks, mut fence } = self.batches.pop_front().unwrap();
+            for mut chunk in chunks {
+                if chunk.dedicated {
+                    Self::destroy_chunk(device, chunk);
+                } else {
+                    chunk.offset = 0;
+                    self.free.push(chunk);
+                }
+            }
+            // The chunks are already recycled, only the fence is lost on failure.
+            if let Err(err) = device.reset_fence(&mut fence) {
+                device.destroy_fence(fence);
+                return Err(err.into());
+            }
+            self.free_fences.push(fence);
+        }
+        Ok(())
+    }
+
+    /// Free the chunks that are not in use.
+    pub unsafe fn shrink(&mut self, device: &B::Device) {
+        for chunk in self.free.drain(..) {
+            Self::destroy_chunk(device, chunk);
+        }
+    }
+
+    /// Get the statistics of the staging memory.
+    pub fn stats(&self) -> Stats {
+        let chunks = self
+            .active
+            .iter()
+            .chain(self.batches.iter().flat_map(|batch| batch.chunks.iter()))
+            .chain(self.free.iter());
+        Stats {
+            chunks: chunks.clone().count(),
+            allocated: chunks.map(|chunk| chunk.size).sum(),
+            pending_batches: self.batches.len(),
+        }
+    }
+
+    /// Destroy the belt and its resources.
+    ///
+    /// All the submitted batches must have completed.
+    pub unsafe fn destroy(mut self, device: &B::Device) {
+        for batch in self.batches.drain(..) {
+            device.destroy_fence(batch.fence);
+            for chunk in batch.chunks {
+                Self::destroy_chunk(device, chunk);
+            }
+        }
+        for chunk in self.active.drain(..).chain(self.free.drain(..)) {
+            Self::destroy_chunk(device, chunk);
+        }
+        for fence in self.free_fences.drain(..) {
+            device.destroy_fence(fence);
+        }
+    }
+
+    /// Copy `data` into a chunk, returning it with the offset of the copy.
+    unsafe fn write(
+        &mut self,
+        device: &B::Device,
+        data: &[u8],
+        alignment: u64,
+    ) -> Result<(&Chunk<B>, u64), Error> {
+        let size = data.len() as u64;
+        let position = self
+            .active
+            .iter()
+            .position(|chunk| align(chunk.offset, alignment) + size <= chunk.size);
+        let index = match position {
+            Some(index) => index,
+            None => {
+                let chunk = if size <= self.chunk_size {
+                    match self.free.pop() {
+                        Some(chunk) => chunk,
+                        None => self.create_chunk(device, self.chunk_size, false)?,
+                    }
+                } else {
+                    self.create_chunk(device, size, true)?
+                };
+                self.active.push(chunk);
+                self.active.len() - 1
+            }
+        };
+
+        let chunk = &mut self.active[index];
+        let offset = align(chunk.offset, alignment);
+        ptr::copy_nonoverlapping(data.as_ptr(), chunk.ptr.add(offset as usize), data.len());
+        chunk.offset = offset + size;
+        Ok((chunk, offset))
+    }
+
+    unsafe fn create_chunk(
+        &self,
+        device: &B::Device,
+        size: u64,
+        dedicated: bool,
+    ) -> Result<Chunk<B>, Error> {
+        let mut buffer = device.create_buffer(size, buffer::Usage::TRANSFER_SRC)?;
+        let requirements = device.get_buffer_requirements(&buffer);
+
+        // Prefer coherent memory, which doesn't need to be flushed.
+        let find_type = |properties: memory::Properties| {
+            self.memory_types.iter().enumerate().position(|(id, ty)| {
+                requirements.type_mask & (1 << id) != 0 && ty.properties.contains(properties)
+            })
+        };
+        let (type_id, coherent) =
+            match find_type(memory::Properties::CPU_VISIBLE | memory::Properties::COHERENT) {
+                Some(id) => (id, true),
+                None => match find_type(memory::Properties::CPU_VISIBLE) {
+                    Some(id) => (id, false),
+                    None => {
+                        device.destroy_buffer(buffer);
+                        return Err(Error::NoMemoryType);
+                    }
+                },
+            };
+
+        let memory_size = align(requirements.size, self.non_coherent_atom_size);
+        log::debug!("Allocating a staging chunk of {} bytes", memory_size);
+        let mut memory = match device.allocate_memory(MemoryTypeId(type_id), memory_size) {
+            Ok(memory) => memory,
+            Err(err) => {
+                device.destroy_buffer(buffer);
+                return Err(err.into());
+            }
+        };
+        let result = device
+            .bind_buffer_memory(&memory, 0, &mut buffer)
+            .map_err(Error::from)
+            .and_then(|()| {
+                device
+                    .map_memory(&mut memory, memory::Segment::ALL)
+                    .map_err(Error::from)
+            });
+        match result {
+            Ok(ptr) => Ok(Chunk {
+                memory,
+                buffer,
+                ptr,
+                size,
+                offset: 0,
+                coherent,
+                dedicated,
+            }),
+            Err(err) => {
+                device.destroy_buffer(buffer);
+                device.free_memory(memory);
+                Err(err)
+            }
+        }
+    }
+
+    unsafe fn destroy_chunk(device: &B::Device, mut chunk: Chunk<B>) {
+        device.unmap_memory(&mut chunk.memory);
+        device.destroy_buffer(chunk.buffer);
+        device.free_memory(chunk.memory);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gfx_backend_software as software;
+    use hal::{
+        adapter::PhysicalDevice as _,
+        pool::CommandPool as _,
+        queue::{Queue as _, QueueGroup},
+        Instance as _,
+    };
+
+    type B = software::Backend;
+
+    struct Context {
+        device: <B as Backend>::Device,
+        queue_group: QueueGroup<B>,
+        pool: <B as Backend>::CommandPool,
+        belt: StagingBelt<B>,
+    }
+
+    impl Context {
+        fn new(chunk_size: u64) -> Self {
+            let instance = software::Instance::create("upload", 1).unwrap();
+            let adapter = instance.enumerate_adapters().remove(0);
+            let family = &adapter.queue_families[0];
+            let mut gpu = unsafe {
+                adapter
+                    .physical_device
+                    .open(&[(family, &[1.0])], hal::Features::empty())
+                    .unwrap()
+            };
+            let queue_group = gpu.queue_groups.pop().unwrap();
+            let pool = unsafe {
+                gpu.device
+                    .create_command_pool(
+                        queue_group.family,
+                        hal::pool::CommandPoolCreateFlags::empty(),
+                    )
+                    .unwrap()
+            };
+            let belt = StagingBelt::new(
+                &adapter.physical_device.memory_properties(),
+                &adapter.physical_device.limits(),
+                chunk_size,
+            );
+            Context {
+                device: gpu.device,
+                queue_group,
+                pool,
+                belt,
+            }
+        }
+
+        /// Create a buffer of `size` bytes bound to host visible memory.
+        unsafe fn create_buffer(
+            &self,
+            size: u64,
+        ) -> (<B as Backend>::Buffer, <B as Backend>::Memory) {
+            let mut buffer = self
+                .device
+                .create_buffer(size, buffer::Usage::TRANSFER_DST)
+                .unwrap();
+            let memory = self.device.allocate_memory(MemoryTypeId(0), size).unwrap();
+            self.device
+                .bind_buffer_memory(&memory, 0, &mut buffer)
+                .unwrap();
+            (buffer, memory)
+        }
+
+        /// Upload each of `uploads` into `dst` and finish the batch.
+        ///
+        /// The batch is submitted with its fence if `signal` is set, leaving
+        /// the fence unsignaled otherwise.
+        unsafe fn upload(
+            &mut self,
+            dst: &<B as Backend>::Buffer,
+            uploads: &[(buffer::Offset, &[u8])],
+            signal: bool,
+        ) {
+            let mut cmd_buffer = self.pool.allocate_one(command::Level::Primary);
+            cmd_buffer.begin_primary(command::CommandBufferFlags::ONE_TIME_SUBMIT);
+            for &(offset, data) in uploads {
+                self.belt
+                    .upload_buffer(&self.device, &mut cmd_buffer, dst, offset, data)
+                    .unwrap();
+            }
+            cmd_buffer.finish();
+            let fence = self.belt.finish(&self.device).unwrap();
+            self.queue_group.queues[0]
+                .submit(
+                    iter::once(&cmd_buffer),
+                    iter::empty(),
+                    iter::empty(),
+                    if signal { Some(fence) } else { None },
+                )
+                .unwrap();
+            self.pool.free(iter::once(cmd_buffer));
+        }
+
+        unsafe fn read(&self, memory: &mut <B as Backend>::Memory, size: usize) -> Vec<u8> {
+            let ptr = self
+                .device
+                .map_memory(memory, memory::Segment::ALL)
+                .unwrap();
+            let data = std::slice::from_raw_parts(ptr, size).to_vec();
+            self.device.unmap_memory(memory);
+            data
+        }
+    }
+
+    #[test]
+    fn upload_buffer() {
+        let mut context = Context::new(64);
+        unsafe {
+            let (dst, mut memory) = context.create_buffer(16);
+            context.upload(&dst, &[(0, &[1, 2, 3, 4]), (8, &[5, 6, 7, 8])], true);
+            assert_eq!(
+                context.read(&mut memory, 16),
+                vec![1, 2, 3, 4, 0, 0, 0, 0, 5, 6, 7, 8, 0, 0, 0, 0]
+            );
+        }
+    }
+
+    #[test]
+    fn recycle_chunks() {
+        let mut context = Context::new(64);
+        unsafe {
+            let (dst, _memory) = context.create_buffer(256);
+            context.upload(&dst, &[(0, &[1; 48]), (64, &[2; 48])], true);
+            let stats = context.belt.stats();
+            assert_eq!(stats.chunks, 2);
+            assert_eq!(stats.pending_batches, 1);
+
+            context.belt.recall(&context.device).unwrap();
+            assert_eq!(
+                context.belt.stats(),
+                Stats {
+                    chunks: 2,
+                    allocated: 128,
+                    pending_batches: 0,
+                }
+            );
+
+            // The recycled chunks are reused instead of allocating new ones.
+            context.upload(&dst, &[(0, &[3; 32]), (32, &[4; 32])], true);
+            assert_eq!(context.belt.stats().chunks, 2);
+            context.belt.recall(&context.device).unwrap();
+
+            context.belt.shrink(&context.device);
+            assert_eq!(context.belt.stats(), Stats::default());
+        }
+    }
+
+    #[test]
+    fn free_dedicated_chunks() {
+        let mut context = Context::new(64);
+        unsafe {
+            let (dst, mut memory) = context.create_buffer(256);
+            context.upload(&dst, &[(0, &[7; 256])], true);
+            assert_eq!(context.belt.stats().allocated, 256);
+            assert_eq!(context.read(&mut memory, 256), vec![7; 256]);
+
+            context.belt.recall(&context.device).unwrap();
+            assert_eq!(context.belt.stats(), Stats::default());
+        }
+    }
+
+    #[test]
+    fn recall_in_order() {
+        let mut context = Context::new(64);
+        unsafe {
+            let (dst, _memory) = context.create_buffer(64);
+            context.upload(&dst, &[(0, &[1; 4])], false);
+            context.upload(&dst, &[(0, &[2; 4])], true);
+            context.upload(&dst, &[(0, &[3; 4])], true);
+
+            // The signaled batches wait for the first one.
+            context.belt.recall(&context.device).unwrap();
+            assert_eq!(context.belt.stats().pending_batches, 3);
+
+            context.queue_group.queues[0]
+                .submit(
+                    iter::empty(),
+                    iter::empty(),
+                    iter::empty(),
+                    Some(&mut context.belt.batches[0].fence),
+                )
+                .unwrap();
+            context.belt.recall(&context.device).unwrap();
+            assert_eq!(context.belt.stats().pending_batches, 0);
+            assert_eq!(context.belt.free.len(), 3);
+            assert_eq!(context.belt.free_fences.len(), 3);
+        }
+    }
+}
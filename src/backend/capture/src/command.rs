@@ -0,0 +1,1152 @@
+use crate::{
+    trace::{self, Action, Command, ObjectKind},
+    Backend, Handle, Image, Shared,
+};
+
+use hal::{buffer, command, image, memory, pool, pso, query};
+
+use std::{ops::Range, sync::Arc};
+
+fn map_rendering_attachment<'a, B: hal::Backend>(
+    info: &command::RenderingAttachmentInfo<'a, Backend<B>>,
+) -> command::RenderingAttachmentInfo<'a, B> {
+    command::RenderingAttachmentInfo {
+        image_view: info.image_view.raw.get(),
+        desc: info.desc.clone(),
+        clear_value: info.clear_value,
+    }
+}
+
+fn map_barrier<'a, B: hal::Backend>(
+    barrier: memory::Barrier<'a, Backend<B>>,
+) -> (memory::Barrier<'a, B>, trace::Barrier) {
+    match barrier {
+        memory::Barrier::AllBuffers(access) => (
+            memory::Barrier::AllBuffers(access.clone()),
+            trace::Barrier::AllBuffers(access),
+        ),
+        memory::Barrier::AllImages(access) => (
+            memory::Barrier::AllImages(access.clone()),
+            trace::Barrier::AllImages(access),
+        ),
+        memory::Barrier::Buffer {
+            states,
+            target,
+            range,
+            families,
+        } => (
+            memory::Barrier::Buffer {
+                states: states.clone(),
+                target: &target.raw,
+                range: range.clone(),
+                families: families.clone(),
+            },
+            trace::Barrier::Buffer {
+                states,
+                target: target.id,
+                range,
+                families,
+            },
+        ),
+        memory::Barrier::Image {
+            states,
+            target,
+            range,
+            families,
+        } => (
+            memory::Barrier::Image {
+                states: states.clone(),
+                target: target.raw.get(),
+                range: range.clone(),
+                families: families.clone(),
+            },
+            trace::Barrier::Image {
+                states,
+                target: target.id,
+                range,
+                families,
+            },
+        ),
+        memory::Barrier::Aliasing { before, after } => (
+            memory::Barrier::Aliasing {
+                before: before.as_ref().map(map_aliased_resource),
+                after: after.as_ref().map(map_aliased_resource),
+            },
+            trace::Barrier::Aliasing {
+                before: before.as_ref().map(record_aliased_resource),
+                after: after.as_ref().map(record_aliased_resource),
+            },
+        ),
+    }
+}
+
+fn map_aliased_resource<'a, B: hal::Backend>(
+    resource: &memory::AliasedResource<'a, Backend<B>>,
+) -> memory::AliasedResource<'a, B> {
+    match *resource {
+        memory::AliasedResource::Buffer(buffer) => memory::AliasedResource::Buffer(&buffer.raw),
+        memory::AliasedResource::Image(image) => memory::AliasedResource::Image(image.raw.get()),
+    }
+}
+
+fn record_aliased_resource<B: hal::Backend>(
+    resource: &memory::AliasedResource<Backend<B>>,
+) -> trace::AliasedResource {
+    match *resource {
+        memory::AliasedResource::Buffer(buffer) => trace::AliasedResource::Buffer(buffer.id),
+        memory::AliasedResource::Image(image) => trace::AliasedResource::Image(image.id),
+    }
+}
+
+fn map_query<'a, B: hal::Backend>(
+    query: query::Query<'a, Backend<B>>,
+) -> (query::Query<'a, B>, trace::Query) {
+    (
+        query::Query {
+            pool: &query.pool.raw,
+            id: query.id,
+        },
+        (query.pool.id, query.id),
+    )
+}
+
+#[derive(Debug)]
+pub struct CommandPool<B: hal::Backend> {
+    pub(crate) raw: B::CommandPool,
+    pub(crate) id: trace::Id,
+    pub(crate) shared: Arc<Shared>,
+}
+
+impl<B: hal::Backend> CommandPool<B> {
+    fn wrap(&self, raw: B::CommandBuffer) -> CommandBuffer<B> {
+        CommandBuffer {
+            raw,
+            id: self.shared.next_id(ObjectKind::CommandBuffer),
+            shared: Arc::clone(&self.shared),
+            flags: command::CommandBufferFlags::empty(),
+            inheritance: None,
+            commands: Vec::new(),
+            content_hash: None,
+        }
+    }
+}
+
+impl<B: hal::Backend> pool::CommandPool<Backend<B>> for CommandPool<B> {
+    unsafe fn reset(&mut self, release_resources: bool) {
+        self.shared.add(Action::ResetCommandPool(self.id));
+        self.raw.reset(release_resources);
+    }
+
+    unsafe fn allocate_one(&mut self, level: command::Level) -> CommandBuffer<B> {
+        let raw = self.raw.allocate_one(level);
+        let cmd_buf = self.wrap(raw);
+        self.shared.add(Action::AllocateCommandBuffers {
+            pool: self.id,
+            level,
+            ids: vec![cmd_buf.id],
+        });
+        cmd_buf
+    }
+
+    unsafe fn allocate<E>(&mut self, num: usize, level: command::Level, list: &mut E)
+    where
+        E: Extend<CommandBuffer<B>>,
+    {
+        let mut raw_list = Vec::with_capacity(num);
+        self.raw.allocate(num, level, &mut raw_list);
+        let cmd_bufs = raw_list
+            .into_iter()
+            .map(|raw| self.wrap(raw))
+            .collect::<Vec<_>>();
+        self.shared.add(Action::AllocateCommandBuffers {
+            pool: self.id,
+            level,
+            ids: cmd_bufs.iter().map(|cmd_buf| cmd_buf.id).collect(),
+        });
+        list.extend(cmd_bufs);
+    }
+
+    unsafe fn free<I>(&mut self, buffers: I)
+    where
+        I: Iterator<Item = CommandBuffer<B>>,
+    {
+        // Collected first, since the inner pool may not consume the iterator.
+        let (ids, raw): (Vec<_>, Vec<_>) = buffers.map(|cmd_buf| (cmd_buf.id, cmd_buf.raw)).unzip();
+        self.raw.free(raw.into_iter());
+        self.shared
+            .add(Action::FreeCommandBuffers { pool: self.id, ids });
+    }
+}
+
+/// Command buffer that keeps the commands recorded since `begin`,
+/// and adds them to the trace as a whole at `finish`.
+#[derive(Debug)]
+pub struct CommandBuffer<B: hal::Backend> {
+    pub(crate) raw: B::CommandBuffer,
+    pub(crate) id: trace::Id,
+    shared: Arc<Shared>,
+    flags: command::CommandBufferFlags,
+    inheritance: Option<trace::InheritanceInfo>,
+    commands: Vec<Command>,
+    content_hash: Option<u64>,
+}
+
+impl<B: hal::Backend> CommandBuffer<B> {
+    /// Hash of the commands of the last finished recording.
+    ///
+    /// Only computed in the deterministic mode, see `Instance::set_deterministic`.
+    /// Returns `None` otherwise, or if the command buffer is not finished.
+    pub fn content_hash(&self) -> Option<u64> {
+        self.content_hash
+    }
+}
+
+impl<B: hal::Backend> command::CommandBuffer<Backend<B>> for CommandBuffer<B> {
+    unsafe fn begin(
+        &mut self,
+        flags: command::CommandBufferFlags,
+        inheritance_info: command::CommandBufferInheritanceInfo<Backend<B>>,
+    ) {
+        self.flags = flags;
+        self.commands.clear();
+        self.content_hash = None;
+        self.inheritance = Some(trace::InheritanceInfo {
+            subpass: inheritance_info
+                .subpass
+                .as_ref()
+                .map(|subpass| (subpass.main_pass.id, subpass.index)),
+            framebuffer: inheritance_info
+                .framebuffer
+                .map(|framebuffer| framebuffer.id),
+            occlusion_query_enable: inheritance_info.occlusion_query_enable,
+            occlusion_query_flags: inheritance_info.occlusion_query_flags,
+            pipeline_statistics: inheritance_info.pipeline_statistics,
+        });
+        self.raw.begin(
+            flags,
+            command::CommandBufferInheritanceInfo {
+                subpass: inheritance_info.subpass.map(|subpass| hal::pass::Subpass {
+                    index: subpass.index,
+                    main_pass: &subpass.main_pass.raw,
+                }),
+                framebuffer: inheritance_info
+                    .framebuffer
+                    .map(|framebuffer| &framebuffer.raw),
+                occlusion_query_enable: inheritance_info.occlusion_query_enable,
+                occlusion_query_flags: inheritance_info.occlusion_query_flags,
+                pipeline_statistics: inheritance_info.pipeline_statistics,
+            },
+        );
+    }
+
+    unsafe fn finish(&mut self) {
+        match self.inheritance.take() {
+            Some(inheritance) => {
+                if self.shared.is_deterministic() {
+                    self.content_hash = Some(trace::hash_commands(&self.commands));
+                }
+                self.shared.add(Action::RecordCommandBuffer {
+                    id: self.id,
+                    flags: self.flags,
+                    inheritance,
+                    commands: self.commands.drain(..).collect(),
+                    hash: self.content_hash,
+                })
+            }
+            None => warn!("Command buffer is finished without beginning"),
+        }
+        self.raw.finish();
+    }
+
+    unsafe fn reset(&mut self, release_resources: bool) {
+        self.inheritance = None;
+        self.commands.clear();
+        self.content_hash = None;
+        self.raw.reset(release_resources);
+    }
+
+    unsafe fn pipeline_barrier<'a, T>(
+        &mut self,
+        stages: Range<pso::PipelineStage>,
+        dependencies: memory::Dependencies,
+        barriers: T,
+    ) where
+        T: Iterator<Item = memory::Barrier<'a, Backend<B>>>,
+    {
+        let (raw_barriers, barriers): (Vec<_>, Vec<_>) = barriers.map(map_barrier).unzip();
+        self.raw
+            .pipeline_barrier(stages.clone(), dependencies, raw_barriers.into_iter());
+        self.commands.push(Command::PipelineBarrier {
+            stages,
+            dependencies,
+            barriers,
+        });
+    }
+
+    unsafe fn begin_barrier<'a, T>(
+        &mut self,
+        event: &Handle<B::Event>,
+        stages: Range<pso::PipelineStage>,
+        barriers: T,
+    ) where
+        T: Iterator<Item = memory::Barrier<'a, Backend<B>>>,
+    {
+        let (raw_barriers, barriers): (Vec<_>, Vec<_>) = barriers.map(map_barrier).unzip();
+        self.raw
+            .begin_barrier(&event.raw, stages.clone(), raw_barriers.into_iter());
+        self.commands.push(Command::BeginBarrier {
+            event: event.id,
+            stages,
+            barriers,
+        });
+    }
+
+    unsafe fn end_barrier<'a, T>(
+        &mut self,
+        event: &Handle<B::Event>,
+        stages: Range<pso::PipelineStage>,
+        barriers: T,
+    ) where
+        T: Iterator<Item = memory::Barrier<'a, Backend<B>>>,
+    {
+        let (raw_barriers, barriers): (Vec<_>, Vec<_>) = barriers.map(map_barrier).unzip();
+        self.raw
+            .end_barrier(&event.raw, stages.clone(), raw_barriers.into_iter());
+        self.commands.push(Command::EndBarrier {
+            event: event.id,
+            stages,
+            barriers,
+        });
+    }
+
+    unsafe fn fill_buffer(
+        &mut self,
+        buffer: &Handle<B::Buffer>,
+        range: buffer::SubRange,
+        data: u32,
+    ) {
+        self.raw.fill_buffer(&buffer.raw, range.clone(), data);
+        self.commands.push(Command::FillBuffer {
+            buffer: buffer.id,
+            range,
+            data,
+        });
+    }
+
+    unsafe fn update_buffer(
+        &mut self,
+        buffer: &Handle<B::Buffer>,
+        offset: buffer::Offset,
+        data: &[u8],
+    ) {
+        self.raw.update_buffer(&buffer.raw, offset, data);
+        self.commands.push(Command::UpdateBuffer {
+            buffer: buffer.id,
+            offset,
+            data: data.to_vec(),
+        });
+    }
+
+    unsafe fn clear_image<T>(
+        &mut self,
+        image: &Image<B>,
+        layout: image::Layout,
+        value: command::ClearValue,
+        subresource_ranges: T,
+    ) where
+        T: Iterator<Item = image::SubresourceRange>,
+    {
+        let ranges = subresource_ranges.collect::<Vec<_>>();
+        self.raw
+            .clear_image(image.raw.get(), layout, value, ranges.iter().cloned());
+        self.commands.push(Command::ClearImage {
+            image: image.id,
+            layout,
+            value: trace::clear_value_bits(value),
+            ranges,
+        });
+    }
+
+    unsafe fn clear_attachments<T, U>(&mut self, clears: T, rects: U)
+    where
+        T: Iterator<Item = command::AttachmentClear>,
+        U: Iterator<Item = pso::ClearRect>,
+    {
+        let clears = clears.collect::<Vec<_>>();
+        let rects = rects.collect::<Vec<_>>();
+        self.raw
+            .clear_attachments(clears.iter().cloned(), rects.iter().cloned());
+        self.commands.push(Command::ClearAttachments {
+            clears: clears
+                .into_iter()
+                .map(|clear| match clear {
+                    command::AttachmentClear::Color { index, value } => {
+                        trace::AttachmentClear::Color {
+                            index,
+                            value: value.uint32,
+                        }
+                    }
+                    command::AttachmentClear::DepthStencil { depth, stencil } => {
+                        trace::AttachmentClear::DepthStencil { depth, stencil }
+                    }
+                })
+                .collect(),
+            rects,
+        });
+    }
+
+    unsafe fn resolve_image<T>(
+        &mut self,
+        src: &Image<B>,
+        src_layout: image::Layout,
+        dst: &Image<B>,
+        dst_layout: image::Layout,
+        regions: T,
+    ) where
+        T: Iterator<Item = command::ImageResolve>,
+    {
+        let regions = regions.collect::<Vec<_>>();
+        self.raw.resolve_image(
+            src.raw.get(),
+            src_layout,
+            dst.raw.get(),
+            dst_layout,
+            regions.iter().cloned(),
+        );
+        self.commands.push(Command::ResolveImage {
+            src: src.id,
+            src_layout,
+            dst: dst.id,
+            dst_layout,
+            regions,
+        });
+    }
+
+    unsafe fn blit_image<T>(
+        &mut self,
+        src: &Image<B>,
+        src_layout: image::Layout,
+        dst: &Image<B>,
+        dst_layout: image::Layout,
+        filter: image::Filter,
+        regions: T,
+    ) where
+        T: Iterator<Item = command::ImageBlit>,
+    {
+        let regions = regions.collect::<Vec<_>>();
+        self.raw.blit_image(
+            src.raw.get(),
+            src_layout,
+            dst.raw.get(),
+            dst_layout,
+            filter,
+            regions.iter().cloned(),
+        );
+        self.commands.push(Command::BlitImage {
+            src: src.id,
+            src_layout,
+            dst: dst.id,
+            dst_layout,
+            filter,
+            regions,
+        });
+    }
+
+    unsafe fn bind_index_buffer(
+        &mut self,
+        buffer: &Handle<B::Buffer>,
+        sub: buffer::SubRange,
+        ty: hal::IndexType,
+    ) {
+        self.raw.bind_index_buffer(&buffer.raw, sub.clone(), ty);
+        self.commands.push(Command::BindIndexBuffer {
+            buffer: buffer.id,
+            range: sub,
+            index_type: ty,
+        });
+    }
+
+    unsafe fn bind_vertex_buffers<'a, T>(&mut self, first_binding: pso::BufferIndex, buffers: T)
+    where
+        T: Iterator<Item = (&'a Handle<B::Buffer>, buffer::SubRange)>,
+    {
+        let buffers = buffers.collect::<Vec<_>>();
+        self.raw.bind_vertex_buffers(
+            first_binding,
+            buffers
+                .iter()
+                .map(|&(buffer, ref sub)| (&buffer.raw, sub.clone())),
+        );
+        self.commands.push(Command::BindVertexBuffers {
+            first_binding,
+            buffers: buffers
+                .into_iter()
+                .map(|(buffer, sub)| (buffer.id, sub))
+                .collect(),
+        });
+    }
+
+    unsafe fn set_viewports<T>(&mut self, first_viewport: u32, viewports: T)
+    where
+        T: Iterator<Item = pso::Viewport>,
+    {
+        let viewports = viewports.collect::<Vec<_>>();
+        self.raw
+            .set_viewports(first_viewport, viewports.iter().cloned());
+        self.commands.push(Command::SetViewports {
+            first_viewport,
+            viewports,
+        });
+    }
+
+    unsafe fn set_scissors<T>(&mut self, first_scissor: u32, rects: T)
+    where
+        T: Iterator<Item = pso::Rect>,
+    {
+        let rects = rects.collect::<Vec<_>>();
+        self.raw.set_scissors(first_scissor, rects.iter().cloned());
+        self.commands.push(Command::SetScissors {
+            first_scissor,
+            rects,
+        });
+    }
+
+    unsafe fn set_stencil_reference(&mut self, faces: pso::Face, value: pso::StencilValue) {
+        self.raw.set_stencil_reference(faces, value);
+        self.commands
+            .push(Command::SetStencilReference { faces, value });
+    }
+
+    unsafe fn set_stencil_read_mask(&mut self, faces: pso::Face, value: pso::StencilValue) {
+        self.raw.set_stencil_read_mask(faces, value);
+        self.commands
+            .push(Command::SetStencilReadMask { faces, value });
+    }
+
+    unsafe fn set_stencil_write_mask(&mut self, faces: pso::Face, value: pso::StencilValue) {
+        self.raw.set_stencil_write_mask(faces, value);
+        self.commands
+            .push(Command::SetStencilWriteMask { faces, value });
+    }
+
+    unsafe fn set_blend_constants(&mut self, color: pso::ColorValue) {
+        self.raw.set_blend_constants(color);
+        self.commands.push(Command::SetBlendConstants(color));
+    }
+
+    unsafe fn set_depth_bounds(&mut self, bounds: Range<f32>) {
+        self.raw.set_depth_bounds(bounds.clone());
+        self.commands.push(Command::SetDepthBounds(bounds));
+    }
+
+    unsafe fn set_line_width(&mut self, width: f32) {
+        self.raw.set_line_width(width);
+        self.commands.push(Command::SetLineWidth(width));
+    }
+
+    unsafe fn set_depth_bias(&mut self, depth_bias: pso::DepthBias) {
+        self.raw.set_depth_bias(depth_bias);
+        self.commands.push(Command::SetDepthBias(depth_bias));
+    }
+
+    unsafe fn set_shading_rate(
+        &mut self,
+        rate: pso::ShadingRate,
+        combiners: [pso::ShadingRateCombiner; 2],
+    ) {
+        self.raw.set_shading_rate(rate, combiners);
+        self.commands
+            .push(Command::SetShadingRate { rate, combiners });
+    }
+
+    unsafe fn set_sample_locations(
+        &mut self,
+        samples: image::NumSamples,
+        locations: &[pso::SampleLocation],
+    ) {
+        self.raw.set_sample_locations(samples, locations);
+        self.commands.push(Command::SetSampleLocations {
+            samples,
+            locations: locations.to_vec(),
+        });
+    }
+
+    unsafe fn begin_render_pass<'a, T>(
+        &mut self,
+        render_pass: &Handle<B::RenderPass>,
+        framebuffer: &Handle<B::Framebuffer>,
+        render_area: pso::Rect,
+        attachments: T,
+        first_subpass: command::SubpassContents,
+    ) where
+        T: Iterator<Item = command::RenderAttachmentInfo<'a, Backend<B>>>,
+    {
+        let attachments = attachments.collect::<Vec<_>>();
+        self.raw.begin_render_pass(
+            &render_pass.raw,
+            &framebuffer.raw,
+            render_area,
+            attachments
+                .iter()
+                .map(|info| command::RenderAttachmentInfo {
+                    image_view: info.image_view.raw.get(),
+                    clear_value: info.clear_value,
+                }),
+            first_subpass,
+        );
+        self.commands.push(Command::BeginRenderPass {
+            render_pass: render_pass.id,
+            framebuffer: framebuffer.id,
+            render_area,
+            attachments: attachments
+                .iter()
+                .map(|info| {
+                    (
+                        info.image_view.id,
+                        trace::clear_value_bits(info.clear_value),
+                    )
+                })
+                .collect(),
+            first_subpass,
+        });
+    }
+
+    unsafe fn next_subpass(&mut self, contents: command::SubpassContents) {
+        self.raw.next_subpass(contents);
+        self.commands.push(Command::NextSubpass(contents));
+    }
+
+    unsafe fn end_render_pass(&mut self) {
+        self.raw.end_render_pass();
+        self.commands.push(Command::EndRenderPass);
+    }
+
+    unsafe fn begin_rendering(&mut self, info: &command::RenderingInfo<Backend<B>>) {
+        let colors = info
+            .colors
+            .iter()
+            .map(map_rendering_attachment)
+            .collect::<Vec<_>>();
+        let depth_stencil = info.depth_stencil.map(map_rendering_attachment);
+        self.raw.begin_rendering(&command::RenderingInfo {
+            render_area: info.render_area,
+            layers: info.layers,
+            colors: &colors,
+            depth_stencil: depth_stencil.as_ref(),
+            flags: info.flags,
+        });
+        self.commands.push(Command::BeginRendering {
+            render_area: info.render_area,
+            layers: info.layers,
+            colors: info
+                .colors
+                .iter()
+                .map(trace::RenderingAttachment::from)
+                .collect(),
+            depth_stencil: info.depth_stencil.map(trace::RenderingAttachment::from),
+            flags: info.flags,
+        });
+    }
+
+    unsafe fn end_rendering(&mut self) {
+        self.raw.end_rendering();
+        self.commands.push(Command::EndRendering);
+    }
+
+    unsafe fn bind_graphics_pipeline(&mut self, pipeline: &Handle<B::GraphicsPipeline>) {
+        self.raw.bind_graphics_pipeline(&pipeline.raw);
+        self.commands
+            .push(Command::BindGraphicsPipeline(pipeline.id));
+    }
+
+    unsafe fn bind_graphics_descriptor_sets<'a, I, J>(
+        &mut self,
+        layout: &Handle<B::PipelineLayout>,
+        first_set: usize,
+        sets: I,
+        offsets: J,
+    ) where
+        I: Iterator<Item = &'a Handle<B::DescriptorSet>>,
+        J: Iterator<Item = command::DescriptorSetOffset>,
+    {
+        let sets = sets.collect::<Vec<_>>();
+        let offsets = offsets.collect::<Vec<_>>();
+        self.raw.bind_graphics_descriptor_sets(
+            &layout.raw,
+            first_set,
+            sets.iter().map(|set| &set.raw),
+            offsets.iter().cloned(),
+        );
+        self.commands.push(Command::BindGraphicsDescriptorSets {
+            layout: layout.id,
+            first_set,
+            sets: sets.iter().map(|set| set.id).collect(),
+            offsets,
+        });
+    }
+
+    unsafe fn bind_compute_pipeline(&mut self, pipeline: &Handle<B::ComputePipeline>) {
+        self.raw.bind_compute_pipeline(&pipeline.raw);
+        self.commands
+            .push(Command::BindComputePipeline(pipeline.id));
+    }
+
+    unsafe fn bind_compute_descriptor_sets<'a, I, J>(
+        &mut self,
+        layout: &Handle<B::PipelineLayout>,
+        first_set: usize,
+        sets: I,
+        offsets: J,
+    ) where
+        I: Iterator<Item = &'a Handle<B::DescriptorSet>>,
+        J: Iterator<Item = command::DescriptorSetOffset>,
+    {
+        let sets = sets.collect::<Vec<_>>();
+        let offsets = offsets.collect::<Vec<_>>();
+        self.raw.bind_compute_descriptor_sets(
+            &layout.raw,
+            first_set,
+            sets.iter().map(|set| &set.raw),
+            offsets.iter().cloned(),
+        );
+        self.commands.push(Command::BindComputeDescriptorSets {
+            layout: layout.id,
+            first_set,
+            sets: sets.iter().map(|set| set.id).collect(),
+            offsets,
+        });
+    }
+
+    unsafe fn dispatch(&mut self, count: hal::WorkGroupCount) {
+        self.raw.dispatch(count);
+        self.commands.push(Command::Dispatch(count));
+    }
+
+    unsafe fn dispatch_indirect(&mut self, buffer: &Handle<B::Buffer>, offset: buffer::Offset) {
+        self.raw.dispatch_indirect(&buffer.raw, offset);
+        self.commands.push(Command::DispatchIndirect {
+            buffer: buffer.id,
+            offset,
+        });
+    }
+
+    unsafe fn copy_buffer<T>(
+        &mut self,
+        src: &Handle<B::Buffer>,
+        dst: &Handle<B::Buffer>,
+        regions: T,
+    ) where
+        T: Iterator<Item = command::BufferCopy>,
+    {
+        let regions = regions.collect::<Vec<_>>();
+        self.raw
+            .copy_buffer(&src.raw, &dst.raw, regions.iter().cloned());
+        self.commands.push(Command::CopyBuffer {
+            src: src.id,
+            dst: dst.id,
+            regions,
+        });
+    }
+
+    unsafe fn copy_image<T>(
+        &mut self,
+        src: &Image<B>,
+        src_layout: image::Layout,
+        dst: &Image<B>,
+        dst_layout: image::Layout,
+        regions: T,
+    ) where
+        T: Iterator<Item = command::ImageCopy>,
+    {
+        let regions = regions.collect::<Vec<_>>();
+        self.raw.copy_image(
+            src.raw.get(),
+            src_layout,
+            dst.raw.get(),
+            dst_layout,
+            regions.iter().cloned(),
+        );
+        self.commands.push(Command::CopyImage {
+            src: src.id,
+            src_layout,
+            dst: dst.id,
+            dst_layout,
+            regions,
+        });
+    }
+
+    unsafe fn copy_buffer_to_image<T>(
+        &mut self,
+        src: &Handle<B::Buffer>,
+        dst: &Image<B>,
+        dst_layout: image::Layout,
+        regions: T,
+    ) where
+        T: Iterator<Item = command::BufferImageCopy>,
+    {
+        let regions = regions.collect::<Vec<_>>();
+        self.raw
+            .copy_buffer_to_image(&src.raw, dst.raw.get(), dst_layout, regions.iter().cloned());
+        self.commands.push(Command::CopyBufferToImage {
+            src: src.id,
+            dst: dst.id,
+            dst_layout,
+            regions,
+        });
+    }
+
+    unsafe fn copy_image_to_buffer<T>(
+        &mut self,
+        src: &Image<B>,
+        src_layout: image::Layout,
+        dst: &Handle<B::Buffer>,
+        regions: T,
+    ) where
+        T: Iterator<Item = command::BufferImageCopy>,
+    {
+        let regions = regions.collect::<Vec<_>>();
+        self.raw
+            .copy_image_to_buffer(src.raw.get(), src_layout, &dst.raw, regions.iter().cloned());
+        self.commands.push(Command::CopyImageToBuffer {
+            src: src.id,
+            src_layout,
+            dst: dst.id,
+            regions,
+        });
+    }
+
+    unsafe fn draw(
+        &mut self,
+        vertices: Range<hal::VertexCount>,
+        instances: Range<hal::InstanceCount>,
+    ) {
+        self.raw.draw(vertices.clone(), instances.clone());
+        self.commands.push(Command::Draw {
+            vertices,
+            instances,
+        });
+    }
+
+    unsafe fn draw_indexed(
+        &mut self,
+        indices: Range<hal::IndexCount>,
+        base_vertex: hal::VertexOffset,
+        instances: Range<hal::InstanceCount>,
+    ) {
+        self.raw
+            .draw_indexed(indices.clone(), base_vertex, instances.clone());
+        self.commands.push(Command::DrawIndexed {
+            indices,
+            base_vertex,
+            instances,
+        });
+    }
+
+    unsafe fn draw_indirect(
+        &mut self,
+        buffer: &Handle<B::Buffer>,
+        offset: buffer::Offset,
+        draw_count: hal::DrawCount,
+        stride: buffer::Stride,
+    ) {
+        self.raw
+            .draw_indirect(&buffer.raw, offset, draw_count, stride);
+        self.commands.push(Command::DrawIndirect {
+            buffer: buffer.id,
+            offset,
+            draw_count,
+            stride,
+        });
+    }
+
+    unsafe fn draw_indexed_indirect(
+        &mut self,
+        buffer: &Handle<B::Buffer>,
+        offset: buffer::Offset,
+        draw_count: hal::DrawCount,
+        stride: buffer::Stride,
+    ) {
+        self.raw
+            .draw_indexed_indirect(&buffer.raw, offset, draw_count, stride);
+        self.commands.push(Command::DrawIndexedIndirect {
+            buffer: buffer.id,
+            offset,
+            draw_count,
+            stride,
+        });
+    }
+
+    unsafe fn draw_indirect_count(
+        &mut self,
+        buffer: &Handle<B::Buffer>,
+        offset: buffer::Offset,
+        count_buffer: &Handle<B::Buffer>,
+        count_buffer_offset: buffer::Offset,
+        max_draw_count: u32,
+        stride: buffer::Stride,
+    ) {
+        self.raw.draw_indirect_count(
+            &buffer.raw,
+            offset,
+            &count_buffer.raw,
+            count_buffer_offset,
+            max_draw_count,
+            stride,
+        );
+        self.commands.push(Command::DrawIndirectCount {
+            buffer: buffer.id,
+            offset,
+            count_buffer: count_buffer.id,
+            count_buffer_offset,
+            max_draw_count,
+            stride,
+        });
+    }
+
+    unsafe fn draw_indexed_indirect_count(
+        &mut self,
+        buffer: &Handle<B::Buffer>,
+        offset: buffer::Offset,
+        count_buffer: &Handle<B::Buffer>,
+        count_buffer_offset: buffer::Offset,
+        max_draw_count: u32,
+        stride: buffer::Stride,
+    ) {
+        self.raw.draw_indexed_indirect_count(
+            &buffer.raw,
+            offset,
+            &count_buffer.raw,
+            count_buffer_offset,
+            max_draw_count,
+            stride,
+        );
+        self.commands.push(Command::DrawIndexedIndirectCount {
+            buffer: buffer.id,
+            offset,
+            count_buffer: count_buffer.id,
+            count_buffer_offset,
+            max_draw_count,
+            stride,
+        });
+    }
+
+    unsafe fn draw_mesh_tasks(&mut self, task_count: hal::TaskCount, first_task: hal::TaskCount) {
+        self.raw.draw_mesh_tasks(task_count, first_task);
+        self.commands.push(Command::DrawMeshTasks {
+            task_count,
+            first_task,
+        });
+    }
+
+    unsafe fn draw_mesh_tasks_indirect(
+        &mut self,
+        buffer: &Handle<B::Buffer>,
+        offset: buffer::Offset,
+        draw_count: hal::DrawCount,
+        stride: buffer::Stride,
+    ) {
+        self.raw
+            .draw_mesh_tasks_indirect(&buffer.raw, offset, draw_count, stride);
+        self.commands.push(Command::DrawMeshTasksIndirect {
+            buffer: buffer.id,
+            offset,
+            draw_count,
+            stride,
+        });
+    }
+
+    unsafe fn draw_mesh_tasks_indirect_count(
+        &mut self,
+        buffer: &Handle<B::Buffer>,
+        offset: buffer::Offset,
+        count_buffer: &Handle<B::Buffer>,
+        count_buffer_offset: buffer::Offset,
+        max_draw_count: hal::DrawCount,
+        stride: buffer::Stride,
+    ) {
+        self.raw.draw_mesh_tasks_indirect_count(
+            &buffer.raw,
+            offset,
+            &count_buffer.raw,
+            count_buffer_offset,
+            max_draw_count,
+            stride,
+        );
+        self.commands.push(Command::DrawMeshTasksIndirectCount {
+            buffer: buffer.id,
+            offset,
+            count_buffer: count_buffer.id,
+            count_buffer_offset,
+            max_draw_count,
+            stride,
+        });
+    }
+
+    unsafe fn set_event(&mut self, event: &Handle<B::Event>, stages: pso::PipelineStage) {
+        self.raw.set_event(&event.raw, stages);
+        self.commands.push(Command::SetEvent {
+            event: event.id,
+            stages,
+        });
+    }
+
+    unsafe fn reset_event(&mut self, event: &Handle<B::Event>, stages: pso::PipelineStage) {
+        self.raw.reset_event(&event.raw, stages);
+        self.commands.push(Command::ResetEvent {
+            event: event.id,
+            stages,
+        });
+    }
+
+    unsafe fn wait_events<'a, I, J>(
+        &mut self,
+        events: I,
+        stages: Range<pso::PipelineStage>,
+        barriers: J,
+    ) where
+        I: Iterator<Item = &'a Handle<B::Event>>,
+        J: Iterator<Item = memory::Barrier<'a, Backend<B>>>,
+    {
+        let events = events.collect::<Vec<_>>();
+        let (raw_barriers, barriers): (Vec<_>, Vec<_>) = barriers.map(map_barrier).unzip();
+        self.raw.wait_events(
+            events.iter().map(|event| &event.raw),
+            stages.clone(),
+            raw_barriers.into_iter(),
+        );
+        self.commands.push(Command::WaitEvents {
+            events: events.iter().map(|event| event.id).collect(),
+            stages,
+            barriers,
+        });
+    }
+
+    unsafe fn begin_query(&mut self, query: query::Query<Backend<B>>, flags: query::ControlFlags) {
+        let (raw, query) = map_query(query);
+        self.raw.begin_query(raw, flags);
+        self.commands.push(Command::BeginQuery { query, flags });
+    }
+
+    unsafe fn end_query(&mut self, query: query::Query<Backend<B>>) {
+        let (raw, query) = map_query(query);
+        self.raw.end_query(raw);
+        self.commands.push(Command::EndQuery(query));
+    }
+
+    unsafe fn reset_query_pool(&mut self, pool: &Handle<B::QueryPool>, queries: Range<query::Id>) {
+        self.raw.reset_query_pool(&pool.raw, queries.clone());
+        self.commands.push(Command::ResetQueryPool {
+            pool: pool.id,
+            queries,
+        });
+    }
+
+    unsafe fn copy_query_pool_results(
+        &mut self,
+        pool: &Handle<B::QueryPool>,
+        queries: Range<query::Id>,
+        buffer: &Handle<B::Buffer>,
+        offset: buffer::Offset,
+        stride: buffer::Stride,
+        flags: query::ResultFlags,
+    ) {
+        self.raw.copy_query_pool_results(
+            &pool.raw,
+            queries.clone(),
+            &buffer.raw,
+            offset,
+            stride,
+            flags,
+        );
+        self.commands.push(Command::CopyQueryPoolResults {
+            pool: pool.id,
+            queries,
+            buffer: buffer.id,
+            offset,
+            stride,
+            flags,
+        });
+    }
+
+    unsafe fn write_timestamp(
+        &mut self,
+        stage: pso::PipelineStage,
+        query: query::Query<Backend<B>>,
+    ) {
+        let (raw, query) = map_query(query);
+        self.raw.write_timestamp(stage, raw);
+        self.commands.push(Command::WriteTimestamp { stage, query });
+    }
+
+    unsafe fn push_graphics_constants(
+        &mut self,
+        layout: &Handle<B::PipelineLayout>,
+        stages: pso::ShaderStageFlags,
+        offset: u32,
+        constants: &[u32],
+    ) {
+        self.raw
+            .push_graphics_constants(&layout.raw, stages, offset, constants);
+        self.commands.push(Command::PushGraphicsConstants {
+            layout: layout.id,
+            stages,
+            offset,
+            constants: constants.to_vec(),
+        });
+    }
+
+    unsafe fn push_compute_constants(
+        &mut self,
+        layout: &Handle<B::PipelineLayout>,
+        offset: u32,
+        constants: &[u32],
+    ) {
+        self.raw
+            .push_compute_constants(&layout.raw, offset, constants);
+        self.commands.push(Command::PushComputeConstants {
+            layout: layout.id,
+            offset,
+            constants: constants.to_vec(),
+        });
+    }
+
+    unsafe fn execute_commands<'a, T>(&mut self, cmd_buffers: T)
+    where
+        T: Iterator<Item = &'a CommandBuffer<B>>,
+    {
+        let cmd_buffers = cmd_buffers.collect::<Vec<_>>();
+        self.raw
+            .execute_commands(cmd_buffers.iter().map(|cmd_buf| &cmd_buf.raw));
+        self.commands.push(Command::ExecuteCommands(
+            cmd_buffers.iter().map(|cmd_buf| cmd_buf.id).collect(),
+        ));
+    }
+
+    unsafe fn insert_debug_marker(&mut self, name: &str, color: u32) {
+        self.raw.insert_debug_marker(name, color);
+        self.commands.push(Command::InsertDebugMarker {
+            name: name.to_string(),
+            color,
+        });
+    }
+
+    unsafe fn begin_debug_marker(&mut self, name: &str, color: u32) {
+        self.raw.begin_debug_marker(name, color);
+        self.commands.push(Command::BeginDebugMarker {
+            name: name.to_string(),
+            color,
+        });
+    }
+
+    unsafe fn end_debug_marker(&mut self) {
+        self.raw.end_debug_marker();
+        self.commands.push(Command::EndDebugMarker);
+    }
+
+    unsafe fn set_checkpoint(&mut self, label: &str) {
+        self.raw.set_checkpoint(label);
+        self.commands.push(Command::SetCheckpoint {
+            label: label.to_string(),
+        });
+    }
+}
@@ -0,0 +1,1320 @@
+use crate::{
+    trace::{self, Action, ObjectKind},
+    Backend, CommandBuffer, CommandPool, Handle, Image, ImageView, Memory, Shared, Slot,
+};
+
+use hal::{
+    buffer, command, device, format, image, memory, pass, pool, pso, query, queue::QueueFamilyId,
+    MemoryTypeId,
+};
+
+#[cfg(unix)]
+use std::os::unix::io::RawFd;
+use std::{ops::Range, sync::Arc};
+
+#[derive(Debug)]
+pub struct DescriptorPool<B: hal::Backend> {
+    raw: B::DescriptorPool,
+    id: trace::Id,
+    shared: Arc<Shared>,
+}
+
+impl<B: hal::Backend> pso::DescriptorPool<Backend<B>> for DescriptorPool<B> {
+    unsafe fn allocate_one(
+        &mut self,
+        layout: &Handle<B::DescriptorSetLayout>,
+    ) -> Result<Handle<B::DescriptorSet>, pso::AllocationError> {
+        let raw = self.raw.allocate_one(&layout.raw)?;
+        let id = self.shared.next_id(ObjectKind::DescriptorSet);
+        self.shared.add(Action::AllocateDescriptorSets {
+            pool: self.id,
+            layouts: vec![layout.id],
+            ids: vec![id],
+        });
+        Ok(Handle { raw, id })
+    }
+
+    unsafe fn allocate<'a, I, E>(
+        &mut self,
+        layouts: I,
+        list: &mut E,
+    ) -> Result<(), pso::AllocationError>
+    where
+        I: Iterator<Item = &'a Handle<B::DescriptorSetLayout>>,
+        E: Extend<Handle<B::DescriptorSet>>,
+    {
+        let layouts = layouts.collect::<Vec<_>>();
+        let mut raw_sets = Vec::with_capacity(layouts.len());
+        let result = self
+            .raw
+            .allocate(layouts.iter().map(|layout| &layout.raw), &mut raw_sets);
+        // Some of the sets may be allocated even if the call fails.
+        let ids = raw_sets
+            .iter()
+            .map(|_| self.shared.next_id(ObjectKind::DescriptorSet))
+            .collect::<Vec<_>>();
+        self.shared.add(Action::AllocateDescriptorSets {
+            pool: self.id,
+            layouts: layouts[..ids.len()]
+                .iter()
+                .map(|layout| layout.id)
+                .collect(),
+            ids: ids.clone(),
+        });
+        list.extend(
+            raw_sets
+                .into_iter()
+                .zip(ids)
+                .map(|(raw, id)| Handle { raw, id }),
+        );
+        result
+    }
+
+    unsafe fn free<I>(&mut self, descriptor_sets: I)
+    where
+        I: Iterator<Item = Handle<B::DescriptorSet>>,
+    {
+        // Collected first, since the inner pool may not consume the iterator.
+        let (ids, raw): (Vec<_>, Vec<_>) = descriptor_sets.map(|set| (set.id, set.raw)).unzip();
+        self.raw.free(raw.into_iter());
+        self.shared
+            .add(Action::FreeDescriptorSets { pool: self.id, ids });
+    }
+
+    unsafe fn reset(&mut self) {
+        self.shared.add(Action::ResetDescriptorPool(self.id));
+        self.raw.reset()
+    }
+}
+
+#[derive(Debug)]
+pub struct Device<B: hal::Backend> {
+    pub(crate) raw: B::Device,
+    pub(crate) shared: Arc<Shared>,
+}
+
+impl<B: hal::Backend> Device<B> {
+    pub(crate) fn new(raw: B::Device, shared: Arc<Shared>) -> Self {
+        Device { raw, shared }
+    }
+
+    /// Describe the creation state of a graphics or a compute pipeline: the descriptor,
+    /// the formats of the render pass attachments, and the hashes of the shaders.
+    ///
+    /// This is meant for crash handlers to report the pipeline that was bound when
+    /// the device got lost. Returns `None` if the handle isn't a live pipeline.
+    pub fn describe_pipeline<P>(&self, pipeline: &Handle<P>) -> Option<trace::PipelineDebugInfo> {
+        self.shared.pipeline_description(pipeline.id)
+    }
+
+    fn wrap<T>(&self, kind: ObjectKind, raw: T) -> Handle<T> {
+        Handle {
+            raw,
+            id: self.shared.next_id(kind),
+        }
+    }
+
+    fn wrap_memory(&self, raw: B::Memory, memory_type: MemoryTypeId, size: u64) -> Memory<B> {
+        let id = self.shared.next_id(ObjectKind::Memory);
+        self.shared.add(Action::AllocateMemory {
+            id,
+            properties: self.shared.memory_properties(memory_type),
+            size,
+        });
+        Memory { raw, id, size }
+    }
+
+    fn record_base_pipeline<P>(parent: &pso::BasePipeline<Handle<P>>) -> trace::BasePipeline {
+        match *parent {
+            pso::BasePipeline::Pipeline(pipeline) => trace::BasePipeline::Pipeline(pipeline.id),
+            pso::BasePipeline::Index(index) => trace::BasePipeline::Index(index),
+            pso::BasePipeline::None => trace::BasePipeline::None,
+        }
+    }
+
+    fn map_entry_point<'a>(
+        entry_point: &pso::EntryPoint<'a, Backend<B>>,
+    ) -> pso::EntryPoint<'a, B> {
+        pso::EntryPoint {
+            entry: entry_point.entry,
+            module: &entry_point.module.raw,
+            specialization: entry_point.specialization.clone(),
+        }
+    }
+
+    fn map_base_pipeline<'a, P>(
+        parent: &pso::BasePipeline<'a, Handle<P>>,
+    ) -> pso::BasePipeline<'a, P> {
+        match *parent {
+            pso::BasePipeline::Pipeline(pipeline) => pso::BasePipeline::Pipeline(&pipeline.raw),
+            pso::BasePipeline::Index(index) => pso::BasePipeline::Index(index),
+            pso::BasePipeline::None => pso::BasePipeline::None,
+        }
+    }
+}
+
+impl<B: hal::Backend> device::Device<Backend<B>> for Device<B> {
+    unsafe fn allocate_memory(
+        &self,
+        memory_type: MemoryTypeId,
+        size: u64,
+    ) -> Result<Memory<B>, device::AllocationError> {
+        let raw = self.raw.allocate_memory(memory_type, size)?;
+        Ok(self.wrap_memory(raw, memory_type, size))
+    }
+
+    unsafe fn allocate_memory_with_priority(
+        &self,
+        memory_type: MemoryTypeId,
+        size: u64,
+        priority: memory::Priority,
+    ) -> Result<Memory<B>, device::AllocationError> {
+        let raw = self
+            .raw
+            .allocate_memory_with_priority(memory_type, size, priority)?;
+        Ok(self.wrap_memory(raw, memory_type, size))
+    }
+
+    unsafe fn set_memory_priority(&self, memory: &Memory<B>, priority: memory::Priority) {
+        // Only a hint for the residency, which doesn't affect the replay.
+        self.raw.set_memory_priority(&memory.raw, priority)
+    }
+
+    unsafe fn allocate_dedicated_memory(
+        &self,
+        memory_type: MemoryTypeId,
+        size: u64,
+        resource: memory::DedicatedResource<Backend<B>>,
+    ) -> Result<Memory<B>, device::AllocationError> {
+        let raw_resource = match resource {
+            memory::DedicatedResource::Buffer(buffer) => {
+                memory::DedicatedResource::Buffer(&buffer.raw)
+            }
+            memory::DedicatedResource::Image(image) => {
+                memory::DedicatedResource::Image(image.raw.get())
+            }
+        };
+        let raw = self
+            .raw
+            .allocate_dedicated_memory(memory_type, size, raw_resource)?;
+        Ok(self.wrap_memory(raw, memory_type, size))
+    }
+
+    #[cfg(unix)]
+    unsafe fn allocate_exportable_memory(
+        &self,
+        memory_type: MemoryTypeId,
+        size: u64,
+    ) -> Result<Memory<B>, device::ExternalMemoryError> {
+        let raw = self.raw.allocate_exportable_memory(memory_type, size)?;
+        Ok(self.wrap_memory(raw, memory_type, size))
+    }
+
+    #[cfg(unix)]
+    unsafe fn import_dma_buf(
+        &self,
+        memory_type: MemoryTypeId,
+        size: u64,
+        fd: RawFd,
+    ) -> Result<Memory<B>, device::ExternalMemoryError> {
+        warn!("Contents of the imported memory are not captured");
+        let raw = self.raw.import_dma_buf(memory_type, size, fd)?;
+        Ok(self.wrap_memory(raw, memory_type, size))
+    }
+
+    #[cfg(unix)]
+    unsafe fn export_dma_buf(
+        &self,
+        memory: &Memory<B>,
+    ) -> Result<RawFd, device::ExternalMemoryError> {
+        self.raw.export_dma_buf(&memory.raw)
+    }
+
+    unsafe fn free_memory(&self, memory: Memory<B>) {
+        self.shared.free_memory(memory.id);
+        self.shared.add(Action::FreeMemory(memory.id));
+        self.raw.free_memory(memory.raw)
+    }
+
+    unsafe fn create_command_pool(
+        &self,
+        family: QueueFamilyId,
+        create_flags: pool::CommandPoolCreateFlags,
+    ) -> Result<CommandPool<B>, device::OutOfMemory> {
+        let raw = self.raw.create_command_pool(family, create_flags)?;
+        let id = self.shared.next_id(ObjectKind::CommandPool);
+        self.shared.add(Action::CreateCommandPool {
+            id,
+            flags: create_flags,
+        });
+        Ok(CommandPool {
+            raw,
+            id,
+            shared: Arc::clone(&self.shared),
+        })
+    }
+
+    unsafe fn destroy_command_pool(&self, pool: CommandPool<B>) {
+        self.shared.add(Action::DestroyCommandPool(pool.id));
+        self.raw.destroy_command_pool(pool.raw)
+    }
+
+    unsafe fn create_render_pass<'a, Ia, Is, Id>(
+        &self,
+        attachments: Ia,
+        subpasses: Is,
+        dependencies: Id,
+    ) -> Result<Handle<B::RenderPass>, device::OutOfMemory>
+    where
+        Ia: Iterator<Item = pass::Attachment>,
+        Is: Iterator<Item = pass::SubpassDesc<'a>>,
+        Id: Iterator<Item = pass::SubpassDependency>,
+    {
+        let attachments = attachments.collect::<Vec<_>>();
+        let subpasses = subpasses.collect::<Vec<_>>();
+        let dependencies = dependencies.collect::<Vec<_>>();
+        let render_pass = self.wrap(
+            ObjectKind::RenderPass,
+            self.raw.create_render_pass(
+                attachments.iter().cloned(),
+                subpasses.iter().cloned(),
+                dependencies.iter().cloned(),
+            )?,
+        );
+        self.shared.describe_render_pass(
+            render_pass.id,
+            attachments.iter().map(|at| at.format).collect(),
+        );
+        self.shared.add(Action::CreateRenderPass {
+            id: render_pass.id,
+            attachments,
+            subpasses: subpasses
+                .into_iter()
+                .map(trace::SubpassDesc::from)
+                .collect(),
+            dependencies,
+        });
+        Ok(render_pass)
+    }
+
+    unsafe fn create_rendering_pass<I>(
+        &self,
+        colors: I,
+        depth_stencil: Option<pass::Attachment>,
+    ) -> Result<Handle<B::RenderPass>, device::OutOfMemory>
+    where
+        I: Iterator<Item = pass::Attachment>,
+    {
+        let colors = colors.collect::<Vec<_>>();
+        let render_pass = self.wrap(
+            ObjectKind::RenderPass,
+            self.raw
+                .create_rendering_pass(colors.iter().cloned(), depth_stencil.clone())?,
+        );
+        self.shared.describe_render_pass(
+            render_pass.id,
+            colors
+                .iter()
+                .chain(&depth_stencil)
+                .map(|at| at.format)
+                .collect(),
+        );
+        self.shared.add(Action::CreateRenderingPass {
+            id: render_pass.id,
+            colors,
+            depth_stencil,
+        });
+        Ok(render_pass)
+    }
+
+    unsafe fn destroy_render_pass(&self, rp: Handle<B::RenderPass>) {
+        self.shared.forget(rp.id);
+        self.shared.add(Action::DestroyRenderPass(rp.id));
+        self.raw.destroy_render_pass(rp.raw)
+    }
+
+    unsafe fn create_pipeline_layout<'a, Is, Ic>(
+        &self,
+        set_layouts: Is,
+        push_constant: Ic,
+    ) -> Result<Handle<B::PipelineLayout>, device::OutOfMemory>
+    where
+        Is: Iterator<Item = &'a Handle<B::DescriptorSetLayout>>,
+        Ic: Iterator<Item = (pso::ShaderStageFlags, Range<u32>)>,
+    {
+        let set_layouts = set_layouts.collect::<Vec<_>>();
+        let push_constant_ranges = push_constant.collect::<Vec<_>>();
+        let layout = self.wrap(
+            ObjectKind::PipelineLayout,
+            self.raw.create_pipeline_layout(
+                set_layouts.iter().map(|layout| &layout.raw),
+                push_constant_ranges.iter().cloned(),
+            )?,
+        );
+        self.shared.add(Action::CreatePipelineLayout {
+            id: layout.id,
+            set_layouts: set_layouts.iter().map(|layout| layout.id).collect(),
+            push_constant_ranges,
+        });
+        Ok(layout)
+    }
+
+    unsafe fn destroy_pipeline_layout(&self, layout: Handle<B::PipelineLayout>) {
+        self.shared.add(Action::DestroyPipelineLayout(layout.id));
+        self.raw.destroy_pipeline_layout(layout.raw)
+    }
+
+    unsafe fn create_pipeline_cache(
+        &self,
+        data: Option<&[u8]>,
+    ) -> Result<Handle<B::PipelineCache>, device::OutOfMemory> {
+        let cache = self.wrap(
+            ObjectKind::PipelineCache,
+            self.raw.create_pipeline_cache(data)?,
+        );
+        self.shared.add(Action::CreatePipelineCache {
+            id: cache.id,
+            data: data.map(|data| self.shared.make_file("cache", data)),
+        });
+        Ok(cache)
+    }
+
+    unsafe fn get_pipeline_cache_data(
+        &self,
+        cache: &Handle<B::PipelineCache>,
+    ) -> Result<Vec<u8>, device::OutOfMemory> {
+        self.raw.get_pipeline_cache_data(&cache.raw)
+    }
+
+    unsafe fn merge_pipeline_caches<'a, I>(
+        &self,
+        target: &mut Handle<B::PipelineCache>,
+        sources: I,
+    ) -> Result<(), device::OutOfMemory>
+    where
+        I: Iterator<Item = &'a Handle<B::PipelineCache>>,
+    {
+        let sources = sources.collect::<Vec<_>>();
+        self.raw
+            .merge_pipeline_caches(&mut target.raw, sources.iter().map(|cache| &cache.raw))?;
+        self.shared.add(Action::MergePipelineCaches {
+            target: target.id,
+            sources: sources.iter().map(|cache| cache.id).collect(),
+        });
+        Ok(())
+    }
+
+    unsafe fn destroy_pipeline_cache(&self, cache: Handle<B::PipelineCache>) {
+        self.shared.add(Action::DestroyPipelineCache(cache.id));
+        self.raw.destroy_pipeline_cache(cache.raw)
+    }
+
+    unsafe fn create_graphics_pipeline<'a>(
+        &self,
+        desc: &pso::GraphicsPipelineDesc<'a, Backend<B>>,
+        cache: Option<&Handle<B::PipelineCache>>,
+    ) -> Result<Handle<B::GraphicsPipeline>, pso::CreationError> {
+        let (raw_assembler, primitive_assembler) = match desc.primitive_assembler {
+            pso::PrimitiveAssemblerDesc::Vertex {
+                buffers,
+                attributes,
+                ref input_assembler,
+                ref vertex,
+                ref tessellation,
+                ref geometry,
+            } => (
+                pso::PrimitiveAssemblerDesc::Vertex {
+                    buffers,
+                    attributes,
+                    input_assembler: input_assembler.clone(),
+                    vertex: Self::map_entry_point(vertex),
+                    tessellation: tessellation.as_ref().map(|&(ref hull, ref domain)| {
+                        (Self::map_entry_point(hull), Self::map_entry_point(domain))
+                    }),
+                    geometry: geometry.as_ref().map(Self::map_entry_point),
+                },
+                trace::PrimitiveAssembler::Vertex {
+                    buffers: buffers.to_vec(),
+                    attributes: attributes.to_vec(),
+                    input_assembler: input_assembler.clone(),
+                    vertex: trace::EntryPoint::from(vertex),
+                    tessellation: tessellation.as_ref().map(|&(ref hull, ref domain)| {
+                        (
+                            trace::EntryPoint::from(hull),
+                            trace::EntryPoint::from(domain),
+                        )
+                    }),
+                    geometry: geometry.as_ref().map(trace::EntryPoint::from),
+                },
+            ),
+            pso::PrimitiveAssemblerDesc::Mesh { ref task, ref mesh } => (
+                pso::PrimitiveAssemblerDesc::Mesh {
+                    task: task.as_ref().map(Self::map_entry_point),
+                    mesh: Self::map_entry_point(mesh),
+                },
+                trace::PrimitiveAssembler::Mesh {
+                    task: task.as_ref().map(trace::EntryPoint::from),
+                    mesh: trace::EntryPoint::from(mesh),
+                },
+            ),
+        };
+
+        let raw_desc = pso::GraphicsPipelineDesc {
+            label: desc.label,
+            primitive_assembler: raw_assembler,
+            rasterizer: desc.rasterizer,
+            fragment: desc.fragment.as_ref().map(Self::map_entry_point),
+            blender: desc.blender.clone(),
+            depth_stencil: desc.depth_stencil,
+            multisampling: desc.multisampling.clone(),
+            baked_states: desc.baked_states.clone(),
+            layout: &desc.layout.raw,
+            subpass: pass::Subpass {
+                index: desc.subpass.index,
+                main_pass: &desc.subpass.main_pass.raw,
+            },
+            flags: desc.flags,
+            parent: Self::map_base_pipeline(&desc.parent),
+        };
+        let pipeline = self.wrap(
+            ObjectKind::GraphicsPipeline,
+            self.raw
+                .create_graphics_pipeline(&raw_desc, cache.map(|cache| &cache.raw))?,
+        );
+
+        let desc = trace::GraphicsPipelineDesc {
+            label: desc.label.map(str::to_string),
+            primitive_assembler,
+            rasterizer: desc.rasterizer,
+            fragment: desc.fragment.as_ref().map(trace::EntryPoint::from),
+            blender: desc.blender.clone(),
+            depth_stencil: desc.depth_stencil,
+            multisampling: desc.multisampling.clone(),
+            baked_states: desc.baked_states.clone(),
+            layout: desc.layout.id,
+            subpass: (desc.subpass.main_pass.id, desc.subpass.index),
+            flags: desc.flags,
+            parent: Self::record_base_pipeline(&desc.parent),
+        };
+        self.shared
+            .describe_pipeline(pipeline.id, trace::PipelineDesc::Graphics(desc.clone()));
+        self.shared.add(Action::CreateGraphicsPipeline {
+            id: pipeline.id,
+            desc,
+            cache: cache.map(|cache| cache.id),
+        });
+        Ok(pipeline)
+    }
+
+    unsafe fn destroy_graphics_pipeline(&self, pipeline: Handle<B::GraphicsPipeline>) {
+        self.shared.forget(pipeline.id);
+        self.shared
+            .add(Action::DestroyGraphicsPipeline(pipeline.id));
+        self.raw.destroy_graphics_pipeline(pipeline.raw)
+    }
+
+    unsafe fn create_compute_pipeline<'a>(
+        &self,
+        desc: &pso::ComputePipelineDesc<'a, Backend<B>>,
+        cache: Option<&Handle<B::PipelineCache>>,
+    ) -> Result<Handle<B::ComputePipeline>, pso::CreationError> {
+        let raw_desc = pso::ComputePipelineDesc {
+            label: desc.label,
+            shader: Self::map_entry_point(&desc.shader),
+            layout: &desc.layout.raw,
+            flags: desc.flags,
+            parent: Self::map_base_pipeline(&desc.parent),
+        };
+        let pipeline = self.wrap(
+            ObjectKind::ComputePipeline,
+            self.raw
+                .create_compute_pipeline(&raw_desc, cache.map(|cache| &cache.raw))?,
+        );
+        let desc = trace::ComputePipelineDesc {
+            label: desc.label.map(str::to_string),
+            shader: trace::EntryPoint::from(&desc.shader),
+            layout: desc.layout.id,
+            flags: desc.flags,
+            parent: Self::record_base_pipeline(&desc.parent),
+        };
+        self.shared
+            .describe_pipeline(pipeline.id, trace::PipelineDesc::Compute(desc.clone()));
+        self.shared.add(Action::CreateComputePipeline {
+            id: pipeline.id,
+            desc,
+            cache: cache.map(|cache| cache.id),
+        });
+        Ok(pipeline)
+    }
+
+    unsafe fn destroy_compute_pipeline(&self, pipeline: Handle<B::ComputePipeline>) {
+        self.shared.forget(pipeline.id);
+        self.shared.add(Action::DestroyComputePipeline(pipeline.id));
+        self.raw.destroy_compute_pipeline(pipeline.raw)
+    }
+
+    unsafe fn create_framebuffer<I>(
+        &self,
+        pass: &Handle<B::RenderPass>,
+        attachments: I,
+        extent: image::Extent,
+    ) -> Result<Handle<B::Framebuffer>, device::OutOfMemory>
+    where
+        I: Iterator<Item = image::FramebufferAttachment>,
+    {
+        let attachments = attachments.collect::<Vec<_>>();
+        let framebuffer = self.wrap(
+            ObjectKind::Framebuffer,
+            self.raw
+                .create_framebuffer(&pass.raw, attachments.iter().cloned(), extent)?,
+        );
+        self.shared.add(Action::CreateFramebuffer {
+            id: framebuffer.id,
+            render_pass: pass.id,
+            attachments,
+            extent,
+        });
+        Ok(framebuffer)
+    }
+
+    unsafe fn destroy_framebuffer(&self, buf: Handle<B::Framebuffer>) {
+        self.shared.add(Action::DestroyFramebuffer(buf.id));
+        self.raw.destroy_framebuffer(buf.raw)
+    }
+
+    unsafe fn create_shader_module(
+        &self,
+        spirv: &[u32],
+    ) -> Result<Handle<B::ShaderModule>, device::ShaderError> {
+        let module = self.wrap(
+            ObjectKind::ShaderModule,
+            self.raw.create_shader_module(spirv)?,
+        );
+        let bytes = spirv
+            .iter()
+            .flat_map(|word| word.to_le_bytes().to_vec())
+            .collect::<Vec<_>>();
+        self.shared.describe_shader_module(module.id, &bytes);
+        self.shared.add(Action::CreateShaderModule {
+            id: module.id,
+            data: self.shared.make_file("spv", &bytes),
+        });
+        Ok(module)
+    }
+
+    unsafe fn destroy_shader_module(&self, shader: Handle<B::ShaderModule>) {
+        self.shared.forget(shader.id);
+        self.shared.add(Action::DestroyShaderModule(shader.id));
+        self.raw.destroy_shader_module(shader.raw)
+    }
+
+    unsafe fn create_buffer(
+        &self,
+        size: u64,
+        usage: buffer::Usage,
+    ) -> Result<Handle<B::Buffer>, buffer::CreationError> {
+        let buffer = self.wrap(ObjectKind::Buffer, self.raw.create_buffer(size, usage)?);
+        self.shared.add(Action::CreateBuffer {
+            id: buffer.id,
+            size,
+            usage,
+        });
+        Ok(buffer)
+    }
+
+    unsafe fn get_buffer_requirements(&self, buf: &Handle<B::Buffer>) -> memory::Requirements {
+        self.raw.get_buffer_requirements(&buf.raw)
+    }
+
+    unsafe fn bind_buffer_memory(
+        &self,
+        memory: &Memory<B>,
+        offset: u64,
+        buf: &mut Handle<B::Buffer>,
+    ) -> Result<(), device::BindError> {
+        self.raw
+            .bind_buffer_memory(&memory.raw, offset, &mut buf.raw)?;
+        self.shared.add(Action::BindBufferMemory {
+            buffer: buf.id,
+            memory: memory.id,
+            offset,
+        });
+        Ok(())
+    }
+
+    unsafe fn destroy_buffer(&self, buffer: Handle<B::Buffer>) {
+        self.shared.add(Action::DestroyBuffer(buffer.id));
+        self.raw.destroy_buffer(buffer.raw)
+    }
+
+    unsafe fn create_buffer_view(
+        &self,
+        buf: &Handle<B::Buffer>,
+        fmt: Option<format::Format>,
+        range: buffer::SubRange,
+    ) -> Result<Handle<B::BufferView>, buffer::ViewCreationError> {
+        let view = self.wrap(
+            ObjectKind::BufferView,
+            self.raw.create_buffer_view(&buf.raw, fmt, range.clone())?,
+        );
+        self.shared.add(Action::CreateBufferView {
+            id: view.id,
+            buffer: buf.id,
+            format: fmt,
+            range,
+        });
+        Ok(view)
+    }
+
+    unsafe fn destroy_buffer_view(&self, view: Handle<B::BufferView>) {
+        self.shared.add(Action::DestroyBufferView(view.id));
+        self.raw.destroy_buffer_view(view.raw)
+    }
+
+    unsafe fn create_image(
+        &self,
+        kind: image::Kind,
+        mip_levels: image::Level,
+        format: format::Format,
+        tiling: image::Tiling,
+        usage: image::Usage,
+        view_caps: image::ViewCapabilities,
+    ) -> Result<Image<B>, image::CreationError> {
+        let raw = self
+            .raw
+            .create_image(kind, mip_levels, format, tiling, usage, view_caps)?;
+        let image = self.wrap(ObjectKind::Image, Slot::Owned(raw));
+        self.shared.add(Action::CreateImage {
+            id: image.id,
+            kind,
+            mip_levels,
+            format,
+            tiling,
+            usage,
+            view_caps,
+        });
+        Ok(image)
+    }
+
+    unsafe fn create_image_with_drm_modifier(
+        &self,
+        kind: image::Kind,
+        format: format::Format,
+        tiling: image::DrmTiling,
+        usage: image::Usage,
+        view_caps: image::ViewCapabilities,
+    ) -> Result<Image<B>, image::CreationError> {
+        warn!("Image with a DRM format modifier is captured with the linear tiling");
+        let raw = self
+            .raw
+            .create_image_with_drm_modifier(kind, format, tiling, usage, view_caps)?;
+        let image = self.wrap(ObjectKind::Image, Slot::Owned(raw));
+        self.shared.add(Action::CreateImage {
+            id: image.id,
+            kind,
+            mip_levels: 1,
+            format,
+            tiling: image::Tiling::Linear,
+            usage,
+            view_caps,
+        });
+        Ok(image)
+    }
+
+    unsafe fn get_image_drm_modifier(&self, image: &Image<B>) -> Option<image::DrmModifier> {
+        self.raw.get_image_drm_modifier(image.raw.get())
+    }
+
+    unsafe fn get_image_requirements(&self, image: &Image<B>) -> memory::Requirements {
+        self.raw.get_image_requirements(image.raw.get())
+    }
+
+    unsafe fn get_image_subresource_footprint(
+        &self,
+        image: &Image<B>,
+        subresource: image::Subresource,
+    ) -> image::SubresourceFootprint {
+        self.raw
+            .get_image_subresource_footprint(image.raw.get(), subresource)
+    }
+
+    unsafe fn bind_image_memory(
+        &self,
+        memory: &Memory<B>,
+        offset: u64,
+        image: &mut Image<B>,
+    ) -> Result<(), device::BindError> {
+        match image.raw {
+            Slot::Owned(ref mut raw) => self.raw.bind_image_memory(&memory.raw, offset, raw)?,
+            Slot::Swapchain(_) => panic!("Swapchain images can't be bound to memory"),
+        }
+        self.shared.add(Action::BindImageMemory {
+            image: image.id,
+            memory: memory.id,
+            offset,
+        });
+        Ok(())
+    }
+
+    unsafe fn destroy_image(&self, image: Image<B>) {
+        self.shared.add(Action::DestroyImage(image.id));
+        if let Slot::Owned(raw) = image.raw {
+            self.raw.destroy_image(raw)
+        }
+    }
+
+    unsafe fn create_image_view(
+        &self,
+        image: &Image<B>,
+        view_kind: image::ViewKind,
+        format: format::Format,
+        swizzle: format::Swizzle,
+        range: image::SubresourceRange,
+    ) -> Result<ImageView<B>, image::ViewCreationError> {
+        let raw = self.raw.create_image_view(
+            image.raw.get(),
+            view_kind,
+            format,
+            swizzle,
+            range.clone(),
+        )?;
+        let view = ImageView {
+            raw: Slot::Owned(raw),
+            id: self.shared.next_id(ObjectKind::ImageView),
+        };
+        self.shared.add(Action::CreateImageView {
+            id: view.id,
+            image: image.id,
+            view_kind,
+            format,
+            swizzle,
+            range,
+        });
+        Ok(view)
+    }
+
+    unsafe fn destroy_image_view(&self, view: ImageView<B>) {
+        self.shared.add(Action::DestroyImageView(view.id));
+        if let Slot::Owned(raw) = view.raw {
+            self.raw.destroy_image_view(raw)
+        }
+    }
+
+    unsafe fn create_sampler(
+        &self,
+        desc: &image::SamplerDesc,
+    ) -> Result<Handle<B::Sampler>, device::AllocationError> {
+        let sampler = self.wrap(ObjectKind::Sampler, self.raw.create_sampler(desc)?);
+        self.shared.add(Action::CreateSampler {
+            id: sampler.id,
+            desc: desc.clone(),
+        });
+        Ok(sampler)
+    }
+
+    unsafe fn destroy_sampler(&self, sampler: Handle<B::Sampler>) {
+        self.shared.add(Action::DestroySampler(sampler.id));
+        self.raw.destroy_sampler(sampler.raw)
+    }
+
+    unsafe fn create_sampler_ycbcr_conversion(
+        &self,
+        desc: &image::SamplerYcbcrConversionDesc,
+    ) -> Result<Handle<B::SamplerYcbcrConversion>, image::CreationError> {
+        let conversion = self.wrap(
+            ObjectKind::SamplerYcbcrConversion,
+            self.raw.create_sampler_ycbcr_conversion(desc)?,
+        );
+        self.shared.add(Action::CreateSamplerYcbcrConversion {
+            id: conversion.id,
+            desc: desc.clone(),
+        });
+        Ok(conversion)
+    }
+
+    unsafe fn destroy_sampler_ycbcr_conversion(
+        &self,
+        conversion: Handle<B::SamplerYcbcrConversion>,
+    ) {
+        self.shared
+            .add(Action::DestroySamplerYcbcrConversion(conversion.id));
+        self.raw.destroy_sampler_ycbcr_conversion(conversion.raw)
+    }
+
+    unsafe fn create_ycbcr_sampler(
+        &self,
+        desc: &image::SamplerDesc,
+        conversion: &Handle<B::SamplerYcbcrConversion>,
+    ) -> Result<Handle<B::Sampler>, device::AllocationError> {
+        let sampler = self.wrap(
+            ObjectKind::Sampler,
+            self.raw.create_ycbcr_sampler(desc, &conversion.raw)?,
+        );
+        self.shared.add(Action::CreateYcbcrSampler {
+            id: sampler.id,
+            desc: desc.clone(),
+            conversion: conversion.id,
+        });
+        Ok(sampler)
+    }
+
+    unsafe fn create_ycbcr_image_view(
+        &self,
+        image: &Image<B>,
+        view_kind: image::ViewKind,
+        range: image::SubresourceRange,
+        conversion: &Handle<B::SamplerYcbcrConversion>,
+    ) -> Result<ImageView<B>, image::ViewCreationError> {
+        let raw = self.raw.create_ycbcr_image_view(
+            image.raw.get(),
+            view_kind,
+            range.clone(),
+            &conversion.raw,
+        )?;
+        let view = ImageView {
+            raw: Slot::Owned(raw),
+            id: self.shared.next_id(ObjectKind::ImageView),
+        };
+        self.shared.add(Action::CreateYcbcrImageView {
+            id: view.id,
+            image: image.id,
+            view_kind,
+            range,
+            conversion: conversion.id,
+        });
+        Ok(view)
+    }
+
+    unsafe fn create_descriptor_pool<I>(
+        &self,
+        max_sets: usize,
+        descriptor_ranges: I,
+        flags: pso::DescriptorPoolCreateFlags,
+    ) -> Result<DescriptorPool<B>, device::OutOfMemory>
+    where
+        I: Iterator<Item = pso::DescriptorRangeDesc>,
+    {
+        let ranges = descriptor_ranges.collect::<Vec<_>>();
+        let raw = self
+            .raw
+            .create_descriptor_pool(max_sets, ranges.iter().cloned(), flags)?;
+        let id = self.shared.next_id(ObjectKind::DescriptorPool);
+        self.shared.add(Action::CreateDescriptorPool {
+            id,
+            max_sets,
+            ranges,
+            flags,
+        });
+        Ok(DescriptorPool {
+            raw,
+            id,
+            shared: Arc::clone(&self.shared),
+        })
+    }
+
+    unsafe fn destroy_descriptor_pool(&self, pool: DescriptorPool<B>) {
+        self.shared.add(Action::DestroyDescriptorPool(pool.id));
+        self.raw.destroy_descriptor_pool(pool.raw)
+    }
+
+    unsafe fn create_descriptor_set_layout<'a, I, J>(
+        &self,
+        bindings: I,
+        immutable_samplers: J,
+    ) -> Result<Handle<B::DescriptorSetLayout>, device::OutOfMemory>
+    where
+        I: Iterator<Item = pso::DescriptorSetLayoutBinding>,
+        J: Iterator<Item = &'a Handle<B::Sampler>>,
+    {
+        let bindings = bindings.collect::<Vec<_>>();
+        let immutable_samplers = immutable_samplers.collect::<Vec<_>>();
+        let layout = self.wrap(
+            ObjectKind::DescriptorSetLayout,
+            self.raw.create_descriptor_set_layout(
+                bindings.iter().cloned(),
+                immutable_samplers.iter().map(|sampler| &sampler.raw),
+            )?,
+        );
+        self.shared.add(Action::CreateDescriptorSetLayout {
+            id: layout.id,
+            bindings,
+            immutable_samplers: immutable_samplers
+                .iter()
+                .map(|sampler| sampler.id)
+                .collect(),
+        });
+        Ok(layout)
+    }
+
+    unsafe fn destroy_descriptor_set_layout(&self, layout: Handle<B::DescriptorSetLayout>) {
+        self.shared
+            .add(Action::DestroyDescriptorSetLayout(layout.id));
+        self.raw.destroy_descriptor_set_layout(layout.raw)
+    }
+
+    unsafe fn write_descriptor_set<'a, I>(&self, op: pso::DescriptorSetWrite<'a, Backend<B>, I>)
+    where
+        I: Iterator<Item = pso::Descriptor<'a, Backend<B>>>,
+    {
+        let mut descriptors = Vec::new();
+        self.raw.write_descriptor_set(pso::DescriptorSetWrite {
+            set: &mut op.set.raw,
+            binding: op.binding,
+            array_offset: op.array_offset,
+            descriptors: op.descriptors.map(|descriptor| match descriptor {
+                pso::Descriptor::Sampler(sampler) => {
+                    descriptors.push(trace::Descriptor::Sampler(sampler.id));
+                    pso::Descriptor::Sampler(&sampler.raw)
+                }
+                pso::Descriptor::Image(view, layout) => {
+                    descriptors.push(trace::Descriptor::Image(view.id, layout));
+                    pso::Descriptor::Image(view.raw.get(), layout)
+                }
+                pso::Descriptor::CombinedImageSampler(view, layout, sampler) => {
+                    descriptors.push(trace::Descriptor::CombinedImageSampler(
+                        view.id, layout, sampler.id,
+                    ));
+                    pso::Descriptor::CombinedImageSampler(view.raw.get(), layout, &sampler.raw)
+                }
+                pso::Descriptor::Buffer(buffer, sub) => {
+                    descriptors.push(trace::Descriptor::Buffer(buffer.id, sub.clone()));
+                    pso::Descriptor::Buffer(&buffer.raw, sub)
+                }
+                pso::Descriptor::TexelBuffer(view) => {
+                    descriptors.push(trace::Descriptor::TexelBuffer(view.id));
+                    pso::Descriptor::TexelBuffer(&view.raw)
+                }
+                pso::Descriptor::InlineUniformBlock(data) => {
+                    descriptors.push(trace::Descriptor::InlineUniformBlock(data.to_vec()));
+                    pso::Descriptor::InlineUniformBlock(data)
+                }
+                pso::Descriptor::Null => {
+                    descriptors.push(trace::Descriptor::Null);
+                    pso::Descriptor::Null
+                }
+            }),
+        });
+        self.shared.add(Action::WriteDescriptorSet {
+            set: op.set.id,
+            binding: op.binding,
+            array_offset: op.array_offset,
+            descriptors,
+        });
+    }
+
+    unsafe fn copy_descriptor_set<'a>(&self, op: pso::DescriptorSetCopy<'a, Backend<B>>) {
+        self.shared.add(Action::CopyDescriptorSet {
+            src_set: op.src_set.id,
+            src_binding: op.src_binding,
+            src_array_offset: op.src_array_offset,
+            dst_set: op.dst_set.id,
+            dst_binding: op.dst_binding,
+            dst_array_offset: op.dst_array_offset,
+            count: op.count,
+        });
+        self.raw.copy_descriptor_set(pso::DescriptorSetCopy {
+            src_set: &op.src_set.raw,
+            src_binding: op.src_binding,
+            src_array_offset: op.src_array_offset,
+            dst_set: &mut op.dst_set.raw,
+            dst_binding: op.dst_binding,
+            dst_array_offset: op.dst_array_offset,
+            count: op.count,
+        })
+    }
+
+    unsafe fn map_memory(
+        &self,
+        memory: &mut Memory<B>,
+        segment: memory::Segment,
+    ) -> Result<*mut u8, device::MapError> {
+        let ptr = self.raw.map_memory(&mut memory.raw, segment.clone())?;
+        let end = match segment.size {
+            Some(size) => segment.offset + size,
+            None => memory.size,
+        };
+        self.shared
+            .map(memory.id, memory.size, ptr, segment.offset..end);
+        Ok(ptr)
+    }
+
+    unsafe fn flush_mapped_memory_ranges<'a, I>(&self, ranges: I) -> Result<(), device::OutOfMemory>
+    where
+        I: Iterator<Item = (&'a Memory<B>, memory::Segment)>,
+    {
+        let ranges = ranges.collect::<Vec<_>>();
+        for &(memory, ref segment) in ranges.iter() {
+            let end = match segment.size {
+                Some(size) => segment.offset + size,
+                None => memory.size,
+            };
+            self.shared.capture_memory(memory.id, segment.offset..end);
+        }
+        self.raw.flush_mapped_memory_ranges(
+            ranges
+                .into_iter()
+                .map(|(memory, segment)| (&memory.raw, segment)),
+        )
+    }
+
+    unsafe fn invalidate_mapped_memory_ranges<'a, I>(
+        &self,
+        ranges: I,
+    ) -> Result<(), device::OutOfMemory>
+    where
+        I: Iterator<Item = (&'a Memory<B>, memory::Segment)>,
+    {
+        self.raw
+            .invalidate_mapped_memory_ranges(ranges.map(|(memory, segment)| (&memory.raw, segment)))
+    }
+
+    unsafe fn unmap_memory(&self, memory: &mut Memory<B>) {
+        self.shared.unmap(memory.id);
+        self.raw.unmap_memory(&mut memory.raw)
+    }
+
+    unsafe fn copy_memory_to_image<I>(
+        &self,
+        data: &[u8],
+        image: &Image<B>,
+        layout: image::Layout,
+        regions: I,
+    ) -> Result<(), device::MapError>
+    where
+        I: Iterator<Item = command::BufferImageCopy>,
+    {
+        let regions = regions.collect::<Vec<_>>();
+        self.raw
+            .copy_memory_to_image(data, image.raw.get(), layout, regions.iter().cloned())?;
+        self.shared.add(Action::CopyMemoryToImage {
+            image: image.id,
+            layout,
+            data: self.shared.make_file("bin", data),
+            regions,
+        });
+        Ok(())
+    }
+
+    unsafe fn copy_image_to_memory<I>(
+        &self,
+        image: &Image<B>,
+        layout: image::Layout,
+        data: &mut [u8],
+        regions: I,
+    ) -> Result<(), device::MapError>
+    where
+        I: Iterator<Item = command::BufferImageCopy>,
+    {
+        self.raw
+            .copy_image_to_memory(image.raw.get(), layout, data, regions)
+    }
+
+    fn create_semaphore(&self) -> Result<Handle<B::Semaphore>, device::OutOfMemory> {
+        let semaphore = self.wrap(ObjectKind::Semaphore, self.raw.create_semaphore()?);
+        self.shared.add(Action::CreateSemaphore(semaphore.id));
+        Ok(semaphore)
+    }
+
+    unsafe fn destroy_semaphore(&self, semaphore: Handle<B::Semaphore>) {
+        self.shared.add(Action::DestroySemaphore(semaphore.id));
+        self.raw.destroy_semaphore(semaphore.raw)
+    }
+
+    fn create_fence(&self, signaled: bool) -> Result<Handle<B::Fence>, device::OutOfMemory> {
+        let fence = self.wrap(ObjectKind::Fence, self.raw.create_fence(signaled)?);
+        self.shared.add(Action::CreateFence {
+            id: fence.id,
+            signaled,
+        });
+        Ok(fence)
+    }
+
+    unsafe fn reset_fence(&self, fence: &mut Handle<B::Fence>) -> Result<(), device::OutOfMemory> {
+        self.shared.add(Action::ResetFence(fence.id));
+        self.raw.reset_fence(&mut fence.raw)
+    }
+
+    unsafe fn wait_for_fence(
+        &self,
+        fence: &Handle<B::Fence>,
+        timeout_ns: u64,
+    ) -> Result<bool, device::WaitError> {
+        let signaled = self.raw.wait_for_fence(&fence.raw, timeout_ns)?;
+        if signaled {
+            self.shared.add(Action::WaitForFences {
+                ids: vec![fence.id],
+                wait: device::WaitFor::All,
+            });
+        }
+        Ok(signaled)
+    }
+
+    unsafe fn wait_for_fences<'a, I>(
+        &self,
+        fences: I,
+        wait: device::WaitFor,
+        timeout_ns: u64,
+    ) -> Result<bool, device::WaitError>
+    where
+        I: Iterator<Item = &'a Handle<B::Fence>>,
+    {
+        let fences = fences.collect::<Vec<_>>();
+        let signaled = self.raw.wait_for_fences(
+            fences.iter().map(|fence| &fence.raw),
+            wait.clone(),
+            timeout_ns,
+        )?;
+        if signaled {
+            self.shared.add(Action::WaitForFences {
+                ids: fences.iter().map(|fence| fence.id).collect(),
+                wait,
+            });
+        }
+        Ok(signaled)
+    }
+
+    unsafe fn get_fence_status(
+        &self,
+        fence: &Handle<B::Fence>,
+    ) -> Result<bool, device::DeviceLost> {
+        let signaled = self.raw.get_fence_status(&fence.raw)?;
+        if signaled {
+            self.shared.add(Action::WaitForFences {
+                ids: vec![fence.id],
+                wait: device::WaitFor::All,
+            });
+        }
+        Ok(signaled)
+    }
+
+    unsafe fn destroy_fence(&self, fence: Handle<B::Fence>) {
+        self.shared.add(Action::DestroyFence(fence.id));
+        self.raw.destroy_fence(fence.raw)
+    }
+
+    fn create_event(&self) -> Result<Handle<B::Event>, device::OutOfMemory> {
+        let event = self.wrap(ObjectKind::Event, self.raw.create_event()?);
+        self.shared.add(Action::CreateEvent(event.id));
+        Ok(event)
+    }
+
+    unsafe fn destroy_event(&self, event: Handle<B::Event>) {
+        self.shared.add(Action::DestroyEvent(event.id));
+        self.raw.destroy_event(event.raw)
+    }
+
+    unsafe fn get_event_status(&self, event: &Handle<B::Event>) -> Result<bool, device::WaitError> {
+        self.raw.get_event_status(&event.raw)
+    }
+
+    unsafe fn set_event(&self, event: &mut Handle<B::Event>) -> Result<(), device::OutOfMemory> {
+        self.shared.add(Action::SetEvent(event.id));
+        self.raw.set_event(&mut event.raw)
+    }
+
+    unsafe fn reset_event(&self, event: &mut Handle<B::Event>) -> Result<(), device::OutOfMemory> {
+        self.shared.add(Action::ResetEvent(event.id));
+        self.raw.reset_event(&mut event.raw)
+    }
+
+    unsafe fn create_query_pool(
+        &self,
+        ty: query::Type,
+        count: query::Id,
+    ) -> Result<Handle<B::QueryPool>, query::CreationError> {
+        let pool = self.wrap(
+            ObjectKind::QueryPool,
+            self.raw.create_query_pool(ty, count)?,
+        );
+        self.shared.add(Action::CreateQueryPool {
+            id: pool.id,
+            ty,
+            count,
+        });
+        Ok(pool)
+    }
+
+    unsafe fn destroy_query_pool(&self, pool: Handle<B::QueryPool>) {
+        self.shared.add(Action::DestroyQueryPool(pool.id));
+        self.raw.destroy_query_pool(pool.raw)
+    }
+
+    unsafe fn get_query_pool_results(
+        &self,
+        pool: &Handle<B::QueryPool>,
+        queries: Range<query::Id>,
+        data: &mut [u8],
+        stride: buffer::Stride,
+        flags: query::ResultFlags,
+    ) -> Result<bool, device::WaitError> {
+        self.raw
+            .get_query_pool_results(&pool.raw, queries, data, stride, flags)
+    }
+
+    fn wait_idle(&self) -> Result<(), device::WaitError> {
+        self.shared.add(Action::WaitIdle);
+        self.raw.wait_idle()
+    }
+
+    fn get_device_lost_reason(&self) -> Option<device::DeviceLostReason> {
+        self.raw.get_device_lost_reason()
+    }
+
+    unsafe fn set_image_name(&self, image: &mut Image<B>, name: &str) {
+        if let Slot::Owned(ref mut raw) = image.raw {
+            self.raw.set_image_name(raw, name)
+        }
+    }
+
+    unsafe fn set_buffer_name(&self, buffer: &mut Handle<B::Buffer>, name: &str) {
+        self.raw.set_buffer_name(&mut buffer.raw, name)
+    }
+
+    unsafe fn set_command_buffer_name(&self, command_buffer: &mut CommandBuffer<B>, name: &str) {
+        self.raw
+            .set_command_buffer_name(&mut command_buffer.raw, name)
+    }
+
+    unsafe fn set_semaphore_name(&self, semaphore: &mut Handle<B::Semaphore>, name: &str) {
+        self.raw.set_semaphore_name(&mut semaphore.raw, name)
+    }
+
+    unsafe fn set_fence_name(&self, fence: &mut Handle<B::Fence>, name: &str) {
+        self.raw.set_fence_name(&mut fence.raw, name)
+    }
+
+    unsafe fn set_framebuffer_name(&self, framebuffer: &mut Handle<B::Framebuffer>, name: &str) {
+        self.raw.set_framebuffer_name(&mut framebuffer.raw, name)
+    }
+
+    unsafe fn set_render_pass_name(&self, render_pass: &mut Handle<B::RenderPass>, name: &str) {
+        self.raw.set_render_pass_name(&mut render_pass.raw, name)
+    }
+
+    unsafe fn set_descriptor_set_name(
+        &self,
+        descriptor_set: &mut Handle<B::DescriptorSet>,
+        name: &str,
+    ) {
+        self.raw
+            .set_descriptor_set_name(&mut descriptor_set.raw, name)
+    }
+
+    unsafe fn set_descriptor_set_layout_name(
+        &self,
+        descriptor_set_layout: &mut Handle<B::DescriptorSetLayout>,
+        name: &str,
+    ) {
+        self.raw
+            .set_descriptor_set_layout_name(&mut descriptor_set_layout.raw, name)
+    }
+
+    unsafe fn set_pipeline_layout_name(
+        &self,
+        pipeline_layout: &mut Handle<B::PipelineLayout>,
+        name: &str,
+    ) {
+        self.raw
+            .set_pipeline_layout_name(&mut pipeline_layout.raw, name)
+    }
+}
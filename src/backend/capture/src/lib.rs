@@ -0,0 +1,892 @@
+/*!
+# Capture and replay layer for gfx-hal
+
+This backend wraps any other `Backend` and forwards every call to it, while
+serializing the calls into a trace: resource creation, descriptor updates,
+command buffer recordings, submissions and presentation. The trace can be
+replayed later on any backend with the `replay::Player`, which makes bug reports
+reproducible across machines, and allows comparing the rendering output of the
+backends.
+
+Each opened device writes its trace into the capture directory of the instance,
+see `Instance::set_capture_dir`. The directory contains `trace.ron` with the list
+of `trace::Action`, and the binary files it refers to: shader code, pipeline
+cache data and the contents of the memory written by the host.
+
+Host writes to mapped memory are detected by comparing the mapped ranges with
+their last known contents, at flushes, unmaps and submissions. The memory is
+assumed to be zero-initialized.
+
+For golden tests, the instance can be switched into the deterministic mode with
+`Instance::set_deterministic`. The objects then get identifiers allocated per
+`trace::ObjectKind`, and every recorded command buffer gets a stable hash of its
+commands, see `CommandBuffer::content_hash`. Comparing the hashes of the traces
+captured on different backends pinpoints the first diverging recording.
+
+Crash handlers can attach the state of the bound pipelines to the reports, with
+`Device::describe_pipeline` giving a self-contained `trace::PipelineDebugInfo`.
+!*/
+
+#[macro_use]
+extern crate log;
+extern crate gfx_hal as hal;
+
+use hal::{adapter, format, image, queue, window, Features, Limits};
+use parking_lot::Mutex;
+
+use std::{
+    borrow::Borrow,
+    collections::HashMap,
+    env, fmt,
+    fs::{self, File},
+    io::{self, Write as _},
+    marker::PhantomData,
+    ops::Range,
+    path::{Path, PathBuf},
+    slice,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+mod command;
+mod device;
+pub mod replay;
+#[cfg(test)]
+mod tests;
+pub mod trace;
+
+use crate::trace::{Action, FileName, Id, ObjectKind};
+pub use crate::{
+    command::{CommandBuffer, CommandPool},
+    device::{DescriptorPool, Device},
+};
+
+/// Environment variable overriding the default capture directory.
+pub const CAPTURE_DIR_VAR: &str = "GFX_CAPTURE_DIR";
+const DEFAULT_CAPTURE_DIR: &str = "gfx-capture";
+
+/// Granularity of the host write detection in mapped memory.
+const CHUNK_SIZE: u64 = 256;
+
+/// Surfaces are created by the instance, so their identifiers
+/// are not bound to a particular device.
+///
+/// Not used in the deterministic mode, which counts the surfaces per instance.
+static NEXT_SURFACE_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Wrapper of an inner backend object, identified in the trace by `id`.
+#[derive(Debug)]
+pub struct Handle<T> {
+    pub(crate) raw: T,
+    pub(crate) id: Id,
+}
+
+/// Image or image view, either created by the device or borrowed from
+/// an acquired swapchain image.
+#[derive(Debug)]
+pub enum Slot<T> {
+    Owned(T),
+    /// Points into the boxed swapchain image, which outlives the slot.
+    Swapchain(*const T),
+}
+
+unsafe impl<T: Sync> Send for Slot<T> {}
+unsafe impl<T: Sync> Sync for Slot<T> {}
+
+impl<T> Slot<T> {
+    pub(crate) fn get(&self) -> &T {
+        match *self {
+            Slot::Owned(ref raw) => raw,
+            Slot::Swapchain(raw) => unsafe { &*raw },
+        }
+    }
+}
+
+pub type Image<B> = Handle<Slot<<B as hal::Backend>::Image>>;
+
+/// Image view wrapper.
+///
+/// Not an alias of `Handle`, so that the swapchain image can borrow both
+/// the image and the view, even if the inner backend uses the same type.
+#[derive(Debug)]
+pub struct ImageView<B: hal::Backend> {
+    pub(crate) raw: Slot<B::ImageView>,
+    pub(crate) id: Id,
+}
+
+#[derive(Debug)]
+pub struct Memory<B: hal::Backend> {
+    pub(crate) raw: B::Memory,
+    pub(crate) id: Id,
+    pub(crate) size: u64,
+}
+
+/// Host mapping of a memory object.
+#[derive(Debug)]
+struct Mapping {
+    /// Host address of the start of `range`, if the memory is currently mapped.
+    ptr: Option<*mut u8>,
+    range: Range<u64>,
+    /// Contents of the whole memory as of the last capture.
+    shadow: Vec<u8>,
+}
+
+unsafe impl Send for Mapping {}
+
+/// Writer of the trace file and its binary data.
+#[derive(Debug)]
+struct Trace {
+    dir: PathBuf,
+    file: Option<File>,
+    data_count: usize,
+}
+
+impl Trace {
+    fn new(dir: &Path) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let mut file = File::create(dir.join(trace::TRACE_FILE))?;
+        file.write_all(b"[\n")?;
+        Ok(Trace {
+            dir: dir.to_path_buf(),
+            file: Some(file),
+            data_count: 0,
+        })
+    }
+
+    fn add(&mut self, action: Action) {
+        let result = match self.file {
+            Some(ref mut file) => {
+                match ron::ser::to_string_pretty(&action, ron::ser::PrettyConfig::default()) {
+                    Ok(string) => writeln!(file, "{},", string),
+                    Err(e) => {
+                        error!("Unable to serialize {:?}: {:?}", action, e);
+                        Ok(())
+                    }
+                }
+            }
+            None => return,
+        };
+        if let Err(e) = result {
+            error!("Capture is stopped, unable to write the trace: {:?}", e);
+            self.file = None;
+        }
+    }
+
+    fn make_file(&mut self, extension: &str, data: &[u8]) -> FileName {
+        self.data_count += 1;
+        let name = format!("data{}.{}", self.data_count, extension);
+        if let Err(e) = fs::write(self.dir.join(&name), data) {
+            error!("Unable to write {}: {:?}", name, e);
+        }
+        name
+    }
+}
+
+impl Drop for Trace {
+    fn drop(&mut self) {
+        if let Some(ref mut file) = self.file {
+            let _ = file.write_all(b"]\n");
+        }
+    }
+}
+
+/// Creation state of the live objects, which pipeline descriptions refer to.
+#[derive(Debug, Default)]
+struct Descriptions {
+    shader_hashes: HashMap<Id, u64>,
+    attachment_formats: HashMap<Id, Vec<Option<format::Format>>>,
+    pipelines: HashMap<Id, trace::PipelineDebugInfo>,
+}
+
+/// State of the capture, shared by the objects of a device.
+#[derive(Debug)]
+pub(crate) struct Shared {
+    next_id: AtomicU64,
+    /// Per-kind counters of the identifiers, if the capture is deterministic.
+    kind_ids: Option<Vec<AtomicU64>>,
+    memory_types: Vec<adapter::MemoryType>,
+    trace: Mutex<Trace>,
+    mappings: Mutex<HashMap<Id, Mapping>>,
+    descriptions: Mutex<Descriptions>,
+}
+
+impl Shared {
+    pub fn next_id(&self, kind: ObjectKind) -> Id {
+        match self.kind_ids {
+            Some(ref kind_ids) => {
+                kind.make_id(kind_ids[kind as usize].fetch_add(1, Ordering::Relaxed))
+            }
+            None => self.next_id.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+
+    pub fn is_deterministic(&self) -> bool {
+        self.kind_ids.is_some()
+    }
+
+    pub fn add(&self, action: Action) {
+        self.trace.lock().add(action);
+    }
+
+    pub fn make_file(&self, extension: &str, data: &[u8]) -> FileName {
+        self.trace.lock().make_file(extension, data)
+    }
+
+    pub fn memory_properties(&self, memory_type: hal::MemoryTypeId) -> hal::memory::Properties {
+        self.memory_types[memory_type.0].properties
+    }
+
+    pub fn map(&self, memory: Id, size: u64, ptr: *mut u8, range: Range<u64>) {
+        let mut mappings = self.mappings.lock();
+        let mapping = mappings.entry(memory).or_insert_with(|| Mapping {
+            ptr: None,
+            range: 0..0,
+            shadow: vec![0; size as usize],
+        });
+        mapping.ptr = Some(ptr);
+        mapping.range = range;
+    }
+
+    pub fn unmap(&self, memory: Id) {
+        self.capture_memory(memory, 0..!0);
+        if let Some(mapping) = self.mappings.lock().get_mut(&memory) {
+            mapping.ptr = None;
+        }
+    }
+
+    pub fn free_memory(&self, memory: Id) {
+        self.mappings.lock().remove(&memory);
+    }
+
+    pub fn describe_shader_module(&self, id: Id, spirv: &[u8]) {
+        self.descriptions
+            .lock()
+            .shader_hashes
+            .insert(id, trace::hash_bytes(spirv));
+    }
+
+    pub fn describe_render_pass(&self, id: Id, formats: Vec<Option<format::Format>>) {
+        self.descriptions
+            .lock()
+            .attachment_formats
+            .insert(id, formats);
+    }
+
+    /// Remember the pipeline state, resolving the shader modules and the render pass
+    /// it refers to, since those can be destroyed before the pipeline.
+    pub fn describe_pipeline(&self, id: Id, desc: trace::PipelineDesc) {
+        let mut descriptions = self.descriptions.lock();
+        let mut shader_hashes = Vec::new();
+        for ep in desc.entry_points() {
+            match descriptions.shader_hashes.get(&ep.module) {
+                Some(&hash) => shader_hashes.push((ep.module, hash)),
+                None => warn!("Shader module {} is not described", ep.module),
+            }
+        }
+        let attachment_formats = match desc {
+            trace::PipelineDesc::Graphics(ref desc) => descriptions
+                .attachment_formats
+                .get(&desc.subpass.0)
+                .cloned()
+                .unwrap_or_default(),
+            trace::PipelineDesc::Compute(_) => Vec::new(),
+        };
+        let info = trace::PipelineDebugInfo {
+            id,
+            desc,
+            attachment_formats,
+            shader_hashes,
+        };
+        descriptions.pipelines.insert(id, info);
+    }
+
+    pub fn pipeline_description(&self, id: Id) -> Option<trace::PipelineDebugInfo> {
+        self.descriptions.lock().pipelines.get(&id).cloned()
+    }
+
+    /// Forget the description of a destroyed object.
+    pub fn forget(&self, id: Id) {
+        let mut descriptions = self.descriptions.lock();
+        descriptions.shader_hashes.remove(&id);
+        descriptions.attachment_formats.remove(&id);
+        descriptions.pipelines.remove(&id);
+    }
+
+    /// Record the host writes into the mapped part of `range` of the memory.
+    pub fn capture_memory(&self, memory: Id, range: Range<u64>) {
+        let mut mappings = self.mappings.lock();
+        let mapping = match mappings.get_mut(&memory) {
+            Some(mapping) => mapping,
+            None => return,
+        };
+        let ptr = match mapping.ptr {
+            Some(ptr) => ptr,
+            None => return,
+        };
+        let start = range.start.max(mapping.range.start);
+        let end = range.end.min(mapping.range.end);
+        if start >= end {
+            return;
+        }
+        let base = mapping.range.start;
+        let data = unsafe {
+            slice::from_raw_parts(ptr, (mapping.range.end - mapping.range.start) as usize)
+        };
+
+        let mut runs = Vec::new();
+        let mut run_start = None;
+        let mut offset = start;
+        while offset < end {
+            let chunk_end = (offset + CHUNK_SIZE).min(end);
+            let changed = data[(offset - base) as usize..(chunk_end - base) as usize]
+                != mapping.shadow[offset as usize..chunk_end as usize];
+            match (changed, run_start) {
+                (true, None) => run_start = Some(offset),
+                (false, Some(run)) => {
+                    runs.push(run..offset);
+                    run_start = None;
+                }
+                _ => {}
+            }
+            offset = chunk_end;
+        }
+        if let Some(run) = run_start {
+            runs.push(run..end);
+        }
+
+        let mut trace = self.trace.lock();
+        for run in runs {
+            let bytes = &data[(run.start - base) as usize..(run.end - base) as usize];
+            mapping.shadow[run.start as usize..run.end as usize].copy_from_slice(bytes);
+            let data = trace.make_file("bin", bytes);
+            trace.add(Action::WriteMemory {
+                id: memory,
+                offset: run.start,
+                data,
+            });
+        }
+    }
+
+    /// Record the host writes into all the mapped memory.
+    pub fn capture_mapped_memory(&self) {
+        let ids = self
+            .mappings
+            .lock()
+            .iter()
+            .filter(|&(_, mapping)| mapping.ptr.is_some())
+            .map(|(&id, _)| id)
+            .collect::<Vec<_>>();
+        for id in ids {
+            self.capture_memory(id, 0..!0);
+        }
+    }
+}
+
+/// Capturing wrapper around the backend `B`.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Backend<B>(PhantomData<B>);
+
+impl<B: hal::Backend> hal::Backend for Backend<B> {
+    type Instance = Instance<B>;
+    type PhysicalDevice = PhysicalDevice<B>;
+    type Device = Device<B>;
+    type Surface = Surface<B>;
+
+    type QueueFamily = B::QueueFamily;
+    type Queue = Queue<B>;
+    type CommandBuffer = CommandBuffer<B>;
+
+    type Memory = Memory<B>;
+    type CommandPool = CommandPool<B>;
+
+    type ShaderModule = Handle<B::ShaderModule>;
+    type RenderPass = Handle<B::RenderPass>;
+    type Framebuffer = Handle<B::Framebuffer>;
+
+    type Buffer = Handle<B::Buffer>;
+    type BufferView = Handle<B::BufferView>;
+    type Image = Image<B>;
+    type ImageView = ImageView<B>;
+    type Sampler = Handle<B::Sampler>;
+    type SamplerYcbcrConversion = Handle<B::SamplerYcbcrConversion>;
+
+    type ComputePipeline = Handle<B::ComputePipeline>;
+    type GraphicsPipeline = Handle<B::GraphicsPipeline>;
+    type PipelineCache = Handle<B::PipelineCache>;
+    type PipelineLayout = Handle<B::PipelineLayout>;
+    type DescriptorPool = DescriptorPool<B>;
+    type DescriptorSet = Handle<B::DescriptorSet>;
+    type DescriptorSetLayout = Handle<B::DescriptorSetLayout>;
+
+    type Fence = Handle<B::Fence>;
+    type Semaphore = Handle<B::Semaphore>;
+    type Event = Handle<B::Event>;
+    type QueryPool = Handle<B::QueryPool>;
+}
+
+pub struct Instance<B: hal::Backend> {
+    raw: B::Instance,
+    capture_dir: PathBuf,
+    deterministic: bool,
+    next_surface_id: AtomicU64,
+}
+
+impl<B: hal::Backend> fmt::Debug for Instance<B> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("Instance")
+    }
+}
+
+impl<B: hal::Backend> Instance<B> {
+    /// Change the directory, into which the devices opened from adapters
+    /// enumerated after this call write their traces.
+    ///
+    /// Defaults to the value of `GFX_CAPTURE_DIR`, or `gfx-capture` if it's not set.
+    pub fn set_capture_dir(&mut self, dir: impl AsRef<Path>) {
+        self.capture_dir = dir.as_ref().to_path_buf();
+    }
+
+    /// Enable or disable the deterministic mode for the devices opened from
+    /// adapters enumerated after this call, and the surfaces created after it.
+    ///
+    /// In this mode the identifiers of the objects are namespaced by their
+    /// `trace::ObjectKind`, so they only depend on the order of creation
+    /// of the objects of the same kind, and the recorded command buffers
+    /// are hashed with `trace::hash_commands`.
+    ///
+    /// Disabled by default.
+    pub fn set_deterministic(&mut self, deterministic: bool) {
+        self.deterministic = deterministic;
+    }
+}
+
+impl<B: hal::Backend> hal::Instance<Backend<B>> for Instance<B> {
+    fn create(name: &str, version: u32) -> Result<Self, hal::UnsupportedBackend> {
+        Ok(Instance {
+            raw: B::Instance::create(name, version)?,
+            capture_dir: env::var_os(CAPTURE_DIR_VAR)
+                .map_or_else(|| PathBuf::from(DEFAULT_CAPTURE_DIR), PathBuf::from),
+            deterministic: false,
+            next_surface_id: AtomicU64::new(0),
+        })
+    }
+
+    fn enumerate_adapters(&self) -> Vec<adapter::Adapter<Backend<B>>> {
+        self.raw
+            .enumerate_adapters()
+            .into_iter()
+            .map(|adapter| adapter::Adapter {
+                info: adapter.info.clone(),
+                physical_device: PhysicalDevice {
+                    raw: adapter.physical_device,
+                    info: adapter.info,
+                    capture_dir: self.capture_dir.clone(),
+                    deterministic: self.deterministic,
+                },
+                queue_families: adapter.queue_families,
+            })
+            .collect()
+    }
+
+    unsafe fn create_surface(
+        &self,
+        has_handle: &impl raw_window_handle::HasRawWindowHandle,
+    ) -> Result<Surface<B>, window::InitError> {
+        self.raw.create_surface(has_handle).map(|raw| Surface {
+            raw,
+            id: if self.deterministic {
+                ObjectKind::Surface.make_id(self.next_surface_id.fetch_add(1, Ordering::Relaxed))
+            } else {
+                NEXT_SURFACE_ID.fetch_add(1, Ordering::Relaxed)
+            },
+            shared: None,
+        })
+    }
+
+    unsafe fn destroy_surface(&self, surface: Surface<B>) {
+        self.raw.destroy_surface(surface.raw);
+    }
+}
+
+#[derive(Debug)]
+pub struct PhysicalDevice<B: hal::Backend> {
+    raw: B::PhysicalDevice,
+    info: adapter::AdapterInfo,
+    capture_dir: PathBuf,
+    deterministic: bool,
+}
+
+impl<B: hal::Backend> adapter::PhysicalDevice<Backend<B>> for PhysicalDevice<B> {
+    unsafe fn open(
+        &self,
+        families: &[(&B::QueueFamily, &[queue::QueuePriority])],
+        requested_features: Features,
+    ) -> Result<adapter::Gpu<Backend<B>>, hal::device::CreationError> {
+        let trace = Trace::new(&self.capture_dir).map_err(|e| {
+            error!(
+                "Unable to start the capture in {:?}: {:?}",
+                self.capture_dir, e
+            );
+            hal::device::CreationError::InitializationFailed
+        })?;
+        let gpu = self.raw.open(families, requested_features)?;
+        let shared = Arc::new(Shared {
+            next_id: AtomicU64::new(0),
+            kind_ids: if self.deterministic {
+                Some((0..ObjectKind::COUNT).map(|_| AtomicU64::new(0)).collect())
+            } else {
+                None
+            },
+            memory_types: self.raw.memory_properties().memory_types,
+            trace: Mutex::new(trace),
+            mappings: Mutex::new(HashMap::new()),
+            descriptions: Mutex::new(Descriptions::default()),
+        });
+        shared.add(Action::Init {
+            adapter: self.info.clone(),
+            features: requested_features,
+        });
+
+        Ok(adapter::Gpu {
+            device: Device::new(gpu.device, Arc::clone(&shared)),
+            queue_groups: gpu
+                .queue_groups
+                .into_iter()
+                .map(|group| queue::QueueGroup {
+                    family: group.family,
+                    queues: group
+                        .queues
+                        .into_iter()
+                        .map(|raw| Queue {
+                            raw,
+                            shared: Arc::clone(&shared),
+                        })
+                        .collect(),
+                })
+                .collect(),
+        })
+    }
+
+    fn format_properties(&self, format: Option<format::Format>) -> format::Properties {
+        self.raw.format_properties(format)
+    }
+
+    fn image_format_properties(
+        &self,
+        format: format::Format,
+        dimensions: u8,
+        tiling: image::Tiling,
+        usage: image::Usage,
+        view_caps: image::ViewCapabilities,
+    ) -> Option<image::FormatProperties> {
+        self.raw
+            .image_format_properties(format, dimensions, tiling, usage, view_caps)
+    }
+
+    fn drm_modifier_properties(&self, format: format::Format) -> Vec<image::DrmModifierProperties> {
+        self.raw.drm_modifier_properties(format)
+    }
+
+    fn cooperative_matrix_properties(&self) -> Vec<adapter::CooperativeMatrixProperties> {
+        self.raw.cooperative_matrix_properties()
+    }
+
+    fn memory_properties(&self) -> adapter::MemoryProperties {
+        self.raw.memory_properties()
+    }
+
+    fn features(&self) -> Features {
+        self.raw.features()
+    }
+
+    fn capabilities(&self) -> hal::Capabilities {
+        self.raw.capabilities()
+    }
+
+    fn limits(&self) -> Limits {
+        self.raw.limits()
+    }
+
+    fn is_valid_cache(&self, cache: &[u8]) -> bool {
+        self.raw.is_valid_cache(cache)
+    }
+}
+
+#[derive(Debug)]
+pub struct Queue<B: hal::Backend> {
+    raw: B::Queue,
+    shared: Arc<Shared>,
+}
+
+impl<B: hal::Backend> Queue<B> {
+    fn record_submit<'a>(
+        &self,
+        command_buffers: &[&'a CommandBuffer<B>],
+        wait_semaphores: &[(&'a Handle<B::Semaphore>, hal::pso::PipelineStage)],
+        signal_semaphores: &[&'a Handle<B::Semaphore>],
+        fence: Option<&Handle<B::Fence>>,
+        protected: bool,
+    ) {
+        self.shared.capture_mapped_memory();
+        self.shared.add(Action::Submit {
+            command_buffers: command_buffers.iter().map(|cmd_buf| cmd_buf.id).collect(),
+            wait_semaphores: wait_semaphores
+                .iter()
+                .map(|&(semaphore, stage)| (semaphore.id, stage))
+                .collect(),
+            signal_semaphores: signal_semaphores
+                .iter()
+                .map(|semaphore| semaphore.id)
+                .collect(),
+            fence: fence.map(|fence| fence.id),
+            protected,
+        });
+    }
+}
+
+impl<B: hal::Backend> queue::Queue<Backend<B>> for Queue<B> {
+    unsafe fn submit<'a, Ic, Iw, Is>(
+        &mut self,
+        command_buffers: Ic,
+        wait_semaphores: Iw,
+        signal_semaphores: Is,
+        fence: Option<&mut Handle<B::Fence>>,
+    ) -> Result<(), queue::SubmitError>
+    where
+        Ic: Iterator<Item = &'a CommandBuffer<B>>,
+        Iw: Iterator<Item = (&'a Handle<B::Semaphore>, hal::pso::PipelineStage)>,
+        Is: Iterator<Item = &'a Handle<B::Semaphore>>,
+    {
+        let command_buffers = command_buffers.collect::<Vec<_>>();
+        let wait_semaphores = wait_semaphores.collect::<Vec<_>>();
+        let signal_semaphores = signal_semaphores.collect::<Vec<_>>();
+        self.record_submit(
+            &command_buffers,
+            &wait_semaphores,
+            &signal_semaphores,
+            fence.as_ref().map(|fence| &**fence),
+            false,
+        );
+        self.raw.submit(
+            command_buffers.into_iter().map(|cmd_buf| &cmd_buf.raw),
+            wait_semaphores
+                .into_iter()
+                .map(|(semaphore, stage)| (&semaphore.raw, stage)),
+            signal_semaphores
+                .into_iter()
+                .map(|semaphore| &semaphore.raw),
+            fence.map(|fence| &mut fence.raw),
+        )
+    }
+
+    unsafe fn submit_protected<'a, Ic, Iw, Is>(
+        &mut self,
+        command_buffers: Ic,
+        wait_semaphores: Iw,
+        signal_semaphores: Is,
+        fence: Option<&mut Handle<B::Fence>>,
+    ) -> Result<(), queue::SubmitError>
+    where
+        Ic: Iterator<Item = &'a CommandBuffer<B>>,
+        Iw: Iterator<Item = (&'a Handle<B::Semaphore>, hal::pso::PipelineStage)>,
+        Is: Iterator<Item = &'a Handle<B::Semaphore>>,
+    {
+        let command_buffers = command_buffers.collect::<Vec<_>>();
+        let wait_semaphores = wait_semaphores.collect::<Vec<_>>();
+        let signal_semaphores = signal_semaphores.collect::<Vec<_>>();
+        self.record_submit(
+            &command_buffers,
+            &wait_semaphores,
+            &signal_semaphores,
+            fence.as_ref().map(|fence| &**fence),
+            true,
+        );
+        self.raw.submit_protected(
+            command_buffers.into_iter().map(|cmd_buf| &cmd_buf.raw),
+            wait_semaphores
+                .into_iter()
+                .map(|(semaphore, stage)| (&semaphore.raw, stage)),
+            signal_semaphores
+                .into_iter()
+                .map(|semaphore| &semaphore.raw),
+            fence.map(|fence| &mut fence.raw),
+        )
+    }
+
+    unsafe fn present(
+        &mut self,
+        surface: &mut Surface<B>,
+        image: SwapchainImage<B>,
+        wait_semaphore: Option<&mut Handle<B::Semaphore>>,
+    ) -> Result<Option<window::Suboptimal>, window::PresentError> {
+        self.shared.add(Action::Present {
+            surface: surface.id,
+            wait_semaphore: wait_semaphore.as_ref().map(|semaphore| semaphore.id),
+        });
+        self.raw.present(
+            &mut surface.raw,
+            *image.raw,
+            wait_semaphore.map(|semaphore| &mut semaphore.raw),
+        )
+    }
+
+    unsafe fn present_with_time(
+        &mut self,
+        surface: &mut Surface<B>,
+        image: SwapchainImage<B>,
+        wait_semaphore: Option<&mut Handle<B::Semaphore>>,
+        time: window::PresentTime,
+    ) -> Result<Option<window::Suboptimal>, window::PresentError> {
+        self.shared.add(Action::Present {
+            surface: surface.id,
+            wait_semaphore: wait_semaphore.as_ref().map(|semaphore| semaphore.id),
+        });
+        self.raw.present_with_time(
+            &mut surface.raw,
+            *image.raw,
+            wait_semaphore.map(|semaphore| &mut semaphore.raw),
+            time,
+        )
+    }
+
+    fn wait_idle(&mut self) -> Result<(), hal::device::WaitError> {
+        self.shared.add(Action::WaitIdle);
+        self.raw.wait_idle()
+    }
+
+    fn timestamp_period(&self) -> f32 {
+        self.raw.timestamp_period()
+    }
+}
+
+#[derive(Debug)]
+pub struct Surface<B: hal::Backend> {
+    raw: B::Surface,
+    id: Id,
+    /// Capture of the device the swapchain is configured with.
+    shared: Option<Arc<Shared>>,
+}
+
+impl<B: hal::Backend> window::Surface<Backend<B>> for Surface<B> {
+    fn supports_queue_family(&self, family: &B::QueueFamily) -> bool {
+        self.raw.supports_queue_family(family)
+    }
+
+    fn capabilities(&self, physical_device: &PhysicalDevice<B>) -> window::SurfaceCapabilities {
+        self.raw.capabilities(&physical_device.raw)
+    }
+
+    fn supported_formats(
+        &self,
+        physical_device: &PhysicalDevice<B>,
+    ) -> Option<Vec<format::Format>> {
+        self.raw.supported_formats(&physical_device.raw)
+    }
+}
+
+type RawSwapchainImage<B> =
+    <<B as hal::Backend>::Surface as window::PresentationSurface<B>>::SwapchainImage;
+
+/// Acquired swapchain image, with the wrappers of its image and view.
+#[derive(Debug)]
+pub struct SwapchainImage<B: hal::Backend> {
+    image: Image<B>,
+    view: ImageView<B>,
+    /// Boxed to keep the address borrowed by `image` and `view`.
+    raw: Box<RawSwapchainImage<B>>,
+}
+
+impl<B: hal::Backend> Borrow<Image<B>> for SwapchainImage<B> {
+    fn borrow(&self) -> &Image<B> {
+        &self.image
+    }
+}
+
+impl<B: hal::Backend> Borrow<ImageView<B>> for SwapchainImage<B> {
+    fn borrow(&self) -> &ImageView<B> {
+        &self.view
+    }
+}
+
+impl<B: hal::Backend> window::PresentationSurface<Backend<B>> for Surface<B> {
+    type SwapchainImage = SwapchainImage<B>;
+
+    unsafe fn configure_swapchain(
+        &mut self,
+        device: &Device<B>,
+        config: window::SwapchainConfig,
+    ) -> Result<(), window::SwapchainError> {
+        self.raw.configure_swapchain(&device.raw, config.clone())?;
+        device.shared.add(Action::ConfigureSwapchain {
+            surface: self.id,
+            config,
+        });
+        self.shared = Some(Arc::clone(&device.shared));
+        Ok(())
+    }
+
+    unsafe fn unconfigure_swapchain(&mut self, device: &Device<B>) {
+        device.shared.add(Action::UnconfigureSwapchain(self.id));
+        self.shared = None;
+        self.raw.unconfigure_swapchain(&device.raw)
+    }
+
+    unsafe fn acquire_image(
+        &mut self,
+        timeout_ns: u64,
+    ) -> Result<(SwapchainImage<B>, Option<window::Suboptimal>), window::AcquireError> {
+        let (raw, suboptimal) = self.raw.acquire_image(timeout_ns)?;
+        let raw = Box::new(raw);
+        let shared = self.shared.as_ref().expect("Swapchain is not configured");
+        let image = Handle {
+            raw: Slot::Swapchain(Borrow::<B::Image>::borrow(&*raw) as *const _),
+            id: shared.next_id(ObjectKind::Image),
+        };
+        let view = ImageView {
+            raw: Slot::Swapchain(Borrow::<B::ImageView>::borrow(&*raw) as *const _),
+            id: shared.next_id(ObjectKind::ImageView),
+        };
+        shared.add(Action::AcquireImage {
+            surface: self.id,
+            image: image.id,
+            view: view.id,
+        });
+        Ok((SwapchainImage { image, view, raw }, suboptimal))
+    }
+
+    unsafe fn set_present_mode(
+        &mut self,
+        mode: window::PresentMode,
+    ) -> Result<(), window::PresentModeIncompatible> {
+        self.raw.set_present_mode(mode)
+    }
+
+    unsafe fn acquire_full_screen_exclusive(
+        &mut self,
+    ) -> Result<(), window::FullScreenExclusiveError> {
+        self.raw.acquire_full_screen_exclusive()
+    }
+
+    unsafe fn release_full_screen_exclusive(
+        &mut self,
+    ) -> Result<(), window::FullScreenExclusiveError> {
+        self.raw.release_full_screen_exclusive()
+    }
+
+    unsafe fn get_refresh_duration(&mut self) -> Option<u64> {
+        self.raw.get_refresh_duration()
+    }
+
+    unsafe fn get_past_presentation_timing(&mut self) -> Vec<window::PresentationTiming> {
+        self.raw.get_past_presentation_timing()
+    }
+
+    unsafe fn set_hdr_metadata(
+        &mut self,
+        metadata: &window::HdrMetadata,
+    ) -> Result<(), window::HdrMetadataUnsupported> {
+        self.raw.set_hdr_metadata(metadata)
+    }
+}
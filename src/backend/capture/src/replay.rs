@@ -0,0 +1,1819 @@
+//! Replay of the captured traces on any backend.
+//!
+//! The memory of the trace is virtual: every buffer and image gets a dedicated
+//! allocation on the replaying device, since the memory requirements and types
+//! of the capturing backend don't carry over. The host writes of the trace are
+//! applied to the buffers bound to the written ranges. Presentation targets
+//! offscreen images, one per configured surface.
+
+use crate::trace::{self, Action, Command, FileName, Id};
+
+use hal::{
+    adapter,
+    command::{self, CommandBuffer as _},
+    device::Device as _,
+    format, image, memory, pass,
+    pool::CommandPool as _,
+    pso::{self, DescriptorPool as _},
+    query,
+    queue::{Queue as _, QueueFamilyId},
+    Backend,
+};
+
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    error::Error,
+    fmt, fs, io, iter,
+    ops::Range,
+    path::{Path, PathBuf},
+    ptr,
+};
+
+/// Error of loading or replaying a trace.
+#[derive(Debug)]
+pub enum ReplayError {
+    /// Failed to read the trace or its data files.
+    Io(io::Error),
+    /// Failed to parse the trace.
+    Parse(ron::de::Error),
+    /// The trace refers to an object that doesn't exist.
+    UnknownObject(Id),
+    /// The replaying device failed to execute an action.
+    Device(String),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ReplayError::Io(ref err) => write!(formatter, "IO error: {}", err),
+            ReplayError::Parse(ref err) => write!(formatter, "Parse error: {}", err),
+            ReplayError::UnknownObject(id) => write!(formatter, "Unknown object {}", id),
+            ReplayError::Device(ref message) => write!(formatter, "Device error: {}", message),
+        }
+    }
+}
+
+impl Error for ReplayError {}
+
+impl From<io::Error> for ReplayError {
+    fn from(err: io::Error) -> Self {
+        ReplayError::Io(err)
+    }
+}
+
+fn device_error(err: impl fmt::Display) -> ReplayError {
+    ReplayError::Device(err.to_string())
+}
+
+fn get<T>(map: &HashMap<Id, T>, id: Id) -> Result<&T, ReplayError> {
+    map.get(&id).ok_or(ReplayError::UnknownObject(id))
+}
+
+fn get_mut<T>(map: &mut HashMap<Id, T>, id: Id) -> Result<&mut T, ReplayError> {
+    map.get_mut(&id).ok_or(ReplayError::UnknownObject(id))
+}
+
+fn take<T>(map: &mut HashMap<Id, T>, id: Id) -> Result<T, ReplayError> {
+    map.remove(&id).ok_or(ReplayError::UnknownObject(id))
+}
+
+/// Loads the list of actions from a capture directory.
+pub fn load(dir: impl AsRef<Path>) -> Result<Vec<Action>, ReplayError> {
+    let string = fs::read_to_string(dir.as_ref().join(trace::TRACE_FILE))?;
+    ron::de::from_str(&string).map_err(ReplayError::Parse)
+}
+
+/// Memory object of the trace.
+#[derive(Debug)]
+struct VirtualMemory {
+    properties: memory::Properties,
+    /// Contents written by the host, allocated on the first write.
+    contents: Vec<u8>,
+    size: u64,
+    /// Buffers bound to the memory, with their offsets.
+    buffers: Vec<(Id, u64)>,
+}
+
+/// Resource with its dedicated memory.
+#[derive(Debug)]
+pub(crate) struct Resource<B: Backend, T> {
+    raw: T,
+    size: u64,
+    pub(crate) memory: Option<B::Memory>,
+}
+
+/// Offscreen image replacing the swapchain of a surface.
+#[derive(Debug)]
+struct Offscreen<B: Backend> {
+    image: B::Image,
+    view: B::ImageView,
+    memory: B::Memory,
+}
+
+#[derive(Debug)]
+pub(crate) struct Objects<B: Backend> {
+    memories: HashMap<Id, VirtualMemory>,
+    pub(crate) buffers: HashMap<Id, Resource<B, B::Buffer>>,
+    buffer_views: HashMap<Id, B::BufferView>,
+    images: HashMap<Id, Resource<B, B::Image>>,
+    image_views: HashMap<Id, B::ImageView>,
+    samplers: HashMap<Id, B::Sampler>,
+    ycbcr_conversions: HashMap<Id, B::SamplerYcbcrConversion>,
+    render_passes: HashMap<Id, B::RenderPass>,
+    framebuffers: HashMap<Id, B::Framebuffer>,
+    pipeline_layouts: HashMap<Id, B::PipelineLayout>,
+    pipeline_caches: HashMap<Id, B::PipelineCache>,
+    shader_modules: HashMap<Id, B::ShaderModule>,
+    graphics_pipelines: HashMap<Id, B::GraphicsPipeline>,
+    compute_pipelines: HashMap<Id, B::ComputePipeline>,
+    descriptor_set_layouts: HashMap<Id, B::DescriptorSetLayout>,
+    descriptor_pools: HashMap<Id, B::DescriptorPool>,
+    /// Descriptor sets, along with their pools.
+    descriptor_sets: HashMap<Id, (Id, B::DescriptorSet)>,
+    semaphores: HashMap<Id, B::Semaphore>,
+    fences: HashMap<Id, B::Fence>,
+    events: HashMap<Id, B::Event>,
+    query_pools: HashMap<Id, B::QueryPool>,
+    command_pools: HashMap<Id, B::CommandPool>,
+    surfaces: HashMap<Id, Offscreen<B>>,
+    /// Acquired swapchain images and views, with their surfaces.
+    acquired: HashMap<Id, Id>,
+}
+
+impl<B: Backend> Default for Objects<B> {
+    fn default() -> Self {
+        Objects {
+            memories: HashMap::new(),
+            buffers: HashMap::new(),
+            buffer_views: HashMap::new(),
+            images: HashMap::new(),
+            image_views: HashMap::new(),
+            samplers: HashMap::new(),
+            ycbcr_conversions: HashMap::new(),
+            render_passes: HashMap::new(),
+            framebuffers: HashMap::new(),
+            pipeline_layouts: HashMap::new(),
+            pipeline_caches: HashMap::new(),
+            shader_modules: HashMap::new(),
+            graphics_pipelines: HashMap::new(),
+            compute_pipelines: HashMap::new(),
+            descriptor_set_layouts: HashMap::new(),
+            descriptor_pools: HashMap::new(),
+            descriptor_sets: HashMap::new(),
+            semaphores: HashMap::new(),
+            fences: HashMap::new(),
+            events: HashMap::new(),
+            query_pools: HashMap::new(),
+            command_pools: HashMap::new(),
+            surfaces: HashMap::new(),
+            acquired: HashMap::new(),
+        }
+    }
+}
+
+impl<B: Backend> Objects<B> {
+    fn buffer(&self, id: Id) -> Result<&B::Buffer, ReplayError> {
+        get(&self.buffers, id).map(|buffer| &buffer.raw)
+    }
+
+    fn image(&self, id: Id) -> Result<&B::Image, ReplayError> {
+        match self.acquired.get(&id) {
+            Some(&surface) => get(&self.surfaces, surface).map(|offscreen| &offscreen.image),
+            None => get(&self.images, id).map(|image| &image.raw),
+        }
+    }
+
+    fn image_view(&self, id: Id) -> Result<&B::ImageView, ReplayError> {
+        match self.acquired.get(&id) {
+            Some(&surface) => get(&self.surfaces, surface).map(|offscreen| &offscreen.view),
+            None => get(&self.image_views, id),
+        }
+    }
+
+    fn entry_point<'a>(
+        &'a self,
+        entry_point: &'a trace::EntryPoint,
+    ) -> Result<pso::EntryPoint<'a, B>, ReplayError> {
+        Ok(pso::EntryPoint {
+            entry: &entry_point.entry,
+            module: get(&self.shader_modules, entry_point.module)?,
+            specialization: pso::Specialization {
+                constants: Cow::Borrowed(&entry_point.specialization_constants),
+                data: Cow::Borrowed(&entry_point.specialization_data),
+            },
+        })
+    }
+
+    fn base_pipeline<'a, P>(
+        pipelines: &'a HashMap<Id, P>,
+        parent: &trace::BasePipeline,
+    ) -> Result<pso::BasePipeline<'a, P>, ReplayError> {
+        Ok(match *parent {
+            trace::BasePipeline::Pipeline(id) => pso::BasePipeline::Pipeline(get(pipelines, id)?),
+            trace::BasePipeline::Index(index) => pso::BasePipeline::Index(index),
+            trace::BasePipeline::None => pso::BasePipeline::None,
+        })
+    }
+
+    fn barrier<'a>(
+        &'a self,
+        barrier: &trace::Barrier,
+    ) -> Result<memory::Barrier<'a, B>, ReplayError> {
+        Ok(match *barrier {
+            trace::Barrier::AllBuffers(ref access) => memory::Barrier::AllBuffers(access.clone()),
+            trace::Barrier::AllImages(ref access) => memory::Barrier::AllImages(access.clone()),
+            trace::Barrier::Buffer {
+                ref states,
+                target,
+                ref range,
+                ref families,
+            } => memory::Barrier::Buffer {
+                states: states.clone(),
+                target: self.buffer(target)?,
+                range: range.clone(),
+                families: families.clone(),
+            },
+            trace::Barrier::Image {
+                ref states,
+                target,
+                ref range,
+                ref families,
+            } => memory::Barrier::Image {
+                states: states.clone(),
+                target: self.image(target)?,
+                range: range.clone(),
+                families: families.clone(),
+            },
+            trace::Barrier::Aliasing {
+                ref before,
+                ref after,
+            } => memory::Barrier::Aliasing {
+                before: before
+                    .as_ref()
+                    .map(|resource| self.aliased_resource(resource))
+                    .transpose()?,
+                after: after
+                    .as_ref()
+                    .map(|resource| self.aliased_resource(resource))
+                    .transpose()?,
+            },
+        })
+    }
+
+    fn aliased_resource<'a>(
+        &'a self,
+        resource: &trace::AliasedResource,
+    ) -> Result<memory::AliasedResource<'a, B>, ReplayError> {
+        Ok(match *resource {
+            trace::AliasedResource::Buffer(id) => memory::AliasedResource::Buffer(self.buffer(id)?),
+            trace::AliasedResource::Image(id) => memory::AliasedResource::Image(self.image(id)?),
+        })
+    }
+
+    fn query(&self, query: trace::Query) -> Result<query::Query<B>, ReplayError> {
+        Ok(query::Query {
+            pool: get(&self.query_pools, query.0)?,
+            id: query.1,
+        })
+    }
+}
+
+/// Player of the captured actions on a device.
+#[derive(Debug)]
+pub struct Player<B: Backend> {
+    dir: PathBuf,
+    memory_types: Vec<adapter::MemoryType>,
+    family: QueueFamilyId,
+    pub(crate) objects: Objects<B>,
+    /// Command buffers, along with their pools.
+    command_buffers: HashMap<Id, (Id, B::CommandBuffer)>,
+}
+
+impl<B: Backend> Player<B> {
+    /// Create a player of the trace in `dir`, on a device with the given memory
+    /// properties, submitting to a queue of `family`.
+    pub fn new(
+        dir: impl AsRef<Path>,
+        memory_properties: &adapter::MemoryProperties,
+        family: QueueFamilyId,
+    ) -> Self {
+        Player {
+            dir: dir.as_ref().to_path_buf(),
+            memory_types: memory_properties.memory_types.clone(),
+            family,
+            objects: Objects::default(),
+            command_buffers: HashMap::new(),
+        }
+    }
+
+    fn read_file(&self, name: &FileName) -> Result<Vec<u8>, ReplayError> {
+        Ok(fs::read(self.dir.join(name))?)
+    }
+
+    /// Find the memory type matching the captured properties best.
+    fn memory_type(
+        &self,
+        requirements: &memory::Requirements,
+        properties: memory::Properties,
+    ) -> Result<hal::MemoryTypeId, ReplayError> {
+        let allowed = |&(index, _): &(usize, &adapter::MemoryType)| {
+            requirements.type_mask & (1 << index) != 0
+        };
+        self.memory_types
+            .iter()
+            .enumerate()
+            .filter(allowed)
+            .find(|&(_, ty)| ty.properties.contains(properties))
+            .or_else(|| {
+                self.memory_types
+                    .iter()
+                    .enumerate()
+                    .filter(allowed)
+                    .find(|&(_, ty)| {
+                        ty.properties
+                            .contains(properties & memory::Properties::CPU_VISIBLE)
+                    })
+            })
+            .map(|(index, _)| index.into())
+            .ok_or_else(|| ReplayError::Device("No compatible memory type".to_string()))
+    }
+
+    /// Allocate memory for a single resource, returning it
+    /// along with the properties of the chosen memory type.
+    unsafe fn allocate_dedicated(
+        &self,
+        device: &B::Device,
+        resource: memory::DedicatedResource<B>,
+        requirements: memory::Requirements,
+        properties: memory::Properties,
+    ) -> Result<(B::Memory, memory::Properties), ReplayError> {
+        let memory_type = self.memory_type(&requirements, properties)?;
+        let memory = device
+            .allocate_dedicated_memory(memory_type, requirements.size, resource)
+            .map_err(device_error)?;
+        Ok((memory, self.memory_types[memory_type.0].properties))
+    }
+
+    /// Upload the host-written contents of `range` of the virtual memory
+    /// into the dedicated memory of a buffer bound at `offset`.
+    unsafe fn upload(
+        device: &B::Device,
+        buffer: &mut Resource<B, B::Buffer>,
+        offset: u64,
+        contents: &[u8],
+        range: Range<u64>,
+    ) -> Result<(), ReplayError> {
+        let start = range.start.max(offset);
+        let end = range.end.min(offset + buffer.size);
+        if start >= end {
+            return Ok(());
+        }
+        let memory = match buffer.memory {
+            Some(ref mut memory) => memory,
+            None => return Ok(()),
+        };
+        let ptr = device
+            .map_memory(memory, memory::Segment::ALL)
+            .map_err(device_error)?;
+        ptr::copy_nonoverlapping(
+            contents[(start - range.start) as usize..].as_ptr(),
+            ptr.offset((start - offset) as isize),
+            (end - start) as usize,
+        );
+        device
+            .flush_mapped_memory_ranges(iter::once((&*memory, memory::Segment::ALL)))
+            .map_err(device_error)?;
+        device.unmap_memory(memory);
+        Ok(())
+    }
+
+    unsafe fn create_offscreen(
+        &self,
+        device: &B::Device,
+        config: &hal::window::SwapchainConfig,
+    ) -> Result<Offscreen<B>, ReplayError> {
+        let kind = image::Kind::D2(config.extent.width, config.extent.height, 1, 1);
+        let mut image = device
+            .create_image(
+                kind,
+                1,
+                config.format,
+                image::Tiling::Optimal,
+                config.image_usage,
+                image::ViewCapabilities::empty(),
+            )
+            .map_err(device_error)?;
+        let requirements = device.get_image_requirements(&image);
+        let (memory, _) = self.allocate_dedicated(
+            device,
+            memory::DedicatedResource::Image(&image),
+            requirements,
+            memory::Properties::DEVICE_LOCAL,
+        )?;
+        device
+            .bind_image_memory(&memory, 0, &mut image)
+            .map_err(device_error)?;
+        let view = device
+            .create_image_view(
+                &image,
+                image::ViewKind::D2,
+                config.format,
+                format::Swizzle::NO,
+                image::SubresourceRange {
+                    aspects: format::Aspects::COLOR,
+                    ..Default::default()
+                },
+            )
+            .map_err(device_error)?;
+        Ok(Offscreen {
+            image,
+            view,
+            memory,
+        })
+    }
+
+    unsafe fn destroy_offscreen(device: &B::Device, offscreen: Offscreen<B>) {
+        device.destroy_image_view(offscreen.view);
+        device.destroy_image(offscreen.image);
+        device.free_memory(offscreen.memory);
+    }
+
+    /// Play a single action on the device and the queue.
+    ///
+    /// # Safety
+    ///
+    /// The actions have to be played in the order of the trace,
+    /// on the same device and queue.
+    pub unsafe fn play(
+        &mut self,
+        device: &B::Device,
+        queue: &mut B::Queue,
+        action: Action,
+    ) -> Result<(), ReplayError> {
+        let objects = &mut self.objects;
+        match action {
+            Action::Init { adapter, features } => {
+                info!(
+                    "Replaying a trace of {:?} with features {:?}",
+                    adapter, features
+                );
+            }
+            Action::AllocateMemory {
+                id,
+                properties,
+                size,
+            } => {
+                objects.memories.insert(
+                    id,
+                    VirtualMemory {
+                        properties,
+                        contents: Vec::new(),
+                        size,
+                        buffers: Vec::new(),
+                    },
+                );
+            }
+            Action::WriteMemory { id, offset, data } => {
+                let data = self.read_file(&data)?;
+                let objects = &mut self.objects;
+                let memory = get_mut(&mut objects.memories, id)?;
+                if memory.contents.is_empty() {
+                    memory.contents = vec![0; memory.size as usize];
+                }
+                let range = offset..offset + data.len() as u64;
+                memory.contents[range.start as usize..range.end as usize].copy_from_slice(&data);
+                for &(buffer, buffer_offset) in memory.buffers.iter() {
+                    let buffer = get_mut(&mut objects.buffers, buffer)?;
+                    Self::upload(device, buffer, buffer_offset, &data, range.clone())?;
+                }
+            }
+            Action::FreeMemory(id) => {
+                take(&mut objects.memories, id)?;
+            }
+            Action::CopyMemoryToImage {
+                image,
+                layout,
+                data,
+                regions,
+            } => {
+                let data = self.read_file(&data)?;
+                device
+                    .copy_memory_to_image(
+                        &data,
+                        self.objects.image(image)?,
+                        layout,
+                        regions.into_iter(),
+                    )
+                    .map_err(device_error)?;
+            }
+            Action::CreateCommandPool { id, flags } => {
+                let pool = device
+                    .create_command_pool(self.family, flags)
+                    .map_err(device_error)?;
+                objects.command_pools.insert(id, pool);
+            }
+            Action::ResetCommandPool(id) => {
+                get_mut(&mut objects.command_pools, id)?.reset(false);
+            }
+            Action::DestroyCommandPool(id) => {
+                let mut pool = take(&mut objects.command_pools, id)?;
+                let ids = self
+                    .command_buffers
+                    .iter()
+                    .filter(|&(_, &(pool_id, _))| pool_id == id)
+                    .map(|(&cmd_buf, _)| cmd_buf)
+                    .collect::<Vec<_>>();
+                let command_buffers = &mut self.command_buffers;
+                pool.free(
+                    ids.into_iter()
+                        .filter_map(|id| command_buffers.remove(&id))
+                        .map(|(_, cmd_buf)| cmd_buf),
+                );
+                device.destroy_command_pool(pool);
+            }
+            Action::AllocateCommandBuffers { pool, level, ids } => {
+                let mut raw_list = Vec::with_capacity(ids.len());
+                get_mut(&mut objects.command_pools, pool)?.allocate(
+                    ids.len(),
+                    level,
+                    &mut raw_list,
+                );
+                self.command_buffers.extend(
+                    ids.into_iter()
+                        .zip(raw_list)
+                        .map(|(id, cmd_buf)| (id, (pool, cmd_buf))),
+                );
+            }
+            Action::FreeCommandBuffers { pool, ids } => {
+                let mut cmd_bufs = Vec::with_capacity(ids.len());
+                for id in ids {
+                    cmd_bufs.push(take(&mut self.command_buffers, id)?.1);
+                }
+                get_mut(&mut objects.command_pools, pool)?.free(cmd_bufs.into_iter());
+            }
+            Action::RecordCommandBuffer {
+                id,
+                flags,
+                inheritance,
+                commands,
+                hash: _,
+            } => {
+                // Taken out while recording, since it may execute other command buffers.
+                let (pool, mut cmd_buf) = take(&mut self.command_buffers, id)?;
+                let result = self.record(&mut cmd_buf, flags, &inheritance, commands);
+                self.command_buffers.insert(id, (pool, cmd_buf));
+                result?;
+            }
+            Action::CreateRenderPass {
+                id,
+                attachments,
+                subpasses,
+                dependencies,
+            } => {
+                let render_pass = device
+                    .create_render_pass(
+                        attachments.into_iter(),
+                        subpasses.iter().map(|subpass| pass::SubpassDesc {
+                            colors: &subpass.colors,
+                            depth_stencil: subpass.depth_stencil.as_ref(),
+                            inputs: &subpass.inputs,
+                            resolves: &subpass.resolves,
+                            preserves: &subpass.preserves,
+                            view_mask: subpass.view_mask,
+                            shading_rate: subpass.shading_rate.as_ref(),
+                        }),
+                        dependencies.into_iter(),
+                    )
+                    .map_err(device_error)?;
+                objects.render_passes.insert(id, render_pass);
+            }
+            Action::CreateRenderingPass {
+                id,
+                colors,
+                depth_stencil,
+            } => {
+                let render_pass = device
+                    .create_rendering_pass(colors.into_iter(), depth_stencil)
+                    .map_err(device_error)?;
+                objects.render_passes.insert(id, render_pass);
+            }
+            Action::DestroyRenderPass(id) => {
+                device.destroy_render_pass(take(&mut objects.render_passes, id)?);
+            }
+            Action::CreatePipelineLayout {
+                id,
+                set_layouts,
+                push_constant_ranges,
+            } => {
+                let set_layouts = set_layouts
+                    .iter()
+                    .map(|&layout| get(&objects.descriptor_set_layouts, layout))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let layout = device
+                    .create_pipeline_layout(
+                        set_layouts.into_iter(),
+                        push_constant_ranges.into_iter(),
+                    )
+                    .map_err(device_error)?;
+                objects.pipeline_layouts.insert(id, layout);
+            }
+            Action::DestroyPipelineLayout(id) => {
+                device.destroy_pipeline_layout(take(&mut objects.pipeline_layouts, id)?);
+            }
+            Action::CreatePipelineCache { id, data: _ } => {
+                // The cache data is specific to the capturing backend and driver.
+                let cache = device.create_pipeline_cache(None).map_err(device_error)?;
+                objects.pipeline_caches.insert(id, cache);
+            }
+            Action::MergePipelineCaches { target, sources } => {
+                let mut target_cache = take(&mut objects.pipeline_caches, target)?;
+                let result = sources
+                    .iter()
+                    .map(|&source| get(&objects.pipeline_caches, source))
+                    .collect::<Result<Vec<_>, _>>()
+                    .and_then(|sources| {
+                        device
+                            .merge_pipeline_caches(&mut target_cache, sources.into_iter())
+                            .map_err(device_error)
+                    });
+                objects.pipeline_caches.insert(target, target_cache);
+                result?;
+            }
+            Action::DestroyPipelineCache(id) => {
+                device.destroy_pipeline_cache(take(&mut objects.pipeline_caches, id)?);
+            }
+            Action::CreateGraphicsPipeline { id, desc, cache } => {
+                let primitive_assembler = match desc.primitive_assembler {
+                    trace::PrimitiveAssembler::Vertex {
+                        ref buffers,
+                        ref attributes,
+                        ref input_assembler,
+                        ref vertex,
+                        ref tessellation,
+                        ref geometry,
+                    } => pso::PrimitiveAssemblerDesc::Vertex {
+                        buffers,
+                        attributes,
+                        input_assembler: input_assembler.clone(),
+                        vertex: objects.entry_point(vertex)?,
+                        tessellation: match *tessellation {
+                            Some((ref hull, ref domain)) => {
+                                Some((objects.entry_point(hull)?, objects.entry_point(domain)?))
+                            }
+                            None => None,
+                        },
+                        geometry: match *geometry {
+                            Some(ref geometry) => Some(objects.entry_point(geometry)?),
+                            None => None,
+                        },
+                    },
+                    trace::PrimitiveAssembler::Mesh { ref task, ref mesh } => {
+                        pso::PrimitiveAssemblerDesc::Mesh {
+                            task: match *task {
+                                Some(ref task) => Some(objects.entry_point(task)?),
+                                None => None,
+                            },
+                            mesh: objects.entry_point(mesh)?,
+                        }
+                    }
+                };
+                let raw_desc = pso::GraphicsPipelineDesc {
+                    label: desc.label.as_deref(),
+                    primitive_assembler,
+                    rasterizer: desc.rasterizer,
+                    fragment: match desc.fragment {
+                        Some(ref fragment) => Some(objects.entry_point(fragment)?),
+                        None => None,
+                    },
+                    blender: desc.blender.clone(),
+                    depth_stencil: desc.depth_stencil,
+                    multisampling: desc.multisampling.clone(),
+                    baked_states: desc.baked_states.clone(),
+                    layout: get(&objects.pipeline_layouts, desc.layout)?,
+                    subpass: pass::Subpass {
+                        index: desc.subpass.1,
+                        main_pass: get(&objects.render_passes, desc.subpass.0)?,
+                    },
+                    flags: desc.flags,
+                    parent: Objects::<B>::base_pipeline(&objects.graphics_pipelines, &desc.parent)?,
+                };
+                let cache = match cache {
+                    Some(cache) => Some(get(&objects.pipeline_caches, cache)?),
+                    None => None,
+                };
+                let pipeline = device
+                    .create_graphics_pipeline(&raw_desc, cache)
+                    .map_err(device_error)?;
+                objects.graphics_pipelines.insert(id, pipeline);
+            }
+            Action::DestroyGraphicsPipeline(id) => {
+                device.destroy_graphics_pipeline(take(&mut objects.graphics_pipelines, id)?);
+            }
+            Action::CreateComputePipeline { id, desc, cache } => {
+                let raw_desc = pso::ComputePipelineDesc {
+                    label: desc.label.as_deref(),
+                    shader: objects.entry_point(&desc.shader)?,
+                    layout: get(&objects.pipeline_layouts, desc.layout)?,
+                    flags: desc.flags,
+                    parent: Objects::<B>::base_pipeline(&objects.compute_pipelines, &desc.parent)?,
+                };
+                let cache = match cache {
+                    Some(cache) => Some(get(&objects.pipeline_caches, cache)?),
+                    None => None,
+                };
+                let pipeline = device
+                    .create_compute_pipeline(&raw_desc, cache)
+                    .map_err(device_error)?;
+                objects.compute_pipelines.insert(id, pipeline);
+            }
+            Action::DestroyComputePipeline(id) => {
+                device.destroy_compute_pipeline(take(&mut objects.compute_pipelines, id)?);
+            }
+            Action::CreateFramebuffer {
+                id,
+                render_pass,
+                attachments,
+                extent,
+            } => {
+                let framebuffer = device
+                    .create_framebuffer(
+                        get(&objects.render_passes, render_pass)?,
+                        attachments.into_iter(),
+                        extent,
+                    )
+                    .map_err(device_error)?;
+                objects.framebuffers.insert(id, framebuffer);
+            }
+            Action::DestroyFramebuffer(id) => {
+                device.destroy_framebuffer(take(&mut objects.framebuffers, id)?);
+            }
+            Action::CreateShaderModule { id, data } => {
+                let bytes = self.read_file(&data)?;
+                let spirv = bytes
+                    .chunks(4)
+                    .map(|chunk| u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+                    .collect::<Vec<_>>();
+                let module = device.create_shader_module(&spirv).map_err(device_error)?;
+                self.objects.shader_modules.insert(id, module);
+            }
+            Action::DestroyShaderModule(id) => {
+                device.destroy_shader_module(take(&mut objects.shader_modules, id)?);
+            }
+            Action::CreateBuffer { id, size, usage } => {
+                let buffer = device.create_buffer(size, usage).map_err(device_error)?;
+                objects.buffers.insert(
+                    id,
+                    Resource {
+                        raw: buffer,
+                        size,
+                        memory: None,
+                    },
+                );
+            }
+            Action::BindBufferMemory {
+                buffer,
+                memory,
+                offset,
+            } => {
+                let properties = get(&self.objects.memories, memory)?.properties;
+                let raw_buffer = &get(&self.objects.buffers, buffer)?.raw;
+                let requirements = device.get_buffer_requirements(raw_buffer);
+                let (raw_memory, raw_properties) = self.allocate_dedicated(
+                    device,
+                    memory::DedicatedResource::Buffer(raw_buffer),
+                    requirements,
+                    properties,
+                )?;
+
+                let objects = &mut self.objects;
+                let raw_buffer = get_mut(&mut objects.buffers, buffer)?;
+                if let Err(err) = device.bind_buffer_memory(&raw_memory, 0, &mut raw_buffer.raw) {
+                    device.free_memory(raw_memory);
+                    return Err(device_error(err));
+                }
+                raw_buffer.memory = Some(raw_memory);
+                let virtual_memory = get_mut(&mut objects.memories, memory)?;
+                if raw_properties.contains(memory::Properties::CPU_VISIBLE) {
+                    virtual_memory.buffers.push((buffer, offset));
+                    if !virtual_memory.contents.is_empty() {
+                        Self::upload(
+                            device,
+                            raw_buffer,
+                            offset,
+                            &virtual_memory.contents,
+                            0..virtual_memory.size,
+                        )?;
+                    }
+                } else if properties.contains(memory::Properties::CPU_VISIBLE) {
+                    warn!("Host writes to buffer {} are not replayed", buffer);
+                }
+            }
+            Action::DestroyBuffer(id) => {
+                let buffer = take(&mut objects.buffers, id)?;
+                for memory in objects.memories.values_mut() {
+                    memory.buffers.retain(|&(buffer, _)| buffer != id);
+                }
+                device.destroy_buffer(buffer.raw);
+                if let Some(memory) = buffer.memory {
+                    device.free_memory(memory);
+                }
+            }
+            Action::CreateBufferView {
+                id,
+                buffer,
+                format,
+                range,
+            } => {
+                let view = device
+                    .create_buffer_view(objects.buffer(buffer)?, format, range)
+                    .map_err(device_error)?;
+                objects.buffer_views.insert(id, view);
+            }
+            Action::DestroyBufferView(id) => {
+                device.destroy_buffer_view(take(&mut objects.buffer_views, id)?);
+            }
+            Action::CreateImage {
+                id,
+                kind,
+                mip_levels,
+                format,
+                tiling,
+                usage,
+                view_caps,
+            } => {
+                let image = device
+                    .create_image(kind, mip_levels, format, tiling, usage, view_caps)
+                    .map_err(device_error)?;
+                objects.images.insert(
+                    id,
+                    Resource {
+                        raw: image,
+                        size: 0,
+                        memory: None,
+                    },
+                );
+            }
+            Action::BindImageMemory {
+                image,
+                memory,
+                offset: _,
+            } => {
+                let properties = get(&self.objects.memories, memory)?.properties;
+                if properties.contains(memory::Properties::CPU_VISIBLE) {
+                    warn!(
+                        "Host writes to the memory of image {} are not replayed",
+                        image
+                    );
+                }
+                let raw_image = &get(&self.objects.images, image)?.raw;
+                let requirements = device.get_image_requirements(raw_image);
+                let (raw_memory, _) = self.allocate_dedicated(
+                    device,
+                    memory::DedicatedResource::Image(raw_image),
+                    requirements,
+                    properties,
+                )?;
+                let raw_image = get_mut(&mut self.objects.images, image)?;
+                if let Err(err) = device.bind_image_memory(&raw_memory, 0, &mut raw_image.raw) {
+                    device.free_memory(raw_memory);
+                    return Err(device_error(err));
+                }
+                raw_image.size = requirements.size;
+                raw_image.memory = Some(raw_memory);
+            }
+            Action::DestroyImage(id) => {
+                // Swapchain images are not destroyed by the application.
+                if let Some(image) = objects.images.remove(&id) {
+                    device.destroy_image(image.raw);
+                    if let Some(memory) = image.memory {
+                        device.free_memory(memory);
+                    }
+                }
+            }
+            Action::CreateImageView {
+                id,
+                image,
+                view_kind,
+                format,
+                swizzle,
+                range,
+            } => {
+                let view = device
+                    .create_image_view(objects.image(image)?, view_kind, format, swizzle, range)
+                    .map_err(device_error)?;
+                objects.image_views.insert(id, view);
+            }
+            Action::CreateYcbcrImageView {
+                id,
+                image,
+                view_kind,
+                range,
+                conversion,
+            } => {
+                let view = device
+                    .create_ycbcr_image_view(
+                        objects.image(image)?,
+                        view_kind,
+                        range,
+                        get(&objects.ycbcr_conversions, conversion)?,
+                    )
+                    .map_err(device_error)?;
+                objects.image_views.insert(id, view);
+            }
+            Action::DestroyImageView(id) => {
+                if let Some(view) = objects.image_views.remove(&id) {
+                    device.destroy_image_view(view);
+                }
+            }
+            Action::CreateSampler { id, desc } => {
+                let sampler = device.create_sampler(&desc).map_err(device_error)?;
+                objects.samplers.insert(id, sampler);
+            }
+            Action::CreateYcbcrSampler {
+                id,
+                desc,
+                conversion,
+            } => {
+                let sampler = device
+                    .create_ycbcr_sampler(&desc, get(&objects.ycbcr_conversions, conversion)?)
+                    .map_err(device_error)?;
+                objects.samplers.insert(id, sampler);
+            }
+            Action::DestroySampler(id) => {
+                device.destroy_sampler(take(&mut objects.samplers, id)?);
+            }
+            Action::CreateSamplerYcbcrConversion { id, desc } => {
+                let conversion = device
+                    .create_sampler_ycbcr_conversion(&desc)
+                    .map_err(device_error)?;
+                objects.ycbcr_conversions.insert(id, conversion);
+            }
+            Action::DestroySamplerYcbcrConversion(id) => {
+                device.destroy_sampler_ycbcr_conversion(take(&mut objects.ycbcr_conversions, id)?);
+            }
+            Action::CreateDescriptorPool {
+                id,
+                max_sets,
+                ranges,
+                flags,
+            } => {
+                let pool = device
+                    .create_descriptor_pool(max_sets, ranges.into_iter(), flags)
+                    .map_err(device_error)?;
+                objects.descriptor_pools.insert(id, pool);
+            }
+            Action::ResetDescriptorPool(id) => {
+                get_mut(&mut objects.descriptor_pools, id)?.reset();
+                objects
+                    .descriptor_sets
+                    .retain(|_, &mut (pool, _)| pool != id);
+            }
+            Action::DestroyDescriptorPool(id) => {
+                device.destroy_descriptor_pool(take(&mut objects.descriptor_pools, id)?);
+                objects
+                    .descriptor_sets
+                    .retain(|_, &mut (pool, _)| pool != id);
+            }
+            Action::AllocateDescriptorSets { pool, layouts, ids } => {
+                let set_layouts = &objects.descriptor_set_layouts;
+                let layouts = layouts
+                    .iter()
+                    .map(|&layout| get(set_layouts, layout))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let mut sets = Vec::with_capacity(ids.len());
+                get_mut(&mut objects.descriptor_pools, pool)?
+                    .allocate(layouts.into_iter(), &mut sets)
+                    .map_err(device_error)?;
+                objects
+                    .descriptor_sets
+                    .extend(ids.into_iter().zip(sets).map(|(id, set)| (id, (pool, set))));
+            }
+            Action::FreeDescriptorSets { pool, ids } => {
+                let mut sets = Vec::with_capacity(ids.len());
+                for id in ids {
+                    sets.push(take(&mut objects.descriptor_sets, id)?.1);
+                }
+                get_mut(&mut objects.descriptor_pools, pool)?.free(sets.into_iter());
+            }
+            Action::CreateDescriptorSetLayout {
+                id,
+                bindings,
+                immutable_samplers,
+            } => {
+                let immutable_samplers = immutable_samplers
+                    .iter()
+                    .map(|&sampler| get(&objects.samplers, sampler))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let layout = device
+                    .create_descriptor_set_layout(
+                        bindings.into_iter(),
+                        immutable_samplers.into_iter(),
+                    )
+                    .map_err(device_error)?;
+                objects.descriptor_set_layouts.insert(id, layout);
+            }
+            Action::DestroyDescriptorSetLayout(id) => {
+                device
+                    .destroy_descriptor_set_layout(take(&mut objects.descriptor_set_layouts, id)?);
+            }
+            Action::WriteDescriptorSet {
+                set,
+                binding,
+                array_offset,
+                descriptors,
+            } => {
+                // Taken out to be written while the other objects are borrowed.
+                let (pool, mut raw_set) = take(&mut objects.descriptor_sets, set)?;
+                let result = descriptors
+                    .iter()
+                    .map(|descriptor| {
+                        Ok(match *descriptor {
+                            trace::Descriptor::Sampler(sampler) => {
+                                pso::Descriptor::Sampler(get(&objects.samplers, sampler)?)
+                            }
+                            trace::Descriptor::Image(view, layout) => {
+                                pso::Descriptor::Image(objects.image_view(view)?, layout)
+                            }
+                            trace::Descriptor::CombinedImageSampler(view, layout, sampler) => {
+                                pso::Descriptor::CombinedImageSampler(
+                                    objects.image_view(view)?,
+                                    layout,
+                                    get(&objects.samplers, sampler)?,
+                                )
+                            }
+                            trace::Descriptor::Buffer(buffer, ref sub) => {
+                                pso::Descriptor::Buffer(objects.buffer(buffer)?, sub.clone())
+                            }
+                            trace::Descriptor::TexelBuffer(view) => {
+                                pso::Descriptor::TexelBuffer(get(&objects.buffer_views, view)?)
+                            }
+                            trace::Descriptor::InlineUniformBlock(ref data) => {
+                                pso::Descriptor::InlineUniformBlock(data)
+                            }
+                            trace::Descriptor::Null => pso::Descriptor::Null,
+                        })
+                    })
+                    .collect::<Result<Vec<_>, ReplayError>>()
+                    .map(|raw_descriptors| {
+                        device.write_descriptor_set(pso::DescriptorSetWrite {
+                            set: &mut raw_set,
+                            binding,
+                            array_offset,
+                            descriptors: raw_descriptors.into_iter(),
+                        })
+                    });
+                objects.descriptor_sets.insert(set, (pool, raw_set));
+                result?;
+            }
+            Action::CopyDescriptorSet {
+                src_set,
+                src_binding,
+                src_array_offset,
+                dst_set,
+                dst_binding,
+                dst_array_offset,
+                count,
+            } => {
+                let (pool, mut raw_dst_set) = take(&mut objects.descriptor_sets, dst_set)?;
+                let result = match objects.descriptor_sets.get(&src_set) {
+                    Some(&(_, ref raw_src_set)) => {
+                        device.copy_descriptor_set(pso::DescriptorSetCopy {
+                            src_set: raw_src_set,
+                            src_binding,
+                            src_array_offset,
+                            dst_set: &mut raw_dst_set,
+                            dst_binding,
+                            dst_array_offset,
+                            count,
+                        });
+                        Ok(())
+                    }
+                    None if src_set == dst_set => {
+                        warn!("Copies within descriptor set {} are not replayed", src_set);
+                        Ok(())
+                    }
+                    None => Err(ReplayError::UnknownObject(src_set)),
+                };
+                objects.descriptor_sets.insert(dst_set, (pool, raw_dst_set));
+                result?;
+            }
+            Action::CreateSemaphore(id) => {
+                let semaphore = device.create_semaphore().map_err(device_error)?;
+                objects.semaphores.insert(id, semaphore);
+            }
+            Action::DestroySemaphore(id) => {
+                device.destroy_semaphore(take(&mut objects.semaphores, id)?);
+            }
+            Action::CreateFence { id, signaled } => {
+                let fence = device.create_fence(signaled).map_err(device_error)?;
+                objects.fences.insert(id, fence);
+            }
+            Action::ResetFence(id) => {
+                device
+                    .reset_fence(get_mut(&mut objects.fences, id)?)
+                    .map_err(device_error)?;
+            }
+            Action::WaitForFences { ids, wait } => {
+                let fences = ids
+                    .iter()
+                    .map(|&fence| get(&objects.fences, fence))
+                    .collect::<Result<Vec<_>, _>>()?;
+                device
+                    .wait_for_fences(fences.into_iter(), wait, !0)
+                    .map_err(device_error)?;
+            }
+            Action::DestroyFence(id) => {
+                device.destroy_fence(take(&mut objects.fences, id)?);
+            }
+            Action::CreateEvent(id) => {
+                let event = device.create_event().map_err(device_error)?;
+                objects.events.insert(id, event);
+            }
+            Action::SetEvent(id) => {
+                device
+                    .set_event(get_mut(&mut objects.events, id)?)
+                    .map_err(device_error)?;
+            }
+            Action::ResetEvent(id) => {
+                device
+                    .reset_event(get_mut(&mut objects.events, id)?)
+                    .map_err(device_error)?;
+            }
+            Action::DestroyEvent(id) => {
+                device.destroy_event(take(&mut objects.events, id)?);
+            }
+            Action::CreateQueryPool { id, ty, count } => {
+                let pool = device.create_query_pool(ty, count).map_err(device_error)?;
+                objects.query_pools.insert(id, pool);
+            }
+            Action::DestroyQueryPool(id) => {
+                device.destroy_query_pool(take(&mut objects.query_pools, id)?);
+            }
+            Action::Submit {
+                command_buffers,
+                wait_semaphores,
+                signal_semaphores,
+                fence,
+                protected,
+            } => {
+                let recorded = &self.command_buffers;
+                let command_buffers = command_buffers
+                    .iter()
+                    .map(|&id| get(recorded, id).map(|&(_, ref cmd_buf)| cmd_buf))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let semaphores = &objects.semaphores;
+                let wait_semaphores = wait_semaphores
+                    .iter()
+                    .map(|&(id, stage)| get(semaphores, id).map(|semaphore| (semaphore, stage)))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let signal_semaphores = signal_semaphores
+                    .iter()
+                    .map(|&id| get(semaphores, id))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let fence = match fence {
+                    Some(fence) => Some(get_mut(&mut objects.fences, fence)?),
+                    None => None,
+                };
+                if protected {
+                    queue
+                        .submit_protected(
+                            command_buffers.into_iter(),
+                            wait_semaphores.into_iter(),
+                            signal_semaphores.into_iter(),
+                            fence,
+                        )
+                        .map_err(device_error)?;
+                } else {
+                    queue
+                        .submit(
+                            command_buffers.into_iter(),
+                            wait_semaphores.into_iter(),
+                            signal_semaphores.into_iter(),
+                            fence,
+                        )
+                        .map_err(device_error)?;
+                }
+            }
+            Action::ConfigureSwapchain { surface, config } => {
+                let offscreen = self.create_offscreen(device, &config)?;
+                let objects = &mut self.objects;
+                if let Some(old) = objects.surfaces.insert(surface, offscreen) {
+                    device.wait_idle().map_err(device_error)?;
+                    Self::destroy_offscreen(device, old);
+                }
+            }
+            Action::UnconfigureSwapchain(surface) => {
+                if let Some(offscreen) = objects.surfaces.remove(&surface) {
+                    device.wait_idle().map_err(device_error)?;
+                    Self::destroy_offscreen(device, offscreen);
+                }
+                objects
+                    .acquired
+                    .retain(|_, &mut acquired| acquired != surface);
+            }
+            Action::AcquireImage {
+                surface,
+                image,
+                view,
+            } => {
+                objects.acquired.insert(image, surface);
+                objects.acquired.insert(view, surface);
+            }
+            Action::Present {
+                surface: _,
+                wait_semaphore,
+            } => {
+                if let Some(semaphore) = wait_semaphore {
+                    queue
+                        .submit(
+                            iter::empty(),
+                            iter::once((
+                                get(&objects.semaphores, semaphore)?,
+                                pso::PipelineStage::BOTTOM_OF_PIPE,
+                            )),
+                            iter::empty(),
+                            None,
+                        )
+                        .map_err(device_error)?;
+                }
+            }
+            Action::WaitIdle => {
+                device.wait_idle().map_err(device_error)?;
+            }
+        }
+        Ok(())
+    }
+
+    unsafe fn record(
+        &self,
+        cmd_buf: &mut B::CommandBuffer,
+        flags: command::CommandBufferFlags,
+        inheritance: &trace::InheritanceInfo,
+        commands: Vec<Command>,
+    ) -> Result<(), ReplayError> {
+        let objects = &self.objects;
+        let subpass = match inheritance.subpass {
+            Some((render_pass, index)) => Some(pass::Subpass {
+                index,
+                main_pass: get(&objects.render_passes, render_pass)?,
+            }),
+            None => None,
+        };
+        let framebuffer = match inheritance.framebuffer {
+            Some(framebuffer) => Some(get(&objects.framebuffers, framebuffer)?),
+            None => None,
+        };
+        cmd_buf.begin(
+            flags,
+            command::CommandBufferInheritanceInfo {
+                subpass,
+                framebuffer,
+                occlusion_query_enable: inheritance.occlusion_query_enable,
+                occlusion_query_flags: inheritance.occlusion_query_flags,
+                pipeline_statistics: inheritance.pipeline_statistics,
+            },
+        );
+
+        for command in commands {
+            match command {
+                Command::PipelineBarrier {
+                    stages,
+                    dependencies,
+                    barriers,
+                } => {
+                    let barriers = barriers
+                        .iter()
+                        .map(|barrier| objects.barrier(barrier))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    cmd_buf.pipeline_barrier(stages, dependencies, barriers.into_iter());
+                }
+                Command::BeginBarrier {
+                    event,
+                    stages,
+                    barriers,
+                } => {
+                    let barriers = barriers
+                        .iter()
+                        .map(|barrier| objects.barrier(barrier))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    cmd_buf.begin_barrier(
+                        get(&objects.events, event)?,
+                        stages,
+                        barriers.into_iter(),
+                    );
+                }
+                Command::EndBarrier {
+                    event,
+                    stages,
+                    barriers,
+                } => {
+                    let barriers = barriers
+                        .iter()
+                        .map(|barrier| objects.barrier(barrier))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    cmd_buf.end_barrier(get(&objects.events, event)?, stages, barriers.into_iter());
+                }
+                Command::FillBuffer {
+                    buffer,
+                    range,
+                    data,
+                } => cmd_buf.fill_buffer(objects.buffer(buffer)?, range, data),
+                Command::UpdateBuffer {
+                    buffer,
+                    offset,
+                    data,
+                } => cmd_buf.update_buffer(objects.buffer(buffer)?, offset, &data),
+                Command::ClearImage {
+                    image,
+                    layout,
+                    value,
+                    ranges,
+                } => cmd_buf.clear_image(
+                    objects.image(image)?,
+                    layout,
+                    trace::clear_value_from_bits(value),
+                    ranges.into_iter(),
+                ),
+                Command::ClearAttachments { clears, rects } => cmd_buf.clear_attachments(
+                    clears.into_iter().map(|clear| match clear {
+                        trace::AttachmentClear::Color { index, value } => {
+                            command::AttachmentClear::Color {
+                                index,
+                                value: command::ClearColor { uint32: value },
+                            }
+                        }
+                        trace::AttachmentClear::DepthStencil { depth, stencil } => {
+                            command::AttachmentClear::DepthStencil { depth, stencil }
+                        }
+                    }),
+                    rects.into_iter(),
+                ),
+                Command::ResolveImage {
+                    src,
+                    src_layout,
+                    dst,
+                    dst_layout,
+                    regions,
+                } => cmd_buf.resolve_image(
+                    objects.image(src)?,
+                    src_layout,
+                    objects.image(dst)?,
+                    dst_layout,
+                    regions.into_iter(),
+                ),
+                Command::BlitImage {
+                    src,
+                    src_layout,
+                    dst,
+                    dst_layout,
+                    filter,
+                    regions,
+                } => cmd_buf.blit_image(
+                    objects.image(src)?,
+                    src_layout,
+                    objects.image(dst)?,
+                    dst_layout,
+                    filter,
+                    regions.into_iter(),
+                ),
+                Command::BindIndexBuffer {
+                    buffer,
+                    range,
+                    index_type,
+                } => cmd_buf.bind_index_buffer(objects.buffer(buffer)?, range, index_type),
+                Command::BindVertexBuffers {
+                    first_binding,
+                    buffers,
+                } => {
+                    let buffers = buffers
+                        .into_iter()
+                        .map(|(buffer, range)| Ok((objects.buffer(buffer)?, range)))
+                        .collect::<Result<Vec<_>, ReplayError>>()?;
+                    cmd_buf.bind_vertex_buffers(first_binding, buffers.into_iter());
+                }
+                Command::SetViewports {
+                    first_viewport,
+                    viewports,
+                } => cmd_buf.set_viewports(first_viewport, viewports.into_iter()),
+                Command::SetScissors {
+                    first_scissor,
+                    rects,
+                } => cmd_buf.set_scissors(first_scissor, rects.into_iter()),
+                Command::SetStencilReference { faces, value } => {
+                    cmd_buf.set_stencil_reference(faces, value)
+                }
+                Command::SetStencilReadMask { faces, value } => {
+                    cmd_buf.set_stencil_read_mask(faces, value)
+                }
+                Command::SetStencilWriteMask { faces, value } => {
+                    cmd_buf.set_stencil_write_mask(faces, value)
+                }
+                Command::SetBlendConstants(color) => cmd_buf.set_blend_constants(color),
+                Command::SetDepthBounds(bounds) => cmd_buf.set_depth_bounds(bounds),
+                Command::SetLineWidth(width) => cmd_buf.set_line_width(width),
+                Command::SetDepthBias(depth_bias) => cmd_buf.set_depth_bias(depth_bias),
+                Command::SetShadingRate { rate, combiners } => {
+                    cmd_buf.set_shading_rate(rate, combiners)
+                }
+                Command::SetSampleLocations { samples, locations } => {
+                    cmd_buf.set_sample_locations(samples, &locations)
+                }
+                Command::BeginRenderPass {
+                    render_pass,
+                    framebuffer,
+                    render_area,
+                    attachments,
+                    first_subpass,
+                } => {
+                    let attachments = attachments
+                        .into_iter()
+                        .map(|(view, value)| {
+                            Ok(command::RenderAttachmentInfo {
+                                image_view: objects.image_view(view)?,
+                                clear_value: trace::clear_value_from_bits(value),
+                            })
+                        })
+                        .collect::<Result<Vec<_>, ReplayError>>()?;
+                    cmd_buf.begin_render_pass(
+                        get(&objects.render_passes, render_pass)?,
+                        get(&objects.framebuffers, framebuffer)?,
+                        render_area,
+                        attachments.into_iter(),
+                        first_subpass,
+                    );
+                }
+                Command::NextSubpass(contents) => cmd_buf.next_subpass(contents),
+                Command::EndRenderPass => cmd_buf.end_render_pass(),
+                Command::BeginRendering {
+                    render_area,
+                    layers,
+                    colors,
+                    depth_stencil,
+                    flags,
+                } => {
+                    let map_attachment = |at: trace::RenderingAttachment| {
+                        Ok(command::RenderingAttachmentInfo {
+                            image_view: objects.image_view(at.image_view)?,
+                            desc: at.desc,
+                            clear_value: trace::clear_value_from_bits(at.clear_value),
+                        })
+                    };
+                    let colors = colors
+                        .into_iter()
+                        .map(map_attachment)
+                        .collect::<Result<Vec<_>, ReplayError>>()?;
+                    let depth_stencil = depth_stencil.map(map_attachment).transpose()?;
+                    cmd_buf.begin_rendering(&command::RenderingInfo {
+                        render_area,
+                        layers,
+                        colors: &colors,
+                        depth_stencil: depth_stencil.as_ref(),
+                        flags,
+                    });
+                }
+                Command::EndRendering => cmd_buf.end_rendering(),
+                Command::BindGraphicsPipeline(pipeline) => {
+                    cmd_buf.bind_graphics_pipeline(get(&objects.graphics_pipelines, pipeline)?)
+                }
+                Command::BindGraphicsDescriptorSets {
+                    layout,
+                    first_set,
+                    sets,
+                    offsets,
+                } => {
+                    let sets = sets
+                        .iter()
+                        .map(|&set| get(&objects.descriptor_sets, set).map(|&(_, ref set)| set))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    cmd_buf.bind_graphics_descriptor_sets(
+                        get(&objects.pipeline_layouts, layout)?,
+                        first_set,
+                        sets.into_iter(),
+                        offsets.into_iter(),
+                    );
+                }
+                Command::BindComputePipeline(pipeline) => {
+                    cmd_buf.bind_compute_pipeline(get(&objects.compute_pipelines, pipeline)?)
+                }
+                Command::BindComputeDescriptorSets {
+                    layout,
+                    first_set,
+                    sets,
+                    offsets,
+                } => {
+                    let sets = sets
+                        .iter()
+                        .map(|&set| get(&objects.descriptor_sets, set).map(|&(_, ref set)| set))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    cmd_buf.bind_compute_descriptor_sets(
+                        get(&objects.pipeline_layouts, layout)?,
+                        first_set,
+                        sets.into_iter(),
+                        offsets.into_iter(),
+                    );
+                }
+                Command::Dispatch(count) => cmd_buf.dispatch(count),
+                Command::DispatchIndirect { buffer, offset } => {
+                    cmd_buf.dispatch_indirect(objects.buffer(buffer)?, offset)
+                }
+                Command::CopyBuffer { src, dst, regions } => cmd_buf.copy_buffer(
+                    objects.buffer(src)?,
+                    objects.buffer(dst)?,
+                    regions.into_iter(),
+                ),
+                Command::CopyImage {
+                    src,
+                    src_layout,
+                    dst,
+                    dst_layout,
+                    regions,
+                } => cmd_buf.copy_image(
+                    objects.image(src)?,
+                    src_layout,
+                    objects.image(dst)?,
+                    dst_layout,
+                    regions.into_iter(),
+                ),
+                Command::CopyBufferToImage {
+                    src,
+                    dst,
+                    dst_layout,
+                    regions,
+                } => cmd_buf.copy_buffer_to_image(
+                    objects.buffer(src)?,
+                    objects.image(dst)?,
+                    dst_layout,
+                    regions.into_iter(),
+                ),
+                Command::CopyImageToBuffer {
+                    src,
+                    src_layout,
+                    dst,
+                    regions,
+                } => cmd_buf.copy_image_to_buffer(
+                    objects.image(src)?,
+                    src_layout,
+                    objects.buffer(dst)?,
+                    regions.into_iter(),
+                ),
+                Command::Draw {
+                    vertices,
+                    instances,
+                } => cmd_buf.draw(vertices, instances),
+                Command::DrawIndexed {
+                    indices,
+                    base_vertex,
+                    instances,
+                } => cmd_buf.draw_indexed(indices, base_vertex, instances),
+                Command::DrawIndirect {
+                    buffer,
+                    offset,
+                    draw_count,
+                    stride,
+                } => cmd_buf.draw_indirect(objects.buffer(buffer)?, offset, draw_count, stride),
+                Command::DrawIndexedIndirect {
+                    buffer,
+                    offset,
+                    draw_count,
+                    stride,
+                } => cmd_buf.draw_indexed_indirect(
+                    objects.buffer(buffer)?,
+                    offset,
+                    draw_count,
+                    stride,
+                ),
+                Command::DrawIndirectCount {
+                    buffer,
+                    offset,
+                    count_buffer,
+                    count_buffer_offset,
+                    max_draw_count,
+                    stride,
+                } => cmd_buf.draw_indirect_count(
+                    objects.buffer(buffer)?,
+                    offset,
+                    objects.buffer(count_buffer)?,
+                    count_buffer_offset,
+                    max_draw_count,
+                    stride,
+                ),
+                Command::DrawIndexedIndirectCount {
+                    buffer,
+                    offset,
+                    count_buffer,
+                    count_buffer_offset,
+                    max_draw_count,
+                    stride,
+                } => cmd_buf.draw_indexed_indirect_count(
+                    objects.buffer(buffer)?,
+                    offset,
+                    objects.buffer(count_buffer)?,
+                    count_buffer_offset,
+                    max_draw_count,
+                    stride,
+                ),
+                Command::DrawMeshTasks {
+                    task_count,
+                    first_task,
+                } => cmd_buf.draw_mesh_tasks(task_count, first_task),
+                Command::DrawMeshTasksIndirect {
+                    buffer,
+                    offset,
+                    draw_count,
+                    stride,
+                } => cmd_buf.draw_mesh_tasks_indirect(
+                    objects.buffer(buffer)?,
+                    offset,
+                    draw_count,
+                    stride,
+                ),
+                Command::DrawMeshTasksIndirectCount {
+                    buffer,
+                    offset,
+                    count_buffer,
+                    count_buffer_offset,
+                    max_draw_count,
+                    stride,
+                } => cmd_buf.draw_mesh_tasks_indirect_count(
+                    objects.buffer(buffer)?,
+                    offset,
+                    objects.buffer(count_buffer)?,
+                    count_buffer_offset,
+                    max_draw_count,
+                    stride,
+                ),
+                Command::SetEvent { event, stages } => {
+                    cmd_buf.set_event(get(&objects.events, event)?, stages)
+                }
+                Command::ResetEvent { event, stages } => {
+                    cmd_buf.reset_event(get(&objects.events, event)?, stages)
+                }
+                Command::WaitEvents {
+                    events,
+                    stages,
+                    barriers,
+                } => {
+                    let events = events
+                        .iter()
+                        .map(|&event| get(&objects.events, event))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    let barriers = barriers
+                        .iter()
+                        .map(|barrier| objects.barrier(barrier))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    cmd_buf.wait_events(events.into_iter(), stages, barriers.into_iter());
+                }
+                Command::BeginQuery { query, flags } => {
+                    cmd_buf.begin_query(objects.query(query)?, flags)
+                }
+                Command::EndQuery(query) => cmd_buf.end_query(objects.query(query)?),
+                Command::ResetQueryPool { pool, queries } => {
+                    cmd_buf.reset_query_pool(get(&objects.query_pools, pool)?, queries)
+                }
+                Command::CopyQueryPoolResults {
+                    pool,
+                    queries,
+                    buffer,
+                    offset,
+                    stride,
+                    flags,
+                } => cmd_buf.copy_query_pool_results(
+                    get(&objects.query_pools, pool)?,
+                    queries,
+                    objects.buffer(buffer)?,
+                    offset,
+                    stride,
+                    flags,
+                ),
+                Command::WriteTimestamp { stage, query } => {
+                    cmd_buf.write_timestamp(stage, objects.query(query)?)
+                }
+                Command::PushGraphicsConstants {
+                    layout,
+                    stages,
+                    offset,
+                    constants,
+                } => cmd_buf.push_graphics_constants(
+                    get(&objects.pipeline_layouts, layout)?,
+                    stages,
+                    offset,
+                    &constants,
+                ),
+                Command::PushComputeConstants {
+                    layout,
+                    offset,
+                    constants,
+                } => cmd_buf.push_compute_constants(
+                    get(&objects.pipeline_layouts, layout)?,
+                    offset,
+                    &constants,
+                ),
+                Command::ExecuteCommands(ids) => {
+                    let cmd_bufs = ids
+                        .iter()
+                        .map(|&id| get(&self.command_buffers, id).map(|&(_, ref cmd_buf)| cmd_buf))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    cmd_buf.execute_commands(cmd_bufs.into_iter());
+                }
+                Command::InsertDebugMarker { name, color } => {
+                    cmd_buf.insert_debug_marker(&name, color)
+                }
+                Command::BeginDebugMarker { name, color } => {
+                    cmd_buf.begin_debug_marker(&name, color)
+                }
+                Command::EndDebugMarker => cmd_buf.end_debug_marker(),
+                Command::SetCheckpoint { label } => cmd_buf.set_checkpoint(&label),
+            }
+        }
+
+        cmd_buf.finish();
+        Ok(())
+    }
+
+    /// Destroy all the objects that are still alive at the end of the trace.
+    pub unsafe fn dispose(self, device: &B::Device) {
+        let _ = device.wait_idle();
+        let objects = self.objects;
+        let mut command_buffers = self.command_buffers;
+        for (id, mut pool) in objects.command_pools {
+            let ids = command_buffers
+                .iter()
+                .filter(|&(_, &(pool_id, _))| pool_id == id)
+                .map(|(&cmd_buf, _)| cmd_buf)
+                .collect::<Vec<_>>();
+            pool.free(
+                ids.into_iter()
+                    .filter_map(|id| command_buffers.remove(&id))
+                    .map(|(_, cmd_buf)| cmd_buf),
+            );
+            device.destroy_command_pool(pool);
+        }
+        for (_, pool) in objects.descriptor_pools {
+            device.destroy_descriptor_pool(pool);
+        }
+        for (_, layout) in objects.descriptor_set_layouts {
+            device.destroy_descriptor_set_layout(layout);
+        }
+        for (_, pipeline) in objects.graphics_pipelines {
+            device.destroy_graphics_pipeline(pipeline);
+        }
+        for (_, pipeline) in objects.compute_pipelines {
+            device.destroy_compute_pipeline(pipeline);
+        }
+        for (_, cache) in objects.pipeline_caches {
+            device.destroy_pipeline_cache(cache);
+        }
+        for (_, layout) in objects.pipeline_layouts {
+            device.destroy_pipeline_layout(layout);
+        }
+        for (_, module) in objects.shader_modules {
+            device.destroy_shader_module(module);
+        }
+        for (_, framebuffer) in objects.framebuffers {
+            device.destroy_framebuffer(framebuffer);
+        }
+        for (_, render_pass) in objects.render_passes {
+            device.destroy_render_pass(render_pass);
+        }
+        for (_, view) in objects.buffer_views {
+            device.destroy_buffer_view(view);
+        }
+        for (_, view) in objects.image_views {
+            device.destroy_image_view(view);
+        }
+        for (_, sampler) in objects.samplers {
+            device.destroy_sampler(sampler);
+        }
+        for (_, conversion) in objects.ycbcr_conversions {
+            device.destroy_sampler_ycbcr_conversion(conversion);
+        }
+        for (_, buffer) in objects.buffers {
+            device.destroy_buffer(buffer.raw);
+            if let Some(memory) = buffer.memory {
+                device.free_memory(memory);
+            }
+        }
+        for (_, image) in objects.images {
+            device.destroy_image(image.raw);
+            if let Some(memory) = image.memory {
+                device.free_memory(memory);
+            }
+        }
+        for (_, offscreen) in objects.surfaces {
+            Self::destroy_offscreen(device, offscreen);
+        }
+        for (_, semaphore) in objects.semaphores {
+            device.destroy_semaphore(semaphore);
+        }
+        for (_, fence) in objects.fences {
+            device.destroy_fence(fence);
+        }
+        for (_, event) in objects.events {
+            device.destroy_event(event);
+        }
+        for (_, pool) in objects.query_pools {
+            device.destroy_query_pool(pool);
+        }
+    }
+}
@@ -0,0 +1,301 @@
+//! Round trip of a capture over the software backend, and the stability
+//! of the trace format.
+
+use crate::{
+    replay::{self, Player},
+    trace::{self, Action, ObjectKind},
+    Instance,
+};
+use gfx_backend_software as software;
+use hal::{
+    adapter::PhysicalDevice as _,
+    buffer,
+    command::{self, CommandBuffer as _},
+    device::Device as _,
+    memory,
+    pool::{CommandPool as _, CommandPoolCreateFlags},
+    queue::Queue as _,
+    Instance as _, MemoryTypeId,
+};
+
+use std::{env, fs, iter, path::PathBuf, process, slice};
+
+const SIZE: u64 = 16;
+const FILL: u32 = 0xAABB_CCDD;
+
+/// Empty directory for the capture of a test.
+fn capture_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("gfx-capture-{}-{}", name, process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+/// Contents of the destination buffer after `capture_copy`.
+fn expected_contents() -> Vec<u8> {
+    let mut data = (0..SIZE as u8).collect::<Vec<_>>();
+    data[12..16].copy_from_slice(&FILL.to_le_bytes());
+    data
+}
+
+/// Write a pattern into a source buffer from the host, copy it into a
+/// destination buffer, and overwrite the last word of it with a fill.
+/// Returns the contents of the destination buffer, as seen by the capture.
+fn capture_copy(dir: &PathBuf) -> Vec<u8> {
+    let mut instance = Instance::<software::Backend>::create("capture", 1).unwrap();
+    instance.set_capture_dir(dir);
+    instance.set_deterministic(true);
+    let adapter = instance.enumerate_adapters().remove(0);
+    unsafe {
+        let mut gpu = adapter
+            .physical_device
+            .open(
+                &[(&adapter.queue_families[0], &[1.0])],
+                hal::Features::empty(),
+            )
+            .unwrap();
+        let device = &gpu.device;
+        let queue_group = &mut gpu.queue_groups[0];
+
+        let usage = buffer::Usage::TRANSFER_SRC | buffer::Usage::TRANSFER_DST;
+        let mut src = device.create_buffer(SIZE, usage).unwrap();
+        let mut dst = device.create_buffer(SIZE, usage).unwrap();
+        let mut memory = device.allocate_memory(MemoryTypeId(0), 2 * SIZE).unwrap();
+        device.bind_buffer_memory(&memory, 0, &mut src).unwrap();
+        device.bind_buffer_memory(&memory, SIZE, &mut dst).unwrap();
+
+        let ptr = device
+            .map_memory(&mut memory, memory::Segment::ALL)
+            .unwrap();
+        slice::from_raw_parts_mut(ptr, SIZE as usize)
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, byte)| *byte = i as u8);
+        device
+            .flush_mapped_memory_ranges(iter::once((&memory, memory::Segment::ALL)))
+            .unwrap();
+        device.unmap_memory(&mut memory);
+
+        let mut pool = device
+            .create_command_pool(queue_group.family, CommandPoolCreateFlags::empty())
+            .unwrap();
+        let mut cmd_buffer = pool.allocate_one(command::Level::Primary);
+        cmd_buffer.begin_primary(command::CommandBufferFlags::ONE_TIME_SUBMIT);
+        cmd_buffer.copy_buffer(
+            &src,
+            &dst,
+            iter::once(command::BufferCopy {
+                src: 0,
+                dst: 0,
+                size: SIZE,
+            }),
+        );
+        cmd_buffer.fill_buffer(
+            &dst,
+            buffer::SubRange {
+                offset: 12,
+                size: Some(4),
+            },
+            FILL,
+        );
+        cmd_buffer.finish();
+
+        let mut fence = device.create_fence(false).unwrap();
+        queue_group.queues[0]
+            .submit(
+                iter::once(&cmd_buffer),
+                iter::empty(),
+                iter::empty(),
+                Some(&mut fence),
+            )
+            .unwrap();
+        device.wait_for_fence(&fence, !0).unwrap();
+
+        let ptr = device
+            .map_memory(&mut memory, memory::Segment::ALL)
+            .unwrap();
+        let data = slice::from_raw_parts(ptr.offset(SIZE as isize), SIZE as usize).to_vec();
+        device.unmap_memory(&mut memory);
+
+        device.destroy_fence(fence);
+        pool.free(iter::once(cmd_buffer));
+        device.destroy_command_pool(pool);
+        device.destroy_buffer(src);
+        device.destroy_buffer(dst);
+        device.free_memory(memory);
+        data
+    }
+}
+
+#[test]
+fn replay_round_trip() {
+    let dir = capture_dir("round-trip");
+    assert_eq!(capture_copy(&dir), expected_contents());
+
+    let actions = replay::load(&dir).unwrap();
+    let recorded = actions
+        .iter()
+        .find_map(|action| match *action {
+            Action::RecordCommandBuffer {
+                ref commands, hash, ..
+            } => Some((commands, hash)),
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(recorded.1, Some(trace::hash_commands(recorded.0)));
+
+    // Stop at the wait for the submission, so that the buffer is only
+    // written by the replayed commands, and not destroyed yet.
+    let end = actions
+        .iter()
+        .position(|action| matches!(*action, Action::WaitForFences { .. }))
+        .unwrap()
+        + 1;
+
+    let instance = software::Instance::create("replay", 1).unwrap();
+    let adapter = instance.enumerate_adapters().remove(0);
+    unsafe {
+        let mut gpu = adapter
+            .physical_device
+            .open(
+                &[(&adapter.queue_families[0], &[1.0])],
+                hal::Features::empty(),
+            )
+            .unwrap();
+        let device = &gpu.device;
+        let queue_group = &mut gpu.queue_groups[0];
+        let mut player = Player::<software::Backend>::new(
+            &dir,
+            &adapter.physical_device.memory_properties(),
+            queue_group.family,
+        );
+        for action in actions.into_iter().take(end) {
+            player
+                .play(device, &mut queue_group.queues[0], action)
+                .unwrap();
+        }
+        device.wait_idle().unwrap();
+
+        let dst = player
+            .objects
+            .buffers
+            .get_mut(&ObjectKind::Buffer.make_id(1))
+            .unwrap();
+        let memory = dst.memory.as_mut().unwrap();
+        let ptr = device.map_memory(memory, memory::Segment::ALL).unwrap();
+        let data = slice::from_raw_parts(ptr, SIZE as usize).to_vec();
+        device.unmap_memory(memory);
+        assert_eq!(data, expected_contents());
+
+        player.dispose(device);
+    }
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn trace_is_closed() {
+    let dir = capture_dir("closed");
+    capture_copy(&dir);
+    let actions = replay::load(&dir).unwrap();
+    match actions.first() {
+        Some(Action::Init { .. }) => {}
+        other => panic!("Unexpected first action {:?}", other),
+    }
+    match actions.last() {
+        Some(Action::FreeMemory(id)) => assert_eq!(*id, ObjectKind::Memory.make_id(0)),
+        other => panic!("Unexpected last action {:?}", other),
+    }
+    let freed = actions
+        .iter()
+        .find_map(|action| match *action {
+            Action::FreeCommandBuffers { ref ids, .. } => Some(ids),
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(freed, &[ObjectKind::CommandBuffer.make_id(0)]);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+/// Actions in the format written by the capture. Changing it breaks the
+/// traces attached to the existing bug reports.
+const GOLDEN_TRACE: &str = r#"[
+AllocateMemory(
+    id: 281474976710656,
+    properties: (
+        bits: 15,
+    ),
+    size: 32,
+),
+CreateBuffer(
+    id: 3096224743817216,
+    size: 16,
+    usage: (
+        bits: 3,
+    ),
+),
+BindBufferMemory(
+    buffer: 3096224743817216,
+    memory: 281474976710656,
+    offset: 0,
+),
+WriteMemory(
+    id: 281474976710656,
+    offset: 0,
+    data: "data1.bin",
+),
+RecordCommandBuffer(
+    id: 844424930131968,
+    flags: (
+        bits: 1,
+    ),
+    inheritance: (
+        subpass: None,
+        framebuffer: None,
+        occlusion_query_enable: false,
+        occlusion_query_flags: (
+            bits: 0,
+        ),
+        pipeline_statistics: (
+            bits: 0,
+        ),
+    ),
+    commands: [
+        FillBuffer(
+            buffer: 3096224743817216,
+            range: (
+                offset: 12,
+                size: Some(4),
+            ),
+            data: 2864434397,
+        ),
+    ],
+    hash: Some(1520799942934186218),
+),
+DestroyBuffer(3096224743817216),
+]
+"#;
+
+#[test]
+fn trace_format_is_stable() {
+    assert_eq!(ObjectKind::Memory.make_id(0), 281474976710656);
+    assert_eq!(ObjectKind::CommandBuffer.make_id(0), 844424930131968);
+    assert_eq!(ObjectKind::Buffer.make_id(0), 3096224743817216);
+
+    let actions = ron::de::from_str::<Vec<Action>>(GOLDEN_TRACE).unwrap();
+    assert_eq!(actions.len(), 6);
+    match actions[4] {
+        Action::RecordCommandBuffer {
+            ref commands, hash, ..
+        } => assert_eq!(hash, Some(trace::hash_commands(commands))),
+        ref other => panic!("Unexpected action {:?}", other),
+    }
+
+    // Written the same way as the trace file.
+    let mut serialized = String::from("[\n");
+    for action in actions.iter() {
+        let string = ron::ser::to_string_pretty(action, ron::ser::PrettyConfig::default()).unwrap();
+        serialized.push_str(&string);
+        serialized.push_str(",\n");
+    }
+    serialized.push_str("]\n");
+    assert_eq!(serialized, GOLDEN_TRACE);
+}
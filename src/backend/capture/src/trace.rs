@@ -0,0 +1,858 @@
+//! Serializable description of the captured calls.
+//!
+//! Objects are referred to by their `Id`, and the bulky binary data
+//! (shader code, memory contents, pipeline caches) is stored in separate
+//! files next to the trace, referred to by their `FileName`.
+
+use hal::{
+    adapter, buffer, command, device, format, image, memory, pass, pool, pso, query, window,
+};
+use serde::{Deserialize, Serialize};
+
+use std::ops::Range;
+
+/// Identifier of a captured object, unique within a trace.
+pub type Id = u64;
+/// Name of a binary file in the capture directory.
+pub type FileName = String;
+
+/// Name of the trace file in the capture directory.
+pub const TRACE_FILE: &str = "trace.ron";
+
+/// Kind of a captured object.
+///
+/// In the deterministic mode, the identifiers are allocated per kind, and the
+/// kind is stored in the upper bits of the `Id`. Creating an extra object of one
+/// kind then doesn't shift the identifiers of all the other objects.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ObjectKind {
+    Memory,
+    CommandPool,
+    CommandBuffer,
+    RenderPass,
+    Framebuffer,
+    ShaderModule,
+    PipelineLayout,
+    PipelineCache,
+    GraphicsPipeline,
+    ComputePipeline,
+    Buffer,
+    BufferView,
+    Image,
+    ImageView,
+    Sampler,
+    SamplerYcbcrConversion,
+    DescriptorPool,
+    DescriptorSetLayout,
+    DescriptorSet,
+    Semaphore,
+    Fence,
+    Event,
+    QueryPool,
+    Surface,
+}
+
+impl ObjectKind {
+    /// Number of the object kinds.
+    pub const COUNT: usize = ObjectKind::Surface as usize + 1;
+    /// Number of the lower bits of a deterministic `Id` holding the index
+    /// of the object within its kind.
+    pub const INDEX_BITS: u32 = 48;
+
+    /// Make the deterministic `Id` of the object with the given index.
+    pub fn make_id(self, index: u64) -> Id {
+        debug_assert!(index < 1 << Self::INDEX_BITS);
+        (self as u64 + 1) << Self::INDEX_BITS | index
+    }
+}
+
+/// Compute a hash of the recorded commands, which is stable across runs,
+/// platforms and backends.
+///
+/// The hashes of the command buffers, captured in the deterministic mode on
+/// different backends, can be compared to find the first diverging recording.
+pub fn hash_commands(commands: &[Command]) -> u64 {
+    let string = ron::ser::to_string(&commands).unwrap_or_else(|e| {
+        error!("Unable to serialize the commands: {:?}", e);
+        String::new()
+    });
+    hash_bytes(string.as_bytes())
+}
+
+/// Compute a hash of the data, which is stable across runs and platforms.
+pub fn hash_bytes(data: &[u8]) -> u64 {
+    // FNV-1a: unlike `std` hashers, it isn't seeded.
+    data.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Bits of a `command::ClearValue` or `command::ClearColor`.
+pub type ClearBits = [u32; 4];
+
+pub fn clear_value_bits(value: command::ClearValue) -> ClearBits {
+    unsafe { value.color.uint32 }
+}
+
+pub fn clear_value_from_bits(bits: ClearBits) -> command::ClearValue {
+    command::ClearValue {
+        color: command::ClearColor { uint32: bits },
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EntryPoint {
+    pub entry: String,
+    pub module: Id,
+    pub specialization_constants: Vec<pso::SpecializationConstant>,
+    pub specialization_data: Vec<u8>,
+}
+
+impl<B: hal::Backend> From<&pso::EntryPoint<'_, crate::Backend<B>>> for EntryPoint {
+    fn from(ep: &pso::EntryPoint<crate::Backend<B>>) -> Self {
+        EntryPoint {
+            entry: ep.entry.to_string(),
+            module: ep.module.id,
+            specialization_constants: ep.specialization.constants.to_vec(),
+            specialization_data: ep.specialization.data.to_vec(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum BasePipeline {
+    Pipeline(Id),
+    Index(usize),
+    None,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum PrimitiveAssembler {
+    Vertex {
+        buffers: Vec<pso::VertexBufferDesc>,
+        attributes: Vec<pso::AttributeDesc>,
+        input_assembler: pso::InputAssemblerDesc,
+        vertex: EntryPoint,
+        tessellation: Option<(EntryPoint, EntryPoint)>,
+        geometry: Option<EntryPoint>,
+    },
+    Mesh {
+        task: Option<EntryPoint>,
+        mesh: EntryPoint,
+    },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GraphicsPipelineDesc {
+    pub label: Option<String>,
+    pub primitive_assembler: PrimitiveAssembler,
+    pub rasterizer: pso::Rasterizer,
+    pub fragment: Option<EntryPoint>,
+    pub blender: pso::BlendDesc,
+    pub depth_stencil: pso::DepthStencilDesc,
+    pub multisampling: Option<pso::Multisampling>,
+    pub baked_states: pso::BakedStates,
+    pub layout: Id,
+    pub subpass: (Id, pass::SubpassId),
+    pub flags: pso::PipelineCreationFlags,
+    pub parent: BasePipeline,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ComputePipelineDesc {
+    pub label: Option<String>,
+    pub shader: EntryPoint,
+    pub layout: Id,
+    pub flags: pso::PipelineCreationFlags,
+    pub parent: BasePipeline,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum PipelineDesc {
+    Graphics(GraphicsPipelineDesc),
+    Compute(ComputePipelineDesc),
+}
+
+impl PipelineDesc {
+    /// Entry points of all the shader stages of the pipeline.
+    pub fn entry_points(&self) -> Vec<&EntryPoint> {
+        let desc = match *self {
+            PipelineDesc::Graphics(ref desc) => desc,
+            PipelineDesc::Compute(ref desc) => return vec![&desc.shader],
+        };
+        let mut entry_points = match desc.primitive_assembler {
+            PrimitiveAssembler::Vertex {
+                ref vertex,
+                ref tessellation,
+                ref geometry,
+                ..
+            } => {
+                let mut list = vec![vertex];
+                if let Some((ref hull, ref domain)) = *tessellation {
+                    list.push(hull);
+                    list.push(domain);
+                }
+                list.extend(geometry);
+                list
+            }
+            PrimitiveAssembler::Mesh { ref task, ref mesh } => {
+                task.iter().chain(Some(mesh)).collect()
+            }
+        };
+        entry_points.extend(&desc.fragment);
+        entry_points
+    }
+}
+
+/// Creation state of a pipeline, see `Device::describe_pipeline`.
+///
+/// It's self-contained, so that crash handlers can attach it to the report
+/// without the rest of the trace.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PipelineDebugInfo {
+    pub id: Id,
+    pub desc: PipelineDesc,
+    /// Formats of the attachments of the render pass, for graphics pipelines.
+    pub attachment_formats: Vec<Option<format::Format>>,
+    /// Hashes of the SPIR-V code of the shader modules, computed with `hash_bytes`.
+    pub shader_hashes: Vec<(Id, u64)>,
+}
+
+impl PipelineDebugInfo {
+    /// Serialize into a compact single line, fitting the crash report annotations.
+    pub fn to_compact_string(&self) -> String {
+        ron::ser::to_string(self).unwrap_or_else(|e| {
+            error!("Unable to serialize {:?}: {:?}", self, e);
+            String::new()
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SubpassDesc {
+    pub colors: Vec<pass::AttachmentRef>,
+    pub depth_stencil: Option<pass::AttachmentRef>,
+    pub inputs: Vec<pass::AttachmentRef>,
+    pub resolves: Vec<pass::AttachmentRef>,
+    pub preserves: Vec<pass::AttachmentId>,
+    pub view_mask: u32,
+    pub shading_rate: Option<pass::AttachmentRef>,
+}
+
+impl From<pass::SubpassDesc<'_>> for SubpassDesc {
+    fn from(desc: pass::SubpassDesc) -> Self {
+        SubpassDesc {
+            colors: desc.colors.to_vec(),
+            depth_stencil: desc.depth_stencil.cloned(),
+            inputs: desc.inputs.to_vec(),
+            resolves: desc.resolves.to_vec(),
+            preserves: desc.preserves.to_vec(),
+            view_mask: desc.view_mask,
+            shading_rate: desc.shading_rate.cloned(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RenderingAttachment {
+    pub image_view: Id,
+    pub desc: pass::Attachment,
+    pub clear_value: ClearBits,
+}
+
+impl<B: hal::Backend> From<&command::RenderingAttachmentInfo<'_, crate::Backend<B>>>
+    for RenderingAttachment
+{
+    fn from(info: &command::RenderingAttachmentInfo<crate::Backend<B>>) -> Self {
+        RenderingAttachment {
+            image_view: info.image_view.id,
+            desc: info.desc.clone(),
+            clear_value: clear_value_bits(info.clear_value),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub enum Descriptor {
+    Sampler(Id),
+    Image(Id, image::Layout),
+    CombinedImageSampler(Id, image::Layout, Id),
+    Buffer(Id, buffer::SubRange),
+    TexelBuffer(Id),
+    InlineUniformBlock(Vec<u8>),
+    Null,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub enum Barrier {
+    AllBuffers(Range<buffer::Access>),
+    AllImages(Range<image::Access>),
+    Buffer {
+        states: Range<buffer::State>,
+        target: Id,
+        range: buffer::SubRange,
+        families: Option<Range<hal::queue::QueueFamilyId>>,
+    },
+    Image {
+        states: Range<image::State>,
+        target: Id,
+        range: image::SubresourceRange,
+        families: Option<Range<hal::queue::QueueFamilyId>>,
+    },
+    Aliasing {
+        before: Option<AliasedResource>,
+        after: Option<AliasedResource>,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub enum AliasedResource {
+    Buffer(Id),
+    Image(Id),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub enum AttachmentClear {
+    Color {
+        index: usize,
+        value: ClearBits,
+    },
+    DepthStencil {
+        depth: Option<pso::DepthValue>,
+        stencil: Option<pso::StencilValue>,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InheritanceInfo {
+    pub subpass: Option<(Id, pass::SubpassId)>,
+    pub framebuffer: Option<Id>,
+    pub occlusion_query_enable: bool,
+    pub occlusion_query_flags: query::ControlFlags,
+    pub pipeline_statistics: query::PipelineStatistic,
+}
+
+/// Query of a pool, with the pool `Id`.
+pub type Query = (Id, query::Id);
+
+/// Command recorded into a command buffer.
+#[derive(Debug, Serialize, Deserialize)]
+pub enum Command {
+    PipelineBarrier {
+        stages: Range<pso::PipelineStage>,
+        dependencies: memory::Dependencies,
+        barriers: Vec<Barrier>,
+    },
+    BeginBarrier {
+        event: Id,
+        stages: Range<pso::PipelineStage>,
+        barriers: Vec<Barrier>,
+    },
+    EndBarrier {
+        event: Id,
+        stages: Range<pso::PipelineStage>,
+        barriers: Vec<Barrier>,
+    },
+    FillBuffer {
+        buffer: Id,
+        range: buffer::SubRange,
+        data: u32,
+    },
+    UpdateBuffer {
+        buffer: Id,
+        offset: buffer::Offset,
+        data: Vec<u8>,
+    },
+    ClearImage {
+        image: Id,
+        layout: image::Layout,
+        value: ClearBits,
+        ranges: Vec<image::SubresourceRange>,
+    },
+    ClearAttachments {
+        clears: Vec<AttachmentClear>,
+        rects: Vec<pso::ClearRect>,
+    },
+    ResolveImage {
+        src: Id,
+        src_layout: image::Layout,
+        dst: Id,
+        dst_layout: image::Layout,
+        regions: Vec<command::ImageResolve>,
+    },
+    BlitImage {
+        src: Id,
+        src_layout: image::Layout,
+        dst: Id,
+        dst_layout: image::Layout,
+        filter: image::Filter,
+        regions: Vec<command::ImageBlit>,
+    },
+    BindIndexBuffer {
+        buffer: Id,
+        range: buffer::SubRange,
+        index_type: hal::IndexType,
+    },
+    BindVertexBuffers {
+        first_binding: pso::BufferIndex,
+        buffers: Vec<(Id, buffer::SubRange)>,
+    },
+    SetViewports {
+        first_viewport: u32,
+        viewports: Vec<pso::Viewport>,
+    },
+    SetScissors {
+        first_scissor: u32,
+        rects: Vec<pso::Rect>,
+    },
+    SetStencilReference {
+        faces: pso::Face,
+        value: pso::StencilValue,
+    },
+    SetStencilReadMask {
+        faces: pso::Face,
+        value: pso::StencilValue,
+    },
+    SetStencilWriteMask {
+        faces: pso::Face,
+        value: pso::StencilValue,
+    },
+    SetBlendConstants(pso::ColorValue),
+    SetDepthBounds(Range<f32>),
+    SetLineWidth(f32),
+    SetDepthBias(pso::DepthBias),
+    SetShadingRate {
+        rate: pso::ShadingRate,
+        combiners: [pso::ShadingRateCombiner; 2],
+    },
+    SetSampleLocations {
+        samples: image::NumSamples,
+        locations: Vec<pso::SampleLocation>,
+    },
+    BeginRenderPass {
+        render_pass: Id,
+        framebuffer: Id,
+        render_area: pso::Rect,
+        attachments: Vec<(Id, ClearBits)>,
+        first_subpass: command::SubpassContents,
+    },
+    NextSubpass(command::SubpassContents),
+    EndRenderPass,
+    BeginRendering {
+        render_area: pso::Rect,
+        layers: image::Layer,
+        colors: Vec<RenderingAttachment>,
+        depth_stencil: Option<RenderingAttachment>,
+        flags: command::RenderingFlags,
+    },
+    EndRendering,
+    BindGraphicsPipeline(Id),
+    BindGraphicsDescriptorSets {
+        layout: Id,
+        first_set: usize,
+        sets: Vec<Id>,
+        offsets: Vec<command::DescriptorSetOffset>,
+    },
+    BindComputePipeline(Id),
+    BindComputeDescriptorSets {
+        layout: Id,
+        first_set: usize,
+        sets: Vec<Id>,
+        offsets: Vec<command::DescriptorSetOffset>,
+    },
+    Dispatch(hal::WorkGroupCount),
+    DispatchIndirect {
+        buffer: Id,
+        offset: buffer::Offset,
+    },
+    CopyBuffer {
+        src: Id,
+        dst: Id,
+        regions: Vec<command::BufferCopy>,
+    },
+    CopyImage {
+        src: Id,
+        src_layout: image::Layout,
+        dst: Id,
+        dst_layout: image::Layout,
+        regions: Vec<command::ImageCopy>,
+    },
+    CopyBufferToImage {
+        src: Id,
+        dst: Id,
+        dst_layout: image::Layout,
+        regions: Vec<command::BufferImageCopy>,
+    },
+    CopyImageToBuffer {
+        src: Id,
+        src_layout: image::Layout,
+        dst: Id,
+        regions: Vec<command::BufferImageCopy>,
+    },
+    Draw {
+        vertices: Range<hal::VertexCount>,
+        instances: Range<hal::InstanceCount>,
+    },
+    DrawIndexed {
+        indices: Range<hal::IndexCount>,
+        base_vertex: hal::VertexOffset,
+        instances: Range<hal::InstanceCount>,
+    },
+    DrawIndirect {
+        buffer: Id,
+        offset: buffer::Offset,
+        draw_count: hal::DrawCount,
+        stride: buffer::Stride,
+    },
+    DrawIndexedIndirect {
+        buffer: Id,
+        offset: buffer::Offset,
+        draw_count: hal::DrawCount,
+        stride: buffer::Stride,
+    },
+    DrawIndirectCount {
+        buffer: Id,
+        offset: buffer::Offset,
+        count_buffer: Id,
+        count_buffer_offset: buffer::Offset,
+        max_draw_count: u32,
+        stride: buffer::Stride,
+    },
+    DrawIndexedIndirectCount {
+        buffer: Id,
+        offset: buffer::Offset,
+        count_buffer: Id,
+        count_buffer_offset: buffer::Offset,
+        max_draw_count: u32,
+        stride: buffer::Stride,
+    },
+    DrawMeshTasks {
+        task_count: hal::TaskCount,
+        first_task: hal::TaskCount,
+    },
+    DrawMeshTasksIndirect {
+        buffer: Id,
+        offset: buffer::Offset,
+        draw_count: hal::DrawCount,
+        stride: buffer::Stride,
+    },
+    DrawMeshTasksIndirectCount {
+        buffer: Id,
+        offset: buffer::Offset,
+        count_buffer: Id,
+        count_buffer_offset: buffer::Offset,
+        max_draw_count: hal::DrawCount,
+        stride: buffer::Stride,
+    },
+    SetEvent {
+        event: Id,
+        stages: pso::PipelineStage,
+    },
+    ResetEvent {
+        event: Id,
+        stages: pso::PipelineStage,
+    },
+    WaitEvents {
+        events: Vec<Id>,
+        stages: Range<pso::PipelineStage>,
+        barriers: Vec<Barrier>,
+    },
+    BeginQuery {
+        query: Query,
+        flags: query::ControlFlags,
+    },
+    EndQuery(Query),
+    ResetQueryPool {
+        pool: Id,
+        queries: Range<query::Id>,
+    },
+    CopyQueryPoolResults {
+        pool: Id,
+        queries: Range<query::Id>,
+        buffer: Id,
+        offset: buffer::Offset,
+        stride: buffer::Stride,
+        flags: query::ResultFlags,
+    },
+    WriteTimestamp {
+        stage: pso::PipelineStage,
+        query: Query,
+    },
+    PushGraphicsConstants {
+        layout: Id,
+        stages: pso::ShaderStageFlags,
+        offset: u32,
+        constants: Vec<u32>,
+    },
+    PushComputeConstants {
+        layout: Id,
+        offset: u32,
+        constants: Vec<u32>,
+    },
+    ExecuteCommands(Vec<Id>),
+    InsertDebugMarker {
+        name: String,
+        color: u32,
+    },
+    BeginDebugMarker {
+        name: String,
+        color: u32,
+    },
+    EndDebugMarker,
+    SetCheckpoint {
+        label: String,
+    },
+}
+
+/// Captured call, in the order they were made.
+#[derive(Debug, Serialize, Deserialize)]
+pub enum Action {
+    Init {
+        adapter: adapter::AdapterInfo,
+        features: hal::Features,
+    },
+    AllocateMemory {
+        id: Id,
+        properties: memory::Properties,
+        size: u64,
+    },
+    /// Contents of a mapped memory range written by the host.
+    WriteMemory {
+        id: Id,
+        offset: u64,
+        data: FileName,
+    },
+    FreeMemory(Id),
+    /// Contents written by the host directly into an image.
+    CopyMemoryToImage {
+        image: Id,
+        layout: image::Layout,
+        data: FileName,
+        regions: Vec<command::BufferImageCopy>,
+    },
+    CreateCommandPool {
+        id: Id,
+        flags: pool::CommandPoolCreateFlags,
+    },
+    ResetCommandPool(Id),
+    DestroyCommandPool(Id),
+    AllocateCommandBuffers {
+        pool: Id,
+        level: command::Level,
+        ids: Vec<Id>,
+    },
+    FreeCommandBuffers {
+        pool: Id,
+        ids: Vec<Id>,
+    },
+    /// Whole recording of a command buffer, from `begin` to `finish`.
+    RecordCommandBuffer {
+        id: Id,
+        flags: command::CommandBufferFlags,
+        inheritance: InheritanceInfo,
+        commands: Vec<Command>,
+        /// Result of `hash_commands`, if captured in the deterministic mode.
+        #[serde(default)]
+        hash: Option<u64>,
+    },
+    CreateRenderPass {
+        id: Id,
+        attachments: Vec<pass::Attachment>,
+        subpasses: Vec<SubpassDesc>,
+        dependencies: Vec<pass::SubpassDependency>,
+    },
+    /// Render pass for the pipelines used within `begin_rendering`.
+    CreateRenderingPass {
+        id: Id,
+        colors: Vec<pass::Attachment>,
+        depth_stencil: Option<pass::Attachment>,
+    },
+    DestroyRenderPass(Id),
+    CreatePipelineLayout {
+        id: Id,
+        set_layouts: Vec<Id>,
+        push_constant_ranges: Vec<(pso::ShaderStageFlags, Range<u32>)>,
+    },
+    DestroyPipelineLayout(Id),
+    CreatePipelineCache {
+        id: Id,
+        data: Option<FileName>,
+    },
+    MergePipelineCaches {
+        target: Id,
+        sources: Vec<Id>,
+    },
+    DestroyPipelineCache(Id),
+    CreateGraphicsPipeline {
+        id: Id,
+        desc: GraphicsPipelineDesc,
+        cache: Option<Id>,
+    },
+    DestroyGraphicsPipeline(Id),
+    CreateComputePipeline {
+        id: Id,
+        desc: ComputePipelineDesc,
+        cache: Option<Id>,
+    },
+    DestroyComputePipeline(Id),
+    CreateFramebuffer {
+        id: Id,
+        render_pass: Id,
+        attachments: Vec<image::FramebufferAttachment>,
+        extent: image::Extent,
+    },
+    DestroyFramebuffer(Id),
+    CreateShaderModule {
+        id: Id,
+        data: FileName,
+    },
+    DestroyShaderModule(Id),
+    CreateBuffer {
+        id: Id,
+        size: u64,
+        usage: buffer::Usage,
+    },
+    BindBufferMemory {
+        buffer: Id,
+        memory: Id,
+        offset: u64,
+    },
+    DestroyBuffer(Id),
+    CreateBufferView {
+        id: Id,
+        buffer: Id,
+        format: Option<format::Format>,
+        range: buffer::SubRange,
+    },
+    DestroyBufferView(Id),
+    CreateImage {
+        id: Id,
+        kind: image::Kind,
+        mip_levels: image::Level,
+        format: format::Format,
+        tiling: image::Tiling,
+        usage: image::Usage,
+        view_caps: image::ViewCapabilities,
+    },
+    BindImageMemory {
+        image: Id,
+        memory: Id,
+        offset: u64,
+    },
+    DestroyImage(Id),
+    CreateImageView {
+        id: Id,
+        image: Id,
+        view_kind: image::ViewKind,
+        format: format::Format,
+        swizzle: format::Swizzle,
+        range: image::SubresourceRange,
+    },
+    CreateYcbcrImageView {
+        id: Id,
+        image: Id,
+        view_kind: image::ViewKind,
+        range: image::SubresourceRange,
+        conversion: Id,
+    },
+    DestroyImageView(Id),
+    CreateSampler {
+        id: Id,
+        desc: image::SamplerDesc,
+    },
+    CreateYcbcrSampler {
+        id: Id,
+        desc: image::SamplerDesc,
+        conversion: Id,
+    },
+    DestroySampler(Id),
+    CreateSamplerYcbcrConversion {
+        id: Id,
+        desc: image::SamplerYcbcrConversionDesc,
+    },
+    DestroySamplerYcbcrConversion(Id),
+    CreateDescriptorPool {
+        id: Id,
+        max_sets: usize,
+        ranges: Vec<pso::DescriptorRangeDesc>,
+        flags: pso::DescriptorPoolCreateFlags,
+    },
+    ResetDescriptorPool(Id),
+    DestroyDescriptorPool(Id),
+    AllocateDescriptorSets {
+        pool: Id,
+        layouts: Vec<Id>,
+        ids: Vec<Id>,
+    },
+    FreeDescriptorSets {
+        pool: Id,
+        ids: Vec<Id>,
+    },
+    CreateDescriptorSetLayout {
+        id: Id,
+        bindings: Vec<pso::DescriptorSetLayoutBinding>,
+        immutable_samplers: Vec<Id>,
+    },
+    DestroyDescriptorSetLayout(Id),
+    WriteDescriptorSet {
+        set: Id,
+        binding: pso::DescriptorBinding,
+        array_offset: pso::DescriptorArrayIndex,
+        descriptors: Vec<Descriptor>,
+    },
+    CopyDescriptorSet {
+        src_set: Id,
+        src_binding: pso::DescriptorBinding,
+        src_array_offset: pso::DescriptorArrayIndex,
+        dst_set: Id,
+        dst_binding: pso::DescriptorBinding,
+        dst_array_offset: pso::DescriptorArrayIndex,
+        count: usize,
+    },
+    CreateSemaphore(Id),
+    DestroySemaphore(Id),
+    CreateFence {
+        id: Id,
+        signaled: bool,
+    },
+    ResetFence(Id),
+    /// Fences observed as signaled by the application.
+    WaitForFences {
+        ids: Vec<Id>,
+        wait: device::WaitFor,
+    },
+    DestroyFence(Id),
+    CreateEvent(Id),
+    SetEvent(Id),
+    ResetEvent(Id),
+    DestroyEvent(Id),
+    CreateQueryPool {
+        id: Id,
+        ty: query::Type,
+        count: query::Id,
+    },
+    DestroyQueryPool(Id),
+    Submit {
+        command_buffers: Vec<Id>,
+        wait_semaphores: Vec<(Id, pso::PipelineStage)>,
+        signal_semaphores: Vec<Id>,
+        fence: Option<Id>,
+        protected: bool,
+    },
+    ConfigureSwapchain {
+        surface: Id,
+        config: window::SwapchainConfig,
+    },
+    UnconfigureSwapchain(Id),
+    /// Image acquired from a surface, along with its view.
+    AcquireImage {
+        surface: Id,
+        image: Id,
+        view: Id,
+    },
+    Present {
+        surface: Id,
+        wait_semaphore: Option<Id>,
+    },
+    WaitIdle,
+}
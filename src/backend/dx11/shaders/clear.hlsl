@@ -1,7 +1,7 @@
-cbuffer ClearColorF32   : register(b0) { float4 ClearF32; };
-cbuffer ClearColorU32   : register(b0) { uint4 ClearU32; };
-cbuffer ClearColorI32   : register(b0) { int4 ClearI32; };
-cbuffer ClearColorDepth : register(b0) { float ClearDepth; };
+cbuffer ClearColorF32   : register(b13) { float4 ClearF32; };
+cbuffer ClearColorU32   : register(b13) { uint4 ClearU32; };
+cbuffer ClearColorI32   : register(b13) { int4 ClearI32; };
+cbuffer ClearColorDepth : register(b13) { float ClearDepth; };
 
 // fullscreen triangle
 float4 vs_partial_clear(uint id : SV_VertexID) : SV_Position
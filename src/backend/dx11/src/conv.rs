@@ -33,6 +33,7 @@ pub fn map_index_type(ty: IndexType) -> DXGI_FORMAT {
 pub fn viewable_format(format: DXGI_FORMAT) -> DXGI_FORMAT {
     match format {
         DXGI_FORMAT_D32_FLOAT_S8X24_UINT => DXGI_FORMAT_R32_FLOAT_X8X24_TYPELESS,
+        DXGI_FORMAT_D24_UNORM_S8_UINT => DXGI_FORMAT_R24_UNORM_X8_TYPELESS,
         DXGI_FORMAT_D32_FLOAT => DXGI_FORMAT_R32_FLOAT,
         DXGI_FORMAT_D16_UNORM => DXGI_FORMAT_R16_UNORM,
         _ => format,
@@ -93,8 +94,11 @@ pub fn map_format(format: Format) -> Option<DXGI_FORMAT> {
         B10g11r11Ufloat => DXGI_FORMAT_R11G11B10_FLOAT,
         E5b9g9r9Ufloat => DXGI_FORMAT_R9G9B9E5_SHAREDEXP,
         D16Unorm => DXGI_FORMAT_D16_UNORM,
+        D24UnormS8Uint => DXGI_FORMAT_D24_UNORM_S8_UINT,
         D32Sfloat => DXGI_FORMAT_D32_FLOAT,
         D32SfloatS8Uint => DXGI_FORMAT_D32_FLOAT_S8X24_UINT,
+        // Emulated, see `is_emulated_format`
+        S8Uint | D16UnormS8Uint => DXGI_FORMAT_D24_UNORM_S8_UINT,
         Bc1RgbUnorm | Bc1RgbaUnorm => DXGI_FORMAT_BC1_UNORM,
         Bc1RgbSrgb | Bc1RgbaSrgb => DXGI_FORMAT_BC1_UNORM_SRGB,
         Bc2Unorm => DXGI_FORMAT_BC2_UNORM,
@@ -116,6 +120,15 @@ pub fn map_format(format: Format) -> Option<DXGI_FORMAT> {
     Some(format)
 }
 
+/// Returns true if the format has no DXGI equivalent, and is backed by
+/// `DXGI_FORMAT_D24_UNORM_S8_UINT` instead.
+pub fn is_emulated_format(format: Format) -> bool {
+    match format {
+        Format::S8Uint | Format::D16UnormS8Uint => true,
+        _ => false,
+    }
+}
+
 pub fn map_format_nosrgb(format: Format) -> Option<DXGI_FORMAT> {
     // NOTE: DXGI doesn't allow sRGB format on the swapchain, but
     //       creating RTV of swapchain buffers with sRGB works
@@ -278,6 +291,17 @@ impl DecomposedDxgiFormat {
                 copy_srv: Some(DXGI_FORMAT_R32_FLOAT_X8X24_TYPELESS),
             },
 
+            DXGI_FORMAT_D24_UNORM_S8_UINT => DecomposedDxgiFormat {
+                typeless: DXGI_FORMAT_R24G8_TYPELESS,
+                // TODO: depth or stencil?
+                srv: Some(DXGI_FORMAT_R24_UNORM_X8_TYPELESS),
+                rtv: None,
+                uav: None,
+                dsv: Some(format),
+                copy_uav: None,
+                copy_srv: Some(DXGI_FORMAT_R24_UNORM_X8_TYPELESS),
+            },
+
             DXGI_FORMAT_D32_FLOAT => DecomposedDxgiFormat {
                 typeless: DXGI_FORMAT_R32_TYPELESS,
                 srv: Some(DXGI_FORMAT_R32_FLOAT),
@@ -457,8 +481,9 @@ pub fn map_viewport(viewport: &Viewport) -> D3D11_VIEWPORT {
         TopLeftY: viewport.rect.y as _,
         Width: viewport.rect.w as _,
         Height: viewport.rect.h as _,
-        MinDepth: viewport.depth.start,
-        MaxDepth: viewport.depth.end,
+        // D3D11 doesn't support depth ranges outside of [0, 1]
+        MinDepth: viewport.depth.start.max(0.0).min(1.0),
+        MaxDepth: viewport.depth.end.max(0.0).min(1.0),
     }
 }
 
@@ -533,7 +558,7 @@ pub(crate) fn map_rasterizer_desc(
         DepthBias: bias.const_factor as INT,
         DepthBiasClamp: bias.clamp,
         SlopeScaledDepthBias: bias.slope_factor,
-        DepthClipEnable: !desc.depth_clamping as _,
+        DepthClipEnable: desc.depth_clip_enable.unwrap_or(!desc.depth_clamping) as _,
         // TODO:
         ScissorEnable: TRUE,
         MultisampleEnable: multisampled as _,
@@ -599,6 +624,7 @@ fn map_blend_op(operation: BlendOp) -> (D3D11_BLEND_OP, D3D11_BLEND, D3D11_BLEND
         ),
         BlendOp::Min => (D3D11_BLEND_OP_MIN, D3D11_BLEND_ZERO, D3D11_BLEND_ZERO),
         BlendOp::Max => (D3D11_BLEND_OP_MAX, D3D11_BLEND_ZERO, D3D11_BLEND_ZERO),
+        BlendOp::Advanced(op) => panic!("Advanced blend operation {:?} is not supported", op),
     }
 }
 
@@ -621,6 +647,7 @@ fn map_alpha_blend_op(operation: BlendOp) -> (D3D11_BLEND_OP, D3D11_BLEND, D3D11
         ),
         BlendOp::Min => (D3D11_BLEND_OP_MIN, D3D11_BLEND_ZERO, D3D11_BLEND_ZERO),
         BlendOp::Max => (D3D11_BLEND_OP_MAX, D3D11_BLEND_ZERO, D3D11_BLEND_ZERO),
+        BlendOp::Advanced(op) => panic!("Advanced blend operation {:?} is not supported", op),
     }
 }
 
@@ -1,4 +1,7 @@
-use auxil::ShaderStage;
+use auxil::{
+    workers::{DetachedComputePipelineDesc, DetachedGraphicsPipelineDesc},
+    ShaderStage,
+};
 use hal::{
     adapter::MemoryProperties, buffer, device, format, image, memory, pass, pool, pso,
     pso::VertexInputRate, query, queue::QueueFamilyId, window,
@@ -34,6 +37,19 @@ use crate::{
 //TODO: expose coherent type 0x2 when it's properly supported
 const BUFFER_TYPE_MASK: u32 = 0x1 | 0x4;
 
+/// Returns the reason of the device removal, or `None` if the device is still operational.
+pub(crate) fn device_lost_cause(raw: &d3d11::ID3D11Device) -> Option<device::DeviceLostCause> {
+    let cause = match unsafe { raw.GetDeviceRemovedReason() } {
+        winerror::S_OK => return None,
+        winerror::DXGI_ERROR_DEVICE_HUNG => device::DeviceLostCause::Hung,
+        winerror::DXGI_ERROR_DEVICE_RESET => device::DeviceLostCause::Reset,
+        winerror::DXGI_ERROR_DEVICE_REMOVED => device::DeviceLostCause::Removed,
+        winerror::DXGI_ERROR_DRIVER_INTERNAL_ERROR => device::DeviceLostCause::DriverError,
+        _ => device::DeviceLostCause::Unknown,
+    };
+    Some(cause)
+}
+
 struct InputLayout {
     raw: ComPtr<d3d11::ID3D11InputLayout>,
     required_bindings: u32,
@@ -50,12 +66,13 @@ pub struct DepthStencilState {
 }
 
 pub struct Device {
-    raw: ComPtr<d3d11::ID3D11Device>,
+    pub(crate) raw: ComPtr<d3d11::ID3D11Device>,
     raw1: Option<ComPtr<d3d11_1::ID3D11Device1>>,
     pub(crate) context: ComPtr<d3d11::ID3D11DeviceContext>,
     features: hal::Features,
     memory_properties: MemoryProperties,
     internal: Arc<internal::Internal>,
+    pipeline_workers: auxil::workers::Workers,
 }
 
 impl fmt::Debug for Device {
@@ -66,6 +83,7 @@ impl fmt::Debug for Device {
 
 impl Drop for Device {
     fn drop(&mut self) {
+        self.pipeline_workers.join();
         if let Ok(debug) = self.raw.cast::<d3d11sdklayers::ID3D11Debug>() {
             unsafe {
                 debug.ReportLiveDeviceObjects(d3d11sdklayers::D3D11_RLDO_DETAIL);
@@ -92,6 +110,10 @@ impl Device {
             context,
             features,
             memory_properties,
+            pipeline_workers: auxil::workers::Workers::new(
+                "gfx-dx11-pipelines",
+                auxil::workers::PIPELINE_THREADS,
+            ),
         }
     }
 
@@ -99,720 +121,877 @@ impl Device {
         self.raw.as_raw()
     }
 
-    fn create_rasterizer_state(
-        &self,
-        rasterizer_desc: &pso::Rasterizer,
-        multisampling_desc: &Option<pso::Multisampling>,
-    ) -> Result<ComPtr<d3d11::ID3D11RasterizerState>, pso::CreationError> {
-        let mut rasterizer = ptr::null_mut();
-        let desc = conv::map_rasterizer_desc(rasterizer_desc, multisampling_desc);
-
-        let hr = unsafe {
-            self.raw
-                .CreateRasterizerState(&desc, &mut rasterizer as *mut *mut _ as *mut *mut _)
-        };
-
-        if winerror::SUCCEEDED(hr) {
-            Ok(unsafe { ComPtr::from_raw(rasterizer) })
-        } else {
-            Err(pso::CreationError::Other)
+    fn pipeline_device(&self) -> PipelineDevice {
+        PipelineDevice {
+            raw: self.raw.clone(),
+            features: self.features,
         }
     }
 
-    fn create_blend_state(
+    fn view_image_as_shader_resource(
         &self,
-        blend_desc: &pso::BlendDesc,
-        multisampling: &Option<pso::Multisampling>,
-    ) -> Result<ComPtr<d3d11::ID3D11BlendState>, pso::CreationError> {
-        let mut blend = ptr::null_mut();
-        let desc = conv::map_blend_desc(blend_desc, multisampling);
+        info: &ViewInfo,
+    ) -> Result<ComPtr<d3d11::ID3D11ShaderResourceView>, image::ViewCreationError> {
+        let mut desc: d3d11::D3D11_SHADER_RESOURCE_VIEW_DESC = unsafe { mem::zeroed() };
+        desc.Format = info.format;
+        if desc.Format == dxgiformat::DXGI_FORMAT_D32_FLOAT_S8X24_UINT {
+            desc.Format = dxgiformat::DXGI_FORMAT_R32_FLOAT_X8X24_TYPELESS;
+        }
+
+        #[allow(non_snake_case)]
+        let MostDetailedMip = info.levels.start as _;
+        #[allow(non_snake_case)]
+        let MipLevels = (info.levels.end - info.levels.start) as _;
+        #[allow(non_snake_case)]
+        let FirstArraySlice = info.layers.start as _;
+        #[allow(non_snake_case)]
+        let ArraySize = (info.layers.end - info.layers.start) as _;
+
+        match info.view_kind {
+            image::ViewKind::D1 => {
+                desc.ViewDimension = d3dcommon::D3D11_SRV_DIMENSION_TEXTURE1D;
+                *unsafe { desc.u.Texture1D_mut() } = d3d11::D3D11_TEX1D_SRV {
+                    MostDetailedMip,
+                    MipLevels,
+                }
+            }
+            image::ViewKind::D1Array => {
+                desc.ViewDimension = d3dcommon::D3D11_SRV_DIMENSION_TEXTURE1DARRAY;
+                *unsafe { desc.u.Texture1DArray_mut() } = d3d11::D3D11_TEX1D_ARRAY_SRV {
+                    MostDetailedMip,
+                    MipLevels,
+                    FirstArraySlice,
+                    ArraySize,
+                }
+            }
+            image::ViewKind::D2 if info.kind.num_samples() > 1 => {
+                desc.ViewDimension = d3dcommon::D3D11_SRV_DIMENSION_TEXTURE2DMS;
+                *unsafe { desc.u.Texture2DMS_mut() } = d3d11::D3D11_TEX2DMS_SRV {
+                    UnusedField_NothingToDefine: 0,
+                }
+            }
+            image::ViewKind::D2 => {
+                desc.ViewDimension = d3dcommon::D3D11_SRV_DIMENSION_TEXTURE2D;
+                *unsafe { desc.u.Texture2D_mut() } = d3d11::D3D11_TEX2D_SRV {
+                    MostDetailedMip,
+                    MipLevels,
+                }
+            }
+            image::ViewKind::D2Array if info.kind.num_samples() > 1 => {
+                desc.ViewDimension = d3dcommon::D3D11_SRV_DIMENSION_TEXTURE2DMSARRAY;
+                *unsafe { desc.u.Texture2DMSArray_mut() } = d3d11::D3D11_TEX2DMS_ARRAY_SRV {
+                    FirstArraySlice,
+                    ArraySize,
+                }
+            }
+            image::ViewKind::D2Array => {
+                desc.ViewDimension = d3dcommon::D3D11_SRV_DIMENSION_TEXTURE2DARRAY;
+                *unsafe { desc.u.Texture2DArray_mut() } = d3d11::D3D11_TEX2D_ARRAY_SRV {
+                    MostDetailedMip,
+                    MipLevels,
+                    FirstArraySlice,
+                    ArraySize,
+                }
+            }
+            image::ViewKind::D3 => {
+                desc.ViewDimension = d3dcommon::D3D11_SRV_DIMENSION_TEXTURE3D;
+                *unsafe { desc.u.Texture3D_mut() } = d3d11::D3D11_TEX3D_SRV {
+                    MostDetailedMip,
+                    MipLevels,
+                }
+            }
+            image::ViewKind::Cube => {
+                desc.ViewDimension = d3dcommon::D3D11_SRV_DIMENSION_TEXTURECUBE;
+                *unsafe { desc.u.TextureCube_mut() } = d3d11::D3D11_TEXCUBE_SRV {
+                    MostDetailedMip,
+                    MipLevels,
+                }
+            }
+            image::ViewKind::CubeArray => {
+                desc.ViewDimension = d3dcommon::D3D11_SRV_DIMENSION_TEXTURECUBEARRAY;
+                *unsafe { desc.u.TextureCubeArray_mut() } = d3d11::D3D11_TEXCUBE_ARRAY_SRV {
+                    MostDetailedMip,
+                    MipLevels,
+                    First2DArrayFace: FirstArraySlice,
+                    NumCubes: ArraySize / 6,
+                }
+            }
+        }
 
+        let mut srv = ptr::null_mut();
         let hr = unsafe {
-            self.raw
-                .CreateBlendState(&desc, &mut blend as *mut *mut _ as *mut *mut _)
+            self.raw.CreateShaderResourceView(
+                info.resource,
+                &desc,
+                &mut srv as *mut *mut _ as *mut *mut _,
+            )
         };
 
         if winerror::SUCCEEDED(hr) {
-            Ok(unsafe { ComPtr::from_raw(blend) })
+            Ok(unsafe { ComPtr::from_raw(srv) })
         } else {
-            Err(pso::CreationError::Other)
+            Err(image::ViewCreationError::Unsupported)
         }
     }
 
-    fn create_depth_stencil_state(
+    fn view_image_as_unordered_access(
         &self,
-        depth_desc: &pso::DepthStencilDesc,
-    ) -> Result<DepthStencilState, pso::CreationError> {
-        let mut depth = ptr::null_mut();
-        let (desc, stencil_ref, read_only) = conv::map_depth_stencil_desc(depth_desc);
+        info: &ViewInfo,
+    ) -> Result<ComPtr<d3d11::ID3D11UnorderedAccessView>, image::ViewCreationError> {
+        let mut desc: d3d11::D3D11_UNORDERED_ACCESS_VIEW_DESC = unsafe { mem::zeroed() };
+        desc.Format = info.format;
+
+        #[allow(non_snake_case)]
+        let MipSlice = info.levels.start as _;
+        #[allow(non_snake_case)]
+        let FirstArraySlice = info.layers.start as _;
+        #[allow(non_snake_case)]
+        let ArraySize = (info.layers.end - info.layers.start) as _;
+
+        match info.view_kind {
+            image::ViewKind::D1 => {
+                desc.ViewDimension = d3d11::D3D11_UAV_DIMENSION_TEXTURE1D;
+                *unsafe { desc.u.Texture1D_mut() } = d3d11::D3D11_TEX1D_UAV {
+                    MipSlice: info.levels.start as _,
+                }
+            }
+            image::ViewKind::D1Array => {
+                desc.ViewDimension = d3d11::D3D11_UAV_DIMENSION_TEXTURE1DARRAY;
+                *unsafe { desc.u.Texture1DArray_mut() } = d3d11::D3D11_TEX1D_ARRAY_UAV {
+                    MipSlice,
+                    FirstArraySlice,
+                    ArraySize,
+                }
+            }
+            image::ViewKind::D2 => {
+                desc.ViewDimension = d3d11::D3D11_UAV_DIMENSION_TEXTURE2D;
+                *unsafe { desc.u.Texture2D_mut() } = d3d11::D3D11_TEX2D_UAV {
+                    MipSlice: info.levels.start as _,
+                }
+            }
+            image::ViewKind::D2Array => {
+                desc.ViewDimension = d3d11::D3D11_UAV_DIMENSION_TEXTURE2DARRAY;
+                *unsafe { desc.u.Texture2DArray_mut() } = d3d11::D3D11_TEX2D_ARRAY_UAV {
+                    MipSlice,
+                    FirstArraySlice,
+                    ArraySize,
+                }
+            }
+            image::ViewKind::D3 => {
+                desc.ViewDimension = d3d11::D3D11_UAV_DIMENSION_TEXTURE3D;
+                *unsafe { desc.u.Texture3D_mut() } = d3d11::D3D11_TEX3D_UAV {
+                    MipSlice,
+                    FirstWSlice: FirstArraySlice,
+                    WSize: ArraySize,
+                }
+            }
+            _ => unimplemented!(),
+        }
 
+        let mut uav = ptr::null_mut();
         let hr = unsafe {
-            self.raw
-                .CreateDepthStencilState(&desc, &mut depth as *mut *mut _ as *mut *mut _)
+            self.raw.CreateUnorderedAccessView(
+                info.resource,
+                &desc,
+                &mut uav as *mut *mut _ as *mut *mut _,
+            )
         };
 
         if winerror::SUCCEEDED(hr) {
-            Ok(DepthStencilState {
-                raw: unsafe { ComPtr::from_raw(depth) },
-                stencil_ref,
-                read_only,
-            })
+            Ok(unsafe { ComPtr::from_raw(uav) })
         } else {
-            Err(pso::CreationError::Other)
+            error!("CreateUnorderedAccessView failed: 0x{:x}", hr);
+
+            Err(image::ViewCreationError::Unsupported)
         }
     }
 
-    fn create_input_layout(
+    pub(crate) fn view_image_as_render_target(
         &self,
-        vs: ComPtr<d3dcommon::ID3DBlob>,
-        vertex_buffers: &[pso::VertexBufferDesc],
-        attributes: &[pso::AttributeDesc],
-        input_assembler: &pso::InputAssemblerDesc,
-        vertex_semantic_remapping: auxil::FastHashMap<u32, Option<(u32, u32)>>,
-    ) -> Result<InputLayout, pso::CreationError> {
-        let mut layout = ptr::null_mut();
+        info: &ViewInfo,
+    ) -> Result<ComPtr<d3d11::ID3D11RenderTargetView>, image::ViewCreationError> {
+        let mut desc: d3d11::D3D11_RENDER_TARGET_VIEW_DESC = unsafe { mem::zeroed() };
+        desc.Format = info.format;
 
-        let mut vertex_strides = Vec::new();
-        let mut required_bindings = 0u32;
-        let mut max_vertex_bindings = 0u32;
-        for buffer in vertex_buffers {
-            required_bindings |= 1 << buffer.binding as u32;
-            max_vertex_bindings = max_vertex_bindings.max(1u32 + buffer.binding as u32);
+        #[allow(non_snake_case)]
+        let MipSlice = info.levels.start as _;
+        #[allow(non_snake_case)]
+        let FirstArraySlice = info.layers.start as _;
+        #[allow(non_snake_case)]
+        let ArraySize = (info.layers.end - info.layers.start) as _;
 
-            while vertex_strides.len() <= buffer.binding as usize {
-                vertex_strides.push(0);
+        match info.view_kind {
+            image::ViewKind::D1 => {
+                desc.ViewDimension = d3d11::D3D11_RTV_DIMENSION_TEXTURE1D;
+                *unsafe { desc.u.Texture1D_mut() } = d3d11::D3D11_TEX1D_RTV { MipSlice }
             }
-
-            vertex_strides[buffer.binding as usize] = buffer.stride;
-        }
-
-        // See [`shader::introspect_spirv_vertex_semantic_remapping`] for details of why this is needed.
-        let semantics: Vec<_> = attributes
-            .iter()
-            .map(
-                |attrib| match vertex_semantic_remapping.get(&attrib.location) {
-                    Some(Some((major, minor))) => {
-                        let name = std::borrow::Cow::Owned(format!("TEXCOORD{}_\0", major));
-                        let location = *minor;
-                        (name, location)
-                    }
-                    _ => {
-                        let name = std::borrow::Cow::Borrowed("TEXCOORD\0");
-                        let location = attrib.location;
-                        (name, location)
-                    }
-                },
-            )
-            .collect();
-
-        let input_elements = attributes
-            .iter()
-            .zip(semantics.iter())
-            .filter_map(|(attrib, (semantic_name, semantic_index))| {
-                let buffer_desc = match vertex_buffers
-                    .iter()
-                    .find(|buffer_desc| buffer_desc.binding == attrib.binding)
-                {
-                    Some(buffer_desc) => buffer_desc,
-                    None => {
-                        // TODO:
-                        // error!("Couldn't find associated vertex buffer description {:?}", attrib.binding);
-                        return Some(Err(pso::CreationError::Other));
+            image::ViewKind::D1Array => {
+                desc.ViewDimension = d3d11::D3D11_RTV_DIMENSION_TEXTURE1DARRAY;
+                *unsafe { desc.u.Texture1DArray_mut() } = d3d11::D3D11_TEX1D_ARRAY_RTV {
+                    MipSlice,
+                    FirstArraySlice,
+                    ArraySize,
+                }
+            }
+            image::ViewKind::D2 => {
+                if info.kind.num_samples() > 1 {
+                    desc.ViewDimension = d3d11::D3D11_RTV_DIMENSION_TEXTURE2DMS;
+                    *unsafe { desc.u.Texture2DMS_mut() } = d3d11::D3D11_TEX2DMS_RTV {
+                        UnusedField_NothingToDefine: 0,
                     }
-                };
-
-                let (slot_class, step_rate) = match buffer_desc.rate {
-                    VertexInputRate::Vertex => (d3d11::D3D11_INPUT_PER_VERTEX_DATA, 0),
-                    VertexInputRate::Instance(divisor) => {
-                        (d3d11::D3D11_INPUT_PER_INSTANCE_DATA, divisor)
+                } else {
+                    desc.ViewDimension = d3d11::D3D11_RTV_DIMENSION_TEXTURE2D;
+                    *unsafe { desc.u.Texture2D_mut() } = d3d11::D3D11_TEX2D_RTV { MipSlice }
+                }
+            }
+            image::ViewKind::D2Array => {
+                if info.kind.num_samples() > 1 {
+                    desc.ViewDimension = d3d11::D3D11_RTV_DIMENSION_TEXTURE2DMSARRAY;
+                    *unsafe { desc.u.Texture2DMSArray_mut() } = d3d11::D3D11_TEX2DMS_ARRAY_RTV {
+                        FirstArraySlice,
+                        ArraySize,
                     }
-                };
-                let format = attrib.element.format;
-
-                Some(Ok(d3d11::D3D11_INPUT_ELEMENT_DESC {
-                    SemanticName: semantic_name.as_ptr() as *const _, // Semantic name used by SPIRV-Cross
-                    SemanticIndex: *semantic_index,
-                    Format: match conv::map_format(format) {
-                        Some(fm) => fm,
-                        None => {
-                            // TODO:
-                            // error!("Unable to find DXGI format for {:?}", format);
-                            return Some(Err(pso::CreationError::Other));
-                        }
-                    },
-                    InputSlot: attrib.binding as _,
-                    AlignedByteOffset: attrib.element.offset,
-                    InputSlotClass: slot_class,
-                    InstanceDataStepRate: step_rate as _,
-                }))
-            })
-            .collect::<Result<Vec<_>, _>>()?;
-
-        let hr = unsafe {
-            self.raw.CreateInputLayout(
-                input_elements.as_ptr(),
-                input_elements.len() as _,
-                vs.GetBufferPointer(),
-                vs.GetBufferSize(),
-                &mut layout as *mut *mut _ as *mut *mut _,
-            )
-        };
-
-        if winerror::SUCCEEDED(hr) {
-            let topology = conv::map_topology(input_assembler);
-
-            Ok(InputLayout {
-                raw: unsafe { ComPtr::from_raw(layout) },
-                required_bindings,
-                max_vertex_bindings,
-                topology,
-                vertex_strides,
-            })
-        } else {
-            error!("CreateInputLayout error 0x{:X}", hr);
-            Err(pso::CreationError::Other)
+                } else {
+                    desc.ViewDimension = d3d11::D3D11_RTV_DIMENSION_TEXTURE2DARRAY;
+                    *unsafe { desc.u.Texture2DArray_mut() } = d3d11::D3D11_TEX2D_ARRAY_RTV {
+                        MipSlice,
+                        FirstArraySlice,
+                        ArraySize,
+                    }
+                }
+            }
+            image::ViewKind::D3 => {
+                desc.ViewDimension = d3d11::D3D11_RTV_DIMENSION_TEXTURE3D;
+                *unsafe { desc.u.Texture3D_mut() } = d3d11::D3D11_TEX3D_RTV {
+                    MipSlice,
+                    FirstWSlice: FirstArraySlice,
+                    WSize: ArraySize,
+                }
+            }
+            _ => unimplemented!(),
         }
-    }
-
-    fn create_vertex_shader(
-        &self,
-        blob: ComPtr<d3dcommon::ID3DBlob>,
-    ) -> Result<ComPtr<d3d11::ID3D11VertexShader>, pso::CreationError> {
-        let mut vs = ptr::null_mut();
 
+        let mut rtv = ptr::null_mut();
         let hr = unsafe {
-            self.raw.CreateVertexShader(
-                blob.GetBufferPointer(),
-                blob.GetBufferSize(),
-                ptr::null_mut(),
-                &mut vs as *mut *mut _ as *mut *mut _,
+            self.raw.CreateRenderTargetView(
+                info.resource,
+                &desc,
+                &mut rtv as *mut *mut _ as *mut *mut _,
             )
         };
 
         if winerror::SUCCEEDED(hr) {
-            Ok(unsafe { ComPtr::from_raw(vs) })
+            Ok(unsafe { ComPtr::from_raw(rtv) })
         } else {
-            Err(pso::CreationError::Other)
-        }
-    }
-
-    fn create_pixel_shader(
-        &self,
-        blob: ComPtr<d3dcommon::ID3DBlob>,
-    ) -> Result<ComPtr<d3d11::ID3D11PixelShader>, pso::CreationError> {
-        let mut ps = ptr::null_mut();
-
-        let hr = unsafe {
-            self.raw.CreatePixelShader(
-                blob.GetBufferPointer(),
-                blob.GetBufferSize(),
-                ptr::null_mut(),
-                &mut ps as *mut *mut _ as *mut *mut _,
-            )
-        };
+            error!("CreateRenderTargetView failed: 0x{:x}", hr);
 
-        if winerror::SUCCEEDED(hr) {
-            Ok(unsafe { ComPtr::from_raw(ps) })
-        } else {
-            Err(pso::CreationError::Other)
+            Err(image::ViewCreationError::Unsupported)
         }
     }
 
-    fn create_geometry_shader(
+    fn view_image_as_depth_stencil(
         &self,
-        blob: ComPtr<d3dcommon::ID3DBlob>,
-    ) -> Result<ComPtr<d3d11::ID3D11GeometryShader>, pso::CreationError> {
-        let mut gs = ptr::null_mut();
+        info: &ViewInfo,
+        read_only_stencil: Option<bool>,
+    ) -> Result<ComPtr<d3d11::ID3D11DepthStencilView>, image::ViewCreationError> {
+        #![allow(non_snake_case)]
 
-        let hr = unsafe {
-            self.raw.CreateGeometryShader(
-                blob.GetBufferPointer(),
-                blob.GetBufferSize(),
-                ptr::null_mut(),
-                &mut gs as *mut *mut _ as *mut *mut _,
-            )
-        };
+        let MipSlice = info.levels.start as _;
+        let FirstArraySlice = info.layers.start as _;
+        let ArraySize = (info.layers.end - info.layers.start) as _;
+        assert_eq!(info.levels.start + 1, info.levels.end);
+        assert!(info.layers.end <= info.kind.num_layers());
 
-        if winerror::SUCCEEDED(hr) {
-            Ok(unsafe { ComPtr::from_raw(gs) })
-        } else {
-            Err(pso::CreationError::Other)
+        let mut desc: d3d11::D3D11_DEPTH_STENCIL_VIEW_DESC = unsafe { mem::zeroed() };
+        desc.Format = info.format;
+
+        if let Some(stencil) = read_only_stencil {
+            desc.Flags = match stencil {
+                true => d3d11::D3D11_DSV_READ_ONLY_DEPTH | d3d11::D3D11_DSV_READ_ONLY_STENCIL,
+                false => d3d11::D3D11_DSV_READ_ONLY_DEPTH,
+            }
         }
-    }
 
-    fn create_hull_shader(
-        &self,
-        blob: ComPtr<d3dcommon::ID3DBlob>,
-    ) -> Result<ComPtr<d3d11::ID3D11HullShader>, pso::CreationError> {
-        let mut hs = ptr::null_mut();
+        match info.view_kind {
+            image::ViewKind::D2 => {
+                if info.kind.num_samples() > 1 {
+                    desc.ViewDimension = d3d11::D3D11_DSV_DIMENSION_TEXTURE2DMS;
+                    *unsafe { desc.u.Texture2DMS_mut() } = d3d11::D3D11_TEX2DMS_DSV {
+                        UnusedField_NothingToDefine: 0,
+                    }
+                } else {
+                    desc.ViewDimension = d3d11::D3D11_DSV_DIMENSION_TEXTURE2D;
+                    *unsafe { desc.u.Texture2D_mut() } = d3d11::D3D11_TEX2D_DSV { MipSlice }
+                }
+            }
+            image::ViewKind::D2Array => {
+                if info.kind.num_samples() > 1 {
+                    desc.ViewDimension = d3d11::D3D11_DSV_DIMENSION_TEXTURE2DMSARRAY;
+                    *unsafe { desc.u.Texture2DMSArray_mut() } = d3d11::D3D11_TEX2DMS_ARRAY_DSV {
+                        FirstArraySlice,
+                        ArraySize,
+                    }
+                } else {
+                    desc.ViewDimension = d3d11::D3D11_DSV_DIMENSION_TEXTURE2DARRAY;
+                    *unsafe { desc.u.Texture2DArray_mut() } = d3d11::D3D11_TEX2D_ARRAY_DSV {
+                        MipSlice,
+                        FirstArraySlice,
+                        ArraySize,
+                    }
+                }
+            }
+            _ => unimplemented!(),
+        }
 
+        let mut dsv = ptr::null_mut();
         let hr = unsafe {
-            self.raw.CreateHullShader(
-                blob.GetBufferPointer(),
-                blob.GetBufferSize(),
-                ptr::null_mut(),
-                &mut hs as *mut *mut _ as *mut *mut _,
+            self.raw.CreateDepthStencilView(
+                info.resource,
+                &desc,
+                &mut dsv as *mut *mut _ as *mut *mut _,
             )
         };
 
         if winerror::SUCCEEDED(hr) {
-            Ok(unsafe { ComPtr::from_raw(hs) })
+            Ok(unsafe { ComPtr::from_raw(dsv) })
         } else {
-            Err(pso::CreationError::Other)
+            error!("CreateDepthStencilView failed: 0x{:x}", hr);
+
+            Err(image::ViewCreationError::Unsupported)
         }
     }
 
-    fn create_domain_shader(
+    pub(crate) fn create_swapchain_impl(
         &self,
-        blob: ComPtr<d3dcommon::ID3DBlob>,
-    ) -> Result<ComPtr<d3d11::ID3D11DomainShader>, pso::CreationError> {
-        let mut ds = ptr::null_mut();
+        config: &window::SwapchainConfig,
+        window_handle: HWND,
+        factory: ComPtr<dxgi::IDXGIFactory>,
+    ) -> Result<(ComPtr<dxgi::IDXGISwapChain>, dxgiformat::DXGI_FORMAT), window::SwapchainError>
+    {
+        // TODO: use IDXGIFactory2 for >=11.1
+        // TODO: this function should be able to fail (Result)?
 
-        let hr = unsafe {
-            self.raw.CreateDomainShader(
-                blob.GetBufferPointer(),
-                blob.GetBufferSize(),
-                ptr::null_mut(),
-                &mut ds as *mut *mut _ as *mut *mut _,
-            )
-        };
+        debug!("{:#?}", config);
+        let non_srgb_format = conv::map_format_nosrgb(config.format).unwrap();
+
+        let mut desc = dxgi::DXGI_SWAP_CHAIN_DESC {
+            BufferDesc: dxgitype::DXGI_MODE_DESC {
+                Width: config.extent.width,
+                Height: config.extent.height,
+                // TODO: should this grab max value of all monitor hz? vsync
+                //       will clamp to current monitor anyways?
+                RefreshRate: dxgitype::DXGI_RATIONAL {
+                    Numerator: 1,
+                    Denominator: 60,
+                },
+                Format: non_srgb_format,
+                ScanlineOrdering: dxgitype::DXGI_MODE_SCANLINE_ORDER_UNSPECIFIED,
+                Scaling: dxgitype::DXGI_MODE_SCALING_UNSPECIFIED,
+            },
+            SampleDesc: dxgitype::DXGI_SAMPLE_DESC {
+                Count: 1,
+                Quality: 0,
+            },
+            BufferUsage: dxgitype::DXGI_USAGE_RENDER_TARGET_OUTPUT,
+            BufferCount: config.image_count,
+            OutputWindow: window_handle,
+            // TODO:
+            Windowed: TRUE,
+            // TODO:
+            SwapEffect: dxgi::DXGI_SWAP_EFFECT_DISCARD,
+            Flags: 0,
+        };
+
+        let dxgi_swapchain = {
+            let mut swapchain: *mut dxgi::IDXGISwapChain = ptr::null_mut();
+            let hr = unsafe {
+                factory.CreateSwapChain(
+                    self.raw.as_raw() as *mut _,
+                    &mut desc as *mut _,
+                    &mut swapchain as *mut *mut _ as *mut *mut _,
+                )
+            };
+            assert_eq!(hr, winerror::S_OK);
+
+            unsafe { ComPtr::from_raw(swapchain) }
+        };
+        Ok((dxgi_swapchain, non_srgb_format))
+    }
+}
+
+/// The part of the device the pipelines are created with, which is moved
+/// to the pipeline creation threads. D3D11 devices are thread-safe.
+struct PipelineDevice {
+    raw: ComPtr<d3d11::ID3D11Device>,
+    features: hal::Features,
+}
+
+unsafe impl Send for PipelineDevice {}
+
+impl PipelineDevice {
+    fn create_rasterizer_state(
+        &self,
+        rasterizer_desc: &pso::Rasterizer,
+        multisampling_desc: &Option<pso::Multisampling>,
+    ) -> Result<ComPtr<d3d11::ID3D11RasterizerState>, pso::CreationError> {
+        let mut rasterizer = ptr::null_mut();
+        let desc = conv::map_rasterizer_desc(rasterizer_desc, multisampling_desc);
+
+        let hr = unsafe {
+            self.raw
+                .CreateRasterizerState(&desc, &mut rasterizer as *mut *mut _ as *mut *mut _)
+        };
 
         if winerror::SUCCEEDED(hr) {
-            Ok(unsafe { ComPtr::from_raw(ds) })
+            Ok(unsafe { ComPtr::from_raw(rasterizer) })
         } else {
             Err(pso::CreationError::Other)
         }
     }
 
-    fn create_compute_shader(
+    fn create_blend_state(
         &self,
-        blob: ComPtr<d3dcommon::ID3DBlob>,
-    ) -> Result<ComPtr<d3d11::ID3D11ComputeShader>, pso::CreationError> {
-        let mut cs = ptr::null_mut();
+        blend_desc: &pso::BlendDesc,
+        multisampling: &Option<pso::Multisampling>,
+    ) -> Result<ComPtr<d3d11::ID3D11BlendState>, pso::CreationError> {
+        let mut blend = ptr::null_mut();
+        let desc = conv::map_blend_desc(blend_desc, multisampling);
 
         let hr = unsafe {
-            self.raw.CreateComputeShader(
-                blob.GetBufferPointer(),
-                blob.GetBufferSize(),
-                ptr::null_mut(),
-                &mut cs as *mut *mut _ as *mut *mut _,
-            )
+            self.raw
+                .CreateBlendState(&desc, &mut blend as *mut *mut _ as *mut *mut _)
         };
 
         if winerror::SUCCEEDED(hr) {
-            Ok(unsafe { ComPtr::from_raw(cs) })
+            Ok(unsafe { ComPtr::from_raw(blend) })
         } else {
             Err(pso::CreationError::Other)
         }
     }
 
-    // TODO: fix return type..
-    fn extract_entry_point(
-        stage: ShaderStage,
-        source: &pso::EntryPoint<Backend>,
-        layout: &PipelineLayout,
-        features: &hal::Features,
-    ) -> Result<Option<ComPtr<d3dcommon::ID3DBlob>>, pso::CreationError> {
-        // TODO: entrypoint stuff
-        match *source.module {
-            ShaderModule::Dxbc(ref _shader) => {
-                error!("DXBC modules are not supported yet");
-                Err(pso::CreationError::Other)
-            }
-            ShaderModule::Spirv(ref raw_data) => Ok(shader::compile_spirv_entrypoint(
-                raw_data, stage, source, layout, features,
-            )?),
+    fn create_depth_stencil_state(
+        &self,
+        depth_desc: &pso::DepthStencilDesc,
+    ) -> Result<DepthStencilState, pso::CreationError> {
+        let mut depth = ptr::null_mut();
+        let (desc, stencil_ref, read_only) = conv::map_depth_stencil_desc(depth_desc);
+
+        let hr = unsafe {
+            self.raw
+                .CreateDepthStencilState(&desc, &mut depth as *mut *mut _ as *mut *mut _)
+        };
+
+        if winerror::SUCCEEDED(hr) {
+            Ok(DepthStencilState {
+                raw: unsafe { ComPtr::from_raw(depth) },
+                stencil_ref,
+                read_only,
+            })
+        } else {
+            Err(pso::CreationError::Other)
         }
     }
 
-    fn view_image_as_shader_resource(
+    fn create_input_layout(
         &self,
-        info: &ViewInfo,
-    ) -> Result<ComPtr<d3d11::ID3D11ShaderResourceView>, image::ViewCreationError> {
-        let mut desc: d3d11::D3D11_SHADER_RESOURCE_VIEW_DESC = unsafe { mem::zeroed() };
-        desc.Format = info.format;
-        if desc.Format == dxgiformat::DXGI_FORMAT_D32_FLOAT_S8X24_UINT {
-            desc.Format = dxgiformat::DXGI_FORMAT_R32_FLOAT_X8X24_TYPELESS;
-        }
+        vs: ComPtr<d3dcommon::ID3DBlob>,
+        vertex_buffers: &[pso::VertexBufferDesc],
+        attributes: &[pso::AttributeDesc],
+        input_assembler: &pso::InputAssemblerDesc,
+        vertex_semantic_remapping: auxil::FastHashMap<u32, Option<(u32, u32)>>,
+    ) -> Result<InputLayout, pso::CreationError> {
+        let mut layout = ptr::null_mut();
 
-        #[allow(non_snake_case)]
-        let MostDetailedMip = info.levels.start as _;
-        #[allow(non_snake_case)]
-        let MipLevels = (info.levels.end - info.levels.start) as _;
-        #[allow(non_snake_case)]
-        let FirstArraySlice = info.layers.start as _;
-        #[allow(non_snake_case)]
-        let ArraySize = (info.layers.end - info.layers.start) as _;
+        let mut vertex_strides = Vec::new();
+        let mut required_bindings = 0u32;
+        let mut max_vertex_bindings = 0u32;
+        for buffer in vertex_buffers {
+            required_bindings |= 1 << buffer.binding as u32;
+            max_vertex_bindings = max_vertex_bindings.max(1u32 + buffer.binding as u32);
 
-        match info.view_kind {
-            image::ViewKind::D1 => {
-                desc.ViewDimension = d3dcommon::D3D11_SRV_DIMENSION_TEXTURE1D;
-                *unsafe { desc.u.Texture1D_mut() } = d3d11::D3D11_TEX1D_SRV {
-                    MostDetailedMip,
-                    MipLevels,
-                }
-            }
-            image::ViewKind::D1Array => {
-                desc.ViewDimension = d3dcommon::D3D11_SRV_DIMENSION_TEXTURE1DARRAY;
-                *unsafe { desc.u.Texture1DArray_mut() } = d3d11::D3D11_TEX1D_ARRAY_SRV {
-                    MostDetailedMip,
-                    MipLevels,
-                    FirstArraySlice,
-                    ArraySize,
-                }
-            }
-            image::ViewKind::D2 if info.kind.num_samples() > 1 => {
-                desc.ViewDimension = d3dcommon::D3D11_SRV_DIMENSION_TEXTURE2DMS;
-                *unsafe { desc.u.Texture2DMS_mut() } = d3d11::D3D11_TEX2DMS_SRV {
-                    UnusedField_NothingToDefine: 0,
-                }
-            }
-            image::ViewKind::D2 => {
-                desc.ViewDimension = d3dcommon::D3D11_SRV_DIMENSION_TEXTURE2D;
-                *unsafe { desc.u.Texture2D_mut() } = d3d11::D3D11_TEX2D_SRV {
-                    MostDetailedMip,
-                    MipLevels,
-                }
-            }
-            image::ViewKind::D2Array if info.kind.num_samples() > 1 => {
-                desc.ViewDimension = d3dcommon::D3D11_SRV_DIMENSION_TEXTURE2DMSARRAY;
-                *unsafe { desc.u.Texture2DMSArray_mut() } = d3d11::D3D11_TEX2DMS_ARRAY_SRV {
-                    FirstArraySlice,
-                    ArraySize,
-                }
-            }
-            image::ViewKind::D2Array => {
-                desc.ViewDimension = d3dcommon::D3D11_SRV_DIMENSION_TEXTURE2DARRAY;
-                *unsafe { desc.u.Texture2DArray_mut() } = d3d11::D3D11_TEX2D_ARRAY_SRV {
-                    MostDetailedMip,
-                    MipLevels,
-                    FirstArraySlice,
-                    ArraySize,
-                }
-            }
-            image::ViewKind::D3 => {
-                desc.ViewDimension = d3dcommon::D3D11_SRV_DIMENSION_TEXTURE3D;
-                *unsafe { desc.u.Texture3D_mut() } = d3d11::D3D11_TEX3D_SRV {
-                    MostDetailedMip,
-                    MipLevels,
-                }
-            }
-            image::ViewKind::Cube => {
-                desc.ViewDimension = d3dcommon::D3D11_SRV_DIMENSION_TEXTURECUBE;
-                *unsafe { desc.u.TextureCube_mut() } = d3d11::D3D11_TEXCUBE_SRV {
-                    MostDetailedMip,
-                    MipLevels,
-                }
-            }
-            image::ViewKind::CubeArray => {
-                desc.ViewDimension = d3dcommon::D3D11_SRV_DIMENSION_TEXTURECUBEARRAY;
-                *unsafe { desc.u.TextureCubeArray_mut() } = d3d11::D3D11_TEXCUBE_ARRAY_SRV {
-                    MostDetailedMip,
-                    MipLevels,
-                    First2DArrayFace: FirstArraySlice,
-                    NumCubes: ArraySize / 6,
-                }
+            while vertex_strides.len() <= buffer.binding as usize {
+                vertex_strides.push(0);
             }
+
+            vertex_strides[buffer.binding as usize] = buffer.stride;
         }
 
-        let mut srv = ptr::null_mut();
+        // See [`shader::introspect_spirv_vertex_semantic_remapping`] for details of why this is needed.
+        let semantics: Vec<_> = attributes
+            .iter()
+            .map(
+                |attrib| match vertex_semantic_remapping.get(&attrib.location) {
+                    Some(Some((major, minor))) => {
+                        let name = std::borrow::Cow::Owned(format!("TEXCOORD{}_\0", major));
+                        let location = *minor;
+                        (name, location)
+                    }
+                    _ => {
+                        let name = std::borrow::Cow::Borrowed("TEXCOORD\0");
+                        let location = attrib.location;
+                        (name, location)
+                    }
+                },
+            )
+            .collect();
+
+        let input_elements = attributes
+            .iter()
+            .zip(semantics.iter())
+            .filter_map(|(attrib, (semantic_name, semantic_index))| {
+                let buffer_desc = match vertex_buffers
+                    .iter()
+                    .find(|buffer_desc| buffer_desc.binding == attrib.binding)
+                {
+                    Some(buffer_desc) => buffer_desc,
+                    None => {
+                        // TODO:
+                        // error!("Couldn't find associated vertex buffer description {:?}", attrib.binding);
+                        return Some(Err(pso::CreationError::Other));
+                    }
+                };
+
+                let (slot_class, step_rate) = match buffer_desc.rate {
+                    VertexInputRate::Vertex => (d3d11::D3D11_INPUT_PER_VERTEX_DATA, 0),
+                    VertexInputRate::Instance(divisor) => {
+                        (d3d11::D3D11_INPUT_PER_INSTANCE_DATA, divisor)
+                    }
+                };
+                let format = attrib.element.format;
+
+                Some(Ok(d3d11::D3D11_INPUT_ELEMENT_DESC {
+                    SemanticName: semantic_name.as_ptr() as *const _, // Semantic name used by SPIRV-Cross
+                    SemanticIndex: *semantic_index,
+                    Format: match conv::map_format(format) {
+                        Some(fm) => fm,
+                        None => {
+                            // TODO:
+                            // error!("Unable to find DXGI format for {:?}", format);
+                            return Some(Err(pso::CreationError::Other));
+                        }
+                    },
+                    InputSlot: attrib.binding as _,
+                    AlignedByteOffset: attrib.element.offset,
+                    InputSlotClass: slot_class,
+                    InstanceDataStepRate: step_rate as _,
+                }))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
         let hr = unsafe {
-            self.raw.CreateShaderResourceView(
-                info.resource,
-                &desc,
-                &mut srv as *mut *mut _ as *mut *mut _,
+            self.raw.CreateInputLayout(
+                input_elements.as_ptr(),
+                input_elements.len() as _,
+                vs.GetBufferPointer(),
+                vs.GetBufferSize(),
+                &mut layout as *mut *mut _ as *mut *mut _,
             )
         };
 
         if winerror::SUCCEEDED(hr) {
-            Ok(unsafe { ComPtr::from_raw(srv) })
+            let topology = conv::map_topology(input_assembler);
+
+            Ok(InputLayout {
+                raw: unsafe { ComPtr::from_raw(layout) },
+                required_bindings,
+                max_vertex_bindings,
+                topology,
+                vertex_strides,
+            })
         } else {
-            Err(image::ViewCreationError::Unsupported)
+            error!("CreateInputLayout error 0x{:X}", hr);
+            Err(pso::CreationError::Other)
         }
     }
 
-    fn view_image_as_unordered_access(
+    fn create_vertex_shader(
         &self,
-        info: &ViewInfo,
-    ) -> Result<ComPtr<d3d11::ID3D11UnorderedAccessView>, image::ViewCreationError> {
-        let mut desc: d3d11::D3D11_UNORDERED_ACCESS_VIEW_DESC = unsafe { mem::zeroed() };
-        desc.Format = info.format;
+        blob: ComPtr<d3dcommon::ID3DBlob>,
+    ) -> Result<ComPtr<d3d11::ID3D11VertexShader>, pso::CreationError> {
+        let mut vs = ptr::null_mut();
 
-        #[allow(non_snake_case)]
-        let MipSlice = info.levels.start as _;
-        #[allow(non_snake_case)]
-        let FirstArraySlice = info.layers.start as _;
-        #[allow(non_snake_case)]
-        let ArraySize = (info.layers.end - info.layers.start) as _;
+        let hr = unsafe {
+            self.raw.CreateVertexShader(
+                blob.GetBufferPointer(),
+                blob.GetBufferSize(),
+                ptr::null_mut(),
+                &mut vs as *mut *mut _ as *mut *mut _,
+            )
+        };
 
-        match info.view_kind {
-            image::ViewKind::D1 => {
-                desc.ViewDimension = d3d11::D3D11_UAV_DIMENSION_TEXTURE1D;
-                *unsafe { desc.u.Texture1D_mut() } = d3d11::D3D11_TEX1D_UAV {
-                    MipSlice: info.levels.start as _,
-                }
-            }
-            image::ViewKind::D1Array => {
-                desc.ViewDimension = d3d11::D3D11_UAV_DIMENSION_TEXTURE1DARRAY;
-                *unsafe { desc.u.Texture1DArray_mut() } = d3d11::D3D11_TEX1D_ARRAY_UAV {
-                    MipSlice,
-                    FirstArraySlice,
-                    ArraySize,
-                }
-            }
-            image::ViewKind::D2 => {
-                desc.ViewDimension = d3d11::D3D11_UAV_DIMENSION_TEXTURE2D;
-                *unsafe { desc.u.Texture2D_mut() } = d3d11::D3D11_TEX2D_UAV {
-                    MipSlice: info.levels.start as _,
-                }
-            }
-            image::ViewKind::D2Array => {
-                desc.ViewDimension = d3d11::D3D11_UAV_DIMENSION_TEXTURE2DARRAY;
-                *unsafe { desc.u.Texture2DArray_mut() } = d3d11::D3D11_TEX2D_ARRAY_UAV {
-                    MipSlice,
-                    FirstArraySlice,
-                    ArraySize,
-                }
-            }
-            image::ViewKind::D3 => {
-                desc.ViewDimension = d3d11::D3D11_UAV_DIMENSION_TEXTURE3D;
-                *unsafe { desc.u.Texture3D_mut() } = d3d11::D3D11_TEX3D_UAV {
-                    MipSlice,
-                    FirstWSlice: FirstArraySlice,
-                    WSize: ArraySize,
-                }
-            }
-            _ => unimplemented!(),
+        if winerror::SUCCEEDED(hr) {
+            Ok(unsafe { ComPtr::from_raw(vs) })
+        } else {
+            Err(pso::CreationError::Other)
         }
+    }
+
+    fn create_pixel_shader(
+        &self,
+        blob: ComPtr<d3dcommon::ID3DBlob>,
+    ) -> Result<ComPtr<d3d11::ID3D11PixelShader>, pso::CreationError> {
+        let mut ps = ptr::null_mut();
 
-        let mut uav = ptr::null_mut();
         let hr = unsafe {
-            self.raw.CreateUnorderedAccessView(
-                info.resource,
-                &desc,
-                &mut uav as *mut *mut _ as *mut *mut _,
+            self.raw.CreatePixelShader(
+                blob.GetBufferPointer(),
+                blob.GetBufferSize(),
+                ptr::null_mut(),
+                &mut ps as *mut *mut _ as *mut *mut _,
             )
         };
 
         if winerror::SUCCEEDED(hr) {
-            Ok(unsafe { ComPtr::from_raw(uav) })
+            Ok(unsafe { ComPtr::from_raw(ps) })
         } else {
-            error!("CreateUnorderedAccessView failed: 0x{:x}", hr);
-
-            Err(image::ViewCreationError::Unsupported)
+            Err(pso::CreationError::Other)
         }
     }
 
-    pub(crate) fn view_image_as_render_target(
+    fn create_geometry_shader(
         &self,
-        info: &ViewInfo,
-    ) -> Result<ComPtr<d3d11::ID3D11RenderTargetView>, image::ViewCreationError> {
-        let mut desc: d3d11::D3D11_RENDER_TARGET_VIEW_DESC = unsafe { mem::zeroed() };
-        desc.Format = info.format;
+        blob: ComPtr<d3dcommon::ID3DBlob>,
+    ) -> Result<ComPtr<d3d11::ID3D11GeometryShader>, pso::CreationError> {
+        let mut gs = ptr::null_mut();
 
-        #[allow(non_snake_case)]
-        let MipSlice = info.levels.start as _;
-        #[allow(non_snake_case)]
-        let FirstArraySlice = info.layers.start as _;
-        #[allow(non_snake_case)]
-        let ArraySize = (info.layers.end - info.layers.start) as _;
+        let hr = unsafe {
+            self.raw.CreateGeometryShader(
+                blob.GetBufferPointer(),
+                blob.GetBufferSize(),
+                ptr::null_mut(),
+                &mut gs as *mut *mut _ as *mut *mut _,
+            )
+        };
 
-        match info.view_kind {
-            image::ViewKind::D1 => {
-                desc.ViewDimension = d3d11::D3D11_RTV_DIMENSION_TEXTURE1D;
-                *unsafe { desc.u.Texture1D_mut() } = d3d11::D3D11_TEX1D_RTV { MipSlice }
-            }
-            image::ViewKind::D1Array => {
-                desc.ViewDimension = d3d11::D3D11_RTV_DIMENSION_TEXTURE1DARRAY;
-                *unsafe { desc.u.Texture1DArray_mut() } = d3d11::D3D11_TEX1D_ARRAY_RTV {
-                    MipSlice,
-                    FirstArraySlice,
-                    ArraySize,
-                }
-            }
-            image::ViewKind::D2 => {
-                if info.kind.num_samples() > 1 {
-                    desc.ViewDimension = d3d11::D3D11_RTV_DIMENSION_TEXTURE2DMS;
-                    *unsafe { desc.u.Texture2DMS_mut() } = d3d11::D3D11_TEX2DMS_RTV {
-                        UnusedField_NothingToDefine: 0,
-                    }
-                } else {
-                    desc.ViewDimension = d3d11::D3D11_RTV_DIMENSION_TEXTURE2D;
-                    *unsafe { desc.u.Texture2D_mut() } = d3d11::D3D11_TEX2D_RTV { MipSlice }
-                }
-            }
-            image::ViewKind::D2Array => {
-                if info.kind.num_samples() > 1 {
-                    desc.ViewDimension = d3d11::D3D11_RTV_DIMENSION_TEXTURE2DMSARRAY;
-                    *unsafe { desc.u.Texture2DMSArray_mut() } = d3d11::D3D11_TEX2DMS_ARRAY_RTV {
-                        FirstArraySlice,
-                        ArraySize,
-                    }
-                } else {
-                    desc.ViewDimension = d3d11::D3D11_RTV_DIMENSION_TEXTURE2DARRAY;
-                    *unsafe { desc.u.Texture2DArray_mut() } = d3d11::D3D11_TEX2D_ARRAY_RTV {
-                        MipSlice,
-                        FirstArraySlice,
-                        ArraySize,
-                    }
-                }
-            }
-            image::ViewKind::D3 => {
-                desc.ViewDimension = d3d11::D3D11_RTV_DIMENSION_TEXTURE3D;
-                *unsafe { desc.u.Texture3D_mut() } = d3d11::D3D11_TEX3D_RTV {
-                    MipSlice,
-                    FirstWSlice: FirstArraySlice,
-                    WSize: ArraySize,
-                }
-            }
-            _ => unimplemented!(),
+        if winerror::SUCCEEDED(hr) {
+            Ok(unsafe { ComPtr::from_raw(gs) })
+        } else {
+            Err(pso::CreationError::Other)
         }
+    }
+
+    fn create_hull_shader(
+        &self,
+        blob: ComPtr<d3dcommon::ID3DBlob>,
+    ) -> Result<ComPtr<d3d11::ID3D11HullShader>, pso::CreationError> {
+        let mut hs = ptr::null_mut();
 
-        let mut rtv = ptr::null_mut();
         let hr = unsafe {
-            self.raw.CreateRenderTargetView(
-                info.resource,
-                &desc,
-                &mut rtv as *mut *mut _ as *mut *mut _,
+            self.raw.CreateHullShader(
+                blob.GetBufferPointer(),
+                blob.GetBufferSize(),
+                ptr::null_mut(),
+                &mut hs as *mut *mut _ as *mut *mut _,
             )
         };
 
         if winerror::SUCCEEDED(hr) {
-            Ok(unsafe { ComPtr::from_raw(rtv) })
+            Ok(unsafe { ComPtr::from_raw(hs) })
         } else {
-            error!("CreateRenderTargetView failed: 0x{:x}", hr);
-
-            Err(image::ViewCreationError::Unsupported)
+            Err(pso::CreationError::Other)
         }
     }
 
-    fn view_image_as_depth_stencil(
+    fn create_domain_shader(
         &self,
-        info: &ViewInfo,
-        read_only_stencil: Option<bool>,
-    ) -> Result<ComPtr<d3d11::ID3D11DepthStencilView>, image::ViewCreationError> {
-        #![allow(non_snake_case)]
-
-        let MipSlice = info.levels.start as _;
-        let FirstArraySlice = info.layers.start as _;
-        let ArraySize = (info.layers.end - info.layers.start) as _;
-        assert_eq!(info.levels.start + 1, info.levels.end);
-        assert!(info.layers.end <= info.kind.num_layers());
+        blob: ComPtr<d3dcommon::ID3DBlob>,
+    ) -> Result<ComPtr<d3d11::ID3D11DomainShader>, pso::CreationError> {
+        let mut ds = ptr::null_mut();
 
-        let mut desc: d3d11::D3D11_DEPTH_STENCIL_VIEW_DESC = unsafe { mem::zeroed() };
-        desc.Format = info.format;
+        let hr = unsafe {
+            self.raw.CreateDomainShader(
+                blob.GetBufferPointer(),
+                blob.GetBufferSize(),
+                ptr::null_mut(),
+                &mut ds as *mut *mut _ as *mut *mut _,
+            )
+        };
 
-        if let Some(stencil) = read_only_stencil {
-            desc.Flags = match stencil {
-                true => d3d11::D3D11_DSV_READ_ONLY_DEPTH | d3d11::D3D11_DSV_READ_ONLY_STENCIL,
-                false => d3d11::D3D11_DSV_READ_ONLY_DEPTH,
-            }
+        if winerror::SUCCEEDED(hr) {
+            Ok(unsafe { ComPtr::from_raw(ds) })
+        } else {
+            Err(pso::CreationError::Other)
         }
+    }
 
-        match info.view_kind {
-            image::ViewKind::D2 => {
-                if info.kind.num_samples() > 1 {
-                    desc.ViewDimension = d3d11::D3D11_DSV_DIMENSION_TEXTURE2DMS;
-                    *unsafe { desc.u.Texture2DMS_mut() } = d3d11::D3D11_TEX2DMS_DSV {
-                        UnusedField_NothingToDefine: 0,
-                    }
-                } else {
-                    desc.ViewDimension = d3d11::D3D11_DSV_DIMENSION_TEXTURE2D;
-                    *unsafe { desc.u.Texture2D_mut() } = d3d11::D3D11_TEX2D_DSV { MipSlice }
-                }
-            }
-            image::ViewKind::D2Array => {
-                if info.kind.num_samples() > 1 {
-                    desc.ViewDimension = d3d11::D3D11_DSV_DIMENSION_TEXTURE2DMSARRAY;
-                    *unsafe { desc.u.Texture2DMSArray_mut() } = d3d11::D3D11_TEX2DMS_ARRAY_DSV {
-                        FirstArraySlice,
-                        ArraySize,
-                    }
-                } else {
-                    desc.ViewDimension = d3d11::D3D11_DSV_DIMENSION_TEXTURE2DARRAY;
-                    *unsafe { desc.u.Texture2DArray_mut() } = d3d11::D3D11_TEX2D_ARRAY_DSV {
-                        MipSlice,
-                        FirstArraySlice,
-                        ArraySize,
-                    }
-                }
-            }
-            _ => unimplemented!(),
-        }
+    fn create_compute_shader(
+        &self,
+        blob: ComPtr<d3dcommon::ID3DBlob>,
+    ) -> Result<ComPtr<d3d11::ID3D11ComputeShader>, pso::CreationError> {
+        let mut cs = ptr::null_mut();
 
-        let mut dsv = ptr::null_mut();
         let hr = unsafe {
-            self.raw.CreateDepthStencilView(
-                info.resource,
-                &desc,
-                &mut dsv as *mut *mut _ as *mut *mut _,
+            self.raw.CreateComputeShader(
+                blob.GetBufferPointer(),
+                blob.GetBufferSize(),
+                ptr::null_mut(),
+                &mut cs as *mut *mut _ as *mut *mut _,
             )
         };
 
         if winerror::SUCCEEDED(hr) {
-            Ok(unsafe { ComPtr::from_raw(dsv) })
+            Ok(unsafe { ComPtr::from_raw(cs) })
         } else {
-            error!("CreateDepthStencilView failed: 0x{:x}", hr);
+            Err(pso::CreationError::Other)
+        }
+    }
 
-            Err(image::ViewCreationError::Unsupported)
+    // TODO: fix return type..
+    fn extract_entry_point(
+        stage: ShaderStage,
+        source: &pso::EntryPoint<Backend>,
+        layout: &PipelineLayout,
+        features: &hal::Features,
+    ) -> Result<Option<ComPtr<d3dcommon::ID3DBlob>>, pso::CreationError> {
+        // TODO: entrypoint stuff
+        match *source.module {
+            ShaderModule::Dxbc(ref _shader) => {
+                error!("DXBC modules are not supported yet");
+                Err(pso::CreationError::Other)
+            }
+            ShaderModule::Spirv(ref raw_data) => Ok(shader::compile_spirv_entrypoint(
+                raw_data, stage, source, layout, features,
+            )?),
         }
     }
 
-    pub(crate) fn create_swapchain_impl(
+    unsafe fn create_graphics_pipeline<'a>(
         &self,
-        config: &window::SwapchainConfig,
-        window_handle: HWND,
-        factory: ComPtr<dxgi::IDXGIFactory>,
-    ) -> Result<(ComPtr<dxgi::IDXGISwapChain>, dxgiformat::DXGI_FORMAT), window::SwapchainError>
-    {
-        // TODO: use IDXGIFactory2 for >=11.1
-        // TODO: this function should be able to fail (Result)?
+        desc: &pso::GraphicsPipelineDesc<'a, Backend>,
+    ) -> Result<GraphicsPipeline, pso::CreationError> {
+        let features = &self.features;
+        let build_shader =
+            |stage: ShaderStage, source: Option<&pso::EntryPoint<'a, Backend>>| match source {
+                Some(src) => Self::extract_entry_point(stage, src, desc.layout, features),
+                None => Ok(None),
+            };
 
-        debug!("{:#?}", config);
-        let non_srgb_format = conv::map_format_nosrgb(config.format).unwrap();
+        let (layout, vs, gs, hs, ds) = match desc.primitive_assembler {
+            pso::PrimitiveAssemblerDesc::Vertex {
+                buffers,
+                attributes,
+                ref input_assembler,
+                ref vertex,
+                ref tessellation,
+                ref geometry,
+            } => {
+                let vertex_semantic_remapping = match vertex.module {
+                    ShaderModule::Spirv(spirv) => {
+                        shader::introspect_spirv_vertex_semantic_remapping(spirv)?
+                    }
+                    _ => unimplemented!(),
+                };
 
-        let mut desc = dxgi::DXGI_SWAP_CHAIN_DESC {
-            BufferDesc: dxgitype::DXGI_MODE_DESC {
-                Width: config.extent.width,
-                Height: config.extent.height,
-                // TODO: should this grab max value of all monitor hz? vsync
-                //       will clamp to current monitor anyways?
-                RefreshRate: dxgitype::DXGI_RATIONAL {
-                    Numerator: 1,
-                    Denominator: 60,
-                },
-                Format: non_srgb_format,
-                ScanlineOrdering: dxgitype::DXGI_MODE_SCANLINE_ORDER_UNSPECIFIED,
-                Scaling: dxgitype::DXGI_MODE_SCALING_UNSPECIFIED,
-            },
-            SampleDesc: dxgitype::DXGI_SAMPLE_DESC {
-                Count: 1,
-                Quality: 0,
-            },
-            BufferUsage: dxgitype::DXGI_USAGE_RENDER_TARGET_OUTPUT,
-            BufferCount: config.image_count,
-            OutputWindow: window_handle,
-            // TODO:
-            Windowed: TRUE,
-            // TODO:
-            SwapEffect: dxgi::DXGI_SWAP_EFFECT_DISCARD,
-            Flags: 0,
+                let vs = build_shader(ShaderStage::Vertex, Some(&vertex))?.unwrap();
+                let gs = build_shader(ShaderStage::Geometry, geometry.as_ref())?;
+
+                let layout = self.create_input_layout(
+                    vs.clone(),
+                    buffers,
+                    attributes,
+                    input_assembler,
+                    vertex_semantic_remapping,
+                )?;
+
+                let vs = self.create_vertex_shader(vs)?;
+                let gs = if let Some(blob) = gs {
+                    Some(self.create_geometry_shader(blob)?)
+                } else {
+                    None
+                };
+                let (hs, ds) = if let Some(ts) = tessellation {
+                    let hs = build_shader(ShaderStage::Hull, Some(&ts.0))?.unwrap();
+                    let ds = build_shader(ShaderStage::Domain, Some(&ts.1))?.unwrap();
+
+                    (
+                        Some(self.create_hull_shader(hs)?),
+                        Some(self.create_domain_shader(ds)?),
+                    )
+                } else {
+                    (None, None)
+                };
+
+                (layout, vs, gs, hs, ds)
+            }
+            pso::PrimitiveAssemblerDesc::Mesh { .. } => {
+                return Err(pso::CreationError::UnsupportedPipeline)
+            }
         };
 
-        let dxgi_swapchain = {
-            let mut swapchain: *mut dxgi::IDXGISwapChain = ptr::null_mut();
-            let hr = unsafe {
-                factory.CreateSwapChain(
-                    self.raw.as_raw() as *mut _,
-                    &mut desc as *mut _,
-                    &mut swapchain as *mut *mut _ as *mut *mut _,
-                )
+        let ps = build_shader(ShaderStage::Fragment, desc.fragment.as_ref())?;
+        let ps = if let Some(blob) = ps {
+            Some(self.create_pixel_shader(blob)?)
+        } else {
+            None
+        };
+
+        if let Some(ref ms) = desc.multisampling {
+            if ms.alpha_coverage && ms.rasterization_samples <= 1 {
+                warn!("Alpha-to-coverage has no effect without multisampling");
+            }
+            // Never exposed through `Features::ALPHA_TO_ONE`
+            if ms.alpha_to_one {
+                warn!("Alpha-to-one is not supported, ignoring");
+            }
+            // Never exposed through `Features::SAMPLE_RATE_SHADING`, the shaders
+            // reading the sample index are the only ones running per sample
+            if ms.sample_shading.is_some() {
+                warn!("Sample shading is not supported, ignoring");
+            }
+        }
+
+        let rasterizer_state =
+            self.create_rasterizer_state(&desc.rasterizer, &desc.multisampling)?;
+        let blend_state = self.create_blend_state(&desc.blender, &desc.multisampling)?;
+        let depth_stencil_state = Some(self.create_depth_stencil_state(&desc.depth_stencil)?);
+
+        match desc.label {
+            Some(label) if verify_debug_ascii(label) => {
+                let mut name = label.to_string();
+
+                set_debug_name_with_suffix(&blend_state, &mut name, " -- Blend State");
+                set_debug_name_with_suffix(&rasterizer_state, &mut name, " -- Rasterizer State");
+                set_debug_name_with_suffix(&layout.raw, &mut name, " -- Input Layout");
+                if let Some(ref dss) = depth_stencil_state {
+                    set_debug_name_with_suffix(&dss.raw, &mut name, " -- Depth Stencil State");
+                }
+            }
+            _ => {}
+        }
+
+        Ok(GraphicsPipeline {
+            vs,
+            gs,
+            ds,
+            hs,
+            ps,
+            topology: layout.topology,
+            input_layout: layout.raw,
+            rasterizer_state,
+            blend_state,
+            depth_stencil_state,
+            baked_states: desc.baked_states.clone(),
+            required_bindings: layout.required_bindings,
+            max_vertex_bindings: layout.max_vertex_bindings,
+            strides: layout.vertex_strides,
+        })
+    }
+
+    unsafe fn create_compute_pipeline<'a>(
+        &self,
+        desc: &pso::ComputePipelineDesc<'a, Backend>,
+    ) -> Result<ComputePipeline, pso::CreationError> {
+        let features = &self.features;
+        let build_shader =
+            |stage: ShaderStage, source: Option<&pso::EntryPoint<'a, Backend>>| match source {
+                Some(src) => Self::extract_entry_point(stage, src, desc.layout, features),
+                None => Ok(None),
             };
-            assert_eq!(hr, winerror::S_OK);
 
-            unsafe { ComPtr::from_raw(swapchain) }
-        };
-        Ok((dxgi_swapchain, non_srgb_format))
+        let cs = build_shader(ShaderStage::Compute, Some(&desc.shader))?.unwrap();
+        let cs = self.create_compute_shader(cs)?;
+
+        Ok(ComputePipeline { cs })
     }
 }
 
@@ -869,11 +1048,9 @@ impl device::Device<Backend> for Device {
         Ok(RenderPass {
             attachments: attachments.collect(),
             subpasses: subpasses
-                .map(|desc| SubpassDesc {
-                    color_attachments: desc.colors.to_vec(),
-                    depth_stencil_attachment: desc.depth_stencil.cloned(),
-                    input_attachments: desc.inputs.to_vec(),
-                    resolve_attachments: desc.resolves.to_vec(),
+                .map(|desc| {
+                    assert_eq!(desc.view_mask, 0, "DX11 doesn't support MULTIVIEW");
+                    SubpassDesc::from(&desc)
                 })
                 .collect(),
         })
@@ -964,107 +1141,26 @@ impl device::Device<Backend> for Device {
         desc: &pso::GraphicsPipelineDesc<'a, Backend>,
         _cache: Option<&()>,
     ) -> Result<GraphicsPipeline, pso::CreationError> {
-        let features = &self.features;
-        let build_shader =
-            |stage: ShaderStage, source: Option<&pso::EntryPoint<'a, Backend>>| match source {
-                Some(src) => Self::extract_entry_point(stage, src, desc.layout, features),
-                None => Ok(None),
-            };
-
-        let (layout, vs, gs, hs, ds) = match desc.primitive_assembler {
-            pso::PrimitiveAssemblerDesc::Vertex {
-                buffers,
-                attributes,
-                ref input_assembler,
-                ref vertex,
-                ref tessellation,
-                ref geometry,
-            } => {
-                let vertex_semantic_remapping = match vertex.module {
-                    ShaderModule::Spirv(spirv) => {
-                        shader::introspect_spirv_vertex_semantic_remapping(spirv)?
-                    }
-                    _ => unimplemented!(),
-                };
-
-                let vs = build_shader(ShaderStage::Vertex, Some(&vertex))?.unwrap();
-                let gs = build_shader(ShaderStage::Geometry, geometry.as_ref())?;
-
-                let layout = self.create_input_layout(
-                    vs.clone(),
-                    buffers,
-                    attributes,
-                    input_assembler,
-                    vertex_semantic_remapping,
-                )?;
-
-                let vs = self.create_vertex_shader(vs)?;
-                let gs = if let Some(blob) = gs {
-                    Some(self.create_geometry_shader(blob)?)
-                } else {
-                    None
-                };
-                let (hs, ds) = if let Some(ts) = tessellation {
-                    let hs = build_shader(ShaderStage::Hull, Some(&ts.0))?.unwrap();
-                    let ds = build_shader(ShaderStage::Domain, Some(&ts.1))?.unwrap();
-
-                    (
-                        Some(self.create_hull_shader(hs)?),
-                        Some(self.create_domain_shader(ds)?),
-                    )
-                } else {
-                    (None, None)
-                };
-
-                (layout, vs, gs, hs, ds)
-            }
-            pso::PrimitiveAssemblerDesc::Mesh { .. } => {
-                return Err(pso::CreationError::UnsupportedPipeline)
-            }
-        };
+        self.pipeline_device().create_graphics_pipeline(desc)
+    }
 
-        let ps = build_shader(ShaderStage::Fragment, desc.fragment.as_ref())?;
-        let ps = if let Some(blob) = ps {
-            Some(self.create_pixel_shader(blob)?)
-        } else {
-            None
+    unsafe fn create_graphics_pipeline_async<'a>(
+        &self,
+        desc: &pso::GraphicsPipelineDesc<'a, Backend>,
+        _cache: Option<&()>,
+    ) -> pso::PipelineTask<GraphicsPipeline> {
+        let device = self.pipeline_device();
+        let detached = match DetachedGraphicsPipelineDesc::new(desc) {
+            Some(detached) => detached,
+            None => return pso::PipelineTask::ready(device.create_graphics_pipeline(desc)),
         };
 
-        let rasterizer_state =
-            self.create_rasterizer_state(&desc.rasterizer, &desc.multisampling)?;
-        let blend_state = self.create_blend_state(&desc.blender, &desc.multisampling)?;
-        let depth_stencil_state = Some(self.create_depth_stencil_state(&desc.depth_stencil)?);
-
-        match desc.label {
-            Some(label) if verify_debug_ascii(label) => {
-                let mut name = label.to_string();
-
-                set_debug_name_with_suffix(&blend_state, &mut name, " -- Blend State");
-                set_debug_name_with_suffix(&rasterizer_state, &mut name, " -- Rasterizer State");
-                set_debug_name_with_suffix(&layout.raw, &mut name, " -- Input Layout");
-                if let Some(ref dss) = depth_stencil_state {
-                    set_debug_name_with_suffix(&dss.raw, &mut name, " -- Depth Stencil State");
-                }
-            }
-            _ => {}
-        }
-
-        Ok(GraphicsPipeline {
-            vs,
-            gs,
-            ds,
-            hs,
-            ps,
-            topology: layout.topology,
-            input_layout: layout.raw,
-            rasterizer_state,
-            blend_state,
-            depth_stencil_state,
-            baked_states: desc.baked_states.clone(),
-            required_bindings: layout.required_bindings,
-            max_vertex_bindings: layout.max_vertex_bindings,
-            strides: layout.vertex_strides,
-        })
+        let (sender, task) = pso::PipelineTask::new();
+        self.pipeline_workers.execute(move || {
+            let result = detached.with(|desc| device.create_graphics_pipeline(desc));
+            let _ = sender.send(result);
+        });
+        task
     }
 
     unsafe fn create_compute_pipeline<'a>(
@@ -1072,17 +1168,26 @@ impl device::Device<Backend> for Device {
         desc: &pso::ComputePipelineDesc<'a, Backend>,
         _cache: Option<&()>,
     ) -> Result<ComputePipeline, pso::CreationError> {
-        let features = &self.features;
-        let build_shader =
-            |stage: ShaderStage, source: Option<&pso::EntryPoint<'a, Backend>>| match source {
-                Some(src) => Self::extract_entry_point(stage, src, desc.layout, features),
-                None => Ok(None),
-            };
+        self.pipeline_device().create_compute_pipeline(desc)
+    }
 
-        let cs = build_shader(ShaderStage::Compute, Some(&desc.shader))?.unwrap();
-        let cs = self.create_compute_shader(cs)?;
+    unsafe fn create_compute_pipeline_async<'a>(
+        &self,
+        desc: &pso::ComputePipelineDesc<'a, Backend>,
+        _cache: Option<&()>,
+    ) -> pso::PipelineTask<ComputePipeline> {
+        let device = self.pipeline_device();
+        let detached = match DetachedComputePipelineDesc::new(desc) {
+            Some(detached) => detached,
+            None => return pso::PipelineTask::ready(device.create_compute_pipeline(desc)),
+        };
 
-        Ok(ComputePipeline { cs })
+        let (sender, task) = pso::PipelineTask::new();
+        self.pipeline_workers.execute(move || {
+            let result = detached.with(|desc| device.create_compute_pipeline(desc));
+            let _ = sender.send(result);
+        });
+        task
     }
 
     unsafe fn create_framebuffer<I>(
@@ -1129,7 +1234,7 @@ impl device::Device<Backend> for Device {
             bind |= d3d11::D3D11_BIND_SHADER_RESOURCE;
         }
 
-        if usage.intersects(Usage::TRANSFER_DST | Usage::STORAGE) {
+        if usage.intersects(Usage::TRANSFER_DST | Usage::STORAGE | Usage::STORAGE_TEXEL) {
             bind |= d3d11::D3D11_BIND_UNORDERED_ACCESS;
         }
 
@@ -1176,6 +1281,8 @@ impl device::Device<Backend> for Device {
                 size,
                 alignment: 4,
                 type_mask: BUFFER_TYPE_MASK,
+                prefers_dedicated: false,
+                requires_dedicated: false,
             },
         })
     }
@@ -1374,11 +1481,76 @@ impl device::Device<Backend> for Device {
 
     unsafe fn create_buffer_view(
         &self,
-        _buffer: &Buffer,
-        _format: Option<format::Format>,
-        _range: buffer::SubRange,
+        buffer: &Buffer,
+        format: Option<format::Format>,
+        sub: buffer::SubRange,
     ) -> Result<BufferView, buffer::ViewCreationError> {
-        unimplemented!()
+        let format = format.ok_or(buffer::ViewCreationError::UnsupportedFormat(None))?;
+        let dxgi_format = conv::map_format(format)
+            .ok_or(buffer::ViewCreationError::UnsupportedFormat(Some(format)))?;
+
+        let stride = format.surface_desc().bits as u64 / 8;
+        let first_element = (sub.offset / stride) as u32;
+        let num_elements = (sub.size_to(buffer.requirements.size) / stride) as u32;
+
+        let srv = if buffer.internal.usage.contains(buffer::Usage::UNIFORM_TEXEL) {
+            let mut desc = mem::zeroed::<d3d11::D3D11_SHADER_RESOURCE_VIEW_DESC>();
+            desc.Format = dxgi_format;
+            desc.ViewDimension = d3dcommon::D3D11_SRV_DIMENSION_BUFFEREX;
+            *desc.u.BufferEx_mut() = d3d11::D3D11_BUFFEREX_SRV {
+                FirstElement: first_element,
+                NumElements: num_elements,
+                Flags: 0,
+            };
+
+            let mut srv: *mut d3d11::ID3D11ShaderResourceView = ptr::null_mut();
+            let hr = self.raw.CreateShaderResourceView(
+                buffer.internal.raw as *mut _,
+                &desc,
+                &mut srv as *mut *mut _ as *mut *mut _,
+            );
+
+            if !winerror::SUCCEEDED(hr) {
+                error!("CreateShaderResourceView failed: 0x{:x}", hr);
+
+                return Err(buffer::ViewCreationError::UnsupportedFormat(Some(format)));
+            }
+
+            Some(srv)
+        } else {
+            None
+        };
+
+        let uav = if buffer.internal.usage.contains(buffer::Usage::STORAGE_TEXEL) {
+            let mut desc = mem::zeroed::<d3d11::D3D11_UNORDERED_ACCESS_VIEW_DESC>();
+            desc.Format = dxgi_format;
+            desc.ViewDimension = d3d11::D3D11_UAV_DIMENSION_BUFFER;
+            *desc.u.Buffer_mut() = d3d11::D3D11_BUFFER_UAV {
+                FirstElement: first_element,
+                NumElements: num_elements,
+                Flags: 0,
+            };
+
+            let mut uav: *mut d3d11::ID3D11UnorderedAccessView = ptr::null_mut();
+            let hr = self.raw.CreateUnorderedAccessView(
+                buffer.internal.raw as *mut _,
+                &desc,
+                &mut uav as *mut *mut _ as *mut *mut _,
+            );
+
+            if !winerror::SUCCEEDED(hr) {
+                error!("CreateUnorderedAccessView failed: 0x{:x}", hr);
+                srv.map(|srv| (&*srv).Release());
+
+                return Err(buffer::ViewCreationError::UnsupportedFormat(Some(format)));
+            }
+
+            Some(uav)
+        } else {
+            None
+        };
+
+        Ok(BufferView { srv, uav })
     }
 
     unsafe fn create_image(
@@ -1419,6 +1591,8 @@ impl device::Device<Backend> for Device {
                 size: size,
                 alignment: 4,
                 type_mask: 0x1, // device-local only
+                prefers_dedicated: false,
+                requires_dedicated: false,
             },
         })
     }
@@ -1895,7 +2069,7 @@ impl device::Device<Backend> for Device {
     unsafe fn create_descriptor_set_layout<'a, I, J>(
         &self,
         layout_bindings: I,
-        _immutable_samplers: J,
+        mut immutable_samplers: J,
     ) -> Result<DescriptorSetLayout, device::OutOfMemory>
     where
         I: Iterator<Item = pso::DescriptorSetLayoutBinding>,
@@ -1903,6 +2077,13 @@ impl device::Device<Backend> for Device {
     {
         let mut total = MultiStageData::<RegisterData<_>>::default();
         let mut bindings = layout_bindings.collect::<Vec<_>>();
+        if bindings
+            .iter()
+            .any(|b| b.ty == pso::DescriptorType::InlineUniformBlock)
+        {
+            error!("DX11 doesn't support INLINE_UNIFORM_BLOCK");
+            return Err(device::OutOfMemory::Host);
+        }
 
         for binding in bindings.iter() {
             let content = DescriptorContent::from(binding.ty);
@@ -1925,7 +2106,22 @@ impl device::Device<Backend> for Device {
             total.add_content_many(content, stage_flags, binding.count as _);
         }
 
+        // immutable samplers come in the order of the bindings that use them
+        let mut immutable_samplers = bindings
+            .iter()
+            .filter(|binding| binding.immutable_samplers)
+            .map(|binding| {
+                let samplers = immutable_samplers
+                    .by_ref()
+                    .take(binding.count)
+                    .map(|sampler| sampler.sampler_handle.clone())
+                    .collect();
+                (binding.binding, samplers)
+            })
+            .collect::<Vec<_>>();
+
         bindings.sort_by_key(|a| a.binding);
+        immutable_samplers.sort_by_key(|&(binding, _)| binding);
 
         let accum = total.map_register(|count| RegisterAccumulator {
             res_index: *count as ResourceIndex,
@@ -1934,6 +2130,7 @@ impl device::Device<Backend> for Device {
         Ok(DescriptorSetLayout {
             bindings: Arc::new(bindings),
             pool_mapping: accum.to_mapping(),
+            immutable_samplers: Arc::new(immutable_samplers),
         })
     }
 
@@ -2010,7 +2207,28 @@ impl device::Device<Backend> for Device {
                     u: image.uav_handle.map_or(ptr::null_mut(), |h| h as *mut _),
                     s: sampler.sampler_handle.as_raw() as *mut _,
                 },
-                pso::Descriptor::TexelBuffer(_buffer_view) => unimplemented!(),
+                pso::Descriptor::TexelBuffer(buffer_view) => RegisterData {
+                    c: ptr::null_mut(),
+                    t: buffer_view.srv.map_or(ptr::null_mut(), |p| p as *mut _),
+                    u: buffer_view.uav.map_or(ptr::null_mut(), |p| p as *mut _),
+                    s: ptr::null_mut(),
+                },
+                // Unbound views read as zeros, and the writes into them are discarded.
+                pso::Descriptor::Null => RegisterData {
+                    c: ptr::null_mut(),
+                    t: ptr::null_mut(),
+                    u: ptr::null_mut(),
+                    s: ptr::null_mut(),
+                },
+                pso::Descriptor::InlineUniformBlock(_) => {
+                    error!("DX11 doesn't support INLINE_UNIFORM_BLOCK");
+                    RegisterData {
+                        c: ptr::null_mut(),
+                        t: ptr::null_mut(),
+                        u: ptr::null_mut(),
+                        s: ptr::null_mut(),
+                    }
+                }
             };
 
             let content = DescriptorContent::from(binding.ty);
@@ -2044,7 +2262,8 @@ impl device::Device<Backend> for Device {
                 let offsets = mapping.map_other(|map| map.u);
                 op.set.assign_stages(&offsets, stage_flags, handles.u);
             };
-            if content.contains(DescriptorContent::SAMPLER) {
+            // Immutable samplers are assigned when the set is allocated.
+            if content.contains(DescriptorContent::SAMPLER) && !binding.immutable_samplers {
                 let offsets = mapping.map_other(|map| map.s);
                 op.set
                     .assign_stages(&offsets, binding.stage_flags, handles.s);
@@ -2109,6 +2328,34 @@ impl device::Device<Backend> for Device {
         // persistent mapping FTW
     }
 
+    unsafe fn copy_memory_to_image<I>(
+        &self,
+        _data: &[u8],
+        _image: &Image,
+        _layout: image::Layout,
+        _regions: I,
+    ) -> Result<(), device::MapError>
+    where
+        I: Iterator<Item = hal::command::BufferImageCopy>,
+    {
+        // `Features::HOST_IMAGE_COPY` isn't reported, images are never host-accessible
+        Err(device::MapError::Access)
+    }
+
+    unsafe fn copy_image_to_memory<I>(
+        &self,
+        _image: &Image,
+        _layout: image::Layout,
+        _data: &mut [u8],
+        _regions: I,
+    ) -> Result<(), device::MapError>
+    where
+        I: Iterator<Item = hal::command::BufferImageCopy>,
+    {
+        // `Features::HOST_IMAGE_COPY` isn't reported, images are never host-accessible
+        Err(device::MapError::Access)
+    }
+
     unsafe fn flush_mapped_memory_ranges<'a, I>(&self, ranges: I) -> Result<(), device::OutOfMemory>
     where
         I: Iterator<Item = (&'a Memory, memory::Segment)>,
@@ -2294,8 +2541,9 @@ impl device::Device<Backend> for Device {
         internal.release_resources();
     }
 
-    unsafe fn destroy_buffer_view(&self, _view: BufferView) {
-        //unimplemented!()
+    unsafe fn destroy_buffer_view(&self, view: BufferView) {
+        view.srv.map(|srv| (&*srv).Release());
+        view.uav.map(|uav| (&*uav).Release());
     }
 
     unsafe fn destroy_image(&self, mut image: Image) {
@@ -2308,6 +2556,33 @@ impl device::Device<Backend> for Device {
 
     unsafe fn destroy_sampler(&self, _sampler: Sampler) {}
 
+    unsafe fn create_sampler_ycbcr_conversion(
+        &self,
+        desc: &image::SamplerYcbcrConversionDesc,
+    ) -> Result<(), image::CreationError> {
+        Err(image::CreationError::Format(desc.format))
+    }
+
+    unsafe fn destroy_sampler_ycbcr_conversion(&self, _conversion: ()) {}
+
+    unsafe fn create_ycbcr_sampler(
+        &self,
+        _desc: &image::SamplerDesc,
+        _conversion: &(),
+    ) -> Result<Sampler, device::AllocationError> {
+        unimplemented!()
+    }
+
+    unsafe fn create_ycbcr_image_view(
+        &self,
+        _image: &Image,
+        _view_kind: image::ViewKind,
+        _range: image::SubresourceRange,
+        _conversion: &(),
+    ) -> Result<ImageView, image::ViewCreationError> {
+        unimplemented!()
+    }
+
     unsafe fn destroy_descriptor_pool(&self, _pool: DescriptorPool) {
         //unimplemented!()
     }
@@ -2328,9 +2603,21 @@ impl device::Device<Backend> for Device {
         //unimplemented!()
     }
 
-    fn wait_idle(&self) -> Result<(), device::OutOfMemory> {
-        Ok(())
-        // unimplemented!()
+    fn wait_idle(&self) -> Result<(), device::WaitError> {
+        match device_lost_cause(&self.raw) {
+            Some(_) => Err(device::DeviceLost.into()),
+            None => Ok(()),
+        }
+    }
+
+    fn get_device_lost_reason(&self) -> Option<device::DeviceLostReason> {
+        device_lost_cause(&self.raw).map(|cause| device::DeviceLostReason {
+            cause,
+            description: String::new(),
+            fault_address: None,
+            last_checkpoint: None,
+            pending_checkpoints: Vec::new(),
+        })
     }
 
     unsafe fn set_image_name(&self, image: &mut Image, name: &str) {
@@ -120,6 +120,7 @@ pub struct Internal {
     partial_clear_depth_stencil_state: ComPtr<d3d11::ID3D11DepthStencilState>,
     partial_clear_depth_state: ComPtr<d3d11::ID3D11DepthStencilState>,
     partial_clear_stencil_state: ComPtr<d3d11::ID3D11DepthStencilState>,
+    partial_clear_rasterizer_state: ComPtr<d3d11::ID3D11RasterizerState>,
 
     // blitting
     vs_blit_2d: ComPtr<d3d11::ID3D11VertexShader>,
@@ -316,6 +317,34 @@ impl Internal {
             }
         };
 
+        // The clear rectangles are covered through the viewport, so neither
+        // culling nor the scissor test of the bound pipeline may apply.
+        let rasterizer_state = {
+            let desc = d3d11::D3D11_RASTERIZER_DESC {
+                FillMode: d3d11::D3D11_FILL_SOLID,
+                CullMode: d3d11::D3D11_CULL_NONE,
+                FrontCounterClockwise: FALSE,
+                DepthBias: 0,
+                DepthBiasClamp: 0.0,
+                SlopeScaledDepthBias: 0.0,
+                DepthClipEnable: TRUE,
+                ScissorEnable: FALSE,
+                MultisampleEnable: FALSE,
+                AntialiasedLineEnable: FALSE,
+            };
+
+            let mut rasterizer_state = ptr::null_mut();
+            let hr = unsafe {
+                device.CreateRasterizerState(
+                    &desc,
+                    &mut rasterizer_state as *mut *mut _ as *mut *mut _,
+                )
+            };
+            assert_eq!(winerror::S_OK, hr);
+
+            unsafe { ComPtr::from_raw(rasterizer_state) }
+        };
+
         let (sampler_nearest, sampler_linear) = {
             let mut desc = d3d11::D3D11_SAMPLER_DESC {
                 Filter: d3d11::D3D11_FILTER_MIN_MAG_MIP_POINT,
@@ -621,6 +650,7 @@ impl Internal {
             partial_clear_depth_stencil_state: depth_stencil_state,
             partial_clear_depth_state: depth_state,
             partial_clear_stencil_state: stencil_state,
+            partial_clear_rasterizer_state: rasterizer_state,
 
             vs_blit_2d: compile_vs(device, blit_shaders, "vs_blit_2d"),
 
@@ -1153,6 +1183,7 @@ impl Internal {
         clears: T,
         rects: U,
         cache: &RenderPassCache,
+        push_constant_buffer: &ComPtr<d3d11::ID3D11Buffer>,
     ) where
         T: Iterator<Item = command::AttachmentClear>,
         U: Iterator<Item = pso::ClearRect>,
@@ -1167,7 +1198,14 @@ impl Internal {
             context.IASetPrimitiveTopology(d3dcommon::D3D11_PRIMITIVE_TOPOLOGY_TRIANGLELIST);
             context.IASetInputLayout(ptr::null_mut());
             context.VSSetShader(self.vs_partial_clear.as_raw(), ptr::null_mut(), 0);
-            context.PSSetConstantBuffers(0, 1, [const_buf.buffer.as_raw()].as_ptr());
+            context.RSSetState(self.partial_clear_rasterizer_state.as_raw());
+            context.OMSetBlendState(ptr::null_mut(), &[0f32; 4], !0);
+            // Borrow the push constant slot to keep the user constant buffers bound.
+            context.PSSetConstantBuffers(
+                d3d11::D3D11_COMMONSHADER_CONSTANT_BUFFER_API_SLOT_COUNT - 1,
+                1,
+                [const_buf.buffer.as_raw()].as_ptr(),
+            );
         }
 
         let subpass = &cache.render_pass.subpasses[cache.current_subpass as usize];
@@ -1277,7 +1315,7 @@ impl Internal {
                                     0,
                                 );
                             }
-                            (None, None) => {}
+                            (None, None) => continue,
                         }
 
                         context.OMSetRenderTargets(
@@ -1285,11 +1323,6 @@ impl Internal {
                             ptr::null_mut(),
                             attachment.dsv_handle.unwrap(),
                         );
-                        context.PSSetShader(
-                            self.ps_partial_clear_depth.as_raw(),
-                            ptr::null_mut(),
-                            0,
-                        );
                     }
 
                     for clear_rect in &clear_rects {
@@ -1306,5 +1339,13 @@ impl Internal {
                 }
             }
         }
+
+        unsafe {
+            context.PSSetConstantBuffers(
+                d3d11::D3D11_COMMONSHADER_CONSTANT_BUFFER_API_SLOT_COUNT - 1,
+                1,
+                [push_constant_buffer.as_raw()].as_ptr(),
+            );
+        }
     }
 }
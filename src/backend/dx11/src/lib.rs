@@ -54,7 +54,7 @@ use std::{
     fmt, mem,
     ops::Range,
     os::raw::c_void,
-    ptr,
+    ptr, slice,
     sync::{Arc, Weak},
 };
 
@@ -157,7 +157,11 @@ fn get_features(
         | hal::Features::SAMPLER_MIRROR_CLAMP_EDGE
         | hal::Features::SAMPLER_ANISOTROPY
         | hal::Features::DEPTH_CLAMP
-        | hal::Features::NDC_Y_UP;
+        | hal::Features::DEPTH_CLIP_ENABLE
+        | hal::Features::NULL_DESCRIPTOR
+        | hal::Features::NDC_Y_UP
+        | hal::Features::DYNAMIC_RENDERING
+        | hal::Features::PRESENT_MODE_SWITCH;
 
     features.set(
         hal::Features::TEXTURE_DESCRIPTOR_ARRAY
@@ -295,12 +299,13 @@ fn get_limits(feature_level: d3dcommon::D3D_FEATURE_LEVEL) -> hal::Limits {
             as _,
         max_vertex_input_bindings: d3d11::D3D11_IA_VERTEX_INPUT_RESOURCE_SLOT_COUNT as _, // TODO: verify same as attributes
         max_vertex_output_components: d3d11::D3D11_VS_OUTPUT_REGISTER_COUNT as _,         // TODO
-        min_texel_buffer_offset_alignment: 1,                                             // TODO
+        min_texel_buffer_offset_alignment: 16,                                            // TODO
         min_uniform_buffer_offset_alignment: 16,
         min_storage_buffer_offset_alignment: 16, // TODO
         framebuffer_color_sample_counts: max_samples,
         framebuffer_depth_sample_counts: max_samples,
         framebuffer_stencil_sample_counts: max_samples,
+        storage_image_sample_counts: 0x1,
         max_color_attachments,
         buffer_image_granularity: 1,
         non_coherent_atom_size: 1, // TODO
@@ -395,6 +400,12 @@ fn get_format_properties(
                 props.buffer_features |= format::BufferFeature::UNIFORM_TEXEL;
             }
         }
+        if conv::is_emulated_format(format) {
+            // The copies would expose the layout of the wider backing format.
+            props.optimal_tiling -=
+                format::ImageFeature::TRANSFER_SRC | format::ImageFeature::TRANSFER_DST;
+            props.linear_tiling = format::ImageFeature::empty();
+        }
 
         let hr = unsafe {
             device.CheckFeatureSupport(
@@ -756,6 +767,7 @@ impl adapter::PhysicalDevice<Backend> for PhysicalDevice {
 
                 // TODO: multiple queues?
                 let queue = Queue {
+                    device: device.raw.clone(),
                     context: device.context.clone(),
                 };
                 group.add_queue(queue);
@@ -958,6 +970,10 @@ impl window::Surface<Backend> for Surface {
             },
             max_image_layers: 1,
             usage: image::Usage::COLOR_ATTACHMENT,
+            current_transform: window::SurfaceTransform::IDENTITY,
+            transforms: window::SurfaceTransform::IDENTITY,
+            color_spaces: window::ColorSpace::SRGB_NONLINEAR,
+            extended_dynamic_range: None,
         }
     }
 
@@ -1100,6 +1116,8 @@ impl window::PresentationSurface<Backend> for Surface {
                     size: 0,
                     alignment: 1,
                     type_mask: 0,
+                    prefers_dedicated: false,
+                    requires_dedicated: false,
                 },
             }),
             is_init: true,
@@ -1107,6 +1125,22 @@ impl window::PresentationSurface<Backend> for Surface {
         Ok(())
     }
 
+    unsafe fn set_present_mode(
+        &mut self,
+        mode: window::PresentMode,
+    ) -> Result<(), window::PresentModeIncompatible> {
+        // The sync interval is given with each present
+        match self.presentation {
+            Some(ref mut presentation)
+                if mode == window::PresentMode::IMMEDIATE || mode == window::PresentMode::FIFO =>
+            {
+                presentation.mode = mode;
+                Ok(())
+            }
+            _ => Err(window::PresentModeIncompatible),
+        }
+    }
+
     unsafe fn unconfigure_swapchain(&mut self, _device: &device::Device) {
         self.presentation = None;
     }
@@ -1150,6 +1184,7 @@ impl queue::QueueFamily for QueueFamily {
 
 #[derive(Clone)]
 pub struct Queue {
+    device: ComPtr<d3d11::ID3D11Device>,
     context: ComPtr<d3d11::ID3D11DeviceContext>,
 }
 
@@ -1169,7 +1204,8 @@ impl queue::Queue<Backend> for Queue {
         _wait_semaphores: Iw,
         _signal_semaphores: Is,
         fence: Option<&mut Fence>,
-    ) where
+    ) -> Result<(), queue::SubmitError>
+    where
         Ic: Iterator<Item = &'a CommandBuffer>,
     {
         let _scope = debug_scope!(&self.context, "Submit(fence={:?})", fence);
@@ -1197,10 +1233,16 @@ impl queue::Queue<Backend> for Queue {
             }
         }
 
+        // The fence must not be signaled if the work was dropped on the floor.
+        if device::device_lost_cause(&self.device).is_some() {
+            return Err(hal::device::DeviceLost.into());
+        }
+
         if let Some(fence) = fence {
             *fence.mutex.lock() = true;
             fence.condvar.notify_all();
         }
+        Ok(())
     }
 
     unsafe fn present(
@@ -1222,9 +1264,11 @@ impl queue::Queue<Backend> for Queue {
         Ok(None)
     }
 
-    fn wait_idle(&mut self) -> Result<(), hal::device::OutOfMemory> {
-        // unimplemented!()
-        Ok(())
+    fn wait_idle(&mut self) -> Result<(), hal::device::WaitError> {
+        match device::device_lost_cause(&self.device) {
+            Some(_) => Err(hal::device::DeviceLost.into()),
+            None => Ok(()),
+        }
     }
 
     fn timestamp_period(&self) -> f32 {
@@ -1372,6 +1416,38 @@ bitflags! {
         const VIEWPORTS = (1 << 8);
         const BLEND_STATE = (1 << 9);
         const DEPTH_STENCIL_STATE = (1 << 10);
+        const INDEX_BUFFER = (1 << 11);
+        const GRAPHICS_SHADER_RESOURCES = (1 << 12);
+    }
+}
+
+const MAX_SHADER_RESOURCES: usize = d3d11::D3D11_COMMONSHADER_INPUT_RESOURCE_SLOT_COUNT as _;
+
+/// Shader resource views bound to a shader stage.
+struct ShaderResources {
+    views: [*mut d3d11::ID3D11ShaderResourceView; MAX_SHADER_RESOURCES],
+    len: u32,
+}
+
+impl ShaderResources {
+    fn new() -> Self {
+        ShaderResources {
+            views: [ptr::null_mut(); MAX_SHADER_RESOURCES],
+            len: 0,
+        }
+    }
+
+    fn set(&mut self, start: u32, views: &[*mut d3d11::ID3D11ShaderResourceView]) {
+        let start = start as usize;
+        self.views[start..start + views.len()].copy_from_slice(views);
+        self.len = self.len.max((start + views.len()) as u32);
+    }
+
+    fn clear(&mut self) {
+        for view in &mut self.views[..self.len as usize] {
+            *view = ptr::null_mut();
+        }
+        self.len = 0;
     }
 }
 
@@ -1405,6 +1481,21 @@ pub struct CommandBufferState {
     stencil_read_mask: Option<pso::StencilValue>,
     stencil_write_mask: Option<pso::StencilValue>,
     current_blend: Option<*mut d3d11::ID3D11BlendState>,
+    index_buffer: Option<(*mut d3d11::ID3D11Buffer, dxgiformat::DXGI_FORMAT, u32)>,
+
+    // D3D11 unbinds the shader resources and the vertex and index buffers that get
+    // bound as an output elsewhere, so the inputs are kept to be bound again after
+    // a dispatch has written into them.
+    vs_srvs: ShaderResources,
+    ps_srvs: ShaderResources,
+
+    // Compute state is bound at every dispatch, since the internal operations
+    // override it, and the UAVs are unbound right after to free up the resources
+    // written by the dispatch for the following commands.
+    compute_shader: Option<ComPtr<d3d11::ID3D11ComputeShader>>,
+    cs_srvs: ShaderResources,
+    cs_uav_len: u32,
+    cs_uavs: [*mut d3d11::ID3D11UnorderedAccessView; d3d11::D3D11_PS_CS_UAV_REGISTER_COUNT as _],
 }
 
 impl fmt::Debug for CommandBufferState {
@@ -1437,6 +1528,13 @@ impl CommandBufferState {
             stencil_read_mask: None,
             stencil_write_mask: None,
             current_blend: None,
+            index_buffer: None,
+            vs_srvs: ShaderResources::new(),
+            ps_srvs: ShaderResources::new(),
+            compute_shader: None,
+            cs_srvs: ShaderResources::new(),
+            cs_uav_len: 0,
+            cs_uavs: [ptr::null_mut(); d3d11::D3D11_PS_CS_UAV_REGISTER_COUNT as _],
         }
     }
 
@@ -1459,6 +1557,15 @@ impl CommandBufferState {
         self.stencil_read_mask = None;
         self.stencil_write_mask = None;
         self.current_blend = None;
+        self.index_buffer = None;
+        self.vs_srvs.clear();
+        self.ps_srvs.clear();
+        self.compute_shader = None;
+        self.cs_srvs.clear();
+        for uav in &mut self.cs_uavs[..self.cs_uav_len as usize] {
+            *uav = ptr::null_mut();
+        }
+        self.cs_uav_len = 0;
     }
 
     pub fn set_vertex_buffer(
@@ -1504,6 +1611,95 @@ impl CommandBufferState {
         }
     }
 
+    pub fn set_index_buffer(
+        &mut self,
+        buffer: *mut d3d11::ID3D11Buffer,
+        format: dxgiformat::DXGI_FORMAT,
+        offset: u32,
+    ) {
+        self.index_buffer = Some((buffer, format, offset));
+
+        self.dirty_flag.insert(DirtyStateFlag::INDEX_BUFFER);
+    }
+
+    pub fn bind_index_buffer(&mut self, context: &ComPtr<d3d11::ID3D11DeviceContext>) {
+        if !self.dirty_flag.contains(DirtyStateFlag::INDEX_BUFFER) {
+            return;
+        }
+
+        if let Some((buffer, format, offset)) = self.index_buffer {
+            unsafe {
+                context.IASetIndexBuffer(buffer, format, offset);
+            }
+        }
+
+        self.dirty_flag.remove(DirtyStateFlag::INDEX_BUFFER);
+    }
+
+    pub fn bind_graphics_shader_resources(&mut self, context: &ComPtr<d3d11::ID3D11DeviceContext>) {
+        if !self
+            .dirty_flag
+            .contains(DirtyStateFlag::GRAPHICS_SHADER_RESOURCES)
+        {
+            return;
+        }
+
+        unsafe {
+            if self.vs_srvs.len > 0 {
+                context.VSSetShaderResources(0, self.vs_srvs.len, self.vs_srvs.views.as_ptr());
+            }
+            if self.ps_srvs.len > 0 {
+                context.PSSetShaderResources(0, self.ps_srvs.len, self.ps_srvs.views.as_ptr());
+            }
+        }
+
+        self.dirty_flag
+            .remove(DirtyStateFlag::GRAPHICS_SHADER_RESOURCES);
+    }
+
+    pub fn set_compute_uavs(&mut self, start: u32, uavs: &[*mut d3d11::ID3D11UnorderedAccessView]) {
+        let start = start as usize;
+        self.cs_uavs[start..start + uavs.len()].copy_from_slice(uavs);
+        self.cs_uav_len = self.cs_uav_len.max((start + uavs.len()) as u32);
+    }
+
+    pub fn bind_compute_state(&mut self, context: &ComPtr<d3d11::ID3D11DeviceContext>) {
+        unsafe {
+            if let Some(ref cs) = self.compute_shader {
+                context.CSSetShader(cs.as_raw(), ptr::null_mut(), 0);
+            }
+            if self.cs_srvs.len > 0 {
+                context.CSSetShaderResources(0, self.cs_srvs.len, self.cs_srvs.views.as_ptr());
+            }
+            if self.cs_uav_len > 0 {
+                context.CSSetUnorderedAccessViews(
+                    0,
+                    self.cs_uav_len,
+                    self.cs_uavs.as_ptr(),
+                    ptr::null_mut(),
+                );
+            }
+        }
+    }
+
+    pub fn unbind_compute_uavs(&mut self, context: &ComPtr<d3d11::ID3D11DeviceContext>) {
+        if self.cs_uav_len == 0 {
+            return;
+        }
+
+        let nulls = [ptr::null_mut(); d3d11::D3D11_PS_CS_UAV_REGISTER_COUNT as usize];
+        unsafe {
+            context.CSSetUnorderedAccessViews(0, self.cs_uav_len, nulls.as_ptr(), ptr::null_mut());
+        }
+
+        // The graphics inputs written by the dispatch have been unbound.
+        self.dirty_flag.insert(
+            DirtyStateFlag::VERTEX_BUFFERS
+                | DirtyStateFlag::INDEX_BUFFER
+                | DirtyStateFlag::GRAPHICS_SHADER_RESOURCES,
+        );
+    }
+
     pub fn set_viewports(&mut self, viewports: &[d3d11::D3D11_VIEWPORT]) {
         self.viewports.clear();
         self.viewports.extend(viewports);
@@ -1779,6 +1975,8 @@ impl CommandBufferState {
         self.bind_render_targets(context);
         self.bind_graphics_pipeline(context);
         self.bind_vertex_buffers(context);
+        self.bind_index_buffer(context);
+        self.bind_graphics_shader_resources(context);
         self.bind_viewports(context);
     }
 }
@@ -2066,6 +2264,38 @@ impl CommandBuffer {
         self.cache.clear();
         self.debug_scopes.clear();
     }
+
+    // Clear a subresource of an image that can't be rendered to, by uploading the texels.
+    unsafe fn clear_image_by_update(
+        &mut self,
+        image: &Image,
+        level: image::Level,
+        layer: image::Layer,
+        color: command::ClearColor,
+    ) {
+        let texel = match image.format.pack_ufloat_color(color.float32) {
+            Some(texel) => texel,
+            None => {
+                warn!(
+                    "Unable to clear {:?} image, as it isn't renderable",
+                    image.format
+                );
+                return;
+            }
+        };
+        let extent = image.kind.extent().at_level(level);
+        let row_pitch = extent.width * mem::size_of::<u32>() as u32;
+        let data = vec![texel; (extent.width * extent.height * extent.depth) as usize];
+
+        self.context.UpdateSubresource(
+            image.internal.raw,
+            image.calc_subresource(level as _, layer as _),
+            ptr::null(),
+            data.as_ptr() as *const _,
+            row_pitch,
+            row_pitch * extent.height,
+        );
+    }
 }
 
 impl command::CommandBuffer<Backend> for CommandBuffer {
@@ -2110,8 +2340,15 @@ impl command::CommandBuffer<Backend> for CommandBuffer {
         self.list.replace(Some(ComPtr::from_raw(list)));
     }
 
-    unsafe fn reset(&mut self, _release_resources: bool) {
+    unsafe fn reset(&mut self, release_resources: bool) {
         self.reset();
+        // the recorded list can't be executed after a reset
+        self.list.replace(None);
+        if release_resources {
+            self.flush_coherent_memory = Vec::new();
+            self.invalidate_coherent_memory = Vec::new();
+            self.debug_scopes = Vec::new();
+        }
     }
 
     unsafe fn begin_render_pass<'a, T>(
@@ -2185,12 +2422,47 @@ impl command::CommandBuffer<Backend> for CommandBuffer {
             current_render_pass.resolve_msaa(&self.context);
         }
 
-        self.context
-            .OMSetRenderTargets(8, [ptr::null_mut(); 8].as_ptr(), ptr::null_mut());
+        // Unbind the attachments and the pixel shader UAVs, so that they can be
+        // read by the commands following the render pass.
+        let nulls = [ptr::null_mut(); d3d11::D3D11_PS_CS_UAV_REGISTER_COUNT as usize];
+        self.context.OMSetRenderTargetsAndUnorderedAccessViews(
+            0,
+            ptr::null(),
+            ptr::null_mut(),
+            0,
+            d3d11::D3D11_PS_CS_UAV_REGISTER_COUNT,
+            nulls.as_ptr(),
+            ptr::null(),
+        );
+        self.cache.set_render_targets(&[], None, None);
 
         self.render_pass_cache = None;
     }
 
+    unsafe fn begin_rendering(&mut self, info: &command::RenderingInfo<Backend>) {
+        let render_pass = info.with_subpass(|attachments, subpass| RenderPass {
+            attachments: attachments.to_vec(),
+            subpasses: vec![SubpassDesc::from(&subpass)],
+        });
+        let framebuffer = Framebuffer {
+            layers: info.layers,
+        };
+        self.begin_render_pass(
+            &render_pass,
+            &framebuffer,
+            info.render_area,
+            info.attachments().map(|at| command::RenderAttachmentInfo {
+                image_view: at.image_view,
+                clear_value: at.clear_value,
+            }),
+            command::SubpassContents::Inline,
+        );
+    }
+
+    unsafe fn end_rendering(&mut self) {
+        self.end_render_pass();
+    }
+
     unsafe fn pipeline_barrier<'a, T>(
         &mut self,
         _stages: Range<pso::PipelineStage>,
@@ -2203,6 +2475,19 @@ impl command::CommandBuffer<Backend> for CommandBuffer {
         // unimplemented!()
     }
 
+    unsafe fn begin_barrier<'a, T>(&mut self, _: &(), _: Range<pso::PipelineStage>, _: T)
+    where
+        T: Iterator<Item = memory::Barrier<'a, Backend>>,
+    {
+        // Synchronization is implicit, same as for `pipeline_barrier`
+    }
+
+    unsafe fn end_barrier<'a, T>(&mut self, _: &(), _: Range<pso::PipelineStage>, _: T)
+    where
+        T: Iterator<Item = memory::Barrier<'a, Backend>>,
+    {
+    }
+
     unsafe fn clear_image<T>(
         &mut self,
         image: &Image,
@@ -2230,10 +2515,13 @@ impl command::CommandBuffer<Backend> for CommandBuffer {
                     let level = range.level_start + rel_level;
                     let layer = range.layer_start + rel_layer;
                     if range.aspects.contains(format::Aspects::COLOR) {
-                        self.context.ClearRenderTargetView(
-                            image.get_rtv(level, layer).unwrap().as_raw(),
-                            &value.color.float32,
-                        );
+                        match image.get_rtv(level, layer) {
+                            Some(rtv) => {
+                                self.context
+                                    .ClearRenderTargetView(rtv.as_raw(), &value.color.float32);
+                            }
+                            None => self.clear_image_by_update(image, level, layer, value.color),
+                        }
                     } else {
                         self.context.ClearDepthStencilView(
                             image.get_dsv(level, layer).unwrap().as_raw(),
@@ -2260,8 +2548,13 @@ impl command::CommandBuffer<Backend> for CommandBuffer {
                     | DirtyStateFlag::VIEWPORTS
                     | DirtyStateFlag::RENDER_TARGETS_AND_UAVS,
             );
-            self.internal
-                .clear_attachments(&self.context, clears, rects, pass);
+            self.internal.clear_attachments(
+                &self.context,
+                clears,
+                rects,
+                pass,
+                &self.push_constant_buffer,
+            );
             self.cache.bind(&self.context);
         } else {
             panic!("`clear_attachments` can only be called inside a renderpass")
@@ -2303,11 +2596,12 @@ impl command::CommandBuffer<Backend> for CommandBuffer {
     }
 
     unsafe fn bind_index_buffer(&mut self, buffer: &Buffer, sub: buffer::SubRange, ty: IndexType) {
-        self.context.IASetIndexBuffer(
+        self.cache.set_index_buffer(
             buffer.internal.raw,
             conv::map_index_type(ty),
             sub.offset as u32,
         );
+        self.cache.bind_index_buffer(&self.context);
     }
 
     unsafe fn bind_vertex_buffers<'a, T>(&mut self, first_binding: pso::BufferIndex, buffers: T)
@@ -2383,6 +2677,22 @@ impl command::CommandBuffer<Backend> for CommandBuffer {
         // unimplemented!()
     }
 
+    unsafe fn set_shading_rate(
+        &mut self,
+        _rate: pso::ShadingRate,
+        _combiners: [pso::ShadingRateCombiner; 2],
+    ) {
+        panic!("DX11 doesn't support SHADING_RATE")
+    }
+
+    unsafe fn set_sample_locations(
+        &mut self,
+        _samples: image::NumSamples,
+        _locations: &[pso::SampleLocation],
+    ) {
+        panic!("DX11 doesn't support SAMPLE_LOCATIONS")
+    }
+
     unsafe fn bind_graphics_pipeline(&mut self, pipeline: &GraphicsPipeline) {
         self.cache.set_graphics_pipeline(pipeline.clone());
         self.cache.bind(&self.context);
@@ -2400,15 +2710,6 @@ impl command::CommandBuffer<Backend> for CommandBuffer {
     {
         let _scope = debug_scope!(&self.context, "BindGraphicsDescriptorSets");
 
-        // TODO: find a better solution to invalidating old bindings..
-        let nulls = [ptr::null_mut(); d3d11::D3D11_PS_CS_UAV_REGISTER_COUNT as usize];
-        self.context.CSSetUnorderedAccessViews(
-            0,
-            d3d11::D3D11_PS_CS_UAV_REGISTER_COUNT,
-            nulls.as_ptr(),
-            ptr::null_mut(),
-        );
-
         let mut offset_iter = offsets;
 
         for (set, info) in sets.zip(&layout.sets[first_set..]) {
@@ -2490,11 +2791,16 @@ impl command::CommandBuffer<Backend> for CommandBuffer {
                 }
             }
             if let Some(rd) = info.registers.vs.t.as_some() {
+                let views = slice::from_raw_parts(
+                    set.handles.offset(rd.pool_offset as isize) as *const *mut _,
+                    rd.count as usize,
+                );
                 self.context.VSSetShaderResources(
                     rd.res_index as u32,
                     rd.count as u32,
-                    set.handles.offset(rd.pool_offset as isize) as *const *mut _,
+                    views.as_ptr(),
                 );
+                self.cache.vs_srvs.set(rd.res_index as u32, views);
             }
             if let Some(rd) = info.registers.vs.s.as_some() {
                 self.context.VSSetSamplers(
@@ -2539,11 +2845,16 @@ impl command::CommandBuffer<Backend> for CommandBuffer {
                 }
             }
             if let Some(rd) = info.registers.ps.t.as_some() {
+                let views = slice::from_raw_parts(
+                    set.handles.offset(rd.pool_offset as isize) as *const *mut _,
+                    rd.count as usize,
+                );
                 self.context.PSSetShaderResources(
                     rd.res_index as u32,
                     rd.count as u32,
-                    set.handles.offset(rd.pool_offset as isize) as *const *mut _,
+                    views.as_ptr(),
                 );
+                self.cache.ps_srvs.set(rd.res_index as u32, views);
             }
             if let Some(rd) = info.registers.ps.s.as_some() {
                 self.context.PSSetSamplers(
@@ -2576,8 +2887,7 @@ impl command::CommandBuffer<Backend> for CommandBuffer {
     }
 
     unsafe fn bind_compute_pipeline(&mut self, pipeline: &ComputePipeline) {
-        self.context
-            .CSSetShader(pipeline.cs.as_raw(), ptr::null_mut(), 0);
+        self.cache.compute_shader = Some(pipeline.cs.clone());
     }
 
     unsafe fn bind_compute_descriptor_sets<'a, I, J>(
@@ -2592,14 +2902,6 @@ impl command::CommandBuffer<Backend> for CommandBuffer {
     {
         let _scope = debug_scope!(&self.context, "BindComputeDescriptorSets");
 
-        let nulls = [ptr::null_mut(); d3d11::D3D11_PS_CS_UAV_REGISTER_COUNT as usize];
-        self.context.CSSetUnorderedAccessViews(
-            0,
-            d3d11::D3D11_PS_CS_UAV_REGISTER_COUNT,
-            nulls.as_ptr(),
-            ptr::null_mut(),
-        );
-
         let mut offset_iter = offsets;
 
         for (set, info) in sets.zip(&layout.sets[first_set..]) {
@@ -2680,18 +2982,21 @@ impl command::CommandBuffer<Backend> for CommandBuffer {
                 }
             }
             if let Some(rd) = info.registers.cs.t.as_some() {
-                self.context.CSSetShaderResources(
+                self.cache.cs_srvs.set(
                     rd.res_index as u32,
-                    rd.count as u32,
-                    set.handles.offset(rd.pool_offset as isize) as *const *mut _,
+                    slice::from_raw_parts(
+                        set.handles.offset(rd.pool_offset as isize) as *const *mut _,
+                        rd.count as usize,
+                    ),
                 );
             }
             if let Some(rd) = info.registers.cs.u.as_some() {
-                self.context.CSSetUnorderedAccessViews(
+                self.cache.set_compute_uavs(
                     rd.res_index as u32,
-                    rd.count as u32,
-                    set.handles.offset(rd.pool_offset as isize) as *const *mut _,
-                    ptr::null_mut(),
+                    slice::from_raw_parts(
+                        set.handles.offset(rd.pool_offset as isize) as *const *mut _,
+                        rd.count as usize,
+                    ),
                 );
             }
             if let Some(rd) = info.registers.cs.s.as_some() {
@@ -2705,11 +3010,20 @@ impl command::CommandBuffer<Backend> for CommandBuffer {
     }
 
     unsafe fn dispatch(&mut self, count: WorkGroupCount) {
+        if count.contains(&0) {
+            debug!("Skipping an empty dispatch");
+            return;
+        }
+        self.cache.bind_compute_state(&self.context);
         self.context.Dispatch(count[0], count[1], count[2]);
+        self.cache.unbind_compute_uavs(&self.context);
     }
 
-    unsafe fn dispatch_indirect(&mut self, _buffer: &Buffer, _offset: buffer::Offset) {
-        unimplemented!()
+    unsafe fn dispatch_indirect(&mut self, buffer: &Buffer, offset: buffer::Offset) {
+        self.cache.bind_compute_state(&self.context);
+        self.context
+            .DispatchIndirect(buffer.internal.raw, offset as _);
+        self.cache.unbind_compute_uavs(&self.context);
     }
 
     unsafe fn fill_buffer(&mut self, _buffer: &Buffer, _sub: buffer::SubRange, _data: u32) {
@@ -2813,6 +3127,10 @@ impl command::CommandBuffer<Backend> for CommandBuffer {
     }
 
     unsafe fn draw(&mut self, vertices: Range<VertexCount>, instances: Range<InstanceCount>) {
+        if vertices.start >= vertices.end || instances.start >= instances.end {
+            debug!("Skipping an empty draw");
+            return;
+        }
         self.context.DrawInstanced(
             vertices.end - vertices.start,
             instances.end - instances.start,
@@ -2827,6 +3145,10 @@ impl command::CommandBuffer<Backend> for CommandBuffer {
         base_vertex: VertexOffset,
         instances: Range<InstanceCount>,
     ) {
+        if indices.start >= indices.end || instances.start >= instances.end {
+            debug!("Skipping an empty indexed draw");
+            return;
+        }
         self.context.DrawIndexedInstanced(
             indices.end - indices.start,
             instances.end - instances.start,
@@ -2843,6 +3165,10 @@ impl command::CommandBuffer<Backend> for CommandBuffer {
         draw_count: DrawCount,
         _stride: buffer::Stride,
     ) {
+        if draw_count == 0 {
+            debug!("Skipping an empty indirect draw");
+            return;
+        }
         assert_eq!(draw_count, 1, "DX11 doesn't support MULTI_DRAW_INDIRECT");
         self.context
             .DrawInstancedIndirect(buffer.internal.raw, offset as _);
@@ -2855,6 +3181,10 @@ impl command::CommandBuffer<Backend> for CommandBuffer {
         draw_count: DrawCount,
         _stride: buffer::Stride,
     ) {
+        if draw_count == 0 {
+            debug!("Skipping an empty indirect draw");
+            return;
+        }
         assert_eq!(draw_count, 1, "DX11 doesn't support MULTI_DRAW_INDIRECT");
         self.context
             .DrawIndexedInstancedIndirect(buffer.internal.raw, offset as _);
@@ -3014,6 +3344,10 @@ impl command::CommandBuffer<Backend> for CommandBuffer {
     unsafe fn end_debug_marker(&mut self) {
         // self.debug_scopes.pop();
     }
+
+    unsafe fn set_checkpoint(&mut self, _label: &str) {
+        // not supported
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -3353,6 +3687,17 @@ pub struct SubpassDesc {
     pub resolve_attachments: Vec<pass::AttachmentRef>,
 }
 
+impl<'a> From<&pass::SubpassDesc<'a>> for SubpassDesc {
+    fn from(desc: &pass::SubpassDesc<'a>) -> Self {
+        SubpassDesc {
+            color_attachments: desc.colors.to_vec(),
+            depth_stencil_attachment: desc.depth_stencil.cloned(),
+            input_attachments: desc.inputs.to_vec(),
+            resolve_attachments: desc.resolves.to_vec(),
+        }
+    }
+}
+
 impl SubpassDesc {
     pub(crate) fn is_using(&self, at_id: pass::AttachmentId) -> bool {
         self.color_attachments
@@ -3424,7 +3769,13 @@ unsafe impl Send for Buffer {}
 unsafe impl Sync for Buffer {}
 
 #[derive(Debug)]
-pub struct BufferView;
+pub struct BufferView {
+    srv: Option<*mut d3d11::ID3D11ShaderResourceView>,
+    uav: Option<*mut d3d11::ID3D11UnorderedAccessView>,
+}
+
+unsafe impl Send for BufferView {}
+unsafe impl Sync for BufferView {}
 
 pub struct Image {
     kind: image::Kind,
@@ -3900,6 +4251,11 @@ pub struct PipelineLayout {
     sets: Vec<DescriptorSetInfo>,
 }
 
+type ImmutableSamplers = Vec<(
+    pso::DescriptorBinding,
+    Vec<ComPtr<d3d11::ID3D11SamplerState>>,
+)>;
+
 /// The descriptor set layout contains mappings from a given binding to the offset in our
 /// descriptor pool storage and what type of descriptor it is (combined image sampler takes up two
 /// handles).
@@ -3907,8 +4263,13 @@ pub struct PipelineLayout {
 pub struct DescriptorSetLayout {
     bindings: Arc<Vec<pso::DescriptorSetLayoutBinding>>,
     pool_mapping: MultiStageData<RegisterData<RegisterPoolMapping>>,
+    /// Immutable samplers, sorted by the binding, with one sampler per array element.
+    immutable_samplers: Arc<ImmutableSamplers>,
 }
 
+unsafe impl Send for DescriptorSetLayout {}
+unsafe impl Sync for DescriptorSetLayout {}
+
 #[derive(Debug)]
 struct CoherentBufferFlushRange {
     device_buffer: *mut d3d11::ID3D11Buffer,
@@ -4042,6 +4403,10 @@ impl From<pso::DescriptorType> for DescriptorContent {
                         dynamic_offset: true,
                     },
             } => DescriptorContent::CBV | DescriptorContent::DYNAMIC,
+            Dt::Buffer {
+                ty: Bdt::Uniform,
+                format: Bdf::Texel,
+            } => DescriptorContent::SRV,
             Dt::Buffer {
                 ty: Bdt::Uniform, ..
             } => DescriptorContent::CBV,
@@ -4059,6 +4424,11 @@ impl From<pso::DescriptorType> for DescriptorContent {
                         dynamic_offset: true,
                     },
             } => DescriptorContent::UAV | DescriptorContent::DYNAMIC,
+            // Storage texel buffers are always generated as UAVs by spirv-cross.
+            Dt::Buffer {
+                ty: Bdt::Storage { .. },
+                format: Bdf::Texel,
+            } => DescriptorContent::UAV,
             Dt::Buffer {
                 ty: Bdt::Storage { read_only: true },
                 ..
@@ -4067,6 +4437,8 @@ impl From<pso::DescriptorType> for DescriptorContent {
                 ty: Bdt::Storage { read_only: false },
                 ..
             } => DescriptorContent::UAV,
+            // Set layouts with inline uniform blocks fail to be created.
+            Dt::InlineUniformBlock => DescriptorContent::empty(),
         }
     }
 }
@@ -4125,6 +4497,29 @@ impl DescriptorSet {
             self.assign(offsets.cs, value);
         }
     }
+
+    /// Fill in the immutable samplers of the layout, which the descriptor writes leave untouched.
+    unsafe fn assign_immutable_samplers(&self) {
+        let mut mapping = self
+            .layout
+            .pool_mapping
+            .map_register(|mapping| mapping.offset);
+        let mut immutable_samplers = self.layout.immutable_samplers.iter();
+
+        for binding in self.layout.bindings.iter() {
+            let content = DescriptorContent::from(binding.ty);
+            if !binding.immutable_samplers {
+                mapping.add_content_many(content, binding.stage_flags, binding.count as _);
+                continue;
+            }
+            let (_, samplers) = immutable_samplers.next().unwrap();
+            for sampler in samplers {
+                let offsets = mapping.map_other(|map| map.s);
+                self.assign_stages(&offsets, binding.stage_flags, sampler.as_raw() as *mut _);
+                mapping.add_content_many(content, binding.stage_flags, 1);
+            }
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -4165,7 +4560,7 @@ impl pso::DescriptorPool<Backend> for DescriptorPool {
                     *handle = Descriptor(ptr::null_mut());
                 }
 
-                DescriptorSet {
+                let set = DescriptorSet {
                     offset: range.start,
                     len,
                     handles: self.handles.as_mut_ptr().offset(range.start as _),
@@ -4176,8 +4571,11 @@ impl pso::DescriptorPool<Backend> for DescriptorPool {
                     layout: DescriptorSetLayout {
                         bindings: Arc::clone(&layout.bindings),
                         pool_mapping: layout.pool_mapping.clone(),
+                        immutable_samplers: Arc::clone(&layout.immutable_samplers),
                     },
-                }
+                };
+                set.assign_immutable_samplers();
+                set
             })
             .map_err(|_| pso::AllocationError::OutOfPoolMemory)
     }
@@ -4235,6 +4633,7 @@ impl hal::Backend for Backend {
 
     type ImageView = ImageView;
     type Sampler = Sampler;
+    type SamplerYcbcrConversion = ();
 
     type ComputePipeline = ComputePipeline;
     type GraphicsPipeline = GraphicsPipeline;
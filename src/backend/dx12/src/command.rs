@@ -8,22 +8,25 @@ use arrayvec::ArrayVec;
 use smallvec::SmallVec;
 use winapi::{
     ctypes,
-    shared::{dxgiformat, minwindef, winerror},
+    shared::{dxgiformat, dxgitype, minwindef, winerror},
     um::{d3d12, d3dcommon},
     Interface,
 };
 
-use std::{cmp, fmt, iter, mem, ops::Range, ptr, sync::Arc};
+use std::{cmp, fmt, iter, mem, ops::Range, ptr, slice, sync::Arc};
 
 use crate::{
-    conv, descriptors_cpu, device, internal, pool::PoolShared, resource as r, validate_line_width,
-    Backend, Device, Shared, MAX_DESCRIPTOR_SETS, MAX_VERTEX_BUFFERS,
+    conv, descriptors_cpu, device, ext, internal, pool::PoolShared, resource as r,
+    validate_line_width, Backend, Device, Shared, MAX_DESCRIPTOR_SETS, MAX_VERTEX_BUFFERS,
 };
 
 // Fixed size of the root signature.
 // Limited by D3D12.
 const ROOT_SIGNATURE_SIZE: usize = 64;
 
+// Size of the upload memory chunks backing the spilled push constants.
+const SPILL_CHUNK_SIZE: u64 = 0x10000;
+
 const NULL_VERTEX_BUFFER_VIEW: d3d12::D3D12_VERTEX_BUFFER_VIEW = d3d12::D3D12_VERTEX_BUFFER_VIEW {
     BufferLocation: 0,
     SizeInBytes: 0,
@@ -47,9 +50,46 @@ fn up_align(x: u32, alignment: u32) -> u32 {
     (x + alignment - 1) & !(alignment - 1)
 }
 
+fn beginning_access(
+    aspect_present: bool,
+    first_use: bool,
+    load: pass::AttachmentLoadOp,
+    clear: d3d12::D3D12_CLEAR_VALUE,
+) -> ext::D3D12_RENDER_PASS_BEGINNING_ACCESS {
+    let ty = match load {
+        _ if !aspect_present => ext::D3D12_RENDER_PASS_BEGINNING_ACCESS_TYPE_NO_ACCESS,
+        _ if !first_use => ext::D3D12_RENDER_PASS_BEGINNING_ACCESS_TYPE_PRESERVE,
+        pass::AttachmentLoadOp::Load => ext::D3D12_RENDER_PASS_BEGINNING_ACCESS_TYPE_PRESERVE,
+        pass::AttachmentLoadOp::Clear => ext::D3D12_RENDER_PASS_BEGINNING_ACCESS_TYPE_CLEAR,
+        pass::AttachmentLoadOp::DontCare => ext::D3D12_RENDER_PASS_BEGINNING_ACCESS_TYPE_DISCARD,
+    };
+    ext::D3D12_RENDER_PASS_BEGINNING_ACCESS {
+        Type: ty,
+        Clear: clear,
+    }
+}
+
+fn ending_access(
+    aspect_present: bool,
+    last_use: bool,
+    store: pass::AttachmentStoreOp,
+) -> ext::D3D12_RENDER_PASS_ENDING_ACCESS {
+    let ty = match store {
+        _ if !aspect_present => ext::D3D12_RENDER_PASS_ENDING_ACCESS_TYPE_NO_ACCESS,
+        _ if !last_use => ext::D3D12_RENDER_PASS_ENDING_ACCESS_TYPE_PRESERVE,
+        pass::AttachmentStoreOp::Store => ext::D3D12_RENDER_PASS_ENDING_ACCESS_TYPE_PRESERVE,
+        pass::AttachmentStoreOp::DontCare => ext::D3D12_RENDER_PASS_ENDING_ACCESS_TYPE_DISCARD,
+    };
+    ext::D3D12_RENDER_PASS_ENDING_ACCESS {
+        Type: ty,
+        Resolve: unsafe { mem::zeroed() },
+    }
+}
+
 #[derive(Clone, Debug)]
 struct AttachmentInfo {
     subpass_id: Option<pass::SubpassId>,
+    last_subpass_id: Option<pass::SubpassId>,
     view: r::ImageView,
     clear_value: Option<com::ClearValue>,
     stencil_value: Option<u32>,
@@ -61,6 +101,9 @@ pub struct RenderPassCache {
     attachments: Vec<AttachmentInfo>,
     num_layers: image::Layer,
     has_name: bool,
+    /// Flags of the native render passes, recorded for each subpass
+    /// if the command list supports `ID3D12GraphicsCommandList4`.
+    native_flags: Option<ext::D3D12_RENDER_PASS_FLAGS>,
 }
 
 impl fmt::Debug for RenderPassCache {
@@ -190,6 +233,9 @@ struct PipelineCache {
 
     temp_constants: Vec<u32>,
 
+    // Push constants of the layouts that spill them out of the root signature
+    spilled_constants: Vec<u32>,
+
     // Descriptor heap gpu handle offsets
     srv_cbv_uav_start: u64,
     sampler_start: u64,
@@ -267,6 +313,43 @@ impl PipelineCache {
         [heap_srv_cbv_uav, heap_sampler]
     }
 
+    /// Write push constants spilled out of the root signature, uploading the affected
+    /// ranges and pointing their root CBVs to the new contents.
+    unsafe fn push_spilled_constants(
+        &mut self,
+        spill: &mut SpillMemory,
+        device: native::Device,
+        layout: &r::PipelineLayout,
+        offset: usize,
+        data: &[u32],
+    ) {
+        let end = offset + data.len();
+        if self.spilled_constants.len() < end {
+            self.spilled_constants.resize(end, 0);
+        }
+        self.spilled_constants[offset..end].copy_from_slice(data);
+
+        for (constant, &root_offset) in layout
+            .shared
+            .constants
+            .iter()
+            .zip(layout.shared.parameter_offsets.iter())
+        {
+            let range = constant.range.start as usize..constant.range.end as usize;
+            if range.end <= offset || range.start >= end {
+                continue;
+            }
+            if self.spilled_constants.len() < range.end {
+                self.spilled_constants.resize(range.end, 0);
+            }
+            // The root CBV keeps the previous contents if the upload fails
+            if let Some(address) = spill.upload(device, &self.spilled_constants[range]) {
+                self.user_data
+                    .set_descriptor_cbv(root_offset as usize, address);
+            }
+        }
+    }
+
     fn flush_user_data<F, G, H>(
         &mut self,
         mut constants_update: F,
@@ -302,9 +385,13 @@ impl PipelineCache {
                             .map(|ud| match *ud {
                                 RootElement::Constant(v) => v,
                                 _ => {
-                                    warn!(
+                                    auxil::report!(
+                                        Warn,
+                                        "gfx::dx12::command",
+                                        "root-constant-mismatch",
                                         "Unset or mismatching root constant at index {:?} ({:?})",
-                                        c, ud
+                                        c,
+                                        ud
                                     );
                                     0
                                 }
@@ -334,7 +421,10 @@ impl PipelineCache {
                     descriptor_cbv_update(root_index as u32, buffer);
                 }
                 RootElement::DescriptorPlaceholder | RootElement::Undefined => {
-                    error!(
+                    auxil::report!(
+                        Error,
+                        "gfx::dx12::command",
+                        "undefined-root-user-data",
                         "Undefined user data element in the root signature at {}",
                         root_offset
                     );
@@ -346,6 +436,125 @@ impl PipelineCache {
     }
 }
 
+/// Create a persistently mapped buffer in an upload heap.
+unsafe fn create_upload_buffer(
+    device: native::Device,
+    size: u64,
+) -> Result<(native::Resource, *mut u8), winerror::HRESULT> {
+    let properties = d3d12::D3D12_HEAP_PROPERTIES {
+        Type: d3d12::D3D12_HEAP_TYPE_UPLOAD,
+        CPUPageProperty: d3d12::D3D12_CPU_PAGE_PROPERTY_UNKNOWN,
+        MemoryPoolPreference: d3d12::D3D12_MEMORY_POOL_UNKNOWN,
+        CreationNodeMask: 0,
+        VisibleNodeMask: 0,
+    };
+    let desc = d3d12::D3D12_RESOURCE_DESC {
+        Dimension: d3d12::D3D12_RESOURCE_DIMENSION_BUFFER,
+        Alignment: 0,
+        Width: size,
+        Height: 1,
+        DepthOrArraySize: 1,
+        MipLevels: 1,
+        Format: dxgiformat::DXGI_FORMAT_UNKNOWN,
+        SampleDesc: dxgitype::DXGI_SAMPLE_DESC {
+            Count: 1,
+            Quality: 0,
+        },
+        Layout: d3d12::D3D12_TEXTURE_LAYOUT_ROW_MAJOR,
+        Flags: d3d12::D3D12_RESOURCE_FLAG_NONE,
+    };
+
+    let mut resource = native::Resource::null();
+    let hr = device.CreateCommittedResource(
+        &properties,
+        d3d12::D3D12_HEAP_FLAG_NONE,
+        &desc,
+        d3d12::D3D12_RESOURCE_STATE_GENERIC_READ,
+        ptr::null(),
+        &d3d12::ID3D12Resource::uuidof(),
+        resource.mut_void(),
+    );
+    if !winerror::SUCCEEDED(hr) {
+        return Err(hr);
+    }
+    let mut mapped = ptr::null_mut();
+    let hr = resource.Map(0, &d3d12::D3D12_RANGE { Begin: 0, End: 0 }, &mut mapped);
+    if !winerror::SUCCEEDED(hr) {
+        resource.destroy();
+        return Err(hr);
+    }
+    Ok((resource, mapped as *mut u8))
+}
+
+/// Upload memory for the push constants spilled out of the root signature.
+///
+/// The chunks are sub-allocated linearly, and recycled when the command buffer is reset.
+#[derive(Default)]
+struct SpillMemory {
+    chunks: Vec<(native::Resource, *mut u8)>,
+    current: usize,
+    offset: u64,
+}
+
+impl SpillMemory {
+    /// Copy the data into the upload memory, returning its GPU address,
+    /// or `None` if a new chunk can't be allocated.
+    unsafe fn upload(
+        &mut self,
+        device: native::Device,
+        data: &[u32],
+    ) -> Option<d3d12::D3D12_GPU_VIRTUAL_ADDRESS> {
+        let size = (data.len() * mem::size_of::<u32>()) as u64;
+        if self.offset + size > SPILL_CHUNK_SIZE {
+            self.current += 1;
+            self.offset = 0;
+        }
+        if self.current == self.chunks.len() {
+            match create_upload_buffer(device, SPILL_CHUNK_SIZE) {
+                Ok(chunk) => self.chunks.push(chunk),
+                Err(hr) => {
+                    auxil::report!(
+                        Error,
+                        "gfx::dx12::command",
+                        "push-constants-spill-failed",
+                        "Unable to allocate the memory for the spilled push constants: {:x}",
+                        hr
+                    );
+                    return None;
+                }
+            }
+        }
+
+        let (resource, mapped) = self.chunks[self.current];
+        ptr::copy_nonoverlapping(
+            data.as_ptr(),
+            mapped.add(self.offset as usize) as *mut u32,
+            data.len(),
+        );
+        let address = resource.gpu_virtual_address() + self.offset;
+        let alignment = d3d12::D3D12_CONSTANT_BUFFER_DATA_PLACEMENT_ALIGNMENT as u64;
+        self.offset += (size + alignment - 1) & !(alignment - 1);
+        Some(address)
+    }
+
+    /// Recycle the first chunk, releasing the others.
+    unsafe fn reset(&mut self) {
+        if self.chunks.len() > 1 {
+            for (resource, _) in self.chunks.drain(1..) {
+                resource.destroy();
+            }
+        }
+        self.current = 0;
+        self.offset = 0;
+    }
+
+    unsafe fn destroy(&mut self) {
+        for (resource, _) in self.chunks.drain(..) {
+            resource.destroy();
+        }
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 enum BindPoint {
     Compute,
@@ -439,6 +648,8 @@ pub struct CommandBuffer {
     temporary_gpu_heaps: Vec<native::DescriptorHeap>,
     /// Resources that need to be alive till the end of the GPU execution.
     retained_resources: Vec<native::Resource>,
+    /// Upload memory for the spilled push constants.
+    spill_memory: SpillMemory,
 
     /// Temporary wide string for the marker.
     temp_marker: Vec<u16>,
@@ -492,6 +703,7 @@ impl CommandBuffer {
             rtv_pools: Vec::new(),
             temporary_gpu_heaps: Vec::new(),
             retained_resources: Vec::new(),
+            spill_memory: SpillMemory::default(),
             temp_marker: Vec::new(),
             barriers: Vec::new(),
             raw_name: Vec::new(),
@@ -499,7 +711,7 @@ impl CommandBuffer {
     }
 
     pub(crate) unsafe fn destroy(
-        self,
+        mut self,
     ) -> (
         Option<native::CommandAllocator>,
         Option<native::GraphicsCommandList>,
@@ -521,13 +733,20 @@ impl CommandBuffer {
         for resource in &self.retained_resources {
             resource.destroy();
         }
+        self.spill_memory.destroy();
         (self.allocator, list)
     }
 
     pub(crate) unsafe fn as_raw_list(&self) -> *mut d3d12::ID3D12CommandList {
         match self.phase {
             Phase::Executable => (),
-            other => error!("Submitting a command buffer in {:?} state", other),
+            other => auxil::report!(
+                Error,
+                "gfx::dx12::command",
+                "invalid-submit-state",
+                "Submitting a command buffer in {:?} state",
+                other
+            ),
         }
         self.raw.as_mut_ptr() as *mut _
     }
@@ -596,8 +815,35 @@ impl CommandBuffer {
         self.flush_barriers();
     }
 
+    /// Bind the shading rate attachment of the current subpass, if it has one,
+    /// or unbind it at the end of the subpass.
+    fn bind_shading_rate_image(&self, bind: bool) {
+        let state = self.pass_cache.as_ref().unwrap();
+        let id =
+            match state.render_pass.subpasses[self.cur_subpass as usize].shading_rate_attachment {
+                Some((id, _)) => id,
+                None => return,
+            };
+        let resource = if bind {
+            state.attachments[id].view.resource.as_mut_ptr()
+        } else {
+            ptr::null_mut()
+        };
+        unsafe {
+            let (cmd_list5, hr) = self.raw.cast::<ext::ID3D12GraphicsCommandList5>();
+            debug_assert!(winerror::SUCCEEDED(hr));
+            cmd_list5.RSSetShadingRateImage(resource);
+            cmd_list5.destroy();
+        }
+    }
+
     fn bind_targets(&mut self) {
+        self.bind_shading_rate_image(true);
         let state = self.pass_cache.as_ref().unwrap();
+        if let Some(flags) = state.native_flags {
+            self.begin_native_pass(flags);
+            return;
+        }
         let subpass = &state.render_pass.subpasses[self.cur_subpass as usize];
 
         // collect render targets
@@ -648,6 +894,10 @@ impl CommandBuffer {
 
     fn resolve_attachments(&self) {
         let state = self.pass_cache.as_ref().unwrap();
+        if state.native_flags.is_some() {
+            // resolved by the ending accesses of the native render pass
+            return;
+        }
         let subpass = &state.render_pass.subpasses[self.cur_subpass as usize];
 
         for (&(src_attachment, _), &(dst_attachment, _)) in subpass
@@ -688,6 +938,250 @@ impl CommandBuffer {
         }
     }
 
+    /// Begin a native render pass for the current subpass, with the load and store
+    /// operations of the attachments translated into beginning and ending accesses.
+    fn begin_native_pass(&self, flags: ext::D3D12_RENDER_PASS_FLAGS) {
+        let state = self.pass_cache.as_ref().unwrap();
+        let subpass = &state.render_pass.subpasses[self.cur_subpass as usize];
+        let is_first_use = |at: &AttachmentInfo| at.subpass_id == Some(self.cur_subpass);
+        let is_last_use = |at: &AttachmentInfo| at.last_subpass_id == Some(self.cur_subpass);
+
+        // The ending accesses point into the resolve parameters,
+        // which need to stay alive until the render pass begins.
+        let mut resolve_parameters = Vec::new();
+        let mut color_descs = Vec::with_capacity(subpass.color_attachments.len());
+        for (i, &(id, _)) in subpass.color_attachments.iter().enumerate() {
+            let at = &state.attachments[id];
+            let ops = state.render_pass.attachments[id].ops;
+
+            let mut clear: d3d12::D3D12_CLEAR_VALUE = unsafe { mem::zeroed() };
+            clear.Format = at.view.dxgi_format;
+            if let Some(cv) = at.clear_value {
+                unsafe {
+                    *clear.u.Color_mut() = cv.color.float32;
+                }
+            }
+
+            let mut ending = ending_access(true, is_last_use(at), ops.store);
+            match subpass.resolve_attachments.get(i) {
+                Some(&(dst_id, _)) if dst_id != pass::ATTACHMENT_UNUSED => {
+                    let src = &at.view;
+                    let dst = &state.attachments[dst_id].view;
+                    // Attachments only have a single mip level by specification.
+                    let subresources = (0..state.num_layers)
+                        .map(|l| {
+                            ext::D3D12_RENDER_PASS_ENDING_ACCESS_RESOLVE_SUBRESOURCE_PARAMETERS {
+                                SrcSubresource: src.calc_subresource(
+                                    src.mip_levels.0 as _,
+                                    (src.layers.0 + l) as _,
+                                ),
+                                DstSubresource: dst.calc_subresource(
+                                    dst.mip_levels.0 as _,
+                                    (dst.layers.0 + l) as _,
+                                ),
+                                DstX: state.target_rect.left as _,
+                                DstY: state.target_rect.top as _,
+                                SrcRect: state.target_rect,
+                            }
+                        })
+                        .collect::<Vec<_>>();
+                    let preserve_source =
+                        if ending.Type == ext::D3D12_RENDER_PASS_ENDING_ACCESS_TYPE_PRESERVE {
+                            minwindef::TRUE
+                        } else {
+                            minwindef::FALSE
+                        };
+                    ending = ext::D3D12_RENDER_PASS_ENDING_ACCESS {
+                        Type: ext::D3D12_RENDER_PASS_ENDING_ACCESS_TYPE_RESOLVE,
+                        Resolve: ext::D3D12_RENDER_PASS_ENDING_ACCESS_RESOLVE_PARAMETERS {
+                            pSrcResource: src.resource.as_mut_ptr(),
+                            pDstResource: dst.resource.as_mut_ptr(),
+                            SubresourceCount: subresources.len() as _,
+                            pSubresourceParameters: subresources.as_ptr(),
+                            Format: dst.dxgi_format,
+                            ResolveMode: ext::D3D12_RESOLVE_MODE_AVERAGE,
+                            PreserveResolveSource: preserve_source,
+                        },
+                    };
+                    resolve_parameters.push(subresources);
+                }
+                _ => {}
+            }
+
+            color_descs.push(ext::D3D12_RENDER_PASS_RENDER_TARGET_DESC {
+                cpuDescriptor: at.view.handle_rtv.raw().unwrap(),
+                BeginningAccess: beginning_access(true, is_first_use(at), ops.load, clear),
+                EndingAccess: ending,
+            });
+        }
+
+        let ds_desc = subpass.depth_stencil_attachment.map(|(id, _)| {
+            let at = &state.attachments[id];
+            let attachment = &state.render_pass.attachments[id];
+            let aspects = attachment
+                .format
+                .map_or(Aspects::empty(), |format| format.surface_desc().aspects);
+
+            let mut clear: d3d12::D3D12_CLEAR_VALUE = unsafe { mem::zeroed() };
+            clear.Format = at.view.dxgi_format;
+            unsafe {
+                *clear.u.DepthStencil_mut() = d3d12::D3D12_DEPTH_STENCIL_VALUE {
+                    Depth: at.clear_value.map_or(0.0, |cv| cv.depth_stencil.depth),
+                    Stencil: at.stencil_value.unwrap_or_default() as _,
+                };
+            }
+
+            let has_depth = aspects.contains(Aspects::DEPTH);
+            let has_stencil = aspects.contains(Aspects::STENCIL);
+            ext::D3D12_RENDER_PASS_DEPTH_STENCIL_DESC {
+                cpuDescriptor: at.view.handle_dsv.unwrap().raw,
+                DepthBeginningAccess: beginning_access(
+                    has_depth,
+                    is_first_use(at),
+                    attachment.ops.load,
+                    clear,
+                ),
+                StencilBeginningAccess: beginning_access(
+                    has_stencil,
+                    is_first_use(at),
+                    attachment.stencil_ops.load,
+                    clear,
+                ),
+                DepthEndingAccess: ending_access(has_depth, is_last_use(at), attachment.ops.store),
+                StencilEndingAccess: ending_access(
+                    has_stencil,
+                    is_last_use(at),
+                    attachment.stencil_ops.store,
+                ),
+            }
+        });
+
+        unsafe {
+            let (cmd_list4, hr) = self.raw.cast::<ext::ID3D12GraphicsCommandList4>();
+            debug_assert!(winerror::SUCCEEDED(hr));
+            cmd_list4.BeginRenderPass(
+                color_descs.len() as _,
+                color_descs.as_ptr(),
+                ds_desc
+                    .as_ref()
+                    .map_or(ptr::null(), |desc| desc as *const _),
+                flags,
+            );
+            cmd_list4.destroy();
+        }
+        drop(resolve_parameters);
+    }
+
+    /// End the native render pass of the current subpass, if any.
+    fn end_native_pass(&self) {
+        if let Some(RenderPassCache {
+            native_flags: Some(_),
+            ..
+        }) = self.pass_cache
+        {
+            unsafe {
+                let (cmd_list4, hr) = self.raw.cast::<ext::ID3D12GraphicsCommandList4>();
+                debug_assert!(winerror::SUCCEEDED(hr));
+                cmd_list4.EndRenderPass();
+                cmd_list4.destroy();
+            }
+        }
+    }
+
+    unsafe fn begin_render_pass_impl<'a, T>(
+        &mut self,
+        render_pass: &r::RenderPass,
+        framebuffer: &r::Framebuffer,
+        target_rect: pso::Rect,
+        attachment_infos: T,
+        native_flags: ext::D3D12_RENDER_PASS_FLAGS,
+    ) where
+        T: Iterator<Item = com::RenderAttachmentInfo<'a, Backend>>,
+    {
+        // Make sure that no subpass works with Present as intermediate layout.
+        // This wouldn't make much sense, and proceeding with this constraint
+        // allows the state transitions generated from subpass dependencies
+        // to ignore the layouts completely.
+        assert!(!render_pass.subpasses.iter().any(|sp| sp
+            .color_attachments
+            .iter()
+            .chain(sp.depth_stencil_attachment.iter())
+            .chain(sp.input_attachments.iter())
+            .any(|aref| aref.1 == image::Layout::Present)));
+
+        if !render_pass.raw_name.is_empty() {
+            let n = &render_pass.raw_name;
+            self.raw
+                .BeginEvent(0, n.as_ptr() as *const _, n.len() as u32 * 2);
+        }
+
+        self.barriers.clear();
+        let mut attachments = Vec::new();
+        for (i, (info, attachment)) in attachment_infos
+            .zip(render_pass.attachments.iter())
+            .enumerate()
+        {
+            let view = info.image_view.clone();
+            // for swapchain views, we consider the initial layout to always be `General`
+            let pass_start_state =
+                conv::map_image_resource_state(image::Access::empty(), attachment.layouts.start);
+            if view.is_swapchain() && pass_start_state != d3d12::D3D12_RESOURCE_STATE_COMMON {
+                let barrier = d3d12::D3D12_RESOURCE_TRANSITION_BARRIER {
+                    pResource: view.resource.as_mut_ptr(),
+                    Subresource: d3d12::D3D12_RESOURCE_BARRIER_ALL_SUBRESOURCES,
+                    StateBefore: d3d12::D3D12_RESOURCE_STATE_COMMON,
+                    StateAfter: pass_start_state,
+                };
+                self.barriers.push(Self::transition_barrier(barrier));
+            }
+
+            attachments.push(AttachmentInfo {
+                subpass_id: render_pass
+                    .subpasses
+                    .iter()
+                    .position(|sp| sp.is_using(i))
+                    .map(|i| i as pass::SubpassId),
+                last_subpass_id: render_pass
+                    .subpasses
+                    .iter()
+                    .rposition(|sp| sp.is_using(i))
+                    .map(|i| i as pass::SubpassId),
+                view,
+                clear_value: if attachment.ops.load == pass::AttachmentLoadOp::Clear {
+                    Some(info.clear_value)
+                } else {
+                    None
+                },
+                stencil_value: if attachment.stencil_ops.load == pass::AttachmentLoadOp::Clear {
+                    Some(info.clear_value.depth_stencil.stencil)
+                } else {
+                    None
+                },
+            });
+        }
+        self.flush_barriers();
+
+        let (cmd_list4, hr) = self.raw.cast::<ext::ID3D12GraphicsCommandList4>();
+        let native_flags = if winerror::SUCCEEDED(hr) {
+            cmd_list4.destroy();
+            Some(native_flags)
+        } else {
+            None
+        };
+
+        self.pass_cache = Some(RenderPassCache {
+            render_pass: render_pass.clone(),
+            target_rect: get_rect(&target_rect),
+            attachments,
+            num_layers: framebuffer.layers,
+            has_name: !render_pass.raw_name.is_empty(),
+            native_flags,
+        });
+        self.cur_subpass = 0;
+        self.insert_subpass_barriers(BarrierPoint::Pre);
+        self.bind_targets();
+    }
+
     fn clear_render_target_view(
         &self,
         rtv: d3d12::D3D12_CPU_DESCRIPTOR_HANDLE,
@@ -1096,6 +1590,96 @@ impl CommandBuffer {
         }
     }
 
+    /// Clear a color subresource range that can't be rendered to, by copying
+    /// the packed texels from an upload buffer.
+    unsafe fn clear_image_by_copy(
+        &mut self,
+        image: &r::ImageBound,
+        base_state: d3d12::D3D12_RESOURCE_STATES,
+        color: com::ClearColor,
+        sub: &image::SubresourceRange,
+    ) {
+        let format = match image.surface_type {
+            format::SurfaceType::B10_G11_R11 => format::Format::B10g11r11Ufloat,
+            format::SurfaceType::E5_B9_G9_R9 => format::Format::E5b9g9r9Ufloat,
+            _ => {
+                auxil::report!(
+                    Warn,
+                    "gfx::dx12::command",
+                    "clear-non-renderable-image",
+                    "Unable to clear a color image, as it isn't renderable"
+                );
+                return;
+            }
+        };
+        let texel = format.pack_ufloat_color(color.float32).unwrap();
+
+        // The largest level of the range, the smaller ones read a part of the same texels.
+        let extent = image.kind.extent().at_level(sub.level_start);
+        let row_pitch = (extent.width * mem::size_of::<u32>() as u32
+            + d3d12::D3D12_TEXTURE_DATA_PITCH_ALIGNMENT
+            - 1)
+            & !(d3d12::D3D12_TEXTURE_DATA_PITCH_ALIGNMENT - 1);
+        let size = row_pitch as u64 * extent.height as u64 * extent.depth as u64;
+        let (buffer, mapped) = match create_upload_buffer(self.shared.service_pipes.device, size) {
+            Ok(upload) => upload,
+            Err(hr) => {
+                auxil::report!(
+                    Error,
+                    "gfx::dx12::command",
+                    "clear-upload-failed",
+                    "Unable to allocate the upload buffer for a clear: {:x}",
+                    hr
+                );
+                return;
+            }
+        };
+        for value in slice::from_raw_parts_mut(mapped as *mut u32, size as usize / 4) {
+            *value = texel;
+        }
+        self.retained_resources.push(buffer);
+
+        self.barriers.clear();
+        self.fill_texture_barries(
+            image,
+            base_state..d3d12::D3D12_RESOURCE_STATE_COPY_DEST,
+            sub,
+        );
+        self.flush_barriers();
+
+        let mut src = d3d12::D3D12_TEXTURE_COPY_LOCATION {
+            pResource: buffer.as_mut_ptr(),
+            Type: d3d12::D3D12_TEXTURE_COPY_TYPE_PLACED_FOOTPRINT,
+            u: mem::zeroed(),
+        };
+        let mut dst = d3d12::D3D12_TEXTURE_COPY_LOCATION {
+            pResource: image.resource.as_mut_ptr(),
+            Type: d3d12::D3D12_TEXTURE_COPY_TYPE_SUBRESOURCE_INDEX,
+            u: mem::zeroed(),
+        };
+        let num_layers = sub.resolve_layer_count(image.kind.num_layers());
+        for level in sub.level_start..sub.level_start + sub.resolve_level_count(image.mip_levels) {
+            let level_extent = image.kind.extent().at_level(level);
+            *src.u.PlacedFootprint_mut() = d3d12::D3D12_PLACED_SUBRESOURCE_FOOTPRINT {
+                Offset: 0,
+                Footprint: d3d12::D3D12_SUBRESOURCE_FOOTPRINT {
+                    Format: image.descriptor.Format,
+                    Width: level_extent.width,
+                    Height: level_extent.height,
+                    Depth: level_extent.depth,
+                    RowPitch: row_pitch,
+                },
+            };
+            for layer in sub.layer_start..sub.layer_start + num_layers {
+                *dst.u.SubresourceIndex_mut() = image.calc_subresource(level as _, layer as _, 0);
+                self.raw.CopyTextureRegion(&dst, 0, 0, 0, &src, ptr::null());
+            }
+        }
+
+        self.flip_barriers();
+        self.flush_barriers();
+    }
+
     fn flip_barriers(&mut self) {
         for barrier in self.barriers.iter_mut() {
             if barrier.Type == d3d12::D3D12_RESOURCE_BARRIER_TYPE_TRANSITION {
@@ -1136,29 +1720,48 @@ impl CommandBuffer {
             StateBefore: states.start,
             StateAfter: states.end,
         });
+        let image_aspects = target.surface_type.desc().aspects;
         let full_range = image::SubresourceRange {
             aspects: range.aspects,
             ..Default::default()
         };
         let num_levels = range.resolve_level_count(target.mip_levels);
         let num_layers = range.resolve_layer_count(target.kind.num_layers());
+        // Depth and stencil live in separate planes, which can be transitioned on their own.
+        let planes = if image_aspects.contains(Aspects::DEPTH | Aspects::STENCIL) {
+            let start = if range.aspects.contains(Aspects::DEPTH) {
+                0
+            } else {
+                1
+            };
+            let end = if range.aspects.contains(Aspects::STENCIL) {
+                2
+            } else {
+                1
+            };
+            start..end
+        } else {
+            0..1
+        };
 
-        if *range == full_range {
+        if *range == full_range && range.aspects.contains(image_aspects) {
             // Only one barrier if it affects the whole image.
             self.barriers.push(bar);
         } else {
-            // Generate barrier for each layer/level combination.
-            for rel_level in 0..num_levels {
-                for rel_layer in 0..num_layers {
-                    unsafe {
-                        let transition_barrier = &mut *bar.u.Transition_mut();
-                        transition_barrier.Subresource = target.calc_subresource(
-                            (range.level_start + rel_level) as _,
-                            (range.layer_start + rel_layer) as _,
-                            0,
-                        );
+            // Generate barrier for each plane/layer/level combination.
+            for plane in planes {
+                for rel_level in 0..num_levels {
+                    for rel_layer in 0..num_layers {
+                        unsafe {
+                            let transition_barrier = &mut *bar.u.Transition_mut();
+                            transition_barrier.Subresource = target.calc_subresource(
+                                (range.level_start + rel_level) as _,
+                                (range.layer_start + rel_layer) as _,
+                                plane,
+                            );
+                        }
+                        self.barriers.push(bar);
                     }
-                    self.barriers.push(bar);
                 }
             }
         }
@@ -1174,6 +1777,137 @@ impl CommandBuffer {
         )
     }
 
+    /// Appends the native barriers of `barriers` to `self.barriers`.
+    unsafe fn fill_barriers<'a, T>(&mut self, stages: &Range<pso::PipelineStage>, barriers: T)
+    where
+        T: Iterator<Item = memory::Barrier<'a, Backend>>,
+    {
+        // transition barriers
+        for barrier in barriers {
+            match barrier {
+                memory::Barrier::AllBuffers(_) | memory::Barrier::AllImages(_) => {
+                    // Aliasing barrier with NULL resource is the closest we can get to
+                    // a global memory barrier in Vulkan.
+                    // Was suggested by a Microsoft representative as well as some of the IHVs.
+                    let mut bar = d3d12::D3D12_RESOURCE_BARRIER {
+                        Type: d3d12::D3D12_RESOURCE_BARRIER_TYPE_UAV,
+                        Flags: d3d12::D3D12_RESOURCE_BARRIER_FLAG_NONE,
+                        u: mem::zeroed(),
+                    };
+                    *bar.u.UAV_mut() = d3d12::D3D12_RESOURCE_UAV_BARRIER {
+                        pResource: ptr::null_mut(),
+                    };
+                    self.barriers.push(bar);
+                }
+                memory::Barrier::Buffer {
+                    ref states,
+                    target,
+                    ref families,
+                    range: _,
+                } => {
+                    // TODO: Implement queue family ownership transitions for dx12
+                    if let Some(f) = families {
+                        if f.start.0 != f.end.0 {
+                            unimplemented!("Queue family resource ownership transitions are not implemented for DX12 (attempted transition from queue family {} to {}", f.start.0, f.end.0);
+                        }
+                    }
+
+                    let state_src = conv::map_buffer_resource_state(states.start);
+                    let state_dst = conv::map_buffer_resource_state(states.end);
+
+                    if state_src == state_dst {
+                        continue;
+                    }
+
+                    let target = target.expect_bound();
+                    let bar = Self::transition_barrier(d3d12::D3D12_RESOURCE_TRANSITION_BARRIER {
+                        pResource: target.resource.as_mut_ptr(),
+                        Subresource: d3d12::D3D12_RESOURCE_BARRIER_ALL_SUBRESOURCES,
+                        StateBefore: state_src,
+                        StateAfter: state_dst,
+                    });
+
+                    self.barriers.push(bar);
+                }
+                memory::Barrier::Image {
+                    ref states,
+                    target,
+                    ref families,
+                    ref range,
+                } => {
+                    // TODO: Implement queue family ownership transitions for dx12
+                    if let Some(f) = families {
+                        if f.start.0 != f.end.0 {
+                            unimplemented!("Queue family resource ownership transitions are not implemented for DX12 (attempted transition from queue family {} to {}", f.start.0, f.end.0);
+                        }
+                    }
+
+                    let state_src = conv::map_image_resource_state(states.start.0, states.start.1);
+                    let state_dst = conv::map_image_resource_state(states.end.0, states.end.1);
+
+                    let target = target.expect_bound();
+
+                    match target.place {
+                        r::Place::Heap { .. } => {
+                            self.fill_texture_barries(target, state_src..state_dst, range);
+                        }
+                        r::Place::Swapchain { .. } => {} //ignore
+                    }
+                }
+                memory::Barrier::Aliasing { before, after } => {
+                    fn raw_resource(
+                        resource: Option<memory::AliasedResource<Backend>>,
+                    ) -> *mut d3d12::ID3D12Resource {
+                        match resource {
+                            Some(memory::AliasedResource::Buffer(buffer)) => {
+                                buffer.expect_bound().resource.as_mut_ptr()
+                            }
+                            Some(memory::AliasedResource::Image(image)) => {
+                                image.expect_bound().resource.as_mut_ptr()
+                            }
+                            None => ptr::null_mut(),
+                        }
+                    }
+
+                    let mut bar = d3d12::D3D12_RESOURCE_BARRIER {
+                        Type: d3d12::D3D12_RESOURCE_BARRIER_TYPE_ALIASING,
+                        Flags: d3d12::D3D12_RESOURCE_BARRIER_FLAG_NONE,
+                        u: mem::zeroed(),
+                    };
+                    *bar.u.Aliasing_mut() = d3d12::D3D12_RESOURCE_ALIASING_BARRIER {
+                        pResourceBefore: raw_resource(before),
+                        pResourceAfter: raw_resource(after),
+                    };
+                    self.barriers.push(bar);
+                }
+            }
+        }
+
+        let all_shader_stages = pso::PipelineStage::VERTEX_SHADER
+            | pso::PipelineStage::FRAGMENT_SHADER
+            | pso::PipelineStage::COMPUTE_SHADER
+            | pso::PipelineStage::GEOMETRY_SHADER
+            | pso::PipelineStage::HULL_SHADER
+            | pso::PipelineStage::DOMAIN_SHADER;
+
+        // UAV barriers
+        //
+        // TODO: Currently always add a global UAV barrier.
+        //       WAR only requires an execution barrier but D3D12 seems to need
+        //       a UAV barrier for this according to docs. Can we make this better?
+        if (stages.start & stages.end).intersects(all_shader_stages) {
+            let mut barrier = d3d12::D3D12_RESOURCE_BARRIER {
+                Type: d3d12::D3D12_RESOURCE_BARRIER_TYPE_UAV,
+                Flags: d3d12::D3D12_RESOURCE_BARRIER_FLAG_NONE,
+                u: mem::zeroed(),
+            };
+            *barrier.u.UAV_mut() = d3d12::D3D12_RESOURCE_UAV_BARRIER {
+                pResource: ptr::null_mut(),
+            };
+            self.barriers.push(barrier);
+        }
+    }
+
     unsafe fn flush_barriers(&self) {
         if !self.barriers.is_empty() {
             self.raw
@@ -1253,6 +1987,7 @@ impl com::CommandBuffer<Backend> for CommandBuffer {
         for resource in self.retained_resources.drain(..) {
             resource.destroy();
         }
+        self.spill_memory.reset();
     }
 
     unsafe fn begin_render_pass<'a, T>(
@@ -1265,77 +2000,18 @@ impl com::CommandBuffer<Backend> for CommandBuffer {
     ) where
         T: Iterator<Item = com::RenderAttachmentInfo<'a, Backend>>,
     {
-        // Make sure that no subpass works with Present as intermediate layout.
-        // This wouldn't make much sense, and proceeding with this constraint
-        // allows the state transitions generated from subpass dependencies
-        // to ignore the layouts completely.
-        assert!(!render_pass.subpasses.iter().any(|sp| sp
-            .color_attachments
-            .iter()
-            .chain(sp.depth_stencil_attachment.iter())
-            .chain(sp.input_attachments.iter())
-            .any(|aref| aref.1 == image::Layout::Present)));
-
-        if !render_pass.raw_name.is_empty() {
-            let n = &render_pass.raw_name;
-            self.raw
-                .BeginEvent(0, n.as_ptr() as *const _, n.len() as u32 * 2);
-        }
-
-        self.barriers.clear();
-        let mut attachments = Vec::new();
-        for (i, (info, attachment)) in attachment_infos
-            .zip(render_pass.attachments.iter())
-            .enumerate()
-        {
-            let view = info.image_view.clone();
-            // for swapchain views, we consider the initial layout to always be `General`
-            let pass_start_state =
-                conv::map_image_resource_state(image::Access::empty(), attachment.layouts.start);
-            if view.is_swapchain() && pass_start_state != d3d12::D3D12_RESOURCE_STATE_COMMON {
-                let barrier = d3d12::D3D12_RESOURCE_TRANSITION_BARRIER {
-                    pResource: view.resource.as_mut_ptr(),
-                    Subresource: d3d12::D3D12_RESOURCE_BARRIER_ALL_SUBRESOURCES,
-                    StateBefore: d3d12::D3D12_RESOURCE_STATE_COMMON,
-                    StateAfter: pass_start_state,
-                };
-                self.barriers.push(Self::transition_barrier(barrier));
-            }
-
-            attachments.push(AttachmentInfo {
-                subpass_id: render_pass
-                    .subpasses
-                    .iter()
-                    .position(|sp| sp.is_using(i))
-                    .map(|i| i as pass::SubpassId),
-                view,
-                clear_value: if attachment.ops.load == pass::AttachmentLoadOp::Clear {
-                    Some(info.clear_value)
-                } else {
-                    None
-                },
-                stencil_value: if attachment.stencil_ops.load == pass::AttachmentLoadOp::Clear {
-                    Some(info.clear_value.depth_stencil.stencil)
-                } else {
-                    None
-                },
-            });
-        }
-        self.flush_barriers();
-
-        self.pass_cache = Some(RenderPassCache {
-            render_pass: render_pass.clone(),
-            target_rect: get_rect(&target_rect),
-            attachments,
-            num_layers: framebuffer.layers,
-            has_name: !render_pass.raw_name.is_empty(),
-        });
-        self.cur_subpass = 0;
-        self.insert_subpass_barriers(BarrierPoint::Pre);
-        self.bind_targets();
+        self.begin_render_pass_impl(
+            render_pass,
+            framebuffer,
+            target_rect,
+            attachment_infos,
+            ext::D3D12_RENDER_PASS_FLAG_NONE,
+        );
     }
 
     unsafe fn next_subpass(&mut self, _contents: com::SubpassContents) {
+        self.end_native_pass();
+        self.bind_shading_rate_image(false);
         self.insert_subpass_barriers(BarrierPoint::Post);
         self.resolve_attachments();
 
@@ -1345,6 +2021,8 @@ impl com::CommandBuffer<Backend> for CommandBuffer {
     }
 
     unsafe fn end_render_pass(&mut self) {
+        self.end_native_pass();
+        self.bind_shading_rate_image(false);
         self.insert_subpass_barriers(BarrierPoint::Post);
         self.resolve_attachments();
 
@@ -1374,6 +2052,43 @@ impl com::CommandBuffer<Backend> for CommandBuffer {
         }
     }
 
+    unsafe fn begin_rendering(&mut self, info: &com::RenderingInfo<Backend>) {
+        // Dynamic rendering goes through the same path as a single-subpass render pass.
+        // Suspending and resuming map to the native render pass flags, and are otherwise
+        // handled by the load and store operations of the attachments.
+        let render_pass = info.with_subpass(|attachments, subpass| {
+            Device::build_render_pass(
+                attachments.iter().cloned(),
+                iter::once(subpass),
+                iter::empty(),
+            )
+        });
+        let framebuffer = r::Framebuffer {
+            layers: info.layers,
+        };
+        let mut flags = ext::D3D12_RENDER_PASS_FLAG_NONE;
+        if info.flags.contains(com::RenderingFlags::SUSPENDING) {
+            flags |= ext::D3D12_RENDER_PASS_FLAG_SUSPENDING_PASS;
+        }
+        if info.flags.contains(com::RenderingFlags::RESUMING) {
+            flags |= ext::D3D12_RENDER_PASS_FLAG_RESUMING_PASS;
+        }
+        self.begin_render_pass_impl(
+            &render_pass,
+            &framebuffer,
+            info.render_area,
+            info.attachments().map(|at| com::RenderAttachmentInfo {
+                image_view: at.image_view,
+                clear_value: at.clear_value,
+            }),
+            flags,
+        );
+    }
+
+    unsafe fn end_rendering(&mut self) {
+        self.end_render_pass();
+    }
+
     unsafe fn pipeline_barrier<'a, T>(
         &mut self,
         stages: Range<pso::PipelineStage>,
@@ -1383,122 +2098,44 @@ impl com::CommandBuffer<Backend> for CommandBuffer {
         T: Iterator<Item = memory::Barrier<'a, Backend>>,
     {
         self.barriers.clear();
+        self.fill_barriers(&stages, barriers);
+        self.flush_barriers();
+    }
 
-        // transition barriers
-        for barrier in barriers {
-            match barrier {
-                memory::Barrier::AllBuffers(_) | memory::Barrier::AllImages(_) => {
-                    // Aliasing barrier with NULL resource is the closest we can get to
-                    // a global memory barrier in Vulkan.
-                    // Was suggested by a Microsoft representative as well as some of the IHVs.
-                    let mut bar = d3d12::D3D12_RESOURCE_BARRIER {
-                        Type: d3d12::D3D12_RESOURCE_BARRIER_TYPE_UAV,
-                        Flags: d3d12::D3D12_RESOURCE_BARRIER_FLAG_NONE,
-                        u: mem::zeroed(),
-                    };
-                    *bar.u.UAV_mut() = d3d12::D3D12_RESOURCE_UAV_BARRIER {
-                        pResource: ptr::null_mut(),
-                    };
-                    self.barriers.push(bar);
-                }
-                memory::Barrier::Buffer {
-                    ref states,
-                    target,
-                    ref families,
-                    range: _,
-                } => {
-                    // TODO: Implement queue family ownership transitions for dx12
-                    if let Some(f) = families {
-                        if f.start.0 != f.end.0 {
-                            unimplemented!("Queue family resource ownership transitions are not implemented for DX12 (attempted transition from queue family {} to {}", f.start.0, f.end.0);
-                        }
-                    }
-
-                    let state_src = conv::map_buffer_resource_state(states.start);
-                    let state_dst = conv::map_buffer_resource_state(states.end);
-
-                    if state_src == state_dst {
-                        continue;
-                    }
-
-                    let target = target.expect_bound();
-                    let bar = Self::transition_barrier(d3d12::D3D12_RESOURCE_TRANSITION_BARRIER {
-                        pResource: target.resource.as_mut_ptr(),
-                        Subresource: d3d12::D3D12_RESOURCE_BARRIER_ALL_SUBRESOURCES,
-                        StateBefore: state_src,
-                        StateAfter: state_dst,
-                    });
-
-                    self.barriers.push(bar);
-                }
-                memory::Barrier::Image {
-                    ref states,
-                    target,
-                    ref families,
-                    ref range,
-                } => {
-                    // TODO: Implement queue family ownership transitions for dx12
-                    if let Some(f) = families {
-                        if f.start.0 != f.end.0 {
-                            unimplemented!("Queue family resource ownership transitions are not implemented for DX12 (attempted transition from queue family {} to {}", f.start.0, f.end.0);
-                        }
-                    }
-
-                    let state_src = conv::map_image_resource_state(states.start.0, states.start.1);
-                    let state_dst = conv::map_image_resource_state(states.end.0, states.end.1);
-
-                    let target = target.expect_bound();
-
-                    match target.place {
-                        r::Place::Heap { .. } => {
-                            self.fill_texture_barries(target, state_src..state_dst, range);
-                        }
-                        r::Place::Swapchain { .. } => {} //ignore
-                    }
-                }
-            }
-        }
-
-        let all_shader_stages = pso::PipelineStage::VERTEX_SHADER
-            | pso::PipelineStage::FRAGMENT_SHADER
-            | pso::PipelineStage::COMPUTE_SHADER
-            | pso::PipelineStage::GEOMETRY_SHADER
-            | pso::PipelineStage::HULL_SHADER
-            | pso::PipelineStage::DOMAIN_SHADER;
-
-        // UAV barriers
-        //
-        // TODO: Currently always add a global UAV barrier.
-        //       WAR only requires an execution barrier but D3D12 seems to need
-        //       a UAV barrier for this according to docs. Can we make this better?
-        if (stages.start & stages.end).intersects(all_shader_stages) {
-            let mut barrier = d3d12::D3D12_RESOURCE_BARRIER {
-                Type: d3d12::D3D12_RESOURCE_BARRIER_TYPE_UAV,
-                Flags: d3d12::D3D12_RESOURCE_BARRIER_FLAG_NONE,
-                u: mem::zeroed(),
-            };
-            *barrier.u.UAV_mut() = d3d12::D3D12_RESOURCE_UAV_BARRIER {
-                pResource: ptr::null_mut(),
-            };
-            self.barriers.push(barrier);
+    unsafe fn begin_barrier<'a, T>(
+        &mut self,
+        _event: &(),
+        stages: Range<pso::PipelineStage>,
+        barriers: T,
+    ) where
+        T: Iterator<Item = memory::Barrier<'a, Backend>>,
+    {
+        // Only the transitions can be split, the rest is issued at the end
+        self.barriers.clear();
+        self.fill_barriers(&stages, barriers);
+        self.barriers
+            .retain(|bar| bar.Type == d3d12::D3D12_RESOURCE_BARRIER_TYPE_TRANSITION);
+        for bar in self.barriers.iter_mut() {
+            bar.Flags = d3d12::D3D12_RESOURCE_BARRIER_FLAG_BEGIN_ONLY;
         }
+        self.flush_barriers();
+    }
 
-        // Alias barriers
-        //
-        // TODO: Optimize, don't always add an alias barrier
-        if false {
-            let mut barrier = d3d12::D3D12_RESOURCE_BARRIER {
-                Type: d3d12::D3D12_RESOURCE_BARRIER_TYPE_ALIASING,
-                Flags: d3d12::D3D12_RESOURCE_BARRIER_FLAG_NONE,
-                u: mem::zeroed(),
-            };
-            *barrier.u.Aliasing_mut() = d3d12::D3D12_RESOURCE_ALIASING_BARRIER {
-                pResourceBefore: ptr::null_mut(),
-                pResourceAfter: ptr::null_mut(),
-            };
-            self.barriers.push(barrier);
+    unsafe fn end_barrier<'a, T>(
+        &mut self,
+        _event: &(),
+        stages: Range<pso::PipelineStage>,
+        barriers: T,
+    ) where
+        T: Iterator<Item = memory::Barrier<'a, Backend>>,
+    {
+        self.barriers.clear();
+        self.fill_barriers(&stages, barriers);
+        for bar in self.barriers.iter_mut() {
+            if bar.Type == d3d12::D3D12_RESOURCE_BARRIER_TYPE_TRANSITION {
+                bar.Flags = d3d12::D3D12_RESOURCE_BARRIER_FLAG_END_ONLY;
+            }
         }
-
         self.flush_barriers();
     }
 
@@ -1515,8 +2152,17 @@ impl com::CommandBuffer<Backend> for CommandBuffer {
         let base_state = conv::map_image_resource_state(image::Access::TRANSFER_WRITE, layout);
 
         for sub in subresource_ranges {
+            if sub.aspects.contains(Aspects::COLOR) && image.clear_cv.is_empty() {
+                self.clear_image_by_copy(image, base_state, value.color, &sub);
+                continue;
+            }
             if sub.level_start != 0 || image.mip_levels != 1 {
-                warn!("Clearing non-zero mipmap levels is not supported yet");
+                auxil::report!(
+                    Warn,
+                    "gfx::dx12::command",
+                    "clear-mip-level-unsupported",
+                    "Clearing non-zero mipmap levels is not supported yet"
+                );
             }
             let target_state = if sub.aspects.contains(Aspects::COLOR) {
                 d3d12::D3D12_RESOURCE_STATE_RENDER_TARGET
@@ -1531,8 +2177,15 @@ impl com::CommandBuffer<Backend> for CommandBuffer {
             for rel_layer in 0..sub.resolve_layer_count(image.kind.num_layers()) {
                 let layer = (sub.layer_start + rel_layer) as usize;
                 if sub.aspects.contains(Aspects::COLOR) {
-                    let rtv = image.clear_cv[layer].raw;
-                    self.clear_render_target_view(rtv, value.color, &[]);
+                    match image.clear_cv.get(layer) {
+                        Some(rtv) => self.clear_render_target_view(rtv.raw, value.color, &[]),
+                        None => auxil::report!(
+                            Warn,
+                            "gfx::dx12::command",
+                            "clear-non-renderable-image",
+                            "Unable to clear a color image, as it isn't renderable"
+                        ),
+                    }
                 }
                 if sub.aspects.contains(Aspects::DEPTH) {
                     let dsv = image.clear_dv[layer].raw;
@@ -1976,8 +2629,9 @@ impl com::CommandBuffer<Backend> for CommandBuffer {
                 TopLeftY: vp.rect.y as _,
                 Width: vp.rect.w as _,
                 Height: vp.rect.h as _,
-                MinDepth: vp.depth.start,
-                MaxDepth: vp.depth.end,
+                // D3D12 doesn't support depth ranges outside of [0, 1]
+                MinDepth: vp.depth.start.max(0.0).min(1.0),
+                MaxDepth: vp.depth.end.max(0.0).min(1.0),
             };
             if i + first_viewport as usize >= self.viewport_cache.len() {
                 self.viewport_cache.push(viewport);
@@ -2015,7 +2669,10 @@ impl com::CommandBuffer<Backend> for CommandBuffer {
         assert!(!faces.is_empty());
 
         if !faces.is_all() {
-            warn!(
+            auxil::report!(
+                Warn,
+                "gfx::dx12::command",
+                "stencil-reference-face-mismatch",
                 "Stencil ref values set for both faces but only one was requested ({})",
                 faces.bits(),
             );
@@ -2040,7 +2697,12 @@ impl com::CommandBuffer<Backend> for CommandBuffer {
             cmd_list1.OMSetDepthBounds(bounds.start, bounds.end);
             cmd_list1.destroy();
         } else {
-            warn!("Depth bounds test is not supported");
+            auxil::report!(
+                Warn,
+                "gfx::dx12::command",
+                "depth-bounds-unsupported",
+                "Depth bounds test is not supported"
+            );
         }
     }
 
@@ -2053,6 +2715,54 @@ impl com::CommandBuffer<Backend> for CommandBuffer {
         // unimplemented!()
     }
 
+    unsafe fn set_shading_rate(
+        &mut self,
+        rate: pso::ShadingRate,
+        combiners: [pso::ShadingRateCombiner; 2],
+    ) {
+        let raw_combiners = [
+            conv::map_shading_rate_combiner(combiners[0]),
+            conv::map_shading_rate_combiner(combiners[1]),
+        ];
+        // Tier 1 doesn't have the combiners, both of them are pass-through anyway there.
+        let combiners_ptr = if combiners == [pso::ShadingRateCombiner::Keep; 2] {
+            ptr::null()
+        } else {
+            raw_combiners.as_ptr()
+        };
+        let (cmd_list5, hr) = self.raw.cast::<ext::ID3D12GraphicsCommandList5>();
+        debug_assert!(winerror::SUCCEEDED(hr));
+        cmd_list5.RSSetShadingRate(conv::map_shading_rate(rate), combiners_ptr);
+        cmd_list5.destroy();
+    }
+
+    unsafe fn set_sample_locations(
+        &mut self,
+        samples: image::NumSamples,
+        locations: &[pso::SampleLocation],
+    ) {
+        // D3D12 positions are relative to the pixel center, in 1/16th of a pixel
+        let mut positions = locations
+            .iter()
+            .map(|location| d3d12::D3D12_SAMPLE_POSITION {
+                X: ((location.x - 0.5) * 16.0).round().max(-8.0).min(7.0) as _,
+                Y: ((location.y - 0.5) * 16.0).round().max(-8.0).min(7.0) as _,
+            })
+            .collect::<SmallVec<[_; 16]>>();
+        let (cmd_list1, hr) = self.raw.cast::<d3d12::ID3D12GraphicsCommandList1>();
+        if winerror::SUCCEEDED(hr) {
+            cmd_list1.SetSamplePositions(samples as _, 1, positions.as_mut_ptr());
+            cmd_list1.destroy();
+        } else {
+            auxil::report!(
+                Warn,
+                "gfx::dx12::command",
+                "sample-locations-unsupported",
+                "Programmable sample positions are not supported"
+            );
+        }
+    }
+
     unsafe fn bind_graphics_pipeline(&mut self, pipeline: &r::GraphicsPipeline) {
         match self.gr_pipeline.pipeline {
             Some((_, ref shared)) if Arc::ptr_eq(shared, &pipeline.shared) => {
@@ -2150,6 +2860,10 @@ impl com::CommandBuffer<Backend> for CommandBuffer {
     }
 
     unsafe fn dispatch(&mut self, count: WorkGroupCount) {
+        if count.contains(&0) {
+            debug!("Skipping an empty dispatch");
+            return;
+        }
         self.set_compute_bind_point();
         self.raw.dispatch(count);
     }
@@ -2175,7 +2889,12 @@ impl com::CommandBuffer<Backend> for CommandBuffer {
             .size
             .map_or(buffer.requirements.size, |s| range.offset + s) as i32;
         if start % 4 != 0 || end % 4 != 0 {
-            warn!("Fill buffer bounds have to be multiples of 4");
+            auxil::report!(
+                Warn,
+                "gfx::dx12::command",
+                "fill-buffer-unaligned",
+                "Fill buffer bounds have to be multiples of 4"
+            );
         }
         let rect = d3d12::D3D12_RECT {
             left: start / bytes_per_unit,
@@ -2316,7 +3035,12 @@ impl com::CommandBuffer<Backend> for CommandBuffer {
             let (heap_ptr, heap_offset) = match src.place {
                 r::Place::Heap { raw, offset } => (raw.as_mut_ptr(), offset),
                 r::Place::Swapchain {} => {
-                    error!("Unable to copy swapchain image, skipping");
+                    auxil::report!(
+                        Error,
+                        "gfx::dx12::command",
+                        "swapchain-copy-skipped",
+                        "Unable to copy swapchain image, skipping"
+                    );
                     return;
                 }
             };
@@ -2529,6 +3253,10 @@ impl com::CommandBuffer<Backend> for CommandBuffer {
     }
 
     unsafe fn draw(&mut self, vertices: Range<VertexCount>, instances: Range<InstanceCount>) {
+        if vertices.start >= vertices.end || instances.start >= instances.end {
+            debug!("Skipping an empty draw");
+            return;
+        }
         self.set_graphics_bind_point();
         self.raw.draw(
             vertices.end - vertices.start,
@@ -2544,6 +3272,10 @@ impl com::CommandBuffer<Backend> for CommandBuffer {
         base_vertex: VertexOffset,
         instances: Range<InstanceCount>,
     ) {
+        if indices.start >= indices.end || instances.start >= instances.end {
+            debug!("Skipping an empty indexed draw");
+            return;
+        }
         self.set_graphics_bind_point();
         self.raw.draw_indexed(
             indices.end - indices.start,
@@ -2561,6 +3293,10 @@ impl com::CommandBuffer<Backend> for CommandBuffer {
         draw_count: DrawCount,
         stride: buffer::Stride,
     ) {
+        if draw_count == 0 {
+            debug!("Skipping an empty indirect draw");
+            return;
+        }
         assert_eq!(stride, 16);
         let buffer = buffer.expect_bound();
         self.set_graphics_bind_point();
@@ -2581,6 +3317,10 @@ impl com::CommandBuffer<Backend> for CommandBuffer {
         draw_count: DrawCount,
         stride: buffer::Stride,
     ) {
+        if draw_count == 0 {
+            debug!("Skipping an empty indirect draw");
+            return;
+        }
         assert_eq!(stride, 20);
         let buffer = buffer.expect_bound();
         self.set_graphics_bind_point();
@@ -2758,27 +3498,47 @@ impl com::CommandBuffer<Backend> for CommandBuffer {
 
     unsafe fn push_graphics_constants(
         &mut self,
-        _layout: &r::PipelineLayout,
+        layout: &r::PipelineLayout,
         _stages: pso::ShaderStageFlags,
         offset: u32,
         constants: &[u32],
     ) {
         assert!(offset % 4 == 0);
-        self.gr_pipeline
-            .user_data
-            .set_constants(offset as usize / 4, constants);
+        if layout.shared.spilled_constants {
+            self.gr_pipeline.push_spilled_constants(
+                &mut self.spill_memory,
+                self.shared.service_pipes.device,
+                layout,
+                offset as usize / 4,
+                constants,
+            );
+        } else {
+            self.gr_pipeline
+                .user_data
+                .set_constants(offset as usize / 4, constants);
+        }
     }
 
     unsafe fn push_compute_constants(
         &mut self,
-        _layout: &r::PipelineLayout,
+        layout: &r::PipelineLayout,
         offset: u32,
         constants: &[u32],
     ) {
         assert!(offset % 4 == 0);
-        self.comp_pipeline
-            .user_data
-            .set_constants(offset as usize / 4, constants);
+        if layout.shared.spilled_constants {
+            self.comp_pipeline.push_spilled_constants(
+                &mut self.spill_memory,
+                self.shared.service_pipes.device,
+                layout,
+                offset as usize / 4,
+                constants,
+            );
+        } else {
+            self.comp_pipeline
+                .user_data
+                .set_constants(offset as usize / 4, constants);
+        }
     }
 
     unsafe fn execute_commands<'a, T>(&mut self, cmd_buffers: T)
@@ -2786,7 +3546,12 @@ impl com::CommandBuffer<Backend> for CommandBuffer {
         T: Iterator<Item = &'a CommandBuffer>,
     {
         for _cmd_buf in cmd_buffers {
-            error!("TODO: execute_commands");
+            auxil::report!(
+                Error,
+                "gfx::dx12::command",
+                "execute-commands-unimplemented",
+                "TODO: execute_commands"
+            );
         }
     }
 
@@ -2801,4 +3566,10 @@ impl com::CommandBuffer<Backend> for CommandBuffer {
     unsafe fn end_debug_marker(&mut self) {
         self.raw.EndEvent();
     }
+
+    unsafe fn set_checkpoint(&mut self, label: &str) {
+        // DRED records the marker strings as the breadcrumb contexts.
+        let (ptr, size) = self.fill_marker(label);
+        self.raw.SetMarker(0, ptr, size);
+    }
 }
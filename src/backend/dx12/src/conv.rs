@@ -1,4 +1,4 @@
-use crate::validate_line_width;
+use crate::{ext, validate_line_width};
 
 use spirv_cross::spirv;
 use std::mem;
@@ -7,6 +7,7 @@ use winapi::{
     shared::{
         basetsd::UINT8,
         dxgiformat::*,
+        dxgitype,
         minwindef::{FALSE, INT, TRUE, UINT},
     },
     um::{d3d12::*, d3dcommon::*},
@@ -17,6 +18,7 @@ use hal::{
     buffer,
     format::{Format, ImageFeature, SurfaceType, Swizzle},
     image, pso,
+    window::ColorSpace,
 };
 
 use native::ShaderVisibility;
@@ -92,6 +94,8 @@ pub fn map_format(format: Format) -> Option<DXGI_FORMAT> {
         X8D24Unorm if reverse => DXGI_FORMAT_D24_UNORM_S8_UINT,
         D32Sfloat => DXGI_FORMAT_D32_FLOAT,
         D32SfloatS8Uint => DXGI_FORMAT_D32_FLOAT_S8X24_UINT,
+        // Emulated, see `is_emulated_format`
+        S8Uint | D16UnormS8Uint => DXGI_FORMAT_D24_UNORM_S8_UINT,
         Bc1RgbUnorm | Bc1RgbaUnorm => DXGI_FORMAT_BC1_UNORM,
         Bc1RgbSrgb | Bc1RgbaSrgb => DXGI_FORMAT_BC1_UNORM_SRGB,
         Bc2Unorm => DXGI_FORMAT_BC2_UNORM,
@@ -113,10 +117,21 @@ pub fn map_format(format: Format) -> Option<DXGI_FORMAT> {
     Some(format)
 }
 
+/// Returns true if the format has no DXGI equivalent, and is backed by
+/// `DXGI_FORMAT_D24_UNORM_S8_UINT` instead.
+pub fn is_emulated_format(format: Format) -> bool {
+    match format {
+        Format::S8Uint | Format::D16UnormS8Uint => true,
+        _ => false,
+    }
+}
+
 pub fn map_format_shader_depth(surface: SurfaceType) -> Option<DXGI_FORMAT> {
     match surface {
         SurfaceType::D16 => Some(DXGI_FORMAT_R16_UNORM),
-        SurfaceType::X8D24 | SurfaceType::D24_S8 => Some(DXGI_FORMAT_R24_UNORM_X8_TYPELESS),
+        SurfaceType::X8D24 | SurfaceType::D24_S8 | SurfaceType::D16_S8 => {
+            Some(DXGI_FORMAT_R24_UNORM_X8_TYPELESS)
+        }
         SurfaceType::D32 => Some(DXGI_FORMAT_R32_FLOAT),
         SurfaceType::D32_S8 => Some(DXGI_FORMAT_R32_FLOAT_X8X24_TYPELESS),
         _ => return None,
@@ -125,7 +140,9 @@ pub fn map_format_shader_depth(surface: SurfaceType) -> Option<DXGI_FORMAT> {
 
 pub fn map_format_shader_stencil(surface: SurfaceType) -> Option<DXGI_FORMAT> {
     match surface {
-        SurfaceType::D24_S8 => Some(DXGI_FORMAT_X24_TYPELESS_G8_UINT),
+        SurfaceType::S8 | SurfaceType::D16_S8 | SurfaceType::D24_S8 => {
+            Some(DXGI_FORMAT_X24_TYPELESS_G8_UINT)
+        }
         SurfaceType::D32_S8 => Some(DXGI_FORMAT_X32_TYPELESS_G8X24_UINT),
         _ => None,
     }
@@ -134,7 +151,9 @@ pub fn map_format_shader_stencil(surface: SurfaceType) -> Option<DXGI_FORMAT> {
 pub fn map_format_dsv(surface: SurfaceType) -> Option<DXGI_FORMAT> {
     match surface {
         SurfaceType::D16 => Some(DXGI_FORMAT_D16_UNORM),
-        SurfaceType::X8D24 | SurfaceType::D24_S8 => Some(DXGI_FORMAT_D24_UNORM_S8_UINT),
+        SurfaceType::S8 | SurfaceType::D16_S8 | SurfaceType::X8D24 | SurfaceType::D24_S8 => {
+            Some(DXGI_FORMAT_D24_UNORM_S8_UINT)
+        }
         SurfaceType::D32 => Some(DXGI_FORMAT_D32_FLOAT),
         SurfaceType::D32_S8 => Some(DXGI_FORMAT_D32_FLOAT_S8X24_UINT),
         _ => None,
@@ -151,6 +170,18 @@ pub fn map_format_nosrgb(format: Format) -> Option<DXGI_FORMAT> {
     }
 }
 
+pub fn map_color_space(color_space: ColorSpace) -> dxgitype::DXGI_COLOR_SPACE_TYPE {
+    if color_space == ColorSpace::SRGB_NONLINEAR {
+        dxgitype::DXGI_COLOR_SPACE_RGB_FULL_G22_NONE_P709
+    } else if color_space == ColorSpace::LINEAR {
+        dxgitype::DXGI_COLOR_SPACE_RGB_FULL_G10_NONE_P709
+    } else if color_space == ColorSpace::HDR10 {
+        dxgitype::DXGI_COLOR_SPACE_RGB_FULL_G2084_NONE_P2020
+    } else {
+        panic!("Unexpected color space {:?}", color_space)
+    }
+}
+
 pub fn map_swizzle(swizzle: Swizzle) -> UINT {
     use hal::format::Component::*;
 
@@ -215,7 +246,7 @@ pub fn map_surface_type(st: SurfaceType) -> Option<DXGI_FORMAT> {
         D16 => DXGI_FORMAT_R16_TYPELESS,
         X8D24 => DXGI_FORMAT_R24G8_TYPELESS,
         D32 => DXGI_FORMAT_R32_TYPELESS,
-        D24_S8 => DXGI_FORMAT_R24G8_TYPELESS,
+        D24_S8 | S8 | D16_S8 => DXGI_FORMAT_R24G8_TYPELESS,
         D32_S8 => DXGI_FORMAT_R32_FLOAT_X8X24_TYPELESS,
         _ => return None,
     })
@@ -288,15 +319,21 @@ pub fn map_rasterizer(rasterizer: &pso::Rasterizer, multisample: bool) -> D3D12_
         DepthBias: bias.const_factor as INT,
         DepthBiasClamp: bias.clamp,
         SlopeScaledDepthBias: bias.slope_factor,
-        DepthClipEnable: !rasterizer.depth_clamping as _,
+        DepthClipEnable: rasterizer
+            .depth_clip_enable
+            .unwrap_or(!rasterizer.depth_clamping) as _,
         MultisampleEnable: if multisample { TRUE } else { FALSE },
         ForcedSampleCount: 0,         // TODO: currently not supported
         AntialiasedLineEnable: FALSE, // TODO: currently not supported
-        ConservativeRaster: if rasterizer.conservative {
-            // TODO: check support
-            D3D12_CONSERVATIVE_RASTERIZATION_MODE_ON
-        } else {
-            D3D12_CONSERVATIVE_RASTERIZATION_MODE_OFF
+        ConservativeRaster: match rasterizer.conservative {
+            Some(pso::ConservativeRasterization::Overestimate(_)) => {
+                D3D12_CONSERVATIVE_RASTERIZATION_MODE_ON
+            }
+            Some(pso::ConservativeRasterization::Underestimate) => {
+                error!("Conservative underestimation is not supported");
+                D3D12_CONSERVATIVE_RASTERIZATION_MODE_OFF
+            }
+            None => D3D12_CONSERVATIVE_RASTERIZATION_MODE_OFF,
         },
     }
 }
@@ -353,6 +390,7 @@ fn map_blend_op(
         ),
         Min => (D3D12_BLEND_OP_MIN, D3D12_BLEND_ZERO, D3D12_BLEND_ZERO),
         Max => (D3D12_BLEND_OP_MAX, D3D12_BLEND_ZERO, D3D12_BLEND_ZERO),
+        Advanced(op) => panic!("Advanced blend operation {:?} is not supported", op),
     }
 }
 
@@ -561,6 +599,9 @@ fn derive_immutable_image_states(access: image::Access) -> D3D12_RESOURCE_STATES
         state |= D3D12_RESOURCE_STATE_PIXEL_SHADER_RESOURCE
             | D3D12_RESOURCE_STATE_NON_PIXEL_SHADER_RESOURCE;
     }
+    if access.contains(image::Access::SHADING_RATE_READ) {
+        state |= ext::D3D12_RESOURCE_STATE_SHADING_RATE_SOURCE;
+    }
 
     state
 }
@@ -591,7 +632,13 @@ pub fn map_image_resource_state(
         // the same as COMMON (general state)
         image::Layout::Present => D3D12_RESOURCE_STATE_PRESENT,
         image::Layout::ColorAttachmentOptimal => D3D12_RESOURCE_STATE_RENDER_TARGET,
-        image::Layout::DepthStencilAttachmentOptimal => D3D12_RESOURCE_STATE_DEPTH_WRITE,
+        image::Layout::DepthStencilAttachmentOptimal
+        | image::Layout::DepthAttachmentOptimal
+        | image::Layout::StencilAttachmentOptimal => D3D12_RESOURCE_STATE_DEPTH_WRITE,
+        // Render pass transitions cover both planes at once, so the mixed layouts
+        // keep the whole resource writable. Depth tests still read from it.
+        image::Layout::DepthReadOnlyStencilAttachmentOptimal
+        | image::Layout::DepthAttachmentStencilReadOnlyOptimal => D3D12_RESOURCE_STATE_DEPTH_WRITE,
         // `TRANSFER_WRITE` requires special handling as it requires RESOLVE_DEST | COPY_DEST
         // but only 1 write-only allowed. We do the required translation before the commands.
         // We currently assume that `COPY_DEST` is more common state than out of renderpass resolves.
@@ -600,13 +647,33 @@ pub fn map_image_resource_state(
         image::Layout::TransferDstOptimal => D3D12_RESOURCE_STATE_COPY_DEST,
         image::Layout::TransferSrcOptimal => D3D12_RESOURCE_STATE_COPY_SOURCE,
         image::Layout::General => derive_mutable_image_states(access),
-        image::Layout::ShaderReadOnlyOptimal | image::Layout::DepthStencilReadOnlyOptimal => {
-            derive_immutable_image_states(access)
-        }
+        image::Layout::ShaderReadOnlyOptimal
+        | image::Layout::DepthStencilReadOnlyOptimal
+        | image::Layout::DepthReadOnlyOptimal
+        | image::Layout::StencilReadOnlyOptimal => derive_immutable_image_states(access),
         image::Layout::Undefined | image::Layout::Preinitialized => D3D12_RESOURCE_STATE_COMMON,
     }
 }
 
+/// The rates are encoded as `log2(width) << 2 | log2(height)`, like the texels
+/// of the shading rate images.
+pub fn map_shading_rate(rate: pso::ShadingRate) -> ext::D3D12_SHADING_RATE {
+    rate.width.trailing_zeros() << 2 | rate.height.trailing_zeros()
+}
+
+pub fn map_shading_rate_combiner(
+    combiner: pso::ShadingRateCombiner,
+) -> ext::D3D12_SHADING_RATE_COMBINER {
+    match combiner {
+        pso::ShadingRateCombiner::Keep => ext::D3D12_SHADING_RATE_COMBINER_PASSTHROUGH,
+        pso::ShadingRateCombiner::Replace => ext::D3D12_SHADING_RATE_COMBINER_OVERRIDE,
+        pso::ShadingRateCombiner::Min => ext::D3D12_SHADING_RATE_COMBINER_MIN,
+        pso::ShadingRateCombiner::Max => ext::D3D12_SHADING_RATE_COMBINER_MAX,
+        // The rates are added in the log2 space
+        pso::ShadingRateCombiner::Mul => ext::D3D12_SHADING_RATE_COMBINER_SUM,
+    }
+}
+
 pub fn map_shader_visibility(flags: pso::ShaderStageFlags) -> ShaderVisibility {
     use hal::pso::ShaderStageFlags as Ssf;
 
@@ -14,7 +14,11 @@ use winapi::{
     Interface,
 };
 
-use auxil::{spirv_cross_specialize_ast, ShaderStage};
+use auxil::{
+    spirv_cross_specialize_ast,
+    workers::{DetachedComputePipelineDesc, DetachedGraphicsPipelineDesc},
+    ShaderStage,
+};
 use hal::{
     buffer, device as d, format, format::Aspects, image, memory, memory::Requirements, pass,
     pool::CommandPoolCreateFlags, pso, pso::VertexInputRate, query, queue::QueueFamilyId,
@@ -22,7 +26,7 @@ use hal::{
 };
 
 use crate::{
-    command as cmd, conv, descriptors_cpu, pool::CommandPool, resource as r, root_constants,
+    command as cmd, conv, descriptors_cpu, ext, pool::CommandPool, resource as r, root_constants,
     root_constants::RootConstant, window::Swapchain, Backend as B, Device, MemoryGroup,
     MAX_VERTEX_BUFFERS, NUM_HEAP_PROPERTIES, QUEUE_FAMILIES,
 };
@@ -30,6 +34,9 @@ use native::{PipelineStateSubobject, Subobject};
 
 // Register space used for root constants.
 const ROOT_CONSTANT_SPACE: u32 = 0;
+// Number of DWORDs available for the root constants. Larger push constants
+// are spilled into root CBVs, keeping the root signature within its limit.
+const MAX_ROOT_CONSTANTS: u32 = 32;
 
 const MEM_TYPE_MASK: u32 = 0x7;
 const MEM_TYPE_SHIFT: u32 = 3;
@@ -41,10 +48,28 @@ const MEM_TYPE_TARGET_SHIFT: u32 = MEM_TYPE_SHIFT * MemoryGroup::TargetOnly as u
 
 pub const IDENTITY_MAPPING: UINT = 0x1688; // D3D12_DEFAULT_SHADER_4_COMPONENT_MAPPING
 
+/// Device handle and enabled features, moved to the pipeline creation threads.
+/// D3D12 devices are free-threaded.
+struct WorkerDevice {
+    raw: native::Device,
+    features: hal::Features,
+}
+
+unsafe impl Send for WorkerDevice {}
+
 fn wide_cstr(name: &str) -> Vec<u16> {
     name.encode_utf16().chain(iter::once(0)).collect()
 }
 
+/// Reads a null-terminated wide string, treating the null pointer as an empty string.
+unsafe fn from_wide_ptr(ptr: *const u16) -> String {
+    if ptr.is_null() {
+        return String::new();
+    }
+    let length = (0..).take_while(|&i| *ptr.add(i) != 0).count();
+    String::from_utf16_lossy(slice::from_raw_parts(ptr, length))
+}
+
 /// Emit error during shader module creation. Used if we don't expect an error
 /// but might panic due to an exception in SPIRV-Cross.
 fn gen_unexpected_error(err: SpirvErrorCode) -> pso::CreationError {
@@ -240,6 +265,269 @@ impl GraphicsPipelineStateSubobjectStream {
 }
 
 impl Device {
+    /// Builds a render pass, also used for the dynamic rendering of command buffers.
+    pub(crate) fn build_render_pass<'a, Ia, Is, Id>(
+        attachments: Ia,
+        subpasses: Is,
+        dependencies: Id,
+    ) -> r::RenderPass
+    where
+        Ia: Iterator<Item = pass::Attachment>,
+        Is: Iterator<Item = pass::SubpassDesc<'a>>,
+        Id: Iterator<Item = pass::SubpassDependency>,
+    {
+        #[derive(Copy, Clone, Debug, PartialEq)]
+        enum SubState {
+            New(d3d12::D3D12_RESOURCE_STATES),
+            // Color attachment which will be resolved at the end of the subpass
+            Resolve(d3d12::D3D12_RESOURCE_STATES),
+            Preserve,
+            Undefined,
+        }
+        /// Temporary information about every sub-pass
+        struct SubInfo<'a> {
+            desc: pass::SubpassDesc<'a>,
+            /// States before the render-pass (in self.start)
+            /// and after the render-pass (in self.end).
+            external_dependencies: Range<image::Access>,
+            /// Counts the number of dependencies that need to be resolved
+            /// before starting this subpass.
+            unresolved_dependencies: u16,
+        }
+        struct AttachmentInfo {
+            sub_states: Vec<SubState>,
+            last_state: d3d12::D3D12_RESOURCE_STATES,
+            barrier_start_index: usize,
+        }
+
+        let attachments = attachments.collect::<SmallVec<[_; 5]>>();
+        let mut sub_infos = subpasses
+            .map(|desc| SubInfo {
+                desc: desc.clone(),
+                external_dependencies: image::Access::empty()..image::Access::empty(),
+                unresolved_dependencies: 0,
+            })
+            .collect::<SmallVec<[_; 1]>>();
+        let dependencies = dependencies.collect::<SmallVec<[_; 2]>>();
+
+        let mut att_infos = (0..attachments.len())
+            .map(|_| AttachmentInfo {
+                sub_states: vec![SubState::Undefined; sub_infos.len()],
+                last_state: d3d12::D3D12_RESOURCE_STATE_COMMON, // is to be overwritten
+                barrier_start_index: 0,
+            })
+            .collect::<SmallVec<[_; 5]>>();
+
+        for dep in &dependencies {
+            match dep.passes {
+                Range {
+                    start: None,
+                    end: None,
+                } => {
+                    error!("Unexpected external-external dependency!");
+                }
+                Range {
+                    start: None,
+                    end: Some(sid),
+                } => {
+                    sub_infos[sid as usize].external_dependencies.start |= dep.accesses.start;
+                }
+                Range {
+                    start: Some(sid),
+                    end: None,
+                } => {
+                    sub_infos[sid as usize].external_dependencies.end |= dep.accesses.end;
+                }
+                Range {
+                    start: Some(from_sid),
+                    end: Some(sid),
+                } => {
+                    //Note: self-dependencies are ignored
+                    if from_sid != sid {
+                        sub_infos[sid as usize].unresolved_dependencies += 1;
+                    }
+                }
+            }
+        }
+
+        // Fill out subpass known layouts
+        for (sid, sub_info) in sub_infos.iter().enumerate() {
+            let sub = &sub_info.desc;
+            for (i, &(id, _layout)) in sub.colors.iter().enumerate() {
+                let target_state = d3d12::D3D12_RESOURCE_STATE_RENDER_TARGET;
+                let state = match sub.resolves.get(i) {
+                    Some(_) => SubState::Resolve(target_state),
+                    None => SubState::New(target_state),
+                };
+                let old = mem::replace(&mut att_infos[id].sub_states[sid], state);
+                debug_assert_eq!(SubState::Undefined, old);
+            }
+            for &(id, layout) in sub.depth_stencil {
+                let state = SubState::New(match layout {
+                    image::Layout::DepthStencilAttachmentOptimal
+                    | image::Layout::DepthAttachmentOptimal
+                    | image::Layout::StencilAttachmentOptimal
+                    | image::Layout::DepthReadOnlyStencilAttachmentOptimal
+                    | image::Layout::DepthAttachmentStencilReadOnlyOptimal => {
+                        d3d12::D3D12_RESOURCE_STATE_DEPTH_WRITE
+                    }
+                    image::Layout::DepthStencilReadOnlyOptimal
+                    | image::Layout::DepthReadOnlyOptimal
+                    | image::Layout::StencilReadOnlyOptimal => {
+                        d3d12::D3D12_RESOURCE_STATE_DEPTH_READ
+                    }
+                    image::Layout::General => d3d12::D3D12_RESOURCE_STATE_DEPTH_WRITE,
+                    _ => {
+                        error!("Unexpected depth/stencil layout: {:?}", layout);
+                        d3d12::D3D12_RESOURCE_STATE_COMMON
+                    }
+                });
+                let old = mem::replace(&mut att_infos[id].sub_states[sid], state);
+                debug_assert_eq!(SubState::Undefined, old);
+            }
+            for &(id, _layout) in sub.inputs {
+                let state = SubState::New(d3d12::D3D12_RESOURCE_STATE_PIXEL_SHADER_RESOURCE);
+                let old = mem::replace(&mut att_infos[id].sub_states[sid], state);
+                debug_assert_eq!(SubState::Undefined, old);
+            }
+            for &(id, _layout) in sub.resolves {
+                let state = SubState::New(d3d12::D3D12_RESOURCE_STATE_RESOLVE_DEST);
+                let old = mem::replace(&mut att_infos[id].sub_states[sid], state);
+                debug_assert_eq!(SubState::Undefined, old);
+            }
+            for &(id, _layout) in sub.shading_rate {
+                let state = SubState::New(ext::D3D12_RESOURCE_STATE_SHADING_RATE_SOURCE);
+                let old = mem::replace(&mut att_infos[id].sub_states[sid], state);
+                debug_assert_eq!(SubState::Undefined, old);
+            }
+            for &id in sub.preserves {
+                let old = mem::replace(&mut att_infos[id].sub_states[sid], SubState::Preserve);
+                debug_assert_eq!(SubState::Undefined, old);
+            }
+        }
+
+        let mut rp = r::RenderPass {
+            attachments: attachments.iter().cloned().collect(),
+            subpasses: Vec::new(),
+            post_barriers: Vec::new(),
+            raw_name: Vec::new(),
+        };
+
+        while let Some(sid) = sub_infos
+            .iter()
+            .position(|si| si.unresolved_dependencies == 0)
+        {
+            for dep in &dependencies {
+                if dep.passes.start != dep.passes.end
+                    && dep.passes.start == Some(sid as pass::SubpassId)
+                {
+                    if let Some(other) = dep.passes.end {
+                        sub_infos[other as usize].unresolved_dependencies -= 1;
+                    }
+                }
+            }
+
+            let si = &mut sub_infos[sid];
+            si.unresolved_dependencies = !0; // mark as done
+
+            // Subpass barriers
+            let mut pre_barriers = Vec::new();
+            let mut post_barriers = Vec::new();
+            for (att_id, (ai, att)) in att_infos.iter_mut().zip(attachments.iter()).enumerate() {
+                // Attachment wasn't used before, figure out the initial state
+                if ai.barrier_start_index == 0 {
+                    //Note: the external dependencies are provided for all attachments that are
+                    // first used in this sub-pass, so they may contain more states than we expect
+                    // for this particular attachment.
+                    ai.last_state = conv::map_image_resource_state(
+                        si.external_dependencies.start,
+                        att.layouts.start,
+                    );
+                }
+                // Barrier from previous subpass to current or following subpasses.
+                match ai.sub_states[sid] {
+                    SubState::Preserve => {
+                        ai.barrier_start_index = rp.subpasses.len() + 1;
+                    }
+                    SubState::New(state) if state != ai.last_state => {
+                        let barrier = r::BarrierDesc::new(att_id, ai.last_state..state);
+                        match rp.subpasses.get_mut(ai.barrier_start_index) {
+                            Some(past_subpass) => {
+                                let split = barrier.split();
+                                past_subpass.pre_barriers.push(split.start);
+                                pre_barriers.push(split.end);
+                            }
+                            None => pre_barriers.push(barrier),
+                        }
+                        ai.last_state = state;
+                        ai.barrier_start_index = rp.subpasses.len() + 1;
+                    }
+                    SubState::Resolve(state) => {
+                        // 1. Standard pre barrier to update state from previous pass into desired substate.
+                        if state != ai.last_state {
+                            let barrier = r::BarrierDesc::new(att_id, ai.last_state..state);
+                            match rp.subpasses.get_mut(ai.barrier_start_index) {
+                                Some(past_subpass) => {
+                                    let split = barrier.split();
+                                    past_subpass.pre_barriers.push(split.start);
+                                    pre_barriers.push(split.end);
+                                }
+                                None => pre_barriers.push(barrier),
+                            }
+                        }
+
+                        // 2. Post Barrier at the end of the subpass into RESOLVE_SOURCE.
+                        let resolve_state = d3d12::D3D12_RESOURCE_STATE_RESOLVE_SOURCE;
+                        let barrier = r::BarrierDesc::new(att_id, state..resolve_state);
+                        post_barriers.push(barrier);
+
+                        ai.last_state = resolve_state;
+                        ai.barrier_start_index = rp.subpasses.len() + 1;
+                    }
+                    SubState::Undefined | SubState::New(_) => {}
+                };
+            }
+
+            rp.subpasses.push(r::SubpassDesc {
+                color_attachments: si.desc.colors.iter().cloned().collect(),
+                depth_stencil_attachment: si.desc.depth_stencil.cloned(),
+                input_attachments: si.desc.inputs.iter().cloned().collect(),
+                resolve_attachments: si.desc.resolves.iter().cloned().collect(),
+                shading_rate_attachment: si.desc.shading_rate.cloned(),
+                pre_barriers,
+                post_barriers,
+            });
+        }
+        // if this fails, our graph has cycles
+        assert_eq!(rp.subpasses.len(), sub_infos.len());
+        assert!(sub_infos.iter().all(|si| si.unresolved_dependencies == !0));
+
+        // take care of the post-pass transitions at the end of the renderpass.
+        for (att_id, (ai, att)) in att_infos.iter().zip(attachments.iter()).enumerate() {
+            let state_dst = if ai.barrier_start_index == 0 {
+                // attachment wasn't used in any sub-pass?
+                continue;
+            } else {
+                let si = &sub_infos[ai.barrier_start_index - 1];
+                conv::map_image_resource_state(si.external_dependencies.end, att.layouts.end)
+            };
+            if state_dst == ai.last_state {
+                continue;
+            }
+            let barrier = r::BarrierDesc::new(att_id, ai.last_state..state_dst);
+            match rp.subpasses.get_mut(ai.barrier_start_index) {
+                Some(past_subpass) => {
+                    let split = barrier.split();
+                    past_subpass.pre_barriers.push(split.start);
+                    rp.post_barriers.push(split.end);
+                }
+                None => rp.post_barriers.push(barrier),
+            }
+        }
+
+        rp
+    }
+
     fn parse_spirv(raw_data: &[u32]) -> Result<spirv::Ast<hlsl::Target>, pso::CreationError> {
         let module = spirv::Module::from_words(raw_data);
 
@@ -1056,8 +1344,9 @@ impl Device {
         //TODO: proper error type?
         let non_srgb_format = conv::map_format_nosrgb(config.format).unwrap();
 
+        // Allow tearing whenever possible, so that the present mode can be switched to `IMMEDIATE`
         let mut flags = dxgi::DXGI_SWAP_CHAIN_FLAG_FRAME_LATENCY_WAITABLE_OBJECT;
-        if config.present_mode.contains(w::PresentMode::IMMEDIATE) {
+        if unsafe { crate::window::supports_tearing(&factory) } {
             flags |= dxgi::DXGI_SWAP_CHAIN_FLAG_ALLOW_TEARING;
         }
 
@@ -1144,481 +1433,682 @@ impl Device {
             acquired_count: 0,
         }
     }
-}
 
-impl d::Device<B> for Device {
-    unsafe fn allocate_memory(
-        &self,
-        mem_type: hal::MemoryTypeId,
-        size: u64,
-    ) -> Result<r::Memory, d::AllocationError> {
-        let mem_type = mem_type.0;
-        let mem_base_id = mem_type % NUM_HEAP_PROPERTIES;
-        let heap_property = &self.heap_properties[mem_base_id];
-
-        let properties = d3d12::D3D12_HEAP_PROPERTIES {
-            Type: d3d12::D3D12_HEAP_TYPE_CUSTOM,
-            CPUPageProperty: heap_property.page_property,
-            MemoryPoolPreference: heap_property.memory_pool,
-            CreationNodeMask: 0,
-            VisibleNodeMask: 0,
-        };
+    /// Create a graphics pipeline, only with the device handle and the enabled features
+    /// so that it can run on the pipeline creation threads.
+    unsafe fn create_graphics_pipeline_impl<'a>(
+        raw: native::Device,
+        features: &hal::Features,
+        desc: &pso::GraphicsPipelineDesc<'a, B>,
+    ) -> Result<r::GraphicsPipeline, pso::CreationError> {
+        enum ShaderBc {
+            Owned(native::Blob),
+            Borrowed(native::Blob),
+            None,
+        }
+        impl ShaderBc {
+            pub fn shader(&self) -> native::Shader {
+                match *self {
+                    ShaderBc::Owned(ref bc) | ShaderBc::Borrowed(ref bc) => {
+                        native::Shader::from_blob(*bc)
+                    }
+                    ShaderBc::None => native::Shader::null(),
+                }
+            }
+        }
 
-        // Exposed memory types are grouped according to their capabilities.
-        // See `MemoryGroup` for more details.
-        let mem_group = mem_type / NUM_HEAP_PROPERTIES;
+        let build_shader = |stage: ShaderStage,
+                            source: Option<&pso::EntryPoint<'a, B>>|
+         -> Result<ShaderBc, pso::CreationError> {
+            let source = match source {
+                Some(src) => src,
+                None => return Ok(ShaderBc::None),
+            };
 
-        let desc = d3d12::D3D12_HEAP_DESC {
-            SizeInBytes: size,
-            Properties: properties,
-            Alignment: d3d12::D3D12_DEFAULT_MSAA_RESOURCE_PLACEMENT_ALIGNMENT as _, // TODO: not always..?
-            Flags: match mem_group {
-                0 => d3d12::D3D12_HEAP_FLAG_ALLOW_ALL_BUFFERS_AND_TEXTURES,
-                1 => d3d12::D3D12_HEAP_FLAG_ALLOW_ONLY_BUFFERS,
-                2 => d3d12::D3D12_HEAP_FLAG_ALLOW_ONLY_NON_RT_DS_TEXTURES,
-                3 => d3d12::D3D12_HEAP_FLAG_ALLOW_ONLY_RT_DS_TEXTURES,
-                _ => unreachable!(),
-            },
+            let (shader, owned) = Self::extract_entry_point(stage, source, desc.layout, features)?;
+            Ok(if owned {
+                ShaderBc::Owned(shader)
+            } else {
+                ShaderBc::Borrowed(shader)
+            })
         };
 
-        let mut heap = native::Heap::null();
-        let hr = self
-            .raw
-            .clone()
-            .CreateHeap(&desc, &d3d12::ID3D12Heap::uuidof(), heap.mut_void());
-        if hr != winerror::S_OK {
-            if hr != winerror::E_OUTOFMEMORY {
-                error!("Error in CreateHeap: 0x{:X}", hr);
-            }
-            return Err(d::OutOfMemory::Device.into());
-        }
-
-        // The first memory heap of each group corresponds to the default heap, which is can never
-        // be mapped.
-        // Devices supporting heap tier 1 can only created buffers on mem group 1 (ALLOW_ONLY_BUFFERS).
-        // Devices supporting heap tier 2 always expose only mem group 0 and don't have any further restrictions.
-        let is_mapable = mem_base_id != 0
-            && (mem_group == MemoryGroup::Universal as _
-                || mem_group == MemoryGroup::BufferOnly as _);
+        let vertex_buffers: Vec<pso::VertexBufferDesc> = Vec::new();
+        let attributes: Vec<pso::AttributeDesc> = Vec::new();
+        let mesh_input_assembler = pso::InputAssemblerDesc::new(pso::Primitive::TriangleList);
+        let (vertex_buffers, attributes, input_assembler, vs, gs, hs, ds, _, _) =
+            match desc.primitive_assembler {
+                pso::PrimitiveAssemblerDesc::Vertex {
+                    buffers,
+                    attributes,
+                    ref input_assembler,
+                    ref vertex,
+                    ref tessellation,
+                    ref geometry,
+                } => {
+                    let (hs, ds) = if let Some(ts) = tessellation {
+                        (Some(&ts.0), Some(&ts.1))
+                    } else {
+                        (None, None)
+                    };
 
-        // Create a buffer resource covering the whole memory slice to be able to map the whole memory.
-        let resource = if is_mapable {
-            let mut resource = native::Resource::null();
-            let desc = d3d12::D3D12_RESOURCE_DESC {
-                Dimension: d3d12::D3D12_RESOURCE_DIMENSION_BUFFER,
-                Alignment: 0,
-                Width: size,
-                Height: 1,
-                DepthOrArraySize: 1,
-                MipLevels: 1,
-                Format: dxgiformat::DXGI_FORMAT_UNKNOWN,
-                SampleDesc: dxgitype::DXGI_SAMPLE_DESC {
-                    Count: 1,
-                    Quality: 0,
-                },
-                Layout: d3d12::D3D12_TEXTURE_LAYOUT_ROW_MAJOR,
-                Flags: d3d12::D3D12_RESOURCE_FLAG_NONE,
+                    (
+                        buffers,
+                        attributes,
+                        input_assembler,
+                        Some(vertex),
+                        geometry.as_ref(),
+                        hs,
+                        ds,
+                        None,
+                        None,
+                    )
+                }
+                pso::PrimitiveAssemblerDesc::Mesh { ref task, ref mesh } => (
+                    &vertex_buffers[..],
+                    &attributes[..],
+                    &mesh_input_assembler,
+                    None,
+                    None,
+                    None,
+                    None,
+                    task.as_ref(),
+                    Some(mesh),
+                ),
             };
 
-            assert_eq!(
-                winerror::S_OK,
-                self.raw.clone().CreatePlacedResource(
-                    heap.as_mut_ptr(),
-                    0,
-                    &desc,
-                    d3d12::D3D12_RESOURCE_STATE_COMMON,
-                    ptr::null(),
-                    &d3d12::ID3D12Resource::uuidof(),
-                    resource.mut_void(),
-                )
-            );
-
-            Some(resource)
+        let vertex_semantic_remapping = if let Some(ref vs) = vs {
+            // If we have a pre-compiled shader, we've lost the information we need to recover
+            // this information, so just pretend like this workaround never existed and hope
+            // for the best.
+            if let crate::resource::ShaderModule::Spirv(ref spv) = vs.module {
+                Some(Self::introspect_spirv_vertex_semantic_remapping(spv)?)
+            } else {
+                None
+            }
         } else {
             None
         };
 
-        Ok(r::Memory {
-            heap,
-            type_id: mem_type,
-            size,
-            resource,
-        })
-    }
-
-    unsafe fn create_command_pool(
-        &self,
-        family: QueueFamilyId,
-        create_flags: CommandPoolCreateFlags,
-    ) -> Result<CommandPool, d::OutOfMemory> {
-        let list_type = QUEUE_FAMILIES[family.0].native_type();
-        Ok(CommandPool::new(
-            self.raw,
-            list_type,
-            &self.shared,
-            create_flags,
-        ))
-    }
+        let vs = build_shader(ShaderStage::Vertex, vs)?;
+        let gs = build_shader(ShaderStage::Geometry, gs)?;
+        let hs = build_shader(ShaderStage::Domain, hs)?;
+        let ds = build_shader(ShaderStage::Hull, ds)?;
+        let ps = build_shader(ShaderStage::Fragment, desc.fragment.as_ref())?;
 
-    unsafe fn destroy_command_pool(&self, _pool: CommandPool) {}
+        // Rebind vertex buffers, see native.rs for more details.
+        let mut vertex_bindings = [None; MAX_VERTEX_BUFFERS];
+        let mut vertex_strides = [0; MAX_VERTEX_BUFFERS];
 
-    unsafe fn create_render_pass<'a, Ia, Is, Id>(
-        &self,
-        attachments: Ia,
-        subpasses: Is,
-        dependencies: Id,
-    ) -> Result<r::RenderPass, d::OutOfMemory>
-    where
-        Ia: Iterator<Item = pass::Attachment>,
-        Is: Iterator<Item = pass::SubpassDesc<'a>>,
-        Id: Iterator<Item = pass::SubpassDependency>,
-    {
-        #[derive(Copy, Clone, Debug, PartialEq)]
-        enum SubState {
-            New(d3d12::D3D12_RESOURCE_STATES),
-            // Color attachment which will be resolved at the end of the subpass
-            Resolve(d3d12::D3D12_RESOURCE_STATES),
-            Preserve,
-            Undefined,
-        }
-        /// Temporary information about every sub-pass
-        struct SubInfo<'a> {
-            desc: pass::SubpassDesc<'a>,
-            /// States before the render-pass (in self.start)
-            /// and after the render-pass (in self.end).
-            external_dependencies: Range<image::Access>,
-            /// Counts the number of dependencies that need to be resolved
-            /// before starting this subpass.
-            unresolved_dependencies: u16,
+        for buffer in vertex_buffers {
+            vertex_strides[buffer.binding as usize] = buffer.stride;
         }
-        struct AttachmentInfo {
-            sub_states: Vec<SubState>,
-            last_state: d3d12::D3D12_RESOURCE_STATES,
-            barrier_start_index: usize,
+        // Fill in identity mapping where we don't need to adjust anything.
+        for attrib in attributes {
+            let binding = attrib.binding as usize;
+            let stride = vertex_strides[attrib.binding as usize];
+            if attrib.element.offset < stride {
+                vertex_bindings[binding] = Some(r::VertexBinding {
+                    stride: vertex_strides[attrib.binding as usize],
+                    offset: 0,
+                    mapped_binding: binding,
+                });
+            }
         }
 
-        let attachments = attachments.collect::<SmallVec<[_; 5]>>();
-        let mut sub_infos = subpasses
-            .map(|desc| SubInfo {
-                desc: desc.clone(),
-                external_dependencies: image::Access::empty()..image::Access::empty(),
-                unresolved_dependencies: 0,
-            })
-            .collect::<SmallVec<[_; 1]>>();
-        let dependencies = dependencies.collect::<SmallVec<[_; 2]>>();
-
-        let mut att_infos = (0..attachments.len())
-            .map(|_| AttachmentInfo {
-                sub_states: vec![SubState::Undefined; sub_infos.len()],
-                last_state: d3d12::D3D12_RESOURCE_STATE_COMMON, // is to be overwritten
-                barrier_start_index: 0,
-            })
-            .collect::<SmallVec<[_; 5]>>();
-
-        for dep in &dependencies {
-            match dep.passes {
-                Range {
-                    start: None,
-                    end: None,
-                } => {
-                    error!("Unexpected external-external dependency!");
-                }
-                Range {
-                    start: None,
-                    end: Some(sid),
-                } => {
-                    sub_infos[sid as usize].external_dependencies.start |= dep.accesses.start;
-                }
-                Range {
-                    start: Some(sid),
-                    end: None,
-                } => {
-                    sub_infos[sid as usize].external_dependencies.end |= dep.accesses.end;
-                }
-                Range {
-                    start: Some(from_sid),
-                    end: Some(sid),
-                } => {
-                    //Note: self-dependencies are ignored
-                    if from_sid != sid {
-                        sub_infos[sid as usize].unresolved_dependencies += 1;
-                    }
-                }
-            }
-        }
-
-        // Fill out subpass known layouts
-        for (sid, sub_info) in sub_infos.iter().enumerate() {
-            let sub = &sub_info.desc;
-            for (i, &(id, _layout)) in sub.colors.iter().enumerate() {
-                let target_state = d3d12::D3D12_RESOURCE_STATE_RENDER_TARGET;
-                let state = match sub.resolves.get(i) {
-                    Some(_) => SubState::Resolve(target_state),
-                    None => SubState::New(target_state),
-                };
-                let old = mem::replace(&mut att_infos[id].sub_states[sid], state);
-                debug_assert_eq!(SubState::Undefined, old);
-            }
-            for &(id, layout) in sub.depth_stencil {
-                let state = SubState::New(match layout {
-                    image::Layout::DepthStencilAttachmentOptimal => {
-                        d3d12::D3D12_RESOURCE_STATE_DEPTH_WRITE
-                    }
-                    image::Layout::DepthStencilReadOnlyOptimal => {
-                        d3d12::D3D12_RESOURCE_STATE_DEPTH_READ
+        // See [`introspect_spirv_vertex_semantic_remapping`] for details of why this is needed.
+        let semantics: Vec<_> = attributes
+            .iter()
+            .map(|attrib| {
+                let semantics = vertex_semantic_remapping
+                    .as_ref()
+                    .and_then(|map| map.get(&attrib.location));
+                match semantics {
+                    Some(Some((major, minor))) => {
+                        let name = std::borrow::Cow::Owned(format!("TEXCOORD{}_\0", major));
+                        let location = *minor;
+                        (name, location)
                     }
-                    image::Layout::General => d3d12::D3D12_RESOURCE_STATE_DEPTH_WRITE,
                     _ => {
-                        error!("Unexpected depth/stencil layout: {:?}", layout);
-                        d3d12::D3D12_RESOURCE_STATE_COMMON
+                        let name = std::borrow::Cow::Borrowed("TEXCOORD\0");
+                        let location = attrib.location;
+                        (name, location)
                     }
-                });
-                let old = mem::replace(&mut att_infos[id].sub_states[sid], state);
-                debug_assert_eq!(SubState::Undefined, old);
-            }
-            for &(id, _layout) in sub.inputs {
-                let state = SubState::New(d3d12::D3D12_RESOURCE_STATE_PIXEL_SHADER_RESOURCE);
-                let old = mem::replace(&mut att_infos[id].sub_states[sid], state);
-                debug_assert_eq!(SubState::Undefined, old);
-            }
-            for &(id, _layout) in sub.resolves {
-                let state = SubState::New(d3d12::D3D12_RESOURCE_STATE_RESOLVE_DEST);
-                let old = mem::replace(&mut att_infos[id].sub_states[sid], state);
-                debug_assert_eq!(SubState::Undefined, old);
-            }
-            for &id in sub.preserves {
-                let old = mem::replace(&mut att_infos[id].sub_states[sid], SubState::Preserve);
-                debug_assert_eq!(SubState::Undefined, old);
-            }
-        }
-
-        let mut rp = r::RenderPass {
-            attachments: attachments.iter().cloned().collect(),
-            subpasses: Vec::new(),
-            post_barriers: Vec::new(),
-            raw_name: Vec::new(),
-        };
+                }
+            })
+            .collect();
 
-        while let Some(sid) = sub_infos
+        // Define input element descriptions
+        let input_element_descs = attributes
             .iter()
-            .position(|si| si.unresolved_dependencies == 0)
-        {
-            for dep in &dependencies {
-                if dep.passes.start != dep.passes.end
-                    && dep.passes.start == Some(sid as pass::SubpassId)
+            .zip(semantics.iter())
+            .filter_map(|(attrib, (semantic_name, semantic_index))| {
+                let buffer_desc = match vertex_buffers
+                    .iter()
+                    .find(|buffer_desc| buffer_desc.binding == attrib.binding)
                 {
-                    if let Some(other) = dep.passes.end {
-                        sub_infos[other as usize].unresolved_dependencies -= 1;
+                    Some(buffer_desc) => buffer_desc,
+                    None => {
+                        error!(
+                            "Couldn't find associated vertex buffer description {:?}",
+                            attrib.binding
+                        );
+                        return Some(Err(pso::CreationError::Other));
                     }
-                }
-            }
-
-            let si = &mut sub_infos[sid];
-            si.unresolved_dependencies = !0; // mark as done
+                };
 
-            // Subpass barriers
-            let mut pre_barriers = Vec::new();
-            let mut post_barriers = Vec::new();
-            for (att_id, (ai, att)) in att_infos.iter_mut().zip(attachments.iter()).enumerate() {
-                // Attachment wasn't used before, figure out the initial state
-                if ai.barrier_start_index == 0 {
-                    //Note: the external dependencies are provided for all attachments that are
-                    // first used in this sub-pass, so they may contain more states than we expect
-                    // for this particular attachment.
-                    ai.last_state = conv::map_image_resource_state(
-                        si.external_dependencies.start,
-                        att.layouts.start,
-                    );
-                }
-                // Barrier from previous subpass to current or following subpasses.
-                match ai.sub_states[sid] {
-                    SubState::Preserve => {
-                        ai.barrier_start_index = rp.subpasses.len() + 1;
+                let (slot_class, step_rate) = match buffer_desc.rate {
+                    VertexInputRate::Vertex => {
+                        (d3d12::D3D12_INPUT_CLASSIFICATION_PER_VERTEX_DATA, 0)
                     }
-                    SubState::New(state) if state != ai.last_state => {
-                        let barrier = r::BarrierDesc::new(att_id, ai.last_state..state);
-                        match rp.subpasses.get_mut(ai.barrier_start_index) {
-                            Some(past_subpass) => {
-                                let split = barrier.split();
-                                past_subpass.pre_barriers.push(split.start);
-                                pre_barriers.push(split.end);
-                            }
-                            None => pre_barriers.push(barrier),
-                        }
-                        ai.last_state = state;
-                        ai.barrier_start_index = rp.subpasses.len() + 1;
+                    VertexInputRate::Instance(divisor) => {
+                        (d3d12::D3D12_INPUT_CLASSIFICATION_PER_INSTANCE_DATA, divisor)
                     }
-                    SubState::Resolve(state) => {
-                        // 1. Standard pre barrier to update state from previous pass into desired substate.
-                        if state != ai.last_state {
-                            let barrier = r::BarrierDesc::new(att_id, ai.last_state..state);
-                            match rp.subpasses.get_mut(ai.barrier_start_index) {
-                                Some(past_subpass) => {
-                                    let split = barrier.split();
-                                    past_subpass.pre_barriers.push(split.start);
-                                    pre_barriers.push(split.end);
-                                }
-                                None => pre_barriers.push(barrier),
-                            }
-                        }
+                };
+                let format = attrib.element.format;
 
-                        // 2. Post Barrier at the end of the subpass into RESOLVE_SOURCE.
-                        let resolve_state = d3d12::D3D12_RESOURCE_STATE_RESOLVE_SOURCE;
-                        let barrier = r::BarrierDesc::new(att_id, state..resolve_state);
-                        post_barriers.push(barrier);
+                // Check if we need to add a new remapping in-case the offset is
+                // higher than the vertex stride.
+                // In this case we rebase the attribute to zero offset.
+                let binding = attrib.binding as usize;
+                let stride = vertex_strides[binding];
+                let offset = attrib.element.offset;
+                let (input_slot, offset) = if stride <= offset {
+                    // Number of input attributes may not exceed bindings, see limits.
+                    // We will always find at least one free binding.
+                    let mapping = vertex_bindings.iter().position(Option::is_none).unwrap();
+                    vertex_bindings[mapping] = Some(r::VertexBinding {
+                        stride: vertex_strides[binding],
+                        offset: offset,
+                        mapped_binding: binding,
+                    });
 
-                        ai.last_state = resolve_state;
-                        ai.barrier_start_index = rp.subpasses.len() + 1;
-                    }
-                    SubState::Undefined | SubState::New(_) => {}
+                    (mapping, 0)
+                } else {
+                    (binding, offset)
                 };
+
+                Some(Ok(d3d12::D3D12_INPUT_ELEMENT_DESC {
+                    SemanticName: semantic_name.as_ptr() as *const _, // Semantic name used by SPIRV-Cross
+                    SemanticIndex: *semantic_index,
+                    Format: match conv::map_format(format) {
+                        Some(fm) => fm,
+                        None => {
+                            error!("Unable to find DXGI format for {:?}", format);
+                            return Some(Err(pso::CreationError::Other));
+                        }
+                    },
+                    InputSlot: input_slot as _,
+                    AlignedByteOffset: offset,
+                    InputSlotClass: slot_class,
+                    InstanceDataStepRate: step_rate as _,
+                }))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // TODO: check maximum number of rtvs
+        // Get associated subpass information
+        let pass = {
+            let subpass = &desc.subpass;
+            match subpass.main_pass.subpasses.get(subpass.index as usize) {
+                Some(subpass) => subpass,
+                None => return Err(pso::CreationError::InvalidSubpass(subpass.index)),
             }
+        };
 
-            rp.subpasses.push(r::SubpassDesc {
-                color_attachments: si.desc.colors.iter().cloned().collect(),
-                depth_stencil_attachment: si.desc.depth_stencil.cloned(),
-                input_attachments: si.desc.inputs.iter().cloned().collect(),
-                resolve_attachments: si.desc.resolves.iter().cloned().collect(),
-                pre_barriers,
-                post_barriers,
-            });
+        // Get color attachment formats from subpass
+        let (rtvs, num_rtvs) = {
+            let mut rtvs = [dxgiformat::DXGI_FORMAT_UNKNOWN;
+                d3d12::D3D12_SIMULTANEOUS_RENDER_TARGET_COUNT as usize];
+            let mut num_rtvs = 0;
+            for (rtv, target) in rtvs.iter_mut().zip(pass.color_attachments.iter()) {
+                let format = desc.subpass.main_pass.attachments[target.0].format;
+                *rtv = format
+                    .and_then(conv::map_format)
+                    .unwrap_or(dxgiformat::DXGI_FORMAT_UNKNOWN);
+                num_rtvs += 1;
+            }
+            (rtvs, num_rtvs)
+        };
+
+        // Never exposed through `Features::SAMPLE_RATE_SHADING`, the shaders
+        // reading the sample index are the only ones running per sample
+        if desc
+            .multisampling
+            .as_ref()
+            .map_or(false, |ms| ms.sample_shading.is_some())
+        {
+            warn!("Sample shading is not supported, ignoring");
         }
-        // if this fails, our graph has cycles
-        assert_eq!(rp.subpasses.len(), sub_infos.len());
-        assert!(sub_infos.iter().all(|si| si.unresolved_dependencies == !0));
 
-        // take care of the post-pass transitions at the end of the renderpass.
-        for (att_id, (ai, att)) in att_infos.iter().zip(attachments.iter()).enumerate() {
-            let state_dst = if ai.barrier_start_index == 0 {
-                // attachment wasn't used in any sub-pass?
-                continue;
+        let sample_desc = dxgitype::DXGI_SAMPLE_DESC {
+            Count: match desc.multisampling {
+                Some(ref ms) => ms.rasterization_samples as _,
+                None => 1,
+            },
+            Quality: 0,
+        };
+
+        // Setup pipeline description
+        let pso_desc = d3d12::D3D12_GRAPHICS_PIPELINE_STATE_DESC {
+            pRootSignature: desc.layout.shared.signature.as_mut_ptr(),
+            VS: *vs.shader(),
+            PS: *ps.shader(),
+            GS: *gs.shader(),
+            DS: *ds.shader(),
+            HS: *hs.shader(),
+            StreamOutput: d3d12::D3D12_STREAM_OUTPUT_DESC {
+                pSODeclaration: ptr::null(),
+                NumEntries: 0,
+                pBufferStrides: ptr::null(),
+                NumStrides: 0,
+                RasterizedStream: 0,
+            },
+            BlendState: d3d12::D3D12_BLEND_DESC {
+                AlphaToCoverageEnable: desc.multisampling.as_ref().map_or(FALSE, |ms| {
+                    if ms.alpha_coverage {
+                        TRUE
+                    } else {
+                        FALSE
+                    }
+                }),
+                IndependentBlendEnable: TRUE,
+                RenderTarget: conv::map_render_targets(&desc.blender.targets),
+            },
+            SampleMask: match desc.multisampling {
+                Some(ref ms) => ms.sample_mask as u32,
+                None => UINT::max_value(),
+            },
+            RasterizerState: conv::map_rasterizer(&desc.rasterizer, desc.multisampling.is_some()),
+            DepthStencilState: conv::map_depth_stencil(&desc.depth_stencil),
+            InputLayout: d3d12::D3D12_INPUT_LAYOUT_DESC {
+                pInputElementDescs: if input_element_descs.is_empty() {
+                    ptr::null()
+                } else {
+                    input_element_descs.as_ptr()
+                },
+                NumElements: input_element_descs.len() as u32,
+            },
+            IBStripCutValue: match input_assembler.restart_index {
+                Some(hal::IndexType::U16) => d3d12::D3D12_INDEX_BUFFER_STRIP_CUT_VALUE_0xFFFF,
+                Some(hal::IndexType::U32) => d3d12::D3D12_INDEX_BUFFER_STRIP_CUT_VALUE_0xFFFFFFFF,
+                None => d3d12::D3D12_INDEX_BUFFER_STRIP_CUT_VALUE_DISABLED,
+            },
+            PrimitiveTopologyType: conv::map_topology_type(input_assembler.primitive),
+            NumRenderTargets: num_rtvs,
+            RTVFormats: rtvs,
+            DSVFormat: pass
+                .depth_stencil_attachment
+                .and_then(|att_ref| {
+                    desc.subpass.main_pass.attachments[att_ref.0]
+                        .format
+                        .and_then(|f| conv::map_format_dsv(f.base_format().0))
+                })
+                .unwrap_or(dxgiformat::DXGI_FORMAT_UNKNOWN),
+            SampleDesc: sample_desc,
+            NodeMask: 0,
+            CachedPSO: d3d12::D3D12_CACHED_PIPELINE_STATE {
+                pCachedBlob: ptr::null(),
+                CachedBlobSizeInBytes: 0,
+            },
+            Flags: d3d12::D3D12_PIPELINE_STATE_FLAG_NONE,
+        };
+        let topology = conv::map_topology(input_assembler);
+
+        // Create PSO
+        let mut pipeline = native::PipelineState::null();
+        let hr = if desc.depth_stencil.depth_bounds {
+            // The DepthBoundsTestEnable option isn't available in the original D3D12_GRAPHICS_PIPELINE_STATE_DESC struct.
+            // Instead, we must use the newer subobject stream method.
+            let (device2, hr) = raw.cast::<d3d12::ID3D12Device2>();
+            if winerror::SUCCEEDED(hr) {
+                let mut pss_stream = GraphicsPipelineStateSubobjectStream::new(&pso_desc, true);
+                let pss_desc = d3d12::D3D12_PIPELINE_STATE_STREAM_DESC {
+                    SizeInBytes: mem::size_of_val(&pss_stream),
+                    pPipelineStateSubobjectStream: &mut pss_stream as *mut _ as _,
+                };
+                device2.CreatePipelineState(
+                    &pss_desc,
+                    &d3d12::ID3D12PipelineState::uuidof(),
+                    pipeline.mut_void(),
+                )
             } else {
-                let si = &sub_infos[ai.barrier_start_index - 1];
-                conv::map_image_resource_state(si.external_dependencies.end, att.layouts.end)
-            };
-            if state_dst == ai.last_state {
-                continue;
-            }
-            let barrier = r::BarrierDesc::new(att_id, ai.last_state..state_dst);
-            match rp.subpasses.get_mut(ai.barrier_start_index) {
-                Some(past_subpass) => {
-                    let split = barrier.split();
-                    past_subpass.pre_barriers.push(split.start);
-                    rp.post_barriers.push(split.end);
-                }
-                None => rp.post_barriers.push(barrier),
+                hr
             }
-        }
-
-        Ok(rp)
-    }
+        } else {
+            raw.clone().CreateGraphicsPipelineState(
+                &pso_desc,
+                &d3d12::ID3D12PipelineState::uuidof(),
+                pipeline.mut_void(),
+            )
+        };
 
-    unsafe fn create_pipeline_layout<'a, Is, Ic>(
-        &self,
-        sets: Is,
-        push_constant_ranges: Ic,
-    ) -> Result<r::PipelineLayout, d::OutOfMemory>
-    where
-        Is: Iterator<Item = &'a r::DescriptorSetLayout>,
-        Ic: Iterator<Item = (pso::ShaderStageFlags, Range<u32>)>,
-    {
-        // Pipeline layouts are implemented as RootSignature for D3D12.
-        //
-        // Push Constants are implemented as root constants.
-        //
-        // Each descriptor set layout will be one table entry of the root signature.
-        // We have the additional restriction that SRV/CBV/UAV and samplers need to be
-        // separated, so each set layout will actually occupy up to 2 entries!
-        // SRV/CBV/UAV tables are added to the signature first, then Sampler tables,
-        // and finally dynamic uniform descriptors.
-        //
-        // Dynamic uniform buffers are implemented as root descriptors.
-        // This allows to handle the dynamic offsets properly, which would not be feasible
-        // with a combination of root constant and descriptor table.
-        //
-        // Root signature layout:
-        //     Root Constants: Register: Offest/4, Space: 0
-        //     ...
-        // DescriptorTable0: Space: 1 (SrvCbvUav)
-        // DescriptorTable0: Space: 1 (Sampler)
-        // Root Descriptors 0
-        // DescriptorTable1: Space: 2 (SrvCbvUav)
-        // Root Descriptors 1
-        //     ...
+        let destroy_shader = |shader: ShaderBc| {
+            if let ShaderBc::Owned(bc) = shader {
+                bc.destroy();
+            }
+        };
 
-        let sets = sets.collect::<Vec<_>>();
+        destroy_shader(vs);
+        destroy_shader(ps);
+        destroy_shader(gs);
+        destroy_shader(hs);
+        destroy_shader(ds);
 
-        let mut root_offset = 0u32;
-        let root_constants = root_constants::split(push_constant_ranges)
-            .iter()
-            .map(|constant| {
-                assert!(constant.range.start <= constant.range.end);
-                root_offset += constant.range.end - constant.range.start;
+        if winerror::SUCCEEDED(hr) {
+            let mut baked_states = desc.baked_states.clone();
+            if !desc.depth_stencil.depth_bounds {
+                baked_states.depth_bounds = None;
+            }
+            if let Some(name) = desc.label {
+                let cwstr = wide_cstr(name);
+                pipeline.SetName(cwstr.as_ptr());
+            }
 
-                RootConstant {
-                    stages: constant.stages,
-                    range: constant.range.start..constant.range.end,
-                }
+            Ok(r::GraphicsPipeline {
+                raw: pipeline,
+                shared: Arc::clone(&desc.layout.shared),
+                topology,
+                vertex_bindings,
+                baked_states,
             })
-            .collect::<Vec<_>>();
+        } else {
+            error!("Failed to build shader: {:x}", hr);
+            Err(pso::CreationError::Other)
+        }
+    }
 
-        info!(
-            "Creating a pipeline layout with {} sets and {} root constants",
-            sets.len(),
-            root_constants.len()
-        );
+    /// Create a compute pipeline, only with the device handle and the enabled features
+    /// so that it can run on the pipeline creation threads.
+    unsafe fn create_compute_pipeline_impl<'a>(
+        raw: native::Device,
+        features: &hal::Features,
+        desc: &pso::ComputePipelineDesc<'a, B>,
+    ) -> Result<r::ComputePipeline, pso::CreationError> {
+        let (cs, cs_destroy) =
+            Self::extract_entry_point(ShaderStage::Compute, &desc.shader, desc.layout, features)?;
 
-        // Number of elements in the root signature.
-        // Guarantees that no re-allocation is done, and our pointers are valid
-        let mut parameters = Vec::with_capacity(root_constants.len() + sets.len() * 2);
-        let mut parameter_offsets = Vec::with_capacity(parameters.capacity());
+        let (pipeline, hr) = raw.create_compute_pipeline_state(
+            desc.layout.shared.signature,
+            native::Shader::from_blob(cs),
+            0,
+            native::CachedPSO::null(),
+            native::PipelineStateFlags::empty(),
+        );
 
-        // Convert root signature descriptions into root signature parameters.
-        for root_constant in root_constants.iter() {
-            debug!(
-                "\tRoot constant set={} range {:?}",
-                ROOT_CONSTANT_SPACE, root_constant.range
-            );
-            parameter_offsets.push(root_constant.range.start);
-            parameters.push(native::RootParameter::constants(
-                conv::map_shader_visibility(root_constant.stages),
-                native::Binding {
-                    register: root_constant.range.start as _,
-                    space: ROOT_CONSTANT_SPACE,
-                },
-                (root_constant.range.end - root_constant.range.start) as _,
-            ));
+        if cs_destroy {
+            cs.destroy();
         }
 
-        // Offest of `spaceN` for descriptor tables. Root constants will be in
-        // `space0`.
-        // This has to match `patch_spirv_resources` logic.
-        let root_space_offset = if !root_constants.is_empty() { 1 } else { 0 };
+        if winerror::SUCCEEDED(hr) {
+            if let Some(name) = desc.label {
+                let cwstr = wide_cstr(name);
+                pipeline.SetName(cwstr.as_ptr());
+            }
 
-        // Collect the whole number of bindings we will create upfront.
-        // It allows us to preallocate enough storage to avoid reallocation,
-        // which could cause invalid pointers.
-        let total = sets
-            .iter()
-            .map(|desc_set| {
-                let mut sum = 0;
-                for binding in desc_set.bindings.iter() {
-                    let content = r::DescriptorContent::from(binding.ty);
-                    if !content.is_dynamic() {
-                        sum += content.bits().count_ones() as usize;
-                    }
-                }
-                sum
+            Ok(r::ComputePipeline {
+                raw: pipeline,
+                shared: Arc::clone(&desc.layout.shared),
             })
-            .sum();
-        let mut ranges = Vec::with_capacity(total);
+        } else {
+            error!("Failed to build shader: {:x}", hr);
+            Err(pso::CreationError::Other)
+        }
+    }
+}
 
-        let elements = sets
-            .iter()
-            .enumerate()
-            .map(|(i, set)| {
-                let space = (root_space_offset + i) as u32;
-                let mut table_type = r::SetTableTypes::empty();
+impl d::Device<B> for Device {
+    unsafe fn allocate_memory(
+        &self,
+        mem_type: hal::MemoryTypeId,
+        size: u64,
+    ) -> Result<r::Memory, d::AllocationError> {
+        let mem_type = mem_type.0;
+        let mem_base_id = mem_type % NUM_HEAP_PROPERTIES;
+        let heap_property = &self.heap_properties[mem_base_id];
+
+        let properties = d3d12::D3D12_HEAP_PROPERTIES {
+            Type: d3d12::D3D12_HEAP_TYPE_CUSTOM,
+            CPUPageProperty: heap_property.page_property,
+            MemoryPoolPreference: heap_property.memory_pool,
+            CreationNodeMask: 0,
+            VisibleNodeMask: 0,
+        };
+
+        // Exposed memory types are grouped according to their capabilities.
+        // See `MemoryGroup` for more details.
+        let mem_group = mem_type / NUM_HEAP_PROPERTIES;
+
+        let desc = d3d12::D3D12_HEAP_DESC {
+            SizeInBytes: size,
+            Properties: properties,
+            Alignment: d3d12::D3D12_DEFAULT_MSAA_RESOURCE_PLACEMENT_ALIGNMENT as _, // TODO: not always..?
+            Flags: match mem_group {
+                0 => d3d12::D3D12_HEAP_FLAG_ALLOW_ALL_BUFFERS_AND_TEXTURES,
+                1 => d3d12::D3D12_HEAP_FLAG_ALLOW_ONLY_BUFFERS,
+                2 => d3d12::D3D12_HEAP_FLAG_ALLOW_ONLY_NON_RT_DS_TEXTURES,
+                3 => d3d12::D3D12_HEAP_FLAG_ALLOW_ONLY_RT_DS_TEXTURES,
+                _ => unreachable!(),
+            },
+        };
+
+        let mut heap = native::Heap::null();
+        let hr = self
+            .raw
+            .clone()
+            .CreateHeap(&desc, &d3d12::ID3D12Heap::uuidof(), heap.mut_void());
+        if hr != winerror::S_OK {
+            if hr != winerror::E_OUTOFMEMORY {
+                error!("Error in CreateHeap: 0x{:X}", hr);
+            }
+            return Err(d::OutOfMemory::Device.into());
+        }
+
+        // The first memory heap of each group corresponds to the default heap, which is can never
+        // be mapped.
+        // Devices supporting heap tier 1 can only created buffers on mem group 1 (ALLOW_ONLY_BUFFERS).
+        // Devices supporting heap tier 2 always expose only mem group 0 and don't have any further restrictions.
+        let is_mapable = mem_base_id != 0
+            && (mem_group == MemoryGroup::Universal as _
+                || mem_group == MemoryGroup::BufferOnly as _);
+
+        // Create a buffer resource covering the whole memory slice to be able to map the whole memory.
+        let resource = if is_mapable {
+            let mut resource = native::Resource::null();
+            let desc = d3d12::D3D12_RESOURCE_DESC {
+                Dimension: d3d12::D3D12_RESOURCE_DIMENSION_BUFFER,
+                Alignment: 0,
+                Width: size,
+                Height: 1,
+                DepthOrArraySize: 1,
+                MipLevels: 1,
+                Format: dxgiformat::DXGI_FORMAT_UNKNOWN,
+                SampleDesc: dxgitype::DXGI_SAMPLE_DESC {
+                    Count: 1,
+                    Quality: 0,
+                },
+                Layout: d3d12::D3D12_TEXTURE_LAYOUT_ROW_MAJOR,
+                Flags: d3d12::D3D12_RESOURCE_FLAG_NONE,
+            };
+
+            assert_eq!(
+                winerror::S_OK,
+                self.raw.clone().CreatePlacedResource(
+                    heap.as_mut_ptr(),
+                    0,
+                    &desc,
+                    d3d12::D3D12_RESOURCE_STATE_COMMON,
+                    ptr::null(),
+                    &d3d12::ID3D12Resource::uuidof(),
+                    resource.mut_void(),
+                )
+            );
+
+            Some(resource)
+        } else {
+            None
+        };
+
+        Ok(r::Memory {
+            heap,
+            type_id: mem_type,
+            size,
+            resource,
+        })
+    }
+
+    unsafe fn create_command_pool(
+        &self,
+        family: QueueFamilyId,
+        create_flags: CommandPoolCreateFlags,
+    ) -> Result<CommandPool, d::OutOfMemory> {
+        let list_type = QUEUE_FAMILIES[family.0].native_type();
+        Ok(CommandPool::new(
+            self.raw,
+            list_type,
+            &self.shared,
+            create_flags,
+        ))
+    }
+
+    unsafe fn destroy_command_pool(&self, _pool: CommandPool) {}
+
+    unsafe fn create_render_pass<'a, Ia, Is, Id>(
+        &self,
+        attachments: Ia,
+        subpasses: Is,
+        dependencies: Id,
+    ) -> Result<r::RenderPass, d::OutOfMemory>
+    where
+        Ia: Iterator<Item = pass::Attachment>,
+        Is: Iterator<Item = pass::SubpassDesc<'a>>,
+        Id: Iterator<Item = pass::SubpassDependency>,
+    {
+        let subpasses = subpasses.inspect(|subpass| {
+            assert_eq!(subpass.view_mask, 0, "DX12 doesn't support MULTIVIEW");
+        });
+        Ok(Self::build_render_pass(attachments, subpasses, dependencies))
+    }
+
+    unsafe fn create_pipeline_layout<'a, Is, Ic>(
+        &self,
+        sets: Is,
+        push_constant_ranges: Ic,
+    ) -> Result<r::PipelineLayout, d::OutOfMemory>
+    where
+        Is: Iterator<Item = &'a r::DescriptorSetLayout>,
+        Ic: Iterator<Item = (pso::ShaderStageFlags, Range<u32>)>,
+    {
+        // Pipeline layouts are implemented as RootSignature for D3D12.
+        //
+        // Push Constants are implemented as root constants, or root CBVs
+        // pointing to the upload memory if they don't fit the root signature.
+        //
+        // Each descriptor set layout will be one table entry of the root signature.
+        // We have the additional restriction that SRV/CBV/UAV and samplers need to be
+        // separated, so each set layout will actually occupy up to 2 entries!
+        // SRV/CBV/UAV tables are added to the signature first, then Sampler tables,
+        // and finally dynamic uniform descriptors.
+        //
+        // Dynamic uniform buffers are implemented as root descriptors.
+        // This allows to handle the dynamic offsets properly, which would not be feasible
+        // with a combination of root constant and descriptor table.
+        //
+        // Root signature layout:
+        //     Root Constants: Register: Offest/4, Space: 0
+        //     ...
+        // DescriptorTable0: Space: 1 (SrvCbvUav)
+        // DescriptorTable0: Space: 1 (Sampler)
+        // Root Descriptors 0
+        // DescriptorTable1: Space: 2 (SrvCbvUav)
+        // Root Descriptors 1
+        //     ...
+
+        let sets = sets.collect::<Vec<_>>();
+
+        let mut root_offset = 0u32;
+        let root_constants = root_constants::split(push_constant_ranges)
+            .iter()
+            .map(|constant| {
+                assert!(constant.range.start <= constant.range.end);
+                root_offset += constant.range.end - constant.range.start;
+
+                RootConstant {
+                    stages: constant.stages,
+                    range: constant.range.start..constant.range.end,
+                }
+            })
+            .collect::<Vec<_>>();
+        let spilled_constants = root_offset > MAX_ROOT_CONSTANTS;
+        if spilled_constants {
+            // root CBV costs 2 words
+            root_offset = 2 * root_constants.len() as u32;
+        }
+
+        info!(
+            "Creating a pipeline layout with {} sets and {} root constants",
+            sets.len(),
+            root_constants.len()
+        );
+
+        // Number of elements in the root signature.
+        // Guarantees that no re-allocation is done, and our pointers are valid
+        let mut parameters = Vec::with_capacity(root_constants.len() + sets.len() * 2);
+        let mut parameter_offsets = Vec::with_capacity(parameters.capacity());
+
+        // Convert root signature descriptions into root signature parameters.
+        for (i, root_constant) in root_constants.iter().enumerate() {
+            debug!(
+                "\tRoot constant set={} range {:?}",
+                ROOT_CONSTANT_SPACE, root_constant.range
+            );
+            let visibility = conv::map_shader_visibility(root_constant.stages);
+            let binding = native::Binding {
+                register: root_constant.range.start as _,
+                space: ROOT_CONSTANT_SPACE,
+            };
+            if spilled_constants {
+                parameter_offsets.push(2 * i as u32);
+                parameters.push(native::RootParameter::cbv_descriptor(visibility, binding));
+            } else {
+                parameter_offsets.push(root_constant.range.start);
+                parameters.push(native::RootParameter::constants(
+                    visibility,
+                    binding,
+                    (root_constant.range.end - root_constant.range.start) as _,
+                ));
+            }
+        }
+
+        // Offest of `spaceN` for descriptor tables. Root constants will be in
+        // `space0`.
+        // This has to match `patch_spirv_resources` logic.
+        let root_space_offset = if !root_constants.is_empty() { 1 } else { 0 };
+
+        // Collect the whole number of bindings we will create upfront.
+        // It allows us to preallocate enough storage to avoid reallocation,
+        // which could cause invalid pointers.
+        let total = sets
+            .iter()
+            .map(|desc_set| {
+                let mut sum = 0;
+                for binding in desc_set.bindings.iter() {
+                    let content = r::DescriptorContent::from(binding.ty);
+                    if !content.is_dynamic() {
+                        sum += content.bits().count_ones() as usize;
+                    }
+                }
+                sum
+            })
+            .sum();
+        let mut ranges = Vec::with_capacity(total);
+
+        let elements = sets
+            .iter()
+            .enumerate()
+            .map(|(i, set)| {
+                let space = (root_space_offset + i) as u32;
+                let mut table_type = r::SetTableTypes::empty();
                 let root_table_offset = root_offset;
 
                 //TODO: split between sampler and non-sampler tables
@@ -1706,465 +2196,131 @@ impl d::Device<B> for Device {
                         if content.contains(r::DescriptorContent::CBV) {
                             parameter_offsets.push(root_offset);
                             parameters
-                                .push(native::RootParameter::cbv_descriptor(visibility, binding));
-                            root_offset += 2; // root CBV costs 2 words
-                        }
-                        if content.contains(r::DescriptorContent::SRV) {
-                            parameter_offsets.push(root_offset);
-                            parameters
-                                .push(native::RootParameter::srv_descriptor(visibility, binding));
-                            root_offset += 2; // root SRV costs 2 words
-                        }
-                        if content.contains(r::DescriptorContent::UAV) {
-                            parameter_offsets.push(root_offset);
-                            parameters
-                                .push(native::RootParameter::uav_descriptor(visibility, binding));
-                            root_offset += 2; // root UAV costs 2 words
-                        }
-                    }
-                }
-
-                r::RootElement {
-                    table: r::RootTable {
-                        ty: table_type,
-                        offset: root_table_offset as _,
-                    },
-                    mutable_bindings,
-                }
-            })
-            .collect();
-
-        // Ensure that we didn't reallocate!
-        debug_assert_eq!(ranges.len(), total);
-        assert_eq!(parameters.len(), parameter_offsets.len());
-
-        // TODO: error handling
-        let (signature_raw, error) = match self.library.serialize_root_signature(
-            native::RootSignatureVersion::V1_0,
-            &parameters,
-            &[],
-            native::RootSignatureFlags::ALLOW_IA_INPUT_LAYOUT,
-        ) {
-            Ok((pair, hr)) if winerror::SUCCEEDED(hr) => pair,
-            Ok((_, hr)) => panic!("Can't serialize root signature: {:?}", hr),
-            Err(e) => panic!("Can't find serialization function: {:?}", e),
-        };
-
-        if !error.is_null() {
-            error!(
-                "Root signature serialization error: {:?}",
-                error.as_c_str().to_str().unwrap()
-            );
-            error.destroy();
-        }
-
-        // TODO: error handling
-        let (signature, _hr) = self.raw.create_root_signature(signature_raw, 0);
-        signature_raw.destroy();
-
-        Ok(r::PipelineLayout {
-            shared: Arc::new(r::PipelineShared {
-                signature,
-                constants: root_constants,
-                parameter_offsets,
-                total_slots: root_offset,
-            }),
-            elements,
-        })
-    }
-
-    unsafe fn create_pipeline_cache(&self, _data: Option<&[u8]>) -> Result<(), d::OutOfMemory> {
-        Ok(())
-    }
-
-    unsafe fn get_pipeline_cache_data(&self, _cache: &()) -> Result<Vec<u8>, d::OutOfMemory> {
-        //empty
-        Ok(Vec::new())
-    }
-
-    unsafe fn destroy_pipeline_cache(&self, _: ()) {
-        //empty
-    }
-
-    unsafe fn merge_pipeline_caches<'a, I>(&self, _: &mut (), _: I) -> Result<(), d::OutOfMemory>
-    where
-        I: Iterator<Item = &'a ()>,
-    {
-        //empty
-        Ok(())
-    }
-
-    unsafe fn create_graphics_pipeline<'a>(
-        &self,
-        desc: &pso::GraphicsPipelineDesc<'a, B>,
-        _cache: Option<&()>,
-    ) -> Result<r::GraphicsPipeline, pso::CreationError> {
-        enum ShaderBc {
-            Owned(native::Blob),
-            Borrowed(native::Blob),
-            None,
-        }
-        let features = &self.features;
-        impl ShaderBc {
-            pub fn shader(&self) -> native::Shader {
-                match *self {
-                    ShaderBc::Owned(ref bc) | ShaderBc::Borrowed(ref bc) => {
-                        native::Shader::from_blob(*bc)
-                    }
-                    ShaderBc::None => native::Shader::null(),
-                }
-            }
-        }
-
-        let build_shader = |stage: ShaderStage,
-                            source: Option<&pso::EntryPoint<'a, B>>|
-         -> Result<ShaderBc, pso::CreationError> {
-            let source = match source {
-                Some(src) => src,
-                None => return Ok(ShaderBc::None),
-            };
-
-            let (shader, owned) = Self::extract_entry_point(stage, source, desc.layout, features)?;
-            Ok(if owned {
-                ShaderBc::Owned(shader)
-            } else {
-                ShaderBc::Borrowed(shader)
-            })
-        };
-
-        let vertex_buffers: Vec<pso::VertexBufferDesc> = Vec::new();
-        let attributes: Vec<pso::AttributeDesc> = Vec::new();
-        let mesh_input_assembler = pso::InputAssemblerDesc::new(pso::Primitive::TriangleList);
-        let (vertex_buffers, attributes, input_assembler, vs, gs, hs, ds, _, _) =
-            match desc.primitive_assembler {
-                pso::PrimitiveAssemblerDesc::Vertex {
-                    buffers,
-                    attributes,
-                    ref input_assembler,
-                    ref vertex,
-                    ref tessellation,
-                    ref geometry,
-                } => {
-                    let (hs, ds) = if let Some(ts) = tessellation {
-                        (Some(&ts.0), Some(&ts.1))
-                    } else {
-                        (None, None)
-                    };
-
-                    (
-                        buffers,
-                        attributes,
-                        input_assembler,
-                        Some(vertex),
-                        geometry.as_ref(),
-                        hs,
-                        ds,
-                        None,
-                        None,
-                    )
-                }
-                pso::PrimitiveAssemblerDesc::Mesh { ref task, ref mesh } => (
-                    &vertex_buffers[..],
-                    &attributes[..],
-                    &mesh_input_assembler,
-                    None,
-                    None,
-                    None,
-                    None,
-                    task.as_ref(),
-                    Some(mesh),
-                ),
-            };
-
-        let vertex_semantic_remapping = if let Some(ref vs) = vs {
-            // If we have a pre-compiled shader, we've lost the information we need to recover
-            // this information, so just pretend like this workaround never existed and hope
-            // for the best.
-            if let crate::resource::ShaderModule::Spirv(ref spv) = vs.module {
-                Some(Self::introspect_spirv_vertex_semantic_remapping(spv)?)
-            } else {
-                None
-            }
-        } else {
-            None
-        };
-
-        let vs = build_shader(ShaderStage::Vertex, vs)?;
-        let gs = build_shader(ShaderStage::Geometry, gs)?;
-        let hs = build_shader(ShaderStage::Domain, hs)?;
-        let ds = build_shader(ShaderStage::Hull, ds)?;
-        let ps = build_shader(ShaderStage::Fragment, desc.fragment.as_ref())?;
-
-        // Rebind vertex buffers, see native.rs for more details.
-        let mut vertex_bindings = [None; MAX_VERTEX_BUFFERS];
-        let mut vertex_strides = [0; MAX_VERTEX_BUFFERS];
-
-        for buffer in vertex_buffers {
-            vertex_strides[buffer.binding as usize] = buffer.stride;
-        }
-        // Fill in identity mapping where we don't need to adjust anything.
-        for attrib in attributes {
-            let binding = attrib.binding as usize;
-            let stride = vertex_strides[attrib.binding as usize];
-            if attrib.element.offset < stride {
-                vertex_bindings[binding] = Some(r::VertexBinding {
-                    stride: vertex_strides[attrib.binding as usize],
-                    offset: 0,
-                    mapped_binding: binding,
-                });
-            }
-        }
-
-        // See [`introspect_spirv_vertex_semantic_remapping`] for details of why this is needed.
-        let semantics: Vec<_> = attributes
-            .iter()
-            .map(|attrib| {
-                let semantics = vertex_semantic_remapping
-                    .as_ref()
-                    .and_then(|map| map.get(&attrib.location));
-                match semantics {
-                    Some(Some((major, minor))) => {
-                        let name = std::borrow::Cow::Owned(format!("TEXCOORD{}_\0", major));
-                        let location = *minor;
-                        (name, location)
-                    }
-                    _ => {
-                        let name = std::borrow::Cow::Borrowed("TEXCOORD\0");
-                        let location = attrib.location;
-                        (name, location)
-                    }
-                }
-            })
-            .collect();
-
-        // Define input element descriptions
-        let input_element_descs = attributes
-            .iter()
-            .zip(semantics.iter())
-            .filter_map(|(attrib, (semantic_name, semantic_index))| {
-                let buffer_desc = match vertex_buffers
-                    .iter()
-                    .find(|buffer_desc| buffer_desc.binding == attrib.binding)
-                {
-                    Some(buffer_desc) => buffer_desc,
-                    None => {
-                        error!(
-                            "Couldn't find associated vertex buffer description {:?}",
-                            attrib.binding
-                        );
-                        return Some(Err(pso::CreationError::Other));
-                    }
-                };
-
-                let (slot_class, step_rate) = match buffer_desc.rate {
-                    VertexInputRate::Vertex => {
-                        (d3d12::D3D12_INPUT_CLASSIFICATION_PER_VERTEX_DATA, 0)
-                    }
-                    VertexInputRate::Instance(divisor) => {
-                        (d3d12::D3D12_INPUT_CLASSIFICATION_PER_INSTANCE_DATA, divisor)
-                    }
-                };
-                let format = attrib.element.format;
-
-                // Check if we need to add a new remapping in-case the offset is
-                // higher than the vertex stride.
-                // In this case we rebase the attribute to zero offset.
-                let binding = attrib.binding as usize;
-                let stride = vertex_strides[binding];
-                let offset = attrib.element.offset;
-                let (input_slot, offset) = if stride <= offset {
-                    // Number of input attributes may not exceed bindings, see limits.
-                    // We will always find at least one free binding.
-                    let mapping = vertex_bindings.iter().position(Option::is_none).unwrap();
-                    vertex_bindings[mapping] = Some(r::VertexBinding {
-                        stride: vertex_strides[binding],
-                        offset: offset,
-                        mapped_binding: binding,
-                    });
-
-                    (mapping, 0)
-                } else {
-                    (binding, offset)
-                };
-
-                Some(Ok(d3d12::D3D12_INPUT_ELEMENT_DESC {
-                    SemanticName: semantic_name.as_ptr() as *const _, // Semantic name used by SPIRV-Cross
-                    SemanticIndex: *semantic_index,
-                    Format: match conv::map_format(format) {
-                        Some(fm) => fm,
-                        None => {
-                            error!("Unable to find DXGI format for {:?}", format);
-                            return Some(Err(pso::CreationError::Other));
-                        }
-                    },
-                    InputSlot: input_slot as _,
-                    AlignedByteOffset: offset,
-                    InputSlotClass: slot_class,
-                    InstanceDataStepRate: step_rate as _,
-                }))
-            })
-            .collect::<Result<Vec<_>, _>>()?;
-
-        // TODO: check maximum number of rtvs
-        // Get associated subpass information
-        let pass = {
-            let subpass = &desc.subpass;
-            match subpass.main_pass.subpasses.get(subpass.index as usize) {
-                Some(subpass) => subpass,
-                None => return Err(pso::CreationError::InvalidSubpass(subpass.index)),
-            }
-        };
-
-        // Get color attachment formats from subpass
-        let (rtvs, num_rtvs) = {
-            let mut rtvs = [dxgiformat::DXGI_FORMAT_UNKNOWN;
-                d3d12::D3D12_SIMULTANEOUS_RENDER_TARGET_COUNT as usize];
-            let mut num_rtvs = 0;
-            for (rtv, target) in rtvs.iter_mut().zip(pass.color_attachments.iter()) {
-                let format = desc.subpass.main_pass.attachments[target.0].format;
-                *rtv = format
-                    .and_then(conv::map_format)
-                    .unwrap_or(dxgiformat::DXGI_FORMAT_UNKNOWN);
-                num_rtvs += 1;
-            }
-            (rtvs, num_rtvs)
-        };
-
-        let sample_desc = dxgitype::DXGI_SAMPLE_DESC {
-            Count: match desc.multisampling {
-                Some(ref ms) => ms.rasterization_samples as _,
-                None => 1,
-            },
-            Quality: 0,
-        };
-
-        // Setup pipeline description
-        let pso_desc = d3d12::D3D12_GRAPHICS_PIPELINE_STATE_DESC {
-            pRootSignature: desc.layout.shared.signature.as_mut_ptr(),
-            VS: *vs.shader(),
-            PS: *ps.shader(),
-            GS: *gs.shader(),
-            DS: *ds.shader(),
-            HS: *hs.shader(),
-            StreamOutput: d3d12::D3D12_STREAM_OUTPUT_DESC {
-                pSODeclaration: ptr::null(),
-                NumEntries: 0,
-                pBufferStrides: ptr::null(),
-                NumStrides: 0,
-                RasterizedStream: 0,
-            },
-            BlendState: d3d12::D3D12_BLEND_DESC {
-                AlphaToCoverageEnable: desc.multisampling.as_ref().map_or(FALSE, |ms| {
-                    if ms.alpha_coverage {
-                        TRUE
-                    } else {
-                        FALSE
-                    }
-                }),
-                IndependentBlendEnable: TRUE,
-                RenderTarget: conv::map_render_targets(&desc.blender.targets),
-            },
-            SampleMask: match desc.multisampling {
-                Some(ref ms) => ms.sample_mask as u32,
-                None => UINT::max_value(),
-            },
-            RasterizerState: conv::map_rasterizer(&desc.rasterizer, desc.multisampling.is_some()),
-            DepthStencilState: conv::map_depth_stencil(&desc.depth_stencil),
-            InputLayout: d3d12::D3D12_INPUT_LAYOUT_DESC {
-                pInputElementDescs: if input_element_descs.is_empty() {
-                    ptr::null()
-                } else {
-                    input_element_descs.as_ptr()
-                },
-                NumElements: input_element_descs.len() as u32,
-            },
-            IBStripCutValue: match input_assembler.restart_index {
-                Some(hal::IndexType::U16) => d3d12::D3D12_INDEX_BUFFER_STRIP_CUT_VALUE_0xFFFF,
-                Some(hal::IndexType::U32) => d3d12::D3D12_INDEX_BUFFER_STRIP_CUT_VALUE_0xFFFFFFFF,
-                None => d3d12::D3D12_INDEX_BUFFER_STRIP_CUT_VALUE_DISABLED,
-            },
-            PrimitiveTopologyType: conv::map_topology_type(input_assembler.primitive),
-            NumRenderTargets: num_rtvs,
-            RTVFormats: rtvs,
-            DSVFormat: pass
-                .depth_stencil_attachment
-                .and_then(|att_ref| {
-                    desc.subpass.main_pass.attachments[att_ref.0]
-                        .format
-                        .and_then(|f| conv::map_format_dsv(f.base_format().0))
-                })
-                .unwrap_or(dxgiformat::DXGI_FORMAT_UNKNOWN),
-            SampleDesc: sample_desc,
-            NodeMask: 0,
-            CachedPSO: d3d12::D3D12_CACHED_PIPELINE_STATE {
-                pCachedBlob: ptr::null(),
-                CachedBlobSizeInBytes: 0,
-            },
-            Flags: d3d12::D3D12_PIPELINE_STATE_FLAG_NONE,
-        };
-        let topology = conv::map_topology(input_assembler);
+                                .push(native::RootParameter::cbv_descriptor(visibility, binding));
+                            root_offset += 2; // root CBV costs 2 words
+                        }
+                        if content.contains(r::DescriptorContent::SRV) {
+                            parameter_offsets.push(root_offset);
+                            parameters
+                                .push(native::RootParameter::srv_descriptor(visibility, binding));
+                            root_offset += 2; // root SRV costs 2 words
+                        }
+                        if content.contains(r::DescriptorContent::UAV) {
+                            parameter_offsets.push(root_offset);
+                            parameters
+                                .push(native::RootParameter::uav_descriptor(visibility, binding));
+                            root_offset += 2; // root UAV costs 2 words
+                        }
+                    }
+                }
 
-        // Create PSO
-        let mut pipeline = native::PipelineState::null();
-        let hr = if desc.depth_stencil.depth_bounds {
-            // The DepthBoundsTestEnable option isn't available in the original D3D12_GRAPHICS_PIPELINE_STATE_DESC struct.
-            // Instead, we must use the newer subobject stream method.
-            let (device2, hr) = self.raw.cast::<d3d12::ID3D12Device2>();
-            if winerror::SUCCEEDED(hr) {
-                let mut pss_stream = GraphicsPipelineStateSubobjectStream::new(&pso_desc, true);
-                let pss_desc = d3d12::D3D12_PIPELINE_STATE_STREAM_DESC {
-                    SizeInBytes: mem::size_of_val(&pss_stream),
-                    pPipelineStateSubobjectStream: &mut pss_stream as *mut _ as _,
-                };
-                device2.CreatePipelineState(
-                    &pss_desc,
-                    &d3d12::ID3D12PipelineState::uuidof(),
-                    pipeline.mut_void(),
-                )
-            } else {
-                hr
-            }
-        } else {
-            self.raw.clone().CreateGraphicsPipelineState(
-                &pso_desc,
-                &d3d12::ID3D12PipelineState::uuidof(),
-                pipeline.mut_void(),
-            )
-        };
+                r::RootElement {
+                    table: r::RootTable {
+                        ty: table_type,
+                        offset: root_table_offset as _,
+                    },
+                    mutable_bindings,
+                }
+            })
+            .collect();
 
-        let destroy_shader = |shader: ShaderBc| {
-            if let ShaderBc::Owned(bc) = shader {
-                bc.destroy();
-            }
+        // Ensure that we didn't reallocate!
+        debug_assert_eq!(ranges.len(), total);
+        assert_eq!(parameters.len(), parameter_offsets.len());
+
+        // TODO: error handling
+        let (signature_raw, error) = match self.library.serialize_root_signature(
+            native::RootSignatureVersion::V1_0,
+            &parameters,
+            &[],
+            native::RootSignatureFlags::ALLOW_IA_INPUT_LAYOUT,
+        ) {
+            Ok((pair, hr)) if winerror::SUCCEEDED(hr) => pair,
+            Ok((_, hr)) => panic!("Can't serialize root signature: {:?}", hr),
+            Err(e) => panic!("Can't find serialization function: {:?}", e),
         };
 
-        destroy_shader(vs);
-        destroy_shader(ps);
-        destroy_shader(gs);
-        destroy_shader(hs);
-        destroy_shader(ds);
+        if !error.is_null() {
+            error!(
+                "Root signature serialization error: {:?}",
+                error.as_c_str().to_str().unwrap()
+            );
+            error.destroy();
+        }
 
-        if winerror::SUCCEEDED(hr) {
-            let mut baked_states = desc.baked_states.clone();
-            if !desc.depth_stencil.depth_bounds {
-                baked_states.depth_bounds = None;
-            }
-            if let Some(name) = desc.label {
-                let cwstr = wide_cstr(name);
-                pipeline.SetName(cwstr.as_ptr());
+        // TODO: error handling
+        let (signature, _hr) = self.raw.create_root_signature(signature_raw, 0);
+        signature_raw.destroy();
+
+        Ok(r::PipelineLayout {
+            shared: Arc::new(r::PipelineShared {
+                signature,
+                constants: root_constants,
+                spilled_constants,
+                parameter_offsets,
+                total_slots: root_offset,
+            }),
+            elements,
+        })
+    }
+
+    unsafe fn create_pipeline_cache(&self, _data: Option<&[u8]>) -> Result<(), d::OutOfMemory> {
+        Ok(())
+    }
+
+    unsafe fn get_pipeline_cache_data(&self, _cache: &()) -> Result<Vec<u8>, d::OutOfMemory> {
+        //empty
+        Ok(Vec::new())
+    }
+
+    unsafe fn destroy_pipeline_cache(&self, _: ()) {
+        //empty
+    }
+
+    unsafe fn merge_pipeline_caches<'a, I>(&self, _: &mut (), _: I) -> Result<(), d::OutOfMemory>
+    where
+        I: Iterator<Item = &'a ()>,
+    {
+        //empty
+        Ok(())
+    }
+
+    unsafe fn create_graphics_pipeline<'a>(
+        &self,
+        desc: &pso::GraphicsPipelineDesc<'a, B>,
+        _cache: Option<&()>,
+    ) -> Result<r::GraphicsPipeline, pso::CreationError> {
+        Self::create_graphics_pipeline_impl(self.raw, &self.features, desc)
+    }
+
+    unsafe fn create_graphics_pipeline_async<'a>(
+        &self,
+        desc: &pso::GraphicsPipelineDesc<'a, B>,
+        _cache: Option<&()>,
+    ) -> pso::PipelineTask<r::GraphicsPipeline> {
+        let detached = match DetachedGraphicsPipelineDesc::new(desc) {
+            Some(detached) => detached,
+            None => {
+                return pso::PipelineTask::ready(Self::create_graphics_pipeline_impl(
+                    self.raw,
+                    &self.features,
+                    desc,
+                ))
             }
+        };
+        let device = WorkerDevice {
+            raw: self.raw,
+            features: self.features,
+        };
 
-            Ok(r::GraphicsPipeline {
-                raw: pipeline,
-                shared: Arc::clone(&desc.layout.shared),
-                topology,
-                vertex_bindings,
-                baked_states,
-            })
-        } else {
-            error!("Failed to build shader: {:x}", hr);
-            Err(pso::CreationError::Other)
-        }
+        let (sender, task) = pso::PipelineTask::new();
+        self.pipeline_workers.execute(move || {
+            let result = detached.with(|desc| {
+                Self::create_graphics_pipeline_impl(device.raw, &device.features, desc)
+            });
+            let _ = sender.send(result);
+        });
+        task
     }
 
     unsafe fn create_compute_pipeline<'a>(
@@ -2172,39 +2328,37 @@ impl d::Device<B> for Device {
         desc: &pso::ComputePipelineDesc<'a, B>,
         _cache: Option<&()>,
     ) -> Result<r::ComputePipeline, pso::CreationError> {
-        let (cs, cs_destroy) = Self::extract_entry_point(
-            ShaderStage::Compute,
-            &desc.shader,
-            desc.layout,
-            &self.features,
-        )?;
-
-        let (pipeline, hr) = self.raw.create_compute_pipeline_state(
-            desc.layout.shared.signature,
-            native::Shader::from_blob(cs),
-            0,
-            native::CachedPSO::null(),
-            native::PipelineStateFlags::empty(),
-        );
-
-        if cs_destroy {
-            cs.destroy();
-        }
+        Self::create_compute_pipeline_impl(self.raw, &self.features, desc)
+    }
 
-        if winerror::SUCCEEDED(hr) {
-            if let Some(name) = desc.label {
-                let cwstr = wide_cstr(name);
-                pipeline.SetName(cwstr.as_ptr());
+    unsafe fn create_compute_pipeline_async<'a>(
+        &self,
+        desc: &pso::ComputePipelineDesc<'a, B>,
+        _cache: Option<&()>,
+    ) -> pso::PipelineTask<r::ComputePipeline> {
+        let detached = match DetachedComputePipelineDesc::new(desc) {
+            Some(detached) => detached,
+            None => {
+                return pso::PipelineTask::ready(Self::create_compute_pipeline_impl(
+                    self.raw,
+                    &self.features,
+                    desc,
+                ))
             }
+        };
+        let device = WorkerDevice {
+            raw: self.raw,
+            features: self.features,
+        };
 
-            Ok(r::ComputePipeline {
-                raw: pipeline,
-                shared: Arc::clone(&desc.layout.shared),
-            })
-        } else {
-            error!("Failed to build shader: {:x}", hr);
-            Err(pso::CreationError::Other)
-        }
+        let (sender, task) = pso::PipelineTask::new();
+        self.pipeline_workers.execute(move || {
+            let result = detached.with(|desc| {
+                Self::create_compute_pipeline_impl(device.raw, &device.features, desc)
+            });
+            let _ = sender.send(result);
+        });
+        task
     }
 
     unsafe fn create_framebuffer<I>(
@@ -2252,6 +2406,8 @@ impl d::Device<B> for Device {
             size,
             alignment: d3d12::D3D12_DEFAULT_RESOURCE_PLACEMENT_ALIGNMENT as u64,
             type_mask: MEM_TYPE_MASK << type_mask_shift,
+            prefers_dedicated: false,
+            requires_dedicated: false,
         };
 
         Ok(r::Buffer::Unbound(r::BufferUnbound {
@@ -2530,6 +2686,9 @@ impl d::Device<B> for Device {
                 size: alloc_info.SizeInBytes,
                 alignment: alloc_info.Alignment,
                 type_mask: MEM_TYPE_MASK << type_mask_shift,
+                // targets perform better in their own heaps on some drivers
+                prefers_dedicated: desc.Flags & target_flags != 0,
+                requires_dedicated: false,
             },
             format,
             kind,
@@ -2959,9 +3118,15 @@ impl d::Device<B> for Device {
         I: Iterator<Item = pso::DescriptorSetLayoutBinding>,
         J: Iterator<Item = &'a r::Sampler>,
     {
-        Ok(r::DescriptorSetLayout {
-            bindings: bindings.collect(),
-        })
+        let bindings = bindings.collect::<Vec<_>>();
+        if bindings
+            .iter()
+            .any(|b| b.ty == pso::DescriptorType::InlineUniformBlock)
+        {
+            error!("DX12 doesn't support INLINE_UNIFORM_BLOCK");
+            return Err(d::OutOfMemory::Host);
+        }
+        Ok(r::DescriptorSetLayout { bindings })
     }
 
     unsafe fn write_descriptor_set<'a, I>(&self, op: pso::DescriptorSetWrite<'a, B, I>)
@@ -3098,6 +3263,74 @@ impl d::Device<B> for Device {
                         src_uav = Some(handle.raw);
                     }
                 }
+                pso::Descriptor::InlineUniformBlock(_) => {
+                    error!("DX12 doesn't support INLINE_UNIFORM_BLOCK");
+                    offset += 1;
+                    continue;
+                }
+                pso::Descriptor::Null => {
+                    let ty = match bind_info.view_range {
+                        Some(ref range) if !bind_info.content.is_dynamic() => range.ty,
+                        _ => {
+                            // Root descriptors have no null form, their address is used as is.
+                            error!(
+                                "Null descriptors can't be written into dynamic buffer bindings"
+                            );
+                            offset += 1;
+                            continue;
+                        }
+                    };
+                    // Null views read as zeros and discard the writes. The dimension of
+                    // an image isn't known from the layout, so a 2D view is created.
+                    let (srv_dimension, uav_dimension, format) = match ty {
+                        pso::DescriptorType::Buffer { .. } => (
+                            d3d12::D3D12_SRV_DIMENSION_BUFFER,
+                            d3d12::D3D12_UAV_DIMENSION_BUFFER,
+                            dxgiformat::DXGI_FORMAT_R32_UINT,
+                        ),
+                        _ => (
+                            d3d12::D3D12_SRV_DIMENSION_TEXTURE2D,
+                            d3d12::D3D12_UAV_DIMENSION_TEXTURE2D,
+                            dxgiformat::DXGI_FORMAT_R8G8B8A8_UNORM,
+                        ),
+                    };
+                    if bind_info.content.contains(r::DescriptorContent::CBV) {
+                        let desc = d3d12::D3D12_CONSTANT_BUFFER_VIEW_DESC {
+                            BufferLocation: 0,
+                            SizeInBytes: 0,
+                        };
+                        let handle = descriptor_updater.alloc_handle(self.raw);
+                        self.raw.CreateConstantBufferView(&desc, handle);
+                        src_cbv = Some(handle);
+                    }
+                    if bind_info.content.contains(r::DescriptorContent::SRV) {
+                        let desc = d3d12::D3D12_SHADER_RESOURCE_VIEW_DESC {
+                            Format: format,
+                            Shader4ComponentMapping: IDENTITY_MAPPING,
+                            ViewDimension: srv_dimension,
+                            u: mem::zeroed(),
+                        };
+                        let handle = descriptor_updater.alloc_handle(self.raw);
+                        self.raw
+                            .CreateShaderResourceView(ptr::null_mut(), &desc, handle);
+                        src_srv = Some(handle);
+                    }
+                    if bind_info.content.contains(r::DescriptorContent::UAV) {
+                        let desc = d3d12::D3D12_UNORDERED_ACCESS_VIEW_DESC {
+                            Format: format,
+                            ViewDimension: uav_dimension,
+                            u: mem::zeroed(),
+                        };
+                        let handle = descriptor_updater.alloc_handle(self.raw);
+                        self.raw.CreateUnorderedAccessView(
+                            ptr::null_mut(),
+                            ptr::null_mut(),
+                            &desc,
+                            handle,
+                        );
+                        src_uav = Some(handle);
+                    }
+                }
             }
 
             if let Some(handle) = src_cbv {
@@ -3219,6 +3452,34 @@ impl d::Device<B> for Device {
         }
     }
 
+    unsafe fn copy_memory_to_image<I>(
+        &self,
+        _data: &[u8],
+        _image: &r::Image,
+        _layout: image::Layout,
+        _regions: I,
+    ) -> Result<(), d::MapError>
+    where
+        I: Iterator<Item = hal::command::BufferImageCopy>,
+    {
+        // `Features::HOST_IMAGE_COPY` isn't reported, images are never host-accessible
+        Err(d::MapError::Access)
+    }
+
+    unsafe fn copy_image_to_memory<I>(
+        &self,
+        _image: &r::Image,
+        _layout: image::Layout,
+        _data: &mut [u8],
+        _regions: I,
+    ) -> Result<(), d::MapError>
+    where
+        I: Iterator<Item = hal::command::BufferImageCopy>,
+    {
+        // `Features::HOST_IMAGE_COPY` isn't reported, images are never host-accessible
+        Err(d::MapError::Access)
+    }
+
     unsafe fn flush_mapped_memory_ranges<'a, I>(&self, ranges: I) -> Result<(), d::OutOfMemory>
     where
         I: Iterator<Item = (&'a r::Memory, memory::Segment)>,
@@ -3630,6 +3891,33 @@ impl d::Device<B> for Device {
         // We don't destroy samplers, they are permanently cached
     }
 
+    unsafe fn create_sampler_ycbcr_conversion(
+        &self,
+        desc: &image::SamplerYcbcrConversionDesc,
+    ) -> Result<(), image::CreationError> {
+        Err(image::CreationError::Format(desc.format))
+    }
+
+    unsafe fn destroy_sampler_ycbcr_conversion(&self, _conversion: ()) {}
+
+    unsafe fn create_ycbcr_sampler(
+        &self,
+        _desc: &image::SamplerDesc,
+        _conversion: &(),
+    ) -> Result<r::Sampler, d::AllocationError> {
+        unimplemented!()
+    }
+
+    unsafe fn create_ycbcr_image_view(
+        &self,
+        _image: &r::Image,
+        _view_kind: image::ViewKind,
+        _range: image::SubresourceRange,
+        _conversion: &(),
+    ) -> Result<r::ImageView, image::ViewCreationError> {
+        unimplemented!()
+    }
+
     unsafe fn destroy_descriptor_pool(&self, pool: r::DescriptorPool) {
         let view_range = pool.heap_srv_cbv_uav.range_allocator.initial_range();
         if view_range.start < view_range.end {
@@ -3656,13 +3944,112 @@ impl d::Device<B> for Device {
         unimplemented!()
     }
 
-    fn wait_idle(&self) -> Result<(), d::OutOfMemory> {
+    fn wait_idle(&self) -> Result<(), d::WaitError> {
         for queue in &self.queues {
             queue.wait_idle_impl()?;
         }
         Ok(())
     }
 
+    fn get_device_lost_reason(&self) -> Option<d::DeviceLostReason> {
+        let cause = match unsafe { self.raw.GetDeviceRemovedReason() } {
+            winerror::S_OK => return None,
+            winerror::DXGI_ERROR_DEVICE_HUNG => d::DeviceLostCause::Hung,
+            winerror::DXGI_ERROR_DEVICE_RESET => d::DeviceLostCause::Reset,
+            winerror::DXGI_ERROR_DEVICE_REMOVED => d::DeviceLostCause::Removed,
+            winerror::DXGI_ERROR_DRIVER_INTERNAL_ERROR => d::DeviceLostCause::DriverError,
+            _ => d::DeviceLostCause::Unknown,
+        };
+        let mut reason = d::DeviceLostReason {
+            cause,
+            description: String::new(),
+            fault_address: None,
+            last_checkpoint: None,
+            pending_checkpoints: Vec::new(),
+        };
+
+        // DRED is only available if it was enabled before the device creation.
+        let (dred, hr) = unsafe { self.raw.cast::<ext::ID3D12DeviceRemovedExtendedData>() };
+        if !winerror::SUCCEEDED(hr) {
+            return Some(reason);
+        }
+        unsafe {
+            let mut page_fault = mem::zeroed::<ext::D3D12_DRED_PAGE_FAULT_OUTPUT>();
+            if winerror::SUCCEEDED(dred.GetPageFaultAllocationOutput(&mut page_fault))
+                && page_fault.PageFaultVA != 0
+            {
+                reason.cause = d::DeviceLostCause::PageFault;
+                reason.fault_address = Some(page_fault.PageFaultVA);
+            }
+
+            // Report the command lists that didn't run to completion.
+            let mut breadcrumbs = mem::zeroed::<ext::D3D12_DRED_AUTO_BREADCRUMBS_OUTPUT>();
+            if winerror::SUCCEEDED(dred.GetAutoBreadcrumbsOutput(&mut breadcrumbs)) {
+                let mut node_ptr = breadcrumbs.pHeadAutoBreadcrumbNode;
+                while let Some(node) = node_ptr.as_ref() {
+                    let completed = if node.pLastBreadcrumbValue.is_null() {
+                        0
+                    } else {
+                        *node.pLastBreadcrumbValue
+                    };
+                    if completed < node.BreadcrumbCount {
+                        let name = from_wide_ptr(node.pCommandListDebugNameW);
+                        reason.description += &format!(
+                            "Command list {:?} stopped after {} of {} operations\n",
+                            name, completed, node.BreadcrumbCount,
+                        );
+                    }
+                    node_ptr = node.pNext;
+                }
+            }
+
+            // The checkpoints are the markers recorded in the breadcrumb contexts.
+            let (dred1, hr) = dred.cast::<ext::ID3D12DeviceRemovedExtendedData1>();
+            let mut breadcrumbs = mem::zeroed::<ext::D3D12_DRED_AUTO_BREADCRUMBS_OUTPUT1>();
+            if winerror::SUCCEEDED(hr)
+                && winerror::SUCCEEDED(dred1.GetAutoBreadcrumbsOutput1(&mut breadcrumbs))
+            {
+                let mut node_ptr = breadcrumbs.pHeadAutoBreadcrumbNode;
+                while let Some(node) = node_ptr.as_ref() {
+                    let completed = if node.pLastBreadcrumbValue.is_null() {
+                        0
+                    } else {
+                        *node.pLastBreadcrumbValue
+                    };
+                    let contexts = if node.pBreadcrumbContexts.is_null() {
+                        &[][..]
+                    } else {
+                        slice::from_raw_parts(
+                            node.pBreadcrumbContexts,
+                            node.BreadcrumbContextsCount as usize,
+                        )
+                    };
+                    for context in contexts {
+                        if context.BreadcrumbIndex >= node.BreadcrumbCount
+                            || *node.pCommandHistory.add(context.BreadcrumbIndex as usize)
+                                != ext::D3D12_AUTO_BREADCRUMB_OP_SETMARKER
+                        {
+                            continue;
+                        }
+                        let label = from_wide_ptr(context.pContextString);
+                        if context.BreadcrumbIndex < completed {
+                            reason.last_checkpoint = Some(label);
+                        } else {
+                            reason.pending_checkpoints.push(label);
+                        }
+                    }
+                    node_ptr = node.pNext;
+                }
+            }
+            if winerror::SUCCEEDED(hr) {
+                dred1.destroy();
+            }
+            dred.destroy();
+        }
+
+        Some(reason)
+    }
+
     unsafe fn set_image_name(&self, image: &mut r::Image, name: &str) {
         let cwstr = wide_cstr(name);
         match *image {
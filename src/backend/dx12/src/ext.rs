@@ -0,0 +1,314 @@
+//! Render pass bindings of `ID3D12GraphicsCommandList4`, variable rate shading of
+//! `ID3D12GraphicsCommandList5`, and the device removal diagnostics (DRED), missing from `winapi`.
+
+#![allow(dead_code, non_camel_case_types, non_snake_case)]
+
+use winapi::{
+    ctypes::{c_char, c_void, wchar_t},
+    shared::{
+        basetsd::SIZE_T,
+        dxgiformat::DXGI_FORMAT,
+        minwindef::{BOOL, UINT},
+    },
+    um::{
+        d3d12::{
+            ID3D12CommandQueue, ID3D12GraphicsCommandList, ID3D12GraphicsCommandList1,
+            ID3D12GraphicsCommandList1Vtbl, ID3D12Resource, D3D12_CLEAR_VALUE,
+            D3D12_CPU_DESCRIPTOR_HANDLE, D3D12_FEATURE, D3D12_GPU_VIRTUAL_ADDRESS, D3D12_RECT,
+            D3D12_RESOURCE_STATES,
+        },
+        unknwnbase::{IUnknown, IUnknownVtbl},
+        winnt::HRESULT,
+    },
+    RIDL,
+};
+
+pub type D3D12_RENDER_PASS_BEGINNING_ACCESS_TYPE = u32;
+pub const D3D12_RENDER_PASS_BEGINNING_ACCESS_TYPE_DISCARD: D3D12_RENDER_PASS_BEGINNING_ACCESS_TYPE =
+    0;
+pub const D3D12_RENDER_PASS_BEGINNING_ACCESS_TYPE_PRESERVE:
+    D3D12_RENDER_PASS_BEGINNING_ACCESS_TYPE = 1;
+pub const D3D12_RENDER_PASS_BEGINNING_ACCESS_TYPE_CLEAR: D3D12_RENDER_PASS_BEGINNING_ACCESS_TYPE =
+    2;
+pub const D3D12_RENDER_PASS_BEGINNING_ACCESS_TYPE_NO_ACCESS:
+    D3D12_RENDER_PASS_BEGINNING_ACCESS_TYPE = 3;
+
+pub type D3D12_RENDER_PASS_ENDING_ACCESS_TYPE = u32;
+pub const D3D12_RENDER_PASS_ENDING_ACCESS_TYPE_DISCARD: D3D12_RENDER_PASS_ENDING_ACCESS_TYPE = 0;
+pub const D3D12_RENDER_PASS_ENDING_ACCESS_TYPE_PRESERVE: D3D12_RENDER_PASS_ENDING_ACCESS_TYPE = 1;
+pub const D3D12_RENDER_PASS_ENDING_ACCESS_TYPE_RESOLVE: D3D12_RENDER_PASS_ENDING_ACCESS_TYPE = 2;
+pub const D3D12_RENDER_PASS_ENDING_ACCESS_TYPE_NO_ACCESS: D3D12_RENDER_PASS_ENDING_ACCESS_TYPE = 3;
+
+pub type D3D12_RESOLVE_MODE = u32;
+pub const D3D12_RESOLVE_MODE_AVERAGE: D3D12_RESOLVE_MODE = 3;
+
+pub type D3D12_RENDER_PASS_FLAGS = u32;
+pub const D3D12_RENDER_PASS_FLAG_NONE: D3D12_RENDER_PASS_FLAGS = 0;
+pub const D3D12_RENDER_PASS_FLAG_SUSPENDING_PASS: D3D12_RENDER_PASS_FLAGS = 0x2;
+pub const D3D12_RENDER_PASS_FLAG_RESUMING_PASS: D3D12_RENDER_PASS_FLAGS = 0x4;
+
+// The access structures hold a union with a single member in C.
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct D3D12_RENDER_PASS_BEGINNING_ACCESS {
+    pub Type: D3D12_RENDER_PASS_BEGINNING_ACCESS_TYPE,
+    pub Clear: D3D12_CLEAR_VALUE,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct D3D12_RENDER_PASS_ENDING_ACCESS_RESOLVE_SUBRESOURCE_PARAMETERS {
+    pub SrcSubresource: UINT,
+    pub DstSubresource: UINT,
+    pub DstX: UINT,
+    pub DstY: UINT,
+    pub SrcRect: D3D12_RECT,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct D3D12_RENDER_PASS_ENDING_ACCESS_RESOLVE_PARAMETERS {
+    pub pSrcResource: *mut ID3D12Resource,
+    pub pDstResource: *mut ID3D12Resource,
+    pub SubresourceCount: UINT,
+    pub pSubresourceParameters:
+        *const D3D12_RENDER_PASS_ENDING_ACCESS_RESOLVE_SUBRESOURCE_PARAMETERS,
+    pub Format: DXGI_FORMAT,
+    pub ResolveMode: D3D12_RESOLVE_MODE,
+    pub PreserveResolveSource: BOOL,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct D3D12_RENDER_PASS_ENDING_ACCESS {
+    pub Type: D3D12_RENDER_PASS_ENDING_ACCESS_TYPE,
+    pub Resolve: D3D12_RENDER_PASS_ENDING_ACCESS_RESOLVE_PARAMETERS,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct D3D12_RENDER_PASS_RENDER_TARGET_DESC {
+    pub cpuDescriptor: D3D12_CPU_DESCRIPTOR_HANDLE,
+    pub BeginningAccess: D3D12_RENDER_PASS_BEGINNING_ACCESS,
+    pub EndingAccess: D3D12_RENDER_PASS_ENDING_ACCESS,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct D3D12_RENDER_PASS_DEPTH_STENCIL_DESC {
+    pub cpuDescriptor: D3D12_CPU_DESCRIPTOR_HANDLE,
+    pub DepthBeginningAccess: D3D12_RENDER_PASS_BEGINNING_ACCESS,
+    pub StencilBeginningAccess: D3D12_RENDER_PASS_BEGINNING_ACCESS,
+    pub DepthEndingAccess: D3D12_RENDER_PASS_ENDING_ACCESS,
+    pub StencilEndingAccess: D3D12_RENDER_PASS_ENDING_ACCESS,
+}
+
+RIDL! {#[uuid(0x38c3e585, 0xff17, 0x412c, 0x91, 0x50, 0x4f, 0xc6, 0xf9, 0xd7, 0x2a, 0x28)]
+interface ID3D12GraphicsCommandList2(ID3D12GraphicsCommandList2Vtbl):
+    ID3D12GraphicsCommandList1(ID3D12GraphicsCommandList1Vtbl) {
+    fn WriteBufferImmediate(
+        Count: UINT,
+        pParams: *const c_void,
+        pModes: *const c_void,
+    ) -> (),
+}}
+
+RIDL! {#[uuid(0x6fda83a7, 0xb84c, 0x4e38, 0x9a, 0xc8, 0xc7, 0xbd, 0x22, 0x01, 0x6b, 0x3d)]
+interface ID3D12GraphicsCommandList3(ID3D12GraphicsCommandList3Vtbl):
+    ID3D12GraphicsCommandList2(ID3D12GraphicsCommandList2Vtbl) {
+    fn SetProtectedResourceSession(
+        pProtectedResourceSession: *mut c_void,
+    ) -> (),
+}}
+
+RIDL! {#[uuid(0x8754318e, 0xd3a9, 0x4541, 0x98, 0xcf, 0x64, 0x5b, 0x50, 0xdc, 0x48, 0x74)]
+interface ID3D12GraphicsCommandList4(ID3D12GraphicsCommandList4Vtbl):
+    ID3D12GraphicsCommandList3(ID3D12GraphicsCommandList3Vtbl) {
+    fn BeginRenderPass(
+        NumRenderTargets: UINT,
+        pRenderTargets: *const D3D12_RENDER_PASS_RENDER_TARGET_DESC,
+        pDepthStencil: *const D3D12_RENDER_PASS_DEPTH_STENCIL_DESC,
+        Flags: D3D12_RENDER_PASS_FLAGS,
+    ) -> (),
+    fn EndRenderPass() -> (),
+    fn InitializeMetaCommand(
+        pMetaCommand: *mut c_void,
+        pInitializationParametersData: *const c_void,
+        InitializationParametersDataSizeInBytes: SIZE_T,
+    ) -> (),
+    fn ExecuteMetaCommand(
+        pMetaCommand: *mut c_void,
+        pExecutionParametersData: *const c_void,
+        ExecutionParametersDataSizeInBytes: SIZE_T,
+    ) -> (),
+    fn BuildRaytracingAccelerationStructure(
+        pDesc: *const c_void,
+        NumPostbuildInfoDescs: UINT,
+        pPostbuildInfoDescs: *const c_void,
+    ) -> (),
+    fn EmitRaytracingAccelerationStructurePostbuildInfo(
+        pDesc: *const c_void,
+        NumSourceAccelerationStructures: UINT,
+        pSourceAccelerationStructureData: *const D3D12_GPU_VIRTUAL_ADDRESS,
+    ) -> (),
+    fn CopyRaytracingAccelerationStructure(
+        DestAccelerationStructureData: D3D12_GPU_VIRTUAL_ADDRESS,
+        SourceAccelerationStructureData: D3D12_GPU_VIRTUAL_ADDRESS,
+        Mode: UINT,
+    ) -> (),
+    fn SetPipelineState1(
+        pStateObject: *mut c_void,
+    ) -> (),
+    fn DispatchRays(
+        pDesc: *const c_void,
+    ) -> (),
+}}
+
+pub type D3D12_SHADING_RATE = u32;
+
+pub type D3D12_SHADING_RATE_COMBINER = u32;
+pub const D3D12_SHADING_RATE_COMBINER_PASSTHROUGH: D3D12_SHADING_RATE_COMBINER = 0;
+pub const D3D12_SHADING_RATE_COMBINER_OVERRIDE: D3D12_SHADING_RATE_COMBINER = 1;
+pub const D3D12_SHADING_RATE_COMBINER_MIN: D3D12_SHADING_RATE_COMBINER = 2;
+pub const D3D12_SHADING_RATE_COMBINER_MAX: D3D12_SHADING_RATE_COMBINER = 3;
+pub const D3D12_SHADING_RATE_COMBINER_SUM: D3D12_SHADING_RATE_COMBINER = 4;
+
+pub const D3D12_RESOURCE_STATE_SHADING_RATE_SOURCE: D3D12_RESOURCE_STATES = 0x100_0000;
+
+pub type D3D12_VARIABLE_SHADING_RATE_TIER = u32;
+pub const D3D12_VARIABLE_SHADING_RATE_TIER_NOT_SUPPORTED: D3D12_VARIABLE_SHADING_RATE_TIER = 0;
+pub const D3D12_VARIABLE_SHADING_RATE_TIER_1: D3D12_VARIABLE_SHADING_RATE_TIER = 1;
+pub const D3D12_VARIABLE_SHADING_RATE_TIER_2: D3D12_VARIABLE_SHADING_RATE_TIER = 2;
+
+pub const D3D12_FEATURE_D3D12_OPTIONS6: D3D12_FEATURE = 30;
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct D3D12_FEATURE_DATA_D3D12_OPTIONS6 {
+    pub AdditionalShadingRatesSupported: BOOL,
+    pub PerPrimitiveShadingRateSupportedWithViewportIndexing: BOOL,
+    pub VariableShadingRateTier: D3D12_VARIABLE_SHADING_RATE_TIER,
+    pub ShadingRateImageTileSize: UINT,
+    pub BackgroundProcessingSupported: BOOL,
+}
+
+RIDL! {#[uuid(0x55050859, 0x4024, 0x474c, 0x87, 0xf5, 0x64, 0x72, 0xea, 0xee, 0x44, 0xea)]
+interface ID3D12GraphicsCommandList5(ID3D12GraphicsCommandList5Vtbl):
+    ID3D12GraphicsCommandList4(ID3D12GraphicsCommandList4Vtbl) {
+    fn RSSetShadingRate(
+        baseShadingRate: D3D12_SHADING_RATE,
+        combiners: *const D3D12_SHADING_RATE_COMBINER,
+    ) -> (),
+    fn RSSetShadingRateImage(
+        shadingRateImage: *mut ID3D12Resource,
+    ) -> (),
+}}
+
+pub type D3D12_DRED_ENABLEMENT = u32;
+pub const D3D12_DRED_ENABLEMENT_SYSTEM_CONTROLLED: D3D12_DRED_ENABLEMENT = 0;
+pub const D3D12_DRED_ENABLEMENT_FORCED_OFF: D3D12_DRED_ENABLEMENT = 1;
+pub const D3D12_DRED_ENABLEMENT_FORCED_ON: D3D12_DRED_ENABLEMENT = 2;
+
+pub type D3D12_AUTO_BREADCRUMB_OP = u32;
+pub const D3D12_AUTO_BREADCRUMB_OP_SETMARKER: D3D12_AUTO_BREADCRUMB_OP = 0;
+
+#[repr(C)]
+pub struct D3D12_AUTO_BREADCRUMB_NODE {
+    pub pCommandListDebugNameA: *const c_char,
+    pub pCommandListDebugNameW: *const wchar_t,
+    pub pCommandQueueDebugNameA: *const c_char,
+    pub pCommandQueueDebugNameW: *const wchar_t,
+    pub pCommandList: *mut ID3D12GraphicsCommandList,
+    pub pCommandQueue: *mut ID3D12CommandQueue,
+    pub BreadcrumbCount: UINT,
+    pub pLastBreadcrumbValue: *const UINT,
+    pub pCommandHistory: *const D3D12_AUTO_BREADCRUMB_OP,
+    pub pNext: *const D3D12_AUTO_BREADCRUMB_NODE,
+}
+
+#[repr(C)]
+pub struct D3D12_DRED_AUTO_BREADCRUMBS_OUTPUT {
+    pub pHeadAutoBreadcrumbNode: *const D3D12_AUTO_BREADCRUMB_NODE,
+}
+
+#[repr(C)]
+pub struct D3D12_DRED_BREADCRUMB_CONTEXT {
+    pub BreadcrumbIndex: UINT,
+    pub pContextString: *const wchar_t,
+}
+
+#[repr(C)]
+pub struct D3D12_AUTO_BREADCRUMB_NODE1 {
+    pub pCommandListDebugNameA: *const c_char,
+    pub pCommandListDebugNameW: *const wchar_t,
+    pub pCommandQueueDebugNameA: *const c_char,
+    pub pCommandQueueDebugNameW: *const wchar_t,
+    pub pCommandList: *mut ID3D12GraphicsCommandList,
+    pub pCommandQueue: *mut ID3D12CommandQueue,
+    pub BreadcrumbCount: UINT,
+    pub pLastBreadcrumbValue: *const UINT,
+    pub pCommandHistory: *const D3D12_AUTO_BREADCRUMB_OP,
+    pub pNext: *const D3D12_AUTO_BREADCRUMB_NODE1,
+    pub BreadcrumbContextsCount: UINT,
+    pub pBreadcrumbContexts: *const D3D12_DRED_BREADCRUMB_CONTEXT,
+}
+
+#[repr(C)]
+pub struct D3D12_DRED_AUTO_BREADCRUMBS_OUTPUT1 {
+    pub pHeadAutoBreadcrumbNode: *const D3D12_AUTO_BREADCRUMB_NODE1,
+}
+
+// The allocation nodes are not inspected, so they are left opaque.
+
+#[repr(C)]
+pub struct D3D12_DRED_PAGE_FAULT_OUTPUT {
+    pub PageFaultVA: D3D12_GPU_VIRTUAL_ADDRESS,
+    pub pHeadExistingAllocationNode: *const c_void,
+    pub pHeadRecentFreedAllocationNode: *const c_void,
+}
+
+RIDL! {#[uuid(0x82bc481c, 0x6b9b, 0x4030, 0xae, 0xdb, 0x7e, 0xe3, 0xd1, 0xdf, 0x1e, 0x63)]
+interface ID3D12DeviceRemovedExtendedDataSettings(ID3D12DeviceRemovedExtendedDataSettingsVtbl):
+    IUnknown(IUnknownVtbl) {
+    fn SetAutoBreadcrumbsEnablement(
+        Enablement: D3D12_DRED_ENABLEMENT,
+    ) -> (),
+    fn SetPageFaultEnablement(
+        Enablement: D3D12_DRED_ENABLEMENT,
+    ) -> (),
+    fn SetWatsonDumpEnablement(
+        Enablement: D3D12_DRED_ENABLEMENT,
+    ) -> (),
+}}
+
+RIDL! {#[uuid(0xdbd5ae51, 0x3317, 0x4f0a, 0xad, 0xf9, 0x1d, 0x7c, 0xed, 0xca, 0xae, 0x0b)]
+interface ID3D12DeviceRemovedExtendedDataSettings1(ID3D12DeviceRemovedExtendedDataSettings1Vtbl):
+    ID3D12DeviceRemovedExtendedDataSettings(ID3D12DeviceRemovedExtendedDataSettingsVtbl) {
+    fn SetBreadcrumbContextEnablement(
+        Enablement: D3D12_DRED_ENABLEMENT,
+    ) -> (),
+}}
+
+RIDL! {#[uuid(0x98931d33, 0x5ae8, 0x4791, 0xaa, 0x3c, 0x1a, 0x73, 0xa2, 0x93, 0x4e, 0x71)]
+interface ID3D12DeviceRemovedExtendedData(ID3D12DeviceRemovedExtendedDataVtbl):
+    IUnknown(IUnknownVtbl) {
+    fn GetAutoBreadcrumbsOutput(
+        pOutput: *mut D3D12_DRED_AUTO_BREADCRUMBS_OUTPUT,
+    ) -> HRESULT,
+    fn GetPageFaultAllocationOutput(
+        pOutput: *mut D3D12_DRED_PAGE_FAULT_OUTPUT,
+    ) -> HRESULT,
+}}
+
+// `D3D12_DRED_PAGE_FAULT_OUTPUT1` only differs by the type of the opaque allocation nodes.
+RIDL! {#[uuid(0x9727a022, 0xcf1d, 0x4dda, 0x9e, 0xba, 0xef, 0xfa, 0x65, 0x3f, 0xc5, 0x06)]
+interface ID3D12DeviceRemovedExtendedData1(ID3D12DeviceRemovedExtendedData1Vtbl):
+    ID3D12DeviceRemovedExtendedData(ID3D12DeviceRemovedExtendedDataVtbl) {
+    fn GetAutoBreadcrumbsOutput1(
+        pOutput: *mut D3D12_DRED_AUTO_BREADCRUMBS_OUTPUT1,
+    ) -> HRESULT,
+    fn GetPageFaultAllocationOutput1(
+        pOutput: *mut D3D12_DRED_PAGE_FAULT_OUTPUT,
+    ) -> HRESULT,
+}}
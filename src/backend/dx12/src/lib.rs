@@ -27,6 +27,7 @@ mod command;
 mod conv;
 mod descriptors_cpu;
 mod device;
+mod ext;
 mod internal;
 mod pool;
 mod resource;
@@ -35,8 +36,9 @@ mod window;
 
 use auxil::FastHashMap;
 use hal::{
-    adapter, format as f, image, memory, pso::PipelineStage, queue as q, Capabilities, Features,
-    Limits,
+    adapter, format as f, image, memory,
+    pso::{PipelineStage, ShadingRate},
+    queue as q, Capabilities, Features, Limits,
 };
 use range_alloc::RangeAllocator;
 
@@ -477,8 +479,10 @@ impl Queue {
         self.raw.destroy();
     }
 
-    fn wait_idle_impl(&self) -> Result<(), hal::device::OutOfMemory> {
-        self.raw.signal(self.idle_fence, 1);
+    fn wait_idle_impl(&self) -> Result<(), hal::device::WaitError> {
+        if self.raw.signal(self.idle_fence, 1) == winerror::DXGI_ERROR_DEVICE_REMOVED {
+            return Err(hal::device::DeviceLost.into());
+        }
         assert_eq!(
             winerror::S_OK,
             self.idle_fence.set_event_on_completion(self.idle_event, 1)
@@ -486,6 +490,10 @@ impl Queue {
 
         unsafe {
             synchapi::WaitForSingleObject(self.idle_event.0, winbase::INFINITE);
+            // Fences of a removed device are signaled with the maximum value.
+            if self.idle_fence.GetCompletedValue() == u64::MAX {
+                return Err(hal::device::DeviceLost.into());
+            }
         }
 
         Ok(())
@@ -502,7 +510,8 @@ impl q::Queue<Backend> for Queue {
         _wait_semaphores: Iw,
         _signal_semaphores: Is,
         fence: Option<&mut resource::Fence>,
-    ) where
+    ) -> Result<(), q::SubmitError>
+    where
         Ic: Iterator<Item = &'a command::CommandBuffer>,
         Iw: Iterator<Item = (&'a resource::Semaphore, PipelineStage)>,
         Is: Iterator<Item = &'a resource::Semaphore>,
@@ -520,8 +529,14 @@ impl q::Queue<Backend> for Queue {
             .ExecuteCommandLists(lists.len() as _, lists.as_ptr());
 
         if let Some(fence) = fence {
-            assert_eq!(winerror::S_OK, self.raw.Signal(fence.raw.as_mut_ptr(), 1));
+            match self.raw.Signal(fence.raw.as_mut_ptr(), 1) {
+                winerror::S_OK => {}
+                winerror::DXGI_ERROR_DEVICE_REMOVED => return Err(hal::device::DeviceLost.into()),
+                winerror::E_OUTOFMEMORY => return Err(hal::device::OutOfMemory::Host.into()),
+                hr => panic!("Unexpected signal status 0x{:X}", hr),
+            }
         }
+        Ok(())
     }
 
     unsafe fn present(
@@ -530,10 +545,20 @@ impl q::Queue<Backend> for Queue {
         image: window::SwapchainImage,
         _wait_semaphore: Option<&mut resource::Semaphore>,
     ) -> Result<Option<hal::window::Suboptimal>, hal::window::PresentError> {
-        surface.present(image).map(|()| None)
+        surface.present(image, None).map(|()| None)
     }
 
-    fn wait_idle(&mut self) -> Result<(), hal::device::OutOfMemory> {
+    unsafe fn present_with_time(
+        &mut self,
+        surface: &mut window::Surface,
+        image: window::SwapchainImage,
+        _wait_semaphore: Option<&mut resource::Semaphore>,
+        time: hal::window::PresentTime,
+    ) -> Result<Option<hal::window::Suboptimal>, hal::window::PresentError> {
+        surface.present(image, Some(time)).map(|()| None)
+    }
+
+    fn wait_idle(&mut self) -> Result<(), hal::device::WaitError> {
         self.wait_idle_impl()
     }
 
@@ -631,6 +656,7 @@ pub struct Device {
     // Indicates that there is currently an active device.
     open: Arc<Mutex<bool>>,
     library: Arc<native::D3D12Lib>,
+    pipeline_workers: auxil::workers::Workers,
 }
 
 impl fmt::Debug for Device {
@@ -717,6 +743,10 @@ impl Device {
             present_queue,
             queues: Vec::new(),
             open: Arc::clone(&physical_device.is_open),
+            pipeline_workers: auxil::workers::Workers::new(
+                "gfx-dx12-pipelines",
+                auxil::workers::PIPELINE_THREADS,
+            ),
         }
     }
 
@@ -735,6 +765,8 @@ impl Device {
 impl Drop for Device {
     fn drop(&mut self) {
         *self.open.lock() = false;
+        // The pending pipelines are created with the raw device
+        self.pipeline_workers.join();
 
         unsafe {
             for queue in &mut self.queues {
@@ -781,6 +813,40 @@ impl Drop for Instance {
 unsafe impl Send for Instance {}
 unsafe impl Sync for Instance {}
 
+/// Turn on the breadcrumbs and the page fault reporting of DRED
+/// for the devices created afterwards.
+#[cfg(debug_assertions)]
+unsafe fn enable_dred() -> Result<(), winerror::HRESULT> {
+    type GetDebugInterfaceFun = extern "system" fn(
+        winapi::shared::guiddef::REFIID,
+        *mut *mut winapi::ctypes::c_void,
+    ) -> winerror::HRESULT;
+
+    let library = libloading::Library::new("d3d12.dll").map_err(|_| winerror::E_FAIL)?;
+    let func: libloading::Symbol<GetDebugInterfaceFun> = library
+        .get(b"D3D12GetDebugInterface")
+        .map_err(|_| winerror::E_FAIL)?;
+
+    let mut settings = native::WeakPtr::<ext::ID3D12DeviceRemovedExtendedDataSettings>::null();
+    let hr = func(
+        &ext::ID3D12DeviceRemovedExtendedDataSettings::uuidof(),
+        settings.mut_void(),
+    );
+    if !winerror::SUCCEEDED(hr) {
+        return Err(hr);
+    }
+    settings.SetAutoBreadcrumbsEnablement(ext::D3D12_DRED_ENABLEMENT_FORCED_ON);
+    settings.SetPageFaultEnablement(ext::D3D12_DRED_ENABLEMENT_FORCED_ON);
+    // The breadcrumb contexts, carrying the checkpoint labels, need DRED 1.2.
+    let (settings1, hr) = settings.cast::<ext::ID3D12DeviceRemovedExtendedDataSettings1>();
+    if winerror::SUCCEEDED(hr) {
+        settings1.SetBreadcrumbContextEnablement(ext::D3D12_DRED_ENABLEMENT_FORCED_ON);
+        settings1.destroy();
+    }
+    settings.destroy();
+    Ok(())
+}
+
 impl hal::Instance<Backend> for Instance {
     fn create(_: &str, _: u32) -> Result<Self, hal::UnsupportedBackend> {
         let lib_main = match native::D3D12Lib::new() {
@@ -802,6 +868,10 @@ impl hal::Instance<Backend> for Instance {
                     warn!("Unable to get D3D12 debug interface");
                 }
             }
+            // Enable the device removal diagnostics, reported by `get_device_lost_reason`
+            if let Err(hr) = unsafe { enable_dred() } {
+                warn!("Unable to enable DRED: 0x{:X}", hr);
+            }
         }
 
         let lib_dxgi = native::DxgiLib::new().unwrap();
@@ -957,7 +1027,7 @@ impl hal::Instance<Backend> for Instance {
                 )
             });
 
-            let depth_bounds_test_supported = {
+            let (depth_bounds_test_supported, programmable_sample_positions) = {
                 let mut features2: d3d12::D3D12_FEATURE_DATA_D3D12_OPTIONS2 =
                     unsafe { mem::zeroed() };
                 let hr = unsafe {
@@ -968,15 +1038,44 @@ impl hal::Instance<Backend> for Instance {
                     )
                 };
                 if hr == winerror::S_OK {
-                    features2.DepthBoundsTestSupported != 0
+                    (
+                        features2.DepthBoundsTestSupported != 0,
+                        features2.ProgrammableSamplePositionsTier
+                            != d3d12::D3D12_PROGRAMMABLE_SAMPLE_POSITIONS_TIER_NOT_SUPPORTED,
+                    )
                 } else {
-                    false
+                    (false, false)
                 }
             };
 
+            let features6 = {
+                let mut features6 = ext::D3D12_FEATURE_DATA_D3D12_OPTIONS6::default();
+                let hr = unsafe {
+                    device.CheckFeatureSupport(
+                        ext::D3D12_FEATURE_D3D12_OPTIONS6,
+                        &mut features6 as *mut _ as *mut _,
+                        mem::size_of::<ext::D3D12_FEATURE_DATA_D3D12_OPTIONS6>() as _,
+                    )
+                };
+                if hr == winerror::S_OK {
+                    features6
+                } else {
+                    ext::D3D12_FEATURE_DATA_D3D12_OPTIONS6::default()
+                }
+            };
+            // Tier 1 only has the per-draw rates, tier 2 adds the combiners and the shading rate image
+            let shading_rate_tier = features6.VariableShadingRateTier;
+
             let heterogeneous_resource_heaps =
                 features.ResourceHeapTier != d3d12::D3D12_RESOURCE_HEAP_TIER_1;
 
+            // Tier 1 has an uncertainty of 1/2 pixel, higher tiers of 1/256 pixel
+            let primitive_overestimation_size = match features.ConservativeRasterizationTier {
+                d3d12::D3D12_CONSERVATIVE_RASTERIZATION_TIER_NOT_SUPPORTED => None,
+                d3d12::D3D12_CONSERVATIVE_RASTERIZATION_TIER_1 => Some(0.5),
+                _ => Some(1.0 / 256.0),
+            };
+
             let uma = features_architecture.UMA == TRUE;
             let cc_uma = features_architecture.CacheCoherentUMA == TRUE;
 
@@ -1128,6 +1227,11 @@ impl hal::Instance<Backend> for Instance {
                 }
                 heaps
             };
+            let memory_properties = adapter::MemoryProperties {
+                memory_types,
+                memory_heaps,
+            };
+
             //TODO: find a way to get a tighter bound?
             let sample_count_mask = 0x3F;
 
@@ -1169,6 +1273,7 @@ impl hal::Instance<Backend> for Instance {
                     Features::FORMAT_BC |
                     Features::INSTANCE_RATE |
                     Features::DEPTH_CLAMP |
+                    Features::DEPTH_CLIP_ENABLE |
                     Features::SAMPLER_MIP_LOD_BIAS |
                     Features::SAMPLER_BORDER_COLOR |
                     Features::MUTABLE_COMPARISON_SAMPLER |
@@ -1181,7 +1286,20 @@ impl hal::Instance<Backend> for Instance {
                     Features::SAMPLED_TEXTURE_DESCRIPTOR_INDEXING |
                     Features::STORAGE_TEXTURE_DESCRIPTOR_INDEXING |
                     Features::UNSIZED_DESCRIPTOR_ARRAY |
-                    Features::DRAW_INDIRECT_COUNT,
+                    Features::DRAW_INDIRECT_COUNT |
+                    Features::FULL_SCREEN_EXCLUSIVE |
+                    Features::SEPARATE_DEPTH_STENCIL_LAYOUTS |
+                    Features::PRESENT_TIMING |
+                    Features::DYNAMIC_RENDERING |
+                    Features::PRESENT_MODE_SWITCH |
+                    Features::HDR_METADATA |
+                    Features::NULL_DESCRIPTOR |
+                    if programmable_sample_positions { Features::SAMPLE_LOCATIONS } else { Features::empty() } |
+                    if primitive_overestimation_size.is_some() { Features::CONSERVATIVE_RASTERIZATION } else { Features::empty() } |
+                    // The interlocked resources are translated to the rasterizer ordered views
+                    if features.ROVsSupported != 0 { Features::FRAGMENT_SHADER_INTERLOCK } else { Features::empty() } |
+                    if shading_rate_tier >= ext::D3D12_VARIABLE_SHADING_RATE_TIER_1 { Features::SHADING_RATE } else { Features::empty() } |
+                    if shading_rate_tier >= ext::D3D12_VARIABLE_SHADING_RATE_TIER_2 { Features::SHADING_RATE_ATTACHMENT } else { Features::empty() },
                 limits: Limits {
                     //TODO: verify all of these not linked to constants
                     max_memory_allocation_count: !0,
@@ -1216,8 +1334,9 @@ impl hal::Instance<Backend> for Instance {
                     max_per_stage_resources: !0,
                     max_uniform_buffer_range: (d3d12::D3D12_REQ_CONSTANT_BUFFER_ELEMENT_COUNT * 16) as _,
                     max_storage_buffer_range: !0,
-                    // Is actually 256, but need space for the descriptors in there, so leave at 128 to discourage explosions
-                    max_push_constants_size: 128,
+                    // Only 128 bytes fit into the root signature next to the descriptors,
+                    // larger push constants get spilled into root CBVs.
+                    max_push_constants_size: 256,
                     max_image_1d_size: d3d12::D3D12_REQ_TEXTURE1D_U_DIMENSION as _,
                     max_image_2d_size: d3d12::D3D12_REQ_TEXTURE2D_U_OR_V_DIMENSION as _,
                     max_image_3d_size: d3d12::D3D12_REQ_TEXTURE3D_U_V_OR_W_DIMENSION as _,
@@ -1259,13 +1378,31 @@ impl hal::Instance<Backend> for Instance {
                     framebuffer_color_sample_counts: sample_count_mask,
                     framebuffer_depth_sample_counts: sample_count_mask,
                     framebuffer_stencil_sample_counts: sample_count_mask,
+                    // Unordered access views of multisampled resources are not supported
+                    storage_image_sample_counts: 0x1,
                     max_color_attachments: d3d12::D3D12_SIMULTANEOUS_RENDER_TARGET_COUNT as _,
                     buffer_image_granularity: 1,
                     non_coherent_atom_size: 1, //TODO: confirm
+                    // Only the UMA architectures have host-visible memory local to the device
+                    device_local_host_visible_heap_size: auxil::device_local_host_visible_heap_size(&memory_properties),
                     max_sampler_anisotropy: 16.,
                     optimal_buffer_copy_offset_alignment: d3d12::D3D12_TEXTURE_DATA_PLACEMENT_ALIGNMENT as _,
                     optimal_buffer_copy_pitch_alignment: d3d12::D3D12_TEXTURE_DATA_PITCH_ALIGNMENT as _,
                     min_vertex_input_binding_stride_alignment: 1,
+                    standard_sample_locations: true,
+                    // Sample positions are specified in 1/16th of a pixel, up to 16 samples
+                    sample_location_sample_counts: if programmable_sample_positions { 0x1F } else { 0 },
+                    sample_location_sub_pixel_bits: 4,
+                    primitive_overestimation_size: primitive_overestimation_size.unwrap_or(0.0),
+                    shading_rate_attachment_texel_size: [features6.ShadingRateImageTileSize; 2],
+                    // 2x4, 4x2 and 4x4 are the additional rates
+                    max_shading_rate: if shading_rate_tier == ext::D3D12_VARIABLE_SHADING_RATE_TIER_NOT_SUPPORTED {
+                        ShadingRate::FULL
+                    } else if features6.AdditionalShadingRatesSupported != 0 {
+                        ShadingRate { width: 4, height: 4 }
+                    } else {
+                        ShadingRate { width: 2, height: 2 }
+                    },
                     .. Limits::default() //TODO
                 },
                 format_properties: Arc::new(FormatProperties::new(device)),
@@ -1275,10 +1412,7 @@ impl hal::Instance<Backend> for Instance {
                 },
                 workarounds,
                 heap_properties,
-                memory_properties: adapter::MemoryProperties {
-                    memory_types,
-                    memory_heaps,
-                },
+                memory_properties,
                 is_open: Arc::new(Mutex::new(false)),
             };
 
@@ -1334,6 +1468,7 @@ impl hal::Backend for Backend {
     type Image = resource::Image;
     type ImageView = resource::ImageView;
     type Sampler = resource::Sampler;
+    type SamplerYcbcrConversion = ();
 
     type ComputePipeline = resource::ComputePipeline;
     type GraphicsPipeline = resource::GraphicsPipeline;
@@ -1484,6 +1619,12 @@ impl FormatProperties {
                 }
             }
             //TODO: blits, linear tiling
+            if conv::is_emulated_format(format) {
+                // The copies would expose the layout of the wider backing format.
+                props.optimal_tiling -=
+                    f::ImageFeature::TRANSFER_SRC | f::ImageFeature::TRANSFER_DST;
+                props.linear_tiling = f::ImageFeature::empty();
+            }
             props
         };
 
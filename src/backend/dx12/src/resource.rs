@@ -62,6 +62,7 @@ pub struct SubpassDesc {
     pub(crate) depth_stencil_attachment: Option<pass::AttachmentRef>,
     pub(crate) input_attachments: Vec<pass::AttachmentRef>,
     pub(crate) resolve_attachments: Vec<pass::AttachmentRef>,
+    pub(crate) shading_rate_attachment: Option<pass::AttachmentRef>,
     pub(crate) pre_barriers: Vec<BarrierDesc>,
     pub(crate) post_barriers: Vec<BarrierDesc>,
 }
@@ -75,6 +76,7 @@ impl SubpassDesc {
             .chain(self.depth_stencil_attachment.iter())
             .chain(self.input_attachments.iter())
             .chain(self.resolve_attachments.iter())
+            .chain(self.shading_rate_attachment.iter())
             .any(|&(id, _)| id == at_id)
     }
 }
@@ -149,6 +151,8 @@ pub struct PipelineShared {
     pub(crate) signature: native::RootSignature,
     /// Disjunct, sorted vector of root constant ranges.
     pub(crate) constants: Vec<RootConstant>,
+    /// Whether the root constants are spilled into root CBVs.
+    pub(crate) spilled_constants: bool,
     /// A root offset per parameter.
     pub(crate) parameter_offsets: Vec<u32>,
     /// Total number of root slots occupied by the pipeline.
@@ -522,6 +526,8 @@ impl From<pso::DescriptorType> for DescriptorContent {
                 },
             },
             Dt::InputAttachment => Dc::SRV,
+            // Set layouts with inline uniform blocks fail to be created.
+            Dt::InlineUniformBlock => Dc::empty(),
         }
     }
 }
@@ -1,13 +1,17 @@
-use std::{borrow::Borrow, fmt, mem, os::raw::c_void};
+use std::{borrow::Borrow, collections::VecDeque, fmt, mem, os::raw::c_void, ptr};
 
 use winapi::{
     shared::{
-        dxgi, dxgi1_4, dxgi1_5, dxgitype,
+        dxgi, dxgi1_4, dxgi1_5, dxgiformat, dxgitype,
         minwindef::{BOOL, FALSE, TRUE},
         windef::{HWND, RECT},
         winerror,
     },
-    um::{d3d12, synchapi, winbase, winnt::HANDLE, winuser::GetClientRect},
+    um::{
+        d3d12, dwmapi, profileapi, synchapi, winbase,
+        winnt::{HANDLE, LARGE_INTEGER},
+        winuser::GetClientRect,
+    },
 };
 
 use crate::{conv, resource as r, Backend, Device, Instance, PhysicalDevice, QueueFamily};
@@ -29,6 +33,15 @@ struct Presentation {
     format: f::Format,
     size: w::Extent2D,
     mode: w::PresentMode,
+    /// Created with `DXGI_SWAP_CHAIN_FLAG_ALLOW_TEARING`, so that it can present immediately.
+    allow_tearing: bool,
+    full_screen_exclusive: w::FullScreenExclusive,
+    refresh_rate: Option<w::RefreshRate>,
+    color_space: w::ColorSpace,
+    is_full_screen: bool,
+    /// Timing requests of the presents that haven't been reported yet,
+    /// along with the DXGI present count.
+    pending_times: VecDeque<(u32, w::PresentTime)>,
 }
 
 pub struct Surface {
@@ -46,15 +59,68 @@ impl fmt::Debug for Surface {
 unsafe impl Send for Surface {}
 unsafe impl Sync for Surface {}
 
+/// Maximum number of present timing requests waiting to be reported.
+const MAX_PENDING_PRESENT_TIMES: usize = 16;
+
+/// Check if the swapchains can be created with `DXGI_SWAP_CHAIN_FLAG_ALLOW_TEARING`.
+pub(crate) unsafe fn supports_tearing(factory: &native::WeakPtr<dxgi1_4::IDXGIFactory4>) -> bool {
+    let (f5, hr) = factory.cast::<dxgi1_5::IDXGIFactory5>();
+    if winerror::SUCCEEDED(hr) {
+        let mut allow_tearing: BOOL = FALSE;
+        let hr = f5.CheckFeatureSupport(
+            dxgi1_5::DXGI_FEATURE_PRESENT_ALLOW_TEARING,
+            &mut allow_tearing as *mut _ as *mut _,
+            mem::size_of::<BOOL>() as _,
+        );
+
+        f5.destroy();
+
+        winerror::SUCCEEDED(hr) && allow_tearing == TRUE
+    } else {
+        false
+    }
+}
+
+/// Get the color spaces the swapchain can be presented in, with its current format.
+unsafe fn supported_color_spaces(
+    swapchain: &native::WeakPtr<dxgi1_4::IDXGISwapChain3>,
+) -> w::ColorSpace {
+    let mut color_spaces = w::ColorSpace::SRGB_NONLINEAR;
+    for &color_space in &[w::ColorSpace::LINEAR, w::ColorSpace::HDR10] {
+        let mut support = 0;
+        let hr = swapchain.CheckColorSpaceSupport(conv::map_color_space(color_space), &mut support);
+        if winerror::SUCCEEDED(hr)
+            && support & dxgi1_4::DXGI_SWAP_CHAIN_COLOR_SPACE_SUPPORT_FLAG_PRESENT != 0
+        {
+            color_spaces |= color_space;
+        }
+    }
+    color_spaces
+}
+
+/// Convert a value of the performance counter into nanoseconds.
+unsafe fn qpc_to_nanoseconds(ticks: u64) -> u64 {
+    let mut frequency: LARGE_INTEGER = mem::zeroed();
+    profileapi::QueryPerformanceFrequency(&mut frequency);
+    (ticks as u128 * 1_000_000_000 / *frequency.QuadPart() as u128) as u64
+}
+
 impl Surface {
-    pub(crate) unsafe fn present(&mut self, image: SwapchainImage) -> Result<(), w::PresentError> {
+    pub(crate) unsafe fn present(
+        &mut self,
+        image: SwapchainImage,
+        time: Option<w::PresentTime>,
+    ) -> Result<(), w::PresentError> {
         let present = self.presentation.as_mut().unwrap();
         let sc = &mut present.swapchain;
         sc.acquired_count -= 1;
 
         let expected_index = sc.inner.GetCurrentBackBufferIndex() as w::SwapImageIndex;
         if image.index != expected_index {
-            log::warn!(
+            auxil::report!(
+                Warn,
+                "gfx::dx12::window",
+                "unexpected-frame-index",
                 "Expected frame {}, got frame {}",
                 expected_index,
                 image.index
@@ -63,12 +129,25 @@ impl Surface {
         }
 
         let (interval, flags) = match present.mode {
+            // Tearing is implied in exclusive full screen, and the flag isn't allowed there
+            w::PresentMode::IMMEDIATE if present.is_full_screen => (0, 0),
             w::PresentMode::IMMEDIATE => (0, dxgi::DXGI_PRESENT_ALLOW_TEARING),
             w::PresentMode::FIFO => (1, 0),
             _ => (1, 0), // Surface was created with an unsupported present mode, fall back to FIFO
         };
 
         sc.inner.Present(interval, flags);
+
+        // DXGI has no way to schedule a present, so only the feedback is provided.
+        if let Some(time) = time {
+            let mut present_count = 0;
+            if winerror::SUCCEEDED(sc.inner.GetLastPresentCount(&mut present_count)) {
+                if present.pending_times.len() == MAX_PENDING_PRESENT_TIMES {
+                    present.pending_times.pop_front();
+                }
+                present.pending_times.push_back((present_count, time));
+            }
+        }
         Ok(())
     }
 }
@@ -93,29 +172,20 @@ impl w::Surface<Backend> for Surface {
             })
         };
 
-        let allow_tearing = unsafe {
-            let (f5, hr) = self.factory.cast::<dxgi1_5::IDXGIFactory5>();
-            if winerror::SUCCEEDED(hr) {
-                let mut allow_tearing: BOOL = FALSE;
-                let hr = f5.CheckFeatureSupport(
-                    dxgi1_5::DXGI_FEATURE_PRESENT_ALLOW_TEARING,
-                    &mut allow_tearing as *mut _ as *mut _,
-                    mem::size_of::<BOOL>() as _,
-                );
-
-                f5.destroy();
-
-                winerror::SUCCEEDED(hr) && allow_tearing == TRUE
-            } else {
-                false
-            }
-        };
+        let allow_tearing = unsafe { supports_tearing(&self.factory) };
 
         let mut present_modes = w::PresentMode::FIFO;
         if allow_tearing {
             present_modes |= w::PresentMode::IMMEDIATE;
         }
 
+        // The color space support can only be checked on a swapchain,
+        // so only sRGB is reported until the surface is configured.
+        let color_spaces = match self.presentation {
+            Some(ref present) => unsafe { supported_color_spaces(&present.swapchain.inner) },
+            None => w::ColorSpace::SRGB_NONLINEAR,
+        };
+
         w::SurfaceCapabilities {
             present_modes,
             composite_alpha_modes: w::CompositeAlphaMode::OPAQUE, //TODO
@@ -130,6 +200,10 @@ impl w::Surface<Backend> for Surface {
             },
             max_image_layers: 1,
             usage: i::Usage::COLOR_ATTACHMENT | i::Usage::TRANSFER_SRC | i::Usage::TRANSFER_DST,
+            current_transform: w::SurfaceTransform::IDENTITY,
+            transforms: w::SurfaceTransform::IDENTITY,
+            color_spaces,
+            extended_dynamic_range: None,
         }
     }
 
@@ -174,17 +248,25 @@ impl w::PresentationSurface<Backend> for Surface {
     ) -> Result<(), w::SwapchainError> {
         assert!(i::Usage::COLOR_ATTACHMENT.contains(config.image_usage));
 
-        let swapchain = match self.presentation.take() {
-            Some(present) => {
-                if present.format == config.format && present.size == config.extent {
+        let mut is_full_screen = false;
+        let (swapchain, allow_tearing) = match self.presentation.take() {
+            Some(mut present) => {
+                if present.format == config.format
+                    && present.size == config.extent
+                    && present.full_screen_exclusive == config.full_screen_exclusive
+                    && present.color_space == config.color_space
+                {
+                    present.refresh_rate = config.refresh_rate;
                     self.presentation = Some(present);
                     return Ok(());
                 }
+                is_full_screen = present.is_full_screen;
                 // can't have image resources in flight used by GPU
                 device.wait_idle().unwrap();
 
+                // The flags have to match the ones the swapchain was created with
                 let mut flags = dxgi::DXGI_SWAP_CHAIN_FLAG_FRAME_LATENCY_WAITABLE_OBJECT;
-                if config.present_mode.contains(w::PresentMode::IMMEDIATE) {
+                if present.allow_tearing {
                     flags |= dxgi::DXGI_SWAP_CHAIN_FLAG_ALLOW_TEARING;
                 }
 
@@ -200,34 +282,85 @@ impl w::PresentationSurface<Backend> for Surface {
                     error!("ResizeBuffers failed with 0x{:x}", result as u32);
                     return Err(w::SwapchainError::WindowInUse);
                 }
-                inner
+                (inner, present.allow_tearing)
             }
             None => {
                 let (swapchain, _) =
                     device.create_swapchain_impl(&config, self.wnd_handle, self.factory.clone())?;
-                swapchain
+                (swapchain, supports_tearing(&self.factory))
             }
         };
 
-        // Disable automatic Alt+Enter handling by DXGI.
+        // The color space is reset along with the buffers.
+        let color_space = if supported_color_spaces(&swapchain).contains(config.color_space) {
+            config.color_space
+        } else {
+            error!("Color space {:?} is not supported", config.color_space);
+            w::ColorSpace::SRGB_NONLINEAR
+        };
+        let hr = swapchain.SetColorSpace1(conv::map_color_space(color_space));
+        if !winerror::SUCCEEDED(hr) {
+            error!("SetColorSpace1 failed with 0x{:x}", hr as u32);
+        }
+
+        // Disable automatic Alt+Enter handling by DXGI, unless the full screen is left to it.
         const DXGI_MWA_NO_WINDOW_CHANGES: u32 = 1;
         const DXGI_MWA_NO_ALT_ENTER: u32 = 2;
-        self.factory.MakeWindowAssociation(
-            self.wnd_handle,
-            DXGI_MWA_NO_WINDOW_CHANGES | DXGI_MWA_NO_ALT_ENTER,
-        );
+        let association_flags = match config.full_screen_exclusive {
+            w::FullScreenExclusive::Allowed => 0,
+            _ => DXGI_MWA_NO_WINDOW_CHANGES | DXGI_MWA_NO_ALT_ENTER,
+        };
+        self.factory
+            .MakeWindowAssociation(self.wnd_handle, association_flags);
+
+        if is_full_screen
+            && config.full_screen_exclusive != w::FullScreenExclusive::ApplicationControlled
+        {
+            swapchain.SetFullscreenState(FALSE, ptr::null_mut());
+            is_full_screen = false;
+        }
 
         self.presentation = Some(Presentation {
             swapchain: device.wrap_swapchain(swapchain, &config),
             format: config.format,
             size: config.extent,
             mode: config.present_mode,
+            allow_tearing,
+            full_screen_exclusive: config.full_screen_exclusive,
+            refresh_rate: config.refresh_rate,
+            color_space: config.color_space,
+            is_full_screen,
+            pending_times: VecDeque::new(),
         });
         Ok(())
     }
 
+    unsafe fn set_present_mode(
+        &mut self,
+        mode: w::PresentMode,
+    ) -> Result<(), w::PresentModeIncompatible> {
+        // The sync interval is given with each present
+        match self.presentation {
+            Some(ref mut present)
+                if mode == w::PresentMode::FIFO
+                    || (mode == w::PresentMode::IMMEDIATE && present.allow_tearing) =>
+            {
+                present.mode = mode;
+                Ok(())
+            }
+            _ => Err(w::PresentModeIncompatible),
+        }
+    }
+
     unsafe fn unconfigure_swapchain(&mut self, device: &Device) {
         if let Some(mut present) = self.presentation.take() {
+            // Swapchains can't be released in exclusive full screen
+            if present.is_full_screen {
+                present
+                    .swapchain
+                    .inner
+                    .SetFullscreenState(FALSE, ptr::null_mut());
+            }
             let _ = present.swapchain.wait(winbase::INFINITE);
             let _ = device.wait_idle(); //TODO: this shouldn't be needed,
                                         // but it complains that the queue is still used otherwise
@@ -288,6 +421,8 @@ impl w::PresentationSurface<Backend> for Surface {
                 size: 0,
                 alignment: 1,
                 type_mask: 0,
+                prefers_dedicated: false,
+                requires_dedicated: false,
             },
         };
 
@@ -310,6 +445,176 @@ impl w::PresentationSurface<Backend> for Surface {
 
         Ok((swapchain_image, None))
     }
+
+    unsafe fn acquire_full_screen_exclusive(&mut self) -> Result<(), w::FullScreenExclusiveError> {
+        let present = match self.presentation {
+            Some(ref mut present)
+                if present.full_screen_exclusive
+                    == w::FullScreenExclusive::ApplicationControlled =>
+            {
+                present
+            }
+            _ => return Err(w::FullScreenExclusiveError::NotApplicationControlled),
+        };
+        if present.is_full_screen {
+            return Ok(());
+        }
+
+        if let Some(rate) = present.refresh_rate {
+            let mode = dxgitype::DXGI_MODE_DESC {
+                Width: present.size.width,
+                Height: present.size.height,
+                RefreshRate: dxgitype::DXGI_RATIONAL {
+                    Numerator: rate.numerator,
+                    Denominator: rate.denominator,
+                },
+                Format: dxgiformat::DXGI_FORMAT_UNKNOWN,
+                ScanlineOrdering: dxgitype::DXGI_MODE_SCANLINE_ORDER_UNSPECIFIED,
+                Scaling: dxgitype::DXGI_MODE_SCALING_UNSPECIFIED,
+            };
+            let hr = present.swapchain.inner.ResizeTarget(&mode);
+            if !winerror::SUCCEEDED(hr) {
+                warn!("ResizeTarget failed with 0x{:x}", hr as u32);
+            }
+        }
+
+        match present
+            .swapchain
+            .inner
+            .SetFullscreenState(TRUE, ptr::null_mut())
+        {
+            winerror::S_OK => {
+                present.is_full_screen = true;
+                Ok(())
+            }
+            winerror::DXGI_ERROR_NOT_CURRENTLY_AVAILABLE
+            | winerror::DXGI_STATUS_MODE_CHANGE_IN_PROGRESS => {
+                Err(w::FullScreenExclusiveError::NotAvailable)
+            }
+            winerror::E_OUTOFMEMORY => Err(hal::device::OutOfMemory::Host.into()),
+            hr => {
+                error!("SetFullscreenState failed with 0x{:x}", hr as u32);
+                Err(w::FullScreenExclusiveError::NotAvailable)
+            }
+        }
+    }
+
+    unsafe fn get_refresh_duration(&mut self) -> Option<u64> {
+        let present = self.presentation.as_ref()?;
+        match present.refresh_rate {
+            // In exclusive full screen, the display runs at the requested mode.
+            Some(rate) if present.is_full_screen && rate.numerator != 0 => {
+                Some(1_000_000_000 * rate.denominator as u64 / rate.numerator as u64)
+            }
+            _ => {
+                let mut info: dwmapi::DWM_TIMING_INFO = mem::zeroed();
+                info.cbSize = mem::size_of::<dwmapi::DWM_TIMING_INFO>() as _;
+                let hr = dwmapi::DwmGetCompositionTimingInfo(ptr::null_mut(), &mut info);
+                if winerror::SUCCEEDED(hr) {
+                    Some(qpc_to_nanoseconds(info.qpcRefreshPeriod))
+                } else {
+                    auxil::report!(
+                        Warn,
+                        "gfx::dx12::window",
+                        "composition-timing-failed",
+                        "DwmGetCompositionTimingInfo failed with 0x{:x}",
+                        hr as u32
+                    );
+                    None
+                }
+            }
+        }
+    }
+
+    unsafe fn get_past_presentation_timing(&mut self) -> Vec<w::PresentationTiming> {
+        let present = match self.presentation {
+            Some(ref mut present) => present,
+            None => return Vec::new(),
+        };
+        if present.pending_times.is_empty() {
+            return Vec::new();
+        }
+
+        // Statistics are only reported for the last displayed present.
+        let mut stats: dxgi::DXGI_FRAME_STATISTICS = mem::zeroed();
+        if !winerror::SUCCEEDED(present.swapchain.inner.GetFrameStatistics(&mut stats)) {
+            return Vec::new();
+        }
+
+        let mut timings = Vec::new();
+        while let Some(&(present_count, time)) = present.pending_times.front() {
+            if present_count > stats.PresentCount {
+                break;
+            }
+            present.pending_times.pop_front();
+            if present_count == stats.PresentCount {
+                timings.push(w::PresentationTiming {
+                    present_id: time.present_id,
+                    desired_present_time: time.desired_present_time,
+                    actual_present_time: qpc_to_nanoseconds(*stats.SyncQPCTime.QuadPart() as u64),
+                });
+            }
+        }
+        timings
+    }
+
+    unsafe fn release_full_screen_exclusive(&mut self) -> Result<(), w::FullScreenExclusiveError> {
+        let present = match self.presentation {
+            Some(ref mut present)
+                if present.full_screen_exclusive
+                    == w::FullScreenExclusive::ApplicationControlled =>
+            {
+                present
+            }
+            _ => return Err(w::FullScreenExclusiveError::NotApplicationControlled),
+        };
+        if present.is_full_screen {
+            present
+                .swapchain
+                .inner
+                .SetFullscreenState(FALSE, ptr::null_mut());
+            present.is_full_screen = false;
+        }
+        Ok(())
+    }
+
+    unsafe fn set_hdr_metadata(
+        &mut self,
+        metadata: &w::HdrMetadata,
+    ) -> Result<(), w::HdrMetadataUnsupported> {
+        let present = self
+            .presentation
+            .as_ref()
+            .ok_or(w::HdrMetadataUnsupported)?;
+        let (swapchain4, hr) = present.swapchain.inner.cast::<dxgi1_5::IDXGISwapChain4>();
+        if !winerror::SUCCEEDED(hr) {
+            return Err(w::HdrMetadataUnsupported);
+        }
+        // Chromaticities are in units of 0.00002, and the minimum luminance in units of 0.0001 nits
+        let xy = |[x, y]: [f32; 2]| [(x * 50000.0) as u16, (y * 50000.0) as u16];
+        let mut hdr10 = dxgi1_5::DXGI_HDR_METADATA_HDR10 {
+            RedPrimary: xy(metadata.red_primary),
+            GreenPrimary: xy(metadata.green_primary),
+            BluePrimary: xy(metadata.blue_primary),
+            WhitePoint: xy(metadata.white_point),
+            MaxMasteringLuminance: metadata.max_luminance as u32,
+            MinMasteringLuminance: (metadata.min_luminance * 10000.0) as u32,
+            MaxContentLightLevel: metadata.max_content_light_level as u16,
+            MaxFrameAverageLightLevel: metadata.max_frame_average_light_level as u16,
+        };
+        let hr = swapchain4.SetHDRMetaData(
+            dxgi1_5::DXGI_HDR_METADATA_TYPE_HDR10,
+            mem::size_of::<dxgi1_5::DXGI_HDR_METADATA_HDR10>() as _,
+            &mut hdr10 as *mut _ as *mut _,
+        );
+        swapchain4.destroy();
+        if winerror::SUCCEEDED(hr) {
+            Ok(())
+        } else {
+            error!("SetHDRMetaData failed with 0x{:x}", hr as u32);
+            Err(w::HdrMetadataUnsupported)
+        }
+    }
 }
 
 #[derive(Debug)]
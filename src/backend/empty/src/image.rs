@@ -25,6 +25,8 @@ impl Image {
             size,
             alignment: 1,
             type_mask: !0,
+            prefers_dedicated: false,
+            requires_dedicated: false,
         }
     }
 }
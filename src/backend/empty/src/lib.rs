@@ -47,6 +47,7 @@ impl hal::Backend for Backend {
     type Image = Image;
     type ImageView = ();
     type Sampler = ();
+    type SamplerYcbcrConversion = ();
 
     type ComputePipeline = ();
     type GraphicsPipeline = ();
@@ -162,10 +163,17 @@ impl adapter::PhysicalDevice<Backend> for PhysicalDevice {
 #[derive(Debug)]
 pub struct Queue;
 impl queue::Queue<Backend> for Queue {
-    unsafe fn submit<'a, Ic, Iw, Is>(&mut self, _: Ic, _: Iw, _: Is, _: Option<&mut ()>)
+    unsafe fn submit<'a, Ic, Iw, Is>(
+        &mut self,
+        _: Ic,
+        _: Iw,
+        _: Is,
+        _: Option<&mut ()>,
+    ) -> Result<(), queue::SubmitError>
     where
         Ic: Iterator<Item = &'a CommandBuffer>,
     {
+        Ok(())
     }
 
     unsafe fn present(
@@ -177,7 +185,7 @@ impl queue::Queue<Backend> for Queue {
         Ok(None)
     }
 
-    fn wait_idle(&mut self) -> Result<(), device::OutOfMemory> {
+    fn wait_idle(&mut self) -> Result<(), device::WaitError> {
         unimplemented!("{}", NOT_SUPPORTED_MESSAGE)
     }
 
@@ -293,6 +301,21 @@ impl device::Device<Backend> for Device {
         Ok(())
     }
 
+    unsafe fn create_sampler_ycbcr_conversion(
+        &self,
+        _: &hal::image::SamplerYcbcrConversionDesc,
+    ) -> Result<(), hal::image::CreationError> {
+        Ok(())
+    }
+
+    unsafe fn create_ycbcr_sampler(
+        &self,
+        _: &hal::image::SamplerDesc,
+        _: &(),
+    ) -> Result<(), device::AllocationError> {
+        Ok(())
+    }
+
     unsafe fn create_buffer(
         &self,
         size: u64,
@@ -307,6 +330,8 @@ impl device::Device<Backend> for Device {
             // TODO: perhaps require stronger alignments?
             alignment: 1,
             type_mask: !0,
+            prefers_dedicated: false,
+            requires_dedicated: false,
         }
     }
 
@@ -372,6 +397,16 @@ impl device::Device<Backend> for Device {
         Ok(())
     }
 
+    unsafe fn create_ycbcr_image_view(
+        &self,
+        _: &Image,
+        _: hal::image::ViewKind,
+        _: hal::image::SubresourceRange,
+        _: &(),
+    ) -> Result<(), hal::image::ViewCreationError> {
+        Ok(())
+    }
+
     unsafe fn create_descriptor_pool<I>(
         &self,
         _: usize,
@@ -462,6 +497,32 @@ impl device::Device<Backend> for Device {
 
     unsafe fn unmap_memory(&self, _memory: &mut Memory) {}
 
+    unsafe fn copy_memory_to_image<I>(
+        &self,
+        _data: &[u8],
+        _image: &Image,
+        _layout: hal::image::Layout,
+        _regions: I,
+    ) -> Result<(), device::MapError>
+    where
+        I: Iterator<Item = command::BufferImageCopy>,
+    {
+        unimplemented!("{}", NOT_SUPPORTED_MESSAGE)
+    }
+
+    unsafe fn copy_image_to_memory<I>(
+        &self,
+        _image: &Image,
+        _layout: hal::image::Layout,
+        _data: &mut [u8],
+        _regions: I,
+    ) -> Result<(), device::MapError>
+    where
+        I: Iterator<Item = command::BufferImageCopy>,
+    {
+        unimplemented!("{}", NOT_SUPPORTED_MESSAGE)
+    }
+
     unsafe fn flush_mapped_memory_ranges<'a, I>(&self, _: I) -> Result<(), device::OutOfMemory>
     where
         I: Iterator<Item = (&'a Memory, hal::memory::Segment)>,
@@ -505,6 +566,8 @@ impl device::Device<Backend> for Device {
 
     unsafe fn destroy_sampler(&self, _: ()) {}
 
+    unsafe fn destroy_sampler_ycbcr_conversion(&self, _: ()) {}
+
     unsafe fn destroy_descriptor_pool(&self, _: DescriptorPool) {}
 
     unsafe fn destroy_descriptor_set_layout(&self, _: DescriptorSetLayout) {}
@@ -517,7 +580,7 @@ impl device::Device<Backend> for Device {
         unimplemented!("{}", NOT_SUPPORTED_MESSAGE)
     }
 
-    fn wait_idle(&self) -> Result<(), device::OutOfMemory> {
+    fn wait_idle(&self) -> Result<(), device::WaitError> {
         Ok(())
     }
 
@@ -619,9 +682,7 @@ impl command::CommandBuffer<Backend> for CommandBuffer {
 
     unsafe fn finish(&mut self) {}
 
-    unsafe fn reset(&mut self, _: bool) {
-        unimplemented!("{}", NOT_SUPPORTED_MESSAGE)
-    }
+    unsafe fn reset(&mut self, _: bool) {}
 
     unsafe fn pipeline_barrier<'a, T>(
         &mut self,
@@ -725,6 +786,18 @@ impl command::CommandBuffer<Backend> for CommandBuffer {
         unimplemented!("{}", NOT_SUPPORTED_MESSAGE)
     }
 
+    unsafe fn set_shading_rate(&mut self, _: pso::ShadingRate, _: [pso::ShadingRateCombiner; 2]) {
+        unimplemented!("{}", NOT_SUPPORTED_MESSAGE)
+    }
+
+    unsafe fn set_sample_locations(
+        &mut self,
+        _: hal::image::NumSamples,
+        _: &[pso::SampleLocation],
+    ) {
+        unimplemented!("{}", NOT_SUPPORTED_MESSAGE)
+    }
+
     unsafe fn begin_render_pass<'a, T>(
         &mut self,
         _: &(),
@@ -752,6 +825,14 @@ impl command::CommandBuffer<Backend> for CommandBuffer {
         // Do nothing
     }
 
+    unsafe fn begin_rendering(&mut self, _: &command::RenderingInfo<Backend>) {
+        unimplemented!("{}", NOT_SUPPORTED_MESSAGE)
+    }
+
+    unsafe fn end_rendering(&mut self) {
+        unimplemented!("{}", NOT_SUPPORTED_MESSAGE)
+    }
+
     unsafe fn bind_compute_pipeline(&mut self, _: &()) {
         unimplemented!("{}", NOT_SUPPORTED_MESSAGE)
     }
@@ -954,6 +1035,10 @@ impl command::CommandBuffer<Backend> for CommandBuffer {
     unsafe fn end_debug_marker(&mut self) {
         unimplemented!("{}", NOT_SUPPORTED_MESSAGE)
     }
+
+    unsafe fn set_checkpoint(&mut self, _: &str) {
+        unimplemented!("{}", NOT_SUPPORTED_MESSAGE)
+    }
 }
 
 /// Dummy surface.
@@ -987,6 +1072,10 @@ impl window::Surface<Backend> for Surface {
             usage,
             present_modes,
             composite_alpha_modes,
+            current_transform: window::SurfaceTransform::IDENTITY,
+            transforms: window::SurfaceTransform::IDENTITY,
+            color_spaces: window::ColorSpace::SRGB_NONLINEAR,
+            extended_dynamic_range: None,
         }
     }
 
@@ -19,6 +19,8 @@ use parking_lot::Mutex;
 
 use std::{iter, mem, ops::Range, slice, sync::Arc};
 
+const NOT_SUPPORTED_MESSAGE: &str = "This function is not supported by the GL backend";
+
 // Command buffer implementation details:
 //
 // The underlying commands and data are stored inside the associated command pool.
@@ -78,8 +80,16 @@ pub enum Command {
         uniform: n::UniformDesc,
         buffer: BufferSlice,
     },
+    /// Upload the contents of a uniform buffer into an emulated uniform block.
+    SetUniformBlock {
+        block: n::UniformBlockDesc,
+        buffer: n::RawBuffer,
+        offset: i32,
+        size: i32,
+    },
     BindRasterizer {
         rasterizer: pso::Rasterizer,
+        multisampling: Option<pso::Multisampling>,
     },
     BindDepth(Option<pso::Comparison>),
     SetViewports {
@@ -89,6 +99,7 @@ pub enum Command {
     },
     SetScissors(u32, BufferSlice),
     SetBlendColor(pso::ColorValue),
+    SetDepthBias(pso::DepthBias),
 
     /// Clear floating-point color drawbuffer of bound framebuffer.
     ClearBufferColorF(DrawBuffer, [f32; 4]),
@@ -98,9 +109,13 @@ pub enum Command {
     ClearBufferColorI(DrawBuffer, [i32; 4]),
     /// Clear depth-stencil drawbuffer of bound framebuffer.
     ClearBufferDepthStencil(Option<pso::DepthValue>, Option<pso::StencilValue>),
+    /// Restrict subsequent clears to the given rectangle, or lift the restriction.
+    SetClearRect(Option<[i32; 4]>),
     /// Clear the currently bound texture with the given color.
     ClearTexture([f32; 4]),
     FillBuffer(n::RawBuffer, Range<buffer::Offset>, u32),
+    BeginQuery(n::Query, u32),
+    EndQuery(u32),
 
     BindFramebuffer {
         target: FrameBufferTarget,
@@ -114,7 +129,7 @@ pub enum Command {
     SetBlend(Option<pso::BlendState>),
     SetBlendSlot(ColorSlot, Option<pso::BlendState>),
     BindAttribute(n::AttributeDesc, n::RawBuffer, i32, u32),
-    //UnbindAttribute(n::AttributeDesc),
+    UnbindAttribute(u32),
     CopyBufferToBuffer(n::RawBuffer, n::RawBuffer, command::BufferCopy),
     CopyBufferToTexture {
         src_buffer: n::RawBuffer,
@@ -148,6 +163,8 @@ pub enum Command {
     },
 
     BindBufferRange(u32, u32, n::RawBuffer, i32, i32),
+    /// Bind `(buffer, offset, size)` ranges to the consecutive indices starting at the given one.
+    BindBufferRanges(u32, u32, BufferSlice),
     BindTexture(u32, n::Texture, n::TextureTarget),
     BindSampler(u32, n::Sampler),
     SetTextureSamplerSettings(u32, n::TextureTarget, image::SamplerDesc),
@@ -158,6 +175,7 @@ pub enum Command {
     SetStencilMaskSeparate(pso::Sided<pso::StencilValue>),
 
     MemoryBarrier(u32),
+    TextureBarrier,
 }
 
 pub type FrameBufferTarget = u32;
@@ -182,6 +200,56 @@ struct TextureSlotInfo {
     sampler_index: Option<u8>,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum BindPoint {
+    Graphics,
+    Compute,
+}
+
+// Resolved descriptor set bindings, keyed by the GL binding index.
+type SetBindings = Vec<(u32, n::DescSetBindings)>;
+
+// Buffer, offset and size of a range bound to an indexed buffer target.
+type BufferRange = (n::RawBuffer, i32, i32);
+
+fn is_dynamic_buffer(ty: pso::DescriptorType) -> bool {
+    match ty {
+        pso::DescriptorType::Buffer {
+            format: pso::BufferDescriptorFormat::Structured { dynamic_offset },
+            ..
+        } => dynamic_offset,
+        _ => false,
+    }
+}
+
+// Pipeline and descriptor state recorded for a single bind point.
+//
+// GL only has one set of binding slots shared by draws and dispatches,
+// so the state of both bind points is tracked separately and flushed
+// into GL whenever the other bind point gets used.
+#[derive(Debug)]
+struct BindPointState {
+    // Program of the bound pipeline.
+    program: Option<n::Program>,
+    // Sampler redirection map of the bound pipeline.
+    sampler_map: n::SamplerBindMap,
+    // Immutable samplers of the bound pipeline's layout.
+    immutable_samplers: Vec<(u8, n::FatSampler)>,
+    // Bound descriptor sets, indexed by set number.
+    sets: Vec<Option<SetBindings>>,
+}
+
+impl BindPointState {
+    fn new() -> Self {
+        BindPointState {
+            program: None,
+            sampler_map: [None; MAX_TEXTURE_SLOTS],
+            immutable_samplers: Vec::new(),
+            sets: Vec::new(),
+        }
+    }
+}
+
 // Cache current states of the command buffer
 #[derive(Debug)]
 struct Cache {
@@ -212,6 +280,16 @@ struct Cache {
     attributes: Vec<n::AttributeDesc>,
     // Active uniforms
     uniforms: Vec<n::UniformDesc>,
+    // Active emulated uniform blocks.
+    uniform_blocks: Vec<n::UniformBlockDesc>,
+    // Uniform buffers bound to the emulated uniform blocks, per slot.
+    uniform_buffers: [Option<(n::RawBuffer, i32, i32)>; MAX_TEXTURE_SLOTS],
+    // Indicates that the emulated uniform blocks have to be uploaded before the next draw.
+    dirty_uniform_blocks: bool,
+    // Buffer ranges bound to the indexed uniform buffer targets.
+    bound_uniform_buffers: Vec<Option<BufferRange>>,
+    // Buffer ranges bound to the indexed storage buffer targets.
+    bound_storage_buffers: Vec<Option<BufferRange>>,
     // Current depth mask
     depth_mask: Option<bool>,
     // Current stencil mask
@@ -220,6 +298,12 @@ struct Cache {
     samplers: Vec<Option<n::FatSampler>>,
     /// Current sampler redirection map.
     texture_slots: [TextureSlotInfo; MAX_TEXTURE_SLOTS],
+    /// Bind point whose state is currently applied.
+    bind_point: Option<BindPoint>,
+    /// State bound to the graphics bind point.
+    graphics: BindPointState,
+    /// State bound to the compute bind point.
+    compute: BindPointState,
 }
 
 impl Cache {
@@ -238,10 +322,25 @@ impl Cache {
             vertex_buffer_descs: Vec::new(),
             attributes: Vec::new(),
             uniforms: Vec::new(),
+            uniform_blocks: Vec::new(),
+            uniform_buffers: [None; MAX_TEXTURE_SLOTS],
+            dirty_uniform_blocks: false,
+            bound_uniform_buffers: Vec::new(),
+            bound_storage_buffers: Vec::new(),
             depth_mask: None,
             stencil_mask: None,
             samplers: (0..MAX_SAMPLERS).map(|_| None).collect(),
             texture_slots: [TextureSlotInfo::default(); MAX_TEXTURE_SLOTS],
+            bind_point: None,
+            graphics: BindPointState::new(),
+            compute: BindPointState::new(),
+        }
+    }
+
+    fn bind_point_state(&mut self, bind_point: BindPoint) -> &mut BindPointState {
+        match bind_point {
+            BindPoint::Graphics => &mut self.graphics,
+            BindPoint::Compute => &mut self.compute,
         }
     }
 }
@@ -251,12 +350,16 @@ impl Cache {
 #[derive(Debug, Clone, Copy)]
 pub struct Limits {
     max_viewports: usize,
+    min_uniform_buffer_offset_alignment: buffer::Offset,
+    min_storage_buffer_offset_alignment: buffer::Offset,
 }
 
 impl From<hal::Limits> for Limits {
     fn from(l: hal::Limits) -> Self {
         Limits {
             max_viewports: l.max_viewports,
+            min_uniform_buffer_offset_alignment: l.min_uniform_buffer_offset_alignment,
+            min_storage_buffer_offset_alignment: l.min_storage_buffer_offset_alignment,
         }
     }
 }
@@ -322,7 +425,7 @@ impl CommandStorage {
         slice
     }
 
-    fn reset(&mut self) {
+    fn reset(&mut self, release_resources: bool) {
         let mut memory = self
             .memory
             .try_lock()
@@ -334,11 +437,9 @@ impl CommandStorage {
             BufferMemory::Individual {
                 ref mut storage, ..
             } => {
-                // TODO: should use the `release_resources` and shrink the buffers?
-                storage.get_mut(&self.id).map(|buffer| {
-                    buffer.commands.clear();
-                    buffer.data.clear();
-                });
+                if let Some(buffer) = storage.get_mut(&self.id) {
+                    buffer.reset(release_resources);
+                }
             }
         }
     }
@@ -489,11 +590,19 @@ impl CommandBuffer {
         for attribute in attributes {
             let binding = attribute.binding as usize;
 
-            if vertex_buffers.len() <= binding {
-                error!("No vertex buffer bound at {}", binding);
-            }
-
-            let (handle, range) = vertex_buffers[binding].as_ref().unwrap();
+            let (handle, range) = match vertex_buffers.get(binding) {
+                Some(&Some(ref bound)) => bound,
+                _ => {
+                    auxil::report!(
+                        Error,
+                        "gfx::gl::command",
+                        "missing-vertex-buffer",
+                        "No vertex buffer bound at {}",
+                        binding
+                    );
+                    continue;
+                }
+            };
 
             let mut attribute = attribute.clone();
             attribute.offset += range.start as u32;
@@ -511,7 +620,13 @@ impl CommandBuffer {
                         desc.rate.as_uint() as u32,
                     ));
                 }
-                _ => error!("No vertex buffer description bound at {}", binding),
+                _ => auxil::report!(
+                    Error,
+                    "gfx::gl::command",
+                    "missing-vertex-buffer-description",
+                    "No vertex buffer description bound at {}",
+                    binding
+                ),
             }
         }
     }
@@ -534,13 +649,17 @@ impl CommandBuffer {
         self.data
             .push_cmd(Command::SetColorMask(None, pso::ColorMask::ALL));
 
-        for (rat, info) in state
+        for (index, (rat, info)) in state
             .render_pass
             .attachments
             .iter()
             .zip(state.attachments.iter())
+            .enumerate()
         {
-            //TODO: check if the subpass is one where we need this clear
+            // Only the first sub-pass using an attachment clears it.
+            if state.render_pass.first_subpass_using(index) != self.cur_subpass {
+                continue;
+            }
 
             // View format needs to be known at this point.
             // All attachments specified in the renderpass must have a valid,
@@ -653,6 +772,7 @@ impl CommandBuffer {
 
     fn bind_descriptor_sets<'a, I, J>(
         &mut self,
+        bind_point: BindPoint,
         layout: &n::PipelineLayout,
         first_set: usize,
         sets: I,
@@ -661,16 +781,12 @@ impl CommandBuffer {
         I: Iterator<Item = &'a n::DescriptorSet>,
         J: Iterator<Item = command::DescriptorSetOffset>,
     {
-        if let Some(_) = offsets.next() {
-            warn!("Dynamic offsets are not supported yet");
-        }
-
-        let mut dirty_textures = 0u32;
-        let mut dirty_samplers = 0u32;
+        let is_active = self.cache.bind_point == Some(bind_point);
         let mut set = first_set as usize;
         for desc_set in sets {
+            let mut bindings = SetBindings::with_capacity(desc_set.bindings.len());
             for (binding_layout, new_binding) in
-                desc_set.layout.iter().zip(desc_set.bindings.iter())
+                desc_set.layout.bindings.iter().zip(&desc_set.bindings)
             {
                 let binding = layout.sets[set].bindings[binding_layout.binding as usize] as u32;
                 match *new_binding {
@@ -679,43 +795,333 @@ impl CommandBuffer {
                         buffer,
                         offset,
                         size,
-                    } => {
-                        let bind_point = match register {
-                            n::BindingRegister::UniformBuffers => glow::UNIFORM_BUFFER,
-                            n::BindingRegister::StorageBuffers => glow::SHADER_STORAGE_BUFFER,
-                            n::BindingRegister::Textures => panic!("Wrong desc set binding"),
-                        };
-                        self.data.push_cmd(Command::BindBufferRange(
-                            bind_point,
+                    } if is_dynamic_buffer(binding_layout.ty) => {
+                        let dynamic_offset = self.dynamic_offset(register, offsets.next());
+                        bindings.push((
                             binding,
-                            buffer,
-                            offset as i32,
-                            size as i32,
+                            n::DescSetBindings::Buffer {
+                                register,
+                                buffer,
+                                offset: offset + dynamic_offset,
+                                size,
+                            },
                         ));
                     }
-                    n::DescSetBindings::Texture(texture, textype) => {
-                        dirty_textures |= 1 << binding;
-                        self.cache.texture_slots[binding as usize].tex_target = textype;
-                        self.data
-                            .push_cmd(Command::BindTexture(binding, texture, textype));
-                    }
-                    n::DescSetBindings::Sampler(sampler) => {
-                        dirty_samplers |= 1 << binding;
-                        self.cache.samplers[binding as usize] =
-                            Some(n::FatSampler::Sampler(sampler));
-                    }
-                    n::DescSetBindings::SamplerDesc(ref info) => {
-                        dirty_samplers |= 1 << binding;
-                        self.cache.samplers[binding as usize] =
-                            Some(n::FatSampler::Info(info.clone()));
+                    // Split into the texture and its sampler slot, paired by the layout.
+                    n::DescSetBindings::CombinedImageSampler(texture, textype, ref sampler) => {
+                        bindings.push((binding, n::DescSetBindings::Texture(texture, textype)));
+                        let sampler_slot = match layout.sampler_map[binding as usize] {
+                            Some(slot) => slot as u32,
+                            None => panic!("Combined image sampler without a paired sampler slot"),
+                        };
+                        bindings.push((
+                            sampler_slot,
+                            match *sampler {
+                                n::FatSampler::Sampler(sampler) => {
+                                    n::DescSetBindings::Sampler(sampler)
+                                }
+                                n::FatSampler::Info(ref info) => {
+                                    n::DescSetBindings::SamplerDesc(info.clone())
+                                }
+                            },
+                        ));
                     }
+                    ref other => bindings.push((binding, other.clone())),
                 }
             }
 
+            if is_active {
+                self.apply_bindings(&bindings);
+            }
+
+            let state = self.cache.bind_point_state(bind_point);
+            if state.sets.len() <= set {
+                state.sets.resize(set + 1, None);
+            }
+            state.sets[set] = Some(bindings);
+
             set += 1;
         }
 
-        self.update_sampler_states(dirty_textures, dirty_samplers);
+        if offsets.next().is_some() {
+            auxil::report!(
+                Warn,
+                "gfx::gl::command",
+                "extra-dynamic-offsets",
+                "More dynamic offsets are provided than there are dynamic buffers in the sets"
+            );
+        }
+    }
+
+    // Validate the dynamic offset of a buffer binding.
+    fn dynamic_offset(
+        &mut self,
+        register: n::BindingRegister,
+        offset: Option<command::DescriptorSetOffset>,
+    ) -> i32 {
+        let offset = match offset {
+            Some(offset) => offset,
+            None => {
+                auxil::report!(
+                    Warn,
+                    "gfx::gl::command",
+                    "missing-dynamic-offset",
+                    "Not enough dynamic offsets for the dynamic buffers in the sets"
+                );
+                self.cache.error_state = true;
+                return 0;
+            }
+        };
+        let alignment = match register {
+            n::BindingRegister::UniformBuffers => self.limits.min_uniform_buffer_offset_alignment,
+            _ => self.limits.min_storage_buffer_offset_alignment,
+        };
+        if offset as buffer::Offset % alignment != 0 {
+            auxil::report!(
+                Warn,
+                "gfx::gl::command",
+                "unaligned-dynamic-offset",
+                "Dynamic offset {} is not a multiple of {}",
+                offset,
+                alignment
+            );
+            self.cache.error_state = true;
+        }
+        offset as i32
+    }
+
+    // Bind a buffer range to an indexed target, unless it's already bound there.
+    // Returns `false` if there is nothing to do.
+    fn cache_buffer_range(&mut self, target: u32, index: u32, range: BufferRange) -> bool {
+        let bound = match target {
+            glow::UNIFORM_BUFFER => &mut self.cache.bound_uniform_buffers,
+            _ => &mut self.cache.bound_storage_buffers,
+        };
+        if bound.len() <= index as usize {
+            bound.resize(index as usize + 1, None);
+        }
+        bound[index as usize].replace(range) != Some(range)
+    }
+
+    // Record the new indexed buffer bindings, with a single command per run of consecutive indices.
+    fn bind_buffer_ranges(&mut self, mut ranges: Vec<(u32, u32, BufferRange)>) {
+        ranges.sort_by_key(|&(target, index, _)| (target, index));
+        let mut rest = &ranges[..];
+        while let Some(&(target, first, range)) = rest.first() {
+            let count = rest
+                .iter()
+                .enumerate()
+                .take_while(|&(i, &(t, index, _))| t == target && index == first + i as u32)
+                .count();
+            if count == 1 {
+                let (buffer, offset, size) = range;
+                self.data.push_cmd(Command::BindBufferRange(
+                    target, first, buffer, offset, size,
+                ));
+            } else {
+                let run = rest[..count]
+                    .iter()
+                    .map(|&(_, _, range)| range)
+                    .collect::<Vec<_>>();
+                let ptr = self.data.add(&run);
+                self.data
+                    .push_cmd(Command::BindBufferRanges(target, first, ptr));
+            }
+            rest = &rest[count..];
+        }
+    }
+
+    fn apply_bindings(&mut self, bindings: &[(u32, n::DescSetBindings)]) {
+        let emulate_uniform_buffers = !self
+            .legacy_featues
+            .contains(info::LegacyFeatures::CONSTANT_BUFFER);
+
+        // Fast path for a set with a single uniform buffer, which is typically
+        // a dynamic one rebound with a new offset before every draw.
+        if let [(
+            binding,
+            n::DescSetBindings::Buffer {
+                register: n::BindingRegister::UniformBuffers,
+                buffer,
+                offset,
+                size,
+            },
+        )] = *bindings
+        {
+            if !emulate_uniform_buffers {
+                let range = (buffer, offset, size);
+                if self.cache_buffer_range(glow::UNIFORM_BUFFER, binding, range) {
+                    self.data.push_cmd(Command::BindBufferRange(
+                        glow::UNIFORM_BUFFER,
+                        binding,
+                        buffer,
+                        offset,
+                        size,
+                    ));
+                }
+                return;
+            }
+        }
+
+        let mut dirty_textures = 0u32;
+        let mut dirty_samplers = 0u32;
+        let mut buffer_ranges = Vec::new();
+        for &(binding, ref new_binding) in bindings {
+            match *new_binding {
+                n::DescSetBindings::Buffer {
+                    register,
+                    buffer,
+                    offset,
+                    size,
+                } => {
+                    let target = match register {
+                        n::BindingRegister::UniformBuffers if emulate_uniform_buffers => {
+                            self.cache.uniform_buffers[binding as usize] =
+                                Some((buffer, offset as i32, size as i32));
+                            self.cache.dirty_uniform_blocks = true;
+                            continue;
+                        }
+                        n::BindingRegister::UniformBuffers => glow::UNIFORM_BUFFER,
+                        n::BindingRegister::StorageBuffers => glow::SHADER_STORAGE_BUFFER,
+                        n::BindingRegister::Textures => panic!("Wrong desc set binding"),
+                    };
+                    let range = (buffer, offset as i32, size as i32);
+                    if self.cache_buffer_range(target, binding, range) {
+                        buffer_ranges.push((target, binding, range));
+                    }
+                }
+                n::DescSetBindings::Texture(texture, textype) => {
+                    dirty_textures |= 1 << binding;
+                    self.cache.texture_slots[binding as usize].tex_target = textype;
+                    self.data
+                        .push_cmd(Command::BindTexture(binding, texture, textype));
+                }
+                n::DescSetBindings::Sampler(sampler) => {
+                    dirty_samplers |= 1 << binding;
+                    self.cache.samplers[binding as usize] = Some(n::FatSampler::Sampler(sampler));
+                }
+                n::DescSetBindings::SamplerDesc(ref info) => {
+                    dirty_samplers |= 1 << binding;
+                    self.cache.samplers[binding as usize] = Some(n::FatSampler::Info(info.clone()));
+                }
+                n::DescSetBindings::CombinedImageSampler(..) => {
+                    panic!("Combined image samplers are split when binding")
+                }
+            }
+        }
+
+        if !buffer_ranges.is_empty() {
+            self.bind_buffer_ranges(buffer_ranges);
+        }
+        if dirty_textures != 0 || dirty_samplers != 0 {
+            self.update_sampler_states(dirty_textures, dirty_samplers);
+        }
+    }
+
+    // Upload the bound uniform buffers into the emulated uniform blocks, if they changed.
+    fn update_uniform_blocks(&mut self) {
+        if !self.cache.dirty_uniform_blocks {
+            return;
+        }
+        self.cache.dirty_uniform_blocks = false;
+
+        for block in self.cache.uniform_blocks.iter() {
+            match self.cache.uniform_buffers[block.slot as usize] {
+                Some((buffer, offset, size)) => self.data.push_cmd(Command::SetUniformBlock {
+                    block: block.clone(),
+                    buffer,
+                    offset,
+                    size,
+                }),
+                None => auxil::report!(
+                    Warn,
+                    "gfx::gl::command",
+                    "missing-uniform-buffer",
+                    "No uniform buffer bound at slot {}",
+                    block.slot
+                ),
+            }
+        }
+    }
+
+    fn bind_program(&mut self, program: n::Program) {
+        if self.cache.program != Some(program) {
+            self.cache.program = Some(program);
+            self.data.push_cmd(Command::BindProgram(program));
+        }
+    }
+
+    fn bind_sampler_map(&mut self, sampler_map: &n::SamplerBindMap) {
+        let mut dirty_textures = 0u32;
+        for (texture_index, (slot, &sampler_index)) in self
+            .cache
+            .texture_slots
+            .iter_mut()
+            .zip(sampler_map.iter())
+            .enumerate()
+        {
+            if slot.sampler_index != sampler_index {
+                slot.sampler_index = sampler_index;
+                dirty_textures |= 1 << texture_index;
+            }
+        }
+        if dirty_textures != 0 {
+            self.update_sampler_states(dirty_textures, 0);
+        }
+    }
+
+    fn bind_immutable_samplers(&mut self, samplers: &[(u8, n::FatSampler)]) {
+        let mut dirty_samplers = 0u32;
+        for &(slot, ref sampler) in samplers {
+            let cached = &mut self.cache.samplers[slot as usize];
+            if cached.as_ref() != Some(sampler) {
+                *cached = Some(sampler.clone());
+                dirty_samplers |= 1 << slot;
+            }
+        }
+        if dirty_samplers != 0 {
+            self.update_sampler_states(0, dirty_samplers);
+        }
+    }
+
+    // Flush the program and descriptor sets of `bind_point` into GL,
+    // if the last draw or dispatch used the other bind point.
+    fn switch_bind_point(&mut self, bind_point: BindPoint) {
+        if self.cache.bind_point == Some(bind_point) {
+            return;
+        }
+        self.cache.bind_point = Some(bind_point);
+
+        let state = self.cache.bind_point_state(bind_point);
+        let program = state.program;
+        let sampler_map = state.sampler_map;
+        let immutable_samplers = state.immutable_samplers.clone();
+        let sets = state.sets.clone();
+
+        if let Some(program) = program {
+            self.bind_program(program);
+        }
+        self.bind_sampler_map(&sampler_map);
+        for bindings in sets.iter().flatten() {
+            self.apply_bindings(bindings);
+        }
+        self.bind_immutable_samplers(&immutable_samplers);
+    }
+
+    // Apply the compute state and validate it before recording a dispatch.
+    fn prepare_dispatch(&mut self) -> bool {
+        if self.cache.compute.program.is_none() {
+            auxil::report!(Warn, "gfx::gl::command", "missing-compute-pipeline", "No compute pipeline bound. A compute pipeline needs to be bound before calling `dispatch`.");
+            self.cache.error_state = true;
+            return false;
+        }
+        let has_compute_sets = self.cache.compute.sets.iter().any(Option::is_some);
+        let has_graphics_sets = self.cache.graphics.sets.iter().any(Option::is_some);
+        if !has_compute_sets && has_graphics_sets {
+            auxil::report!(Warn, "gfx::gl::command", "missing-compute-descriptor-sets", "Dispatch without any compute descriptor sets bound. Sets bound to the graphics bind point are not visible to compute.");
+        }
+        self.switch_bind_point(BindPoint::Compute);
+        true
     }
 }
 
@@ -738,14 +1144,13 @@ impl command::CommandBuffer<Backend> for CommandBuffer {
         // no-op
     }
 
-    unsafe fn reset(&mut self, _release_resources: bool) {
-        if !self.individual_reset {
-            error!("Associated pool must allow individual resets.");
-            return;
-        }
-
+    unsafe fn reset(&mut self, release_resources: bool) {
         self.soft_reset();
-        self.data.reset();
+        // The linear memory is shared by all the command buffers of the pool,
+        // so it's only reclaimed when the whole pool is reset.
+        if self.individual_reset {
+            self.data.reset(release_resources);
+        }
     }
 
     unsafe fn pipeline_barrier<'a, T>(
@@ -784,6 +1189,8 @@ impl command::CommandBuffer<Backend> for CommandBuffer {
                         mask |= glow::SHADER_IMAGE_ACCESS_BARRIER_BIT;
                     }
                 }
+                // Every resource gets its own GL object, nothing is actually aliased.
+                memory::Barrier::Aliasing { .. } => {}
             }
         }
 
@@ -792,6 +1199,20 @@ impl command::CommandBuffer<Backend> for CommandBuffer {
         }
     }
 
+    unsafe fn begin_barrier<'a, T>(&mut self, _: &(), _: Range<pso::PipelineStage>, _: T)
+    where
+        T: Iterator<Item = memory::Barrier<'a, Backend>>,
+    {
+        // No events in GL, the whole barrier is issued at the end
+    }
+
+    unsafe fn end_barrier<'a, T>(&mut self, _: &(), stages: Range<pso::PipelineStage>, barriers: T)
+    where
+        T: Iterator<Item = memory::Barrier<'a, Backend>>,
+    {
+        self.pipeline_barrier(stages, memory::Dependencies::empty(), barriers);
+    }
+
     unsafe fn fill_buffer(&mut self, buffer: &n::Buffer, sub: buffer::SubRange, data: u32) {
         let (raw_buffer, parent_range) = buffer.as_bound();
         let range = crate::resolve_sub_range(&sub, parent_range);
@@ -853,8 +1274,6 @@ impl command::CommandBuffer<Backend> for CommandBuffer {
             attachments,
         });
 
-        //Note: we currently only expect one sub-pass.
-
         self.data.push_cmd(Command::BindFramebuffer {
             target: glow::DRAW_FRAMEBUFFER,
             framebuffer: framebuffer.raw,
@@ -868,13 +1287,32 @@ impl command::CommandBuffer<Backend> for CommandBuffer {
     }
 
     unsafe fn next_subpass(&mut self, _contents: command::SubpassContents) {
-        unimplemented!()
+        self.cur_subpass += 1;
+
+        // Input attachments are sampled from the textures that are still attached
+        // to the framebuffer, so the previous writes need to be made visible to them.
+        let state = self.pass_cache.as_ref().unwrap();
+        let subpass = &state.render_pass.subpasses[self.cur_subpass as usize];
+        if !subpass.input_attachments.is_empty() {
+            self.data.push_cmd(Command::TextureBarrier);
+        }
+
+        self.begin_subpass();
     }
 
     unsafe fn end_render_pass(&mut self) {
         // TODO
     }
 
+    unsafe fn begin_rendering(&mut self, _info: &command::RenderingInfo<Backend>) {
+        // `Features::DYNAMIC_RENDERING` isn't reported
+        unimplemented!("{}", NOT_SUPPORTED_MESSAGE)
+    }
+
+    unsafe fn end_rendering(&mut self) {
+        unimplemented!("{}", NOT_SUPPORTED_MESSAGE)
+    }
+
     unsafe fn clear_image<T>(
         &mut self,
         image: &n::Image,
@@ -960,12 +1398,116 @@ impl command::CommandBuffer<Backend> for CommandBuffer {
         }
     }
 
-    unsafe fn clear_attachments<T, U>(&mut self, _: T, _: U)
+    unsafe fn clear_attachments<T, U>(&mut self, clears: T, rects: U)
     where
         T: Iterator<Item = command::AttachmentClear>,
         U: Iterator<Item = pso::ClearRect>,
     {
-        unimplemented!()
+        let state = match self.pass_cache {
+            Some(ref state) => state,
+            None => {
+                auxil::report!(
+                    Error,
+                    "gfx::gl::command",
+                    "clear-attachments-outside-pass",
+                    "Attachments can only be cleared inside a render pass."
+                );
+                self.cache.error_state = true;
+                return;
+            }
+        };
+        let subpass = &state.render_pass.subpasses[self.cur_subpass as usize];
+        let clears = clears.collect::<Vec<_>>();
+
+        // Write masks apply to buffer clears in gl, temporarily reset them.
+        let clear_color = clears
+            .iter()
+            .any(|clear| matches!(*clear, command::AttachmentClear::Color { .. }));
+        let clear_depth_stencil = clears
+            .iter()
+            .any(|clear| matches!(*clear, command::AttachmentClear::DepthStencil { .. }));
+        if clear_color {
+            self.data
+                .push_cmd(Command::SetColorMask(None, pso::ColorMask::ALL));
+        }
+        if clear_depth_stencil {
+            self.data.push_cmd(Command::SetDepthMask(true));
+            self.data.push_cmd(Command::SetStencilMask(!0));
+        }
+
+        // Each rectangle is cleared through the scissor test, the layer range
+        // is ignored as gl clears all layers of a layered attachment.
+        for rect in rects {
+            let rect = rect.rect;
+            self.data.push_cmd(Command::SetClearRect(Some([
+                rect.x as i32,
+                rect.y as i32,
+                rect.w as i32,
+                rect.h as i32,
+            ])));
+
+            for clear in &clears {
+                match *clear {
+                    command::AttachmentClear::Color { index, value } => {
+                        let attachment =
+                            &state.render_pass.attachments[subpass.color_attachments[index]];
+                        let channel = attachment.format.unwrap().base_format().1;
+                        let draw_buffer = index as DrawBuffer;
+
+                        self.data.push_cmd(match channel {
+                            ChannelType::Unorm
+                            | ChannelType::Snorm
+                            | ChannelType::Ufloat
+                            | ChannelType::Sfloat
+                            | ChannelType::Srgb
+                            | ChannelType::Uscaled
+                            | ChannelType::Sscaled => {
+                                Command::ClearBufferColorF(draw_buffer, value.float32)
+                            }
+                            ChannelType::Uint => {
+                                Command::ClearBufferColorU(draw_buffer, value.uint32)
+                            }
+                            ChannelType::Sint => {
+                                Command::ClearBufferColorI(draw_buffer, value.sint32)
+                            }
+                        });
+                    }
+                    command::AttachmentClear::DepthStencil { depth, stencil } => {
+                        if depth.is_some() || stencil.is_some() {
+                            self.data
+                                .push_cmd(Command::ClearBufferDepthStencil(depth, stencil));
+                        }
+                    }
+                }
+            }
+        }
+        self.data.push_cmd(Command::SetClearRect(None));
+
+        // Restore the masks of the bound pipeline
+        if clear_color {
+            let blend_targets = &self.cache.blend_targets;
+            match blend_targets.first() {
+                Some(&Some(target)) if blend_targets.iter().all(|t| *t == Some(target)) => {
+                    self.data.push_cmd(Command::SetColorMask(None, target.mask));
+                }
+                _ => {
+                    for (slot, target) in blend_targets.iter().enumerate() {
+                        if let Some(target) = target {
+                            self.data
+                                .push_cmd(Command::SetColorMask(Some(slot as _), target.mask));
+                        }
+                    }
+                }
+            }
+        }
+        if clear_depth_stencil {
+            if let Some(mask) = self.cache.depth_mask {
+                self.data.push_cmd(Command::SetDepthMask(mask));
+            }
+            if let Some(mask) = self.cache.stencil_mask {
+                self.data.push_cmd(Command::SetStencilMaskSeparate(mask));
+            }
+        }
     }
 
     unsafe fn resolve_image<T>(
@@ -992,7 +1534,12 @@ impl command::CommandBuffer<Backend> for CommandBuffer {
     ) where
         T: Iterator<Item = command::ImageBlit>,
     {
-        error!("Blit is not implemented");
+        auxil::report!(
+            Error,
+            "gfx::gl::command",
+            "blit-unimplemented",
+            "Blit is not implemented"
+        );
     }
 
     unsafe fn bind_index_buffer(
@@ -1051,7 +1598,12 @@ impl command::CommandBuffer<Backend> for CommandBuffer {
 
         match len {
             0 => {
-                error!("Number of viewports can not be zero.");
+                auxil::report!(
+                    Error,
+                    "gfx::gl::command",
+                    "empty-viewports",
+                    "Number of viewports can not be zero."
+                );
                 self.cache.error_state = true;
             }
             n if n + first_viewport as usize <= self.limits.max_viewports => {
@@ -1062,7 +1614,7 @@ impl command::CommandBuffer<Backend> for CommandBuffer {
                 });
             }
             _ => {
-                error!("Number of viewports and first viewport index exceed the number of maximum viewports");
+                auxil::report!(Error, "gfx::gl::command", "viewports-out-of-range", "Number of viewports and first viewport index exceed the number of maximum viewports");
                 self.cache.error_state = true;
             }
         }
@@ -1087,7 +1639,12 @@ impl command::CommandBuffer<Backend> for CommandBuffer {
 
         match len {
             0 => {
-                error!("Number of scissors can not be zero.");
+                auxil::report!(
+                    Error,
+                    "gfx::gl::command",
+                    "empty-scissors",
+                    "Number of scissors can not be zero."
+                );
                 self.cache.error_state = true;
             }
             n if n + first_scissor as usize <= self.limits.max_viewports => {
@@ -1095,7 +1652,7 @@ impl command::CommandBuffer<Backend> for CommandBuffer {
                     .push_cmd(Command::SetScissors(first_scissor, scissors_ptr));
             }
             _ => {
-                error!("Number of scissors and first scissor index exceed the maximum number of viewports");
+                auxil::report!(Error, "gfx::gl::command", "scissors-out-of-range", "Number of scissors and first scissor index exceed the maximum number of viewports");
                 self.cache.error_state = true;
             }
         }
@@ -1143,15 +1700,46 @@ impl command::CommandBuffer<Backend> for CommandBuffer {
     }
 
     unsafe fn set_depth_bounds(&mut self, _: Range<f32>) {
-        warn!("Depth bounds test is not supported");
+        auxil::report!(
+            Warn,
+            "gfx::gl::command",
+            "depth-bounds-unsupported",
+            "Depth bounds test is not supported"
+        );
     }
 
     unsafe fn set_line_width(&mut self, _width: f32) {
         unimplemented!()
     }
 
-    unsafe fn set_depth_bias(&mut self, _depth_bias: pso::DepthBias) {
-        unimplemented!()
+    unsafe fn set_depth_bias(&mut self, depth_bias: pso::DepthBias) {
+        self.data.push_cmd(Command::SetDepthBias(depth_bias));
+    }
+
+    unsafe fn set_shading_rate(
+        &mut self,
+        _rate: pso::ShadingRate,
+        _combiners: [pso::ShadingRateCombiner; 2],
+    ) {
+        auxil::report!(
+            Warn,
+            "gfx::gl::command",
+            "shading-rate-unsupported",
+            "Variable rate shading is not supported"
+        );
+    }
+
+    unsafe fn set_sample_locations(
+        &mut self,
+        _samples: image::NumSamples,
+        _locations: &[pso::SampleLocation],
+    ) {
+        auxil::report!(
+            Warn,
+            "gfx::gl::command",
+            "sample-locations-unsupported",
+            "Programmable sample locations are not supported"
+        );
     }
 
     unsafe fn bind_graphics_pipeline(&mut self, pipeline: &n::GraphicsPipeline) {
@@ -1166,20 +1754,36 @@ impl command::CommandBuffer<Backend> for CommandBuffer {
             }
         }
 
-        if self.cache.program != Some(pipeline.program) {
-            self.cache.program = Some(pipeline.program);
-            self.data.push_cmd(Command::BindProgram(pipeline.program));
+        self.cache.graphics.program = Some(pipeline.program);
+        self.cache.graphics.sampler_map = pipeline.sampler_map;
+        if self.cache.bind_point == Some(BindPoint::Graphics) {
+            self.bind_program(pipeline.program);
         }
 
+        // Disable the attribute arrays left over from the previous pipeline,
+        // so that draws without vertex input don't fetch from stale buffers.
+        for attribute in self.cache.attributes.iter() {
+            if pipeline
+                .attributes
+                .iter()
+                .all(|at| at.location != attribute.location)
+            {
+                self.data
+                    .push_cmd(Command::UnbindAttribute(attribute.location));
+            }
+        }
         self.cache.attributes = pipeline.attributes.clone();
         self.cache.vertex_buffer_descs = pipeline.vertex_buffers.clone();
 
         self.cache.uniforms = pipeline.uniforms.clone();
+        self.cache.uniform_blocks = pipeline.uniform_blocks.clone();
+        self.cache.dirty_uniform_blocks = true;
 
         self.update_blend_targets(&pipeline.blend_targets);
 
         self.data.push_cmd(Command::BindRasterizer {
             rasterizer: pipeline.rasterizer,
+            multisampling: pipeline.multisampling.clone(),
         });
         self.data
             .push_cmd(Command::BindDepth(pipeline.depth.map(|d| d.fun)));
@@ -1201,21 +1805,10 @@ impl command::CommandBuffer<Backend> for CommandBuffer {
             self.set_depth_bounds(bounds.clone());
         }
 
-        let mut dirty_textures = 0u32;
-        for (texture_index, (slot, &sampler_index)) in self
-            .cache
-            .texture_slots
-            .iter_mut()
-            .zip(pipeline.sampler_map.iter())
-            .enumerate()
-        {
-            if slot.sampler_index != sampler_index {
-                slot.sampler_index = sampler_index;
-                dirty_textures |= 1 << texture_index;
-            }
-        }
-        if dirty_textures != 0 {
-            self.update_sampler_states(dirty_textures, 0);
+        self.cache.graphics.immutable_samplers = pipeline.immutable_samplers.clone();
+        if self.cache.bind_point == Some(BindPoint::Graphics) {
+            self.bind_sampler_map(&pipeline.sampler_map);
+            self.bind_immutable_samplers(&pipeline.immutable_samplers);
         }
     }
 
@@ -1229,13 +1822,17 @@ impl command::CommandBuffer<Backend> for CommandBuffer {
         I: Iterator<Item = &'a n::DescriptorSet>,
         J: Iterator<Item = command::DescriptorSetOffset>,
     {
-        self.bind_descriptor_sets(layout, first_set, sets, offsets)
+        self.bind_descriptor_sets(BindPoint::Graphics, layout, first_set, sets, offsets)
     }
 
     unsafe fn bind_compute_pipeline(&mut self, pipeline: &n::ComputePipeline) {
-        if self.cache.program != Some(pipeline.program) {
-            self.cache.program = Some(pipeline.program);
-            self.data.push_cmd(Command::BindProgram(pipeline.program));
+        self.cache.compute.program = Some(pipeline.program);
+        self.cache.compute.sampler_map = pipeline.sampler_map;
+        self.cache.compute.immutable_samplers = pipeline.immutable_samplers.clone();
+        if self.cache.bind_point == Some(BindPoint::Compute) {
+            self.bind_program(pipeline.program);
+            self.bind_sampler_map(&pipeline.sampler_map);
+            self.bind_immutable_samplers(&pipeline.immutable_samplers);
         }
     }
 
@@ -1249,14 +1846,24 @@ impl command::CommandBuffer<Backend> for CommandBuffer {
         I: Iterator<Item = &'a n::DescriptorSet>,
         J: Iterator<Item = command::DescriptorSetOffset>,
     {
-        self.bind_descriptor_sets(layout, first_set, sets, offsets)
+        self.bind_descriptor_sets(BindPoint::Compute, layout, first_set, sets, offsets)
     }
 
     unsafe fn dispatch(&mut self, count: hal::WorkGroupCount) {
+        if count.contains(&0) {
+            debug!("Skipping an empty dispatch");
+            return;
+        }
+        if !self.prepare_dispatch() {
+            return;
+        }
         self.data.push_cmd(Command::Dispatch(count));
     }
 
     unsafe fn dispatch_indirect(&mut self, buffer: &n::Buffer, offset: buffer::Offset) {
+        if !self.prepare_dispatch() {
+            return;
+        }
         let (raw_buffer, range) = buffer.as_bound();
         self.data
             .push_cmd(Command::DispatchIndirect(raw_buffer, range.start + offset));
@@ -1278,7 +1885,12 @@ impl command::CommandBuffer<Backend> for CommandBuffer {
         }
 
         if self.data.buf.size == old_size {
-            error!("At least one region must be specified");
+            auxil::report!(
+                Error,
+                "gfx::gl::command",
+                "empty-copy-regions",
+                "At least one region must be specified"
+            );
         }
     }
 
@@ -1310,7 +1922,12 @@ impl command::CommandBuffer<Backend> for CommandBuffer {
         }
 
         if self.data.buf.size == old_size {
-            error!("At least one region must be specified");
+            auxil::report!(
+                Error,
+                "gfx::gl::command",
+                "empty-copy-regions",
+                "At least one region must be specified"
+            );
         }
     }
 
@@ -1351,7 +1968,12 @@ impl command::CommandBuffer<Backend> for CommandBuffer {
         }
 
         if self.data.buf.size == old_size {
-            error!("At least one region must be specified");
+            auxil::report!(
+                Error,
+                "gfx::gl::command",
+                "empty-copy-regions",
+                "At least one region must be specified"
+            );
         }
     }
 
@@ -1392,7 +2014,12 @@ impl command::CommandBuffer<Backend> for CommandBuffer {
         }
 
         if self.data.buf.size == old_size {
-            error!("At least one region must be specified");
+            auxil::report!(
+                Error,
+                "gfx::gl::command",
+                "empty-copy-regions",
+                "At least one region must be specified"
+            );
         }
     }
 
@@ -1401,6 +2028,13 @@ impl command::CommandBuffer<Backend> for CommandBuffer {
         vertices: Range<hal::VertexCount>,
         mut instances: Range<hal::InstanceCount>,
     ) {
+        if vertices.start >= vertices.end || instances.start >= instances.end {
+            debug!("Skipping an empty draw");
+            return;
+        }
+        self.switch_bind_point(BindPoint::Graphics);
+        self.update_uniform_blocks();
+
         if !self
             .legacy_featues
             .contains(info::LegacyFeatures::DRAW_INSTANCED_BASE)
@@ -1421,7 +2055,7 @@ impl command::CommandBuffer<Backend> for CommandBuffer {
                 });
             }
             None => {
-                warn!("No primitive bound. An active pipeline needs to be bound before calling `draw`.");
+                auxil::report!(Warn, "gfx::gl::command", "missing-primitive", "No primitive bound. An active pipeline needs to be bound before calling `draw`.");
                 self.cache.error_state = true;
             }
         }
@@ -1433,6 +2067,13 @@ impl command::CommandBuffer<Backend> for CommandBuffer {
         base_vertex: hal::VertexOffset,
         mut instances: Range<hal::InstanceCount>,
     ) {
+        if indices.start >= indices.end || instances.start >= instances.end {
+            debug!("Skipping an empty indexed draw");
+            return;
+        }
+        self.switch_bind_point(BindPoint::Graphics);
+        self.update_uniform_blocks();
+
         if !self
             .legacy_featues
             .contains(info::LegacyFeatures::DRAW_INSTANCED_BASE)
@@ -1447,7 +2088,7 @@ impl command::CommandBuffer<Backend> for CommandBuffer {
         let (index_type, buffer_range) = match &self.cache.index_type_range {
             Some((index_type, buffer_range)) => (index_type, buffer_range),
             None => {
-                warn!("No index type bound. An index buffer needs to be bound before calling `draw_indexed`.");
+                auxil::report!(Warn, "gfx::gl::command", "missing-index-type", "No index type bound. An index buffer needs to be bound before calling `draw_indexed`.");
                 self.cache.error_state = true;
                 return;
             }
@@ -1476,7 +2117,7 @@ impl command::CommandBuffer<Backend> for CommandBuffer {
                 });
             }
             None => {
-                warn!("No primitive bound. An active pipeline needs to be bound before calling `draw_indexed`.");
+                auxil::report!(Warn, "gfx::gl::command", "missing-primitive", "No primitive bound. An active pipeline needs to be bound before calling `draw_indexed`.");
                 self.cache.error_state = true;
             }
         }
@@ -1567,13 +2208,14 @@ impl command::CommandBuffer<Backend> for CommandBuffer {
         unimplemented!()
     }
 
-    unsafe fn begin_query(&mut self, _query: query::Query<Backend>, _flags: query::ControlFlags) {
-        unimplemented!()
+    unsafe fn begin_query(&mut self, query: query::Query<Backend>, _flags: query::ControlFlags) {
+        let raw = query.pool.queries[query.id as usize];
+        self.data.push_cmd(Command::BeginQuery(raw, query.pool.target));
     }
 
     unsafe fn copy_query_pool_results(
         &mut self,
-        _pool: &(),
+        _pool: &n::QueryPool,
         _queries: Range<query::Id>,
         _buffer: &n::Buffer,
         _offset: buffer::Offset,
@@ -1583,12 +2225,12 @@ impl command::CommandBuffer<Backend> for CommandBuffer {
         unimplemented!()
     }
 
-    unsafe fn end_query(&mut self, _query: query::Query<Backend>) {
-        unimplemented!()
+    unsafe fn end_query(&mut self, query: query::Query<Backend>) {
+        self.data.push_cmd(Command::EndQuery(query.pool.target));
     }
 
-    unsafe fn reset_query_pool(&mut self, _pool: &(), _queries: Range<query::Id>) {
-        unimplemented!()
+    unsafe fn reset_query_pool(&mut self, _pool: &n::QueryPool, _queries: Range<query::Id>) {
+        // Nothing to do, GL queries are reset when they begin
     }
 
     unsafe fn write_timestamp(&mut self, _: pso::PipelineStage, _: query::Query<Backend>) {
@@ -1602,6 +2244,9 @@ impl command::CommandBuffer<Backend> for CommandBuffer {
         offset: u32,
         constants: &[u32],
     ) {
+        // Uniforms are stored in the program object, so the graphics program
+        // has to be current before they get uploaded.
+        self.switch_bind_point(BindPoint::Graphics);
         let buffer = self.data.add(constants);
 
         let uniforms = &self.cache.uniforms;
@@ -1649,4 +2294,91 @@ impl command::CommandBuffer<Backend> for CommandBuffer {
     unsafe fn end_debug_marker(&mut self) {
         //TODO
     }
+
+    unsafe fn set_checkpoint(&mut self, _label: &str) {
+        // not supported
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hal::command::CommandBuffer as _;
+
+    fn recorded_commands(cmd_buffer: &CommandBuffer) -> Vec<String> {
+        match *cmd_buffer.data.memory.lock() {
+            BufferMemory::Linear(ref buffer) => buffer
+                .commands
+                .iter()
+                .filter_map(|cmd| match *cmd {
+                    Command::BindProgram(program) => Some(format!("program {}", program)),
+                    Command::BindUniform { .. } => Some("uniform".to_string()),
+                    Command::Dispatch(..) => Some("dispatch".to_string()),
+                    _ => None,
+                })
+                .collect(),
+            BufferMemory::Individual { .. } => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn push_constants_between_dispatches() {
+        let memory = Arc::new(Mutex::new(BufferMemory::Linear(pool::OwnedBuffer::new())));
+        let mut cmd_buffer = CommandBuffer::new(
+            None,
+            hal::Limits::default().into(),
+            memory,
+            info::LegacyFeatures::empty(),
+        );
+        let layout = n::PipelineLayout {
+            sets: Vec::new(),
+            sampler_map: [None; MAX_TEXTURE_SLOTS],
+            immutable_samplers: Vec::new(),
+        };
+        let graphics = n::GraphicsPipeline {
+            program: 1,
+            primitive: glow::TRIANGLES,
+            patch_size: None,
+            blend_targets: Vec::new(),
+            attributes: Vec::new(),
+            vertex_buffers: Vec::new(),
+            uniforms: vec![n::UniformDesc {
+                location: crate::Starc::new(0),
+                offset: 0,
+                utype: glow::FLOAT_VEC4,
+            }],
+            uniform_blocks: Vec::new(),
+            rasterizer: pso::Rasterizer::FILL,
+            multisampling: None,
+            depth: None,
+            baked_states: pso::BakedStates::default(),
+            sampler_map: [None; MAX_TEXTURE_SLOTS],
+            immutable_samplers: Vec::new(),
+        };
+        let compute = n::ComputePipeline {
+            program: 2,
+            sampler_map: [None; MAX_TEXTURE_SLOTS],
+            immutable_samplers: Vec::new(),
+        };
+
+        unsafe {
+            cmd_buffer.bind_graphics_pipeline(&graphics);
+            cmd_buffer.bind_compute_pipeline(&compute);
+            cmd_buffer.dispatch([1, 1, 1]);
+            cmd_buffer.push_graphics_constants(&layout, pso::ShaderStageFlags::VERTEX, 0, &[0; 4]);
+            cmd_buffer.dispatch([1, 1, 1]);
+        }
+
+        assert_eq!(
+            recorded_commands(&cmd_buffer),
+            [
+                "program 2",
+                "dispatch",
+                "program 1",
+                "uniform",
+                "program 2",
+                "dispatch",
+            ]
+        );
+    }
 }
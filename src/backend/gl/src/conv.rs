@@ -87,6 +87,25 @@ impl FormatDescription {
     }
 }
 
+/// Returns true if the format has no GL equivalent, and is backed by
+/// `DEPTH24_STENCIL8` instead.
+pub fn is_emulated_format(format: Format) -> bool {
+    format == Format::D16UnormS8Uint
+}
+
+/// Returns true if the format can back a buffer texture, on both GL and GLES.
+pub fn is_texel_buffer_format(format: Format) -> bool {
+    use hal::format::Format::*;
+    match format {
+        R8Unorm | R8Uint | R8Sint | R16Uint | R16Sint | R16Sfloat | R32Uint | R32Sint
+        | R32Sfloat | Rg8Unorm | Rg8Uint | Rg8Sint | Rg16Uint | Rg16Sint | Rg16Sfloat
+        | Rg32Uint | Rg32Sint | Rg32Sfloat | Rgb32Uint | Rgb32Sint | Rgb32Sfloat | Rgba8Unorm
+        | Rgba8Uint | Rgba8Sint | Rgba16Uint | Rgba16Sint | Rgba16Sfloat | Rgba32Uint
+        | Rgba32Sint | Rgba32Sfloat => true,
+        _ => false,
+    }
+}
+
 pub fn describe_format(format: Format) -> Option<FormatDescription> {
     use crate::native::VertexAttribFunction::*;
     use hal::format::Format::*;
@@ -216,7 +235,27 @@ pub fn describe_format(format: Format) -> Option<FormatDescription> {
             FormatDescription::new(glow::RGBA32I, glow::RGBA_INTEGER, glow::INT, 4, Integer)
         }
         Rgba32Sfloat => FormatDescription::new(glow::RGBA32F, glow::RGBA, glow::FLOAT, 4, Float),
-        S8Uint => FormatDescription::new(glow::R8, glow::RED, glow::UNSIGNED_BYTE, 1, Integer),
+        B10g11r11Ufloat => FormatDescription::new(
+            glow::R11F_G11F_B10F,
+            glow::RGB,
+            glow::UNSIGNED_INT_10F_11F_11F_REV,
+            3,
+            Float,
+        ),
+        E5b9g9r9Ufloat => FormatDescription::new(
+            glow::RGB9_E5,
+            glow::RGB,
+            glow::UNSIGNED_INT_5_9_9_9_REV,
+            3,
+            Float,
+        ),
+        S8Uint => FormatDescription::new(
+            glow::STENCIL_INDEX8,
+            glow::STENCIL_INDEX,
+            glow::UNSIGNED_BYTE,
+            1,
+            Integer,
+        ),
         D16Unorm => FormatDescription::new(
             glow::DEPTH_COMPONENT16,
             glow::DEPTH_COMPONENT,
@@ -224,7 +263,8 @@ pub fn describe_format(format: Format) -> Option<FormatDescription> {
             1,
             Float,
         ),
-        D24UnormS8Uint => FormatDescription::new(
+        // Emulated, see `is_emulated_format`
+        D16UnormS8Uint | D24UnormS8Uint => FormatDescription::new(
             glow::DEPTH24_STENCIL8,
             glow::DEPTH_STENCIL,
             glow::UNSIGNED_INT,
@@ -1,5 +1,5 @@
 use crate::{
-    command as cmd, conv,
+    command as cmd, conv, dma_buf,
     info::LegacyFeatures,
     native as n,
     pool::{BufferMemory, CommandPool, OwnedBuffer},
@@ -8,8 +8,8 @@ use crate::{
 };
 
 use hal::{
-    buffer, device as d,
-    format::{Format, Swizzle},
+    buffer, command, device as d,
+    format::{Aspects, Format, Swizzle},
     image as i, memory, pass,
     pool::CommandPoolCreateFlags,
     pso, query, queue,
@@ -18,11 +18,19 @@ use hal::{
 use glow::HasContext;
 use parking_lot::Mutex;
 
+#[cfg(unix)]
+use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
 use std::{ops::Range, slice, sync::Arc};
 
 #[cfg(feature = "cross")]
 type CrossAst = spirv_cross::spirv::Ast<spirv_cross::glsl::Target>;
 
+/// Name prefix of the `vec4` arrays emulating uniform blocks, followed by the binding slot.
+const UNIFORM_BUFFER_PREFIX: &str = "gfx_uniform_buffer_";
+/// Size of the zeroed buffer bound in place of null buffer descriptors,
+/// matching the minimal `GL_MAX_UNIFORM_BLOCK_SIZE`.
+const NULL_BUFFER_SIZE: i32 = 16 << 10;
+
 fn create_fbo_internal(
     share: &Starc<Share>,
 ) -> Option<<GlContext as glow::HasContext>::Framebuffer> {
@@ -36,6 +44,36 @@ fn create_fbo_internal(
     }
 }
 
+/// Returns the number of 2D slices a host copy region touches.
+fn host_copy_slice_count(region: &command::BufferImageCopy) -> usize {
+    let layers = &region.image_layers.layers;
+    (region.image_extent.depth as usize) * (layers.end - layers.start) as usize
+}
+
+/// Returns the number of bytes of host memory a copy region touches.
+fn host_copy_size(image: &n::Image, region: &command::BufferImageCopy) -> usize {
+    let bytes_per_texel = (image.format_desc.bits / 8) as usize;
+    let width = region.buffer_width.max(region.image_extent.width) as usize;
+    let height = region.buffer_height.max(region.image_extent.height) as usize;
+    width * height * bytes_per_texel * host_copy_slice_count(region)
+}
+
+/// Returns whether the host can copy to and from textures of this target.
+fn host_copy_target_supported(target: u32) -> bool {
+    matches!(
+        target,
+        glow::TEXTURE_2D | glow::TEXTURE_2D_ARRAY | glow::TEXTURE_3D
+    )
+}
+
+/// Maps the error left by a host copy to the reported one.
+fn map_host_copy_error(err: crate::Error) -> d::MapError {
+    match err {
+        crate::Error::OutOfMemory => d::OutOfMemory::Host.into(),
+        _ => d::MapError::Access,
+    }
+}
+
 struct CompilationContext<'a> {
     layout: &'a n::PipelineLayout,
     sampler_map: &'a mut n::SamplerBindMap,
@@ -52,11 +90,75 @@ impl<'a> CompilationContext<'a> {
     }
 }
 
+/// Zeroed resources bound in place of `pso::Descriptor::Null`.
+///
+/// Unlike the native null descriptors, the writes through them are not discarded.
+#[derive(Debug)]
+pub(crate) struct NullResources {
+    buffer: n::RawBuffer,
+    texture: n::Texture,
+    texel_buffer: Option<n::Texture>,
+}
+
+impl NullResources {
+    pub(crate) unsafe fn new(gl: &GlContainer) -> Self {
+        let buffer = gl.create_buffer().unwrap();
+        gl.bind_buffer(glow::UNIFORM_BUFFER, Some(buffer));
+        gl.buffer_data_u8_slice(
+            glow::UNIFORM_BUFFER,
+            &[0; NULL_BUFFER_SIZE as usize],
+            glow::STATIC_DRAW,
+        );
+        gl.bind_buffer(glow::UNIFORM_BUFFER, None);
+
+        // A single level, so that the texture is complete with the default sampler state.
+        let texture = gl.create_texture().unwrap();
+        gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+        gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAX_LEVEL, 0);
+        gl.tex_image_2d(
+            glow::TEXTURE_2D,
+            0,
+            glow::RGBA8 as i32,
+            1,
+            1,
+            0,
+            glow::RGBA,
+            glow::UNSIGNED_BYTE,
+            Some(&[0; 4]),
+        );
+        gl.bind_texture(glow::TEXTURE_2D, None);
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let texel_buffer = gl.tex_buffer_range.map(|tex_buffer_range| {
+            let raw = gl.create_texture().unwrap();
+            gl.bind_texture(glow::TEXTURE_BUFFER, Some(raw));
+            tex_buffer_range(
+                glow::TEXTURE_BUFFER,
+                glow::RGBA32F,
+                buffer,
+                0,
+                NULL_BUFFER_SIZE as isize,
+            );
+            gl.bind_texture(glow::TEXTURE_BUFFER, None);
+            raw
+        });
+        #[cfg(target_arch = "wasm32")]
+        let texel_buffer = None;
+
+        NullResources {
+            buffer,
+            texture,
+            texel_buffer,
+        }
+    }
+}
+
 /// GL device.
 #[derive(Debug)]
 pub struct Device {
     pub(crate) share: Starc<Share>,
     features: hal::Features,
+    null_resources: Option<NullResources>,
     pub always_prefer_naga: bool,
     #[cfg(feature = "cross")]
     spv_options: naga::back::spv::Options,
@@ -64,16 +166,31 @@ pub struct Device {
 
 impl Drop for Device {
     fn drop(&mut self) {
+        if let Some(null) = self.null_resources.take() {
+            let gl = &self.share.context;
+            unsafe {
+                gl.delete_buffer(null.buffer);
+                gl.delete_texture(null.texture);
+                if let Some(texel_buffer) = null.texel_buffer {
+                    gl.delete_texture(texel_buffer);
+                }
+            }
+        }
         self.share.open.set(false);
     }
 }
 
 impl Device {
     /// Create a new `Device`.
-    pub(crate) fn new(share: Starc<Share>, features: hal::Features) -> Self {
+    pub(crate) fn new(
+        share: Starc<Share>,
+        features: hal::Features,
+        null_resources: Option<NullResources>,
+    ) -> Self {
         Device {
             share: share,
             features,
+            null_resources,
             always_prefer_naga: false,
             #[cfg(feature = "cross")]
             spv_options: {
@@ -149,7 +266,7 @@ impl Device {
         let program = unsafe { gl.create_program().unwrap() };
 
         let mut name_binding_map = FastHashMap::<String, (n::BindingRegister, u8)>::default();
-        let mut sampler_map = [None; MAX_TEXTURE_SLOTS];
+        let mut sampler_map = layout.sampler_map;
 
         let mut has_vertex_stage = false;
         let mut has_fragment_stage = false;
@@ -184,7 +301,12 @@ impl Device {
         if has_vertex_stage && !has_fragment_stage {
             let sl = &self.share.info.shading_language;
             let version = (sl.major * 100 + sl.minor * 10) as u16;
-            let shader_type = if sl.is_embedded { "es" } else { "" };
+            // GLSL ES 1.00 has no profile suffix
+            let shader_type = if sl.is_embedded && sl.major >= 3 {
+                "es"
+            } else {
+                ""
+            };
             let shader_src = format!(
                 "#version {version} {shader_type} \n void main(void) {{}}",
                 version = version,
@@ -253,6 +375,34 @@ impl Device {
         Ok((program, sampler_map))
     }
 
+    /// Adjust the blend states of the color targets to what can be set on this device.
+    ///
+    /// Without `Features::INDEPENDENT_BLENDING`, all the blended targets share the state
+    /// of the first one, and without per-slot enables the blending is enabled for all of them.
+    fn blend_targets(&self, blender: &pso::BlendDesc) -> Vec<pso::ColorBlendDesc> {
+        let mut targets = blender.targets.clone();
+        if self.features.contains(hal::Features::INDEPENDENT_BLENDING) {
+            return targets;
+        }
+        let blend = match targets.iter().find_map(|target| target.blend) {
+            Some(blend) => blend,
+            None => return targets,
+        };
+        let per_slot_enable = self.share.private_caps.per_slot_blend_enable;
+        if targets
+            .iter()
+            .any(|target| target.blend.map_or(!per_slot_enable, |b| b != blend))
+        {
+            warn!("Independent blending requires `Features::INDEPENDENT_BLENDING`, using the first blend state");
+        }
+        for target in targets.iter_mut() {
+            if target.blend.is_some() || !per_slot_enable {
+                target.blend = Some(blend);
+            }
+        }
+        targets
+    }
+
     fn _bind_target_compat(gl: &GlContainer, point: u32, attachment: u32, view: &n::ImageView) {
         match *view {
             n::ImageView::Renderbuffer { raw: rb, .. } => unsafe {
@@ -418,13 +568,25 @@ impl Device {
             layout,
             nb_map,
         );
+        // Subpass inputs are translated into texel fetches from regular textures.
         self.remap_binding(
             ast,
-            &res.uniform_buffers,
-            n::BindingRegister::UniformBuffers,
+            &res.subpass_inputs,
+            n::BindingRegister::Textures,
             layout,
             nb_map,
         );
+        if self.share.emulate_uniform_buffers() {
+            self.flatten_uniform_buffers(ast, &res.uniform_buffers, layout);
+        } else {
+            self.remap_binding(
+                ast,
+                &res.uniform_buffers,
+                n::BindingRegister::UniformBuffers,
+                layout,
+                nb_map,
+            );
+        }
         self.remap_binding(
             ast,
             &res.storage_buffers,
@@ -470,6 +632,36 @@ impl Device {
         }
     }
 
+    /// Turn the uniform blocks into `vec4` arrays, named after their binding slots.
+    #[cfg(feature = "cross")]
+    fn flatten_uniform_buffers(
+        &self,
+        ast: &mut CrossAst,
+        all_res: &[spirv_cross::spirv::Resource],
+        layout: &n::PipelineLayout,
+    ) {
+        use spirv_cross::spirv::Decoration;
+
+        for res in all_res {
+            let set = ast
+                .get_decoration(res.id, Decoration::DescriptorSet)
+                .unwrap();
+            let binding = ast.get_decoration(res.id, Decoration::Binding).unwrap();
+            let slot = layout.sets[set as usize].bindings[binding as usize];
+
+            // The flattened array is named after the block type
+            ast.set_name(
+                res.base_type_id,
+                &format!("{}{}", UNIFORM_BUFFER_PREFIX, slot),
+            )
+            .unwrap();
+            ast.flatten_buffer_block(res.id).unwrap();
+            ast.unset_decoration(res.id, Decoration::Binding).unwrap();
+            ast.unset_decoration(res.id, Decoration::DescriptorSet)
+                .unwrap();
+        }
+    }
+
     #[cfg(feature = "cross")]
     fn combine_separate_images_and_samplers(
         &self,
@@ -644,8 +836,10 @@ impl Device {
             entry_point: (stage, ep.entry.to_string()),
         };
 
+        // Naga doesn't flatten the uniform blocks when they are emulated
+        let allow_naga = !self.share.emulate_uniform_buffers();
         let mut result = Err(d::ShaderError::CompilationFailed(String::new()));
-        if ep.module.prefer_naga {
+        if ep.module.prefer_naga && allow_naga {
             if let Some(ref shader) = ep.module.naga {
                 result = Self::compile_shader_library_naga(
                     &self.share.context,
@@ -669,7 +863,7 @@ impl Device {
             debug!("SPIRV-Cross generated shader:\n{}", glsl);
             result = Self::create_shader_module_raw(&self.share.context, &glsl, stage);
         }
-        if result.is_err() && !ep.module.prefer_naga {
+        if result.is_err() && !ep.module.prefer_naga && allow_naga {
             if let Some(ref shader) = ep.module.naga {
                 result = Self::compile_shader_library_naga(
                     &self.share.context,
@@ -681,6 +875,41 @@ impl Device {
         }
         result
     }
+
+    /// Import the dma-buf `fd` as the storage of an image created with a DRM format modifier.
+    #[cfg(unix)]
+    unsafe fn bind_dma_buf(
+        &self,
+        fd: RawFd,
+        offset: u64,
+        image: &n::Image,
+        layout: &n::DrmLayout,
+    ) -> Result<(), d::BindError> {
+        let import = self.share.context.dma_buf.as_ref().unwrap();
+        let texture = match image.object_type {
+            n::ImageType::Texture { raw, .. } => raw,
+            n::ImageType::Renderbuffer { .. } => unreachable!(),
+        };
+        let egl_image = import.import(fd, offset, image.kind.extent(), layout);
+        if egl_image.is_null() {
+            error!("Unable to import dma-buf {} with {:?}", fd, layout);
+            return Err(d::BindError::WrongMemory);
+        }
+
+        let gl = &self.share.context;
+        gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+        (import.image_target_texture)(glow::TEXTURE_2D, egl_image);
+        gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAX_LEVEL, 0);
+        gl.bind_texture(glow::TEXTURE_2D, None);
+        // The texture keeps the buffer alive on its own.
+        (import.destroy_image)(import.display, egl_image);
+
+        if let Err(err) = self.share.check() {
+            error!("Error binding dma-buf {} to a texture: {:?}", fd, err);
+            return Err(d::BindError::WrongMemory);
+        }
+        Ok(())
+    }
 }
 
 pub(crate) unsafe fn set_sampler_info<SetParamFloat, SetParamFloatVec, SetParamInt>(
@@ -821,12 +1050,22 @@ impl d::Device<B> for Device {
                     panic!("Error allocating memory buffer {:?}", err);
                 }
 
+                if self.share.emulate_uniform_buffers()
+                    && buffer_usage.contains(buffer::Usage::UNIFORM)
+                {
+                    self.share
+                        .uniform_buffer_data
+                        .borrow_mut()
+                        .insert(raw, vec![0; size as usize].into_boxed_slice());
+                }
+
                 Ok(n::Memory {
                     properties: memory_type.properties,
                     buffer: Some((raw, target)),
                     size,
                     map_flags,
                     emulate_map_allocation: None,
+                    dma_buf: None,
                 })
             }
 
@@ -838,11 +1077,37 @@ impl d::Device<B> for Device {
                     size,
                     map_flags: 0,
                     emulate_map_allocation: None,
+                    dma_buf: None,
                 })
             }
         }
     }
 
+    #[cfg(unix)]
+    unsafe fn import_dma_buf(
+        &self,
+        mem_type: hal::MemoryTypeId,
+        size: u64,
+        fd: RawFd,
+    ) -> Result<n::Memory, d::ExternalMemoryError> {
+        if self.share.context.dma_buf.is_none() {
+            return Err(d::ExternalMemoryError::Unsupported);
+        }
+        // Buffers have their own storage, only the images can be imported.
+        match self.share.memory_types[mem_type.0 as usize].1 {
+            MemoryUsage::Image => {}
+            MemoryUsage::Buffer(_) => return Err(d::ExternalMemoryError::InvalidHandle),
+        }
+        Ok(n::Memory {
+            properties: memory::Properties::DEVICE_LOCAL,
+            buffer: None,
+            size,
+            map_flags: 0,
+            emulate_map_allocation: None,
+            dma_buf: Some(std::fs::File::from_raw_fd(fd)),
+        })
+    }
+
     unsafe fn create_command_pool(
         &self,
         _family: queue::QueueFamilyId,
@@ -892,13 +1157,16 @@ impl d::Device<B> for Device {
                     subpass.colors.len() <= self.share.limits.max_color_attachments,
                     "Color attachment limit exceeded"
                 );
+                assert_eq!(subpass.view_mask, 0, "GL doesn't support MULTIVIEW");
                 let color_attachments = subpass.colors.iter().map(|&(index, _)| index).collect();
 
                 let depth_stencil = subpass.depth_stencil.map(|ds| ds.0);
+                let input_attachments = subpass.inputs.iter().map(|&(index, _)| index).collect();
 
                 n::SubpassDesc {
                     color_attachments,
                     depth_stencil,
+                    input_attachments,
                 }
             })
             .collect();
@@ -923,39 +1191,77 @@ impl d::Device<B> for Device {
         let mut num_textures = 0usize;
         let mut num_uniform_buffers = 0usize;
         let mut num_storage_buffers = 0usize;
+        let mut sampler_map = [None; MAX_TEXTURE_SLOTS];
+        let mut immutable_samplers = Vec::new();
 
-        for layout_bindings in layouts {
+        for set_layout in layouts {
             // create a vector with the size enough to hold all the bindings, filled with `!0`
-            let mut bindings =
-                vec![!0; layout_bindings.last().map_or(0, |b| b.binding as usize + 1)];
+            let num_bindings = set_layout
+                .bindings
+                .last()
+                .map_or(0, |b| b.binding as usize + 1);
+            let mut bindings = vec![!0; num_bindings];
 
-            for binding in layout_bindings.iter() {
-                assert!(!binding.immutable_samplers); //TODO
+            for binding in set_layout.bindings.iter() {
                 let counter = match binding.ty {
                     pso::DescriptorType::Sampler => &mut num_samplers,
                     pso::DescriptorType::InputAttachment | pso::DescriptorType::Image { .. } => {
                         &mut num_textures
                     }
+                    // Texel buffers are bound as buffer textures.
                     pso::DescriptorType::Buffer {
-                        ty,
-                        format: _, //TODO
-                    } => match ty {
+                        format: pso::BufferDescriptorFormat::Texel,
+                        ..
+                    } => &mut num_textures,
+                    pso::DescriptorType::Buffer { ty, .. } => match ty {
                         pso::BufferDescriptorType::Uniform => &mut num_uniform_buffers,
                         pso::BufferDescriptorType::Storage { .. } => &mut num_storage_buffers,
                     },
+                    // Set layouts with inline uniform blocks fail to be created.
+                    pso::DescriptorType::InlineUniformBlock => continue,
                 };
 
                 bindings[binding.binding as usize] = (*counter).try_into().unwrap();
                 *counter += binding.count;
+
+                // Combined image samplers get a sampler slot of their own, paired
+                // with the texture once here instead of per pipeline.
+                if let pso::DescriptorType::Image {
+                    ty: pso::ImageDescriptorType::Sampled { with_sampler: true },
+                } = binding.ty
+                {
+                    let first_texture = bindings[binding.binding as usize] as usize;
+                    for i in 0..binding.count {
+                        sampler_map[first_texture + i] =
+                            Some((num_samplers + i).try_into().unwrap());
+                    }
+                    num_samplers += binding.count;
+                }
+
+                if binding.immutable_samplers {
+                    let first_slot = bindings[binding.binding as usize] as usize;
+                    for i in 0..binding.count {
+                        let sampler_slot = match binding.ty {
+                            pso::DescriptorType::Sampler => (first_slot + i).try_into().unwrap(),
+                            _ => sampler_map[first_slot + i].unwrap(),
+                        };
+                        let sampler = set_layout.immutable_sampler(binding.binding, i).unwrap();
+                        immutable_samplers.push((sampler_slot, sampler.clone()));
+                    }
+                }
             }
 
             sets.push(n::PipelineLayoutSet {
-                layout: Arc::clone(layout_bindings),
+                layout: Arc::clone(set_layout),
                 bindings,
             });
         }
 
-        Ok(n::PipelineLayout { sets })
+        Ok(n::PipelineLayout {
+            sets,
+            sampler_map,
+            immutable_samplers,
+        })
     }
 
     unsafe fn create_pipeline_cache(&self, _data: Option<&[u8]>) -> Result<(), d::OutOfMemory> {
@@ -1025,6 +1331,7 @@ impl d::Device<B> for Device {
         };
 
         let mut uniforms = Vec::new();
+        let mut uniform_blocks = Vec::new();
         {
             let gl = &self.share.context;
             let count = gl.get_active_uniforms(program);
@@ -1036,6 +1343,18 @@ impl d::Device<B> for Device {
                     gl.get_active_uniform(program, uniform).unwrap();
 
                 if let Some(location) = gl.get_uniform_location(program, &name) {
+                    if let Some(suffix) = name.strip_prefix(UNIFORM_BUFFER_PREFIX) {
+                        // Arrays are listed under the name of their first element
+                        let slot = suffix.trim_end_matches("[0]").parse().unwrap();
+                        uniform_blocks.push(n::UniformBlockDesc {
+                            location: Starc::new(location),
+                            slot,
+                            count: size as u32,
+                            utype,
+                        });
+                        continue;
+                    }
+
                     // Sampler2D won't show up in UniformLocation and the only other uniforms
                     // should be push constants
                     uniforms.push(n::UniformDesc {
@@ -1049,11 +1368,31 @@ impl d::Device<B> for Device {
             }
         }
 
+        let mut multisampling = desc.multisampling.clone();
+        if let Some(ref mut ms) = multisampling {
+            if (ms.alpha_coverage || ms.alpha_to_one) && ms.rasterization_samples <= 1 {
+                warn!("Alpha-to-coverage and alpha-to-one have no effect without multisampling");
+            }
+            if ms.alpha_to_one && !self.features.contains(hal::Features::ALPHA_TO_ONE) {
+                warn!("Alpha-to-one requires `Features::ALPHA_TO_ONE`, ignoring");
+                ms.alpha_to_one = false;
+            }
+            // Never exposed through `Features::SAMPLE_RATE_SHADING`, as there is
+            // no way to set the minimum sample shading value
+            if ms.sample_shading.is_some() {
+                warn!("Sample shading is not supported, ignoring");
+                ms.sample_shading = None;
+            }
+        }
+        if desc.blender.logic_op.is_some() && !self.features.contains(hal::Features::LOGIC_OP) {
+            warn!("Logic operations require `Features::LOGIC_OP`, ignoring");
+        }
+
         Ok(n::GraphicsPipeline {
             program,
             primitive: conv::input_assember_to_gl_primitive(input_assembler),
             patch_size,
-            blend_targets: desc.blender.targets.clone(),
+            blend_targets: self.blend_targets(&desc.blender),
             vertex_buffers,
             attributes: desc_attributes
                 .iter()
@@ -1070,10 +1409,13 @@ impl d::Device<B> for Device {
                 })
                 .collect(),
             uniforms,
+            uniform_blocks,
             rasterizer: desc.rasterizer,
+            multisampling,
             depth: desc.depth_stencil.depth,
             baked_states: desc.baked_states.clone(),
             sampler_map,
+            immutable_samplers: desc.layout.immutable_samplers.clone(),
         })
     }
 
@@ -1090,6 +1432,7 @@ impl d::Device<B> for Device {
         Ok(n::ComputePipeline {
             program,
             sampler_map,
+            immutable_samplers: desc.layout.immutable_samplers.clone(),
         })
     }
 
@@ -1263,15 +1606,6 @@ impl d::Device<B> for Device {
         size: u64,
         usage: buffer::Usage,
     ) -> Result<n::Buffer, buffer::CreationError> {
-        if !self
-            .share
-            .legacy_features
-            .contains(LegacyFeatures::CONSTANT_BUFFER)
-            && usage.contains(buffer::Usage::UNIFORM)
-        {
-            return Err(buffer::CreationError::UnsupportedUsage(usage));
-        }
-
         Ok(n::Buffer::Unbound { size, usage })
     }
 
@@ -1287,6 +1621,8 @@ impl d::Device<B> for Device {
             // currently the only alignment requirement.
             alignment: 4,
             type_mask: self.share.buffer_memory_type_mask(usage),
+            prefers_dedicated: false,
+            requires_dedicated: false,
         }
     }
 
@@ -1369,6 +1705,207 @@ impl d::Device<B> for Device {
         }
     }
 
+    unsafe fn copy_memory_to_image<I>(
+        &self,
+        data: &[u8],
+        image: &n::Image,
+        _layout: i::Layout,
+        regions: I,
+    ) -> Result<(), d::MapError>
+    where
+        I: Iterator<Item = command::BufferImageCopy>,
+    {
+        let gl = &self.share.context;
+        let (target, texture, format, pixel_type) = match image.object_type {
+            n::ImageType::Texture {
+                target,
+                raw,
+                format,
+                pixel_type,
+                ..
+            } => (target, raw, format, pixel_type),
+            n::ImageType::Renderbuffer { .. } => return Err(d::MapError::Access),
+        };
+        if !host_copy_target_supported(target) {
+            return Err(d::MapError::Access);
+        }
+
+        gl.bind_buffer(glow::PIXEL_UNPACK_BUFFER, None);
+        gl.active_texture(glow::TEXTURE0);
+        gl.bind_texture(target, Some(texture));
+
+        let mut result = Ok(());
+        for region in regions {
+            let size = host_copy_size(image, &region);
+            let pixels = match data.get(region.buffer_offset as usize..) {
+                Some(pixels) if pixels.len() >= size => glow::PixelUnpackData::Slice(pixels),
+                _ => {
+                    result = Err(d::MapError::OutOfBounds);
+                    break;
+                }
+            };
+            gl.pixel_store_i32(glow::UNPACK_ROW_LENGTH, region.buffer_width as i32);
+            gl.pixel_store_i32(glow::UNPACK_IMAGE_HEIGHT, region.buffer_height as i32);
+
+            let layers = &region.image_layers.layers;
+            match target {
+                glow::TEXTURE_2D => gl.tex_sub_image_2d(
+                    target,
+                    region.image_layers.level as _,
+                    region.image_offset.x,
+                    region.image_offset.y,
+                    region.image_extent.width as _,
+                    region.image_extent.height as _,
+                    format,
+                    pixel_type,
+                    pixels,
+                ),
+                glow::TEXTURE_2D_ARRAY => gl.tex_sub_image_3d(
+                    target,
+                    region.image_layers.level as _,
+                    region.image_offset.x,
+                    region.image_offset.y,
+                    layers.start as i32,
+                    region.image_extent.width as _,
+                    region.image_extent.height as _,
+                    (layers.end - layers.start) as i32,
+                    format,
+                    pixel_type,
+                    pixels,
+                ),
+                glow::TEXTURE_3D => gl.tex_sub_image_3d(
+                    target,
+                    region.image_layers.level as _,
+                    region.image_offset.x,
+                    region.image_offset.y,
+                    region.image_offset.z,
+                    region.image_extent.width as _,
+                    region.image_extent.height as _,
+                    region.image_extent.depth as _,
+                    format,
+                    pixel_type,
+                    pixels,
+                ),
+                _ => unreachable!(),
+            }
+        }
+
+        gl.pixel_store_i32(glow::UNPACK_ROW_LENGTH, 0);
+        gl.pixel_store_i32(glow::UNPACK_IMAGE_HEIGHT, 0);
+        gl.bind_texture(target, None);
+
+        if let Err(err) = self.share.check() {
+            error!("Error copying memory to image: {:?}", err);
+            return Err(map_host_copy_error(err));
+        }
+        result
+    }
+
+    unsafe fn copy_image_to_memory<I>(
+        &self,
+        image: &n::Image,
+        _layout: i::Layout,
+        data: &mut [u8],
+        regions: I,
+    ) -> Result<(), d::MapError>
+    where
+        I: Iterator<Item = command::BufferImageCopy>,
+    {
+        let gl = &self.share.context;
+        let (target, texture, format, pixel_type) = match image.object_type {
+            n::ImageType::Texture {
+                target,
+                raw,
+                format,
+                pixel_type,
+                ..
+            } => (target, raw, format, pixel_type),
+            n::ImageType::Renderbuffer { .. } => return Err(d::MapError::Access),
+        };
+        // only color data can be read back through a framebuffer
+        if image.format_desc.aspects != Aspects::COLOR || !host_copy_target_supported(target) {
+            return Err(d::MapError::Access);
+        }
+        let attachment = glow::COLOR_ATTACHMENT0;
+
+        // The texture is read back one layer at a time through a temporary framebuffer.
+        let fbo = gl.create_framebuffer().unwrap();
+        gl.bind_framebuffer(glow::READ_FRAMEBUFFER, Some(fbo));
+        gl.bind_buffer(glow::PIXEL_PACK_BUFFER, None);
+
+        let mut result = Ok(());
+        for region in regions {
+            let size = host_copy_size(image, &region);
+            let pixels = match data.get_mut(region.buffer_offset as usize..) {
+                Some(pixels) if pixels.len() >= size => pixels,
+                _ => {
+                    result = Err(d::MapError::OutOfBounds);
+                    break;
+                }
+            };
+            gl.pixel_store_i32(glow::PACK_ROW_LENGTH, region.buffer_width as i32);
+
+            let slice_pitch = size / host_copy_slice_count(&region);
+            let level = region.image_layers.level as i32;
+            let (z_range, layer_range) = match target {
+                glow::TEXTURE_2D => (0..1, 0..1),
+                glow::TEXTURE_3D => (
+                    region.image_offset.z..region.image_offset.z + region.image_extent.depth as i32,
+                    0..1,
+                ),
+                _ => (
+                    0..1,
+                    region.image_layers.layers.start as i32..region.image_layers.layers.end as i32,
+                ),
+            };
+            let slices = z_range.flat_map(|z| layer_range.clone().map(move |l| (z, l)));
+            for (i, (z, layer)) in slices.enumerate() {
+                match target {
+                    glow::TEXTURE_2D => gl.framebuffer_texture_2d(
+                        glow::READ_FRAMEBUFFER,
+                        attachment,
+                        target,
+                        Some(texture),
+                        level,
+                    ),
+                    glow::TEXTURE_3D => gl.framebuffer_texture_layer(
+                        glow::READ_FRAMEBUFFER,
+                        attachment,
+                        Some(texture),
+                        level,
+                        z,
+                    ),
+                    _ => gl.framebuffer_texture_layer(
+                        glow::READ_FRAMEBUFFER,
+                        attachment,
+                        Some(texture),
+                        level,
+                        layer,
+                    ),
+                }
+                gl.read_pixels(
+                    region.image_offset.x,
+                    region.image_offset.y,
+                    region.image_extent.width as _,
+                    region.image_extent.height as _,
+                    format,
+                    pixel_type,
+                    glow::PixelPackData::Slice(&mut pixels[i * slice_pitch..]),
+                );
+            }
+        }
+
+        gl.pixel_store_i32(glow::PACK_ROW_LENGTH, 0);
+        gl.bind_framebuffer(glow::READ_FRAMEBUFFER, None);
+        gl.delete_framebuffer(fbo);
+
+        if let Err(err) = self.share.check() {
+            error!("Error copying image to memory: {:?}", err);
+            return Err(map_host_copy_error(err));
+        }
+        result
+    }
+
     unsafe fn flush_mapped_memory_ranges<'a, I>(&self, ranges: I) -> Result<(), d::OutOfMemory>
     where
         I: Iterator<Item = (&'a n::Memory, memory::Segment)>,
@@ -1386,6 +1923,10 @@ impl d::Device<B> for Device {
                 let ptr = mem.emulate_map_allocation.unwrap();
                 let slice = slice::from_raw_parts_mut(ptr.offset(offset as isize), size as usize);
                 gl.buffer_sub_data_u8_slice(target, offset as i32, slice);
+                // Memory mapping is always emulated along with the uniform buffers
+                if let Some(data) = self.share.uniform_buffer_data.borrow_mut().get_mut(&buffer) {
+                    data[offset as usize..(offset + size) as usize].copy_from_slice(slice);
+                }
             } else {
                 gl.flush_mapped_buffer_range(target, offset as i32, size as i32);
             }
@@ -1434,13 +1975,53 @@ impl d::Device<B> for Device {
         Ok(())
     }
 
+    #[cfg(not(target_arch = "wasm32"))]
+    unsafe fn create_buffer_view(
+        &self,
+        buffer: &n::Buffer,
+        format: Option<Format>,
+        sub: buffer::SubRange,
+    ) -> Result<n::BufferView, buffer::ViewCreationError> {
+        let gl = &self.share.context;
+        let format = format.ok_or(buffer::ViewCreationError::UnsupportedFormat(None))?;
+        let (tex_buffer_range, desc) = match (gl.tex_buffer_range, conv::describe_format(format)) {
+            (Some(fun), Some(desc)) if conv::is_texel_buffer_format(format) => (fun, desc),
+            _ => return Err(buffer::ViewCreationError::UnsupportedFormat(Some(format))),
+        };
+
+        let (raw_buffer, buffer_range) = buffer.as_bound();
+        let range = crate::resolve_sub_range(&sub, buffer_range);
+
+        let raw = gl.create_texture().unwrap();
+        gl.bind_texture(glow::TEXTURE_BUFFER, Some(raw));
+        tex_buffer_range(
+            glow::TEXTURE_BUFFER,
+            desc.tex_internal,
+            raw_buffer,
+            range.start as isize,
+            (range.end - range.start) as isize,
+        );
+        gl.bind_texture(glow::TEXTURE_BUFFER, None);
+
+        if let Err(err) = self.share.check() {
+            panic!(
+                "Error creating buffer view: {:?} for format: {:?}",
+                err, format
+            );
+        }
+
+        Ok(n::BufferView { raw })
+    }
+
+    #[cfg(target_arch = "wasm32")]
     unsafe fn create_buffer_view(
         &self,
         _: &n::Buffer,
-        _: Option<Format>,
+        format: Option<Format>,
         _: buffer::SubRange,
     ) -> Result<n::BufferView, buffer::ViewCreationError> {
-        unimplemented!()
+        // WebGL has no buffer textures
+        Err(buffer::ViewCreationError::UnsupportedFormat(format))
     }
 
     unsafe fn create_image(
@@ -1458,7 +2039,13 @@ impl d::Device<B> for Device {
         let channel = format.base_format().1;
 
         let mut pixel_count: u64 = 0;
-        let image = if num_levels > 1 || usage.intersects(i::Usage::STORAGE | i::Usage::SAMPLED) {
+        let texture_usage = i::Usage::STORAGE | i::Usage::SAMPLED | i::Usage::INPUT_ATTACHMENT;
+        let is_texture = num_levels > 1 || usage.intersects(texture_usage);
+        if is_texture && format == Format::S8Uint && !self.share.private_caps.texture_stencil8 {
+            warn!("Stencil-only textures are not supported, only renderbuffers");
+            return Err(i::CreationError::Format(format));
+        }
+        let image = if is_texture {
             let name = gl.create_texture().unwrap();
             let target = match kind {
                 i::Kind::D2(w, h, 1, 1) => {
@@ -1596,12 +2183,92 @@ impl d::Device<B> for Device {
                 size,
                 alignment: 1,
                 type_mask,
+                prefers_dedicated: false,
+                requires_dedicated: false,
             },
             num_levels,
             num_layers: kind.num_layers(),
+            drm: None,
+        })
+    }
+
+    unsafe fn create_image_with_drm_modifier(
+        &self,
+        kind: i::Kind,
+        format: Format,
+        tiling: i::DrmTiling,
+        _usage: i::Usage,
+        _view_caps: i::ViewCapabilities,
+    ) -> Result<n::Image, i::CreationError> {
+        let import = match self.share.context.dma_buf {
+            Some(ref import) => import,
+            None => return Err(i::CreationError::DrmModifier),
+        };
+        // The buffers can only be imported, so their layout is already known.
+        let (modifier, planes) = match tiling {
+            i::DrmTiling::Explicit { modifier, planes } => (modifier, planes),
+            i::DrmTiling::List(_) => return Err(i::CreationError::DrmModifier),
+        };
+        let height = match kind {
+            i::Kind::D2(_, height, 1, 1) => height,
+            _ => return Err(i::CreationError::Kind),
+        };
+        let desc = conv::describe_format(format).ok_or(i::CreationError::Format(format))?;
+        let fourcc = dma_buf::map_format(format).ok_or(i::CreationError::Format(format))?;
+        if planes.is_empty()
+            || planes.len() > dma_buf::MAX_PLANES
+            || !import.modifiers(fourcc).contains(&modifier)
+        {
+            return Err(i::CreationError::DrmModifier);
+        }
+
+        let mut layout = n::DrmLayout {
+            fourcc,
+            modifier,
+            planes: [i::DrmPlaneLayout {
+                offset: 0,
+                row_pitch: 0,
+            }; dma_buf::MAX_PLANES],
+            plane_count: planes.len(),
+        };
+        layout.planes[..planes.len()].copy_from_slice(planes);
+        let size = planes
+            .iter()
+            .map(|plane| plane.offset + plane.row_pitch * height as u64)
+            .max()
+            .unwrap();
+
+        // The storage comes with the memory, when it's bound.
+        let name = self.share.context.create_texture().unwrap();
+        Ok(n::Image {
+            object_type: n::ImageType::Texture {
+                target: glow::TEXTURE_2D,
+                raw: name,
+                level_count: 1,
+                layer_count: 1,
+                format: desc.tex_external,
+                pixel_type: desc.data_type,
+            },
+            kind,
+            format_desc: format.base_format().0.desc(),
+            channel: format.base_format().1,
+            requirements: memory::Requirements {
+                size,
+                alignment: 1,
+                type_mask: self.share.image_memory_type_mask(),
+                prefers_dedicated: true,
+                requires_dedicated: false,
+            },
+            num_levels: 1,
+            num_layers: 1,
+            drm: Some(layout),
         })
     }
 
+    unsafe fn get_image_drm_modifier(&self, image: &n::Image) -> Option<i::DrmModifier> {
+        image.drm.map(|layout| layout.modifier)
+    }
+
     unsafe fn get_image_requirements(&self, unbound: &n::Image) -> memory::Requirements {
         unbound.requirements
     }
@@ -1626,13 +2293,21 @@ impl d::Device<B> for Device {
         }
     }
 
+    #[cfg_attr(not(unix), allow(unused_variables))]
     unsafe fn bind_image_memory(
         &self,
-        _memory: &n::Memory,
-        _offset: u64,
-        _image: &mut n::Image,
+        memory: &n::Memory,
+        offset: u64,
+        image: &mut n::Image,
     ) -> Result<(), d::BindError> {
-        Ok(())
+        match (image.drm, &memory.dma_buf) {
+            (None, _) => Ok(()),
+            #[cfg(unix)]
+            (Some(ref layout), Some(file)) => {
+                self.bind_dma_buf(file.as_raw_fd(), offset, image, layout)
+            }
+            (Some(_), _) => Err(d::BindError::WrongMemory),
+        }
     }
 
     unsafe fn create_image_view(
@@ -1707,16 +2382,35 @@ impl d::Device<B> for Device {
     unsafe fn create_descriptor_set_layout<'a, I, J>(
         &self,
         layout: I,
-        _immutable_samplers: J,
+        mut immutable_samplers: J,
     ) -> Result<n::DescriptorSetLayout, d::OutOfMemory>
     where
         I: Iterator<Item = pso::DescriptorSetLayoutBinding>,
         J: Iterator<Item = &'a n::FatSampler>,
     {
         let mut bindings = layout.collect::<Vec<_>>();
+        if bindings
+            .iter()
+            .any(|b| b.ty == pso::DescriptorType::InlineUniformBlock)
+        {
+            error!("GL doesn't support INLINE_UNIFORM_BLOCK");
+            return Err(d::OutOfMemory::Host);
+        }
+        // immutable samplers come in the order of the bindings that use them
+        let immutable_samplers = bindings
+            .iter()
+            .filter(|b| b.immutable_samplers)
+            .map(|b| {
+                let samplers = immutable_samplers.by_ref().take(b.count).cloned().collect();
+                (b.binding, samplers)
+            })
+            .collect();
         // all operations rely on the ascending bindings order
         bindings.sort_by_key(|b| b.binding);
-        Ok(Arc::new(bindings))
+        Ok(Arc::new(n::DescriptorSetLayoutData {
+            bindings,
+            immutable_samplers,
+        }))
     }
 
     unsafe fn write_descriptor_set<'a, I>(&self, op: pso::DescriptorSetWrite<'a, B, I>)
@@ -1726,12 +2420,18 @@ impl d::Device<B> for Device {
         let mut layout_index = op
             .set
             .layout
+            .bindings
             .binary_search_by_key(&op.binding, |b| b.binding)
             .unwrap();
         let mut array_offset = op.array_offset;
 
         for descriptor in op.descriptors {
-            let binding_layout = &op.set.layout[layout_index];
+            let binding_layout = &op.set.layout.bindings[layout_index];
+            // Immutable samplers take the place of the written ones.
+            let immutable_sampler = op
+                .set
+                .layout
+                .immutable_sampler(binding_layout.binding, array_offset);
             let binding = match descriptor {
                 pso::Descriptor::Buffer(buffer, ref sub) => {
                     let (raw_buffer, buffer_range) = buffer.as_bound();
@@ -1758,23 +2458,15 @@ impl d::Device<B> for Device {
                         size: (range.end - range.start) as i32,
                     }
                 }
-                pso::Descriptor::CombinedImageSampler(view, _layout, sampler) => {
-                    match *view {
-                        n::ImageView::Texture { target, raw, .. } => op
-                            .set
-                            .bindings
-                            .push(n::DescSetBindings::Texture(raw, target)),
-                        n::ImageView::Renderbuffer { .. } => {
-                            panic!("Texture doesn't support shader binding")
-                        }
+                pso::Descriptor::CombinedImageSampler(view, _layout, sampler) => match *view {
+                    n::ImageView::Texture { target, raw, .. } => {
+                        let sampler = immutable_sampler.unwrap_or(sampler);
+                        n::DescSetBindings::CombinedImageSampler(raw, target, sampler.clone())
                     }
-                    match *sampler {
-                        n::FatSampler::Sampler(sampler) => n::DescSetBindings::Sampler(sampler),
-                        n::FatSampler::Info(ref info) => {
-                            n::DescSetBindings::SamplerDesc(info.clone())
-                        }
+                    n::ImageView::Renderbuffer { .. } => {
+                        panic!("Texture doesn't support shader binding")
                     }
-                }
+                },
                 pso::Descriptor::Image(view, _layout) => match *view {
                     n::ImageView::Texture { target, raw, .. } => {
                         n::DescSetBindings::Texture(raw, target)
@@ -1783,11 +2475,56 @@ impl d::Device<B> for Device {
                         panic!("Texture doesn't support shader binding")
                     }
                 },
-                pso::Descriptor::Sampler(sampler) => match *sampler {
+                pso::Descriptor::Sampler(sampler) => match *immutable_sampler.unwrap_or(sampler) {
                     n::FatSampler::Sampler(sampler) => n::DescSetBindings::Sampler(sampler),
                     n::FatSampler::Info(ref info) => n::DescSetBindings::SamplerDesc(info.clone()),
                 },
-                pso::Descriptor::TexelBuffer(_view) => unimplemented!(),
+                pso::Descriptor::TexelBuffer(view) => {
+                    n::DescSetBindings::Texture(view.raw, glow::TEXTURE_BUFFER)
+                }
+                // Layouts with inline uniform blocks can't be created.
+                pso::Descriptor::InlineUniformBlock(_) => panic!(
+                    "Can't write inline uniform block into descriptor of type {:?}",
+                    binding_layout.ty
+                ),
+                pso::Descriptor::Null => {
+                    let null = self
+                        .null_resources
+                        .as_ref()
+                        .expect("Null descriptors require `Features::NULL_DESCRIPTOR`");
+                    match binding_layout.ty {
+                        pso::DescriptorType::Buffer {
+                            ty,
+                            format: pso::BufferDescriptorFormat::Structured { .. },
+                        } => n::DescSetBindings::Buffer {
+                            register: match ty {
+                                pso::BufferDescriptorType::Uniform => {
+                                    n::BindingRegister::UniformBuffers
+                                }
+                                pso::BufferDescriptorType::Storage { .. } => {
+                                    n::BindingRegister::StorageBuffers
+                                }
+                            },
+                            buffer: null.buffer,
+                            offset: 0,
+                            size: NULL_BUFFER_SIZE,
+                        },
+                        pso::DescriptorType::Buffer { .. } => n::DescSetBindings::Texture(
+                            null.texel_buffer.expect("Texel buffers are not supported"),
+                            glow::TEXTURE_BUFFER,
+                        ),
+                        pso::DescriptorType::Image { .. }
+                        | pso::DescriptorType::InputAttachment => {
+                            n::DescSetBindings::Texture(null.texture, glow::TEXTURE_2D)
+                        }
+                        pso::DescriptorType::Sampler | pso::DescriptorType::InlineUniformBlock => {
+                            panic!(
+                                "Can't write null into descriptor of type {:?}",
+                                binding_layout.ty
+                            )
+                        }
+                    }
+                }
             };
 
             //TODO: overwrite instead of pushing on top
@@ -1942,6 +2679,7 @@ impl d::Device<B> for Device {
 
     unsafe fn free_memory(&self, memory: n::Memory) {
         if let Some((buffer, _)) = memory.buffer {
+            self.share.uniform_buffer_data.borrow_mut().remove(&buffer);
             self.share.context.delete_buffer(buffer);
         }
     }
@@ -1949,24 +2687,92 @@ impl d::Device<B> for Device {
     unsafe fn create_query_pool(
         &self,
         ty: query::Type,
-        _count: query::Id,
-    ) -> Result<(), query::CreationError> {
-        Err(query::CreationError::Unsupported(ty))
+        count: query::Id,
+    ) -> Result<n::QueryPool, query::CreationError> {
+        let caps = &self.share.private_caps;
+        let target = match ty {
+            query::Type::Occlusion if caps.samples_passed_query => glow::SAMPLES_PASSED,
+            query::Type::Occlusion if caps.occlusion_query => glow::ANY_SAMPLES_PASSED,
+            // Timestamps need 64-bit query results, which are not exposed yet.
+            _ => return Err(query::CreationError::Unsupported(ty)),
+        };
+
+        let gl = &self.share.context;
+        let mut queries = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            match gl.create_query() {
+                Ok(query) => queries.push(query),
+                Err(err) => {
+                    error!("Unable to create a query: {}", err);
+                    for query in queries {
+                        gl.delete_query(query);
+                    }
+                    return Err(d::OutOfMemory::Host.into());
+                }
+            }
+        }
+        Ok(n::QueryPool { target, queries })
     }
 
-    unsafe fn destroy_query_pool(&self, _: ()) {
-        unimplemented!()
+    unsafe fn destroy_query_pool(&self, pool: n::QueryPool) {
+        let gl = &self.share.context;
+        for query in pool.queries {
+            gl.delete_query(query);
+        }
     }
 
     unsafe fn get_query_pool_results(
         &self,
-        _pool: &(),
-        _queries: Range<query::Id>,
-        _data: &mut [u8],
-        _stride: buffer::Stride,
-        _flags: query::ResultFlags,
+        pool: &n::QueryPool,
+        queries: Range<query::Id>,
+        data: &mut [u8],
+        stride: buffer::Stride,
+        flags: query::ResultFlags,
     ) -> Result<bool, d::WaitError> {
-        unimplemented!()
+        let gl = &self.share.context;
+        let mut all_ready = true;
+
+        for (i, &query) in pool.queries[queries.start as usize..queries.end as usize]
+            .iter()
+            .enumerate()
+        {
+            // Reading the result itself blocks until it's available.
+            let available = flags.contains(query::ResultFlags::WAIT)
+                || gl.get_query_parameter_u32(query, glow::QUERY_RESULT_AVAILABLE) != 0;
+            all_ready &= available;
+
+            let value = if available {
+                Some(gl.get_query_parameter_u32(query, glow::QUERY_RESULT))
+            } else if flags.contains(query::ResultFlags::PARTIAL) {
+                Some(0)
+            } else {
+                None
+            };
+
+            let chunk = &mut data[i * stride as usize..];
+            if flags.contains(query::ResultFlags::BITS_64) {
+                let chunk = chunk.as_mut_ptr() as *mut u64;
+                if let Some(value) = value {
+                    *chunk = value as u64;
+                }
+                if flags.contains(query::ResultFlags::WITH_AVAILABILITY) {
+                    *chunk.offset(1) = available as u64;
+                }
+            } else {
+                let chunk = chunk.as_mut_ptr() as *mut u32;
+                if let Some(value) = value {
+                    *chunk = value;
+                }
+                if flags.contains(query::ResultFlags::WITH_AVAILABILITY) {
+                    *chunk.offset(1) = available as u32;
+                }
+            }
+        }
+
+        if let Err(err) = self.share.check() {
+            error!("Error getting query results: {:?}", err);
+        }
+        Ok(all_ready)
     }
 
     unsafe fn destroy_shader_module(&self, _: n::ShaderModule) {
@@ -1997,8 +2803,8 @@ impl d::Device<B> for Device {
         // Nothing to do
     }
 
-    unsafe fn destroy_buffer_view(&self, _: n::BufferView) {
-        // Nothing to do
+    unsafe fn destroy_buffer_view(&self, view: n::BufferView) {
+        self.share.context.delete_texture(view.raw);
     }
 
     unsafe fn destroy_image(&self, image: n::Image) {
@@ -2021,6 +2827,33 @@ impl d::Device<B> for Device {
         }
     }
 
+    unsafe fn create_sampler_ycbcr_conversion(
+        &self,
+        desc: &i::SamplerYcbcrConversionDesc,
+    ) -> Result<(), i::CreationError> {
+        Err(i::CreationError::Format(desc.format))
+    }
+
+    unsafe fn destroy_sampler_ycbcr_conversion(&self, _conversion: ()) {}
+
+    unsafe fn create_ycbcr_sampler(
+        &self,
+        _desc: &i::SamplerDesc,
+        _conversion: &(),
+    ) -> Result<n::FatSampler, d::AllocationError> {
+        unimplemented!()
+    }
+
+    unsafe fn create_ycbcr_image_view(
+        &self,
+        _image: &n::Image,
+        _view_kind: i::ViewKind,
+        _range: i::SubresourceRange,
+        _conversion: &(),
+    ) -> Result<n::ImageView, i::ViewCreationError> {
+        unimplemented!()
+    }
+
     unsafe fn destroy_descriptor_pool(&self, _: n::DescriptorPool) {
         // Nothing to do
     }
@@ -2046,7 +2879,7 @@ impl d::Device<B> for Device {
         unimplemented!()
     }
 
-    fn wait_idle(&self) -> Result<(), d::OutOfMemory> {
+    fn wait_idle(&self) -> Result<(), d::WaitError> {
         unsafe {
             self.share.context.finish();
         }
@@ -0,0 +1,172 @@
+//! Import of Linux dma-bufs as textures, with `EGL_EXT_image_dma_buf_import`.
+//!
+//! GL can't allocate such buffers itself, so the images are only imported:
+//! an EGL image is created over the planes of the buffer, and becomes the
+//! storage of a texture with `glEGLImageTargetTexture2DOES`.
+#![cfg_attr(not(unix), allow(dead_code))]
+
+use crate::native::DrmLayout;
+use hal::{format::Format, image};
+use std::{os::raw, ptr};
+
+/// `eglCreateImageKHR`, from `EGL_KHR_image_base`.
+pub type CreateImage = unsafe extern "system" fn(
+    display: *mut raw::c_void,
+    context: *mut raw::c_void,
+    target: u32,
+    buffer: *mut raw::c_void,
+    attributes: *const i32,
+) -> *mut raw::c_void;
+/// `eglDestroyImageKHR`, from `EGL_KHR_image_base`.
+pub type DestroyImage =
+    unsafe extern "system" fn(display: *mut raw::c_void, image: *mut raw::c_void) -> u32;
+/// `eglQueryDmaBufModifiersEXT`, from `EGL_EXT_image_dma_buf_import_modifiers`.
+pub type QueryModifiers = unsafe extern "system" fn(
+    display: *mut raw::c_void,
+    format: i32,
+    max_modifiers: i32,
+    modifiers: *mut u64,
+    external_only: *mut u32,
+    num_modifiers: *mut i32,
+) -> u32;
+/// `glEGLImageTargetTexture2DOES`, from `GL_OES_EGL_image`.
+pub type ImageTargetTexture = unsafe extern "system" fn(target: u32, image: *mut raw::c_void);
+
+const EGL_NONE: i32 = 0x3038;
+const EGL_HEIGHT: i32 = 0x3056;
+const EGL_WIDTH: i32 = 0x3057;
+const EGL_LINUX_DMA_BUF_EXT: u32 = 0x3270;
+const EGL_LINUX_DRM_FOURCC_EXT: i32 = 0x3271;
+/// File descriptor, offset, pitch, and the low and high halves of the modifier of each plane.
+const PLANE_ATTRIBUTES: [[i32; 5]; MAX_PLANES] = [
+    [0x3272, 0x3273, 0x3274, 0x3443, 0x3444],
+    [0x3275, 0x3276, 0x3277, 0x3445, 0x3446],
+    [0x3278, 0x3279, 0x327A, 0x3447, 0x3448],
+    [0x3440, 0x3441, 0x3442, 0x3449, 0x344A],
+];
+
+/// Maximum number of memory planes of an imported image.
+pub const MAX_PLANES: usize = 4;
+
+fn fourcc(code: &[u8; 4]) -> u32 {
+    code[0] as u32 | (code[1] as u32) << 8 | (code[2] as u32) << 16 | (code[3] as u32) << 24
+}
+
+/// Map a format to its DRM fourcc code, as defined by `drm_fourcc.h`.
+///
+/// The DRM formats are little-endian, listing the channels from the most
+/// significant bits.
+pub fn map_format(format: Format) -> Option<u32> {
+    Some(match format {
+        Format::R8Unorm => fourcc(b"R8  "),
+        Format::Rg8Unorm => fourcc(b"GR88"),
+        Format::R16Unorm => fourcc(b"R16 "),
+        Format::Rgba8Unorm => fourcc(b"AB24"),
+        Format::Bgra8Unorm => fourcc(b"AR24"),
+        Format::A2b10g10r10Unorm => fourcc(b"AB30"),
+        Format::Rgba16Sfloat => fourcc(b"AB4H"),
+        _ => return None,
+    })
+}
+
+/// Entry points and display used to import dma-bufs.
+#[derive(Clone, Copy, Debug)]
+pub struct DmaBufImport {
+    pub display: *mut raw::c_void,
+    pub create_image: CreateImage,
+    pub destroy_image: DestroyImage,
+    /// Without `EGL_EXT_image_dma_buf_import_modifiers`, the buffers are
+    /// imported with their implicit layout, which is assumed to be linear.
+    pub query_modifiers: Option<QueryModifiers>,
+    pub image_target_texture: ImageTargetTexture,
+}
+
+impl DmaBufImport {
+    /// Get the modifiers a DRM format can be imported with as a regular texture.
+    pub unsafe fn modifiers(&self, fourcc: u32) -> Vec<image::DrmModifier> {
+        let query = match self.query_modifiers {
+            Some(query) => query,
+            None => return vec![image::DrmModifier::LINEAR],
+        };
+        let mut count = 0;
+        if query(
+            self.display,
+            fourcc as i32,
+            0,
+            ptr::null_mut(),
+            ptr::null_mut(),
+            &mut count,
+        ) == 0
+        {
+            return Vec::new();
+        }
+        let mut modifiers = vec![0; count as usize];
+        let mut external_only = vec![0; count as usize];
+        if query(
+            self.display,
+            fourcc as i32,
+            count,
+            modifiers.as_mut_ptr(),
+            external_only.as_mut_ptr(),
+            &mut count,
+        ) == 0
+        {
+            return Vec::new();
+        }
+        // The external-only ones can't back a `TEXTURE_2D`.
+        modifiers
+            .into_iter()
+            .zip(external_only)
+            .take(count as usize)
+            .filter(|&(_, external)| external == 0)
+            .map(|(modifier, _)| image::DrmModifier(modifier))
+            .collect()
+    }
+
+    /// Create an EGL image over the planes of the dma-buf `fd`, starting at `offset`.
+    ///
+    /// Returns a null pointer if the implementation rejects the buffer or its layout.
+    pub unsafe fn import(
+        &self,
+        fd: raw::c_int,
+        offset: u64,
+        extent: image::Extent,
+        layout: &DrmLayout,
+    ) -> *mut raw::c_void {
+        let mut attributes = vec![
+            EGL_WIDTH,
+            extent.width as i32,
+            EGL_HEIGHT,
+            extent.height as i32,
+            EGL_LINUX_DRM_FOURCC_EXT,
+            layout.fourcc as i32,
+        ];
+        for (plane, names) in layout.planes().iter().zip(PLANE_ATTRIBUTES.iter()) {
+            attributes.extend_from_slice(&[
+                names[0],
+                fd,
+                names[1],
+                (offset + plane.offset) as i32,
+                names[2],
+                plane.row_pitch as i32,
+            ]);
+            if self.query_modifiers.is_some() {
+                attributes.extend_from_slice(&[
+                    names[3],
+                    layout.modifier.0 as i32,
+                    names[4],
+                    (layout.modifier.0 >> 32) as i32,
+                ]);
+            }
+        }
+        attributes.push(EGL_NONE);
+
+        (self.create_image)(
+            self.display,
+            ptr::null_mut(),
+            EGL_LINUX_DMA_BUF_EXT,
+            ptr::null_mut(),
+            attributes.as_ptr(),
+        )
+    }
+}
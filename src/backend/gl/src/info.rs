@@ -57,12 +57,17 @@ impl Version {
     /// resulting in an `Err`.
     pub fn parse(mut src: &str) -> Result<Version, &str> {
         // TODO: Parse version and optional vendor
+        // WebGL 1 is based on OpenGL ES 2.0, and WebGL 2 on OpenGL ES 3.0
         let webgl_sig = "WebGL ";
-        let is_webgl = src.contains(webgl_sig);
-        if is_webgl {
+        if let Some(pos) = src.find(webgl_sig) {
+            let major = if src[pos + webgl_sig.len()..].starts_with('1') {
+                2
+            } else {
+                3
+            };
             return Ok(Version {
                 is_embedded: true,
-                major: 2,
+                major,
                 minor: 0,
                 revision: None,
                 vendor_info: "".to_string(),
@@ -206,10 +211,33 @@ pub struct PrivateCaps {
     pub draw_buffers: bool,
     /// Whether separate color masks per output buffer are supported.
     pub per_slot_color_mask: bool,
+    /// Blending can be enabled separately per output buffer, with `glEnablei`.
+    pub per_slot_blend_enable: bool,
     /// Reading from textures into CPU memory is supported.
     pub get_tex_image: bool,
     /// Inserting memory barriers.
     pub memory_barrier: bool,
+    /// Ordering the texture fetches after the framebuffer writes with `glTextureBarrier`.
+    pub texture_barrier: bool,
+    /// Floating-point formats, including `R11F_G11F_B10F`, are color-renderable.
+    pub color_buffer_float: bool,
+    /// Occlusion queries, at least of the `ANY_SAMPLES_PASSED` kind.
+    pub occlusion_query: bool,
+    /// Occlusion queries counting the samples with `SAMPLES_PASSED`.
+    pub samples_passed_query: bool,
+    /// Textures, and not only renderbuffers, can have the `STENCIL_INDEX8` format.
+    pub texture_stencil8: bool,
+    /// Textures can have sRGB formats, which is not the case in OpenGL ES 2.0 and WebGL 1.
+    pub srgb_texture: bool,
+    /// `FRAMEBUFFER_SRGB` can be disabled to write into sRGB framebuffers without encoding.
+    /// It's enabled outside of presentation.
+    pub framebuffer_srgb_control: bool,
+    /// Buffer textures can be created over a sub-range, with `glTexBufferRange`.
+    pub texture_buffer_range: bool,
+    /// Consecutive buffer ranges can be bound with a single `glBindBuffersRange`.
+    pub multi_bind: bool,
+    /// EGL images can back textures, with `glEGLImageTargetTexture2DOES`.
+    pub egl_image: bool,
 }
 
 /// OpenGL implementation information
@@ -276,7 +304,12 @@ impl Info {
         let version = Version::parse(&raw_version).unwrap();
         let raw_shader_version;
         let shading_language = if IS_WEBGL {
-            Version::new_embedded(3, 0, String::from(""))
+            // GLSL ES 1.00 for WebGL 1, and GLSL ES 3.00 for WebGL 2
+            if version.major < 3 {
+                Version::new_embedded(1, 0, String::from(""))
+            } else {
+                Version::new_embedded(3, 0, String::from(""))
+            }
         } else {
             raw_shader_version = get_string(gl, glow::SHADING_LANGUAGE_VERSION).unwrap_or_default();
             Version::parse(&raw_shader_version).unwrap()
@@ -360,7 +393,8 @@ pub(crate) fn query_all(
     use self::Requirement::*;
     let info = Info::get(gl);
     let max_texture_size = get_usize(gl, glow::MAX_TEXTURE_SIZE).unwrap_or(64) as u32;
-    let max_samples = get_usize(gl, glow::MAX_SAMPLES).unwrap_or(8);
+    // OpenGL ES 2.0 has no multisampled framebuffers
+    let max_samples = get_usize(gl, glow::MAX_SAMPLES).unwrap_or(1);
     let max_samples_mask = (max_samples * 2 - 1) as u8;
     let max_texel_elements = if IS_WEBGL {
         0
@@ -373,6 +407,9 @@ pub(crate) fn query_all(
         get_u64(gl, glow::SHADER_STORAGE_BUFFER_OFFSET_ALIGNMENT).unwrap_or(256)
     };
 
+    // Dynamic buffers can take any of the binding points
+    let max_uniform_buffer_bindings = get_usize(gl, glow::MAX_UNIFORM_BUFFER_BINDINGS).unwrap_or(0);
+
     let mut limits = Limits {
         max_image_1d_size: max_texture_size,
         max_image_2d_size: max_texture_size,
@@ -387,6 +424,10 @@ pub(crate) fn query_all(
         min_uniform_buffer_offset_alignment: get_u64(gl, glow::UNIFORM_BUFFER_OFFSET_ALIGNMENT)
             .unwrap_or(1024),
         min_storage_buffer_offset_alignment,
+        max_descriptor_set_uniform_buffers_dynamic: max_uniform_buffer_bindings,
+        // Push constants are plain uniforms, and every GL version has room
+        // for at least 1 KB of them per stage.
+        max_push_constants_size: 256,
         framebuffer_color_sample_counts: max_samples_mask,
         non_coherent_atom_size: 1,
         max_color_attachments: get_usize(gl, glow::MAX_COLOR_ATTACHMENTS)
@@ -402,11 +443,21 @@ pub(crate) fn query_all(
         // TODO: extension
         limits.max_viewports = get_usize(gl, glow::MAX_VIEWPORTS).unwrap_or(0);
     }
+    if info.is_supported(&[Core(4, 3), Es(3, 2), Ext("GL_ARB_texture_buffer_range")]) {
+        limits.min_texel_buffer_offset_alignment =
+            get_u64(gl, glow::TEXTURE_BUFFER_OFFSET_ALIGNMENT).unwrap_or(256);
+    }
+    // Advanced blending is only defined with a single draw buffer
+    if info.is_supported(&[Ext("GL_KHR_blend_equation_advanced_coherent")]) {
+        limits.max_advanced_blend_color_attachments = 1;
+    }
 
     //TODO: technically compute is exposed in Es(3, 1), but GLES requires 3.2
     // for any storage buffers. We need to investigate if this requirement
     // can be lowered.
     if info.is_supported(&[Core(4, 3), Es(3, 2), Ext("GL_ARB_compute_shader")]) {
+        limits.max_descriptor_set_storage_buffers_dynamic =
+            get_usize(gl, glow::MAX_SHADER_STORAGE_BUFFER_BINDINGS).unwrap_or(0);
         for (i, (count, size)) in limits
             .max_compute_work_group_count
             .iter_mut()
@@ -422,9 +473,16 @@ pub(crate) fn query_all(
         }
     }
 
-    let mut features = Features::NDC_Y_UP | Features::MUTABLE_COMPARISON_SAMPLER;
+    let mut features = Features::NDC_Y_UP;
     let mut legacy = LegacyFeatures::empty();
 
+    if info.is_supported(&[Core(1, 4), Es(3, 0), Ext("GL_EXT_shadow_samplers")]) {
+        features |= Features::MUTABLE_COMPARISON_SAMPLER;
+    }
+    if !info.is_supported(&[Core(1, 2), Es(3, 0), Ext("GL_OES_texture_3D")]) {
+        limits.max_image_3d_size = 0;
+    }
+
     if info.is_supported(&[
         Core(4, 6),
         Ext("GL_ARB_texture_filter_anisotropic"),
@@ -448,9 +506,21 @@ pub(crate) fn query_all(
     if info.is_supported(&[Core(4, 4), Ext("ARB_texture_mirror_clamp_to_edge")]) {
         features |= Features::SAMPLER_MIRROR_CLAMP_EDGE;
     }
-    if info.is_supported(&[Core(4, 0), Es(3, 2), Ext("GL_EXT_draw_buffers2")]) && !info.is_webgl() {
+    if info.is_supported(&[Core(1, 3)]) && !info.is_webgl() {
+        features |= Features::ALPHA_TO_ONE;
+    }
+    // `GL_EXT_draw_buffers2` only has the per-slot enables, not the blend functions
+    if info.is_supported(&[Core(4, 0), Es(3, 2)]) && !info.is_webgl() {
         features |= Features::INDEPENDENT_BLENDING;
     }
+    // Without the coherent variant, blend barriers would be needed between the draws
+    if info.is_supported(&[Ext("GL_KHR_blend_equation_advanced_coherent")]) {
+        features |= Features::BLEND_OPERATION_ADVANCED;
+    }
+    // `glLineStipple` is only in the compatibility profile, so only smoothing is exposed
+    if info.is_supported(&[Core(1, 0)]) {
+        features |= Features::LINE_RASTERIZATION_SMOOTH;
+    }
 
     // TODO
     if false && info.is_supported(&[Core(4, 3), Es(3, 1)]) {
@@ -487,6 +557,17 @@ pub(crate) fn query_all(
     }
     if info.is_supported(&[Core(3, 1), Es(3, 0), Ext("GL_ARB_uniform_buffer_object")]) {
         legacy |= LegacyFeatures::CONSTANT_BUFFER;
+        // Null descriptors are emulated by binding zeroed resources.
+        features |= Features::NULL_DESCRIPTOR;
+        limits.max_uniform_buffer_range =
+            get_u64(gl, glow::MAX_UNIFORM_BLOCK_SIZE).unwrap_or(16 << 10);
+    } else {
+        // Uniform buffers are emulated with arrays of `vec4` uniforms,
+        // which have to fit into both the vertex and the fragment stages.
+        let vectors = get_u64(gl, glow::MAX_VERTEX_UNIFORM_VECTORS)
+            .unwrap_or(128)
+            .min(get_u64(gl, glow::MAX_FRAGMENT_UNIFORM_VECTORS).unwrap_or(16));
+        limits.max_uniform_buffer_range = vectors * 16;
     }
     if info.is_supported(&[Core(4, 0)]) {
         // TODO: extension
@@ -506,6 +587,15 @@ pub(crate) fn query_all(
     if info.is_supported(&[Core(3, 3), Es(3, 0)]) {
         legacy |= LegacyFeatures::INSTANCED_ATTRIBUTE_BINDING;
     }
+    if info.is_supported(&[Core(3, 0), Es(3, 0)]) {
+        features |= Features::HOST_IMAGE_COPY;
+    }
+
+    let samples_passed_query = info.is_supported(&[Core(1, 5), Ext("GL_ARB_occlusion_query")]);
+    let occlusion_query = samples_passed_query || info.is_supported(&[Es(3, 0)]);
+    if samples_passed_query {
+        features |= Features::PRECISE_OCCLUSION_QUERY;
+    }
 
     let mut performance_caveats = PerformanceCaveats::empty();
     //TODO: extension
@@ -542,8 +632,33 @@ pub(crate) fn query_all(
         depth_range_f64_precision: !info.version.is_embedded, // TODO
         draw_buffers: info.is_supported(&[Core(2, 0), Es(3, 0)]),
         per_slot_color_mask: info.is_supported(&[Core(3, 0)]),
+        per_slot_blend_enable: info.is_supported(&[Core(3, 0), Es(3, 2)]) && !info.is_webgl(),
         get_tex_image: !info.version.is_embedded,
         memory_barrier: info.is_supported(&[Core(4, 2), Es(3, 1)]),
+        texture_barrier: info.is_supported(&[
+            Core(4, 5),
+            Ext("GL_ARB_texture_barrier"),
+            Ext("GL_NV_texture_barrier"),
+        ]),
+        color_buffer_float: info.is_supported(&[Core(3, 0), Ext("GL_EXT_color_buffer_float")]),
+        occlusion_query,
+        samples_passed_query,
+        texture_stencil8: info.is_supported(&[
+            Core(4, 4),
+            Es(3, 2),
+            Ext("GL_ARB_texture_stencil8"),
+            Ext("GL_OES_texture_stencil8"),
+        ]),
+        srgb_texture: info.is_supported(&[Core(2, 1), Es(3, 0), Ext("GL_EXT_sRGB")]),
+        framebuffer_srgb_control: info.is_supported(&[
+            Core(3, 2),
+            Ext("GL_ARB_framebuffer_sRGB"),
+            Ext("GL_EXT_sRGB_write_control"),
+        ]),
+        texture_buffer_range: !info.is_webgl()
+            && info.is_supported(&[Core(4, 3), Es(3, 2), Ext("GL_ARB_texture_buffer_range")]),
+        multi_bind: !info.is_webgl() && info.is_supported(&[Core(4, 4), Ext("GL_ARB_multi_bind")]),
+        egl_image: !info.is_webgl() && info.is_supported(&[Ext("GL_OES_EGL_image")]),
     };
 
     (info, features, legacy, limits, capabilities, private)
@@ -603,5 +718,13 @@ mod tests {
             Version::parse("OpenGL ES GLSL ES 3.20"),
             Ok(Version::new_embedded(3, 2, String::new()))
         );
+        assert_eq!(
+            Version::parse("WebGL 1.0 (OpenGL ES 2.0 Chromium)"),
+            Ok(Version::new_embedded(2, 0, String::new()))
+        );
+        assert_eq!(
+            Version::parse("WebGL 2.0 (OpenGL ES 3.0 Chromium)"),
+            Ok(Version::new_embedded(3, 0, String::new()))
+        );
     }
 }
@@ -12,12 +12,26 @@ Then a pipeline gets created, and we track all the texture-sampler associations.
 We only support at most one sampler used with each texture so far. The linear index
 of this sampler is stored per texture slot in `SamplerBindMap` array.
 
+Combined image samplers don't need any of the shader reflection: the pipeline layout
+gives each of them a sampler slot next to the texture slot, and every pipeline of that
+layout starts from this static pairing.
+
 The texture-sampler pairs get potentially invalidated in 2 places:
   - when a new pipeline is bound, we update the linear indices of associated samplers
   - when a new descriptor set is bound, we update both the textures and the samplers
 
 We expect that the changes to sampler states between any 2 pipelines of the same layout
 will be minimal, if any.
+
+## Uniform buffers
+
+OpenGL ES 2.0 and WebGL 1 have no uniform buffers, so they get emulated:
+  - the uniform blocks of the shaders are flattened into `vec4` arrays by SPIRV-Cross,
+    which requires the `cross` feature
+  - the memory usable for uniform buffers is only the CPU-visible one, and a copy
+    of the flushed contents is kept on the CPU side
+  - the arrays are uploaded from that copy before the draws that follow a change
+    of the pipeline or of the bound uniform buffers
 */
 
 #![allow(missing_docs, missing_copy_implementations)]
@@ -26,7 +40,7 @@ will be minimal, if any.
 extern crate log;
 
 use std::{
-    cell::Cell,
+    cell::{Cell, RefCell},
     collections::HashMap,
     fmt,
     hash::BuildHasherDefault,
@@ -43,6 +57,7 @@ pub use self::info::{Info, PlatformName, Version};
 mod command;
 mod conv;
 mod device;
+mod dma_buf;
 mod info;
 mod native;
 mod pool;
@@ -70,8 +85,26 @@ const MAX_SAMPLERS: usize = 16;
 const MAX_TEXTURE_SLOTS: usize = 16;
 const MAX_COLOR_ATTACHMENTS: usize = 16;
 
+/// `glTexBufferRange`, which isn't exposed by glow.
+type TexBufferRange = unsafe extern "system" fn(u32, u32, u32, isize, isize);
+/// `glBindBuffersRange`, which isn't exposed by glow.
+type BindBuffersRange =
+    unsafe extern "system" fn(u32, u32, i32, *const u32, *const isize, *const isize);
+/// `glTextureBarrier`, which isn't exposed by glow.
+type TextureBarrier = unsafe extern "system" fn();
+
 struct GlContainer {
     context: GlContext,
+    /// Entry point for buffer textures, when supported by the platform.
+    tex_buffer_range: Option<TexBufferRange>,
+    /// Entry point for binding consecutive buffer ranges at once, with GL 4.4
+    /// or `ARB_multi_bind`.
+    bind_buffers_range: Option<BindBuffersRange>,
+    /// Entry point for ordering texture fetches after framebuffer writes, with GL 4.5
+    /// or `ARB_texture_barrier`.
+    texture_barrier: Option<TextureBarrier>,
+    /// Entry points for importing dma-bufs, when supported by the platform.
+    dma_buf: Option<dma_buf::DmaBufImport>,
 }
 
 impl Deref for GlContainer {
@@ -107,6 +140,7 @@ impl hal::Backend for Backend {
     type Image = native::Image;
     type ImageView = native::ImageView;
     type Sampler = native::FatSampler;
+    type SamplerYcbcrConversion = ();
 
     type ComputePipeline = native::ComputePipeline;
     type GraphicsPipeline = native::GraphicsPipeline;
@@ -119,7 +153,7 @@ impl hal::Backend for Backend {
     type Fence = native::Fence;
     type Semaphore = native::Semaphore;
     type Event = ();
-    type QueryPool = ();
+    type QueryPool = native::QueryPool;
 }
 
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
@@ -212,9 +246,18 @@ struct Share {
     // Indicates if there is an active logical device.
     open: Cell<bool>,
     memory_types: Vec<(adapter::MemoryType, MemoryUsage)>,
+    /// CPU-side contents of the buffers backing emulated uniform buffers.
+    uniform_buffer_data: RefCell<FastHashMap<native::RawBuffer, Box<[u8]>>>,
 }
 
 impl Share {
+    /// Whether uniform buffers are emulated with plain uniforms.
+    fn emulate_uniform_buffers(&self) -> bool {
+        !self
+            .legacy_features
+            .contains(info::LegacyFeatures::CONSTANT_BUFFER)
+    }
+
     /// Fails during a debug build if the implementation's error flag was set.
     fn check(&self) -> Result<(), Error> {
         if cfg!(debug_assertions) {
@@ -354,11 +397,40 @@ unsafe impl<T: ?Sized> Sync for Wstarc<T> {}
 pub struct PhysicalDevice(Starc<Share>);
 
 impl PhysicalDevice {
-    fn new_adapter(context: GlContext) -> adapter::Adapter<Backend> {
-        let gl = GlContainer { context };
+    fn new_adapter(
+        context: GlContext,
+        tex_buffer_range: Option<TexBufferRange>,
+        bind_buffers_range: Option<BindBuffersRange>,
+        texture_barrier: Option<TextureBarrier>,
+        dma_buf: Option<dma_buf::DmaBufImport>,
+    ) -> adapter::Adapter<Backend> {
+        let mut gl = GlContainer {
+            context,
+            tex_buffer_range,
+            bind_buffers_range,
+            texture_barrier,
+            dma_buf,
+        };
         // query information
-        let (info, supported_features, legacy_features, limits, public_caps, private_caps) =
+        let (info, mut supported_features, legacy_features, limits, public_caps, private_caps) =
             info::query_all(&gl);
+        if !private_caps.texture_buffer_range {
+            gl.tex_buffer_range = None;
+        }
+        if !private_caps.multi_bind {
+            gl.bind_buffers_range = None;
+        }
+        if !private_caps.texture_barrier {
+            gl.texture_barrier = None;
+        }
+        if !private_caps.egl_image {
+            gl.dma_buf = None;
+        }
+        // dma-bufs can only be imported, with an explicit modifier
+        if gl.dma_buf.is_some() {
+            supported_features |=
+                hal::Features::DRM_FORMAT_MODIFIER | hal::Features::EXTERNAL_MEMORY_DMA_BUF;
+        }
         info!("Vendor: {:?}", info.platform_name.vendor);
         info!("Renderer: {:?}", info.platform_name.renderer);
         info!("Version: {:?}", info.version);
@@ -377,10 +449,10 @@ impl PhysicalDevice {
 
         let mut memory_types = Vec::new();
 
-        let mut add_buffer_memory_type = |memory_type: adapter::MemoryType| {
+        let mut add_buffer_memory_type = |memory_type: adapter::MemoryType, usage| {
             if private_caps.index_buffer_role_change {
                 // If `index_buffer_role_change` is true, we can use a buffer for any role
-                memory_types.push((memory_type, MemoryUsage::Buffer(buffer::Usage::all())));
+                memory_types.push((memory_type, MemoryUsage::Buffer(usage)));
             } else {
                 // If `index_buffer_role_change` is false, ELEMENT_ARRAY_BUFFER buffers may not be
                 // mixed with other targets, so we need to provide one type of memory for INDEX
@@ -388,7 +460,7 @@ impl PhysicalDevice {
                 memory_types.push((memory_type, MemoryUsage::Buffer(buffer::Usage::INDEX)));
                 memory_types.push((
                     memory_type,
-                    MemoryUsage::Buffer(buffer::Usage::all() - buffer::Usage::INDEX),
+                    MemoryUsage::Buffer(usage - buffer::Usage::INDEX),
                 ));
             }
         };
@@ -400,21 +472,37 @@ impl PhysicalDevice {
             memory::Properties::empty()
         };
         // Mimicking vulkan, memory types with more flags should come before those with fewer flags
-        add_buffer_memory_type(adapter::MemoryType {
-            properties: coherent_flag
-                | memory::Properties::CPU_VISIBLE
-                | memory::Properties::CPU_CACHED,
-            heap_index: CPU_VISIBLE_HEAP,
-        });
-        add_buffer_memory_type(adapter::MemoryType {
-            properties: coherent_flag | memory::Properties::CPU_VISIBLE,
-            heap_index: CPU_VISIBLE_HEAP,
-        });
-
-        add_buffer_memory_type(adapter::MemoryType {
-            properties: memory::Properties::DEVICE_LOCAL,
-            heap_index: DEVICE_LOCAL_HEAP,
-        });
+        add_buffer_memory_type(
+            adapter::MemoryType {
+                properties: coherent_flag
+                    | memory::Properties::CPU_VISIBLE
+                    | memory::Properties::CPU_CACHED,
+                heap_index: CPU_VISIBLE_HEAP,
+            },
+            buffer::Usage::all(),
+        );
+        add_buffer_memory_type(
+            adapter::MemoryType {
+                properties: coherent_flag | memory::Properties::CPU_VISIBLE,
+                heap_index: CPU_VISIBLE_HEAP,
+            },
+            buffer::Usage::all(),
+        );
+
+        // Emulated uniform buffers are read back from the CPU-visible memory
+        let device_local_usage = if legacy_features.contains(info::LegacyFeatures::CONSTANT_BUFFER)
+        {
+            buffer::Usage::all()
+        } else {
+            buffer::Usage::all() - buffer::Usage::UNIFORM
+        };
+        add_buffer_memory_type(
+            adapter::MemoryType {
+                properties: memory::Properties::DEVICE_LOCAL,
+                heap_index: DEVICE_LOCAL_HEAP,
+            },
+            device_local_usage,
+        );
 
         // There is always a single device-local memory type for images
         memory_types.push((
@@ -440,6 +528,7 @@ impl PhysicalDevice {
             private_caps,
             open: Cell::new(false),
             memory_types,
+            uniform_buffer_data: RefCell::new(FastHashMap::default()),
         };
         if let Err(err) = share.check() {
             panic!("Error querying info: {:?}", err);
@@ -569,12 +658,18 @@ impl adapter::PhysicalDevice<Backend> for PhysicalDevice {
             gl.bind_vertex_array(vao);
         }
 
+        let null_resources = if requested_features.contains(hal::Features::NULL_DESCRIPTOR) {
+            Some(device::NullResources::new(gl))
+        } else {
+            None
+        };
+
         if let Err(err) = self.0.check() {
             panic!("Error opening adapter: {:?}", err);
         }
 
         Ok(adapter::Gpu {
-            device: Device::new(self.0.clone(), requested_features),
+            device: Device::new(self.0.clone(), requested_features, null_resources),
             queue_groups: families
                 .iter()
                 .map(|&(_family, priorities)| {
@@ -588,14 +683,67 @@ impl adapter::PhysicalDevice<Backend> for PhysicalDevice {
         })
     }
 
-    fn format_properties(&self, _: Option<hal::format::Format>) -> hal::format::Properties {
-        use hal::format::{BufferFeature as Bf, ImageFeature as If};
+    fn format_properties(&self, format: Option<hal::format::Format>) -> hal::format::Properties {
+        use hal::format::{BufferFeature as Bf, Format as F, ImageFeature as If};
 
         // TODO: These are for show
+        let mut optimal_tiling = If::TRANSFER_SRC | If::TRANSFER_DST | If::SAMPLED;
+        match format {
+            Some(format) if conv::describe_format(format).is_none() => {
+                return hal::format::Properties::default();
+            }
+            Some(format)
+                if format.base_format().1 == hal::format::ChannelType::Srgb
+                    && !self.0.private_caps.srgb_texture =>
+            {
+                return hal::format::Properties::default();
+            }
+            Some(F::B10g11r11Ufloat) => {
+                optimal_tiling |= If::SAMPLED_LINEAR;
+                if self.0.private_caps.color_buffer_float {
+                    optimal_tiling |= If::COLOR_ATTACHMENT | If::COLOR_ATTACHMENT_BLEND;
+                }
+            }
+            // Shared exponent formats are never color-renderable
+            Some(F::E5b9g9r9Ufloat) => {
+                optimal_tiling |= If::SAMPLED_LINEAR;
+            }
+            // The copies would expose the layout of the wider backing format.
+            Some(format) if conv::is_emulated_format(format) => {
+                return hal::format::Properties {
+                    linear_tiling: If::empty(),
+                    optimal_tiling: If::SAMPLED | If::DEPTH_STENCIL_ATTACHMENT,
+                    buffer_features: Bf::empty(),
+                };
+            }
+            // Stencil-only textures are not always available, unlike the renderbuffers
+            Some(F::S8Uint) => {
+                optimal_tiling |= If::DEPTH_STENCIL_ATTACHMENT;
+                if !self.0.private_caps.texture_stencil8 {
+                    optimal_tiling -= If::SAMPLED;
+                }
+            }
+            Some(format) if format.is_depth() => {
+                optimal_tiling |= If::DEPTH_STENCIL_ATTACHMENT;
+            }
+            _ => {}
+        }
+
+        let mut buffer_features = Bf::VERTEX;
+        match format {
+            Some(format)
+                if self.0.context.tex_buffer_range.is_some()
+                    && conv::is_texel_buffer_format(format) =>
+            {
+                buffer_features |= Bf::UNIFORM_TEXEL;
+            }
+            _ => {}
+        }
+
         hal::format::Properties {
             linear_tiling: If::TRANSFER_SRC | If::TRANSFER_DST | If::empty(),
-            optimal_tiling: If::TRANSFER_SRC | If::TRANSFER_DST | If::SAMPLED,
-            buffer_features: Bf::VERTEX,
+            optimal_tiling,
+            buffer_features,
         }
     }
 
@@ -604,10 +752,15 @@ impl adapter::PhysicalDevice<Backend> for PhysicalDevice {
         format: hal::format::Format,
         _dimensions: u8,
         _tiling: image::Tiling,
-        _usage: image::Usage,
+        usage: image::Usage,
         _view_caps: image::ViewCapabilities,
     ) -> Option<image::FormatProperties> {
         conv::describe_format(format)?;
+        if format.base_format().1 == hal::format::ChannelType::Srgb
+            && !self.0.private_caps.srgb_texture
+        {
+            return None;
+        }
         Some(image::FormatProperties {
             max_extent: image::Extent {
                 width: !0,
@@ -616,11 +769,44 @@ impl adapter::PhysicalDevice<Backend> for PhysicalDevice {
             },
             max_levels: !0,
             max_layers: !0,
-            sample_count_mask: 127,
+            // Only the renderbuffers can be multisampled, not the textures
+            sample_count_mask: if usage.intersects(
+                image::Usage::STORAGE | image::Usage::SAMPLED | image::Usage::INPUT_ATTACHMENT,
+            ) {
+                0x1
+            } else {
+                127
+            },
             max_resource_size: !0,
         })
     }
 
+    fn drm_modifier_properties(
+        &self,
+        format: hal::format::Format,
+    ) -> Vec<image::DrmModifierProperties> {
+        use hal::format::ImageFeature as If;
+
+        let (import, fourcc) = match (self.0.context.dma_buf, dma_buf::map_format(format)) {
+            (Some(import), Some(fourcc)) => (import, fourcc),
+            _ => return Vec::new(),
+        };
+        let features = self.format_properties(Some(format)).optimal_tiling
+            & (If::SAMPLED
+                | If::SAMPLED_LINEAR
+                | If::COLOR_ATTACHMENT
+                | If::COLOR_ATTACHMENT_BLEND);
+        unsafe { import.modifiers(fourcc) }
+            .into_iter()
+            .map(|modifier| image::DrmModifierProperties {
+                modifier,
+                // The number of planes isn't exposed by EGL, only single-plane formats are mapped.
+                plane_count: 1,
+                features,
+            })
+            .collect()
+    }
+
     fn memory_properties(&self) -> adapter::MemoryProperties {
         adapter::MemoryProperties {
             memory_types: self
@@ -1,4 +1,4 @@
-use crate::{Backend, GlContext, MAX_TEXTURE_SLOTS};
+use crate::{dma_buf, Backend, GlContext, MAX_TEXTURE_SLOTS};
 
 use hal::{
     buffer, format, image as i,
@@ -6,7 +6,7 @@ use hal::{
     pass, pso, window as w,
 };
 
-use std::{borrow::Borrow, fmt, ops::Range, sync::Arc};
+use std::{borrow::Borrow, fmt, fs, ops::Range, sync::Arc};
 
 pub type TextureTarget = u32;
 pub type TextureFormat = u32;
@@ -21,10 +21,32 @@ pub type Renderbuffer = <GlContext as glow::HasContext>::Renderbuffer;
 pub type RawFramebuffer = <GlContext as glow::HasContext>::Framebuffer;
 pub type Texture = <GlContext as glow::HasContext>::Texture;
 pub type Sampler = <GlContext as glow::HasContext>::Sampler;
+pub type Query = <GlContext as glow::HasContext>::Query;
 // TODO: UniformLocation was copy in glow 0.3, but in 0.4 it isn't. Wrap it in a Starc for now
 // to make it `Sync + Send` instead.
 pub type UniformLocation = crate::Starc<<GlContext as glow::HasContext>::UniformLocation>;
-pub type DescriptorSetLayout = Arc<Vec<pso::DescriptorSetLayoutBinding>>;
+pub type DescriptorSetLayout = Arc<DescriptorSetLayoutData>;
+
+#[derive(Debug)]
+pub struct DescriptorSetLayoutData {
+    /// Bindings of the layout, sorted by the binding index.
+    pub(crate) bindings: Vec<pso::DescriptorSetLayoutBinding>,
+    /// Immutable samplers, one per array element of the bindings that have them.
+    pub(crate) immutable_samplers: Vec<(pso::DescriptorBinding, Vec<FatSampler>)>,
+}
+
+impl DescriptorSetLayoutData {
+    pub(crate) fn immutable_sampler(
+        &self,
+        binding: pso::DescriptorBinding,
+        array_index: pso::DescriptorArrayIndex,
+    ) -> Option<&FatSampler> {
+        self.immutable_samplers
+            .iter()
+            .find(|&&(b, _)| b == binding)
+            .map(|(_, samplers)| &samplers[array_index])
+    }
+}
 
 #[derive(Clone, Debug)]
 pub struct Framebuffer {
@@ -54,7 +76,10 @@ impl Buffer {
 }
 
 #[derive(Debug)]
-pub struct BufferView;
+pub struct BufferView {
+    /// Buffer texture sourcing its texels from the buffer range.
+    pub(crate) raw: Texture,
+}
 
 #[derive(Debug)]
 pub enum Fence {
@@ -65,6 +90,16 @@ pub enum Fence {
 unsafe impl Send for Fence {}
 unsafe impl Sync for Fence {}
 
+#[derive(Debug)]
+pub struct QueryPool {
+    /// Target of the queries, e.g. `SAMPLES_PASSED` for occlusion.
+    pub(crate) target: u32,
+    pub(crate) queries: Vec<Query>,
+}
+
+unsafe impl Send for QueryPool {}
+unsafe impl Sync for QueryPool {}
+
 #[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
 pub enum BindingRegister {
     Textures,
@@ -85,16 +120,20 @@ pub struct GraphicsPipeline {
     pub(crate) attributes: Vec<AttributeDesc>,
     pub(crate) vertex_buffers: Vec<Option<pso::VertexBufferDesc>>,
     pub(crate) uniforms: Vec<UniformDesc>,
+    pub(crate) uniform_blocks: Vec<UniformBlockDesc>,
     pub(crate) rasterizer: pso::Rasterizer,
+    pub(crate) multisampling: Option<pso::Multisampling>,
     pub(crate) depth: Option<pso::DepthTest>,
     pub(crate) baked_states: pso::BakedStates,
     pub(crate) sampler_map: SamplerBindMap,
+    pub(crate) immutable_samplers: Vec<(u8, FatSampler)>,
 }
 
 #[derive(Clone, Debug)]
 pub struct ComputePipeline {
     pub(crate) program: Program,
     pub(crate) sampler_map: SamplerBindMap,
+    pub(crate) immutable_samplers: Vec<(u8, FatSampler)>,
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -107,6 +146,23 @@ pub struct Image {
     pub(crate) requirements: Requirements,
     pub(crate) num_levels: i::Level,
     pub(crate) num_layers: i::Layer,
+    /// Layout of the dma-buf to import when the memory is bound.
+    pub(crate) drm: Option<DrmLayout>,
+}
+
+/// Layout of an image imported from a dma-buf.
+#[derive(Copy, Clone, Debug)]
+pub struct DrmLayout {
+    pub(crate) fourcc: u32,
+    pub(crate) modifier: i::DrmModifier,
+    pub(crate) planes: [i::DrmPlaneLayout; dma_buf::MAX_PLANES],
+    pub(crate) plane_count: usize,
+}
+
+impl DrmLayout {
+    pub(crate) fn planes(&self) -> &[i::DrmPlaneLayout] {
+        &self.planes[..self.plane_count]
+    }
 }
 
 impl Image {
@@ -216,9 +272,12 @@ impl SwapchainImage {
                     size: 0,
                     alignment: 1,
                     type_mask: 0,
+                    prefers_dedicated: false,
+                    requires_dedicated: false,
                 },
                 num_levels: 1,
                 num_layers: 1,
+                drm: None,
             },
             view: ImageView::Renderbuffer {
                 raw: renderbuffer,
@@ -239,6 +298,8 @@ pub(crate) enum DescSetBindings {
     Texture(Texture, TextureTarget),
     Sampler(Sampler),
     SamplerDesc(i::SamplerDesc),
+    /// Texture with its sampler, paired statically by the pipeline layout.
+    CombinedImageSampler(Texture, TextureTarget, FatSampler),
 }
 
 #[derive(Clone, Debug)]
@@ -299,6 +360,8 @@ pub struct Memory {
     pub(crate) size: u64,
     pub(crate) map_flags: u32,
     pub(crate) emulate_map_allocation: Option<*mut u8>,
+    /// Imported dma-buf, closed when the memory is freed.
+    pub(crate) dma_buf: Option<fs::File>,
 }
 
 unsafe impl Send for Memory {}
@@ -314,6 +377,7 @@ pub struct RenderPass {
 pub struct SubpassDesc {
     pub(crate) color_attachments: Vec<usize>,
     pub(crate) depth_stencil: Option<usize>,
+    pub(crate) input_attachments: Vec<usize>,
 }
 
 impl SubpassDesc {
@@ -328,6 +392,23 @@ impl SubpassDesc {
                 .map(|p| glow::COLOR_ATTACHMENT0 + p as u32)
         }
     }
+
+    /// Check if an attachment is written or read by this sub-pass.
+    pub(crate) fn is_using(&self, at_id: pass::AttachmentId) -> bool {
+        self.depth_stencil == Some(at_id)
+            || self.color_attachments.contains(&at_id)
+            || self.input_attachments.contains(&at_id)
+    }
+}
+
+impl RenderPass {
+    /// Index of the first sub-pass using an attachment, which performs its load operation.
+    pub(crate) fn first_subpass_using(&self, at_id: pass::AttachmentId) -> pass::SubpassId {
+        self.subpasses
+            .iter()
+            .position(|subpass| subpass.is_using(at_id))
+            .unwrap_or(0) as pass::SubpassId
+    }
 }
 
 #[derive(Debug)]
@@ -345,6 +426,12 @@ pub(crate) struct PipelineLayoutSet {
 pub struct PipelineLayout {
     /// Resource mapping for descriptor sets.
     pub(crate) sets: Vec<PipelineLayoutSet>,
+    /// Samplers of the combined image-sampler bindings, indexed by their texture slots.
+    /// Every pipeline created with this layout starts from this map.
+    pub(crate) sampler_map: SamplerBindMap,
+    /// Immutable samplers of all the sets, keyed by the sampler index in the whole layout.
+    /// They are bound together with the pipelines of this layout.
+    pub(crate) immutable_samplers: Vec<(u8, FatSampler)>,
 }
 
 #[derive(Debug)]
@@ -368,6 +455,18 @@ pub struct UniformDesc {
     pub(crate) utype: u32,
 }
 
+/// Uniform block emulated with an array of `vec4` uniforms.
+#[derive(Clone, Debug)]
+pub struct UniformBlockDesc {
+    pub(crate) location: UniformLocation,
+    /// Binding slot of the uniform buffer.
+    pub(crate) slot: u8,
+    /// Number of `vec4` elements in the array.
+    pub(crate) count: u32,
+    /// Type of the elements, either `FLOAT_VEC4` or `INT_VEC4`.
+    pub(crate) utype: u32,
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum VertexAttribFunction {
     Float,   // glVertexAttribPointer
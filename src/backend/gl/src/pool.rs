@@ -20,9 +20,13 @@ impl OwnedBuffer {
         }
     }
 
-    fn clear(&mut self) {
-        self.commands.clear();
-        self.data.clear();
+    pub(crate) fn reset(&mut self, release_resources: bool) {
+        if release_resources {
+            *self = OwnedBuffer::new();
+        } else {
+            self.commands.clear();
+            self.data.clear();
+        }
     }
 }
 
@@ -62,7 +66,7 @@ pub struct CommandPool {
 }
 
 impl hal::pool::CommandPool<Backend> for CommandPool {
-    unsafe fn reset(&mut self, _release_resources: bool) {
+    unsafe fn reset(&mut self, release_resources: bool) {
         let mut memory = self
             .memory
             .try_lock()
@@ -70,13 +74,13 @@ impl hal::pool::CommandPool<Backend> for CommandPool {
 
         match *memory {
             BufferMemory::Linear(ref mut buffer) => {
-                buffer.clear();
+                buffer.reset(release_resources);
             }
             BufferMemory::Individual {
                 ref mut storage, ..
             } => {
                 for (_, ref mut buffer) in storage {
-                    buffer.clear();
+                    buffer.reset(release_resources);
                 }
             }
         }
@@ -1,6 +1,6 @@
 use crate::{
-    command as com, device, info::LegacyFeatures, native, state, Backend, Device, GlContext, Share,
-    Starc, Surface, MAX_COLOR_ATTACHMENTS,
+    command as com, device, info::LegacyFeatures, native, state, Backend, Device, GlContainer,
+    GlContext, Share, Starc, Surface, MAX_COLOR_ATTACHMENTS,
 };
 
 use arrayvec::ArrayVec;
@@ -23,6 +23,8 @@ struct State {
     // Currently bound index/element buffer.
     // None denotes that we don't know what is currently bound.
     index_buffer: Option<native::RawBuffer>,
+    // Mask of the enabled vertex attribute arrays.
+    attributes: u64,
     // Currently set viewports.
     num_viewports: usize,
     // Currently set scissor rects.
@@ -36,6 +38,7 @@ impl State {
         State {
             vao: false,
             index_buffer: None,
+            attributes: 0,
             num_viewports: 0,
             num_scissors: 0,
         }
@@ -64,6 +67,43 @@ pub struct Queue {
 
 const FILL_DATA_WORDS: usize = 16 << 10;
 
+/// Bind the ranges to the consecutive indices with a single call, if `glBindBuffersRange`
+/// is available. Returns `false` if they have to be bound one by one.
+#[cfg(not(target_arch = "wasm32"))]
+unsafe fn bind_buffers_range(
+    gl: &GlContainer,
+    target: u32,
+    first: u32,
+    ranges: &[(native::RawBuffer, i32, i32)],
+) -> bool {
+    let bind_buffers_range = match gl.bind_buffers_range {
+        Some(fun) => fun,
+        None => return false,
+    };
+    let buffers = ranges.iter().map(|r| r.0).collect::<Vec<_>>();
+    let offsets = ranges.iter().map(|r| r.1 as isize).collect::<Vec<_>>();
+    let sizes = ranges.iter().map(|r| r.2 as isize).collect::<Vec<_>>();
+    bind_buffers_range(
+        target,
+        first,
+        ranges.len() as i32,
+        buffers.as_ptr(),
+        offsets.as_ptr(),
+        sizes.as_ptr(),
+    );
+    true
+}
+
+#[cfg(target_arch = "wasm32")]
+unsafe fn bind_buffers_range(
+    _gl: &GlContainer,
+    _target: u32,
+    _first: u32,
+    _ranges: &[(native::RawBuffer, i32, i32)],
+) -> bool {
+    false
+}
+
 impl Queue {
     /// Create a new command queue.
     pub(crate) fn new(
@@ -73,14 +113,15 @@ impl Queue {
     ) -> Self {
         let gl = &share.context;
         let fill_buffer = unsafe {
+            // `COPY_READ_BUFFER` is not available in OpenGL ES 2.0
             let buffer = gl.create_buffer().unwrap();
-            gl.bind_buffer(glow::COPY_READ_BUFFER, Some(buffer));
+            gl.bind_buffer(glow::ARRAY_BUFFER, Some(buffer));
             gl.buffer_data_size(
-                glow::COPY_READ_BUFFER,
+                glow::ARRAY_BUFFER,
                 FILL_DATA_WORDS as i32 * 4,
                 glow::STREAM_DRAW,
             );
-            gl.bind_buffer(glow::COPY_READ_BUFFER, None);
+            gl.bind_buffer(glow::ARRAY_BUFFER, None);
             buffer
         };
         Queue {
@@ -117,7 +158,7 @@ impl Queue {
                 (glow::UNSIGNED_BYTE, glow::UNSIGNED_SHORT, glow::UNSIGNED_INT),
             C::Float => (glow::ZERO, glow::HALF_FLOAT, glow::FLOAT),
             C::Srgb => {
-                error!("Unsupported Srgb channel type");
+                auxil::report!(Error, "gfx::gl::queue", "srgb-channel-unsupported", "Unsupported Srgb channel type");
                 return
             }
         };
@@ -134,7 +175,7 @@ impl Queue {
             S::R32_G32_B32     => (3, fm32),
             S::R32_G32_B32_A32 => (4, fm32),
             _ => {
-                error!("Unsupported element type: {:?}", bel.elem.format.0);
+                auxil::report!(Error, "gfx::gl::queue", "element-type-unsupported", "Unsupported element type: {:?}", bel.elem.format.0);
                 return
             }
         };
@@ -166,7 +207,7 @@ impl Queue {
             unsafe { gl.VertexAttribDivisor(slot as glow::types::GLuint,
                 bel.desc.rate as glow::types::GLuint) };
         } else if bel.desc.rate != 0 {
-            error!("Instanced arrays are not supported");
+            auxil::report!(Error, "gfx::gl::queue", "instanced-arrays-unsupported", "Instanced arrays are not supported");
         }
     }
     */
@@ -208,6 +249,13 @@ impl Queue {
             self.state.vao = true
         }
 
+        // Disable the attribute arrays enabled by the previous command buffers
+        while self.state.attributes != 0 {
+            let location = self.state.attributes.trailing_zeros();
+            unsafe { gl.disable_vertex_attrib_array(location) };
+            self.state.attributes &= !(1 << location);
+        }
+
         // Reset indirect draw buffer
         if self
             .share
@@ -274,12 +322,20 @@ impl Queue {
                             );
                         }
                     } else {
-                        error!(
+                        auxil::report!(
+                            Error,
+                            "gfx::gl::queue",
+                            "base-instance-unsupported",
                             "Instanced draw calls with non-zero base instance are not supported"
                         );
                     }
                 } else {
-                    error!("Instanced draw calls are not supported");
+                    auxil::report!(
+                        Error,
+                        "gfx::gl::queue",
+                        "instanced-draw-unsupported",
+                        "Instanced draw calls are not supported"
+                    );
                 }
             }
             com::Command::DrawIndexed {
@@ -315,7 +371,12 @@ impl Queue {
                             );
                         }
                     } else {
-                        error!("Base vertex with indexed drawing not supported");
+                        auxil::report!(
+                            Error,
+                            "gfx::gl::queue",
+                            "base-vertex-unsupported",
+                            "Base vertex with indexed drawing not supported"
+                        );
                     }
                 } else if legacy.contains(LegacyFeatures::DRAW_INDEXED_INSTANCED) {
                     if base_vertex == 0 && instances.start == 0 {
@@ -342,12 +403,22 @@ impl Queue {
                             );
                         }
                     } else if instances.start == 0 {
-                        error!("Base vertex with instanced indexed drawing is not supported");
+                        auxil::report!(
+                            Error,
+                            "gfx::gl::queue",
+                            "base-vertex-unsupported",
+                            "Base vertex with instanced indexed drawing is not supported"
+                        );
                     } else if caveats
                         .contains(hal::PerformanceCaveats::BASE_VERTEX_INSTANCE_DRAWING)
                     {
                         //TODO: this is supposed to be a workaround, not an error
-                        error!("Instance bases with instanced indexed drawing is not supported");
+                        auxil::report!(
+                            Error,
+                            "gfx::gl::queue",
+                            "base-instance-unsupported",
+                            "Instance bases with instanced indexed drawing is not supported"
+                        );
                     } else {
                         unsafe {
                             gl.draw_elements_instanced_base_vertex_base_instance(
@@ -362,7 +433,12 @@ impl Queue {
                         }
                     }
                 } else {
-                    error!("Instanced indexed drawing is not supported");
+                    auxil::report!(
+                        Error,
+                        "gfx::gl::queue",
+                        "instanced-draw-unsupported",
+                        "Instanced indexed drawing is not supported"
+                    );
                 }
             }
             com::Command::Dispatch(count) => {
@@ -444,6 +520,11 @@ impl Queue {
             com::Command::SetBlendColor(color) => {
                 state::set_blend_color(&self.share.context, color);
             }
+            com::Command::SetDepthBias(bias) => unsafe {
+                self.share
+                    .context
+                    .polygon_offset(bias.slope_factor, bias.const_factor);
+            },
             com::Command::ClearBufferColorF(draw_buffer, mut cv) => unsafe {
                 self.share
                     .context
@@ -476,6 +557,16 @@ impl Queue {
                     _ => unreachable!(),
                 };
             },
+            com::Command::SetClearRect(rect) => unsafe {
+                let gl = &self.share.context;
+                match rect {
+                    Some(rect) => {
+                        gl.enable(glow::SCISSOR_TEST);
+                        gl.scissor(rect[0], rect[1], rect[2], rect[3]);
+                    }
+                    None => gl.disable(glow::SCISSOR_TEST),
+                }
+            },
             com::Command::ClearTexture(_color) => unimplemented!(),
             com::Command::BindFramebuffer {
                 target,
@@ -500,6 +591,12 @@ impl Queue {
                     self.bind_target(target, attachment, view);
                 }
             }
+            com::Command::BeginQuery(query, target) => unsafe {
+                self.share.context.begin_query(target, query);
+            },
+            com::Command::EndQuery(target) => unsafe {
+                self.share.context.end_query(target);
+            },
             com::Command::FillBuffer(buffer, ref range, value) => {
                 //Note: buffers with `DYNAMIC_STORAGE_BIT` can't be uploaded to directly.
                 // And we expect the target buffers to be on GPU, where we assign this flag.
@@ -557,9 +654,20 @@ impl Queue {
             }
             com::Command::SetBlendSlot(slot, ref blend) => {
                 if self.share.private_caps.draw_buffers {
-                    state::set_blend_slot(&self.share.context, slot, blend, &self.features);
+                    state::set_blend_slot(
+                        &self.share.context,
+                        slot,
+                        blend,
+                        &self.features,
+                        &self.share.private_caps,
+                    );
                 } else {
-                    warn!("Draw buffers are not supported");
+                    auxil::report!(
+                        Warn,
+                        "gfx::gl::queue",
+                        "draw-buffers-unsupported",
+                        "Draw buffers are not supported"
+                    );
                 }
             }
             com::Command::BindAttribute(ref attribute, handle, stride, rate) => unsafe {
@@ -601,16 +709,22 @@ impl Queue {
                 {
                     gl.vertex_attrib_divisor(location, rate);
                 } else if rate > 0 {
-                    error!("Binding attribute with instanced input rate is not supported");
+                    auxil::report!(
+                        Error,
+                        "gfx::gl::queue",
+                        "instanced-attribute-unsupported",
+                        "Binding attribute with instanced input rate is not supported"
+                    );
                 }
 
                 gl.enable_vertex_attrib_array(location);
                 gl.bind_buffer(glow::ARRAY_BUFFER, None);
+                self.state.attributes |= 1 << location;
+            },
+            com::Command::UnbindAttribute(location) => unsafe {
+                self.share.context.disable_vertex_attrib_array(location);
+                self.state.attributes &= !(1 << location);
             },
-            /*
-            com::Command::UnbindAttribute(ref attribute) => unsafe {
-                self.share.context.DisableVertexAttribArray(attribute.location);
-            }*/
             com::Command::CopyBufferToBuffer(src, dst, ref r) => unsafe {
                 let gl = &self.share.context;
                 gl.bind_buffer(glow::COPY_READ_BUFFER, Some(src));
@@ -679,7 +793,12 @@ impl Queue {
                 gl.bind_buffer(glow::PIXEL_UNPACK_BUFFER, None);
             },
             com::Command::CopyBufferToRenderbuffer(..) => {
-                error!("CopyBufferToRenderbuffer is not implemented");
+                auxil::report!(
+                    Error,
+                    "gfx::gl::queue",
+                    "copy-buffer-to-renderbuffer-unimplemented",
+                    "CopyBufferToRenderbuffer is not implemented"
+                );
             }
             com::Command::CopyTextureToBuffer {
                 src_texture,
@@ -714,16 +833,31 @@ impl Queue {
                     }
                 } else {
                     //TODO: use FBO
-                    error!("CopyTextureToBuffer is not implemented on GLES");
+                    auxil::report!(
+                        Error,
+                        "gfx::gl::queue",
+                        "copy-texture-to-buffer-unimplemented",
+                        "CopyTextureToBuffer is not implemented on GLES"
+                    );
                 }
             }
             com::Command::CopyRenderbufferToBuffer(..) => {
                 //TODO: use FBO
-                error!("CopyRenderbufferToBuffer is not implemented");
+                auxil::report!(
+                    Error,
+                    "gfx::gl::queue",
+                    "copy-renderbuffer-to-buffer-unimplemented",
+                    "CopyRenderbufferToBuffer is not implemented"
+                );
             }
             com::Command::CopyImageToTexture(..) => {
                 //TODO: use FBO
-                error!("CopyImageToTexture is not implemented");
+                auxil::report!(
+                    Error,
+                    "gfx::gl::queue",
+                    "copy-image-to-texture-unimplemented",
+                    "CopyImageToTexture is not implemented"
+                );
             }
             com::Command::CopyImageToRenderbuffer {
                 src_image,
@@ -793,6 +927,15 @@ impl Queue {
                 let gl = &self.share.context;
                 gl.bind_buffer_range(target, index, Some(buffer), offset, size);
             },
+            com::Command::BindBufferRanges(target, first, data_ptr) => unsafe {
+                let gl = &self.share.context;
+                let ranges = Self::get::<(native::RawBuffer, i32, i32)>(data_buf, data_ptr);
+                if !bind_buffers_range(gl, target, first, ranges) {
+                    for (i, &(buffer, offset, size)) in ranges.iter().enumerate() {
+                        gl.bind_buffer_range(target, first + i as u32, Some(buffer), offset, size);
+                    }
+                }
+            },
             com::Command::BindTexture(index, texture, textype) => unsafe {
                 let gl = &self.share.context;
                 gl.active_texture(glow::TEXTURE0 + index);
@@ -832,7 +975,7 @@ impl Queue {
             if let Some(bind) = bind_opt {
             tex::bind_sampler(gl, bind, &sampler.info, &self.share.private_caps);
             }else {
-            error!("Trying to bind a sampler to slot {}, when sampler objects are not supported, and no texture is bound there", slot);
+            auxil::report!(Error, "gfx::gl::queue", "missing-sampler-texture", "Trying to bind a sampler to slot {}, when sampler objects are not supported, and no texture is bound there", slot);
             }
             }
             },
@@ -933,7 +1076,54 @@ impl Queue {
                     }
                 }
             }
-            com::Command::BindRasterizer { rasterizer } => {
+            com::Command::SetUniformBlock {
+                ref block,
+                buffer,
+                offset,
+                size,
+            } => {
+                let gl = &self.share.context;
+                let buffer_data = self.share.uniform_buffer_data.borrow();
+                let data = match buffer_data.get(&buffer) {
+                    Some(data) => data,
+                    None => {
+                        auxil::report!(
+                            Error,
+                            "gfx::gl::queue",
+                            "uniform-buffer-not-host-visible",
+                            "Uniform buffer memory is not CPU-visible"
+                        );
+                        return;
+                    }
+                };
+                let size = (size as usize).min(block.count as usize * 16) & !15;
+                let bytes = &data[offset as usize..offset as usize + size];
+                let location = Some(&*block.location);
+
+                unsafe {
+                    match block.utype {
+                        glow::FLOAT_VEC4 => {
+                            let values = bytes
+                                .chunks_exact(4)
+                                .map(|c| f32::from_ne_bytes([c[0], c[1], c[2], c[3]]))
+                                .collect::<Vec<_>>();
+                            gl.uniform_4_f32_slice(location, &values);
+                        }
+                        glow::INT_VEC4 => {
+                            let values = bytes
+                                .chunks_exact(4)
+                                .map(|c| i32::from_ne_bytes([c[0], c[1], c[2], c[3]]))
+                                .collect::<Vec<_>>();
+                            gl.uniform_4_i32_slice(location, &values);
+                        }
+                        _ => panic!("Unsupported uniform block datatype!"),
+                    }
+                }
+            }
+            com::Command::BindRasterizer {
+                rasterizer,
+                ref multisampling,
+            } => {
                 use hal::pso::FrontFace::*;
                 use hal::pso::PolygonMode::*;
 
@@ -971,6 +1161,21 @@ impl Queue {
                     unsafe { gl.line_width(w) };
                 }
 
+                if self
+                    .features
+                    .contains(hal::Features::LINE_RASTERIZATION_SMOOTH)
+                {
+                    unsafe {
+                        if rasterizer.line_rasterization.mode
+                            == hal::pso::LineRasterizationMode::Smooth
+                        {
+                            gl.enable(glow::LINE_SMOOTH);
+                        } else {
+                            gl.disable(glow::LINE_SMOOTH);
+                        }
+                    }
+                }
+
                 //TODO: this is not available in GLES
                 //unsafe { gl.polygon_mode(glow::FRONT_AND_BACK, gl_draw) };
 
@@ -979,14 +1184,33 @@ impl Queue {
                         gl.enable(gl_offset);
                         gl.polygon_offset(bias.slope_factor as _, bias.const_factor as _);
                     },
-                    _ => unsafe { gl.disable(gl_offset) },
+                    Some(hal::pso::State::Dynamic) => unsafe { gl.enable(gl_offset) },
+                    None => unsafe { gl.disable(gl_offset) },
+                }
+
+                let (alpha_coverage, alpha_to_one) = multisampling
+                    .as_ref()
+                    .map_or((false, false), |ms| (ms.alpha_coverage, ms.alpha_to_one));
+                unsafe {
+                    if alpha_coverage {
+                        gl.enable(glow::SAMPLE_ALPHA_TO_COVERAGE);
+                    } else {
+                        gl.disable(glow::SAMPLE_ALPHA_TO_COVERAGE);
+                    }
                 }
 
                 if !self.share.info.is_webgl() && !self.share.info.version.is_embedded {
-                    match false {
-                        //TODO
-                        true => unsafe { gl.enable(glow::MULTISAMPLE) },
-                        false => unsafe { gl.disable(glow::MULTISAMPLE) },
+                    unsafe {
+                        if multisampling.is_some() {
+                            gl.enable(glow::MULTISAMPLE);
+                        } else {
+                            gl.disable(glow::MULTISAMPLE);
+                        }
+                        if alpha_to_one {
+                            gl.enable(glow::SAMPLE_ALPHA_TO_ONE);
+                        } else {
+                            gl.disable(glow::SAMPLE_ALPHA_TO_ONE);
+                        }
                     }
                 }
             }
@@ -1031,7 +1255,7 @@ impl Queue {
                     if slot.is_some() {
                         // TODO: the generator of these commands should coalesce identical masks to prevent this warning
                         //       as much as is possible.
-                        warn!("GLES and WebGL do not support per-target color masks. Falling back on global mask.");
+                        auxil::report!(Warn, "gfx::gl::queue", "color-mask-per-target-unsupported", "GLES and WebGL do not support per-target color masks. Falling back on global mask.");
                     }
                     self.share.context.color_mask(
                         mask.contains(Cm::RED) as _,
@@ -1062,6 +1286,13 @@ impl Queue {
                     }
                 }
             }
+            com::Command::TextureBarrier => {
+                if let Some(texture_barrier) = self.share.context.texture_barrier {
+                    unsafe {
+                        texture_barrier();
+                    }
+                }
+            }
         }
         if let Err(err) = self.share.check() {
             panic!("Error {:?} executing command: {:?}", err, cmd)
@@ -1076,7 +1307,8 @@ impl hal::queue::Queue<Backend> for Queue {
         _wait_semaphores: Iw,
         _signal_semaphores: Is,
         fence: Option<&mut native::Fence>,
-    ) where
+    ) -> Result<(), hal::queue::SubmitError>
+    where
         Ic: Iterator<Item = &'a com::CommandBuffer>,
         Iw: Iterator<Item = (&'a native::Semaphore, hal::pso::PipelineStage)>,
         Is: Iterator<Item = &'a native::Semaphore>,
@@ -1118,6 +1350,7 @@ impl hal::queue::Queue<Backend> for Queue {
                 native::Fence::Idle { signaled: true }
             }
         }
+        Ok(())
     }
 
     unsafe fn present(
@@ -1129,7 +1362,7 @@ impl hal::queue::Queue<Backend> for Queue {
         surface.present(image, &self.share.context)
     }
 
-    fn wait_idle(&mut self) -> Result<(), hal::device::OutOfMemory> {
+    fn wait_idle(&mut self) -> Result<(), hal::device::WaitError> {
         unsafe {
             self.share.context.finish();
         }
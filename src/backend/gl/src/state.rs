@@ -1,4 +1,4 @@
-use crate::{ColorSlot, GlContainer};
+use crate::{info::PrivateCaps, ColorSlot, GlContainer};
 use glow::HasContext;
 use hal::pso;
 
@@ -126,17 +126,49 @@ fn map_blend_op(operation: pso::BlendOp) -> (u32, u32, u32) {
         ),
         pso::BlendOp::Min => (glow::MIN, glow::ZERO, glow::ZERO),
         pso::BlendOp::Max => (glow::MAX, glow::ZERO, glow::ZERO),
+        pso::BlendOp::Advanced(op) => (map_advanced_blend_op(op), glow::ZERO, glow::ZERO),
     }
 }
 
+/// Map an advanced blend operation to its `GL_KHR_blend_equation_advanced` equation.
+fn map_advanced_blend_op(operation: pso::AdvancedBlendOp) -> u32 {
+    use hal::pso::AdvancedBlendOp::*;
+    match operation {
+        Multiply => 0x9294,
+        Screen => 0x9295,
+        Overlay => 0x9296,
+        Darken => 0x9297,
+        Lighten => 0x9298,
+        ColorDodge => 0x9299,
+        ColorBurn => 0x929A,
+        HardLight => 0x929B,
+        SoftLight => 0x929C,
+        Difference => 0x929E,
+        Exclusion => 0x92A0,
+        HslHue => 0x92AD,
+        HslSaturation => 0x92AE,
+        HslColor => 0x92AF,
+        HslLuminosity => 0x92B0,
+    }
+}
+
+unsafe fn set_blend_func(gl: &GlContainer, blend: &pso::BlendState) {
+    let (color_eq, color_src, color_dst) = map_blend_op(blend.color);
+    let (alpha_eq, alpha_src, alpha_dst) = map_blend_op(blend.alpha);
+    // The advanced equations apply to the alpha as well, and don't use the factors
+    if let pso::BlendOp::Advanced(_) = blend.color {
+        gl.blend_equation(color_eq);
+        return;
+    }
+    gl.blend_equation_separate(color_eq, alpha_eq);
+    gl.blend_func_separate(color_src, color_dst, alpha_src, alpha_dst);
+}
+
 pub(crate) fn set_blend(gl: &GlContainer, blend: &Option<pso::BlendState>) {
     match blend {
         Some(ref blend) => unsafe {
-            let (color_eq, color_src, color_dst) = map_blend_op(blend.color);
-            let (alpha_eq, alpha_src, alpha_dst) = map_blend_op(blend.alpha);
             gl.enable(glow::BLEND);
-            gl.blend_equation_separate(color_eq, alpha_eq);
-            gl.blend_func_separate(color_src, color_dst, alpha_src, alpha_dst);
+            set_blend_func(gl, blend);
         },
         None => unsafe {
             gl.disable(glow::BLEND);
@@ -149,26 +181,42 @@ pub(crate) fn set_blend_slot(
     slot: ColorSlot,
     blend: &Option<pso::BlendState>,
     features: &hal::Features,
+    private_caps: &PrivateCaps,
 ) {
-    if !features.contains(hal::Features::INDEPENDENT_BLENDING) {
-        warn!("independent blending is not supported");
-        return;
+    if features.contains(hal::Features::INDEPENDENT_BLENDING) {
+        match blend {
+            Some(ref blend) => unsafe {
+                let (color_eq, color_src, color_dst) = map_blend_op(blend.color);
+                let (alpha_eq, alpha_src, alpha_dst) = map_blend_op(blend.alpha);
+                gl.enable_draw_buffer(glow::BLEND, slot as _);
+                if let pso::BlendOp::Advanced(_) = blend.color {
+                    gl.blend_equation_draw_buffer(slot as _, color_eq);
+                } else {
+                    gl.blend_equation_separate_draw_buffer(slot as _, color_eq, alpha_eq);
+                    gl.blend_func_separate_draw_buffer(
+                        slot as _, color_src, color_dst, alpha_src, alpha_dst,
+                    );
+                }
+            },
+            None => unsafe {
+                gl.disable_draw_buffer(glow::BLEND, slot as _);
+            },
+        };
+    } else if private_caps.per_slot_blend_enable {
+        // The blended slots share the same state, see `Device::blend_targets`
+        match blend {
+            Some(ref blend) => unsafe {
+                gl.enable_draw_buffer(glow::BLEND, slot as _);
+                set_blend_func(gl, blend);
+            },
+            None => unsafe {
+                gl.disable_draw_buffer(glow::BLEND, slot as _);
+            },
+        };
+    } else {
+        // All the slots are blended the same way, see `Device::blend_targets`
+        set_blend(gl, blend);
     }
-
-    match blend {
-        Some(ref blend) => unsafe {
-            let (color_eq, color_src, color_dst) = map_blend_op(blend.color);
-            let (alpha_eq, alpha_src, alpha_dst) = map_blend_op(blend.alpha);
-            gl.enable_draw_buffer(glow::BLEND, slot as _);
-            gl.blend_equation_separate_draw_buffer(slot as _, color_eq, alpha_eq);
-            gl.blend_func_separate_draw_buffer(
-                slot as _, color_src, color_dst, alpha_src, alpha_dst,
-            );
-        },
-        None => unsafe {
-            gl.disable_draw_buffer(glow::BLEND, slot as _);
-        },
-    };
 }
 
 pub(crate) fn _unlock_color_mask(gl: &GlContainer) {
@@ -1,10 +1,10 @@
 //! EGL-based surface and swapchain.
 
-use crate::{conv, native, GlContainer, PhysicalDevice, Starc};
+use crate::{conv, dma_buf, native, GlContainer, PhysicalDevice, Share, Starc};
 use glow::HasContext;
 use hal::{image, window as w};
 use parking_lot::Mutex;
-use std::{os::raw, ptr};
+use std::{mem, os::raw, ptr};
 
 #[derive(Debug)]
 pub struct Swapchain {
@@ -14,6 +14,9 @@ pub struct Swapchain {
     extent: w::Extent2D,
     format: native::TextureFormat,
     channel: hal::format::ChannelType,
+    /// Copy the values into the window without encoding them,
+    /// by disabling `FRAMEBUFFER_SRGB` for the present blit.
+    raw_copy: bool,
 }
 
 #[derive(Debug)]
@@ -31,7 +34,12 @@ pub struct Inner {
     config: egl::Config,
     context: egl::Context,
     pbuffer: egl::Surface,
-    wl_display: Option<*mut raw::c_void>,
+    /// dma-bufs can be imported as EGL images.
+    supports_dma_buf_import: bool,
+    /// dma-bufs can be imported with explicit modifiers.
+    supports_dma_buf_modifiers: bool,
+    /// Platform and native connection of the display, if it was obtained from one.
+    platform_display: Option<(u32, *mut raw::c_void)>,
 }
 
 unsafe impl Send for Instance {}
@@ -39,6 +47,8 @@ unsafe impl Sync for Instance {}
 
 const EGL_PLATFORM_WAYLAND_KHR: u32 = 0x31D8;
 const EGL_PLATFORM_X11_KHR: u32 = 0x31D5;
+#[cfg_attr(any(target_os = "android", target_os = "macos"), allow(dead_code))]
+const EGL_PLATFORM_XCB_EXT: u32 = 0x31DC;
 
 type XOpenDisplayFun =
     unsafe extern "system" fn(display_name: *const raw::c_char) -> *mut raw::c_void;
@@ -172,6 +182,11 @@ impl Inner {
             }
         }
 
+        let supports_dma_buf_import = display_extensions.contains("EGL_KHR_image_base")
+            && display_extensions.contains("EGL_EXT_image_dma_buf_import");
+        let supports_dma_buf_modifiers =
+            display_extensions.contains("EGL_EXT_image_dma_buf_import_modifiers");
+
         let (config, supports_native_window) = choose_config(&egl, display)?;
         egl.bind_api(egl::OPENGL_ES_API).unwrap();
 
@@ -211,9 +226,42 @@ impl Inner {
             config,
             context,
             pbuffer,
-            wl_display: None,
+            supports_dma_buf_import,
+            supports_dma_buf_modifiers,
+            platform_display: None,
         })
     }
+
+    /// Re-initialize on the native display connection of a window, since the
+    /// surfaces can only be created on the display they belong to.
+    #[cfg_attr(any(target_os = "android", target_os = "macos"), allow(dead_code))]
+    fn switch_display(
+        &mut self,
+        platform: u32,
+        native_display: *mut raw::c_void,
+        wsi_library: Option<&libloading::Library>,
+    ) -> Result<(), w::InitError> {
+        if self.platform_display == Some((platform, native_display)) {
+            return Ok(());
+        }
+        let display_attributes = [egl::ATTRIB_NONE];
+        let display = self
+            .egl
+            .upcast::<egl::EGL1_5>()
+            .ok_or(w::InitError::UnsupportedWindowHandle)?
+            .get_platform_display(platform, native_display, &display_attributes)
+            .map_err(|e| {
+                log::warn!("Error in get_platform_display: {:?}", e);
+                w::InitError::UnsupportedWindowHandle
+            })?;
+
+        let new_inner = Inner::create(self.egl.clone(), display, wsi_library)
+            .map_err(|_| w::InitError::UnsupportedWindowHandle)?;
+        let old_inner = std::mem::replace(self, new_inner);
+        self.platform_display = Some((platform, native_display));
+        drop(old_inner);
+        Ok(())
+    }
 }
 
 impl Drop for Inner {
@@ -246,6 +294,7 @@ impl hal::Instance<crate::Backend> for Instance {
         log::info!("Client extensions: {:?}", client_ext_str);
 
         let mut wsi_library = None;
+        let mut x11_display = None;
 
         let wayland_library = if client_ext_str.contains(&"EGL_EXT_platform_wayland") {
             test_wayland_display()
@@ -277,6 +326,7 @@ impl hal::Instance<crate::Backend> for Instance {
             log::info!("Using X11 platform");
             let display_attributes = [egl::ATTRIB_NONE];
             wsi_library = Some(library);
+            x11_display = Some(display);
             egl.get_platform_display(EGL_PLATFORM_X11_KHR, display.as_ptr(), &display_attributes)
                 .unwrap()
         } else {
@@ -284,7 +334,10 @@ impl hal::Instance<crate::Backend> for Instance {
             egl.get_display(egl::DEFAULT_DISPLAY).unwrap()
         };
 
-        let inner = Inner::create(egl.clone(), display, wsi_library.as_ref())?;
+        let mut inner = Inner::create(egl.clone(), display, wsi_library.as_ref())?;
+        if let Some(x11_display) = x11_display {
+            inner.platform_display = Some((EGL_PLATFORM_X11_KHR, x11_display.as_ptr()));
+        }
 
         Ok(Instance {
             inner: Mutex::new(inner),
@@ -312,8 +365,50 @@ impl hal::Instance<crate::Backend> for Instance {
                     .map_or(ptr::null(), |p| p as *const _)
             })
         };
+        let tex_buffer_range = inner
+            .egl
+            .get_proc_address("glTexBufferRange")
+            .map(|p| unsafe { mem::transmute::<_, crate::TexBufferRange>(p) });
+        let bind_buffers_range = inner
+            .egl
+            .get_proc_address("glBindBuffersRange")
+            .map(|p| unsafe { mem::transmute::<_, crate::BindBuffersRange>(p) });
+        let texture_barrier = ["glTextureBarrier", "glTextureBarrierNV"]
+            .iter()
+            .find_map(|name| inner.egl.get_proc_address(name))
+            .map(|p| unsafe { mem::transmute::<_, crate::TextureBarrier>(p) });
+        let dma_buf = if inner.supports_dma_buf_import {
+            let egl = &inner.egl;
+            match (
+                egl.get_proc_address("eglCreateImageKHR"),
+                egl.get_proc_address("eglDestroyImageKHR"),
+                egl.get_proc_address("glEGLImageTargetTexture2DOES"),
+            ) {
+                (Some(create_image), Some(destroy_image), Some(image_target_texture)) => unsafe {
+                    Some(dma_buf::DmaBufImport {
+                        display: inner.display.as_ptr(),
+                        create_image: mem::transmute(create_image),
+                        destroy_image: mem::transmute(destroy_image),
+                        query_modifiers: egl
+                            .get_proc_address("eglQueryDmaBufModifiersEXT")
+                            .filter(|_| inner.supports_dma_buf_modifiers)
+                            .map(|p| mem::transmute::<_, dma_buf::QueryModifiers>(p)),
+                        image_target_texture: mem::transmute(image_target_texture),
+                    })
+                },
+                _ => None,
+            }
+        } else {
+            None
+        };
         // Create physical device
-        vec![PhysicalDevice::new_adapter(context)]
+        vec![PhysicalDevice::new_adapter(
+            context,
+            tex_buffer_range,
+            bind_buffers_range,
+            texture_barrier,
+            dma_buf,
+        )]
     }
 
     #[cfg_attr(target_os = "macos", allow(unused, unused_mut, unreachable_code))]
@@ -330,11 +425,17 @@ impl hal::Instance<crate::Backend> for Instance {
         let native_window_ptr = match has_handle.raw_window_handle() {
             #[cfg(not(any(target_os = "android", target_os = "macos")))]
             Rwh::Xlib(handle) => {
+                if !handle.display.is_null() {
+                    inner.switch_display(EGL_PLATFORM_X11_KHR, handle.display, None)?;
+                }
                 temp_xlib_handle = handle.window;
                 &mut temp_xlib_handle as *mut _ as *mut std::ffi::c_void
             }
             #[cfg(not(any(target_os = "android", target_os = "macos")))]
             Rwh::Xcb(handle) => {
+                if !handle.connection.is_null() {
+                    inner.switch_display(EGL_PLATFORM_XCB_EXT, handle.connection, None)?;
+                }
                 temp_xcb_handle = handle.window;
                 &mut temp_xcb_handle as *mut _ as *mut std::ffi::c_void
             }
@@ -348,32 +449,11 @@ impl hal::Instance<crate::Backend> for Instance {
                  *
                  * See gfx-rs/gfx#3545
                  */
-                if inner
-                    .wl_display
-                    .map(|ptr| ptr != handle.display)
-                    .unwrap_or(true)
-                {
-                    use std::ops::DerefMut;
-                    let display_attributes = [egl::ATTRIB_NONE];
-                    let display = inner
-                        .egl
-                        .upcast::<egl::EGL1_5>()
-                        .unwrap()
-                        .get_platform_display(
-                            EGL_PLATFORM_WAYLAND_KHR,
-                            handle.display,
-                            &display_attributes,
-                        )
-                        .unwrap();
-
-                    let new_inner =
-                        Inner::create(inner.egl.clone(), display, self.wsi_library.as_ref())
-                            .map_err(|_| w::InitError::UnsupportedWindowHandle)?;
-
-                    let old_inner = std::mem::replace(inner.deref_mut(), new_inner);
-                    inner.wl_display = Some(handle.display);
-                    drop(old_inner);
-                }
+                inner.switch_display(
+                    EGL_PLATFORM_WAYLAND_KHR,
+                    handle.display,
+                    self.wsi_library.as_ref(),
+                )?;
 
                 let wl_egl_window_create: libloading::Symbol<WlEglWindowCreateFun> = self
                     .wsi_library
@@ -440,6 +520,7 @@ impl hal::Instance<crate::Backend> for Instance {
             display: inner.display,
             context: inner.context,
             presentable: inner.supports_native_window,
+            srgb: inner.version >= (1, 5),
             pbuffer: inner.pbuffer,
             wl_window,
             swapchain: None,
@@ -472,6 +553,8 @@ pub struct Surface {
     context: egl::Context,
     pbuffer: egl::Surface,
     presentable: bool,
+    /// The window surface has the sRGB color space, encoding the values written into it.
+    srgb: bool,
     wl_window: Option<*mut raw::c_void>,
     pub(crate) swapchain: Option<Swapchain>,
 }
@@ -502,6 +585,12 @@ impl w::PresentationSurface<crate::Backend> for Surface {
             );
         }
 
+        if !self
+            .color_spaces(&device.share)
+            .contains(config.color_space)
+        {
+            log::error!("Color space {:?} is not supported", config.color_space);
+        }
         let desc = conv::describe_format(config.format).unwrap();
 
         let gl = &device.share.context;
@@ -530,6 +619,9 @@ impl w::PresentationSurface<crate::Backend> for Surface {
             extent: config.extent,
             format: desc.tex_internal,
             channel: config.format.base_format().1,
+            raw_copy: self.srgb
+                && config.color_space == w::ColorSpace::SRGB_NONLINEAR
+                && device.share.private_caps.framebuffer_srgb_control,
         });
 
         Ok(())
@@ -550,7 +642,7 @@ impl w::PresentationSurface<crate::Backend> for Surface {
         let sc = self.swapchain.as_ref().unwrap();
         let sc_image =
             native::SwapchainImage::new(sc.renderbuffer, sc.format, sc.extent, sc.channel);
-        Ok((sc_image, None))
+        Ok((sc_image, self.check_extent(sc.extent)))
     }
 }
 
@@ -559,34 +651,93 @@ impl w::Surface<crate::Backend> for Surface {
         self.presentable
     }
 
-    fn capabilities(&self, _physical_device: &PhysicalDevice) -> w::SurfaceCapabilities {
+    fn capabilities(&self, physical_device: &PhysicalDevice) -> w::SurfaceCapabilities {
+        // The swapchain is a renderbuffer blitted into the window on present.
+        let max_size = physical_device.0.limits.max_image_2d_size;
         w::SurfaceCapabilities {
             present_modes: w::PresentMode::FIFO,                  //TODO
             composite_alpha_modes: w::CompositeAlphaMode::OPAQUE, //TODO
             image_count: 2..=2,
-            current_extent: None,
+            current_extent: self.current_extent(),
             extents: w::Extent2D {
-                width: 4,
-                height: 4,
+                width: 1,
+                height: 1,
             }..=w::Extent2D {
-                width: 4096,
-                height: 4096,
+                width: max_size,
+                height: max_size,
             },
             max_image_layers: 1,
             usage: image::Usage::COLOR_ATTACHMENT,
+            current_transform: w::SurfaceTransform::IDENTITY,
+            transforms: w::SurfaceTransform::IDENTITY,
+            color_spaces: self.color_spaces(&physical_device.0),
+            extended_dynamic_range: None,
         }
     }
 
     fn supported_formats(
         &self,
-        _physical_device: &PhysicalDevice,
+        physical_device: &PhysicalDevice,
     ) -> Option<Vec<hal::format::Format>> {
         use hal::format::Format;
-        Some(vec![Format::Rgba8Srgb, Format::Bgra8Srgb])
+        let mut formats = vec![Format::Rgba8Srgb, Format::Bgra8Srgb];
+        // Linear values are stored in a format without encoding
+        if self
+            .color_spaces(&physical_device.0)
+            .contains(w::ColorSpace::LINEAR)
+        {
+            formats.push(Format::Rgba8Unorm);
+        }
+        Some(formats)
     }
 }
 
 impl Surface {
+    /// Returns the color spaces the swapchain can be presented in.
+    ///
+    /// Linear values get encoded by the present blit into an sRGB window.
+    fn color_spaces(&self, share: &Share) -> w::ColorSpace {
+        if self.srgb && share.private_caps.framebuffer_srgb_control {
+            w::ColorSpace::SRGB_NONLINEAR | w::ColorSpace::LINEAR
+        } else {
+            w::ColorSpace::SRGB_NONLINEAR
+        }
+    }
+
+    /// Returns the size of the window, as seen by EGL.
+    ///
+    /// Wayland windows don't have one, since their size is defined
+    /// by the client with `wl_egl_window_resize`.
+    fn current_extent(&self) -> Option<w::Extent2D> {
+        if self.wl_window.is_some() {
+            return None;
+        }
+        let width = self
+            .egl
+            .query_surface(self.display, self.raw, egl::WIDTH)
+            .ok()?;
+        let height = self
+            .egl
+            .query_surface(self.display, self.raw, egl::HEIGHT)
+            .ok()?;
+        if width > 0 && height > 0 {
+            Some(w::Extent2D {
+                width: width as u32,
+                height: height as u32,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Checks if the window was resized since the swapchain was configured.
+    fn check_extent(&self, extent: w::Extent2D) -> Option<w::Suboptimal> {
+        match self.current_extent() {
+            Some(current) if current != extent => Some(w::Suboptimal),
+            _ => None,
+        }
+    }
+
     pub(crate) unsafe fn present(
         &mut self,
         _image: native::SwapchainImage,
@@ -602,6 +753,9 @@ impl Surface {
                 Some(self.context),
             )
             .unwrap();
+        if sc.raw_copy {
+            gl.disable(glow::FRAMEBUFFER_SRGB);
+        }
         gl.bind_framebuffer(glow::DRAW_FRAMEBUFFER, None);
         gl.bind_framebuffer(glow::READ_FRAMEBUFFER, Some(sc.framebuffer));
         gl.blit_framebuffer(
@@ -617,6 +771,9 @@ impl Surface {
             glow::NEAREST,
         );
         gl.bind_framebuffer(glow::READ_FRAMEBUFFER, None);
+        if sc.raw_copy {
+            gl.enable(glow::FRAMEBUFFER_SRGB);
+        }
 
         self.egl.swap_buffers(self.display, self.raw).unwrap();
 
@@ -629,6 +786,6 @@ impl Surface {
             )
             .unwrap();
 
-        Ok(None)
+        Ok(self.check_extent(sc.extent))
     }
 }
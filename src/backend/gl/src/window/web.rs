@@ -52,14 +52,26 @@ impl hal::Instance<B> for Instance {
                 let webgl2_context = canvas
                     .get_context_with_context_options("webgl2", &context_options)
                     .expect("Cannot create WebGL2 context")
-                    .and_then(|context| context.dyn_into::<web_sys::WebGl2RenderingContext>().ok())
-                    .expect("Cannot convert into WebGL2 context");
-                glow::Context::from_webgl2_context(webgl2_context)
+                    .and_then(|context| context.dyn_into::<web_sys::WebGl2RenderingContext>().ok());
+                match webgl2_context {
+                    Some(context) => glow::Context::from_webgl2_context(context),
+                    // Fall back to the WebGL1 profile
+                    None => {
+                        let webgl1_context = canvas
+                            .get_context_with_context_options("webgl", &context_options)
+                            .expect("Cannot create WebGL context")
+                            .and_then(|context| {
+                                context.dyn_into::<web_sys::WebGlRenderingContext>().ok()
+                            })
+                            .expect("Cannot convert into WebGL context");
+                        glow::Context::from_webgl1_context(webgl1_context)
+                    }
+                }
             }
             None => return Vec::new(),
         };
 
-        let adapter = PhysicalDevice::new_adapter(context);
+        let adapter = PhysicalDevice::new_adapter(context, None, None, None, None);
         vec![adapter]
     }
 
@@ -160,6 +172,11 @@ impl window::Surface<B> for Surface {
             extents: extent..=extent,
             max_image_layers: 1,
             usage: image::Usage::COLOR_ATTACHMENT | image::Usage::TRANSFER_SRC,
+            current_transform: window::SurfaceTransform::IDENTITY,
+            transforms: window::SurfaceTransform::IDENTITY,
+            // The canvas isn't sRGB, so the values are presented as they are
+            color_spaces: window::ColorSpace::SRGB_NONLINEAR,
+            extended_dynamic_range: None,
         }
     }
 
@@ -1,19 +1,20 @@
 use crate::{
     conversions as conv,
     internal::{BlitVertex, ClearKey, ClearVertex},
-    native, soft, window, AsNative, Backend, BufferPtr, FastHashMap, OnlineRecording,
+    native, soft, window, AsNative, Backend, BufferPtr, Device, FastHashMap, OnlineRecording,
     PrivateDisabilities, ResourceIndex, ResourcePtr, SamplerPtr, Shared, TexturePtr,
     MAX_BOUND_DESCRIPTOR_SETS, MAX_COLOR_ATTACHMENTS,
 };
 
 use hal::{
     buffer, command as com,
-    device::OutOfMemory,
+    device::{DeviceLost, WaitError},
     format::{Aspects, FormatDesc},
     image as i, memory,
     pass::AttachmentLoadOp,
     pso, query,
-    window::{PresentError, Suboptimal},
+    queue::SubmitError,
+    window::{PresentError, PresentTime, Suboptimal},
     DrawCount, IndexCount, IndexType, InstanceCount, TaskCount, VertexCount, VertexOffset,
     WorkGroupCount,
 };
@@ -26,7 +27,7 @@ use copyless::VecHelper;
 use dispatch;
 use foreign_types::ForeignType;
 use metal::{self, MTLIndexType, MTLPrimitiveType, MTLScissorRect, MTLSize, MTLViewport, NSRange};
-use objc::rc::autoreleasepool;
+use objc::{rc::autoreleasepool, runtime::Object};
 use parking_lot::Mutex;
 
 #[cfg(feature = "dispatch")]
@@ -118,8 +119,8 @@ impl QueueInner {
         self.reserve.start -= 1;
     }
 
-    /// Block until GPU is idle.
-    pub(crate) fn wait_idle(queue: &Mutex<Self>) {
+    /// Block until GPU is idle, returning the last finished command buffer.
+    pub(crate) fn wait_idle(queue: &Mutex<Self>) -> metal::CommandBuffer {
         debug!("waiting for idle");
         // note: we deliberately don't hold the Mutex lock while waiting,
         // since the completion handlers need to access it.
@@ -130,6 +131,7 @@ impl QueueInner {
         cmd_buf.commit();
         cmd_buf.wait_until_completed();
         queue.lock().release(token);
+        cmd_buf
     }
 }
 
@@ -290,7 +292,9 @@ struct RenderPipelineState {
 
 #[derive(Debug)]
 struct SubpassInfo {
-    descriptor: metal::RenderPassDescriptor,
+    /// Descriptor of the new render encoder, or `None` if the subpass is merged
+    /// into the encoder of the previous one.
+    descriptor: Option<metal::RenderPassDescriptor>,
     combined_aspects: Aspects,
     formats: native::SubpassFormats,
     operations: native::SubpassData<native::AttachmentOps>,
@@ -301,6 +305,10 @@ struct SubpassInfo {
 struct DescriptorSetInfo {
     graphics_resources: Vec<(ResourcePtr, metal::MTLResourceUsage)>,
     compute_resources: Vec<(ResourcePtr, metal::MTLResourceUsage)>,
+    /// The set is bound to the graphics bind point.
+    graphics_bound: bool,
+    /// The set is bound to the compute bind point.
+    compute_bound: bool,
 }
 
 #[derive(Debug, Default)]
@@ -341,7 +349,10 @@ struct State {
     rasterizer_state: Option<native::RasterizerState>,
     depth_bias: pso::DepthBias,
     stencil: native::StencilState<pso::StencilValue>,
-    push_constants: Vec<u32>,
+    /// Push constants of the graphics and compute bind points, which
+    /// are kept apart so that a dispatch doesn't see the graphics ones.
+    graphics_push_constants: Vec<u32>,
+    compute_push_constants: Vec<u32>,
     visibility_query: (metal::MTLVisibilityResultMode, buffer::Offset),
     target: TargetState,
     pending_subpasses: Vec<SubpassInfo>,
@@ -371,7 +382,8 @@ impl State {
             read_masks: pso::Sided::new(!0),
             write_masks: pso::Sided::new(!0),
         };
-        self.push_constants.clear();
+        self.graphics_push_constants.clear();
+        self.compute_push_constants.clear();
         self.pending_subpasses.clear();
         self.resources_vs.clear();
         self.resources_ps.clear();
@@ -379,6 +391,8 @@ impl State {
         for ds in self.descriptor_sets.iter_mut() {
             ds.graphics_resources.clear();
             ds.compute_resources.clear();
+            ds.graphics_bound = false;
+            ds.compute_bound = false;
         }
         self.index_buffer = None;
         self.vertex_buffers.clear();
@@ -452,7 +466,7 @@ impl State {
         let (com_pso, com_rast) = self.make_pso_commands();
 
         let render_resources = iter::once(&self.resources_vs).chain(iter::once(&self.resources_ps));
-        let push_constants = self.push_constants.as_slice();
+        let push_constants = self.graphics_push_constants.as_slice();
         let com_resources = [naga::ShaderStage::Vertex, naga::ShaderStage::Fragment]
             .iter()
             .zip(render_resources)
@@ -527,7 +541,7 @@ impl State {
                 .push_constants
                 .map(|pc| soft::ComputeCommand::BindBufferData {
                     index: pc.buffer_index as _,
-                    words: &self.push_constants[..pc.count as usize],
+                    words: &self.compute_push_constants[..pc.count as usize],
                 });
         let com_used_resources = self.descriptor_sets.iter().flat_map(|ds| {
             ds.compute_resources
@@ -544,6 +558,15 @@ impl State {
             .chain(com_used_resources)
     }
 
+    /// Warn if a dispatch would only see sets bound to the graphics bind point.
+    fn check_dispatch(&self) {
+        let has_compute_sets = self.descriptor_sets.iter().any(|ds| ds.compute_bound);
+        let has_graphics_sets = self.descriptor_sets.iter().any(|ds| ds.graphics_bound);
+        if !has_compute_sets && has_graphics_sets {
+            warn!("Dispatch without any compute descriptor sets bound. Sets bound to the graphics bind point are not visible to compute.");
+        }
+    }
+
     fn set_vertex_buffers(&mut self, end: usize) -> Option<soft::RenderCommand<&soft::Ref>> {
         let rps = self.render_pso.as_ref()?;
         let start = end - rps.vertex_buffers.len();
@@ -632,7 +655,7 @@ impl State {
         soft::RenderCommand::BindBufferData {
             stage: naga::ShaderStage::Vertex,
             index: pc.buffer_index,
-            words: &self.push_constants[..pc.count as usize],
+            words: &self.graphics_push_constants[..pc.count as usize],
         }
     }
 
@@ -644,7 +667,7 @@ impl State {
         soft::RenderCommand::BindBufferData {
             stage: naga::ShaderStage::Fragment,
             index: pc.buffer_index,
-            words: &self.push_constants[..pc.count as usize],
+            words: &self.graphics_push_constants[..pc.count as usize],
         }
     }
 
@@ -655,7 +678,7 @@ impl State {
         self.resources_cs.push_constants = Some(pc);
         soft::ComputeCommand::BindBufferData {
             index: pc.buffer_index,
-            words: &self.push_constants[..pc.count as usize],
+            words: &self.compute_push_constants[..pc.count as usize],
         }
     }
 
@@ -664,10 +687,13 @@ impl State {
         vp: &'a pso::Viewport,
         disabilities: PrivateDisabilities,
     ) -> soft::RenderCommand<&'a soft::Ref> {
-        let depth = vp.depth.start..if disabilities.broken_viewport_near_depth {
-            vp.depth.end - vp.depth.start
+        // Metal doesn't support depth ranges outside of [0, 1]
+        let near = vp.depth.start.max(0.0).min(1.0);
+        let far = vp.depth.end.max(0.0).min(1.0);
+        let depth = near..if disabilities.broken_viewport_near_depth {
+            far - near
         } else {
-            vp.depth.end
+            far
         };
         self.viewport = Some((vp.rect, depth.clone()));
         soft::RenderCommand::SetViewport(vp.rect, depth)
@@ -720,10 +746,9 @@ impl State {
         soft::RenderCommand::SetBlendColor(*color)
     }
 
-    fn update_push_constants(&mut self, offset: u32, constants: &[u32], total: u32) {
+    fn update_push_constants(data: &mut Vec<u32>, offset: u32, constants: &[u32], total: u32) {
         assert_eq!(offset % WORD_ALIGNMENT as u32, 0);
         let offset = (offset / WORD_ALIGNMENT as u32) as usize;
-        let data = &mut self.push_constants;
         if data.len() < total as usize {
             data.resize(total as usize, 0);
         }
@@ -1567,12 +1592,18 @@ pub struct CommandBufferInner {
     active_visibility_queries: Vec<query::Id>,
     events: Vec<(Arc<AtomicBool>, bool)>,
     host_events: Vec<Arc<AtomicBool>>,
+    checkpoints: Vec<String>,
 }
 
 impl Drop for CommandBufferInner {
     fn drop(&mut self) {
         if self.sink.is_some() {
-            error!("Command buffer not released properly!");
+            auxil::report!(
+                Error,
+                "gfx::metal::command",
+                "command-buffer-leaked",
+                "Command buffer not released properly!"
+            );
         }
     }
 }
@@ -1605,10 +1636,27 @@ impl CommandBufferInner {
             }
             None => {}
         };
-        self.retained_buffers.clear();
-        self.retained_textures.clear();
-        self.active_visibility_queries.clear();
-        self.events.clear();
+        if release {
+            // drop the storage kept around by the previous non-releasing resets
+            self.backup_journal = None;
+            #[cfg(feature = "dispatch")]
+            {
+                self.backup_capacity = None;
+            }
+            self.retained_buffers = Vec::new();
+            self.retained_textures = Vec::new();
+            self.active_visibility_queries = Vec::new();
+            self.events = Vec::new();
+            self.host_events = Vec::new();
+            self.checkpoints = Vec::new();
+        } else {
+            self.retained_buffers.clear();
+            self.retained_textures.clear();
+            self.active_visibility_queries.clear();
+            self.events.clear();
+            self.host_events.clear();
+            self.checkpoints.clear();
+        }
     }
 
     fn sink(&mut self) -> &mut CommandSink {
@@ -1645,7 +1693,11 @@ fn div(a: u32, b: u32) -> u32 {
     (a + b - 1) / b
 }
 
-fn compute_pitches(region: &com::BufferImageCopy, fd: FormatDesc, extent: &MTLSize) -> (u32, u32) {
+pub(crate) fn compute_pitches(
+    region: &com::BufferImageCopy,
+    fd: FormatDesc,
+    extent: &MTLSize,
+) -> (u32, u32) {
     let buffer_width = if region.buffer_width == 0 {
         extent.width as u32
     } else {
@@ -2194,6 +2246,48 @@ impl Queue {
             }
         }
     }
+
+    fn present_impl(
+        &mut self,
+        surface: &mut window::Surface,
+        image: window::SwapchainImage,
+        wait_semaphore: Option<&mut native::Semaphore>,
+        time: Option<PresentTime>,
+    ) {
+        if let Some(semaphore) = wait_semaphore {
+            if let Some(ref system) = semaphore.system {
+                system.wait(!0);
+            }
+        }
+
+        let time = time.filter(|_| self.shared.private_caps.present_timing);
+        let queue = self.shared.queue.lock();
+        let drawable = image.into_drawable();
+        autoreleasepool(|| {
+            let command_buffer = queue.raw.new_command_buffer();
+            if INTERNAL_LABELS {
+                command_buffer.set_label("present");
+            }
+            self.record_empty(command_buffer);
+
+            match time {
+                Some(time) if time.desired_present_time != 0 => {
+                    // Host time is expressed in seconds
+                    let seconds = time.desired_present_time as f64 / 1_000_000_000.0;
+                    let () = unsafe {
+                        msg_send![command_buffer, presentDrawable: drawable.as_ref() atTime: seconds]
+                    };
+                }
+                _ => command_buffer.present_drawable(&drawable),
+            }
+            command_buffer.commit();
+            surface.set_last_present(command_buffer.to_owned());
+        });
+
+        if let Some(time) = time {
+            surface.track_present(time, drawable);
+        }
+    }
 }
 
 impl hal::queue::Queue<Backend> for Queue {
@@ -2203,12 +2297,16 @@ impl hal::queue::Queue<Backend> for Queue {
         wait_semaphores: Iw,
         signal_semaphores: Is,
         fence: Option<&mut native::Fence>,
-    ) where
+    ) -> Result<(), SubmitError>
+    where
         Ic: Iterator<Item = &'a CommandBuffer>,
         Iw: Iterator<Item = (&'a native::Semaphore, pso::PipelineStage)>,
         Is: Iterator<Item = &'a native::Semaphore>,
     {
         debug!("submitting with fence {:?}", fence);
+        if self.shared.device_lost.lock().is_some() {
+            return Err(DeviceLost.into());
+        }
         self.wait(wait_semaphores.map(|(s, _)| s));
 
         let system_semaphores = signal_semaphores
@@ -2218,6 +2316,7 @@ impl hal::queue::Queue<Backend> for Queue {
         #[allow(unused_mut)]
         let (mut num_immediate, mut num_deferred, mut num_remote) = (0, 0, 0);
         let mut event_commands = Vec::new();
+        let mut checkpoints = Vec::new();
         let do_signal = fence.is_some() || !system_semaphores.is_empty();
 
         autoreleasepool(|| {
@@ -2236,11 +2335,13 @@ impl hal::queue::Queue<Backend> for Queue {
                     ref mut active_visibility_queries,
                     ref events,
                     ref host_events,
+                    checkpoints: ref cmd_checkpoints,
                     ..
                 } = *inner;
 
                 //TODO: split event commands into immediate/blocked submissions?
                 event_commands.extend_from_slice(events);
+                checkpoints.extend_from_slice(cmd_checkpoints);
                 // wait for anything not previously fired
                 let wait_events = host_events
                     .iter()
@@ -2324,7 +2425,10 @@ impl hal::queue::Queue<Backend> for Queue {
                 }
             }
 
-            if do_signal || !event_commands.is_empty() || !self.active_visibility_queries.is_empty()
+            if do_signal
+                || !event_commands.is_empty()
+                || !self.active_visibility_queries.is_empty()
+                || !checkpoints.is_empty()
             {
                 //Note: there is quite a bit copying here
                 let free_buffers = self.retained_buffers.drain(..).collect::<Vec<_>>();
@@ -2335,8 +2439,13 @@ impl hal::queue::Queue<Backend> for Queue {
                     let queries = self.active_visibility_queries.drain(..).collect::<Vec<_>>();
                     Some((Arc::clone(&self.shared), queries))
                 };
+                let checkpoints = if checkpoints.is_empty() {
+                    None
+                } else {
+                    Some((Arc::clone(&self.shared), checkpoints))
+                };
 
-                let block = ConcreteBlock::new(move |_cb: *mut ()| {
+                let block = ConcreteBlock::new(move |cb: *mut ()| {
                     // signal the semaphores
                     for semaphore in &system_semaphores {
                         semaphore.signal();
@@ -2361,6 +2470,18 @@ impl hal::queue::Queue<Backend> for Queue {
                         let _ = vis.allocator.lock();
                         vis.condvar.notify_all();
                     }
+                    // track the progress of the checkpoints
+                    if let Some((ref shared, ref labels)) = checkpoints {
+                        let status: metal::MTLCommandBufferStatus =
+                            msg_send![cb as *mut Object, status];
+                        let mut progress = shared.checkpoints.lock();
+                        match status {
+                            metal::MTLCommandBufferStatus::Completed => {
+                                progress.last = labels.last().cloned();
+                            }
+                            _ => progress.failed.extend_from_slice(labels),
+                        }
+                    }
                 })
                 .copy();
 
@@ -2402,37 +2523,33 @@ impl hal::queue::Queue<Backend> for Queue {
                 counters.signal_command_buffers += 1;
             }
         }
+        Ok(())
     }
 
     unsafe fn present(
         &mut self,
-        _surface: &mut window::Surface,
+        surface: &mut window::Surface,
         image: window::SwapchainImage,
         wait_semaphore: Option<&mut native::Semaphore>,
     ) -> Result<Option<Suboptimal>, PresentError> {
-        if let Some(semaphore) = wait_semaphore {
-            if let Some(ref system) = semaphore.system {
-                system.wait(!0);
-            }
-        }
-
-        let queue = self.shared.queue.lock();
-        let drawable = image.into_drawable();
-        autoreleasepool(|| {
-            let command_buffer = queue.raw.new_command_buffer();
-            if INTERNAL_LABELS {
-                command_buffer.set_label("present");
-            }
-            self.record_empty(command_buffer);
+        self.present_impl(surface, image, wait_semaphore, None);
+        Ok(None)
+    }
 
-            command_buffer.present_drawable(&drawable);
-            command_buffer.commit();
-        });
+    unsafe fn present_with_time(
+        &mut self,
+        surface: &mut window::Surface,
+        image: window::SwapchainImage,
+        wait_semaphore: Option<&mut native::Semaphore>,
+        time: PresentTime,
+    ) -> Result<Option<Suboptimal>, PresentError> {
+        self.present_impl(surface, image, wait_semaphore, Some(time));
         Ok(None)
     }
 
-    fn wait_idle(&mut self) -> Result<(), OutOfMemory> {
-        QueueInner::wait_idle(&self.shared.queue);
+    fn wait_idle(&mut self) -> Result<(), WaitError> {
+        let cmd_buf = QueueInner::wait_idle(&self.shared.queue);
+        self.shared.check_command_buffer(&cmd_buf)?;
         Ok(())
     }
 
@@ -2478,6 +2595,7 @@ impl hal::pool::CommandPool<Backend> for CommandPool {
             active_visibility_queries: Vec::new(),
             events: Vec::new(),
             host_events: Vec::new(),
+            checkpoints: Vec::new(),
         }));
         self.allocated.push(Arc::clone(&inner));
 
@@ -2509,7 +2627,8 @@ impl hal::pool::CommandPool<Backend> for CommandPool {
                     read_masks: pso::Sided::new(!0),
                     write_masks: pso::Sided::new(!0),
                 },
-                push_constants: Vec::new(),
+                graphics_push_constants: Vec::new(),
+                compute_push_constants: Vec::new(),
                 vertex_buffers: Vec::new(),
                 target: TargetState::default(),
                 visibility_query: (metal::MTLVisibilityResultMode::Disabled, 0),
@@ -2550,7 +2669,12 @@ impl hal::pool::CommandPool<Backend> for CommandPool {
                 Some(index) => {
                     self.allocated.swap_remove(index);
                 }
-                None => error!("Unable to free a command buffer!"),
+                None => auxil::report!(
+                    Error,
+                    "gfx::metal::command",
+                    "command-buffer-free-failed",
+                    "Unable to free a command buffer!"
+                ),
             }
         }
     }
@@ -2568,6 +2692,53 @@ impl CommandBuffer {
             }
         }
     }
+
+    // Clear an image that can't be rendered to, by copying the packed texels from a buffer.
+    fn clear_image_by_copy<T>(&mut self, image: &native::Image, texel: u32, subresource_ranges: T)
+    where
+        T: Iterator<Item = i::SubresourceRange>,
+    {
+        let raw = image.like.as_texture();
+        let mut inner = self.inner.borrow_mut();
+        for sub in subresource_ranges {
+            let num_layers = sub.resolve_layer_count(image.kind.num_layers());
+            let num_levels = sub.resolve_level_count(image.mip_levels);
+            for level in sub.level_start..sub.level_start + num_levels {
+                let extent = image.kind.extent().at_level(level);
+                let num_texels =
+                    (extent.width * extent.height * extent.depth) as usize * num_layers as usize;
+                let data = vec![texel; num_texels];
+                let src = self.shared.device.lock().new_buffer_with_data(
+                    data.as_ptr() as _,
+                    (num_texels * mem::size_of::<u32>()) as _,
+                    metal::MTLResourceOptions::CPUCacheModeWriteCombined,
+                );
+                if INTERNAL_LABELS {
+                    src.set_label("clear_image");
+                }
+
+                let command = soft::BlitCommand::CopyBufferToImage {
+                    src: AsNative::from(src.as_ref()),
+                    dst: AsNative::from(raw),
+                    dst_desc: image.format_desc,
+                    region: com::BufferImageCopy {
+                        buffer_offset: 0,
+                        buffer_width: 0,
+                        buffer_height: 0,
+                        image_layers: i::SubresourceLayers {
+                            aspects: Aspects::COLOR,
+                            level,
+                            layers: sub.layer_start..sub.layer_start + num_layers,
+                        },
+                        image_offset: i::Offset::ZERO,
+                        image_extent: extent,
+                    },
+                };
+                inner.sink().blit_commands(iter::once(command));
+                inner.retained_buffers.push(src);
+            }
+        }
+    }
 }
 
 impl com::CommandBuffer<Backend> for CommandBuffer {
@@ -2661,7 +2832,12 @@ impl com::CommandBuffer<Backend> for CommandBuffer {
                     ));
                 }
                 _ => {
-                    warn!("Unexpected inheritance info on a primary command buffer");
+                    auxil::report!(
+                        Warn,
+                        "gfx::metal::command",
+                        "unexpected-inheritance-info",
+                        "Unexpected inheritance info on a primary command buffer"
+                    );
                 }
             }
         }
@@ -2787,6 +2963,18 @@ impl com::CommandBuffer<Backend> for CommandBuffer {
     ) where
         T: Iterator<Item = i::SubresourceRange>,
     {
+        if image.mtl_format == metal::MTLPixelFormat::RGB9E5Float
+            && !self.shared.private_caps.format_rgb9e5_all
+            && !self.shared.private_caps.format_rgb9e5_no_write
+        {
+            // Shared exponent images are only filterable here, so the clear can't be rendered
+            let texel = hal::format::Format::E5b9g9r9Ufloat
+                .pack_ufloat_color(value.color.float32)
+                .unwrap();
+            self.clear_image_by_copy(image, texel, subresource_ranges);
+            return;
+        }
+
         let CommandBufferInner {
             ref mut retained_textures,
             ref mut sink,
@@ -3194,9 +3382,13 @@ impl com::CommandBuffer<Backend> for CommandBuffer {
             let de = dst.kind.extent().at_level(r.dst_subresource.level);
             //TODO: support 3D textures
             if se.depth != 1 || de.depth != 1 {
-                warn!(
+                auxil::report!(
+                    Warn,
+                    "gfx::metal::command",
+                    "image-3d-blit-unsupported",
                     "3D image blits are not supported properly yet: {:?} -> {:?}",
-                    se, de
+                    se,
+                    de
                 );
             }
 
@@ -3470,7 +3662,12 @@ impl com::CommandBuffer<Backend> for CommandBuffer {
     }
 
     unsafe fn set_depth_bounds(&mut self, _: Range<f32>) {
-        warn!("Depth bounds test is not supported");
+        auxil::report!(
+            Warn,
+            "gfx::metal::command",
+            "depth-bounds-unsupported",
+            "Depth bounds test is not supported"
+        );
     }
 
     unsafe fn set_line_width(&mut self, width: f32) {
@@ -3485,6 +3682,34 @@ impl com::CommandBuffer<Backend> for CommandBuffer {
         self.inner.borrow_mut().sink().pre_render().issue(com);
     }
 
+    unsafe fn set_shading_rate(
+        &mut self,
+        _rate: pso::ShadingRate,
+        _combiners: [pso::ShadingRateCombiner; 2],
+    ) {
+        //TODO: rasterization rate maps only cover the attachment-based rates
+        auxil::report!(
+            Warn,
+            "gfx::metal::command",
+            "shading-rate-unsupported",
+            "Variable rate shading is not supported"
+        );
+    }
+
+    unsafe fn set_sample_locations(
+        &mut self,
+        _samples: i::NumSamples,
+        _locations: &[pso::SampleLocation],
+    ) {
+        //TODO: `MTLRenderPassDescriptor::setSamplePositions` requires breaking the pass
+        auxil::report!(
+            Warn,
+            "gfx::metal::command",
+            "sample-locations-unsupported",
+            "Programmable sample locations are not supported"
+        );
+    }
+
     unsafe fn set_stencil_reference(&mut self, faces: pso::Face, value: pso::StencilValue) {
         assign_sides(&mut self.state.stencil.reference_values, faces, value);
         let com =
@@ -3526,79 +3751,108 @@ impl com::CommandBuffer<Backend> for CommandBuffer {
 
         //Note: we stack the subpasses in the opposite order
         for subpass in render_pass.subpasses.iter().rev() {
-            let mut combined_aspects = Aspects::empty();
-            let descriptor = autoreleasepool(|| {
-                let descriptor = self
-                    .pool_shared
-                    .render_pass_descriptors
-                    .lock()
-                    .alloc(&self.shared);
-                if self.shared.private_caps.layered_rendering {
-                    descriptor.set_render_target_array_length(framebuffer.extent.depth as _);
-                }
-
-                for (i, at) in subpass.attachments.colors.iter().enumerate() {
-                    let rat = &render_pass.attachments[at.id];
-                    let &(ref texture, ref clear_value) = &self.temp.render_attachments[at.id];
-                    let desc = descriptor.color_attachments().object_at(i as _).unwrap();
-
-                    combined_aspects |= Aspects::COLOR;
-                    desc.set_texture(Some(texture.as_ref()));
+            let mut combined_aspects = if subpass.attachments.colors.is_empty() {
+                Aspects::empty()
+            } else {
+                Aspects::COLOR
+            };
+            if let Some(ref at) = subpass.attachments.depth_stencil {
+                let rat = &render_pass.attachments[at.id];
+                combined_aspects |= rat.format.unwrap().surface_desc().aspects;
+            }
 
-                    if at.ops.contains(native::AttachmentOps::LOAD) {
-                        desc.set_load_action(conv::map_load_operation(rat.ops.load));
-                        if rat.ops.load == AttachmentLoadOp::Clear {
-                            desc.set_clear_color(at.channel.interpret(clear_value.color));
-                        }
-                    }
-                    if let Some(id) = at.resolve_id {
-                        let &(ref resolve_texture, _) = &self.temp.render_attachments[id];
-                        //Note: the selection of levels and slices is already handled by `ImageView`
-                        desc.set_resolve_texture(Some(resolve_texture.as_ref()));
-                        desc.set_store_action(conv::map_resolved_store_operation(rat.ops.store));
-                    } else if at.ops.contains(native::AttachmentOps::STORE) {
-                        desc.set_store_action(conv::map_store_operation(rat.ops.store));
+            let descriptor = if subpass.merged {
+                None
+            } else {
+                Some(autoreleasepool(|| {
+                    let descriptor = self
+                        .pool_shared
+                        .render_pass_descriptors
+                        .lock()
+                        .alloc(&self.shared);
+                    if self.shared.private_caps.layered_rendering {
+                        descriptor.set_render_target_array_length(framebuffer.extent.depth as _);
                     }
-                }
 
-                if let Some(ref at) = subpass.attachments.depth_stencil {
-                    let rat = &render_pass.attachments[at.id];
-                    let &(ref texture, ref clear_value) = &self.temp.render_attachments[at.id];
-                    let aspects = rat.format.unwrap().surface_desc().aspects;
-                    combined_aspects |= aspects;
+                    for (i, at) in subpass.attachments.colors.iter().enumerate() {
+                        let rat = &render_pass.attachments[at.id];
+                        let &(ref texture, ref clear_value) = &self.temp.render_attachments[at.id];
+                        let desc = descriptor.color_attachments().object_at(i as _).unwrap();
 
-                    if aspects.contains(Aspects::DEPTH) {
-                        let desc = descriptor.depth_attachment().unwrap();
                         desc.set_texture(Some(texture.as_ref()));
 
                         if at.ops.contains(native::AttachmentOps::LOAD) {
                             desc.set_load_action(conv::map_load_operation(rat.ops.load));
                             if rat.ops.load == AttachmentLoadOp::Clear {
-                                desc.set_clear_depth(clear_value.depth_stencil.depth as f64);
+                                desc.set_clear_color(at.channel.interpret(clear_value.color));
                             }
+                        } else {
+                            desc.set_load_action(metal::MTLLoadAction::Load);
                         }
-                        if at.ops.contains(native::AttachmentOps::STORE) {
+                        if let Some(id) = at.resolve_id {
+                            let &(ref resolve_texture, _) = &self.temp.render_attachments[id];
+                            //Note: the selection of levels and slices is already handled by `ImageView`
+                            desc.set_resolve_texture(Some(resolve_texture.as_ref()));
+                            desc.set_store_action(conv::map_resolved_store_operation(
+                                rat.ops.store,
+                            ));
+                        } else if at.ops.contains(native::AttachmentOps::STORE) {
                             desc.set_store_action(conv::map_store_operation(rat.ops.store));
+                        } else {
+                            desc.set_store_action(metal::MTLStoreAction::Store);
                         }
                     }
-                    if aspects.contains(Aspects::STENCIL) {
-                        let desc = descriptor.stencil_attachment().unwrap();
-                        desc.set_texture(Some(texture.as_ref()));
 
-                        if at.ops.contains(native::AttachmentOps::LOAD) {
-                            desc.set_load_action(conv::map_load_operation(rat.stencil_ops.load));
-                            if rat.stencil_ops.load == AttachmentLoadOp::Clear {
-                                desc.set_clear_stencil(clear_value.depth_stencil.stencil);
+                    if let Some(ref at) = subpass.attachments.depth_stencil {
+                        let rat = &render_pass.attachments[at.id];
+                        let &(ref texture, ref clear_value) = &self.temp.render_attachments[at.id];
+                        let aspects = rat.format.unwrap().surface_desc().aspects;
+
+                        if aspects.contains(Aspects::DEPTH) {
+                            let desc = descriptor.depth_attachment().unwrap();
+                            desc.set_texture(Some(texture.as_ref()));
+
+                            if at.ops.contains(native::AttachmentOps::LOAD) {
+                                desc.set_load_action(conv::map_load_operation(rat.ops.load));
+                                if rat.ops.load == AttachmentLoadOp::Clear {
+                                    desc.set_clear_depth(clear_value.depth_stencil.depth as f64);
+                                }
+                            } else {
+                                desc.set_load_action(metal::MTLLoadAction::Load);
+                            }
+                            if at.ops.contains(native::AttachmentOps::STORE) {
+                                desc.set_store_action(conv::map_store_operation(rat.ops.store));
+                            } else {
+                                desc.set_store_action(metal::MTLStoreAction::Store);
                             }
                         }
-                        if at.ops.contains(native::AttachmentOps::STORE) {
-                            desc.set_store_action(conv::map_store_operation(rat.stencil_ops.store));
+                        if aspects.contains(Aspects::STENCIL) {
+                            let desc = descriptor.stencil_attachment().unwrap();
+                            desc.set_texture(Some(texture.as_ref()));
+
+                            if at.ops.contains(native::AttachmentOps::LOAD) {
+                                desc.set_load_action(conv::map_load_operation(
+                                    rat.stencil_ops.load,
+                                ));
+                                if rat.stencil_ops.load == AttachmentLoadOp::Clear {
+                                    desc.set_clear_stencil(clear_value.depth_stencil.stencil);
+                                }
+                            } else {
+                                desc.set_load_action(metal::MTLLoadAction::Load);
+                            }
+                            if at.ops.contains(native::AttachmentOps::STORE) {
+                                desc.set_store_action(conv::map_store_operation(
+                                    rat.stencil_ops.store,
+                                ));
+                            } else {
+                                desc.set_store_action(metal::MTLStoreAction::Store);
+                            }
                         }
                     }
-                }
 
-                descriptor
-            });
+                    descriptor
+                }))
+            };
 
             self.state.pending_subpasses.alloc().init(SubpassInfo {
                 descriptor,
@@ -3656,19 +3910,58 @@ impl com::CommandBuffer<Backend> for CommandBuffer {
             .chain(com_scissor)
             .chain(com_ds);
 
-        autoreleasepool(|| {
-            self.inner
-                .borrow_mut()
-                .sink()
-                .switch_render(sin.descriptor, &self.pool_shared)
-                .issue_many(init_commands);
-        });
+        match sin.descriptor {
+            Some(descriptor) => autoreleasepool(|| {
+                self.inner
+                    .borrow_mut()
+                    .sink()
+                    .switch_render(descriptor, &self.pool_shared)
+                    .issue_many(init_commands);
+            }),
+            None => {
+                self.inner
+                    .borrow_mut()
+                    .sink()
+                    .pre_render()
+                    .issue_many(init_commands);
+            }
+        }
     }
 
     unsafe fn end_render_pass(&mut self) {
         self.inner.borrow_mut().sink().stop_encoding();
     }
 
+    unsafe fn begin_rendering(&mut self, info: &com::RenderingInfo<Backend>) {
+        // The render pass is only a CPU-side description, which gets
+        // translated into the `MTLRenderPassDescriptor` right away.
+        let render_pass = info.with_subpass(|attachments, subpass| {
+            Device::build_render_pass(
+                &self.shared.private_caps,
+                attachments.iter().cloned(),
+                iter::once(subpass),
+                iter::empty(),
+            )
+        });
+        let framebuffer = native::Framebuffer {
+            extent: info.extent(),
+        };
+        self.begin_render_pass(
+            &render_pass,
+            &framebuffer,
+            info.render_area,
+            info.attachments().map(|at| com::RenderAttachmentInfo {
+                image_view: at.image_view,
+                clear_value: at.clear_value,
+            }),
+            com::SubpassContents::Inline,
+        );
+    }
+
+    unsafe fn end_rendering(&mut self) {
+        self.end_render_pass();
+    }
+
     unsafe fn bind_graphics_pipeline(&mut self, pipeline: &native::GraphicsPipeline) {
         let mut inner = self.inner.borrow_mut();
         let mut pre = inner.sink().pre_render();
@@ -3731,14 +4024,14 @@ impl com::CommandBuffer<Backend> for CommandBuffer {
                 if let Some(pc) = pipeline.vs_pc_info {
                     if Some(pc) != self.state.resources_vs.push_constants {
                         // if we don't have enough constants, then binding will follow
-                        if pc.count as usize <= self.state.push_constants.len() {
+                        if pc.count as usize <= self.state.graphics_push_constants.len() {
                             pre.issue(self.state.push_vs_constants(pc));
                         }
                     }
                 }
                 if let Some(pc) = pipeline.ps_pc_info {
                     if Some(pc) != self.state.resources_ps.push_constants
-                        && pc.count as usize <= self.state.push_constants.len()
+                        && pc.count as usize <= self.state.graphics_push_constants.len()
                     {
                         pre.issue(self.state.push_ps_constants(pc));
                     }
@@ -3815,6 +4108,7 @@ impl com::CommandBuffer<Backend> for CommandBuffer {
         for (set_offset, (info, desc_set)) in
             pipe_layout.infos[first_set..].iter().zip(sets).enumerate()
         {
+            self.state.descriptor_sets[first_set + set_offset].graphics_bound = true;
             match *desc_set {
                 native::DescriptorSet::Emulated {
                     ref pool,
@@ -3951,7 +4245,7 @@ impl com::CommandBuffer<Backend> for CommandBuffer {
 
         if let Some(pc) = pipeline.pc_info {
             if Some(pc) != self.state.resources_cs.push_constants
-                && pc.count as usize <= self.state.push_constants.len()
+                && pc.count as usize <= self.state.compute_push_constants.len()
             {
                 pre.issue(self.state.push_cs_constants(pc));
             }
@@ -3978,6 +4272,7 @@ impl com::CommandBuffer<Backend> for CommandBuffer {
         for (set_offset, (info, desc_set)) in
             pipe_layout.infos[first_set..].iter().zip(sets).enumerate()
         {
+            self.state.descriptor_sets[first_set + set_offset].compute_bound = true;
             let res_offset = &info.offsets.cs;
             match *desc_set {
                 native::DescriptorSet::Emulated {
@@ -4070,6 +4365,11 @@ impl com::CommandBuffer<Backend> for CommandBuffer {
     }
 
     unsafe fn dispatch(&mut self, count: WorkGroupCount) {
+        if count.contains(&0) {
+            debug!("Skipping an empty dispatch");
+            return;
+        }
+        self.state.check_dispatch();
         let mut inner = self.inner.borrow_mut();
         let (mut pre, init) = inner.sink().switch_compute();
         if init {
@@ -4087,6 +4387,7 @@ impl com::CommandBuffer<Backend> for CommandBuffer {
     }
 
     unsafe fn dispatch_indirect(&mut self, buffer: &native::Buffer, offset: buffer::Offset) {
+        self.state.check_dispatch();
         let mut inner = self.inner.borrow_mut();
         let (mut pre, init) = inner.sink().switch_compute();
         if init {
@@ -4376,7 +4677,8 @@ impl com::CommandBuffer<Backend> for CommandBuffer {
 
     unsafe fn draw(&mut self, vertices: Range<VertexCount>, instances: Range<InstanceCount>) {
         debug_assert!(self.state.render_pso_is_compatible);
-        if instances.start == instances.end {
+        if vertices.start >= vertices.end || instances.start >= instances.end {
+            debug!("Skipping an empty draw");
             return;
         }
 
@@ -4395,7 +4697,8 @@ impl com::CommandBuffer<Backend> for CommandBuffer {
         instances: Range<InstanceCount>,
     ) {
         debug_assert!(self.state.render_pso_is_compatible);
-        if instances.start == instances.end {
+        if indices.start >= indices.end || instances.start >= instances.end {
+            debug!("Skipping an empty indexed draw");
             return;
         }
 
@@ -4420,6 +4723,10 @@ impl com::CommandBuffer<Backend> for CommandBuffer {
         count: DrawCount,
         stride: buffer::Stride,
     ) {
+        if count == 0 {
+            debug!("Skipping an empty indirect draw");
+            return;
+        }
         assert_eq!(offset % WORD_ALIGNMENT, 0);
         assert_eq!(stride % WORD_ALIGNMENT as u32, 0);
         debug_assert!(self.state.render_pso_is_compatible);
@@ -4445,6 +4752,10 @@ impl com::CommandBuffer<Backend> for CommandBuffer {
         count: DrawCount,
         stride: buffer::Stride,
     ) {
+        if count == 0 {
+            debug!("Skipping an empty indirect draw");
+            return;
+        }
         assert_eq!(offset % WORD_ALIGNMENT, 0);
         assert_eq!(stride % WORD_ALIGNMENT as u32, 0);
         debug_assert!(self.state.render_pso_is_compatible);
@@ -4766,8 +5077,12 @@ impl com::CommandBuffer<Backend> for CommandBuffer {
         offset: u32,
         constants: &[u32],
     ) {
-        self.state
-            .update_push_constants(offset, constants, layout.total_push_constants);
+        State::update_push_constants(
+            &mut self.state.graphics_push_constants,
+            offset,
+            constants,
+            layout.total_push_constants,
+        );
         if stages.intersects(pso::ShaderStageFlags::GRAPHICS) {
             let mut inner = self.inner.borrow_mut();
             let mut pre = inner.sink().pre_render();
@@ -4789,8 +5104,12 @@ impl com::CommandBuffer<Backend> for CommandBuffer {
         offset: u32,
         constants: &[u32],
     ) {
-        self.state
-            .update_push_constants(offset, constants, layout.total_push_constants);
+        State::update_push_constants(
+            &mut self.state.compute_push_constants,
+            offset,
+            constants,
+            layout.total_push_constants,
+        );
         let pc = layout.push_constants.cs.unwrap();
 
         // Note: the whole range is re-uploaded, which may be inefficient
@@ -4832,6 +5151,8 @@ impl com::CommandBuffer<Backend> for CommandBuffer {
                     a.compute_resources.clear();
                     a.compute_resources.extend_from_slice(&b.compute_resources);
                 }
+                a.graphics_bound |= b.graphics_bound;
+                a.compute_bound |= b.compute_bound;
             }
 
             let mut inner_self = self.inner.borrow_mut();
@@ -4878,4 +5199,8 @@ impl com::CommandBuffer<Backend> for CommandBuffer {
     unsafe fn end_debug_marker(&mut self) {
         //TODO
     }
+
+    unsafe fn set_checkpoint(&mut self, label: &str) {
+        self.inner.borrow_mut().checkpoints.push(label.to_string());
+    }
 }
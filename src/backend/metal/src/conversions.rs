@@ -27,6 +27,10 @@ impl PrivateCapabilities {
             f::D24UnormS8Uint if self.format_depth24_stencil8 => Depth24Unorm_Stencil8,
             f::D32Sfloat => Depth32Float,
             f::D32SfloatS8Uint => Depth32Float_Stencil8,
+            f::S8Uint => Stencil8,
+            // Emulated with a wider depth, see `is_emulated_format`
+            f::D16UnormS8Uint if self.format_depth24_stencil8 => Depth24Unorm_Stencil8,
+            f::D16UnormS8Uint => Depth32Float_Stencil8,
             f::R8Unorm => R8Unorm,
             f::R8Snorm => R8Snorm,
             f::R8Uint => R8Uint,
@@ -442,7 +446,7 @@ impl PrivateCapabilities {
                 If::DEPTH_STENCIL_ATTACHMENT | If::SAMPLED_LINEAR
             }
             Depth32Float if self.format_depth32float_none => If::DEPTH_STENCIL_ATTACHMENT,
-            Stencil8 => If::empty(),
+            Stencil8 => If::DEPTH_STENCIL_ATTACHMENT,
             Depth24Unorm_Stencil8 if self.format_depth24_stencil8 => If::DEPTH_STENCIL_ATTACHMENT,
             Depth32Float_Stencil8 if self.format_depth32_stencil8_filter => {
                 If::DEPTH_STENCIL_ATTACHMENT | If::SAMPLED_LINEAR
@@ -459,6 +463,15 @@ impl PrivateCapabilities {
             _ => If::empty(),
         };
 
+        // The copies would expose the layout of the wider format backing the emulated one.
+        if Self::is_emulated_format(format) {
+            return Properties {
+                linear_tiling: If::empty(),
+                optimal_tiling: If::SAMPLED | extra_optimal,
+                buffer_features: Bf::empty(),
+            };
+        }
+
         Properties {
             linear_tiling: If::TRANSFER_SRC | If::TRANSFER_DST,
             optimal_tiling: If::SAMPLED
@@ -470,6 +483,13 @@ impl PrivateCapabilities {
             buffer_features: Bf::all(),
         }
     }
+
+    /// Returns true if the format has no Metal equivalent, and is backed by a format
+    /// with more bits instead. `D16UnormS8Uint` is stored as `Depth24Unorm_Stencil8`
+    /// where it's supported, and as `Depth32Float_Stencil8` otherwise.
+    pub fn is_emulated_format(format: Format) -> bool {
+        format == Format::D16UnormS8Uint
+    }
 }
 
 pub fn map_load_operation(operation: pass::AttachmentLoadOp) -> MTLLoadAction {
@@ -572,6 +592,7 @@ pub fn map_blend_op(
             MTLBlendFactor::Zero,
             MTLBlendFactor::Zero,
         ),
+        Advanced(op) => panic!("Advanced blend operation {:?} is not supported", op),
     }
 }
 
@@ -2,16 +2,17 @@
 use crate::internal::FastStorageMap;
 use crate::{
     command, conversions as conv, internal::Channel, native as n, AsNative, Backend, FastHashMap,
-    OnlineRecording, QueueFamily, ResourceIndex, Shared, VisibilityShared,
+    OnlineRecording, PrivateCapabilities, QueueFamily, ResourceIndex, Shared, VisibilityShared,
     MAX_BOUND_DESCRIPTOR_SETS, MAX_COLOR_ATTACHMENTS,
 };
 
 use arrayvec::ArrayVec;
+use block::ConcreteBlock;
 use cocoa_foundation::foundation::NSUInteger;
 use copyless::VecHelper;
 use foreign_types::{ForeignType, ForeignTypeRef};
 use hal::{
-    adapter, buffer, device as d, format, image, memory,
+    adapter, buffer, command as com, device as d, format, image, memory,
     memory::Properties,
     pass,
     pool::CommandPoolCreateFlags,
@@ -22,8 +23,8 @@ use hal::{
 };
 use metal::{
     CaptureManager, MTLCPUCacheMode, MTLLanguageVersion, MTLPrimitiveTopologyClass,
-    MTLPrimitiveType, MTLResourceOptions, MTLSamplerMipFilter, MTLStorageMode, MTLTextureType,
-    MTLVertexStepFunction, NSRange,
+    MTLPrimitiveType, MTLRegion, MTLResourceOptions, MTLSamplerMipFilter, MTLStorageMode,
+    MTLTextureType, MTLVertexStepFunction, NSRange,
 };
 use objc::{
     rc::autoreleasepool,
@@ -32,10 +33,19 @@ use objc::{
 use parking_lot::Mutex;
 
 #[cfg(feature = "cross")]
-use std::collections::{hash_map::Entry, BTreeMap};
 use std::{
-    cmp, iter, mem,
-    ops::Range,
+    collections::{
+        hash_map::{DefaultHasher, Entry},
+        BTreeMap,
+    },
+    hash::{Hash, Hasher},
+};
+use std::{
+    cmp,
+    ffi::CStr,
+    iter, mem,
+    ops::{Deref, Range},
+    os::raw::{c_char, c_void},
     ptr,
     sync::{
         atomic::{AtomicBool, Ordering},
@@ -46,6 +56,25 @@ use std::{
 
 const STRIDE_GRANULARITY: pso::ElemStride = 4; //TODO: work around?
 const SHADER_STAGE_COUNT: usize = 3;
+/// `MTLHeapTypePlacement`, the resources are placed at the offsets given by the user.
+const MTL_HEAP_TYPE_PLACEMENT: NSUInteger = 1;
+/// `MTLPipelineOptionNone`, no reflection is requested.
+const MTL_PIPELINE_OPTION_NONE: NSUInteger = 0;
+
+#[cfg(feature = "cross")]
+fn hash_spirv(raw_data: &[u32]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    raw_data.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(feature = "cross")]
+fn hash_specialization(specialization: &pso::Specialization) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    specialization.constants.hash(&mut hasher);
+    specialization.data.hash(&mut hasher);
+    hasher.finish()
+}
 
 #[derive(Clone, Debug)]
 enum FunctionError {
@@ -136,12 +165,18 @@ impl VisibilityShared {
 pub struct Device {
     pub(crate) shared: Arc<Shared>,
     invalidation_queue: command::QueueInner,
+    memory_kinds: Vec<MemoryTypes>,
     memory_types: Vec<adapter::MemoryType>,
     features: hal::Features,
     pub online_recording: OnlineRecording,
+    /// Translate the shader modules created after this is set with Naga first,
+    /// falling back to SPIRV-Cross (if enabled) when Naga fails to parse, validate
+    /// or translate a module. Defaults to the "prefer-naga" feature.
     pub always_prefer_naga: bool,
     #[cfg(feature = "cross")]
     spv_options: naga::back::spv::Options,
+    #[cfg(feature = "cross")]
+    shader_cache: FastStorageMap<n::ShaderCacheKey, n::ModuleInfo>,
 }
 unsafe impl Send for Device {}
 unsafe impl Sync for Device {}
@@ -160,7 +195,10 @@ impl Drop for Device {
 }
 
 bitflags! {
-    /// Memory type bits.
+    /// Memory type kinds.
+    ///
+    /// The exposed memory types are a subset of these, see `PhysicalDevice::memory_kinds`
+    /// for the kind of each memory type index.
     struct MemoryTypes: u32 {
         // = `DEVICE_LOCAL`
         const PRIVATE = 1<<0;
@@ -172,24 +210,66 @@ bitflags! {
         // Memory range invalidation is implemented to stall the whole pipeline.
         // It's inefficient, therefore we aren't going to expose this type.
         //const MANAGED_DOWNLOAD = 1<<3;
+        // = `DEVICE_LOCAL | LAZILY_ALLOCATED`
+        // Only usable by the transient attachments, on the tile-based GPUs.
+        const MEMORYLESS = 1<<4;
     }
 }
 
 impl MemoryTypes {
-    fn describe(index: usize) -> (MTLStorageMode, MTLCPUCacheMode) {
-        match Self::from_bits(1 << index).unwrap() {
+    fn describe(self) -> (MTLStorageMode, MTLCPUCacheMode) {
+        match self {
             Self::PRIVATE => (MTLStorageMode::Private, MTLCPUCacheMode::DefaultCache),
             Self::SHARED => (MTLStorageMode::Shared, MTLCPUCacheMode::DefaultCache),
             Self::MANAGED_UPLOAD => (MTLStorageMode::Managed, MTLCPUCacheMode::WriteCombined),
             //Self::MANAGED_DOWNLOAD => (MTLStorageMode::Managed, MTLCPUCacheMode::DefaultCache),
+            Self::MEMORYLESS => (MTLStorageMode::Memoryless, MTLCPUCacheMode::DefaultCache),
+            _ => unreachable!(),
+        }
+    }
+
+    fn memory_type(self, unified_memory: bool) -> adapter::MemoryType {
+        let mut memory_type = match self {
+            Self::PRIVATE => adapter::MemoryType {
+                properties: Properties::DEVICE_LOCAL,
+                heap_index: 0,
+            },
+            Self::SHARED => adapter::MemoryType {
+                properties: Properties::CPU_VISIBLE | Properties::COHERENT,
+                heap_index: 1,
+            },
+            Self::MANAGED_UPLOAD => adapter::MemoryType {
+                properties: Properties::DEVICE_LOCAL | Properties::CPU_VISIBLE,
+                heap_index: 1,
+            },
+            Self::MEMORYLESS => adapter::MemoryType {
+                properties: Properties::DEVICE_LOCAL | Properties::LAZILY_ALLOCATED,
+                heap_index: 0,
+            },
             _ => unreachable!(),
+        };
+        // All the memory is local to the device with the unified memory architecture
+        if unified_memory {
+            memory_type.properties |= Properties::DEVICE_LOCAL;
+            memory_type.heap_index = 0;
         }
+        memory_type
     }
 }
 
+/// Mask of the memory type indices of the given kinds.
+fn memory_type_mask(memory_kinds: &[MemoryTypes], types: MemoryTypes) -> u32 {
+    memory_kinds
+        .iter()
+        .enumerate()
+        .filter(|&(_, &kind)| types.contains(kind))
+        .fold(0, |mask, (index, _)| mask | 1 << index)
+}
+
 #[derive(Debug)]
 pub struct PhysicalDevice {
     pub(crate) shared: Arc<Shared>,
+    memory_kinds: Vec<MemoryTypes>,
     memory_types: Vec<adapter::MemoryType>,
 }
 unsafe impl Send for PhysicalDevice {}
@@ -197,41 +277,20 @@ unsafe impl Sync for PhysicalDevice {}
 
 impl PhysicalDevice {
     pub(crate) fn new(shared: Arc<Shared>) -> Self {
-        let memory_types = if shared.private_caps.os_is_mac {
-            vec![
-                adapter::MemoryType {
-                    // PRIVATE
-                    properties: Properties::DEVICE_LOCAL,
-                    heap_index: 0,
-                },
-                adapter::MemoryType {
-                    // SHARED
-                    properties: Properties::CPU_VISIBLE | Properties::COHERENT,
-                    heap_index: 1,
-                },
-                adapter::MemoryType {
-                    // MANAGED_UPLOAD
-                    properties: Properties::DEVICE_LOCAL | Properties::CPU_VISIBLE,
-                    heap_index: 1,
-                },
-                // MANAGED_DOWNLOAD (removed)
-            ]
-        } else {
-            vec![
-                adapter::MemoryType {
-                    // PRIVATE
-                    properties: Properties::DEVICE_LOCAL,
-                    heap_index: 0,
-                },
-                adapter::MemoryType {
-                    // SHARED
-                    properties: Properties::CPU_VISIBLE | Properties::COHERENT,
-                    heap_index: 1,
-                },
-            ]
-        };
+        let mut memory_kinds = vec![MemoryTypes::PRIVATE, MemoryTypes::SHARED];
+        if shared.private_caps.os_is_mac {
+            memory_kinds.push(MemoryTypes::MANAGED_UPLOAD);
+        }
+        if shared.private_caps.memoryless_storage {
+            memory_kinds.push(MemoryTypes::MEMORYLESS);
+        }
+        let memory_types = memory_kinds
+            .iter()
+            .map(|&kind| kind.memory_type(shared.private_caps.unified_memory))
+            .collect();
         PhysicalDevice {
             shared: shared.clone(),
+            memory_kinds,
             memory_types,
         }
     }
@@ -314,12 +373,15 @@ impl adapter::PhysicalDevice<Backend> for PhysicalDevice {
         let device = Device {
             shared: self.shared.clone(),
             invalidation_queue: command::QueueInner::new(&*device, Some(1)),
+            memory_kinds: self.memory_kinds.clone(),
             memory_types: self.memory_types.clone(),
             features: requested_features,
             online_recording: OnlineRecording::default(),
-            always_prefer_naga: false,
+            always_prefer_naga: cfg!(feature = "prefer-naga"),
             #[cfg(feature = "cross")]
             spv_options,
+            #[cfg(feature = "cross")]
+            shader_cache: FastStorageMap::default(),
         };
 
         Ok(adapter::Gpu {
@@ -394,7 +456,12 @@ impl adapter::PhysicalDevice<Backend> for PhysicalDevice {
                 } else {
                     self.shared.private_caps.max_texture_layers as _
                 },
-                sample_count_mask: self.shared.private_caps.sample_count_mask as _,
+                // Multisampled textures can't be written from the shaders
+                sample_count_mask: if usage.contains(image::Usage::STORAGE) {
+                    0x1
+                } else {
+                    self.shared.private_caps.sample_count_mask as _
+                },
                 //TODO: buffers and textures have separate limits
                 // Max buffer size is determined by feature set
                 // Max texture size does not appear to be documented publicly
@@ -402,18 +469,46 @@ impl adapter::PhysicalDevice<Backend> for PhysicalDevice {
             })
     }
 
+    fn cooperative_matrix_properties(&self) -> Vec<adapter::CooperativeMatrixProperties> {
+        use hal::adapter::CooperativeMatrixComponentType as Ct;
+        if !self.shared.private_caps.simdgroup_matrix {
+            return Vec::new();
+        }
+        // `simdgroup_half8x8` and `simdgroup_float8x8`, with `simdgroup_multiply_accumulate`
+        // accepting mixed precision operands.
+        [
+            (Ct::Float16, Ct::Float16),
+            (Ct::Float16, Ct::Float32),
+            (Ct::Float32, Ct::Float32),
+        ]
+        .iter()
+        .map(|&(ab_type, c_type)| adapter::CooperativeMatrixProperties {
+            m_size: 8,
+            n_size: 8,
+            k_size: 8,
+            a_type: ab_type,
+            b_type: ab_type,
+            c_type,
+            result_type: c_type,
+            saturating_accumulation: false,
+        })
+        .collect()
+    }
+
     fn memory_properties(&self) -> adapter::MemoryProperties {
+        let pc = &self.shared.private_caps;
+        let mut memory_heaps = vec![adapter::MemoryHeap {
+            size: pc.working_set_size,
+            flags: memory::HeapFlags::DEVICE_LOCAL,
+        }];
+        if !pc.unified_memory {
+            memory_heaps.push(adapter::MemoryHeap {
+                size: pc.max_buffer_size,
+                flags: memory::HeapFlags::empty(),
+            });
+        }
         adapter::MemoryProperties {
-            memory_heaps: vec![
-                adapter::MemoryHeap {
-                    size: !0, //TODO: private memory limits
-                    flags: memory::HeapFlags::DEVICE_LOCAL,
-                },
-                adapter::MemoryHeap {
-                    size: self.shared.private_caps.max_buffer_size,
-                    flags: memory::HeapFlags::empty(),
-                },
-            ],
+            memory_heaps,
             memory_types: self.memory_types.to_vec(),
         }
     }
@@ -434,7 +529,10 @@ impl adapter::PhysicalDevice<Backend> for PhysicalDevice {
             | F::SEPARATE_STENCIL_REF_VALUES
             | F::SHADER_CLIP_DISTANCE
             | F::MUTABLE_UNNORMALIZED_SAMPLER
-            | F::NDC_Y_UP;
+            | F::NDC_Y_UP
+            | F::DYNAMIC_RENDERING
+            | F::HOST_IMAGE_COPY
+            | F::NULL_DESCRIPTOR;
 
         features.set(
             F::IMAGE_CUBE_ARRAY,
@@ -462,6 +560,31 @@ impl adapter::PhysicalDevice<Backend> for PhysicalDevice {
             F::MUTABLE_COMPARISON_SAMPLER,
             self.shared.private_caps.mutable_comparison_samplers,
         );
+        features.set(F::PRESENT_TIMING, self.shared.private_caps.present_timing);
+        features.set(
+            F::PRESENT_MODE_SWITCH,
+            self.shared.private_caps.os_is_mac
+                && self.shared.private_caps.has_version_at_least(10, 13),
+        );
+        features.set(
+            F::HDR_METADATA,
+            self.shared.private_caps.os_is_mac
+                && self.shared.private_caps.has_version_at_least(10, 15),
+        );
+        features.set(
+            F::COOPERATIVE_MATRIX,
+            self.shared.private_caps.simdgroup_matrix,
+        );
+        // Only SPIRV-Cross sizes the unsized arrays in the argument buffers
+        features.set(
+            F::UNSIZED_DESCRIPTOR_ARRAY,
+            cfg!(feature = "cross") && self.shared.private_caps.argument_buffers_tier2,
+        );
+        // Only SPIRV-Cross translates the interlock into the raster order groups
+        features.set(
+            F::FRAGMENT_SHADER_INTERLOCK,
+            cfg!(feature = "cross") && self.shared.private_caps.raster_order_groups,
+        );
 
         //TODO: F::DEPTH_BOUNDS
         //TODO: F::SAMPLER_MIRROR_CLAMP_EDGE
@@ -482,6 +605,17 @@ impl adapter::PhysicalDevice<Backend> for PhysicalDevice {
     fn limits(&self) -> hal::Limits {
         let pc = &self.shared.private_caps;
         let device = self.shared.device.lock();
+        // Tier 2 argument buffers lift the limits of the resources per stage,
+        // according to the Metal feature set tables
+        let (max_buffers, max_textures, max_samplers) = if pc.argument_buffers_tier2 {
+            (500_000, 500_000, 1024)
+        } else {
+            (
+                pc.max_buffers_per_stage,
+                pc.max_textures_per_stage,
+                pc.max_samplers_per_stage,
+            )
+        };
         hal::Limits {
             max_image_1d_size: pc.max_texture_size as _,
             max_image_2d_size: pc.max_texture_size as _,
@@ -491,40 +625,31 @@ impl adapter::PhysicalDevice<Backend> for PhysicalDevice {
             max_texel_elements: (pc.max_texture_size * pc.max_texture_size) as usize,
             max_uniform_buffer_range: pc.max_buffer_size,
             max_storage_buffer_range: pc.max_buffer_size,
-            // "Maximum length of an inlined constant data buffer, per graphics or compute function"
-            max_push_constants_size: 0x1000,
+            // Inlined constant data buffers take up to 4 KB, but the size is
+            // capped to the one of the other backends.
+            max_push_constants_size: 256,
             max_sampler_allocation_count: !0,
             max_bound_descriptor_sets: MAX_BOUND_DESCRIPTOR_SETS as _,
-            max_descriptor_set_samplers: pc.max_samplers_per_stage as usize * SHADER_STAGE_COUNT,
-            max_descriptor_set_uniform_buffers: pc.max_buffers_per_stage as usize
-                * SHADER_STAGE_COUNT,
+            max_descriptor_set_samplers: max_samplers as usize * SHADER_STAGE_COUNT,
+            max_descriptor_set_uniform_buffers: max_buffers as usize * SHADER_STAGE_COUNT,
             max_descriptor_set_uniform_buffers_dynamic: 8 * SHADER_STAGE_COUNT,
-            max_descriptor_set_storage_buffers: pc.max_buffers_per_stage as usize
-                * SHADER_STAGE_COUNT,
+            max_descriptor_set_storage_buffers: max_buffers as usize * SHADER_STAGE_COUNT,
             max_descriptor_set_storage_buffers_dynamic: 4 * SHADER_STAGE_COUNT,
-            max_descriptor_set_sampled_images: pc
-                .max_textures_per_stage
-                .min(pc.max_samplers_per_stage)
-                as usize
-                * SHADER_STAGE_COUNT,
-            max_descriptor_set_storage_images: pc.max_textures_per_stage as usize
-                * SHADER_STAGE_COUNT,
-            max_descriptor_set_input_attachments: pc.max_textures_per_stage as usize
+            max_descriptor_set_sampled_images: max_textures.min(max_samplers) as usize
                 * SHADER_STAGE_COUNT,
+            max_descriptor_set_storage_images: max_textures as usize * SHADER_STAGE_COUNT,
+            max_descriptor_set_input_attachments: max_textures as usize * SHADER_STAGE_COUNT,
             max_fragment_input_components: pc.max_fragment_input_components as usize,
             max_framebuffer_layers: 2048, // TODO: Determine is this is the correct value
             max_memory_allocation_count: 4096, // TODO: Determine is this is the correct value
 
-            max_per_stage_descriptor_samplers: pc.max_samplers_per_stage as usize,
-            max_per_stage_descriptor_uniform_buffers: pc.max_buffers_per_stage as usize,
-            max_per_stage_descriptor_storage_buffers: pc.max_buffers_per_stage as usize,
-            max_per_stage_descriptor_sampled_images: pc
-                .max_textures_per_stage
-                .min(pc.max_samplers_per_stage)
-                as usize,
-            max_per_stage_descriptor_storage_images: pc.max_textures_per_stage as usize,
-            max_per_stage_descriptor_input_attachments: pc.max_textures_per_stage as usize, //TODO
-            max_per_stage_resources: 0x100,                                                 //TODO
+            max_per_stage_descriptor_samplers: max_samplers as usize,
+            max_per_stage_descriptor_uniform_buffers: max_buffers as usize,
+            max_per_stage_descriptor_storage_buffers: max_buffers as usize,
+            max_per_stage_descriptor_sampled_images: max_textures.min(max_samplers) as usize,
+            max_per_stage_descriptor_storage_images: max_textures as usize,
+            max_per_stage_descriptor_input_attachments: max_textures as usize, //TODO
+            max_per_stage_resources: 0x100,                                    //TODO
 
             max_patch_size: 0, // No tessellation
 
@@ -562,12 +687,17 @@ impl adapter::PhysicalDevice<Backend> for PhysicalDevice {
             framebuffer_color_sample_counts: 0b101,   // TODO
             framebuffer_depth_sample_counts: 0b101,   // TODO
             framebuffer_stencil_sample_counts: 0b101, // TODO
+            // Multisampled textures can't be written from the shaders
+            storage_image_sample_counts: 0x1,
             max_color_attachments: pc.max_color_render_targets as usize,
 
             buffer_image_granularity: 1,
             // Note: we issue Metal buffer-to-buffer copies on memory flush/invalidate,
             // and those need to operate on sizes being multiples of 4.
             non_coherent_atom_size: 4,
+            device_local_host_visible_heap_size: auxil::device_local_host_visible_heap_size(
+                &self.memory_properties(),
+            ),
             max_sampler_anisotropy: 16.,
             min_vertex_input_binding_stride_alignment: STRIDE_GRANULARITY as u64,
 
@@ -587,10 +717,336 @@ impl LanguageVersion {
     }
 }
 
+/// Graphics pipeline missing only its render pipeline state.
+struct GraphicsPipelineParts {
+    vs_lib: metal::Library,
+    fs_lib: Option<metal::Library>,
+    primitive_type: MTLPrimitiveType,
+    vs_pc_info: Option<n::PushConstantInfo>,
+    ps_pc_info: Option<n::PushConstantInfo>,
+    rasterizer_state: Option<n::RasterizerState>,
+    depth_bias: pso::State<pso::DepthBias>,
+    depth_stencil_desc: pso::DepthStencilDesc,
+    baked_states: pso::BakedStates,
+    vertex_buffers: n::VertexBufferVec,
+    attachment_formats: n::SubpassFormats,
+    samples: image::NumSamples,
+}
+
+unsafe impl Send for GraphicsPipelineParts {}
+
+impl GraphicsPipelineParts {
+    fn finish(self, raw: metal::RenderPipelineState) -> n::GraphicsPipeline {
+        n::GraphicsPipeline {
+            vs_lib: self.vs_lib,
+            fs_lib: self.fs_lib,
+            raw,
+            primitive_type: self.primitive_type,
+            vs_pc_info: self.vs_pc_info,
+            ps_pc_info: self.ps_pc_info,
+            rasterizer_state: self.rasterizer_state,
+            depth_bias: self.depth_bias,
+            depth_stencil_desc: self.depth_stencil_desc,
+            baked_states: self.baked_states,
+            vertex_buffers: self.vertex_buffers,
+            attachment_formats: self.attachment_formats,
+            samples: self.samples,
+        }
+    }
+}
+
+/// Compute pipeline missing only its compute pipeline state.
+struct ComputePipelineParts {
+    cs_lib: metal::Library,
+    work_group_size: metal::MTLSize,
+    pc_info: Option<n::PushConstantInfo>,
+}
+
+unsafe impl Send for ComputePipelineParts {}
+
+impl ComputePipelineParts {
+    fn finish(self, raw: metal::ComputePipelineState) -> n::ComputePipeline {
+        n::ComputePipeline {
+            cs_lib: self.cs_lib,
+            raw,
+            work_group_size: self.work_group_size,
+            pc_info: self.pc_info,
+        }
+    }
+}
+
+/// Read the description of an `NSError`.
+unsafe fn error_description(error: *mut Object) -> String {
+    if error.is_null() {
+        return String::from("unknown error");
+    }
+    let string: *mut Object = msg_send![error, localizedDescription];
+    let utf8: *const c_char = msg_send![string, UTF8String];
+    CStr::from_ptr(utf8).to_string_lossy().into_owned()
+}
+
 impl Device {
+    fn host_transfer_storage_mode(&self) -> MTLStorageMode {
+        if self.shared.private_caps.shared_textures {
+            MTLStorageMode::Shared
+        } else {
+            MTLStorageMode::Managed
+        }
+    }
+
+    /// Places a buffer into the heap at the given offset.
+    fn placed_buffer(
+        heap: &metal::HeapRef,
+        size: u64,
+        options: MTLResourceOptions,
+        offset: u64,
+    ) -> Option<metal::Buffer> {
+        let raw: *mut metal::MTLBuffer = unsafe {
+            msg_send![heap,
+                newBufferWithLength: size as NSUInteger
+                options: options
+                offset: offset as NSUInteger
+            ]
+        };
+        if raw.is_null() {
+            None
+        } else {
+            Some(unsafe { metal::Buffer::from_ptr(raw) })
+        }
+    }
+
+    /// Places a texture into the heap at the given offset.
+    fn placed_texture(
+        heap: &metal::HeapRef,
+        descriptor: &metal::TextureDescriptorRef,
+        offset: u64,
+    ) -> Option<metal::Texture> {
+        let raw: *mut metal::MTLTexture = unsafe {
+            msg_send![heap,
+                newTextureWithDescriptor: descriptor
+                offset: offset as NSUInteger
+            ]
+        };
+        if raw.is_null() {
+            None
+        } else {
+            Some(unsafe { metal::Texture::from_ptr(raw) })
+        }
+    }
+
+    fn host_transfer_texture(image: &n::Image) -> Result<&metal::TextureRef, d::MapError> {
+        match image.like {
+            n::ImageLike::Texture(ref texture) => match texture.storage_mode() {
+                MTLStorageMode::Shared | MTLStorageMode::Managed => Ok(texture),
+                _ => Err(d::MapError::Access),
+            },
+            n::ImageLike::Unbound { .. } | n::ImageLike::Buffer(..) => Err(d::MapError::Access),
+        }
+    }
+
+    /// Returns the Metal region of a host copy, together with the row and layer pitches
+    /// of the host memory, making sure that all of the layers fit into `data_len` bytes.
+    fn host_copy_region(
+        image: &n::Image,
+        region: &com::BufferImageCopy,
+        data_len: usize,
+    ) -> Result<(MTLRegion, usize, usize), d::MapError> {
+        let size = conv::map_extent(region.image_extent);
+        let (row_pitch, slice_pitch) = command::compute_pitches(region, image.format_desc, &size);
+        let layer_count =
+            (region.image_layers.layers.end - region.image_layers.layers.start) as usize;
+        let end = region.buffer_offset as usize + slice_pitch as usize * layer_count;
+        if end > data_len {
+            return Err(d::MapError::OutOfBounds);
+        }
+        let mtl_region = MTLRegion {
+            origin: conv::map_offset(region.image_offset),
+            size,
+        };
+        Ok((mtl_region, row_pitch as usize, slice_pitch as usize))
+    }
+
+    /// Builds a render pass, also used for the dynamic rendering of command buffers.
+    pub(crate) fn build_render_pass<'a, Ia, Is, Id>(
+        private_caps: &PrivateCapabilities,
+        attachments: Ia,
+        subpasses: Is,
+        dependencies: Id,
+    ) -> n::RenderPass
+    where
+        Ia: Iterator<Item = pass::Attachment>,
+        Is: Iterator<Item = pass::SubpassDesc<'a>>,
+        Id: Iterator<Item = pass::SubpassDependency>,
+    {
+        let attachments: Vec<pass::Attachment> = attachments.collect();
+
+        let mut subpasses: Vec<n::Subpass> = subpasses
+            .map(|sub| {
+                let mut colors: ArrayVec<[_; MAX_COLOR_ATTACHMENTS]> = sub
+                    .colors
+                    .iter()
+                    .map(|&(id, _)| {
+                        let hal_format = attachments[id].format.expect("No format!");
+                        n::AttachmentInfo {
+                            id,
+                            resolve_id: None,
+                            ops: n::AttachmentOps::empty(),
+                            format: private_caps
+                                .map_format(hal_format)
+                                .expect("Unable to map color format!"),
+                            channel: Channel::from(hal_format.base_format().1),
+                        }
+                    })
+                    .collect();
+                for (color, &(resolve_id, _)) in colors.iter_mut().zip(sub.resolves.iter()) {
+                    if resolve_id != pass::ATTACHMENT_UNUSED {
+                        color.resolve_id = Some(resolve_id);
+                    }
+                }
+                let depth_stencil = sub.depth_stencil.map(|&(id, _)| {
+                    let hal_format = attachments[id].format.expect("No format!");
+                    n::AttachmentInfo {
+                        id,
+                        resolve_id: None,
+                        ops: n::AttachmentOps::empty(),
+                        format: private_caps
+                            .map_format(hal_format)
+                            .expect("Unable to map depth-stencil format!"),
+                        channel: Channel::Float,
+                    }
+                });
+
+                let samples = colors
+                    .iter()
+                    .chain(depth_stencil.as_ref())
+                    .map(|at_info| attachments[at_info.id].samples)
+                    .max()
+                    .unwrap_or(1);
+
+                n::Subpass {
+                    attachments: n::SubpassData {
+                        colors,
+                        depth_stencil,
+                    },
+                    inputs: sub.inputs.iter().map(|&(id, _)| id).collect(),
+                    samples,
+                    merged: false,
+                }
+            })
+            .collect();
+
+        // find the subpasses that depend on the earlier ones through anything
+        // other than the attachment accesses, which are ordered within an encoder
+        let attachment_stages = pso::PipelineStage::EARLY_FRAGMENT_TESTS
+            | pso::PipelineStage::LATE_FRAGMENT_TESTS
+            | pso::PipelineStage::COLOR_ATTACHMENT_OUTPUT;
+        let attachment_accesses = image::Access::COLOR_ATTACHMENT_READ
+            | image::Access::COLOR_ATTACHMENT_WRITE
+            | image::Access::DEPTH_STENCIL_ATTACHMENT_READ
+            | image::Access::DEPTH_STENCIL_ATTACHMENT_WRITE;
+        let mut needs_barrier = vec![false; subpasses.len()];
+        for dep in dependencies {
+            if let (Some(_), Some(dst)) = (dep.passes.start, dep.passes.end) {
+                if !attachment_stages.contains(dep.stages.start | dep.stages.end)
+                    || !attachment_accesses.contains(dep.accesses.start | dep.accesses.end)
+                {
+                    needs_barrier[dst as usize] = true;
+                }
+            }
+        }
+
+        // merge the subpasses rendering into the same attachments into a single encoder
+        //TODO: also merge the subpasses reading the previous ones through input attachments,
+        // once the shader translation can map them to the framebuffer fetch
+        for index in 1..subpasses.len() {
+            let (head, tail) = subpasses.split_at_mut(index);
+            let prev = head.last().unwrap();
+            let sub = &mut tail[0];
+            sub.merged = !needs_barrier[index]
+                && sub.inputs.is_empty()
+                && sub.samples == prev.samples
+                && prev
+                    .attachments
+                    .colors
+                    .iter()
+                    .chain(&sub.attachments.colors)
+                    .all(|at| at.resolve_id.is_none())
+                && prev
+                    .attachments
+                    .colors
+                    .iter()
+                    .map(|at| at.id)
+                    .eq(sub.attachments.colors.iter().map(|at| at.id))
+                && prev.attachments.depth_stencil.as_ref().map(|at| at.id)
+                    == sub.attachments.depth_stencil.as_ref().map(|at| at.id);
+        }
+
+        // sprinkle load operations
+        // an attachment receives LOAD flag on a subpass if it's the first sub-pass that uses it
+        let mut use_mask = 0u64;
+        for sub in subpasses.iter_mut() {
+            for at in sub.attachments.colors.iter_mut() {
+                if use_mask & 1 << at.id == 0 {
+                    at.ops |= n::AttachmentOps::LOAD;
+                    use_mask ^= 1 << at.id;
+                }
+            }
+            if let Some(ref mut at) = sub.attachments.depth_stencil {
+                if use_mask & 1 << at.id == 0 {
+                    at.ops |= n::AttachmentOps::LOAD;
+                    use_mask ^= 1 << at.id;
+                }
+            }
+        }
+        // sprinkle store operations
+        // an attachment receives STORE flag on a subpass if it's the last sub-pass that uses it
+        for sub in subpasses.iter_mut().rev() {
+            for at in sub.attachments.colors.iter_mut() {
+                if use_mask & 1 << at.id != 0 {
+                    at.ops |= n::AttachmentOps::STORE;
+                    use_mask ^= 1 << at.id;
+                }
+            }
+            if let Some(ref mut at) = sub.attachments.depth_stencil {
+                if use_mask & 1 << at.id != 0 {
+                    at.ops |= n::AttachmentOps::STORE;
+                    use_mask ^= 1 << at.id;
+                }
+            }
+        }
+        // merged subpasses get their attachments stored by the encoder of the first one
+        for index in (1..subpasses.len()).rev() {
+            let (head, tail) = subpasses.split_at_mut(index);
+            let sub = &tail[0];
+            if sub.merged {
+                let prev = head.last_mut().unwrap();
+                for (at, sub_at) in prev
+                    .attachments
+                    .colors
+                    .iter_mut()
+                    .zip(&sub.attachments.colors)
+                {
+                    at.ops |= sub_at.ops;
+                }
+                if let (Some(at), Some(sub_at)) = (
+                    prev.attachments.depth_stencil.as_mut(),
+                    sub.attachments.depth_stencil.as_ref(),
+                ) {
+                    at.ops |= sub_at.ops;
+                }
+            }
+        }
+
+        n::RenderPass {
+            attachments,
+            subpasses,
+            name: String::new(),
+        }
+    }
+
     fn _is_heap_coherent(&self, heap: &n::MemoryHeap) -> bool {
         match *heap {
-            n::MemoryHeap::Private => false,
+            n::MemoryHeap::Private | n::MemoryHeap::Memoryless => false,
             n::MemoryHeap::Public(memory_type, _) => self.memory_types[memory_type.0]
                 .properties
                 .contains(Properties::COHERENT),
@@ -681,6 +1137,85 @@ impl Device {
         })
     }
 
+    /// Compile the shader library with SPIRV-Cross, reusing the one compiled before
+    /// for the same module, specialization and options.
+    #[cfg(feature = "cross")]
+    fn compile_shader_library_cached(
+        &self,
+        ep: &pso::EntryPoint<Backend>,
+        compiler_options: &spirv_cross::msl::CompilerOptions,
+        stage: naga::ShaderStage,
+    ) -> Result<n::ModuleInfo, String> {
+        let key = n::ShaderCacheKey {
+            spv_hash: ep.module.spv_hash,
+            specialization_hash: hash_specialization(&ep.specialization),
+            options: compiler_options.clone(),
+        };
+        let mut error = None;
+        let info = self.shader_cache.prepare_maybe(&key, || {
+            match Self::compile_shader_library_cross(
+                &self.shared.device,
+                &ep.module.spv,
+                compiler_options,
+                self.shared.private_caps.msl_version,
+                &ep.specialization,
+                stage,
+            ) {
+                Ok(info) => Some(info),
+                Err(e) => {
+                    error = Some(e);
+                    None
+                }
+            }
+        });
+        match info {
+            Some(info) => Ok((*info).clone()),
+            None => Err(error.unwrap_or_default()),
+        }
+    }
+
+    /// Compile the shader of an entry point ahead of the pipeline creation,
+    /// so that the pipelines using it with the same layout and specialization
+    /// don't block on the shader translation and library compilation.
+    ///
+    /// `stage` is a single stage among vertex, fragment and compute. `primitive` is
+    /// the topology of the graphics pipelines, and is ignored for compute shaders.
+    pub fn prewarm_shader_cache(
+        &self,
+        ep: &pso::EntryPoint<Backend>,
+        layout: &n::PipelineLayout,
+        stage: pso::ShaderStageFlags,
+        primitive: pso::Primitive,
+    ) -> Result<(), pso::CreationError> {
+        let stage = if stage == pso::ShaderStageFlags::VERTEX {
+            naga::ShaderStage::Vertex
+        } else if stage == pso::ShaderStageFlags::FRAGMENT {
+            naga::ShaderStage::Fragment
+        } else if stage == pso::ShaderStageFlags::COMPUTE {
+            naga::ShaderStage::Compute
+        } else {
+            return Err(pso::CreationError::UnsupportedPipeline);
+        };
+        let primitive_class = match (stage, primitive) {
+            (naga::ShaderStage::Compute, _) => MTLPrimitiveTopologyClass::Unspecified,
+            (_, pso::Primitive::PointList) => MTLPrimitiveTopologyClass::Point,
+            (_, pso::Primitive::LineList) | (_, pso::Primitive::LineStrip) => {
+                MTLPrimitiveTopologyClass::Line
+            }
+            (_, pso::Primitive::TriangleList) | (_, pso::Primitive::TriangleStrip) => {
+                MTLPrimitiveTopologyClass::Triangle
+            }
+            (_, pso::Primitive::PatchList(_)) => MTLPrimitiveTopologyClass::Unspecified,
+        };
+        self.load_shader(ep, layout, primitive_class, None, stage).map(|_| ())
+    }
+
+    /// Drop all the shader libraries cached by the device.
+    pub fn clear_shader_cache(&self) {
+        #[cfg(feature = "cross")]
+        self.shader_cache.whole_write().clear();
+    }
+
     fn compile_shader_library_naga(
         device: &Mutex<metal::Device>,
         shader: &d::NagaShader,
@@ -758,8 +1293,10 @@ impl Device {
         let _ = primitive_class;
 
         let info = match pipeline_cache {
+            // The cache only holds SPIRV-Cross translations, so it's bypassed
+            // for the modules preferring Naga.
             #[cfg(feature = "cross")]
-            Some(cache) => {
+            Some(cache) if !ep.module.prefer_naga => {
                 module_map = cache
                     .modules
                     .get_or_create_with(&compiler_options, FastStorageMap::default);
@@ -785,19 +1322,15 @@ impl Device {
                         if let Err(d::ShaderError::CompilationFailed(ref msg)) = result {
                             warn!("Naga: {:?}", msg);
                         }
+                    } else {
+                        warn!("Naga is unable to load the module, falling back");
                     }
                 }
                 #[cfg(feature = "cross")]
                 if result.is_err() {
-                    result = Self::compile_shader_library_cross(
-                        device,
-                        &ep.module.spv,
-                        &compiler_options,
-                        self.shared.private_caps.msl_version,
-                        &ep.specialization,
-                        stage,
-                    )
-                    .map_err(d::ShaderError::CompilationFailed);
+                    result = self
+                        .compile_shader_library_cached(ep, &compiler_options, stage)
+                        .map_err(d::ShaderError::CompilationFailed);
                 }
                 if result.is_err() && !ep.module.prefer_naga {
                     if let Some(ref shader) = ep.module.naga {
@@ -986,143 +1519,400 @@ impl Device {
             bpc: 8,
         }
     }
-}
-
-impl hal::device::Device<Backend> for Device {
-    unsafe fn create_command_pool(
-        &self,
-        _family: QueueFamilyId,
-        _flags: CommandPoolCreateFlags,
-    ) -> Result<command::CommandPool, d::OutOfMemory> {
-        Ok(command::CommandPool::new(
-            &self.shared,
-            self.online_recording.clone(),
-        ))
-    }
-
-    unsafe fn destroy_command_pool(&self, mut pool: command::CommandPool) {
-        use hal::pool::CommandPool as _;
-        pool.reset(false);
-    }
 
-    unsafe fn create_render_pass<'a, Ia, Is, Id>(
+    /// Prepare the render pipeline descriptor, along with the rest of the pipeline.
+    unsafe fn prepare_graphics_pipeline<'a>(
         &self,
-        attachments: Ia,
-        subpasses: Is,
-        _dependencies: Id,
-    ) -> Result<n::RenderPass, d::OutOfMemory>
-    where
-        Ia: Iterator<Item = pass::Attachment>,
-        Is: Iterator<Item = pass::SubpassDesc<'a>>,
-    {
-        let attachments: Vec<pass::Attachment> = attachments.collect();
+        pipeline_desc: &pso::GraphicsPipelineDesc<'a, Backend>,
+        cache: Option<&n::PipelineCache>,
+    ) -> Result<(metal::RenderPipelineDescriptor, GraphicsPipelineParts), pso::CreationError> {
+        let pipeline = metal::RenderPipelineDescriptor::new();
+        let pipeline_layout = &pipeline_desc.layout;
+        let (rp_attachments, subpass) = {
+            let pass::Subpass { main_pass, index } = pipeline_desc.subpass;
+            (&main_pass.attachments, &main_pass.subpasses[index as usize])
+        };
 
-        let mut subpasses: Vec<n::Subpass> = subpasses
-            .map(|sub| {
-                let mut colors: ArrayVec<[_; MAX_COLOR_ATTACHMENTS]> = sub
-                    .colors
-                    .iter()
-                    .map(|&(id, _)| {
-                        let hal_format = attachments[id].format.expect("No format!");
-                        n::AttachmentInfo {
-                            id,
-                            resolve_id: None,
-                            ops: n::AttachmentOps::empty(),
-                            format: self
-                                .shared
-                                .private_caps
-                                .map_format(hal_format)
-                                .expect("Unable to map color format!"),
-                            channel: Channel::from(hal_format.base_format().1),
-                        }
-                    })
-                    .collect();
-                for (color, &(resolve_id, _)) in colors.iter_mut().zip(sub.resolves.iter()) {
-                    if resolve_id != pass::ATTACHMENT_UNUSED {
-                        color.resolve_id = Some(resolve_id);
-                    }
+        let (desc_vertex_buffers, attributes, input_assembler, vs) =
+            match pipeline_desc.primitive_assembler {
+                pso::PrimitiveAssemblerDesc::Vertex {
+                    tessellation: Some(_),
+                    ..
+                } => {
+                    error!("Tessellation is not supported");
+                    return Err(pso::CreationError::UnsupportedPipeline);
                 }
-                let depth_stencil = sub.depth_stencil.map(|&(id, _)| {
-                    let hal_format = attachments[id].format.expect("No format!");
-                    n::AttachmentInfo {
-                        id,
-                        resolve_id: None,
-                        ops: n::AttachmentOps::empty(),
-                        format: self
-                            .shared
-                            .private_caps
-                            .map_format(hal_format)
-                            .expect("Unable to map depth-stencil format!"),
-                        channel: Channel::Float,
-                    }
-                });
+                pso::PrimitiveAssemblerDesc::Vertex {
+                    geometry: Some(_), ..
+                } => {
+                    error!("Geometry shader is not supported");
+                    return Err(pso::CreationError::UnsupportedPipeline);
+                }
+                pso::PrimitiveAssemblerDesc::Mesh { .. } => {
+                    error!("Mesh shader is not supported");
+                    return Err(pso::CreationError::UnsupportedPipeline);
+                }
+                pso::PrimitiveAssemblerDesc::Vertex {
+                    buffers,
+                    attributes,
+                    ref input_assembler,
+                    ref vertex,
+                    tessellation: _,
+                    geometry: _,
+                } => (buffers, attributes, input_assembler, vertex),
+            };
 
-                let samples = colors
-                    .iter()
-                    .chain(depth_stencil.as_ref())
-                    .map(|at_info| attachments[at_info.id].samples)
-                    .max()
-                    .unwrap_or(1);
+        let (primitive_class, primitive_type) = match input_assembler.primitive {
+            pso::Primitive::PointList => {
+                (MTLPrimitiveTopologyClass::Point, MTLPrimitiveType::Point)
+            }
+            pso::Primitive::LineList => (MTLPrimitiveTopologyClass::Line, MTLPrimitiveType::Line),
+            pso::Primitive::LineStrip => {
+                (MTLPrimitiveTopologyClass::Line, MTLPrimitiveType::LineStrip)
+            }
+            pso::Primitive::TriangleList => (
+                MTLPrimitiveTopologyClass::Triangle,
+                MTLPrimitiveType::Triangle,
+            ),
+            pso::Primitive::TriangleStrip => (
+                MTLPrimitiveTopologyClass::Triangle,
+                MTLPrimitiveType::TriangleStrip,
+            ),
+            pso::Primitive::PatchList(_) => (
+                MTLPrimitiveTopologyClass::Unspecified,
+                MTLPrimitiveType::Point,
+            ),
+        };
+        if self.shared.private_caps.layered_rendering {
+            pipeline.set_input_primitive_topology(primitive_class);
+        }
 
-                n::Subpass {
-                    attachments: n::SubpassData {
-                        colors,
-                        depth_stencil,
-                    },
-                    inputs: sub.inputs.iter().map(|&(id, _)| id).collect(),
-                    samples,
-                }
-            })
-            .collect();
+        // Vertex shader
+        let (vs_lib, vs_function, _, enable_rasterization) = self.load_shader(
+            vs,
+            pipeline_layout,
+            primitive_class,
+            cache,
+            naga::ShaderStage::Vertex,
+        )?;
+        pipeline.set_vertex_function(Some(&vs_function));
 
-        // sprinkle load operations
-        // an attachment receives LOAD flag on a subpass if it's the first sub-pass that uses it
-        let mut use_mask = 0u64;
-        for sub in subpasses.iter_mut() {
-            for at in sub.attachments.colors.iter_mut() {
-                if use_mask & 1 << at.id == 0 {
-                    at.ops |= n::AttachmentOps::LOAD;
-                    use_mask ^= 1 << at.id;
-                }
+        // Fragment shader
+        let fs_function;
+        let fs_lib = match pipeline_desc.fragment {
+            Some(ref ep) => {
+                let (lib, fun, _, _) = self.load_shader(
+                    ep,
+                    pipeline_layout,
+                    primitive_class,
+                    cache,
+                    naga::ShaderStage::Fragment,
+                )?;
+                fs_function = fun;
+                pipeline.set_fragment_function(Some(&fs_function));
+                Some(lib)
             }
-            if let Some(ref mut at) = sub.attachments.depth_stencil {
-                if use_mask & 1 << at.id == 0 {
-                    at.ops |= n::AttachmentOps::LOAD;
-                    use_mask ^= 1 << at.id;
+            None => {
+                // TODO: This is a workaround for what appears to be a Metal validation bug
+                // A pixel format is required even though no attachments are provided
+                if subpass.attachments.colors.is_empty()
+                    && subpass.attachments.depth_stencil.is_none()
+                {
+                    pipeline.set_depth_attachment_pixel_format(metal::MTLPixelFormat::Depth32Float);
                 }
+                None
+            }
+        };
+
+        pipeline.set_rasterization_enabled(enable_rasterization);
+
+        // Assign target formats
+        let blend_targets = pipeline_desc
+            .blender
+            .targets
+            .iter()
+            .chain(iter::repeat(&pso::ColorBlendDesc::EMPTY));
+        for (i, (at, color_desc)) in subpass
+            .attachments
+            .colors
+            .iter()
+            .zip(blend_targets)
+            .enumerate()
+        {
+            let desc = pipeline
+                .color_attachments()
+                .object_at(i as u64)
+                .expect("too many color attachments");
+
+            desc.set_pixel_format(at.format);
+            desc.set_write_mask(conv::map_write_mask(color_desc.mask));
+
+            if let Some(ref blend) = color_desc.blend {
+                desc.set_blending_enabled(true);
+                let (color_op, color_src, color_dst) = conv::map_blend_op(blend.color);
+                let (alpha_op, alpha_src, alpha_dst) = conv::map_blend_op(blend.alpha);
+
+                desc.set_rgb_blend_operation(color_op);
+                desc.set_source_rgb_blend_factor(color_src);
+                desc.set_destination_rgb_blend_factor(color_dst);
+
+                desc.set_alpha_blend_operation(alpha_op);
+                desc.set_source_alpha_blend_factor(alpha_src);
+                desc.set_destination_alpha_blend_factor(alpha_dst);
             }
         }
-        // sprinkle store operations
-        // an attachment receives STORE flag on a subpass if it's the last sub-pass that uses it
-        for sub in subpasses.iter_mut().rev() {
-            for at in sub.attachments.colors.iter_mut() {
-                if use_mask & 1 << at.id != 0 {
-                    at.ops |= n::AttachmentOps::STORE;
-                    use_mask ^= 1 << at.id;
-                }
+        if let Some(ref at) = subpass.attachments.depth_stencil {
+            let orig_format = rp_attachments[at.id].format.unwrap();
+            if orig_format.is_depth() {
+                pipeline.set_depth_attachment_pixel_format(at.format);
             }
-            if let Some(ref mut at) = sub.attachments.depth_stencil {
-                if use_mask & 1 << at.id != 0 {
-                    at.ops |= n::AttachmentOps::STORE;
-                    use_mask ^= 1 << at.id;
-                }
+            if orig_format.is_stencil() {
+                pipeline.set_stencil_attachment_pixel_format(at.format);
             }
         }
 
-        Ok(n::RenderPass {
-            attachments,
-            subpasses,
-            name: String::new(),
-        })
-    }
+        // Vertex buffers
+        let vertex_descriptor = metal::VertexDescriptor::new();
+        let mut vertex_buffers: n::VertexBufferVec = Vec::new();
+        trace!("Vertex attribute remapping started");
 
-    unsafe fn create_pipeline_layout<'a, Is, Ic>(
-        &self,
-        set_layouts: Is,
-        push_constant_ranges: Ic,
-    ) -> Result<n::PipelineLayout, d::OutOfMemory>
-    where
-        Is: Iterator<Item = &'a n::DescriptorSetLayout>,
+        for &pso::AttributeDesc {
+            location,
+            binding,
+            element,
+        } in attributes
+        {
+            let original = desc_vertex_buffers
+                .iter()
+                .find(|vb| vb.binding == binding)
+                .expect("no associated vertex buffer found");
+            // handle wrapping offsets
+            let elem_size = element.format.surface_desc().bits as pso::ElemOffset / 8;
+            let (cut_offset, base_offset) =
+                if original.stride == 0 || element.offset + elem_size <= original.stride {
+                    (element.offset, 0)
+                } else {
+                    let remainder = element.offset % original.stride;
+                    if remainder + elem_size <= original.stride {
+                        (remainder, element.offset - remainder)
+                    } else {
+                        (0, element.offset)
+                    }
+                };
+            let relative_index = vertex_buffers
+                .iter()
+                .position(|(ref vb, offset)| vb.binding == binding && base_offset == *offset)
+                .unwrap_or_else(|| {
+                    vertex_buffers.alloc().init((original.clone(), base_offset));
+                    vertex_buffers.len() - 1
+                });
+            let mtl_buffer_index = self.shared.private_caps.max_buffers_per_stage
+                - 1
+                - (relative_index as ResourceIndex);
+            if mtl_buffer_index < pipeline_layout.total.vs.buffers {
+                error!("Attribute offset {} exceeds the stride {}, and there is no room for replacement.",
+                    element.offset, original.stride);
+                return Err(pso::CreationError::Other);
+            }
+            trace!("\tAttribute[{}] is mapped to vertex buffer[{}] with binding {} and offsets {} + {}",
+                location, binding, mtl_buffer_index, base_offset, cut_offset);
+            // pass the refined data to Metal
+            let mtl_attribute_desc = vertex_descriptor
+                .attributes()
+                .object_at(location as u64)
+                .expect("too many vertex attributes");
+            let mtl_vertex_format =
+                conv::map_vertex_format(element.format).expect("unsupported vertex format");
+            mtl_attribute_desc.set_format(mtl_vertex_format);
+            mtl_attribute_desc.set_buffer_index(mtl_buffer_index as _);
+            mtl_attribute_desc.set_offset(cut_offset as _);
+        }
+
+        for (i, (vb, _)) in vertex_buffers.iter().enumerate() {
+            let mtl_buffer_desc = vertex_descriptor
+                .layouts()
+                .object_at(self.shared.private_caps.max_buffers_per_stage as u64 - 1 - i as u64)
+                .expect("too many vertex descriptor layouts");
+            if vb.stride % STRIDE_GRANULARITY != 0 {
+                error!(
+                    "Stride ({}) must be a multiple of {}",
+                    vb.stride, STRIDE_GRANULARITY
+                );
+                return Err(pso::CreationError::Other);
+            }
+            if vb.stride != 0 {
+                mtl_buffer_desc.set_stride(vb.stride as u64);
+                match vb.rate {
+                    VertexInputRate::Vertex => {
+                        mtl_buffer_desc.set_step_function(MTLVertexStepFunction::PerVertex);
+                    }
+                    VertexInputRate::Instance(divisor) => {
+                        mtl_buffer_desc.set_step_function(MTLVertexStepFunction::PerInstance);
+                        mtl_buffer_desc.set_step_rate(divisor as u64);
+                    }
+                }
+            } else {
+                mtl_buffer_desc.set_stride(256); // big enough to fit all the elements
+                mtl_buffer_desc.set_step_function(MTLVertexStepFunction::PerInstance);
+                mtl_buffer_desc.set_step_rate(!0);
+            }
+        }
+        if !vertex_buffers.is_empty() {
+            pipeline.set_vertex_descriptor(Some(&vertex_descriptor));
+        }
+
+        if let pso::State::Static(w) = pipeline_desc.rasterizer.line_width {
+            if w != 1.0 {
+                warn!("Unsupported line width: {:?}", w);
+            }
+        }
+
+        let rasterizer_state = Some(n::RasterizerState {
+            front_winding: conv::map_winding(pipeline_desc.rasterizer.front_face),
+            fill_mode: conv::map_polygon_mode(pipeline_desc.rasterizer.polygon_mode),
+            cull_mode: match conv::map_cull_face(pipeline_desc.rasterizer.cull_face) {
+                Some(mode) => mode,
+                None => {
+                    //TODO - Metal validation fails with
+                    // RasterizationEnabled is false but the vertex shader's return type is not void
+                    error!("Culling both sides is not yet supported");
+                    //pipeline.set_rasterization_enabled(false);
+                    metal::MTLCullMode::None
+                }
+            },
+            depth_clip: if self.shared.private_caps.depth_clip_mode {
+                Some(if pipeline_desc.rasterizer.depth_clamping {
+                    metal::MTLDepthClipMode::Clamp
+                } else {
+                    metal::MTLDepthClipMode::Clip
+                })
+            } else {
+                None
+            },
+        });
+        let depth_bias = pipeline_desc
+            .rasterizer
+            .depth_bias
+            .unwrap_or(pso::State::Static(pso::DepthBias::default()));
+
+        // prepare the depth-stencil state now
+        let device = self.shared.device.lock();
+        self.shared
+            .service_pipes
+            .depth_stencil_states
+            .prepare(&pipeline_desc.depth_stencil, &*device);
+
+        let samples = if let Some(multisampling) = &pipeline_desc.multisampling {
+            pipeline.set_sample_count(multisampling.rasterization_samples as u64);
+            pipeline.set_alpha_to_coverage_enabled(multisampling.alpha_coverage);
+            pipeline.set_alpha_to_one_enabled(multisampling.alpha_to_one);
+            // TODO: sample_mask
+            // Never exposed through `Features::SAMPLE_RATE_SHADING`, the shaders
+            // reading the sample index are the only ones running per sample
+            if multisampling.sample_shading.is_some() {
+                warn!("Sample shading is not supported, ignoring");
+            }
+            multisampling.rasterization_samples
+        } else {
+            1
+        };
+
+        if let Some(name) = pipeline_desc.label {
+            pipeline.set_label(name);
+        }
+
+        let parts = GraphicsPipelineParts {
+            vs_lib,
+            fs_lib,
+            primitive_type,
+            vs_pc_info: pipeline_desc.layout.push_constants.vs,
+            ps_pc_info: pipeline_desc.layout.push_constants.ps,
+            rasterizer_state,
+            depth_bias,
+            depth_stencil_desc: pipeline_desc.depth_stencil.clone(),
+            baked_states: pipeline_desc.baked_states.clone(),
+            vertex_buffers,
+            attachment_formats: subpass.attachments.map(|at| (at.format, at.channel)),
+            samples,
+        };
+        Ok((pipeline, parts))
+    }
+
+    /// Prepare the compute pipeline descriptor, along with the rest of the pipeline.
+    unsafe fn prepare_compute_pipeline<'a>(
+        &self,
+        pipeline_desc: &pso::ComputePipelineDesc<'a, Backend>,
+        cache: Option<&n::PipelineCache>,
+    ) -> Result<(metal::ComputePipelineDescriptor, ComputePipelineParts), pso::CreationError> {
+        let pipeline = metal::ComputePipelineDescriptor::new();
+
+        let (cs_lib, cs_function, work_group_size, _) = self.load_shader(
+            &pipeline_desc.shader,
+            &pipeline_desc.layout,
+            MTLPrimitiveTopologyClass::Unspecified,
+            cache,
+            naga::ShaderStage::Compute,
+        )?;
+        pipeline.set_compute_function(Some(&cs_function));
+        if let Some(name) = pipeline_desc.label {
+            pipeline.set_label(name);
+        }
+
+        let parts = ComputePipelineParts {
+            cs_lib,
+            work_group_size,
+            pc_info: pipeline_desc.layout.push_constants.cs,
+        };
+        Ok((pipeline, parts))
+    }
+}
+
+impl hal::device::Device<Backend> for Device {
+    unsafe fn create_command_pool(
+        &self,
+        _family: QueueFamilyId,
+        _flags: CommandPoolCreateFlags,
+    ) -> Result<command::CommandPool, d::OutOfMemory> {
+        Ok(command::CommandPool::new(
+            &self.shared,
+            self.online_recording.clone(),
+        ))
+    }
+
+    unsafe fn destroy_command_pool(&self, mut pool: command::CommandPool) {
+        use hal::pool::CommandPool as _;
+        pool.reset(false);
+    }
+
+    unsafe fn create_render_pass<'a, Ia, Is, Id>(
+        &self,
+        attachments: Ia,
+        subpasses: Is,
+        dependencies: Id,
+    ) -> Result<n::RenderPass, d::OutOfMemory>
+    where
+        Ia: Iterator<Item = pass::Attachment>,
+        Is: Iterator<Item = pass::SubpassDesc<'a>>,
+        Id: Iterator<Item = pass::SubpassDependency>,
+    {
+        let subpasses = subpasses.inspect(|subpass| {
+            assert_eq!(subpass.view_mask, 0, "Metal doesn't support MULTIVIEW");
+        });
+        Ok(Self::build_render_pass(
+            &self.shared.private_caps,
+            attachments,
+            subpasses,
+            dependencies,
+        ))
+    }
+
+    unsafe fn create_pipeline_layout<'a, Is, Ic>(
+        &self,
+        set_layouts: Is,
+        push_constant_ranges: Ic,
+    ) -> Result<n::PipelineLayout, d::OutOfMemory>
+    where
+        Is: Iterator<Item = &'a n::DescriptorSetLayout>,
         Ic: Iterator<Item = (pso::ShaderStageFlags, Range<u32>)>,
     {
         struct StageInfo {
@@ -1150,6 +1940,8 @@ impl hal::device::Device<Backend> for Device {
         let mut binding_map = FastHashMap::default();
         let mut argument_buffer_bindings = FastHashMap::default();
         #[cfg(feature = "cross")]
+        let mut argument_buffer_resources = Vec::new();
+        #[cfg(feature = "cross")]
         let mut const_samplers = BTreeMap::new();
         let mut infos = Vec::new();
 
@@ -1267,7 +2059,8 @@ impl hal::device::Device<Backend> for Device {
                     }
                 }
                 n::DescriptorSetLayout::ArgumentBuffer {
-                    bindings: _,
+                    #[cfg(feature = "cross")]
+                    ref bindings,
                     stage_flags,
                     ..
                 } => {
@@ -1275,9 +2068,18 @@ impl hal::device::Device<Backend> for Device {
                         if !stage_flags.contains(info.stage.into()) {
                             continue;
                         }
-                        //TODO: mark `bindings` as belonging to the argument buffer
                         argument_buffer_bindings
                             .insert((info.stage, set_index as u32), info.counters.buffers);
+                        #[cfg(feature = "cross")]
+                        argument_buffer_resources.extend(bindings.iter().map(|(&binding, arg)| {
+                            (
+                                info.stage,
+                                set_index as u32,
+                                binding,
+                                arg.ids.clone(),
+                                arg.count,
+                            )
+                        }));
                         info.counters.buffers += 1;
                     }
                 }
@@ -1345,7 +2147,23 @@ impl hal::device::Device<Backend> for Device {
                         count: 0,
                     },
                 );
-                //TODO: assign argument buffer locations
+            }
+            // resources within the argument buffers, at their argument indices
+            for (stage, desc_set, binding, ids, count) in argument_buffer_resources {
+                compiler_options.resource_binding_overrides.insert(
+                    msl::ResourceBindingLocation {
+                        stage: conv::map_naga_stage_to_cross(stage),
+                        desc_set,
+                        binding,
+                    },
+                    msl::ResourceBinding {
+                        buffer_id: ids.buffers.unwrap_or(!0),
+                        texture_id: ids.textures.unwrap_or(!0),
+                        sampler_id: ids.samplers.unwrap_or(!0),
+                        // sizes the unsized arrays in the shader
+                        count: count as u32,
+                    },
+                );
             }
             // push constants
             for info in stage_infos.iter() {
@@ -1446,410 +2264,170 @@ impl hal::device::Device<Backend> for Device {
 
     unsafe fn destroy_pipeline_cache(&self, _cache: n::PipelineCache) {
         //drop
-    }
-
-    #[cfg_attr(not(feature = "cross"), allow(unused_variables))]
-    unsafe fn merge_pipeline_caches<'a, I>(
-        &self,
-        target: &mut n::PipelineCache,
-        sources: I,
-    ) -> Result<(), d::OutOfMemory>
-    where
-        I: Iterator<Item = &'a n::PipelineCache>,
-    {
-        #[cfg(feature = "cross")]
-        {
-            //TODO: reduce the locking here
-            let mut dst = target.modules.whole_write();
-            for source in sources {
-                let src = source.modules.whole_write();
-                for (key, value) in src.iter() {
-                    let storage = dst
-                        .entry(key.clone())
-                        .or_insert_with(FastStorageMap::default);
-                    let mut dst_module = storage.whole_write();
-                    let src_module = value.whole_write();
-                    for (key_module, value_module) in src_module.iter() {
-                        match dst_module.entry(key_module.clone()) {
-                            Entry::Vacant(em) => {
-                                em.insert(value_module.clone());
-                            }
-                            Entry::Occupied(em) => {
-                                if em.get().library.as_ptr() != value_module.library.as_ptr()
-                                    || em.get().entry_point_map != value_module.entry_point_map
-                                {
-                                    warn!(
-                                        "Merged module don't match, target: {:?}, source: {:?}",
-                                        em.get(),
-                                        value_module
-                                    );
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }
-
-        Ok(())
-    }
-
-    unsafe fn create_graphics_pipeline<'a>(
-        &self,
-        pipeline_desc: &pso::GraphicsPipelineDesc<'a, Backend>,
-        cache: Option<&n::PipelineCache>,
-    ) -> Result<n::GraphicsPipeline, pso::CreationError> {
-        debug!("create_graphics_pipeline {:#?}", pipeline_desc);
-        let pipeline = metal::RenderPipelineDescriptor::new();
-        let pipeline_layout = &pipeline_desc.layout;
-        let (rp_attachments, subpass) = {
-            let pass::Subpass { main_pass, index } = pipeline_desc.subpass;
-            (&main_pass.attachments, &main_pass.subpasses[index as usize])
-        };
-
-        let (desc_vertex_buffers, attributes, input_assembler, vs) =
-            match pipeline_desc.primitive_assembler {
-                pso::PrimitiveAssemblerDesc::Vertex {
-                    tessellation: Some(_),
-                    ..
-                } => {
-                    error!("Tessellation is not supported");
-                    return Err(pso::CreationError::UnsupportedPipeline);
-                }
-                pso::PrimitiveAssemblerDesc::Vertex {
-                    geometry: Some(_), ..
-                } => {
-                    error!("Geometry shader is not supported");
-                    return Err(pso::CreationError::UnsupportedPipeline);
-                }
-                pso::PrimitiveAssemblerDesc::Mesh { .. } => {
-                    error!("Mesh shader is not supported");
-                    return Err(pso::CreationError::UnsupportedPipeline);
-                }
-                pso::PrimitiveAssemblerDesc::Vertex {
-                    buffers,
-                    attributes,
-                    ref input_assembler,
-                    ref vertex,
-                    tessellation: _,
-                    geometry: _,
-                } => (buffers, attributes, input_assembler, vertex),
-            };
-
-        let (primitive_class, primitive_type) = match input_assembler.primitive {
-            pso::Primitive::PointList => {
-                (MTLPrimitiveTopologyClass::Point, MTLPrimitiveType::Point)
-            }
-            pso::Primitive::LineList => (MTLPrimitiveTopologyClass::Line, MTLPrimitiveType::Line),
-            pso::Primitive::LineStrip => {
-                (MTLPrimitiveTopologyClass::Line, MTLPrimitiveType::LineStrip)
-            }
-            pso::Primitive::TriangleList => (
-                MTLPrimitiveTopologyClass::Triangle,
-                MTLPrimitiveType::Triangle,
-            ),
-            pso::Primitive::TriangleStrip => (
-                MTLPrimitiveTopologyClass::Triangle,
-                MTLPrimitiveType::TriangleStrip,
-            ),
-            pso::Primitive::PatchList(_) => (
-                MTLPrimitiveTopologyClass::Unspecified,
-                MTLPrimitiveType::Point,
-            ),
-        };
-        if self.shared.private_caps.layered_rendering {
-            pipeline.set_input_primitive_topology(primitive_class);
-        }
-
-        // Vertex shader
-        let (vs_lib, vs_function, _, enable_rasterization) = self.load_shader(
-            vs,
-            pipeline_layout,
-            primitive_class,
-            cache,
-            naga::ShaderStage::Vertex,
-        )?;
-        pipeline.set_vertex_function(Some(&vs_function));
-
-        // Fragment shader
-        let fs_function;
-        let fs_lib = match pipeline_desc.fragment {
-            Some(ref ep) => {
-                let (lib, fun, _, _) = self.load_shader(
-                    ep,
-                    pipeline_layout,
-                    primitive_class,
-                    cache,
-                    naga::ShaderStage::Fragment,
-                )?;
-                fs_function = fun;
-                pipeline.set_fragment_function(Some(&fs_function));
-                Some(lib)
-            }
-            None => {
-                // TODO: This is a workaround for what appears to be a Metal validation bug
-                // A pixel format is required even though no attachments are provided
-                if subpass.attachments.colors.is_empty()
-                    && subpass.attachments.depth_stencil.is_none()
-                {
-                    pipeline.set_depth_attachment_pixel_format(metal::MTLPixelFormat::Depth32Float);
-                }
-                None
-            }
-        };
-
-        pipeline.set_rasterization_enabled(enable_rasterization);
-
-        // Assign target formats
-        let blend_targets = pipeline_desc
-            .blender
-            .targets
-            .iter()
-            .chain(iter::repeat(&pso::ColorBlendDesc::EMPTY));
-        for (i, (at, color_desc)) in subpass
-            .attachments
-            .colors
-            .iter()
-            .zip(blend_targets)
-            .enumerate()
-        {
-            let desc = pipeline
-                .color_attachments()
-                .object_at(i as u64)
-                .expect("too many color attachments");
-
-            desc.set_pixel_format(at.format);
-            desc.set_write_mask(conv::map_write_mask(color_desc.mask));
-
-            if let Some(ref blend) = color_desc.blend {
-                desc.set_blending_enabled(true);
-                let (color_op, color_src, color_dst) = conv::map_blend_op(blend.color);
-                let (alpha_op, alpha_src, alpha_dst) = conv::map_blend_op(blend.alpha);
-
-                desc.set_rgb_blend_operation(color_op);
-                desc.set_source_rgb_blend_factor(color_src);
-                desc.set_destination_rgb_blend_factor(color_dst);
-
-                desc.set_alpha_blend_operation(alpha_op);
-                desc.set_source_alpha_blend_factor(alpha_src);
-                desc.set_destination_alpha_blend_factor(alpha_dst);
-            }
-        }
-        if let Some(ref at) = subpass.attachments.depth_stencil {
-            let orig_format = rp_attachments[at.id].format.unwrap();
-            if orig_format.is_depth() {
-                pipeline.set_depth_attachment_pixel_format(at.format);
-            }
-            if orig_format.is_stencil() {
-                pipeline.set_stencil_attachment_pixel_format(at.format);
-            }
-        }
-
-        // Vertex buffers
-        let vertex_descriptor = metal::VertexDescriptor::new();
-        let mut vertex_buffers: n::VertexBufferVec = Vec::new();
-        trace!("Vertex attribute remapping started");
-
-        for &pso::AttributeDesc {
-            location,
-            binding,
-            element,
-        } in attributes
-        {
-            let original = desc_vertex_buffers
-                .iter()
-                .find(|vb| vb.binding == binding)
-                .expect("no associated vertex buffer found");
-            // handle wrapping offsets
-            let elem_size = element.format.surface_desc().bits as pso::ElemOffset / 8;
-            let (cut_offset, base_offset) =
-                if original.stride == 0 || element.offset + elem_size <= original.stride {
-                    (element.offset, 0)
-                } else {
-                    let remainder = element.offset % original.stride;
-                    if remainder + elem_size <= original.stride {
-                        (remainder, element.offset - remainder)
-                    } else {
-                        (0, element.offset)
-                    }
-                };
-            let relative_index = vertex_buffers
-                .iter()
-                .position(|(ref vb, offset)| vb.binding == binding && base_offset == *offset)
-                .unwrap_or_else(|| {
-                    vertex_buffers.alloc().init((original.clone(), base_offset));
-                    vertex_buffers.len() - 1
-                });
-            let mtl_buffer_index = self.shared.private_caps.max_buffers_per_stage
-                - 1
-                - (relative_index as ResourceIndex);
-            if mtl_buffer_index < pipeline_layout.total.vs.buffers {
-                error!("Attribute offset {} exceeds the stride {}, and there is no room for replacement.",
-                    element.offset, original.stride);
-                return Err(pso::CreationError::Other);
-            }
-            trace!("\tAttribute[{}] is mapped to vertex buffer[{}] with binding {} and offsets {} + {}",
-                location, binding, mtl_buffer_index, base_offset, cut_offset);
-            // pass the refined data to Metal
-            let mtl_attribute_desc = vertex_descriptor
-                .attributes()
-                .object_at(location as u64)
-                .expect("too many vertex attributes");
-            let mtl_vertex_format =
-                conv::map_vertex_format(element.format).expect("unsupported vertex format");
-            mtl_attribute_desc.set_format(mtl_vertex_format);
-            mtl_attribute_desc.set_buffer_index(mtl_buffer_index as _);
-            mtl_attribute_desc.set_offset(cut_offset as _);
-        }
-
-        for (i, (vb, _)) in vertex_buffers.iter().enumerate() {
-            let mtl_buffer_desc = vertex_descriptor
-                .layouts()
-                .object_at(self.shared.private_caps.max_buffers_per_stage as u64 - 1 - i as u64)
-                .expect("too many vertex descriptor layouts");
-            if vb.stride % STRIDE_GRANULARITY != 0 {
-                error!(
-                    "Stride ({}) must be a multiple of {}",
-                    vb.stride, STRIDE_GRANULARITY
-                );
-                return Err(pso::CreationError::Other);
-            }
-            if vb.stride != 0 {
-                mtl_buffer_desc.set_stride(vb.stride as u64);
-                match vb.rate {
-                    VertexInputRate::Vertex => {
-                        mtl_buffer_desc.set_step_function(MTLVertexStepFunction::PerVertex);
-                    }
-                    VertexInputRate::Instance(divisor) => {
-                        mtl_buffer_desc.set_step_function(MTLVertexStepFunction::PerInstance);
-                        mtl_buffer_desc.set_step_rate(divisor as u64);
+    }
+
+    #[cfg_attr(not(feature = "cross"), allow(unused_variables))]
+    unsafe fn merge_pipeline_caches<'a, I>(
+        &self,
+        target: &mut n::PipelineCache,
+        sources: I,
+    ) -> Result<(), d::OutOfMemory>
+    where
+        I: Iterator<Item = &'a n::PipelineCache>,
+    {
+        #[cfg(feature = "cross")]
+        {
+            //TODO: reduce the locking here
+            let mut dst = target.modules.whole_write();
+            for source in sources {
+                let src = source.modules.whole_write();
+                for (key, value) in src.iter() {
+                    let storage = dst
+                        .entry(key.clone())
+                        .or_insert_with(FastStorageMap::default);
+                    let mut dst_module = storage.whole_write();
+                    let src_module = value.whole_write();
+                    for (key_module, value_module) in src_module.iter() {
+                        match dst_module.entry(key_module.clone()) {
+                            Entry::Vacant(em) => {
+                                em.insert(value_module.clone());
+                            }
+                            Entry::Occupied(em) => {
+                                if em.get().library.as_ptr() != value_module.library.as_ptr()
+                                    || em.get().entry_point_map != value_module.entry_point_map
+                                {
+                                    warn!(
+                                        "Merged module don't match, target: {:?}, source: {:?}",
+                                        em.get(),
+                                        value_module
+                                    );
+                                }
+                            }
+                        }
                     }
                 }
-            } else {
-                mtl_buffer_desc.set_stride(256); // big enough to fit all the elements
-                mtl_buffer_desc.set_step_function(MTLVertexStepFunction::PerInstance);
-                mtl_buffer_desc.set_step_rate(!0);
             }
         }
-        if !vertex_buffers.is_empty() {
-            pipeline.set_vertex_descriptor(Some(&vertex_descriptor));
-        }
 
-        if let pso::State::Static(w) = pipeline_desc.rasterizer.line_width {
-            if w != 1.0 {
-                warn!("Unsupported line width: {:?}", w);
-            }
-        }
+        Ok(())
+    }
 
-        let rasterizer_state = Some(n::RasterizerState {
-            front_winding: conv::map_winding(pipeline_desc.rasterizer.front_face),
-            fill_mode: conv::map_polygon_mode(pipeline_desc.rasterizer.polygon_mode),
-            cull_mode: match conv::map_cull_face(pipeline_desc.rasterizer.cull_face) {
-                Some(mode) => mode,
-                None => {
-                    //TODO - Metal validation fails with
-                    // RasterizationEnabled is false but the vertex shader's return type is not void
-                    error!("Culling both sides is not yet supported");
-                    //pipeline.set_rasterization_enabled(false);
-                    metal::MTLCullMode::None
-                }
-            },
-            depth_clip: if self.shared.private_caps.depth_clip_mode {
-                Some(if pipeline_desc.rasterizer.depth_clamping {
-                    metal::MTLDepthClipMode::Clamp
-                } else {
-                    metal::MTLDepthClipMode::Clip
-                })
-            } else {
-                None
-            },
-        });
-        let depth_bias = pipeline_desc
-            .rasterizer
-            .depth_bias
-            .unwrap_or(pso::State::Static(pso::DepthBias::default()));
+    unsafe fn create_graphics_pipeline<'a>(
+        &self,
+        pipeline_desc: &pso::GraphicsPipelineDesc<'a, Backend>,
+        cache: Option<&n::PipelineCache>,
+    ) -> Result<n::GraphicsPipeline, pso::CreationError> {
+        debug!("create_graphics_pipeline {:#?}", pipeline_desc);
+        let (pipeline, parts) = self.prepare_graphics_pipeline(pipeline_desc, cache)?;
 
-        // prepare the depth-stencil state now
-        let device = self.shared.device.lock();
         self.shared
-            .service_pipes
-            .depth_stencil_states
-            .prepare(&pipeline_desc.depth_stencil, &*device);
-
-        let samples = if let Some(multisampling) = &pipeline_desc.multisampling {
-            pipeline.set_sample_count(multisampling.rasterization_samples as u64);
-            pipeline.set_alpha_to_coverage_enabled(multisampling.alpha_coverage);
-            pipeline.set_alpha_to_one_enabled(multisampling.alpha_to_one);
-            // TODO: sample_mask
-            // TODO: sample_shading
-            multisampling.rasterization_samples
-        } else {
-            1
-        };
-
-        if let Some(name) = pipeline_desc.label {
-            pipeline.set_label(name);
-        }
-
-        device
+            .device
+            .lock()
             .new_render_pipeline_state(&pipeline)
-            .map(|raw| n::GraphicsPipeline {
-                vs_lib,
-                fs_lib,
-                raw,
-                primitive_type,
-                vs_pc_info: pipeline_desc.layout.push_constants.vs,
-                ps_pc_info: pipeline_desc.layout.push_constants.ps,
-                rasterizer_state,
-                depth_bias,
-                depth_stencil_desc: pipeline_desc.depth_stencil.clone(),
-                baked_states: pipeline_desc.baked_states.clone(),
-                vertex_buffers,
-                attachment_formats: subpass.attachments.map(|at| (at.format, at.channel)),
-                samples,
-            })
+            .map(|raw| parts.finish(raw))
             .map_err(|err| {
                 error!("PSO creation failed: {}", err);
                 pso::CreationError::Other
             })
     }
 
+    unsafe fn create_graphics_pipeline_async<'a>(
+        &self,
+        pipeline_desc: &pso::GraphicsPipelineDesc<'a, Backend>,
+        cache: Option<&n::PipelineCache>,
+    ) -> pso::PipelineTask<n::GraphicsPipeline> {
+        debug!("create_graphics_pipeline_async {:#?}", pipeline_desc);
+        let (pipeline, parts) = match self.prepare_graphics_pipeline(pipeline_desc, cache) {
+            Ok(prepared) => prepared,
+            Err(err) => return pso::PipelineTask::ready(Err(err)),
+        };
+
+        // Metal compiles the state in the background and calls the handler once
+        let (sender, task) = pso::PipelineTask::new();
+        let pending = Mutex::new(Some((sender, parts)));
+        let block = ConcreteBlock::new(move |state: *mut Object, error: *mut Object| {
+            let (sender, parts) = match pending.lock().take() {
+                Some(pending) => pending,
+                None => return,
+            };
+            let result = if state.is_null() {
+                error!("PSO creation failed: {}", error_description(error));
+                Err(pso::CreationError::Other)
+            } else {
+                let raw = metal::RenderPipelineStateRef::from_ptr(state as *mut _).to_owned();
+                Ok(parts.finish(raw))
+            };
+            let _ = sender.send(result);
+        })
+        .copy();
+
+        let device = self.shared.device.lock();
+        let () = msg_send![&**device,
+            newRenderPipelineStateWithDescriptor: &*pipeline
+            completionHandler: block.deref() as *const _
+        ];
+        task
+    }
+
     unsafe fn create_compute_pipeline<'a>(
         &self,
         pipeline_desc: &pso::ComputePipelineDesc<'a, Backend>,
         cache: Option<&n::PipelineCache>,
     ) -> Result<n::ComputePipeline, pso::CreationError> {
         debug!("create_compute_pipeline {:?}", pipeline_desc);
-        let pipeline = metal::ComputePipelineDescriptor::new();
-
-        let (cs_lib, cs_function, work_group_size, _) = self.load_shader(
-            &pipeline_desc.shader,
-            &pipeline_desc.layout,
-            MTLPrimitiveTopologyClass::Unspecified,
-            cache,
-            naga::ShaderStage::Compute,
-        )?;
-        pipeline.set_compute_function(Some(&cs_function));
-        if let Some(name) = pipeline_desc.label {
-            pipeline.set_label(name);
-        }
+        let (pipeline, parts) = self.prepare_compute_pipeline(pipeline_desc, cache)?;
 
         self.shared
             .device
             .lock()
             .new_compute_pipeline_state(&pipeline)
-            .map(|raw| n::ComputePipeline {
-                cs_lib,
-                raw,
-                work_group_size,
-                pc_info: pipeline_desc.layout.push_constants.cs,
-            })
+            .map(|raw| parts.finish(raw))
             .map_err(|err| {
                 error!("PSO creation failed: {}", err);
                 pso::CreationError::Other
             })
     }
 
+    unsafe fn create_compute_pipeline_async<'a>(
+        &self,
+        pipeline_desc: &pso::ComputePipelineDesc<'a, Backend>,
+        cache: Option<&n::PipelineCache>,
+    ) -> pso::PipelineTask<n::ComputePipeline> {
+        debug!("create_compute_pipeline_async {:?}", pipeline_desc);
+        let (pipeline, parts) = match self.prepare_compute_pipeline(pipeline_desc, cache) {
+            Ok(prepared) => prepared,
+            Err(err) => return pso::PipelineTask::ready(Err(err)),
+        };
+
+        let (sender, task) = pso::PipelineTask::new();
+        let pending = Mutex::new(Some((sender, parts)));
+        let block = ConcreteBlock::new(
+            move |state: *mut Object, _reflection: *mut Object, error: *mut Object| {
+                let (sender, parts) = match pending.lock().take() {
+                    Some(pending) => pending,
+                    None => return,
+                };
+                let result = if state.is_null() {
+                    error!("PSO creation failed: {}", error_description(error));
+                    Err(pso::CreationError::Other)
+                } else {
+                    let raw = metal::ComputePipelineStateRef::from_ptr(state as *mut _).to_owned();
+                    Ok(parts.finish(raw))
+                };
+                let _ = sender.send(result);
+            },
+        )
+        .copy();
+
+        let device = self.shared.device.lock();
+        let () = msg_send![&**device,
+            newComputePipelineStateWithDescriptor: &*pipeline
+            options: MTL_PIPELINE_OPTION_NONE
+            completionHandler: block.deref() as *const _
+        ];
+        task
+    }
+
     unsafe fn create_framebuffer<I>(
         &self,
         _render_pass: &n::RenderPass,
@@ -1867,6 +2445,8 @@ impl hal::device::Device<Backend> for Device {
             prefer_naga: self.always_prefer_naga,
             #[cfg(feature = "cross")]
             spv: raw_data.to_vec(),
+            #[cfg(feature = "cross")]
+            spv_hash: hash_spirv(raw_data),
             naga: {
                 let parser =
                     naga::front::spv::Parser::new(raw_data.iter().cloned(), &Default::default());
@@ -1894,15 +2474,17 @@ impl hal::device::Device<Backend> for Device {
         &self,
         shader: d::NagaShader,
     ) -> Result<n::ShaderModule, (d::ShaderError, d::NagaShader)> {
+        #[cfg(feature = "cross")]
+        let spv = match naga::back::spv::write_vec(&shader.module, &self.spv_options) {
+            Ok(spv) => spv,
+            Err(e) => return Err((d::ShaderError::CompilationFailed(format!("{}", e)), shader)),
+        };
         Ok(n::ShaderModule {
             prefer_naga: true,
             #[cfg(feature = "cross")]
-            spv: match naga::back::spv::write_vec(&shader.module, &self.spv_options) {
-                Ok(spv) => spv,
-                Err(e) => {
-                    return Err((d::ShaderError::CompilationFailed(format!("{}", e)), shader))
-                }
-            },
+            spv_hash: hash_spirv(&spv),
+            #[cfg(feature = "cross")]
+            spv,
             naga: Some(shader),
         })
     }
@@ -1923,6 +2505,33 @@ impl hal::device::Device<Backend> for Device {
 
     unsafe fn destroy_sampler(&self, _sampler: n::Sampler) {}
 
+    unsafe fn create_sampler_ycbcr_conversion(
+        &self,
+        desc: &image::SamplerYcbcrConversionDesc,
+    ) -> Result<(), image::CreationError> {
+        Err(image::CreationError::Format(desc.format))
+    }
+
+    unsafe fn destroy_sampler_ycbcr_conversion(&self, _conversion: ()) {}
+
+    unsafe fn create_ycbcr_sampler(
+        &self,
+        _desc: &image::SamplerDesc,
+        _conversion: &(),
+    ) -> Result<n::Sampler, d::AllocationError> {
+        unimplemented!()
+    }
+
+    unsafe fn create_ycbcr_image_view(
+        &self,
+        _image: &n::Image,
+        _view_kind: image::ViewKind,
+        _range: image::SubresourceRange,
+        _conversion: &(),
+    ) -> Result<n::ImageView, image::ViewCreationError> {
+        unimplemented!()
+    }
+
     unsafe fn map_memory(
         &self,
         memory: &mut n::Memory,
@@ -1933,7 +2542,9 @@ impl hal::device::Device<Backend> for Device {
 
         let base_ptr = match memory.heap {
             n::MemoryHeap::Public(_, ref cpu_buffer) => cpu_buffer.contents() as *mut u8,
-            n::MemoryHeap::Native(_) | n::MemoryHeap::Private => panic!("Unable to map memory!"),
+            n::MemoryHeap::Native(_) | n::MemoryHeap::Private | n::MemoryHeap::Memoryless => {
+                panic!("Unable to map memory!")
+            }
         };
         Ok(base_ptr.offset(range.start as _))
     }
@@ -1942,6 +2553,93 @@ impl hal::device::Device<Backend> for Device {
         debug!("unmap_memory of size {}", memory.size);
     }
 
+    unsafe fn copy_memory_to_image<I>(
+        &self,
+        data: &[u8],
+        image: &n::Image,
+        _layout: image::Layout,
+        regions: I,
+    ) -> Result<(), d::MapError>
+    where
+        I: Iterator<Item = com::BufferImageCopy>,
+    {
+        let texture = Self::host_transfer_texture(image)?;
+        let raw = texture.as_ptr() as *mut Object;
+        for region in regions {
+            let (mtl_region, row_pitch, slice_pitch) =
+                Self::host_copy_region(image, &region, data.len())?;
+            // only 3D textures have several images per slice
+            let image_pitch = if image.mtl_type == MTLTextureType::D3 {
+                slice_pitch
+            } else {
+                0
+            };
+            for (i, layer) in region.image_layers.layers.clone().enumerate() {
+                let offset = region.buffer_offset as usize + i * slice_pitch;
+                let bytes = data.as_ptr().add(offset);
+                let () = msg_send![raw,
+                    replaceRegion: mtl_region
+                    mipmapLevel: region.image_layers.level as NSUInteger
+                    slice: layer as NSUInteger
+                    withBytes: bytes as *const c_void
+                    bytesPerRow: row_pitch as NSUInteger
+                    bytesPerImage: image_pitch as NSUInteger
+                ];
+            }
+        }
+        Ok(())
+    }
+
+    unsafe fn copy_image_to_memory<I>(
+        &self,
+        image: &n::Image,
+        _layout: image::Layout,
+        data: &mut [u8],
+        regions: I,
+    ) -> Result<(), d::MapError>
+    where
+        I: Iterator<Item = com::BufferImageCopy>,
+    {
+        let texture = Self::host_transfer_texture(image)?;
+        if texture.storage_mode() == MTLStorageMode::Managed {
+            // bring the device writes to the CPU copy of the texture
+            let cmd_buffer = self.invalidation_queue.spawn_temp();
+            autoreleasepool(|| {
+                let encoder = cmd_buffer.new_blit_command_encoder();
+                encoder.synchronize_resource(texture);
+                encoder.end_encoding();
+            });
+            cmd_buffer.set_label("copy_image_to_memory");
+            cmd_buffer.commit();
+            cmd_buffer.wait_until_completed();
+        }
+
+        let raw = texture.as_ptr() as *mut Object;
+        for region in regions {
+            let (mtl_region, row_pitch, slice_pitch) =
+                Self::host_copy_region(image, &region, data.len())?;
+            // only 3D textures have several images per slice
+            let image_pitch = if image.mtl_type == MTLTextureType::D3 {
+                slice_pitch
+            } else {
+                0
+            };
+            for (i, layer) in region.image_layers.layers.clone().enumerate() {
+                let offset = region.buffer_offset as usize + i * slice_pitch;
+                let bytes = data.as_mut_ptr().add(offset);
+                let () = msg_send![raw,
+                    getBytes: bytes as *mut c_void
+                    bytesPerRow: row_pitch as NSUInteger
+                    bytesPerImage: image_pitch as NSUInteger
+                    fromRegion: mtl_region
+                    mipmapLevel: region.image_layers.level as NSUInteger
+                    slice: layer as NSUInteger
+                ];
+            }
+        }
+        Ok(())
+    }
+
     unsafe fn flush_mapped_memory_ranges<'a, I>(&self, iter: I) -> Result<(), d::OutOfMemory>
     where
         I: Iterator<Item = (&'a n::Memory, memory::Segment)>,
@@ -1954,7 +2652,7 @@ impl hal::device::Device<Backend> for Device {
             match memory.heap {
                 n::MemoryHeap::Native(_) => unimplemented!(),
                 n::MemoryHeap::Public(mt, ref cpu_buffer)
-                    if 1 << mt.0 != MemoryTypes::SHARED.bits() as usize =>
+                    if self.memory_kinds[mt.0] != MemoryTypes::SHARED =>
                 {
                     cpu_buffer.did_modify_range(NSRange {
                         location: range.start as _,
@@ -1962,7 +2660,9 @@ impl hal::device::Device<Backend> for Device {
                     });
                 }
                 n::MemoryHeap::Public(..) => continue,
-                n::MemoryHeap::Private => panic!("Can't map private memory!"),
+                n::MemoryHeap::Private | n::MemoryHeap::Memoryless => {
+                    panic!("Can't map private memory!")
+                }
             };
         }
 
@@ -1990,13 +2690,15 @@ impl hal::device::Device<Backend> for Device {
                 match memory.heap {
                     n::MemoryHeap::Native(_) => unimplemented!(),
                     n::MemoryHeap::Public(mt, ref cpu_buffer)
-                        if 1 << mt.0 != MemoryTypes::SHARED.bits() as usize =>
+                        if self.memory_kinds[mt.0] != MemoryTypes::SHARED =>
                     {
                         num_syncs += 1;
                         encoder.synchronize_resource(cpu_buffer);
                     }
                     n::MemoryHeap::Public(..) => continue,
-                    n::MemoryHeap::Private => panic!("Can't map private memory!"),
+                    n::MemoryHeap::Private | n::MemoryHeap::Memoryless => {
+                        panic!("Can't map private memory!")
+                    }
                 };
             }
             encoder.end_encoding();
@@ -2028,7 +2730,7 @@ impl hal::device::Device<Backend> for Device {
         &self,
         max_sets: usize,
         descriptor_ranges: I,
-        _flags: pso::DescriptorPoolCreateFlags,
+        flags: pso::DescriptorPoolCreateFlags,
     ) -> Result<n::DescriptorPool, d::OutOfMemory>
     where
         I: Iterator<Item = pso::DescriptorRangeDesc>,
@@ -2055,13 +2757,14 @@ impl hal::device::Device<Backend> for Device {
 
             let alignment = self.shared.private_caps.buffer_alignment;
             let total_size = encoder.encoded_length() + (max_sets as u64) * alignment;
-            let raw = device.new_buffer(total_size, MTLResourceOptions::empty());
 
             Ok(n::DescriptorPool::new_argument(
-                raw,
+                device.clone(),
                 total_size,
                 alignment,
                 total_resources,
+                max_sets,
+                flags,
             ))
         } else {
             let mut counters = n::ResourceData::<n::PoolResourceIndex>::new();
@@ -2071,7 +2774,7 @@ impl hal::device::Device<Backend> for Device {
                     dr.count as pso::DescriptorBinding,
                 );
             }
-            Ok(n::DescriptorPool::new_emulated(counters))
+            Ok(n::DescriptorPool::new_emulated(counters, max_sets, flags))
         }
     }
 
@@ -2084,11 +2787,20 @@ impl hal::device::Device<Backend> for Device {
         I: Iterator<Item = pso::DescriptorSetLayoutBinding>,
         J: Iterator<Item = &'a n::Sampler>,
     {
+        let layout_bindings = binding_iter.collect::<Vec<_>>();
+        if layout_bindings
+            .iter()
+            .any(|b| b.ty == pso::DescriptorType::InlineUniformBlock)
+        {
+            error!("Metal doesn't support INLINE_UNIFORM_BLOCK");
+            return Err(d::OutOfMemory::Host);
+        }
+
         if self.shared.private_caps.argument_buffers {
             let mut stage_flags = pso::ShaderStageFlags::empty();
             let mut arguments = n::ArgumentArray::default();
             let mut bindings = FastHashMap::default();
-            for desc in binding_iter {
+            for desc in layout_bindings {
                 //TODO: have the API providing the dimensions and MSAA flag
                 // for textures in an argument buffer
                 match desc.ty {
@@ -2108,9 +2820,8 @@ impl hal::device::Device<Backend> for Device {
                     | pso::DescriptorType::Buffer {
                         ty: pso::BufferDescriptorType::Storage { .. },
                         format: pso::BufferDescriptorFormat::Texel,
-                    } => {
-                        //TODO: bind storage buffers and images separately
-                        error!("Storage images are not yet supported in argument buffers!");
+                    } if !self.shared.private_caps.argument_buffers_tier2 => {
+                        error!("Storage images in argument buffers require Tier 2 hardware!");
                     }
                     _ => {}
                 }
@@ -2118,33 +2829,23 @@ impl hal::device::Device<Backend> for Device {
                 stage_flags |= desc.stage_flags;
                 let content = n::DescriptorContent::from(desc.ty);
                 let usage = n::ArgumentArray::describe_usage(desc.ty);
-                let bind_target = naga::back::msl::BindTarget {
-                    buffer: if content.contains(n::DescriptorContent::BUFFER) {
-                        Some(arguments.push(metal::MTLDataType::Pointer, desc.count, usage) as u8)
-                    } else {
-                        None
-                    },
-                    texture: if content.contains(n::DescriptorContent::TEXTURE) {
-                        Some(arguments.push(metal::MTLDataType::Texture, desc.count, usage) as u8)
-                    } else {
-                        None
-                    },
-                    sampler: if content.contains(n::DescriptorContent::SAMPLER) {
-                        Some(arguments.push(metal::MTLDataType::Sampler, desc.count, usage) as u8)
+                let mut push = |ty, kind| {
+                    if content.contains(kind) {
+                        Some(arguments.push(ty, desc.count, usage) as n::PoolResourceIndex)
                     } else {
                         None
-                    },
-                    mutable: content.contains(n::DescriptorContent::WRITABLE),
+                    }
+                };
+                let ids = n::ResourceData {
+                    buffers: push(metal::MTLDataType::Pointer, n::DescriptorContent::BUFFER),
+                    textures: push(metal::MTLDataType::Texture, n::DescriptorContent::TEXTURE),
+                    samplers: push(metal::MTLDataType::Sampler, n::DescriptorContent::SAMPLER),
                 };
-                let res_offset = bind_target
-                    .buffer
-                    .or(bind_target.texture)
-                    .or(bind_target.sampler)
-                    .unwrap() as u32;
+                let res_offset = ids.buffers.or(ids.textures).or(ids.samplers).unwrap();
                 bindings.insert(
                     desc.binding,
                     n::ArgumentLayout {
-                        bind_target,
+                        ids,
                         res_offset,
                         count: desc.count,
                         usage,
@@ -2173,7 +2874,7 @@ impl hal::device::Device<Backend> for Device {
             let mut desc_layouts = Vec::new();
             let mut total = n::ResourceData::new();
 
-            for slb in binding_iter {
+            for slb in layout_bindings {
                 let mut content = n::DescriptorContent::from(slb.ty);
                 total.add_many(content, slb.count as _);
 
@@ -2305,6 +3006,26 @@ impl hal::device::Device<Backend> for Device {
                                 range.start + sub.offset,
                             );
                         }
+                        pso::Descriptor::InlineUniformBlock(_) => {
+                            error!("Metal doesn't support INLINE_UNIFORM_BLOCK");
+                        }
+                        pso::Descriptor::Null => {
+                            if layout.content.contains(n::DescriptorContent::BUFFER) {
+                                data.buffers[counters.buffers as usize] = (
+                                    layout.stages,
+                                    Some(AsNative::from(self.shared.null_buffer.as_ref())),
+                                    0,
+                                );
+                            } else if layout.content.contains(n::DescriptorContent::TEXTURE) {
+                                data.textures[counters.textures as usize] = (
+                                    layout.stages,
+                                    Some(AsNative::from(self.shared.null_texture.as_ref())),
+                                    image::Layout::General,
+                                );
+                            } else {
+                                panic!("Can't write null into descriptor {:?}", layout);
+                            }
+                        }
                     }
                     counters.add(layout.content);
                 }
@@ -2380,6 +3101,28 @@ impl hal::device::Device<Backend> for Device {
                             data.ptr = (&**buf_raw).as_ptr();
                             arg_index += 1;
                         }
+                        pso::Descriptor::InlineUniformBlock(_) => {
+                            error!("Metal doesn't support INLINE_UNIFORM_BLOCK");
+                        }
+                        pso::Descriptor::Null => {
+                            let content = bindings[&op.binding].content;
+                            if content.contains(n::DescriptorContent::BUFFER) {
+                                let null_buffer = self.shared.null_buffer.as_ref();
+                                encoder.set_buffer(arg_index, null_buffer, 0);
+                                data.ptr = (&**null_buffer).as_ptr();
+                                arg_index += 1;
+                            } else if content.contains(n::DescriptorContent::TEXTURE) {
+                                let null_texture = self.shared.null_texture.as_ref();
+                                encoder.set_texture(arg_index, null_texture);
+                                data.ptr = (&**null_texture).as_ptr();
+                                // combined image-samplers share the index, see above
+                                if !content.contains(n::DescriptorContent::SAMPLER) {
+                                    arg_index += 1;
+                                }
+                            } else {
+                                panic!("Can't write null into descriptor {:?}", content);
+                            }
+                        }
                     }
                 }
             }
@@ -2413,24 +3156,28 @@ impl hal::device::Device<Backend> for Device {
         memory_type: hal::MemoryTypeId,
         size: u64,
     ) -> Result<n::Memory, d::AllocationError> {
-        let (storage, cache) = MemoryTypes::describe(memory_type.0);
+        let (storage, cache) = self.memory_kinds[memory_type.0].describe();
         let device = self.shared.device.lock();
         debug!("allocate_memory type {:?} of size {}", memory_type, size);
 
-        // Heaps cannot be used for CPU coherent resources
-        //TEMP: MacOS supports Private only, iOS and tvOS can do private/shared
-        let heap = if self.shared.private_caps.resource_heaps
-            && storage != MTLStorageMode::Shared
-            && false
+        // Only the private memory is backed by a heap, since the host visible one
+        // needs a CPU buffer for the mapping. MacOS doesn't allow shared heaps anyway.
+        let heap = if self.shared.private_caps.placement_heaps && storage == MTLStorageMode::Private
         {
             let descriptor = metal::HeapDescriptor::new();
             descriptor.set_storage_mode(storage);
             descriptor.set_cpu_cache_mode(cache);
             descriptor.set_size(size);
+            let () = msg_send![descriptor, setType: MTL_HEAP_TYPE_PLACEMENT];
             let heap_raw = device.new_heap(&descriptor);
+            if heap_raw.as_ptr().is_null() {
+                return Err(d::OutOfMemory::Device.into());
+            }
             n::MemoryHeap::Native(heap_raw)
         } else if storage == MTLStorageMode::Private {
             n::MemoryHeap::Private
+        } else if storage == MTLStorageMode::Memoryless {
+            n::MemoryHeap::Memoryless
         } else {
             let options = conv::resource_options_from_storage_and_cache(storage, cache);
             let cpu_buffer = device.new_buffer(size, options);
@@ -2472,8 +3219,11 @@ impl hal::device::Device<Backend> for Device {
         if self.shared.private_caps.resource_heaps {
             // We don't know what memory type the user will try to allocate the buffer with, so we test them
             // all get the most stringent ones.
-            for (i, _mt) in self.memory_types.iter().enumerate() {
-                let (storage, cache) = MemoryTypes::describe(i);
+            for &kind in self.memory_kinds.iter() {
+                if kind == MemoryTypes::MEMORYLESS {
+                    continue;
+                }
+                let (storage, cache) = kind.describe();
                 let options = conv::resource_options_from_storage_and_cache(storage, cache);
                 let requirements = self
                     .shared
@@ -2495,10 +3245,15 @@ impl hal::device::Device<Backend> for Device {
             size: (max_size + SIZE_MASK) & !SIZE_MASK,
             alignment: max_alignment,
             type_mask: if !supports_texel_view || self.shared.private_caps.shared_textures {
-                MemoryTypes::all().bits()
+                memory_type_mask(&self.memory_kinds, MemoryTypes::all() ^ MemoryTypes::MEMORYLESS)
             } else {
-                (MemoryTypes::all() ^ MemoryTypes::SHARED).bits()
+                memory_type_mask(
+                    &self.memory_kinds,
+                    MemoryTypes::all() ^ MemoryTypes::SHARED ^ MemoryTypes::MEMORYLESS,
+                )
             },
+            prefers_dedicated: false,
+            requires_dedicated: false,
         }
     }
 
@@ -2519,15 +3274,13 @@ impl hal::device::Device<Backend> for Device {
                     heap.storage_mode(),
                     heap.cpu_cache_mode(),
                 );
-                let raw = heap.new_buffer(size, options).unwrap_or_else(|| {
-                    // TODO: disable hazard tracking?
-                    self.shared.device.lock().new_buffer(size, options)
-                });
+                let raw = Self::placed_buffer(heap, size, options, offset)
+                    .ok_or(d::BindError::OutOfBounds)?;
                 raw.set_label(name);
                 n::Buffer::Bound {
                     raw,
                     options,
-                    range: 0..size, //TODO?
+                    range: 0..size,
                 }
             }
             n::MemoryHeap::Public(mt, ref cpu_buffer) => {
@@ -2535,7 +3288,7 @@ impl hal::device::Device<Backend> for Device {
                     "\tmapped to public heap with address {:?}",
                     cpu_buffer.as_ptr()
                 );
-                let (storage, cache) = MemoryTypes::describe(mt.0);
+                let (storage, cache) = self.memory_kinds[mt.0].describe();
                 let options = conv::resource_options_from_storage_and_cache(storage, cache);
                 if offset == 0x0 && size == cpu_buffer.length() {
                     cpu_buffer.set_label(name);
@@ -2566,6 +3319,7 @@ impl hal::device::Device<Backend> for Device {
                     range: 0..size,
                 }
             }
+            n::MemoryHeap::Memoryless => panic!("Buffers can't be bound to memoryless memory!"),
         };
 
         Ok(())
@@ -2735,12 +3489,21 @@ impl hal::device::Device<Backend> for Device {
             && format_desc.aspects.contains(format::Aspects::COLOR)
             && tiling == image::Tiling::Linear
             && host_usage.contains(usage);
+        let attachment_usage = image::Usage::TRANSIENT_ATTACHMENT
+            | image::Usage::COLOR_ATTACHMENT
+            | image::Usage::DEPTH_STENCIL_ATTACHMENT;
+        let memoryless = self.shared.private_caps.memoryless_storage
+            && usage.contains(image::Usage::TRANSIENT_ATTACHMENT)
+            && attachment_usage.contains(usage)
+            && tiling == image::Tiling::Optimal;
 
         Ok(n::Image {
             like: n::ImageLike::Unbound {
                 descriptor,
                 mip_sizes,
                 host_visible,
+                memoryless,
+                host_transfer: usage.contains(image::Usage::HOST_TRANSFER),
                 name: String::new(),
             },
             kind,
@@ -2753,13 +3516,14 @@ impl hal::device::Device<Backend> for Device {
     }
 
     unsafe fn get_image_requirements(&self, image: &n::Image) -> memory::Requirements {
-        let (descriptor, mip_sizes, host_visible) = match image.like {
+        let (descriptor, mip_sizes, host_visible, memoryless) = match image.like {
             n::ImageLike::Unbound {
                 ref descriptor,
                 ref mip_sizes,
                 host_visible,
+                memoryless,
                 ..
-            } => (descriptor, mip_sizes, host_visible),
+            } => (descriptor, mip_sizes, host_visible, memoryless),
             n::ImageLike::Texture(..) | n::ImageLike::Buffer(..) => {
                 panic!("Expected Image::Unbound")
             }
@@ -2775,11 +3539,11 @@ impl hal::device::Device<Backend> for Device {
             } else {
                 MemoryTypes::PRIVATE
             };
-            for (i, _) in self.memory_types.iter().enumerate() {
-                if !types.contains(MemoryTypes::from_bits(1 << i).unwrap()) {
+            for &kind in self.memory_kinds.iter() {
+                if !types.contains(kind) {
                     continue;
                 }
-                let (storage, cache_mode) = MemoryTypes::describe(i);
+                let (storage, cache_mode) = kind.describe();
                 descriptor.set_storage_mode(storage);
                 descriptor.set_cpu_cache_mode(cache_mode);
 
@@ -2791,10 +3555,18 @@ impl hal::device::Device<Backend> for Device {
                 max_size = cmp::max(max_size, requirements.size);
                 max_alignment = cmp::max(max_alignment, requirements.align);
             }
+            // Memoryless textures don't occupy any heap memory
+            let types = if memoryless {
+                types | MemoryTypes::MEMORYLESS
+            } else {
+                types
+            };
             memory::Requirements {
                 size: max_size,
                 alignment: max_alignment,
-                type_mask: types.bits(),
+                type_mask: memory_type_mask(&self.memory_kinds, types),
+                prefers_dedicated: false,
+                requires_dedicated: false,
             }
         } else if host_visible {
             assert_eq!(mip_sizes.len(), 1);
@@ -2802,13 +3574,25 @@ impl hal::device::Device<Backend> for Device {
             memory::Requirements {
                 size: (mip_sizes[0] + mask) & !mask,
                 alignment: self.shared.private_caps.buffer_alignment,
-                type_mask: MemoryTypes::all().bits(),
+                type_mask: memory_type_mask(
+                    &self.memory_kinds,
+                    MemoryTypes::all() ^ MemoryTypes::MEMORYLESS,
+                ),
+                prefers_dedicated: false,
+                requires_dedicated: false,
             }
         } else {
+            let types = if memoryless {
+                MemoryTypes::PRIVATE | MemoryTypes::MEMORYLESS
+            } else {
+                MemoryTypes::PRIVATE
+            };
             memory::Requirements {
                 size: mip_sizes.iter().sum(),
                 alignment: 4,
-                type_mask: MemoryTypes::PRIVATE.bits(),
+                type_mask: memory_type_mask(&self.memory_kinds, types),
+                prefers_dedicated: false,
+                requires_dedicated: false,
             }
         }
     }
@@ -2840,31 +3624,38 @@ impl hal::device::Device<Backend> for Device {
         image: &mut n::Image,
     ) -> Result<(), d::BindError> {
         let like = {
-            let (descriptor, mip_sizes, name) = match image.like {
+            let (descriptor, mip_sizes, host_transfer, name) = match image.like {
                 n::ImageLike::Unbound {
                     ref descriptor,
                     ref mip_sizes,
+                    host_transfer,
                     ref name,
                     ..
-                } => (descriptor, mip_sizes, name),
+                } => (descriptor, mip_sizes, host_transfer, name),
                 n::ImageLike::Texture(..) | n::ImageLike::Buffer(..) => {
                     panic!("Expected Image::Unbound")
                 }
             };
 
             match memory.heap {
+                // The host copies need the texture contents to be accessible by the CPU,
+                // which the private storage and the heaps don't allow.
+                n::MemoryHeap::Native(_) | n::MemoryHeap::Private if host_transfer => {
+                    descriptor.set_storage_mode(self.host_transfer_storage_mode());
+                    let texture = self.shared.device.lock().new_texture(descriptor);
+                    texture.set_label(name);
+                    n::ImageLike::Texture(texture)
+                }
                 n::MemoryHeap::Native(ref heap) => {
                     let resource_options = conv::resource_options_from_storage_and_cache(
                         heap.storage_mode(),
                         heap.cpu_cache_mode(),
                     );
                     descriptor.set_resource_options(resource_options);
-                    n::ImageLike::Texture(heap.new_texture(descriptor).unwrap_or_else(|| {
-                        // TODO: disable hazard tracking?
-                        let texture = self.shared.device.lock().new_texture(&descriptor);
-                        texture.set_label(name);
-                        texture
-                    }))
+                    let texture = Self::placed_texture(heap, descriptor, offset)
+                        .ok_or(d::BindError::OutOfBounds)?;
+                    texture.set_label(name);
+                    n::ImageLike::Texture(texture)
                 }
                 n::MemoryHeap::Public(_memory_type, ref cpu_buffer) => {
                     assert_eq!(mip_sizes.len(), 1);
@@ -2891,6 +3682,12 @@ impl hal::device::Device<Backend> for Device {
                     texture.set_label(name);
                     n::ImageLike::Texture(texture)
                 }
+                n::MemoryHeap::Memoryless => {
+                    descriptor.set_storage_mode(MTLStorageMode::Memoryless);
+                    let texture = self.shared.device.lock().new_texture(descriptor);
+                    texture.set_label(name);
+                    n::ImageLike::Texture(texture)
+                }
             }
         };
 
@@ -2996,12 +3793,17 @@ impl hal::device::Device<Backend> for Device {
             n::Fence::PendingSubmission(ref cmd_buf) => {
                 if timeout_ns == !0 {
                     cmd_buf.wait_until_completed();
+                    self.shared.check_command_buffer(cmd_buf)?;
                     return Ok(true);
                 }
                 let start = time::Instant::now();
                 loop {
-                    if let metal::MTLCommandBufferStatus::Completed = cmd_buf.status() {
-                        return Ok(true);
+                    match cmd_buf.status() {
+                        metal::MTLCommandBufferStatus::Completed => return Ok(true),
+                        metal::MTLCommandBufferStatus::Error => {
+                            self.shared.check_command_buffer(cmd_buf)?;
+                        }
+                        _ => {}
                     }
                     if to_ns(start.elapsed()) >= timeout_ns {
                         return Ok(false);
@@ -3018,6 +3820,10 @@ impl hal::device::Device<Backend> for Device {
             n::Fence::Idle { signaled } => signaled,
             n::Fence::PendingSubmission(ref cmd_buf) => match cmd_buf.status() {
                 metal::MTLCommandBufferStatus::Completed => true,
+                metal::MTLCommandBufferStatus::Error => {
+                    self.shared.check_command_buffer(cmd_buf)?;
+                    false
+                }
                 _ => false,
             },
         })
@@ -3156,11 +3962,20 @@ impl hal::device::Device<Backend> for Device {
         Ok(is_ready)
     }
 
-    fn wait_idle(&self) -> Result<(), d::OutOfMemory> {
-        command::QueueInner::wait_idle(&self.shared.queue);
+    fn wait_idle(&self) -> Result<(), d::WaitError> {
+        let cmd_buf = command::QueueInner::wait_idle(&self.shared.queue);
+        self.shared.check_command_buffer(&cmd_buf)?;
         Ok(())
     }
 
+    fn get_device_lost_reason(&self) -> Option<d::DeviceLostReason> {
+        let mut reason = self.shared.device_lost.lock().clone()?;
+        let progress = self.shared.checkpoints.lock();
+        reason.last_checkpoint = progress.last.clone();
+        reason.pending_checkpoints = progress.failed.clone();
+        Some(reason)
+    }
+
     unsafe fn set_image_name(&self, image: &mut n::Image, name: &str) {
         match image {
             n::Image {
@@ -64,7 +64,7 @@ use hal::{
 };
 use range_alloc::RangeAllocator;
 
-use cocoa_foundation::foundation::NSInteger;
+use cocoa_foundation::foundation::{NSInteger, NSUInteger};
 #[cfg(feature = "dispatch")]
 use dispatch;
 use foreign_types::ForeignTypeRef;
@@ -79,9 +79,10 @@ use parking_lot::{Condvar, Mutex};
 
 use std::{
     collections::HashMap,
+    ffi::CStr,
     hash::BuildHasherDefault,
     mem,
-    os::raw::c_void,
+    os::raw::{c_char, c_void},
     ptr::NonNull,
     sync::{Arc, Once},
 };
@@ -96,6 +97,7 @@ mod window;
 
 pub use crate::command::CommandPool;
 pub use crate::device::{Device, LanguageVersion, PhysicalDevice};
+pub use crate::native::DescriptorPoolUsage;
 pub use crate::window::Surface;
 
 pub type GraphicsCommandPool = CommandPool;
@@ -155,6 +157,12 @@ const MAX_ACTIVE_COMMAND_BUFFERS: usize = 1 << 14;
 const MAX_VISIBILITY_QUERIES: usize = 1 << 14;
 const MAX_COLOR_ATTACHMENTS: usize = 8;
 const MAX_BOUND_DESCRIPTOR_SETS: usize = 8;
+/// Size of the zeroed buffer bound in place of null descriptors.
+const NULL_BUFFER_SIZE: u64 = 1 << 16;
+
+// `MTLGPUFamily` values, not exposed by `metal-rs` yet.
+const MTL_GPU_FAMILY_APPLE7: NSInteger = 1007;
+const MTL_GPU_FAMILY_MAC2: NSInteger = 2002;
 
 #[derive(Debug, Clone, Copy)]
 pub struct QueueFamily {}
@@ -181,6 +189,17 @@ struct VisibilityShared {
     condvar: Condvar,
 }
 
+/// Progress of the submitted checkpoints, tracked by the completion handlers.
+/// Metal doesn't tell how far a failed command buffer went, so all of its
+/// checkpoints are considered pending.
+#[derive(Debug, Default)]
+struct CheckpointProgress {
+    /// Last checkpoint of the latest completed submission.
+    last: Option<String>,
+    /// Checkpoints of the failed submissions.
+    failed: Vec<String>,
+}
+
 #[derive(Debug)]
 struct Shared {
     device: Mutex<metal::Device>,
@@ -190,6 +209,13 @@ struct Shared {
     disabilities: PrivateDisabilities,
     private_caps: PrivateCapabilities,
     visibility: VisibilityShared,
+    /// Zeroed resources bound in place of `pso::Descriptor::Null`.
+    /// Unlike the native null descriptors, the writes through them are not discarded.
+    null_buffer: metal::Buffer,
+    null_texture: metal::Texture,
+    /// Reason of the first command buffer failure, after which the device is considered lost.
+    device_lost: Mutex<Option<hal::device::DeviceLostReason>>,
+    checkpoints: Mutex<CheckpointProgress>,
 }
 
 unsafe impl Send for Shared {}
@@ -213,6 +239,26 @@ impl Shared {
                 as hal::buffer::Offset,
             condvar: Condvar::new(),
         };
+
+        let zeros = vec![0u8; NULL_BUFFER_SIZE as usize];
+        let null_buffer = device.new_buffer_with_data(
+            zeros.as_ptr() as _,
+            NULL_BUFFER_SIZE,
+            metal::MTLResourceOptions::StorageModeShared,
+        );
+        // A single texel, aliasing the start of the zeroed buffer.
+        let descriptor = metal::TextureDescriptor::new();
+        descriptor.set_texture_type(metal::MTLTextureType::D2);
+        descriptor.set_width(1);
+        descriptor.set_height(1);
+        descriptor.set_mipmap_level_count(1);
+        descriptor.set_pixel_format(metal::MTLPixelFormat::RGBA8Unorm);
+        descriptor.set_resource_options(metal::MTLResourceOptions::StorageModeShared);
+        descriptor.set_storage_mode(metal::MTLStorageMode::Shared);
+        descriptor.set_usage(metal::MTLTextureUsage::ShaderRead);
+        let null_texture =
+            null_buffer.new_texture_with_descriptor(&descriptor, 0, private_caps.buffer_alignment);
+
         Shared {
             queue: Mutex::new(command::QueueInner::new(
                 &device,
@@ -228,13 +274,60 @@ impl Shared {
             private_caps,
             device: Mutex::new(device),
             visibility,
+            null_buffer,
+            null_texture,
+            device_lost: Mutex::new(None),
+            checkpoints: Mutex::new(CheckpointProgress::default()),
+        }
+    }
+
+    /// Checks the status of a finished command buffer, recording the device loss if it failed.
+    fn check_command_buffer(
+        &self,
+        cmd_buf: &metal::CommandBufferRef,
+    ) -> Result<(), hal::device::DeviceLost> {
+        let mut device_lost = self.device_lost.lock();
+        if device_lost.is_none() {
+            if let metal::MTLCommandBufferStatus::Error = cmd_buf.status() {
+                let (code, description) = unsafe {
+                    let error: *mut Object = msg_send![cmd_buf, error];
+                    let code: NSInteger = msg_send![error, code];
+                    let string: *mut Object = msg_send![error, localizedDescription];
+                    let utf8: *const c_char = msg_send![string, UTF8String];
+                    (code, CStr::from_ptr(utf8).to_string_lossy().into_owned())
+                };
+                error!("Command buffer failed with code {}: {}", code, description);
+                // Values of `MTLCommandBufferError`.
+                let cause = match code {
+                    2 => hal::device::DeviceLostCause::Hung,
+                    3 => hal::device::DeviceLostCause::PageFault,
+                    11 => hal::device::DeviceLostCause::Removed,
+                    _ => hal::device::DeviceLostCause::Unknown,
+                };
+                *device_lost = Some(hal::device::DeviceLostReason {
+                    cause,
+                    description,
+                    fault_address: None,
+                    last_checkpoint: None,
+                    pending_checkpoints: Vec::new(),
+                });
+            }
+        }
+        match *device_lost {
+            Some(_) => Err(hal::device::DeviceLost),
+            None => Ok(()),
         }
     }
 }
 
 #[derive(Clone, Debug, Default)]
 pub struct Experiments {
+    /// Back the descriptor sets with argument buffers, binding each set as a single buffer.
+    /// Tier 2 hardware also gets writable textures and unsized arrays in the sets.
     pub argument_buffers: bool,
+    /// Back the private memory with placement heaps, binding the resources at their offsets.
+    /// Metal doesn't track the hazards of the resources placed in a heap.
+    pub placement_heaps: bool,
 }
 
 #[derive(Debug)]
@@ -465,6 +558,7 @@ impl hal::Backend for Backend {
     type Image = native::Image;
     type ImageView = native::ImageView;
     type Sampler = native::Sampler;
+    type SamplerYcbcrConversion = ();
 
     type ComputePipeline = native::ComputePipeline;
     type GraphicsPipeline = native::GraphicsPipeline;
@@ -492,6 +586,16 @@ const RESOURCE_HEAP_SUPPORT: &[MTLFeatureSet] = &[
     MTLFeatureSet::macOS_GPUFamily2_v1,
 ];
 
+const MEMORYLESS_STORAGE_SUPPORT: &[MTLFeatureSet] = &[
+    MTLFeatureSet::iOS_GPUFamily1_v3,
+    MTLFeatureSet::iOS_GPUFamily2_v3,
+    MTLFeatureSet::iOS_GPUFamily3_v2,
+    MTLFeatureSet::iOS_GPUFamily4_v1,
+    MTLFeatureSet::iOS_GPUFamily5_v1,
+    MTLFeatureSet::tvOS_GPUFamily1_v2,
+    MTLFeatureSet::tvOS_GPUFamily2_v1,
+];
+
 const ARGUMENT_BUFFER_SUPPORT: &[MTLFeatureSet] = &[
     MTLFeatureSet::iOS_GPUFamily1_v4,
     MTLFeatureSet::iOS_GPUFamily2_v4,
@@ -674,19 +778,28 @@ struct PrivateCapabilities {
     // if TRUE, we'll report `NON_FILL_POLYGON_MODE` feature without the points support
     expose_line_mode: bool,
     resource_heaps: bool,
+    placement_heaps: bool,
     argument_buffers: bool,
+    argument_buffers_tier2: bool,
     shared_textures: bool,
+    memoryless_storage: bool,
     mutable_comparison_samplers: bool,
     sampler_clamp_to_border: bool,
     base_instance: bool,
     base_vertex_instance_drawing: bool,
     dual_source_blending: bool,
     low_power: bool,
+    unified_memory: bool,
+    working_set_size: u64,
     headless: bool,
     layered_rendering: bool,
     function_specialization: bool,
     depth_clip_mode: bool,
     texture_cube_array: bool,
+    present_timing: bool,
+    simdgroup_matrix: bool,
+    raster_order_groups: bool,
+    maximum_drawable_count: bool,
     format_depth24_stencil8: bool,
     format_depth32_stencil8_filter: bool,
     format_depth32_stencil8_none: bool,
@@ -757,6 +870,40 @@ impl PrivateCapabilities {
             .any(|x| raw.supports_feature_set(x))
     }
 
+    fn supports_family(raw: &metal::DeviceRef, family: NSInteger) -> bool {
+        let supported: BOOL = unsafe { msg_send![raw, supportsFamily: family] };
+        supported == YES
+    }
+
+    fn has_unified_memory(raw: &metal::DeviceRef) -> bool {
+        let unified: BOOL = unsafe { msg_send![raw, hasUnifiedMemory] };
+        unified == YES
+    }
+
+    fn supports_argument_buffers_tier2(raw: &metal::DeviceRef) -> bool {
+        // MTLArgumentBuffersTier2
+        let tier: NSUInteger = unsafe { msg_send![raw, argumentBuffersSupport] };
+        tier >= 1
+    }
+
+    fn supports_raster_order_groups(raw: &metal::DeviceRef) -> bool {
+        let supported: BOOL = unsafe { msg_send![raw, areRasterOrderGroupsSupported] };
+        supported == YES
+    }
+
+    /// Size of the memory the device can use without affecting its performance.
+    fn recommended_working_set_size(raw: &metal::DeviceRef) -> Option<u64> {
+        unsafe {
+            let responds: BOOL =
+                msg_send![raw, respondsToSelector: sel!(recommendedMaxWorkingSetSize)];
+            if responds == YES {
+                Some(msg_send![raw, recommendedMaxWorkingSetSize])
+            } else {
+                None
+            }
+        }
+    }
+
     fn new(device: &metal::Device, experiments: &Experiments) -> Self {
         #[repr(C)]
         #[derive(Clone, Copy, Debug)]
@@ -817,9 +964,21 @@ impl PrivateCapabilities {
             read_write_texture_tier: device.read_write_texture_support(),
             expose_line_mode: true,
             resource_heaps: Self::supports_any(&device, RESOURCE_HEAP_SUPPORT),
+            placement_heaps: experiments.placement_heaps
+                && Self::supports_any(&device, RESOURCE_HEAP_SUPPORT)
+                && if os_is_mac {
+                    Self::version_at_least(major, minor, 10, 15)
+                } else {
+                    Self::version_at_least(major, minor, 13, 0)
+                },
             argument_buffers: experiments.argument_buffers
                 && Self::supports_any(&device, ARGUMENT_BUFFER_SUPPORT),
+            argument_buffers_tier2: experiments.argument_buffers
+                && Self::supports_any(&device, ARGUMENT_BUFFER_SUPPORT)
+                && Self::supports_argument_buffers_tier2(&device),
             shared_textures: !os_is_mac,
+            memoryless_storage: !os_is_mac
+                && Self::supports_any(&device, MEMORYLESS_STORAGE_SUPPORT),
             mutable_comparison_samplers: Self::supports_any(
                 &device,
                 MUTABLE_COMPARISON_SAMPLER_SUPPORT,
@@ -829,11 +988,41 @@ impl PrivateCapabilities {
             base_vertex_instance_drawing: Self::supports_any(&device, BASE_VERTEX_INSTANCE_SUPPORT),
             dual_source_blending: Self::supports_any(&device, DUAL_SOURCE_BLEND_SUPPORT),
             low_power: !os_is_mac || device.is_low_power(),
+            // `hasUnifiedMemory` is only available since macOS 10.15, telling Apple silicon apart
+            unified_memory: !os_is_mac
+                || device.is_low_power()
+                || (Self::version_at_least(major, minor, 10, 15)
+                    && Self::has_unified_memory(&device)),
+            working_set_size: Self::recommended_working_set_size(&device).unwrap_or(!0),
             headless: os_is_mac && device.is_headless(),
             layered_rendering: Self::supports_any(&device, LAYERED_RENDERING_SUPPORT),
             function_specialization: Self::supports_any(&device, FUNCTION_SPECIALIZATION_SUPPORT),
             depth_clip_mode: Self::supports_any(&device, DEPTH_CLIP_MODE),
             texture_cube_array: Self::supports_any(&device, TEXTURE_CUBE_ARRAY_SUPPORT),
+            // `presentedTime` requires macOS 10.15.4, which can't be told apart from 10.15
+            present_timing: if os_is_mac {
+                Self::version_at_least(major, minor, 10, 16)
+            } else {
+                Self::version_at_least(major, minor, 10, 3)
+            },
+            // `simdgroup_matrix` requires MSL 2.3 and an Apple7 or Mac2 family GPU
+            simdgroup_matrix: if os_is_mac {
+                Self::version_at_least(major, minor, 11, 0)
+                    && Self::supports_family(&device, MTL_GPU_FAMILY_MAC2)
+            } else {
+                Self::version_at_least(major, minor, 14, 0)
+                    && Self::supports_family(&device, MTL_GPU_FAMILY_APPLE7)
+            },
+            raster_order_groups: if os_is_mac {
+                Self::version_at_least(major, minor, 10, 13)
+            } else {
+                Self::version_at_least(major, minor, 11, 0)
+            } && Self::supports_raster_order_groups(&device),
+            maximum_drawable_count: if os_is_mac {
+                Self::version_at_least(major, minor, 10, 13)
+            } else {
+                Self::version_at_least(major, minor, 11, 2)
+            },
             format_depth24_stencil8: os_is_mac && device.d24_s8_supported(),
             format_depth32_stencil8_filter: os_is_mac,
             format_depth32_stencil8_none: !os_is_mac,
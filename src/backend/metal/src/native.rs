@@ -16,6 +16,7 @@ use hal::{
 use range_alloc::RangeAllocator;
 
 use arrayvec::ArrayVec;
+use foreign_types::ForeignType;
 use metal;
 use parking_lot::RwLock;
 
@@ -41,6 +42,8 @@ pub struct ShaderModule {
     pub(crate) prefer_naga: bool,
     #[cfg(feature = "cross")]
     pub(crate) spv: Vec<u32>,
+    #[cfg(feature = "cross")]
+    pub(crate) spv_hash: u64,
     pub(crate) naga: Option<hal::device::NagaShader>,
 }
 
@@ -98,6 +101,9 @@ pub struct Subpass {
     pub attachments: SubpassData<AttachmentInfo>,
     pub inputs: Vec<AttachmentId>,
     pub samples: image::NumSamples,
+    /// Keep encoding into the render encoder of the previous subpass,
+    /// leaving the attachment contents in tile memory between the two.
+    pub merged: bool,
 }
 
 #[derive(Debug)]
@@ -216,6 +222,15 @@ impl fmt::Debug for PipelineCache {
     }
 }
 
+/// Key of the shader libraries cached by the device.
+#[cfg(feature = "cross")]
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct ShaderCacheKey {
+    pub spv_hash: u64,
+    pub specialization_hash: u64,
+    pub options: spirv_cross::msl::CompilerOptions,
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct RasterizerState {
     //TODO: more states
@@ -290,6 +305,9 @@ pub enum ImageLike {
         descriptor: metal::TextureDescriptor,
         mip_sizes: Vec<buffer::Offset>,
         host_visible: bool,
+        memoryless: bool,
+        /// Created with `Usage::HOST_TRANSFER`, requiring a CPU accessible texture.
+        host_transfer: bool,
         name: String,
     },
     /// This is a linearly tiled HOST-visible image, which is represented by a buffer.
@@ -447,26 +465,122 @@ pub struct DescriptorArgumentPoolInner {
     pub(crate) resources: Vec<UsedResource>,
 }
 
+/// A chunk of the argument buffer memory, sub-allocated between descriptor sets.
+#[derive(Debug)]
+pub struct DescriptorArgumentChunk {
+    pub(crate) raw: metal::Buffer,
+    pub(crate) allocator: RangeAllocator<buffer::Offset>,
+}
+
+/// Descriptor set accounting, shared by all kinds of descriptor pools.
+#[derive(Debug)]
+pub struct DescriptorPoolSets {
+    pub(crate) flags: pso::DescriptorPoolCreateFlags,
+    pub(crate) max: usize,
+    pub(crate) allocated: usize,
+}
+
+/// Usage statistics of a descriptor pool.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DescriptorPoolUsage {
+    /// Number of descriptor sets currently allocated.
+    pub sets: usize,
+    /// Maximum number of descriptor sets, as requested at creation.
+    pub max_sets: usize,
+    /// Number of descriptors currently allocated, over all resource kinds.
+    pub descriptors: PoolResourceIndex,
+    /// Maximum number of descriptors, as requested at creation.
+    pub max_descriptors: PoolResourceIndex,
+    /// Number of descriptors the pool storage holds. This exceeds
+    /// `max_descriptors` when the pool had to grow because of fragmentation.
+    pub capacity: PoolResourceIndex,
+}
+
 #[derive(Debug)]
 pub enum DescriptorPool {
     Emulated {
         inner: Arc<RwLock<DescriptorEmulatedPoolInner>>,
         allocators: ResourceData<RangeAllocator<PoolResourceIndex>>,
+        budget: ResourceData<PoolResourceIndex>,
+        sets: DescriptorPoolSets,
     },
     ArgumentBuffer {
-        raw: metal::Buffer,
-        raw_allocator: RangeAllocator<buffer::Offset>,
+        device: metal::Device,
+        chunks: Vec<DescriptorArgumentChunk>,
+        chunk_size: buffer::Offset,
         alignment: buffer::Offset,
         inner: Arc<RwLock<DescriptorArgumentPoolInner>>,
         res_allocator: RangeAllocator<PoolResourceIndex>,
+        budget: PoolResourceIndex,
+        sets: DescriptorPoolSets,
     },
 }
 //TODO: re-evaluate Send/Sync here
 unsafe impl Send for DescriptorPool {}
 unsafe impl Sync for DescriptorPool {}
 
+/// Allocates `count` entries from a pool created with the `budget` of them.
+///
+/// If there are enough free entries, but they are fragmented, and the pool
+/// `can_grow`, the allocator is extended by a chunk, up to twice the budget,
+/// and `grow` is called with the new size to extend the storage accordingly.
+fn allocate_pool_range(
+    allocator: &mut RangeAllocator<PoolResourceIndex>,
+    count: PoolResourceIndex,
+    budget: PoolResourceIndex,
+    can_grow: bool,
+    grow: impl FnOnce(PoolResourceIndex),
+) -> Result<Range<PoolResourceIndex>, pso::AllocationError> {
+    if count == 0 {
+        return Ok(0..0);
+    }
+    let size = allocator.initial_range().end;
+    if size - allocator.total_available() + count > budget {
+        return Err(pso::AllocationError::OutOfPoolMemory);
+    }
+    if let Ok(range) = allocator.allocate_range(count) {
+        return Ok(range);
+    }
+    let new_size = (size + count.max(budget / 4)).min(2 * budget);
+    if !can_grow || new_size <= size {
+        return Err(pso::AllocationError::FragmentedPool);
+    }
+    debug!("\tgrowing the pool from {} to {} entries", size, new_size);
+    allocator.grow_to(new_size);
+    grow(new_size);
+    allocator
+        .allocate_range(count)
+        .map_err(|_| pso::AllocationError::FragmentedPool)
+}
+
+/// Returns the number of bytes occupied by a descriptor set in an argument buffer.
+///
+/// Every allocation is a multiple of the alignment, so that all of them start aligned.
+fn argument_size(encoder: &metal::ArgumentEncoderRef, alignment: buffer::Offset) -> buffer::Offset {
+    (encoder.encoded_length().max(1) + alignment - 1) & !(alignment - 1)
+}
+
+impl DescriptorPoolSets {
+    fn new(flags: pso::DescriptorPoolCreateFlags, max: usize) -> Self {
+        DescriptorPoolSets {
+            flags,
+            max,
+            allocated: 0,
+        }
+    }
+
+    fn can_free(&self) -> bool {
+        self.flags
+            .contains(pso::DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET)
+    }
+}
+
 impl DescriptorPool {
-    pub(crate) fn new_emulated(counters: ResourceData<PoolResourceIndex>) -> Self {
+    pub(crate) fn new_emulated(
+        counters: ResourceData<PoolResourceIndex>,
+        max_sets: usize,
+        flags: pso::DescriptorPoolCreateFlags,
+    ) -> Self {
         let inner = DescriptorEmulatedPoolInner {
             samplers: vec![Default::default(); counters.samplers as usize],
             textures: vec![Default::default(); counters.textures as usize],
@@ -479,27 +593,86 @@ impl DescriptorPool {
                 textures: RangeAllocator::new(0..counters.textures),
                 buffers: RangeAllocator::new(0..counters.buffers),
             },
+            budget: counters,
+            sets: DescriptorPoolSets::new(flags, max_sets),
         }
     }
 
     pub(crate) fn new_argument(
-        raw: metal::Buffer,
+        device: metal::Device,
         total_bytes: buffer::Offset,
         alignment: buffer::Offset,
         total_resources: usize,
+        max_sets: usize,
+        flags: pso::DescriptorPoolCreateFlags,
     ) -> Self {
         let default = UsedResource {
             ptr: ptr::null_mut(),
             usage: metal::MTLResourceUsage::empty(),
         };
+        let raw = device.new_buffer(total_bytes, metal::MTLResourceOptions::empty());
         DescriptorPool::ArgumentBuffer {
-            raw,
-            raw_allocator: RangeAllocator::new(0..total_bytes),
+            device,
+            chunks: vec![DescriptorArgumentChunk {
+                raw,
+                allocator: RangeAllocator::new(0..total_bytes),
+            }],
+            chunk_size: total_bytes,
             alignment,
             inner: Arc::new(RwLock::new(DescriptorArgumentPoolInner {
                 resources: vec![default; total_resources],
             })),
             res_allocator: RangeAllocator::new(0..total_resources as PoolResourceIndex),
+            budget: total_resources as PoolResourceIndex,
+            sets: DescriptorPoolSets::new(flags, max_sets),
+        }
+    }
+
+    fn sets_mut(&mut self) -> &mut DescriptorPoolSets {
+        match *self {
+            DescriptorPool::Emulated { ref mut sets, .. }
+            | DescriptorPool::ArgumentBuffer { ref mut sets, .. } => sets,
+        }
+    }
+
+    /// Returns the current usage statistics of this pool.
+    pub fn usage(&self) -> DescriptorPoolUsage {
+        match *self {
+            DescriptorPool::Emulated {
+                ref allocators,
+                ref budget,
+                ref sets,
+                ..
+            } => {
+                let capacity = allocators.samplers.initial_range().end
+                    + allocators.textures.initial_range().end
+                    + allocators.buffers.initial_range().end;
+                let available = allocators.samplers.total_available()
+                    + allocators.textures.total_available()
+                    + allocators.buffers.total_available();
+                DescriptorPoolUsage {
+                    sets: sets.allocated,
+                    max_sets: sets.max,
+                    descriptors: capacity - available,
+                    max_descriptors: budget.samplers + budget.textures + budget.buffers,
+                    capacity,
+                }
+            }
+            DescriptorPool::ArgumentBuffer {
+                ref res_allocator,
+                budget,
+                ref sets,
+                ..
+            } => {
+                let capacity = res_allocator.initial_range().end;
+                DescriptorPoolUsage {
+                    sets: sets.allocated,
+                    max_sets: sets.max,
+                    descriptors: capacity - res_allocator.total_available(),
+                    max_descriptors: budget,
+                    capacity,
+                }
+            }
         }
     }
 
@@ -514,17 +687,22 @@ impl DescriptorPool {
                 );
             }
             DescriptorPool::ArgumentBuffer {
-                ref raw_allocator,
+                ref chunks,
                 ref res_allocator,
                 ..
             } => {
                 trace!(
-                    "\tavailable {} bytes for {} resources",
-                    raw_allocator.total_available(),
+                    "\tavailable {} bytes in {} chunks for {} resources",
+                    chunks
+                        .iter()
+                        .map(|chunk| chunk.allocator.total_available())
+                        .sum::<buffer::Offset>(),
+                    chunks.len(),
                     res_allocator.total_available(),
                 );
             }
         }
+        trace!("\t{:?}", self.usage());
     }
 }
 
@@ -534,10 +712,18 @@ impl pso::DescriptorPool<Backend> for DescriptorPool {
         set_layout: &DescriptorSetLayout,
     ) -> Result<DescriptorSet, pso::AllocationError> {
         self.report_available();
-        match *self {
+        {
+            let sets = self.sets_mut();
+            if sets.allocated >= sets.max {
+                return Err(pso::AllocationError::OutOfPoolMemory);
+            }
+        }
+        let result = match *self {
             DescriptorPool::Emulated {
                 ref inner,
                 ref mut allocators,
+                ref budget,
+                ref sets,
             } => {
                 debug!("pool: allocate_one");
                 let (layouts, total, immutable_samplers) = match *set_layout {
@@ -548,72 +734,78 @@ impl pso::DescriptorPool<Backend> for DescriptorPool {
                     } => (layouts, total, immutable_samplers),
                     _ => return Err(pso::AllocationError::IncompatibleLayout),
                 };
+                let can_grow = sets.can_free();
 
                 // try to allocate the ranges from the pool
-                let sampler_range = if total.samplers != 0 {
-                    match allocators.samplers.allocate_range(total.samplers as _) {
-                        Ok(range) => {
-                            // fill out the stages for immutable samplers
-                            let mut data = inner.write();
-                            let mut offset = range.start as usize;
-                            for layout in layouts.iter() {
-                                if layout.content.contains(DescriptorContent::SAMPLER) {
-                                    if immutable_samplers.contains_key(&layout.binding) {
-                                        data.samplers[offset] = (layout.stages, None);
-                                    }
-                                    offset += 1;
-                                }
-                            }
-                            range
-                        }
-                        Err(e) => {
-                            return Err(if e.fragmented_free_length >= total.samplers {
-                                pso::AllocationError::FragmentedPool
-                            } else {
-                                pso::AllocationError::OutOfPoolMemory
-                            });
+                let sampler_range = allocate_pool_range(
+                    &mut allocators.samplers,
+                    total.samplers,
+                    budget.samplers,
+                    can_grow,
+                    |size| {
+                        inner
+                            .write()
+                            .samplers
+                            .resize(size as usize, Default::default())
+                    },
+                )?;
+                let texture_range = match allocate_pool_range(
+                    &mut allocators.textures,
+                    total.textures,
+                    budget.textures,
+                    can_grow,
+                    |size| {
+                        inner
+                            .write()
+                            .textures
+                            .resize(size as usize, Default::default())
+                    },
+                ) {
+                    Ok(range) => range,
+                    Err(e) => {
+                        if sampler_range.end != 0 {
+                            allocators.samplers.free_range(sampler_range);
                         }
+                        return Err(e);
                     }
-                } else {
-                    0..0
                 };
-                let texture_range = if total.textures != 0 {
-                    match allocators.textures.allocate_range(total.textures as _) {
-                        Ok(range) => range,
-                        Err(e) => {
-                            if sampler_range.end != 0 {
-                                allocators.samplers.free_range(sampler_range);
-                            }
-                            return Err(if e.fragmented_free_length >= total.samplers {
-                                pso::AllocationError::FragmentedPool
-                            } else {
-                                pso::AllocationError::OutOfPoolMemory
-                            });
+                let buffer_range = match allocate_pool_range(
+                    &mut allocators.buffers,
+                    total.buffers,
+                    budget.buffers,
+                    can_grow,
+                    |size| {
+                        inner
+                            .write()
+                            .buffers
+                            .resize(size as usize, Default::default())
+                    },
+                ) {
+                    Ok(range) => range,
+                    Err(e) => {
+                        if sampler_range.end != 0 {
+                            allocators.samplers.free_range(sampler_range);
                         }
+                        if texture_range.end != 0 {
+                            allocators.textures.free_range(texture_range);
+                        }
+                        return Err(e);
                     }
-                } else {
-                    0..0
                 };
-                let buffer_range = if total.buffers != 0 {
-                    match allocators.buffers.allocate_range(total.buffers as _) {
-                        Ok(range) => range,
-                        Err(e) => {
-                            if sampler_range.end != 0 {
-                                allocators.samplers.free_range(sampler_range);
-                            }
-                            if texture_range.end != 0 {
-                                allocators.textures.free_range(texture_range);
+
+                if sampler_range.end != 0 {
+                    // fill out the stages for immutable samplers
+                    let mut data = inner.write();
+                    let mut offset = sampler_range.start as usize;
+                    for layout in layouts.iter() {
+                        if layout.content.contains(DescriptorContent::SAMPLER) {
+                            if immutable_samplers.contains_key(&layout.binding) {
+                                data.samplers[offset] = (layout.stages, None);
                             }
-                            return Err(if e.fragmented_free_length >= total.samplers {
-                                pso::AllocationError::FragmentedPool
-                            } else {
-                                pso::AllocationError::OutOfPoolMemory
-                            });
+                            offset += 1;
                         }
                     }
-                } else {
-                    0..0
-                };
+                }
 
                 let resources = ResourceData {
                     buffers: buffer_range,
@@ -621,18 +813,21 @@ impl pso::DescriptorPool<Backend> for DescriptorPool {
                     samplers: sampler_range,
                 };
 
-                Ok(DescriptorSet::Emulated {
+                DescriptorSet::Emulated {
                     pool: Arc::clone(inner),
                     layouts: Arc::clone(layouts),
                     resources,
-                })
+                }
             }
             DescriptorPool::ArgumentBuffer {
-                ref raw,
-                ref mut raw_allocator,
+                ref device,
+                ref mut chunks,
+                chunk_size,
                 alignment,
                 ref inner,
                 ref mut res_allocator,
+                budget,
+                ref sets,
             } => {
                 let (encoder, stage_flags, bindings, total) = match *set_layout {
                     DescriptorSetLayout::ArgumentBuffer {
@@ -644,20 +839,48 @@ impl pso::DescriptorPool<Backend> for DescriptorPool {
                     } => (encoder, stage_flags, bindings, total),
                     _ => return Err(pso::AllocationError::IncompatibleLayout),
                 };
-                let range = res_allocator
-                    .allocate_range(total as PoolResourceIndex)
-                    .map_err(|_| pso::AllocationError::OutOfPoolMemory)?;
-
-                let raw_range = raw_allocator
-                    .allocate_range(encoder.encoded_length() + alignment)
-                    .expect("Argument encoding length is inconsistent!");
-                let raw_offset = (raw_range.start + alignment - 1) & !(alignment - 1);
+                let range = allocate_pool_range(
+                    res_allocator,
+                    total as PoolResourceIndex,
+                    budget,
+                    sets.can_free(),
+                    |size| {
+                        let default = UsedResource {
+                            ptr: ptr::null_mut(),
+                            usage: metal::MTLResourceUsage::empty(),
+                        };
+                        inner.write().resources.resize(size as usize, default)
+                    },
+                )?;
+
+                let size = argument_size(encoder, alignment);
+                let found = chunks.iter_mut().find_map(|chunk| {
+                    let raw_range = chunk.allocator.allocate_range(size).ok()?;
+                    Some((chunk.raw.clone(), raw_range.start))
+                });
+                let (raw, raw_offset) = match found {
+                    Some(pair) => pair,
+                    None => {
+                        // The encoding size estimate at creation is not exact,
+                        // and the space can get fragmented, so add another chunk.
+                        let new_size = chunk_size.max(size);
+                        debug!("\tadding an argument buffer chunk of {} bytes", new_size);
+                        let raw = device.new_buffer(new_size, metal::MTLResourceOptions::empty());
+                        let mut allocator = RangeAllocator::new(0..new_size);
+                        let raw_range = allocator.allocate_range(size).unwrap();
+                        chunks.push(DescriptorArgumentChunk {
+                            raw: raw.clone(),
+                            allocator,
+                        });
+                        (raw, raw_range.start)
+                    }
+                };
 
                 #[cfg(feature = "cross")]
                 {
                     let mut data = inner.write();
                     for arg in bindings.values() {
-                        if arg.bind_target.buffer.is_some() || arg.bind_target.texture.is_some() {
+                        if arg.ids.buffers.is_some() || arg.ids.textures.is_some() {
                             let pos = (range.start + arg.res_offset) as usize;
                             for ur in data.resources[pos..pos + arg.count].iter_mut() {
                                 ur.usage = arg.usage;
@@ -666,27 +889,40 @@ impl pso::DescriptorPool<Backend> for DescriptorPool {
                     }
                 }
 
-                Ok(DescriptorSet::ArgumentBuffer {
-                    raw: raw.clone(),
+                DescriptorSet::ArgumentBuffer {
+                    raw,
                     raw_offset,
                     pool: Arc::clone(inner),
                     range,
                     encoder: encoder.clone(),
                     bindings: Arc::clone(bindings),
                     stage_flags,
-                })
+                }
             }
-        }
+        };
+
+        self.sets_mut().allocated += 1;
+        Ok(result)
     }
 
     unsafe fn free<I>(&mut self, descriptor_sets: I)
     where
         I: Iterator<Item = DescriptorSet>,
     {
+        if !self.sets_mut().can_free() {
+            auxil::report!(
+                Error,
+                "gfx::metal::device",
+                "descriptor-pool-free",
+                "Freeing descriptor sets from a pool created without `FREE_DESCRIPTOR_SET`"
+            );
+        }
         match self {
             DescriptorPool::Emulated {
                 ref inner,
                 ref mut allocators,
+                ref mut sets,
+                ..
             } => {
                 debug!("pool: free_sets");
                 let mut data = inner.write();
@@ -718,6 +954,7 @@ impl pso::DescriptorPool<Backend> for DescriptorPool {
                             if resources.buffers.start != resources.buffers.end {
                                 allocators.buffers.free_range(resources.buffers);
                             }
+                            sets.allocated -= 1;
                         }
                         DescriptorSet::ArgumentBuffer { .. } => panic!(
                             "Tried to free a DescriptorSet not given out by this DescriptorPool!"
@@ -726,9 +963,11 @@ impl pso::DescriptorPool<Backend> for DescriptorPool {
                 }
             }
             DescriptorPool::ArgumentBuffer {
-                ref mut raw_allocator,
+                ref mut chunks,
+                alignment,
                 ref mut res_allocator,
                 ref inner,
+                ref mut sets,
                 ..
             } => {
                 let mut data = inner.write();
@@ -738,6 +977,7 @@ impl pso::DescriptorPool<Backend> for DescriptorPool {
                             "Tried to free a DescriptorSet not given out by this DescriptorPool!"
                         ),
                         DescriptorSet::ArgumentBuffer {
+                            raw,
                             raw_offset,
                             range,
                             encoder,
@@ -750,9 +990,19 @@ impl pso::DescriptorPool<Backend> for DescriptorPool {
                                 ur.usage = metal::MTLResourceUsage::empty();
                             }
 
-                            let handle_range = raw_offset..raw_offset + encoder.encoded_length();
-                            raw_allocator.free_range(handle_range);
-                            res_allocator.free_range(range);
+                            let chunk = chunks
+                                .iter_mut()
+                                .find(|chunk| chunk.raw.as_ptr() == raw.as_ptr())
+                                .expect(
+                                    "Tried to free a DescriptorSet not given out by this DescriptorPool!",
+                                );
+                            let handle_range =
+                                raw_offset..raw_offset + argument_size(&encoder, *alignment);
+                            chunk.allocator.free_range(handle_range);
+                            if range.start != range.end {
+                                res_allocator.free_range(range);
+                            }
+                            sets.allocated -= 1;
                         }
                     }
                 }
@@ -766,8 +1016,11 @@ impl pso::DescriptorPool<Backend> for DescriptorPool {
             DescriptorPool::Emulated {
                 ref inner,
                 ref mut allocators,
+                ref budget,
+                ref mut sets,
             } => {
                 debug!("pool: reset");
+                sets.allocated = 0;
                 if allocators.samplers.is_empty()
                     && allocators.textures.is_empty()
                     && allocators.buffers.is_empty()
@@ -792,17 +1045,30 @@ impl pso::DescriptorPool<Backend> for DescriptorPool {
                     }
                 }
 
-                allocators.samplers.reset();
-                allocators.textures.reset();
-                allocators.buffers.reset();
+                // compact the storage back to the budget
+                data.samplers.truncate(budget.samplers as usize);
+                data.textures.truncate(budget.textures as usize);
+                data.buffers.truncate(budget.buffers as usize);
+                *allocators = ResourceData {
+                    samplers: RangeAllocator::new(0..budget.samplers),
+                    textures: RangeAllocator::new(0..budget.textures),
+                    buffers: RangeAllocator::new(0..budget.buffers),
+                };
             }
             DescriptorPool::ArgumentBuffer {
-                ref mut raw_allocator,
+                ref mut chunks,
+                ref inner,
                 ref mut res_allocator,
+                budget,
+                ref mut sets,
                 ..
             } => {
-                raw_allocator.reset();
-                res_allocator.reset();
+                sets.allocated = 0;
+                // the sets may still reference the extra chunks, but they are retained
+                chunks.truncate(1);
+                chunks[0].allocator.reset();
+                inner.write().resources.truncate(budget as usize);
+                *res_allocator = RangeAllocator::new(0..budget);
             }
         }
     }
@@ -851,6 +1117,8 @@ impl From<pso::DescriptorType> for DescriptorContent {
                 }
             }
             pso::DescriptorType::InputAttachment => DescriptorContent::TEXTURE,
+            // Set layouts with inline uniform blocks fail to be created.
+            pso::DescriptorType::InlineUniformBlock => DescriptorContent::empty(),
         }
     }
 }
@@ -866,7 +1134,8 @@ pub struct DescriptorLayout {
 
 #[derive(Debug)]
 pub struct ArgumentLayout {
-    pub(crate) bind_target: naga::back::msl::BindTarget,
+    /// Argument indices of the buffers, textures, and samplers of this binding.
+    pub(crate) ids: ResourceData<Option<PoolResourceIndex>>,
     pub(crate) res_offset: PoolResourceIndex,
     pub(crate) count: pso::DescriptorArrayIndex,
     pub(crate) usage: metal::MTLResourceUsage,
@@ -948,6 +1217,8 @@ pub(crate) enum MemoryHeap {
     Private,
     Public(MemoryTypeId, metal::Buffer),
     Native(metal::Heap),
+    /// Transient attachments only, nothing is actually allocated.
+    Memoryless,
 }
 
 #[derive(Default)]
@@ -977,6 +1248,7 @@ impl ArgumentArray {
                 },
             },
             Dt::InputAttachment => MTLResourceUsage::Sample,
+            Dt::InlineUniformBlock => MTLResourceUsage::Read,
         }
     }
 
@@ -7,14 +7,41 @@ use crate::{
 use hal::{format, image, window as w};
 
 use crate::CGRect;
+use cocoa_foundation::foundation::NSInteger;
 use objc::rc::autoreleasepool;
-use objc::runtime::Object;
+use objc::runtime::{Object, BOOL, NO, YES};
 use parking_lot::Mutex;
 
 use std::borrow::Borrow;
-use std::ptr::NonNull;
+use std::collections::VecDeque;
+use std::os::raw::c_void;
+use std::ptr::{self, NonNull};
 use std::thread;
 
+/// Maximum number of presented drawables kept around for timing feedback.
+const MAX_TRACKED_PRESENTS: usize = 16;
+/// Number of drawables `CAMetalLayer` uses by default.
+const DEFAULT_DRAWABLE_COUNT: w::SwapImageIndex = 3;
+
+#[link(name = "CoreGraphics", kind = "framework")]
+extern "C" {
+    fn CGColorSpaceCreateWithName(name: *mut Object) -> *mut c_void;
+    fn CGColorSpaceRelease(space: *mut c_void);
+}
+
+/// Color spaces `CAMetalLayer` can present in on the given OS.
+fn supported_color_spaces(caps: &crate::PrivateCapabilities) -> w::ColorSpace {
+    let mut color_spaces = w::ColorSpace::SRGB_NONLINEAR;
+    // The layer color space is only available on macOS
+    if caps.os_is_mac && caps.has_version_at_least(10, 12) {
+        color_spaces |= w::ColorSpace::LINEAR;
+    }
+    if caps.os_is_mac && caps.has_version_at_least(11, 0) {
+        color_spaces |= w::ColorSpace::HDR10;
+    }
+    color_spaces
+}
+
 #[derive(Debug)]
 pub struct Surface {
     view: Option<NonNull<Object>>,
@@ -22,6 +49,19 @@ pub struct Surface {
     swapchain_format: metal::MTLPixelFormat,
     swapchain_format_desc: format::FormatDesc,
     main_thread_id: thread::ThreadId,
+    /// Drawables presented with a timing request, which haven't been reported yet.
+    presented: VecDeque<(w::PresentTime, metal::MetalDrawable)>,
+    /// Command buffer of the last present.
+    last_present: Option<metal::CommandBuffer>,
+    /// The layer supports `displaySyncEnabled`, switching between `FIFO` and `IMMEDIATE`.
+    can_set_display_sync: bool,
+    /// The layer supports `EDRMetadata`, describing the HDR content.
+    can_set_edr_metadata: bool,
+    /// Wait for the previous present to be done by the GPU before acquiring the next image.
+    ///
+    /// This keeps the CPU from getting ahead of the GPU by more than a frame,
+    /// trading throughput for lower input latency.
+    pub low_latency: bool,
 }
 
 unsafe impl Send for Surface {}
@@ -40,7 +80,24 @@ impl Surface {
                 aspects: format::Aspects::empty(),
             },
             main_thread_id: thread::current().id(),
+            presented: VecDeque::new(),
+            last_present: None,
+            can_set_display_sync: false,
+            can_set_edr_metadata: false,
+            low_latency: false,
+        }
+    }
+
+    pub(crate) fn set_last_present(&mut self, command_buffer: metal::CommandBuffer) {
+        self.last_present = Some(command_buffer);
+    }
+
+    pub(crate) fn track_present(&mut self, time: w::PresentTime, drawable: metal::MetalDrawable) {
+        // Drawables that never make it to the screen would otherwise be kept forever
+        if self.presented.len() == MAX_TRACKED_PRESENTS {
+            self.presented.pop_front();
         }
+        self.presented.push_back((time, drawable));
     }
 
     pub(crate) fn dispose(self) {
@@ -67,8 +124,16 @@ impl Surface {
             caps.has_version_at_least(11, 0)
         };
         let can_set_display_sync = is_mac && caps.has_version_at_least(10, 13);
+        let can_set_edr_metadata = is_mac && caps.has_version_at_least(10, 15);
         let drawable_size =
             metal::CGSize::new(config.extent.width as f64, config.extent.height as f64);
+        let color_spaces = supported_color_spaces(caps);
+        let color_space = if color_spaces.contains(config.color_space) {
+            config.color_space
+        } else {
+            error!("Color space {:?} is not supported", config.color_space);
+            w::ColorSpace::SRGB_NONLINEAR
+        };
 
         match config.composite_alpha_mode {
             w::CompositeAlphaMode::OPAQUE => render_layer.set_opaque(true),
@@ -94,8 +159,21 @@ impl Surface {
             render_layer.set_pixel_format(mtl_format);
             render_layer.set_framebuffer_only(framebuffer_only as _);
 
-            // this gets ignored on iOS for certain OS/device combinations (iphone5s iOS 10.3)
-            let () = msg_send![*render_layer, setMaximumDrawableCount: config.image_count as u64];
+            if caps.maximum_drawable_count {
+                let count = config.image_count.max(2).min(3);
+                if count != config.image_count {
+                    warn!(
+                        "Unsupported swapchain image count {}, using {}",
+                        config.image_count, count
+                    );
+                }
+                let () = msg_send![*render_layer, setMaximumDrawableCount: count as u64];
+            } else if config.image_count != DEFAULT_DRAWABLE_COUNT {
+                warn!(
+                    "Swapchain image count can't be changed from {}",
+                    DEFAULT_DRAWABLE_COUNT
+                );
+            }
 
             render_layer.set_drawable_size(drawable_size);
             if can_set_next_drawable_timeout {
@@ -104,11 +182,62 @@ impl Surface {
             if can_set_display_sync {
                 let () = msg_send![*render_layer, setDisplaySyncEnabled: display_sync];
             }
+            // The layer is left untouched where the color space can't be set
+            if color_spaces != w::ColorSpace::SRGB_NONLINEAR {
+                let (name, extended_range): (&[u8], bool) = match color_space {
+                    w::ColorSpace::LINEAR => (b"kCGColorSpaceExtendedLinearSRGB\0", true),
+                    w::ColorSpace::HDR10 => (b"kCGColorSpaceITUR_2100_PQ\0", true),
+                    _ => (b"kCGColorSpaceSRGB\0", false),
+                };
+                let name: *mut Object =
+                    msg_send![class!(NSString), stringWithUTF8String: name.as_ptr()];
+                let raw_color_space = CGColorSpaceCreateWithName(name);
+                let () = msg_send![*render_layer, setColorspace: raw_color_space];
+                CGColorSpaceRelease(raw_color_space);
+                let () =
+                    msg_send![*render_layer, setWantsExtendedDynamicRangeContent: extended_range];
+            }
+            if can_set_edr_metadata {
+                let () = msg_send![*render_layer, setEDRMetadata: ptr::null_mut::<Object>()];
+            }
         };
 
         mtl_format
     }
 
+    /// Extended dynamic range of the screen showing the view, which is only known on macOS.
+    fn extended_dynamic_range(
+        &self,
+        caps: &crate::PrivateCapabilities,
+    ) -> Option<w::ExtendedDynamicRange> {
+        if !caps.os_is_mac || !caps.has_version_at_least(10, 11) {
+            return None;
+        }
+        unsafe {
+            let window: *mut Object = match self.view {
+                Some(view) => msg_send![view.as_ptr(), window],
+                None => ptr::null_mut(),
+            };
+            // Layers without a window are assumed to end up on the main screen
+            let screen: *mut Object = if window.is_null() {
+                msg_send![class!(NSScreen), mainScreen]
+            } else {
+                msg_send![window, screen]
+            };
+            if screen.is_null() {
+                return None;
+            }
+            let max_component_value: metal::CGFloat =
+                msg_send![screen, maximumExtendedDynamicRangeColorComponentValue];
+            let render_layer = self.render_layer.lock();
+            let enabled: BOOL = msg_send![*render_layer, wantsExtendedDynamicRangeContent];
+            Some(w::ExtendedDynamicRange {
+                max_component_value: max_component_value as f32,
+                enabled: enabled == YES,
+            })
+        }
+    }
+
     fn dimensions(&self) -> w::Extent2D {
         let (size, scale): (metal::CGSize, metal::CGFloat) = match self.view {
             Some(view) if !cfg!(target_os = "macos") => unsafe {
@@ -177,17 +306,19 @@ impl w::Surface<Backend> for Surface {
     }
 
     fn capabilities(&self, physical_device: &PhysicalDevice) -> w::SurfaceCapabilities {
-        let current_extent = if self.main_thread_id == thread::current().id() {
-            Some(self.dimensions())
-        } else {
-            warn!("Unable to get the current view dimensions on a non-main thread");
-            None
-        };
-
         let device_caps = &physical_device.shared.private_caps;
 
-        let can_set_maximum_drawables_count =
-            device_caps.os_is_mac || device_caps.has_version_at_least(11, 2);
+        let (current_extent, extended_dynamic_range) =
+            if self.main_thread_id == thread::current().id() {
+                (
+                    Some(self.dimensions()),
+                    self.extended_dynamic_range(device_caps),
+                )
+            } else {
+                warn!("Unable to get the current view dimensions on a non-main thread");
+                (None, None)
+            };
+
         let can_set_display_sync =
             device_caps.os_is_mac && device_caps.has_version_at_least(10, 13);
 
@@ -201,12 +332,12 @@ impl w::Surface<Backend> for Surface {
                 | w::CompositeAlphaMode::POSTMULTIPLIED
                 | w::CompositeAlphaMode::INHERIT,
             //Note: this is hardcoded in `CAMetalLayer` documentation
-            image_count: if can_set_maximum_drawables_count {
+            image_count: if device_caps.maximum_drawable_count {
                 2..=3
             } else {
                 // 3 is the default in `CAMetalLayer` documentation
                 // iOS 10.3 was tested to use 3 on iphone5s
-                3..=3
+                DEFAULT_DRAWABLE_COUNT..=DEFAULT_DRAWABLE_COUNT
             },
             current_extent,
             extents: w::Extent2D {
@@ -221,15 +352,25 @@ impl w::Surface<Backend> for Surface {
             //| image::Usage::SAMPLED
             //| image::Usage::TRANSFER_SRC
             //| image::Usage::TRANSFER_DST,
+            current_transform: w::SurfaceTransform::IDENTITY,
+            transforms: w::SurfaceTransform::IDENTITY,
+            color_spaces: supported_color_spaces(device_caps),
+            extended_dynamic_range,
         }
     }
 
-    fn supported_formats(&self, _physical_device: &PhysicalDevice) -> Option<Vec<format::Format>> {
-        Some(vec![
+    fn supported_formats(&self, physical_device: &PhysicalDevice) -> Option<Vec<format::Format>> {
+        let caps = &physical_device.shared.private_caps;
+        let mut formats = vec![
             format::Format::Bgra8Unorm,
             format::Format::Bgra8Srgb,
             format::Format::Rgba16Sfloat,
-        ])
+        ];
+        // 10-bit formats, used for the HDR10 content
+        if caps.os_is_mac && caps.has_version_at_least(10, 13) {
+            formats.push(format::Format::A2b10g10r10Unorm);
+        }
+        Some(formats)
     }
 }
 
@@ -243,17 +384,104 @@ impl w::PresentationSurface<Backend> for Surface {
     ) -> Result<(), w::SwapchainError> {
         assert!(image::Usage::COLOR_ATTACHMENT.contains(config.image_usage));
         self.swapchain_format = self.configure(&device.shared, &config);
+        let caps = &device.shared.private_caps;
+        self.can_set_display_sync = caps.os_is_mac && caps.has_version_at_least(10, 13);
+        self.can_set_edr_metadata = caps.os_is_mac && caps.has_version_at_least(10, 15);
+        Ok(())
+    }
+
+    unsafe fn set_present_mode(
+        &mut self,
+        mode: w::PresentMode,
+    ) -> Result<(), w::PresentModeIncompatible> {
+        if self.swapchain_format == metal::MTLPixelFormat::Invalid || !self.can_set_display_sync {
+            return Err(w::PresentModeIncompatible);
+        }
+        let display_sync = match mode {
+            w::PresentMode::FIFO => true,
+            w::PresentMode::IMMEDIATE => false,
+            _ => return Err(w::PresentModeIncompatible),
+        };
+        let render_layer = self.render_layer.lock();
+        let () = msg_send![*render_layer, setDisplaySyncEnabled: display_sync];
         Ok(())
     }
 
     unsafe fn unconfigure_swapchain(&mut self, _device: &Device) {
         self.swapchain_format = metal::MTLPixelFormat::Invalid;
+        self.presented.clear();
+        self.last_present = None;
+    }
+
+    unsafe fn get_refresh_duration(&mut self) -> Option<u64> {
+        let screen: *mut Object = if cfg!(target_os = "macos") {
+            msg_send![class!(NSScreen), mainScreen]
+        } else {
+            msg_send![class!(UIScreen), mainScreen]
+        };
+        if screen.is_null() {
+            return None;
+        }
+        // Only available since macOS 12 and iOS 10.3
+        let responds: BOOL = msg_send![screen, respondsToSelector: sel!(maximumFramesPerSecond)];
+        if responds == NO {
+            return None;
+        }
+        let fps: NSInteger = msg_send![screen, maximumFramesPerSecond];
+        if fps > 0 {
+            Some(1_000_000_000 / fps as u64)
+        } else {
+            None
+        }
+    }
+
+    unsafe fn get_past_presentation_timing(&mut self) -> Vec<w::PresentationTiming> {
+        let mut timings = Vec::new();
+        while let Some(&(time, ref drawable)) = self.presented.front() {
+            // Zero until the drawable is on screen
+            let presented_time: f64 = msg_send![drawable.as_ref(), presentedTime];
+            if presented_time == 0.0 {
+                break;
+            }
+            timings.push(w::PresentationTiming {
+                present_id: time.present_id,
+                desired_present_time: time.desired_present_time,
+                actual_present_time: (presented_time * 1_000_000_000.0) as u64,
+            });
+            self.presented.pop_front();
+        }
+        timings
+    }
+
+    unsafe fn set_hdr_metadata(
+        &mut self,
+        metadata: &w::HdrMetadata,
+    ) -> Result<(), w::HdrMetadataUnsupported> {
+        if self.swapchain_format == metal::MTLPixelFormat::Invalid || !self.can_set_edr_metadata {
+            return Err(w::HdrMetadataUnsupported);
+        }
+        // Only the luminance range of the mastering display is taken into account.
+        // The linear value of 1.0 is the 100 nits reference white.
+        let edr_metadata: *mut Object = msg_send![class!(CAEDRMetadata),
+            HDR10MetadataWithMinLuminance: metadata.min_luminance
+            maxLuminance: metadata.max_luminance
+            opticalOutputScale: 100.0f32
+        ];
+        let render_layer = self.render_layer.lock();
+        let () = msg_send![*render_layer, setEDRMetadata: edr_metadata];
+        Ok(())
     }
 
     unsafe fn acquire_image(
         &mut self,
         _timeout_ns: u64, //TODO: use the timeout
     ) -> Result<(Self::SwapchainImage, Option<w::Suboptimal>), w::AcquireError> {
+        if let Some(command_buffer) = self.last_present.take() {
+            if self.low_latency {
+                command_buffer.wait_until_completed();
+            }
+        }
+
         let render_layer = self.render_layer.lock();
         let (drawable, texture) = autoreleasepool(|| {
             let drawable = render_layer.next_drawable().unwrap();
@@ -0,0 +1,888 @@
+//! Command buffers, recorded as a list of commands and replayed at submission.
+
+use crate::{
+    raster::{self, Renderer},
+    resource::{
+        Buffer, DescriptorSet, Descriptors, Event, Framebuffer, GraphicsPipeline, Image, ImageData,
+        ImageView, PipelineState, RenderPass, RenderPassInfo, Slice, Subpass,
+    },
+    Backend, NOT_SUPPORTED_MESSAGE,
+};
+
+use hal::{buffer, command, image, memory, pool, pso, query};
+
+use std::{
+    ops::Range,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+#[derive(Clone, Debug)]
+enum Command {
+    BeginRenderPass {
+        info: Arc<RenderPassInfo>,
+        attachments: Vec<(ImageView, command::ClearValue)>,
+        area: pso::Rect,
+    },
+    NextSubpass,
+    EndRenderPass,
+    BindPipeline(Arc<PipelineState>),
+    BindVertexBuffers {
+        first: u32,
+        buffers: Vec<Slice>,
+    },
+    BindIndexBuffer(Slice, hal::IndexType),
+    BindDescriptorSets {
+        first: usize,
+        sets: Vec<Arc<Descriptors>>,
+    },
+    PushConstants {
+        offset: u32,
+        data: Vec<u32>,
+    },
+    SetViewports {
+        first: u32,
+        viewports: Vec<pso::Viewport>,
+    },
+    SetScissors {
+        first: u32,
+        rects: Vec<pso::Rect>,
+    },
+    SetBlendConstants(pso::ColorValue),
+    Draw {
+        vertices: Range<u32>,
+        instances: Range<u32>,
+    },
+    DrawIndexed {
+        indices: Range<u32>,
+        base_vertex: i32,
+        instances: Range<u32>,
+    },
+    DrawIndirect {
+        buffer: Slice,
+        count: u32,
+        stride: u32,
+        indexed: bool,
+    },
+    ClearAttachments {
+        clears: Vec<command::AttachmentClear>,
+        rects: Vec<pso::ClearRect>,
+    },
+    CopyBuffer {
+        src: Slice,
+        dst: Slice,
+        regions: Vec<command::BufferCopy>,
+    },
+    CopyImage {
+        src: ImageData,
+        dst: ImageData,
+        regions: Vec<command::ImageCopy>,
+    },
+    CopyBufferToImage {
+        src: Slice,
+        dst: ImageData,
+        regions: Vec<command::BufferImageCopy>,
+    },
+    CopyImageToBuffer {
+        src: ImageData,
+        dst: Slice,
+        regions: Vec<command::BufferImageCopy>,
+    },
+    FillBuffer {
+        dst: Slice,
+        data: u32,
+    },
+    UpdateBuffer {
+        dst: Slice,
+        data: Vec<u8>,
+    },
+    ClearImage {
+        image: ImageData,
+        value: command::ClearValue,
+        ranges: Vec<image::SubresourceRange>,
+    },
+    SetEvent(Arc<AtomicBool>, bool),
+}
+
+#[derive(Debug)]
+pub struct CommandPool;
+
+impl pool::CommandPool<Backend> for CommandPool {
+    unsafe fn allocate_one(&mut self, level: command::Level) -> CommandBuffer {
+        assert_eq!(
+            level,
+            command::Level::Primary,
+            "Only primary command buffers are supported"
+        );
+        CommandBuffer {
+            commands: Vec::new(),
+        }
+    }
+
+    unsafe fn reset(&mut self, _release_resources: bool) {}
+
+    unsafe fn free<I>(&mut self, _buffers: I)
+    where
+        I: Iterator<Item = CommandBuffer>,
+    {
+    }
+}
+
+/// Command buffer, keeping the resources it references alive until it's reset.
+#[derive(Debug)]
+pub struct CommandBuffer {
+    commands: Vec<Command>,
+}
+
+impl CommandBuffer {
+    /// Execute the recorded commands.
+    pub(crate) fn execute(&self) {
+        let mut renderer = Renderer::default();
+        for command in &self.commands {
+            execute(&mut renderer, command);
+        }
+    }
+}
+
+/// Iterate over the offsets of the texel rows touched by a copy between a buffer and an image.
+///
+/// Yields the buffer offset, the image offset and the row size in bytes.
+pub(crate) fn image_rows<'a>(
+    image: &'a ImageData,
+    region: &'a command::BufferImageCopy,
+) -> impl Iterator<Item = (u64, u64, u64)> + 'a {
+    let texel_size = image.layout.texel_size;
+    let extent = region.image_extent;
+    let row_pitch = region.buffer_width.max(extent.width) as u64 * texel_size;
+    let slice_pitch = region.buffer_height.max(extent.height) as u64 * row_pitch;
+    let layer_pitch = slice_pitch * extent.depth as u64;
+    let layers = region.image_layers.layers.clone();
+    let level = region.image_layers.level;
+    let offset = region.image_offset;
+    layers.enumerate().flat_map(move |(index, layer)| {
+        (0..extent.depth).flat_map(move |z| {
+            (0..extent.height).filter_map(move |y| {
+                let image_offset = image.offset(
+                    level,
+                    layer,
+                    offset.x as u32,
+                    offset.y as u32 + y,
+                    offset.z as u32 + z,
+                )?;
+                let buffer_offset = region.buffer_offset
+                    + index as u64 * layer_pitch
+                    + z as u64 * slice_pitch
+                    + y as u64 * row_pitch;
+                Some((
+                    buffer_offset,
+                    image_offset,
+                    extent.width as u64 * texel_size,
+                ))
+            })
+        })
+    })
+}
+
+fn draw_indirect(renderer: &Renderer, buffer: &Slice, count: u32, stride: u32, indexed: bool) {
+    let words = if indexed { 5 } else { 4 };
+    for draw in 0..count as u64 {
+        let mut args = [0u32; 5];
+        for (i, arg) in args.iter_mut().take(words).enumerate() {
+            let mut bytes = [0u8; 4];
+            buffer.read(draw * stride as u64 + 4 * i as u64, &mut bytes);
+            *arg = u32::from_le_bytes(bytes);
+        }
+        if indexed {
+            let [index_count, instance_count, first_index, vertex_offset, first_instance] = args;
+            renderer.draw_indexed(
+                first_index..first_index + index_count,
+                vertex_offset as i32,
+                first_instance..first_instance + instance_count,
+            );
+        } else {
+            let [vertex_count, instance_count, first_vertex, first_instance, _] = args;
+            renderer.draw(
+                first_vertex..first_vertex + vertex_count,
+                first_instance..first_instance + instance_count,
+            );
+        }
+    }
+}
+
+fn execute(renderer: &mut Renderer, command: &Command) {
+    match *command {
+        Command::BeginRenderPass {
+            ref info,
+            ref attachments,
+            area,
+        } => renderer.begin_render_pass(Arc::clone(info), attachments.clone(), area),
+        Command::NextSubpass => renderer.next_subpass(),
+        Command::EndRenderPass => renderer.end_render_pass(),
+        Command::BindPipeline(ref pipeline) => renderer.bind_pipeline(Arc::clone(pipeline)),
+        Command::BindVertexBuffers { first, ref buffers } => {
+            renderer.bind_vertex_buffers(first, buffers)
+        }
+        Command::BindIndexBuffer(ref buffer, ty) => renderer.bind_index_buffer(buffer.clone(), ty),
+        Command::BindDescriptorSets { first, ref sets } => {
+            renderer.bind_descriptor_sets(first, sets)
+        }
+        Command::PushConstants { offset, ref data } => renderer.push_constants(offset, data),
+        Command::SetViewports {
+            first,
+            ref viewports,
+        } => renderer.set_viewports(first, viewports),
+        Command::SetScissors { first, ref rects } => renderer.set_scissors(first, rects),
+        Command::SetBlendConstants(constants) => renderer.set_blend_constants(constants),
+        Command::Draw {
+            ref vertices,
+            ref instances,
+        } => renderer.draw(vertices.clone(), instances.clone()),
+        Command::DrawIndexed {
+            ref indices,
+            base_vertex,
+            ref instances,
+        } => renderer.draw_indexed(indices.clone(), base_vertex, instances.clone()),
+        Command::DrawIndirect {
+            ref buffer,
+            count,
+            stride,
+            indexed,
+        } => draw_indirect(renderer, buffer, count, stride, indexed),
+        Command::ClearAttachments {
+            ref clears,
+            ref rects,
+        } => renderer.clear_attachments(clears, rects),
+        Command::CopyBuffer {
+            ref src,
+            ref dst,
+            ref regions,
+        } => {
+            for region in regions {
+                src.copy_to(region.src, dst, region.dst, region.size);
+            }
+        }
+        Command::CopyImage {
+            ref src,
+            ref dst,
+            ref regions,
+        } => {
+            for region in regions {
+                let layers = region.src_subresource.layers.clone();
+                for (index, src_layer) in layers.enumerate() {
+                    let dst_layer = region.dst_subresource.layers.start + index as image::Layer;
+                    for z in 0..region.extent.depth {
+                        for y in 0..region.extent.height {
+                            let src_offset = src.offset(
+                                region.src_subresource.level,
+                                src_layer,
+                                region.src_offset.x as u32,
+                                region.src_offset.y as u32 + y,
+                                region.src_offset.z as u32 + z,
+                            );
+                            let dst_offset = dst.offset(
+                                region.dst_subresource.level,
+                                dst_layer,
+                                region.dst_offset.x as u32,
+                                region.dst_offset.y as u32 + y,
+                                region.dst_offset.z as u32 + z,
+                            );
+                            if let (Some(src_offset), Some(dst_offset)) = (src_offset, dst_offset) {
+                                let size = region.extent.width as u64 * src.layout.texel_size;
+                                src.slice.copy_to(src_offset, &dst.slice, dst_offset, size);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Command::CopyBufferToImage {
+            ref src,
+            ref dst,
+            ref regions,
+        } => {
+            for region in regions {
+                for (buffer_offset, image_offset, size) in image_rows(dst, region) {
+                    src.copy_to(buffer_offset, &dst.slice, image_offset, size);
+                }
+            }
+        }
+        Command::CopyImageToBuffer {
+            ref src,
+            ref dst,
+            ref regions,
+        } => {
+            for region in regions {
+                for (buffer_offset, image_offset, size) in image_rows(src, region) {
+                    src.slice.copy_to(image_offset, dst, buffer_offset, size);
+                }
+            }
+        }
+        Command::FillBuffer { ref dst, data } => {
+            let bytes = data.to_le_bytes();
+            for offset in (0..dst.size / 4).map(|word| word * 4) {
+                dst.write(offset, &bytes);
+            }
+        }
+        Command::UpdateBuffer { ref dst, ref data } => dst.write(0, data),
+        Command::ClearImage {
+            ref image,
+            value,
+            ref ranges,
+        } => {
+            let value = raster::clear_value(image.format(), value);
+            for range in ranges {
+                let levels = range.level_start
+                    ..range
+                        .level_count
+                        .map_or(image.layout.levels.len() as image::Level, |count| {
+                            range.level_start + count
+                        });
+                let layers = range.layer_start
+                    ..range
+                        .layer_count
+                        .map_or(image.layout.layers, |count| range.layer_start + count);
+                for level in levels {
+                    let extent = match image.level(level) {
+                        Some(layout) => layout.extent,
+                        None => continue,
+                    };
+                    for layer in layers.clone() {
+                        for z in 0..extent.depth {
+                            for y in 0..extent.height {
+                                for x in 0..extent.width {
+                                    image.write(level, layer, x, y, z, &value);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Command::SetEvent(ref event, value) => event.store(value, Ordering::Release),
+    }
+}
+
+impl command::CommandBuffer<Backend> for CommandBuffer {
+    unsafe fn begin(
+        &mut self,
+        _flags: command::CommandBufferFlags,
+        _inheritance_info: command::CommandBufferInheritanceInfo<Backend>,
+    ) {
+        self.commands.clear();
+    }
+
+    unsafe fn finish(&mut self) {}
+
+    unsafe fn reset(&mut self, release_resources: bool) {
+        if release_resources {
+            self.commands = Vec::new();
+        } else {
+            self.commands.clear();
+        }
+    }
+
+    unsafe fn pipeline_barrier<'a, T>(
+        &mut self,
+        _stages: Range<pso::PipelineStage>,
+        _dependencies: memory::Dependencies,
+        _barriers: T,
+    ) where
+        T: Iterator<Item = memory::Barrier<'a, Backend>>,
+    {
+        // Commands are executed in order, so there is nothing to synchronize
+    }
+
+    unsafe fn fill_buffer(&mut self, buffer: &Buffer, range: buffer::SubRange, data: u32) {
+        self.commands.push(Command::FillBuffer {
+            dst: buffer.slice(&range),
+            data,
+        });
+    }
+
+    unsafe fn update_buffer(&mut self, buffer: &Buffer, offset: buffer::Offset, data: &[u8]) {
+        self.commands.push(Command::UpdateBuffer {
+            dst: buffer.slice(&buffer::SubRange {
+                offset,
+                size: Some(data.len() as buffer::Offset),
+            }),
+            data: data.to_vec(),
+        });
+    }
+
+    unsafe fn clear_image<T>(
+        &mut self,
+        image: &Image,
+        _layout: image::Layout,
+        value: command::ClearValue,
+        subresource_ranges: T,
+    ) where
+        T: Iterator<Item = image::SubresourceRange>,
+    {
+        self.commands.push(Command::ClearImage {
+            image: image.data().clone(),
+            value,
+            ranges: subresource_ranges.collect(),
+        });
+    }
+
+    unsafe fn clear_attachments<T, U>(&mut self, clears: T, rects: U)
+    where
+        T: Iterator<Item = command::AttachmentClear>,
+        U: Iterator<Item = pso::ClearRect>,
+    {
+        self.commands.push(Command::ClearAttachments {
+            clears: clears.collect(),
+            rects: rects.collect(),
+        });
+    }
+
+    unsafe fn resolve_image<T>(
+        &mut self,
+        _: &Image,
+        _: image::Layout,
+        _: &Image,
+        _: image::Layout,
+        _: T,
+    ) where
+        T: Iterator<Item = command::ImageResolve>,
+    {
+        unimplemented!("{}", NOT_SUPPORTED_MESSAGE)
+    }
+
+    unsafe fn blit_image<T>(
+        &mut self,
+        _: &Image,
+        _: image::Layout,
+        _: &Image,
+        _: image::Layout,
+        _: image::Filter,
+        _: T,
+    ) where
+        T: Iterator<Item = command::ImageBlit>,
+    {
+        unimplemented!("{}", NOT_SUPPORTED_MESSAGE)
+    }
+
+    unsafe fn bind_index_buffer(
+        &mut self,
+        buffer: &Buffer,
+        sub: buffer::SubRange,
+        ty: hal::IndexType,
+    ) {
+        self.commands
+            .push(Command::BindIndexBuffer(buffer.slice(&sub), ty));
+    }
+
+    unsafe fn bind_vertex_buffers<'a, T>(&mut self, first_binding: pso::BufferIndex, buffers: T)
+    where
+        T: Iterator<Item = (&'a Buffer, buffer::SubRange)>,
+    {
+        self.commands.push(Command::BindVertexBuffers {
+            first: first_binding,
+            buffers: buffers.map(|(buffer, sub)| buffer.slice(&sub)).collect(),
+        });
+    }
+
+    unsafe fn set_viewports<T>(&mut self, first_viewport: u32, viewports: T)
+    where
+        T: Iterator<Item = pso::Viewport>,
+    {
+        self.commands.push(Command::SetViewports {
+            first: first_viewport,
+            viewports: viewports.collect(),
+        });
+    }
+
+    unsafe fn set_scissors<T>(&mut self, first_scissor: u32, rects: T)
+    where
+        T: Iterator<Item = pso::Rect>,
+    {
+        self.commands.push(Command::SetScissors {
+            first: first_scissor,
+            rects: rects.collect(),
+        });
+    }
+
+    unsafe fn set_stencil_reference(&mut self, _faces: pso::Face, _value: pso::StencilValue) {
+        // Stencil testing is not supported
+    }
+
+    unsafe fn set_stencil_read_mask(&mut self, _faces: pso::Face, _value: pso::StencilValue) {
+        // Stencil testing is not supported
+    }
+
+    unsafe fn set_stencil_write_mask(&mut self, _faces: pso::Face, _value: pso::StencilValue) {
+        // Stencil testing is not supported
+    }
+
+    unsafe fn set_blend_constants(&mut self, color: pso::ColorValue) {
+        self.commands.push(Command::SetBlendConstants(color));
+    }
+
+    unsafe fn set_depth_bounds(&mut self, _bounds: Range<f32>) {
+        // Depth bounds testing is not supported
+    }
+
+    unsafe fn set_line_width(&mut self, _width: f32) {
+        // Lines are not rasterized
+    }
+
+    unsafe fn set_depth_bias(&mut self, _depth_bias: pso::DepthBias) {
+        // Depth bias is not supported
+    }
+
+    unsafe fn set_shading_rate(&mut self, _: pso::ShadingRate, _: [pso::ShadingRateCombiner; 2]) {
+        unimplemented!("{}", NOT_SUPPORTED_MESSAGE)
+    }
+
+    unsafe fn set_sample_locations(&mut self, _: image::NumSamples, _: &[pso::SampleLocation]) {
+        unimplemented!("{}", NOT_SUPPORTED_MESSAGE)
+    }
+
+    unsafe fn begin_render_pass<'a, T>(
+        &mut self,
+        render_pass: &RenderPass,
+        _framebuffer: &Framebuffer,
+        render_area: pso::Rect,
+        attachments: T,
+        _first_subpass: command::SubpassContents,
+    ) where
+        T: Iterator<Item = command::RenderAttachmentInfo<'a, Backend>>,
+    {
+        self.commands.push(Command::BeginRenderPass {
+            info: Arc::clone(&render_pass.info),
+            attachments: attachments
+                .map(|info| (info.image_view.clone(), info.clear_value))
+                .collect(),
+            area: render_area,
+        });
+    }
+
+    unsafe fn next_subpass(&mut self, _contents: command::SubpassContents) {
+        self.commands.push(Command::NextSubpass);
+    }
+
+    unsafe fn end_render_pass(&mut self) {
+        self.commands.push(Command::EndRenderPass);
+    }
+
+    unsafe fn begin_rendering(&mut self, info: &command::RenderingInfo<Backend>) {
+        let render_pass = RenderPassInfo {
+            attachments: info.attachments().map(|at| info.attachment_desc(at)).collect(),
+            subpasses: vec![Subpass {
+                colors: (0..info.colors.len()).collect(),
+                depth_stencil: info.depth_stencil.map(|_| info.colors.len()),
+            }],
+        };
+        self.commands.push(Command::BeginRenderPass {
+            info: Arc::new(render_pass),
+            attachments: info
+                .attachments()
+                .map(|at| (at.image_view.clone(), at.clear_value))
+                .collect(),
+            area: info.render_area,
+        });
+    }
+
+    unsafe fn end_rendering(&mut self) {
+        self.commands.push(Command::EndRenderPass);
+    }
+
+    unsafe fn bind_graphics_pipeline(&mut self, pipeline: &GraphicsPipeline) {
+        self.commands
+            .push(Command::BindPipeline(Arc::clone(&pipeline.state)));
+    }
+
+    unsafe fn bind_graphics_descriptor_sets<'a, I, J>(
+        &mut self,
+        _layout: &(),
+        first_set: usize,
+        sets: I,
+        offsets: J,
+    ) where
+        I: Iterator<Item = &'a DescriptorSet>,
+        J: Iterator<Item = command::DescriptorSetOffset>,
+    {
+        let mut offsets = offsets;
+        self.commands.push(Command::BindDescriptorSets {
+            first: first_set,
+            sets: sets
+                .map(|set| Arc::new(set.resolve(&mut offsets)))
+                .collect(),
+        });
+    }
+
+    unsafe fn bind_compute_pipeline(&mut self, _: &()) {
+        unimplemented!("{}", NOT_SUPPORTED_MESSAGE)
+    }
+
+    unsafe fn bind_compute_descriptor_sets<'a, I, J>(&mut self, _: &(), _: usize, _: I, _: J)
+    where
+        I: Iterator<Item = &'a DescriptorSet>,
+        J: Iterator<Item = command::DescriptorSetOffset>,
+    {
+        unimplemented!("{}", NOT_SUPPORTED_MESSAGE)
+    }
+
+    unsafe fn dispatch(&mut self, _: hal::WorkGroupCount) {
+        unimplemented!("{}", NOT_SUPPORTED_MESSAGE)
+    }
+
+    unsafe fn dispatch_indirect(&mut self, _: &Buffer, _: buffer::Offset) {
+        unimplemented!("{}", NOT_SUPPORTED_MESSAGE)
+    }
+
+    unsafe fn copy_buffer<T>(&mut self, src: &Buffer, dst: &Buffer, regions: T)
+    where
+        T: Iterator<Item = command::BufferCopy>,
+    {
+        self.commands.push(Command::CopyBuffer {
+            src: buffer_slice(src),
+            dst: buffer_slice(dst),
+            regions: regions.collect(),
+        });
+    }
+
+    unsafe fn copy_image<T>(
+        &mut self,
+        src: &Image,
+        _src_layout: image::Layout,
+        dst: &Image,
+        _dst_layout: image::Layout,
+        regions: T,
+    ) where
+        T: Iterator<Item = command::ImageCopy>,
+    {
+        self.commands.push(Command::CopyImage {
+            src: src.data().clone(),
+            dst: dst.data().clone(),
+            regions: regions.collect(),
+        });
+    }
+
+    unsafe fn copy_buffer_to_image<T>(
+        &mut self,
+        src: &Buffer,
+        dst: &Image,
+        _dst_layout: image::Layout,
+        regions: T,
+    ) where
+        T: Iterator<Item = command::BufferImageCopy>,
+    {
+        self.commands.push(Command::CopyBufferToImage {
+            src: buffer_slice(src),
+            dst: dst.data().clone(),
+            regions: regions.collect(),
+        });
+    }
+
+    unsafe fn copy_image_to_buffer<T>(
+        &mut self,
+        src: &Image,
+        _src_layout: image::Layout,
+        dst: &Buffer,
+        regions: T,
+    ) where
+        T: Iterator<Item = command::BufferImageCopy>,
+    {
+        self.commands.push(Command::CopyImageToBuffer {
+            src: src.data().clone(),
+            dst: buffer_slice(dst),
+            regions: regions.collect(),
+        });
+    }
+
+    unsafe fn draw(
+        &mut self,
+        vertices: Range<hal::VertexCount>,
+        instances: Range<hal::InstanceCount>,
+    ) {
+        self.commands.push(Command::Draw {
+            vertices,
+            instances,
+        });
+    }
+
+    unsafe fn draw_indexed(
+        &mut self,
+        indices: Range<hal::IndexCount>,
+        base_vertex: hal::VertexOffset,
+        instances: Range<hal::InstanceCount>,
+    ) {
+        self.commands.push(Command::DrawIndexed {
+            indices,
+            base_vertex,
+            instances,
+        });
+    }
+
+    unsafe fn draw_indirect(
+        &mut self,
+        buffer: &Buffer,
+        offset: buffer::Offset,
+        draw_count: hal::DrawCount,
+        stride: buffer::Stride,
+    ) {
+        self.commands.push(Command::DrawIndirect {
+            buffer: buffer.slice(&buffer::SubRange { offset, size: None }),
+            count: draw_count,
+            stride,
+            indexed: false,
+        });
+    }
+
+    unsafe fn draw_indexed_indirect(
+        &mut self,
+        buffer: &Buffer,
+        offset: buffer::Offset,
+        draw_count: hal::DrawCount,
+        stride: buffer::Stride,
+    ) {
+        self.commands.push(Command::DrawIndirect {
+            buffer: buffer.slice(&buffer::SubRange { offset, size: None }),
+            count: draw_count,
+            stride,
+            indexed: true,
+        });
+    }
+
+    unsafe fn draw_indirect_count(
+        &mut self,
+        _: &Buffer,
+        _: buffer::Offset,
+        _: &Buffer,
+        _: buffer::Offset,
+        _: u32,
+        _: buffer::Stride,
+    ) {
+        unimplemented!("{}", NOT_SUPPORTED_MESSAGE)
+    }
+
+    unsafe fn draw_indexed_indirect_count(
+        &mut self,
+        _: &Buffer,
+        _: buffer::Offset,
+        _: &Buffer,
+        _: buffer::Offset,
+        _: u32,
+        _: buffer::Stride,
+    ) {
+        unimplemented!("{}", NOT_SUPPORTED_MESSAGE)
+    }
+
+    unsafe fn draw_mesh_tasks(&mut self, _: hal::TaskCount, _: hal::TaskCount) {
+        unimplemented!("{}", NOT_SUPPORTED_MESSAGE)
+    }
+
+    unsafe fn draw_mesh_tasks_indirect(
+        &mut self,
+        _: &Buffer,
+        _: buffer::Offset,
+        _: hal::DrawCount,
+        _: buffer::Stride,
+    ) {
+        unimplemented!("{}", NOT_SUPPORTED_MESSAGE)
+    }
+
+    unsafe fn draw_mesh_tasks_indirect_count(
+        &mut self,
+        _: &Buffer,
+        _: buffer::Offset,
+        _: &Buffer,
+        _: buffer::Offset,
+        _: u32,
+        _: buffer::Stride,
+    ) {
+        unimplemented!("{}", NOT_SUPPORTED_MESSAGE)
+    }
+
+    unsafe fn set_event(&mut self, event: &Event, _stages: pso::PipelineStage) {
+        self.commands
+            .push(Command::SetEvent(Arc::clone(&event.set), true));
+    }
+
+    unsafe fn reset_event(&mut self, event: &Event, _stages: pso::PipelineStage) {
+        self.commands
+            .push(Command::SetEvent(Arc::clone(&event.set), false));
+    }
+
+    unsafe fn wait_events<'a, I, J>(
+        &mut self,
+        _events: I,
+        _stages: Range<pso::PipelineStage>,
+        _barriers: J,
+    ) where
+        I: Iterator<Item = &'a Event>,
+        J: Iterator<Item = memory::Barrier<'a, Backend>>,
+    {
+        // Events are set by the earlier commands of the same queue
+    }
+
+    unsafe fn begin_query(&mut self, _: query::Query<Backend>, _: query::ControlFlags) {
+        unimplemented!("{}", NOT_SUPPORTED_MESSAGE)
+    }
+
+    unsafe fn end_query(&mut self, _: query::Query<Backend>) {
+        unimplemented!("{}", NOT_SUPPORTED_MESSAGE)
+    }
+
+    unsafe fn reset_query_pool(&mut self, _: &(), _: Range<query::Id>) {
+        unimplemented!("{}", NOT_SUPPORTED_MESSAGE)
+    }
+
+    unsafe fn copy_query_pool_results(
+        &mut self,
+        _: &(),
+        _: Range<query::Id>,
+        _: &Buffer,
+        _: buffer::Offset,
+        _: buffer::Stride,
+        _: query::ResultFlags,
+    ) {
+        unimplemented!("{}", NOT_SUPPORTED_MESSAGE)
+    }
+
+    unsafe fn write_timestamp(&mut self, _: pso::PipelineStage, _: query::Query<Backend>) {
+        unimplemented!("{}", NOT_SUPPORTED_MESSAGE)
+    }
+
+    unsafe fn push_graphics_constants(
+        &mut self,
+        _layout: &(),
+        _stages: pso::ShaderStageFlags,
+        offset: u32,
+        constants: &[u32],
+    ) {
+        self.commands.push(Command::PushConstants {
+            offset,
+            data: constants.to_vec(),
+        });
+    }
+
+    unsafe fn push_compute_constants(&mut self, _: &(), _: u32, _: &[u32]) {
+        unimplemented!("{}", NOT_SUPPORTED_MESSAGE)
+    }
+
+    unsafe fn execute_commands<'a, T>(&mut self, cmd_buffers: T)
+    where
+        T: Iterator<Item = &'a CommandBuffer>,
+    {
+        for cmd_buffer in cmd_buffers {
+            self.commands.extend(cmd_buffer.commands.iter().cloned());
+        }
+    }
+
+    unsafe fn insert_debug_marker(&mut self, _name: &str, _color: u32) {}
+
+    unsafe fn begin_debug_marker(&mut self, _name: &str, _color: u32) {}
+
+    unsafe fn end_debug_marker(&mut self) {}
+
+    unsafe fn set_checkpoint(&mut self, _label: &str) {}
+}
+
+fn buffer_slice(buffer: &Buffer) -> Slice {
+    buffer.slice(&buffer::SubRange::WHOLE)
+}
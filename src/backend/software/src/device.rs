@@ -0,0 +1,742 @@
+use crate::{
+    command::{image_rows, CommandBuffer, CommandPool},
+    resource::{
+        Buffer, Descriptor, DescriptorPool, DescriptorSet, DescriptorSetLayout, Descriptors, Event,
+        Fence, Framebuffer, GraphicsPipeline, Image, ImageLayout, ImageView, Memory, PipelineState,
+        RenderPass, RenderPassInfo, Sampler, ShaderModule, Stage, Subpass,
+    },
+    spirv, Backend, NOT_SUPPORTED_MESSAGE,
+};
+
+use hal::{buffer, command, device, format, image, memory, pass, pool, pso, query, queue};
+
+use std::{
+    ops::Range,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+/// Alignment of the buffers and images in memory.
+const ALIGNMENT: u64 = 16;
+
+#[derive(Debug)]
+pub struct Device;
+
+fn create_stage(
+    entry: &pso::EntryPoint<Backend>,
+    execution_model: u32,
+) -> Result<Stage, pso::CreationError> {
+    let module = &entry.module.module;
+    let function = module
+        .entry_point(entry.entry, execution_model)
+        .ok_or_else(|| pso::CreationError::MissingEntryPoint(entry.entry.to_string()))?;
+    let constants = module
+        .specialize(&entry.specialization)
+        .map_err(pso::CreationError::InvalidSpecialization)?;
+    Ok(Stage {
+        module: Arc::clone(module),
+        function,
+        constants,
+    })
+}
+
+/// Move to the next bindings when the array index is past the end of the binding.
+fn next_descriptor(
+    bindings: &[pso::DescriptorSetLayoutBinding],
+    mut binding: pso::DescriptorBinding,
+    mut index: pso::DescriptorArrayIndex,
+) -> Option<(&pso::DescriptorSetLayoutBinding, pso::DescriptorArrayIndex)> {
+    let last = bindings.iter().map(|layout| layout.binding).max()?;
+    while binding <= last {
+        match bindings.iter().find(|layout| layout.binding == binding) {
+            Some(layout) if index < layout.count as pso::DescriptorArrayIndex => {
+                return Some((layout, index))
+            }
+            Some(layout) => {
+                index -= layout.count as pso::DescriptorArrayIndex;
+                binding += 1;
+            }
+            None => binding += 1,
+        }
+    }
+    None
+}
+
+impl pso::DescriptorPool<Backend> for DescriptorPool {
+    unsafe fn allocate_one(
+        &mut self,
+        layout: &DescriptorSetLayout,
+    ) -> Result<DescriptorSet, pso::AllocationError> {
+        Ok(DescriptorSet {
+            bindings: Arc::clone(&layout.bindings),
+            descriptors: layout.immutable_samplers.clone(),
+        })
+    }
+
+    unsafe fn free<I>(&mut self, descriptor_sets: I)
+    where
+        I: Iterator<Item = DescriptorSet>,
+    {
+        for _ in descriptor_sets {
+            // Let the descriptor set drop
+        }
+    }
+
+    unsafe fn reset(&mut self) {}
+}
+
+impl device::Device<Backend> for Device {
+    unsafe fn allocate_memory(
+        &self,
+        _memory_type: hal::MemoryTypeId,
+        size: u64,
+    ) -> Result<Memory, device::AllocationError> {
+        Memory::allocate(size)
+    }
+
+    unsafe fn free_memory(&self, _memory: Memory) {
+        // Let the memory drop, the storage is released once no resource references it
+    }
+
+    unsafe fn create_command_pool(
+        &self,
+        _family: queue::QueueFamilyId,
+        _create_flags: pool::CommandPoolCreateFlags,
+    ) -> Result<CommandPool, device::OutOfMemory> {
+        Ok(CommandPool)
+    }
+
+    unsafe fn destroy_command_pool(&self, _pool: CommandPool) {}
+
+    unsafe fn create_render_pass<'a, Ia, Is, Id>(
+        &self,
+        attachments: Ia,
+        subpasses: Is,
+        _dependencies: Id,
+    ) -> Result<RenderPass, device::OutOfMemory>
+    where
+        Ia: Iterator<Item = pass::Attachment>,
+        Is: Iterator<Item = pass::SubpassDesc<'a>>,
+        Id: Iterator<Item = pass::SubpassDependency>,
+    {
+        let info = RenderPassInfo {
+            attachments: attachments.collect(),
+            subpasses: subpasses
+                .map(|subpass| {
+                    assert_eq!(subpass.view_mask, 0, "Multiview is not supported");
+                    Subpass {
+                        colors: subpass.colors.iter().map(|&(id, _)| id).collect(),
+                        depth_stencil: subpass.depth_stencil.map(|&(id, _)| id),
+                    }
+                })
+                .collect(),
+        };
+        Ok(RenderPass {
+            info: Arc::new(info),
+        })
+    }
+
+    unsafe fn destroy_render_pass(&self, _render_pass: RenderPass) {}
+
+    unsafe fn create_pipeline_layout<'a, Is, Ic>(
+        &self,
+        _set_layouts: Is,
+        _push_constant_ranges: Ic,
+    ) -> Result<(), device::OutOfMemory>
+    where
+        Is: Iterator<Item = &'a DescriptorSetLayout>,
+        Ic: Iterator<Item = (pso::ShaderStageFlags, Range<u32>)>,
+    {
+        Ok(())
+    }
+
+    unsafe fn destroy_pipeline_layout(&self, _layout: ()) {}
+
+    unsafe fn create_pipeline_cache(
+        &self,
+        _data: Option<&[u8]>,
+    ) -> Result<(), device::OutOfMemory> {
+        Ok(())
+    }
+
+    unsafe fn get_pipeline_cache_data(&self, _cache: &()) -> Result<Vec<u8>, device::OutOfMemory> {
+        Ok(Vec::new())
+    }
+
+    unsafe fn merge_pipeline_caches<'a, I>(
+        &self,
+        _target: &mut (),
+        _sources: I,
+    ) -> Result<(), device::OutOfMemory>
+    where
+        I: Iterator<Item = &'a ()>,
+    {
+        Ok(())
+    }
+
+    unsafe fn destroy_pipeline_cache(&self, _cache: ()) {}
+
+    unsafe fn create_graphics_pipeline<'a>(
+        &self,
+        desc: &pso::GraphicsPipelineDesc<'a, Backend>,
+        _cache: Option<&()>,
+    ) -> Result<GraphicsPipeline, pso::CreationError> {
+        let (buffers, attributes, input_assembler, vertex) = match desc.primitive_assembler {
+            pso::PrimitiveAssemblerDesc::Vertex {
+                buffers,
+                attributes,
+                ref input_assembler,
+                ref vertex,
+                tessellation: None,
+                geometry: None,
+            } => (buffers, attributes, input_assembler, vertex),
+            _ => return Err(pso::CreationError::UnsupportedPipeline),
+        };
+        let fragment = match desc.fragment {
+            Some(ref entry) => Some(create_stage(entry, spirv::model::FRAGMENT)?),
+            None => None,
+        };
+        let state = PipelineState {
+            vertex: create_stage(vertex, spirv::model::VERTEX)?,
+            flat_inputs: fragment
+                .as_ref()
+                .map_or(Vec::new(), |stage| stage.module.flat_inputs()),
+            fragment,
+            buffers: buffers.to_vec(),
+            attributes: attributes.to_vec(),
+            primitive: input_assembler.primitive,
+            rasterizer: desc.rasterizer.clone(),
+            blender: desc.blender.clone(),
+            depth: desc.depth_stencil.depth,
+            baked_states: desc.baked_states.clone(),
+        };
+        Ok(GraphicsPipeline {
+            state: Arc::new(state),
+        })
+    }
+
+    unsafe fn destroy_graphics_pipeline(&self, _pipeline: GraphicsPipeline) {}
+
+    unsafe fn create_compute_pipeline<'a>(
+        &self,
+        _desc: &pso::ComputePipelineDesc<'a, Backend>,
+        _cache: Option<&()>,
+    ) -> Result<(), pso::CreationError> {
+        Err(pso::CreationError::UnsupportedPipeline)
+    }
+
+    unsafe fn destroy_compute_pipeline(&self, _pipeline: ()) {}
+
+    unsafe fn create_framebuffer<I>(
+        &self,
+        _render_pass: &RenderPass,
+        _attachments: I,
+        _extent: image::Extent,
+    ) -> Result<Framebuffer, device::OutOfMemory>
+    where
+        I: Iterator<Item = image::FramebufferAttachment>,
+    {
+        Ok(Framebuffer)
+    }
+
+    unsafe fn destroy_framebuffer(&self, _framebuffer: Framebuffer) {}
+
+    unsafe fn create_shader_module(
+        &self,
+        spirv_data: &[u32],
+    ) -> Result<ShaderModule, device::ShaderError> {
+        let module =
+            spirv::Module::parse(spirv_data).map_err(device::ShaderError::CompilationFailed)?;
+        Ok(ShaderModule {
+            module: Arc::new(module),
+        })
+    }
+
+    unsafe fn destroy_shader_module(&self, _module: ShaderModule) {}
+
+    unsafe fn create_buffer(
+        &self,
+        size: u64,
+        _usage: buffer::Usage,
+    ) -> Result<Buffer, buffer::CreationError> {
+        Ok(Buffer { size, slice: None })
+    }
+
+    unsafe fn get_buffer_requirements(&self, buffer: &Buffer) -> memory::Requirements {
+        memory::Requirements {
+            size: buffer.size,
+            alignment: ALIGNMENT,
+            type_mask: 1,
+            prefers_dedicated: false,
+            requires_dedicated: false,
+        }
+    }
+
+    unsafe fn bind_buffer_memory(
+        &self,
+        memory: &Memory,
+        offset: u64,
+        buffer: &mut Buffer,
+    ) -> Result<(), device::BindError> {
+        buffer.bind(memory, offset)
+    }
+
+    unsafe fn destroy_buffer(&self, _buffer: Buffer) {}
+
+    unsafe fn create_buffer_view(
+        &self,
+        _buffer: &Buffer,
+        format: Option<format::Format>,
+        _range: buffer::SubRange,
+    ) -> Result<(), buffer::ViewCreationError> {
+        Err(buffer::ViewCreationError::UnsupportedFormat(format))
+    }
+
+    unsafe fn destroy_buffer_view(&self, _view: ()) {}
+
+    unsafe fn create_image(
+        &self,
+        kind: image::Kind,
+        mip_levels: image::Level,
+        format: format::Format,
+        _tiling: image::Tiling,
+        _usage: image::Usage,
+        _view_caps: image::ViewCapabilities,
+    ) -> Result<Image, image::CreationError> {
+        Ok(Image {
+            kind,
+            layout: Arc::new(ImageLayout::new(kind, mip_levels, format)?),
+            data: None,
+        })
+    }
+
+    unsafe fn get_image_requirements(&self, image: &Image) -> memory::Requirements {
+        memory::Requirements {
+            size: image.layout.size,
+            alignment: ALIGNMENT,
+            type_mask: 1,
+            prefers_dedicated: false,
+            requires_dedicated: false,
+        }
+    }
+
+    unsafe fn get_image_subresource_footprint(
+        &self,
+        image: &Image,
+        subresource: image::Subresource,
+    ) -> image::SubresourceFootprint {
+        let level = &image.layout.levels[subresource.level as usize];
+        let start = level.offset + subresource.layer as u64 * level.layer_pitch;
+        image::SubresourceFootprint {
+            slice: start..start + level.layer_pitch,
+            row_pitch: level.row_pitch,
+            array_pitch: level.layer_pitch,
+            depth_pitch: level.depth_pitch,
+        }
+    }
+
+    unsafe fn bind_image_memory(
+        &self,
+        memory: &Memory,
+        offset: u64,
+        image: &mut Image,
+    ) -> Result<(), device::BindError> {
+        image.bind(memory, offset)
+    }
+
+    unsafe fn destroy_image(&self, _image: Image) {}
+
+    unsafe fn create_image_view(
+        &self,
+        image: &Image,
+        view_kind: image::ViewKind,
+        format: format::Format,
+        swizzle: format::Swizzle,
+        range: image::SubresourceRange,
+    ) -> Result<ImageView, image::ViewCreationError> {
+        match (image.kind, view_kind) {
+            (image::Kind::D1(..), image::ViewKind::D1)
+            | (image::Kind::D1(..), image::ViewKind::D1Array)
+            | (image::Kind::D2(..), image::ViewKind::D2)
+            | (image::Kind::D2(..), image::ViewKind::D2Array)
+            | (image::Kind::D2(..), image::ViewKind::Cube)
+            | (image::Kind::D2(..), image::ViewKind::CubeArray)
+            | (image::Kind::D3(..), image::ViewKind::D3) => {}
+            _ => return Err(image::ViewCreationError::BadKind(view_kind)),
+        }
+        let data = image.data();
+        if format != data.format() {
+            return Err(image::ViewCreationError::BadFormat(format));
+        }
+        if swizzle != format::Swizzle::NO {
+            warn!(
+                "Swizzling of the image views is not supported: {:?}",
+                swizzle
+            );
+        }
+        let num_levels = image.layout.levels.len() as image::Level;
+        let levels = range.level_start
+            ..range
+                .level_count
+                .map_or(num_levels, |count| range.level_start + count);
+        if levels.end > num_levels {
+            return Err(image::ViewCreationError::Level(levels.end - 1));
+        }
+        let layers = range.layer_start
+            ..range
+                .layer_count
+                .map_or(image.layout.layers, |count| range.layer_start + count);
+        if layers.end > image.layout.layers {
+            return Err(image::LayerError::OutOfBounds.into());
+        }
+        Ok(ImageView {
+            data: data.clone(),
+            kind: view_kind,
+            levels,
+            layers,
+        })
+    }
+
+    unsafe fn destroy_image_view(&self, _view: ImageView) {}
+
+    unsafe fn create_sampler(
+        &self,
+        desc: &image::SamplerDesc,
+    ) -> Result<Sampler, device::AllocationError> {
+        Ok(Sampler {
+            desc: Arc::new(desc.clone()),
+        })
+    }
+
+    unsafe fn destroy_sampler(&self, _sampler: Sampler) {}
+
+    unsafe fn create_sampler_ycbcr_conversion(
+        &self,
+        desc: &image::SamplerYcbcrConversionDesc,
+    ) -> Result<(), image::CreationError> {
+        Err(image::CreationError::Format(desc.format))
+    }
+
+    unsafe fn destroy_sampler_ycbcr_conversion(&self, _conversion: ()) {}
+
+    unsafe fn create_ycbcr_sampler(
+        &self,
+        _desc: &image::SamplerDesc,
+        _conversion: &(),
+    ) -> Result<Sampler, device::AllocationError> {
+        unimplemented!("{}", NOT_SUPPORTED_MESSAGE)
+    }
+
+    unsafe fn create_ycbcr_image_view(
+        &self,
+        _image: &Image,
+        _view_kind: image::ViewKind,
+        _range: image::SubresourceRange,
+        _conversion: &(),
+    ) -> Result<ImageView, image::ViewCreationError> {
+        unimplemented!("{}", NOT_SUPPORTED_MESSAGE)
+    }
+
+    unsafe fn create_descriptor_pool<I>(
+        &self,
+        _max_sets: usize,
+        _descriptor_ranges: I,
+        _flags: pso::DescriptorPoolCreateFlags,
+    ) -> Result<DescriptorPool, device::OutOfMemory>
+    where
+        I: Iterator<Item = pso::DescriptorRangeDesc>,
+    {
+        Ok(DescriptorPool)
+    }
+
+    unsafe fn destroy_descriptor_pool(&self, _pool: DescriptorPool) {}
+
+    unsafe fn create_descriptor_set_layout<'a, I, J>(
+        &self,
+        bindings: I,
+        immutable_samplers: J,
+    ) -> Result<DescriptorSetLayout, device::OutOfMemory>
+    where
+        I: Iterator<Item = pso::DescriptorSetLayoutBinding>,
+        J: Iterator<Item = &'a Sampler>,
+    {
+        let mut bindings = bindings.collect::<Vec<_>>();
+        bindings.sort_by_key(|binding| binding.binding);
+        let mut immutable_samplers = immutable_samplers;
+        let mut samplers = Descriptors::default();
+        for binding in bindings.iter().filter(|binding| binding.immutable_samplers) {
+            for index in 0..binding.count as pso::DescriptorArrayIndex {
+                if let Some(sampler) = immutable_samplers.next() {
+                    samplers.insert(
+                        (binding.binding, index),
+                        Descriptor::Sampler(sampler.clone()),
+                    );
+                }
+            }
+        }
+        Ok(DescriptorSetLayout {
+            bindings: Arc::new(bindings),
+            immutable_samplers: samplers,
+        })
+    }
+
+    unsafe fn destroy_descriptor_set_layout(&self, _layout: DescriptorSetLayout) {}
+
+    unsafe fn write_descriptor_set<'a, I>(&self, op: pso::DescriptorSetWrite<'a, Backend, I>)
+    where
+        I: Iterator<Item = pso::Descriptor<'a, Backend>>,
+    {
+        let set = op.set;
+        let bindings = Arc::clone(&set.bindings);
+        let mut position = (op.binding, op.array_offset);
+        for descriptor in op.descriptors {
+            let (layout, index) = match next_descriptor(&bindings, position.0, position.1) {
+                Some(found) => found,
+                None => {
+                    error!("Descriptor write is out of the set bounds");
+                    return;
+                }
+            };
+            position = (layout.binding, index + 1);
+            let key = (layout.binding, index);
+            let immutable_sampler = match set.descriptors.get(&key) {
+                Some(Descriptor::Sampler(sampler))
+                | Some(Descriptor::CombinedImageSampler(_, sampler))
+                    if layout.immutable_samplers =>
+                {
+                    Some(sampler.clone())
+                }
+                _ => None,
+            };
+            let value = match descriptor {
+                pso::Descriptor::Sampler(sampler) => match immutable_sampler {
+                    Some(_) => continue,
+                    None => Descriptor::Sampler(sampler.clone()),
+                },
+                pso::Descriptor::Image(view, _) => match immutable_sampler {
+                    Some(sampler) => Descriptor::CombinedImageSampler(view.clone(), sampler),
+                    None => Descriptor::Image(view.clone()),
+                },
+                pso::Descriptor::CombinedImageSampler(view, _, sampler) => {
+                    Descriptor::CombinedImageSampler(
+                        view.clone(),
+                        immutable_sampler.unwrap_or_else(|| sampler.clone()),
+                    )
+                }
+                pso::Descriptor::Buffer(buffer, sub) => Descriptor::Buffer(buffer.slice(&sub)),
+                pso::Descriptor::TexelBuffer(_)
+                | pso::Descriptor::InlineUniformBlock(_)
+                | pso::Descriptor::Null => {
+                    error!("Descriptor type is not supported");
+                    continue;
+                }
+            };
+            set.descriptors.insert(key, value);
+        }
+    }
+
+    unsafe fn copy_descriptor_set<'a>(&self, op: pso::DescriptorSetCopy<'a, Backend>) {
+        let mut src = (op.src_binding, op.src_array_offset);
+        let mut dst = (op.dst_binding, op.dst_array_offset);
+        for _ in 0..op.count {
+            let (src_layout, src_index) = match next_descriptor(&op.src_set.bindings, src.0, src.1)
+            {
+                Some(found) => found,
+                None => return,
+            };
+            let (dst_layout, dst_index) = match next_descriptor(&op.dst_set.bindings, dst.0, dst.1)
+            {
+                Some(found) => found,
+                None => return,
+            };
+            src = (src_layout.binding, src_index + 1);
+            dst = (dst_layout.binding, dst_index + 1);
+            let key = (dst_layout.binding, dst_index);
+            match op.src_set.descriptors.get(&(src_layout.binding, src_index)) {
+                Some(descriptor) => op.dst_set.descriptors.insert(key, descriptor.clone()),
+                None => op.dst_set.descriptors.remove(&key),
+            };
+        }
+    }
+
+    unsafe fn map_memory(
+        &self,
+        memory: &mut Memory,
+        segment: memory::Segment,
+    ) -> Result<*mut u8, device::MapError> {
+        if segment.offset > memory.size() {
+            return Err(device::MapError::OutOfBounds);
+        }
+        Ok(memory.ptr().offset(segment.offset as isize))
+    }
+
+    unsafe fn flush_mapped_memory_ranges<'a, I>(
+        &self,
+        _ranges: I,
+    ) -> Result<(), device::OutOfMemory>
+    where
+        I: Iterator<Item = (&'a Memory, memory::Segment)>,
+    {
+        Ok(())
+    }
+
+    unsafe fn invalidate_mapped_memory_ranges<'a, I>(
+        &self,
+        _ranges: I,
+    ) -> Result<(), device::OutOfMemory>
+    where
+        I: Iterator<Item = (&'a Memory, memory::Segment)>,
+    {
+        Ok(())
+    }
+
+    unsafe fn unmap_memory(&self, _memory: &mut Memory) {}
+
+    unsafe fn copy_memory_to_image<I>(
+        &self,
+        data: &[u8],
+        image: &Image,
+        _layout: image::Layout,
+        regions: I,
+    ) -> Result<(), device::MapError>
+    where
+        I: Iterator<Item = command::BufferImageCopy>,
+    {
+        let dst = image.data();
+        for region in regions {
+            for (buffer_offset, image_offset, size) in image_rows(dst, &region) {
+                let bytes = data
+                    .get(buffer_offset as usize..(buffer_offset + size) as usize)
+                    .ok_or(device::MapError::OutOfBounds)?;
+                dst.slice.write(image_offset, bytes);
+            }
+        }
+        Ok(())
+    }
+
+    unsafe fn copy_image_to_memory<I>(
+        &self,
+        image: &Image,
+        _layout: image::Layout,
+        data: &mut [u8],
+        regions: I,
+    ) -> Result<(), device::MapError>
+    where
+        I: Iterator<Item = command::BufferImageCopy>,
+    {
+        let src = image.data();
+        for region in regions {
+            for (buffer_offset, image_offset, size) in image_rows(src, &region) {
+                let bytes = data
+                    .get_mut(buffer_offset as usize..(buffer_offset + size) as usize)
+                    .ok_or(device::MapError::OutOfBounds)?;
+                src.slice.read(image_offset, bytes);
+            }
+        }
+        Ok(())
+    }
+
+    fn create_semaphore(&self) -> Result<(), device::OutOfMemory> {
+        Ok(())
+    }
+
+    unsafe fn destroy_semaphore(&self, _semaphore: ()) {}
+
+    fn create_fence(&self, signaled: bool) -> Result<Fence, device::OutOfMemory> {
+        Ok(Fence { signaled })
+    }
+
+    unsafe fn reset_fence(&self, fence: &mut Fence) -> Result<(), device::OutOfMemory> {
+        fence.signaled = false;
+        Ok(())
+    }
+
+    unsafe fn wait_for_fence(
+        &self,
+        fence: &Fence,
+        _timeout_ns: u64,
+    ) -> Result<bool, device::WaitError> {
+        // Submissions are executed immediately, so there is nothing to wait for
+        Ok(fence.signaled)
+    }
+
+    unsafe fn get_fence_status(&self, fence: &Fence) -> Result<bool, device::DeviceLost> {
+        Ok(fence.signaled)
+    }
+
+    unsafe fn destroy_fence(&self, _fence: Fence) {}
+
+    fn create_event(&self) -> Result<Event, device::OutOfMemory> {
+        Ok(Event {
+            set: Arc::new(AtomicBool::new(false)),
+        })
+    }
+
+    unsafe fn destroy_event(&self, _event: Event) {}
+
+    unsafe fn get_event_status(&self, event: &Event) -> Result<bool, device::WaitError> {
+        Ok(event.set.load(Ordering::Acquire))
+    }
+
+    unsafe fn set_event(&self, event: &mut Event) -> Result<(), device::OutOfMemory> {
+        event.set.store(true, Ordering::Release);
+        Ok(())
+    }
+
+    unsafe fn reset_event(&self, event: &mut Event) -> Result<(), device::OutOfMemory> {
+        event.set.store(false, Ordering::Release);
+        Ok(())
+    }
+
+    unsafe fn create_query_pool(
+        &self,
+        ty: query::Type,
+        _count: query::Id,
+    ) -> Result<(), query::CreationError> {
+        Err(query::CreationError::Unsupported(ty))
+    }
+
+    unsafe fn destroy_query_pool(&self, _pool: ()) {}
+
+    unsafe fn get_query_pool_results(
+        &self,
+        _pool: &(),
+        _queries: Range<query::Id>,
+        _data: &mut [u8],
+        _stride: buffer::Stride,
+        _flags: query::ResultFlags,
+    ) -> Result<bool, device::WaitError> {
+        unimplemented!("{}", NOT_SUPPORTED_MESSAGE)
+    }
+
+    fn wait_idle(&self) -> Result<(), device::WaitError> {
+        Ok(())
+    }
+
+    unsafe fn set_image_name(&self, _image: &mut Image, _name: &str) {}
+
+    unsafe fn set_buffer_name(&self, _buffer: &mut Buffer, _name: &str) {}
+
+    unsafe fn set_command_buffer_name(&self, _command_buffer: &mut CommandBuffer, _name: &str) {}
+
+    unsafe fn set_semaphore_name(&self, _semaphore: &mut (), _name: &str) {}
+
+    unsafe fn set_fence_name(&self, _fence: &mut Fence, _name: &str) {}
+
+    unsafe fn set_framebuffer_name(&self, _framebuffer: &mut Framebuffer, _name: &str) {}
+
+    unsafe fn set_render_pass_name(&self, _render_pass: &mut RenderPass, _name: &str) {}
+
+    unsafe fn set_descriptor_set_name(&self, _descriptor_set: &mut DescriptorSet, _name: &str) {}
+
+    unsafe fn set_descriptor_set_layout_name(
+        &self,
+        _descriptor_set_layout: &mut DescriptorSetLayout,
+        _name: &str,
+    ) {
+    }
+
+    unsafe fn set_pipeline_layout_name(&self, _pipeline_layout: &mut (), _name: &str) {}
+}
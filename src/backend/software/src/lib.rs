@@ -0,0 +1,377 @@
+//! Software backend, rasterizing on the CPU.
+//!
+//! It implements a small subset of the API: graphics pipelines with vertex and
+//! fragment shaders drawing triangles into single-sampled color and depth attachments,
+//! and the transfer operations between buffers and images. Shaders are interpreted
+//! from SPIR-V, so rendering is slow but deterministic, which makes the backend
+//! suitable for testing the pixel output of an application without a GPU.
+//!
+//! Command buffers are executed synchronously at submission, and no presentation
+//! surfaces are supported.
+
+#[macro_use]
+extern crate log;
+extern crate gfx_hal as hal;
+
+use crate::{
+    command::{CommandBuffer, CommandPool},
+    device::Device,
+    resource::{
+        Buffer, DescriptorPool, DescriptorSet, DescriptorSetLayout, Event, Fence, Framebuffer,
+        GraphicsPipeline, Image, ImageView, Memory, RenderPass, Sampler, ShaderModule,
+    },
+};
+
+use hal::{adapter, format, image, memory, pso, queue, window};
+
+use std::borrow::Borrow;
+
+mod command;
+mod device;
+mod raster;
+mod resource;
+mod spirv;
+#[cfg(test)]
+mod tests;
+mod texel;
+
+const NOT_SUPPORTED_MESSAGE: &str = "This function is not supported by the software backend";
+
+const QUEUE_FAMILY_ID: queue::QueueFamilyId = queue::QueueFamilyId(0);
+
+/// Largest dimension of the images.
+const MAX_IMAGE_SIZE: image::Size = 4096;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Backend {}
+impl hal::Backend for Backend {
+    type Instance = Instance;
+    type PhysicalDevice = PhysicalDevice;
+    type Device = Device;
+    type Surface = Surface;
+
+    type QueueFamily = QueueFamily;
+    type Queue = Queue;
+    type CommandBuffer = CommandBuffer;
+
+    type Memory = Memory;
+    type CommandPool = CommandPool;
+
+    type ShaderModule = ShaderModule;
+    type RenderPass = RenderPass;
+    type Framebuffer = Framebuffer;
+
+    type Buffer = Buffer;
+    type BufferView = ();
+    type Image = Image;
+    type ImageView = ImageView;
+    type Sampler = Sampler;
+    type SamplerYcbcrConversion = ();
+
+    type ComputePipeline = ();
+    type GraphicsPipeline = GraphicsPipeline;
+    type PipelineCache = ();
+    type PipelineLayout = ();
+    type DescriptorSetLayout = DescriptorSetLayout;
+    type DescriptorPool = DescriptorPool;
+    type DescriptorSet = DescriptorSet;
+
+    type Fence = Fence;
+    type Semaphore = ();
+    type Event = Event;
+    type QueryPool = ();
+}
+
+#[derive(Debug)]
+pub struct PhysicalDevice;
+impl adapter::PhysicalDevice<Backend> for PhysicalDevice {
+    unsafe fn open(
+        &self,
+        families: &[(&QueueFamily, &[queue::QueuePriority])],
+        requested_features: hal::Features,
+    ) -> Result<adapter::Gpu<Backend>, hal::device::CreationError> {
+        if !self.features().contains(requested_features) {
+            return Err(hal::device::CreationError::MissingFeature);
+        }
+        let mut queue_group = queue::QueueGroup::new(QUEUE_FAMILY_ID);
+        for _ in families.iter().flat_map(|&(_, priorities)| priorities) {
+            queue_group.add_queue(Queue);
+        }
+        Ok(adapter::Gpu {
+            device: Device,
+            queue_groups: vec![queue_group],
+        })
+    }
+
+    fn format_properties(&self, format: Option<format::Format>) -> format::Properties {
+        let format = match format {
+            Some(format) if texel::is_supported(format) => format,
+            _ => return format::Properties::default(),
+        };
+        let mut features = format::ImageFeature::SAMPLED
+            | format::ImageFeature::TRANSFER_SRC
+            | format::ImageFeature::TRANSFER_DST;
+        if format.is_depth() {
+            features |= format::ImageFeature::DEPTH_STENCIL_ATTACHMENT;
+        } else {
+            features |= format::ImageFeature::COLOR_ATTACHMENT;
+            if !texel::is_integer(format) {
+                features |= format::ImageFeature::SAMPLED_LINEAR
+                    | format::ImageFeature::COLOR_ATTACHMENT_BLEND;
+            }
+        }
+        format::Properties {
+            linear_tiling: features,
+            optimal_tiling: features,
+            buffer_features: format::BufferFeature::VERTEX,
+        }
+    }
+
+    fn image_format_properties(
+        &self,
+        format: format::Format,
+        dimensions: u8,
+        _tiling: image::Tiling,
+        _usage: image::Usage,
+        _view_caps: image::ViewCapabilities,
+    ) -> Option<image::FormatProperties> {
+        if !texel::is_supported(format) {
+            return None;
+        }
+        let max_extent = match dimensions {
+            1 => image::Extent {
+                width: MAX_IMAGE_SIZE,
+                height: 1,
+                depth: 1,
+            },
+            2 => image::Extent {
+                width: MAX_IMAGE_SIZE,
+                height: MAX_IMAGE_SIZE,
+                depth: 1,
+            },
+            3 => image::Extent {
+                width: MAX_IMAGE_SIZE,
+                height: MAX_IMAGE_SIZE,
+                depth: MAX_IMAGE_SIZE,
+            },
+            _ => return None,
+        };
+        Some(image::FormatProperties {
+            max_extent,
+            max_levels: 13,
+            max_layers: 256,
+            sample_count_mask: 1,
+            max_resource_size: !0,
+        })
+    }
+
+    fn memory_properties(&self) -> adapter::MemoryProperties {
+        adapter::MemoryProperties {
+            memory_types: vec![adapter::MemoryType {
+                properties: memory::Properties::DEVICE_LOCAL
+                    | memory::Properties::CPU_VISIBLE
+                    | memory::Properties::COHERENT
+                    | memory::Properties::CPU_CACHED,
+                heap_index: 0,
+            }],
+            memory_heaps: vec![adapter::MemoryHeap {
+                size: 1 << 30,
+                flags: memory::HeapFlags::DEVICE_LOCAL,
+            }],
+        }
+    }
+
+    fn features(&self) -> hal::Features {
+        hal::Features::FULL_DRAW_INDEX_U32
+            | hal::Features::INDEPENDENT_BLENDING
+            | hal::Features::DRAW_INDIRECT_FIRST_INSTANCE
+            | hal::Features::SAMPLER_MIRROR_CLAMP_EDGE
+            | hal::Features::INSTANCE_RATE
+            | hal::Features::SAMPLER_BORDER_COLOR
+            | hal::Features::DYNAMIC_RENDERING
+            | hal::Features::HOST_IMAGE_COPY
+    }
+
+    fn capabilities(&self) -> hal::Capabilities {
+        Default::default()
+    }
+
+    fn limits(&self) -> hal::Limits {
+        hal::Limits {
+            max_image_1d_size: MAX_IMAGE_SIZE,
+            max_image_2d_size: MAX_IMAGE_SIZE,
+            max_image_3d_size: MAX_IMAGE_SIZE,
+            max_image_array_layers: 256,
+            max_push_constants_size: raster::MAX_PUSH_CONSTANTS_SIZE,
+            max_bound_descriptor_sets: 8,
+            max_vertex_input_attributes: 16,
+            max_vertex_input_bindings: 16,
+            max_vertex_input_attribute_offset: 2047,
+            max_vertex_input_binding_stride: 2048,
+            max_viewports: 1,
+            max_viewport_dimensions: [MAX_IMAGE_SIZE; 2],
+            max_framebuffer_extent: image::Extent {
+                width: MAX_IMAGE_SIZE,
+                height: MAX_IMAGE_SIZE,
+                depth: 1,
+            },
+            framebuffer_color_sample_counts: 1,
+            framebuffer_depth_sample_counts: 1,
+            max_color_attachments: 8,
+            min_uniform_buffer_offset_alignment: 4,
+            min_storage_buffer_offset_alignment: 4,
+            optimal_buffer_copy_offset_alignment: 1,
+            optimal_buffer_copy_pitch_alignment: 1,
+            non_coherent_atom_size: 1,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct QueueFamily;
+impl queue::QueueFamily for QueueFamily {
+    fn queue_type(&self) -> queue::QueueType {
+        queue::QueueType::General
+    }
+    fn max_queues(&self) -> usize {
+        1
+    }
+    fn id(&self) -> queue::QueueFamilyId {
+        QUEUE_FAMILY_ID
+    }
+}
+
+/// Command queue, executing the command buffers as they are submitted.
+#[derive(Debug)]
+pub struct Queue;
+impl queue::Queue<Backend> for Queue {
+    unsafe fn submit<'a, Ic, Iw, Is>(
+        &mut self,
+        command_buffers: Ic,
+        _wait_semaphores: Iw,
+        _signal_semaphores: Is,
+        fence: Option<&mut Fence>,
+    ) -> Result<(), hal::queue::SubmitError>
+    where
+        Ic: Iterator<Item = &'a CommandBuffer>,
+        Iw: Iterator<Item = (&'a (), pso::PipelineStage)>,
+        Is: Iterator<Item = &'a ()>,
+    {
+        for command_buffer in command_buffers {
+            command_buffer.execute();
+        }
+        if let Some(fence) = fence {
+            fence.signaled = true;
+        }
+        Ok(())
+    }
+
+    unsafe fn present(
+        &mut self,
+        surface: &mut Surface,
+        _image: SwapchainImage,
+        _wait_semaphore: Option<&mut ()>,
+    ) -> Result<Option<window::Suboptimal>, window::PresentError> {
+        match *surface {}
+    }
+
+    fn wait_idle(&mut self) -> Result<(), hal::device::WaitError> {
+        Ok(())
+    }
+
+    fn timestamp_period(&self) -> f32 {
+        1.0
+    }
+}
+
+/// Presentation surface, which can't be created since there is nothing to present to.
+#[derive(Debug)]
+pub enum Surface {}
+impl window::Surface<Backend> for Surface {
+    fn supports_queue_family(&self, _family: &QueueFamily) -> bool {
+        match *self {}
+    }
+
+    fn capabilities(&self, _physical_device: &PhysicalDevice) -> window::SurfaceCapabilities {
+        match *self {}
+    }
+
+    fn supported_formats(&self, _physical_device: &PhysicalDevice) -> Option<Vec<format::Format>> {
+        match *self {}
+    }
+}
+
+#[derive(Debug)]
+pub enum SwapchainImage {}
+impl Borrow<Image> for SwapchainImage {
+    fn borrow(&self) -> &Image {
+        match *self {}
+    }
+}
+impl Borrow<ImageView> for SwapchainImage {
+    fn borrow(&self) -> &ImageView {
+        match *self {}
+    }
+}
+
+impl window::PresentationSurface<Backend> for Surface {
+    type SwapchainImage = SwapchainImage;
+
+    unsafe fn configure_swapchain(
+        &mut self,
+        _device: &Device,
+        _config: window::SwapchainConfig,
+    ) -> Result<(), window::SwapchainError> {
+        match *self {}
+    }
+
+    unsafe fn unconfigure_swapchain(&mut self, _device: &Device) {
+        match *self {}
+    }
+
+    unsafe fn acquire_image(
+        &mut self,
+        _timeout_ns: u64,
+    ) -> Result<(SwapchainImage, Option<window::Suboptimal>), window::AcquireError> {
+        match *self {}
+    }
+}
+
+#[derive(Debug)]
+pub struct Instance;
+
+impl hal::Instance<Backend> for Instance {
+    fn create(name: &str, version: u32) -> Result<Self, hal::UnsupportedBackend> {
+        debug!(
+            "Creating software backend instance with name '{}' and version {}",
+            name, version
+        );
+        Ok(Instance)
+    }
+
+    fn enumerate_adapters(&self) -> Vec<adapter::Adapter<Backend>> {
+        let info = adapter::AdapterInfo {
+            name: "Software Rasterizer".to_string(),
+            vendor: 0,
+            device: 0,
+            device_type: adapter::DeviceType::Cpu,
+        };
+        vec![adapter::Adapter {
+            info,
+            physical_device: PhysicalDevice,
+            queue_families: vec![QueueFamily],
+        }]
+    }
+
+    unsafe fn create_surface(
+        &self,
+        _raw_window_handle: &impl raw_window_handle::HasRawWindowHandle,
+    ) -> Result<Surface, window::InitError> {
+        Err(window::InitError::UnsupportedWindowHandle)
+    }
+
+    unsafe fn destroy_surface(&self, surface: Surface) {
+        match surface {}
+    }
+}
@@ -0,0 +1,956 @@
+//! Rasterization of the triangles into the attachments of the current subpass.
+//!
+//! Triangles are clipped against the near and far planes of the clip volume, or only
+//! against the eye plane when depth clipping is disabled. The side planes are left to
+//! the pixel bounds of the draw.
+//! Pixels are sampled at their centers following the top-left rule, without multisampling.
+
+use crate::{
+    resource::{Descriptor, Descriptors, ImageView, PipelineState, RenderPassInfo, Slice},
+    spirv::{self, builtin, Value},
+    texel,
+};
+
+use hal::{
+    command,
+    format::{Aspects, Format},
+    pso,
+};
+
+use std::{ops::Range, sync::Arc};
+
+/// Size of the push constant storage, in bytes.
+pub const MAX_PUSH_CONSTANTS_SIZE: usize = 256;
+
+#[derive(Debug)]
+struct Pass {
+    info: Arc<RenderPassInfo>,
+    attachments: Vec<(ImageView, command::ClearValue)>,
+    subpass: usize,
+    area: pso::Rect,
+}
+
+#[derive(Clone, Debug)]
+struct VertexOutput {
+    position: [f32; 4],
+    varyings: Vec<(u32, Value)>,
+}
+
+/// Vertex in window coordinates.
+#[derive(Clone, Copy, Debug)]
+struct WindowVertex {
+    x: f32,
+    y: f32,
+    z: f32,
+    /// Reciprocal of the clip space `w`.
+    inv_w: f32,
+}
+
+/// Attachments and fixed function state of a draw call.
+struct Target<'a> {
+    pipeline: &'a PipelineState,
+    colors: Vec<&'a ImageView>,
+    depth: Option<&'a ImageView>,
+    viewport: pso::Viewport,
+    /// Pixel bounds of the draw, after the scissor and render area.
+    bounds: (i32, i32, i32, i32),
+    blend_constants: pso::ColorValue,
+}
+
+/// Graphics state of the command buffer being executed.
+#[derive(Debug)]
+pub struct Renderer {
+    pipeline: Option<Arc<PipelineState>>,
+    vertex_buffers: Vec<Option<Slice>>,
+    index_buffer: Option<(Slice, hal::IndexType)>,
+    descriptor_sets: Vec<Option<Arc<Descriptors>>>,
+    push_constants: Vec<u8>,
+    viewport: Option<pso::Viewport>,
+    scissor: Option<pso::Rect>,
+    blend_constants: pso::ColorValue,
+    pass: Option<Pass>,
+}
+
+impl Default for Renderer {
+    fn default() -> Self {
+        Renderer {
+            pipeline: None,
+            vertex_buffers: Vec::new(),
+            index_buffer: None,
+            descriptor_sets: Vec::new(),
+            push_constants: vec![0; MAX_PUSH_CONSTANTS_SIZE],
+            viewport: None,
+            scissor: None,
+            blend_constants: [0.0; 4],
+            pass: None,
+        }
+    }
+}
+
+fn edge(a: &WindowVertex, b: &WindowVertex, x: f32, y: f32) -> f32 {
+    (b.x - a.x) * (y - a.y) - (b.y - a.y) * (x - a.x)
+}
+
+/// Returns true for the top and left edges of a clockwise triangle in window coordinates.
+fn is_top_left(a: &WindowVertex, b: &WindowVertex) -> bool {
+    (a.y == b.y && b.x > a.x) || b.y < a.y
+}
+
+fn compare(fun: pso::Comparison, value: f32, reference: f32) -> bool {
+    match fun {
+        pso::Comparison::Never => false,
+        pso::Comparison::Less => value < reference,
+        pso::Comparison::Equal => value == reference,
+        pso::Comparison::LessEqual => value <= reference,
+        pso::Comparison::Greater => value > reference,
+        pso::Comparison::NotEqual => value != reference,
+        pso::Comparison::GreaterEqual => value >= reference,
+        pso::Comparison::Always => true,
+    }
+}
+
+fn blend_factor(
+    factor: pso::Factor,
+    channel: usize,
+    src: &[f32; 4],
+    dst: &[f32; 4],
+    constants: &pso::ColorValue,
+) -> f32 {
+    use hal::pso::Factor as F;
+    match factor {
+        F::Zero => 0.0,
+        F::One => 1.0,
+        F::SrcColor => src[channel],
+        F::OneMinusSrcColor => 1.0 - src[channel],
+        F::DstColor => dst[channel],
+        F::OneMinusDstColor => 1.0 - dst[channel],
+        F::SrcAlpha => src[3],
+        F::OneMinusSrcAlpha => 1.0 - src[3],
+        F::DstAlpha => dst[3],
+        F::OneMinusDstAlpha => 1.0 - dst[3],
+        F::ConstColor => constants[channel],
+        F::OneMinusConstColor => 1.0 - constants[channel],
+        F::ConstAlpha => constants[3],
+        F::OneMinusConstAlpha => 1.0 - constants[3],
+        F::SrcAlphaSaturate if channel == 3 => 1.0,
+        F::SrcAlphaSaturate => src[3].min(1.0 - dst[3]),
+        // Dual source blending is not supported
+        F::Src1Color | F::OneMinusSrc1Color | F::Src1Alpha | F::OneMinusSrc1Alpha => 0.0,
+    }
+}
+
+fn blend(
+    op: pso::BlendOp,
+    channel: usize,
+    src: &[f32; 4],
+    dst: &[f32; 4],
+    constants: &pso::ColorValue,
+) -> f32 {
+    let (s, d) = (src[channel], dst[channel]);
+    let factors = |src_factor, dst_factor| {
+        (
+            s * blend_factor(src_factor, channel, src, dst, constants),
+            d * blend_factor(dst_factor, channel, src, dst, constants),
+        )
+    };
+    match op {
+        pso::BlendOp::Add { src, dst } => {
+            let (s, d) = factors(src, dst);
+            s + d
+        }
+        pso::BlendOp::Sub { src, dst } => {
+            let (s, d) = factors(src, dst);
+            s - d
+        }
+        pso::BlendOp::RevSub { src, dst } => {
+            let (s, d) = factors(src, dst);
+            d - s
+        }
+        pso::BlendOp::Min => s.min(d),
+        pso::BlendOp::Max => s.max(d),
+        pso::BlendOp::Advanced(op) => panic!("Advanced blend operation {:?} is not supported", op),
+    }
+}
+
+fn to_array(value: &Value) -> [f32; 4] {
+    let mut array = [0.0; 4];
+    for (a, v) in array.iter_mut().zip(value.floats()) {
+        *a = v;
+    }
+    array
+}
+
+/// Interpolate a varying with perspective corrected barycentric weights.
+fn interpolate(values: [&Value; 3], weights: &[f32; 3]) -> Value {
+    match *values[0] {
+        Value::Float(_) => Value::Float(
+            values
+                .iter()
+                .zip(weights.iter())
+                .map(|(value, weight)| value.float() * weight)
+                .sum(),
+        ),
+        Value::Composite(ref components) => Value::Composite(
+            (0..components.len() as u32)
+                .map(|i| {
+                    let parts = [
+                        values[0].component(i),
+                        values[1].component(i),
+                        values[2].component(i),
+                    ];
+                    interpolate([&parts[0], &parts[1], &parts[2]], weights)
+                })
+                .collect(),
+        ),
+        // Integers and booleans are taken from the provoking vertex
+        ref other => other.clone(),
+    }
+}
+
+/// Smallest clip space `w` kept when the triangles are not clipped in depth.
+const MIN_W: f32 = 1.0e-6;
+
+/// Clip a triangle against the planes where `distance` is non-negative.
+///
+/// The resulting polygon starts with the provoking vertex or a vertex on its edge,
+/// so the flat varyings are taken from the provoking vertex.
+fn clip(
+    polygon: Vec<VertexOutput>,
+    flat_inputs: &[u32],
+    distance: impl Fn(&[f32; 4]) -> f32,
+) -> Vec<VertexOutput> {
+    let mut clipped = Vec::with_capacity(polygon.len() + 1);
+    for (i, current) in polygon.iter().enumerate() {
+        let next = &polygon[(i + 1) % polygon.len()];
+        let (d0, d1) = (distance(&current.position), distance(&next.position));
+        if d0 >= 0.0 {
+            clipped.push(current.clone());
+        }
+        if (d0 >= 0.0) != (d1 >= 0.0) {
+            // Always go from the inside vertex, so that the edges shared
+            // by two triangles are clipped at exactly the same point
+            let (inside, outside, t) = if d0 >= 0.0 {
+                (current, next, d0 / (d0 - d1))
+            } else {
+                (next, current, d1 / (d1 - d0))
+            };
+            let weights = [1.0 - t, t, 0.0];
+            let mut position = [0.0; 4];
+            for (c, p) in position.iter_mut().enumerate() {
+                *p = inside.position[c] * weights[0] + outside.position[c] * weights[1];
+            }
+            let varyings = inside
+                .varyings
+                .iter()
+                .map(|&(location, ref value)| {
+                    let value = if flat_inputs.contains(&location) {
+                        polygon[0].varying(location).clone()
+                    } else {
+                        let other = outside.varying(location);
+                        interpolate([value, other, other], &weights)
+                    };
+                    (location, value)
+                })
+                .collect();
+            clipped.push(VertexOutput { position, varyings });
+        }
+    }
+    clipped
+}
+
+/// Convert a clear value into the texel value of the format.
+pub fn clear_value(format: Format, value: command::ClearValue) -> Value {
+    if format.is_depth() {
+        let depth = unsafe { value.depth_stencil.depth };
+        Value::vector(vec![depth, 0.0, 0.0, 0.0])
+    } else if texel::is_integer(format) {
+        let uint32 = unsafe { value.color.uint32 };
+        Value::Composite(uint32.iter().map(|&value| Value::Int(value)).collect())
+    } else {
+        let float32 = unsafe { value.color.float32 };
+        Value::vector(float32.iter().cloned())
+    }
+}
+
+/// Fill a rectangle of the view layers with a value.
+fn fill(view: &ImageView, rect: pso::Rect, layers: Range<hal::image::Layer>, value: &Value) {
+    let x = rect.x.max(0) as u32..(rect.x as i32 + rect.w as i32).max(0) as u32;
+    let y = rect.y.max(0) as u32..(rect.y as i32 + rect.h as i32).max(0) as u32;
+    for layer in layers {
+        for y in y.clone() {
+            for x in x.clone() {
+                view.write(layer, x, y, value);
+            }
+        }
+    }
+}
+
+struct VertexEnvironment<'a> {
+    renderer: &'a Renderer,
+    pipeline: &'a PipelineState,
+    vertex: u32,
+    instance: u32,
+}
+
+impl spirv::Environment for VertexEnvironment<'_> {
+    fn input(&self, location: u32) -> Option<Value> {
+        let attribute = self
+            .pipeline
+            .attributes
+            .iter()
+            .find(|attribute| attribute.location == location)?;
+        let desc = self
+            .pipeline
+            .buffers
+            .iter()
+            .find(|desc| desc.binding == attribute.binding)?;
+        let buffer = self
+            .renderer
+            .vertex_buffers
+            .get(attribute.binding as usize)?
+            .as_ref()?;
+        let index = match desc.rate {
+            pso::VertexInputRate::Vertex => self.vertex,
+            pso::VertexInputRate::Instance(0) => 0,
+            pso::VertexInputRate::Instance(divisor) => self.instance / divisor as u32,
+        };
+        let format = attribute.element.format;
+        let offset = index as u64 * desc.stride as u64 + attribute.element.offset as u64;
+        let mut bytes = [0u8; 16];
+        let size = (format.surface_desc().bits as usize / 8).min(bytes.len());
+        buffer.read(offset, &mut bytes[..size]);
+        Some(texel::decode(format, &bytes))
+    }
+
+    fn builtin(&self, builtin: u32) -> Option<Value> {
+        match builtin {
+            builtin::VERTEX_INDEX => Some(Value::Int(self.vertex)),
+            builtin::INSTANCE_INDEX => Some(Value::Int(self.instance)),
+            _ => None,
+        }
+    }
+
+    fn buffer(&self, set: u32, binding: u32) -> Option<spirv::Memory> {
+        self.renderer.buffer(set, binding)
+    }
+
+    fn descriptors(&self, set: u32, binding: u32) -> Vec<Value> {
+        self.renderer.descriptors(set, binding)
+    }
+
+    fn push_constants(&self) -> spirv::Memory {
+        self.renderer.push_constant_memory()
+    }
+}
+
+struct FragmentEnvironment<'a> {
+    renderer: &'a Renderer,
+    varyings: &'a [(u32, Value)],
+    frag_coord: [f32; 4],
+    front_facing: bool,
+}
+
+impl spirv::Environment for FragmentEnvironment<'_> {
+    fn input(&self, location: u32) -> Option<Value> {
+        self.varyings
+            .iter()
+            .find(|&&(loc, _)| loc == location)
+            .map(|(_, value)| value.clone())
+    }
+
+    fn builtin(&self, builtin: u32) -> Option<Value> {
+        match builtin {
+            builtin::FRAG_COORD => Some(Value::vector(self.frag_coord.iter().cloned())),
+            builtin::FRONT_FACING => Some(Value::Bool(self.front_facing)),
+            _ => None,
+        }
+    }
+
+    fn buffer(&self, set: u32, binding: u32) -> Option<spirv::Memory> {
+        self.renderer.buffer(set, binding)
+    }
+
+    fn descriptors(&self, set: u32, binding: u32) -> Vec<Value> {
+        self.renderer.descriptors(set, binding)
+    }
+
+    fn push_constants(&self) -> spirv::Memory {
+        self.renderer.push_constant_memory()
+    }
+}
+
+impl Renderer {
+    fn buffer(&self, set: u32, binding: u32) -> Option<spirv::Memory> {
+        let descriptors = self.descriptor_sets.get(set as usize)?.as_ref()?;
+        match descriptors.get(&(binding, 0))? {
+            Descriptor::Buffer(slice) => Some(slice.memory()),
+            _ => None,
+        }
+    }
+
+    fn descriptors(&self, set: u32, binding: u32) -> Vec<Value> {
+        let descriptors = match self.descriptor_sets.get(set as usize) {
+            Some(Some(descriptors)) => descriptors,
+            _ => return Vec::new(),
+        };
+        (0..)
+            .map(|index| descriptors.get(&(binding, index)))
+            .take_while(Option::is_some)
+            .map(|descriptor| descriptor.unwrap().value())
+            .collect()
+    }
+
+    fn push_constant_memory(&self) -> spirv::Memory {
+        spirv::Memory {
+            ptr: self.push_constants.as_ptr() as *mut u8,
+            len: self.push_constants.len() as u64,
+        }
+    }
+
+    pub fn bind_pipeline(&mut self, pipeline: Arc<PipelineState>) {
+        self.pipeline = Some(pipeline);
+    }
+
+    pub fn bind_vertex_buffers(&mut self, first: u32, buffers: &[Slice]) {
+        let end = first as usize + buffers.len();
+        if self.vertex_buffers.len() < end {
+            self.vertex_buffers.resize(end, None);
+        }
+        for (target, buffer) in self.vertex_buffers[first as usize..]
+            .iter_mut()
+            .zip(buffers)
+        {
+            *target = Some(buffer.clone());
+        }
+    }
+
+    pub fn bind_index_buffer(&mut self, buffer: Slice, ty: hal::IndexType) {
+        self.index_buffer = Some((buffer, ty));
+    }
+
+    pub fn bind_descriptor_sets(&mut self, first: usize, sets: &[Arc<Descriptors>]) {
+        let end = first + sets.len();
+        if self.descriptor_sets.len() < end {
+            self.descriptor_sets.resize(end, None);
+        }
+        for (target, set) in self.descriptor_sets[first..].iter_mut().zip(sets) {
+            *target = Some(Arc::clone(set));
+        }
+    }
+
+    pub fn push_constants(&mut self, offset: u32, data: &[u32]) {
+        for (i, word) in data.iter().enumerate() {
+            let start = offset as usize + 4 * i;
+            if let Some(target) = self.push_constants.get_mut(start..start + 4) {
+                target.copy_from_slice(&word.to_le_bytes());
+            }
+        }
+    }
+
+    pub fn set_viewports(&mut self, first: u32, viewports: &[pso::Viewport]) {
+        if first == 0 {
+            self.viewport = viewports.first().cloned();
+        }
+    }
+
+    pub fn set_scissors(&mut self, first: u32, rects: &[pso::Rect]) {
+        if first == 0 {
+            self.scissor = rects.first().cloned();
+        }
+    }
+
+    pub fn set_blend_constants(&mut self, constants: pso::ColorValue) {
+        self.blend_constants = constants;
+    }
+
+    pub fn begin_render_pass(
+        &mut self,
+        info: Arc<RenderPassInfo>,
+        attachments: Vec<(ImageView, command::ClearValue)>,
+        area: pso::Rect,
+    ) {
+        for (attachment, &(ref view, clear)) in info.attachments.iter().zip(attachments.iter()) {
+            let layers = 0..view.layers.end - view.layers.start;
+            if attachment.ops.load == hal::pass::AttachmentLoadOp::Clear {
+                fill(view, area, layers, &clear_value(view.data.format(), clear));
+            }
+        }
+        self.pass = Some(Pass {
+            info,
+            attachments,
+            subpass: 0,
+            area,
+        });
+    }
+
+    pub fn next_subpass(&mut self) {
+        if let Some(ref mut pass) = self.pass {
+            pass.subpass += 1;
+        }
+    }
+
+    pub fn end_render_pass(&mut self) {
+        self.pass = None;
+    }
+
+    pub fn clear_attachments(&self, clears: &[command::AttachmentClear], rects: &[pso::ClearRect]) {
+        let pass = match self.pass {
+            Some(ref pass) => pass,
+            None => {
+                error!("Clearing attachments outside of a render pass");
+                return;
+            }
+        };
+        let subpass = &pass.info.subpasses[pass.subpass];
+        for clear in clears {
+            let (attachment, value) = match *clear {
+                command::AttachmentClear::Color { index, value } => (
+                    subpass.colors.get(index).cloned(),
+                    command::ClearValue { color: value },
+                ),
+                command::AttachmentClear::DepthStencil {
+                    depth: Some(depth), ..
+                } => (
+                    subpass.depth_stencil,
+                    command::ClearValue {
+                        depth_stencil: command::ClearDepthStencil { depth, stencil: 0 },
+                    },
+                ),
+                command::AttachmentClear::DepthStencil { depth: None, .. } => continue,
+            };
+            let view = match attachment {
+                Some(attachment) => &pass.attachments[attachment].0,
+                None => continue,
+            };
+            let value = clear_value(view.data.format(), value);
+            for rect in rects {
+                fill(view, rect.rect, rect.layers.clone(), &value);
+            }
+        }
+    }
+
+    pub fn draw(&self, vertices: Range<u32>, instances: Range<u32>) {
+        self.draw_vertices(&vertices.collect::<Vec<_>>(), instances);
+    }
+
+    pub fn draw_indexed(&self, indices: Range<u32>, base_vertex: i32, instances: Range<u32>) {
+        let (buffer, ty) = match self.index_buffer {
+            Some((ref buffer, ty)) => (buffer, ty),
+            None => {
+                error!("Indexed draw without a bound index buffer");
+                return;
+            }
+        };
+        let vertices = indices
+            .map(|index| {
+                let index = match ty {
+                    hal::IndexType::U16 => {
+                        let mut bytes = [0u8; 2];
+                        buffer.read(index as u64 * 2, &mut bytes);
+                        u16::from_le_bytes(bytes) as u32
+                    }
+                    hal::IndexType::U32 => {
+                        let mut bytes = [0u8; 4];
+                        buffer.read(index as u64 * 4, &mut bytes);
+                        u32::from_le_bytes(bytes)
+                    }
+                };
+                (index as i32).wrapping_add(base_vertex) as u32
+            })
+            .collect::<Vec<_>>();
+        self.draw_vertices(&vertices, instances);
+    }
+
+    fn draw_vertices(&self, vertices: &[u32], instances: Range<u32>) {
+        let pipeline = match self.pipeline {
+            Some(ref pipeline) => &**pipeline,
+            None => {
+                error!("Draw without a bound graphics pipeline");
+                return;
+            }
+        };
+        let pass = match self.pass {
+            Some(ref pass) => pass,
+            None => {
+                error!("Draw outside of a render pass");
+                return;
+            }
+        };
+        let viewport = match pipeline
+            .baked_states
+            .viewport
+            .as_ref()
+            .or_else(|| self.viewport.as_ref())
+        {
+            Some(viewport) => viewport.clone(),
+            None => {
+                error!("Draw without a viewport");
+                return;
+            }
+        };
+        let scissor = pipeline
+            .baked_states
+            .scissor
+            .or(self.scissor)
+            .unwrap_or(viewport.rect);
+
+        let subpass = &pass.info.subpasses[pass.subpass];
+        let colors = subpass
+            .colors
+            .iter()
+            .map(|&attachment| &pass.attachments[attachment].0)
+            .collect::<Vec<_>>();
+        let depth = subpass
+            .depth_stencil
+            .map(|attachment| &pass.attachments[attachment].0)
+            .filter(|view| {
+                view.data
+                    .format()
+                    .surface_desc()
+                    .aspects
+                    .contains(Aspects::DEPTH)
+            });
+        let extent = colors
+            .iter()
+            .chain(depth.iter())
+            .map(|view| view.size(0))
+            .fold([i32::MAX; 2], |size, [w, h, _]| {
+                [size[0].min(w as i32), size[1].min(h as i32)]
+            });
+        let rect_bounds = |rect: &pso::Rect| {
+            (
+                rect.x as i32,
+                rect.y as i32,
+                rect.x as i32 + rect.w as i32,
+                rect.y as i32 + rect.h as i32,
+            )
+        };
+        let (sx0, sy0, sx1, sy1) = rect_bounds(&scissor);
+        let (ax0, ay0, ax1, ay1) = rect_bounds(&pass.area);
+        let bounds = (
+            sx0.max(ax0).max(0),
+            sy0.max(ay0).max(0),
+            sx1.min(ax1).min(extent[0]),
+            sy1.min(ay1).min(extent[1]),
+        );
+
+        let target = Target {
+            pipeline,
+            colors,
+            depth,
+            viewport,
+            bounds,
+            blend_constants: pipeline
+                .baked_states
+                .blend_color
+                .unwrap_or(self.blend_constants),
+        };
+
+        let triangles = match pipeline.primitive {
+            pso::Primitive::TriangleList => (0..vertices.len() / 3)
+                .map(|i| [3 * i, 3 * i + 1, 3 * i + 2])
+                .collect::<Vec<_>>(),
+            pso::Primitive::TriangleStrip => (0..vertices.len().saturating_sub(2))
+                .map(|i| {
+                    if i % 2 == 0 {
+                        [i, i + 1, i + 2]
+                    } else {
+                        [i + 1, i, i + 2]
+                    }
+                })
+                .collect(),
+            primitive => {
+                error!("Primitive {:?} is not supported", primitive);
+                return;
+            }
+        };
+
+        for instance in instances {
+            let outputs = vertices
+                .iter()
+                .map(|&vertex| self.run_vertex(pipeline, vertex, instance))
+                .collect::<Vec<_>>();
+            for triangle in &triangles {
+                if let [Some(a), Some(b), Some(c)] = [
+                    &outputs[triangle[0]],
+                    &outputs[triangle[1]],
+                    &outputs[triangle[2]],
+                ] {
+                    self.rasterize(&target, [a, b, c]);
+                }
+            }
+        }
+    }
+
+    fn run_vertex(
+        &self,
+        pipeline: &PipelineState,
+        vertex: u32,
+        instance: u32,
+    ) -> Option<VertexOutput> {
+        let env = VertexEnvironment {
+            renderer: self,
+            pipeline,
+            vertex,
+            instance,
+        };
+        let stage = &pipeline.vertex;
+        let mut invocation = spirv::Invocation::new(&stage.module, &stage.constants, &env);
+        if !invocation.run(stage.function) {
+            return None;
+        }
+        let outputs = invocation.outputs();
+        Some(VertexOutput {
+            position: to_array(&outputs.position.unwrap_or(Value::Undef)),
+            varyings: outputs.locations,
+        })
+    }
+
+    fn rasterize(&self, target: &Target, vertices: [&VertexOutput; 3]) {
+        let rasterizer = &target.pipeline.rasterizer;
+        let depth_clip = rasterizer
+            .depth_clip_enable
+            .unwrap_or(!rasterizer.depth_clamping);
+        let inside = |position: &[f32; 4]| {
+            if depth_clip {
+                position[2] >= 0.0 && position[2] <= position[3]
+            } else {
+                position[3] >= MIN_W
+            }
+        };
+        if vertices.iter().all(|vertex| inside(&vertex.position)) {
+            self.rasterize_clipped(target, vertices);
+            return;
+        }
+
+        let mut polygon = vertices.iter().map(|&vertex| vertex.clone()).collect();
+        let flat_inputs = &target.pipeline.flat_inputs;
+        if depth_clip {
+            polygon = clip(polygon, flat_inputs, |position| position[2]);
+            polygon = clip(polygon, flat_inputs, |position| position[3] - position[2]);
+        } else {
+            polygon = clip(polygon, flat_inputs, |position| position[3] - MIN_W);
+        }
+        for i in 2..polygon.len() {
+            self.rasterize_clipped(target, [&polygon[0], &polygon[i - 1], &polygon[i]]);
+        }
+    }
+
+    fn rasterize_clipped(&self, target: &Target, vertices: [&VertexOutput; 3]) {
+        let viewport = &target.viewport;
+        let window = |position: &[f32; 4]| {
+            let inv_w = 1.0 / position[3];
+            let depth = &viewport.depth;
+            WindowVertex {
+                x: viewport.rect.x as f32
+                    + (position[0] * inv_w + 1.0) * 0.5 * viewport.rect.w as f32,
+                y: viewport.rect.y as f32
+                    + (position[1] * inv_w + 1.0) * 0.5 * viewport.rect.h as f32,
+                z: depth.start + position[2] * inv_w * (depth.end - depth.start),
+                inv_w,
+            }
+        };
+        let mut points = [
+            window(&vertices[0].position),
+            window(&vertices[1].position),
+            window(&vertices[2].position),
+        ];
+
+        let area = edge(&points[0], &points[1], points[2].x, points[2].y);
+        if area == 0.0 || !area.is_finite() {
+            return;
+        }
+        // A negative area in the window space with Y pointing down is counter-clockwise
+        let counter_clockwise = area < 0.0;
+        let rasterizer = &target.pipeline.rasterizer;
+        let front_facing = match rasterizer.front_face {
+            pso::FrontFace::CounterClockwise => counter_clockwise,
+            pso::FrontFace::Clockwise => !counter_clockwise,
+        };
+        let culled_face = if front_facing {
+            pso::Face::FRONT
+        } else {
+            pso::Face::BACK
+        };
+        if rasterizer.cull_face.contains(culled_face) {
+            return;
+        }
+
+        // Make the triangle clockwise, keeping track of the original vertex order
+        let mut order = [0, 1, 2];
+        if counter_clockwise {
+            points.swap(1, 2);
+            order.swap(1, 2);
+        }
+        let area = area.abs();
+        let edges = [(1, 2), (2, 0), (0, 1)];
+        let top_left = [
+            is_top_left(&points[1], &points[2]),
+            is_top_left(&points[2], &points[0]),
+            is_top_left(&points[0], &points[1]),
+        ];
+
+        let (bx0, by0, bx1, by1) = target.bounds;
+        let min_x = points.iter().map(|p| p.x).fold(f32::INFINITY, f32::min);
+        let max_x = points.iter().map(|p| p.x).fold(f32::NEG_INFINITY, f32::max);
+        let min_y = points.iter().map(|p| p.y).fold(f32::INFINITY, f32::min);
+        let max_y = points.iter().map(|p| p.y).fold(f32::NEG_INFINITY, f32::max);
+        let x0 = (min_x.floor() as i32).max(bx0);
+        let x1 = (max_x.ceil() as i32 + 1).min(bx1);
+        let y0 = (min_y.floor() as i32).max(by0);
+        let y1 = (max_y.ceil() as i32 + 1).min(by1);
+
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let (sx, sy) = (x as f32 + 0.5, y as f32 + 0.5);
+                let mut barycentric = [0.0; 3];
+                let mut inside = true;
+                for (i, &(a, b)) in edges.iter().enumerate() {
+                    let weight = edge(&points[a], &points[b], sx, sy);
+                    if weight < 0.0 || (weight == 0.0 && !top_left[i]) {
+                        inside = false;
+                        break;
+                    }
+                    barycentric[i] = weight / area;
+                }
+                if inside {
+                    self.shade(
+                        target,
+                        vertices,
+                        &points,
+                        &order,
+                        barycentric,
+                        front_facing,
+                        x,
+                        y,
+                    );
+                }
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn shade(
+        &self,
+        target: &Target,
+        vertices: [&VertexOutput; 3],
+        points: &[WindowVertex; 3],
+        order: &[usize; 3],
+        barycentric: [f32; 3],
+        front_facing: bool,
+        x: i32,
+        y: i32,
+    ) {
+        let pipeline = target.pipeline;
+        let z = (0..3).map(|i| barycentric[i] * points[i].z).sum::<f32>();
+        let inv_w = (0..3)
+            .map(|i| barycentric[i] * points[i].inv_w)
+            .sum::<f32>();
+
+        // Perspective corrected weights, in the original vertex order
+        let mut weights = [0.0; 3];
+        for i in 0..3 {
+            weights[order[i]] = barycentric[i] * points[i].inv_w / inv_w;
+        }
+        let varyings = vertices[0]
+            .varyings
+            .iter()
+            .map(|&(location, ref value)| {
+                let value = if pipeline.flat_inputs.contains(&location) {
+                    value.clone()
+                } else {
+                    let values = [
+                        value,
+                        vertices[1].varying(location),
+                        vertices[2].varying(location),
+                    ];
+                    interpolate(values, &weights)
+                };
+                (location, value)
+            })
+            .collect::<Vec<_>>();
+
+        let outputs = match pipeline.fragment {
+            Some(ref stage) => {
+                let env = FragmentEnvironment {
+                    renderer: self,
+                    varyings: &varyings,
+                    frag_coord: [x as f32 + 0.5, y as f32 + 0.5, z, inv_w],
+                    front_facing,
+                };
+                let mut invocation = spirv::Invocation::new(&stage.module, &stage.constants, &env);
+                if !invocation.run(stage.function) {
+                    return;
+                }
+                invocation.outputs()
+            }
+            None => spirv::Outputs::default(),
+        };
+
+        let (x, y) = (x as u32, y as u32);
+        if let (Some(test), Some(view)) = (pipeline.depth, target.depth) {
+            let depth = outputs.frag_depth.unwrap_or(z).max(0.0).min(1.0);
+            let stored = view.read(0, 0, x, y, 0).component(0).float();
+            if !compare(test.fun, depth, stored) {
+                return;
+            }
+            if test.write {
+                view.write(0, x, y, &Value::vector(vec![depth, 0.0, 0.0, 0.0]));
+            }
+        }
+
+        for (index, view) in target.colors.iter().enumerate() {
+            let output = match outputs
+                .locations
+                .iter()
+                .find(|&&(location, _)| location == index as u32)
+            {
+                Some((_, output)) => output,
+                None => continue,
+            };
+            if texel::is_integer(view.data.format()) {
+                view.write(0, x, y, output);
+                continue;
+            }
+            let desc = pipeline.blender.targets.get(index);
+            let mask = desc.map_or(pso::ColorMask::ALL, |desc| desc.mask);
+            let src = to_array(output);
+            let dst = to_array(&view.read(0, 0, x, y, 0));
+            let mut result = dst;
+            for (channel, &bit) in [
+                pso::ColorMask::RED,
+                pso::ColorMask::GREEN,
+                pso::ColorMask::BLUE,
+                pso::ColorMask::ALPHA,
+            ]
+            .iter()
+            .enumerate()
+            {
+                if !mask.contains(bit) {
+                    continue;
+                }
+                result[channel] = match desc.and_then(|desc| desc.blend) {
+                    Some(state) => {
+                        let op = if channel == 3 {
+                            state.alpha
+                        } else {
+                            state.color
+                        };
+                        blend(op, channel, &src, &dst, &target.blend_constants)
+                    }
+                    None => src[channel],
+                };
+            }
+            view.write(0, x, y, &Value::vector(result.iter().cloned()));
+        }
+    }
+}
+
+impl VertexOutput {
+    fn varying(&self, location: u32) -> &Value {
+        self.varyings
+            .iter()
+            .find(|&&(loc, _)| loc == location)
+            .map_or(&Value::Undef, |(_, value)| value)
+    }
+}
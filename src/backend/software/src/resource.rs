@@ -0,0 +1,521 @@
+//! Resources backed by host memory.
+
+use crate::{spirv, texel};
+
+use hal::{buffer, device, format, image, pass, pso};
+
+use std::{
+    collections::HashMap,
+    convert::TryInto,
+    ops::Range,
+    ptr,
+    sync::{atomic::AtomicBool, Arc},
+};
+
+/// Host allocation backing a `Memory` object.
+#[derive(Debug)]
+pub struct Storage {
+    ptr: *mut u8,
+    size: u64,
+}
+
+// The storage is only accessed through the raw pointer, either by the application
+// while mapped, or by the queue while executing commands.
+unsafe impl Send for Storage {}
+unsafe impl Sync for Storage {}
+
+impl Storage {
+    fn new(size: u64) -> Result<Self, device::OutOfMemory> {
+        let len: usize = size.try_into().map_err(|_| device::OutOfMemory::Host)?;
+        let data = vec![0u8; len].into_boxed_slice();
+        Ok(Storage {
+            ptr: Box::into_raw(data) as *mut u8,
+            size,
+        })
+    }
+}
+
+impl Drop for Storage {
+    fn drop(&mut self) {
+        unsafe {
+            let data = ptr::slice_from_raw_parts_mut(self.ptr, self.size as usize);
+            drop(Box::from_raw(data));
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Memory {
+    pub(crate) storage: Arc<Storage>,
+}
+
+impl Memory {
+    pub fn allocate(size: u64) -> Result<Self, device::AllocationError> {
+        Ok(Memory {
+            storage: Arc::new(Storage::new(size)?),
+        })
+    }
+
+    pub fn size(&self) -> u64 {
+        self.storage.size
+    }
+
+    pub fn ptr(&self) -> *mut u8 {
+        self.storage.ptr
+    }
+
+    fn slice(&self, offset: u64, size: u64) -> Result<Slice, device::BindError> {
+        if offset + size > self.storage.size {
+            return Err(device::BindError::OutOfBounds);
+        }
+        Ok(Slice {
+            storage: Arc::clone(&self.storage),
+            offset,
+            size,
+        })
+    }
+}
+
+/// Range of a memory allocation bound to a resource.
+#[derive(Clone, Debug)]
+pub struct Slice {
+    storage: Arc<Storage>,
+    offset: u64,
+    pub size: u64,
+}
+
+impl Slice {
+    /// Narrow the slice to a sub-range, clamping it to the bounds.
+    pub fn sub(&self, offset: u64, size: Option<u64>) -> Self {
+        let offset = offset.min(self.size);
+        let size = size.map_or(self.size - offset, |size| size.min(self.size - offset));
+        Slice {
+            storage: Arc::clone(&self.storage),
+            offset: self.offset + offset,
+            size,
+        }
+    }
+
+    /// Move the slice further into the storage, like a dynamic offset does.
+    pub fn shift(&self, offset: u64) -> Self {
+        let offset = (self.offset + offset).min(self.storage.size);
+        Slice {
+            storage: Arc::clone(&self.storage),
+            offset,
+            size: self.size.min(self.storage.size - offset),
+        }
+    }
+
+    pub fn memory(&self) -> spirv::Memory {
+        spirv::Memory {
+            ptr: unsafe { self.storage.ptr.add(self.offset as usize) },
+            len: self.size,
+        }
+    }
+
+    /// Read the bytes at the offset, filling with zeros past the end.
+    pub fn read(&self, offset: u64, data: &mut [u8]) {
+        let available = self.size.saturating_sub(offset).min(data.len() as u64) as usize;
+        unsafe {
+            let src = self.storage.ptr.add((self.offset + offset) as usize);
+            ptr::copy(src, data.as_mut_ptr(), available);
+        }
+        for byte in &mut data[available..] {
+            *byte = 0;
+        }
+    }
+
+    /// Write the bytes at the offset, dropping the ones past the end.
+    pub fn write(&self, offset: u64, data: &[u8]) {
+        let available = self.size.saturating_sub(offset).min(data.len() as u64) as usize;
+        unsafe {
+            let dst = self.storage.ptr.add((self.offset + offset) as usize);
+            ptr::copy(data.as_ptr(), dst, available);
+        }
+    }
+
+    /// Copy a range of bytes into another slice, which may alias this one.
+    pub fn copy_to(&self, src_offset: u64, dst: &Slice, dst_offset: u64, size: u64) {
+        let size = size
+            .min(self.size.saturating_sub(src_offset))
+            .min(dst.size.saturating_sub(dst_offset));
+        unsafe {
+            let src = self.storage.ptr.add((self.offset + src_offset) as usize);
+            let dst = dst.storage.ptr.add((dst.offset + dst_offset) as usize);
+            ptr::copy(src, dst, size as usize);
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Buffer {
+    pub(crate) size: u64,
+    pub(crate) slice: Option<Slice>,
+}
+
+impl Buffer {
+    pub fn bind(&mut self, memory: &Memory, offset: u64) -> Result<(), device::BindError> {
+        self.slice = Some(memory.slice(offset, self.size)?);
+        Ok(())
+    }
+
+    pub fn slice(&self, range: &buffer::SubRange) -> Slice {
+        self.slice
+            .as_ref()
+            .expect("Buffer is not bound to memory")
+            .sub(range.offset, range.size)
+    }
+}
+
+/// Placement of a mipmap level, with all its layers stored one after another.
+#[derive(Clone, Copy, Debug)]
+pub struct LevelLayout {
+    pub offset: u64,
+    pub extent: image::Extent,
+    pub row_pitch: u64,
+    pub depth_pitch: u64,
+    pub layer_pitch: u64,
+}
+
+/// Linear layout of the image texels in memory.
+#[derive(Debug)]
+pub struct ImageLayout {
+    pub format: format::Format,
+    pub texel_size: u64,
+    pub layers: image::Layer,
+    pub levels: Vec<LevelLayout>,
+    pub size: u64,
+}
+
+impl ImageLayout {
+    pub fn new(
+        kind: image::Kind,
+        levels: image::Level,
+        format: format::Format,
+    ) -> Result<Self, image::CreationError> {
+        if !texel::is_supported(format) {
+            return Err(image::CreationError::Format(format));
+        }
+        if kind.num_samples() != 1 {
+            return Err(image::CreationError::Samples(kind.num_samples()));
+        }
+        let texel_size = format.surface_desc().bits as u64 / 8;
+        let layers = kind.num_layers();
+        let mut size = 0;
+        let levels = (0..levels)
+            .map(|level| {
+                let extent = kind.level_extent(level);
+                let row_pitch = extent.width as u64 * texel_size;
+                let depth_pitch = row_pitch * extent.height as u64;
+                let layer_pitch = depth_pitch * extent.depth as u64;
+                let layout = LevelLayout {
+                    offset: size,
+                    extent,
+                    row_pitch,
+                    depth_pitch,
+                    layer_pitch,
+                };
+                size += layer_pitch * layers as u64;
+                layout
+            })
+            .collect();
+        Ok(ImageLayout {
+            format,
+            texel_size,
+            layers,
+            levels,
+            size,
+        })
+    }
+}
+
+/// Image contents, shared by the image, its views and the recorded commands.
+#[derive(Clone, Debug)]
+pub struct ImageData {
+    pub(crate) slice: Slice,
+    pub(crate) layout: Arc<ImageLayout>,
+}
+
+impl ImageData {
+    pub fn format(&self) -> format::Format {
+        self.layout.format
+    }
+
+    pub fn level(&self, level: image::Level) -> Option<&LevelLayout> {
+        self.layout.levels.get(level as usize)
+    }
+
+    /// Offset of a texel in the slice, if it's inside the image.
+    pub fn offset(
+        &self,
+        level: image::Level,
+        layer: image::Layer,
+        x: u32,
+        y: u32,
+        z: u32,
+    ) -> Option<u64> {
+        let level = self.level(level)?;
+        if layer >= self.layout.layers
+            || x >= level.extent.width
+            || y >= level.extent.height
+            || z >= level.extent.depth
+        {
+            return None;
+        }
+        Some(
+            level.offset
+                + layer as u64 * level.layer_pitch
+                + z as u64 * level.depth_pitch
+                + y as u64 * level.row_pitch
+                + x as u64 * self.layout.texel_size,
+        )
+    }
+
+    /// Decode a texel, reading zero outside of the image.
+    pub fn read(
+        &self,
+        level: image::Level,
+        layer: image::Layer,
+        x: u32,
+        y: u32,
+        z: u32,
+    ) -> spirv::Value {
+        let mut bytes = [0u8; 16];
+        if let Some(offset) = self.offset(level, layer, x, y, z) {
+            self.slice
+                .read(offset, &mut bytes[..self.layout.texel_size as usize]);
+        }
+        texel::decode(self.layout.format, &bytes)
+    }
+
+    /// Encode a texel, ignoring the writes outside of the image.
+    pub fn write(
+        &self,
+        level: image::Level,
+        layer: image::Layer,
+        x: u32,
+        y: u32,
+        z: u32,
+        value: &spirv::Value,
+    ) {
+        if let Some(offset) = self.offset(level, layer, x, y, z) {
+            let mut bytes = [0u8; 16];
+            texel::encode(self.layout.format, value, &mut bytes);
+            self.slice
+                .write(offset, &bytes[..self.layout.texel_size as usize]);
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Image {
+    pub(crate) kind: image::Kind,
+    pub(crate) layout: Arc<ImageLayout>,
+    pub(crate) data: Option<ImageData>,
+}
+
+impl Image {
+    pub fn bind(&mut self, memory: &Memory, offset: u64) -> Result<(), device::BindError> {
+        self.data = Some(ImageData {
+            slice: memory.slice(offset, self.layout.size)?,
+            layout: Arc::clone(&self.layout),
+        });
+        Ok(())
+    }
+
+    pub fn data(&self) -> &ImageData {
+        self.data.as_ref().expect("Image is not bound to memory")
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ImageView {
+    pub(crate) data: ImageData,
+    pub(crate) kind: image::ViewKind,
+    pub(crate) levels: Range<image::Level>,
+    pub(crate) layers: Range<image::Layer>,
+}
+
+impl ImageView {
+    pub fn levels(&self) -> image::Level {
+        self.levels.end - self.levels.start
+    }
+
+    /// Size of the view at the level, in the order used by the size queries.
+    pub fn size(&self, level: u32) -> [u32; 3] {
+        let extent = self
+            .data
+            .level(self.levels.start + level as image::Level)
+            .map_or(image::Extent::default(), |level| level.extent);
+        let layers = (self.layers.end - self.layers.start) as u32;
+        match self.kind {
+            image::ViewKind::D1 => [extent.width, 1, 1],
+            image::ViewKind::D1Array => [extent.width, layers, 1],
+            image::ViewKind::D2 | image::ViewKind::Cube => [extent.width, extent.height, 1],
+            image::ViewKind::D2Array => [extent.width, extent.height, layers],
+            image::ViewKind::CubeArray => [extent.width, extent.height, layers / 6],
+            image::ViewKind::D3 => [extent.width, extent.height, extent.depth],
+        }
+    }
+
+    /// Decode a texel relative to the base level and layer of the view.
+    pub fn read(
+        &self,
+        level: image::Level,
+        layer: image::Layer,
+        x: u32,
+        y: u32,
+        z: u32,
+    ) -> spirv::Value {
+        self.data.read(
+            self.levels.start + level,
+            self.layers.start + layer,
+            x,
+            y,
+            z,
+        )
+    }
+
+    pub fn write(&self, layer: image::Layer, x: u32, y: u32, value: &spirv::Value) {
+        self.data
+            .write(self.levels.start, self.layers.start + layer, x, y, 0, value)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Sampler {
+    pub(crate) desc: Arc<image::SamplerDesc>,
+}
+
+#[derive(Debug)]
+pub struct ShaderModule {
+    pub(crate) module: Arc<spirv::Module>,
+}
+
+#[derive(Debug)]
+pub struct Subpass {
+    pub colors: Vec<pass::AttachmentId>,
+    pub depth_stencil: Option<pass::AttachmentId>,
+}
+
+#[derive(Debug)]
+pub struct RenderPassInfo {
+    pub attachments: Vec<pass::Attachment>,
+    pub subpasses: Vec<Subpass>,
+}
+
+#[derive(Debug)]
+pub struct RenderPass {
+    pub(crate) info: Arc<RenderPassInfo>,
+}
+
+/// Framebuffer, carrying no state since the attachments are provided with the render pass.
+#[derive(Debug)]
+pub struct Framebuffer;
+
+/// Shader entry point with its constants specialized.
+#[derive(Debug)]
+pub struct Stage {
+    pub module: Arc<spirv::Module>,
+    pub function: spirv::Id,
+    pub constants: spirv::Constants,
+}
+
+#[derive(Debug)]
+pub struct PipelineState {
+    pub vertex: Stage,
+    pub fragment: Option<Stage>,
+    pub buffers: Vec<pso::VertexBufferDesc>,
+    pub attributes: Vec<pso::AttributeDesc>,
+    pub primitive: pso::Primitive,
+    pub rasterizer: pso::Rasterizer,
+    pub blender: pso::BlendDesc,
+    pub depth: Option<pso::DepthTest>,
+    pub baked_states: pso::BakedStates,
+    /// Locations of the fragment inputs that are not interpolated.
+    pub flat_inputs: Vec<u32>,
+}
+
+#[derive(Debug)]
+pub struct GraphicsPipeline {
+    pub(crate) state: Arc<PipelineState>,
+}
+
+#[derive(Clone, Debug)]
+pub enum Descriptor {
+    Buffer(Slice),
+    Image(ImageView),
+    Sampler(Sampler),
+    CombinedImageSampler(ImageView, Sampler),
+}
+
+impl Descriptor {
+    pub fn value(&self) -> spirv::Value {
+        match *self {
+            Descriptor::Buffer(_) => spirv::Value::Undef,
+            Descriptor::Image(ref view) => spirv::Value::Image(view.clone()),
+            Descriptor::Sampler(ref sampler) => spirv::Value::Sampler(sampler.clone()),
+            Descriptor::CombinedImageSampler(ref view, ref sampler) => {
+                spirv::Value::SampledImage(view.clone(), sampler.clone())
+            }
+        }
+    }
+}
+
+/// Descriptors keyed by the binding and the array index.
+pub type Descriptors = HashMap<(pso::DescriptorBinding, pso::DescriptorArrayIndex), Descriptor>;
+
+#[derive(Debug)]
+pub struct DescriptorSetLayout {
+    pub(crate) bindings: Arc<Vec<pso::DescriptorSetLayoutBinding>>,
+    pub(crate) immutable_samplers: Descriptors,
+}
+
+#[derive(Debug)]
+pub struct DescriptorSet {
+    pub(crate) bindings: Arc<Vec<pso::DescriptorSetLayoutBinding>>,
+    pub(crate) descriptors: Descriptors,
+}
+
+impl DescriptorSet {
+    /// Snapshot the descriptors for binding, applying the dynamic offsets.
+    pub fn resolve(&self, offsets: &mut impl Iterator<Item = u32>) -> Descriptors {
+        let mut descriptors = self.descriptors.clone();
+        let mut bindings = self.bindings.iter().collect::<Vec<_>>();
+        bindings.sort_by_key(|binding| binding.binding);
+        for binding in bindings {
+            match binding.ty {
+                pso::DescriptorType::Buffer {
+                    format:
+                        pso::BufferDescriptorFormat::Structured {
+                            dynamic_offset: true,
+                        },
+                    ..
+                } => {}
+                _ => continue,
+            }
+            for index in 0..binding.count as pso::DescriptorArrayIndex {
+                let offset = offsets.next().unwrap_or(0) as u64;
+                if let Some(Descriptor::Buffer(slice)) =
+                    descriptors.get_mut(&(binding.binding, index))
+                {
+                    *slice = slice.shift(offset);
+                }
+            }
+        }
+        descriptors
+    }
+}
+
+#[derive(Debug)]
+pub struct DescriptorPool;
+
+#[derive(Debug)]
+pub struct Fence {
+    pub(crate) signaled: bool,
+}
+
+#[derive(Debug)]
+pub struct Event {
+    pub(crate) set: Arc<AtomicBool>,
+}
@@ -0,0 +1,2111 @@
+//! Interpreter for the subset of SPIR-V used by simple graphics shaders.
+//!
+//! Modules are parsed once when the shader module is created, rejecting any instruction
+//! the interpreter can't execute, and every shader invocation is then run on its own.
+//! Since invocations are not grouped in quads, derivatives are always zero and
+//! implicit level of detail sampling reads the base level of the view.
+
+use crate::{
+    resource::{ImageView, Sampler},
+    texel,
+};
+use hal::pso;
+
+use std::collections::{BTreeMap, HashMap};
+
+pub type Id = u32;
+
+/// Values of the constants, after specialization.
+pub type Constants = HashMap<Id, Value>;
+
+const MAGIC_NUMBER: u32 = 0x0723_0203;
+/// Number of executed instructions after which an invocation is considered stuck.
+const STEP_LIMIT: usize = 1 << 24;
+
+mod op {
+    pub const NOP: u32 = 0;
+    pub const UNDEF: u32 = 1;
+    pub const LINE: u32 = 8;
+    pub const EXT_INST_IMPORT: u32 = 11;
+    pub const EXT_INST: u32 = 12;
+    pub const ENTRY_POINT: u32 = 15;
+    pub const TYPE_VOID: u32 = 19;
+    pub const TYPE_BOOL: u32 = 20;
+    pub const TYPE_INT: u32 = 21;
+    pub const TYPE_FLOAT: u32 = 22;
+    pub const TYPE_VECTOR: u32 = 23;
+    pub const TYPE_MATRIX: u32 = 24;
+    pub const TYPE_IMAGE: u32 = 25;
+    pub const TYPE_SAMPLER: u32 = 26;
+    pub const TYPE_SAMPLED_IMAGE: u32 = 27;
+    pub const TYPE_ARRAY: u32 = 28;
+    pub const TYPE_RUNTIME_ARRAY: u32 = 29;
+    pub const TYPE_STRUCT: u32 = 30;
+    pub const TYPE_POINTER: u32 = 32;
+    pub const TYPE_FUNCTION: u32 = 33;
+    pub const CONSTANT_TRUE: u32 = 41;
+    pub const CONSTANT_FALSE: u32 = 42;
+    pub const CONSTANT: u32 = 43;
+    pub const CONSTANT_COMPOSITE: u32 = 44;
+    pub const CONSTANT_NULL: u32 = 46;
+    pub const SPEC_CONSTANT_TRUE: u32 = 48;
+    pub const SPEC_CONSTANT_FALSE: u32 = 49;
+    pub const SPEC_CONSTANT: u32 = 50;
+    pub const SPEC_CONSTANT_COMPOSITE: u32 = 51;
+    pub const SPEC_CONSTANT_OP: u32 = 52;
+    pub const FUNCTION: u32 = 54;
+    pub const FUNCTION_PARAMETER: u32 = 55;
+    pub const FUNCTION_END: u32 = 56;
+    pub const FUNCTION_CALL: u32 = 57;
+    pub const VARIABLE: u32 = 59;
+    pub const LOAD: u32 = 61;
+    pub const STORE: u32 = 62;
+    pub const COPY_MEMORY: u32 = 63;
+    pub const ACCESS_CHAIN: u32 = 65;
+    pub const IN_BOUNDS_ACCESS_CHAIN: u32 = 66;
+    pub const ARRAY_LENGTH: u32 = 68;
+    pub const DECORATE: u32 = 71;
+    pub const MEMBER_DECORATE: u32 = 72;
+    pub const VECTOR_EXTRACT_DYNAMIC: u32 = 77;
+    pub const VECTOR_INSERT_DYNAMIC: u32 = 78;
+    pub const VECTOR_SHUFFLE: u32 = 79;
+    pub const COMPOSITE_CONSTRUCT: u32 = 80;
+    pub const COMPOSITE_EXTRACT: u32 = 81;
+    pub const COMPOSITE_INSERT: u32 = 82;
+    pub const COPY_OBJECT: u32 = 83;
+    pub const TRANSPOSE: u32 = 84;
+    pub const SAMPLED_IMAGE: u32 = 86;
+    pub const IMAGE_SAMPLE_IMPLICIT_LOD: u32 = 87;
+    pub const IMAGE_SAMPLE_EXPLICIT_LOD: u32 = 88;
+    pub const IMAGE_FETCH: u32 = 95;
+    pub const IMAGE: u32 = 100;
+    pub const IMAGE_QUERY_SIZE_LOD: u32 = 103;
+    pub const IMAGE_QUERY_SIZE: u32 = 104;
+    pub const IMAGE_QUERY_LEVELS: u32 = 106;
+    pub const CONVERT_F_TO_U: u32 = 109;
+    pub const CONVERT_F_TO_S: u32 = 110;
+    pub const CONVERT_S_TO_F: u32 = 111;
+    pub const CONVERT_U_TO_F: u32 = 112;
+    pub const U_CONVERT: u32 = 113;
+    pub const S_CONVERT: u32 = 114;
+    pub const F_CONVERT: u32 = 115;
+    pub const BITCAST: u32 = 124;
+    pub const S_NEGATE: u32 = 126;
+    pub const F_NEGATE: u32 = 127;
+    pub const I_ADD: u32 = 128;
+    pub const F_ADD: u32 = 129;
+    pub const I_SUB: u32 = 130;
+    pub const F_SUB: u32 = 131;
+    pub const I_MUL: u32 = 132;
+    pub const F_MUL: u32 = 133;
+    pub const U_DIV: u32 = 134;
+    pub const S_DIV: u32 = 135;
+    pub const F_DIV: u32 = 136;
+    pub const U_MOD: u32 = 137;
+    pub const S_REM: u32 = 138;
+    pub const S_MOD: u32 = 139;
+    pub const F_REM: u32 = 140;
+    pub const F_MOD: u32 = 141;
+    pub const VECTOR_TIMES_SCALAR: u32 = 142;
+    pub const MATRIX_TIMES_SCALAR: u32 = 143;
+    pub const VECTOR_TIMES_MATRIX: u32 = 144;
+    pub const MATRIX_TIMES_VECTOR: u32 = 145;
+    pub const MATRIX_TIMES_MATRIX: u32 = 146;
+    pub const OUTER_PRODUCT: u32 = 147;
+    pub const DOT: u32 = 148;
+    pub const ANY: u32 = 154;
+    pub const ALL: u32 = 155;
+    pub const IS_NAN: u32 = 156;
+    pub const IS_INF: u32 = 157;
+    pub const LOGICAL_EQUAL: u32 = 164;
+    pub const LOGICAL_NOT_EQUAL: u32 = 165;
+    pub const LOGICAL_OR: u32 = 166;
+    pub const LOGICAL_AND: u32 = 167;
+    pub const LOGICAL_NOT: u32 = 168;
+    pub const SELECT: u32 = 169;
+    pub const I_EQUAL: u32 = 170;
+    pub const I_NOT_EQUAL: u32 = 171;
+    pub const U_GREATER_THAN: u32 = 172;
+    pub const S_GREATER_THAN: u32 = 173;
+    pub const U_GREATER_THAN_EQUAL: u32 = 174;
+    pub const S_GREATER_THAN_EQUAL: u32 = 175;
+    pub const U_LESS_THAN: u32 = 176;
+    pub const S_LESS_THAN: u32 = 177;
+    pub const U_LESS_THAN_EQUAL: u32 = 178;
+    pub const S_LESS_THAN_EQUAL: u32 = 179;
+    pub const F_ORD_EQUAL: u32 = 180;
+    pub const F_UNORD_EQUAL: u32 = 181;
+    pub const F_ORD_NOT_EQUAL: u32 = 182;
+    pub const F_UNORD_NOT_EQUAL: u32 = 183;
+    pub const F_ORD_LESS_THAN: u32 = 184;
+    pub const F_UNORD_LESS_THAN: u32 = 185;
+    pub const F_ORD_GREATER_THAN: u32 = 186;
+    pub const F_UNORD_GREATER_THAN: u32 = 187;
+    pub const F_ORD_LESS_THAN_EQUAL: u32 = 188;
+    pub const F_UNORD_LESS_THAN_EQUAL: u32 = 189;
+    pub const F_ORD_GREATER_THAN_EQUAL: u32 = 190;
+    pub const F_UNORD_GREATER_THAN_EQUAL: u32 = 191;
+    pub const SHIFT_RIGHT_LOGICAL: u32 = 194;
+    pub const SHIFT_RIGHT_ARITHMETIC: u32 = 195;
+    pub const SHIFT_LEFT_LOGICAL: u32 = 196;
+    pub const BITWISE_OR: u32 = 197;
+    pub const BITWISE_XOR: u32 = 198;
+    pub const BITWISE_AND: u32 = 199;
+    pub const NOT: u32 = 200;
+    pub const DPDX: u32 = 207;
+    pub const FWIDTH_COARSE: u32 = 215;
+    pub const PHI: u32 = 245;
+    pub const LOOP_MERGE: u32 = 246;
+    pub const SELECTION_MERGE: u32 = 247;
+    pub const LABEL: u32 = 248;
+    pub const BRANCH: u32 = 249;
+    pub const BRANCH_CONDITIONAL: u32 = 250;
+    pub const SWITCH: u32 = 251;
+    pub const KILL: u32 = 252;
+    pub const RETURN: u32 = 253;
+    pub const RETURN_VALUE: u32 = 254;
+    pub const UNREACHABLE: u32 = 255;
+    pub const NO_LINE: u32 = 317;
+}
+
+mod decoration {
+    pub const SPEC_ID: u32 = 1;
+    pub const ROW_MAJOR: u32 = 4;
+    pub const ARRAY_STRIDE: u32 = 6;
+    pub const MATRIX_STRIDE: u32 = 7;
+    pub const BUILT_IN: u32 = 11;
+    pub const FLAT: u32 = 14;
+    pub const LOCATION: u32 = 30;
+    pub const BINDING: u32 = 33;
+    pub const DESCRIPTOR_SET: u32 = 34;
+    pub const OFFSET: u32 = 35;
+}
+
+pub mod builtin {
+    pub const POSITION: u32 = 0;
+    pub const FRAG_COORD: u32 = 15;
+    pub const FRONT_FACING: u32 = 17;
+    pub const FRAG_DEPTH: u32 = 22;
+    pub const VERTEX_INDEX: u32 = 42;
+    pub const INSTANCE_INDEX: u32 = 43;
+}
+
+pub mod model {
+    pub const VERTEX: u32 = 0;
+    pub const FRAGMENT: u32 = 4;
+}
+
+mod storage {
+    pub const UNIFORM_CONSTANT: u32 = 0;
+    pub const INPUT: u32 = 1;
+    pub const UNIFORM: u32 = 2;
+    pub const OUTPUT: u32 = 3;
+    pub const PUSH_CONSTANT: u32 = 9;
+    pub const STORAGE_BUFFER: u32 = 12;
+}
+
+mod glsl {
+    pub const ROUND: u32 = 1;
+    pub const ROUND_EVEN: u32 = 2;
+    pub const TRUNC: u32 = 3;
+    pub const F_ABS: u32 = 4;
+    pub const S_ABS: u32 = 5;
+    pub const F_SIGN: u32 = 6;
+    pub const S_SIGN: u32 = 7;
+    pub const FLOOR: u32 = 8;
+    pub const CEIL: u32 = 9;
+    pub const FRACT: u32 = 10;
+    pub const RADIANS: u32 = 11;
+    pub const DEGREES: u32 = 12;
+    pub const SIN: u32 = 13;
+    pub const COS: u32 = 14;
+    pub const TAN: u32 = 15;
+    pub const ASIN: u32 = 16;
+    pub const ACOS: u32 = 17;
+    pub const ATAN: u32 = 18;
+    pub const ATAN2: u32 = 25;
+    pub const POW: u32 = 26;
+    pub const EXP: u32 = 27;
+    pub const LOG: u32 = 28;
+    pub const EXP2: u32 = 29;
+    pub const LOG2: u32 = 30;
+    pub const SQRT: u32 = 31;
+    pub const INVERSE_SQRT: u32 = 32;
+    pub const F_MIN: u32 = 37;
+    pub const U_MIN: u32 = 38;
+    pub const S_MIN: u32 = 39;
+    pub const F_MAX: u32 = 40;
+    pub const U_MAX: u32 = 41;
+    pub const S_MAX: u32 = 42;
+    pub const F_CLAMP: u32 = 43;
+    pub const U_CLAMP: u32 = 44;
+    pub const S_CLAMP: u32 = 45;
+    pub const F_MIX: u32 = 46;
+    pub const STEP: u32 = 48;
+    pub const SMOOTH_STEP: u32 = 49;
+    pub const FMA: u32 = 50;
+    pub const LENGTH: u32 = 66;
+    pub const DISTANCE: u32 = 67;
+    pub const CROSS: u32 = 68;
+    pub const NORMALIZE: u32 = 69;
+    pub const REFLECT: u32 = 71;
+    pub const N_MIN: u32 = 79;
+    pub const N_MAX: u32 = 80;
+    pub const N_CLAMP: u32 = 81;
+}
+
+/// Instructions allowed inside function bodies, besides the ones executed directly.
+const SUPPORTED_INSTRUCTIONS: &[u32] = &[
+    op::UNDEF,
+    op::EXT_INST,
+    op::FUNCTION_CALL,
+    op::VARIABLE,
+    op::LOAD,
+    op::STORE,
+    op::COPY_MEMORY,
+    op::ACCESS_CHAIN,
+    op::IN_BOUNDS_ACCESS_CHAIN,
+    op::ARRAY_LENGTH,
+    op::VECTOR_EXTRACT_DYNAMIC,
+    op::VECTOR_INSERT_DYNAMIC,
+    op::VECTOR_SHUFFLE,
+    op::COMPOSITE_CONSTRUCT,
+    op::COMPOSITE_EXTRACT,
+    op::COMPOSITE_INSERT,
+    op::COPY_OBJECT,
+    op::TRANSPOSE,
+    op::SAMPLED_IMAGE,
+    op::IMAGE_SAMPLE_IMPLICIT_LOD,
+    op::IMAGE_SAMPLE_EXPLICIT_LOD,
+    op::IMAGE_FETCH,
+    op::IMAGE,
+    op::IMAGE_QUERY_SIZE_LOD,
+    op::IMAGE_QUERY_SIZE,
+    op::IMAGE_QUERY_LEVELS,
+    op::CONVERT_F_TO_U,
+    op::CONVERT_F_TO_S,
+    op::CONVERT_S_TO_F,
+    op::CONVERT_U_TO_F,
+    op::U_CONVERT,
+    op::S_CONVERT,
+    op::F_CONVERT,
+    op::BITCAST,
+    op::S_NEGATE,
+    op::F_NEGATE,
+    op::I_ADD,
+    op::F_ADD,
+    op::I_SUB,
+    op::F_SUB,
+    op::I_MUL,
+    op::F_MUL,
+    op::U_DIV,
+    op::S_DIV,
+    op::F_DIV,
+    op::U_MOD,
+    op::S_REM,
+    op::S_MOD,
+    op::F_REM,
+    op::F_MOD,
+    op::VECTOR_TIMES_SCALAR,
+    op::MATRIX_TIMES_SCALAR,
+    op::VECTOR_TIMES_MATRIX,
+    op::MATRIX_TIMES_VECTOR,
+    op::MATRIX_TIMES_MATRIX,
+    op::OUTER_PRODUCT,
+    op::DOT,
+    op::ANY,
+    op::ALL,
+    op::IS_NAN,
+    op::IS_INF,
+    op::LOGICAL_EQUAL,
+    op::LOGICAL_NOT_EQUAL,
+    op::LOGICAL_OR,
+    op::LOGICAL_AND,
+    op::LOGICAL_NOT,
+    op::SELECT,
+    op::I_EQUAL,
+    op::I_NOT_EQUAL,
+    op::U_GREATER_THAN,
+    op::S_GREATER_THAN,
+    op::U_GREATER_THAN_EQUAL,
+    op::S_GREATER_THAN_EQUAL,
+    op::U_LESS_THAN,
+    op::S_LESS_THAN,
+    op::U_LESS_THAN_EQUAL,
+    op::S_LESS_THAN_EQUAL,
+    op::F_ORD_EQUAL,
+    op::F_UNORD_EQUAL,
+    op::F_ORD_NOT_EQUAL,
+    op::F_UNORD_NOT_EQUAL,
+    op::F_ORD_LESS_THAN,
+    op::F_UNORD_LESS_THAN,
+    op::F_ORD_GREATER_THAN,
+    op::F_UNORD_GREATER_THAN,
+    op::F_ORD_LESS_THAN_EQUAL,
+    op::F_UNORD_LESS_THAN_EQUAL,
+    op::F_ORD_GREATER_THAN_EQUAL,
+    op::F_UNORD_GREATER_THAN_EQUAL,
+    op::SHIFT_RIGHT_LOGICAL,
+    op::SHIFT_RIGHT_ARITHMETIC,
+    op::SHIFT_LEFT_LOGICAL,
+    op::BITWISE_OR,
+    op::BITWISE_XOR,
+    op::BITWISE_AND,
+    op::NOT,
+    op::PHI,
+    op::LOOP_MERGE,
+    op::SELECTION_MERGE,
+    op::BRANCH,
+    op::BRANCH_CONDITIONAL,
+    op::SWITCH,
+    op::KILL,
+    op::RETURN,
+    op::RETURN_VALUE,
+    op::UNREACHABLE,
+];
+
+const SUPPORTED_EXTENDED_INSTRUCTIONS: &[u32] = &[
+    glsl::ROUND,
+    glsl::ROUND_EVEN,
+    glsl::TRUNC,
+    glsl::F_ABS,
+    glsl::S_ABS,
+    glsl::F_SIGN,
+    glsl::S_SIGN,
+    glsl::FLOOR,
+    glsl::CEIL,
+    glsl::FRACT,
+    glsl::RADIANS,
+    glsl::DEGREES,
+    glsl::SIN,
+    glsl::COS,
+    glsl::TAN,
+    glsl::ASIN,
+    glsl::ACOS,
+    glsl::ATAN,
+    glsl::ATAN2,
+    glsl::POW,
+    glsl::EXP,
+    glsl::LOG,
+    glsl::EXP2,
+    glsl::LOG2,
+    glsl::SQRT,
+    glsl::INVERSE_SQRT,
+    glsl::F_MIN,
+    glsl::U_MIN,
+    glsl::S_MIN,
+    glsl::F_MAX,
+    glsl::U_MAX,
+    glsl::S_MAX,
+    glsl::F_CLAMP,
+    glsl::U_CLAMP,
+    glsl::S_CLAMP,
+    glsl::F_MIX,
+    glsl::STEP,
+    glsl::SMOOTH_STEP,
+    glsl::FMA,
+    glsl::LENGTH,
+    glsl::DISTANCE,
+    glsl::CROSS,
+    glsl::NORMALIZE,
+    glsl::REFLECT,
+    glsl::N_MIN,
+    glsl::N_MAX,
+    glsl::N_CLAMP,
+];
+
+#[derive(Debug)]
+enum Type {
+    Void,
+    Bool,
+    Int,
+    Float,
+    Vector { component: Id, count: u32 },
+    Matrix { column: Id, count: u32 },
+    Image,
+    Sampler,
+    SampledImage,
+    Array { element: Id, length: u32 },
+    RuntimeArray { element: Id },
+    Struct { members: Vec<Id> },
+    Pointer { pointee: Id },
+    Function,
+}
+
+/// Range of host memory visible to the shader, like a uniform or storage buffer.
+#[derive(Clone, Copy, Debug)]
+pub struct Memory {
+    pub ptr: *mut u8,
+    pub len: u64,
+}
+
+// The memory is owned by the resources bound to the command buffer,
+// which are kept alive for the whole execution of the commands.
+unsafe impl Send for Memory {}
+unsafe impl Sync for Memory {}
+
+impl Memory {
+    fn read(&self, offset: u64) -> u32 {
+        if offset + 4 > self.len {
+            return 0;
+        }
+        unsafe { (self.ptr.add(offset as usize) as *const u32).read_unaligned() }
+    }
+
+    fn write(&self, offset: u64, value: u32) {
+        if offset + 4 <= self.len {
+            unsafe { (self.ptr.add(offset as usize) as *mut u32).write_unaligned(value) }
+        }
+    }
+}
+
+/// Explicit layout of a value in memory.
+#[derive(Clone, Copy, Debug)]
+pub struct Layout {
+    matrix_stride: Option<u32>,
+    row_major: bool,
+    /// Distance between the vector components, which differs for rows of row-major matrices.
+    component_stride: u32,
+}
+
+impl Layout {
+    const DEFAULT: Self = Layout {
+        matrix_stride: None,
+        row_major: false,
+        component_stride: 4,
+    };
+}
+
+#[derive(Clone, Debug)]
+pub enum Pointer {
+    /// Part of a variable owned by the invocation.
+    Slot { slot: usize, path: Vec<u32> },
+    /// Part of a buffer.
+    Memory {
+        memory: Memory,
+        offset: u64,
+        ty: Id,
+        layout: Layout,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub enum Value {
+    Undef,
+    Bool(bool),
+    Int(u32),
+    Float(f32),
+    Composite(Vec<Value>),
+    Pointer(Pointer),
+    Image(ImageView),
+    Sampler(Sampler),
+    SampledImage(ImageView, Sampler),
+}
+
+impl Value {
+    pub fn vector(components: impl IntoIterator<Item = f32>) -> Self {
+        Value::Composite(components.into_iter().map(Value::Float).collect())
+    }
+
+    pub fn float(&self) -> f32 {
+        match *self {
+            Value::Float(value) => value,
+            Value::Int(bits) => f32::from_bits(bits),
+            _ => 0.0,
+        }
+    }
+
+    pub fn bits(&self) -> u32 {
+        match *self {
+            Value::Float(value) => value.to_bits(),
+            Value::Int(bits) => bits,
+            Value::Bool(value) => value as u32,
+            _ => 0,
+        }
+    }
+
+    fn int(&self) -> i32 {
+        self.bits() as i32
+    }
+
+    fn boolean(&self) -> bool {
+        match *self {
+            Value::Bool(value) => value,
+            Value::Int(bits) => bits != 0,
+            _ => false,
+        }
+    }
+
+    pub fn components(&self) -> Vec<Value> {
+        match *self {
+            Value::Composite(ref components) => components.clone(),
+            _ => vec![self.clone()],
+        }
+    }
+
+    pub fn floats(&self) -> Vec<f32> {
+        self.components().iter().map(Value::float).collect()
+    }
+
+    pub fn component(&self, index: u32) -> Value {
+        match *self {
+            Value::Composite(ref components) => components
+                .get(index as usize)
+                .cloned()
+                .unwrap_or(Value::Undef),
+            _ => Value::Undef,
+        }
+    }
+
+    fn get_mut(&mut self, path: &[u32]) -> Option<&mut Value> {
+        let mut value = self;
+        for &index in path {
+            value = match value {
+                Value::Composite(components) => components.get_mut(index as usize)?,
+                _ => return None,
+            };
+        }
+        Some(value)
+    }
+
+    fn get(&self, path: &[u32]) -> Option<&Value> {
+        let mut value = self;
+        for &index in path {
+            value = match value {
+                Value::Composite(components) => components.get(index as usize)?,
+                _ => return None,
+            };
+        }
+        Some(value)
+    }
+
+    fn pointer(self) -> Option<Pointer> {
+        match self {
+            Value::Pointer(pointer) => Some(pointer),
+            _ => None,
+        }
+    }
+}
+
+fn unary(a: &Value, fun: &dyn Fn(&Value) -> Value) -> Value {
+    match *a {
+        Value::Composite(ref components) => {
+            Value::Composite(components.iter().map(|a| unary(a, fun)).collect())
+        }
+        _ => fun(a),
+    }
+}
+
+fn binary(a: &Value, b: &Value, fun: &dyn Fn(&Value, &Value) -> Value) -> Value {
+    match (a, b) {
+        (Value::Composite(a), Value::Composite(b)) => Value::Composite(
+            a.iter()
+                .zip(b.iter())
+                .map(|(a, b)| binary(a, b, fun))
+                .collect(),
+        ),
+        (Value::Composite(a), _) => Value::Composite(a.iter().map(|a| binary(a, b, fun)).collect()),
+        (_, Value::Composite(b)) => Value::Composite(b.iter().map(|b| binary(a, b, fun)).collect()),
+        _ => fun(a, b),
+    }
+}
+
+fn ternary(
+    a: &Value,
+    b: &Value,
+    c: &Value,
+    fun: &dyn Fn(&Value, &Value, &Value) -> Value,
+) -> Value {
+    match *a {
+        Value::Composite(ref components) => Value::Composite(
+            components
+                .iter()
+                .enumerate()
+                .map(|(i, a)| {
+                    let b = match *b {
+                        Value::Composite(_) => b.component(i as u32),
+                        _ => b.clone(),
+                    };
+                    let c = match *c {
+                        Value::Composite(_) => c.component(i as u32),
+                        _ => c.clone(),
+                    };
+                    ternary(a, &b, &c, fun)
+                })
+                .collect(),
+        ),
+        _ => fun(a, b, c),
+    }
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b.iter()).map(|(a, b)| a * b).sum()
+}
+
+fn float_compare(a: &Value, b: &Value, ordered: bool, fun: fn(f32, f32) -> bool) -> Value {
+    binary(a, b, &|a, b| {
+        let (a, b) = (a.float(), b.float());
+        Value::Bool(if a.is_nan() || b.is_nan() {
+            !ordered
+        } else {
+            fun(a, b)
+        })
+    })
+}
+
+fn matrix_times_vector(columns: &[Value], vector: &[f32]) -> Value {
+    let mut result = Vec::new();
+    for (column, &scale) in columns.iter().zip(vector.iter()) {
+        let column = column.floats();
+        result.resize(column.len(), 0.0);
+        for (r, c) in result.iter_mut().zip(column) {
+            *r += c * scale;
+        }
+    }
+    Value::vector(result)
+}
+
+#[derive(Debug, Default)]
+struct Decorations {
+    spec_id: Option<u32>,
+    array_stride: Option<u32>,
+    builtin: Option<u32>,
+    flat: bool,
+    location: Option<u32>,
+    binding: Option<u32>,
+    set: Option<u32>,
+}
+
+#[derive(Debug, Default)]
+struct MemberDecorations {
+    offset: u32,
+    matrix_stride: Option<u32>,
+    row_major: bool,
+    builtin: Option<u32>,
+}
+
+#[derive(Debug)]
+struct Instruction {
+    opcode: u32,
+    operands: Vec<u32>,
+}
+
+#[derive(Debug, Default)]
+struct Function {
+    parameters: Vec<Id>,
+    body: Vec<Instruction>,
+    labels: HashMap<Id, usize>,
+}
+
+#[derive(Debug)]
+struct EntryPoint {
+    model: u32,
+    name: String,
+    function: Id,
+}
+
+#[derive(Debug)]
+struct Variable {
+    pointee: Id,
+    storage: u32,
+    initializer: Option<Id>,
+}
+
+fn parse_string(words: &[u32]) -> (String, usize) {
+    let mut bytes = Vec::new();
+    for (i, word) in words.iter().enumerate() {
+        for &byte in word.to_le_bytes().iter() {
+            if byte == 0 {
+                return (String::from_utf8_lossy(&bytes).into_owned(), i + 1);
+            }
+            bytes.push(byte);
+        }
+    }
+    (String::from_utf8_lossy(&bytes).into_owned(), words.len())
+}
+
+/// Parsed shader module.
+#[derive(Debug, Default)]
+pub struct Module {
+    types: HashMap<Id, Type>,
+    constants: Constants,
+    /// Composite constants in the order of definition, rebuilt after specialization.
+    composites: Vec<(Id, Vec<Id>)>,
+    spec_constants: Vec<Id>,
+    variables: BTreeMap<Id, Variable>,
+    decorations: HashMap<Id, Decorations>,
+    member_decorations: HashMap<(Id, u32), MemberDecorations>,
+    functions: HashMap<Id, Function>,
+    entry_points: Vec<EntryPoint>,
+    glsl_std: Option<Id>,
+}
+
+impl Module {
+    pub fn parse(words: &[u32]) -> Result<Self, String> {
+        if words.len() < 5 || words[0] != MAGIC_NUMBER {
+            return Err("invalid SPIR-V header".to_string());
+        }
+
+        let mut module = Module::default();
+        let mut current: Option<(Id, Function)> = None;
+        let mut position = 5;
+        while position < words.len() {
+            let word_count = (words[position] >> 16) as usize;
+            let opcode = words[position] & 0xFFFF;
+            if word_count == 0 || position + word_count > words.len() {
+                return Err("truncated instruction".to_string());
+            }
+            let operands = &words[position + 1..position + word_count];
+            position += word_count;
+
+            if current.is_none() {
+                if opcode == op::FUNCTION {
+                    current = Some((operands[1], Function::default()));
+                } else {
+                    module.parse_global(opcode, operands)?;
+                }
+                continue;
+            }
+
+            match opcode {
+                op::FUNCTION_END => {
+                    let (id, function) = current.take().unwrap();
+                    module.functions.insert(id, function);
+                }
+                op::FUNCTION_PARAMETER => {
+                    let function = &mut current.as_mut().unwrap().1;
+                    function.parameters.push(operands[1]);
+                }
+                op::LABEL => {
+                    let function = &mut current.as_mut().unwrap().1;
+                    function.labels.insert(operands[0], function.body.len());
+                    function.body.push(Instruction {
+                        opcode,
+                        operands: operands.to_vec(),
+                    });
+                }
+                op::NOP | op::LINE | op::NO_LINE => {}
+                _ => {
+                    module.check_instruction(opcode, operands)?;
+                    let function = &mut current.as_mut().unwrap().1;
+                    function.body.push(Instruction {
+                        opcode,
+                        operands: operands.to_vec(),
+                    });
+                }
+            }
+        }
+
+        if current.is_some() {
+            return Err("unterminated function".to_string());
+        }
+        Ok(module)
+    }
+
+    fn check_instruction(&self, opcode: u32, operands: &[u32]) -> Result<(), String> {
+        if opcode == op::EXT_INST {
+            if Some(operands[2]) != self.glsl_std {
+                return Err("unsupported extended instruction set".to_string());
+            }
+            if !SUPPORTED_EXTENDED_INSTRUCTIONS.contains(&operands[3]) {
+                return Err(format!(
+                    "unsupported GLSL.std.450 instruction {}",
+                    operands[3]
+                ));
+            }
+        } else if (op::DPDX..=op::FWIDTH_COARSE).contains(&opcode) {
+            // Derivatives are always zero
+        } else if !SUPPORTED_INSTRUCTIONS.contains(&opcode) {
+            return Err(format!("unsupported instruction {}", opcode));
+        }
+        Ok(())
+    }
+
+    fn parse_global(&mut self, opcode: u32, operands: &[u32]) -> Result<(), String> {
+        match opcode {
+            op::EXT_INST_IMPORT => {
+                if parse_string(&operands[1..]).0 == "GLSL.std.450" {
+                    self.glsl_std = Some(operands[0]);
+                }
+            }
+            op::ENTRY_POINT => {
+                self.entry_points.push(EntryPoint {
+                    model: operands[0],
+                    function: operands[1],
+                    name: parse_string(&operands[2..]).0,
+                });
+            }
+            op::DECORATE => {
+                let decorations = self.decorations.entry(operands[0]).or_default();
+                let value = operands.get(2).cloned();
+                match operands[1] {
+                    decoration::SPEC_ID => decorations.spec_id = value,
+                    decoration::ARRAY_STRIDE => decorations.array_stride = value,
+                    decoration::BUILT_IN => decorations.builtin = value,
+                    decoration::FLAT => decorations.flat = true,
+                    decoration::LOCATION => decorations.location = value,
+                    decoration::BINDING => decorations.binding = value,
+                    decoration::DESCRIPTOR_SET => decorations.set = value,
+                    _ => {}
+                }
+            }
+            op::MEMBER_DECORATE => {
+                let decorations = self
+                    .member_decorations
+                    .entry((operands[0], operands[1]))
+                    .or_default();
+                let value = operands.get(3).cloned();
+                match operands[2] {
+                    decoration::OFFSET => decorations.offset = value.unwrap_or(0),
+                    decoration::MATRIX_STRIDE => decorations.matrix_stride = value,
+                    decoration::ROW_MAJOR => decorations.row_major = true,
+                    decoration::BUILT_IN => decorations.builtin = value,
+                    _ => {}
+                }
+            }
+            op::TYPE_VOID => {
+                self.types.insert(operands[0], Type::Void);
+            }
+            op::TYPE_BOOL => {
+                self.types.insert(operands[0], Type::Bool);
+            }
+            op::TYPE_INT | op::TYPE_FLOAT => {
+                if operands[1] != 32 {
+                    return Err(format!("unsupported {}-bit scalar type", operands[1]));
+                }
+                let ty = if opcode == op::TYPE_INT {
+                    Type::Int
+                } else {
+                    Type::Float
+                };
+                self.types.insert(operands[0], ty);
+            }
+            op::TYPE_VECTOR => {
+                self.types.insert(
+                    operands[0],
+                    Type::Vector {
+                        component: operands[1],
+                        count: operands[2],
+                    },
+                );
+            }
+            op::TYPE_MATRIX => {
+                self.types.insert(
+                    operands[0],
+                    Type::Matrix {
+                        column: operands[1],
+                        count: operands[2],
+                    },
+                );
+            }
+            op::TYPE_IMAGE => {
+                self.types.insert(operands[0], Type::Image);
+            }
+            op::TYPE_SAMPLER => {
+                self.types.insert(operands[0], Type::Sampler);
+            }
+            op::TYPE_SAMPLED_IMAGE => {
+                self.types.insert(operands[0], Type::SampledImage);
+            }
+            op::TYPE_ARRAY => {
+                let length = self.constants.get(&operands[2]).map_or(0, Value::bits);
+                self.types.insert(
+                    operands[0],
+                    Type::Array {
+                        element: operands[1],
+                        length,
+                    },
+                );
+            }
+            op::TYPE_RUNTIME_ARRAY => {
+                self.types.insert(
+                    operands[0],
+                    Type::RuntimeArray {
+                        element: operands[1],
+                    },
+                );
+            }
+            op::TYPE_STRUCT => {
+                self.types.insert(
+                    operands[0],
+                    Type::Struct {
+                        members: operands[1..].to_vec(),
+                    },
+                );
+            }
+            op::TYPE_POINTER => {
+                self.types.insert(
+                    operands[0],
+                    Type::Pointer {
+                        pointee: operands[2],
+                    },
+                );
+            }
+            op::TYPE_FUNCTION => {
+                self.types.insert(operands[0], Type::Function);
+            }
+            op::CONSTANT_TRUE | op::CONSTANT_FALSE => {
+                self.constants
+                    .insert(operands[1], Value::Bool(opcode == op::CONSTANT_TRUE));
+            }
+            op::SPEC_CONSTANT_TRUE | op::SPEC_CONSTANT_FALSE => {
+                self.constants
+                    .insert(operands[1], Value::Bool(opcode == op::SPEC_CONSTANT_TRUE));
+                self.spec_constants.push(operands[1]);
+            }
+            op::CONSTANT | op::SPEC_CONSTANT => {
+                let value = self.scalar(operands[0], operands[2])?;
+                self.constants.insert(operands[1], value);
+                if opcode == op::SPEC_CONSTANT {
+                    self.spec_constants.push(operands[1]);
+                }
+            }
+            op::CONSTANT_COMPOSITE | op::SPEC_CONSTANT_COMPOSITE => {
+                let parts = operands[2..].to_vec();
+                let value = Value::Composite(
+                    parts
+                        .iter()
+                        .map(|part| self.constants.get(part).cloned().unwrap_or(Value::Undef))
+                        .collect(),
+                );
+                self.constants.insert(operands[1], value);
+                self.composites.push((operands[1], parts));
+            }
+            op::CONSTANT_NULL | op::UNDEF => {
+                let value = self.null_value(operands[0]);
+                self.constants.insert(operands[1], value);
+            }
+            op::SPEC_CONSTANT_OP => {
+                return Err("specialization constant operations are not supported".to_string());
+            }
+            op::VARIABLE => {
+                let pointee = match self.types.get(&operands[0]) {
+                    Some(&Type::Pointer { pointee }) => pointee,
+                    _ => return Err("variable is not a pointer".to_string()),
+                };
+                self.variables.insert(
+                    operands[1],
+                    Variable {
+                        pointee,
+                        storage: operands[2],
+                        initializer: operands.get(3).cloned(),
+                    },
+                );
+            }
+            // Debug information, capabilities and execution modes are irrelevant
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn scalar(&self, ty: Id, bits: u32) -> Result<Value, String> {
+        match self.types.get(&ty) {
+            Some(Type::Bool) => Ok(Value::Bool(bits != 0)),
+            Some(Type::Int) => Ok(Value::Int(bits)),
+            Some(Type::Float) => Ok(Value::Float(f32::from_bits(bits))),
+            _ => Err(format!("invalid constant type {}", ty)),
+        }
+    }
+
+    fn null_value(&self, ty: Id) -> Value {
+        match self.types.get(&ty) {
+            Some(Type::Bool) => Value::Bool(false),
+            Some(Type::Int) => Value::Int(0),
+            Some(Type::Float) => Value::Float(0.0),
+            Some(&Type::Vector { component, count })
+            | Some(&Type::Matrix {
+                column: component,
+                count,
+            })
+            | Some(&Type::Array {
+                element: component,
+                length: count,
+            }) => Value::Composite(vec![self.null_value(component); count as usize]),
+            Some(Type::Struct { members }) => Value::Composite(
+                members
+                    .iter()
+                    .map(|&member| self.null_value(member))
+                    .collect(),
+            ),
+            _ => Value::Undef,
+        }
+    }
+
+    /// Shape a four-component input value to the type of the variable.
+    fn shape(&self, value: Value, ty: Id) -> Value {
+        match (self.types.get(&ty), value) {
+            (Some(&Type::Vector { count, .. }), Value::Composite(mut components)) => {
+                components.truncate(count as usize);
+                Value::Composite(components)
+            }
+            (Some(Type::Bool), value @ Value::Composite(_))
+            | (Some(Type::Int), value @ Value::Composite(_))
+            | (Some(Type::Float), value @ Value::Composite(_)) => value.component(0),
+            (_, value) => value,
+        }
+    }
+
+    fn size_of(&self, ty: Id) -> u32 {
+        match self.types.get(&ty) {
+            Some(Type::Bool) | Some(Type::Int) | Some(Type::Float) => 4,
+            Some(&Type::Vector { count, .. }) => 4 * count,
+            Some(&Type::Matrix { column, count }) => self.size_of(column) * count,
+            Some(&Type::Array { length, .. }) => self.array_stride(ty) * length,
+            Some(Type::Struct { members }) => members
+                .iter()
+                .enumerate()
+                .map(|(i, &member)| {
+                    self.member_layout(ty, i as u32).0 as u32 + self.size_of(member)
+                })
+                .max()
+                .unwrap_or(0),
+            _ => 0,
+        }
+    }
+
+    fn array_stride(&self, ty: Id) -> u32 {
+        if let Some(stride) = self.decorations.get(&ty).and_then(|d| d.array_stride) {
+            return stride;
+        }
+        match self.types.get(&ty) {
+            Some(&Type::Array { element, .. }) | Some(&Type::RuntimeArray { element }) => {
+                self.size_of(element)
+            }
+            _ => 0,
+        }
+    }
+
+    fn member_layout(&self, ty: Id, member: u32) -> (u64, Layout) {
+        match self.member_decorations.get(&(ty, member)) {
+            Some(decorations) => (
+                decorations.offset as u64,
+                Layout {
+                    matrix_stride: decorations.matrix_stride,
+                    row_major: decorations.row_major,
+                    component_stride: 4,
+                },
+            ),
+            None => (0, Layout::DEFAULT),
+        }
+    }
+
+    fn column_layout(&self, column: Id, index: u32, layout: Layout) -> (u64, Layout) {
+        let stride = layout.matrix_stride.unwrap_or_else(|| self.size_of(column));
+        if layout.row_major {
+            let layout = Layout {
+                component_stride: stride,
+                ..Layout::DEFAULT
+            };
+            (4 * index as u64, layout)
+        } else {
+            (stride as u64 * index as u64, Layout::DEFAULT)
+        }
+    }
+
+    /// Find the function of an entry point with the given name and execution model.
+    pub fn entry_point(&self, name: &str, model: u32) -> Option<Id> {
+        self.entry_points
+            .iter()
+            .find(|entry| entry.name == name && entry.model == model)
+            .map(|entry| entry.function)
+    }
+
+    /// Locations of the inputs that are not interpolated.
+    pub fn flat_inputs(&self) -> Vec<u32> {
+        self.variables
+            .iter()
+            .filter(|&(_, variable)| variable.storage == storage::INPUT)
+            .filter_map(|(id, _)| self.decorations.get(id))
+            .filter(|decorations| decorations.flat)
+            .filter_map(|decorations| decorations.location)
+            .collect()
+    }
+
+    /// Resolve the values of the constants with the given specialization.
+    pub fn specialize(&self, specialization: &pso::Specialization) -> Result<Constants, String> {
+        let mut constants = self.constants.clone();
+        for &id in &self.spec_constants {
+            let spec_id = match self.decorations.get(&id).and_then(|d| d.spec_id) {
+                Some(spec_id) => spec_id,
+                None => continue,
+            };
+            let constant = match specialization
+                .constants
+                .iter()
+                .find(|constant| constant.id == spec_id)
+            {
+                Some(constant) => constant,
+                None => continue,
+            };
+            let range = constant.range.start as usize..constant.range.end as usize;
+            let data = specialization
+                .data
+                .get(range)
+                .ok_or_else(|| format!("data of constant {} is out of bounds", spec_id))?;
+            let mut bytes = [0u8; 4];
+            for (byte, &value) in bytes.iter_mut().zip(data) {
+                *byte = value;
+            }
+            let bits = u32::from_le_bytes(bytes);
+            let value = match constants[&id] {
+                Value::Bool(_) => Value::Bool(bits != 0),
+                Value::Float(_) => Value::Float(f32::from_bits(bits)),
+                _ => Value::Int(bits),
+            };
+            constants.insert(id, value);
+        }
+        for &(id, ref parts) in &self.composites {
+            let value = Value::Composite(
+                parts
+                    .iter()
+                    .map(|part| constants.get(part).cloned().unwrap_or(Value::Undef))
+                    .collect(),
+            );
+            constants.insert(id, value);
+        }
+        Ok(constants)
+    }
+}
+
+/// Resources and inputs provided to an invocation.
+pub trait Environment {
+    /// Value of the input at the given location, as four components.
+    fn input(&self, location: u32) -> Option<Value>;
+    fn builtin(&self, builtin: u32) -> Option<Value>;
+    /// Memory of a uniform or storage buffer binding.
+    fn buffer(&self, set: u32, binding: u32) -> Option<Memory>;
+    /// Image and sampler descriptors of a binding, as values.
+    fn descriptors(&self, set: u32, binding: u32) -> Vec<Value>;
+    fn push_constants(&self) -> Memory;
+}
+
+/// Values written by an invocation.
+#[derive(Debug, Default)]
+pub struct Outputs {
+    pub locations: Vec<(u32, Value)>,
+    pub position: Option<Value>,
+    pub frag_depth: Option<f32>,
+}
+
+impl Outputs {
+    fn builtin(&mut self, builtin: u32, value: Value) {
+        match builtin {
+            builtin::POSITION => self.position = Some(value),
+            builtin::FRAG_DEPTH => self.frag_depth = Some(value.float()),
+            _ => {}
+        }
+    }
+}
+
+/// State of a single shader invocation.
+pub struct Invocation<'a> {
+    module: &'a Module,
+    constants: &'a Constants,
+    slots: Vec<Value>,
+    globals: HashMap<Id, Pointer>,
+    killed: bool,
+}
+
+impl<'a> Invocation<'a> {
+    pub fn new(module: &'a Module, constants: &'a Constants, env: &dyn Environment) -> Self {
+        let mut slots = Vec::new();
+        let mut globals = HashMap::new();
+
+        for (&id, variable) in &module.variables {
+            let decorations = module.decorations.get(&id);
+            let set = decorations.and_then(|d| d.set).unwrap_or(0);
+            let binding = decorations.and_then(|d| d.binding).unwrap_or(0);
+            let memory = match variable.storage {
+                storage::UNIFORM | storage::STORAGE_BUFFER => env.buffer(set, binding),
+                storage::PUSH_CONSTANT => Some(env.push_constants()),
+                _ => None,
+            };
+            let pointer = match memory {
+                Some(memory) => Pointer::Memory {
+                    memory,
+                    offset: 0,
+                    ty: variable.pointee,
+                    layout: Layout::DEFAULT,
+                },
+                None => {
+                    let value = match variable.storage {
+                        storage::INPUT => match (
+                            decorations.and_then(|d| d.location),
+                            decorations.and_then(|d| d.builtin),
+                        ) {
+                            (Some(location), _) => env
+                                .input(location)
+                                .map(|value| module.shape(value, variable.pointee)),
+                            (None, Some(builtin)) => env.builtin(builtin),
+                            (None, None) => None,
+                        },
+                        storage::UNIFORM_CONSTANT => {
+                            let mut values = env.descriptors(set, binding);
+                            match module.types.get(&variable.pointee) {
+                                Some(Type::Array { .. }) => Some(Value::Composite(values)),
+                                _ if values.is_empty() => None,
+                                _ => Some(values.swap_remove(0)),
+                            }
+                        }
+                        _ => variable
+                            .initializer
+                            .and_then(|init| constants.get(&init).cloned()),
+                    };
+                    slots.push(value.unwrap_or_else(|| module.null_value(variable.pointee)));
+                    Pointer::Slot {
+                        slot: slots.len() - 1,
+                        path: Vec::new(),
+                    }
+                }
+            };
+            globals.insert(id, pointer);
+        }
+
+        Invocation {
+            module,
+            constants,
+            slots,
+            globals,
+            killed: false,
+        }
+    }
+
+    /// Execute the entry point function, returning `false` if the invocation was killed.
+    pub fn run(&mut self, function: Id) -> bool {
+        self.call(function, Vec::new());
+        !self.killed
+    }
+
+    pub fn outputs(&self) -> Outputs {
+        let mut outputs = Outputs::default();
+        for (id, variable) in &self.module.variables {
+            if variable.storage != storage::OUTPUT {
+                continue;
+            }
+            let value = self.load(&self.globals[id]);
+            let decorations = self.module.decorations.get(id);
+            if let Some(location) = decorations.and_then(|d| d.location) {
+                outputs.locations.push((location, value));
+            } else if let Some(builtin) = decorations.and_then(|d| d.builtin) {
+                outputs.builtin(builtin, value);
+            } else if let Some(Type::Struct { members }) = self.module.types.get(&variable.pointee)
+            {
+                for i in 0..members.len() as u32 {
+                    let decorations = self.module.member_decorations.get(&(variable.pointee, i));
+                    if let Some(builtin) = decorations.and_then(|d| d.builtin) {
+                        outputs.builtin(builtin, value.component(i));
+                    }
+                }
+            }
+        }
+        outputs
+    }
+
+    fn get(&self, frame: &HashMap<Id, Value>, id: Id) -> Value {
+        if let Some(value) = frame.get(&id).or_else(|| self.constants.get(&id)) {
+            return value.clone();
+        }
+        match self.globals.get(&id) {
+            Some(pointer) => Value::Pointer(pointer.clone()),
+            None => Value::Undef,
+        }
+    }
+
+    fn call(&mut self, id: Id, arguments: Vec<Value>) -> Option<Value> {
+        let module = self.module;
+        let function = &module.functions[&id];
+        let mut frame = function
+            .parameters
+            .iter()
+            .cloned()
+            .zip(arguments)
+            .collect::<HashMap<_, _>>();
+        let mut pc = 0;
+        let mut block = 0;
+        let mut steps = 0;
+
+        loop {
+            steps += 1;
+            if steps > STEP_LIMIT {
+                error!("Shader invocation exceeded {} steps, aborting", STEP_LIMIT);
+                self.killed = true;
+                return None;
+            }
+            let instruction = &function.body[pc];
+            let ops = &instruction.operands;
+            pc += 1;
+
+            match instruction.opcode {
+                op::LABEL => {
+                    let previous = block;
+                    block = ops[0];
+                    // All the phi nodes of a block read the values at the end of the previous one
+                    let mut phis = Vec::new();
+                    while let Some(phi) = function.body.get(pc).filter(|i| i.opcode == op::PHI) {
+                        let value = phi.operands[2..]
+                            .chunks(2)
+                            .find(|pair| pair.len() == 2 && pair[1] == previous)
+                            .map_or(Value::Undef, |pair| self.get(&frame, pair[0]));
+                        phis.push((phi.operands[1], value));
+                        pc += 1;
+                    }
+                    frame.extend(phis);
+                }
+                op::SELECTION_MERGE | op::LOOP_MERGE => {}
+                op::BRANCH => pc = function.labels[&ops[0]],
+                op::BRANCH_CONDITIONAL => {
+                    let target = if self.get(&frame, ops[0]).boolean() {
+                        ops[1]
+                    } else {
+                        ops[2]
+                    };
+                    pc = function.labels[&target];
+                }
+                op::SWITCH => {
+                    let selector = self.get(&frame, ops[0]).bits();
+                    let target = ops[2..]
+                        .chunks(2)
+                        .find(|pair| pair.len() == 2 && pair[0] == selector)
+                        .map_or(ops[1], |pair| pair[1]);
+                    pc = function.labels[&target];
+                }
+                op::RETURN | op::UNREACHABLE => return None,
+                op::RETURN_VALUE => return Some(self.get(&frame, ops[0])),
+                op::KILL => {
+                    self.killed = true;
+                    return None;
+                }
+                op::FUNCTION_CALL => {
+                    let arguments = ops[3..].iter().map(|&id| self.get(&frame, id)).collect();
+                    let result = self.call(ops[2], arguments);
+                    if self.killed {
+                        return None;
+                    }
+                    frame.insert(ops[1], result.unwrap_or(Value::Undef));
+                }
+                op::VARIABLE => {
+                    let value = match ops.get(3) {
+                        Some(&init) => self.get(&frame, init),
+                        None => match module.types.get(&ops[0]) {
+                            Some(&Type::Pointer { pointee }) => module.null_value(pointee),
+                            _ => Value::Undef,
+                        },
+                    };
+                    self.slots.push(value);
+                    let pointer = Pointer::Slot {
+                        slot: self.slots.len() - 1,
+                        path: Vec::new(),
+                    };
+                    frame.insert(ops[1], Value::Pointer(pointer));
+                }
+                op::LOAD => {
+                    let value = match self.get(&frame, ops[2]).pointer() {
+                        Some(pointer) => self.load(&pointer),
+                        None => Value::Undef,
+                    };
+                    frame.insert(ops[1], value);
+                }
+                op::STORE => {
+                    if let Some(pointer) = self.get(&frame, ops[0]).pointer() {
+                        let value = self.get(&frame, ops[1]);
+                        self.store(&pointer, value);
+                    }
+                }
+                op::COPY_MEMORY => {
+                    let dst = self.get(&frame, ops[0]).pointer();
+                    let src = self.get(&frame, ops[1]).pointer();
+                    if let (Some(dst), Some(src)) = (dst, src) {
+                        let value = self.load(&src);
+                        self.store(&dst, value);
+                    }
+                }
+                op::ACCESS_CHAIN | op::IN_BOUNDS_ACCESS_CHAIN => {
+                    let indices = ops[3..]
+                        .iter()
+                        .map(|&id| self.get(&frame, id).bits())
+                        .collect::<Vec<_>>();
+                    let value = match self.get(&frame, ops[2]).pointer() {
+                        Some(pointer) => Value::Pointer(self.access(pointer, &indices)),
+                        None => Value::Undef,
+                    };
+                    frame.insert(ops[1], value);
+                }
+                _ => {
+                    let value = self.compute(&frame, instruction);
+                    frame.insert(ops[1], value);
+                }
+            }
+        }
+    }
+
+    fn load(&self, pointer: &Pointer) -> Value {
+        match *pointer {
+            Pointer::Slot { slot, ref path } => {
+                self.slots[slot].get(path).cloned().unwrap_or(Value::Undef)
+            }
+            Pointer::Memory {
+                ref memory,
+                offset,
+                ty,
+                layout,
+            } => self.read_memory(memory, offset, ty, layout),
+        }
+    }
+
+    fn store(&mut self, pointer: &Pointer, value: Value) {
+        match *pointer {
+            Pointer::Slot { slot, ref path } => {
+                if let Some(target) = self.slots[slot].get_mut(path) {
+                    *target = value;
+                }
+            }
+            Pointer::Memory {
+                ref memory,
+                offset,
+                ty,
+                layout,
+            } => self.write_memory(memory, offset, ty, layout, &value),
+        }
+    }
+
+    fn access(&self, pointer: Pointer, indices: &[u32]) -> Pointer {
+        match pointer {
+            Pointer::Slot { slot, mut path } => {
+                path.extend_from_slice(indices);
+                Pointer::Slot { slot, path }
+            }
+            Pointer::Memory {
+                memory,
+                mut offset,
+                mut ty,
+                mut layout,
+            } => {
+                let module = self.module;
+                for &index in indices {
+                    match module.types.get(&ty) {
+                        Some(Type::Struct { members }) => {
+                            let (member_offset, member_layout) = module.member_layout(ty, index);
+                            offset += member_offset;
+                            layout = member_layout;
+                            ty = members.get(index as usize).cloned().unwrap_or(0);
+                        }
+                        Some(&Type::Array { element, .. })
+                        | Some(&Type::RuntimeArray { element }) => {
+                            offset += index as u64 * module.array_stride(ty) as u64;
+                            ty = element;
+                        }
+                        Some(&Type::Matrix { column, .. }) => {
+                            let (column_offset, column_layout) =
+                                module.column_layout(column, index, layout);
+                            offset += column_offset;
+                            layout = column_layout;
+                            ty = column;
+                        }
+                        Some(&Type::Vector { component, .. }) => {
+                            offset += index as u64 * layout.component_stride as u64;
+                            layout = Layout::DEFAULT;
+                            ty = component;
+                        }
+                        _ => {}
+                    }
+                }
+                Pointer::Memory {
+                    memory,
+                    offset,
+                    ty,
+                    layout,
+                }
+            }
+        }
+    }
+
+    fn read_memory(&self, memory: &Memory, offset: u64, ty: Id, layout: Layout) -> Value {
+        let module = self.module;
+        match module.types.get(&ty) {
+            Some(Type::Bool) => Value::Bool(memory.read(offset) != 0),
+            Some(Type::Int) => Value::Int(memory.read(offset)),
+            Some(Type::Float) => Value::Float(f32::from_bits(memory.read(offset))),
+            Some(&Type::Vector { component, count }) => Value::Composite(
+                (0..count)
+                    .map(|i| {
+                        let offset = offset + (i * layout.component_stride) as u64;
+                        self.read_memory(memory, offset, component, Layout::DEFAULT)
+                    })
+                    .collect(),
+            ),
+            Some(&Type::Matrix { column, count }) => Value::Composite(
+                (0..count)
+                    .map(|i| {
+                        let (column_offset, column_layout) =
+                            module.column_layout(column, i, layout);
+                        self.read_memory(memory, offset + column_offset, column, column_layout)
+                    })
+                    .collect(),
+            ),
+            Some(&Type::Array { element, length }) => {
+                let stride = module.array_stride(ty) as u64;
+                Value::Composite(
+                    (0..length as u64)
+                        .map(|i| self.read_memory(memory, offset + i * stride, element, layout))
+                        .collect(),
+                )
+            }
+            Some(Type::Struct { members }) => Value::Composite(
+                members
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &member)| {
+                        let (member_offset, member_layout) = module.member_layout(ty, i as u32);
+                        self.read_memory(memory, offset + member_offset, member, member_layout)
+                    })
+                    .collect(),
+            ),
+            _ => Value::Undef,
+        }
+    }
+
+    fn write_memory(&self, memory: &Memory, offset: u64, ty: Id, layout: Layout, value: &Value) {
+        let module = self.module;
+        match module.types.get(&ty) {
+            Some(Type::Bool) | Some(Type::Int) | Some(Type::Float) => {
+                memory.write(offset, value.bits())
+            }
+            Some(&Type::Vector { component, count }) => {
+                for i in 0..count {
+                    let offset = offset + (i * layout.component_stride) as u64;
+                    let component_value = value.component(i);
+                    self.write_memory(memory, offset, component, Layout::DEFAULT, &component_value);
+                }
+            }
+            Some(&Type::Matrix { column, count }) => {
+                for i in 0..count {
+                    let (column_offset, column_layout) = module.column_layout(column, i, layout);
+                    let column_value = value.component(i);
+                    self.write_memory(
+                        memory,
+                        offset + column_offset,
+                        column,
+                        column_layout,
+                        &column_value,
+                    );
+                }
+            }
+            Some(&Type::Array { element, length }) => {
+                let stride = module.array_stride(ty) as u64;
+                for i in 0..length {
+                    let element_value = value.component(i);
+                    let offset = offset + i as u64 * stride;
+                    self.write_memory(memory, offset, element, layout, &element_value);
+                }
+            }
+            Some(Type::Struct { members }) => {
+                for (i, &member) in members.iter().enumerate() {
+                    let (member_offset, member_layout) = module.member_layout(ty, i as u32);
+                    let member_value = value.component(i as u32);
+                    self.write_memory(
+                        memory,
+                        offset + member_offset,
+                        member,
+                        member_layout,
+                        &member_value,
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Execute an instruction that produces a value without side effects.
+    fn compute(&self, frame: &HashMap<Id, Value>, instruction: &Instruction) -> Value {
+        let module = self.module;
+        let ops = &instruction.operands;
+        let ty = ops[0];
+        let arg = |index: usize| self.get(frame, ops[index]);
+
+        match instruction.opcode {
+            op::UNDEF => module.null_value(ty),
+            op::COPY_OBJECT => arg(2),
+            op::COMPOSITE_CONSTRUCT => {
+                let constituents = (2..ops.len()).map(arg);
+                match module.types.get(&ty) {
+                    Some(Type::Vector { .. }) => Value::Composite(
+                        constituents.flat_map(|value| value.components()).collect(),
+                    ),
+                    _ => Value::Composite(constituents.collect()),
+                }
+            }
+            op::COMPOSITE_EXTRACT => arg(2).get(&ops[3..]).cloned().unwrap_or(Value::Undef),
+            op::COMPOSITE_INSERT => {
+                let mut composite = arg(3);
+                if let Some(target) = composite.get_mut(&ops[4..]) {
+                    *target = arg(2);
+                }
+                composite
+            }
+            op::VECTOR_EXTRACT_DYNAMIC => arg(2).component(arg(3).bits()),
+            op::VECTOR_INSERT_DYNAMIC => {
+                let mut vector = arg(2);
+                if let Some(target) = vector.get_mut(&[arg(4).bits()]) {
+                    *target = arg(3);
+                }
+                vector
+            }
+            op::VECTOR_SHUFFLE => {
+                let mut components = arg(2).components();
+                components.extend(arg(3).components());
+                Value::Composite(
+                    ops[4..]
+                        .iter()
+                        .map(|&index| {
+                            components
+                                .get(index as usize)
+                                .cloned()
+                                .unwrap_or(Value::Undef)
+                        })
+                        .collect(),
+                )
+            }
+            op::TRANSPOSE => {
+                let columns = arg(2).components();
+                let rows = columns
+                    .first()
+                    .map_or(0, |column| column.components().len());
+                Value::Composite(
+                    (0..rows as u32)
+                        .map(|row| {
+                            Value::Composite(columns.iter().map(|c| c.component(row)).collect())
+                        })
+                        .collect(),
+                )
+            }
+            op::SELECT => {
+                let (condition, a, b) = (arg(2), arg(3), arg(4));
+                match condition {
+                    Value::Composite(_) => ternary(&condition, &a, &b, &|c, a, b| {
+                        if c.boolean() {
+                            a.clone()
+                        } else {
+                            b.clone()
+                        }
+                    }),
+                    _ => {
+                        if condition.boolean() {
+                            a
+                        } else {
+                            b
+                        }
+                    }
+                }
+            }
+
+            op::CONVERT_F_TO_U => unary(&arg(2), &|a| Value::Int(a.float() as u32)),
+            op::CONVERT_F_TO_S => unary(&arg(2), &|a| Value::Int(a.float() as i32 as u32)),
+            op::CONVERT_S_TO_F => unary(&arg(2), &|a| Value::Float(a.int() as f32)),
+            op::CONVERT_U_TO_F => unary(&arg(2), &|a| Value::Float(a.bits() as f32)),
+            op::U_CONVERT | op::S_CONVERT | op::F_CONVERT => arg(2),
+            op::BITCAST => {
+                let float = match module.types.get(&ty) {
+                    Some(&Type::Vector { component, .. }) => {
+                        matches!(module.types.get(&component), Some(Type::Float))
+                    }
+                    Some(Type::Float) => true,
+                    _ => false,
+                };
+                unary(&arg(2), &|a| {
+                    if float {
+                        Value::Float(f32::from_bits(a.bits()))
+                    } else {
+                        Value::Int(a.bits())
+                    }
+                })
+            }
+
+            op::S_NEGATE => unary(&arg(2), &|a| Value::Int(a.int().wrapping_neg() as u32)),
+            op::F_NEGATE => unary(&arg(2), &|a| Value::Float(-a.float())),
+            op::I_ADD => binary(&arg(2), &arg(3), &|a, b| {
+                Value::Int(a.bits().wrapping_add(b.bits()))
+            }),
+            op::F_ADD => binary(&arg(2), &arg(3), &|a, b| {
+                Value::Float(a.float() + b.float())
+            }),
+            op::I_SUB => binary(&arg(2), &arg(3), &|a, b| {
+                Value::Int(a.bits().wrapping_sub(b.bits()))
+            }),
+            op::F_SUB => binary(&arg(2), &arg(3), &|a, b| {
+                Value::Float(a.float() - b.float())
+            }),
+            op::I_MUL => binary(&arg(2), &arg(3), &|a, b| {
+                Value::Int(a.bits().wrapping_mul(b.bits()))
+            }),
+            op::F_MUL => binary(&arg(2), &arg(3), &|a, b| {
+                Value::Float(a.float() * b.float())
+            }),
+            op::U_DIV => binary(&arg(2), &arg(3), &|a, b| {
+                Value::Int(a.bits().checked_div(b.bits()).unwrap_or(0))
+            }),
+            op::S_DIV => binary(&arg(2), &arg(3), &|a, b| {
+                Value::Int(a.int().checked_div(b.int()).unwrap_or(0) as u32)
+            }),
+            op::F_DIV => binary(&arg(2), &arg(3), &|a, b| {
+                Value::Float(a.float() / b.float())
+            }),
+            op::U_MOD => binary(&arg(2), &arg(3), &|a, b| {
+                Value::Int(a.bits().checked_rem(b.bits()).unwrap_or(0))
+            }),
+            op::S_REM => binary(&arg(2), &arg(3), &|a, b| {
+                Value::Int(a.int().checked_rem(b.int()).unwrap_or(0) as u32)
+            }),
+            op::S_MOD => binary(&arg(2), &arg(3), &|a, b| {
+                let (a, b) = (a.int(), b.int());
+                let rem = a.checked_rem(b).unwrap_or(0);
+                let result = if rem != 0 && (rem < 0) != (b < 0) {
+                    rem + b
+                } else {
+                    rem
+                };
+                Value::Int(result as u32)
+            }),
+            op::F_REM => binary(&arg(2), &arg(3), &|a, b| {
+                Value::Float(a.float() % b.float())
+            }),
+            op::F_MOD => binary(&arg(2), &arg(3), &|a, b| {
+                let (a, b) = (a.float(), b.float());
+                Value::Float(a - b * (a / b).floor())
+            }),
+            op::VECTOR_TIMES_SCALAR | op::MATRIX_TIMES_SCALAR => {
+                let scale = arg(3).float();
+                unary(&arg(2), &|a| Value::Float(a.float() * scale))
+            }
+            op::VECTOR_TIMES_MATRIX => {
+                let vector = arg(2).floats();
+                Value::vector(
+                    arg(3)
+                        .components()
+                        .iter()
+                        .map(|column| dot(&vector, &column.floats())),
+                )
+            }
+            op::MATRIX_TIMES_VECTOR => matrix_times_vector(&arg(2).components(), &arg(3).floats()),
+            op::MATRIX_TIMES_MATRIX => {
+                let columns = arg(2).components();
+                Value::Composite(
+                    arg(3)
+                        .components()
+                        .iter()
+                        .map(|column| matrix_times_vector(&columns, &column.floats()))
+                        .collect(),
+                )
+            }
+            op::OUTER_PRODUCT => {
+                let a = arg(2);
+                Value::Composite(
+                    arg(3)
+                        .floats()
+                        .into_iter()
+                        .map(|scale| unary(&a, &|a| Value::Float(a.float() * scale)))
+                        .collect(),
+                )
+            }
+            op::DOT => Value::Float(dot(&arg(2).floats(), &arg(3).floats())),
+
+            op::ANY => Value::Bool(arg(2).components().iter().any(Value::boolean)),
+            op::ALL => Value::Bool(arg(2).components().iter().all(Value::boolean)),
+            op::IS_NAN => unary(&arg(2), &|a| Value::Bool(a.float().is_nan())),
+            op::IS_INF => unary(&arg(2), &|a| Value::Bool(a.float().is_infinite())),
+            op::LOGICAL_EQUAL => binary(&arg(2), &arg(3), &|a, b| {
+                Value::Bool(a.boolean() == b.boolean())
+            }),
+            op::LOGICAL_NOT_EQUAL => binary(&arg(2), &arg(3), &|a, b| {
+                Value::Bool(a.boolean() != b.boolean())
+            }),
+            op::LOGICAL_OR => binary(&arg(2), &arg(3), &|a, b| {
+                Value::Bool(a.boolean() || b.boolean())
+            }),
+            op::LOGICAL_AND => binary(&arg(2), &arg(3), &|a, b| {
+                Value::Bool(a.boolean() && b.boolean())
+            }),
+            op::LOGICAL_NOT => unary(&arg(2), &|a| Value::Bool(!a.boolean())),
+            op::I_EQUAL => binary(&arg(2), &arg(3), &|a, b| Value::Bool(a.bits() == b.bits())),
+            op::I_NOT_EQUAL => binary(&arg(2), &arg(3), &|a, b| Value::Bool(a.bits() != b.bits())),
+            op::U_GREATER_THAN => {
+                binary(&arg(2), &arg(3), &|a, b| Value::Bool(a.bits() > b.bits()))
+            }
+            op::S_GREATER_THAN => binary(&arg(2), &arg(3), &|a, b| Value::Bool(a.int() > b.int())),
+            op::U_GREATER_THAN_EQUAL => {
+                binary(&arg(2), &arg(3), &|a, b| Value::Bool(a.bits() >= b.bits()))
+            }
+            op::S_GREATER_THAN_EQUAL => {
+                binary(&arg(2), &arg(3), &|a, b| Value::Bool(a.int() >= b.int()))
+            }
+            op::U_LESS_THAN => binary(&arg(2), &arg(3), &|a, b| Value::Bool(a.bits() < b.bits())),
+            op::S_LESS_THAN => binary(&arg(2), &arg(3), &|a, b| Value::Bool(a.int() < b.int())),
+            op::U_LESS_THAN_EQUAL => {
+                binary(&arg(2), &arg(3), &|a, b| Value::Bool(a.bits() <= b.bits()))
+            }
+            op::S_LESS_THAN_EQUAL => {
+                binary(&arg(2), &arg(3), &|a, b| Value::Bool(a.int() <= b.int()))
+            }
+            op::F_ORD_EQUAL | op::F_UNORD_EQUAL => float_compare(
+                &arg(2),
+                &arg(3),
+                instruction.opcode == op::F_ORD_EQUAL,
+                |a, b| a == b,
+            ),
+            op::F_ORD_NOT_EQUAL | op::F_UNORD_NOT_EQUAL => float_compare(
+                &arg(2),
+                &arg(3),
+                instruction.opcode == op::F_ORD_NOT_EQUAL,
+                |a, b| a != b,
+            ),
+            op::F_ORD_LESS_THAN | op::F_UNORD_LESS_THAN => float_compare(
+                &arg(2),
+                &arg(3),
+                instruction.opcode == op::F_ORD_LESS_THAN,
+                |a, b| a < b,
+            ),
+            op::F_ORD_GREATER_THAN | op::F_UNORD_GREATER_THAN => float_compare(
+                &arg(2),
+                &arg(3),
+                instruction.opcode == op::F_ORD_GREATER_THAN,
+                |a, b| a > b,
+            ),
+            op::F_ORD_LESS_THAN_EQUAL | op::F_UNORD_LESS_THAN_EQUAL => float_compare(
+                &arg(2),
+                &arg(3),
+                instruction.opcode == op::F_ORD_LESS_THAN_EQUAL,
+                |a, b| a <= b,
+            ),
+            op::F_ORD_GREATER_THAN_EQUAL | op::F_UNORD_GREATER_THAN_EQUAL => float_compare(
+                &arg(2),
+                &arg(3),
+                instruction.opcode == op::F_ORD_GREATER_THAN_EQUAL,
+                |a, b| a >= b,
+            ),
+
+            op::SHIFT_RIGHT_LOGICAL => binary(&arg(2), &arg(3), &|a, b| {
+                Value::Int(a.bits().wrapping_shr(b.bits()))
+            }),
+            op::SHIFT_RIGHT_ARITHMETIC => binary(&arg(2), &arg(3), &|a, b| {
+                Value::Int(a.int().wrapping_shr(b.bits()) as u32)
+            }),
+            op::SHIFT_LEFT_LOGICAL => binary(&arg(2), &arg(3), &|a, b| {
+                Value::Int(a.bits().wrapping_shl(b.bits()))
+            }),
+            op::BITWISE_OR => binary(&arg(2), &arg(3), &|a, b| Value::Int(a.bits() | b.bits())),
+            op::BITWISE_XOR => binary(&arg(2), &arg(3), &|a, b| Value::Int(a.bits() ^ b.bits())),
+            op::BITWISE_AND => binary(&arg(2), &arg(3), &|a, b| Value::Int(a.bits() & b.bits())),
+            op::NOT => unary(&arg(2), &|a| Value::Int(!a.bits())),
+            op::DPDX..=op::FWIDTH_COARSE => module.null_value(ty),
+
+            op::EXT_INST => {
+                let arguments = (4..ops.len()).map(arg).collect::<Vec<_>>();
+                extended(ops[3], &arguments)
+            }
+            op::ARRAY_LENGTH => match arg(2).pointer() {
+                Some(Pointer::Memory {
+                    memory, offset, ty, ..
+                }) => {
+                    let member = match module.types.get(&ty) {
+                        Some(Type::Struct { members }) => members[ops[3] as usize],
+                        _ => return Value::Int(0),
+                    };
+                    let member_offset = offset + module.member_layout(ty, ops[3]).0;
+                    let stride = module.array_stride(member).max(1) as u64;
+                    Value::Int((memory.len.saturating_sub(member_offset) / stride) as u32)
+                }
+                _ => Value::Int(0),
+            },
+
+            op::SAMPLED_IMAGE => match (arg(2), arg(3)) {
+                (Value::Image(view), Value::Sampler(sampler)) => Value::SampledImage(view, sampler),
+                _ => Value::Undef,
+            },
+            op::IMAGE => match arg(2) {
+                Value::SampledImage(view, _) => Value::Image(view),
+                _ => Value::Undef,
+            },
+            op::IMAGE_SAMPLE_IMPLICIT_LOD | op::IMAGE_SAMPLE_EXPLICIT_LOD => {
+                let (view, sampler) = match arg(2) {
+                    Value::SampledImage(view, sampler) => (view, sampler),
+                    _ => return Value::vector(vec![0.0; 4]),
+                };
+                let coordinates = arg(3).floats();
+                // Image operands are ordered by their bit: Bias, then Lod
+                let mask = ops.get(4).cloned().unwrap_or(0);
+                let mut next = 5;
+                let mut lod = 0.0;
+                if mask & 0x1 != 0 {
+                    lod += arg(next).float();
+                    next += 1;
+                }
+                if mask & 0x2 != 0 {
+                    lod += arg(next).float();
+                }
+                texel::sample(&view, &sampler, &coordinates, lod)
+            }
+            op::IMAGE_FETCH => {
+                let view = match arg(2) {
+                    Value::Image(view) | Value::SampledImage(view, _) => view,
+                    _ => return Value::vector(vec![0.0; 4]),
+                };
+                let coordinates = arg(3)
+                    .components()
+                    .iter()
+                    .map(Value::int)
+                    .collect::<Vec<_>>();
+                let mask = ops.get(4).cloned().unwrap_or(0);
+                let level = if mask & 0x2 != 0 { arg(5).bits() } else { 0 };
+                texel::fetch(&view, &coordinates, level)
+            }
+            op::IMAGE_QUERY_SIZE_LOD | op::IMAGE_QUERY_SIZE | op::IMAGE_QUERY_LEVELS => {
+                let view = match arg(2) {
+                    Value::Image(view) | Value::SampledImage(view, _) => view,
+                    _ => return module.null_value(ty),
+                };
+                if instruction.opcode == op::IMAGE_QUERY_LEVELS {
+                    return Value::Int(view.levels() as u32);
+                }
+                let level = if instruction.opcode == op::IMAGE_QUERY_SIZE_LOD {
+                    arg(3).bits()
+                } else {
+                    0
+                };
+                let size = view.size(level);
+                match module.types.get(&ty) {
+                    Some(&Type::Vector { count, .. }) => Value::Composite(
+                        size.iter()
+                            .take(count as usize)
+                            .map(|&s| Value::Int(s))
+                            .collect(),
+                    ),
+                    _ => Value::Int(size[0]),
+                }
+            }
+            opcode => unreachable!("Instruction {} was not rejected at parsing", opcode),
+        }
+    }
+}
+
+/// Execute a GLSL.std.450 instruction.
+fn extended(instruction: u32, args: &[Value]) -> Value {
+    let float = |fun: fn(f32) -> f32| unary(&args[0], &|a| Value::Float(fun(a.float())));
+    let float2 = |fun: fn(f32, f32) -> f32| {
+        binary(&args[0], &args[1], &|a, b| {
+            Value::Float(fun(a.float(), b.float()))
+        })
+    };
+    let float3 = |fun: fn(f32, f32, f32) -> f32| {
+        ternary(&args[0], &args[1], &args[2], &|a, b, c| {
+            Value::Float(fun(a.float(), b.float(), c.float()))
+        })
+    };
+    let length = |v: &Value| {
+        let v = v.floats();
+        dot(&v, &v).sqrt()
+    };
+
+    match instruction {
+        glsl::ROUND => float(f32::round),
+        glsl::ROUND_EVEN => float(|a| {
+            let rounded = a.round();
+            if (a - a.trunc()).abs() == 0.5 {
+                2.0 * (a / 2.0).round()
+            } else {
+                rounded
+            }
+        }),
+        glsl::TRUNC => float(f32::trunc),
+        glsl::F_ABS => float(f32::abs),
+        glsl::S_ABS => unary(&args[0], &|a| Value::Int(a.int().wrapping_abs() as u32)),
+        glsl::F_SIGN => float(|a| {
+            if a > 0.0 {
+                1.0
+            } else if a < 0.0 {
+                -1.0
+            } else {
+                0.0
+            }
+        }),
+        glsl::S_SIGN => unary(&args[0], &|a| Value::Int(a.int().signum() as u32)),
+        glsl::FLOOR => float(f32::floor),
+        glsl::CEIL => float(f32::ceil),
+        glsl::FRACT => float(|a| a - a.floor()),
+        glsl::RADIANS => float(f32::to_radians),
+        glsl::DEGREES => float(f32::to_degrees),
+        glsl::SIN => float(f32::sin),
+        glsl::COS => float(f32::cos),
+        glsl::TAN => float(f32::tan),
+        glsl::ASIN => float(f32::asin),
+        glsl::ACOS => float(f32::acos),
+        glsl::ATAN => float(f32::atan),
+        glsl::ATAN2 => float2(f32::atan2),
+        glsl::POW => float2(f32::powf),
+        glsl::EXP => float(f32::exp),
+        glsl::LOG => float(f32::ln),
+        glsl::EXP2 => float(f32::exp2),
+        glsl::LOG2 => float(f32::log2),
+        glsl::SQRT => float(f32::sqrt),
+        glsl::INVERSE_SQRT => float(|a| 1.0 / a.sqrt()),
+        glsl::F_MIN | glsl::N_MIN => float2(f32::min),
+        glsl::F_MAX | glsl::N_MAX => float2(f32::max),
+        glsl::F_CLAMP | glsl::N_CLAMP => float3(|a, low, high| a.max(low).min(high)),
+        glsl::U_MIN => binary(&args[0], &args[1], &|a, b| {
+            Value::Int(a.bits().min(b.bits()))
+        }),
+        glsl::U_MAX => binary(&args[0], &args[1], &|a, b| {
+            Value::Int(a.bits().max(b.bits()))
+        }),
+        glsl::S_MIN => binary(&args[0], &args[1], &|a, b| {
+            Value::Int(a.int().min(b.int()) as u32)
+        }),
+        glsl::S_MAX => binary(&args[0], &args[1], &|a, b| {
+            Value::Int(a.int().max(b.int()) as u32)
+        }),
+        glsl::U_CLAMP => ternary(&args[0], &args[1], &args[2], &|a, low, high| {
+            Value::Int(a.bits().max(low.bits()).min(high.bits()))
+        }),
+        glsl::S_CLAMP => ternary(&args[0], &args[1], &args[2], &|a, low, high| {
+            Value::Int(a.int().max(low.int()).min(high.int()) as u32)
+        }),
+        glsl::F_MIX => float3(|a, b, t| a * (1.0 - t) + b * t),
+        glsl::STEP => float2(|edge, a| if a < edge { 0.0 } else { 1.0 }),
+        glsl::SMOOTH_STEP => float3(|edge0, edge1, a| {
+            let t = ((a - edge0) / (edge1 - edge0)).max(0.0).min(1.0);
+            t * t * (3.0 - 2.0 * t)
+        }),
+        glsl::FMA => float3(|a, b, c| a * b + c),
+        glsl::LENGTH => Value::Float(length(&args[0])),
+        glsl::DISTANCE => {
+            let difference = binary(&args[0], &args[1], &|a, b| {
+                Value::Float(a.float() - b.float())
+            });
+            Value::Float(length(&difference))
+        }
+        glsl::CROSS => {
+            let (a, b) = (args[0].floats(), args[1].floats());
+            Value::vector(vec![
+                a[1] * b[2] - a[2] * b[1],
+                a[2] * b[0] - a[0] * b[2],
+                a[0] * b[1] - a[1] * b[0],
+            ])
+        }
+        glsl::NORMALIZE => {
+            let length = length(&args[0]);
+            unary(&args[0], &|a| Value::Float(a.float() / length))
+        }
+        glsl::REFLECT => {
+            let (incident, normal) = (args[0].floats(), args[1].floats());
+            let scale = 2.0 * dot(&normal, &incident);
+            Value::vector(
+                incident
+                    .iter()
+                    .zip(normal.iter())
+                    .map(|(i, n)| i - scale * n),
+            )
+        }
+        _ => unreachable!(
+            "Extended instruction {} was not rejected at parsing",
+            instruction
+        ),
+    }
+}
@@ -0,0 +1,592 @@
+//! Pixel tests of the rasterizer, drawing through the public API
+//! into a small color target with a depth attachment.
+
+use hal::{
+    adapter::PhysicalDevice as _,
+    buffer,
+    command::{self, CommandBuffer as _},
+    device::Device as _,
+    format::{Aspects, Format, Swizzle},
+    image, memory, pass,
+    pool::{CommandPool as _, CommandPoolCreateFlags},
+    pso,
+    queue::Queue as _,
+    Instance as _, MemoryTypeId,
+};
+
+use std::{iter, ptr};
+
+const SIZE: u32 = 4;
+const COLOR_FORMAT: Format = Format::Rgba32Sfloat;
+const DEPTH_FORMAT: Format = Format::D32Sfloat;
+const TRANSPARENT: [f32; 4] = [0.0; 4];
+const WHITE: [f32; 4] = [1.0; 4];
+const RED: [f32; 4] = [1.0, 0.0, 0.0, 1.0];
+const GREEN: [f32; 4] = [0.0, 1.0, 0.0, 1.0];
+// SPIR-V decorations of the shader outputs
+const BUILT_IN: u32 = 11;
+const LOCATION: u32 = 30;
+
+/// Vertex with a clip space position and a color.
+type Vertex = ([f32; 4], [f32; 4]);
+
+/// Pipeline state and geometry of a draw call.
+struct Draw {
+    primitive: pso::Primitive,
+    vertices: Vec<Vertex>,
+    indices: Option<Vec<u16>>,
+    blend: Option<pso::BlendState>,
+    depth: Option<pso::DepthTest>,
+    depth_clamping: bool,
+}
+
+impl Draw {
+    fn triangles(vertices: Vec<Vertex>) -> Self {
+        Draw {
+            primitive: pso::Primitive::TriangleList,
+            vertices,
+            indices: None,
+            blend: None,
+            depth: None,
+            depth_clamping: false,
+        }
+    }
+}
+
+/// Two triangles covering the whole target at the depth `z`.
+fn quad(z: f32, color: [f32; 4]) -> Vec<Vertex> {
+    [
+        (-1.0, -1.0),
+        (1.0, -1.0),
+        (-1.0, 1.0),
+        (-1.0, 1.0),
+        (1.0, -1.0),
+        (1.0, 1.0),
+    ]
+    .iter()
+    .map(|&(x, y)| ([x, y, z, 1.0], color))
+    .collect()
+}
+
+/// Build the expected pixels of the target, in rows.
+fn expect(pixel: impl Fn(u32, u32) -> [f32; 4]) -> Vec<[f32; 4]> {
+    (0..SIZE)
+        .flat_map(|y| (0..SIZE).map(move |x| (x, y)))
+        .map(|(x, y)| pixel(x, y))
+        .collect()
+}
+
+/// Compare the rendered pixels to the expected ones,
+/// allowing for the rounding of the interpolation.
+fn assert_pixels(actual: Vec<[f32; 4]>, expected: Vec<[f32; 4]>) {
+    let close = actual
+        .iter()
+        .zip(&expected)
+        .all(|(a, e)| a.iter().zip(e).all(|(a, e)| (a - e).abs() < 1.0e-5));
+    assert!(close, "Rendered {:?}, expected {:?}", actual, expected);
+}
+
+/// Assemble a shader of the execution `model` copying the `vec4` inputs at the locations
+/// `0..outputs.len()` to the outputs decorated with the `(decoration, value)` pairs.
+fn pass_through(model: u32, outputs: &[(u32, u32)]) -> Vec<u32> {
+    const CAPABILITY: u32 = 17;
+    const MEMORY_MODEL: u32 = 14;
+    const ENTRY_POINT: u32 = 15;
+    const EXECUTION_MODE: u32 = 16;
+    const DECORATE: u32 = 71;
+    const TYPE_VOID: u32 = 19;
+    const TYPE_FLOAT: u32 = 22;
+    const TYPE_VECTOR: u32 = 23;
+    const TYPE_POINTER: u32 = 32;
+    const TYPE_FUNCTION: u32 = 33;
+    const VARIABLE: u32 = 59;
+    const FUNCTION: u32 = 54;
+    const FUNCTION_END: u32 = 56;
+    const LOAD: u32 = 61;
+    const STORE: u32 = 62;
+    const LABEL: u32 = 248;
+    const RETURN: u32 = 253;
+    const ORIGIN_UPPER_LEFT: u32 = 7;
+    const INPUT: u32 = 1;
+    const OUTPUT: u32 = 3;
+    // Ids of the types, the function and the variables
+    const VOID: u32 = 1;
+    const FUNCTION_TYPE: u32 = 2;
+    const FLOAT: u32 = 3;
+    const VEC4: u32 = 4;
+    const INPUT_POINTER: u32 = 5;
+    const OUTPUT_POINTER: u32 = 6;
+    const MAIN: u32 = 7;
+    const BLOCK: u32 = 8;
+    let input = |i: u32| 10 + 3 * i;
+    let output = |i: u32| 11 + 3 * i;
+    let loaded = |i: u32| 12 + 3 * i;
+
+    fn instruction(words: &mut Vec<u32>, opcode: u32, operands: &[u32]) {
+        words.push(((operands.len() as u32 + 1) << 16) | opcode);
+        words.extend_from_slice(operands);
+    }
+
+    let count = outputs.len() as u32;
+    let mut words = vec![0x0723_0203, 0x0001_0000, 0, 10 + 3 * count, 0];
+    instruction(&mut words, CAPABILITY, &[1]);
+    instruction(&mut words, MEMORY_MODEL, &[0, 1]);
+    let mut entry_point = vec![model, MAIN, u32::from_le_bytes(*b"main"), 0];
+    entry_point.extend((0..count).flat_map(|i| vec![input(i), output(i)]));
+    instruction(&mut words, ENTRY_POINT, &entry_point);
+    if model == crate::spirv::model::FRAGMENT {
+        instruction(&mut words, EXECUTION_MODE, &[MAIN, ORIGIN_UPPER_LEFT]);
+    }
+    for (i, &(decoration, value)) in (0..count).zip(outputs) {
+        instruction(&mut words, DECORATE, &[input(i), LOCATION, i]);
+        instruction(&mut words, DECORATE, &[output(i), decoration, value]);
+    }
+    instruction(&mut words, TYPE_VOID, &[VOID]);
+    instruction(&mut words, TYPE_FUNCTION, &[FUNCTION_TYPE, VOID]);
+    instruction(&mut words, TYPE_FLOAT, &[FLOAT, 32]);
+    instruction(&mut words, TYPE_VECTOR, &[VEC4, FLOAT, 4]);
+    instruction(&mut words, TYPE_POINTER, &[INPUT_POINTER, INPUT, VEC4]);
+    instruction(&mut words, TYPE_POINTER, &[OUTPUT_POINTER, OUTPUT, VEC4]);
+    for i in 0..count {
+        instruction(&mut words, VARIABLE, &[INPUT_POINTER, input(i), INPUT]);
+        instruction(&mut words, VARIABLE, &[OUTPUT_POINTER, output(i), OUTPUT]);
+    }
+    instruction(&mut words, FUNCTION, &[VOID, MAIN, 0, FUNCTION_TYPE]);
+    instruction(&mut words, LABEL, &[BLOCK]);
+    for i in 0..count {
+        instruction(&mut words, LOAD, &[VEC4, loaded(i), input(i)]);
+        instruction(&mut words, STORE, &[output(i), loaded(i)]);
+    }
+    instruction(&mut words, RETURN, &[]);
+    instruction(&mut words, FUNCTION_END, &[]);
+    words
+}
+
+/// Create a buffer bound to its own memory, filled with `data`.
+unsafe fn upload<T: Copy>(
+    device: &crate::Device,
+    usage: buffer::Usage,
+    data: &[T],
+) -> (crate::Buffer, crate::Memory) {
+    let size = std::mem::size_of_val(data) as u64;
+    let mut buffer = device.create_buffer(size, usage).unwrap();
+    let mut memory = device.allocate_memory(MemoryTypeId(0), size).unwrap();
+    device.bind_buffer_memory(&memory, 0, &mut buffer).unwrap();
+    let mapping = device
+        .map_memory(&mut memory, memory::Segment::ALL)
+        .unwrap();
+    ptr::copy_nonoverlapping(data.as_ptr() as *const u8, mapping, size as usize);
+    device.unmap_memory(&mut memory);
+    (buffer, memory)
+}
+
+/// Create an attachment image bound to its own memory, with a view of it.
+unsafe fn attachment(
+    device: &crate::Device,
+    format: Format,
+    usage: image::Usage,
+    aspects: Aspects,
+) -> (crate::Image, crate::ImageView, crate::Memory) {
+    let mut image = device
+        .create_image(
+            image::Kind::D2(SIZE, SIZE, 1, 1),
+            1,
+            format,
+            image::Tiling::Optimal,
+            usage,
+            image::ViewCapabilities::empty(),
+        )
+        .unwrap();
+    let requirements = device.get_image_requirements(&image);
+    let memory = device
+        .allocate_memory(MemoryTypeId(0), requirements.size)
+        .unwrap();
+    device.bind_image_memory(&memory, 0, &mut image).unwrap();
+    let view = device
+        .create_image_view(
+            &image,
+            image::ViewKind::D2,
+            format,
+            Swizzle::NO,
+            image::SubresourceRange {
+                aspects,
+                ..image::SubresourceRange::default()
+            },
+        )
+        .unwrap();
+    (image, view, memory)
+}
+
+/// Render the draws in a single render pass, clearing the color to `clear`
+/// and the depth to 1, and return the pixels of the color attachment in rows.
+#[allow(clippy::let_unit_value)]
+fn render(clear: [f32; 4], draws: &[Draw]) -> Vec<[f32; 4]> {
+    let instance = crate::Instance::create("software", 1).unwrap();
+    let adapter = instance.enumerate_adapters().remove(0);
+    unsafe {
+        let mut gpu = adapter
+            .physical_device
+            .open(
+                &[(&adapter.queue_families[0], &[1.0])],
+                hal::Features::empty(),
+            )
+            .unwrap();
+        let device = gpu.device;
+        let mut queue_group = gpu.queue_groups.remove(0);
+        let mut pool = device
+            .create_command_pool(queue_group.family, CommandPoolCreateFlags::empty())
+            .unwrap();
+
+        let (color_image, color_view, mut color_memory) = attachment(
+            &device,
+            COLOR_FORMAT,
+            image::Usage::COLOR_ATTACHMENT,
+            Aspects::COLOR,
+        );
+        let (_depth_image, depth_view, _depth_memory) = attachment(
+            &device,
+            DEPTH_FORMAT,
+            image::Usage::DEPTH_STENCIL_ATTACHMENT,
+            Aspects::DEPTH,
+        );
+        let attachments = [
+            pass::Attachment {
+                format: Some(COLOR_FORMAT),
+                samples: 1,
+                ops: pass::AttachmentOps::INIT,
+                stencil_ops: pass::AttachmentOps::DONT_CARE,
+                layouts: image::Layout::Undefined..image::Layout::General,
+            },
+            pass::Attachment {
+                format: Some(DEPTH_FORMAT),
+                samples: 1,
+                ops: pass::AttachmentOps::INIT,
+                stencil_ops: pass::AttachmentOps::DONT_CARE,
+                layouts: image::Layout::Undefined..image::Layout::General,
+            },
+        ];
+        let subpass = pass::SubpassDesc {
+            colors: &[(0, image::Layout::ColorAttachmentOptimal)],
+            depth_stencil: Some(&(1, image::Layout::DepthStencilAttachmentOptimal)),
+            inputs: &[],
+            resolves: &[],
+            preserves: &[],
+            view_mask: 0,
+            shading_rate: None,
+        };
+        let render_pass = device
+            .create_render_pass(
+                attachments.iter().cloned(),
+                iter::once(subpass),
+                iter::empty(),
+            )
+            .unwrap();
+        let framebuffer = device
+            .create_framebuffer(
+                &render_pass,
+                iter::empty(),
+                image::Extent {
+                    width: SIZE,
+                    height: SIZE,
+                    depth: 1,
+                },
+            )
+            .unwrap();
+        let layout = device
+            .create_pipeline_layout(iter::empty(), iter::empty())
+            .unwrap();
+        let vertex = device
+            .create_shader_module(&pass_through(
+                crate::spirv::model::VERTEX,
+                &[(BUILT_IN, crate::spirv::builtin::POSITION), (LOCATION, 0)],
+            ))
+            .unwrap();
+        let fragment = device
+            .create_shader_module(&pass_through(
+                crate::spirv::model::FRAGMENT,
+                &[(LOCATION, 0)],
+            ))
+            .unwrap();
+        let entry = |module| pso::EntryPoint {
+            entry: "main",
+            module,
+            specialization: pso::Specialization::default(),
+        };
+
+        let rect = pso::Rect {
+            x: 0,
+            y: 0,
+            w: SIZE as i16,
+            h: SIZE as i16,
+        };
+        let mut cmd_buffer = pool.allocate_one(command::Level::Primary);
+        cmd_buffer.begin_primary(command::CommandBufferFlags::ONE_TIME_SUBMIT);
+        cmd_buffer.begin_render_pass(
+            &render_pass,
+            &framebuffer,
+            rect,
+            vec![
+                command::RenderAttachmentInfo {
+                    image_view: &color_view,
+                    clear_value: command::ClearValue {
+                        color: command::ClearColor { float32: clear },
+                    },
+                },
+                command::RenderAttachmentInfo {
+                    image_view: &depth_view,
+                    clear_value: command::ClearValue {
+                        depth_stencil: command::ClearDepthStencil {
+                            depth: 1.0,
+                            stencil: 0,
+                        },
+                    },
+                },
+            ]
+            .into_iter(),
+            command::SubpassContents::Inline,
+        );
+        cmd_buffer.set_viewports(
+            0,
+            iter::once(pso::Viewport {
+                rect,
+                depth: 0.0..1.0,
+            }),
+        );
+
+        let mut resources = Vec::new();
+        for draw in draws {
+            let mut desc = pso::GraphicsPipelineDesc::new(
+                pso::PrimitiveAssemblerDesc::Vertex {
+                    buffers: &[pso::VertexBufferDesc {
+                        binding: 0,
+                        stride: std::mem::size_of::<Vertex>() as u32,
+                        rate: pso::VertexInputRate::Vertex,
+                    }],
+                    attributes: &[
+                        pso::AttributeDesc {
+                            location: 0,
+                            binding: 0,
+                            element: pso::Element {
+                                format: Format::Rgba32Sfloat,
+                                offset: 0,
+                            },
+                        },
+                        pso::AttributeDesc {
+                            location: 1,
+                            binding: 0,
+                            element: pso::Element {
+                                format: Format::Rgba32Sfloat,
+                                offset: 16,
+                            },
+                        },
+                    ],
+                    input_assembler: pso::InputAssemblerDesc::new(draw.primitive),
+                    vertex: entry(&vertex),
+                    tessellation: None,
+                    geometry: None,
+                },
+                pso::Rasterizer {
+                    depth_clamping: draw.depth_clamping,
+                    ..pso::Rasterizer::FILL
+                },
+                Some(entry(&fragment)),
+                &layout,
+                pass::Subpass {
+                    index: 0,
+                    main_pass: &render_pass,
+                },
+            );
+            desc.blender.targets.push(pso::ColorBlendDesc {
+                mask: pso::ColorMask::ALL,
+                blend: draw.blend,
+            });
+            desc.depth_stencil.depth = draw.depth;
+            let pipeline = device.create_graphics_pipeline(&desc, None).unwrap();
+            let vertices = upload(&device, buffer::Usage::VERTEX, &draw.vertices);
+
+            cmd_buffer.bind_graphics_pipeline(&pipeline);
+            cmd_buffer.bind_vertex_buffers(0, iter::once((&vertices.0, buffer::SubRange::WHOLE)));
+            match draw.indices {
+                Some(ref indices) => {
+                    let buffer = upload(&device, buffer::Usage::INDEX, indices);
+                    cmd_buffer.bind_index_buffer(
+                        &buffer.0,
+                        buffer::SubRange::WHOLE,
+                        hal::IndexType::U16,
+                    );
+                    cmd_buffer.draw_indexed(0..indices.len() as u32, 0, 0..1);
+                    resources.push(buffer);
+                }
+                None => cmd_buffer.draw(0..draw.vertices.len() as u32, 0..1),
+            }
+            resources.push(vertices);
+        }
+        cmd_buffer.end_render_pass();
+        cmd_buffer.finish();
+        queue_group.queues[0]
+            .submit(iter::once(&cmd_buffer), iter::empty(), iter::empty(), None)
+            .unwrap();
+
+        let footprint = device.get_image_subresource_footprint(
+            &color_image,
+            image::Subresource {
+                aspects: Aspects::COLOR,
+                level: 0,
+                layer: 0,
+            },
+        );
+        let mapping = device
+            .map_memory(&mut color_memory, memory::Segment::ALL)
+            .unwrap();
+        let pixels = (0..SIZE)
+            .flat_map(|y| (0..SIZE).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let offset = footprint.slice.start + y as u64 * footprint.row_pitch + x as u64 * 16;
+                ptr::read_unaligned(mapping.add(offset as usize) as *const [f32; 4])
+            })
+            .collect();
+        device.unmap_memory(&mut color_memory);
+        pixels
+    }
+}
+
+#[test]
+fn clear_attachments() {
+    let clear = [0.25, 0.5, 0.75, 1.0];
+    assert_pixels(render(clear, &[]), expect(|_, _| clear));
+}
+
+#[test]
+fn top_left_rule() {
+    // The pixel centers on the diagonal edge, with x + y == 3, are on its right
+    let draw = Draw::triangles(vec![
+        ([-1.0, -1.0, 0.0, 1.0], WHITE),
+        ([1.0, -1.0, 0.0, 1.0], WHITE),
+        ([-1.0, 1.0, 0.0, 1.0], WHITE),
+    ]);
+    assert_pixels(
+        render(TRANSPARENT, &[draw]),
+        expect(|x, y| if x + y < 3 { WHITE } else { TRANSPARENT }),
+    );
+}
+
+#[test]
+fn shared_edges_drawn_once() {
+    let draw = Draw {
+        blend: Some(pso::BlendState::ADD),
+        ..Draw::triangles(quad(0.0, [0.25; 4]))
+    };
+    assert_pixels(render(TRANSPARENT, &[draw]), expect(|_, _| [0.25; 4]));
+}
+
+#[test]
+fn depth_test() {
+    let depth = Some(pso::DepthTest {
+        fun: pso::Comparison::Less,
+        write: true,
+    });
+    let near = Draw {
+        depth,
+        ..Draw::triangles(quad(0.25, RED))
+    };
+    let far = Draw {
+        depth,
+        ..Draw::triangles(quad(0.75, GREEN))
+    };
+    assert_pixels(render(TRANSPARENT, &[near, far]), expect(|_, _| RED));
+}
+
+#[test]
+fn alpha_blending() {
+    let draw = Draw {
+        blend: Some(pso::BlendState::ALPHA),
+        ..Draw::triangles(quad(0.0, [1.0, 0.0, 0.0, 0.5]))
+    };
+    assert_pixels(
+        render([0.0, 0.0, 1.0, 1.0], &[draw]),
+        expect(|_, _| [0.5, 0.0, 0.5, 1.0]),
+    );
+}
+
+#[test]
+fn triangle_strip() {
+    let corners = [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)];
+    let draw = Draw {
+        primitive: pso::Primitive::TriangleStrip,
+        blend: Some(pso::BlendState::ADD),
+        ..Draw::triangles(
+            corners
+                .iter()
+                .map(|&(x, y)| ([x, y, 0.0, 1.0], [0.25; 4]))
+                .collect(),
+        )
+    };
+    assert_pixels(render(TRANSPARENT, &[draw]), expect(|_, _| [0.25; 4]));
+}
+
+#[test]
+fn indexed_triangles() {
+    let corners = [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)];
+    let draw = Draw {
+        indices: Some(vec![0, 1, 2, 2, 1, 3]),
+        blend: Some(pso::BlendState::ADD),
+        ..Draw::triangles(
+            corners
+                .iter()
+                .map(|&(x, y)| ([x, y, 0.0, 1.0], [0.25; 4]))
+                .collect(),
+        )
+    };
+    assert_pixels(render(TRANSPARENT, &[draw]), expect(|_, _| [0.25; 4]));
+}
+
+/// Additive quad whose depth goes from -0.5 on the top row to 1.5 on the bottom one,
+/// so only the two middle rows are within the clip volume.
+fn slanted_quad() -> Draw {
+    Draw {
+        blend: Some(pso::BlendState::ADD),
+        ..Draw::triangles(
+            quad(0.0, [0.5; 4])
+                .into_iter()
+                .map(|([x, y, _, w], color)| ([x, y, 0.5 + y, w], color))
+                .collect(),
+        )
+    }
+}
+
+#[test]
+fn clip_near_and_far_planes() {
+    assert_pixels(
+        render(TRANSPARENT, &[slanted_quad()]),
+        expect(|_, y| {
+            if y == 1 || y == 2 {
+                [0.5; 4]
+            } else {
+                TRANSPARENT
+            }
+        }),
+    );
+}
+
+#[test]
+fn depth_clamping_disables_clipping() {
+    let draw = Draw {
+        depth_clamping: true,
+        ..slanted_quad()
+    };
+    assert_pixels(render(TRANSPARENT, &[draw]), expect(|_, _| [0.5; 4]));
+}
+
+#[test]
+fn clip_vertex_behind_eye() {
+    // The part in front of the eye covers the bottom half of the target
+    let draw = Draw::triangles(vec![
+        ([-1.0, 1.0, 0.5, 1.0], WHITE),
+        ([1.0, 1.0, 0.5, 1.0], WHITE),
+        ([0.0, -3.0, -1.5, -1.0], WHITE),
+    ]);
+    assert_pixels(
+        render(TRANSPARENT, &[draw]),
+        expect(|_, y| if y >= 2 { WHITE } else { TRANSPARENT }),
+    );
+}
@@ -0,0 +1,327 @@
+//! Encoding, decoding and sampling of the texels.
+//!
+//! Texels are decoded into four-component values, with the missing components
+//! filled like the shaders expect, e.g. `(r, 0, 0, 1)` for a single channel format.
+
+use crate::{
+    resource::{ImageView, Sampler},
+    spirv::Value,
+};
+
+use hal::{format::Format, image};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Kind {
+    Unorm,
+    Srgb,
+    Uint,
+    Sint,
+    Float,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Description {
+    kind: Kind,
+    channels: usize,
+    /// Size of each channel in bytes.
+    size: usize,
+    /// Channels are stored in the BGRA order.
+    bgra: bool,
+}
+
+fn describe(format: Format) -> Option<Description> {
+    use hal::format::Format as F;
+    let (kind, channels, size, bgra) = match format {
+        F::R8Unorm => (Kind::Unorm, 1, 1, false),
+        F::Rg8Unorm => (Kind::Unorm, 2, 1, false),
+        F::Rgba8Unorm => (Kind::Unorm, 4, 1, false),
+        F::Rgba8Srgb => (Kind::Srgb, 4, 1, false),
+        F::Bgra8Unorm => (Kind::Unorm, 4, 1, true),
+        F::Bgra8Srgb => (Kind::Srgb, 4, 1, true),
+        F::R8Uint => (Kind::Uint, 1, 1, false),
+        F::Rgba8Uint => (Kind::Uint, 4, 1, false),
+        F::R16Unorm | F::D16Unorm => (Kind::Unorm, 1, 2, false),
+        F::R32Uint => (Kind::Uint, 1, 4, false),
+        F::Rg32Uint => (Kind::Uint, 2, 4, false),
+        F::Rgba32Uint => (Kind::Uint, 4, 4, false),
+        F::R32Sint => (Kind::Sint, 1, 4, false),
+        F::Rg32Sint => (Kind::Sint, 2, 4, false),
+        F::Rgba32Sint => (Kind::Sint, 4, 4, false),
+        F::R32Sfloat | F::D32Sfloat => (Kind::Float, 1, 4, false),
+        F::Rg32Sfloat => (Kind::Float, 2, 4, false),
+        F::Rgb32Sfloat => (Kind::Float, 3, 4, false),
+        F::Rgba32Sfloat => (Kind::Float, 4, 4, false),
+        _ => return None,
+    };
+    Some(Description {
+        kind,
+        channels,
+        size,
+        bgra,
+    })
+}
+
+pub fn is_supported(format: Format) -> bool {
+    describe(format).is_some()
+}
+
+/// Returns true if the format holds integers, which are neither filtered nor blended.
+pub fn is_integer(format: Format) -> bool {
+    match describe(format) {
+        Some(desc) => desc.kind == Kind::Uint || desc.kind == Kind::Sint,
+        None => false,
+    }
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.003_130_8 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+pub fn decode(format: Format, bytes: &[u8]) -> Value {
+    let desc = match describe(format) {
+        Some(desc) => desc,
+        None => return Value::vector(vec![0.0; 4]),
+    };
+    let mut components = match desc.kind {
+        Kind::Uint | Kind::Sint => vec![Value::Int(0), Value::Int(0), Value::Int(0), Value::Int(1)],
+        _ => vec![
+            Value::Float(0.0),
+            Value::Float(0.0),
+            Value::Float(0.0),
+            Value::Float(1.0),
+        ],
+    };
+    let max = ((1u64 << (8 * desc.size)) - 1) as f32;
+
+    for channel in 0..desc.channels {
+        let mut raw = [0u8; 4];
+        raw[..desc.size].copy_from_slice(&bytes[channel * desc.size..(channel + 1) * desc.size]);
+        let raw = u32::from_le_bytes(raw);
+        let value = match desc.kind {
+            Kind::Unorm => Value::Float(raw as f32 / max),
+            Kind::Srgb if channel < 3 => Value::Float(srgb_to_linear(raw as f32 / max)),
+            Kind::Srgb => Value::Float(raw as f32 / max),
+            Kind::Uint => Value::Int(raw),
+            Kind::Sint => {
+                let shift = 32 - 8 * desc.size as u32;
+                Value::Int((((raw << shift) as i32) >> shift) as u32)
+            }
+            Kind::Float => Value::Float(f32::from_bits(raw)),
+        };
+        let target = if desc.bgra && channel < 3 {
+            2 - channel
+        } else {
+            channel
+        };
+        components[target] = value;
+    }
+
+    Value::Composite(components)
+}
+
+pub fn encode(format: Format, value: &Value, bytes: &mut [u8]) {
+    let desc = match describe(format) {
+        Some(desc) => desc,
+        None => return,
+    };
+    let components = value.components();
+    let max = ((1u64 << (8 * desc.size)) - 1) as f32;
+
+    for channel in 0..desc.channels {
+        let source = if desc.bgra && channel < 3 {
+            2 - channel
+        } else {
+            channel
+        };
+        let component = components.get(source).cloned().unwrap_or(Value::Undef);
+        let unorm = |value: f32| (value.max(0.0).min(1.0) * max).round() as u32;
+        let raw = match desc.kind {
+            Kind::Unorm => unorm(component.float()),
+            Kind::Srgb if channel < 3 => unorm(linear_to_srgb(component.float())),
+            Kind::Srgb => unorm(component.float()),
+            Kind::Uint | Kind::Sint => component.bits(),
+            Kind::Float => component.float().to_bits(),
+        };
+        bytes[channel * desc.size..(channel + 1) * desc.size]
+            .copy_from_slice(&raw.to_le_bytes()[..desc.size]);
+    }
+}
+
+fn wrap(coordinate: i32, size: u32, mode: image::WrapMode) -> Option<u32> {
+    let size = size as i32;
+    if size == 0 {
+        return None;
+    }
+    let wrapped = match mode {
+        image::WrapMode::Tile => coordinate.rem_euclid(size),
+        image::WrapMode::Mirror => {
+            let period = coordinate.rem_euclid(2 * size);
+            if period < size {
+                period
+            } else {
+                2 * size - 1 - period
+            }
+        }
+        image::WrapMode::Clamp => coordinate.max(0).min(size - 1),
+        image::WrapMode::Border => {
+            if coordinate < 0 || coordinate >= size {
+                return None;
+            }
+            coordinate
+        }
+        image::WrapMode::MirrorClamp => {
+            let mirrored = if coordinate < 0 {
+                -1 - coordinate
+            } else {
+                coordinate
+            };
+            mirrored.min(size - 1)
+        }
+    };
+    Some(wrapped as u32)
+}
+
+/// Number of the coordinate dimensions and the index of the array layer coordinate.
+fn dimensions(kind: image::ViewKind) -> Option<(usize, Option<usize>)> {
+    match kind {
+        image::ViewKind::D1 => Some((1, None)),
+        image::ViewKind::D1Array => Some((1, Some(1))),
+        image::ViewKind::D2 => Some((2, None)),
+        image::ViewKind::D2Array => Some((2, Some(2))),
+        image::ViewKind::D3 => Some((3, None)),
+        image::ViewKind::Cube | image::ViewKind::CubeArray => None,
+    }
+}
+
+/// Sample a view with the given coordinates and level of detail.
+///
+/// Only the closest mipmap level is read, and cube views are not supported.
+pub fn sample(view: &ImageView, sampler: &Sampler, coordinates: &[f32], lod: f32) -> Value {
+    let desc = &*sampler.desc;
+    let (dimensions, layer_coordinate) = match dimensions(view.kind) {
+        Some(dimensions) => dimensions,
+        None => return Value::vector(vec![0.0; 4]),
+    };
+
+    let lod = (lod + desc.lod_bias.0)
+        .max(desc.lod_range.start.0)
+        .min(desc.lod_range.end.0);
+    let (filter, level) = if lod <= 0.0 {
+        (desc.mag_filter, 0)
+    } else {
+        let level = (lod.round() as u32).min(view.levels().saturating_sub(1) as u32);
+        (desc.min_filter, level)
+    };
+    let size = view.size(level);
+    let layer = layer_coordinate
+        .map_or(0.0, |index| coordinates.get(index).cloned().unwrap_or(0.0))
+        .round()
+        .max(0.0) as image::Layer;
+    let layer = layer.min(view.layers.end - view.layers.start - 1);
+    let wrap_modes = [desc.wrap_mode.0, desc.wrap_mode.1, desc.wrap_mode.2];
+
+    // Texel coordinates and weights of the taps along each dimension
+    let mut axes = Vec::with_capacity(dimensions);
+    for axis in 0..dimensions {
+        let coordinate = coordinates.get(axis).cloned().unwrap_or(0.0);
+        let texel = if desc.normalized {
+            coordinate * size[axis] as f32
+        } else {
+            coordinate
+        };
+        let taps = match filter {
+            image::Filter::Nearest => vec![(texel.floor() as i32, 1.0)],
+            image::Filter::Linear => {
+                let texel = texel - 0.5;
+                let base = texel.floor();
+                let fraction = texel - base;
+                vec![(base as i32, 1.0 - fraction), (base as i32 + 1, fraction)]
+            }
+        };
+        axes.push(
+            taps.into_iter()
+                .map(|(coordinate, weight)| {
+                    (wrap(coordinate, size[axis], wrap_modes[axis]), weight)
+                })
+                .collect::<Vec<_>>(),
+        );
+    }
+
+    let border = match desc.border {
+        image::BorderColor::TransparentBlack => [0.0, 0.0, 0.0, 0.0],
+        image::BorderColor::OpaqueBlack => [0.0, 0.0, 0.0, 1.0],
+        image::BorderColor::OpaqueWhite => [1.0; 4],
+    };
+    let fetch = |texel: [Option<u32>; 3]| -> Value {
+        match texel {
+            [Some(x), Some(y), Some(z)] => view.read(level as image::Level, layer, x, y, z),
+            _ => Value::vector(border.iter().cloned()),
+        }
+    };
+
+    let tap_count = axes.iter().map(Vec::len).product::<usize>();
+    if tap_count == 1 {
+        let mut texel = [Some(0); 3];
+        for (axis, taps) in axes.iter().enumerate() {
+            texel[axis] = taps[0].0;
+        }
+        return fetch(texel);
+    }
+
+    let mut result = [0.0; 4];
+    for tap in 0..tap_count {
+        let mut texel = [Some(0); 3];
+        let mut weight = 1.0;
+        let mut index = tap;
+        for (axis, taps) in axes.iter().enumerate() {
+            let (coordinate, axis_weight) = taps[index % taps.len()];
+            index /= taps.len();
+            texel[axis] = coordinate;
+            weight *= axis_weight;
+        }
+        for (r, value) in result.iter_mut().zip(fetch(texel).floats()) {
+            *r += weight * value;
+        }
+    }
+    Value::vector(result.iter().cloned())
+}
+
+/// Read a texel with integer coordinates, returning zero outside of the view.
+pub fn fetch(view: &ImageView, coordinates: &[i32], level: u32) -> Value {
+    let (dimensions, layer_coordinate) = match dimensions(view.kind) {
+        Some(dimensions) => dimensions,
+        None => return Value::vector(vec![0.0; 4]),
+    };
+    let unsigned = |index: usize| match coordinates.get(index) {
+        Some(&coordinate) if coordinate >= 0 => coordinate as u32,
+        Some(_) => !0,
+        None => 0,
+    };
+    let mut texel = [0; 3];
+    for (axis, texel) in texel.iter_mut().enumerate().take(dimensions) {
+        *texel = unsigned(axis);
+    }
+    let layer = layer_coordinate.map_or(0, unsigned);
+    if level >= view.levels() as u32 || layer >= (view.layers.end - view.layers.start) as u32 {
+        return Value::vector(vec![0.0; 4]);
+    }
+    view.read(
+        level as image::Level,
+        layer as image::Layer,
+        texel[0],
+        texel[1],
+        texel[2],
+    )
+}
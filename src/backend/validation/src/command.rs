@@ -0,0 +1,1423 @@
+use crate::{
+    conv, Backend, Buffer, ComputePipeline, Framebuffer, GraphicsPipeline, Id, RenderPass, Shared,
+};
+
+use hal::{
+    buffer, command, format, image, memory, pass::SubpassId, pool, pso, query,
+    queue::QueueFamilyId, Features,
+};
+
+use std::{
+    collections::HashMap,
+    fmt,
+    ops::Range,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum State {
+    Initial,
+    Recording,
+    Executable,
+}
+
+/// Render pass instance that the recorded commands belong to.
+#[derive(Debug)]
+struct PassScope {
+    /// Render pass of the scope, or `None` for a dynamic rendering.
+    render_pass: Option<Id>,
+    subpass: SubpassId,
+    subpass_count: usize,
+    /// Contents of the current subpass are recorded in secondary command buffers.
+    secondary_contents: bool,
+    /// The scope is inherited by a secondary command buffer, which
+    /// can't change the subpass or end the render pass itself.
+    inherited: bool,
+}
+
+#[derive(Debug)]
+pub struct CommandPool<B: hal::Backend> {
+    pub(crate) raw: B::CommandPool,
+    pub(crate) shared: Arc<Shared>,
+    pub(crate) family: QueueFamilyId,
+    pub(crate) flags: pool::CommandPoolCreateFlags,
+    pub(crate) features: Features,
+}
+
+impl<B: hal::Backend> CommandPool<B> {
+    fn wrap(&self, raw: B::CommandBuffer, level: command::Level) -> CommandBuffer<B> {
+        CommandBuffer {
+            raw,
+            id: self.shared.register("command buffer"),
+            shared: Arc::clone(&self.shared),
+            level,
+            family: self.family,
+            features: self.features,
+            resettable: self
+                .flags
+                .contains(pool::CommandPoolCreateFlags::RESET_INDIVIDUAL),
+            protected: self.flags.contains(pool::CommandPoolCreateFlags::PROTECTED),
+            state: State::Initial,
+            flags: command::CommandBufferFlags::empty(),
+            pass: None,
+            graphics_pipeline: false,
+            compute_pipeline: false,
+            resources: HashMap::new(),
+            submitted: AtomicBool::new(false),
+        }
+    }
+}
+
+impl<B: hal::Backend> pool::CommandPool<Backend<B>> for CommandPool<B> {
+    unsafe fn reset(&mut self, release_resources: bool) {
+        self.raw.reset(release_resources);
+    }
+
+    unsafe fn allocate_one(&mut self, level: command::Level) -> CommandBuffer<B> {
+        let raw = self.raw.allocate_one(level);
+        self.wrap(raw, level)
+    }
+
+    unsafe fn allocate<E>(&mut self, num: usize, level: command::Level, list: &mut E)
+    where
+        E: Extend<CommandBuffer<B>>,
+    {
+        let mut raw_list = Vec::with_capacity(num);
+        self.raw.allocate(num, level, &mut raw_list);
+        list.extend(raw_list.into_iter().map(|raw| self.wrap(raw, level)));
+    }
+
+    unsafe fn free<I>(&mut self, buffers: I)
+    where
+        I: Iterator<Item = CommandBuffer<B>>,
+    {
+        let shared = &self.shared;
+        self.raw.free(buffers.map(|cmd_buf| {
+            shared.unregister(cmd_buf.id);
+            cmd_buf.raw
+        }));
+    }
+}
+
+#[derive(Debug)]
+pub struct CommandBuffer<B: hal::Backend> {
+    pub(crate) raw: B::CommandBuffer,
+    pub(crate) id: Id,
+    shared: Arc<Shared>,
+    level: command::Level,
+    family: QueueFamilyId,
+    features: Features,
+    resettable: bool,
+    protected: bool,
+    state: State,
+    flags: command::CommandBufferFlags,
+    pass: Option<PassScope>,
+    graphics_pipeline: bool,
+    compute_pipeline: bool,
+    /// Objects referenced by the recorded commands, with their labels
+    /// captured at the time of recording.
+    resources: HashMap<Id, String>,
+    /// A one-time-submit command buffer has been submitted since recording.
+    submitted: AtomicBool,
+}
+
+impl<B: hal::Backend> CommandBuffer<B> {
+    /// Report an error, returning `true` if the call is still forwarded.
+    fn report(&self, message: fmt::Arguments) -> bool {
+        self.shared
+            .report(format_args!("{}: {}", self.shared.label(self.id), message))
+    }
+
+    fn reference(&mut self, id: Id) {
+        let shared = &self.shared;
+        self.resources.entry(id).or_insert_with(|| shared.label(id));
+    }
+
+    // The checks return `true` if the command is forwarded to the inner backend.
+
+    fn check_recording(&self, command: &str) -> bool {
+        if self.state != State::Recording {
+            return self.report(format_args!(
+                "`{}` is recorded in the {:?} state",
+                command, self.state
+            ));
+        }
+        true
+    }
+
+    fn check_outside_pass(&self, command: &str) -> bool {
+        let forward = self.check_recording(command);
+        if let Some(ref pass) = self.pass {
+            return self.report(format_args!(
+                "`{}` is recorded inside of {}",
+                command,
+                self.pass_label(pass)
+            )) && forward;
+        }
+        forward
+    }
+
+    fn pass_label(&self, pass: &PassScope) -> &str {
+        match pass.render_pass {
+            Some(id) => &self.resources[&id],
+            None => "dynamic rendering",
+        }
+    }
+
+    fn check_inside_pass(&self, command: &str) -> bool {
+        let forward = self.check_recording(command);
+        if self.pass.is_none() {
+            return self.report(format_args!(
+                "`{}` is recorded outside of a render pass",
+                command
+            )) && forward;
+        }
+        forward
+    }
+
+    /// Check an inline command of a subpass.
+    fn check_inline(&self, command: &str) -> bool {
+        let forward = self.check_inside_pass(command);
+        if let Some(PassScope {
+            secondary_contents: true,
+            ..
+        }) = self.pass
+        {
+            return self.report(format_args!(
+                "`{}` is recorded inline in a subpass with secondary buffer contents",
+                command
+            )) && forward;
+        }
+        forward
+    }
+
+    fn check_draw(&self, command: &str) -> bool {
+        let forward = self.check_inline(command);
+        if !self.graphics_pipeline {
+            return self.report(format_args!(
+                "`{}` is recorded without a bound graphics pipeline",
+                command
+            )) && forward;
+        }
+        forward
+    }
+
+    fn check_dispatch(&self, command: &str) -> bool {
+        let forward = self.check_outside_pass(command);
+        if !self.compute_pipeline {
+            return self.report(format_args!(
+                "`{}` is recorded without a bound compute pipeline",
+                command
+            )) && forward;
+        }
+        forward
+    }
+
+    fn check_barriers<'a>(
+        &mut self,
+        command: &str,
+        stages: &Range<pso::PipelineStage>,
+        barriers: impl Iterator<Item = memory::Barrier<'a, Backend<B>>>,
+    ) -> Option<Vec<memory::Barrier<'a, B>>> {
+        let mut forward = self.check_recording(command);
+        if stages.start.is_empty() || stages.end.is_empty() {
+            forward &= self.report(format_args!(
+                "`{}` has an empty stage mask {:?}",
+                command, stages
+            ));
+        }
+        if self.pass.is_some() {
+            warn!(
+                "{}: `{}` inside of a render pass requires a subpass self-dependency",
+                self.shared.label(self.id),
+                command
+            );
+        }
+
+        let mut raw_barriers = Vec::new();
+        for barrier in barriers {
+            match &barrier {
+                memory::Barrier::AllBuffers(access) if access.start == access.end => {
+                    warn!(
+                        "{}: redundant global buffer barrier",
+                        self.shared.label(self.id)
+                    );
+                }
+                memory::Barrier::AllImages(access) if access.start == access.end => {
+                    warn!(
+                        "{}: redundant global image barrier",
+                        self.shared.label(self.id)
+                    );
+                }
+                memory::Barrier::Buffer {
+                    states,
+                    target,
+                    families,
+                    ..
+                } => {
+                    self.reference(target.id);
+                    forward &= self.check_families(families);
+                    if states.start == states.end && families.is_none() {
+                        warn!(
+                            "{}: redundant barrier for {}",
+                            self.shared.label(self.id),
+                            self.resources[&target.id]
+                        );
+                    }
+                }
+                memory::Barrier::Image {
+                    states,
+                    target,
+                    families,
+                    ..
+                } => {
+                    forward &= self.check_families(families);
+                    if states.end.1 == image::Layout::Undefined {
+                        forward &= self.report(format_args!(
+                            "image {:?} is transitioned into the `Undefined` layout",
+                            target
+                        ));
+                    } else if states.start == states.end && families.is_none() {
+                        warn!(
+                            "{}: redundant barrier for image {:?}",
+                            self.shared.label(self.id),
+                            target
+                        );
+                    }
+                }
+                memory::Barrier::Aliasing { before, after } => {
+                    for resource in before.iter().chain(after.iter()) {
+                        if let memory::AliasedResource::Buffer(buffer) = *resource {
+                            self.reference(buffer.id);
+                        }
+                    }
+                }
+                _ => {}
+            }
+            raw_barriers.push(conv::map_barrier(barrier));
+        }
+        if forward {
+            Some(raw_barriers)
+        } else {
+            None
+        }
+    }
+
+    /// Check that a queue family ownership transfer is recorded on either side of it.
+    fn check_families(&self, families: &Option<Range<QueueFamilyId>>) -> bool {
+        match *families {
+            Some(ref f) if f.start != f.end && self.family != f.start && self.family != f.end => {
+                self.report(format_args!(
+                    "ownership transfer from queue family {:?} to {:?} is recorded for family {:?}",
+                    f.start, f.end, self.family
+                ))
+            }
+            _ => true,
+        }
+    }
+
+    fn reset_tracking(&mut self) {
+        self.pass = None;
+        self.graphics_pipeline = false;
+        self.compute_pipeline = false;
+        self.resources.clear();
+        self.submitted.store(false, Ordering::Relaxed);
+    }
+
+    pub(crate) fn validate_submit(&self, family: QueueFamilyId, protected: bool) {
+        if self.level != command::Level::Primary {
+            self.report(format_args!(
+                "secondary command buffer is submitted to a queue"
+            ));
+        }
+        if self.state != State::Executable {
+            self.report(format_args!("submitted in the {:?} state", self.state));
+        }
+        if self.family != family {
+            self.report(format_args!(
+                "allocated for queue family {:?} is submitted to family {:?}",
+                self.family, family
+            ));
+        }
+        if self.protected != protected {
+            self.report(format_args!(
+                "allocated from {} pool is submitted in {} mode",
+                if self.protected {
+                    "a protected"
+                } else {
+                    "an unprotected"
+                },
+                if protected {
+                    "protected"
+                } else {
+                    "unprotected"
+                },
+            ));
+        }
+        if self
+            .flags
+            .contains(command::CommandBufferFlags::ONE_TIME_SUBMIT)
+            && self.submitted.swap(true, Ordering::Relaxed)
+        {
+            self.report(format_args!(
+                "one-time-submit command buffer is submitted again without re-recording"
+            ));
+        }
+        for (&id, label) in self.resources.iter() {
+            if !self.shared.is_alive(id) {
+                self.report(format_args!("references destroyed {}", label));
+            }
+        }
+    }
+}
+
+impl<B: hal::Backend> command::CommandBuffer<Backend<B>> for CommandBuffer<B> {
+    unsafe fn begin(
+        &mut self,
+        flags: command::CommandBufferFlags,
+        inheritance_info: command::CommandBufferInheritanceInfo<Backend<B>>,
+    ) {
+        let forward = match self.state {
+            State::Recording => self.report(format_args!("begins while already recording")),
+            State::Executable if !self.resettable => self.report(format_args!(
+                "is implicitly reset by `begin`, but the pool lacks `RESET_INDIVIDUAL`"
+            )),
+            _ => true,
+        };
+        if !forward {
+            return;
+        }
+        self.reset_tracking();
+        self.state = State::Recording;
+        self.flags = flags;
+
+        if let Some(framebuffer) = inheritance_info.framebuffer {
+            self.reference(framebuffer.id);
+        }
+        if let Some(ref subpass) = inheritance_info.subpass {
+            self.reference(subpass.main_pass.id);
+        }
+        if self.level == command::Level::Secondary
+            && flags.contains(command::CommandBufferFlags::RENDER_PASS_CONTINUE)
+        {
+            match inheritance_info.subpass {
+                Some(ref subpass) => {
+                    self.pass = Some(PassScope {
+                        render_pass: Some(subpass.main_pass.id),
+                        subpass: subpass.index,
+                        subpass_count: subpass.main_pass.subpasses,
+                        secondary_contents: false,
+                        inherited: true,
+                    });
+                }
+                None => {
+                    self.report(format_args!(
+                        "continues a render pass without an inherited subpass"
+                    ));
+                }
+            }
+        }
+
+        self.raw
+            .begin(flags, conv::map_inheritance_info(inheritance_info));
+    }
+
+    unsafe fn finish(&mut self) {
+        if !self.check_recording("finish") {
+            return;
+        }
+        if let Some(ref pass) = self.pass {
+            if !pass.inherited
+                && !self.report(format_args!("finishes inside of {}", self.pass_label(pass)))
+            {
+                return;
+            }
+        }
+        self.state = State::Executable;
+        self.raw.finish();
+    }
+
+    unsafe fn reset(&mut self, release_resources: bool) {
+        if !self.resettable
+            && !self.report(format_args!(
+                "is reset, but the pool lacks `RESET_INDIVIDUAL`"
+            ))
+        {
+            return;
+        }
+        self.reset_tracking();
+        self.state = State::Initial;
+        self.raw.reset(release_resources);
+    }
+
+    unsafe fn pipeline_barrier<'a, T>(
+        &mut self,
+        stages: Range<pso::PipelineStage>,
+        dependencies: memory::Dependencies,
+        barriers: T,
+    ) where
+        T: Iterator<Item = memory::Barrier<'a, Backend<B>>>,
+    {
+        let barriers = match self.check_barriers("pipeline_barrier", &stages, barriers) {
+            Some(barriers) => barriers,
+            None => return,
+        };
+        self.raw
+            .pipeline_barrier(stages, dependencies, barriers.into_iter());
+    }
+
+    unsafe fn begin_barrier<'a, T>(
+        &mut self,
+        event: &B::Event,
+        stages: Range<pso::PipelineStage>,
+        barriers: T,
+    ) where
+        T: Iterator<Item = memory::Barrier<'a, Backend<B>>>,
+    {
+        if !self.check_outside_pass("begin_barrier") {
+            return;
+        }
+        let barriers = match self.check_barriers("begin_barrier", &stages, barriers) {
+            Some(barriers) => barriers,
+            None => return,
+        };
+        self.raw.begin_barrier(event, stages, barriers.into_iter());
+    }
+
+    unsafe fn end_barrier<'a, T>(
+        &mut self,
+        event: &B::Event,
+        stages: Range<pso::PipelineStage>,
+        barriers: T,
+    ) where
+        T: Iterator<Item = memory::Barrier<'a, Backend<B>>>,
+    {
+        if !self.check_outside_pass("end_barrier") {
+            return;
+        }
+        let barriers = match self.check_barriers("end_barrier", &stages, barriers) {
+            Some(barriers) => barriers,
+            None => return,
+        };
+        self.raw.end_barrier(event, stages, barriers.into_iter());
+    }
+
+    unsafe fn fill_buffer(&mut self, buffer: &Buffer<B>, range: buffer::SubRange, data: u32) {
+        if !self.check_outside_pass("fill_buffer") {
+            return;
+        }
+        self.reference(buffer.id);
+        self.raw.fill_buffer(&buffer.raw, range, data);
+    }
+
+    unsafe fn update_buffer(&mut self, buffer: &Buffer<B>, offset: buffer::Offset, data: &[u8]) {
+        if !self.check_outside_pass("update_buffer") {
+            return;
+        }
+        self.reference(buffer.id);
+        self.raw.update_buffer(&buffer.raw, offset, data);
+    }
+
+    unsafe fn clear_image<T>(
+        &mut self,
+        image: &B::Image,
+        layout: image::Layout,
+        value: command::ClearValue,
+        subresource_ranges: T,
+    ) where
+        T: Iterator<Item = image::SubresourceRange>,
+    {
+        if !self.check_outside_pass("clear_image") {
+            return;
+        }
+        self.raw
+            .clear_image(image, layout, value, subresource_ranges);
+    }
+
+    unsafe fn clear_attachments<T, U>(&mut self, clears: T, rects: U)
+    where
+        T: Iterator<Item = command::AttachmentClear>,
+        U: Iterator<Item = pso::ClearRect>,
+    {
+        if !self.check_inline("clear_attachments") {
+            return;
+        }
+        self.raw.clear_attachments(clears, rects);
+    }
+
+    unsafe fn resolve_image<T>(
+        &mut self,
+        src: &B::Image,
+        src_layout: image::Layout,
+        dst: &B::Image,
+        dst_layout: image::Layout,
+        regions: T,
+    ) where
+        T: Iterator<Item = command::ImageResolve>,
+    {
+        if !self.check_outside_pass("resolve_image") {
+            return;
+        }
+        self.raw
+            .resolve_image(src, src_layout, dst, dst_layout, regions);
+    }
+
+    unsafe fn blit_image<T>(
+        &mut self,
+        src: &B::Image,
+        src_layout: image::Layout,
+        dst: &B::Image,
+        dst_layout: image::Layout,
+        filter: image::Filter,
+        regions: T,
+    ) where
+        T: Iterator<Item = command::ImageBlit>,
+    {
+        if !self.check_outside_pass("blit_image") {
+            return;
+        }
+        self.raw
+            .blit_image(src, src_layout, dst, dst_layout, filter, regions);
+    }
+
+    unsafe fn bind_index_buffer(
+        &mut self,
+        buffer: &Buffer<B>,
+        sub: buffer::SubRange,
+        ty: hal::IndexType,
+    ) {
+        if !self.check_recording("bind_index_buffer") {
+            return;
+        }
+        self.reference(buffer.id);
+        self.raw.bind_index_buffer(&buffer.raw, sub, ty);
+    }
+
+    unsafe fn bind_vertex_buffers<'a, T>(&mut self, first_binding: pso::BufferIndex, buffers: T)
+    where
+        T: Iterator<Item = (&'a Buffer<B>, buffer::SubRange)>,
+    {
+        if !self.check_recording("bind_vertex_buffers") {
+            return;
+        }
+        let buffers = buffers
+            .map(|(buffer, sub)| {
+                self.reference(buffer.id);
+                (&buffer.raw, sub)
+            })
+            .collect::<Vec<_>>();
+        self.raw
+            .bind_vertex_buffers(first_binding, buffers.into_iter());
+    }
+
+    unsafe fn set_viewports<T>(&mut self, first_viewport: u32, viewports: T)
+    where
+        T: Iterator<Item = pso::Viewport>,
+    {
+        if !self.check_recording("set_viewports") {
+            return;
+        }
+        let viewports = viewports.collect::<Vec<_>>();
+        for viewport in &viewports {
+            if !crate::is_depth_range_valid(self.features, &viewport.depth)
+                && !self.report(format_args!(
+                    "viewport depth range {:?} is outside of [0, 1] without DEPTH_RANGE_UNRESTRICTED",
+                    viewport.depth
+                ))
+            {
+                return;
+            }
+        }
+        self.raw
+            .set_viewports(first_viewport, viewports.into_iter());
+    }
+
+    unsafe fn set_scissors<T>(&mut self, first_scissor: u32, rects: T)
+    where
+        T: Iterator<Item = pso::Rect>,
+    {
+        if !self.check_recording("set_scissors") {
+            return;
+        }
+        self.raw.set_scissors(first_scissor, rects);
+    }
+
+    unsafe fn set_stencil_reference(&mut self, faces: pso::Face, value: pso::StencilValue) {
+        if !self.check_recording("set_stencil_reference") {
+            return;
+        }
+        self.raw.set_stencil_reference(faces, value);
+    }
+
+    unsafe fn set_stencil_read_mask(&mut self, faces: pso::Face, value: pso::StencilValue) {
+        if !self.check_recording("set_stencil_read_mask") {
+            return;
+        }
+        self.raw.set_stencil_read_mask(faces, value);
+    }
+
+    unsafe fn set_stencil_write_mask(&mut self, faces: pso::Face, value: pso::StencilValue) {
+        if !self.check_recording("set_stencil_write_mask") {
+            return;
+        }
+        self.raw.set_stencil_write_mask(faces, value);
+    }
+
+    unsafe fn set_blend_constants(&mut self, color: pso::ColorValue) {
+        if !self.check_recording("set_blend_constants") {
+            return;
+        }
+        self.raw.set_blend_constants(color);
+    }
+
+    unsafe fn set_depth_bounds(&mut self, bounds: Range<f32>) {
+        if !self.check_recording("set_depth_bounds") {
+            return;
+        }
+        self.raw.set_depth_bounds(bounds);
+    }
+
+    unsafe fn set_line_width(&mut self, width: f32) {
+        if !self.check_recording("set_line_width") {
+            return;
+        }
+        self.raw.set_line_width(width);
+    }
+
+    unsafe fn set_depth_bias(&mut self, depth_bias: pso::DepthBias) {
+        if !self.check_recording("set_depth_bias") {
+            return;
+        }
+        self.raw.set_depth_bias(depth_bias);
+    }
+
+    unsafe fn set_shading_rate(
+        &mut self,
+        rate: pso::ShadingRate,
+        combiners: [pso::ShadingRateCombiner; 2],
+    ) {
+        if !self.check_recording("set_shading_rate") {
+            return;
+        }
+        if !self.features.contains(Features::SHADING_RATE)
+            && !self.report(format_args!("set_shading_rate without SHADING_RATE"))
+        {
+            return;
+        }
+        if combiners[0] != pso::ShadingRateCombiner::Keep
+            && !self.report(format_args!(
+                "set_shading_rate combines the per-primitive rate with {:?}",
+                combiners[0]
+            ))
+        {
+            return;
+        }
+        if combiners[1] != pso::ShadingRateCombiner::Keep
+            && !self.features.contains(Features::SHADING_RATE_ATTACHMENT)
+            && !self.report(format_args!(
+                "set_shading_rate combines the attachment rate with {:?} without SHADING_RATE_ATTACHMENT",
+                combiners[1]
+            ))
+        {
+            return;
+        }
+        self.raw.set_shading_rate(rate, combiners);
+    }
+
+    unsafe fn set_sample_locations(
+        &mut self,
+        samples: image::NumSamples,
+        locations: &[pso::SampleLocation],
+    ) {
+        if !self.check_recording("set_sample_locations") {
+            return;
+        }
+        if locations.len() != samples as usize {
+            self.report(format_args!(
+                "set_sample_locations with {} locations for {} samples",
+                locations.len(),
+                samples
+            ));
+        }
+        self.raw.set_sample_locations(samples, locations);
+    }
+
+    unsafe fn begin_render_pass<'a, T>(
+        &mut self,
+        render_pass: &RenderPass<B>,
+        framebuffer: &Framebuffer<B>,
+        render_area: pso::Rect,
+        attachments: T,
+        first_subpass: command::SubpassContents,
+    ) where
+        T: Iterator<Item = command::RenderAttachmentInfo<'a, Backend<B>>>,
+    {
+        if !self.check_outside_pass("begin_render_pass") {
+            return;
+        }
+        if self.level != command::Level::Primary
+            && !self.report(format_args!(
+                "secondary command buffer begins a render pass"
+            ))
+        {
+            return;
+        }
+        if render_pass.rendering
+            && !self.report(format_args!(
+                "begins a render pass with {}",
+                self.shared.label(render_pass.id)
+            ))
+        {
+            return;
+        }
+        // The framebuffer only describes the attachments, the views are provided here.
+        let attachments = attachments.collect::<Vec<_>>();
+        if attachments.len() != framebuffer.attachments
+            && !self.report(format_args!(
+                "begins a render pass with {} attachments, but {} has {}",
+                attachments.len(),
+                self.shared.label(framebuffer.id),
+                framebuffer.attachments
+            ))
+        {
+            return;
+        }
+        let area = &render_area;
+        if (area.x < 0
+            || area.y < 0
+            || area.x as u32 + area.w as u32 > framebuffer.extent.width
+            || area.y as u32 + area.h as u32 > framebuffer.extent.height)
+            && !self.report(format_args!(
+                "begins a render pass with the area {:?} outside of {} of {:?}",
+                render_area,
+                self.shared.label(framebuffer.id),
+                framebuffer.extent
+            ))
+        {
+            return;
+        }
+        self.reference(render_pass.id);
+        self.reference(framebuffer.id);
+        self.pass = Some(PassScope {
+            render_pass: Some(render_pass.id),
+            subpass: 0,
+            subpass_count: render_pass.subpasses,
+            secondary_contents: match first_subpass {
+                command::SubpassContents::Inline => false,
+                command::SubpassContents::SecondaryBuffers => true,
+            },
+            inherited: false,
+        });
+        self.raw.begin_render_pass(
+            &render_pass.raw,
+            &framebuffer.raw,
+            render_area,
+            attachments
+                .into_iter()
+                .map(conv::map_render_attachment_info),
+            first_subpass,
+        );
+    }
+
+    unsafe fn next_subpass(&mut self, contents: command::SubpassContents) {
+        if !self.check_inside_pass("next_subpass") {
+            return;
+        }
+        let error = match self.pass {
+            Some(ref pass) if pass.inherited => {
+                Some("advances the subpass of an inherited render pass".to_string())
+            }
+            Some(ref pass) if pass.subpass as usize + 1 >= pass.subpass_count => {
+                Some(format!("advances past the last subpass {}", pass.subpass))
+            }
+            _ => None,
+        };
+        if let Some(error) = error {
+            if !self.report(format_args!("{}", error)) {
+                return;
+            }
+        }
+        if let Some(ref mut pass) = self.pass {
+            pass.subpass += 1;
+            pass.secondary_contents = match contents {
+                command::SubpassContents::Inline => false,
+                command::SubpassContents::SecondaryBuffers => true,
+            };
+        }
+        self.raw.next_subpass(contents);
+    }
+
+    unsafe fn end_render_pass(&mut self) {
+        if !self.check_inside_pass("end_render_pass") {
+            return;
+        }
+        let forward = match self.pass {
+            Some(ref pass) if pass.inherited => {
+                self.report(format_args!("ends an inherited render pass"))
+            }
+            Some(PassScope {
+                render_pass: None, ..
+            }) => self.report(format_args!(
+                "ends dynamic rendering with `end_render_pass`"
+            )),
+            Some(ref pass) if pass.subpass as usize + 1 != pass.subpass_count => {
+                self.report(format_args!(
+                    "ends {} at subpass {} out of {}",
+                    self.pass_label(pass),
+                    pass.subpass,
+                    pass.subpass_count
+                ))
+            }
+            _ => true,
+        };
+        if !forward {
+            return;
+        }
+        self.pass = None;
+        self.raw.end_render_pass();
+    }
+
+    unsafe fn begin_rendering(&mut self, info: &command::RenderingInfo<Backend<B>>) {
+        if !self.check_outside_pass("begin_rendering") {
+            return;
+        }
+        if self.level != command::Level::Primary
+            && !self.report(format_args!("secondary command buffer begins rendering"))
+        {
+            return;
+        }
+        let mut forward = true;
+        for (i, at) in info.colors.iter().enumerate() {
+            let is_color = at.desc.format.map_or(false, |format| {
+                format.surface_desc().aspects.contains(format::Aspects::COLOR)
+            });
+            if !is_color {
+                forward &= self.report(format_args!(
+                    "begins rendering with color attachment {} of format {:?}",
+                    i, at.desc.format
+                ));
+            }
+        }
+        if let Some(at) = info.depth_stencil {
+            let is_depth_stencil = at.desc.format.map_or(false, |format| {
+                format
+                    .surface_desc()
+                    .aspects
+                    .intersects(format::Aspects::DEPTH | format::Aspects::STENCIL)
+            });
+            if !is_depth_stencil {
+                forward &= self.report(format_args!(
+                    "begins rendering with depth/stencil attachment of format {:?}",
+                    at.desc.format
+                ));
+            }
+        }
+        if !forward {
+            return;
+        }
+        self.pass = Some(PassScope {
+            render_pass: None,
+            subpass: 0,
+            subpass_count: 1,
+            secondary_contents: false,
+            inherited: false,
+        });
+        let colors = info
+            .colors
+            .iter()
+            .map(conv::map_rendering_attachment_info)
+            .collect::<Vec<_>>();
+        let depth_stencil = info
+            .depth_stencil
+            .map(conv::map_rendering_attachment_info);
+        self.raw.begin_rendering(&command::RenderingInfo {
+            render_area: info.render_area,
+            layers: info.layers,
+            colors: &colors,
+            depth_stencil: depth_stencil.as_ref(),
+            flags: info.flags,
+        });
+    }
+
+    unsafe fn end_rendering(&mut self) {
+        if !self.check_inside_pass("end_rendering") {
+            return;
+        }
+        let forward = match self.pass {
+            Some(PassScope {
+                render_pass: Some(id),
+                ..
+            }) => self.report(format_args!(
+                "ends {} with `end_rendering`",
+                self.resources[&id]
+            )),
+            _ => true,
+        };
+        if !forward {
+            return;
+        }
+        self.pass = None;
+        self.raw.end_rendering();
+    }
+
+    unsafe fn bind_graphics_pipeline(&mut self, pipeline: &GraphicsPipeline<B>) {
+        if !self.check_recording("bind_graphics_pipeline") {
+            return;
+        }
+        self.reference(pipeline.id);
+        self.graphics_pipeline = true;
+        self.raw.bind_graphics_pipeline(&pipeline.raw);
+    }
+
+    unsafe fn bind_graphics_descriptor_sets<'a, I, J>(
+        &mut self,
+        layout: &B::PipelineLayout,
+        first_set: usize,
+        sets: I,
+        offsets: J,
+    ) where
+        I: Iterator<Item = &'a B::DescriptorSet>,
+        J: Iterator<Item = command::DescriptorSetOffset>,
+    {
+        if !self.check_recording("bind_graphics_descriptor_sets") {
+            return;
+        }
+        self.raw
+            .bind_graphics_descriptor_sets(layout, first_set, sets, offsets);
+    }
+
+    unsafe fn bind_compute_pipeline(&mut self, pipeline: &ComputePipeline<B>) {
+        if !self.check_recording("bind_compute_pipeline") {
+            return;
+        }
+        self.reference(pipeline.id);
+        self.compute_pipeline = true;
+        self.raw.bind_compute_pipeline(&pipeline.raw);
+    }
+
+    unsafe fn bind_compute_descriptor_sets<'a, I, J>(
+        &mut self,
+        layout: &B::PipelineLayout,
+        first_set: usize,
+        sets: I,
+        offsets: J,
+    ) where
+        I: Iterator<Item = &'a B::DescriptorSet>,
+        J: Iterator<Item = command::DescriptorSetOffset>,
+    {
+        if !self.check_recording("bind_compute_descriptor_sets") {
+            return;
+        }
+        self.raw
+            .bind_compute_descriptor_sets(layout, first_set, sets, offsets);
+    }
+
+    unsafe fn dispatch(&mut self, count: hal::WorkGroupCount) {
+        if !self.check_dispatch("dispatch") {
+            return;
+        }
+        self.raw.dispatch(count);
+    }
+
+    unsafe fn dispatch_indirect(&mut self, buffer: &Buffer<B>, offset: buffer::Offset) {
+        if !self.check_dispatch("dispatch_indirect") {
+            return;
+        }
+        self.reference(buffer.id);
+        self.raw.dispatch_indirect(&buffer.raw, offset);
+    }
+
+    unsafe fn copy_buffer<T>(&mut self, src: &Buffer<B>, dst: &Buffer<B>, regions: T)
+    where
+        T: Iterator<Item = command::BufferCopy>,
+    {
+        if !self.check_outside_pass("copy_buffer") {
+            return;
+        }
+        self.reference(src.id);
+        self.reference(dst.id);
+        self.raw.copy_buffer(&src.raw, &dst.raw, regions);
+    }
+
+    unsafe fn copy_image<T>(
+        &mut self,
+        src: &B::Image,
+        src_layout: image::Layout,
+        dst: &B::Image,
+        dst_layout: image::Layout,
+        regions: T,
+    ) where
+        T: Iterator<Item = command::ImageCopy>,
+    {
+        if !self.check_outside_pass("copy_image") {
+            return;
+        }
+        self.raw
+            .copy_image(src, src_layout, dst, dst_layout, regions);
+    }
+
+    unsafe fn copy_buffer_to_image<T>(
+        &mut self,
+        src: &Buffer<B>,
+        dst: &B::Image,
+        dst_layout: image::Layout,
+        regions: T,
+    ) where
+        T: Iterator<Item = command::BufferImageCopy>,
+    {
+        if !self.check_outside_pass("copy_buffer_to_image") {
+            return;
+        }
+        self.reference(src.id);
+        self.raw
+            .copy_buffer_to_image(&src.raw, dst, dst_layout, regions);
+    }
+
+    unsafe fn copy_image_to_buffer<T>(
+        &mut self,
+        src: &B::Image,
+        src_layout: image::Layout,
+        dst: &Buffer<B>,
+        regions: T,
+    ) where
+        T: Iterator<Item = command::BufferImageCopy>,
+    {
+        if !self.check_outside_pass("copy_image_to_buffer") {
+            return;
+        }
+        self.reference(dst.id);
+        self.raw
+            .copy_image_to_buffer(src, src_layout, &dst.raw, regions);
+    }
+
+    unsafe fn draw(
+        &mut self,
+        vertices: Range<hal::VertexCount>,
+        instances: Range<hal::InstanceCount>,
+    ) {
+        if !self.check_draw("draw") {
+            return;
+        }
+        self.raw.draw(vertices, instances);
+    }
+
+    unsafe fn draw_indexed(
+        &mut self,
+        indices: Range<hal::IndexCount>,
+        base_vertex: hal::VertexOffset,
+        instances: Range<hal::InstanceCount>,
+    ) {
+        if !self.check_draw("draw_indexed") {
+            return;
+        }
+        self.raw.draw_indexed(indices, base_vertex, instances);
+    }
+
+    unsafe fn draw_indirect(
+        &mut self,
+        buffer: &Buffer<B>,
+        offset: buffer::Offset,
+        draw_count: hal::DrawCount,
+        stride: buffer::Stride,
+    ) {
+        if !self.check_draw("draw_indirect") {
+            return;
+        }
+        self.reference(buffer.id);
+        self.raw
+            .draw_indirect(&buffer.raw, offset, draw_count, stride);
+    }
+
+    unsafe fn draw_indexed_indirect(
+        &mut self,
+        buffer: &Buffer<B>,
+        offset: buffer::Offset,
+        draw_count: hal::DrawCount,
+        stride: buffer::Stride,
+    ) {
+        if !self.check_draw("draw_indexed_indirect") {
+            return;
+        }
+        self.reference(buffer.id);
+        self.raw
+            .draw_indexed_indirect(&buffer.raw, offset, draw_count, stride);
+    }
+
+    unsafe fn draw_indirect_count(
+        &mut self,
+        buffer: &Buffer<B>,
+        offset: buffer::Offset,
+        count_buffer: &Buffer<B>,
+        count_buffer_offset: buffer::Offset,
+        max_draw_count: u32,
+        stride: buffer::Stride,
+    ) {
+        if !self.check_draw("draw_indirect_count") {
+            return;
+        }
+        self.reference(buffer.id);
+        self.reference(count_buffer.id);
+        self.raw.draw_indirect_count(
+            &buffer.raw,
+            offset,
+            &count_buffer.raw,
+            count_buffer_offset,
+            max_draw_count,
+            stride,
+        );
+    }
+
+    unsafe fn draw_indexed_indirect_count(
+        &mut self,
+        buffer: &Buffer<B>,
+        offset: buffer::Offset,
+        count_buffer: &Buffer<B>,
+        count_buffer_offset: buffer::Offset,
+        max_draw_count: u32,
+        stride: buffer::Stride,
+    ) {
+        if !self.check_draw("draw_indexed_indirect_count") {
+            return;
+        }
+        self.reference(buffer.id);
+        self.reference(count_buffer.id);
+        self.raw.draw_indexed_indirect_count(
+            &buffer.raw,
+            offset,
+            &count_buffer.raw,
+            count_buffer_offset,
+            max_draw_count,
+            stride,
+        );
+    }
+
+    unsafe fn draw_mesh_tasks(&mut self, task_count: hal::TaskCount, first_task: hal::TaskCount) {
+        if !self.check_draw("draw_mesh_tasks") {
+            return;
+        }
+        self.raw.draw_mesh_tasks(task_count, first_task);
+    }
+
+    unsafe fn draw_mesh_tasks_indirect(
+        &mut self,
+        buffer: &Buffer<B>,
+        offset: buffer::Offset,
+        draw_count: hal::DrawCount,
+        stride: buffer::Stride,
+    ) {
+        if !self.check_draw("draw_mesh_tasks_indirect") {
+            return;
+        }
+        self.reference(buffer.id);
+        self.raw
+            .draw_mesh_tasks_indirect(&buffer.raw, offset, draw_count, stride);
+    }
+
+    unsafe fn draw_mesh_tasks_indirect_count(
+        &mut self,
+        buffer: &Buffer<B>,
+        offset: buffer::Offset,
+        count_buffer: &Buffer<B>,
+        count_buffer_offset: buffer::Offset,
+        max_draw_count: hal::DrawCount,
+        stride: buffer::Stride,
+    ) {
+        if !self.check_draw("draw_mesh_tasks_indirect_count") {
+            return;
+        }
+        self.reference(buffer.id);
+        self.reference(count_buffer.id);
+        self.raw.draw_mesh_tasks_indirect_count(
+            &buffer.raw,
+            offset,
+            &count_buffer.raw,
+            count_buffer_offset,
+            max_draw_count,
+            stride,
+        );
+    }
+
+    unsafe fn set_event(&mut self, event: &B::Event, stages: pso::PipelineStage) {
+        if !self.check_outside_pass("set_event") {
+            return;
+        }
+        self.raw.set_event(event, stages);
+    }
+
+    unsafe fn reset_event(&mut self, event: &B::Event, stages: pso::PipelineStage) {
+        if !self.check_outside_pass("reset_event") {
+            return;
+        }
+        self.raw.reset_event(event, stages);
+    }
+
+    unsafe fn wait_events<'a, I, J>(
+        &mut self,
+        events: I,
+        stages: Range<pso::PipelineStage>,
+        barriers: J,
+    ) where
+        I: Iterator<Item = &'a B::Event>,
+        J: Iterator<Item = memory::Barrier<'a, Backend<B>>>,
+    {
+        let barriers = match self.check_barriers("wait_events", &stages, barriers) {
+            Some(barriers) => barriers,
+            None => return,
+        };
+        self.raw.wait_events(events, stages, barriers.into_iter());
+    }
+
+    unsafe fn begin_query(&mut self, query: query::Query<Backend<B>>, flags: query::ControlFlags) {
+        if !self.check_recording("begin_query") {
+            return;
+        }
+        self.raw.begin_query(conv::map_query(query), flags);
+    }
+
+    unsafe fn end_query(&mut self, query: query::Query<Backend<B>>) {
+        if !self.check_recording("end_query") {
+            return;
+        }
+        self.raw.end_query(conv::map_query(query));
+    }
+
+    unsafe fn reset_query_pool(&mut self, pool: &B::QueryPool, queries: Range<query::Id>) {
+        if !self.check_outside_pass("reset_query_pool") {
+            return;
+        }
+        self.raw.reset_query_pool(pool, queries);
+    }
+
+    unsafe fn copy_query_pool_results(
+        &mut self,
+        pool: &B::QueryPool,
+        queries: Range<query::Id>,
+        buffer: &Buffer<B>,
+        offset: buffer::Offset,
+        stride: buffer::Stride,
+        flags: query::ResultFlags,
+    ) {
+        if !self.check_outside_pass("copy_query_pool_results") {
+            return;
+        }
+        self.reference(buffer.id);
+        self.raw
+            .copy_query_pool_results(pool, queries, &buffer.raw, offset, stride, flags);
+    }
+
+    unsafe fn write_timestamp(
+        &mut self,
+        stage: pso::PipelineStage,
+        query: query::Query<Backend<B>>,
+    ) {
+        if !self.check_recording("write_timestamp") {
+            return;
+        }
+        self.raw.write_timestamp(stage, conv::map_query(query));
+    }
+
+    unsafe fn push_graphics_constants(
+        &mut self,
+        layout: &B::PipelineLayout,
+        stages: pso::ShaderStageFlags,
+        offset: u32,
+        constants: &[u32],
+    ) {
+        if !self.check_recording("push_graphics_constants") {
+            return;
+        }
+        self.raw
+            .push_graphics_constants(layout, stages, offset, constants);
+    }
+
+    unsafe fn push_compute_constants(
+        &mut self,
+        layout: &B::PipelineLayout,
+        offset: u32,
+        constants: &[u32],
+    ) {
+        if !self.check_recording("push_compute_constants") {
+            return;
+        }
+        self.raw.push_compute_constants(layout, offset, constants);
+    }
+
+    unsafe fn execute_commands<'a, T>(&mut self, cmd_buffers: T)
+    where
+        T: Iterator<Item = &'a CommandBuffer<B>>,
+    {
+        if !self.check_recording("execute_commands") {
+            return;
+        }
+        let mut forward = true;
+        if self.level != command::Level::Primary {
+            forward &= self.report(format_args!("secondary command buffer executes commands"));
+        }
+        let inside_pass = match self.pass {
+            Some(PassScope {
+                secondary_contents: false,
+                ..
+            }) => {
+                forward &= self.report(format_args!(
+                    "`execute_commands` is recorded in a subpass with inline contents"
+                ));
+                true
+            }
+            Some(_) => true,
+            None => false,
+        };
+
+        let mut raw_buffers = Vec::new();
+        for cmd_buf in cmd_buffers {
+            let label = self.shared.label(cmd_buf.id);
+            if cmd_buf.level != command::Level::Secondary {
+                forward &= self.report(format_args!("executes primary {}", label));
+            }
+            if cmd_buf.state != State::Executable {
+                forward &= self.report(format_args!(
+                    "executes {} in the {:?} state",
+                    label, cmd_buf.state
+                ));
+            }
+            let continues = cmd_buf
+                .flags
+                .contains(command::CommandBufferFlags::RENDER_PASS_CONTINUE);
+            if inside_pass != continues {
+                forward &= self.report(format_args!(
+                    "executes {} {} a render pass, but it was recorded {} `RENDER_PASS_CONTINUE`",
+                    label,
+                    if inside_pass { "inside" } else { "outside" },
+                    if continues { "with" } else { "without" },
+                ));
+            }
+            for (&id, resource_label) in cmd_buf.resources.iter() {
+                self.resources
+                    .entry(id)
+                    .or_insert_with(|| resource_label.clone());
+            }
+            raw_buffers.push(&cmd_buf.raw);
+        }
+        if forward {
+            self.raw.execute_commands(raw_buffers.into_iter());
+        }
+    }
+
+    unsafe fn insert_debug_marker(&mut self, name: &str, color: u32) {
+        self.raw.insert_debug_marker(name, color);
+    }
+
+    unsafe fn begin_debug_marker(&mut self, name: &str, color: u32) {
+        self.raw.begin_debug_marker(name, color);
+    }
+
+    unsafe fn end_debug_marker(&mut self) {
+        self.raw.end_debug_marker();
+    }
+
+    unsafe fn set_checkpoint(&mut self, label: &str) {
+        self.raw.set_checkpoint(label);
+    }
+}
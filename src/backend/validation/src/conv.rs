@@ -0,0 +1,189 @@
+//! Conversions of the generic HAL structures into the ones of the inner backend.
+
+use crate::Backend;
+
+use hal::{command, memory, pass, pso, query};
+
+pub fn map_barrier<B: hal::Backend>(barrier: memory::Barrier<Backend<B>>) -> memory::Barrier<B> {
+    match barrier {
+        memory::Barrier::AllBuffers(access) => memory::Barrier::AllBuffers(access),
+        memory::Barrier::AllImages(access) => memory::Barrier::AllImages(access),
+        memory::Barrier::Buffer {
+            states,
+            target,
+            range,
+            families,
+        } => memory::Barrier::Buffer {
+            states,
+            target: &target.raw,
+            range,
+            families,
+        },
+        memory::Barrier::Image {
+            states,
+            target,
+            range,
+            families,
+        } => memory::Barrier::Image {
+            states,
+            target,
+            range,
+            families,
+        },
+        memory::Barrier::Aliasing { before, after } => memory::Barrier::Aliasing {
+            before: before.map(map_aliased_resource),
+            after: after.map(map_aliased_resource),
+        },
+    }
+}
+
+fn map_aliased_resource<B: hal::Backend>(
+    resource: memory::AliasedResource<Backend<B>>,
+) -> memory::AliasedResource<B> {
+    match resource {
+        memory::AliasedResource::Buffer(buffer) => memory::AliasedResource::Buffer(&buffer.raw),
+        memory::AliasedResource::Image(image) => memory::AliasedResource::Image(image),
+    }
+}
+
+pub fn map_subpass<B: hal::Backend>(subpass: pass::Subpass<Backend<B>>) -> pass::Subpass<B> {
+    pass::Subpass {
+        index: subpass.index,
+        main_pass: &subpass.main_pass.raw,
+    }
+}
+
+pub fn map_inheritance_info<B: hal::Backend>(
+    info: command::CommandBufferInheritanceInfo<Backend<B>>,
+) -> command::CommandBufferInheritanceInfo<B> {
+    command::CommandBufferInheritanceInfo {
+        subpass: info.subpass.map(map_subpass),
+        framebuffer: info.framebuffer.map(|framebuffer| &framebuffer.raw),
+        occlusion_query_enable: info.occlusion_query_enable,
+        occlusion_query_flags: info.occlusion_query_flags,
+        pipeline_statistics: info.pipeline_statistics,
+    }
+}
+
+pub fn map_render_attachment_info<B: hal::Backend>(
+    info: command::RenderAttachmentInfo<Backend<B>>,
+) -> command::RenderAttachmentInfo<B> {
+    command::RenderAttachmentInfo {
+        image_view: info.image_view,
+        clear_value: info.clear_value,
+    }
+}
+
+pub fn map_rendering_attachment_info<'a, B: hal::Backend>(
+    info: &command::RenderingAttachmentInfo<'a, Backend<B>>,
+) -> command::RenderingAttachmentInfo<'a, B> {
+    command::RenderingAttachmentInfo {
+        image_view: info.image_view,
+        desc: info.desc.clone(),
+        clear_value: info.clear_value,
+    }
+}
+
+pub fn map_query<B: hal::Backend>(query: query::Query<Backend<B>>) -> query::Query<B> {
+    query::Query {
+        pool: query.pool,
+        id: query.id,
+    }
+}
+
+pub fn map_descriptor<B: hal::Backend>(
+    descriptor: pso::Descriptor<Backend<B>>,
+) -> pso::Descriptor<B> {
+    match descriptor {
+        pso::Descriptor::Sampler(sampler) => pso::Descriptor::Sampler(sampler),
+        pso::Descriptor::Image(view, layout) => pso::Descriptor::Image(view, layout),
+        pso::Descriptor::CombinedImageSampler(view, layout, sampler) => {
+            pso::Descriptor::CombinedImageSampler(view, layout, sampler)
+        }
+        pso::Descriptor::Buffer(buffer, sub) => pso::Descriptor::Buffer(&buffer.raw, sub),
+        pso::Descriptor::TexelBuffer(view) => pso::Descriptor::TexelBuffer(view),
+        pso::Descriptor::InlineUniformBlock(data) => pso::Descriptor::InlineUniformBlock(data),
+        pso::Descriptor::Null => pso::Descriptor::Null,
+    }
+}
+
+pub fn map_entry_point<'a, B: hal::Backend>(
+    entry_point: &pso::EntryPoint<'a, Backend<B>>,
+) -> pso::EntryPoint<'a, B> {
+    pso::EntryPoint {
+        entry: entry_point.entry,
+        module: &entry_point.module.raw,
+        specialization: entry_point.specialization.clone(),
+    }
+}
+
+pub fn map_base_pipeline<'a, P, R>(
+    parent: &pso::BasePipeline<'a, P>,
+    raw: impl Fn(&'a P) -> &'a R,
+) -> pso::BasePipeline<'a, R> {
+    match *parent {
+        pso::BasePipeline::Pipeline(pipeline) => pso::BasePipeline::Pipeline(raw(pipeline)),
+        pso::BasePipeline::Index(index) => pso::BasePipeline::Index(index),
+        pso::BasePipeline::None => pso::BasePipeline::None,
+    }
+}
+
+pub fn map_graphics_desc<'a, B: hal::Backend>(
+    desc: &pso::GraphicsPipelineDesc<'a, Backend<B>>,
+) -> pso::GraphicsPipelineDesc<'a, B> {
+    let primitive_assembler = match desc.primitive_assembler {
+        pso::PrimitiveAssemblerDesc::Vertex {
+            buffers,
+            attributes,
+            ref input_assembler,
+            ref vertex,
+            ref tessellation,
+            ref geometry,
+        } => pso::PrimitiveAssemblerDesc::Vertex {
+            buffers,
+            attributes,
+            input_assembler: input_assembler.clone(),
+            vertex: map_entry_point(vertex),
+            tessellation: tessellation
+                .as_ref()
+                .map(|&(ref hull, ref domain)| (map_entry_point(hull), map_entry_point(domain))),
+            geometry: geometry.as_ref().map(map_entry_point),
+        },
+        pso::PrimitiveAssemblerDesc::Mesh { ref task, ref mesh } => {
+            pso::PrimitiveAssemblerDesc::Mesh {
+                task: task.as_ref().map(map_entry_point),
+                mesh: map_entry_point(mesh),
+            }
+        }
+    };
+
+    pso::GraphicsPipelineDesc {
+        label: desc.label,
+        primitive_assembler,
+        rasterizer: desc.rasterizer,
+        fragment: desc.fragment.as_ref().map(map_entry_point),
+        blender: desc.blender.clone(),
+        depth_stencil: desc.depth_stencil,
+        multisampling: desc.multisampling.clone(),
+        baked_states: desc.baked_states.clone(),
+        layout: desc.layout,
+        subpass: pass::Subpass {
+            index: desc.subpass.index,
+            main_pass: &desc.subpass.main_pass.raw,
+        },
+        flags: desc.flags,
+        parent: map_base_pipeline(&desc.parent, |pipeline| &pipeline.raw),
+    }
+}
+
+pub fn map_compute_desc<'a, B: hal::Backend>(
+    desc: &pso::ComputePipelineDesc<'a, Backend<B>>,
+) -> pso::ComputePipelineDesc<'a, B> {
+    pso::ComputePipelineDesc {
+        label: desc.label,
+        shader: map_entry_point(&desc.shader),
+        layout: desc.layout,
+        flags: desc.flags,
+        parent: map_base_pipeline(&desc.parent, |pipeline| &pipeline.raw),
+    }
+}
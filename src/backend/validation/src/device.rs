@@ -0,0 +1,988 @@
+use crate::{
+    conv, Backend, Buffer, CommandBuffer, CommandPool, ComputePipeline, Framebuffer,
+    GraphicsPipeline, RenderPass, ShaderModule, Shared,
+};
+
+use hal::{
+    buffer, command, device, format, image, memory, pass, pool, pso, query, queue::QueueFamilyId,
+    Features, MemoryTypeId,
+};
+
+use gfx_auxil::reflect;
+
+#[cfg(unix)]
+use std::os::unix::io::RawFd;
+use std::{ops::Range, sync::Arc};
+
+#[derive(Debug)]
+pub struct DescriptorPool<B: hal::Backend> {
+    raw: B::DescriptorPool,
+}
+
+impl<B: hal::Backend> pso::DescriptorPool<Backend<B>> for DescriptorPool<B> {
+    unsafe fn allocate_one(
+        &mut self,
+        layout: &B::DescriptorSetLayout,
+    ) -> Result<B::DescriptorSet, pso::AllocationError> {
+        self.raw.allocate_one(layout)
+    }
+
+    unsafe fn allocate<'a, I, E>(
+        &mut self,
+        layouts: I,
+        list: &mut E,
+    ) -> Result<(), pso::AllocationError>
+    where
+        I: Iterator<Item = &'a B::DescriptorSetLayout>,
+        E: Extend<B::DescriptorSet>,
+    {
+        self.raw.allocate(layouts, list)
+    }
+
+    unsafe fn free<I>(&mut self, descriptor_sets: I)
+    where
+        I: Iterator<Item = B::DescriptorSet>,
+    {
+        self.raw.free(descriptor_sets)
+    }
+
+    unsafe fn reset(&mut self) {
+        self.raw.reset()
+    }
+}
+
+#[derive(Debug)]
+pub struct Device<B: hal::Backend> {
+    pub(crate) raw: B::Device,
+    features: Features,
+    shared: Arc<Shared>,
+}
+
+impl<B: hal::Backend> Device<B> {
+    pub(crate) fn new(raw: B::Device, features: Features, shared: Arc<Shared>) -> Self {
+        Device {
+            raw,
+            features,
+            shared,
+        }
+    }
+
+    fn check_pipeline_flags(&self, label: Option<&str>, flags: pso::PipelineCreationFlags) {
+        if flags.contains(pso::PipelineCreationFlags::FAIL_ON_PIPELINE_COMPILE_REQUIRED)
+            && !self
+                .features
+                .contains(Features::PIPELINE_CREATION_CACHE_CONTROL)
+        {
+            self.shared.report(format_args!(
+                "pipeline {:?} fails on a required compilation without PIPELINE_CREATION_CACHE_CONTROL",
+                label
+            ));
+        }
+    }
+
+    /// Match the inputs of each shader stage with the outputs of the previous one,
+    /// and the inputs of the vertex stage with the vertex attributes.
+    fn check_shader_interfaces(
+        &self,
+        desc: &pso::GraphicsPipelineDesc<Backend<B>>,
+    ) -> Result<(), pso::CreationError> {
+        let (attributes, mut stages) = match desc.primitive_assembler {
+            pso::PrimitiveAssemblerDesc::Vertex {
+                attributes,
+                ref vertex,
+                ref tessellation,
+                ref geometry,
+                ..
+            } => {
+                let mut stages = vec![vertex];
+                if let Some((ref hull, ref domain)) = *tessellation {
+                    stages.push(hull);
+                    stages.push(domain);
+                }
+                stages.extend(geometry.as_ref());
+                (Some(attributes), stages)
+            }
+            pso::PrimitiveAssemblerDesc::Mesh { ref mesh, .. } => (None, vec![mesh]),
+        };
+        stages.extend(desc.fragment.as_ref());
+
+        // the modules created from naga, or failing the reflection, are skipped
+        let interfaces = stages
+            .iter()
+            .map(|ep| {
+                ep.module.reflection.as_ref().and_then(|reflection| {
+                    reflection
+                        .entry_points
+                        .iter()
+                        .find(|reflected| reflected.name == ep.entry)
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut errors = Vec::new();
+        if let (Some(attributes), Some(vertex)) = (attributes, interfaces[0]) {
+            errors.extend(
+                reflect::check_vertex_attributes(&vertex.inputs, attributes)
+                    .into_iter()
+                    .map(|e| format!("{}: {}", vertex.name, e)),
+            );
+        }
+        for pair in interfaces.windows(2) {
+            if let (Some(prev), Some(next)) = (pair[0], pair[1]) {
+                errors.extend(
+                    reflect::check_interface(&prev.outputs, &next.inputs)
+                        .into_iter()
+                        .map(|e| format!("{}: {}", next.name, e)),
+                );
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            let message = errors.join(", ");
+            self.shared.report(format_args!(
+                "graphics pipeline {:?} has mismatched shader interfaces: {}",
+                desc.label, message
+            ));
+            Err(pso::CreationError::InterfaceMismatch(message))
+        }
+    }
+
+    fn check_host_copy_regions<I>(
+        &self,
+        data_len: usize,
+        regions: I,
+    ) -> Vec<command::BufferImageCopy>
+    where
+        I: Iterator<Item = command::BufferImageCopy>,
+    {
+        let regions = regions.collect::<Vec<_>>();
+        for region in regions.iter() {
+            if region.buffer_offset >= data_len as u64 {
+                self.shared.report(format_args!(
+                    "host image copy starts at offset {} of {} bytes of memory",
+                    region.buffer_offset, data_len
+                ));
+            }
+        }
+        regions
+    }
+}
+
+impl<B: hal::Backend> device::Device<Backend<B>> for Device<B> {
+    unsafe fn allocate_memory(
+        &self,
+        memory_type: MemoryTypeId,
+        size: u64,
+    ) -> Result<B::Memory, device::AllocationError> {
+        self.raw.allocate_memory(memory_type, size)
+    }
+
+    unsafe fn allocate_memory_with_priority(
+        &self,
+        memory_type: MemoryTypeId,
+        size: u64,
+        priority: memory::Priority,
+    ) -> Result<B::Memory, device::AllocationError> {
+        self.raw
+            .allocate_memory_with_priority(memory_type, size, priority)
+    }
+
+    unsafe fn set_memory_priority(&self, memory: &B::Memory, priority: memory::Priority) {
+        self.raw.set_memory_priority(memory, priority)
+    }
+
+    unsafe fn allocate_dedicated_memory(
+        &self,
+        memory_type: MemoryTypeId,
+        size: u64,
+        resource: memory::DedicatedResource<Backend<B>>,
+    ) -> Result<B::Memory, device::AllocationError> {
+        let raw_resource = match resource {
+            memory::DedicatedResource::Buffer(buffer) => {
+                memory::DedicatedResource::Buffer(&buffer.raw)
+            }
+            memory::DedicatedResource::Image(image) => memory::DedicatedResource::Image(image),
+        };
+        self.raw
+            .allocate_dedicated_memory(memory_type, size, raw_resource)
+    }
+
+    #[cfg(unix)]
+    unsafe fn allocate_exportable_memory(
+        &self,
+        memory_type: MemoryTypeId,
+        size: u64,
+    ) -> Result<B::Memory, device::ExternalMemoryError> {
+        self.raw.allocate_exportable_memory(memory_type, size)
+    }
+
+    #[cfg(unix)]
+    unsafe fn import_dma_buf(
+        &self,
+        memory_type: MemoryTypeId,
+        size: u64,
+        fd: RawFd,
+    ) -> Result<B::Memory, device::ExternalMemoryError> {
+        self.raw.import_dma_buf(memory_type, size, fd)
+    }
+
+    #[cfg(unix)]
+    unsafe fn export_dma_buf(
+        &self,
+        memory: &B::Memory,
+    ) -> Result<RawFd, device::ExternalMemoryError> {
+        self.raw.export_dma_buf(memory)
+    }
+
+    unsafe fn free_memory(&self, memory: B::Memory) {
+        self.raw.free_memory(memory)
+    }
+
+    unsafe fn create_command_pool(
+        &self,
+        family: QueueFamilyId,
+        create_flags: pool::CommandPoolCreateFlags,
+    ) -> Result<CommandPool<B>, device::OutOfMemory> {
+        let raw = self.raw.create_command_pool(family, create_flags)?;
+        Ok(CommandPool {
+            raw,
+            shared: Arc::clone(&self.shared),
+            family,
+            flags: create_flags,
+            features: self.features,
+        })
+    }
+
+    unsafe fn destroy_command_pool(&self, pool: CommandPool<B>) {
+        self.raw.destroy_command_pool(pool.raw)
+    }
+
+    unsafe fn create_render_pass<'a, Ia, Is, Id>(
+        &self,
+        attachments: Ia,
+        subpasses: Is,
+        dependencies: Id,
+    ) -> Result<RenderPass<B>, device::OutOfMemory>
+    where
+        Ia: Iterator<Item = pass::Attachment>,
+        Is: Iterator<Item = pass::SubpassDesc<'a>>,
+        Id: Iterator<Item = pass::SubpassDependency>,
+    {
+        let attachments = attachments.collect::<Vec<_>>();
+        let subpasses = subpasses.collect::<Vec<_>>();
+        if subpasses.is_empty() {
+            self.shared
+                .report(format_args!("render pass is created without subpasses"));
+        }
+        if !self.features.contains(Features::MULTIVIEW) {
+            for (index, subpass) in subpasses.iter().enumerate() {
+                if subpass.view_mask != 0 {
+                    self.shared.report(format_args!(
+                        "subpass {} has view mask {:#x} without MULTIVIEW",
+                        index, subpass.view_mask
+                    ));
+                }
+            }
+        }
+        if !self.features.contains(Features::SHADING_RATE_ATTACHMENT) {
+            for (index, subpass) in subpasses.iter().enumerate() {
+                if subpass.shading_rate.is_some() {
+                    self.shared.report(format_args!(
+                        "subpass {} has a shading rate attachment without SHADING_RATE_ATTACHMENT",
+                        index
+                    ));
+                }
+            }
+        }
+        let subpass_count = subpasses.len();
+        let attachment_count = attachments.len();
+        let raw = self.raw.create_render_pass(
+            attachments.into_iter(),
+            subpasses.into_iter(),
+            dependencies,
+        )?;
+        Ok(RenderPass {
+            raw,
+            id: self.shared.register("render pass"),
+            subpasses: subpass_count,
+            attachments: attachment_count,
+            rendering: false,
+        })
+    }
+
+    unsafe fn create_rendering_pass<I>(
+        &self,
+        colors: I,
+        depth_stencil: Option<pass::Attachment>,
+    ) -> Result<RenderPass<B>, device::OutOfMemory>
+    where
+        I: Iterator<Item = pass::Attachment>,
+    {
+        if !self.features.contains(Features::DYNAMIC_RENDERING) {
+            self.shared.report(format_args!(
+                "rendering pass is created without DYNAMIC_RENDERING"
+            ));
+        }
+        let colors = colors.collect::<Vec<_>>();
+        let attachment_count = colors.len() + depth_stencil.is_some() as usize;
+        let raw = self
+            .raw
+            .create_rendering_pass(colors.into_iter(), depth_stencil)?;
+        Ok(RenderPass {
+            raw,
+            id: self.shared.register("rendering pass"),
+            subpasses: 1,
+            attachments: attachment_count,
+            rendering: true,
+        })
+    }
+
+    unsafe fn destroy_render_pass(&self, rp: RenderPass<B>) {
+        self.shared.unregister(rp.id);
+        self.raw.destroy_render_pass(rp.raw)
+    }
+
+    unsafe fn create_pipeline_layout<'a, Is, Ic>(
+        &self,
+        set_layouts: Is,
+        push_constant: Ic,
+    ) -> Result<B::PipelineLayout, device::OutOfMemory>
+    where
+        Is: Iterator<Item = &'a B::DescriptorSetLayout>,
+        Ic: Iterator<Item = (pso::ShaderStageFlags, Range<u32>)>,
+    {
+        self.raw.create_pipeline_layout(set_layouts, push_constant)
+    }
+
+    unsafe fn destroy_pipeline_layout(&self, layout: B::PipelineLayout) {
+        self.raw.destroy_pipeline_layout(layout)
+    }
+
+    unsafe fn create_pipeline_cache(
+        &self,
+        data: Option<&[u8]>,
+    ) -> Result<B::PipelineCache, device::OutOfMemory> {
+        self.raw.create_pipeline_cache(data)
+    }
+
+    unsafe fn get_pipeline_cache_data(
+        &self,
+        cache: &B::PipelineCache,
+    ) -> Result<Vec<u8>, device::OutOfMemory> {
+        self.raw.get_pipeline_cache_data(cache)
+    }
+
+    unsafe fn merge_pipeline_caches<'a, I>(
+        &self,
+        target: &mut B::PipelineCache,
+        sources: I,
+    ) -> Result<(), device::OutOfMemory>
+    where
+        I: Iterator<Item = &'a B::PipelineCache>,
+    {
+        self.raw.merge_pipeline_caches(target, sources)
+    }
+
+    unsafe fn destroy_pipeline_cache(&self, cache: B::PipelineCache) {
+        self.raw.destroy_pipeline_cache(cache)
+    }
+
+    unsafe fn create_graphics_pipeline<'a>(
+        &self,
+        desc: &pso::GraphicsPipelineDesc<'a, Backend<B>>,
+        cache: Option<&B::PipelineCache>,
+    ) -> Result<GraphicsPipeline<B>, pso::CreationError> {
+        if desc.subpass.index as usize >= desc.subpass.main_pass.subpasses {
+            self.shared.report(format_args!(
+                "graphics pipeline {:?} targets subpass {} of {}, which only has {}",
+                desc.label,
+                desc.subpass.index,
+                self.shared.label(desc.subpass.main_pass.id),
+                desc.subpass.main_pass.subpasses
+            ));
+        }
+        self.check_pipeline_flags(desc.label, desc.flags);
+        if let Some(ref viewport) = desc.baked_states.viewport {
+            if !crate::is_depth_range_valid(self.features, &viewport.depth) {
+                self.shared.report(format_args!(
+                    "graphics pipeline {:?} has viewport depth range {:?} outside of [0, 1] without DEPTH_RANGE_UNRESTRICTED",
+                    desc.label, viewport.depth
+                ));
+            }
+        }
+        self.check_shader_interfaces(desc)?;
+        let raw = self
+            .raw
+            .create_graphics_pipeline(&conv::map_graphics_desc(desc), cache)?;
+        Ok(GraphicsPipeline {
+            raw,
+            id: self.shared.register("graphics pipeline"),
+        })
+    }
+
+    unsafe fn destroy_graphics_pipeline(&self, pipeline: GraphicsPipeline<B>) {
+        self.shared.unregister(pipeline.id);
+        self.raw.destroy_graphics_pipeline(pipeline.raw)
+    }
+
+    unsafe fn create_compute_pipeline<'a>(
+        &self,
+        desc: &pso::ComputePipelineDesc<'a, Backend<B>>,
+        cache: Option<&B::PipelineCache>,
+    ) -> Result<ComputePipeline<B>, pso::CreationError> {
+        self.check_pipeline_flags(desc.label, desc.flags);
+        let raw = self
+            .raw
+            .create_compute_pipeline(&conv::map_compute_desc(desc), cache)?;
+        Ok(ComputePipeline {
+            raw,
+            id: self.shared.register("compute pipeline"),
+        })
+    }
+
+    unsafe fn destroy_compute_pipeline(&self, pipeline: ComputePipeline<B>) {
+        self.shared.unregister(pipeline.id);
+        self.raw.destroy_compute_pipeline(pipeline.raw)
+    }
+
+    unsafe fn create_framebuffer<I>(
+        &self,
+        pass: &RenderPass<B>,
+        attachments: I,
+        extent: image::Extent,
+    ) -> Result<Framebuffer<B>, device::OutOfMemory>
+    where
+        I: Iterator<Item = image::FramebufferAttachment>,
+    {
+        let attachments = attachments.collect::<Vec<_>>();
+        if pass.rendering {
+            self.shared.report(format_args!(
+                "framebuffer is created for {}",
+                self.shared.label(pass.id)
+            ));
+        }
+        if attachments.len() != pass.attachments {
+            self.shared.report(format_args!(
+                "framebuffer is created with {} attachments for {} with {}",
+                attachments.len(),
+                self.shared.label(pass.id),
+                pass.attachments
+            ));
+        }
+        if extent.width == 0 || extent.height == 0 || extent.depth == 0 {
+            self.shared.report(format_args!(
+                "framebuffer is created with an empty extent {:?}",
+                extent
+            ));
+        }
+        let attachment_count = attachments.len();
+        let raw = self
+            .raw
+            .create_framebuffer(&pass.raw, attachments.into_iter(), extent)?;
+        Ok(Framebuffer {
+            raw,
+            id: self.shared.register("framebuffer"),
+            attachments: attachment_count,
+            extent,
+        })
+    }
+
+    unsafe fn destroy_framebuffer(&self, buf: Framebuffer<B>) {
+        self.shared.unregister(buf.id);
+        self.raw.destroy_framebuffer(buf.raw)
+    }
+
+    unsafe fn create_shader_module(
+        &self,
+        spirv: &[u32],
+    ) -> Result<ShaderModule<B>, device::ShaderError> {
+        let raw = self.raw.create_shader_module(spirv)?;
+        let reflection = if cfg!(debug_assertions) {
+            reflect::reflect_spirv(spirv)
+                .map_err(|e| warn!("Shader interfaces are not validated: {}", e))
+                .ok()
+        } else {
+            None
+        };
+        Ok(ShaderModule { raw, reflection })
+    }
+
+    unsafe fn create_shader_module_from_naga(
+        &self,
+        shader: device::NagaShader,
+    ) -> Result<ShaderModule<B>, (device::ShaderError, device::NagaShader)> {
+        let raw = self.raw.create_shader_module_from_naga(shader)?;
+        Ok(ShaderModule {
+            raw,
+            reflection: None,
+        })
+    }
+
+    unsafe fn destroy_shader_module(&self, shader: ShaderModule<B>) {
+        self.raw.destroy_shader_module(shader.raw)
+    }
+
+    unsafe fn create_buffer(
+        &self,
+        size: u64,
+        usage: buffer::Usage,
+    ) -> Result<Buffer<B>, buffer::CreationError> {
+        let raw = self.raw.create_buffer(size, usage)?;
+        Ok(Buffer {
+            raw,
+            id: self.shared.register("buffer"),
+        })
+    }
+
+    unsafe fn get_buffer_requirements(&self, buf: &Buffer<B>) -> memory::Requirements {
+        self.raw.get_buffer_requirements(&buf.raw)
+    }
+
+    unsafe fn bind_buffer_memory(
+        &self,
+        memory: &B::Memory,
+        offset: u64,
+        buf: &mut Buffer<B>,
+    ) -> Result<(), device::BindError> {
+        self.raw.bind_buffer_memory(memory, offset, &mut buf.raw)
+    }
+
+    unsafe fn destroy_buffer(&self, buffer: Buffer<B>) {
+        self.shared.unregister(buffer.id);
+        self.raw.destroy_buffer(buffer.raw)
+    }
+
+    unsafe fn create_buffer_view(
+        &self,
+        buf: &Buffer<B>,
+        fmt: Option<format::Format>,
+        range: buffer::SubRange,
+    ) -> Result<B::BufferView, buffer::ViewCreationError> {
+        self.raw.create_buffer_view(&buf.raw, fmt, range)
+    }
+
+    unsafe fn destroy_buffer_view(&self, view: B::BufferView) {
+        self.raw.destroy_buffer_view(view)
+    }
+
+    unsafe fn create_image(
+        &self,
+        kind: image::Kind,
+        mip_levels: image::Level,
+        format: format::Format,
+        tiling: image::Tiling,
+        usage: image::Usage,
+        view_caps: image::ViewCapabilities,
+    ) -> Result<B::Image, image::CreationError> {
+        self.raw
+            .create_image(kind, mip_levels, format, tiling, usage, view_caps)
+    }
+
+    unsafe fn create_image_with_drm_modifier(
+        &self,
+        kind: image::Kind,
+        format: format::Format,
+        tiling: image::DrmTiling,
+        usage: image::Usage,
+        view_caps: image::ViewCapabilities,
+    ) -> Result<B::Image, image::CreationError> {
+        self.raw
+            .create_image_with_drm_modifier(kind, format, tiling, usage, view_caps)
+    }
+
+    unsafe fn get_image_drm_modifier(&self, image: &B::Image) -> Option<image::DrmModifier> {
+        self.raw.get_image_drm_modifier(image)
+    }
+
+    unsafe fn get_image_requirements(&self, image: &B::Image) -> memory::Requirements {
+        self.raw.get_image_requirements(image)
+    }
+
+    unsafe fn get_image_subresource_footprint(
+        &self,
+        image: &B::Image,
+        subresource: image::Subresource,
+    ) -> image::SubresourceFootprint {
+        self.raw.get_image_subresource_footprint(image, subresource)
+    }
+
+    unsafe fn bind_image_memory(
+        &self,
+        memory: &B::Memory,
+        offset: u64,
+        image: &mut B::Image,
+    ) -> Result<(), device::BindError> {
+        self.raw.bind_image_memory(memory, offset, image)
+    }
+
+    unsafe fn destroy_image(&self, image: B::Image) {
+        self.raw.destroy_image(image)
+    }
+
+    unsafe fn create_image_view(
+        &self,
+        image: &B::Image,
+        view_kind: image::ViewKind,
+        format: format::Format,
+        swizzle: format::Swizzle,
+        range: image::SubresourceRange,
+    ) -> Result<B::ImageView, image::ViewCreationError> {
+        self.raw
+            .create_image_view(image, view_kind, format, swizzle, range)
+    }
+
+    unsafe fn create_image_view_with_min_lod(
+        &self,
+        image: &B::Image,
+        view_kind: image::ViewKind,
+        format: format::Format,
+        swizzle: format::Swizzle,
+        range: image::SubresourceRange,
+        min_lod: f32,
+    ) -> Result<B::ImageView, image::ViewCreationError> {
+        self.raw.create_image_view_with_min_lod(image, view_kind, format, swizzle, range, min_lod)
+    }
+
+    unsafe fn destroy_image_view(&self, view: B::ImageView) {
+        self.raw.destroy_image_view(view)
+    }
+
+    unsafe fn create_sampler(
+        &self,
+        desc: &image::SamplerDesc,
+    ) -> Result<B::Sampler, device::AllocationError> {
+        self.raw.create_sampler(desc)
+    }
+
+    unsafe fn destroy_sampler(&self, sampler: B::Sampler) {
+        self.raw.destroy_sampler(sampler)
+    }
+
+    unsafe fn create_sampler_ycbcr_conversion(
+        &self,
+        desc: &image::SamplerYcbcrConversionDesc,
+    ) -> Result<B::SamplerYcbcrConversion, image::CreationError> {
+        self.raw.create_sampler_ycbcr_conversion(desc)
+    }
+
+    unsafe fn destroy_sampler_ycbcr_conversion(&self, conversion: B::SamplerYcbcrConversion) {
+        self.raw.destroy_sampler_ycbcr_conversion(conversion)
+    }
+
+    unsafe fn create_ycbcr_sampler(
+        &self,
+        desc: &image::SamplerDesc,
+        conversion: &B::SamplerYcbcrConversion,
+    ) -> Result<B::Sampler, device::AllocationError> {
+        self.raw.create_ycbcr_sampler(desc, conversion)
+    }
+
+    unsafe fn create_ycbcr_image_view(
+        &self,
+        image: &B::Image,
+        view_kind: image::ViewKind,
+        range: image::SubresourceRange,
+        conversion: &B::SamplerYcbcrConversion,
+    ) -> Result<B::ImageView, image::ViewCreationError> {
+        self.raw
+            .create_ycbcr_image_view(image, view_kind, range, conversion)
+    }
+
+    unsafe fn create_descriptor_pool<I>(
+        &self,
+        max_sets: usize,
+        descriptor_ranges: I,
+        flags: pso::DescriptorPoolCreateFlags,
+    ) -> Result<DescriptorPool<B>, device::OutOfMemory>
+    where
+        I: Iterator<Item = pso::DescriptorRangeDesc>,
+    {
+        let raw = self
+            .raw
+            .create_descriptor_pool(max_sets, descriptor_ranges, flags)?;
+        Ok(DescriptorPool { raw })
+    }
+
+    unsafe fn destroy_descriptor_pool(&self, pool: DescriptorPool<B>) {
+        self.raw.destroy_descriptor_pool(pool.raw)
+    }
+
+    unsafe fn create_descriptor_set_layout<'a, I, J>(
+        &self,
+        bindings: I,
+        immutable_samplers: J,
+    ) -> Result<B::DescriptorSetLayout, device::OutOfMemory>
+    where
+        I: Iterator<Item = pso::DescriptorSetLayoutBinding>,
+        J: Iterator<Item = &'a B::Sampler>,
+    {
+        let bindings = bindings.collect::<Vec<_>>();
+        if !self.features.contains(Features::INLINE_UNIFORM_BLOCK) {
+            for binding in &bindings {
+                if binding.ty == pso::DescriptorType::InlineUniformBlock {
+                    self.shared.report(format_args!(
+                        "binding {} is an inline uniform block without INLINE_UNIFORM_BLOCK",
+                        binding.binding
+                    ));
+                }
+            }
+        }
+        self.raw
+            .create_descriptor_set_layout(bindings.into_iter(), immutable_samplers)
+    }
+
+    unsafe fn destroy_descriptor_set_layout(&self, layout: B::DescriptorSetLayout) {
+        self.raw.destroy_descriptor_set_layout(layout)
+    }
+
+    unsafe fn write_descriptor_set<'a, I>(&self, op: pso::DescriptorSetWrite<'a, Backend<B>, I>)
+    where
+        I: Iterator<Item = pso::Descriptor<'a, Backend<B>>>,
+    {
+        self.raw.write_descriptor_set(pso::DescriptorSetWrite {
+            set: op.set,
+            binding: op.binding,
+            array_offset: op.array_offset,
+            descriptors: op.descriptors.map(conv::map_descriptor),
+        })
+    }
+
+    unsafe fn copy_descriptor_set<'a>(&self, op: pso::DescriptorSetCopy<'a, Backend<B>>) {
+        self.raw.copy_descriptor_set(pso::DescriptorSetCopy {
+            src_set: op.src_set,
+            src_binding: op.src_binding,
+            src_array_offset: op.src_array_offset,
+            dst_set: op.dst_set,
+            dst_binding: op.dst_binding,
+            dst_array_offset: op.dst_array_offset,
+            count: op.count,
+        })
+    }
+
+    unsafe fn map_memory(
+        &self,
+        memory: &mut B::Memory,
+        segment: memory::Segment,
+    ) -> Result<*mut u8, device::MapError> {
+        self.raw.map_memory(memory, segment)
+    }
+
+    unsafe fn flush_mapped_memory_ranges<'a, I>(&self, ranges: I) -> Result<(), device::OutOfMemory>
+    where
+        I: Iterator<Item = (&'a B::Memory, memory::Segment)>,
+    {
+        self.raw.flush_mapped_memory_ranges(ranges)
+    }
+
+    unsafe fn invalidate_mapped_memory_ranges<'a, I>(
+        &self,
+        ranges: I,
+    ) -> Result<(), device::OutOfMemory>
+    where
+        I: Iterator<Item = (&'a B::Memory, memory::Segment)>,
+    {
+        self.raw.invalidate_mapped_memory_ranges(ranges)
+    }
+
+    unsafe fn unmap_memory(&self, memory: &mut B::Memory) {
+        self.raw.unmap_memory(memory)
+    }
+
+    unsafe fn copy_memory_to_image<I>(
+        &self,
+        data: &[u8],
+        image: &B::Image,
+        layout: image::Layout,
+        regions: I,
+    ) -> Result<(), device::MapError>
+    where
+        I: Iterator<Item = command::BufferImageCopy>,
+    {
+        match layout {
+            image::Layout::Undefined
+            | image::Layout::General
+            | image::Layout::TransferDstOptimal => {}
+            _ => {
+                self.shared.report(format_args!(
+                    "memory is copied to an image in the {:?} layout",
+                    layout
+                ));
+            }
+        }
+        let regions = self.check_host_copy_regions(data.len(), regions);
+        self.raw
+            .copy_memory_to_image(data, image, layout, regions.into_iter())
+    }
+
+    unsafe fn copy_image_to_memory<I>(
+        &self,
+        image: &B::Image,
+        layout: image::Layout,
+        data: &mut [u8],
+        regions: I,
+    ) -> Result<(), device::MapError>
+    where
+        I: Iterator<Item = command::BufferImageCopy>,
+    {
+        match layout {
+            image::Layout::General | image::Layout::TransferSrcOptimal => {}
+            _ => {
+                self.shared.report(format_args!(
+                    "memory is copied from an image in the {:?} layout",
+                    layout
+                ));
+            }
+        }
+        let regions = self.check_host_copy_regions(data.len(), regions);
+        self.raw
+            .copy_image_to_memory(image, layout, data, regions.into_iter())
+    }
+
+    fn create_semaphore(&self) -> Result<B::Semaphore, device::OutOfMemory> {
+        self.raw.create_semaphore()
+    }
+
+    unsafe fn destroy_semaphore(&self, semaphore: B::Semaphore) {
+        self.raw.destroy_semaphore(semaphore)
+    }
+
+    fn create_fence(&self, signaled: bool) -> Result<B::Fence, device::OutOfMemory> {
+        self.raw.create_fence(signaled)
+    }
+
+    unsafe fn reset_fence(&self, fence: &mut B::Fence) -> Result<(), device::OutOfMemory> {
+        self.raw.reset_fence(fence)
+    }
+
+    unsafe fn wait_for_fence(
+        &self,
+        fence: &B::Fence,
+        timeout_ns: u64,
+    ) -> Result<bool, device::WaitError> {
+        self.raw.wait_for_fence(fence, timeout_ns)
+    }
+
+    unsafe fn wait_for_fences<'a, I>(
+        &self,
+        fences: I,
+        wait: device::WaitFor,
+        timeout_ns: u64,
+    ) -> Result<bool, device::WaitError>
+    where
+        I: Iterator<Item = &'a B::Fence>,
+    {
+        self.raw.wait_for_fences(fences, wait, timeout_ns)
+    }
+
+    unsafe fn get_fence_status(&self, fence: &B::Fence) -> Result<bool, device::DeviceLost> {
+        self.raw.get_fence_status(fence)
+    }
+
+    unsafe fn destroy_fence(&self, fence: B::Fence) {
+        self.raw.destroy_fence(fence)
+    }
+
+    fn create_event(&self) -> Result<B::Event, device::OutOfMemory> {
+        self.raw.create_event()
+    }
+
+    unsafe fn destroy_event(&self, event: B::Event) {
+        self.raw.destroy_event(event)
+    }
+
+    unsafe fn get_event_status(&self, event: &B::Event) -> Result<bool, device::WaitError> {
+        self.raw.get_event_status(event)
+    }
+
+    unsafe fn set_event(&self, event: &mut B::Event) -> Result<(), device::OutOfMemory> {
+        self.raw.set_event(event)
+    }
+
+    unsafe fn reset_event(&self, event: &mut B::Event) -> Result<(), device::OutOfMemory> {
+        self.raw.reset_event(event)
+    }
+
+    unsafe fn create_query_pool(
+        &self,
+        ty: query::Type,
+        count: query::Id,
+    ) -> Result<B::QueryPool, query::CreationError> {
+        self.raw.create_query_pool(ty, count)
+    }
+
+    unsafe fn destroy_query_pool(&self, pool: B::QueryPool) {
+        self.raw.destroy_query_pool(pool)
+    }
+
+    unsafe fn get_query_pool_results(
+        &self,
+        pool: &B::QueryPool,
+        queries: Range<query::Id>,
+        data: &mut [u8],
+        stride: buffer::Stride,
+        flags: query::ResultFlags,
+    ) -> Result<bool, device::WaitError> {
+        self.raw
+            .get_query_pool_results(pool, queries, data, stride, flags)
+    }
+
+    fn wait_idle(&self) -> Result<(), device::WaitError> {
+        self.raw.wait_idle()
+    }
+
+    fn get_device_lost_reason(&self) -> Option<device::DeviceLostReason> {
+        self.raw.get_device_lost_reason()
+    }
+
+    unsafe fn set_image_name(&self, image: &mut B::Image, name: &str) {
+        self.raw.set_image_name(image, name)
+    }
+
+    unsafe fn set_buffer_name(&self, buffer: &mut Buffer<B>, name: &str) {
+        self.shared.set_name(buffer.id, name);
+        self.raw.set_buffer_name(&mut buffer.raw, name)
+    }
+
+    unsafe fn set_command_buffer_name(&self, command_buffer: &mut CommandBuffer<B>, name: &str) {
+        self.shared.set_name(command_buffer.id, name);
+        self.raw
+            .set_command_buffer_name(&mut command_buffer.raw, name)
+    }
+
+    unsafe fn set_semaphore_name(&self, semaphore: &mut B::Semaphore, name: &str) {
+        self.raw.set_semaphore_name(semaphore, name)
+    }
+
+    unsafe fn set_fence_name(&self, fence: &mut B::Fence, name: &str) {
+        self.raw.set_fence_name(fence, name)
+    }
+
+    unsafe fn set_framebuffer_name(&self, framebuffer: &mut Framebuffer<B>, name: &str) {
+        self.shared.set_name(framebuffer.id, name);
+        self.raw.set_framebuffer_name(&mut framebuffer.raw, name)
+    }
+
+    unsafe fn set_render_pass_name(&self, render_pass: &mut RenderPass<B>, name: &str) {
+        self.shared.set_name(render_pass.id, name);
+        self.raw.set_render_pass_name(&mut render_pass.raw, name)
+    }
+
+    unsafe fn set_descriptor_set_name(&self, descriptor_set: &mut B::DescriptorSet, name: &str) {
+        self.raw.set_descriptor_set_name(descriptor_set, name)
+    }
+
+    unsafe fn set_descriptor_set_layout_name(
+        &self,
+        descriptor_set_layout: &mut B::DescriptorSetLayout,
+        name: &str,
+    ) {
+        self.raw
+            .set_descriptor_set_layout_name(descriptor_set_layout, name)
+    }
+
+    unsafe fn set_pipeline_layout_name(&self, pipeline_layout: &mut B::PipelineLayout, name: &str) {
+        self.raw.set_pipeline_layout_name(pipeline_layout, name)
+    }
+}
@@ -0,0 +1,337 @@
+/*!
+Deterministic entry points for fuzzing the command encoding.
+
+A fuzz target creates a validating device with `Report::Skip` and `Execution::Null`,
+sets up a few resources described by `Resources`, and records the sequences of
+`Command` generated from the fuzzer input into a primary command buffer with `record`.
+The arguments are clamped to the valid ranges instead of relying on the caller,
+and the calls violating the HAL state machine are dropped by the validation layer,
+so the inner backend only receives the usage it's expected to handle.
+
+```ignore
+libfuzzer_sys::fuzz_target!(|commands: Vec<fuzz::Command>| unsafe {
+    fuzz::record(&mut cmd_buffer, &resources, &commands);
+    cmd_buffer.reset(false);
+});
+```
+!*/
+
+use crate::{Buffer, CommandBuffer, ComputePipeline, Framebuffer, GraphicsPipeline, RenderPass};
+use arbitrary::Arbitrary;
+use hal::{
+    buffer,
+    command::{self, CommandBuffer as _},
+    memory, pso, Features, IndexType, Limits,
+};
+
+use std::{iter, ops::Range};
+
+/// Maximum number of vertices, indices and instances of a draw call.
+const MAX_DRAW_COUNT: u32 = 1 << 12;
+/// Maximum number of work groups in each dimension of a dispatch.
+const MAX_DISPATCH_COUNT: u32 = 64;
+/// Maximum number of indirect draws of a single call.
+const MAX_INDIRECT_DRAW_COUNT: u32 = 16;
+
+/// Resources used by the recorded commands.
+///
+/// The pipelines are expected to have no vertex input and no descriptor sets,
+/// and the buffer to be usable for transfers, indices and indirect arguments.
+#[derive(Debug)]
+pub struct Resources<'a, B: hal::Backend> {
+    /// Render pass with a single subpass.
+    pub render_pass: &'a RenderPass<B>,
+    /// Framebuffer of the render pass.
+    pub framebuffer: &'a Framebuffer<B>,
+    /// Image views bound to the framebuffer, one for each attachment.
+    pub attachments: &'a [&'a B::ImageView],
+    /// Extent of the framebuffer.
+    pub extent: hal::image::Extent,
+    /// Graphics pipeline compatible with the render pass.
+    pub graphics_pipeline: &'a GraphicsPipeline<B>,
+    /// Compute pipeline.
+    pub compute_pipeline: &'a ComputePipeline<B>,
+    /// Buffer used by the transfer, index and indirect commands.
+    pub buffer: &'a Buffer<B>,
+    /// Size of `buffer`.
+    pub buffer_size: buffer::Offset,
+    /// Features enabled on the device.
+    pub features: Features,
+    /// Limits of the device.
+    pub limits: Limits,
+}
+
+/// A command generated from the fuzzer input.
+#[derive(Arbitrary, Clone, Debug)]
+pub enum Command {
+    Begin { one_time_submit: bool },
+    Finish,
+    Reset,
+    PipelineBarrier { src: u32, dst: u32 },
+    FillBuffer {
+        offset: u64,
+        size: Option<u64>,
+        data: u32,
+    },
+    UpdateBuffer { offset: u64, data: Vec<u8> },
+    CopyBuffer { src: u64, dst: u64, size: u64 },
+    BindIndexBuffer { offset: u64, wide: bool },
+    SetViewport { rect: [i16; 4], depth: [f32; 2] },
+    SetScissor { rect: [i16; 4] },
+    SetStencilReference { faces: u32, value: u32 },
+    SetBlendConstants([f32; 4]),
+    SetDepthBounds([f32; 2]),
+    SetLineWidth(f32),
+    SetDepthBias {
+        const_factor: f32,
+        clamp: f32,
+        slope_factor: f32,
+    },
+    BeginRenderPass { area: [i16; 4], clear: [f32; 4] },
+    NextSubpass,
+    EndRenderPass,
+    BindGraphicsPipeline,
+    BindComputePipeline,
+    Draw {
+        vertices: [u32; 2],
+        instances: [u32; 2],
+    },
+    DrawIndexed {
+        indices: [u32; 2],
+        base_vertex: i16,
+        instances: [u32; 2],
+    },
+    DrawIndirect { offset: u64, count: u32 },
+    Dispatch([u32; 3]),
+}
+
+fn finite(value: f32, range: Range<f32>) -> f32 {
+    if value.is_finite() {
+        value.max(range.start).min(range.end)
+    } else {
+        range.start
+    }
+}
+
+fn sorted(values: [u32; 2], max: u32) -> Range<u32> {
+    let start = values[0].min(values[1]) % (max + 1);
+    start..start + (values[0].max(values[1]) - values[0].min(values[1])) % (max + 1)
+}
+
+impl<'a, B: hal::Backend> Resources<'a, B> {
+    /// Clamp an offset into the buffer, aligned to 4 bytes.
+    fn offset(&self, offset: u64) -> buffer::Offset {
+        (offset % (self.buffer_size + 1)) & !3
+    }
+
+    /// Clamp a rectangle into the framebuffer.
+    fn rect(&self, rect: [i16; 4]) -> pso::Rect {
+        let width = self.extent.width.min(i16::MAX as u32) as i16;
+        let height = self.extent.height.min(i16::MAX as u32) as i16;
+        let x = rect[0].max(0) % width.max(1);
+        let y = rect[1].max(0) % height.max(1);
+        pso::Rect {
+            x,
+            y,
+            w: rect[2].max(1).min(width - x),
+            h: rect[3].max(1).min(height - y),
+        }
+    }
+}
+
+/// Record `commands` into the primary `cmd_buffer`, clamping their arguments.
+///
+/// The device of `cmd_buffer` has to report the errors with `Report::Skip`.
+pub unsafe fn record<B: hal::Backend>(
+    cmd_buffer: &mut CommandBuffer<B>,
+    resources: &Resources<B>,
+    commands: &[Command],
+) {
+    for command in commands {
+        record_command(cmd_buffer, resources, command);
+    }
+}
+
+unsafe fn record_command<B: hal::Backend>(
+    cmd_buffer: &mut CommandBuffer<B>,
+    res: &Resources<B>,
+    command: &Command,
+) {
+    match *command {
+        Command::Begin { one_time_submit } => {
+            let flags = if one_time_submit {
+                command::CommandBufferFlags::ONE_TIME_SUBMIT
+            } else {
+                command::CommandBufferFlags::empty()
+            };
+            cmd_buffer.begin_primary(flags);
+        }
+        Command::Finish => cmd_buffer.finish(),
+        Command::Reset => cmd_buffer.reset(false),
+        Command::PipelineBarrier { src, dst } => {
+            let src = pso::PipelineStage::from_bits_truncate(src);
+            let dst = pso::PipelineStage::from_bits_truncate(dst);
+            let barrier = memory::Barrier::AllBuffers(
+                buffer::Access::TRANSFER_WRITE..buffer::Access::TRANSFER_READ,
+            );
+            cmd_buffer.pipeline_barrier(
+                src | pso::PipelineStage::TOP_OF_PIPE..dst | pso::PipelineStage::BOTTOM_OF_PIPE,
+                memory::Dependencies::empty(),
+                iter::once(barrier),
+            );
+        }
+        Command::FillBuffer { offset, size, data } => {
+            let offset = res.offset(offset);
+            let size = size.map(|size| (size % (res.buffer_size - offset + 1)) & !3);
+            if size != Some(0) && offset != res.buffer_size {
+                cmd_buffer.fill_buffer(res.buffer, buffer::SubRange { offset, size }, data);
+            }
+        }
+        Command::UpdateBuffer { offset, ref data } => {
+            let offset = res.offset(offset);
+            let size = data.len().min((res.buffer_size - offset) as usize).min(1 << 16) & !3;
+            if size != 0 {
+                cmd_buffer.update_buffer(res.buffer, offset, &data[..size]);
+            }
+        }
+        Command::CopyBuffer { src, dst, size } => {
+            // Copy from the first half of the buffer into the second one, so that
+            // the regions never overlap.
+            let half = (res.buffer_size / 2) & !3;
+            let src = (src % (half + 1)) & !3;
+            let dst = (dst % (half + 1)) & !3;
+            let region = command::BufferCopy {
+                src,
+                dst: half + dst,
+                size: (size % (half - src.max(dst) + 1)) & !3,
+            };
+            if region.size != 0 {
+                cmd_buffer.copy_buffer(res.buffer, res.buffer, iter::once(region));
+            }
+        }
+        Command::BindIndexBuffer { offset, wide } => {
+            let sub = buffer::SubRange {
+                offset: res.offset(offset),
+                size: None,
+            };
+            let ty = if wide { IndexType::U32 } else { IndexType::U16 };
+            cmd_buffer.bind_index_buffer(res.buffer, sub, ty);
+        }
+        Command::SetViewport { rect, depth } => {
+            let depth = finite(depth[0], 0.0..1.0)..finite(depth[1], 0.0..1.0);
+            let viewport = pso::Viewport {
+                rect: res.rect(rect),
+                depth,
+            };
+            cmd_buffer.set_viewports(0, iter::once(viewport));
+        }
+        Command::SetScissor { rect } => {
+            cmd_buffer.set_scissors(0, iter::once(res.rect(rect)));
+        }
+        Command::SetStencilReference { faces, value } => {
+            cmd_buffer.set_stencil_reference(pso::Face::from_bits_truncate(faces), value);
+        }
+        Command::SetBlendConstants(color) => {
+            let color = [
+                finite(color[0], 0.0..1.0),
+                finite(color[1], 0.0..1.0),
+                finite(color[2], 0.0..1.0),
+                finite(color[3], 0.0..1.0),
+            ];
+            cmd_buffer.set_blend_constants(color);
+        }
+        Command::SetDepthBounds(bounds) => {
+            if res.features.contains(Features::DEPTH_BOUNDS) {
+                let min = finite(bounds[0].min(bounds[1]), 0.0..1.0);
+                let max = finite(bounds[0].max(bounds[1]), min..1.0);
+                cmd_buffer.set_depth_bounds(min..max);
+            }
+        }
+        Command::SetLineWidth(width) => {
+            let max = if res.features.contains(Features::LINE_WIDTH) {
+                8.0
+            } else {
+                1.0
+            };
+            cmd_buffer.set_line_width(finite(width, 1.0..max));
+        }
+        Command::SetDepthBias {
+            const_factor,
+            clamp,
+            slope_factor,
+        } => {
+            let range = -1.0e4..1.0e4;
+            let clamp = if res.features.contains(Features::DEPTH_BIAS_CLAMP) {
+                finite(clamp, range.clone())
+            } else {
+                0.0
+            };
+            cmd_buffer.set_depth_bias(pso::DepthBias {
+                const_factor: finite(const_factor, range.clone()),
+                clamp,
+                slope_factor: finite(slope_factor, range),
+            });
+        }
+        Command::BeginRenderPass { area, clear } => {
+            let clear_value = command::ClearValue {
+                color: command::ClearColor { float32: clear },
+            };
+            cmd_buffer.begin_render_pass(
+                res.render_pass,
+                res.framebuffer,
+                res.rect(area),
+                res.attachments
+                    .iter()
+                    .map(|&image_view| command::RenderAttachmentInfo {
+                        image_view,
+                        clear_value,
+                    }),
+                command::SubpassContents::Inline,
+            );
+        }
+        Command::NextSubpass => cmd_buffer.next_subpass(command::SubpassContents::Inline),
+        Command::EndRenderPass => cmd_buffer.end_render_pass(),
+        Command::BindGraphicsPipeline => cmd_buffer.bind_graphics_pipeline(res.graphics_pipeline),
+        Command::BindComputePipeline => cmd_buffer.bind_compute_pipeline(res.compute_pipeline),
+        Command::Draw {
+            vertices,
+            instances,
+        } => {
+            cmd_buffer.draw(
+                sorted(vertices, MAX_DRAW_COUNT),
+                sorted(instances, MAX_DRAW_COUNT),
+            );
+        }
+        Command::DrawIndexed {
+            indices,
+            base_vertex,
+            instances,
+        } => {
+            cmd_buffer.draw_indexed(
+                sorted(indices, MAX_DRAW_COUNT),
+                base_vertex as i32,
+                sorted(instances, MAX_DRAW_COUNT),
+            );
+        }
+        Command::DrawIndirect { offset, count } => {
+            let stride = 16;
+            let offset = res.offset(offset);
+            let max_count = ((res.buffer_size - offset) / stride) as u32;
+            let count = count % (max_count.min(MAX_INDIRECT_DRAW_COUNT) + 1);
+            let count = if res.features.contains(Features::MULTI_DRAW_INDIRECT) {
+                count
+            } else {
+                count.min(1)
+            };
+            cmd_buffer.draw_indirect(res.buffer, offset, count, stride as _);
+        }
+        Command::Dispatch(count) => {
+            let max = res.limits.max_compute_work_group_count;
+            cmd_buffer.dispatch([
+                count[0] % (max[0].min(MAX_DISPATCH_COUNT) + 1),
+                count[1] % (max[1].min(MAX_DISPATCH_COUNT) + 1),
+                count[2] % (max[2].min(MAX_DISPATCH_COUNT) + 1),
+            ]);
+        }
+    }
+}
@@ -0,0 +1,627 @@
+/*!
+# Validation layer for gfx-hal
+
+This backend wraps any other `Backend` and checks the HAL usage rules on the CPU
+before forwarding every call to it:
+  - command buffers follow the begin/finish/reset/submit state machine,
+  - commands are recorded inside or outside of render passes as required,
+  - resources referenced by a command buffer are still alive when it's submitted,
+  - pipeline barriers are checked against a few common mistakes,
+  - in debug builds, the inputs of each shader stage are matched with the outputs
+    of the previous one and with the vertex attributes, failing the graphics
+    pipeline creation with `CreationError::InterfaceMismatch` otherwise.
+
+Violations are reported according to the `Report` mode of the instance, and
+mention the object names given with `Device::set_*_name` where available.
+
+With `Execution::Null`, the submissions are validated as usual but the command
+buffers are not forwarded to the GPU: the queues only submit the semaphores and
+fences, which get signaled right away. This measures the CPU side of an engine
+without the GPU variance, and allows fuzzing the API usage at high frame rates.
+
+With `Report::Skip`, the calls violating the rules are dropped instead of being
+forwarded, so that the inner backend never sees the invalid usage. Together with the
+`fuzz` feature, which exposes the `fuzz` module, this allows fuzzing the command
+encoding of the backends with arbitrary inputs.
+
+Images and image views are forwarded without a wrapper, since swapchain images
+have to be borrowed as the inner backend types, so their lifetimes are not tracked.
+!*/
+
+#[macro_use]
+extern crate log;
+extern crate gfx_hal as hal;
+
+use hal::{
+    adapter, format, image,
+    queue::{self, QueueFamilyId},
+    window, Features, Limits,
+};
+use parking_lot::Mutex;
+
+use std::{
+    collections::HashMap,
+    fmt,
+    iter,
+    marker::PhantomData,
+    ops::Range,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+mod command;
+mod conv;
+mod device;
+#[cfg(feature = "fuzz")]
+pub mod fuzz;
+#[cfg(test)]
+mod tests;
+
+pub use crate::{
+    command::{CommandBuffer, CommandPool},
+    device::{DescriptorPool, Device},
+};
+
+/// Identifier of a tracked object, unique within a device.
+pub(crate) type Id = u64;
+
+/// How the validation errors are reported.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Report {
+    /// Log the error and carry on forwarding the call.
+    Log,
+    /// Panic at the call site of the offending operation.
+    Panic,
+    /// Log the error and drop the offending command, without forwarding it.
+    Skip,
+}
+
+impl Default for Report {
+    fn default() -> Self {
+        Report::Panic
+    }
+}
+
+/// How the validated submissions are executed.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Execution {
+    /// Forward the command buffers to the inner backend.
+    Gpu,
+    /// Skip the command buffers, only signaling the semaphores and fences.
+    Null,
+}
+
+impl Default for Execution {
+    fn default() -> Self {
+        Execution::Gpu
+    }
+}
+
+#[derive(Debug)]
+struct Object {
+    kind: &'static str,
+    name: Option<String>,
+}
+
+/// Registry of the live objects of a device.
+#[derive(Debug)]
+pub(crate) struct Shared {
+    report: Report,
+    next_id: AtomicU64,
+    objects: Mutex<HashMap<Id, Object>>,
+}
+
+/// Check if a viewport depth range is allowed by the enabled features.
+pub(crate) fn is_depth_range_valid(features: Features, depth: &Range<f32>) -> bool {
+    features.contains(Features::DEPTH_RANGE_UNRESTRICTED)
+        || ((0.0..=1.0).contains(&depth.start) && (0.0..=1.0).contains(&depth.end))
+}
+
+impl Shared {
+    fn new(report: Report) -> Self {
+        Shared {
+            report,
+            next_id: AtomicU64::new(0),
+            objects: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn register(&self, kind: &'static str) -> Id {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.objects.lock().insert(id, Object { kind, name: None });
+        id
+    }
+
+    pub fn unregister(&self, id: Id) {
+        self.objects.lock().remove(&id);
+    }
+
+    pub fn set_name(&self, id: Id, name: &str) {
+        if let Some(object) = self.objects.lock().get_mut(&id) {
+            object.name = Some(name.to_string());
+        }
+    }
+
+    pub fn is_alive(&self, id: Id) -> bool {
+        self.objects.lock().contains_key(&id)
+    }
+
+    /// Describe an object for the error messages.
+    pub fn label(&self, id: Id) -> String {
+        match self.objects.lock().get(&id) {
+            Some(&Object {
+                kind,
+                name: Some(ref name),
+            }) => format!("{} '{}'", kind, name),
+            Some(&Object { kind, name: None }) => format!("{} #{}", kind, id),
+            None => format!("destroyed object #{}", id),
+        }
+    }
+
+    /// Report an error, returning `true` if the offending call is still forwarded.
+    pub fn report(&self, message: fmt::Arguments) -> bool {
+        match self.report {
+            Report::Log => {
+                error!("Validation: {}", message);
+                true
+            }
+            Report::Panic => panic!("Validation: {}", message),
+            Report::Skip => {
+                error!("Validation: {}", message);
+                false
+            }
+        }
+    }
+}
+
+/// Validating wrapper around the backend `B`.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Backend<B>(PhantomData<B>);
+
+impl<B: hal::Backend> hal::Backend for Backend<B> {
+    type Instance = Instance<B>;
+    type PhysicalDevice = PhysicalDevice<B>;
+    type Device = Device<B>;
+    type Surface = Surface<B>;
+
+    type QueueFamily = B::QueueFamily;
+    type Queue = Queue<B>;
+    type CommandBuffer = CommandBuffer<B>;
+
+    type Memory = B::Memory;
+    type CommandPool = CommandPool<B>;
+
+    type ShaderModule = ShaderModule<B>;
+    type RenderPass = RenderPass<B>;
+    type Framebuffer = Framebuffer<B>;
+
+    type Buffer = Buffer<B>;
+    type BufferView = B::BufferView;
+    type Image = B::Image;
+    type ImageView = B::ImageView;
+    type Sampler = B::Sampler;
+    type SamplerYcbcrConversion = B::SamplerYcbcrConversion;
+
+    type ComputePipeline = ComputePipeline<B>;
+    type GraphicsPipeline = GraphicsPipeline<B>;
+    type PipelineCache = B::PipelineCache;
+    type PipelineLayout = B::PipelineLayout;
+    type DescriptorPool = DescriptorPool<B>;
+    type DescriptorSet = B::DescriptorSet;
+    type DescriptorSetLayout = B::DescriptorSetLayout;
+
+    type Fence = B::Fence;
+    type Semaphore = B::Semaphore;
+    type Event = B::Event;
+    type QueryPool = B::QueryPool;
+}
+
+#[derive(Debug)]
+pub struct Buffer<B: hal::Backend> {
+    pub(crate) raw: B::Buffer,
+    pub(crate) id: Id,
+}
+
+#[derive(Debug)]
+pub struct ShaderModule<B: hal::Backend> {
+    pub(crate) raw: B::ShaderModule,
+    /// Reflected from SPIR-V in debug builds.
+    pub(crate) reflection: Option<gfx_auxil::reflect::Reflection>,
+}
+
+#[derive(Debug)]
+pub struct RenderPass<B: hal::Backend> {
+    pub(crate) raw: B::RenderPass,
+    pub(crate) id: Id,
+    pub(crate) subpasses: usize,
+    pub(crate) attachments: usize,
+    /// Created by `create_rendering_pass`, only for the pipelines.
+    pub(crate) rendering: bool,
+}
+
+#[derive(Debug)]
+pub struct Framebuffer<B: hal::Backend> {
+    pub(crate) raw: B::Framebuffer,
+    pub(crate) id: Id,
+    pub(crate) attachments: usize,
+    pub(crate) extent: image::Extent,
+}
+
+#[derive(Debug)]
+pub struct GraphicsPipeline<B: hal::Backend> {
+    pub(crate) raw: B::GraphicsPipeline,
+    pub(crate) id: Id,
+}
+
+#[derive(Debug)]
+pub struct ComputePipeline<B: hal::Backend> {
+    pub(crate) raw: B::ComputePipeline,
+    pub(crate) id: Id,
+}
+
+pub struct Instance<B: hal::Backend> {
+    raw: B::Instance,
+    report: Report,
+    execution: Execution,
+}
+
+impl<B: hal::Backend> fmt::Debug for Instance<B> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("Instance")
+    }
+}
+
+impl<B: hal::Backend> Instance<B> {
+    /// Change how the validation errors are reported by the devices
+    /// opened from adapters enumerated after this call.
+    pub fn set_report(&mut self, report: Report) {
+        self.report = report;
+    }
+
+    /// Change how the submissions are executed by the queues
+    /// of the devices opened from adapters enumerated after this call.
+    pub fn set_execution(&mut self, execution: Execution) {
+        self.execution = execution;
+    }
+}
+
+impl<B: hal::Backend> hal::Instance<Backend<B>> for Instance<B> {
+    fn create(name: &str, version: u32) -> Result<Self, hal::UnsupportedBackend> {
+        Ok(Instance {
+            raw: B::Instance::create(name, version)?,
+            report: Report::default(),
+            execution: Execution::default(),
+        })
+    }
+
+    fn enumerate_adapters(&self) -> Vec<adapter::Adapter<Backend<B>>> {
+        self.raw
+            .enumerate_adapters()
+            .into_iter()
+            .map(|adapter| adapter::Adapter {
+                info: adapter.info,
+                physical_device: PhysicalDevice {
+                    raw: adapter.physical_device,
+                    report: self.report,
+                    execution: self.execution,
+                },
+                queue_families: adapter.queue_families,
+            })
+            .collect()
+    }
+
+    unsafe fn create_surface(
+        &self,
+        has_handle: &impl raw_window_handle::HasRawWindowHandle,
+    ) -> Result<Surface<B>, window::InitError> {
+        self.raw
+            .create_surface(has_handle)
+            .map(|raw| Surface { raw })
+    }
+
+    unsafe fn destroy_surface(&self, surface: Surface<B>) {
+        self.raw.destroy_surface(surface.raw);
+    }
+}
+
+#[derive(Debug)]
+pub struct PhysicalDevice<B: hal::Backend> {
+    raw: B::PhysicalDevice,
+    report: Report,
+    execution: Execution,
+}
+
+impl<B: hal::Backend> adapter::PhysicalDevice<Backend<B>> for PhysicalDevice<B> {
+    unsafe fn open(
+        &self,
+        families: &[(&B::QueueFamily, &[queue::QueuePriority])],
+        requested_features: Features,
+    ) -> Result<adapter::Gpu<Backend<B>>, hal::device::CreationError> {
+        let gpu = self.raw.open(families, requested_features)?;
+        let shared = Arc::new(Shared::new(self.report));
+        Ok(adapter::Gpu {
+            device: Device::new(gpu.device, requested_features, Arc::clone(&shared)),
+            queue_groups: gpu
+                .queue_groups
+                .into_iter()
+                .map(|group| {
+                    let family = group.family;
+                    queue::QueueGroup {
+                        family,
+                        queues: group
+                            .queues
+                            .into_iter()
+                            .map(|raw| Queue {
+                                raw,
+                                family,
+                                execution: self.execution,
+                                skipped_command_buffers: 0,
+                            })
+                            .collect(),
+                    }
+                })
+                .collect(),
+        })
+    }
+
+    fn format_properties(&self, format: Option<format::Format>) -> format::Properties {
+        self.raw.format_properties(format)
+    }
+
+    fn image_format_properties(
+        &self,
+        format: format::Format,
+        dimensions: u8,
+        tiling: image::Tiling,
+        usage: image::Usage,
+        view_caps: image::ViewCapabilities,
+    ) -> Option<image::FormatProperties> {
+        self.raw
+            .image_format_properties(format, dimensions, tiling, usage, view_caps)
+    }
+
+    fn drm_modifier_properties(&self, format: format::Format) -> Vec<image::DrmModifierProperties> {
+        self.raw.drm_modifier_properties(format)
+    }
+
+    fn cooperative_matrix_properties(&self) -> Vec<adapter::CooperativeMatrixProperties> {
+        self.raw.cooperative_matrix_properties()
+    }
+
+    fn memory_properties(&self) -> adapter::MemoryProperties {
+        self.raw.memory_properties()
+    }
+
+    fn features(&self) -> Features {
+        self.raw.features()
+    }
+
+    fn capabilities(&self) -> hal::Capabilities {
+        self.raw.capabilities()
+    }
+
+    fn limits(&self) -> Limits {
+        self.raw.limits()
+    }
+
+    fn is_valid_cache(&self, cache: &[u8]) -> bool {
+        self.raw.is_valid_cache(cache)
+    }
+}
+
+#[derive(Debug)]
+pub struct Queue<B: hal::Backend> {
+    raw: B::Queue,
+    family: QueueFamilyId,
+    execution: Execution,
+    skipped_command_buffers: u64,
+}
+
+impl<B: hal::Backend> Queue<B> {
+    /// Number of command buffers validated but not executed
+    /// by this queue in `Execution::Null` mode.
+    pub fn skipped_command_buffers(&self) -> u64 {
+        self.skipped_command_buffers
+    }
+}
+
+impl<B: hal::Backend> queue::Queue<Backend<B>> for Queue<B> {
+    unsafe fn submit<'a, Ic, Iw, Is>(
+        &mut self,
+        command_buffers: Ic,
+        wait_semaphores: Iw,
+        signal_semaphores: Is,
+        fence: Option<&mut B::Fence>,
+    ) -> Result<(), queue::SubmitError>
+    where
+        Ic: Iterator<Item = &'a CommandBuffer<B>>,
+        Iw: Iterator<Item = (&'a B::Semaphore, hal::pso::PipelineStage)>,
+        Is: Iterator<Item = &'a B::Semaphore>,
+    {
+        let family = self.family;
+        match self.execution {
+            Execution::Gpu => {
+                // Validate all of them before the submission, since the inner
+                // backend isn't required to go through the iterator.
+                let command_buffers = command_buffers
+                    .map(|cmd_buf| {
+                        cmd_buf.validate_submit(family, false);
+                        &cmd_buf.raw
+                    })
+                    .collect::<Vec<_>>();
+                self.raw.submit(
+                    command_buffers.into_iter(),
+                    wait_semaphores,
+                    signal_semaphores,
+                    fence,
+                )
+            }
+            Execution::Null => {
+                for cmd_buf in command_buffers {
+                    cmd_buf.validate_submit(family, false);
+                    self.skipped_command_buffers += 1;
+                }
+                self.raw.submit(
+                    iter::empty(),
+                    wait_semaphores,
+                    signal_semaphores,
+                    fence,
+                )
+            }
+        }
+    }
+
+    unsafe fn submit_protected<'a, Ic, Iw, Is>(
+        &mut self,
+        command_buffers: Ic,
+        wait_semaphores: Iw,
+        signal_semaphores: Is,
+        fence: Option<&mut B::Fence>,
+    ) -> Result<(), queue::SubmitError>
+    where
+        Ic: Iterator<Item = &'a CommandBuffer<B>>,
+        Iw: Iterator<Item = (&'a B::Semaphore, hal::pso::PipelineStage)>,
+        Is: Iterator<Item = &'a B::Semaphore>,
+    {
+        let family = self.family;
+        match self.execution {
+            Execution::Gpu => {
+                // Validate all of them before the submission, since the inner
+                // backend isn't required to go through the iterator.
+                let command_buffers = command_buffers
+                    .map(|cmd_buf| {
+                        cmd_buf.validate_submit(family, true);
+                        &cmd_buf.raw
+                    })
+                    .collect::<Vec<_>>();
+                self.raw.submit_protected(
+                    command_buffers.into_iter(),
+                    wait_semaphores,
+                    signal_semaphores,
+                    fence,
+                )
+            }
+            Execution::Null => {
+                for cmd_buf in command_buffers {
+                    cmd_buf.validate_submit(family, true);
+                    self.skipped_command_buffers += 1;
+                }
+                self.raw.submit_protected(
+                    iter::empty(),
+                    wait_semaphores,
+                    signal_semaphores,
+                    fence,
+                )
+            }
+        }
+    }
+
+    unsafe fn present(
+        &mut self,
+        surface: &mut Surface<B>,
+        image: <B::Surface as window::PresentationSurface<B>>::SwapchainImage,
+        wait_semaphore: Option<&mut B::Semaphore>,
+    ) -> Result<Option<window::Suboptimal>, window::PresentError> {
+        self.raw.present(&mut surface.raw, image, wait_semaphore)
+    }
+
+    unsafe fn present_with_time(
+        &mut self,
+        surface: &mut Surface<B>,
+        image: <B::Surface as window::PresentationSurface<B>>::SwapchainImage,
+        wait_semaphore: Option<&mut B::Semaphore>,
+        time: window::PresentTime,
+    ) -> Result<Option<window::Suboptimal>, window::PresentError> {
+        self.raw
+            .present_with_time(&mut surface.raw, image, wait_semaphore, time)
+    }
+
+    fn wait_idle(&mut self) -> Result<(), hal::device::WaitError> {
+        self.raw.wait_idle()
+    }
+
+    fn timestamp_period(&self) -> f32 {
+        self.raw.timestamp_period()
+    }
+}
+
+#[derive(Debug)]
+pub struct Surface<B: hal::Backend> {
+    raw: B::Surface,
+}
+
+impl<B: hal::Backend> window::Surface<Backend<B>> for Surface<B> {
+    fn supports_queue_family(&self, family: &B::QueueFamily) -> bool {
+        self.raw.supports_queue_family(family)
+    }
+
+    fn capabilities(&self, physical_device: &PhysicalDevice<B>) -> window::SurfaceCapabilities {
+        self.raw.capabilities(&physical_device.raw)
+    }
+
+    fn supported_formats(
+        &self,
+        physical_device: &PhysicalDevice<B>,
+    ) -> Option<Vec<format::Format>> {
+        self.raw.supported_formats(&physical_device.raw)
+    }
+}
+
+impl<B: hal::Backend> window::PresentationSurface<Backend<B>> for Surface<B> {
+    type SwapchainImage = <B::Surface as window::PresentationSurface<B>>::SwapchainImage;
+
+    unsafe fn configure_swapchain(
+        &mut self,
+        device: &Device<B>,
+        config: window::SwapchainConfig,
+    ) -> Result<(), window::SwapchainError> {
+        self.raw.configure_swapchain(&device.raw, config)
+    }
+
+    unsafe fn unconfigure_swapchain(&mut self, device: &Device<B>) {
+        self.raw.unconfigure_swapchain(&device.raw)
+    }
+
+    unsafe fn acquire_image(
+        &mut self,
+        timeout_ns: u64,
+    ) -> Result<(Self::SwapchainImage, Option<window::Suboptimal>), window::AcquireError> {
+        self.raw.acquire_image(timeout_ns)
+    }
+
+    unsafe fn set_present_mode(
+        &mut self,
+        mode: window::PresentMode,
+    ) -> Result<(), window::PresentModeIncompatible> {
+        self.raw.set_present_mode(mode)
+    }
+
+    unsafe fn acquire_full_screen_exclusive(
+        &mut self,
+    ) -> Result<(), window::FullScreenExclusiveError> {
+        self.raw.acquire_full_screen_exclusive()
+    }
+
+    unsafe fn release_full_screen_exclusive(
+        &mut self,
+    ) -> Result<(), window::FullScreenExclusiveError> {
+        self.raw.release_full_screen_exclusive()
+    }
+
+    unsafe fn get_refresh_duration(&mut self) -> Option<u64> {
+        self.raw.get_refresh_duration()
+    }
+
+    unsafe fn get_past_presentation_timing(&mut self) -> Vec<window::PresentationTiming> {
+        self.raw.get_past_presentation_timing()
+    }
+
+    unsafe fn set_hdr_metadata(
+        &mut self,
+        metadata: &window::HdrMetadata,
+    ) -> Result<(), window::HdrMetadataUnsupported> {
+        self.raw.set_hdr_metadata(metadata)
+    }
+}
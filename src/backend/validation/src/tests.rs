@@ -0,0 +1,575 @@
+//! Checks that the validation rules fire, over the empty backend, and that the
+//! offending calls are dropped with `Report::Skip`, over the software backend
+//! which makes the effect of the forwarded commands observable.
+
+use crate::{Backend, Buffer, CommandBuffer, Execution, Framebuffer, Instance, RenderPass, Report};
+use gfx_backend_empty as empty;
+use gfx_backend_software as software;
+use hal::{
+    adapter::PhysicalDevice as _,
+    buffer,
+    command::{self, CommandBuffer as _},
+    device::Device as _,
+    image, memory, pass,
+    pool::{CommandPool as _, CommandPoolCreateFlags},
+    pso,
+    queue::{Queue as _, QueueFamilyId, QueueGroup},
+    Instance as _, MemoryTypeId,
+};
+
+use std::iter;
+
+const EXTENT: image::Extent = image::Extent {
+    width: 4,
+    height: 4,
+    depth: 1,
+};
+
+struct Context<B: hal::Backend> {
+    device: crate::Device<B>,
+    queue_group: QueueGroup<Backend<B>>,
+    pool: crate::CommandPool<B>,
+    render_pass: RenderPass<B>,
+    framebuffer: Framebuffer<B>,
+    buffer: Buffer<B>,
+    memory: B::Memory,
+}
+
+impl<B: hal::Backend> Context<B> {
+    fn new(report: Report, execution: Execution, pool_flags: CommandPoolCreateFlags) -> Self {
+        let mut instance = Instance::<B>::create("validation", 1).unwrap();
+        instance.set_report(report);
+        instance.set_execution(execution);
+        let adapter = instance.enumerate_adapters().remove(0);
+        unsafe {
+            let mut gpu = adapter
+                .physical_device
+                .open(
+                    &[(&adapter.queue_families[0], &[1.0])],
+                    hal::Features::empty(),
+                )
+                .unwrap();
+            let device = gpu.device;
+            let queue_group = gpu.queue_groups.remove(0);
+            let pool = device
+                .create_command_pool(queue_group.family, pool_flags)
+                .unwrap();
+            let render_pass = device
+                .create_render_pass(iter::empty(), iter::once(subpass_desc()), iter::empty())
+                .unwrap();
+            let framebuffer = device
+                .create_framebuffer(&render_pass, iter::empty(), EXTENT)
+                .unwrap();
+            let mut buffer = device
+                .create_buffer(
+                    16,
+                    buffer::Usage::TRANSFER_SRC | buffer::Usage::TRANSFER_DST,
+                )
+                .unwrap();
+            let memory = device.allocate_memory(MemoryTypeId(0), 16).unwrap();
+            device.bind_buffer_memory(&memory, 0, &mut buffer).unwrap();
+            Context {
+                device,
+                queue_group,
+                pool,
+                render_pass,
+                framebuffer,
+                buffer,
+                memory,
+            }
+        }
+    }
+
+    fn command_buffer(&mut self) -> CommandBuffer<B> {
+        unsafe { self.pool.allocate_one(command::Level::Primary) }
+    }
+
+    unsafe fn begin_render_pass(&self, cmd_buffer: &mut CommandBuffer<B>) {
+        cmd_buffer.begin_render_pass(
+            &self.render_pass,
+            &self.framebuffer,
+            pso::Rect {
+                x: 0,
+                y: 0,
+                w: EXTENT.width as i16,
+                h: EXTENT.height as i16,
+            },
+            iter::empty(),
+            command::SubpassContents::Inline,
+        );
+    }
+
+    unsafe fn submit(&mut self, cmd_buffer: &CommandBuffer<B>) {
+        self.queue_group.queues[0]
+            .submit(iter::once(cmd_buffer), iter::empty(), iter::empty(), None)
+            .unwrap();
+    }
+
+    /// Create a graphics pipeline for `subpass` of the render pass,
+    /// with the vertex and fragment stages from the given SPIR-V modules.
+    unsafe fn create_pipeline(
+        &self,
+        vertex: &[u32],
+        fragment: &[u32],
+        subpass: pass::SubpassId,
+    ) -> Result<crate::GraphicsPipeline<B>, pso::CreationError> {
+        let vertex = self.device.create_shader_module(vertex).unwrap();
+        let fragment = self.device.create_shader_module(fragment).unwrap();
+        let layout = self
+            .device
+            .create_pipeline_layout(iter::empty(), iter::empty())
+            .unwrap();
+        let entry = |module| pso::EntryPoint {
+            entry: "main",
+            module,
+            specialization: pso::Specialization::default(),
+        };
+        let desc = pso::GraphicsPipelineDesc::new(
+            pso::PrimitiveAssemblerDesc::Vertex {
+                buffers: &[],
+                attributes: &[],
+                input_assembler: pso::InputAssemblerDesc::new(pso::Primitive::TriangleList),
+                vertex: entry(&vertex),
+                tessellation: None,
+                geometry: None,
+            },
+            pso::Rasterizer::FILL,
+            Some(entry(&fragment)),
+            &layout,
+            pass::Subpass {
+                index: subpass,
+                main_pass: &self.render_pass,
+            },
+        );
+        self.device.create_graphics_pipeline(&desc, None)
+    }
+}
+
+fn subpass_desc() -> pass::SubpassDesc<'static> {
+    pass::SubpassDesc {
+        colors: &[],
+        depth_stencil: None,
+        inputs: &[],
+        resolves: &[],
+        preserves: &[],
+        view_mask: 0,
+        shading_rate: None,
+    }
+}
+
+/// Build a SPIR-V module declaring a `main` entry point with a single
+/// interface variable of `components` floats at `location`.
+///
+/// The module has no function bodies, which is enough for the reflection.
+fn interface_module(model: u32, storage: u32, location: u32, components: u32) -> Vec<u32> {
+    const ENTRY_POINT: u32 = 15;
+    const TYPE_FLOAT: u32 = 22;
+    const TYPE_VECTOR: u32 = 23;
+    const TYPE_POINTER: u32 = 32;
+    const VARIABLE: u32 = 59;
+    const DECORATE: u32 = 71;
+    const LOCATION: u32 = 30;
+    let instruction = |opcode: u32, operands: &[u32]| {
+        iter::once(((operands.len() as u32 + 1) << 16) | opcode).chain(operands.to_vec())
+    };
+    let main = u32::from_le_bytes(*b"main");
+    vec![0x0723_0203, 0x0001_0000, 0, 6, 0]
+        .into_iter()
+        .chain(instruction(ENTRY_POINT, &[model, 1, main, 0, 5]))
+        .chain(instruction(DECORATE, &[5, LOCATION, location]))
+        .chain(instruction(TYPE_FLOAT, &[2, 32]))
+        .chain(instruction(TYPE_VECTOR, &[3, 2, components]))
+        .chain(instruction(TYPE_POINTER, &[4, storage, 3]))
+        .chain(instruction(VARIABLE, &[4, 5, storage]))
+        .collect()
+}
+
+fn empty_context() -> Context<empty::Backend> {
+    Context::new(
+        Report::Panic,
+        Execution::Gpu,
+        CommandPoolCreateFlags::RESET_INDIVIDUAL,
+    )
+}
+
+#[test]
+#[should_panic(expected = "`fill_buffer` is recorded in the Initial state")]
+fn command_outside_of_recording() {
+    let mut context = empty_context();
+    let mut cmd_buffer = context.command_buffer();
+    unsafe { cmd_buffer.fill_buffer(&context.buffer, buffer::SubRange::WHOLE, 0) };
+}
+
+#[test]
+#[should_panic(expected = "begins while already recording")]
+fn begin_while_recording() {
+    let mut context = empty_context();
+    let mut cmd_buffer = context.command_buffer();
+    unsafe {
+        cmd_buffer.begin_primary(command::CommandBufferFlags::empty());
+        cmd_buffer.begin_primary(command::CommandBufferFlags::empty());
+    }
+}
+
+#[test]
+#[should_panic(expected = "the pool lacks `RESET_INDIVIDUAL`")]
+fn reset_without_pool_flag() {
+    let mut context = Context::<empty::Backend>::new(
+        Report::Panic,
+        Execution::Gpu,
+        CommandPoolCreateFlags::empty(),
+    );
+    let mut cmd_buffer = context.command_buffer();
+    unsafe {
+        cmd_buffer.begin_primary(command::CommandBufferFlags::empty());
+        cmd_buffer.finish();
+        cmd_buffer.reset(false);
+    }
+}
+
+#[test]
+#[should_panic(expected = "`fill_buffer` is recorded inside of render pass")]
+fn transfer_inside_of_render_pass() {
+    let mut context = empty_context();
+    let mut cmd_buffer = context.command_buffer();
+    unsafe {
+        cmd_buffer.begin_primary(command::CommandBufferFlags::empty());
+        context.begin_render_pass(&mut cmd_buffer);
+        cmd_buffer.fill_buffer(&context.buffer, buffer::SubRange::WHOLE, 0);
+    }
+}
+
+#[test]
+#[should_panic(expected = "`draw` is recorded outside of a render pass")]
+fn draw_outside_of_render_pass() {
+    let mut context = empty_context();
+    let mut cmd_buffer = context.command_buffer();
+    unsafe {
+        cmd_buffer.begin_primary(command::CommandBufferFlags::empty());
+        cmd_buffer.draw(0..3, 0..1);
+    }
+}
+
+#[test]
+#[should_panic(expected = "`draw` is recorded without a bound graphics pipeline")]
+fn draw_without_pipeline() {
+    let mut context = empty_context();
+    let mut cmd_buffer = context.command_buffer();
+    unsafe {
+        cmd_buffer.begin_primary(command::CommandBufferFlags::empty());
+        context.begin_render_pass(&mut cmd_buffer);
+        cmd_buffer.draw(0..3, 0..1);
+    }
+}
+
+#[test]
+#[should_panic(expected = "`dispatch` is recorded without a bound compute pipeline")]
+fn dispatch_without_pipeline() {
+    let mut context = empty_context();
+    let mut cmd_buffer = context.command_buffer();
+    unsafe {
+        cmd_buffer.begin_primary(command::CommandBufferFlags::empty());
+        cmd_buffer.dispatch([1, 1, 1]);
+    }
+}
+
+#[test]
+#[should_panic(expected = "finishes inside of render pass")]
+fn finish_inside_of_render_pass() {
+    let mut context = empty_context();
+    let mut cmd_buffer = context.command_buffer();
+    unsafe {
+        cmd_buffer.begin_primary(command::CommandBufferFlags::empty());
+        context.begin_render_pass(&mut cmd_buffer);
+        cmd_buffer.finish();
+    }
+}
+
+#[test]
+#[should_panic(expected = "`pipeline_barrier` has an empty stage mask")]
+fn barrier_with_empty_stages() {
+    let mut context = empty_context();
+    let mut cmd_buffer = context.command_buffer();
+    unsafe {
+        cmd_buffer.begin_primary(command::CommandBufferFlags::empty());
+        cmd_buffer.pipeline_barrier(
+            pso::PipelineStage::empty()..pso::PipelineStage::TRANSFER,
+            memory::Dependencies::empty(),
+            iter::once(memory::Barrier::AllBuffers(
+                buffer::Access::TRANSFER_WRITE..buffer::Access::TRANSFER_READ,
+            )),
+        );
+    }
+}
+
+#[test]
+#[should_panic(expected = "ownership transfer from queue family")]
+fn ownership_transfer_of_other_families() {
+    let mut context = empty_context();
+    let mut cmd_buffer = context.command_buffer();
+    unsafe {
+        cmd_buffer.begin_primary(command::CommandBufferFlags::empty());
+        cmd_buffer.pipeline_barrier(
+            pso::PipelineStage::TRANSFER..pso::PipelineStage::TRANSFER,
+            memory::Dependencies::empty(),
+            iter::once(memory::Barrier::Buffer {
+                states: buffer::Access::TRANSFER_WRITE..buffer::Access::TRANSFER_READ,
+                target: &context.buffer,
+                range: buffer::SubRange::WHOLE,
+                families: Some(QueueFamilyId(5)..QueueFamilyId(6)),
+            }),
+        );
+    }
+}
+
+#[test]
+#[should_panic(expected = "submitted in the Recording state")]
+fn submit_while_recording() {
+    let mut context = empty_context();
+    let mut cmd_buffer = context.command_buffer();
+    unsafe {
+        cmd_buffer.begin_primary(command::CommandBufferFlags::empty());
+        context.submit(&cmd_buffer);
+    }
+}
+
+#[test]
+#[should_panic(expected = "one-time-submit command buffer is submitted again")]
+fn one_time_submit_twice() {
+    let mut context = empty_context();
+    let mut cmd_buffer = context.command_buffer();
+    unsafe {
+        cmd_buffer.begin_primary(command::CommandBufferFlags::ONE_TIME_SUBMIT);
+        cmd_buffer.finish();
+        context.submit(&cmd_buffer);
+        context.submit(&cmd_buffer);
+    }
+}
+
+#[test]
+#[should_panic(expected = "references destroyed buffer 'staging'")]
+fn submit_with_destroyed_resource() {
+    let mut context = Context::<software::Backend>::new(
+        Report::Panic,
+        Execution::Gpu,
+        CommandPoolCreateFlags::empty(),
+    );
+    let mut cmd_buffer = context.command_buffer();
+    unsafe {
+        let mut buffer = context
+            .device
+            .create_buffer(16, buffer::Usage::TRANSFER_DST)
+            .unwrap();
+        context
+            .device
+            .bind_buffer_memory(&context.memory, 0, &mut buffer)
+            .unwrap();
+        context.device.set_buffer_name(&mut buffer, "staging");
+        cmd_buffer.begin_primary(command::CommandBufferFlags::empty());
+        cmd_buffer.fill_buffer(&buffer, buffer::SubRange::WHOLE, 0);
+        cmd_buffer.finish();
+        context.device.destroy_buffer(buffer);
+        context.submit(&cmd_buffer);
+    }
+}
+
+#[test]
+#[should_panic(expected = "viewport depth range 0.0..2.0 is outside of [0, 1]")]
+fn viewport_depth_out_of_range() {
+    let mut context = empty_context();
+    let mut cmd_buffer = context.command_buffer();
+    unsafe {
+        cmd_buffer.begin_primary(command::CommandBufferFlags::empty());
+        cmd_buffer.set_viewports(
+            0,
+            iter::once(pso::Viewport {
+                rect: pso::Rect {
+                    x: 0,
+                    y: 0,
+                    w: 4,
+                    h: 4,
+                },
+                depth: 0.0..2.0,
+            }),
+        );
+    }
+}
+
+#[test]
+#[should_panic(expected = "render pass is created without subpasses")]
+fn render_pass_without_subpasses() {
+    let context = empty_context();
+    unsafe {
+        let _ = context
+            .device
+            .create_render_pass(iter::empty(), iter::empty(), iter::empty());
+    }
+}
+
+#[test]
+#[should_panic(expected = "binding 2 is an inline uniform block without INLINE_UNIFORM_BLOCK")]
+fn inline_uniform_block_without_feature() {
+    let context = empty_context();
+    unsafe {
+        let _ = context.device.create_descriptor_set_layout(
+            iter::once(pso::DescriptorSetLayoutBinding {
+                binding: 2,
+                ty: pso::DescriptorType::InlineUniformBlock,
+                count: 16,
+                stage_flags: pso::ShaderStageFlags::FRAGMENT,
+                immutable_samplers: false,
+            }),
+            iter::empty(),
+        );
+    }
+}
+
+#[test]
+#[should_panic(expected = "subpass 0 has view mask 0x3 without MULTIVIEW")]
+fn multiview_without_feature() {
+    let context = empty_context();
+    unsafe {
+        let _ = context.device.create_render_pass(
+            iter::empty(),
+            iter::once(pass::SubpassDesc {
+                view_mask: 0x3,
+                ..subpass_desc()
+            }),
+            iter::empty(),
+        );
+    }
+}
+
+#[test]
+#[should_panic(
+    expected = "subpass 0 has a shading rate attachment without SHADING_RATE_ATTACHMENT"
+)]
+fn shading_rate_attachment_without_feature() {
+    let context = empty_context();
+    unsafe {
+        let _ = context.device.create_render_pass(
+            iter::empty(),
+            iter::once(pass::SubpassDesc {
+                shading_rate: Some(&(0, image::Layout::General)),
+                ..subpass_desc()
+            }),
+            iter::empty(),
+        );
+    }
+}
+
+#[test]
+#[should_panic(expected = "set_shading_rate without SHADING_RATE")]
+fn shading_rate_without_feature() {
+    let mut context = empty_context();
+    let mut cmd_buffer = context.command_buffer();
+    unsafe {
+        cmd_buffer.begin_primary(command::CommandBufferFlags::empty());
+        cmd_buffer.set_shading_rate(
+            pso::ShadingRate {
+                width: 2,
+                height: 2,
+            },
+            [pso::ShadingRateCombiner::Keep; 2],
+        );
+    }
+}
+
+#[test]
+#[should_panic(expected = "rendering pass is created without DYNAMIC_RENDERING")]
+fn rendering_pass_without_feature() {
+    let context = empty_context();
+    unsafe {
+        let _ = context.device.create_rendering_pass(iter::empty(), None);
+    }
+}
+
+#[test]
+#[should_panic(expected = "targets subpass 1 of render pass")]
+fn pipeline_of_missing_subpass() {
+    let context = empty_context();
+    unsafe {
+        let _ = context.create_pipeline(&[], &[], 1);
+    }
+}
+
+#[test]
+fn shader_interface_mismatch() {
+    const VERTEX: u32 = 0;
+    const FRAGMENT: u32 = 4;
+    const INPUT: u32 = 1;
+    const OUTPUT: u32 = 3;
+    let context = Context::<empty::Backend>::new(
+        Report::Log,
+        Execution::Gpu,
+        CommandPoolCreateFlags::empty(),
+    );
+    let vertex = interface_module(VERTEX, OUTPUT, 0, 4);
+    unsafe {
+        let fragment = interface_module(FRAGMENT, INPUT, 0, 4);
+        assert!(context.create_pipeline(&vertex, &fragment, 0).is_ok());
+        let fragment = interface_module(FRAGMENT, INPUT, 1, 4);
+        match context.create_pipeline(&vertex, &fragment, 0) {
+            Err(pso::CreationError::InterfaceMismatch(message)) => assert!(
+                message.contains("location 1 is read but not written"),
+                "{}",
+                message
+            ),
+            other => panic!("Unexpected pipeline creation result {:?}", other),
+        }
+    }
+}
+
+/// Record a fill of the buffer, followed by the invalid calls `begin`,
+/// which resets the command buffer, and another fill inside of a render pass.
+/// Returns the contents of the buffer after the submission.
+fn record_invalid_calls(report: Report, execution: Execution) -> Vec<u8> {
+    let mut context = Context::<software::Backend>::new(
+        report,
+        execution,
+        CommandPoolCreateFlags::RESET_INDIVIDUAL,
+    );
+    let mut cmd_buffer = context.command_buffer();
+    unsafe {
+        cmd_buffer.begin_primary(command::CommandBufferFlags::empty());
+        cmd_buffer.fill_buffer(&context.buffer, buffer::SubRange::WHOLE, 0x0101_0101);
+        cmd_buffer.begin_primary(command::CommandBufferFlags::empty());
+        context.begin_render_pass(&mut cmd_buffer);
+        cmd_buffer.fill_buffer(&context.buffer, buffer::SubRange::WHOLE, 0x0202_0202);
+        cmd_buffer.end_render_pass();
+        cmd_buffer.finish();
+        context.submit(&cmd_buffer);
+
+        let ptr = context
+            .device
+            .map_memory(&mut context.memory, memory::Segment::ALL)
+            .unwrap();
+        let data = std::slice::from_raw_parts(ptr, 16).to_vec();
+        context.device.unmap_memory(&mut context.memory);
+        data
+    }
+}
+
+#[test]
+fn log_forwards_invalid_calls() {
+    assert_eq!(
+        record_invalid_calls(Report::Log, Execution::Gpu),
+        vec![2; 16]
+    );
+}
+
+#[test]
+fn skip_drops_invalid_calls() {
+    assert_eq!(
+        record_invalid_calls(Report::Skip, Execution::Gpu),
+        vec![1; 16]
+    );
+}
+
+#[test]
+fn null_execution_skips_command_buffers() {
+    assert_eq!(
+        record_invalid_calls(Report::Skip, Execution::Null),
+        vec![0; 16]
+    );
+}
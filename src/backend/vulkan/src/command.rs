@@ -1,15 +1,17 @@
 use ash::{version::DeviceV1_0, vk};
 use smallvec::SmallVec;
-use std::{collections::hash_map::Entry, ffi::CString, mem, ops::Range, slice, sync::Arc};
+use std::{ffi::CString, mem, ops::Range, slice, sync::Arc};
 
 use inplace_it::inplace_or_alloc_from_iter;
 
-use crate::{conv, native as n, Backend, DebugMessenger, RawDevice, ROUGH_MAX_ATTACHMENT_COUNT};
+use crate::{
+    conv, ext, native as n, Backend, DebugMessenger, RawDevice, ROUGH_MAX_ATTACHMENT_COUNT,
+};
 use hal::{
     buffer, command as com,
     format::Aspects,
-    image::{Filter, Layout, SubresourceRange},
-    memory, pso, query, DrawCount, IndexCount, IndexType, InstanceCount, TaskCount, VertexCount,
+    image::{self, Filter, Layout, SubresourceRange},
+    memory, pass, pso, query, DrawCount, IndexCount, IndexType, InstanceCount, TaskCount, VertexCount,
     VertexOffset, WorkGroupCount,
 };
 
@@ -17,6 +19,18 @@ use hal::{
 pub struct CommandBuffer {
     pub raw: vk::CommandBuffer,
     pub device: Arc<RawDevice>,
+    /// Transitions of the attachments to their final layouts, recorded by
+    /// `end_rendering` with `VK_KHR_dynamic_rendering`.
+    pub(crate) rendering_barriers: Vec<LayoutTransition>,
+}
+
+/// Layout transition of an image, kept as plain data until it's recorded.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct LayoutTransition {
+    image: vk::Image,
+    old_layout: vk::ImageLayout,
+    new_layout: vk::ImageLayout,
+    range: vk::ImageSubresourceRange,
 }
 
 fn debug_color(color: u32) -> [f32; 4] {
@@ -132,6 +146,18 @@ where
                         .build(),
                 );
             }
+            // Aliased resources are synchronized like any other accesses to the memory,
+            // and images are transitioned out of the undefined layout by the user.
+            memory::Barrier::Aliasing { .. } => {
+                global.push(
+                    vk::MemoryBarrier::builder()
+                        .src_access_mask(vk::AccessFlags::MEMORY_WRITE)
+                        .dst_access_mask(
+                            vk::AccessFlags::MEMORY_READ | vk::AccessFlags::MEMORY_WRITE,
+                        )
+                        .build(),
+                );
+            }
         }
     }
 
@@ -142,7 +168,116 @@ where
     }
 }
 
+/// Stages and accesses of the attachments within dynamic rendering, which transition
+/// their layouts like the implicit external dependencies of a render pass.
+const RENDERING_STAGES: vk::PipelineStageFlags = vk::PipelineStageFlags::from_raw(
+    vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT.as_raw()
+        | vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS.as_raw()
+        | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS.as_raw(),
+);
+const RENDERING_ACCESSES: vk::AccessFlags = vk::AccessFlags::from_raw(
+    vk::AccessFlags::COLOR_ATTACHMENT_READ.as_raw()
+        | vk::AccessFlags::COLOR_ATTACHMENT_WRITE.as_raw()
+        | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ.as_raw()
+        | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE.as_raw(),
+);
+
 impl CommandBuffer {
+    unsafe fn begin_dynamic_rendering(
+        &mut self,
+        fns: &ext::dynamic_rendering::DeviceFn,
+        info: &com::RenderingInfo<Backend>,
+    ) {
+        let mut barriers = SmallVec::<[vk::ImageMemoryBarrier; ROUGH_MAX_ATTACHMENT_COUNT]>::new();
+        let mut attachment = |at: &com::RenderingAttachmentInfo<Backend>, layout: Layout| {
+            let layout = conv::map_image_layout(layout);
+            let range = conv::map_subresource_range(&at.image_view.range);
+            let (start, end) = (
+                conv::map_image_layout(at.desc.layouts.start),
+                conv::map_image_layout(at.desc.layouts.end),
+            );
+            if start != layout && !info.flags.contains(com::RenderingFlags::RESUMING) {
+                barriers.push(
+                    vk::ImageMemoryBarrier::builder()
+                        .dst_access_mask(RENDERING_ACCESSES)
+                        .old_layout(start)
+                        .new_layout(layout)
+                        .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                        .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                        .image(at.image_view.image)
+                        .subresource_range(range)
+                        .build(),
+                );
+            }
+            if end != layout && !info.flags.contains(com::RenderingFlags::SUSPENDING) {
+                self.rendering_barriers.push(LayoutTransition {
+                    image: at.image_view.image,
+                    old_layout: layout,
+                    new_layout: end,
+                    range,
+                });
+            }
+            ext::dynamic_rendering::RenderingAttachmentInfo::new(
+                at.image_view.raw,
+                layout,
+                conv::map_attachment_load_op(at.desc.ops.load),
+                conv::map_attachment_store_op(at.desc.ops.store),
+                mem::transmute(at.clear_value),
+            )
+        };
+
+        let colors = info
+            .colors
+            .iter()
+            .map(|at| attachment(at, Layout::ColorAttachmentOptimal))
+            .collect::<SmallVec<[_; ROUGH_MAX_ATTACHMENT_COUNT]>>();
+        let (depth, stencil) = match info.depth_stencil {
+            Some(at) => {
+                let raw = attachment(at, Layout::DepthStencilAttachmentOptimal);
+                let aspects = at.image_view.range.aspects;
+                let stencil = ext::dynamic_rendering::RenderingAttachmentInfo {
+                    load_op: conv::map_attachment_load_op(at.desc.stencil_ops.load),
+                    store_op: conv::map_attachment_store_op(at.desc.stencil_ops.store),
+                    ..raw
+                };
+                (
+                    Some(raw).filter(|_| aspects.contains(Aspects::DEPTH)),
+                    Some(stencil).filter(|_| aspects.contains(Aspects::STENCIL)),
+                )
+            }
+            None => (None, None),
+        };
+
+        if !barriers.is_empty() {
+            self.device.raw.cmd_pipeline_barrier(
+                self.raw,
+                vk::PipelineStageFlags::TOP_OF_PIPE,
+                RENDERING_STAGES,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &barriers,
+            );
+        }
+
+        let mut flags = 0;
+        if info.flags.contains(com::RenderingFlags::SUSPENDING) {
+            flags |= ext::dynamic_rendering::rendering_flags::SUSPENDING;
+        }
+        if info.flags.contains(com::RenderingFlags::RESUMING) {
+            flags |= ext::dynamic_rendering::rendering_flags::RESUMING;
+        }
+        let raw_info = ext::dynamic_rendering::RenderingInfo::new(
+            flags,
+            conv::map_rect(&info.render_area),
+            info.layers as u32,
+            &colors,
+            depth.as_ref(),
+            stencil.as_ref(),
+        );
+        (fns.cmd_begin_rendering)(self.raw, &raw_info);
+    }
+
     fn bind_descriptor_sets<'a, I, J>(
         &mut self,
         bind_point: vk::PipelineBindPoint,
@@ -256,30 +391,21 @@ impl com::CommandBuffer<Backend> for CommandBuffer {
                 .push_next(&mut attachment_info)
                 .build(),
             n::Framebuffer::Legacy {
+                owner,
                 ref name,
-                ref map,
                 extent,
             } => {
-                let raw_fbo = match map.lock().entry(raw_image_views.clone()) {
-                    Entry::Occupied(e) => *e.get(),
-                    Entry::Vacant(e) => {
-                        let info = vk::FramebufferCreateInfo::builder()
-                            .render_pass(render_pass.raw)
-                            .attachments(&raw_image_views)
-                            .width(extent.width)
-                            .height(extent.height)
-                            .layers(extent.depth);
-
-                        let raw = self
-                            .device
-                            .raw
-                            .create_framebuffer(&info, None)
-                            .expect("Unable to create a legacy framebuffer");
-                        self.device
-                            .set_object_name(vk::ObjectType::FRAMEBUFFER, raw, name);
-                        *e.insert(raw)
-                    }
-                };
+                let (raw_fbo, created) = self.device.rendering_cache.lock().framebuffer(
+                    &self.device.raw,
+                    owner,
+                    render_pass.raw,
+                    raw_image_views.clone(),
+                    extent,
+                );
+                if created && !name.is_empty() {
+                    self.device
+                        .set_object_name(vk::ObjectType::FRAMEBUFFER, raw_fbo, name);
+                }
 
                 builder.framebuffer(raw_fbo).build()
             }
@@ -300,6 +426,80 @@ impl com::CommandBuffer<Backend> for CommandBuffer {
         self.device.raw.cmd_end_render_pass(self.raw);
     }
 
+    unsafe fn begin_rendering(&mut self, info: &com::RenderingInfo<Backend>) {
+        if let Some(ref fns) = self.device.extension_fns.dynamic_rendering {
+            let fns = fns.clone();
+            return self.begin_dynamic_rendering(&fns, info);
+        }
+
+        let mut raw_clear_values = SmallVec::<[vk::ClearValue; ROUGH_MAX_ATTACHMENT_COUNT]>::new();
+        let mut raw_image_views = n::FramebufferKey::new();
+        let mut attachments = SmallVec::<[pass::Attachment; ROUGH_MAX_ATTACHMENT_COUNT]>::new();
+        for attachment in info.attachments() {
+            raw_clear_values.push(mem::transmute(attachment.clear_value));
+            raw_image_views.push(attachment.image_view.raw);
+            attachments.push(info.attachment_desc(attachment));
+        }
+
+        // Without `VK_KHR_dynamic_rendering`, the attachments are rendered
+        // with cached render passes and framebuffers.
+        let (render_pass, framebuffer) = {
+            let mut cache = self.device.rendering_cache.lock();
+            let render_pass = cache.render_pass(&self.device.raw, &attachments);
+            let (framebuffer, _) = cache.framebuffer(
+                &self.device.raw,
+                n::RenderingCache::DYNAMIC_RENDERING,
+                render_pass,
+                raw_image_views,
+                info.extent(),
+            );
+            (render_pass, framebuffer)
+        };
+
+        let raw_info = vk::RenderPassBeginInfo::builder()
+            .render_pass(render_pass)
+            .framebuffer(framebuffer)
+            .render_area(conv::map_rect(&info.render_area))
+            .clear_values(&raw_clear_values);
+        self.device
+            .raw
+            .cmd_begin_render_pass(self.raw, &raw_info, vk::SubpassContents::INLINE);
+    }
+
+    unsafe fn end_rendering(&mut self) {
+        let fns = match self.device.extension_fns.dynamic_rendering {
+            Some(ref fns) => fns,
+            None => return self.device.raw.cmd_end_render_pass(self.raw),
+        };
+        (fns.cmd_end_rendering)(self.raw);
+        if !self.rendering_barriers.is_empty() {
+            let barriers = self
+                .rendering_barriers
+                .drain(..)
+                .map(|transition| {
+                    vk::ImageMemoryBarrier::builder()
+                        .src_access_mask(RENDERING_ACCESSES)
+                        .old_layout(transition.old_layout)
+                        .new_layout(transition.new_layout)
+                        .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                        .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                        .image(transition.image)
+                        .subresource_range(transition.range)
+                        .build()
+                })
+                .collect::<SmallVec<[_; ROUGH_MAX_ATTACHMENT_COUNT]>>();
+            self.device.raw.cmd_pipeline_barrier(
+                self.raw,
+                RENDERING_STAGES,
+                vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &barriers,
+            );
+        }
+    }
+
     unsafe fn pipeline_barrier<'a, T>(
         &mut self,
         stages: Range<pso::PipelineStage>,
@@ -606,6 +806,55 @@ impl com::CommandBuffer<Backend> for CommandBuffer {
         );
     }
 
+    unsafe fn set_shading_rate(
+        &mut self,
+        rate: pso::ShadingRate,
+        combiners: [pso::ShadingRateCombiner; 2],
+    ) {
+        let fragment_size = vk::Extent2D {
+            width: rate.width as u32,
+            height: rate.height as u32,
+        };
+        let combiner_ops = [
+            conv::map_shading_rate_combiner(combiners[0]),
+            conv::map_shading_rate_combiner(combiners[1]),
+        ];
+        let fns = self
+            .device
+            .extension_fns
+            .fragment_shading_rate
+            .as_ref()
+            .expect("Feature SHADING_RATE must be enabled to call set_shading_rate");
+        (fns.cmd_set_fragment_shading_rate)(self.raw, &fragment_size, &combiner_ops);
+    }
+
+    unsafe fn set_sample_locations(
+        &mut self,
+        samples: image::NumSamples,
+        locations: &[pso::SampleLocation],
+    ) {
+        let locations = locations
+            .iter()
+            .map(|location| vk::SampleLocationEXT {
+                x: location.x,
+                y: location.y,
+            })
+            .collect::<SmallVec<[_; 16]>>();
+        let info = vk::SampleLocationsInfoEXT::builder()
+            .sample_locations_per_pixel(conv::map_sample_count_flags(samples))
+            .sample_location_grid_size(vk::Extent2D {
+                width: 1,
+                height: 1,
+            })
+            .sample_locations(&locations);
+        self.device
+            .extension_fns
+            .sample_locations
+            .as_ref()
+            .expect("Feature SAMPLE_LOCATIONS must be enabled to call set_sample_locations")
+            .cmd_set_sample_locations_ext(self.raw, &*info);
+    }
+
     unsafe fn bind_graphics_pipeline(&mut self, pipeline: &n::GraphicsPipeline) {
         self.device
             .raw
@@ -657,6 +906,10 @@ impl com::CommandBuffer<Backend> for CommandBuffer {
     }
 
     unsafe fn dispatch(&mut self, count: WorkGroupCount) {
+        if count.contains(&0) {
+            debug!("Skipping an empty dispatch");
+            return;
+        }
         self.device
             .raw
             .cmd_dispatch(self.raw, count[0], count[1], count[2])
@@ -760,6 +1013,10 @@ impl com::CommandBuffer<Backend> for CommandBuffer {
     }
 
     unsafe fn draw(&mut self, vertices: Range<VertexCount>, instances: Range<InstanceCount>) {
+        if vertices.start >= vertices.end || instances.start >= instances.end {
+            debug!("Skipping an empty draw");
+            return;
+        }
         self.device.raw.cmd_draw(
             self.raw,
             vertices.end - vertices.start,
@@ -775,6 +1032,10 @@ impl com::CommandBuffer<Backend> for CommandBuffer {
         base_vertex: VertexOffset,
         instances: Range<InstanceCount>,
     ) {
+        if indices.start >= indices.end || instances.start >= instances.end {
+            debug!("Skipping an empty indexed draw");
+            return;
+        }
         self.device.raw.cmd_draw_indexed(
             self.raw,
             indices.end - indices.start,
@@ -1080,4 +1341,11 @@ impl com::CommandBuffer<Backend> for CommandBuffer {
             ext.cmd_end_debug_utils_label(self.raw);
         }
     }
+
+    unsafe fn set_checkpoint(&mut self, label: &str) {
+        if let Some(ref functor) = self.device.extension_fns.checkpoints {
+            let marker = self.device.checkpoint_labels.lock().marker(label);
+            functor.cmd_set_checkpoint_nv(self.raw, marker);
+        }
+    }
 }
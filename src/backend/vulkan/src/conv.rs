@@ -6,21 +6,30 @@ use hal::{
     buffer, command, format, image,
     memory::Segment,
     pass, pso, query,
-    window::{CompositeAlphaMode, PresentMode},
+    window::{ColorSpace, CompositeAlphaMode, FullScreenExclusive, PresentMode, SurfaceTransform},
     Features, IndexType,
 };
 
 use std::mem;
 
 pub fn map_format(format: format::Format) -> vk::Format {
-    vk::Format::from_raw(format as i32)
+    match format {
+        format::Format::Nv12Unorm => vk::Format::G8_B8R8_2PLANE_420_UNORM,
+        format::Format::P010Unorm => vk::Format::G10X6_B10X6R10X6_2PLANE_420_UNORM_3PACK16,
+        _ => vk::Format::from_raw(format as i32),
+    }
 }
 
 pub fn map_vk_format(vk_format: vk::Format) -> Option<format::Format> {
-    if (vk_format.as_raw() as usize) < format::NUM_FORMATS && vk_format != vk::Format::UNDEFINED {
-        Some(unsafe { mem::transmute(vk_format) })
-    } else {
-        None
+    match vk_format {
+        vk::Format::G8_B8R8_2PLANE_420_UNORM => Some(format::Format::Nv12Unorm),
+        vk::Format::G10X6_B10X6R10X6_2PLANE_420_UNORM_3PACK16 => Some(format::Format::P010Unorm),
+        _ if (vk_format.as_raw() as usize) < format::Format::Nv12Unorm as usize
+            && vk_format != vk::Format::UNDEFINED =>
+        {
+            Some(unsafe { mem::transmute(vk_format) })
+        }
+        _ => None,
     }
 }
 
@@ -63,6 +72,16 @@ pub fn map_image_layout(layout: image::Layout) -> vk::ImageLayout {
         Il::ColorAttachmentOptimal => vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
         Il::DepthStencilAttachmentOptimal => vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
         Il::DepthStencilReadOnlyOptimal => vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL,
+        Il::DepthReadOnlyStencilAttachmentOptimal => {
+            vk::ImageLayout::DEPTH_READ_ONLY_STENCIL_ATTACHMENT_OPTIMAL
+        }
+        Il::DepthAttachmentStencilReadOnlyOptimal => {
+            vk::ImageLayout::DEPTH_ATTACHMENT_STENCIL_READ_ONLY_OPTIMAL
+        }
+        Il::DepthAttachmentOptimal => vk::ImageLayout::DEPTH_ATTACHMENT_OPTIMAL,
+        Il::DepthReadOnlyOptimal => vk::ImageLayout::DEPTH_READ_ONLY_OPTIMAL,
+        Il::StencilAttachmentOptimal => vk::ImageLayout::STENCIL_ATTACHMENT_OPTIMAL,
+        Il::StencilReadOnlyOptimal => vk::ImageLayout::STENCIL_READ_ONLY_OPTIMAL,
         Il::ShaderReadOnlyOptimal => vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
         Il::TransferSrcOptimal => vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
         Il::TransferDstOptimal => vk::ImageLayout::TRANSFER_DST_OPTIMAL,
@@ -195,6 +214,7 @@ pub fn map_descriptor_type(ty: pso::DescriptorType) -> vk::DescriptorType {
             },
         },
         pso::DescriptorType::InputAttachment => vk::DescriptorType::INPUT_ATTACHMENT,
+        pso::DescriptorType::InlineUniformBlock => vk::DescriptorType::INLINE_UNIFORM_BLOCK_EXT,
     }
 }
 
@@ -206,6 +226,31 @@ pub fn map_filter(filter: image::Filter) -> vk::Filter {
     vk::Filter::from_raw(filter as i32)
 }
 
+pub fn map_ycbcr_model(model: image::YcbcrModel) -> vk::SamplerYcbcrModelConversion {
+    use hal::image::YcbcrModel as Ym;
+    match model {
+        Ym::RgbIdentity => vk::SamplerYcbcrModelConversion::RGB_IDENTITY,
+        Ym::YcbcrIdentity => vk::SamplerYcbcrModelConversion::YCBCR_IDENTITY,
+        Ym::Ycbcr709 => vk::SamplerYcbcrModelConversion::YCBCR_709,
+        Ym::Ycbcr601 => vk::SamplerYcbcrModelConversion::YCBCR_601,
+        Ym::Ycbcr2020 => vk::SamplerYcbcrModelConversion::YCBCR_2020,
+    }
+}
+
+pub fn map_ycbcr_range(range: image::YcbcrRange) -> vk::SamplerYcbcrRange {
+    match range {
+        image::YcbcrRange::Full => vk::SamplerYcbcrRange::ITU_FULL,
+        image::YcbcrRange::Narrow => vk::SamplerYcbcrRange::ITU_NARROW,
+    }
+}
+
+pub fn map_chroma_location(location: image::ChromaLocation) -> vk::ChromaLocation {
+    match location {
+        image::ChromaLocation::CositedEven => vk::ChromaLocation::COSITED_EVEN,
+        image::ChromaLocation::Midpoint => vk::ChromaLocation::MIDPOINT,
+    }
+}
+
 pub fn map_mip_filter(filter: image::Filter) -> vk::SamplerMipmapMode {
     vk::SamplerMipmapMode::from_raw(filter as i32)
 }
@@ -357,6 +402,32 @@ pub fn map_blend_op(operation: pso::BlendOp) -> (vk::BlendOp, vk::BlendFactor, v
             vk::BlendFactor::ZERO,
             vk::BlendFactor::ZERO,
         ),
+        Advanced(op) => (
+            map_advanced_blend_op(op),
+            vk::BlendFactor::ZERO,
+            vk::BlendFactor::ZERO,
+        ),
+    }
+}
+
+fn map_advanced_blend_op(operation: pso::AdvancedBlendOp) -> vk::BlendOp {
+    use hal::pso::AdvancedBlendOp::*;
+    match operation {
+        Multiply => vk::BlendOp::MULTIPLY_EXT,
+        Screen => vk::BlendOp::SCREEN_EXT,
+        Overlay => vk::BlendOp::OVERLAY_EXT,
+        Darken => vk::BlendOp::DARKEN_EXT,
+        Lighten => vk::BlendOp::LIGHTEN_EXT,
+        ColorDodge => vk::BlendOp::COLORDODGE_EXT,
+        ColorBurn => vk::BlendOp::COLORBURN_EXT,
+        HardLight => vk::BlendOp::HARDLIGHT_EXT,
+        SoftLight => vk::BlendOp::SOFTLIGHT_EXT,
+        Difference => vk::BlendOp::DIFFERENCE_EXT,
+        Exclusion => vk::BlendOp::EXCLUSION_EXT,
+        HslHue => vk::BlendOp::HSL_HUE_EXT,
+        HslSaturation => vk::BlendOp::HSL_SATURATION_EXT,
+        HslColor => vk::BlendOp::HSL_COLOR_EXT,
+        HslLuminosity => vk::BlendOp::HSL_LUMINOSITY_EXT,
     }
 }
 
@@ -439,6 +510,10 @@ pub fn map_buffer_features(features: vk::FormatFeatureFlags) -> format::BufferFe
 pub(crate) fn map_device_features(
     features: Features,
     imageless_framebuffers: bool,
+    memory_priority: bool,
+    pageable_device_local_memory: bool,
+    device_fault: bool,
+    dynamic_rendering: bool,
 ) -> crate::DeviceCreationFeatures {
     crate::DeviceCreationFeatures {
         // vk::PhysicalDeviceFeatures is a struct composed of Bool32's while
@@ -558,6 +633,192 @@ pub(crate) fn map_device_features(
         } else {
             None
         },
+        multiview: if features.contains(Features::MULTIVIEW) {
+            Some(
+                vk::PhysicalDeviceMultiviewFeatures::builder()
+                    .multiview(true)
+                    .build(),
+            )
+        } else {
+            None
+        },
+        inline_uniform_block: if features.contains(Features::INLINE_UNIFORM_BLOCK) {
+            Some(
+                vk::PhysicalDeviceInlineUniformBlockFeaturesEXT::builder()
+                    .inline_uniform_block(true)
+                    .build(),
+            )
+        } else {
+            None
+        },
+        sampler_ycbcr_conversion: if features.contains(Features::SAMPLER_YCBCR_CONVERSION) {
+            Some(
+                vk::PhysicalDeviceSamplerYcbcrConversionFeatures::builder()
+                    .sampler_ycbcr_conversion(true)
+                    .build(),
+            )
+        } else {
+            None
+        },
+        separate_depth_stencil_layouts: if features
+            .contains(Features::SEPARATE_DEPTH_STENCIL_LAYOUTS)
+        {
+            Some(
+                vk::PhysicalDeviceSeparateDepthStencilLayoutsFeatures::builder()
+                    .separate_depth_stencil_layouts(true)
+                    .build(),
+            )
+        } else {
+            None
+        },
+        protected_memory: if features.contains(Features::PROTECTED_MEMORY) {
+            Some(
+                vk::PhysicalDeviceProtectedMemoryFeatures::builder()
+                    .protected_memory(true)
+                    .build(),
+            )
+        } else {
+            None
+        },
+        image_view_min_lod: if features.contains(Features::IMAGE_VIEW_MIN_LOD) {
+            Some(crate::ext::image_view_min_lod::PhysicalDeviceFeatures::new(true))
+        } else {
+            None
+        },
+        pipeline_creation_cache_control: if features
+            .contains(Features::PIPELINE_CREATION_CACHE_CONTROL)
+        {
+            Some(crate::ext::pipeline_creation_cache_control::PhysicalDeviceFeatures::new(true))
+        } else {
+            None
+        },
+        host_image_copy: if features.contains(Features::HOST_IMAGE_COPY) {
+            Some(crate::ext::host_image_copy::PhysicalDeviceFeatures::new(true))
+        } else {
+            None
+        },
+        cooperative_matrix: if features.contains(Features::COOPERATIVE_MATRIX) {
+            Some(crate::ext::cooperative_matrix::PhysicalDeviceFeatures::new(true))
+        } else {
+            None
+        },
+        swapchain_maintenance: if features.contains(Features::PRESENT_MODE_SWITCH) {
+            Some(crate::ext::swapchain_maintenance1::PhysicalDeviceFeatures::new(true))
+        } else {
+            None
+        },
+        blend_operation_advanced: if features.contains(Features::BLEND_OPERATION_ADVANCED) {
+            Some(
+                vk::PhysicalDeviceBlendOperationAdvancedFeaturesEXT::builder()
+                    .advanced_blend_coherent_operations(true)
+                    .build(),
+            )
+        } else {
+            None
+        },
+        line_rasterization: if features.intersects(
+            Features::LINE_RASTERIZATION_RECTANGULAR
+                | Features::LINE_RASTERIZATION_BRESENHAM
+                | Features::LINE_RASTERIZATION_SMOOTH,
+        ) {
+            let rectangular = features.contains(Features::LINE_RASTERIZATION_RECTANGULAR);
+            let bresenham = features.contains(Features::LINE_RASTERIZATION_BRESENHAM);
+            let smooth = features.contains(Features::LINE_RASTERIZATION_SMOOTH);
+            let stipple = features.contains(Features::LINE_STIPPLE);
+            Some(
+                vk::PhysicalDeviceLineRasterizationFeaturesEXT::builder()
+                    .rectangular_lines(rectangular)
+                    .bresenham_lines(bresenham)
+                    .smooth_lines(smooth)
+                    .stippled_rectangular_lines(stipple && rectangular)
+                    .stippled_bresenham_lines(stipple && bresenham)
+                    .stippled_smooth_lines(stipple && smooth)
+                    .build(),
+            )
+        } else {
+            None
+        },
+        depth_clip_enable: if features.contains(Features::DEPTH_CLIP_ENABLE) {
+            Some(
+                vk::PhysicalDeviceDepthClipEnableFeaturesEXT::builder()
+                    .depth_clip_enable(true)
+                    .build(),
+            )
+        } else {
+            None
+        },
+        fragment_shader_interlock: if features.contains(Features::FRAGMENT_SHADER_INTERLOCK) {
+            Some(
+                vk::PhysicalDeviceFragmentShaderInterlockFeaturesEXT::builder()
+                    .fragment_shader_pixel_interlock(true)
+                    .build(),
+            )
+        } else {
+            None
+        },
+        robustness2: if features.contains(Features::NULL_DESCRIPTOR) {
+            Some(crate::ext::robustness2::PhysicalDeviceFeatures::new(true))
+        } else {
+            None
+        },
+        memory_priority: if memory_priority {
+            Some(
+                vk::PhysicalDeviceMemoryPriorityFeaturesEXT::builder()
+                    .memory_priority(true)
+                    .build(),
+            )
+        } else {
+            None
+        },
+        pageable_device_local_memory: if pageable_device_local_memory {
+            Some(crate::ext::pageable_device_local_memory::PhysicalDeviceFeatures::new(true))
+        } else {
+            None
+        },
+        device_fault: if device_fault {
+            Some(crate::ext::device_fault::PhysicalDeviceFeatures::new(true))
+        } else {
+            None
+        },
+        dynamic_rendering: if dynamic_rendering {
+            Some(crate::ext::dynamic_rendering::PhysicalDeviceFeatures::new(true))
+        } else {
+            None
+        },
+        fragment_shading_rate: if features
+            .intersects(Features::SHADING_RATE | Features::SHADING_RATE_ATTACHMENT)
+        {
+            Some(
+                crate::ext::fragment_shading_rate::PhysicalDeviceFeatures::new(
+                    features.contains(Features::SHADING_RATE),
+                    features.contains(Features::SHADING_RATE_ATTACHMENT),
+                ),
+            )
+        } else {
+            None
+        },
+    }
+}
+
+pub fn map_shading_rate_combiner(combiner: pso::ShadingRateCombiner) -> u32 {
+    use crate::ext::fragment_shading_rate::combiner_op;
+    match combiner {
+        pso::ShadingRateCombiner::Keep => combiner_op::KEEP,
+        pso::ShadingRateCombiner::Replace => combiner_op::REPLACE,
+        pso::ShadingRateCombiner::Min => combiner_op::MIN,
+        pso::ShadingRateCombiner::Max => combiner_op::MAX,
+        pso::ShadingRateCombiner::Mul => combiner_op::MUL,
+    }
+}
+
+pub fn map_line_rasterization_mode(
+    mode: pso::LineRasterizationMode,
+) -> vk::LineRasterizationModeEXT {
+    match mode {
+        pso::LineRasterizationMode::Default => vk::LineRasterizationModeEXT::DEFAULT,
+        pso::LineRasterizationMode::Rectangular => vk::LineRasterizationModeEXT::RECTANGULAR,
+        pso::LineRasterizationMode::Bresenham => vk::LineRasterizationModeEXT::BRESENHAM,
+        pso::LineRasterizationMode::Smooth => vk::LineRasterizationModeEXT::RECTANGULAR_SMOOTH,
     }
 }
 
@@ -639,10 +900,29 @@ pub fn map_viewport(vp: &pso::Viewport, flip_y: bool, shift_y: bool) -> vk::View
     }
 }
 
+pub fn map_image_kind_type(kind: image::Kind) -> vk::ImageType {
+    match kind {
+        image::Kind::D1(..) => vk::ImageType::TYPE_1D,
+        image::Kind::D2(..) => vk::ImageType::TYPE_2D,
+        image::Kind::D3(..) => vk::ImageType::TYPE_3D,
+    }
+}
+
 pub fn map_view_capabilities(caps: image::ViewCapabilities) -> vk::ImageCreateFlags {
     vk::ImageCreateFlags::from_raw(caps.bits())
 }
 
+pub fn map_full_screen_exclusive(mode: FullScreenExclusive) -> vk::FullScreenExclusiveEXT {
+    match mode {
+        FullScreenExclusive::Default => vk::FullScreenExclusiveEXT::DEFAULT,
+        FullScreenExclusive::Allowed => vk::FullScreenExclusiveEXT::ALLOWED,
+        FullScreenExclusive::Disallowed => vk::FullScreenExclusiveEXT::DISALLOWED,
+        FullScreenExclusive::ApplicationControlled => {
+            vk::FullScreenExclusiveEXT::APPLICATION_CONTROLLED
+        }
+    }
+}
+
 pub fn map_present_mode(mode: PresentMode) -> vk::PresentModeKHR {
     if mode == PresentMode::IMMEDIATE {
         vk::PresentModeKHR::IMMEDIATE
@@ -682,6 +962,35 @@ pub fn map_vk_composite_alpha(composite_alpha: vk::CompositeAlphaFlagsKHR) -> Co
     CompositeAlphaMode::from_bits_truncate(composite_alpha.as_raw())
 }
 
+pub fn map_surface_transform(transform: SurfaceTransform) -> vk::SurfaceTransformFlagsKHR {
+    vk::SurfaceTransformFlagsKHR::from_raw(transform.bits())
+}
+
+pub fn map_vk_surface_transform(transform: vk::SurfaceTransformFlagsKHR) -> SurfaceTransform {
+    SurfaceTransform::from_bits_truncate(transform.as_raw())
+}
+
+pub fn map_color_space(color_space: ColorSpace) -> vk::ColorSpaceKHR {
+    if color_space == ColorSpace::SRGB_NONLINEAR {
+        vk::ColorSpaceKHR::SRGB_NONLINEAR
+    } else if color_space == ColorSpace::LINEAR {
+        vk::ColorSpaceKHR::EXTENDED_SRGB_LINEAR_EXT
+    } else if color_space == ColorSpace::HDR10 {
+        vk::ColorSpaceKHR::HDR10_ST2084_EXT
+    } else {
+        panic!("Unexpected color space {:?}", color_space)
+    }
+}
+
+pub fn map_vk_color_space(color_space: vk::ColorSpaceKHR) -> ColorSpace {
+    match color_space {
+        vk::ColorSpaceKHR::SRGB_NONLINEAR => ColorSpace::SRGB_NONLINEAR,
+        vk::ColorSpaceKHR::EXTENDED_SRGB_LINEAR_EXT => ColorSpace::LINEAR,
+        vk::ColorSpaceKHR::HDR10_ST2084_EXT => ColorSpace::HDR10,
+        _ => ColorSpace::empty(),
+    }
+}
+
 pub fn map_descriptor_pool_create_flags(
     flags: pso::DescriptorPoolCreateFlags,
 ) -> vk::DescriptorPoolCreateFlags {
@@ -711,6 +1020,9 @@ pub fn map_vk_memory_properties(flags: vk::MemoryPropertyFlags) -> hal::memory::
     if flags.contains(vk::MemoryPropertyFlags::LAZILY_ALLOCATED) {
         properties |= Properties::LAZILY_ALLOCATED;
     }
+    if flags.contains(vk::MemoryPropertyFlags::PROTECTED) {
+        properties |= Properties::PROTECTED;
+    }
 
     properties
 }
@@ -725,3 +1037,22 @@ pub fn map_vk_memory_heap_flags(flags: vk::MemoryHeapFlags) -> hal::memory::Heap
 
     hal_flags
 }
+
+pub fn map_vk_component_type(ty: i32) -> Option<hal::adapter::CooperativeMatrixComponentType> {
+    use crate::ext::cooperative_matrix::component_type as ct;
+    use hal::adapter::CooperativeMatrixComponentType as Ct;
+    Some(match ty {
+        ct::FLOAT16 => Ct::Float16,
+        ct::FLOAT32 => Ct::Float32,
+        ct::FLOAT64 => Ct::Float64,
+        ct::SINT8 => Ct::Sint8,
+        ct::SINT16 => Ct::Sint16,
+        ct::SINT32 => Ct::Sint32,
+        ct::SINT64 => Ct::Sint64,
+        ct::UINT8 => Ct::Uint8,
+        ct::UINT16 => Ct::Uint16,
+        ct::UINT32 => Ct::Uint32,
+        ct::UINT64 => Ct::Uint64,
+        _ => return None,
+    })
+}
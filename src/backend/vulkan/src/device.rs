@@ -1,24 +1,220 @@
 use arrayvec::ArrayVec;
-use ash::{extensions::khr, version::DeviceV1_0, vk};
+use ash::{
+    extensions::khr,
+    version::{DeviceV1_0, DeviceV1_1},
+    vk,
+};
+use auxil::workers::{DetachedComputePipelineDesc, DetachedGraphicsPipelineDesc};
 use inplace_it::inplace_or_alloc_from_iter;
 use smallvec::SmallVec;
 
 use hal::{
-    memory::{Requirements, Segment},
+    memory::{DedicatedResource, Priority, Requirements, Segment},
     pool::CommandPoolCreateFlags,
     pso::VertexInputRate,
     window::SwapchainConfig,
-    {buffer, device as d, format, image, pass, pso, query, queue}, {Features, MemoryTypeId},
+    {buffer, command as com, device as d, format, image, pass, pso, query, queue},
+    {Features, MemoryTypeId},
+};
+
+#[cfg(unix)]
+use std::os::unix::io::RawFd;
+use std::{
+    ffi::{CStr, CString},
+    iter,
+    marker::PhantomData,
+    mem,
+    ops::Range,
+    ptr,
+    sync::Arc,
+};
+
+use crate::{
+    command as cmd, conv, ext, native as n, pool::RawCommandPool, window as w, Backend as B,
 };
 
-use std::{ffi::CString, marker::PhantomData, mem, ops::Range, ptr, sync::Arc};
+/// Creates a render pass with `vkCreateRenderPass2KHR`, as the shading rate
+/// attachments can only be described with `VkSubpassDescription2`.
+unsafe fn create_render_pass2(
+    device: &super::RawDevice,
+    fns: &ext::fragment_shading_rate::DeviceFn,
+    attachments: &[pass::Attachment],
+    subpasses: &[pass::SubpassDesc],
+    dependencies: &[pass::SubpassDependency],
+) -> Result<n::RenderPass, d::OutOfMemory> {
+    let raw_attachments = attachments
+        .iter()
+        .map(|attachment| {
+            vk::AttachmentDescription2::builder()
+                .format(
+                    attachment
+                        .format
+                        .map_or(vk::Format::UNDEFINED, conv::map_format),
+                )
+                .samples(conv::map_sample_count_flags(attachment.samples))
+                .load_op(conv::map_attachment_load_op(attachment.ops.load))
+                .store_op(conv::map_attachment_store_op(attachment.ops.store))
+                .stencil_load_op(conv::map_attachment_load_op(attachment.stencil_ops.load))
+                .stencil_store_op(conv::map_attachment_store_op(attachment.stencil_ops.store))
+                .initial_layout(conv::map_image_layout(attachment.layouts.start))
+                .final_layout(conv::map_image_layout(attachment.layouts.end))
+                .build()
+        })
+        .collect::<Vec<_>>();
+
+    // Only the input attachments need the aspects, but they are filled in for all of them.
+    let make_ref = |&(id, layout): &pass::AttachmentRef| {
+        let aspects = attachments[id]
+            .format
+            .map_or(format::Aspects::COLOR, |format| {
+                format.surface_desc().aspects
+            });
+        vk::AttachmentReference2::builder()
+            .attachment(id as _)
+            .layout(conv::map_image_layout(layout))
+            .aspect_mask(conv::map_image_aspects(aspects))
+            .build()
+    };
+    let attachment_refs = subpasses
+        .iter()
+        .map(|subpass| {
+            (
+                subpass.colors.iter().map(make_ref).collect::<Box<[_]>>(),
+                subpass.depth_stencil.map(make_ref),
+                subpass.inputs.iter().map(make_ref).collect::<Box<[_]>>(),
+                subpass
+                    .preserves
+                    .iter()
+                    .map(|&id| id as u32)
+                    .collect::<Box<[_]>>(),
+                subpass.resolves.iter().map(make_ref).collect::<Box<[_]>>(),
+                subpass.shading_rate.map(make_ref),
+            )
+        })
+        .collect::<Vec<_>>();
+    let shading_rate_infos = attachment_refs
+        .iter()
+        .map(|(.., shading_rate)| {
+            shading_rate.as_ref().map(|aref| {
+                ext::fragment_shading_rate::AttachmentInfo::new(
+                    aref,
+                    device.shading_rate_texel_size,
+                )
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let raw_subpasses = subpasses
+        .iter()
+        .zip(&attachment_refs)
+        .zip(&shading_rate_infos)
+        .map(
+            |((subpass, (colors, depth_stencil, inputs, preserves, resolves, _)), info)| {
+                let mut raw = vk::SubpassDescription2::builder()
+                    .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
+                    .view_mask(subpass.view_mask)
+                    .input_attachments(inputs)
+                    .color_attachments(colors)
+                    .preserve_attachments(preserves)
+                    .build();
+                if !resolves.is_empty() {
+                    raw.p_resolve_attachments = resolves.as_ptr();
+                }
+                if let Some(ref aref) = *depth_stencil {
+                    raw.p_depth_stencil_attachment = aref;
+                }
+                if let Some(ref info) = *info {
+                    raw.p_next = info as *const _ as *const _;
+                }
+                raw
+            },
+        )
+        .collect::<Vec<_>>();
+
+    let raw_dependencies = dependencies
+        .iter()
+        .map(|sdep| {
+            vk::SubpassDependency2::builder()
+                .src_subpass(
+                    sdep.passes
+                        .start
+                        .map_or(vk::SUBPASS_EXTERNAL, |id| id as u32),
+                )
+                .dst_subpass(sdep.passes.end.map_or(vk::SUBPASS_EXTERNAL, |id| id as u32))
+                .src_stage_mask(conv::map_pipeline_stage(sdep.stages.start))
+                .dst_stage_mask(conv::map_pipeline_stage(sdep.stages.end))
+                .src_access_mask(conv::map_image_access(sdep.accesses.start))
+                .dst_access_mask(conv::map_image_access(sdep.accesses.end))
+                .dependency_flags(mem::transmute(sdep.flags))
+                .build()
+        })
+        .collect::<Vec<_>>();
+
+    let info = vk::RenderPassCreateInfo2::builder()
+        .attachments(&raw_attachments)
+        .subpasses(&raw_subpasses)
+        .dependencies(&raw_dependencies);
+    let mut raw = vk::RenderPass::null();
+    match (fns.create_render_pass2)(device.raw.handle(), &*info, ptr::null(), &mut raw) {
+        vk::Result::SUCCESS => Ok(n::RenderPass {
+            raw,
+            attachment_count: attachments.len(),
+            rendering: None,
+        }),
+        vk::Result::ERROR_OUT_OF_HOST_MEMORY => Err(d::OutOfMemory::Host),
+        vk::Result::ERROR_OUT_OF_DEVICE_MEMORY => Err(d::OutOfMemory::Device),
+        _ => unreachable!(),
+    }
+}
+
+/// Maps the regions of a host image copy, with the host pointers at `buffer_offset` into `data`.
+fn map_host_copy_regions<I>(
+    data: *mut u8,
+    data_len: usize,
+    to_image: bool,
+    regions: I,
+) -> Result<SmallVec<[ext::host_image_copy::MemoryImageCopy; 4]>, d::MapError>
+where
+    I: Iterator<Item = com::BufferImageCopy>,
+{
+    regions
+        .map(|r| {
+            if r.buffer_offset as usize >= data_len {
+                return Err(d::MapError::OutOfBounds);
+            }
+            let host_pointer = unsafe { data.add(r.buffer_offset as usize) };
+            let copy = vk::BufferImageCopy {
+                buffer_offset: 0,
+                buffer_row_length: r.buffer_width,
+                buffer_image_height: r.buffer_height,
+                image_subresource: conv::map_subresource_layers(&r.image_layers),
+                image_offset: conv::map_offset(r.image_offset),
+                image_extent: conv::map_extent(r.image_extent),
+            };
+            Ok(if to_image {
+                ext::host_image_copy::MemoryImageCopy::memory_to_image(host_pointer, &copy)
+            } else {
+                ext::host_image_copy::MemoryImageCopy::image_to_memory(host_pointer, &copy)
+            })
+        })
+        .collect()
+}
 
-use crate::{command as cmd, conv, native as n, pool::RawCommandPool, window as w, Backend as B};
+fn map_host_copy_result(result: vk::Result) -> Result<(), d::MapError> {
+    match result {
+        vk::Result::SUCCESS => Ok(()),
+        vk::Result::ERROR_MEMORY_MAP_FAILED => Err(d::MapError::MappingFailed),
+        vk::Result::ERROR_OUT_OF_HOST_MEMORY => Err(d::OutOfMemory::Host.into()),
+        vk::Result::ERROR_OUT_OF_DEVICE_MEMORY => Err(d::OutOfMemory::Device.into()),
+        vk::Result::ERROR_INITIALIZATION_FAILED => Err(d::MapError::Access),
+        _ => unreachable!(),
+    }
+}
 
 #[derive(Debug, Default)]
 struct GraphicsPipelineInfoBuf<'a> {
-    // 10 is the max amount of dynamic states
-    dynamic_states: ArrayVec<[vk::DynamicState; 10]>,
+    // 11 is the max amount of dynamic states
+    dynamic_states: ArrayVec<[vk::DynamicState; 11]>,
 
     // 5 is the amount of stages
     c_strings: ArrayVec<[CString; 5]>,
@@ -268,11 +464,22 @@ impl<'a> GraphicsPipelineInfoBuf<'a> {
                     (ms.sample_mask & 0xFFFFFFFF) as u32,
                     ((ms.sample_mask >> 32) & 0xFFFFFFFF) as u32,
                 ];
+                if ms.sample_locations {
+                    this.dynamic_states
+                        .push(vk::DynamicState::SAMPLE_LOCATIONS_EXT);
+                }
+                let sample_shading = match ms.sample_shading {
+                    Some(_) if !device.features.contains(Features::SAMPLE_RATE_SHADING) => {
+                        warn!("Sample shading was requested on a device with disabled feature");
+                        None
+                    }
+                    other => other,
+                };
                 vk::PipelineMultisampleStateCreateInfo::builder()
                     .flags(vk::PipelineMultisampleStateCreateFlags::empty())
                     .rasterization_samples(conv::map_sample_count_flags(ms.rasterization_samples))
-                    .sample_shading_enable(ms.sample_shading.is_some())
-                    .min_sample_shading(ms.sample_shading.unwrap_or(0.0))
+                    .sample_shading_enable(sample_shading.is_some())
+                    .min_sample_shading(sample_shading.unwrap_or(0.0))
                     .sample_mask(&this.sample_mask)
                     .alpha_to_coverage_enable(ms.alpha_coverage)
                     .alpha_to_one_enable(ms.alpha_to_one)
@@ -394,6 +601,12 @@ impl<'a> GraphicsPipelineInfoBuf<'a> {
             })
             .build();
 
+        // The shading rate is only ever set with `set_shading_rate`.
+        if device.features.contains(Features::SHADING_RATE) {
+            this.dynamic_states
+                .push(ext::fragment_shading_rate::DYNAMIC_STATE);
+        }
+
         this.pipeline_dynamic_state = vk::PipelineDynamicStateCreateInfo::builder()
             .flags(vk::PipelineDynamicStateCreateFlags::empty())
             .dynamic_states(&this.dynamic_states)
@@ -435,23 +648,319 @@ impl<'a> ComputePipelineInfoBuf<'a> {
     }
 }
 
+unsafe fn create_graphics_pipeline_raw<'a>(
+    device: &super::RawDevice,
+    desc: &pso::GraphicsPipelineDesc<'a, B>,
+    cache: vk::PipelineCache,
+) -> Result<n::GraphicsPipeline, pso::CreationError> {
+    debug!("create_graphics_pipeline {:?}", desc);
+    let buf = GraphicsPipelineInfoBuf::new(desc, device);
+
+    // The locations themselves are dynamic, see `set_sample_locations`
+    let sample_locations_state = vk::PipelineSampleLocationsStateCreateInfoEXT::builder()
+        .sample_locations_enable(true)
+        .build();
+    let mut multisample_state = buf.multisample_state;
+    if desc
+        .multisampling
+        .as_ref()
+        .map_or(false, |ms| ms.sample_locations)
+    {
+        multisample_state.p_next = &sample_locations_state as *const _ as *const _;
+    }
+
+    let line_rasterization = desc.rasterizer.line_rasterization;
+    let stipple = line_rasterization.stipple.unwrap_or(pso::LineStipple {
+        factor: 1,
+        pattern: !0,
+    });
+    let line_state = vk::PipelineRasterizationLineStateCreateInfoEXT::builder()
+        .line_rasterization_mode(conv::map_line_rasterization_mode(line_rasterization.mode))
+        .stippled_line_enable(line_rasterization.stipple.is_some())
+        .line_stipple_factor(stipple.factor)
+        .line_stipple_pattern(stipple.pattern)
+        .build();
+    let mut rasterization_state = buf.rasterization_state;
+    if line_rasterization != pso::LineRasterization::DEFAULT {
+        rasterization_state.p_next = &line_state as *const _ as *const _;
+    }
+    let mut depth_clip_state = vk::PipelineRasterizationDepthClipStateCreateInfoEXT::builder()
+        .depth_clip_enable(desc.rasterizer.depth_clip_enable.unwrap_or(false))
+        .build();
+    if desc.rasterizer.depth_clip_enable.is_some() {
+        depth_clip_state.p_next = rasterization_state.p_next;
+        rasterization_state.p_next = &depth_clip_state as *const _ as *const _;
+    }
+    let (conservative_mode, extra_overestimation_size) = match desc.rasterizer.conservative {
+        Some(pso::ConservativeRasterization::Overestimate(size)) => {
+            (vk::ConservativeRasterizationModeEXT::OVERESTIMATE, size)
+        }
+        Some(pso::ConservativeRasterization::Underestimate) => {
+            (vk::ConservativeRasterizationModeEXT::UNDERESTIMATE, 0.0)
+        }
+        None => (vk::ConservativeRasterizationModeEXT::DISABLED, 0.0),
+    };
+    let mut conservative_state = vk::PipelineRasterizationConservativeStateCreateInfoEXT::builder()
+        .conservative_rasterization_mode(conservative_mode)
+        .extra_primitive_overestimation_size(extra_overestimation_size)
+        .build();
+    if desc.rasterizer.conservative.is_some() {
+        conservative_state.p_next = rasterization_state.p_next;
+        rasterization_state.p_next = &conservative_state as *const _ as *const _;
+    }
+
+    // Pipelines used within `VK_KHR_dynamic_rendering` have no render pass
+    let mut rendering_info = desc.subpass.main_pass.rendering.as_ref().map(|formats| {
+        ext::dynamic_rendering::PipelineRenderingCreateInfo::new(
+            &formats.colors,
+            formats.depth,
+            formats.stencil,
+        )
+    });
+
+    let info = {
+        let (base_handle, base_index) = match desc.parent {
+            pso::BasePipeline::Pipeline(pipeline) => (pipeline.0, -1),
+            pso::BasePipeline::Index(index) => (vk::Pipeline::null(), index as _),
+            pso::BasePipeline::None => (vk::Pipeline::null(), -1),
+        };
+
+        let mut flags = vk::PipelineCreateFlags::empty();
+        match desc.parent {
+            pso::BasePipeline::None => (),
+            _ => {
+                flags |= vk::PipelineCreateFlags::DERIVATIVE;
+            }
+        }
+        if desc
+            .flags
+            .contains(pso::PipelineCreationFlags::DISABLE_OPTIMIZATION)
+        {
+            flags |= vk::PipelineCreateFlags::DISABLE_OPTIMIZATION;
+        }
+        if desc
+            .flags
+            .contains(pso::PipelineCreationFlags::ALLOW_DERIVATIVES)
+        {
+            flags |= vk::PipelineCreateFlags::ALLOW_DERIVATIVES;
+        }
+        if desc
+            .flags
+            .contains(pso::PipelineCreationFlags::FAIL_ON_PIPELINE_COMPILE_REQUIRED)
+        {
+            flags |= ext::pipeline_creation_cache_control::FAIL_ON_PIPELINE_COMPILE_REQUIRED;
+        }
+
+        let builder = vk::GraphicsPipelineCreateInfo::builder()
+            .flags(flags)
+            .stages(&buf.stages)
+            .vertex_input_state(&buf.vertex_input_state)
+            .input_assembly_state(&buf.input_assembly_state)
+            .rasterization_state(&rasterization_state);
+        let builder = match buf.tessellation_state.as_ref() {
+            Some(t) => builder.tessellation_state(t),
+            None => builder,
+        };
+        let builder = builder
+            .viewport_state(&buf.viewport_state)
+            .multisample_state(&multisample_state)
+            .depth_stencil_state(&buf.depth_stencil_state)
+            .color_blend_state(&buf.color_blend_state)
+            .dynamic_state(&buf.pipeline_dynamic_state)
+            .layout(desc.layout.raw)
+            .render_pass(desc.subpass.main_pass.raw)
+            .subpass(desc.subpass.index as _)
+            .base_pipeline_handle(base_handle)
+            .base_pipeline_index(base_index);
+        match rendering_info {
+            Some(ref mut rendering_info) => builder.push_next(rendering_info),
+            None => builder,
+        }
+    };
+
+    let mut pipeline = vk::Pipeline::null();
+
+    match device.raw.fp_v1_0().create_graphics_pipelines(
+        device.raw.handle(),
+        cache,
+        1,
+        &*info,
+        ptr::null(),
+        &mut pipeline,
+    ) {
+        vk::Result::SUCCESS => Ok(n::GraphicsPipeline(pipeline)),
+        vk::Result::ERROR_OUT_OF_HOST_MEMORY => Err(d::OutOfMemory::Host.into()),
+        vk::Result::ERROR_OUT_OF_DEVICE_MEMORY => Err(d::OutOfMemory::Device.into()),
+        ext::pipeline_creation_cache_control::PIPELINE_COMPILE_REQUIRED => {
+            Err(pso::CreationError::CompileRequired)
+        }
+        _ => Err(pso::CreationError::Other),
+    }
+}
+
+unsafe fn create_compute_pipeline_raw<'a>(
+    device: &super::RawDevice,
+    desc: &pso::ComputePipelineDesc<'a, B>,
+    cache: vk::PipelineCache,
+) -> Result<n::ComputePipeline, pso::CreationError> {
+    debug!("create_compute_pipeline {:?}", desc);
+    let buf = ComputePipelineInfoBuf::new(desc);
+
+    let info = {
+        let stage = vk::PipelineShaderStageCreateInfo::builder()
+            .flags(vk::PipelineShaderStageCreateFlags::empty())
+            .stage(vk::ShaderStageFlags::COMPUTE)
+            .module(desc.shader.module.raw)
+            .name(buf.c_string.as_c_str())
+            .specialization_info(&buf.specialization);
+
+        let (base_handle, base_index) = match desc.parent {
+            pso::BasePipeline::Pipeline(pipeline) => (pipeline.0, -1),
+            pso::BasePipeline::Index(index) => (vk::Pipeline::null(), index as _),
+            pso::BasePipeline::None => (vk::Pipeline::null(), -1),
+        };
+
+        let mut flags = vk::PipelineCreateFlags::empty();
+        match desc.parent {
+            pso::BasePipeline::None => (),
+            _ => {
+                flags |= vk::PipelineCreateFlags::DERIVATIVE;
+            }
+        }
+        if desc
+            .flags
+            .contains(pso::PipelineCreationFlags::DISABLE_OPTIMIZATION)
+        {
+            flags |= vk::PipelineCreateFlags::DISABLE_OPTIMIZATION;
+        }
+        if desc
+            .flags
+            .contains(pso::PipelineCreationFlags::ALLOW_DERIVATIVES)
+        {
+            flags |= vk::PipelineCreateFlags::ALLOW_DERIVATIVES;
+        }
+        if desc
+            .flags
+            .contains(pso::PipelineCreationFlags::FAIL_ON_PIPELINE_COMPILE_REQUIRED)
+        {
+            flags |= ext::pipeline_creation_cache_control::FAIL_ON_PIPELINE_COMPILE_REQUIRED;
+        }
+
+        vk::ComputePipelineCreateInfo::builder()
+            .flags(flags)
+            .stage(*stage)
+            .layout(desc.layout.raw)
+            .base_pipeline_handle(base_handle)
+            .base_pipeline_index(base_index)
+            .build()
+    };
+
+    let mut pipeline = vk::Pipeline::null();
+
+    match device.raw.fp_v1_0().create_compute_pipelines(
+        device.raw.handle(),
+        cache,
+        1,
+        &info,
+        ptr::null(),
+        &mut pipeline,
+    ) {
+        vk::Result::SUCCESS => {
+            if let Some(name) = desc.label {
+                device.set_object_name(vk::ObjectType::PIPELINE, pipeline, name);
+            }
+            Ok(n::ComputePipeline(pipeline))
+        }
+        vk::Result::ERROR_OUT_OF_HOST_MEMORY => Err(d::OutOfMemory::Host.into()),
+        vk::Result::ERROR_OUT_OF_DEVICE_MEMORY => Err(d::OutOfMemory::Device.into()),
+        ext::pipeline_creation_cache_control::PIPELINE_COMPILE_REQUIRED => {
+            Err(pso::CreationError::CompileRequired)
+        }
+        _ => Err(pso::CreationError::Other),
+    }
+}
+
 impl d::Device<B> for super::Device {
     unsafe fn allocate_memory(
         &self,
         mem_type: MemoryTypeId,
         size: u64,
     ) -> Result<n::Memory, d::AllocationError> {
-        let info = vk::MemoryAllocateInfo::builder()
-            .allocation_size(size)
-            .memory_type_index(self.get_ash_memory_type_index(mem_type));
+        self.allocate_memory_impl(mem_type, size, None, None)
+    }
 
-        let result = self.shared.raw.allocate_memory(&info, None);
+    unsafe fn allocate_memory_with_priority(
+        &self,
+        mem_type: MemoryTypeId,
+        size: u64,
+        priority: Priority,
+    ) -> Result<n::Memory, d::AllocationError> {
+        let priority = if self.shared.memory_priority {
+            Some(priority.max(0.0).min(1.0))
+        } else {
+            None
+        };
+        self.allocate_memory_impl(mem_type, size, priority, None)
+    }
 
-        match result {
-            Ok(memory) => Ok(n::Memory { raw: memory }),
-            Err(vk::Result::ERROR_TOO_MANY_OBJECTS) => Err(d::AllocationError::TooManyObjects),
-            Err(vk::Result::ERROR_OUT_OF_HOST_MEMORY) => Err(d::OutOfMemory::Host.into()),
-            Err(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY) => Err(d::OutOfMemory::Device.into()),
+    unsafe fn set_memory_priority(&self, memory: &n::Memory, priority: Priority) {
+        if let Some(ref functor) = self.shared.extension_fns.pageable_device_local_memory {
+            (functor.set_device_memory_priority)(
+                self.shared.raw.handle(),
+                memory.raw,
+                priority.max(0.0).min(1.0),
+            );
+        }
+    }
+
+    unsafe fn allocate_dedicated_memory(
+        &self,
+        mem_type: MemoryTypeId,
+        size: u64,
+        resource: DedicatedResource<B>,
+    ) -> Result<n::Memory, d::AllocationError> {
+        let dedicated = if self.shared.dedicated_allocation {
+            Some(resource)
+        } else {
+            None
+        };
+        self.allocate_memory_impl(mem_type, size, None, dedicated)
+    }
+
+    #[cfg(unix)]
+    unsafe fn allocate_exportable_memory(
+        &self,
+        mem_type: MemoryTypeId,
+        size: u64,
+    ) -> Result<n::Memory, d::ExternalMemoryError> {
+        self.allocate_dma_buf_impl(mem_type, size, None)
+    }
+
+    #[cfg(unix)]
+    unsafe fn import_dma_buf(
+        &self,
+        mem_type: MemoryTypeId,
+        size: u64,
+        fd: RawFd,
+    ) -> Result<n::Memory, d::ExternalMemoryError> {
+        self.allocate_dma_buf_impl(mem_type, size, Some(fd))
+    }
+
+    #[cfg(unix)]
+    unsafe fn export_dma_buf(&self, memory: &n::Memory) -> Result<RawFd, d::ExternalMemoryError> {
+        let external_memory_fd = match self.shared.extension_fns.external_memory_fd {
+            Some(ref functions) => functions,
+            None => return Err(d::ExternalMemoryError::Unsupported),
+        };
+        let info = vk::MemoryGetFdInfoKHR::builder()
+            .memory(memory.raw)
+            .handle_type(vk::ExternalMemoryHandleTypeFlags::EXTERNAL_MEMORY_HANDLE_TYPE_DMA_BUF);
+
+        let mut fd = -1;
+        match external_memory_fd.get_memory_fd_khr(self.shared.raw.handle(), &*info, &mut fd) {
+            vk::Result::SUCCESS => Ok(fd),
+            vk::Result::ERROR_TOO_MANY_OBJECTS => Err(d::ExternalMemoryError::TooManyObjects),
+            vk::Result::ERROR_OUT_OF_HOST_MEMORY => Err(d::OutOfMemory::Host.into()),
             _ => unreachable!(),
         }
     }
@@ -468,6 +977,9 @@ impl d::Device<B> for super::Device {
         if create_flags.contains(CommandPoolCreateFlags::RESET_INDIVIDUAL) {
             flags |= vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER;
         }
+        if create_flags.contains(CommandPoolCreateFlags::PROTECTED) {
+            flags |= vk::CommandPoolCreateFlags::PROTECTED;
+        }
 
         let info = vk::CommandPoolCreateInfo::builder()
             .flags(flags)
@@ -501,6 +1013,24 @@ impl d::Device<B> for super::Device {
         Is: Iterator<Item = pass::SubpassDesc<'a>>,
         Id: Iterator<Item = pass::SubpassDependency>,
     {
+        let subpasses = subpasses.collect::<SmallVec<[_; 1]>>();
+        if subpasses
+            .iter()
+            .any(|subpass| subpass.shading_rate.is_some())
+        {
+            let fns = self
+                .shared
+                .extension_fns
+                .fragment_shading_rate
+                .as_ref()
+                .expect(
+                "Feature SHADING_RATE_ATTACHMENT must be enabled to use shading rate attachments",
+            );
+            let attachments = attachments.collect::<SmallVec<[_; 4]>>();
+            let dependencies = dependencies.collect::<SmallVec<[_; 2]>>();
+            return create_render_pass2(&self.shared, fns, &attachments, &subpasses, &dependencies);
+        }
+
         let attachments_iter = attachments.map(|attachment| vk::AttachmentDescription {
             flags: vk::AttachmentDescriptionFlags::empty(), // TODO: may even alias!
             format: attachment
@@ -532,6 +1062,7 @@ impl d::Device<B> for super::Device {
 
         let result = inplace_or_alloc_from_iter(attachments_iter, |attachments| {
             let attachment_refs = subpasses
+                .iter()
                 .map(|subpass| {
                     fn make_ref(&(id, layout): &pass::AttachmentRef) -> vk::AttachmentReference {
                         vk::AttachmentReference {
@@ -549,13 +1080,20 @@ impl d::Device<B> for super::Device {
                         .collect::<Box<[_]>>();
                     let resolves = subpass.resolves.iter().map(make_ref).collect::<Box<[_]>>();
 
-                    (colors, depth_stencil, inputs, preserves, resolves)
+                    (
+                        colors,
+                        depth_stencil,
+                        inputs,
+                        preserves,
+                        resolves,
+                        subpass.view_mask,
+                    )
                 })
                 .collect::<Box<[_]>>();
 
             let subpasses = attachment_refs
                 .iter()
-                .map(|(colors, depth_stencil, inputs, preserves, resolves)| {
+                .map(|(colors, depth_stencil, inputs, preserves, resolves, _)| {
                     vk::SubpassDescription {
                         flags: vk::SubpassDescriptionFlags::empty(),
                         pipeline_bind_point: vk::PipelineBindPoint::GRAPHICS,
@@ -578,12 +1116,22 @@ impl d::Device<B> for super::Device {
                 })
                 .collect::<Box<[_]>>();
 
+            let view_masks = attachment_refs
+                .iter()
+                .map(|&(.., view_mask)| view_mask)
+                .collect::<Box<[_]>>();
+
             inplace_or_alloc_from_iter(dependencies_iter, |dependencies| {
-                let info = vk::RenderPassCreateInfo::builder()
+                let mut multiview_info =
+                    vk::RenderPassMultiviewCreateInfo::builder().view_masks(&view_masks);
+                let mut info = vk::RenderPassCreateInfo::builder()
                     .flags(vk::RenderPassCreateFlags::empty())
                     .attachments(&attachments)
                     .subpasses(&subpasses)
                     .dependencies(&dependencies);
+                if view_masks.iter().any(|&mask| mask != 0) {
+                    info = info.push_next(&mut multiview_info);
+                }
 
                 self.shared
                     .raw
@@ -591,6 +1139,7 @@ impl d::Device<B> for super::Device {
                     .map(|raw| n::RenderPass {
                         raw,
                         attachment_count: attachments.len(),
+                        rendering: None,
                     })
             })
         });
@@ -716,74 +1265,34 @@ impl d::Device<B> for super::Device {
         desc: &pso::GraphicsPipelineDesc<'a, B>,
         cache: Option<&n::PipelineCache>,
     ) -> Result<n::GraphicsPipeline, pso::CreationError> {
-        debug!("create_graphics_pipeline {:?}", desc);
-        let buf = GraphicsPipelineInfoBuf::new(desc, &self.shared);
-
-        let info = {
-            let (base_handle, base_index) = match desc.parent {
-                pso::BasePipeline::Pipeline(pipeline) => (pipeline.0, -1),
-                pso::BasePipeline::Index(index) => (vk::Pipeline::null(), index as _),
-                pso::BasePipeline::None => (vk::Pipeline::null(), -1),
-            };
+        let cache = cache.map_or(vk::PipelineCache::null(), |cache| cache.raw);
+        create_graphics_pipeline_raw(&self.shared, desc, cache)
+    }
 
-            let mut flags = vk::PipelineCreateFlags::empty();
-            match desc.parent {
-                pso::BasePipeline::None => (),
-                _ => {
-                    flags |= vk::PipelineCreateFlags::DERIVATIVE;
-                }
-            }
-            if desc
-                .flags
-                .contains(pso::PipelineCreationFlags::DISABLE_OPTIMIZATION)
-            {
-                flags |= vk::PipelineCreateFlags::DISABLE_OPTIMIZATION;
-            }
-            if desc
-                .flags
-                .contains(pso::PipelineCreationFlags::ALLOW_DERIVATIVES)
-            {
-                flags |= vk::PipelineCreateFlags::ALLOW_DERIVATIVES;
+    unsafe fn create_graphics_pipeline_async<'a>(
+        &self,
+        desc: &pso::GraphicsPipelineDesc<'a, B>,
+        cache: Option<&n::PipelineCache>,
+    ) -> pso::PipelineTask<n::GraphicsPipeline> {
+        let cache = cache.map_or(vk::PipelineCache::null(), |cache| cache.raw);
+        let detached = match DetachedGraphicsPipelineDesc::new(desc) {
+            Some(detached) => detached,
+            None => {
+                return pso::PipelineTask::ready(create_graphics_pipeline_raw(
+                    &self.shared,
+                    desc,
+                    cache,
+                ))
             }
-
-            let builder = vk::GraphicsPipelineCreateInfo::builder()
-                .flags(flags)
-                .stages(&buf.stages)
-                .vertex_input_state(&buf.vertex_input_state)
-                .input_assembly_state(&buf.input_assembly_state)
-                .rasterization_state(&buf.rasterization_state);
-            let builder = match buf.tessellation_state.as_ref() {
-                Some(t) => builder.tessellation_state(t),
-                None => builder,
-            };
-            builder
-                .viewport_state(&buf.viewport_state)
-                .multisample_state(&buf.multisample_state)
-                .depth_stencil_state(&buf.depth_stencil_state)
-                .color_blend_state(&buf.color_blend_state)
-                .dynamic_state(&buf.pipeline_dynamic_state)
-                .layout(desc.layout.raw)
-                .render_pass(desc.subpass.main_pass.raw)
-                .subpass(desc.subpass.index as _)
-                .base_pipeline_handle(base_handle)
-                .base_pipeline_index(base_index)
         };
+        let shared = Arc::clone(&self.shared);
 
-        let mut pipeline = vk::Pipeline::null();
-
-        match self.shared.raw.fp_v1_0().create_graphics_pipelines(
-            self.shared.raw.handle(),
-            cache.map_or(vk::PipelineCache::null(), |cache| cache.raw),
-            1,
-            &*info,
-            ptr::null(),
-            &mut pipeline,
-        ) {
-            vk::Result::SUCCESS => Ok(n::GraphicsPipeline(pipeline)),
-            vk::Result::ERROR_OUT_OF_HOST_MEMORY => Err(d::OutOfMemory::Host.into()),
-            vk::Result::ERROR_OUT_OF_DEVICE_MEMORY => Err(d::OutOfMemory::Device.into()),
-            _ => Err(pso::CreationError::Other),
-        }
+        let (sender, task) = pso::PipelineTask::new();
+        self.pipeline_workers.execute(move || {
+            let result = detached.with(|desc| create_graphics_pipeline_raw(&shared, desc, cache));
+            let _ = sender.send(result);
+        });
+        task
     }
 
     unsafe fn create_compute_pipeline<'a>(
@@ -791,73 +1300,34 @@ impl d::Device<B> for super::Device {
         desc: &pso::ComputePipelineDesc<'a, B>,
         cache: Option<&n::PipelineCache>,
     ) -> Result<n::ComputePipeline, pso::CreationError> {
-        debug!("create_graphics_pipeline {:?}", desc);
-        let buf = ComputePipelineInfoBuf::new(desc);
-
-        let info = {
-            let stage = vk::PipelineShaderStageCreateInfo::builder()
-                .flags(vk::PipelineShaderStageCreateFlags::empty())
-                .stage(vk::ShaderStageFlags::COMPUTE)
-                .module(desc.shader.module.raw)
-                .name(buf.c_string.as_c_str())
-                .specialization_info(&buf.specialization);
-
-            let (base_handle, base_index) = match desc.parent {
-                pso::BasePipeline::Pipeline(pipeline) => (pipeline.0, -1),
-                pso::BasePipeline::Index(index) => (vk::Pipeline::null(), index as _),
-                pso::BasePipeline::None => (vk::Pipeline::null(), -1),
-            };
+        let cache = cache.map_or(vk::PipelineCache::null(), |cache| cache.raw);
+        create_compute_pipeline_raw(&self.shared, desc, cache)
+    }
 
-            let mut flags = vk::PipelineCreateFlags::empty();
-            match desc.parent {
-                pso::BasePipeline::None => (),
-                _ => {
-                    flags |= vk::PipelineCreateFlags::DERIVATIVE;
-                }
-            }
-            if desc
-                .flags
-                .contains(pso::PipelineCreationFlags::DISABLE_OPTIMIZATION)
-            {
-                flags |= vk::PipelineCreateFlags::DISABLE_OPTIMIZATION;
-            }
-            if desc
-                .flags
-                .contains(pso::PipelineCreationFlags::ALLOW_DERIVATIVES)
-            {
-                flags |= vk::PipelineCreateFlags::ALLOW_DERIVATIVES;
+    unsafe fn create_compute_pipeline_async<'a>(
+        &self,
+        desc: &pso::ComputePipelineDesc<'a, B>,
+        cache: Option<&n::PipelineCache>,
+    ) -> pso::PipelineTask<n::ComputePipeline> {
+        let cache = cache.map_or(vk::PipelineCache::null(), |cache| cache.raw);
+        let detached = match DetachedComputePipelineDesc::new(desc) {
+            Some(detached) => detached,
+            None => {
+                return pso::PipelineTask::ready(create_compute_pipeline_raw(
+                    &self.shared,
+                    desc,
+                    cache,
+                ))
             }
-
-            vk::ComputePipelineCreateInfo::builder()
-                .flags(flags)
-                .stage(*stage)
-                .layout(desc.layout.raw)
-                .base_pipeline_handle(base_handle)
-                .base_pipeline_index(base_index)
-                .build()
         };
+        let shared = Arc::clone(&self.shared);
 
-        let mut pipeline = vk::Pipeline::null();
-
-        match self.shared.raw.fp_v1_0().create_compute_pipelines(
-            self.shared.raw.handle(),
-            cache.map_or(vk::PipelineCache::null(), |cache| cache.raw),
-            1,
-            &info,
-            ptr::null(),
-            &mut pipeline,
-        ) {
-            vk::Result::SUCCESS => {
-                if let Some(name) = desc.label {
-                    self.shared
-                        .set_object_name(vk::ObjectType::PIPELINE, pipeline, name);
-                }
-                Ok(n::ComputePipeline(pipeline))
-            }
-            vk::Result::ERROR_OUT_OF_HOST_MEMORY => Err(d::OutOfMemory::Host.into()),
-            vk::Result::ERROR_OUT_OF_DEVICE_MEMORY => Err(d::OutOfMemory::Device.into()),
-            _ => Err(pso::CreationError::Other),
-        }
+        let (sender, task) = pso::PipelineTask::new();
+        self.pipeline_workers.execute(move || {
+            let result = detached.with(|desc| create_compute_pipeline_raw(&shared, desc, cache));
+            let _ = sender.send(result);
+        });
+        task
     }
 
     unsafe fn create_framebuffer<T>(
@@ -871,8 +1341,8 @@ impl d::Device<B> for super::Device {
     {
         if !self.shared.imageless_framebuffers {
             return Ok(n::Framebuffer::Legacy {
+                owner: self.shared.rendering_cache.lock().add_owner(),
                 name: String::new(),
-                map: Default::default(),
                 extent,
             });
         }
@@ -955,51 +1425,42 @@ impl d::Device<B> for super::Device {
         &self,
         desc: &image::SamplerDesc,
     ) -> Result<n::Sampler, d::AllocationError> {
-        use hal::pso::Comparison;
-
-        let (anisotropy_enable, max_anisotropy) =
-            desc.anisotropy_clamp.map_or((false, 1.0), |aniso| {
-                if self.shared.features.contains(Features::SAMPLER_ANISOTROPY) {
-                    (true, aniso as f32)
-                } else {
-                    warn!(
-                        "Anisotropy({}) was requested on a device with disabled feature",
-                        aniso
-                    );
-                    (false, 1.0)
-                }
-            });
-        let info = vk::SamplerCreateInfo::builder()
-            .flags(vk::SamplerCreateFlags::empty())
-            .mag_filter(conv::map_filter(desc.mag_filter))
-            .min_filter(conv::map_filter(desc.min_filter))
-            .mipmap_mode(conv::map_mip_filter(desc.mip_filter))
-            .address_mode_u(conv::map_wrap(desc.wrap_mode.0))
-            .address_mode_v(conv::map_wrap(desc.wrap_mode.1))
-            .address_mode_w(conv::map_wrap(desc.wrap_mode.2))
-            .mip_lod_bias(desc.lod_bias.0)
-            .anisotropy_enable(anisotropy_enable)
-            .max_anisotropy(max_anisotropy)
-            .compare_enable(desc.comparison.is_some())
-            .compare_op(conv::map_comparison(
-                desc.comparison.unwrap_or(Comparison::Never),
-            ))
-            .min_lod(desc.lod_range.start.0)
-            .max_lod(desc.lod_range.end.0)
-            .border_color(conv::map_border_color(desc.border))
-            .unnormalized_coordinates(!desc.normalized);
+        self.create_sampler_impl(desc, None)
+    }
 
-        let result = self.shared.raw.create_sampler(&info, None);
+    unsafe fn create_sampler_ycbcr_conversion(
+        &self,
+        desc: &image::SamplerYcbcrConversionDesc,
+    ) -> Result<n::SamplerYcbcrConversion, image::CreationError> {
+        let format = conv::map_format(desc.format);
+        let info = vk::SamplerYcbcrConversionCreateInfo::builder()
+            .format(format)
+            .ycbcr_model(conv::map_ycbcr_model(desc.model))
+            .ycbcr_range(conv::map_ycbcr_range(desc.range))
+            .components(conv::map_swizzle(desc.swizzle))
+            .x_chroma_offset(conv::map_chroma_location(desc.x_chroma_offset))
+            .y_chroma_offset(conv::map_chroma_location(desc.y_chroma_offset))
+            .chroma_filter(conv::map_filter(desc.chroma_filter))
+            .force_explicit_reconstruction(desc.force_explicit_reconstruction);
+
+        let result = self.shared.raw.create_sampler_ycbcr_conversion(&info, None);
 
         match result {
-            Ok(sampler) => Ok(n::Sampler(sampler)),
-            Err(vk::Result::ERROR_TOO_MANY_OBJECTS) => Err(d::AllocationError::TooManyObjects),
+            Ok(raw) => Ok(n::SamplerYcbcrConversion { raw, format }),
             Err(vk::Result::ERROR_OUT_OF_HOST_MEMORY) => Err(d::OutOfMemory::Host.into()),
             Err(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY) => Err(d::OutOfMemory::Device.into()),
             _ => unreachable!(),
         }
     }
 
+    unsafe fn create_ycbcr_sampler(
+        &self,
+        desc: &image::SamplerDesc,
+        conversion: &n::SamplerYcbcrConversion,
+    ) -> Result<n::Sampler, d::AllocationError> {
+        self.create_sampler_impl(desc, Some(conversion.raw))
+    }
+
     ///
     unsafe fn create_buffer(
         &self,
@@ -1023,12 +1484,17 @@ impl d::Device<B> for super::Device {
     }
 
     unsafe fn get_buffer_requirements(&self, buffer: &n::Buffer) -> Requirements {
-        let req = self.shared.raw.get_buffer_memory_requirements(buffer.raw);
-
-        Requirements {
-            size: req.size,
-            alignment: req.alignment,
-            type_mask: self.filter_memory_requirements(req.memory_type_bits),
+        if self.shared.dedicated_allocation {
+            let info = vk::BufferMemoryRequirementsInfo2::builder().buffer(buffer.raw);
+            let mut dedicated = vk::MemoryDedicatedRequirements::builder();
+            let mut req = vk::MemoryRequirements2::builder().push_next(&mut dedicated);
+            self.shared
+                .raw
+                .get_buffer_memory_requirements2(&info, &mut req);
+            self.map_requirements(req.memory_requirements, &dedicated)
+        } else {
+            let req = self.shared.raw.get_buffer_memory_requirements(buffer.raw);
+            self.map_requirements(req, &vk::MemoryDedicatedRequirements::default())
         }
     }
 
@@ -1083,57 +1549,64 @@ impl d::Device<B> for super::Device {
         usage: image::Usage,
         view_caps: image::ViewCapabilities,
     ) -> Result<n::Image, image::CreationError> {
-        let flags = conv::map_view_capabilities(view_caps);
-        let extent = conv::map_extent(kind.extent());
-        let array_layers = kind.num_layers();
-        let samples = kind.num_samples();
-        let image_type = match kind {
-            image::Kind::D1(..) => vk::ImageType::TYPE_1D,
-            image::Kind::D2(..) => vk::ImageType::TYPE_2D,
-            image::Kind::D3(..) => vk::ImageType::TYPE_3D,
-        };
-
-        //Note: this is a hack, we should expose this in the API instead
-        let layout = match tiling {
-            image::Tiling::Linear => vk::ImageLayout::PREINITIALIZED,
-            image::Tiling::Optimal => vk::ImageLayout::UNDEFINED,
-        };
+        self.create_image_impl(kind, mip_levels, format, tiling, usage, view_caps, None)
+    }
 
-        let info = vk::ImageCreateInfo::builder()
-            .flags(flags)
-            .image_type(image_type)
-            .format(conv::map_format(format))
-            .extent(extent.clone())
-            .mip_levels(mip_levels as u32)
-            .array_layers(array_layers as u32)
-            .samples(conv::map_sample_count_flags(samples))
-            .tiling(conv::map_tiling(tiling))
-            .usage(conv::map_image_usage(usage))
-            .sharing_mode(vk::SharingMode::EXCLUSIVE) // TODO:
-            .initial_layout(layout);
+    unsafe fn create_image_with_drm_modifier(
+        &self,
+        kind: image::Kind,
+        format: format::Format,
+        tiling: image::DrmTiling,
+        usage: image::Usage,
+        view_caps: image::ViewCapabilities,
+    ) -> Result<n::Image, image::CreationError> {
+        if !self.shared.features.contains(Features::DRM_FORMAT_MODIFIER) {
+            return Err(image::CreationError::DrmModifier);
+        }
+        match kind {
+            image::Kind::D2(_, _, 1, 1) => {}
+            _ => return Err(image::CreationError::Kind),
+        }
+        self.create_image_impl(
+            kind,
+            1,
+            format,
+            image::Tiling::Optimal,
+            usage,
+            view_caps,
+            Some(tiling),
+        )
+    }
 
-        let result = self.shared.raw.create_image(&info, None);
-
-        match result {
-            Ok(raw) => Ok(n::Image {
-                raw,
-                ty: image_type,
-                flags,
-                extent,
-            }),
-            Err(vk::Result::ERROR_OUT_OF_HOST_MEMORY) => Err(d::OutOfMemory::Host.into()),
-            Err(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY) => Err(d::OutOfMemory::Device.into()),
-            _ => unreachable!(),
+    unsafe fn get_image_drm_modifier(&self, image: &n::Image) -> Option<image::DrmModifier> {
+        // The query is only valid for images created with a modifier.
+        if image.tiling != vk::ImageTiling::DRM_FORMAT_MODIFIER_EXT {
+            return None;
+        }
+        let drm_format_modifier = self.shared.extension_fns.drm_format_modifier.as_ref()?;
+        let mut properties = vk::ImageDrmFormatModifierPropertiesEXT::builder().build();
+        match drm_format_modifier.get_image_drm_format_modifier_properties_ext(
+            self.shared.raw.handle(),
+            image.raw,
+            &mut properties,
+        ) {
+            vk::Result::SUCCESS => Some(image::DrmModifier(properties.drm_format_modifier)),
+            _ => None,
         }
     }
 
     unsafe fn get_image_requirements(&self, image: &n::Image) -> Requirements {
-        let req = self.shared.raw.get_image_memory_requirements(image.raw);
-
-        Requirements {
-            size: req.size,
-            alignment: req.alignment,
-            type_mask: self.filter_memory_requirements(req.memory_type_bits),
+        if self.shared.dedicated_allocation {
+            let info = vk::ImageMemoryRequirementsInfo2::builder().image(image.raw);
+            let mut dedicated = vk::MemoryDedicatedRequirements::builder();
+            let mut req = vk::MemoryRequirements2::builder().push_next(&mut dedicated);
+            self.shared
+                .raw
+                .get_image_memory_requirements2(&info, &mut req);
+            self.map_requirements(req.memory_requirements, &dedicated)
+        } else {
+            let req = self.shared.raw.get_image_memory_requirements(image.raw);
+            self.map_requirements(req, &vk::MemoryDedicatedRequirements::default())
         }
     }
 
@@ -1182,32 +1655,57 @@ impl d::Device<B> for super::Device {
         swizzle: format::Swizzle,
         range: image::SubresourceRange,
     ) -> Result<n::ImageView, image::ViewCreationError> {
-        let is_cube = image
-            .flags
-            .intersects(vk::ImageCreateFlags::CUBE_COMPATIBLE);
-        let info = vk::ImageViewCreateInfo::builder()
-            .flags(vk::ImageViewCreateFlags::empty())
-            .image(image.raw)
-            .view_type(match conv::map_view_kind(kind, image.ty, is_cube) {
-                Some(ty) => ty,
-                None => return Err(image::ViewCreationError::BadKind(kind)),
-            })
-            .format(conv::map_format(format))
-            .components(conv::map_swizzle(swizzle))
-            .subresource_range(conv::map_subresource_range(&range));
-
-        let result = self.shared.raw.create_image_view(&info, None);
+        self.create_image_view_impl(
+            image,
+            kind,
+            conv::map_format(format),
+            conv::map_swizzle(swizzle),
+            range,
+            None,
+            None,
+        )
+    }
 
-        match result {
-            Ok(raw) => Ok(n::ImageView {
-                image: image.raw,
-                raw,
-                range,
-            }),
-            Err(vk::Result::ERROR_OUT_OF_HOST_MEMORY) => Err(d::OutOfMemory::Host.into()),
-            Err(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY) => Err(d::OutOfMemory::Device.into()),
-            _ => unreachable!(),
+    unsafe fn create_image_view_with_min_lod(
+        &self,
+        image: &n::Image,
+        kind: image::ViewKind,
+        format: format::Format,
+        swizzle: format::Swizzle,
+        range: image::SubresourceRange,
+        min_lod: f32,
+    ) -> Result<n::ImageView, image::ViewCreationError> {
+        if !self.shared.features.contains(Features::IMAGE_VIEW_MIN_LOD) {
+            warn!("Image view min LOD requires `Features::IMAGE_VIEW_MIN_LOD`");
+            return Err(image::ViewCreationError::Unsupported);
         }
+        self.create_image_view_impl(
+            image,
+            kind,
+            conv::map_format(format),
+            conv::map_swizzle(swizzle),
+            range,
+            None,
+            Some(min_lod),
+        )
+    }
+
+    unsafe fn create_ycbcr_image_view(
+        &self,
+        image: &n::Image,
+        kind: image::ViewKind,
+        range: image::SubresourceRange,
+        conversion: &n::SamplerYcbcrConversion,
+    ) -> Result<n::ImageView, image::ViewCreationError> {
+        self.create_image_view_impl(
+            image,
+            kind,
+            conversion.format,
+            vk::ComponentMapping::default(),
+            range,
+            Some(conversion.raw),
+            None,
+        )
     }
 
     unsafe fn create_descriptor_pool<T>(
@@ -1225,10 +1723,22 @@ impl d::Device<B> for super::Device {
         });
 
         let result = inplace_or_alloc_from_iter(pools_iter, |pools| {
-            let info = vk::DescriptorPoolCreateInfo::builder()
+            let inline_uniform_block_ranges = pools
+                .iter()
+                .filter(|pool| pool.ty == vk::DescriptorType::INLINE_UNIFORM_BLOCK_EXT)
+                .count();
+            let mut inline_uniform_block_info =
+                vk::DescriptorPoolInlineUniformBlockCreateInfoEXT::builder()
+                    .max_inline_uniform_block_bindings(
+                        (inline_uniform_block_ranges * max_sets) as u32,
+                    );
+            let mut info = vk::DescriptorPoolCreateInfo::builder()
                 .flags(conv::map_descriptor_pool_create_flags(flags))
                 .max_sets(max_sets as u32)
                 .pool_sizes(&pools);
+            if inline_uniform_block_ranges != 0 {
+                info = info.push_next(&mut inline_uniform_block_info);
+            }
 
             self.shared.raw.create_descriptor_pool(&info, None)
         });
@@ -1305,6 +1815,7 @@ impl d::Device<B> for super::Device {
         let mut image_infos = Vec::new();
         let mut buffer_infos = Vec::new();
         let mut texel_buffer_views = Vec::new();
+        let mut inline_uniform_blocks = Vec::new();
 
         // gfx-hal allows the type and stages to be different between the descriptor
         // in a single write, while Vulkan requires them to be the same.
@@ -1320,6 +1831,33 @@ impl d::Device<B> for super::Device {
 
         for descriptor in descriptors {
             let layout_binding = &op.set.bindings[binding_pos];
+
+            // Inline uniform blocks are addressed in bytes, and each write
+            // covers exactly one binding, so they never get merged.
+            if let pso::Descriptor::InlineUniformBlock(data) = descriptor {
+                raw_writes.push(vk::WriteDescriptorSet {
+                    s_type: vk::StructureType::WRITE_DESCRIPTOR_SET,
+                    p_next: inline_uniform_blocks.len() as _,
+                    dst_set: op.set.raw,
+                    dst_binding: layout_binding.binding,
+                    dst_array_element: array_offset as _,
+                    descriptor_count: data.len() as _,
+                    descriptor_type: vk::DescriptorType::INLINE_UNIFORM_BLOCK_EXT,
+                    p_image_info: ptr::null(),
+                    p_buffer_info: ptr::null(),
+                    p_texel_buffer_view: ptr::null(),
+                });
+                inline_uniform_blocks.push(
+                    vk::WriteDescriptorSetInlineUniformBlockEXT::builder()
+                        .data(data)
+                        .build(),
+                );
+                last_type = vk::DescriptorType::INLINE_UNIFORM_BLOCK_EXT;
+                array_offset = 0;
+                binding_pos += 1;
+                continue;
+            }
+
             array_offset += 1;
             if array_offset == layout_binding.count {
                 array_offset = 0;
@@ -1390,6 +1928,35 @@ impl d::Device<B> for super::Device {
                 pso::Descriptor::TexelBuffer(view) => {
                     texel_buffer_views.push(view.raw);
                 }
+                pso::Descriptor::Null => match descriptor_type {
+                    vk::DescriptorType::UNIFORM_TEXEL_BUFFER
+                    | vk::DescriptorType::STORAGE_TEXEL_BUFFER => {
+                        texel_buffer_views.push(vk::BufferView::null());
+                    }
+                    vk::DescriptorType::UNIFORM_BUFFER
+                    | vk::DescriptorType::STORAGE_BUFFER
+                    | vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC
+                    | vk::DescriptorType::STORAGE_BUFFER_DYNAMIC => {
+                        // Null buffers have to use the whole range
+                        buffer_infos.push(
+                            vk::DescriptorBufferInfo::builder()
+                                .buffer(vk::Buffer::null())
+                                .offset(0)
+                                .range(vk::WHOLE_SIZE)
+                                .build(),
+                        );
+                    }
+                    _ => {
+                        image_infos.push(
+                            vk::DescriptorImageInfo::builder()
+                                .sampler(vk::Sampler::null())
+                                .image_view(vk::ImageView::null())
+                                .image_layout(vk::ImageLayout::GENERAL)
+                                .build(),
+                        );
+                    }
+                },
+                pso::Descriptor::InlineUniformBlock(_) => unreachable!(),
             }
         }
 
@@ -1420,6 +1987,10 @@ impl d::Device<B> for super::Device {
                     raw.p_texel_buffer_view = ptr::null();
                     raw.p_buffer_info = buffer_infos[raw.p_buffer_info as usize..].as_ptr();
                 }
+                Dt::INLINE_UNIFORM_BLOCK_EXT => {
+                    raw.p_next =
+                        &inline_uniform_blocks[raw.p_next as usize] as *const _ as *const _;
+                }
                 _ => panic!("unknown descriptor type"),
             }
         }
@@ -1466,6 +2037,90 @@ impl d::Device<B> for super::Device {
         self.shared.raw.unmap_memory(memory.raw)
     }
 
+    unsafe fn copy_memory_to_image<I>(
+        &self,
+        data: &[u8],
+        image: &n::Image,
+        layout: image::Layout,
+        regions: I,
+    ) -> Result<(), d::MapError>
+    where
+        I: Iterator<Item = com::BufferImageCopy>,
+    {
+        let functor = self
+            .shared
+            .extension_fns
+            .host_image_copy
+            .as_ref()
+            .expect("Feature HOST_IMAGE_COPY must be enabled to call copy_memory_to_image");
+        let regions =
+            map_host_copy_regions(data.as_ptr() as *mut u8, data.len(), true, regions)?;
+
+        let layout = if layout == image::Layout::Undefined {
+            let transitions = regions
+                .iter()
+                .map(|r| {
+                    let layers = r.image_subresource;
+                    ext::host_image_copy::HostImageLayoutTransitionInfo::new(
+                        image.raw,
+                        vk::ImageLayout::UNDEFINED,
+                        vk::ImageLayout::GENERAL,
+                        vk::ImageSubresourceRange {
+                            aspect_mask: layers.aspect_mask,
+                            base_mip_level: layers.mip_level,
+                            level_count: 1,
+                            base_array_layer: layers.base_array_layer,
+                            layer_count: layers.layer_count,
+                        },
+                    )
+                })
+                .collect::<SmallVec<[_; 4]>>();
+            map_host_copy_result((functor.transition_image_layout)(
+                self.shared.raw.handle(),
+                transitions.len() as u32,
+                transitions.as_ptr(),
+            ))?;
+            vk::ImageLayout::GENERAL
+        } else {
+            conv::map_image_layout(layout)
+        };
+
+        let info = ext::host_image_copy::CopyInfo::memory_to_image(image.raw, layout, &regions);
+        map_host_copy_result((functor.copy_memory_to_image)(
+            self.shared.raw.handle(),
+            &info,
+        ))
+    }
+
+    unsafe fn copy_image_to_memory<I>(
+        &self,
+        image: &n::Image,
+        layout: image::Layout,
+        data: &mut [u8],
+        regions: I,
+    ) -> Result<(), d::MapError>
+    where
+        I: Iterator<Item = com::BufferImageCopy>,
+    {
+        let functor = self
+            .shared
+            .extension_fns
+            .host_image_copy
+            .as_ref()
+            .expect("Feature HOST_IMAGE_COPY must be enabled to call copy_image_to_memory");
+        let regions = map_host_copy_regions(data.as_mut_ptr(), data.len(), false, regions)?;
+
+        let info = ext::host_image_copy::CopyInfo::image_to_memory(
+            image.raw,
+            conv::map_image_layout(layout),
+            &regions,
+        );
+        map_host_copy_result((functor.copy_image_to_memory)(
+            self.shared.raw.handle(),
+            &info,
+        ))
+    }
+
     unsafe fn flush_mapped_memory_ranges<'a, I>(&self, ranges: I) -> Result<(), d::OutOfMemory>
     where
         I: Iterator<Item = (&'a n::Memory, Segment)>,
@@ -1699,7 +2354,84 @@ impl d::Device<B> for super::Device {
         self.shared.raw.destroy_shader_module(module.raw, None);
     }
 
+    unsafe fn create_rendering_pass<I>(
+        &self,
+        colors: I,
+        depth_stencil: Option<pass::Attachment>,
+    ) -> Result<n::RenderPass, d::OutOfMemory>
+    where
+        I: Iterator<Item = pass::Attachment>,
+    {
+        if self.shared.extension_fns.dynamic_rendering.is_none() {
+            // Compatible with the render passes of the `RenderingCache`.
+            let mut attachments = colors.collect::<Vec<_>>();
+            let color_refs = (0..attachments.len())
+                .map(|id| (id, image::Layout::ColorAttachmentOptimal))
+                .collect::<Vec<_>>();
+            let depth_stencil_ref = depth_stencil.map(|attachment| {
+                attachments.push(attachment);
+                (
+                    color_refs.len(),
+                    image::Layout::DepthStencilAttachmentOptimal,
+                )
+            });
+            let subpass = pass::SubpassDesc {
+                colors: &color_refs,
+                depth_stencil: depth_stencil_ref.as_ref(),
+                inputs: &[],
+                resolves: &[],
+                preserves: &[],
+                view_mask: 0,
+                shading_rate: None,
+            };
+            return self.create_render_pass(
+                attachments.into_iter(),
+                iter::once(subpass),
+                iter::empty(),
+            );
+        }
+
+        let colors = colors
+            .map(|at| at.format.map_or(vk::Format::UNDEFINED, conv::map_format))
+            .collect::<SmallVec<_>>();
+        let (depth, stencil) = match depth_stencil.as_ref().and_then(|at| at.format) {
+            Some(format) => {
+                let aspects = format.surface_desc().aspects;
+                let raw_format = conv::map_format(format);
+                (
+                    if aspects.contains(format::Aspects::DEPTH) {
+                        raw_format
+                    } else {
+                        vk::Format::UNDEFINED
+                    },
+                    if aspects.contains(format::Aspects::STENCIL) {
+                        raw_format
+                    } else {
+                        vk::Format::UNDEFINED
+                    },
+                )
+            }
+            None => (vk::Format::UNDEFINED, vk::Format::UNDEFINED),
+        };
+        Ok(n::RenderPass {
+            raw: vk::RenderPass::null(),
+            attachment_count: colors.len() + depth_stencil.is_some() as usize,
+            rendering: Some(n::RenderingFormats {
+                colors,
+                depth,
+                stencil,
+            }),
+        })
+    }
+
     unsafe fn destroy_render_pass(&self, rp: n::RenderPass) {
+        if rp.rendering.is_some() {
+            return;
+        }
+        self.shared
+            .rendering_cache
+            .lock()
+            .remove_render_pass(&self.shared.raw, rp.raw);
         self.shared.raw.destroy_render_pass(rp.raw, None);
     }
 
@@ -1720,10 +2452,11 @@ impl d::Device<B> for super::Device {
             n::Framebuffer::ImageLess(raw) => {
                 self.shared.raw.destroy_framebuffer(raw, None);
             }
-            n::Framebuffer::Legacy { map, .. } => {
-                for (_, raw) in map.into_inner() {
-                    self.shared.raw.destroy_framebuffer(raw, None);
-                }
+            n::Framebuffer::Legacy { owner, .. } => {
+                self.shared
+                    .rendering_cache
+                    .lock()
+                    .remove_owner(&self.shared.raw, owner);
             }
         }
     }
@@ -1741,6 +2474,10 @@ impl d::Device<B> for super::Device {
     }
 
     unsafe fn destroy_image_view(&self, view: n::ImageView) {
+        self.shared
+            .rendering_cache
+            .lock()
+            .remove_view(&self.shared.raw, view.raw);
         self.shared.raw.destroy_image_view(view.raw, None);
     }
 
@@ -1748,6 +2485,12 @@ impl d::Device<B> for super::Device {
         self.shared.raw.destroy_sampler(sampler.0, None);
     }
 
+    unsafe fn destroy_sampler_ycbcr_conversion(&self, conversion: n::SamplerYcbcrConversion) {
+        self.shared
+            .raw
+            .destroy_sampler_ycbcr_conversion(conversion.raw, None);
+    }
+
     unsafe fn destroy_descriptor_pool(&self, pool: n::DescriptorPool) {
         self.shared.raw.destroy_descriptor_pool(pool.finish(), None);
     }
@@ -1763,6 +2506,8 @@ impl d::Device<B> for super::Device {
     }
 
     unsafe fn destroy_semaphore(&self, semaphore: n::Semaphore) {
+        #[cfg(debug_assertions)]
+        self.shared.semaphore_tracker.destroy_semaphore(semaphore.0);
         self.shared.raw.destroy_semaphore(semaphore.0, None);
     }
 
@@ -1770,15 +2515,59 @@ impl d::Device<B> for super::Device {
         self.shared.raw.destroy_event(event.0, None);
     }
 
-    fn wait_idle(&self) -> Result<(), d::OutOfMemory> {
+    fn wait_idle(&self) -> Result<(), d::WaitError> {
         match unsafe { self.shared.raw.device_wait_idle() } {
             Ok(()) => Ok(()),
-            Err(vk::Result::ERROR_OUT_OF_HOST_MEMORY) => Err(d::OutOfMemory::Host),
-            Err(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY) => Err(d::OutOfMemory::Device),
+            Err(vk::Result::ERROR_OUT_OF_HOST_MEMORY) => Err(d::OutOfMemory::Host.into()),
+            Err(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY) => Err(d::OutOfMemory::Device.into()),
+            Err(vk::Result::ERROR_DEVICE_LOST) => Err(d::DeviceLost.into()),
             _ => unreachable!(),
         }
     }
 
+    fn get_device_lost_reason(&self) -> Option<d::DeviceLostReason> {
+        let mut reason = self.get_device_fault_info();
+        if let Some(ref functor) = self.shared.extension_fns.checkpoints {
+            let reason = reason.get_or_insert_with(|| d::DeviceLostReason {
+                cause: d::DeviceLostCause::Unknown,
+                description: String::new(),
+                fault_address: None,
+                last_checkpoint: None,
+                pending_checkpoints: Vec::new(),
+            });
+            let labels = self.shared.checkpoint_labels.lock();
+            for &queue in &self.shared.queues {
+                let mut count = 0;
+                unsafe {
+                    functor.get_queue_checkpoint_data_nv(queue, &mut count, ptr::null_mut());
+                }
+                let mut data = vec![vk::CheckpointDataNV::default(); count as usize];
+                unsafe {
+                    functor.get_queue_checkpoint_data_nv(queue, &mut count, data.as_mut_ptr());
+                }
+                data.truncate(count as usize);
+
+                // The last marker that reached the bottom of the pipe is passed,
+                // while the markers seen by the earlier stages are still in flight.
+                for checkpoint in &data {
+                    let label = match labels.label(checkpoint.p_checkpoint_marker) {
+                        Some(label) => label.to_string(),
+                        None => continue,
+                    };
+                    if checkpoint.stage == vk::PipelineStageFlags::BOTTOM_OF_PIPE {
+                        reason.last_checkpoint = Some(label);
+                    } else if !reason.pending_checkpoints.contains(&label) {
+                        reason.pending_checkpoints.push(label);
+                    }
+                }
+            }
+            if let Some(ref last) = reason.last_checkpoint {
+                reason.pending_checkpoints.retain(|label| label != last);
+            }
+        }
+        reason
+    }
+
     unsafe fn set_image_name(&self, image: &mut n::Image, name: &str) {
         self.shared
             .set_object_name(vk::ObjectType::IMAGE, image.raw, name)
@@ -1790,11 +2579,19 @@ impl d::Device<B> for super::Device {
     }
 
     unsafe fn set_command_buffer_name(&self, command_buffer: &mut cmd::CommandBuffer, name: &str) {
+        #[cfg(debug_assertions)]
+        self.shared
+            .semaphore_tracker
+            .set_command_buffer_name(command_buffer.raw, name);
         self.shared
             .set_object_name(vk::ObjectType::COMMAND_BUFFER, command_buffer.raw, name)
     }
 
     unsafe fn set_semaphore_name(&self, semaphore: &mut n::Semaphore, name: &str) {
+        #[cfg(debug_assertions)]
+        self.shared
+            .semaphore_tracker
+            .set_semaphore_name(semaphore.0, name);
         self.shared
             .set_object_name(vk::ObjectType::SEMAPHORE, semaphore.0, name)
     }
@@ -1811,13 +2608,13 @@ impl d::Device<B> for super::Device {
                     .set_object_name(vk::ObjectType::FRAMEBUFFER, raw, name);
             }
             n::Framebuffer::Legacy {
+                owner,
                 name: ref mut old_name,
-                ref mut map,
                 extent: _,
             } => {
                 old_name.clear();
                 old_name.push_str(name);
-                for &raw in map.get_mut().values() {
+                for raw in self.shared.rendering_cache.lock().owned_framebuffers(owner) {
                     self.shared
                         .set_object_name(vk::ObjectType::FRAMEBUFFER, raw, name);
                 }
@@ -1853,7 +2650,453 @@ impl d::Device<B> for super::Device {
     }
 }
 
+/// Interoperability with objects created by `ash`-based code.
+///
+/// The objects created from raw handles take over their ownership: destroying them
+/// with the `hal::device::Device` methods destroys the Vulkan objects. The native objects
+/// don't destroy anything when dropped, so dropping them after taking their raw handles
+/// out hands the ownership back.
+impl super::Device {
+    /// Returns the raw `ash` device.
+    pub fn raw_device(&self) -> &ash::Device {
+        &self.shared.raw
+    }
+
+    /// Returns the physical device this device was created from.
+    pub fn raw_physical_device(&self) -> vk::PhysicalDevice {
+        self.shared.physical_device
+    }
+
+    /// Wrap a raw buffer created on this device.
+    ///
+    /// # Safety
+    ///
+    /// The buffer must be a valid handle created from this device.
+    pub unsafe fn buffer_from_raw(&self, raw: vk::Buffer) -> n::Buffer {
+        n::Buffer { raw }
+    }
+
+    /// Wrap a raw image created on this device, with the `kind`, `tiling` and `view_caps`
+    /// matching its creation parameters.
+    ///
+    /// # Safety
+    ///
+    /// The image must be a valid handle created from this device.
+    pub unsafe fn image_from_raw(
+        &self,
+        raw: vk::Image,
+        kind: image::Kind,
+        tiling: vk::ImageTiling,
+        view_caps: image::ViewCapabilities,
+    ) -> n::Image {
+        n::Image {
+            raw,
+            ty: conv::map_image_kind_type(kind),
+            flags: conv::map_view_capabilities(view_caps),
+            extent: conv::map_extent(kind.extent()),
+            tiling,
+        }
+    }
+
+    /// Wrap a raw image view of `image`, covering the subresource `range`.
+    ///
+    /// # Safety
+    ///
+    /// The view must be a valid handle created from this device for `image`.
+    pub unsafe fn image_view_from_raw(
+        &self,
+        raw: vk::ImageView,
+        image: &n::Image,
+        range: image::SubresourceRange,
+    ) -> n::ImageView {
+        n::ImageView {
+            image: image.raw,
+            raw,
+            range,
+        }
+    }
+
+    /// Wrap a raw memory allocation made on this device.
+    ///
+    /// # Safety
+    ///
+    /// The memory must be a valid handle allocated from this device.
+    pub unsafe fn memory_from_raw(&self, raw: vk::DeviceMemory) -> n::Memory {
+        n::Memory { raw }
+    }
+
+    /// Wrap a raw binary semaphore created on this device.
+    ///
+    /// # Safety
+    ///
+    /// The semaphore must be a valid handle created from this device.
+    pub unsafe fn semaphore_from_raw(&self, raw: vk::Semaphore) -> n::Semaphore {
+        n::Semaphore(raw)
+    }
+
+    /// Wrap a raw fence created on this device.
+    ///
+    /// # Safety
+    ///
+    /// The fence must be a valid handle created from this device.
+    pub unsafe fn fence_from_raw(&self, raw: vk::Fence) -> n::Fence {
+        n::Fence(raw)
+    }
+}
+
 impl super::Device {
+    /// Reads the diagnostics of `VK_EXT_device_fault`, if enabled.
+    fn get_device_fault_info(&self) -> Option<d::DeviceLostReason> {
+        use ext::device_fault::{address_type, AddressInfo, Counts, Info, VendorInfo};
+
+        let functor = self.shared.extension_fns.device_fault.as_ref()?;
+        let device = self.shared.raw.handle();
+        let mut counts = Counts::default();
+        let result =
+            unsafe { (functor.get_device_fault_info)(device, &mut counts, ptr::null_mut()) };
+        if result != vk::Result::SUCCESS {
+            return None;
+        }
+
+        let mut address_infos = vec![AddressInfo::default(); counts.address_info_count as usize];
+        let mut vendor_infos = vec![VendorInfo::default(); counts.vendor_info_count as usize];
+        // The vendor binary isn't requested.
+        counts.vendor_binary_size = 0;
+        let mut info = Info::new(&mut address_infos, &mut vendor_infos);
+        let result = unsafe { (functor.get_device_fault_info)(device, &mut counts, &mut info) };
+        match result {
+            vk::Result::SUCCESS | vk::Result::INCOMPLETE => {}
+            _ => return None,
+        }
+        address_infos.truncate(counts.address_info_count as usize);
+        vendor_infos.truncate(counts.vendor_info_count as usize);
+
+        let mut description = unsafe { CStr::from_ptr(info.description.as_ptr()) }
+            .to_string_lossy()
+            .into_owned();
+        for vendor_info in &vendor_infos {
+            let vendor_description = unsafe { CStr::from_ptr(vendor_info.description.as_ptr()) };
+            description += &format!(
+                "\n{} (code {:#x}, data {:#x})",
+                vendor_description.to_string_lossy(),
+                vendor_info.vendor_fault_code,
+                vendor_info.vendor_fault_data,
+            );
+        }
+
+        if description.is_empty() && address_infos.is_empty() {
+            return None;
+        }
+        let page_fault = address_infos.iter().find(|address_info| {
+            matches!(
+                address_info.address_type,
+                address_type::READ_INVALID
+                    | address_type::WRITE_INVALID
+                    | address_type::EXECUTE_INVALID
+            )
+        });
+
+        Some(d::DeviceLostReason {
+            cause: if page_fault.is_some() {
+                d::DeviceLostCause::PageFault
+            } else {
+                d::DeviceLostCause::Unknown
+            },
+            description,
+            fault_address: page_fault.map(|address_info| address_info.reported_address),
+            last_checkpoint: None,
+            pending_checkpoints: Vec::new(),
+        })
+    }
+
+    unsafe fn create_image_impl(
+        &self,
+        kind: image::Kind,
+        mip_levels: image::Level,
+        format: format::Format,
+        tiling: image::Tiling,
+        usage: image::Usage,
+        view_caps: image::ViewCapabilities,
+        drm_tiling: Option<image::DrmTiling>,
+    ) -> Result<n::Image, image::CreationError> {
+        let flags = conv::map_view_capabilities(view_caps);
+        let extent = conv::map_extent(kind.extent());
+        let array_layers = kind.num_layers();
+        let samples = kind.num_samples();
+        let image_type = conv::map_image_kind_type(kind);
+
+        //Note: this is a hack, we should expose this in the API instead
+        let layout = match tiling {
+            image::Tiling::Linear => vk::ImageLayout::PREINITIALIZED,
+            image::Tiling::Optimal => vk::ImageLayout::UNDEFINED,
+        };
+
+        let modifiers;
+        let plane_layouts;
+        let mut modifier_list_info;
+        let mut modifier_explicit_info;
+        let mut external_info;
+        let mut info = vk::ImageCreateInfo::builder()
+            .flags(flags)
+            .image_type(image_type)
+            .format(conv::map_format(format))
+            .extent(extent.clone())
+            .mip_levels(mip_levels as u32)
+            .array_layers(array_layers as u32)
+            .samples(conv::map_sample_count_flags(samples))
+            .tiling(conv::map_tiling(tiling))
+            .usage(conv::map_image_usage(usage))
+            .sharing_mode(vk::SharingMode::EXCLUSIVE) // TODO:
+            .initial_layout(layout);
+        if let Some(drm_tiling) = drm_tiling {
+            info = info.tiling(vk::ImageTiling::DRM_FORMAT_MODIFIER_EXT);
+            match drm_tiling {
+                image::DrmTiling::List(list) => {
+                    modifiers = list.iter().map(|modifier| modifier.0).collect::<Vec<_>>();
+                    modifier_list_info = vk::ImageDrmFormatModifierListCreateInfoEXT::builder()
+                        .drm_format_modifiers(&modifiers);
+                    info = info.push_next(&mut modifier_list_info);
+                }
+                image::DrmTiling::Explicit { modifier, planes } => {
+                    plane_layouts = planes
+                        .iter()
+                        .map(|plane| vk::SubresourceLayout {
+                            offset: plane.offset,
+                            size: 0,
+                            row_pitch: plane.row_pitch,
+                            array_pitch: 0,
+                            depth_pitch: 0,
+                        })
+                        .collect::<Vec<_>>();
+                    modifier_explicit_info =
+                        vk::ImageDrmFormatModifierExplicitCreateInfoEXT::builder()
+                            .drm_format_modifier(modifier.0)
+                            .plane_layouts(&plane_layouts);
+                    info = info.push_next(&mut modifier_explicit_info);
+                }
+            }
+            // Images with modifiers are meant to be shared, so allow binding them to dma-bufs
+            if self
+                .shared
+                .features
+                .contains(Features::EXTERNAL_MEMORY_DMA_BUF)
+            {
+                external_info = vk::ExternalMemoryImageCreateInfo::builder().handle_types(
+                    vk::ExternalMemoryHandleTypeFlags::EXTERNAL_MEMORY_HANDLE_TYPE_DMA_BUF,
+                );
+                info = info.push_next(&mut external_info);
+            }
+        }
+
+        let result = self.shared.raw.create_image(&info, None);
+
+        match result {
+            Ok(raw) => Ok(n::Image {
+                raw,
+                ty: image_type,
+                flags,
+                extent,
+                tiling: info.tiling,
+            }),
+            Err(vk::Result::ERROR_OUT_OF_HOST_MEMORY) => Err(d::OutOfMemory::Host.into()),
+            Err(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY) => Err(d::OutOfMemory::Device.into()),
+            Err(vk::Result::ERROR_INVALID_DRM_FORMAT_MODIFIER_PLANE_LAYOUT_EXT) => {
+                Err(image::CreationError::DrmModifier)
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    unsafe fn allocate_memory_impl(
+        &self,
+        mem_type: MemoryTypeId,
+        size: u64,
+        priority: Option<Priority>,
+        dedicated: Option<DedicatedResource<B>>,
+    ) -> Result<n::Memory, d::AllocationError> {
+        let mut priority_info;
+        let mut dedicated_info;
+        let mut info = vk::MemoryAllocateInfo::builder()
+            .allocation_size(size)
+            .memory_type_index(self.get_ash_memory_type_index(mem_type));
+        if let Some(priority) = priority {
+            priority_info = vk::MemoryPriorityAllocateInfoEXT::builder().priority(priority);
+            info = info.push_next(&mut priority_info);
+        }
+        if let Some(resource) = dedicated {
+            dedicated_info = match resource {
+                DedicatedResource::Buffer(buffer) => {
+                    vk::MemoryDedicatedAllocateInfo::builder().buffer(buffer.raw)
+                }
+                DedicatedResource::Image(image) => {
+                    vk::MemoryDedicatedAllocateInfo::builder().image(image.raw)
+                }
+            };
+            info = info.push_next(&mut dedicated_info);
+        }
+
+        let result = self.shared.raw.allocate_memory(&info, None);
+
+        match result {
+            Ok(memory) => Ok(n::Memory { raw: memory }),
+            Err(vk::Result::ERROR_TOO_MANY_OBJECTS) => Err(d::AllocationError::TooManyObjects),
+            Err(vk::Result::ERROR_OUT_OF_HOST_MEMORY) => Err(d::OutOfMemory::Host.into()),
+            Err(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY) => Err(d::OutOfMemory::Device.into()),
+            _ => unreachable!(),
+        }
+    }
+
+    #[cfg(unix)]
+    unsafe fn allocate_dma_buf_impl(
+        &self,
+        mem_type: MemoryTypeId,
+        size: u64,
+        import_fd: Option<RawFd>,
+    ) -> Result<n::Memory, d::ExternalMemoryError> {
+        if !self
+            .shared
+            .features
+            .contains(Features::EXTERNAL_MEMORY_DMA_BUF)
+        {
+            return Err(d::ExternalMemoryError::Unsupported);
+        }
+        let handle_type = vk::ExternalMemoryHandleTypeFlags::EXTERNAL_MEMORY_HANDLE_TYPE_DMA_BUF;
+
+        let mut import_info;
+        let mut export_info;
+        let mut info = vk::MemoryAllocateInfo::builder()
+            .allocation_size(size)
+            .memory_type_index(self.get_ash_memory_type_index(mem_type));
+        match import_fd {
+            Some(fd) => {
+                import_info = vk::ImportMemoryFdInfoKHR::builder()
+                    .handle_type(handle_type)
+                    .fd(fd);
+                info = info.push_next(&mut import_info);
+            }
+            None => {
+                export_info = vk::ExportMemoryAllocateInfo::builder().handle_types(handle_type);
+                info = info.push_next(&mut export_info);
+            }
+        }
+
+        let result = self.shared.raw.allocate_memory(&info, None);
+
+        match result {
+            Ok(memory) => Ok(n::Memory { raw: memory }),
+            Err(vk::Result::ERROR_TOO_MANY_OBJECTS) => Err(d::ExternalMemoryError::TooManyObjects),
+            Err(vk::Result::ERROR_OUT_OF_HOST_MEMORY) => Err(d::OutOfMemory::Host.into()),
+            Err(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY) => Err(d::OutOfMemory::Device.into()),
+            Err(vk::Result::ERROR_INVALID_EXTERNAL_HANDLE) => {
+                Err(d::ExternalMemoryError::InvalidHandle)
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    unsafe fn create_sampler_impl(
+        &self,
+        desc: &image::SamplerDesc,
+        ycbcr_conversion: Option<vk::SamplerYcbcrConversion>,
+    ) -> Result<n::Sampler, d::AllocationError> {
+        use hal::pso::Comparison;
+
+        let (anisotropy_enable, max_anisotropy) =
+            desc.anisotropy_clamp.map_or((false, 1.0), |aniso| {
+                if self.shared.features.contains(Features::SAMPLER_ANISOTROPY) {
+                    (true, aniso as f32)
+                } else {
+                    warn!(
+                        "Anisotropy({}) was requested on a device with disabled feature",
+                        aniso
+                    );
+                    (false, 1.0)
+                }
+            });
+        let mut conversion_info;
+        let mut info = vk::SamplerCreateInfo::builder()
+            .flags(vk::SamplerCreateFlags::empty())
+            .mag_filter(conv::map_filter(desc.mag_filter))
+            .min_filter(conv::map_filter(desc.min_filter))
+            .mipmap_mode(conv::map_mip_filter(desc.mip_filter))
+            .address_mode_u(conv::map_wrap(desc.wrap_mode.0))
+            .address_mode_v(conv::map_wrap(desc.wrap_mode.1))
+            .address_mode_w(conv::map_wrap(desc.wrap_mode.2))
+            .mip_lod_bias(desc.lod_bias.0)
+            .anisotropy_enable(anisotropy_enable)
+            .max_anisotropy(max_anisotropy)
+            .compare_enable(desc.comparison.is_some())
+            .compare_op(conv::map_comparison(
+                desc.comparison.unwrap_or(Comparison::Never),
+            ))
+            .min_lod(desc.lod_range.start.0)
+            .max_lod(desc.lod_range.end.0)
+            .border_color(conv::map_border_color(desc.border))
+            .unnormalized_coordinates(!desc.normalized);
+        if let Some(conversion) = ycbcr_conversion {
+            conversion_info = vk::SamplerYcbcrConversionInfo::builder().conversion(conversion);
+            info = info.push_next(&mut conversion_info);
+        }
+
+        let result = self.shared.raw.create_sampler(&info, None);
+
+        match result {
+            Ok(sampler) => Ok(n::Sampler(sampler)),
+            Err(vk::Result::ERROR_TOO_MANY_OBJECTS) => Err(d::AllocationError::TooManyObjects),
+            Err(vk::Result::ERROR_OUT_OF_HOST_MEMORY) => Err(d::OutOfMemory::Host.into()),
+            Err(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY) => Err(d::OutOfMemory::Device.into()),
+            _ => unreachable!(),
+        }
+    }
+
+    unsafe fn create_image_view_impl(
+        &self,
+        image: &n::Image,
+        kind: image::ViewKind,
+        format: vk::Format,
+        components: vk::ComponentMapping,
+        range: image::SubresourceRange,
+        ycbcr_conversion: Option<vk::SamplerYcbcrConversion>,
+        min_lod: Option<f32>,
+    ) -> Result<n::ImageView, image::ViewCreationError> {
+        let is_cube = image
+            .flags
+            .intersects(vk::ImageCreateFlags::CUBE_COMPATIBLE);
+        let mut conversion_info;
+        let mut min_lod_info;
+        let mut info = vk::ImageViewCreateInfo::builder()
+            .flags(vk::ImageViewCreateFlags::empty())
+            .image(image.raw)
+            .view_type(match conv::map_view_kind(kind, image.ty, is_cube) {
+                Some(ty) => ty,
+                None => return Err(image::ViewCreationError::BadKind(kind)),
+            })
+            .format(format)
+            .components(components)
+            .subresource_range(conv::map_subresource_range(&range));
+        if let Some(conversion) = ycbcr_conversion {
+            conversion_info = vk::SamplerYcbcrConversionInfo::builder().conversion(conversion);
+            info = info.push_next(&mut conversion_info);
+        }
+        if let Some(min_lod) = min_lod {
+            min_lod_info = ext::image_view_min_lod::ImageViewCreateInfo::new(min_lod);
+            info = info.push_next(&mut min_lod_info);
+        }
+
+        let result = self.shared.raw.create_image_view(&info, None);
+
+        match result {
+            Ok(raw) => Ok(n::ImageView {
+                image: image.raw,
+                raw,
+                range,
+            }),
+            Err(vk::Result::ERROR_OUT_OF_HOST_MEMORY) => Err(d::OutOfMemory::Host.into()),
+            Err(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY) => Err(d::OutOfMemory::Device.into()),
+            _ => unreachable!(),
+        }
+    }
+
     /// We only work with a subset of Ash-exposed memory types that we know.
     /// This function filters an ash mask into our mask.
     fn filter_memory_requirements(&self, ash_mask: u32) -> u32 {
@@ -1870,6 +3113,20 @@ impl super::Device {
         mask
     }
 
+    fn map_requirements(
+        &self,
+        req: vk::MemoryRequirements,
+        dedicated: &vk::MemoryDedicatedRequirements,
+    ) -> Requirements {
+        Requirements {
+            size: req.size,
+            alignment: req.alignment,
+            type_mask: self.filter_memory_requirements(req.memory_type_bits),
+            prefers_dedicated: dedicated.prefers_dedicated_allocation != vk::FALSE,
+            requires_dedicated: dedicated.requires_dedicated_allocation != vk::FALSE,
+        }
+    }
+
     fn get_ash_memory_type_index(&self, hal_type: MemoryTypeId) -> u32 {
         let mut hal_count = hal_type.0;
         for ash_index in 0..32 {
@@ -1896,25 +3153,98 @@ impl super::Device {
             None => vk::SwapchainKHR::null(),
         };
 
-        let info = vk::SwapchainCreateInfoKHR::builder()
-            .flags(vk::SwapchainCreateFlagsKHR::empty())
+        let mut full_screen_exclusive = config.full_screen_exclusive;
+        if full_screen_exclusive != hal::window::FullScreenExclusive::Default
+            && !self
+                .shared
+                .features
+                .contains(Features::FULL_SCREEN_EXCLUSIVE)
+        {
+            warn!("Exclusive full screen control requires `Features::FULL_SCREEN_EXCLUSIVE`");
+            full_screen_exclusive = hal::window::FullScreenExclusive::Default;
+        }
+        let mut flags = vk::SwapchainCreateFlagsKHR::empty();
+        if config.protected {
+            if self.shared.features.contains(Features::PROTECTED_MEMORY) {
+                flags |= vk::SwapchainCreateFlagsKHR::PROTECTED;
+            } else {
+                warn!("Protected swapchains require `Features::PROTECTED_MEMORY`");
+            }
+        }
+
+        // Concurrent sharing requires at least two distinct families.
+        let mut queue_family_indices = config
+            .queue_families
+            .iter()
+            .map(|family| family.0 as u32)
+            .collect::<Vec<_>>();
+        queue_family_indices.sort();
+        queue_family_indices.dedup();
+
+        let present_mode = conv::map_present_mode(config.present_mode);
+        // Declare the present modes that can be switched to without re-creating the swapchain
+        let present_modes = if self.shared.features.contains(Features::PRESENT_MODE_SWITCH) {
+            surface.compatible_present_modes(&self.shared, present_mode)
+        } else {
+            Vec::new()
+        };
+
+        let mut full_screen_exclusive_info;
+        #[cfg(windows)]
+        let mut full_screen_exclusive_win32_info;
+        let mut present_modes_info;
+        let mut info = vk::SwapchainCreateInfoKHR::builder()
+            .flags(flags)
             .surface(surface.raw.handle)
             .min_image_count(config.image_count)
             .image_format(conv::map_format(config.format))
-            .image_color_space(vk::ColorSpaceKHR::SRGB_NONLINEAR)
+            .image_color_space(conv::map_color_space(config.color_space))
             .image_extent(vk::Extent2D {
                 width: config.extent.width,
                 height: config.extent.height,
             })
             .image_array_layers(1)
             .image_usage(conv::map_image_usage(config.image_usage))
-            .image_sharing_mode(vk::SharingMode::EXCLUSIVE)
-            .pre_transform(vk::SurfaceTransformFlagsKHR::IDENTITY)
+            .image_sharing_mode(if queue_family_indices.len() > 1 {
+                vk::SharingMode::CONCURRENT
+            } else {
+                vk::SharingMode::EXCLUSIVE
+            })
+            .queue_family_indices(if queue_family_indices.len() > 1 {
+                &queue_family_indices
+            } else {
+                &[]
+            })
+            .pre_transform(conv::map_surface_transform(config.pre_transform))
             .composite_alpha(conv::map_composite_alpha_mode(config.composite_alpha_mode))
-            .present_mode(conv::map_present_mode(config.present_mode))
+            .present_mode(present_mode)
             .clipped(true)
             .old_swapchain(old_swapchain);
 
+        if full_screen_exclusive != hal::window::FullScreenExclusive::Default {
+            full_screen_exclusive_info = vk::SurfaceFullScreenExclusiveInfoEXT::builder()
+                .full_screen_exclusive(conv::map_full_screen_exclusive(full_screen_exclusive));
+            info = info.push_next(&mut full_screen_exclusive_info);
+
+            #[cfg(windows)]
+            if full_screen_exclusive == hal::window::FullScreenExclusive::ApplicationControlled {
+                use winapi::{shared::windef::HWND, um::winuser};
+                let hwnd = surface.hwnd.unwrap_or(ptr::null());
+                let hmonitor =
+                    winuser::MonitorFromWindow(hwnd as HWND, winuser::MONITOR_DEFAULTTONEAREST);
+                full_screen_exclusive_win32_info =
+                    vk::SurfaceFullScreenExclusiveWin32InfoEXT::builder()
+                        .hmonitor(hmonitor as vk::HMONITOR);
+                info = info.push_next(&mut full_screen_exclusive_win32_info);
+            }
+        }
+
+        if !present_modes.is_empty() {
+            present_modes_info =
+                ext::swapchain_maintenance1::SwapchainPresentModesCreateInfo::new(&present_modes);
+            info = info.push_next(&mut present_modes_info);
+        }
+
         let result = functor.create_swapchain(&info, None);
 
         if old_swapchain != vk::SwapchainKHR::null() {
@@ -1960,6 +3290,9 @@ impl super::Device {
             functor,
             vendor_id: self.vendor_id,
             extent,
+            full_screen_exclusive,
+            present_mode,
+            present_modes,
         };
 
         let images = backbuffer_images
@@ -1969,6 +3302,7 @@ impl super::Device {
                 ty: vk::ImageType::TYPE_2D,
                 flags: vk::ImageCreateFlags::empty(),
                 extent,
+                tiling: vk::ImageTiling::OPTIMAL,
             })
             .collect();
 
@@ -0,0 +1,1138 @@
+//! Definitions of the extensions that `ash` doesn't provide yet.
+
+use ash::vk;
+
+use std::{
+    ffi::CStr,
+    os::raw::{c_char, c_void},
+    ptr,
+};
+
+/// `VK_EXT_image_view_min_lod`
+pub mod image_view_min_lod {
+    use super::*;
+
+    pub fn name() -> &'static CStr {
+        CStr::from_bytes_with_nul(b"VK_EXT_image_view_min_lod\0").unwrap()
+    }
+
+    const PHYSICAL_DEVICE_FEATURES: vk::StructureType = vk::StructureType::from_raw(1_000_391_000);
+    const CREATE_INFO: vk::StructureType = vk::StructureType::from_raw(1_000_391_001);
+
+    #[repr(C)]
+    #[derive(Copy, Clone, Debug)]
+    pub struct PhysicalDeviceFeatures {
+        pub s_type: vk::StructureType,
+        pub p_next: *mut c_void,
+        pub min_lod: vk::Bool32,
+    }
+
+    impl PhysicalDeviceFeatures {
+        pub fn new(min_lod: bool) -> Self {
+            PhysicalDeviceFeatures {
+                s_type: PHYSICAL_DEVICE_FEATURES,
+                p_next: ptr::null_mut(),
+                min_lod: min_lod as vk::Bool32,
+            }
+        }
+    }
+
+    unsafe impl vk::ExtendsDeviceCreateInfo for PhysicalDeviceFeatures {}
+
+    #[repr(C)]
+    #[derive(Copy, Clone, Debug)]
+    pub struct ImageViewCreateInfo {
+        pub s_type: vk::StructureType,
+        pub p_next: *const c_void,
+        pub min_lod: f32,
+    }
+
+    impl ImageViewCreateInfo {
+        pub fn new(min_lod: f32) -> Self {
+            ImageViewCreateInfo {
+                s_type: CREATE_INFO,
+                p_next: ptr::null(),
+                min_lod,
+            }
+        }
+    }
+
+    unsafe impl vk::ExtendsImageViewCreateInfo for ImageViewCreateInfo {}
+}
+
+/// `VK_EXT_pipeline_creation_cache_control`
+pub mod pipeline_creation_cache_control {
+    use super::*;
+
+    pub fn name() -> &'static CStr {
+        CStr::from_bytes_with_nul(b"VK_EXT_pipeline_creation_cache_control\0").unwrap()
+    }
+
+    const PHYSICAL_DEVICE_FEATURES: vk::StructureType = vk::StructureType::from_raw(1_000_297_000);
+
+    pub const FAIL_ON_PIPELINE_COMPILE_REQUIRED: vk::PipelineCreateFlags =
+        vk::PipelineCreateFlags::from_raw(0x100);
+    pub const PIPELINE_COMPILE_REQUIRED: vk::Result = vk::Result::from_raw(1_000_297_000);
+
+    #[repr(C)]
+    #[derive(Copy, Clone, Debug)]
+    pub struct PhysicalDeviceFeatures {
+        pub s_type: vk::StructureType,
+        pub p_next: *mut c_void,
+        pub pipeline_creation_cache_control: vk::Bool32,
+    }
+
+    impl PhysicalDeviceFeatures {
+        pub fn new(pipeline_creation_cache_control: bool) -> Self {
+            PhysicalDeviceFeatures {
+                s_type: PHYSICAL_DEVICE_FEATURES,
+                p_next: ptr::null_mut(),
+                pipeline_creation_cache_control: pipeline_creation_cache_control as vk::Bool32,
+            }
+        }
+    }
+
+    unsafe impl vk::ExtendsDeviceCreateInfo for PhysicalDeviceFeatures {}
+}
+
+/// `VK_EXT_host_image_copy`
+pub mod host_image_copy {
+    use super::*;
+
+    pub fn name() -> &'static CStr {
+        CStr::from_bytes_with_nul(b"VK_EXT_host_image_copy\0").unwrap()
+    }
+
+    /// Extensions required by `VK_EXT_host_image_copy` before Vulkan 1.3.
+    pub fn dependencies() -> [&'static CStr; 2] {
+        [
+            CStr::from_bytes_with_nul(b"VK_KHR_copy_commands2\0").unwrap(),
+            CStr::from_bytes_with_nul(b"VK_KHR_format_feature_flags2\0").unwrap(),
+        ]
+    }
+
+    const PHYSICAL_DEVICE_FEATURES: vk::StructureType = vk::StructureType::from_raw(1_000_270_000);
+    const MEMORY_TO_IMAGE_COPY: vk::StructureType = vk::StructureType::from_raw(1_000_270_002);
+    const IMAGE_TO_MEMORY_COPY: vk::StructureType = vk::StructureType::from_raw(1_000_270_003);
+    const COPY_IMAGE_TO_MEMORY_INFO: vk::StructureType =
+        vk::StructureType::from_raw(1_000_270_004);
+    const COPY_MEMORY_TO_IMAGE_INFO: vk::StructureType =
+        vk::StructureType::from_raw(1_000_270_005);
+    const HOST_IMAGE_LAYOUT_TRANSITION_INFO: vk::StructureType =
+        vk::StructureType::from_raw(1_000_270_006);
+
+    #[repr(C)]
+    #[derive(Copy, Clone, Debug)]
+    pub struct PhysicalDeviceFeatures {
+        pub s_type: vk::StructureType,
+        pub p_next: *mut c_void,
+        pub host_image_copy: vk::Bool32,
+    }
+
+    impl PhysicalDeviceFeatures {
+        pub fn new(host_image_copy: bool) -> Self {
+            PhysicalDeviceFeatures {
+                s_type: PHYSICAL_DEVICE_FEATURES,
+                p_next: ptr::null_mut(),
+                host_image_copy: host_image_copy as vk::Bool32,
+            }
+        }
+    }
+
+    unsafe impl vk::ExtendsDeviceCreateInfo for PhysicalDeviceFeatures {}
+
+    /// `VkMemoryToImageCopyEXT` and `VkImageToMemoryCopyEXT`, which only differ
+    /// in the constness of the host pointer.
+    #[repr(C)]
+    #[derive(Copy, Clone, Debug)]
+    pub struct MemoryImageCopy {
+        pub s_type: vk::StructureType,
+        pub p_next: *const c_void,
+        pub p_host_pointer: *mut c_void,
+        pub memory_row_length: u32,
+        pub memory_image_height: u32,
+        pub image_subresource: vk::ImageSubresourceLayers,
+        pub image_offset: vk::Offset3D,
+        pub image_extent: vk::Extent3D,
+    }
+
+    impl MemoryImageCopy {
+        pub fn memory_to_image(
+            host_pointer: *const u8,
+            copy: &vk::BufferImageCopy,
+        ) -> Self {
+            MemoryImageCopy {
+                s_type: MEMORY_TO_IMAGE_COPY,
+                p_next: ptr::null(),
+                p_host_pointer: host_pointer as *mut c_void,
+                memory_row_length: copy.buffer_row_length,
+                memory_image_height: copy.buffer_image_height,
+                image_subresource: copy.image_subresource,
+                image_offset: copy.image_offset,
+                image_extent: copy.image_extent,
+            }
+        }
+
+        pub fn image_to_memory(host_pointer: *mut u8, copy: &vk::BufferImageCopy) -> Self {
+            MemoryImageCopy {
+                s_type: IMAGE_TO_MEMORY_COPY,
+                ..Self::memory_to_image(host_pointer, copy)
+            }
+        }
+    }
+
+    /// `VkCopyMemoryToImageInfoEXT` and `VkCopyImageToMemoryInfoEXT`.
+    #[repr(C)]
+    #[derive(Copy, Clone, Debug)]
+    pub struct CopyInfo {
+        pub s_type: vk::StructureType,
+        pub p_next: *const c_void,
+        pub flags: vk::Flags,
+        pub image: vk::Image,
+        pub image_layout: vk::ImageLayout,
+        pub region_count: u32,
+        pub p_regions: *const MemoryImageCopy,
+    }
+
+    impl CopyInfo {
+        pub fn memory_to_image(
+            image: vk::Image,
+            layout: vk::ImageLayout,
+            regions: &[MemoryImageCopy],
+        ) -> Self {
+            CopyInfo {
+                s_type: COPY_MEMORY_TO_IMAGE_INFO,
+                p_next: ptr::null(),
+                flags: 0,
+                image,
+                image_layout: layout,
+                region_count: regions.len() as u32,
+                p_regions: regions.as_ptr(),
+            }
+        }
+
+        pub fn image_to_memory(
+            image: vk::Image,
+            layout: vk::ImageLayout,
+            regions: &[MemoryImageCopy],
+        ) -> Self {
+            CopyInfo {
+                s_type: COPY_IMAGE_TO_MEMORY_INFO,
+                ..Self::memory_to_image(image, layout, regions)
+            }
+        }
+    }
+
+    #[repr(C)]
+    #[derive(Copy, Clone, Debug)]
+    pub struct HostImageLayoutTransitionInfo {
+        pub s_type: vk::StructureType,
+        pub p_next: *const c_void,
+        pub image: vk::Image,
+        pub old_layout: vk::ImageLayout,
+        pub new_layout: vk::ImageLayout,
+        pub subresource_range: vk::ImageSubresourceRange,
+    }
+
+    impl HostImageLayoutTransitionInfo {
+        pub fn new(
+            image: vk::Image,
+            old_layout: vk::ImageLayout,
+            new_layout: vk::ImageLayout,
+            subresource_range: vk::ImageSubresourceRange,
+        ) -> Self {
+            HostImageLayoutTransitionInfo {
+                s_type: HOST_IMAGE_LAYOUT_TRANSITION_INFO,
+                p_next: ptr::null(),
+                image,
+                old_layout,
+                new_layout,
+                subresource_range,
+            }
+        }
+    }
+
+    type PfnCopyMemoryImage =
+        unsafe extern "system" fn(device: vk::Device, info: *const CopyInfo) -> vk::Result;
+    type PfnTransitionImageLayout = unsafe extern "system" fn(
+        device: vk::Device,
+        transition_count: u32,
+        transitions: *const HostImageLayoutTransitionInfo,
+    ) -> vk::Result;
+
+    /// Device level functions of the extension.
+    #[derive(Clone)]
+    pub struct DeviceFn {
+        pub copy_memory_to_image: PfnCopyMemoryImage,
+        pub copy_image_to_memory: PfnCopyMemoryImage,
+        pub transition_image_layout: PfnTransitionImageLayout,
+    }
+
+    impl DeviceFn {
+        /// Load the functions, returning `None` if any of them is missing.
+        pub unsafe fn load(mut get_proc_addr: impl FnMut(&CStr) -> *const c_void) -> Option<Self> {
+            let mut load = |name: &[u8]| {
+                let ptr = get_proc_addr(CStr::from_bytes_with_nul(name).unwrap());
+                if ptr.is_null() {
+                    None
+                } else {
+                    Some(ptr)
+                }
+            };
+            Some(DeviceFn {
+                copy_memory_to_image: std::mem::transmute(load(b"vkCopyMemoryToImageEXT\0")?),
+                copy_image_to_memory: std::mem::transmute(load(b"vkCopyImageToMemoryEXT\0")?),
+                transition_image_layout: std::mem::transmute(load(
+                    b"vkTransitionImageLayoutEXT\0",
+                )?),
+            })
+        }
+    }
+}
+
+/// `VK_KHR_cooperative_matrix`
+pub mod cooperative_matrix {
+    use super::*;
+
+    pub fn name() -> &'static CStr {
+        CStr::from_bytes_with_nul(b"VK_KHR_cooperative_matrix\0").unwrap()
+    }
+
+    const PHYSICAL_DEVICE_FEATURES: vk::StructureType = vk::StructureType::from_raw(1_000_506_000);
+    const PROPERTIES: vk::StructureType = vk::StructureType::from_raw(1_000_506_001);
+
+    /// `VkComponentTypeKHR`
+    pub mod component_type {
+        pub const FLOAT16: i32 = 0;
+        pub const FLOAT32: i32 = 1;
+        pub const FLOAT64: i32 = 2;
+        pub const SINT8: i32 = 3;
+        pub const SINT16: i32 = 4;
+        pub const SINT32: i32 = 5;
+        pub const SINT64: i32 = 6;
+        pub const UINT8: i32 = 7;
+        pub const UINT16: i32 = 8;
+        pub const UINT32: i32 = 9;
+        pub const UINT64: i32 = 10;
+    }
+
+    /// `VK_SCOPE_SUBGROUP_KHR`
+    pub const SCOPE_SUBGROUP: i32 = 3;
+
+    #[repr(C)]
+    #[derive(Copy, Clone, Debug)]
+    pub struct PhysicalDeviceFeatures {
+        pub s_type: vk::StructureType,
+        pub p_next: *mut c_void,
+        pub cooperative_matrix: vk::Bool32,
+        pub cooperative_matrix_robust_buffer_access: vk::Bool32,
+    }
+
+    impl PhysicalDeviceFeatures {
+        pub fn new(cooperative_matrix: bool) -> Self {
+            PhysicalDeviceFeatures {
+                s_type: PHYSICAL_DEVICE_FEATURES,
+                p_next: ptr::null_mut(),
+                cooperative_matrix: cooperative_matrix as vk::Bool32,
+                cooperative_matrix_robust_buffer_access: vk::FALSE,
+            }
+        }
+    }
+
+    unsafe impl vk::ExtendsDeviceCreateInfo for PhysicalDeviceFeatures {}
+
+    /// `VkCooperativeMatrixPropertiesKHR`
+    #[repr(C)]
+    #[derive(Copy, Clone, Debug)]
+    pub struct Properties {
+        pub s_type: vk::StructureType,
+        pub p_next: *mut c_void,
+        pub m_size: u32,
+        pub n_size: u32,
+        pub k_size: u32,
+        pub a_type: i32,
+        pub b_type: i32,
+        pub c_type: i32,
+        pub result_type: i32,
+        pub saturating_accumulation: vk::Bool32,
+        pub scope: i32,
+    }
+
+    impl Default for Properties {
+        fn default() -> Self {
+            Properties {
+                s_type: PROPERTIES,
+                p_next: ptr::null_mut(),
+                m_size: 0,
+                n_size: 0,
+                k_size: 0,
+                a_type: 0,
+                b_type: 0,
+                c_type: 0,
+                result_type: 0,
+                saturating_accumulation: vk::FALSE,
+                scope: 0,
+            }
+        }
+    }
+
+    type PfnGetPhysicalDeviceProperties = unsafe extern "system" fn(
+        physical_device: vk::PhysicalDevice,
+        property_count: *mut u32,
+        properties: *mut Properties,
+    ) -> vk::Result;
+
+    /// Instance level functions of the extension.
+    #[derive(Clone)]
+    pub struct InstanceFn {
+        pub get_physical_device_properties: PfnGetPhysicalDeviceProperties,
+    }
+
+    impl InstanceFn {
+        /// Load the functions, returning `None` if any of them is missing.
+        pub unsafe fn load(mut get_proc_addr: impl FnMut(&CStr) -> *const c_void) -> Option<Self> {
+            let ptr = get_proc_addr(
+                CStr::from_bytes_with_nul(b"vkGetPhysicalDeviceCooperativeMatrixPropertiesKHR\0")
+                    .unwrap(),
+            );
+            if ptr.is_null() {
+                None
+            } else {
+                Some(InstanceFn {
+                    get_physical_device_properties: std::mem::transmute(ptr),
+                })
+            }
+        }
+    }
+}
+
+/// `VK_EXT_surface_maintenance1`
+pub mod surface_maintenance1 {
+    use super::*;
+
+    pub fn name() -> &'static CStr {
+        CStr::from_bytes_with_nul(b"VK_EXT_surface_maintenance1\0").unwrap()
+    }
+
+    const SURFACE_PRESENT_MODE: vk::StructureType = vk::StructureType::from_raw(1_000_274_000);
+    const SURFACE_PRESENT_MODE_COMPATIBILITY: vk::StructureType =
+        vk::StructureType::from_raw(1_000_274_002);
+
+    /// Extends `VkPhysicalDeviceSurfaceInfo2KHR`.
+    #[repr(C)]
+    #[derive(Copy, Clone, Debug)]
+    pub struct SurfacePresentMode {
+        pub s_type: vk::StructureType,
+        pub p_next: *mut c_void,
+        pub present_mode: vk::PresentModeKHR,
+    }
+
+    impl SurfacePresentMode {
+        pub fn new(present_mode: vk::PresentModeKHR) -> Self {
+            SurfacePresentMode {
+                s_type: SURFACE_PRESENT_MODE,
+                p_next: ptr::null_mut(),
+                present_mode,
+            }
+        }
+    }
+
+    /// Extends `VkSurfaceCapabilities2KHR`.
+    #[repr(C)]
+    #[derive(Copy, Clone, Debug)]
+    pub struct SurfacePresentModeCompatibility {
+        pub s_type: vk::StructureType,
+        pub p_next: *mut c_void,
+        pub present_mode_count: u32,
+        pub p_present_modes: *mut vk::PresentModeKHR,
+    }
+
+    impl SurfacePresentModeCompatibility {
+        pub fn new(present_modes: &mut [vk::PresentModeKHR]) -> Self {
+            SurfacePresentModeCompatibility {
+                s_type: SURFACE_PRESENT_MODE_COMPATIBILITY,
+                p_next: ptr::null_mut(),
+                present_mode_count: present_modes.len() as u32,
+                p_present_modes: if present_modes.is_empty() {
+                    ptr::null_mut()
+                } else {
+                    present_modes.as_mut_ptr()
+                },
+            }
+        }
+    }
+}
+
+/// `VK_EXT_swapchain_maintenance1`
+pub mod swapchain_maintenance1 {
+    use super::*;
+
+    pub fn name() -> &'static CStr {
+        CStr::from_bytes_with_nul(b"VK_EXT_swapchain_maintenance1\0").unwrap()
+    }
+
+    const PHYSICAL_DEVICE_FEATURES: vk::StructureType = vk::StructureType::from_raw(1_000_275_000);
+    const SWAPCHAIN_PRESENT_MODES_CREATE_INFO: vk::StructureType =
+        vk::StructureType::from_raw(1_000_275_002);
+    const SWAPCHAIN_PRESENT_MODE_INFO: vk::StructureType =
+        vk::StructureType::from_raw(1_000_275_003);
+
+    #[repr(C)]
+    #[derive(Copy, Clone, Debug)]
+    pub struct PhysicalDeviceFeatures {
+        pub s_type: vk::StructureType,
+        pub p_next: *mut c_void,
+        pub swapchain_maintenance1: vk::Bool32,
+    }
+
+    impl PhysicalDeviceFeatures {
+        pub fn new(swapchain_maintenance1: bool) -> Self {
+            PhysicalDeviceFeatures {
+                s_type: PHYSICAL_DEVICE_FEATURES,
+                p_next: ptr::null_mut(),
+                swapchain_maintenance1: swapchain_maintenance1 as vk::Bool32,
+            }
+        }
+    }
+
+    unsafe impl vk::ExtendsDeviceCreateInfo for PhysicalDeviceFeatures {}
+
+    #[repr(C)]
+    #[derive(Copy, Clone, Debug)]
+    pub struct SwapchainPresentModesCreateInfo {
+        pub s_type: vk::StructureType,
+        pub p_next: *const c_void,
+        pub present_mode_count: u32,
+        pub p_present_modes: *const vk::PresentModeKHR,
+    }
+
+    impl SwapchainPresentModesCreateInfo {
+        pub fn new(present_modes: &[vk::PresentModeKHR]) -> Self {
+            SwapchainPresentModesCreateInfo {
+                s_type: SWAPCHAIN_PRESENT_MODES_CREATE_INFO,
+                p_next: ptr::null(),
+                present_mode_count: present_modes.len() as u32,
+                p_present_modes: present_modes.as_ptr(),
+            }
+        }
+    }
+
+    unsafe impl vk::ExtendsSwapchainCreateInfoKHR for SwapchainPresentModesCreateInfo {}
+
+    #[repr(C)]
+    #[derive(Copy, Clone, Debug)]
+    pub struct SwapchainPresentModeInfo {
+        pub s_type: vk::StructureType,
+        pub p_next: *const c_void,
+        pub swapchain_count: u32,
+        pub p_present_modes: *const vk::PresentModeKHR,
+    }
+
+    impl SwapchainPresentModeInfo {
+        pub fn new(present_modes: &[vk::PresentModeKHR]) -> Self {
+            SwapchainPresentModeInfo {
+                s_type: SWAPCHAIN_PRESENT_MODE_INFO,
+                p_next: ptr::null(),
+                swapchain_count: present_modes.len() as u32,
+                p_present_modes: present_modes.as_ptr(),
+            }
+        }
+    }
+
+    unsafe impl vk::ExtendsPresentInfoKHR for SwapchainPresentModeInfo {}
+}
+
+/// `VK_EXT_robustness2`
+pub mod robustness2 {
+    use super::*;
+
+    pub fn name() -> &'static CStr {
+        CStr::from_bytes_with_nul(b"VK_EXT_robustness2\0").unwrap()
+    }
+
+    const PHYSICAL_DEVICE_FEATURES: vk::StructureType = vk::StructureType::from_raw(1_000_286_000);
+
+    #[repr(C)]
+    #[derive(Copy, Clone, Debug)]
+    pub struct PhysicalDeviceFeatures {
+        pub s_type: vk::StructureType,
+        pub p_next: *mut c_void,
+        pub robust_buffer_access2: vk::Bool32,
+        pub robust_image_access2: vk::Bool32,
+        pub null_descriptor: vk::Bool32,
+    }
+
+    impl PhysicalDeviceFeatures {
+        pub fn new(null_descriptor: bool) -> Self {
+            PhysicalDeviceFeatures {
+                s_type: PHYSICAL_DEVICE_FEATURES,
+                p_next: ptr::null_mut(),
+                robust_buffer_access2: vk::FALSE,
+                robust_image_access2: vk::FALSE,
+                null_descriptor: null_descriptor as vk::Bool32,
+            }
+        }
+    }
+
+    unsafe impl vk::ExtendsDeviceCreateInfo for PhysicalDeviceFeatures {}
+}
+
+/// `VK_EXT_device_fault`
+pub mod device_fault {
+    use super::*;
+
+    pub fn name() -> &'static CStr {
+        CStr::from_bytes_with_nul(b"VK_EXT_device_fault\0").unwrap()
+    }
+
+    const PHYSICAL_DEVICE_FEATURES: vk::StructureType = vk::StructureType::from_raw(1_000_341_000);
+    const COUNTS: vk::StructureType = vk::StructureType::from_raw(1_000_341_001);
+    const INFO: vk::StructureType = vk::StructureType::from_raw(1_000_341_002);
+
+    /// `VkDeviceFaultAddressTypeEXT`
+    pub mod address_type {
+        pub const READ_INVALID: i32 = 1;
+        pub const WRITE_INVALID: i32 = 2;
+        pub const EXECUTE_INVALID: i32 = 3;
+    }
+
+    #[repr(C)]
+    #[derive(Copy, Clone, Debug)]
+    pub struct PhysicalDeviceFeatures {
+        pub s_type: vk::StructureType,
+        pub p_next: *mut c_void,
+        pub device_fault: vk::Bool32,
+        pub device_fault_vendor_binary: vk::Bool32,
+    }
+
+    impl PhysicalDeviceFeatures {
+        pub fn new(device_fault: bool) -> Self {
+            PhysicalDeviceFeatures {
+                s_type: PHYSICAL_DEVICE_FEATURES,
+                p_next: ptr::null_mut(),
+                device_fault: device_fault as vk::Bool32,
+                device_fault_vendor_binary: vk::FALSE,
+            }
+        }
+    }
+
+    unsafe impl vk::ExtendsDeviceCreateInfo for PhysicalDeviceFeatures {}
+
+    /// `VkDeviceFaultCountsEXT`
+    #[repr(C)]
+    #[derive(Copy, Clone, Debug)]
+    pub struct Counts {
+        pub s_type: vk::StructureType,
+        pub p_next: *mut c_void,
+        pub address_info_count: u32,
+        pub vendor_info_count: u32,
+        pub vendor_binary_size: vk::DeviceSize,
+    }
+
+    impl Default for Counts {
+        fn default() -> Self {
+            Counts {
+                s_type: COUNTS,
+                p_next: ptr::null_mut(),
+                address_info_count: 0,
+                vendor_info_count: 0,
+                vendor_binary_size: 0,
+            }
+        }
+    }
+
+    /// `VkDeviceFaultAddressInfoEXT`
+    #[repr(C)]
+    #[derive(Copy, Clone, Debug, Default)]
+    pub struct AddressInfo {
+        pub address_type: i32,
+        pub reported_address: vk::DeviceAddress,
+        pub address_precision: vk::DeviceSize,
+    }
+
+    /// `VkDeviceFaultVendorInfoEXT`
+    #[repr(C)]
+    #[derive(Copy, Clone)]
+    pub struct VendorInfo {
+        pub description: [c_char; vk::MAX_DESCRIPTION_SIZE],
+        pub vendor_fault_code: u64,
+        pub vendor_fault_data: u64,
+    }
+
+    impl Default for VendorInfo {
+        fn default() -> Self {
+            VendorInfo {
+                description: [0; vk::MAX_DESCRIPTION_SIZE],
+                vendor_fault_code: 0,
+                vendor_fault_data: 0,
+            }
+        }
+    }
+
+    /// `VkDeviceFaultInfoEXT`
+    #[repr(C)]
+    pub struct Info {
+        pub s_type: vk::StructureType,
+        pub p_next: *mut c_void,
+        pub description: [c_char; vk::MAX_DESCRIPTION_SIZE],
+        pub p_address_infos: *mut AddressInfo,
+        pub p_vendor_infos: *mut VendorInfo,
+        pub p_vendor_binary_data: *mut c_void,
+    }
+
+    impl Info {
+        pub fn new(address_infos: &mut [AddressInfo], vendor_infos: &mut [VendorInfo]) -> Self {
+            Info {
+                s_type: INFO,
+                p_next: ptr::null_mut(),
+                description: [0; vk::MAX_DESCRIPTION_SIZE],
+                p_address_infos: address_infos.as_mut_ptr(),
+                p_vendor_infos: vendor_infos.as_mut_ptr(),
+                p_vendor_binary_data: ptr::null_mut(),
+            }
+        }
+    }
+
+    type PfnGetDeviceFaultInfo = unsafe extern "system" fn(
+        device: vk::Device,
+        fault_counts: *mut Counts,
+        fault_info: *mut Info,
+    ) -> vk::Result;
+
+    /// Device level functions of the extension.
+    #[derive(Clone)]
+    pub struct DeviceFn {
+        pub get_device_fault_info: PfnGetDeviceFaultInfo,
+    }
+
+    impl DeviceFn {
+        /// Load the functions, returning `None` if any of them is missing.
+        pub unsafe fn load(mut get_proc_addr: impl FnMut(&CStr) -> *const c_void) -> Option<Self> {
+            let ptr =
+                get_proc_addr(CStr::from_bytes_with_nul(b"vkGetDeviceFaultInfoEXT\0").unwrap());
+            if ptr.is_null() {
+                None
+            } else {
+                Some(DeviceFn {
+                    get_device_fault_info: std::mem::transmute(ptr),
+                })
+            }
+        }
+    }
+}
+
+/// `VK_KHR_dynamic_rendering`
+pub mod dynamic_rendering {
+    use super::*;
+
+    pub fn name() -> &'static CStr {
+        CStr::from_bytes_with_nul(b"VK_KHR_dynamic_rendering\0").unwrap()
+    }
+
+    /// Extensions required by `VK_KHR_dynamic_rendering` before Vulkan 1.2.
+    pub fn dependencies() -> [&'static CStr; 2] {
+        [
+            vk::KhrDepthStencilResolveFn::name(),
+            vk::KhrCreateRenderpass2Fn::name(), // Required for `KhrDepthStencilResolveFn`
+        ]
+    }
+
+    const RENDERING_INFO: vk::StructureType = vk::StructureType::from_raw(1_000_044_000);
+    const RENDERING_ATTACHMENT_INFO: vk::StructureType = vk::StructureType::from_raw(1_000_044_001);
+    const PIPELINE_RENDERING_CREATE_INFO: vk::StructureType =
+        vk::StructureType::from_raw(1_000_044_002);
+    const PHYSICAL_DEVICE_FEATURES: vk::StructureType = vk::StructureType::from_raw(1_000_044_003);
+
+    /// `VkRenderingFlagBitsKHR`
+    pub mod rendering_flags {
+        pub const SUSPENDING: u32 = 0x2;
+        pub const RESUMING: u32 = 0x4;
+    }
+
+    #[repr(C)]
+    #[derive(Copy, Clone, Debug)]
+    pub struct PhysicalDeviceFeatures {
+        pub s_type: vk::StructureType,
+        pub p_next: *mut c_void,
+        pub dynamic_rendering: vk::Bool32,
+    }
+
+    impl PhysicalDeviceFeatures {
+        pub fn new(dynamic_rendering: bool) -> Self {
+            PhysicalDeviceFeatures {
+                s_type: PHYSICAL_DEVICE_FEATURES,
+                p_next: ptr::null_mut(),
+                dynamic_rendering: dynamic_rendering as vk::Bool32,
+            }
+        }
+    }
+
+    unsafe impl vk::ExtendsDeviceCreateInfo for PhysicalDeviceFeatures {}
+
+    /// Extends `VkGraphicsPipelineCreateInfo` created without a render pass.
+    #[repr(C)]
+    #[derive(Copy, Clone, Debug)]
+    pub struct PipelineRenderingCreateInfo {
+        pub s_type: vk::StructureType,
+        pub p_next: *const c_void,
+        pub view_mask: u32,
+        pub color_attachment_count: u32,
+        pub p_color_attachment_formats: *const vk::Format,
+        pub depth_attachment_format: vk::Format,
+        pub stencil_attachment_format: vk::Format,
+    }
+
+    impl PipelineRenderingCreateInfo {
+        pub fn new(
+            color_formats: &[vk::Format],
+            depth_format: vk::Format,
+            stencil_format: vk::Format,
+        ) -> Self {
+            PipelineRenderingCreateInfo {
+                s_type: PIPELINE_RENDERING_CREATE_INFO,
+                p_next: ptr::null(),
+                view_mask: 0,
+                color_attachment_count: color_formats.len() as u32,
+                p_color_attachment_formats: color_formats.as_ptr(),
+                depth_attachment_format: depth_format,
+                stencil_attachment_format: stencil_format,
+            }
+        }
+    }
+
+    unsafe impl vk::ExtendsGraphicsPipelineCreateInfo for PipelineRenderingCreateInfo {}
+
+    /// `VkRenderingAttachmentInfoKHR`
+    #[repr(C)]
+    #[derive(Copy, Clone)]
+    pub struct RenderingAttachmentInfo {
+        pub s_type: vk::StructureType,
+        pub p_next: *const c_void,
+        pub image_view: vk::ImageView,
+        pub image_layout: vk::ImageLayout,
+        pub resolve_mode: vk::ResolveModeFlags,
+        pub resolve_image_view: vk::ImageView,
+        pub resolve_image_layout: vk::ImageLayout,
+        pub load_op: vk::AttachmentLoadOp,
+        pub store_op: vk::AttachmentStoreOp,
+        pub clear_value: vk::ClearValue,
+    }
+
+    impl RenderingAttachmentInfo {
+        pub fn new(
+            image_view: vk::ImageView,
+            image_layout: vk::ImageLayout,
+            load_op: vk::AttachmentLoadOp,
+            store_op: vk::AttachmentStoreOp,
+            clear_value: vk::ClearValue,
+        ) -> Self {
+            RenderingAttachmentInfo {
+                s_type: RENDERING_ATTACHMENT_INFO,
+                p_next: ptr::null(),
+                image_view,
+                image_layout,
+                resolve_mode: vk::ResolveModeFlags::NONE,
+                resolve_image_view: vk::ImageView::null(),
+                resolve_image_layout: vk::ImageLayout::UNDEFINED,
+                load_op,
+                store_op,
+                clear_value,
+            }
+        }
+    }
+
+    /// `VkRenderingInfoKHR`
+    #[repr(C)]
+    #[derive(Copy, Clone)]
+    pub struct RenderingInfo {
+        pub s_type: vk::StructureType,
+        pub p_next: *const c_void,
+        pub flags: vk::Flags,
+        pub render_area: vk::Rect2D,
+        pub layer_count: u32,
+        pub view_mask: u32,
+        pub color_attachment_count: u32,
+        pub p_color_attachments: *const RenderingAttachmentInfo,
+        pub p_depth_attachment: *const RenderingAttachmentInfo,
+        pub p_stencil_attachment: *const RenderingAttachmentInfo,
+    }
+
+    impl RenderingInfo {
+        pub fn new(
+            flags: vk::Flags,
+            render_area: vk::Rect2D,
+            layer_count: u32,
+            colors: &[RenderingAttachmentInfo],
+            depth: Option<&RenderingAttachmentInfo>,
+            stencil: Option<&RenderingAttachmentInfo>,
+        ) -> Self {
+            RenderingInfo {
+                s_type: RENDERING_INFO,
+                p_next: ptr::null(),
+                flags,
+                render_area,
+                layer_count,
+                view_mask: 0,
+                color_attachment_count: colors.len() as u32,
+                p_color_attachments: colors.as_ptr(),
+                p_depth_attachment: depth.map_or(ptr::null(), |at| at as *const _),
+                p_stencil_attachment: stencil.map_or(ptr::null(), |at| at as *const _),
+            }
+        }
+    }
+
+    type PfnCmdBeginRendering =
+        unsafe extern "system" fn(command_buffer: vk::CommandBuffer, info: *const RenderingInfo);
+    type PfnCmdEndRendering = unsafe extern "system" fn(command_buffer: vk::CommandBuffer);
+
+    /// Device level functions of the extension.
+    #[derive(Clone)]
+    pub struct DeviceFn {
+        pub cmd_begin_rendering: PfnCmdBeginRendering,
+        pub cmd_end_rendering: PfnCmdEndRendering,
+    }
+
+    impl DeviceFn {
+        /// Load the functions, returning `None` if any of them is missing.
+        pub unsafe fn load(mut get_proc_addr: impl FnMut(&CStr) -> *const c_void) -> Option<Self> {
+            let mut load = |name: &[u8]| {
+                let ptr = get_proc_addr(CStr::from_bytes_with_nul(name).unwrap());
+                if ptr.is_null() {
+                    None
+                } else {
+                    Some(ptr)
+                }
+            };
+            Some(DeviceFn {
+                cmd_begin_rendering: std::mem::transmute(load(b"vkCmdBeginRenderingKHR\0")?),
+                cmd_end_rendering: std::mem::transmute(load(b"vkCmdEndRenderingKHR\0")?),
+            })
+        }
+    }
+}
+
+/// `VK_KHR_fragment_shading_rate`
+pub mod fragment_shading_rate {
+    use super::*;
+
+    pub fn name() -> &'static CStr {
+        CStr::from_bytes_with_nul(b"VK_KHR_fragment_shading_rate\0").unwrap()
+    }
+
+    /// Extensions required by `VK_KHR_fragment_shading_rate` before Vulkan 1.2.
+    pub fn dependencies() -> [&'static CStr; 1] {
+        [vk::KhrCreateRenderpass2Fn::name()]
+    }
+
+    const ATTACHMENT_INFO: vk::StructureType = vk::StructureType::from_raw(1_000_226_000);
+    const PHYSICAL_DEVICE_PROPERTIES: vk::StructureType =
+        vk::StructureType::from_raw(1_000_226_002);
+    const PHYSICAL_DEVICE_FEATURES: vk::StructureType = vk::StructureType::from_raw(1_000_226_003);
+
+    pub const DYNAMIC_STATE: vk::DynamicState = vk::DynamicState::from_raw(1_000_226_000);
+
+    /// `VkFragmentShadingRateCombinerOpKHR`
+    pub mod combiner_op {
+        pub const KEEP: u32 = 0;
+        pub const REPLACE: u32 = 1;
+        pub const MIN: u32 = 2;
+        pub const MAX: u32 = 3;
+        pub const MUL: u32 = 4;
+    }
+
+    #[repr(C)]
+    #[derive(Copy, Clone, Debug)]
+    pub struct PhysicalDeviceFeatures {
+        pub s_type: vk::StructureType,
+        pub p_next: *mut c_void,
+        pub pipeline_fragment_shading_rate: vk::Bool32,
+        pub primitive_fragment_shading_rate: vk::Bool32,
+        pub attachment_fragment_shading_rate: vk::Bool32,
+    }
+
+    impl PhysicalDeviceFeatures {
+        pub fn new(pipeline: bool, attachment: bool) -> Self {
+            PhysicalDeviceFeatures {
+                s_type: PHYSICAL_DEVICE_FEATURES,
+                p_next: ptr::null_mut(),
+                pipeline_fragment_shading_rate: pipeline as vk::Bool32,
+                primitive_fragment_shading_rate: vk::FALSE,
+                attachment_fragment_shading_rate: attachment as vk::Bool32,
+            }
+        }
+    }
+
+    unsafe impl vk::ExtendsDeviceCreateInfo for PhysicalDeviceFeatures {}
+
+    #[repr(C)]
+    #[derive(Copy, Clone, Debug)]
+    pub struct PhysicalDeviceProperties {
+        pub s_type: vk::StructureType,
+        pub p_next: *mut c_void,
+        pub min_fragment_shading_rate_attachment_texel_size: vk::Extent2D,
+        pub max_fragment_shading_rate_attachment_texel_size: vk::Extent2D,
+        pub max_fragment_shading_rate_attachment_texel_size_aspect_ratio: u32,
+        pub primitive_fragment_shading_rate_with_multiple_viewports: vk::Bool32,
+        pub layered_shading_rate_attachments: vk::Bool32,
+        pub fragment_shading_rate_non_trivial_combiner_ops: vk::Bool32,
+        pub max_fragment_size: vk::Extent2D,
+        pub max_fragment_size_aspect_ratio: u32,
+        pub max_fragment_shading_rate_coverage_samples: u32,
+        pub max_fragment_shading_rate_rasterization_samples: vk::SampleCountFlags,
+        pub fragment_shading_rate_with_shader_depth_stencil_writes: vk::Bool32,
+        pub fragment_shading_rate_with_sample_mask: vk::Bool32,
+        pub fragment_shading_rate_with_shader_sample_mask: vk::Bool32,
+        pub fragment_shading_rate_with_conservative_rasterization: vk::Bool32,
+        pub fragment_shading_rate_with_fragment_shader_interlock: vk::Bool32,
+        pub fragment_shading_rate_with_custom_sample_locations: vk::Bool32,
+        pub fragment_shading_rate_strict_multiply_combiner: vk::Bool32,
+    }
+
+    impl Default for PhysicalDeviceProperties {
+        fn default() -> Self {
+            PhysicalDeviceProperties {
+                s_type: PHYSICAL_DEVICE_PROPERTIES,
+                p_next: ptr::null_mut(),
+                min_fragment_shading_rate_attachment_texel_size: vk::Extent2D::default(),
+                max_fragment_shading_rate_attachment_texel_size: vk::Extent2D::default(),
+                max_fragment_shading_rate_attachment_texel_size_aspect_ratio: 0,
+                primitive_fragment_shading_rate_with_multiple_viewports: vk::FALSE,
+                layered_shading_rate_attachments: vk::FALSE,
+                fragment_shading_rate_non_trivial_combiner_ops: vk::FALSE,
+                max_fragment_size: vk::Extent2D::default(),
+                max_fragment_size_aspect_ratio: 0,
+                max_fragment_shading_rate_coverage_samples: 0,
+                max_fragment_shading_rate_rasterization_samples: vk::SampleCountFlags::empty(),
+                fragment_shading_rate_with_shader_depth_stencil_writes: vk::FALSE,
+                fragment_shading_rate_with_sample_mask: vk::FALSE,
+                fragment_shading_rate_with_shader_sample_mask: vk::FALSE,
+                fragment_shading_rate_with_conservative_rasterization: vk::FALSE,
+                fragment_shading_rate_with_fragment_shader_interlock: vk::FALSE,
+                fragment_shading_rate_with_custom_sample_locations: vk::FALSE,
+                fragment_shading_rate_strict_multiply_combiner: vk::FALSE,
+            }
+        }
+    }
+
+    unsafe impl vk::ExtendsPhysicalDeviceProperties2 for PhysicalDeviceProperties {}
+
+    /// Extends `VkSubpassDescription2` with a shading rate attachment.
+    #[repr(C)]
+    #[derive(Copy, Clone, Debug)]
+    pub struct AttachmentInfo {
+        pub s_type: vk::StructureType,
+        pub p_next: *const c_void,
+        pub p_fragment_shading_rate_attachment: *const vk::AttachmentReference2,
+        pub shading_rate_attachment_texel_size: vk::Extent2D,
+    }
+
+    impl AttachmentInfo {
+        pub fn new(attachment: &vk::AttachmentReference2, texel_size: vk::Extent2D) -> Self {
+            AttachmentInfo {
+                s_type: ATTACHMENT_INFO,
+                p_next: ptr::null(),
+                p_fragment_shading_rate_attachment: attachment,
+                shading_rate_attachment_texel_size: texel_size,
+            }
+        }
+    }
+
+    unsafe impl vk::ExtendsSubpassDescription2 for AttachmentInfo {}
+
+    type PfnCmdSetFragmentShadingRate = unsafe extern "system" fn(
+        command_buffer: vk::CommandBuffer,
+        fragment_size: *const vk::Extent2D,
+        combiner_ops: *const [u32; 2],
+    );
+    type PfnCreateRenderPass2 = unsafe extern "system" fn(
+        device: vk::Device,
+        create_info: *const vk::RenderPassCreateInfo2,
+        allocator: *const vk::AllocationCallbacks,
+        render_pass: *mut vk::RenderPass,
+    ) -> vk::Result;
+
+    /// Device level functions of the extension.
+    #[derive(Clone)]
+    pub struct DeviceFn {
+        pub cmd_set_fragment_shading_rate: PfnCmdSetFragmentShadingRate,
+        /// From `VK_KHR_create_renderpass2`, as the shading rate attachments
+        /// can only be described with `VkSubpassDescription2`.
+        pub create_render_pass2: PfnCreateRenderPass2,
+    }
+
+    impl DeviceFn {
+        /// Load the functions, returning `None` if any of them is missing.
+        pub unsafe fn load(mut get_proc_addr: impl FnMut(&CStr) -> *const c_void) -> Option<Self> {
+            let mut load = |name: &[u8]| {
+                let ptr = get_proc_addr(CStr::from_bytes_with_nul(name).unwrap());
+                if ptr.is_null() {
+                    None
+                } else {
+                    Some(ptr)
+                }
+            };
+            Some(DeviceFn {
+                cmd_set_fragment_shading_rate: std::mem::transmute(load(
+                    b"vkCmdSetFragmentShadingRateKHR\0",
+                )?),
+                create_render_pass2: std::mem::transmute(load(b"vkCreateRenderPass2KHR\0")?),
+            })
+        }
+    }
+}
+
+/// `VK_EXT_pageable_device_local_memory`
+pub mod pageable_device_local_memory {
+    use super::*;
+
+    pub fn name() -> &'static CStr {
+        CStr::from_bytes_with_nul(b"VK_EXT_pageable_device_local_memory\0").unwrap()
+    }
+
+    const PHYSICAL_DEVICE_FEATURES: vk::StructureType = vk::StructureType::from_raw(1_000_412_000);
+
+    #[repr(C)]
+    #[derive(Copy, Clone, Debug)]
+    pub struct PhysicalDeviceFeatures {
+        pub s_type: vk::StructureType,
+        pub p_next: *mut c_void,
+        pub pageable_device_local_memory: vk::Bool32,
+    }
+
+    impl PhysicalDeviceFeatures {
+        pub fn new(pageable_device_local_memory: bool) -> Self {
+            PhysicalDeviceFeatures {
+                s_type: PHYSICAL_DEVICE_FEATURES,
+                p_next: ptr::null_mut(),
+                pageable_device_local_memory: pageable_device_local_memory as vk::Bool32,
+            }
+        }
+    }
+
+    unsafe impl vk::ExtendsDeviceCreateInfo for PhysicalDeviceFeatures {}
+
+    type PfnSetDeviceMemoryPriority =
+        unsafe extern "system" fn(device: vk::Device, memory: vk::DeviceMemory, priority: f32);
+
+    /// Device level functions of the extension.
+    #[derive(Clone)]
+    pub struct DeviceFn {
+        pub set_device_memory_priority: PfnSetDeviceMemoryPriority,
+    }
+
+    impl DeviceFn {
+        /// Load the functions, returning `None` if any of them is missing.
+        pub unsafe fn load(mut get_proc_addr: impl FnMut(&CStr) -> *const c_void) -> Option<Self> {
+            let ptr = get_proc_addr(
+                CStr::from_bytes_with_nul(b"vkSetDeviceMemoryPriorityEXT\0").unwrap(),
+            );
+            if ptr.is_null() {
+                None
+            } else {
+                Some(DeviceFn {
+                    set_device_memory_priority: std::mem::transmute(ptr),
+                })
+            }
+        }
+    }
+}
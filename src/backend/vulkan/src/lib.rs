@@ -13,8 +13,10 @@ So we end up using a lot of `inplace_it` to get things collected on stack.
 One part that has actual logic is related to framebuffers. HAL is modelled
 after image-less framebuffers. If the the Vulkan implementation supports it,
 we map it 1:1, and everything is great. If it doesn't expose
-`KHR_imageless_framebuffer`, however, than we have to keep all the created
-framebuffers internally in an internally-synchronized map, per `B::Framebuffer`.
+`KHR_imageless_framebuffer`, however, than we have to create the actual
+framebuffers per set of image views, and keep them in the internally-synchronized
+`RenderingCache` of the device. They are destroyed with any of their views,
+so the swapchain recreation doesn't leave them behind.
 !*/
 
 #![allow(non_snake_case)]
@@ -36,25 +38,26 @@ use ash::{
         khr::Swapchain,
         nv::MeshShader,
     },
-    version::{DeviceV1_0, EntryV1_0, InstanceV1_0},
+    version::{DeviceV1_0, DeviceV1_1, EntryV1_0, InstanceV1_0},
     vk,
 };
 
 use hal::{
     adapter,
-    device::{CreationError as DeviceCreationError, DeviceLost, OutOfMemory},
+    device::{CreationError as DeviceCreationError, DeviceLost, OutOfMemory, WaitError},
     format, image, memory,
-    pso::{PatchSize, PipelineStage},
+    pso::{PatchSize, PipelineStage, ShadingRate},
     queue,
-    window::{OutOfDate, PresentError, Suboptimal, SurfaceLost},
+    window::{OutOfDate, PresentError, PresentTime, Suboptimal, SurfaceLost},
     Capabilities, DynamicStates, Features, Limits,
 };
 
+use parking_lot::Mutex;
 use std::{
     borrow::Cow,
     cmp,
     ffi::{CStr, CString},
-    fmt, mem, slice,
+    fmt, mem, ptr, slice,
     sync::Arc,
     thread, unreachable,
 };
@@ -65,11 +68,17 @@ use ash::EntryCustom;
 mod command;
 mod conv;
 mod device;
+mod ext;
 mod info;
 mod native;
 mod pool;
+#[cfg(debug_assertions)]
+mod track;
 mod window;
 
+pub use ash;
+pub use window::DisplayInfo;
+
 // Sets up the maximum count we expect in most cases, but maybe not all of them.
 const ROUGH_MAX_ATTACHMENT_COUNT: usize = 5;
 
@@ -77,6 +86,10 @@ pub struct RawInstance {
     inner: ash::Instance,
     debug_messenger: Option<DebugMessenger>,
     get_physical_device_properties: Option<vk::KhrGetPhysicalDeviceProperties2Fn>,
+    get_cooperative_matrix_properties: Option<ext::cooperative_matrix::InstanceFn>,
+    /// Surface capabilities query, only loaded if `VK_EXT_surface_maintenance1`
+    /// is enabled, to check the compatibility of the present modes.
+    surface_maintenance: Option<vk::KhrGetSurfaceCapabilities2Fn>,
 }
 
 pub enum DebugMessenger {
@@ -165,6 +178,13 @@ impl fmt::Debug for Instance {
     }
 }
 
+impl Instance {
+    /// Returns the raw `ash` instance.
+    pub fn raw_instance(&self) -> &ash::Instance {
+        &self.raw.inner
+    }
+}
+
 fn map_queue_type(flags: vk::QueueFlags) -> queue::QueueType {
     if flags.contains(vk::QueueFlags::GRAPHICS | vk::QueueFlags::COMPUTE) {
         // TRANSFER_BIT optional
@@ -424,6 +444,7 @@ impl hal::Instance<Backend> for Instance {
                 extensions.push(extensions::khr::XlibSurface::name());
                 extensions.push(extensions::khr::XcbSurface::name());
                 extensions.push(extensions::khr::WaylandSurface::name());
+                extensions.push(extensions::khr::Display::name());
             }
             if cfg!(target_os = "android") {
                 extensions.push(extensions::khr::AndroidSurface::name());
@@ -441,6 +462,9 @@ impl hal::Instance<Backend> for Instance {
             }
 
             extensions.push(vk::KhrGetPhysicalDeviceProperties2Fn::name());
+            extensions.push(vk::KhrGetSurfaceCapabilities2Fn::name());
+            extensions.push(ext::surface_maintenance1::name());
+            extensions.push(vk::ExtSwapchainColorspaceFn::name());
 
             // Only keep available extensions.
             extensions.retain(|&ext| {
@@ -518,6 +542,24 @@ impl hal::Instance<Backend> for Instance {
                 })
             });
 
+        let surface_maintenance = if extensions.contains(&ext::surface_maintenance1::name())
+            && extensions.contains(&vk::KhrGetSurfaceCapabilities2Fn::name())
+        {
+            Some(vk::KhrGetSurfaceCapabilities2Fn::load(|name| unsafe {
+                std::mem::transmute(entry.get_instance_proc_addr(instance.handle(), name.as_ptr()))
+            }))
+        } else {
+            None
+        };
+
+        // Physical device level function of a device extension, may be
+        // present even if none of the devices support it.
+        let get_cooperative_matrix_properties = unsafe {
+            ext::cooperative_matrix::InstanceFn::load(|name| {
+                std::mem::transmute(entry.get_instance_proc_addr(instance.handle(), name.as_ptr()))
+            })
+        };
+
         let debug_messenger = {
             // make sure VK_EXT_debug_utils is available
             if instance_extensions.iter().any(|props| unsafe {
@@ -552,6 +594,8 @@ impl hal::Instance<Backend> for Instance {
                 inner: instance,
                 debug_messenger,
                 get_physical_device_properties,
+                get_cooperative_matrix_properties,
+                surface_maintenance,
             }),
             extensions,
             entry,
@@ -606,7 +650,8 @@ impl hal::Instance<Backend> for Instance {
                         | vk::MemoryPropertyFlags::HOST_VISIBLE
                         | vk::MemoryPropertyFlags::HOST_COHERENT
                         | vk::MemoryPropertyFlags::HOST_CACHED
-                        | vk::MemoryPropertyFlags::LAZILY_ALLOCATED,
+                        | vk::MemoryPropertyFlags::LAZILY_ALLOCATED
+                        | vk::MemoryPropertyFlags::PROTECTED,
                 };
                 let queue_families = unsafe {
                     self.raw
@@ -719,6 +764,11 @@ impl queue::QueueFamily for QueueFamily {
     fn id(&self) -> queue::QueueFamilyId {
         queue::QueueFamilyId(self.index as _)
     }
+    fn supports_protected(&self) -> bool {
+        self.properties
+            .queue_flags
+            .contains(vk::QueueFlags::PROTECTED)
+    }
 }
 
 pub struct PhysicalDevice {
@@ -731,11 +781,95 @@ pub struct PhysicalDevice {
 }
 
 impl PhysicalDevice {
+    /// Returns the raw physical device handle.
+    pub fn raw_handle(&self) -> vk::PhysicalDevice {
+        self.handle
+    }
+
     fn supports_extension(&self, extension: &CStr) -> bool {
         self.extensions
             .iter()
             .any(|ep| unsafe { CStr::from_ptr(ep.extension_name.as_ptr()) } == extension)
     }
+
+    fn supports_multiview(&self) -> bool {
+        self.api_version >= Version::V1_1 || self.supports_extension(vk::KhrMultiviewFn::name())
+    }
+
+    fn supports_memory_priority(&self) -> bool {
+        if !self.supports_extension(vk::ExtMemoryPriorityFn::name()) {
+            return false;
+        }
+        let get_device_properties = match self.instance.get_physical_device_properties {
+            Some(ref get_device_properties) => get_device_properties,
+            None => return false,
+        };
+        let mut memory_priority_features =
+            vk::PhysicalDeviceMemoryPriorityFeaturesEXT::builder().build();
+        let mut features2 = vk::PhysicalDeviceFeatures2KHR::builder().build();
+        features2.p_next = &mut memory_priority_features as *mut _ as *mut _;
+        unsafe {
+            get_device_properties.get_physical_device_features2_khr(self.handle, &mut features2);
+        }
+        memory_priority_features.memory_priority != 0
+    }
+
+    fn supports_pageable_device_local_memory(&self) -> bool {
+        if !self.supports_extension(ext::pageable_device_local_memory::name()) {
+            return false;
+        }
+        let get_device_properties = match self.instance.get_physical_device_properties {
+            Some(ref get_device_properties) => get_device_properties,
+            None => return false,
+        };
+        let mut pageable_features =
+            ext::pageable_device_local_memory::PhysicalDeviceFeatures::new(false);
+        let mut features2 = vk::PhysicalDeviceFeatures2KHR::builder().build();
+        features2.p_next = &mut pageable_features as *mut _ as *mut _;
+        unsafe {
+            get_device_properties.get_physical_device_features2_khr(self.handle, &mut features2);
+        }
+        pageable_features.pageable_device_local_memory != 0
+    }
+
+    fn supports_device_fault(&self) -> bool {
+        if !self.supports_extension(ext::device_fault::name()) {
+            return false;
+        }
+        let get_device_properties = match self.instance.get_physical_device_properties {
+            Some(ref get_device_properties) => get_device_properties,
+            None => return false,
+        };
+        let mut device_fault_features = ext::device_fault::PhysicalDeviceFeatures::new(false);
+        let mut features2 = vk::PhysicalDeviceFeatures2KHR::builder().build();
+        features2.p_next = &mut device_fault_features as *mut _ as *mut _;
+        unsafe {
+            get_device_properties.get_physical_device_features2_khr(self.handle, &mut features2);
+        }
+        device_fault_features.device_fault != 0
+    }
+
+    fn supports_dynamic_rendering(&self) -> bool {
+        if !self.supports_extension(ext::dynamic_rendering::name())
+            || !ext::dynamic_rendering::dependencies()
+                .iter()
+                .all(|&name| self.supports_extension(name))
+        {
+            return false;
+        }
+        let get_device_properties = match self.instance.get_physical_device_properties {
+            Some(ref get_device_properties) => get_device_properties,
+            None => return false,
+        };
+        let mut dynamic_rendering_features =
+            ext::dynamic_rendering::PhysicalDeviceFeatures::new(false);
+        let mut features2 = vk::PhysicalDeviceFeatures2KHR::builder().build();
+        features2.p_next = &mut dynamic_rendering_features as *mut _ as *mut _;
+        unsafe {
+            get_device_properties.get_physical_device_features2_khr(self.handle, &mut features2);
+        }
+        dynamic_rendering_features.dynamic_rendering != 0
+    }
 }
 
 impl fmt::Debug for PhysicalDevice {
@@ -749,6 +883,27 @@ pub struct DeviceCreationFeatures {
     descriptor_indexing: Option<vk::PhysicalDeviceDescriptorIndexingFeaturesEXT>,
     mesh_shaders: Option<vk::PhysicalDeviceMeshShaderFeaturesNV>,
     imageless_framebuffers: Option<vk::PhysicalDeviceImagelessFramebufferFeaturesKHR>,
+    multiview: Option<vk::PhysicalDeviceMultiviewFeatures>,
+    inline_uniform_block: Option<vk::PhysicalDeviceInlineUniformBlockFeaturesEXT>,
+    sampler_ycbcr_conversion: Option<vk::PhysicalDeviceSamplerYcbcrConversionFeatures>,
+    separate_depth_stencil_layouts: Option<vk::PhysicalDeviceSeparateDepthStencilLayoutsFeatures>,
+    protected_memory: Option<vk::PhysicalDeviceProtectedMemoryFeatures>,
+    memory_priority: Option<vk::PhysicalDeviceMemoryPriorityFeaturesEXT>,
+    pageable_device_local_memory: Option<ext::pageable_device_local_memory::PhysicalDeviceFeatures>,
+    image_view_min_lod: Option<ext::image_view_min_lod::PhysicalDeviceFeatures>,
+    pipeline_creation_cache_control:
+        Option<ext::pipeline_creation_cache_control::PhysicalDeviceFeatures>,
+    host_image_copy: Option<ext::host_image_copy::PhysicalDeviceFeatures>,
+    cooperative_matrix: Option<ext::cooperative_matrix::PhysicalDeviceFeatures>,
+    swapchain_maintenance: Option<ext::swapchain_maintenance1::PhysicalDeviceFeatures>,
+    blend_operation_advanced: Option<vk::PhysicalDeviceBlendOperationAdvancedFeaturesEXT>,
+    line_rasterization: Option<vk::PhysicalDeviceLineRasterizationFeaturesEXT>,
+    depth_clip_enable: Option<vk::PhysicalDeviceDepthClipEnableFeaturesEXT>,
+    fragment_shader_interlock: Option<vk::PhysicalDeviceFragmentShaderInterlockFeaturesEXT>,
+    robustness2: Option<ext::robustness2::PhysicalDeviceFeatures>,
+    device_fault: Option<ext::device_fault::PhysicalDeviceFeatures>,
+    dynamic_rendering: Option<ext::dynamic_rendering::PhysicalDeviceFeatures>,
+    fragment_shading_rate: Option<ext::fragment_shading_rate::PhysicalDeviceFeatures>,
 }
 
 impl adapter::PhysicalDevice<Backend> for PhysicalDevice {
@@ -757,11 +912,21 @@ impl adapter::PhysicalDevice<Backend> for PhysicalDevice {
         families: &[(&QueueFamily, &[queue::QueuePriority])],
         requested_features: Features,
     ) -> Result<adapter::Gpu<Backend>, DeviceCreationError> {
+        // Protected queues have to be created and retrieved with the protected flag
+        let queue_flags = |family: &QueueFamily| {
+            if requested_features.contains(Features::PROTECTED_MEMORY)
+                && queue::QueueFamily::supports_protected(family)
+            {
+                vk::DeviceQueueCreateFlags::PROTECTED
+            } else {
+                vk::DeviceQueueCreateFlags::empty()
+            }
+        };
         let family_infos = families
             .iter()
             .map(|&(family, priorities)| {
                 vk::DeviceQueueCreateInfo::builder()
-                    .flags(vk::DeviceQueueCreateFlags::empty())
+                    .flags(queue_flags(family))
                     .queue_family_index(family.index)
                     .queue_priorities(priorities)
                     .build()
@@ -773,9 +938,28 @@ impl adapter::PhysicalDevice<Backend> for PhysicalDevice {
         }
 
         let imageless_framebuffers = self.supports_extension(vk::KhrImagelessFramebufferFn::name());
-
-        let mut enabled_features =
-            conv::map_device_features(requested_features, imageless_framebuffers);
+        let memory_priority = self.supports_memory_priority();
+        // Lets the driver page device-local allocations out in the order of their priorities
+        // instead of failing under memory pressure, so it's enabled whenever available.
+        let pageable_device_local_memory =
+            memory_priority && self.supports_pageable_device_local_memory();
+        // Only used for the diagnostics of the device loss, so it's enabled whenever available.
+        let device_fault = self.supports_device_fault();
+        let checkpoints = self.supports_extension(vk::NvDeviceDiagnosticCheckpointsFn::name());
+        // `begin_rendering` falls back to the `RenderingCache` without the extension.
+        let dynamic_rendering = requested_features.contains(Features::DYNAMIC_RENDERING)
+            && self.supports_dynamic_rendering();
+        let fragment_shading_rate = requested_features
+            .intersects(Features::SHADING_RATE | Features::SHADING_RATE_ATTACHMENT);
+
+        let mut enabled_features = conv::map_device_features(
+            requested_features,
+            imageless_framebuffers,
+            memory_priority,
+            pageable_device_local_memory,
+            device_fault,
+            dynamic_rendering,
+        );
         let enabled_extensions = {
             let mut requested_extensions: Vec<&'static CStr> = Vec::new();
 
@@ -789,6 +973,32 @@ impl adapter::PhysicalDevice<Backend> for PhysicalDevice {
 
             requested_extensions.push(vk::ExtSamplerFilterMinmaxFn::name());
 
+            if memory_priority {
+                requested_extensions.push(vk::ExtMemoryPriorityFn::name());
+            }
+
+            if pageable_device_local_memory {
+                requested_extensions.push(ext::pageable_device_local_memory::name());
+            }
+
+            if device_fault {
+                requested_extensions.push(ext::device_fault::name());
+            }
+
+            if checkpoints {
+                requested_extensions.push(vk::NvDeviceDiagnosticCheckpointsFn::name());
+            }
+
+            if dynamic_rendering {
+                requested_extensions.push(ext::dynamic_rendering::name());
+                requested_extensions.extend_from_slice(&ext::dynamic_rendering::dependencies());
+            }
+
+            if fragment_shading_rate {
+                requested_extensions.push(ext::fragment_shading_rate::name());
+                requested_extensions.extend_from_slice(&ext::fragment_shading_rate::dependencies());
+            }
+
             if requested_features.contains(Features::NDC_Y_UP) {
                 // `VK_AMD_negative_viewport_height` is obsoleted by `VK_KHR_maintenance1` and must not be enabled alongside `VK_KHR_maintenance1` or a 1.1+ device.
                 if self.api_version < Version::V1_1
@@ -815,6 +1025,103 @@ impl adapter::PhysicalDevice<Backend> for PhysicalDevice {
                 requested_extensions.push(DrawIndirectCount::name());
             }
 
+            if requested_features.contains(Features::DRM_FORMAT_MODIFIER) {
+                requested_extensions.push(vk::ExtImageDrmFormatModifierFn::name());
+            }
+
+            if requested_features.contains(Features::EXTERNAL_MEMORY_DMA_BUF) {
+                requested_extensions.push(vk::KhrExternalMemoryFdFn::name());
+                requested_extensions.push(vk::ExtExternalMemoryDmaBufFn::name());
+            }
+
+            if requested_features.contains(Features::FULL_SCREEN_EXCLUSIVE) {
+                requested_extensions.push(vk::ExtFullScreenExclusiveFn::name());
+            }
+
+            if requested_features.contains(Features::PRESENT_TIMING) {
+                requested_extensions.push(vk::GoogleDisplayTimingFn::name());
+            }
+
+            if requested_features.contains(Features::HDR_METADATA) {
+                requested_extensions.push(vk::ExtHdrMetadataFn::name());
+            }
+
+            if requested_features.contains(Features::SAMPLE_LOCATIONS) {
+                requested_extensions.push(vk::ExtSampleLocationsFn::name());
+            }
+
+            if requested_features.contains(Features::DEPTH_RANGE_UNRESTRICTED) {
+                requested_extensions.push(vk::ExtDepthRangeUnrestrictedFn::name());
+            }
+
+            if requested_features.contains(Features::IMAGE_VIEW_MIN_LOD) {
+                requested_extensions.push(ext::image_view_min_lod::name());
+            }
+
+            if requested_features.contains(Features::PIPELINE_CREATION_CACHE_CONTROL) {
+                requested_extensions.push(ext::pipeline_creation_cache_control::name());
+            }
+
+            if requested_features.contains(Features::HOST_IMAGE_COPY) {
+                requested_extensions.push(ext::host_image_copy::name());
+                requested_extensions.extend_from_slice(&ext::host_image_copy::dependencies());
+            }
+
+            if requested_features.contains(Features::COOPERATIVE_MATRIX) {
+                requested_extensions.push(ext::cooperative_matrix::name());
+            }
+
+            if requested_features.contains(Features::PRESENT_MODE_SWITCH) {
+                requested_extensions.push(ext::swapchain_maintenance1::name());
+            }
+
+            if requested_features.contains(Features::BLEND_OPERATION_ADVANCED) {
+                requested_extensions.push(vk::ExtBlendOperationAdvancedFn::name());
+            }
+
+            if requested_features.intersects(
+                Features::LINE_RASTERIZATION_RECTANGULAR
+                    | Features::LINE_RASTERIZATION_BRESENHAM
+                    | Features::LINE_RASTERIZATION_SMOOTH,
+            ) {
+                requested_extensions.push(vk::ExtLineRasterizationFn::name());
+            }
+
+            if requested_features.contains(Features::DEPTH_CLIP_ENABLE) {
+                requested_extensions.push(vk::ExtDepthClipEnableFn::name());
+            }
+
+            if requested_features.intersects(
+                Features::CONSERVATIVE_RASTERIZATION
+                    | Features::CONSERVATIVE_RASTERIZATION_UNDERESTIMATE,
+            ) {
+                requested_extensions.push(vk::ExtConservativeRasterizationFn::name());
+            }
+
+            if requested_features.contains(Features::FRAGMENT_SHADER_INTERLOCK) {
+                requested_extensions.push(vk::ExtFragmentShaderInterlockFn::name());
+            }
+
+            if requested_features.contains(Features::NULL_DESCRIPTOR) {
+                requested_extensions.push(ext::robustness2::name());
+            }
+
+            if requested_features.contains(Features::MULTIVIEW) && self.api_version < Version::V1_1
+            {
+                requested_extensions.push(vk::KhrMultiviewFn::name());
+            }
+
+            if requested_features.contains(Features::INLINE_UNIFORM_BLOCK) {
+                requested_extensions.push(vk::ExtInlineUniformBlockFn::name());
+            }
+
+            if requested_features.contains(Features::SEPARATE_DEPTH_STENCIL_LAYOUTS)
+                && self.api_version < Version::V1_2
+            {
+                requested_extensions.push(vk::KhrSeparateDepthStencilLayoutsFn::name());
+                requested_extensions.push(vk::KhrCreateRenderpass2Fn::name()); // Required for `KhrSeparateDepthStencilLayoutsFn`
+            }
+
             let (supported_extensions, unsupported_extensions) = requested_extensions
                 .iter()
                 .partition::<Vec<&CStr>, _>(|&&extension| self.supports_extension(extension));
@@ -869,6 +1176,66 @@ impl adapter::PhysicalDevice<Backend> for PhysicalDevice {
             if let Some(ref mut feature) = enabled_features.imageless_framebuffers {
                 info = info.push_next(feature);
             }
+            if let Some(ref mut feature) = enabled_features.multiview {
+                info = info.push_next(feature);
+            }
+            if let Some(ref mut feature) = enabled_features.inline_uniform_block {
+                info = info.push_next(feature);
+            }
+            if let Some(ref mut feature) = enabled_features.sampler_ycbcr_conversion {
+                info = info.push_next(feature);
+            }
+            if let Some(ref mut feature) = enabled_features.separate_depth_stencil_layouts {
+                info = info.push_next(feature);
+            }
+            if let Some(ref mut feature) = enabled_features.protected_memory {
+                info = info.push_next(feature);
+            }
+            if let Some(ref mut feature) = enabled_features.memory_priority {
+                info = info.push_next(feature);
+            }
+            if let Some(ref mut feature) = enabled_features.pageable_device_local_memory {
+                info = info.push_next(feature);
+            }
+            if let Some(ref mut feature) = enabled_features.image_view_min_lod {
+                info = info.push_next(feature);
+            }
+            if let Some(ref mut feature) = enabled_features.pipeline_creation_cache_control {
+                info = info.push_next(feature);
+            }
+            if let Some(ref mut feature) = enabled_features.host_image_copy {
+                info = info.push_next(feature);
+            }
+            if let Some(ref mut feature) = enabled_features.cooperative_matrix {
+                info = info.push_next(feature);
+            }
+            if let Some(ref mut feature) = enabled_features.swapchain_maintenance {
+                info = info.push_next(feature);
+            }
+            if let Some(ref mut feature) = enabled_features.blend_operation_advanced {
+                info = info.push_next(feature);
+            }
+            if let Some(ref mut feature) = enabled_features.line_rasterization {
+                info = info.push_next(feature);
+            }
+            if let Some(ref mut feature) = enabled_features.depth_clip_enable {
+                info = info.push_next(feature);
+            }
+            if let Some(ref mut feature) = enabled_features.fragment_shader_interlock {
+                info = info.push_next(feature);
+            }
+            if let Some(ref mut feature) = enabled_features.robustness2 {
+                info = info.push_next(feature);
+            }
+            if let Some(ref mut feature) = enabled_features.device_fault {
+                info = info.push_next(feature);
+            }
+            if let Some(ref mut feature) = enabled_features.dynamic_rendering {
+                info = info.push_next(feature);
+            }
+            if let Some(ref mut feature) = enabled_features.fragment_shading_rate {
+                info = info.push_next(feature);
+            }
 
             match self.instance.inner.create_device(self.handle, &info, None) {
                 Ok(device) => device,
@@ -906,6 +1273,180 @@ impl adapter::PhysicalDevice<Backend> for PhysicalDevice {
             None
         };
 
+        let drm_format_modifier_fn = if requested_features.contains(Features::DRM_FORMAT_MODIFIER) {
+            Some(vk::ExtImageDrmFormatModifierFn::load(|name| {
+                mem::transmute(
+                    self.instance
+                        .inner
+                        .get_device_proc_addr(device_raw.handle(), name.as_ptr()),
+                )
+            }))
+        } else {
+            None
+        };
+
+        let external_memory_fd_fn =
+            if requested_features.contains(Features::EXTERNAL_MEMORY_DMA_BUF) {
+                Some(vk::KhrExternalMemoryFdFn::load(|name| {
+                    mem::transmute(
+                        self.instance
+                            .inner
+                            .get_device_proc_addr(device_raw.handle(), name.as_ptr()),
+                    )
+                }))
+            } else {
+                None
+            };
+
+        let full_screen_exclusive_fn =
+            if requested_features.contains(Features::FULL_SCREEN_EXCLUSIVE) {
+                Some(vk::ExtFullScreenExclusiveFn::load(|name| {
+                    mem::transmute(
+                        self.instance
+                            .inner
+                            .get_device_proc_addr(device_raw.handle(), name.as_ptr()),
+                    )
+                }))
+            } else {
+                None
+            };
+
+        let display_timing_fn = if requested_features.contains(Features::PRESENT_TIMING) {
+            Some(vk::GoogleDisplayTimingFn::load(|name| {
+                mem::transmute(
+                    self.instance
+                        .inner
+                        .get_device_proc_addr(device_raw.handle(), name.as_ptr()),
+                )
+            }))
+        } else {
+            None
+        };
+
+        let hdr_metadata_fn = if requested_features.contains(Features::HDR_METADATA) {
+            Some(vk::ExtHdrMetadataFn::load(|name| {
+                mem::transmute(
+                    self.instance
+                        .inner
+                        .get_device_proc_addr(device_raw.handle(), name.as_ptr()),
+                )
+            }))
+        } else {
+            None
+        };
+
+        let sample_locations_fn = if requested_features.contains(Features::SAMPLE_LOCATIONS) {
+            Some(vk::ExtSampleLocationsFn::load(|name| {
+                mem::transmute(
+                    self.instance
+                        .inner
+                        .get_device_proc_addr(device_raw.handle(), name.as_ptr()),
+                )
+            }))
+        } else {
+            None
+        };
+
+        let host_image_copy_fn = if requested_features.contains(Features::HOST_IMAGE_COPY) {
+            ext::host_image_copy::DeviceFn::load(|name| {
+                mem::transmute(
+                    self.instance
+                        .inner
+                        .get_device_proc_addr(device_raw.handle(), name.as_ptr()),
+                )
+            })
+        } else {
+            None
+        };
+
+        let pageable_device_local_memory_fn = if pageable_device_local_memory {
+            ext::pageable_device_local_memory::DeviceFn::load(|name| {
+                mem::transmute(
+                    self.instance
+                        .inner
+                        .get_device_proc_addr(device_raw.handle(), name.as_ptr()),
+                )
+            })
+        } else {
+            None
+        };
+
+        let device_fault_fn = if device_fault {
+            ext::device_fault::DeviceFn::load(|name| {
+                mem::transmute(
+                    self.instance
+                        .inner
+                        .get_device_proc_addr(device_raw.handle(), name.as_ptr()),
+                )
+            })
+        } else {
+            None
+        };
+
+        let dynamic_rendering_fn = if dynamic_rendering {
+            ext::dynamic_rendering::DeviceFn::load(|name| {
+                mem::transmute(
+                    self.instance
+                        .inner
+                        .get_device_proc_addr(device_raw.handle(), name.as_ptr()),
+                )
+            })
+        } else {
+            None
+        };
+
+        let fragment_shading_rate_fn = if fragment_shading_rate {
+            ext::fragment_shading_rate::DeviceFn::load(|name| {
+                mem::transmute(
+                    self.instance
+                        .inner
+                        .get_device_proc_addr(device_raw.handle(), name.as_ptr()),
+                )
+            })
+        } else {
+            None
+        };
+
+        let checkpoints_fn = if checkpoints {
+            Some(vk::NvDeviceDiagnosticCheckpointsFn::load(|name| {
+                mem::transmute(
+                    self.instance
+                        .inner
+                        .get_device_proc_addr(device_raw.handle(), name.as_ptr()),
+                )
+            }))
+        } else {
+            None
+        };
+
+        // The device keeps the queue handles, to query their checkpoints after a loss.
+        let raw_queues = families
+            .iter()
+            .map(|&(family, ref priorities)| {
+                let flags = queue_flags(family);
+                let queues = (0..priorities.len())
+                    .map(|id| {
+                        if flags.is_empty() {
+                            device_raw.get_device_queue(family.index, id as _)
+                        } else {
+                            let info = vk::DeviceQueueInfo2::builder()
+                                .flags(flags)
+                                .queue_family_index(family.index)
+                                .queue_index(id as _);
+                            let mut queue = vk::Queue::null();
+                            device_raw.fp_v1_1().get_device_queue2(
+                                device_raw.handle(),
+                                &*info,
+                                &mut queue,
+                            );
+                            queue
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                (family, flags, queues)
+            })
+            .collect::<Vec<_>>();
+
         #[cfg(feature = "naga")]
         let naga_options = {
             use naga::back::spv;
@@ -940,33 +1481,64 @@ impl adapter::PhysicalDevice<Backend> for PhysicalDevice {
                 raw: device_raw,
                 features: requested_features,
                 instance: Arc::clone(&self.instance),
+                physical_device: self.handle,
                 extension_fns: DeviceExtensionFunctions {
                     mesh_shaders: mesh_fn,
                     draw_indirect_count: indirect_count_fn,
+                    drm_format_modifier: drm_format_modifier_fn,
+                    external_memory_fd: external_memory_fd_fn,
+                    full_screen_exclusive: full_screen_exclusive_fn,
+                    display_timing: display_timing_fn,
+                    hdr_metadata: hdr_metadata_fn,
+                    sample_locations: sample_locations_fn,
+                    host_image_copy: host_image_copy_fn,
+                    pageable_device_local_memory: pageable_device_local_memory_fn,
+                    device_fault: device_fault_fn,
+                    checkpoints: checkpoints_fn,
+                    dynamic_rendering: dynamic_rendering_fn,
+                    fragment_shading_rate: fragment_shading_rate_fn,
                 },
                 flip_y_requires_shift: self.api_version >= Version::V1_1
                     || self.supports_extension(vk::KhrMaintenance1Fn::name()),
                 imageless_framebuffers,
+                memory_priority,
+                dedicated_allocation: self.api_version >= Version::V1_1,
                 timestamp_period: self.properties.limits.timestamp_period,
+                shading_rate_texel_size: {
+                    let [width, height] = self.limits().shading_rate_attachment_texel_size;
+                    vk::Extent2D { width, height }
+                },
+                rendering_cache: Mutex::new(native::RenderingCache::default()),
+                queues: raw_queues
+                    .iter()
+                    .flat_map(|&(_, _, ref queues)| queues.iter().cloned())
+                    .collect(),
+                checkpoint_labels: Mutex::new(native::CheckpointLabels::default()),
+                #[cfg(debug_assertions)]
+                semaphore_tracker: track::SemaphoreTracker::default(),
             }),
             vendor_id: self.properties.vendor_id,
             valid_ash_memory_types,
             #[cfg(feature = "naga")]
             naga_options,
+            pipeline_workers: auxil::workers::Workers::new(
+                "gfx-vulkan-pipelines",
+                auxil::workers::PIPELINE_THREADS,
+            ),
         };
 
         let device_arc = Arc::clone(&device.shared);
-        let queue_groups = families
-            .iter()
-            .map(|&(family, ref priorities)| {
+        let queue_groups = raw_queues
+            .into_iter()
+            .map(|(family, flags, queues)| {
                 let mut family_raw =
                     queue::QueueGroup::new(queue::QueueFamilyId(family.index as usize));
-                for id in 0..priorities.len() {
-                    let queue_raw = device_arc.raw.get_device_queue(family.index, id as _);
+                for queue_raw in queues {
                     family_raw.add_queue(Queue {
                         raw: Arc::new(queue_raw),
                         device: device_arc.clone(),
                         swapchain_fn: swapchain_fn.clone(),
+                        protected: !flags.is_empty(),
                     });
                 }
                 family_raw
@@ -1047,6 +1619,105 @@ impl adapter::PhysicalDevice<Backend> for PhysicalDevice {
         }
     }
 
+    fn drm_modifier_properties(&self, format: format::Format) -> Vec<image::DrmModifierProperties> {
+        let get_device_properties = match self.instance.get_physical_device_properties {
+            Some(ref get_device_properties)
+                if self.api_version >= Version::V1_1
+                    && self.supports_extension(vk::ExtImageDrmFormatModifierFn::name()) =>
+            {
+                get_device_properties
+            }
+            _ => return Vec::new(),
+        };
+        let vk_format = conv::map_format(format);
+
+        // First query the number of modifiers, then fill them in
+        let mut modifier_list = vk::DrmFormatModifierPropertiesListEXT::builder().build();
+        let mut properties = vk::FormatProperties2KHR::builder().build();
+        properties.p_next = &mut modifier_list as *mut _ as *mut _;
+        unsafe {
+            get_device_properties.get_physical_device_format_properties2_khr(
+                self.handle,
+                vk_format,
+                &mut properties,
+            );
+        }
+
+        let mut modifiers = vec![
+            vk::DrmFormatModifierPropertiesEXT::default();
+            modifier_list.drm_format_modifier_count as usize
+        ];
+        modifier_list.p_drm_format_modifier_properties = modifiers.as_mut_ptr();
+        unsafe {
+            get_device_properties.get_physical_device_format_properties2_khr(
+                self.handle,
+                vk_format,
+                &mut properties,
+            );
+        }
+        modifiers.truncate(modifier_list.drm_format_modifier_count as usize);
+
+        let supports_transfer_bits = self.supports_extension(vk::KhrMaintenance1Fn::name());
+        modifiers
+            .into_iter()
+            .map(|props| image::DrmModifierProperties {
+                modifier: image::DrmModifier(props.drm_format_modifier),
+                plane_count: props.drm_format_modifier_plane_count,
+                features: conv::map_image_features(
+                    props.drm_format_modifier_tiling_features,
+                    supports_transfer_bits,
+                ),
+            })
+            .collect()
+    }
+
+    fn cooperative_matrix_properties(&self) -> Vec<adapter::CooperativeMatrixProperties> {
+        let get_properties = match self.instance.get_cooperative_matrix_properties {
+            Some(ref instance_fn) if self.supports_extension(ext::cooperative_matrix::name()) => {
+                instance_fn.get_physical_device_properties
+            }
+            _ => return Vec::new(),
+        };
+
+        // First query the number of configurations, then fill them in
+        let mut count = 0;
+        let result = unsafe { get_properties(self.handle, &mut count, ptr::null_mut()) };
+        if result != vk::Result::SUCCESS {
+            error!(
+                "Unexpected error in `cooperative_matrix_properties`: {:?}",
+                result
+            );
+            return Vec::new();
+        }
+        let mut properties = vec![ext::cooperative_matrix::Properties::default(); count as usize];
+        let result = unsafe { get_properties(self.handle, &mut count, properties.as_mut_ptr()) };
+        if result != vk::Result::SUCCESS && result != vk::Result::INCOMPLETE {
+            error!(
+                "Unexpected error in `cooperative_matrix_properties`: {:?}",
+                result
+            );
+            return Vec::new();
+        }
+        properties.truncate(count as usize);
+
+        properties
+            .into_iter()
+            .filter(|props| props.scope == ext::cooperative_matrix::SCOPE_SUBGROUP)
+            .filter_map(|props| {
+                Some(adapter::CooperativeMatrixProperties {
+                    m_size: props.m_size,
+                    n_size: props.n_size,
+                    k_size: props.k_size,
+                    a_type: conv::map_vk_component_type(props.a_type)?,
+                    b_type: conv::map_vk_component_type(props.b_type)?,
+                    c_type: conv::map_vk_component_type(props.c_type)?,
+                    result_type: conv::map_vk_component_type(props.result_type)?,
+                    saturating_accumulation: props.saturating_accumulation != 0,
+                })
+            })
+            .collect()
+    }
+
     fn memory_properties(&self) -> adapter::MemoryProperties {
         let mem_properties = unsafe {
             self.instance
@@ -1094,6 +1765,22 @@ impl adapter::PhysicalDevice<Backend> for PhysicalDevice {
                     == info::intel::DEVICE_SKY_LAKE_MASK);
 
         let mut descriptor_indexing_features = None;
+        let mut multiview_features = None;
+        let mut inline_uniform_block_features = None;
+        let mut sampler_ycbcr_conversion_features = None;
+        let mut separate_depth_stencil_layouts_features = None;
+        let mut protected_memory_features = None;
+        let mut image_view_min_lod_features = None;
+        let mut pipeline_creation_cache_control_features = None;
+        let mut host_image_copy_features = None;
+        let mut cooperative_matrix_features = None;
+        let mut swapchain_maintenance_features = None;
+        let mut blend_operation_advanced_features = None;
+        let mut line_rasterization_features = None;
+        let mut depth_clip_enable_features = None;
+        let mut fragment_shader_interlock_features = None;
+        let mut robustness2_features = None;
+        let mut fragment_shading_rate_features = None;
         let features = if let Some(ref get_device_properties) =
             self.instance.get_physical_device_properties
         {
@@ -1110,6 +1797,130 @@ impl adapter::PhysicalDevice<Backend> for PhysicalDevice {
                 let mut_ref = descriptor_indexing_features.as_mut().unwrap();
                 mut_ref.p_next = mem::replace(&mut features2.p_next, mut_ref as *mut _ as *mut _);
             }
+            if self.supports_multiview() {
+                multiview_features = Some(vk::PhysicalDeviceMultiviewFeatures::builder().build());
+
+                let mut_ref = multiview_features.as_mut().unwrap();
+                mut_ref.p_next = mem::replace(&mut features2.p_next, mut_ref as *mut _ as *mut _);
+            }
+            if self.supports_extension(vk::ExtInlineUniformBlockFn::name()) {
+                inline_uniform_block_features =
+                    Some(vk::PhysicalDeviceInlineUniformBlockFeaturesEXT::builder().build());
+
+                let mut_ref = inline_uniform_block_features.as_mut().unwrap();
+                mut_ref.p_next = mem::replace(&mut features2.p_next, mut_ref as *mut _ as *mut _);
+            }
+            // The conversion entry points are only used through the Vulkan 1.1 core
+            if self.api_version >= Version::V1_1 {
+                sampler_ycbcr_conversion_features =
+                    Some(vk::PhysicalDeviceSamplerYcbcrConversionFeatures::builder().build());
+
+                let mut_ref = sampler_ycbcr_conversion_features.as_mut().unwrap();
+                mut_ref.p_next = mem::replace(&mut features2.p_next, mut_ref as *mut _ as *mut _);
+            }
+            if self.api_version >= Version::V1_2
+                || self.supports_extension(vk::KhrSeparateDepthStencilLayoutsFn::name())
+            {
+                separate_depth_stencil_layouts_features =
+                    Some(vk::PhysicalDeviceSeparateDepthStencilLayoutsFeatures::builder().build());
+
+                let mut_ref = separate_depth_stencil_layouts_features.as_mut().unwrap();
+                mut_ref.p_next = mem::replace(&mut features2.p_next, mut_ref as *mut _ as *mut _);
+            }
+            if self.api_version >= Version::V1_1 {
+                protected_memory_features =
+                    Some(vk::PhysicalDeviceProtectedMemoryFeatures::builder().build());
+
+                let mut_ref = protected_memory_features.as_mut().unwrap();
+                mut_ref.p_next = mem::replace(&mut features2.p_next, mut_ref as *mut _ as *mut _);
+            }
+            if self.supports_extension(ext::image_view_min_lod::name()) {
+                image_view_min_lod_features =
+                    Some(ext::image_view_min_lod::PhysicalDeviceFeatures::new(false));
+
+                let mut_ref = image_view_min_lod_features.as_mut().unwrap();
+                mut_ref.p_next = mem::replace(&mut features2.p_next, mut_ref as *mut _ as *mut _);
+            }
+            if self.supports_extension(ext::pipeline_creation_cache_control::name()) {
+                pipeline_creation_cache_control_features = Some(
+                    ext::pipeline_creation_cache_control::PhysicalDeviceFeatures::new(false),
+                );
+
+                let mut_ref = pipeline_creation_cache_control_features.as_mut().unwrap();
+                mut_ref.p_next = mem::replace(&mut features2.p_next, mut_ref as *mut _ as *mut _);
+            }
+            if self.supports_extension(ext::host_image_copy::name())
+                && ext::host_image_copy::dependencies()
+                    .iter()
+                    .all(|&name| self.supports_extension(name))
+            {
+                host_image_copy_features =
+                    Some(ext::host_image_copy::PhysicalDeviceFeatures::new(false));
+
+                let mut_ref = host_image_copy_features.as_mut().unwrap();
+                mut_ref.p_next = mem::replace(&mut features2.p_next, mut_ref as *mut _ as *mut _);
+            }
+            if self.supports_extension(ext::cooperative_matrix::name()) {
+                cooperative_matrix_features =
+                    Some(ext::cooperative_matrix::PhysicalDeviceFeatures::new(false));
+
+                let mut_ref = cooperative_matrix_features.as_mut().unwrap();
+                mut_ref.p_next = mem::replace(&mut features2.p_next, mut_ref as *mut _ as *mut _);
+            }
+            if self.supports_extension(ext::swapchain_maintenance1::name()) {
+                swapchain_maintenance_features = Some(
+                    ext::swapchain_maintenance1::PhysicalDeviceFeatures::new(false),
+                );
+
+                let mut_ref = swapchain_maintenance_features.as_mut().unwrap();
+                mut_ref.p_next = mem::replace(&mut features2.p_next, mut_ref as *mut _ as *mut _);
+            }
+            if self.supports_extension(vk::ExtBlendOperationAdvancedFn::name()) {
+                blend_operation_advanced_features =
+                    Some(vk::PhysicalDeviceBlendOperationAdvancedFeaturesEXT::builder().build());
+
+                let mut_ref = blend_operation_advanced_features.as_mut().unwrap();
+                mut_ref.p_next = mem::replace(&mut features2.p_next, mut_ref as *mut _ as *mut _);
+            }
+            if self.supports_extension(vk::ExtLineRasterizationFn::name()) {
+                line_rasterization_features =
+                    Some(vk::PhysicalDeviceLineRasterizationFeaturesEXT::builder().build());
+
+                let mut_ref = line_rasterization_features.as_mut().unwrap();
+                mut_ref.p_next = mem::replace(&mut features2.p_next, mut_ref as *mut _ as *mut _);
+            }
+            if self.supports_extension(vk::ExtDepthClipEnableFn::name()) {
+                depth_clip_enable_features =
+                    Some(vk::PhysicalDeviceDepthClipEnableFeaturesEXT::builder().build());
+
+                let mut_ref = depth_clip_enable_features.as_mut().unwrap();
+                mut_ref.p_next = mem::replace(&mut features2.p_next, mut_ref as *mut _ as *mut _);
+            }
+            if self.supports_extension(vk::ExtFragmentShaderInterlockFn::name()) {
+                fragment_shader_interlock_features =
+                    Some(vk::PhysicalDeviceFragmentShaderInterlockFeaturesEXT::builder().build());
+
+                let mut_ref = fragment_shader_interlock_features.as_mut().unwrap();
+                mut_ref.p_next = mem::replace(&mut features2.p_next, mut_ref as *mut _ as *mut _);
+            }
+            if self.supports_extension(ext::robustness2::name()) {
+                robustness2_features = Some(ext::robustness2::PhysicalDeviceFeatures::new(false));
+
+                let mut_ref = robustness2_features.as_mut().unwrap();
+                mut_ref.p_next = mem::replace(&mut features2.p_next, mut_ref as *mut _ as *mut _);
+            }
+            if self.supports_extension(ext::fragment_shading_rate::name())
+                && ext::fragment_shading_rate::dependencies()
+                    .iter()
+                    .all(|&name| self.supports_extension(name))
+            {
+                fragment_shading_rate_features = Some(
+                    ext::fragment_shading_rate::PhysicalDeviceFeatures::new(false, false),
+                );
+
+                let mut_ref = fragment_shading_rate_features.as_mut().unwrap();
+                mut_ref.p_next = mem::replace(&mut features2.p_next, mut_ref as *mut _ as *mut _);
+            }
 
             unsafe {
                 get_device_properties
@@ -1131,7 +1942,8 @@ impl adapter::PhysicalDevice<Backend> for PhysicalDevice {
             | Features::SAMPLER_BORDER_COLOR
             | Features::MUTABLE_COMPARISON_SAMPLER
             | Features::MUTABLE_UNNORMALIZED_SAMPLER
-            | Features::TEXTURE_DESCRIPTOR_ARRAY;
+            | Features::TEXTURE_DESCRIPTOR_ARRAY
+            | Features::DYNAMIC_RENDERING;
 
         if self.supports_extension(vk::AmdNegativeViewportHeightFn::name())
             || self.supports_extension(vk::KhrMaintenance1Fn::name())
@@ -1144,6 +1956,33 @@ impl adapter::PhysicalDevice<Backend> for PhysicalDevice {
         if self.supports_extension(DrawIndirectCount::name()) {
             bits |= Features::DRAW_INDIRECT_COUNT
         }
+        // Both extensions depend on functionality that is core in Vulkan 1.1
+        if self.api_version >= Version::V1_1 {
+            if self.supports_extension(vk::ExtImageDrmFormatModifierFn::name()) {
+                bits |= Features::DRM_FORMAT_MODIFIER;
+            }
+            if self.supports_extension(vk::KhrExternalMemoryFdFn::name())
+                && self.supports_extension(vk::ExtExternalMemoryDmaBufFn::name())
+            {
+                bits |= Features::EXTERNAL_MEMORY_DMA_BUF;
+            }
+        }
+        // Exclusive full screen is only meaningful for Win32 surfaces
+        if cfg!(windows) && self.supports_extension(vk::ExtFullScreenExclusiveFn::name()) {
+            bits |= Features::FULL_SCREEN_EXCLUSIVE;
+        }
+        if self.supports_extension(vk::GoogleDisplayTimingFn::name()) {
+            bits |= Features::PRESENT_TIMING;
+        }
+        if self.supports_extension(vk::ExtHdrMetadataFn::name()) {
+            bits |= Features::HDR_METADATA;
+        }
+        if self.supports_extension(vk::ExtSampleLocationsFn::name()) {
+            bits |= Features::SAMPLE_LOCATIONS;
+        }
+        if self.supports_extension(vk::ExtDepthRangeUnrestrictedFn::name()) {
+            bits |= Features::DEPTH_RANGE_UNRESTRICTED;
+        }
         // This will only be some if the extension exists
         if let Some(ref desc_indexing) = descriptor_indexing_features {
             if desc_indexing.shader_sampled_image_array_non_uniform_indexing != 0 {
@@ -1156,6 +1995,137 @@ impl adapter::PhysicalDevice<Backend> for PhysicalDevice {
                 bits |= Features::UNSIZED_DESCRIPTOR_ARRAY;
             }
         }
+        if let Some(ref multiview) = multiview_features {
+            if multiview.multiview != 0 {
+                bits |= Features::MULTIVIEW;
+            }
+        }
+        if let Some(ref inline_uniform_block) = inline_uniform_block_features {
+            if inline_uniform_block.inline_uniform_block != 0 {
+                bits |= Features::INLINE_UNIFORM_BLOCK;
+            }
+        }
+        if let Some(ref sampler_ycbcr_conversion) = sampler_ycbcr_conversion_features {
+            if sampler_ycbcr_conversion.sampler_ycbcr_conversion != 0 {
+                bits |= Features::SAMPLER_YCBCR_CONVERSION;
+            }
+        }
+        if let Some(ref separate_layouts) = separate_depth_stencil_layouts_features {
+            if separate_layouts.separate_depth_stencil_layouts != 0 {
+                bits |= Features::SEPARATE_DEPTH_STENCIL_LAYOUTS;
+            }
+        }
+        if let Some(ref protected_memory) = protected_memory_features {
+            if protected_memory.protected_memory != 0 {
+                bits |= Features::PROTECTED_MEMORY;
+            }
+        }
+        if let Some(ref image_view_min_lod) = image_view_min_lod_features {
+            if image_view_min_lod.min_lod != 0 {
+                bits |= Features::IMAGE_VIEW_MIN_LOD;
+            }
+        }
+        if let Some(ref cache_control) = pipeline_creation_cache_control_features {
+            if cache_control.pipeline_creation_cache_control != 0 {
+                bits |= Features::PIPELINE_CREATION_CACHE_CONTROL;
+            }
+        }
+        if let Some(ref host_image_copy) = host_image_copy_features {
+            if host_image_copy.host_image_copy != 0 {
+                bits |= Features::HOST_IMAGE_COPY;
+            }
+        }
+        if let Some(ref cooperative_matrix) = cooperative_matrix_features {
+            if cooperative_matrix.cooperative_matrix != 0
+                && self.instance.get_cooperative_matrix_properties.is_some()
+            {
+                bits |= Features::COOPERATIVE_MATRIX;
+            }
+        }
+        if let Some(ref swapchain_maintenance) = swapchain_maintenance_features {
+            // The compatible present modes are queried with `VK_EXT_surface_maintenance1`
+            if swapchain_maintenance.swapchain_maintenance1 != 0
+                && self.instance.surface_maintenance.is_some()
+            {
+                bits |= Features::PRESENT_MODE_SWITCH;
+            }
+        }
+        if let Some(ref blend_operation_advanced) = blend_operation_advanced_features {
+            // Only the coherent blending is exposed, as there are no blend barriers
+            if blend_operation_advanced.advanced_blend_coherent_operations != 0 {
+                bits |= Features::BLEND_OPERATION_ADVANCED;
+            }
+        }
+        if let Some(ref line_rasterization) = line_rasterization_features {
+            let modes = [
+                (
+                    Features::LINE_RASTERIZATION_RECTANGULAR,
+                    line_rasterization.rectangular_lines,
+                    line_rasterization.stippled_rectangular_lines,
+                ),
+                (
+                    Features::LINE_RASTERIZATION_BRESENHAM,
+                    line_rasterization.bresenham_lines,
+                    line_rasterization.stippled_bresenham_lines,
+                ),
+                (
+                    Features::LINE_RASTERIZATION_SMOOTH,
+                    line_rasterization.smooth_lines,
+                    line_rasterization.stippled_smooth_lines,
+                ),
+            ];
+            let mut stipple = true;
+            for &(feature, supported, stippled) in modes.iter() {
+                if supported != 0 {
+                    bits |= feature;
+                    stipple &= stippled != 0;
+                }
+            }
+            // Stipple is only exposed if it works with every supported mode
+            if stipple && modes.iter().any(|&(_, supported, _)| supported != 0) {
+                bits |= Features::LINE_STIPPLE;
+            }
+        }
+        if let Some(ref depth_clip_enable) = depth_clip_enable_features {
+            if depth_clip_enable.depth_clip_enable != 0 {
+                bits |= Features::DEPTH_CLIP_ENABLE;
+            }
+        }
+        if let Some(ref fragment_shader_interlock) = fragment_shader_interlock_features {
+            if fragment_shader_interlock.fragment_shader_pixel_interlock != 0 {
+                bits |= Features::FRAGMENT_SHADER_INTERLOCK;
+            }
+        }
+        if let Some(ref robustness2) = robustness2_features {
+            if robustness2.null_descriptor != 0 {
+                bits |= Features::NULL_DESCRIPTOR;
+            }
+        }
+        if let Some(ref fragment_shading_rate) = fragment_shading_rate_features {
+            if fragment_shading_rate.pipeline_fragment_shading_rate != 0 {
+                bits |= Features::SHADING_RATE;
+            }
+            if fragment_shading_rate.attachment_fragment_shading_rate != 0 {
+                bits |= Features::SHADING_RATE_ATTACHMENT;
+            }
+        }
+        if self.supports_extension(vk::ExtConservativeRasterizationFn::name()) {
+            bits |= Features::CONSERVATIVE_RASTERIZATION;
+            // The underestimation support is only reported in the properties
+            if let Some(ref get_device_properties) = self.instance.get_physical_device_properties {
+                let mut conservative_rasterization_properties =
+                    vk::PhysicalDeviceConservativeRasterizationPropertiesEXT::builder().build();
+                let mut properties2 = vk::PhysicalDeviceProperties2KHR::builder()
+                    .push_next(&mut conservative_rasterization_properties);
+                unsafe {
+                    get_device_properties
+                        .get_physical_device_properties2_khr(self.handle, &mut *properties2);
+                }
+                if conservative_rasterization_properties.primitive_underestimation != 0 {
+                    bits |= Features::CONSERVATIVE_RASTERIZATION_UNDERESTIMATE;
+                }
+            }
+        }
 
         if features.robust_buffer_access != 0 {
             bits |= Features::ROBUST_BUFFER_ACCESS;
@@ -1342,6 +2312,48 @@ impl adapter::PhysicalDevice<Backend> for PhysicalDevice {
         let max_group_count = limits.max_compute_work_group_count;
         let max_group_size = limits.max_compute_work_group_size;
 
+        let mut multiview_properties = vk::PhysicalDeviceMultiviewProperties::builder().build();
+        let mut inline_uniform_block_properties =
+            vk::PhysicalDeviceInlineUniformBlockPropertiesEXT::builder().build();
+        let mut protected_memory_properties =
+            vk::PhysicalDeviceProtectedMemoryProperties::builder().build();
+        let mut sample_locations_properties =
+            vk::PhysicalDeviceSampleLocationsPropertiesEXT::builder().build();
+        let mut blend_operation_advanced_properties =
+            vk::PhysicalDeviceBlendOperationAdvancedPropertiesEXT::builder().build();
+        let mut conservative_rasterization_properties =
+            vk::PhysicalDeviceConservativeRasterizationPropertiesEXT::builder().build();
+        let mut fragment_shading_rate_properties =
+            ext::fragment_shading_rate::PhysicalDeviceProperties::default();
+        if let Some(ref get_device_properties) = self.instance.get_physical_device_properties {
+            let mut properties2 = vk::PhysicalDeviceProperties2KHR::builder();
+            if self.supports_multiview() {
+                properties2 = properties2.push_next(&mut multiview_properties);
+            }
+            if self.supports_extension(vk::ExtInlineUniformBlockFn::name()) {
+                properties2 = properties2.push_next(&mut inline_uniform_block_properties);
+            }
+            if self.api_version >= Version::V1_1 {
+                properties2 = properties2.push_next(&mut protected_memory_properties);
+            }
+            if self.supports_extension(vk::ExtSampleLocationsFn::name()) {
+                properties2 = properties2.push_next(&mut sample_locations_properties);
+            }
+            if self.supports_extension(vk::ExtBlendOperationAdvancedFn::name()) {
+                properties2 = properties2.push_next(&mut blend_operation_advanced_properties);
+            }
+            if self.supports_extension(vk::ExtConservativeRasterizationFn::name()) {
+                properties2 = properties2.push_next(&mut conservative_rasterization_properties);
+            }
+            if self.supports_extension(ext::fragment_shading_rate::name()) {
+                properties2 = properties2.push_next(&mut fragment_shading_rate_properties);
+            }
+            unsafe {
+                get_device_properties
+                    .get_physical_device_properties2_khr(self.handle, &mut *properties2);
+            }
+        }
+
         Limits {
             max_image_1d_size: limits.max_image_dimension1_d,
             max_image_2d_size: limits.max_image_dimension2_d,
@@ -1382,16 +2394,48 @@ impl adapter::PhysicalDevice<Backend> for PhysicalDevice {
             framebuffer_depth_sample_counts: limits.framebuffer_depth_sample_counts.as_raw() as _,
             framebuffer_stencil_sample_counts: limits.framebuffer_stencil_sample_counts.as_raw()
                 as _,
+            storage_image_sample_counts: limits.storage_image_sample_counts.as_raw() as _,
             timestamp_compute_and_graphics: limits.timestamp_compute_and_graphics != 0,
             max_color_attachments: limits.max_color_attachments as _,
+            max_advanced_blend_color_attachments: blend_operation_advanced_properties
+                .advanced_blend_max_color_attachments
+                as _,
+            max_multiview_view_count: multiview_properties.max_multiview_view_count,
+            max_multiview_instance_index: multiview_properties.max_multiview_instance_index,
+            shading_rate_attachment_texel_size: [
+                fragment_shading_rate_properties
+                    .min_fragment_shading_rate_attachment_texel_size
+                    .width,
+                fragment_shading_rate_properties
+                    .min_fragment_shading_rate_attachment_texel_size
+                    .height,
+            ],
+            max_shading_rate: ShadingRate {
+                width: fragment_shading_rate_properties
+                    .max_fragment_size
+                    .width
+                    .max(1) as u8,
+                height: fragment_shading_rate_properties
+                    .max_fragment_size
+                    .height
+                    .max(1) as u8,
+            },
             buffer_image_granularity: limits.buffer_image_granularity,
             non_coherent_atom_size: limits.non_coherent_atom_size as _,
+            device_local_host_visible_heap_size: auxil::device_local_host_visible_heap_size(
+                &self.memory_properties(),
+            ),
             max_sampler_anisotropy: limits.max_sampler_anisotropy,
             min_vertex_input_binding_stride_alignment: 1,
             max_bound_descriptor_sets: limits.max_bound_descriptor_sets as _,
             max_compute_shared_memory_size: limits.max_compute_shared_memory_size as _,
             max_compute_work_group_invocations: limits.max_compute_work_group_invocations as _,
             max_descriptor_set_input_attachments: limits.max_descriptor_set_input_attachments as _,
+            max_inline_uniform_block_size: inline_uniform_block_properties
+                .max_inline_uniform_block_size as _,
+            max_descriptor_set_inline_uniform_blocks: inline_uniform_block_properties
+                .max_descriptor_set_inline_uniform_blocks
+                as _,
             max_descriptor_set_sampled_images: limits.max_descriptor_set_sampled_images as _,
             max_descriptor_set_samplers: limits.max_descriptor_set_samplers as _,
             max_descriptor_set_storage_buffers: limits.max_descriptor_set_storage_buffers as _,
@@ -1432,13 +2476,25 @@ impl adapter::PhysicalDevice<Backend> for PhysicalDevice {
                 .max_per_stage_descriptor_uniform_buffers
                 as _,
             max_per_stage_resources: limits.max_per_stage_resources as _,
-            max_push_constants_size: limits.max_push_constants_size as _,
+            // Capped to the size of the other backends, the native push constants
+            // can't be spilled without rewriting the shaders.
+            max_push_constants_size: (limits.max_push_constants_size as usize).min(256),
             max_sampler_allocation_count: limits.max_sampler_allocation_count as _,
             max_sampler_lod_bias: limits.max_sampler_lod_bias as _,
             max_storage_buffer_range: limits.max_storage_buffer_range as _,
             max_uniform_buffer_range: limits.max_uniform_buffer_range as _,
             min_memory_map_alignment: limits.min_memory_map_alignment,
             standard_sample_locations: limits.standard_sample_locations == ash::vk::TRUE,
+            sample_location_sample_counts: sample_locations_properties
+                .sample_location_sample_counts
+                .as_raw() as _,
+            sample_location_sub_pixel_bits: sample_locations_properties
+                .sample_location_sub_pixel_bits,
+            primitive_overestimation_size: conservative_rasterization_properties
+                .primitive_overestimation_size,
+            max_extra_primitive_overestimation_size: conservative_rasterization_properties
+                .max_extra_primitive_overestimation_size,
+            protected_no_fault: protected_memory_properties.protected_no_fault == vk::TRUE,
 
             // TODO: Implement Limits for Mesh Shaders
             //       Depends on VkPhysicalDeviceMeshShaderPropertiesNV which depends on VkPhysicalProperties2
@@ -1516,6 +2572,18 @@ impl adapter::PhysicalDevice<Backend> for PhysicalDevice {
 struct DeviceExtensionFunctions {
     mesh_shaders: Option<MeshShader>,
     draw_indirect_count: Option<DrawIndirectCount>,
+    drm_format_modifier: Option<vk::ExtImageDrmFormatModifierFn>,
+    external_memory_fd: Option<vk::KhrExternalMemoryFdFn>,
+    full_screen_exclusive: Option<vk::ExtFullScreenExclusiveFn>,
+    display_timing: Option<vk::GoogleDisplayTimingFn>,
+    hdr_metadata: Option<vk::ExtHdrMetadataFn>,
+    sample_locations: Option<vk::ExtSampleLocationsFn>,
+    host_image_copy: Option<ext::host_image_copy::DeviceFn>,
+    pageable_device_local_memory: Option<ext::pageable_device_local_memory::DeviceFn>,
+    device_fault: Option<ext::device_fault::DeviceFn>,
+    checkpoints: Option<vk::NvDeviceDiagnosticCheckpointsFn>,
+    dynamic_rendering: Option<ext::dynamic_rendering::DeviceFn>,
+    fragment_shading_rate: Option<ext::fragment_shading_rate::DeviceFn>,
 }
 
 #[doc(hidden)]
@@ -1523,13 +2591,29 @@ pub struct RawDevice {
     raw: ash::Device,
     features: Features,
     instance: Arc<RawInstance>,
+    physical_device: vk::PhysicalDevice,
     extension_fns: DeviceExtensionFunctions,
     /// The `hal::Features::NDC_Y_UP` flag is implemented with either `VK_AMD_negative_viewport_height` or `VK_KHR_maintenance1`/1.1+. The AMD extension for negative viewport height does not require a Y shift.
     ///
     /// This flag is `true` if the device has `VK_KHR_maintenance1`/1.1+ and `false` otherwise (i.e. in the case of `VK_AMD_negative_viewport_height`).
     flip_y_requires_shift: bool,
     imageless_framebuffers: bool,
+    /// Allocations can be given a priority with `VK_EXT_memory_priority`.
+    memory_priority: bool,
+    /// Dedicated allocations are queried and made with the Vulkan 1.1 core.
+    dedicated_allocation: bool,
     timestamp_period: f32,
+    /// Framebuffer area covered by a texel of the shading rate attachments,
+    /// the same as `Limits::shading_rate_attachment_texel_size`.
+    shading_rate_texel_size: vk::Extent2D,
+    /// Render passes and framebuffers created on the fly, for `begin_rendering`
+    /// without `KHR_dynamic_rendering` and the framebuffers without `KHR_imageless_framebuffer`.
+    rendering_cache: Mutex<native::RenderingCache>,
+    /// All the queues of the device, for querying their checkpoints.
+    queues: Vec<vk::Queue>,
+    checkpoint_labels: Mutex<native::CheckpointLabels>,
+    #[cfg(debug_assertions)]
+    semaphore_tracker: track::SemaphoreTracker,
 }
 
 impl fmt::Debug for RawDevice {
@@ -1540,6 +2624,7 @@ impl fmt::Debug for RawDevice {
 impl Drop for RawDevice {
     fn drop(&mut self) {
         unsafe {
+            self.rendering_cache.get_mut().clear(&self.raw);
             self.raw.destroy_device(None);
         }
     }
@@ -1605,6 +2690,8 @@ pub struct Queue {
     raw: RawCommandQueue,
     device: Arc<RawDevice>,
     swapchain_fn: Swapchain,
+    /// Created with the protected flag, accepting both protected and unprotected submissions.
+    protected: bool,
 }
 
 impl fmt::Debug for Queue {
@@ -1613,14 +2700,24 @@ impl fmt::Debug for Queue {
     }
 }
 
-impl queue::Queue<Backend> for Queue {
-    unsafe fn submit<'a, Ic, Iw, Is>(
+impl Queue {
+    /// Returns the raw queue handle.
+    ///
+    /// Submissions made with it have to be externally synchronized with the ones
+    /// made through this queue.
+    pub fn raw_queue(&self) -> vk::Queue {
+        *self.raw
+    }
+
+    unsafe fn submit_impl<'a, Ic, Iw, Is>(
         &mut self,
         command_buffers: Ic,
         wait_semaphores: Iw,
         signal_semaphores: Is,
         fence: Option<&mut native::Fence>,
-    ) where
+        protected: bool,
+    ) -> Result<(), queue::SubmitError>
+    where
         Ic: Iterator<Item = &'a command::CommandBuffer>,
         Iw: Iterator<Item = (&'a native::Semaphore, PipelineStage)>,
         Is: Iterator<Item = &'a native::Semaphore>,
@@ -1638,6 +2735,13 @@ impl queue::Queue<Backend> for Queue {
             .map(|semaphore| semaphore.0)
             .collect::<Vec<_>>();
 
+        #[cfg(debug_assertions)]
+        {
+            let tracker = &self.device.semaphore_tracker;
+            tracker.submit(&tracker.submission_label(&buffers), &waits, &signals);
+        }
+
+        let mut protected_info;
         let mut info = vk::SubmitInfo::builder()
             .wait_semaphores(&waits)
             .command_buffers(&buffers)
@@ -1646,23 +2750,53 @@ impl queue::Queue<Backend> for Queue {
         if !stages.is_empty() {
             info = info.wait_dst_stage_mask(&stages);
         }
+        if protected {
+            if self.protected {
+                protected_info = vk::ProtectedSubmitInfo::builder().protected_submit(true);
+                info = info.push_next(&mut protected_info);
+            } else {
+                auxil::report!(
+                    Error,
+                    "gfx::vulkan::queue",
+                    "unprotected-submit",
+                    "Queue wasn't created protected, submitting as unprotected"
+                );
+            }
+        }
 
         let fence_raw = fence.map(|fence| fence.0).unwrap_or(vk::Fence::null());
 
-        let result = self.device.raw.queue_submit(*self.raw, &[*info], fence_raw);
-        if let Err(e) = result {
-            error!("Submit resulted in {:?}", e);
+        match self.device.raw.queue_submit(*self.raw, &[*info], fence_raw) {
+            Ok(()) => Ok(()),
+            Err(vk::Result::ERROR_OUT_OF_HOST_MEMORY) => Err(OutOfMemory::Host.into()),
+            Err(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY) => Err(OutOfMemory::Device.into()),
+            Err(vk::Result::ERROR_DEVICE_LOST) => Err(DeviceLost.into()),
+            Err(e) => {
+                auxil::report!(
+                    Error,
+                    "gfx::vulkan::queue",
+                    "submit-failed",
+                    "Submit resulted in {:?}",
+                    e
+                );
+                Ok(())
+            }
         }
     }
 
-    unsafe fn present(
+    unsafe fn present_impl(
         &mut self,
         surface: &mut window::Surface,
         image: window::SurfaceImage,
         wait_semaphore: Option<&mut native::Semaphore>,
+        time: Option<PresentTime>,
     ) -> Result<Option<Suboptimal>, PresentError> {
         let ssc = surface.swapchain.as_ref().unwrap();
         let wait_semaphore = if let Some(wait_semaphore) = wait_semaphore {
+            #[cfg(debug_assertions)]
+            self.device
+                .semaphore_tracker
+                .submit("present", &[wait_semaphore.0], &[]);
             wait_semaphore.0
         } else {
             let signals = &[ssc.semaphore.0];
@@ -1677,28 +2811,114 @@ impl queue::Queue<Backend> for Queue {
         let wait_semaphores = &[wait_semaphore];
         let swapchains = &[ssc.swapchain.raw];
         let image_indices = &[image.index];
-        let present_info = vk::PresentInfoKHR::builder()
+        let present_times;
+        let mut present_times_info;
+        let present_modes = [ssc.swapchain.present_mode];
+        let mut present_mode_info;
+        let mut present_info = vk::PresentInfoKHR::builder()
             .wait_semaphores(wait_semaphores)
             .swapchains(swapchains)
             .image_indices(image_indices);
 
+        if let Some(time) = time {
+            if self.device.extension_fns.display_timing.is_some() {
+                present_times = [vk::PresentTimeGOOGLE {
+                    present_id: time.present_id,
+                    desired_present_time: time.desired_present_time,
+                }];
+                present_times_info = vk::PresentTimesInfoGOOGLE::builder().times(&present_times);
+                present_info = present_info.push_next(&mut present_times_info);
+            }
+        }
+
+        if !ssc.swapchain.present_modes.is_empty() {
+            present_mode_info =
+                ext::swapchain_maintenance1::SwapchainPresentModeInfo::new(&present_modes);
+            present_info = present_info.push_next(&mut present_mode_info);
+        }
+
         match self.swapchain_fn.queue_present(*self.raw, &present_info) {
             Ok(true) => Ok(None),
             Ok(false) => Ok(Some(Suboptimal)),
             Err(vk::Result::ERROR_OUT_OF_HOST_MEMORY) => Err(OutOfMemory::Host.into()),
             Err(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY) => Err(OutOfMemory::Device.into()),
             Err(vk::Result::ERROR_DEVICE_LOST) => Err(DeviceLost.into()),
-            Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => Err(OutOfDate.into()),
+            Err(vk::Result::ERROR_OUT_OF_DATE_KHR)
+            | Err(vk::Result::ERROR_FULL_SCREEN_EXCLUSIVE_MODE_LOST_EXT) => Err(OutOfDate.into()),
             Err(vk::Result::ERROR_SURFACE_LOST_KHR) => Err(SurfaceLost.into()),
             _ => panic!("Failed to present frame"),
         }
     }
+}
 
-    fn wait_idle(&mut self) -> Result<(), OutOfMemory> {
+impl queue::Queue<Backend> for Queue {
+    unsafe fn submit<'a, Ic, Iw, Is>(
+        &mut self,
+        command_buffers: Ic,
+        wait_semaphores: Iw,
+        signal_semaphores: Is,
+        fence: Option<&mut native::Fence>,
+    ) -> Result<(), queue::SubmitError>
+    where
+        Ic: Iterator<Item = &'a command::CommandBuffer>,
+        Iw: Iterator<Item = (&'a native::Semaphore, PipelineStage)>,
+        Is: Iterator<Item = &'a native::Semaphore>,
+    {
+        self.submit_impl(
+            command_buffers,
+            wait_semaphores,
+            signal_semaphores,
+            fence,
+            false,
+        )
+    }
+
+    unsafe fn submit_protected<'a, Ic, Iw, Is>(
+        &mut self,
+        command_buffers: Ic,
+        wait_semaphores: Iw,
+        signal_semaphores: Is,
+        fence: Option<&mut native::Fence>,
+    ) -> Result<(), queue::SubmitError>
+    where
+        Ic: Iterator<Item = &'a command::CommandBuffer>,
+        Iw: Iterator<Item = (&'a native::Semaphore, PipelineStage)>,
+        Is: Iterator<Item = &'a native::Semaphore>,
+    {
+        self.submit_impl(
+            command_buffers,
+            wait_semaphores,
+            signal_semaphores,
+            fence,
+            true,
+        )
+    }
+
+    unsafe fn present(
+        &mut self,
+        surface: &mut window::Surface,
+        image: window::SurfaceImage,
+        wait_semaphore: Option<&mut native::Semaphore>,
+    ) -> Result<Option<Suboptimal>, PresentError> {
+        self.present_impl(surface, image, wait_semaphore, None)
+    }
+
+    unsafe fn present_with_time(
+        &mut self,
+        surface: &mut window::Surface,
+        image: window::SurfaceImage,
+        wait_semaphore: Option<&mut native::Semaphore>,
+        time: PresentTime,
+    ) -> Result<Option<Suboptimal>, PresentError> {
+        self.present_impl(surface, image, wait_semaphore, Some(time))
+    }
+
+    fn wait_idle(&mut self) -> Result<(), WaitError> {
         match unsafe { self.device.raw.queue_wait_idle(*self.raw) } {
             Ok(()) => Ok(()),
-            Err(vk::Result::ERROR_OUT_OF_HOST_MEMORY) => Err(OutOfMemory::Host),
-            Err(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY) => Err(OutOfMemory::Device),
+            Err(vk::Result::ERROR_OUT_OF_HOST_MEMORY) => Err(OutOfMemory::Host.into()),
+            Err(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY) => Err(OutOfMemory::Device.into()),
+            Err(vk::Result::ERROR_DEVICE_LOST) => Err(DeviceLost.into()),
             Err(_) => unreachable!(),
         }
     }
@@ -1715,6 +2935,7 @@ pub struct Device {
     valid_ash_memory_types: u32,
     #[cfg(feature = "naga")]
     naga_options: naga::back::spv::Options,
+    pipeline_workers: auxil::workers::Workers,
 }
 
 #[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
@@ -1741,6 +2962,7 @@ impl hal::Backend for Backend {
     type Image = native::Image;
     type ImageView = native::ImageView;
     type Sampler = native::Sampler;
+    type SamplerYcbcrConversion = native::SamplerYcbcrConversion;
 
     type ComputePipeline = native::ComputePipeline;
     type GraphicsPipeline = native::GraphicsPipeline;
@@ -1,14 +1,14 @@
-use crate::{Backend, RawDevice, ROUGH_MAX_ATTACHMENT_COUNT};
+use crate::{conv, Backend, RawDevice, ROUGH_MAX_ATTACHMENT_COUNT};
 use ash::{version::DeviceV1_0, vk};
 use hal::{
     device::OutOfMemory,
-    image::{Extent, SubresourceRange},
-    pso,
+    format::Aspects,
+    image::{Extent, Layout, SubresourceRange},
+    pass, pso,
 };
 use inplace_it::inplace_or_alloc_from_iter;
-use parking_lot::Mutex;
 use smallvec::SmallVec;
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, ffi::c_void, sync::Arc};
 
 #[derive(Debug, Hash)]
 pub struct Semaphore(pub vk::Semaphore);
@@ -30,6 +30,12 @@ pub struct Memory {
     pub(crate) raw: vk::DeviceMemory,
 }
 
+impl Memory {
+    pub fn raw(&self) -> vk::DeviceMemory {
+        self.raw
+    }
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Buffer {
     pub(crate) raw: vk::Buffer,
@@ -38,6 +44,12 @@ pub struct Buffer {
 unsafe impl Sync for Buffer {}
 unsafe impl Send for Buffer {}
 
+impl Buffer {
+    pub fn raw(&self) -> vk::Buffer {
+        self.raw
+    }
+}
+
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub struct BufferView {
     pub(crate) raw: vk::BufferView,
@@ -49,6 +61,13 @@ pub struct Image {
     pub(crate) ty: vk::ImageType,
     pub(crate) flags: vk::ImageCreateFlags,
     pub(crate) extent: vk::Extent3D,
+    pub(crate) tiling: vk::ImageTiling,
+}
+
+impl Image {
+    pub fn raw(&self) -> vk::Image {
+        self.raw
+    }
 }
 
 #[derive(Debug, Hash, PartialEq, Eq)]
@@ -58,27 +77,254 @@ pub struct ImageView {
     pub(crate) range: SubresourceRange,
 }
 
+impl ImageView {
+    pub fn raw(&self) -> vk::ImageView {
+        self.raw
+    }
+}
+
 #[derive(Debug, Hash)]
 pub struct Sampler(pub vk::Sampler);
 
+#[derive(Debug, Hash)]
+pub struct SamplerYcbcrConversion {
+    pub(crate) raw: vk::SamplerYcbcrConversion,
+    pub(crate) format: vk::Format,
+}
+
 #[derive(Debug, Hash)]
 pub struct RenderPass {
     pub raw: vk::RenderPass,
     pub attachment_count: usize,
+    /// Attachment formats of a null render pass from `create_rendering_pass`,
+    /// for the pipelines used within `VK_KHR_dynamic_rendering`.
+    pub rendering: Option<RenderingFormats>,
+}
+
+#[derive(Debug, Hash)]
+pub struct RenderingFormats {
+    pub colors: SmallVec<[vk::Format; ROUGH_MAX_ATTACHMENT_COUNT]>,
+    pub depth: vk::Format,
+    pub stencil: vk::Format,
 }
 
 pub type FramebufferKey = SmallVec<[vk::ImageView; ROUGH_MAX_ATTACHMENT_COUNT]>;
 
+/// Owner of the framebuffers in the `RenderingCache`: either `begin_rendering`,
+/// or a `Framebuffer::Legacy`.
+pub type FramebufferOwner = u64;
+
 #[derive(Debug)]
 pub enum Framebuffer {
     ImageLess(vk::Framebuffer),
+    /// Framebuffer without `KHR_imageless_framebuffer`, the actual framebuffers
+    /// are created per set of image views in the `RenderingCache`.
     Legacy {
+        owner: FramebufferOwner,
         name: String,
-        map: Mutex<HashMap<FramebufferKey, vk::Framebuffer>>,
         extent: Extent,
     },
 }
 
+/// Render passes and framebuffers created on the fly, for `begin_rendering`
+/// and the legacy framebuffers.
+///
+/// The framebuffers are destroyed together with any of their image views,
+/// so the cache doesn't grow with views of the recreated swapchains,
+/// and a new view can't get a stale framebuffer by reusing the handle.
+#[derive(Debug, Default)]
+pub struct RenderingCache {
+    render_passes: HashMap<Vec<pass::Attachment>, vk::RenderPass>,
+    framebuffers:
+        HashMap<(FramebufferOwner, vk::RenderPass, FramebufferKey, Extent), vk::Framebuffer>,
+    next_owner: FramebufferOwner,
+}
+
+impl RenderingCache {
+    pub(crate) unsafe fn render_pass(
+        &mut self,
+        device: &ash::Device,
+        attachments: &[pass::Attachment],
+    ) -> vk::RenderPass {
+        if let Some(&raw) = self.render_passes.get(attachments) {
+            return raw;
+        }
+
+        let raw_attachments = attachments
+            .iter()
+            .map(|at| vk::AttachmentDescription {
+                flags: vk::AttachmentDescriptionFlags::empty(),
+                format: at.format.map_or(vk::Format::UNDEFINED, conv::map_format),
+                samples: conv::map_sample_count_flags(at.samples),
+                load_op: conv::map_attachment_load_op(at.ops.load),
+                store_op: conv::map_attachment_store_op(at.ops.store),
+                stencil_load_op: conv::map_attachment_load_op(at.stencil_ops.load),
+                stencil_store_op: conv::map_attachment_store_op(at.stencil_ops.store),
+                initial_layout: conv::map_image_layout(at.layouts.start),
+                final_layout: conv::map_image_layout(at.layouts.end),
+            })
+            .collect::<SmallVec<[_; ROUGH_MAX_ATTACHMENT_COUNT]>>();
+        // the depth/stencil attachment, if any, goes last
+        let has_depth_stencil = attachments.last().map_or(false, |at| {
+            at.format.map_or(false, |format| {
+                format
+                    .surface_desc()
+                    .aspects
+                    .intersects(Aspects::DEPTH | Aspects::STENCIL)
+            })
+        });
+        let color_count = attachments.len() - has_depth_stencil as usize;
+        let colors = (0..color_count)
+            .map(|id| vk::AttachmentReference {
+                attachment: id as u32,
+                layout: conv::map_image_layout(Layout::ColorAttachmentOptimal),
+            })
+            .collect::<SmallVec<[_; ROUGH_MAX_ATTACHMENT_COUNT]>>();
+        let depth_stencil = vk::AttachmentReference {
+            attachment: color_count as u32,
+            layout: conv::map_image_layout(Layout::DepthStencilAttachmentOptimal),
+        };
+
+        let mut subpass = vk::SubpassDescription::builder()
+            .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
+            .color_attachments(&colors);
+        if has_depth_stencil {
+            subpass = subpass.depth_stencil_attachment(&depth_stencil);
+        }
+        let subpasses = [subpass.build()];
+        let info = vk::RenderPassCreateInfo::builder()
+            .attachments(&raw_attachments)
+            .subpasses(&subpasses);
+
+        let raw = device
+            .create_render_pass(&info, None)
+            .expect("Unable to create a render pass for dynamic rendering");
+        self.render_passes.insert(attachments.to_vec(), raw);
+        raw
+    }
+
+    /// Owner of the framebuffers created for `begin_rendering`.
+    pub(crate) const DYNAMIC_RENDERING: FramebufferOwner = 0;
+
+    /// Allocates an owner for a new legacy framebuffer.
+    pub(crate) fn add_owner(&mut self) -> FramebufferOwner {
+        self.next_owner += 1;
+        self.next_owner
+    }
+
+    /// Returns the framebuffer, and whether it has just been created.
+    pub(crate) unsafe fn framebuffer(
+        &mut self,
+        device: &ash::Device,
+        owner: FramebufferOwner,
+        render_pass: vk::RenderPass,
+        views: FramebufferKey,
+        extent: Extent,
+    ) -> (vk::Framebuffer, bool) {
+        let key = (owner, render_pass, views, extent);
+        if let Some(&raw) = self.framebuffers.get(&key) {
+            return (raw, false);
+        }
+
+        let info = vk::FramebufferCreateInfo::builder()
+            .render_pass(render_pass)
+            .attachments(&key.2)
+            .width(extent.width)
+            .height(extent.height)
+            .layers(extent.depth);
+        let raw = device
+            .create_framebuffer(&info, None)
+            .expect("Unable to create a framebuffer");
+        self.framebuffers.insert(key, raw);
+        (raw, true)
+    }
+
+    /// Lists the framebuffers created for the owner.
+    pub(crate) fn owned_framebuffers(
+        &self,
+        owner: FramebufferOwner,
+    ) -> impl Iterator<Item = vk::Framebuffer> + '_ {
+        self.framebuffers
+            .iter()
+            .filter(move |&(key, _)| key.0 == owner)
+            .map(|(_, &raw)| raw)
+    }
+
+    unsafe fn remove_framebuffers(
+        &mut self,
+        device: &ash::Device,
+        fun: impl Fn(&(FramebufferOwner, vk::RenderPass, FramebufferKey, Extent)) -> bool,
+    ) {
+        self.framebuffers.retain(|key, &mut raw| {
+            if fun(key) {
+                device.destroy_framebuffer(raw, None);
+                false
+            } else {
+                true
+            }
+        });
+    }
+
+    /// Destroys the framebuffers of a legacy framebuffer that is being destroyed.
+    pub(crate) unsafe fn remove_owner(&mut self, device: &ash::Device, owner: FramebufferOwner) {
+        self.remove_framebuffers(device, |key| key.0 == owner);
+    }
+
+    /// Destroys the framebuffers referencing an image view that is about to be destroyed.
+    pub(crate) unsafe fn remove_view(&mut self, device: &ash::Device, view: vk::ImageView) {
+        self.remove_framebuffers(device, |key| key.2.contains(&view));
+    }
+
+    /// Destroys the framebuffers created for a render pass that is about to be destroyed.
+    pub(crate) unsafe fn remove_render_pass(
+        &mut self,
+        device: &ash::Device,
+        render_pass: vk::RenderPass,
+    ) {
+        self.remove_framebuffers(device, |key| key.1 == render_pass);
+    }
+
+    pub(crate) unsafe fn clear(&mut self, device: &ash::Device) {
+        for (_, raw) in self.framebuffers.drain() {
+            device.destroy_framebuffer(raw, None);
+        }
+        for (_, raw) in self.render_passes.drain() {
+            device.destroy_render_pass(raw, None);
+        }
+    }
+}
+
+/// Labels of the checkpoints, for `VK_NV_device_diagnostic_checkpoints`.
+///
+/// The markers given to the driver are the label indices, offset by one
+/// to never be null. Labels are deduplicated, so the table doesn't grow
+/// with every frame.
+#[derive(Debug, Default)]
+pub struct CheckpointLabels {
+    indices: HashMap<String, usize>,
+    labels: Vec<String>,
+}
+
+impl CheckpointLabels {
+    pub(crate) fn marker(&mut self, label: &str) -> *const c_void {
+        let index = match self.indices.get(label) {
+            Some(&index) => index,
+            None => {
+                let index = self.labels.len();
+                self.labels.push(label.to_string());
+                self.indices.insert(label.to_string(), index);
+                index
+            }
+        };
+        (index + 1) as *const _
+    }
+
+    pub(crate) fn label(&self, marker: *const c_void) -> Option<&str> {
+        let index = (marker as usize).checked_sub(1)?;
+        self.labels.get(index).map(String::as_str)
+    }
+}
+
 pub(crate) type SortedBindings = Arc<Vec<pso::DescriptorSetLayoutBinding>>;
 
 #[derive(Debug)]
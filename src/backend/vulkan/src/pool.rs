@@ -42,6 +42,7 @@ impl pool::CommandPool<Backend> for RawCommandPool {
                 .map(|buffer| CommandBuffer {
                     raw: buffer,
                     device: Arc::clone(device),
+                    rendering_barriers: Vec::new(),
                 }),
         );
     }
@@ -51,6 +52,8 @@ impl pool::CommandPool<Backend> for RawCommandPool {
         I: Iterator<Item = CommandBuffer>,
     {
         let buffers: SmallVec<[vk::CommandBuffer; 16]> = cbufs.map(|buffer| buffer.raw).collect();
+        #[cfg(debug_assertions)]
+        self.device.semaphore_tracker.free_command_buffers(&buffers);
         self.device.raw.free_command_buffers(self.raw, &buffers);
     }
 }
@@ -0,0 +1,102 @@
+//! Debug tracking of the binary semaphore state across submissions and presents.
+//!
+//! Waiting on a semaphore that has no pending signal deadlocks the queue without
+//! any error from the driver, so these mistakes are detected and logged here.
+
+use ash::vk::{self, Handle as _};
+use parking_lot::Mutex;
+
+use std::collections::HashMap;
+
+#[derive(Debug, Default)]
+struct State {
+    /// Label of the operation that signaled each semaphore, while the signal is pending.
+    pending: HashMap<u64, String>,
+    semaphore_names: HashMap<u64, String>,
+    command_buffer_names: HashMap<u64, String>,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct SemaphoreTracker {
+    state: Mutex<State>,
+}
+
+impl SemaphoreTracker {
+    pub fn set_semaphore_name(&self, semaphore: vk::Semaphore, name: &str) {
+        self.state
+            .lock()
+            .semaphore_names
+            .insert(semaphore.as_raw(), name.to_string());
+    }
+
+    pub fn set_command_buffer_name(&self, command_buffer: vk::CommandBuffer, name: &str) {
+        self.state
+            .lock()
+            .command_buffer_names
+            .insert(command_buffer.as_raw(), name.to_string());
+    }
+
+    pub fn destroy_semaphore(&self, semaphore: vk::Semaphore) {
+        let mut state = self.state.lock();
+        state.pending.remove(&semaphore.as_raw());
+        state.semaphore_names.remove(&semaphore.as_raw());
+    }
+
+    pub fn free_command_buffers(&self, command_buffers: &[vk::CommandBuffer]) {
+        let mut state = self.state.lock();
+        for command_buffer in command_buffers {
+            state.command_buffer_names.remove(&command_buffer.as_raw());
+        }
+    }
+
+    /// Build a label for a submission of the given command buffers.
+    pub fn submission_label(&self, command_buffers: &[vk::CommandBuffer]) -> String {
+        let state = self.state.lock();
+        let names = command_buffers
+            .iter()
+            .map(|cb| match state.command_buffer_names.get(&cb.as_raw()) {
+                Some(name) => format!("'{}'", name),
+                None => format!("{:?}", cb),
+            })
+            .collect::<Vec<_>>();
+        format!("submission [{}]", names.join(", "))
+    }
+
+    /// Record a queue operation, checking the waits before applying the signals.
+    pub fn submit(&self, label: &str, waits: &[vk::Semaphore], signals: &[vk::Semaphore]) {
+        let mut state = self.state.lock();
+        for &semaphore in waits {
+            if state.pending.remove(&semaphore.as_raw()).is_none() {
+                auxil::report!(
+                    Error,
+                    "gfx::vulkan::queue",
+                    "semaphore-wait-deadlock",
+                    "{} waits on semaphore {} that has no pending signal, this will deadlock",
+                    label,
+                    Self::semaphore_label(&state, semaphore),
+                );
+            }
+        }
+        for &semaphore in signals {
+            if let Some(previous) = state.pending.get(&semaphore.as_raw()) {
+                auxil::report!(
+                    Error,
+                    "gfx::vulkan::queue",
+                    "semaphore-double-signal",
+                    "{} signals semaphore {} that is already signaled by {}",
+                    label,
+                    Self::semaphore_label(&state, semaphore),
+                    previous,
+                );
+            }
+            state.pending.insert(semaphore.as_raw(), label.to_string());
+        }
+    }
+
+    fn semaphore_label(state: &State, semaphore: vk::Semaphore) -> String {
+        match state.semaphore_names.get(&semaphore.as_raw()) {
+            Some(name) => format!("'{}'", name),
+            None => format!("{:?}", semaphore),
+        }
+    }
+}
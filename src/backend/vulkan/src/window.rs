@@ -1,10 +1,10 @@
-use std::{borrow::Borrow, fmt, os::raw::c_void, sync::Arc, time::Instant};
+use std::{borrow::Borrow, ffi::CStr, fmt, os::raw::c_void, ptr, sync::Arc, time::Instant};
 
 use ash::{extensions::khr, version::DeviceV1_0 as _, vk};
 use hal::{format::Format, window as w};
 
 use crate::{
-    conv, info, native, Backend, Device, Instance, PhysicalDevice, QueueFamily, RawDevice,
+    conv, ext, info, native, Backend, Device, Instance, PhysicalDevice, QueueFamily, RawDevice,
     RawInstance,
 };
 
@@ -28,9 +28,12 @@ impl SurfaceSwapchain {
         let _ = device.device_wait_idle();
         device.destroy_fence(self.fence.0, None);
         device.destroy_semaphore(self.semaphore.0, None);
+        let mut rendering_cache = self.device.rendering_cache.lock();
         for frame in self.frames {
+            rendering_cache.remove_view(device, frame.view);
             device.destroy_image_view(frame.view, None);
         }
+        drop(rendering_cache);
         self.swapchain
     }
 }
@@ -40,14 +43,32 @@ pub struct Surface {
     // For vkDestroySurfaceKHR: Host access to surface must be externally synchronized
     pub(crate) raw: Arc<RawSurface>,
     pub(crate) swapchain: Option<SurfaceSwapchain>,
+    /// Window the surface was created from, used to pick the monitor for exclusive full screen.
+    #[cfg(windows)]
+    pub(crate) hwnd: Option<vk::HWND>,
 }
 
+// The window handle is only passed back to the driver and never dereferenced.
+#[cfg(windows)]
+unsafe impl Send for Surface {}
+#[cfg(windows)]
+unsafe impl Sync for Surface {}
+
 impl fmt::Debug for Surface {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         fmt.write_str("Surface")
     }
 }
 
+/// Display connected to a physical device, see `Instance::enumerate_displays`.
+#[derive(Clone, Debug)]
+pub struct DisplayInfo {
+    /// Name of the display, usually derived from its EDID.
+    pub name: String,
+    /// Native resolution of the display.
+    pub resolution: w::Extent2D,
+}
+
 pub struct RawSurface {
     pub(crate) handle: vk::SurfaceKHR,
     pub(crate) functor: khr::Surface,
@@ -155,7 +176,10 @@ impl Instance {
             }
         };
 
-        self.create_surface_from_vk_surface_khr(surface)
+        Surface {
+            hwnd: Some(hwnd),
+            ..self.create_surface_from_vk_surface_khr(surface)
+        }
     }
 
     #[cfg(target_os = "macos")]
@@ -213,6 +237,119 @@ impl Instance {
         self.create_surface_from_vk_surface_khr(surface)
     }
 
+    /// Lists the displays connected to the physical device, which can be presented
+    /// to directly with `create_surface_from_display`, without a display server.
+    #[cfg(all(unix, not(target_os = "android"), not(target_os = "macos")))]
+    pub fn enumerate_displays(&self, physical_device: &PhysicalDevice) -> Vec<DisplayInfo> {
+        if !self.extensions.contains(&khr::Display::name()) {
+            return Vec::new();
+        }
+        let functor = khr::Display::new(&self.entry, &self.raw.inner);
+        match unsafe { functor.get_physical_device_display_properties(physical_device.handle) } {
+            Ok(displays) => displays
+                .iter()
+                .map(|props| DisplayInfo {
+                    name: if props.display_name.is_null() {
+                        String::new()
+                    } else {
+                        unsafe { CStr::from_ptr(props.display_name) }
+                            .to_string_lossy()
+                            .into_owned()
+                    },
+                    resolution: w::Extent2D {
+                        width: props.physical_resolution.width,
+                        height: props.physical_resolution.height,
+                    },
+                })
+                .collect(),
+            Err(e) => {
+                error!("get_physical_device_display_properties error {:?}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Creates a surface presenting directly to a display, as listed by `enumerate_displays`.
+    ///
+    /// The display is driven in its preferred mode, through the first plane that can show it.
+    /// This requires the display not to be owned by a display server, which is the case
+    /// on the embedded systems running without X11 or Wayland.
+    #[cfg(all(unix, not(target_os = "android"), not(target_os = "macos")))]
+    pub fn create_surface_from_display(
+        &self,
+        physical_device: &PhysicalDevice,
+        display_index: usize,
+    ) -> Result<Surface, w::InitError> {
+        if !self.extensions.contains(&khr::Display::name()) {
+            warn!("Vulkan driver does not support VK_KHR_DISPLAY");
+            return Err(w::InitError::UnsupportedWindowHandle);
+        }
+        let functor = khr::Display::new(&self.entry, &self.raw.inner);
+        let pd = physical_device.handle;
+        let vk_error = |e: vk::Result| {
+            error!("Unable to create a display surface: {:?}", e);
+            w::InitError::UnsupportedWindowHandle
+        };
+
+        let surface = unsafe {
+            let displays = functor
+                .get_physical_device_display_properties(pd)
+                .map_err(vk_error)?;
+            let display = displays
+                .get(display_index)
+                .ok_or(w::InitError::UnsupportedWindowHandle)?
+                .display;
+
+            // Pick the largest mode, with the highest refresh rate.
+            let mode = functor
+                .get_display_mode_properties(pd, display)
+                .map_err(vk_error)?
+                .into_iter()
+                .max_by_key(|mode| {
+                    let region = mode.parameters.visible_region;
+                    (region.width * region.height, mode.parameters.refresh_rate)
+                })
+                .ok_or(w::InitError::UnsupportedWindowHandle)?;
+
+            let planes = functor
+                .get_physical_device_display_plane_properties(pd)
+                .map_err(vk_error)?;
+            let mut plane_index = None;
+            for (index, plane) in planes.iter().enumerate() {
+                if plane.current_display != vk::DisplayKHR::null()
+                    && plane.current_display != display
+                {
+                    continue;
+                }
+                let supported = functor
+                    .get_display_plane_supported_displays(pd, index as u32)
+                    .map_err(vk_error)?;
+                if supported.contains(&display) {
+                    plane_index = Some(index);
+                    break;
+                }
+            }
+            let plane_index = plane_index.ok_or_else(|| {
+                warn!("No display plane can show display {}", display_index);
+                w::InitError::UnsupportedWindowHandle
+            })?;
+
+            let info = vk::DisplaySurfaceCreateInfoKHR::builder()
+                .display_mode(mode.display_mode)
+                .plane_index(plane_index as u32)
+                .plane_stack_index(planes[plane_index].current_stack_index)
+                .transform(vk::SurfaceTransformFlagsKHR::IDENTITY)
+                .global_alpha(1.0)
+                .alpha_mode(vk::DisplayPlaneAlphaFlagsKHR::OPAQUE)
+                .image_extent(mode.parameters.visible_region);
+            functor
+                .create_display_plane_surface(&info, None)
+                .map_err(vk_error)?
+        };
+
+        Ok(self.create_surface_from_vk_surface_khr(surface))
+    }
+
     pub fn create_surface_from_vk_surface_khr(&self, surface: vk::SurfaceKHR) -> Surface {
         let functor = khr::Surface::new(&self.entry, &self.raw.inner);
 
@@ -225,6 +362,8 @@ impl Instance {
         Surface {
             raw,
             swapchain: None,
+            #[cfg(windows)]
+            hwnd: None,
         }
     }
 }
@@ -290,6 +429,14 @@ impl w::Surface<Backend> for Surface {
         }
         .expect("Unable to query present modes");
 
+        // Color spaces other than sRGB are only listed with `VK_EXT_swapchain_colorspace`.
+        let raw_formats = unsafe {
+            self.raw
+                .functor
+                .get_physical_device_surface_formats(physical_device.handle, self.raw.handle)
+        }
+        .expect("Unable to query surface formats");
+
         w::SurfaceCapabilities {
             present_modes: raw_present_modes
                 .into_iter()
@@ -302,6 +449,14 @@ impl w::Surface<Backend> for Surface {
             extents: min_extent..=max_extent,
             max_image_layers: caps.max_image_array_layers as _,
             usage: conv::map_vk_image_usage(caps.supported_usage_flags),
+            current_transform: conv::map_vk_surface_transform(caps.current_transform),
+            transforms: conv::map_vk_surface_transform(caps.supported_transforms),
+            color_spaces: raw_formats
+                .into_iter()
+                .fold(w::ColorSpace::SRGB_NONLINEAR, |u, sf| {
+                    u | conv::map_vk_color_space(sf.color_space)
+                }),
+            extended_dynamic_range: None,
         }
     }
 
@@ -319,12 +474,19 @@ impl w::Surface<Backend> for Surface {
             // VK_FORMAT_UNDEFINED, surface has no preferred format. In this case, the application
             // can use any valid VkFormat value.
             vk::Format::UNDEFINED => None,
-            _ => Some(
-                raw_formats
+            _ => {
+                // Formats are listed once per supported color space.
+                let mut formats = Vec::with_capacity(raw_formats.len());
+                for format in raw_formats
                     .into_iter()
                     .filter_map(|sf| conv::map_vk_format(sf.format))
-                    .collect(),
-            ),
+                {
+                    if !formats.contains(&format) {
+                        formats.push(format);
+                    }
+                }
+                Some(formats)
+            }
         }
     }
 }
@@ -348,6 +510,67 @@ impl Borrow<native::ImageView> for SurfaceImage {
     }
 }
 
+impl Surface {
+    /// Returns the present modes a swapchain presenting with `present_mode` can switch to,
+    /// including `present_mode` itself.
+    pub(crate) unsafe fn compatible_present_modes(
+        &self,
+        device: &RawDevice,
+        present_mode: vk::PresentModeKHR,
+    ) -> Vec<vk::PresentModeKHR> {
+        let get_capabilities = match device.instance.surface_maintenance {
+            Some(ref get_capabilities) => get_capabilities,
+            None => return vec![present_mode],
+        };
+
+        let mut mode_info = ext::surface_maintenance1::SurfacePresentMode::new(present_mode);
+        let mut surface_info = vk::PhysicalDeviceSurfaceInfo2KHR::builder()
+            .surface(self.raw.handle)
+            .build();
+        surface_info.p_next = &mut mode_info as *mut _ as *mut _;
+
+        // First query the number of compatible modes, then fill them in
+        let mut compatibility =
+            ext::surface_maintenance1::SurfacePresentModeCompatibility::new(&mut []);
+        let mut capabilities = vk::SurfaceCapabilities2KHR::builder().build();
+        capabilities.p_next = &mut compatibility as *mut _ as *mut _;
+        let result = get_capabilities.get_physical_device_surface_capabilities2_khr(
+            device.physical_device,
+            &surface_info,
+            &mut capabilities,
+        );
+        if result != vk::Result::SUCCESS {
+            error!(
+                "Unexpected error in `compatible_present_modes`: {:?}",
+                result
+            );
+            return vec![present_mode];
+        }
+
+        let mut modes =
+            vec![vk::PresentModeKHR::default(); compatibility.present_mode_count as usize];
+        compatibility = ext::surface_maintenance1::SurfacePresentModeCompatibility::new(&mut modes);
+        let result = get_capabilities.get_physical_device_surface_capabilities2_khr(
+            device.physical_device,
+            &surface_info,
+            &mut capabilities,
+        );
+        if result != vk::Result::SUCCESS {
+            error!(
+                "Unexpected error in `compatible_present_modes`: {:?}",
+                result
+            );
+            return vec![present_mode];
+        }
+        modes.truncate(compatibility.present_mode_count as usize);
+
+        if !modes.contains(&present_mode) {
+            modes.push(present_mode);
+        }
+        modes
+    }
+}
+
 impl w::PresentationSurface<Backend> for Surface {
     type SwapchainImage = SurfaceImage;
 
@@ -397,6 +620,182 @@ impl w::PresentationSurface<Backend> for Surface {
         Ok(())
     }
 
+    unsafe fn set_present_mode(
+        &mut self,
+        mode: w::PresentMode,
+    ) -> Result<(), w::PresentModeIncompatible> {
+        let swapchain = match self.swapchain {
+            Some(ref mut ssc) => &mut ssc.swapchain,
+            None => return Err(w::PresentModeIncompatible),
+        };
+        let present_mode = conv::map_present_mode(mode);
+        if present_mode == swapchain.present_mode || swapchain.present_modes.contains(&present_mode)
+        {
+            swapchain.present_mode = present_mode;
+            Ok(())
+        } else {
+            Err(w::PresentModeIncompatible)
+        }
+    }
+
+    unsafe fn acquire_full_screen_exclusive(&mut self) -> Result<(), w::FullScreenExclusiveError> {
+        let ssc = match self.swapchain {
+            Some(ref ssc)
+                if ssc.swapchain.full_screen_exclusive
+                    == w::FullScreenExclusive::ApplicationControlled =>
+            {
+                ssc
+            }
+            _ => return Err(w::FullScreenExclusiveError::NotApplicationControlled),
+        };
+        let full_screen_exclusive = ssc
+            .device
+            .extension_fns
+            .full_screen_exclusive
+            .as_ref()
+            .ok_or(w::FullScreenExclusiveError::NotApplicationControlled)?;
+        match full_screen_exclusive
+            .acquire_full_screen_exclusive_mode_ext(ssc.device.raw.handle(), ssc.swapchain.raw)
+        {
+            vk::Result::SUCCESS => Ok(()),
+            vk::Result::ERROR_OUT_OF_HOST_MEMORY => Err(hal::device::OutOfMemory::Host.into()),
+            vk::Result::ERROR_OUT_OF_DEVICE_MEMORY => Err(hal::device::OutOfMemory::Device.into()),
+            vk::Result::ERROR_SURFACE_LOST_KHR => Err(w::SurfaceLost.into()),
+            vk::Result::ERROR_INITIALIZATION_FAILED => {
+                Err(w::FullScreenExclusiveError::NotAvailable)
+            }
+            other => {
+                error!(
+                    "Unexpected result acquiring exclusive full screen: {:?}",
+                    other
+                );
+                Err(w::FullScreenExclusiveError::NotAvailable)
+            }
+        }
+    }
+
+    unsafe fn release_full_screen_exclusive(&mut self) -> Result<(), w::FullScreenExclusiveError> {
+        let ssc = match self.swapchain {
+            Some(ref ssc)
+                if ssc.swapchain.full_screen_exclusive
+                    == w::FullScreenExclusive::ApplicationControlled =>
+            {
+                ssc
+            }
+            _ => return Err(w::FullScreenExclusiveError::NotApplicationControlled),
+        };
+        let full_screen_exclusive = ssc
+            .device
+            .extension_fns
+            .full_screen_exclusive
+            .as_ref()
+            .ok_or(w::FullScreenExclusiveError::NotApplicationControlled)?;
+        match full_screen_exclusive
+            .release_full_screen_exclusive_mode_ext(ssc.device.raw.handle(), ssc.swapchain.raw)
+        {
+            vk::Result::SUCCESS => Ok(()),
+            vk::Result::ERROR_SURFACE_LOST_KHR => Err(w::SurfaceLost.into()),
+            other => {
+                error!(
+                    "Unexpected result releasing exclusive full screen: {:?}",
+                    other
+                );
+                Err(w::FullScreenExclusiveError::NotAvailable)
+            }
+        }
+    }
+
+    unsafe fn get_refresh_duration(&mut self) -> Option<u64> {
+        let ssc = self.swapchain.as_ref()?;
+        let display_timing = ssc.device.extension_fns.display_timing.as_ref()?;
+        let mut properties = vk::RefreshCycleDurationGOOGLE::default();
+        match display_timing.get_refresh_cycle_duration_google(
+            ssc.device.raw.handle(),
+            ssc.swapchain.raw,
+            &mut properties,
+        ) {
+            vk::Result::SUCCESS => Some(properties.refresh_duration),
+            other => {
+                warn!("Unable to get the refresh cycle duration: {:?}", other);
+                None
+            }
+        }
+    }
+
+    unsafe fn get_past_presentation_timing(&mut self) -> Vec<w::PresentationTiming> {
+        let ssc = match self.swapchain {
+            Some(ref ssc) => ssc,
+            None => return Vec::new(),
+        };
+        let display_timing = match ssc.device.extension_fns.display_timing {
+            Some(ref display_timing) => display_timing,
+            None => return Vec::new(),
+        };
+
+        let device = ssc.device.raw.handle();
+        let mut count = 0;
+        let result = display_timing.get_past_presentation_timing_google(
+            device,
+            ssc.swapchain.raw,
+            &mut count,
+            ptr::null_mut(),
+        );
+        if result != vk::Result::SUCCESS {
+            warn!("Unable to get the past presentation timing: {:?}", result);
+            return Vec::new();
+        }
+
+        let mut timings = vec![vk::PastPresentationTimingGOOGLE::default(); count as usize];
+        match display_timing.get_past_presentation_timing_google(
+            device,
+            ssc.swapchain.raw,
+            &mut count,
+            timings.as_mut_ptr(),
+        ) {
+            // `INCOMPLETE` leaves the rest for the next call
+            vk::Result::SUCCESS | vk::Result::INCOMPLETE => {}
+            other => {
+                warn!("Unable to get the past presentation timing: {:?}", other);
+                return Vec::new();
+            }
+        }
+        timings.truncate(count as usize);
+
+        timings
+            .into_iter()
+            .map(|timing| w::PresentationTiming {
+                present_id: timing.present_id,
+                desired_present_time: timing.desired_present_time,
+                actual_present_time: timing.actual_present_time,
+            })
+            .collect()
+    }
+
+    unsafe fn set_hdr_metadata(
+        &mut self,
+        metadata: &w::HdrMetadata,
+    ) -> Result<(), w::HdrMetadataUnsupported> {
+        let ssc = self.swapchain.as_ref().ok_or(w::HdrMetadataUnsupported)?;
+        let hdr_metadata = ssc
+            .device
+            .extension_fns
+            .hdr_metadata
+            .as_ref()
+            .ok_or(w::HdrMetadataUnsupported)?;
+        let xy = |[x, y]: [f32; 2]| vk::XYColorEXT { x, y };
+        let info = vk::HdrMetadataEXT::builder()
+            .display_primary_red(xy(metadata.red_primary))
+            .display_primary_green(xy(metadata.green_primary))
+            .display_primary_blue(xy(metadata.blue_primary))
+            .white_point(xy(metadata.white_point))
+            .max_luminance(metadata.max_luminance)
+            .min_luminance(metadata.min_luminance)
+            .max_content_light_level(metadata.max_content_light_level)
+            .max_frame_average_light_level(metadata.max_frame_average_light_level);
+        hdr_metadata.set_hdr_metadata_ext(ssc.device.raw.handle(), 1, &ssc.swapchain.raw, &*info);
+        Ok(())
+    }
+
     unsafe fn unconfigure_swapchain(&mut self, device: &Device) {
         if let Some(ssc) = self.swapchain.take() {
             let swapchain = ssc.release_resources(&device.shared.raw);
@@ -427,6 +826,7 @@ impl w::PresentationSurface<Backend> for Surface {
                         ty: vk::ImageType::TYPE_2D,
                         flags: vk::ImageCreateFlags::empty(),
                         extent: ssc.swapchain.extent,
+                        tiling: vk::ImageTiling::OPTIMAL,
                     },
                     view: native::ImageView {
                         image: frame.image,
@@ -441,7 +841,10 @@ impl w::PresentationSurface<Backend> for Surface {
             }
             Err(vk::Result::NOT_READY) => Err(w::AcquireError::NotReady { timeout: false }),
             Err(vk::Result::TIMEOUT) => Err(w::AcquireError::NotReady { timeout: true }),
-            Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => Err(w::OutOfDate.into()),
+            Err(vk::Result::ERROR_OUT_OF_DATE_KHR)
+            | Err(vk::Result::ERROR_FULL_SCREEN_EXCLUSIVE_MODE_LOST_EXT) => {
+                Err(w::OutOfDate.into())
+            }
             Err(vk::Result::ERROR_SURFACE_LOST_KHR) => Err(w::SurfaceLost.into()),
             Err(vk::Result::ERROR_OUT_OF_HOST_MEMORY) => Err(hal::device::OutOfMemory::Host.into()),
             Err(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY) => {
@@ -458,6 +861,11 @@ pub struct Swapchain {
     pub(crate) functor: khr::Swapchain,
     pub(crate) vendor_id: u32,
     pub(crate) extent: vk::Extent3D,
+    pub(crate) full_screen_exclusive: w::FullScreenExclusive,
+    /// Present mode of the next present.
+    pub(crate) present_mode: vk::PresentModeKHR,
+    /// Present modes that can be switched to, empty if the mode can't be switched.
+    pub(crate) present_modes: Vec<vk::PresentModeKHR>,
 }
 
 impl fmt::Debug for Swapchain {
@@ -490,7 +898,10 @@ impl Swapchain {
             Ok((i, false)) => Ok((i, None)),
             Err(vk::Result::NOT_READY) => Err(w::AcquireError::NotReady { timeout: false }),
             Err(vk::Result::TIMEOUT) => Err(w::AcquireError::NotReady { timeout: true }),
-            Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => Err(w::OutOfDate.into()),
+            Err(vk::Result::ERROR_OUT_OF_DATE_KHR)
+            | Err(vk::Result::ERROR_FULL_SCREEN_EXCLUSIVE_MODE_LOST_EXT) => {
+                Err(w::OutOfDate.into())
+            }
             Err(vk::Result::ERROR_SURFACE_LOST_KHR) => Err(w::SurfaceLost.into()),
             Err(vk::Result::ERROR_OUT_OF_HOST_MEMORY) => Err(hal::device::OutOfMemory::Host.into()),
             Err(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY) => {
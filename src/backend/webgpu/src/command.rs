@@ -7,17 +7,17 @@ use hal::{
         CommandBufferInheritanceInfo, DescriptorSetOffset, ImageBlit, ImageCopy, ImageResolve,
         Level, SubpassContents,
     },
-    device::OutOfMemory,
+    device::WaitError,
     image::{Filter, Layout, SubresourceRange},
     memory::{Barrier, Dependencies},
     pso, query,
-    queue::Submission,
+    queue::{Submission, SubmitError},
     window::{PresentError, PresentationSurface, Suboptimal, SwapImageIndex},
     DrawCount, IndexCount, IndexType, InstanceCount, TaskCount, VertexCount, VertexOffset,
     WorkGroupCount,
 };
 
-use crate::Backend;
+use crate::{Backend, NOT_SUPPORTED_MESSAGE};
 
 #[derive(Debug)]
 pub struct Queue;
@@ -29,7 +29,8 @@ impl hal::queue::Queue<Backend> for Queue {
         _: Iw,
         _: Is,
         _fence: Option<&mut <Backend as hal::Backend>::Fence>,
-    ) where
+    ) -> Result<(), SubmitError>
+    where
         Ic: Iterator<Item = &'a <Backend as hal::Backend>::CommandBuffer>,
     {
         todo!()
@@ -44,7 +45,7 @@ impl hal::queue::Queue<Backend> for Queue {
         todo!()
     }
 
-    fn wait_idle(&mut self) -> Result<(), OutOfMemory> {
+    fn wait_idle(&mut self) -> Result<(), WaitError> {
         todo!()
     }
 }
@@ -233,6 +234,24 @@ impl hal::command::CommandBuffer<Backend> for CommandBuffer {
         todo!()
     }
 
+    unsafe fn set_shading_rate(
+        &mut self,
+        _rate: pso::ShadingRate,
+        _combiners: [pso::ShadingRateCombiner; 2],
+    ) {
+        // `Features::SHADING_RATE` isn't reported
+        unimplemented!("{}", NOT_SUPPORTED_MESSAGE)
+    }
+
+    unsafe fn set_sample_locations(
+        &mut self,
+        _samples: hal::image::NumSamples,
+        _locations: &[pso::SampleLocation],
+    ) {
+        // `Features::SAMPLE_LOCATIONS` isn't reported
+        unimplemented!("{}", NOT_SUPPORTED_MESSAGE)
+    }
+
     unsafe fn begin_render_pass<T>(
         &mut self,
         _render_pass: &<Backend as hal::Backend>::RenderPass,
@@ -255,6 +274,15 @@ impl hal::command::CommandBuffer<Backend> for CommandBuffer {
         todo!()
     }
 
+    unsafe fn begin_rendering(&mut self, _info: &hal::command::RenderingInfo<Backend>) {
+        // `Features::DYNAMIC_RENDERING` isn't reported
+        unimplemented!("{}", NOT_SUPPORTED_MESSAGE)
+    }
+
+    unsafe fn end_rendering(&mut self) {
+        unimplemented!("{}", NOT_SUPPORTED_MESSAGE)
+    }
+
     unsafe fn bind_graphics_pipeline(
         &mut self,
         _pipeline: &<Backend as hal::Backend>::GraphicsPipeline,
@@ -549,4 +577,8 @@ impl hal::command::CommandBuffer<Backend> for CommandBuffer {
     unsafe fn end_debug_marker(&mut self) {
         todo!()
     }
+
+    unsafe fn set_checkpoint(&mut self, _label: &str) {
+        // not supported
+    }
 }
@@ -17,7 +17,7 @@ use hal::{
     MemoryTypeId,
 };
 
-use crate::Backend;
+use crate::{Backend, NOT_SUPPORTED_MESSAGE};
 
 #[derive(Debug)]
 pub struct Device;
@@ -270,6 +270,37 @@ impl hal::device::Device<Backend> for Device {
         todo!()
     }
 
+    unsafe fn create_sampler_ycbcr_conversion(
+        &self,
+        desc: &image::SamplerYcbcrConversionDesc,
+    ) -> Result<<Backend as hal::Backend>::SamplerYcbcrConversion, image::CreationError> {
+        Err(image::CreationError::Format(desc.format))
+    }
+
+    unsafe fn destroy_sampler_ycbcr_conversion(
+        &self,
+        _conversion: <Backend as hal::Backend>::SamplerYcbcrConversion,
+    ) {
+    }
+
+    unsafe fn create_ycbcr_sampler(
+        &self,
+        _desc: &image::SamplerDesc,
+        _conversion: &<Backend as hal::Backend>::SamplerYcbcrConversion,
+    ) -> Result<<Backend as hal::Backend>::Sampler, AllocationError> {
+        todo!()
+    }
+
+    unsafe fn create_ycbcr_image_view(
+        &self,
+        _image: &<Backend as hal::Backend>::Image,
+        _view_kind: image::ViewKind,
+        _range: image::SubresourceRange,
+        _conversion: &<Backend as hal::Backend>::SamplerYcbcrConversion,
+    ) -> Result<<Backend as hal::Backend>::ImageView, image::ViewCreationError> {
+        todo!()
+    }
+
     unsafe fn create_descriptor_pool<I>(
         &self,
         _max_sets: usize,
@@ -338,6 +369,34 @@ impl hal::device::Device<Backend> for Device {
         todo!()
     }
 
+    unsafe fn copy_memory_to_image<I>(
+        &self,
+        _data: &[u8],
+        _image: &<Backend as hal::Backend>::Image,
+        _layout: image::Layout,
+        _regions: I,
+    ) -> Result<(), MapError>
+    where
+        I: Iterator<Item = hal::command::BufferImageCopy>,
+    {
+        // `Features::HOST_IMAGE_COPY` isn't reported
+        unimplemented!("{}", NOT_SUPPORTED_MESSAGE)
+    }
+
+    unsafe fn copy_image_to_memory<I>(
+        &self,
+        _image: &<Backend as hal::Backend>::Image,
+        _layout: image::Layout,
+        _data: &mut [u8],
+        _regions: I,
+    ) -> Result<(), MapError>
+    where
+        I: Iterator<Item = hal::command::BufferImageCopy>,
+    {
+        // `Features::HOST_IMAGE_COPY` isn't reported
+        unimplemented!("{}", NOT_SUPPORTED_MESSAGE)
+    }
+
     fn create_semaphore(&self) -> Result<<Backend as hal::Backend>::Semaphore, OutOfMemory> {
         todo!()
     }
@@ -443,7 +502,7 @@ impl hal::device::Device<Backend> for Device {
         todo!()
     }
 
-    fn wait_idle(&self) -> Result<(), OutOfMemory> {
+    fn wait_idle(&self) -> Result<(), WaitError> {
         todo!()
     }
 
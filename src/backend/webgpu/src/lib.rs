@@ -14,6 +14,8 @@ mod command;
 mod device;
 mod window;
 
+const NOT_SUPPORTED_MESSAGE: &str = "This function is not supported by the WebGPU backend";
+
 pub use crate::command::{CommandBuffer, CommandPool, Queue};
 pub use crate::device::Device;
 pub use crate::window::{Surface, Swapchain};
@@ -43,6 +45,7 @@ impl hal::Backend for Backend {
     type Image = ();
     type ImageView = ();
     type Sampler = ();
+    type SamplerYcbcrConversion = ();
 
     type ComputePipeline = ();
     type GraphicsPipeline = ();
@@ -48,6 +48,59 @@ pub struct MemoryProperties {
     pub memory_heaps: Vec<MemoryHeap>,
 }
 
+/// Numeric type of the components of a cooperative matrix.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum CooperativeMatrixComponentType {
+    /// 16-bit floating point.
+    Float16,
+    /// 32-bit floating point.
+    Float32,
+    /// 64-bit floating point.
+    Float64,
+    /// 8-bit signed integer.
+    Sint8,
+    /// 16-bit signed integer.
+    Sint16,
+    /// 32-bit signed integer.
+    Sint32,
+    /// 64-bit signed integer.
+    Sint64,
+    /// 8-bit unsigned integer.
+    Uint8,
+    /// 16-bit unsigned integer.
+    Uint16,
+    /// 32-bit unsigned integer.
+    Uint32,
+    /// 64-bit unsigned integer.
+    Uint64,
+}
+
+/// A matrix multiply-accumulate configuration `Result = A * B + C` supported
+/// by the cooperative matrix operations of the subgroups in the shaders,
+/// where `A` is `m_size` x `k_size`, `B` is `k_size` x `n_size`, and
+/// `C` and `Result` are `m_size` x `n_size`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CooperativeMatrixProperties {
+    /// Number of rows of `A`, `C` and `Result`.
+    pub m_size: u32,
+    /// Number of columns of `B`, `C` and `Result`.
+    pub n_size: u32,
+    /// Number of columns of `A` and rows of `B`.
+    pub k_size: u32,
+    /// Component type of `A`.
+    pub a_type: CooperativeMatrixComponentType,
+    /// Component type of `B`.
+    pub b_type: CooperativeMatrixComponentType,
+    /// Component type of `C`.
+    pub c_type: CooperativeMatrixComponentType,
+    /// Component type of `Result`.
+    pub result_type: CooperativeMatrixComponentType,
+    /// Whether the integer accumulation saturates instead of wrapping.
+    pub saturating_accumulation: bool,
+}
+
 /// Represents a combination of a [logical device][crate::device::Device] and the
 /// [hardware queues][QueueGroup] it provides.
 ///
@@ -112,6 +165,22 @@ pub trait PhysicalDevice<B: Backend>: fmt::Debug + Any + Send + Sync {
         view_caps: image::ViewCapabilities,
     ) -> Option<image::FormatProperties>;
 
+    /// Fetch the DRM format modifiers supported for a particular format.
+    ///
+    /// Returns an empty list if `Features::DRM_FORMAT_MODIFIER` isn't supported.
+    fn drm_modifier_properties(&self, format: format::Format) -> Vec<image::DrmModifierProperties> {
+        let _ = format;
+        Vec::new()
+    }
+
+    /// Fetch the matrix sizes and component types supported by the
+    /// cooperative matrix operations in the shaders.
+    ///
+    /// Returns an empty list if `Features::COOPERATIVE_MATRIX` isn't supported.
+    fn cooperative_matrix_properties(&self) -> Vec<CooperativeMatrixProperties> {
+        Vec::new()
+    }
+
     /// Fetch details for the memory regions provided by the device.
     fn memory_properties(&self) -> MemoryProperties;
 
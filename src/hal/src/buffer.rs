@@ -5,6 +5,7 @@
 //! They can be used as shader resources, vertex buffers, index buffers or for
 //! specifying the action commands for indirect execution.
 
+#[cfg(feature = "std")]
 use crate::{device::OutOfMemory, format::Format};
 
 /// An offset inside a buffer, in bytes.
@@ -40,6 +41,7 @@ impl SubRange {
 pub type State = Access;
 
 /// Error creating a buffer.
+#[cfg(feature = "std")]
 #[derive(Clone, Debug, PartialEq, thiserror::Error)]
 pub enum CreationError {
     /// Out of either host or device memory.
@@ -53,6 +55,7 @@ pub enum CreationError {
 }
 
 /// Error creating a buffer view.
+#[cfg(feature = "std")]
 #[derive(Clone, Debug, PartialEq, thiserror::Error)]
 pub enum ViewCreationError {
     /// Out of either host or device memory.
@@ -18,13 +18,13 @@ mod structs;
 
 use crate::{
     buffer,
-    image::{Filter, Layout, SubresourceRange},
+    image::{self, Filter, Layout, SubresourceRange},
     memory::{Barrier, Dependencies},
     pass, pso, query, Backend, DrawCount, IndexCount, IndexType, InstanceCount, TaskCount,
     VertexCount, VertexOffset, WorkGroupCount,
 };
 
-use std::{any::Any, fmt, ops::Range};
+use std::{any::Any, fmt, iter, ops::Range};
 
 pub use self::clear::*;
 pub use self::structs::*;
@@ -35,6 +35,7 @@ pub type DescriptorSetOffset = u32;
 bitflags! {
     /// Option flags for various command buffer settings.
     #[derive(Default)]
+    #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
     pub struct CommandBufferFlags: u32 {
         /// Says that the command buffer will be recorded, submitted only once, and then reset and re-filled
         /// for another submission.
@@ -50,8 +51,25 @@ bitflags! {
     }
 }
 
+bitflags! {
+    /// Flags of a [`RenderingInfo`].
+    #[derive(Default)]
+    #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+    pub struct RenderingFlags: u32 {
+        /// The rendering is suspended by `end_rendering`, to be resumed by the next
+        /// `begin_rendering` in submission order, which has the same attachments.
+        ///
+        /// No commands may be recorded between the suspending and the resuming
+        /// rendering, so that a single rendering can be split across command buffers.
+        const SUSPENDING = 0x1;
+        /// The rendering resumes a suspended one.
+        const RESUMING = 0x2;
+    }
+}
+
 /// An enum that indicates whether a command buffer is primary or secondary.
 #[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Level {
     /// Can be submitted to a queue for execution, but cannot be called from other
     /// command buffers.
@@ -61,7 +79,8 @@ pub enum Level {
 }
 
 /// Specifies how commands for the following render passes will be recorded.
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum SubpassContents {
     /// Contents of the subpass will be inline in the command buffer,
     /// NOT in secondary command buffers.
@@ -81,6 +100,94 @@ pub struct RenderAttachmentInfo<'a, B: Backend> {
     pub clear_value: ClearValue,
 }
 
+/// An attachment provided to `begin_rendering`, described in place
+/// instead of being a part of a render pass.
+#[derive(Debug)]
+pub struct RenderingAttachmentInfo<'a, B: Backend> {
+    /// View of the attachment image.
+    pub image_view: &'a B::ImageView,
+    /// Format, sample count, operations and layouts of the attachment.
+    pub desc: pass::Attachment,
+    /// Clear value, if needed.
+    pub clear_value: ClearValue,
+}
+
+/// Attachments to render into with `begin_rendering`.
+#[derive(Debug)]
+pub struct RenderingInfo<'a, B: Backend> {
+    /// Section of the attachments to render.
+    pub render_area: pso::Rect,
+    /// Number of array layers to render into.
+    pub layers: image::Layer,
+    /// Color attachments, in the order of the fragment shader outputs.
+    pub colors: &'a [RenderingAttachmentInfo<'a, B>],
+    /// Depth/stencil attachment, if any.
+    pub depth_stencil: Option<&'a RenderingAttachmentInfo<'a, B>>,
+    /// Suspending and resuming flags.
+    pub flags: RenderingFlags,
+}
+
+impl<'a, B: Backend> RenderingInfo<'a, B> {
+    /// Returns all the attachments, with the depth/stencil one going last.
+    pub fn attachments(&self) -> impl Iterator<Item = &'a RenderingAttachmentInfo<'a, B>> {
+        self.colors.iter().chain(self.depth_stencil)
+    }
+
+    /// Returns the description of an attachment with the operations adjusted to the flags:
+    /// resuming rendering loads the contents, and suspending rendering stores them.
+    pub fn attachment_desc(&self, attachment: &RenderingAttachmentInfo<B>) -> pass::Attachment {
+        let mut desc = attachment.desc.clone();
+        if self.flags.contains(RenderingFlags::RESUMING) {
+            desc.ops.load = pass::AttachmentLoadOp::Load;
+            desc.stencil_ops.load = pass::AttachmentLoadOp::Load;
+        }
+        if self.flags.contains(RenderingFlags::SUSPENDING) {
+            desc.ops.store = pass::AttachmentStoreOp::Store;
+            desc.stencil_ops.store = pass::AttachmentStoreOp::Store;
+        }
+        desc
+    }
+
+    /// Returns the smallest framebuffer extent covering the render area.
+    pub fn extent(&self) -> image::Extent {
+        image::Extent {
+            width: self.render_area.x as u32 + self.render_area.w as u32,
+            height: self.render_area.y as u32 + self.render_area.h as u32,
+            depth: self.layers as u32,
+        }
+    }
+
+    /// Calls `fun` with the attachments and the only subpass of a render pass
+    /// equivalent to this rendering, for the backends emulating it with render passes.
+    pub fn with_subpass<R>(
+        &self,
+        fun: impl FnOnce(&[pass::Attachment], pass::SubpassDesc) -> R,
+    ) -> R {
+        let attachments = self
+            .attachments()
+            .map(|at| self.attachment_desc(at))
+            .collect::<Vec<_>>();
+        let colors = (0..self.colors.len())
+            .map(|id| (id, Layout::ColorAttachmentOptimal))
+            .collect::<Vec<_>>();
+        let depth_stencil = self
+            .depth_stencil
+            .map(|_| (self.colors.len(), Layout::DepthStencilAttachmentOptimal));
+        fun(
+            &attachments,
+            pass::SubpassDesc {
+                colors: &colors,
+                depth_stencil: depth_stencil.as_ref(),
+                inputs: &[],
+                resolves: &[],
+                preserves: &[],
+                view_mask: 0,
+                shading_rate: None,
+            },
+        )
+    }
+}
+
 #[allow(missing_docs)]
 #[derive(Debug)]
 pub struct CommandBufferInheritanceInfo<'a, B: Backend> {
@@ -124,6 +231,10 @@ pub trait CommandBuffer<B: Backend>: fmt::Debug + Any + Send + Sync {
 
     /// Empties the command buffer, optionally releasing all
     /// resources from the commands that have been submitted.
+    ///
+    /// The parent pool has to be created with `CommandPoolCreateFlags::RESET_INDIVIDUAL`.
+    /// If `release_resources` is `false`, the command buffer keeps its memory
+    /// around for the next recording.
     unsafe fn reset(&mut self, release_resources: bool);
 
     // TODO: This REALLY needs to be deeper, but it's complicated.
@@ -138,6 +249,49 @@ pub trait CommandBuffer<B: Backend>: fmt::Debug + Any + Send + Sync {
     ) where
         T: Iterator<Item = Barrier<'a, B>>;
 
+    /// Begins a split barrier, letting the transitions of `barriers` happen
+    /// in the background while the commands recorded until the matching
+    /// [`end_barrier`][CommandBuffer::end_barrier] execute.
+    ///
+    /// - `event` has to be unsignaled, and not used by any other command until
+    ///   the barrier is ended.
+    /// - `stages.start` are the stages that have to complete before the barrier starts.
+    /// - `barriers` have to be passed again, unchanged, to `end_barrier`.
+    ///
+    /// The resources affected by the barriers must not be accessed until it is ended.
+    unsafe fn begin_barrier<'a, T>(
+        &mut self,
+        event: &B::Event,
+        stages: Range<pso::PipelineStage>,
+        barriers: T,
+    ) where
+        T: Iterator<Item = Barrier<'a, B>>,
+    {
+        let _ = barriers;
+        self.set_event(event, stages.start);
+    }
+
+    /// Ends a split barrier started by [`begin_barrier`][CommandBuffer::begin_barrier],
+    /// blocking `stages.end` until the transitions are complete.
+    ///
+    /// `event` is left unsignaled, ready to be used by another split barrier.
+    unsafe fn end_barrier<'a, T>(
+        &mut self,
+        event: &B::Event,
+        stages: Range<pso::PipelineStage>,
+        barriers: T,
+    ) where
+        T: Iterator<Item = Barrier<'a, B>>,
+    {
+        // Re-borrow the barriers, so that they can share the lifetime of the event
+        self.wait_events(
+            iter::once(event),
+            stages.clone(),
+            barriers.map(|barrier| barrier),
+        );
+        self.reset_event(event, stages.end);
+    }
+
     /// Fill a buffer with the given `u32` value.
     unsafe fn fill_buffer(&mut self, buffer: &B::Buffer, range: buffer::SubRange, data: u32);
 
@@ -281,6 +435,34 @@ pub trait CommandBuffer<B: Backend>: fmt::Debug + Any + Send + Sync {
     /// Set the depth bias dynamically.
     unsafe fn set_depth_bias(&mut self, depth_bias: pso::DepthBias);
 
+    /// Set the fragment shading rate of the following draw calls.
+    ///
+    /// The final rate is obtained by combining `rate` with the per-primitive rate
+    /// using `combiners[0]`, and then the result with the rate read from the shading
+    /// rate attachment of the subpass using `combiners[1]`.
+    ///
+    /// Only valid to call if `Features::SHADING_RATE` is enabled. Combiners other than
+    /// `Keep` for the attachment also require `Features::SHADING_RATE_ATTACHMENT`.
+    /// The per-primitive rates aren't exposed yet, so `combiners[0]` has to be `Keep`.
+    unsafe fn set_shading_rate(
+        &mut self,
+        rate: pso::ShadingRate,
+        combiners: [pso::ShadingRateCombiner; 2],
+    );
+
+    /// Set the sample locations of the following draw calls, for the pipelines
+    /// created with `Multisampling::sample_locations`.
+    ///
+    /// `locations` contains one location per sample, and the same pattern is used
+    /// for every pixel. `samples` has to be in `Limits::sample_location_sample_counts`.
+    ///
+    /// Only valid to call if `Features::SAMPLE_LOCATIONS` is enabled.
+    unsafe fn set_sample_locations(
+        &mut self,
+        samples: image::NumSamples,
+        locations: &[pso::SampleLocation],
+    );
+
     /// Begins recording commands for a render pass on the given framebuffer.
     ///
     /// # Arguments
@@ -307,6 +489,20 @@ pub trait CommandBuffer<B: Backend>: fmt::Debug + Any + Send + Sync {
     /// Finishes recording commands for the current a render pass.
     unsafe fn end_render_pass(&mut self);
 
+    /// Begins rendering into the attachments described by `info`, without
+    /// creating `RenderPass` and `Framebuffer` objects.
+    ///
+    /// The rendering consists of a single subpass with inline contents.
+    /// Bound graphics pipelines need to be created against a render pass from
+    /// [`Device::create_rendering_pass`][crate::device::Device::create_rendering_pass]
+    /// with the same attachment formats and sample counts.
+    ///
+    /// Only valid to call if `Features::DYNAMIC_RENDERING` is enabled.
+    unsafe fn begin_rendering(&mut self, info: &RenderingInfo<B>);
+
+    /// Finishes the rendering started by `begin_rendering`.
+    unsafe fn end_rendering(&mut self);
+
     /// Bind a graphics pipeline.
     ///
     /// # Errors
@@ -368,7 +564,7 @@ pub trait CommandBuffer<B: Backend>: fmt::Debug + Any + Send + Sync {
     /// - This function must be called outside of a render pass.
     /// - `count` must be less than or equal to `Limits::max_compute_work_group_count`
     ///
-    /// TODO:
+    /// Dispatching with any of the dimensions being zero is a no-op.
     unsafe fn dispatch(&mut self, count: WorkGroupCount);
 
     /// Works similarly to `dispatch()` but reads parameters from the given
@@ -419,6 +615,8 @@ pub trait CommandBuffer<B: Backend>: fmt::Debug + Any + Send + Sync {
     /// from the currently bound vertex buffers.  It performs instanced
     /// drawing, drawing `instances.len()`
     /// times with an `instanceIndex` starting with the start of the range.
+    ///
+    /// Drawing an empty range of vertices or instances is a no-op.
     unsafe fn draw(&mut self, vertices: Range<VertexCount>, instances: Range<InstanceCount>);
 
     /// Performs indexed drawing, drawing the range of indices
@@ -427,6 +625,8 @@ pub trait CommandBuffer<B: Backend>: fmt::Debug + Any + Send + Sync {
     /// That is, the offset into the vertex buffer is `(current_index + base_vertex)`
     ///
     /// It also performs instanced drawing, identical to `draw()`.
+    ///
+    /// Drawing an empty range of indices or instances is a no-op.
     unsafe fn draw_indexed(
         &mut self,
         indices: Range<IndexCount>,
@@ -610,4 +810,16 @@ pub trait CommandBuffer<B: Backend>: fmt::Debug + Any + Send + Sync {
     unsafe fn begin_debug_marker(&mut self, name: &str, color: u32);
     /// End the last started debug marker scope.
     unsafe fn end_debug_marker(&mut self);
+
+    /// Set a checkpoint with the given label, tracking the progress of the device
+    /// for the diagnostics of a loss, see [`Device::get_device_lost_reason`].
+    ///
+    /// Checkpoints rely on `VK_NV_device_diagnostic_checkpoints` on Vulkan, and on the
+    /// DRED breadcrumb contexts on DX12, where markers inserted with [`insert_debug_marker`]
+    /// show up as checkpoints as well. Metal only tracks the progress at the granularity
+    /// of submissions. Other backends ignore the checkpoints.
+    ///
+    /// [`Device::get_device_lost_reason`]: crate::device::Device::get_device_lost_reason
+    /// [`insert_debug_marker`]: CommandBuffer::insert_debug_marker
+    unsafe fn set_checkpoint(&mut self, label: &str);
 }
@@ -12,8 +12,8 @@
 //! and is used to actually do things.
 
 use crate::{
-    buffer, format, image,
-    memory::{Requirements, Segment},
+    buffer, command, format, image,
+    memory::{DedicatedResource, Priority, Requirements, Segment},
     pass,
     pool::CommandPoolCreateFlags,
     pso,
@@ -23,13 +23,56 @@ use crate::{
     Backend, MemoryTypeId,
 };
 
+#[cfg(unix)]
+use std::os::unix::io::RawFd;
 use std::{any::Any, fmt, iter, ops::Range};
 
 /// Error occurred caused device to be lost.
+///
+/// Once lost, the device can't execute any more work, and all the later submissions
+/// and waits report the loss as well. Applications that need to survive it should:
+///   1. query [`Device::get_device_lost_reason`] for the diagnostics, if needed,
+///   2. destroy all the objects created from the device, then drop the device and its queues,
+///   3. enumerate the adapters again, since the lost one may be gone,
+///   4. open a new device, and re-create the resources with their contents.
 #[derive(Clone, Debug, PartialEq, thiserror::Error)]
 #[error("Device lost")]
 pub struct DeviceLost;
 
+/// Cause of a device loss, as far as the driver reports it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeviceLostCause {
+    /// The driver doesn't report the cause.
+    Unknown,
+    /// The submitted work took too long to execute.
+    Hung,
+    /// The submitted work accessed memory that isn't mapped to the device.
+    PageFault,
+    /// The device was reset, not necessarily because of this application.
+    Reset,
+    /// The device was physically removed, or its driver was upgraded.
+    Removed,
+    /// The driver ran into an internal error.
+    DriverError,
+}
+
+/// Diagnostics of a device loss, returned by [`Device::get_device_lost_reason`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeviceLostReason {
+    /// Cause of the loss.
+    pub cause: DeviceLostCause,
+    /// Backend-specific description of the loss, possibly empty.
+    pub description: String,
+    /// GPU virtual address of the faulting access, if known.
+    pub fault_address: Option<u64>,
+    /// Label of the last checkpoint the device is known to have passed,
+    /// see [`CommandBuffer::set_checkpoint`][crate::command::CommandBuffer::set_checkpoint].
+    pub last_checkpoint: Option<String>,
+    /// Labels of the checkpoints submitted after `last_checkpoint`,
+    /// which the device may have been executing when it got lost.
+    pub pending_checkpoints: Vec<String>,
+}
+
 /// Error allocating memory.
 #[derive(Clone, Debug, PartialEq, thiserror::Error)]
 pub enum OutOfMemory {
@@ -64,6 +107,32 @@ pub enum AllocationError {
     TooManyObjects,
 }
 
+/// Error importing or exporting memory shared with other processes and APIs.
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+pub enum ExternalMemoryError {
+    /// Out of either host or device memory.
+    #[error(transparent)]
+    OutOfMemory(#[from] OutOfMemory),
+    /// Cannot create any more objects.
+    #[error("Too many objects")]
+    TooManyObjects,
+    /// The handle is invalid, or not compatible with the memory type.
+    #[error("Invalid external handle")]
+    InvalidHandle,
+    /// The backend doesn't support this kind of external memory.
+    #[error("External memory is not supported")]
+    Unsupported,
+}
+
+impl From<AllocationError> for ExternalMemoryError {
+    fn from(error: AllocationError) -> Self {
+        match error {
+            AllocationError::OutOfMemory(oom) => ExternalMemoryError::OutOfMemory(oom),
+            AllocationError::TooManyObjects => ExternalMemoryError::TooManyObjects,
+        }
+    }
+}
+
 /// Device creation errors during `open`.
 #[derive(Clone, Debug, PartialEq, thiserror::Error)]
 pub enum CreationError {
@@ -162,6 +231,19 @@ pub enum ShaderModuleDesc<'a> {
     SpirV(&'a [u32]),
 }
 
+/// Language of the shader source code.
+#[cfg(feature = "shaderc")]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ShaderLanguage<'a> {
+    /// GLSL, with the `main` entry point.
+    Glsl,
+    /// HLSL, with the given entry point.
+    Hlsl {
+        /// Name of the entry point function.
+        entry: &'a str,
+    },
+}
+
 /// Naga shader module.
 #[allow(missing_debug_implementations)]
 pub struct NagaShader {
@@ -205,6 +287,83 @@ pub trait Device<B: Backend>: fmt::Debug + Any + Send + Sync {
         size: u64,
     ) -> Result<B::Memory, AllocationError>;
 
+    /// Allocate a memory segment of a specified type, with a priority hint.
+    ///
+    /// The priority is taken into account by the backends that are able to
+    /// control the residency of the allocations, and is ignored otherwise.
+    unsafe fn allocate_memory_with_priority(
+        &self,
+        memory_type: MemoryTypeId,
+        size: u64,
+        priority: Priority,
+    ) -> Result<B::Memory, AllocationError> {
+        let _ = priority;
+        self.allocate_memory(memory_type, size)
+    }
+
+    /// Change the priority hint of an allocated memory segment.
+    ///
+    /// Used by the backends that page device-local memory out under memory pressure,
+    /// to reorder the eviction of the existing allocations. Ignored otherwise.
+    unsafe fn set_memory_priority(&self, memory: &B::Memory, priority: Priority) {
+        let _ = (memory, priority);
+    }
+
+    /// Allocate a memory segment of a specified type, dedicated to a single resource.
+    ///
+    /// Only the given resource can be bound to this memory, at offset 0.
+    /// This is required for the resources reporting `requires_dedicated`
+    /// in their memory requirements, and beneficial for the ones reporting `prefers_dedicated`.
+    /// The backends that don't distinguish dedicated allocations do a regular one.
+    unsafe fn allocate_dedicated_memory(
+        &self,
+        memory_type: MemoryTypeId,
+        size: u64,
+        resource: DedicatedResource<B>,
+    ) -> Result<B::Memory, AllocationError> {
+        let _ = resource;
+        self.allocate_memory(memory_type, size)
+    }
+
+    /// Allocate a memory segment that can be exported as a dma-buf file descriptor.
+    ///
+    /// Requires `Features::EXTERNAL_MEMORY_DMA_BUF`. Backends that can only
+    /// import dma-bufs, like GL, return `ExternalMemoryError::Unsupported`.
+    #[cfg(unix)]
+    unsafe fn allocate_exportable_memory(
+        &self,
+        memory_type: MemoryTypeId,
+        size: u64,
+    ) -> Result<B::Memory, ExternalMemoryError> {
+        let _ = (memory_type, size);
+        Err(ExternalMemoryError::Unsupported)
+    }
+
+    /// Import a dma-buf file descriptor as a memory segment.
+    ///
+    /// The ownership of `fd` is transferred to the implementation on success.
+    /// Requires `Features::EXTERNAL_MEMORY_DMA_BUF`.
+    #[cfg(unix)]
+    unsafe fn import_dma_buf(
+        &self,
+        memory_type: MemoryTypeId,
+        size: u64,
+        fd: RawFd,
+    ) -> Result<B::Memory, ExternalMemoryError> {
+        let _ = (memory_type, size, fd);
+        Err(ExternalMemoryError::Unsupported)
+    }
+
+    /// Export a memory segment as a new dma-buf file descriptor, owned by the caller.
+    ///
+    /// The memory must have been allocated with `allocate_exportable_memory`.
+    /// Backends that can only import dma-bufs, like GL, return `ExternalMemoryError::Unsupported`.
+    #[cfg(unix)]
+    unsafe fn export_dma_buf(&self, memory: &B::Memory) -> Result<RawFd, ExternalMemoryError> {
+        let _ = memory;
+        Err(ExternalMemoryError::Unsupported)
+    }
+
     /// Free device memory
     unsafe fn free_memory(&self, memory: B::Memory);
 
@@ -244,6 +403,46 @@ pub trait Device<B: Backend>: fmt::Debug + Any + Send + Sync {
         Is: Iterator<Item = pass::SubpassDesc<'a>>,
         Id: Iterator<Item = pass::SubpassDependency>;
 
+    /// Create a render pass for the graphics pipelines that are used within
+    /// [`begin_rendering`][crate::command::CommandBuffer::begin_rendering]
+    /// into attachments with these descriptions.
+    ///
+    /// Only the formats and sample counts of the attachments matter.
+    /// The render pass can't begin a render pass instance or create a framebuffer,
+    /// and is destroyed with [`destroy_render_pass`][Device::destroy_render_pass].
+    ///
+    /// Only valid to call if `Features::DYNAMIC_RENDERING` is enabled.
+    unsafe fn create_rendering_pass<I>(
+        &self,
+        colors: I,
+        depth_stencil: Option<pass::Attachment>,
+    ) -> Result<B::RenderPass, OutOfMemory>
+    where
+        I: Iterator<Item = pass::Attachment>,
+    {
+        let mut attachments = colors.collect::<Vec<_>>();
+        let color_refs = (0..attachments.len())
+            .map(|id| (id, image::Layout::ColorAttachmentOptimal))
+            .collect::<Vec<_>>();
+        let depth_stencil_ref = depth_stencil.map(|attachment| {
+            attachments.push(attachment);
+            (
+                color_refs.len(),
+                image::Layout::DepthStencilAttachmentOptimal,
+            )
+        });
+        let subpass = pass::SubpassDesc {
+            colors: &color_refs,
+            depth_stencil: depth_stencil_ref.as_ref(),
+            inputs: &[],
+            resolves: &[],
+            preserves: &[],
+            view_mask: 0,
+            shading_rate: None,
+        };
+        self.create_render_pass(attachments.into_iter(), iter::once(subpass), iter::empty())
+    }
+
     /// Destroys a *render pass* created by this device.
     unsafe fn destroy_render_pass(&self, rp: B::RenderPass);
 
@@ -314,6 +513,20 @@ pub trait Device<B: Backend>: fmt::Debug + Any + Send + Sync {
         cache: Option<&B::PipelineCache>,
     ) -> Result<B::GraphicsPipeline, pso::CreationError>;
 
+    /// Start creating a graphics pipeline in the background, returning a task
+    /// to poll for the result.
+    ///
+    /// The objects referenced by `desc`, and the `cache`, have to be kept alive
+    /// until the task is complete. Backends without background creation
+    /// create the pipeline before returning.
+    unsafe fn create_graphics_pipeline_async<'a>(
+        &self,
+        desc: &pso::GraphicsPipelineDesc<'a, B>,
+        cache: Option<&B::PipelineCache>,
+    ) -> pso::PipelineTask<B::GraphicsPipeline> {
+        pso::PipelineTask::ready(self.create_graphics_pipeline(desc, cache))
+    }
+
     /// Destroy a graphics pipeline.
     ///
     /// The graphics pipeline shouldn't be destroyed before any submitted command buffer,
@@ -327,6 +540,18 @@ pub trait Device<B: Backend>: fmt::Debug + Any + Send + Sync {
         cache: Option<&B::PipelineCache>,
     ) -> Result<B::ComputePipeline, pso::CreationError>;
 
+    /// Start creating a compute pipeline in the background, returning a task
+    /// to poll for the result.
+    ///
+    /// See [`create_graphics_pipeline_async`][Device::create_graphics_pipeline_async].
+    unsafe fn create_compute_pipeline_async<'a>(
+        &self,
+        desc: &pso::ComputePipelineDesc<'a, B>,
+        cache: Option<&B::PipelineCache>,
+    ) -> pso::PipelineTask<B::ComputePipeline> {
+        pso::PipelineTask::ready(self.create_compute_pipeline(desc, cache))
+    }
+
     /// Destroy a compute pipeline.
     ///
     /// The compute pipeline shouldn't be destroyed before any submitted command buffer,
@@ -335,6 +560,12 @@ pub trait Device<B: Backend>: fmt::Debug + Any + Send + Sync {
 
     /// Create a new framebuffer object.
     ///
+    /// Framebuffers are image-less: they only describe the attachments, and the
+    /// image views are provided to `CommandBuffer::begin_render_pass`. The same
+    /// framebuffer can be used with any views matching the descriptions, so it
+    /// doesn't need to be recreated with the swapchain, unless the extent or
+    /// the format of the swapchain images change.
+    ///
     /// # Safety
     /// - `extent.width`, `extent.height` and `extent.depth` **must** be greater than `0`.
     unsafe fn create_framebuffer<I>(
@@ -368,6 +599,50 @@ pub trait Device<B: Backend>: fmt::Debug + Any + Send + Sync {
         Err((ShaderError::Unsupported, shader))
     }
 
+    /// Create a new shader module from GLSL or HLSL source code.
+    ///
+    /// The source is compiled to SPIR-V with `shaderc`, then translated to the
+    /// native form of the backend like the modules created with `create_shader_module`.
+    /// The `stage` must be a single stage.
+    #[cfg(feature = "shaderc")]
+    unsafe fn create_shader_module_from_source(
+        &self,
+        stage: pso::ShaderStageFlags,
+        source: &str,
+        language: ShaderLanguage,
+    ) -> Result<B::ShaderModule, ShaderError> {
+        let kind = match stage {
+            pso::ShaderStageFlags::VERTEX => shaderc::ShaderKind::Vertex,
+            pso::ShaderStageFlags::HULL => shaderc::ShaderKind::TessControl,
+            pso::ShaderStageFlags::DOMAIN => shaderc::ShaderKind::TessEvaluation,
+            pso::ShaderStageFlags::GEOMETRY => shaderc::ShaderKind::Geometry,
+            pso::ShaderStageFlags::FRAGMENT => shaderc::ShaderKind::Fragment,
+            pso::ShaderStageFlags::COMPUTE => shaderc::ShaderKind::Compute,
+            pso::ShaderStageFlags::TASK => shaderc::ShaderKind::Task,
+            pso::ShaderStageFlags::MESH => shaderc::ShaderKind::Mesh,
+            _ => {
+                return Err(ShaderError::CompilationFailed(format!(
+                    "Expected a single shader stage, got {:?}",
+                    stage
+                )))
+            }
+        };
+        let unavailable = || ShaderError::CompilationFailed("shaderc is unavailable".to_string());
+        let mut compiler = shaderc::Compiler::new().ok_or_else(unavailable)?;
+        let mut options = shaderc::CompileOptions::new().ok_or_else(unavailable)?;
+        let entry = match language {
+            ShaderLanguage::Glsl => "main",
+            ShaderLanguage::Hlsl { entry } => {
+                options.set_source_language(shaderc::SourceLanguage::HLSL);
+                entry
+            }
+        };
+        let artifact = compiler
+            .compile_into_spirv(source, kind, "shader", entry, Some(&options))
+            .map_err(|err| ShaderError::CompilationFailed(err.to_string()))?;
+        self.create_shader_module(artifact.as_binary())
+    }
+
     /// Destroy a shader module module
     ///
     /// A shader module can be destroyed while pipelines created using its shaders are still in use.
@@ -426,6 +701,32 @@ pub trait Device<B: Backend>: fmt::Debug + Any + Send + Sync {
         view_caps: image::ViewCapabilities,
     ) -> Result<B::Image, image::CreationError>;
 
+    /// Create a new 2D image object with a DRM format modifier,
+    /// which can be shared with other processes or scanned out.
+    ///
+    /// If `Features::EXTERNAL_MEMORY_DMA_BUF` is enabled, the image can be bound
+    /// to imported or exportable memory.
+    /// Backends that can only import dma-bufs, like GL, require `DrmTiling::Explicit`.
+    /// Requires `Features::DRM_FORMAT_MODIFIER`.
+    unsafe fn create_image_with_drm_modifier(
+        &self,
+        kind: image::Kind,
+        format: format::Format,
+        tiling: image::DrmTiling,
+        usage: image::Usage,
+        view_caps: image::ViewCapabilities,
+    ) -> Result<B::Image, image::CreationError> {
+        let _ = (kind, format, tiling, usage, view_caps);
+        Err(image::CreationError::DrmModifier)
+    }
+
+    /// Get the DRM format modifier chosen for an image created
+    /// with `create_image_with_drm_modifier`.
+    unsafe fn get_image_drm_modifier(&self, image: &B::Image) -> Option<image::DrmModifier> {
+        let _ = image;
+        None
+    }
+
     /// Get memory requirements for the Image
     unsafe fn get_image_requirements(&self, image: &B::Image) -> Requirements;
 
@@ -460,6 +761,31 @@ pub trait Device<B: Backend>: fmt::Debug + Any + Send + Sync {
         range: image::SubresourceRange,
     ) -> Result<B::ImageView, image::ViewCreationError>;
 
+    /// Create a new image view object, clamping the sampled level of detail to `min_lod`,
+    /// so that the more detailed levels of the image are never accessed.
+    ///
+    /// Without `Features::IMAGE_VIEW_MIN_LOD`, the clamp is emulated by starting
+    /// the view at the level `min_lod` rounds down to. The levels are then
+    /// relative to that one in the shaders, e.g. for the image size queries.
+    unsafe fn create_image_view_with_min_lod(
+        &self,
+        image: &B::Image,
+        view_kind: image::ViewKind,
+        format: format::Format,
+        swizzle: format::Swizzle,
+        range: image::SubresourceRange,
+        min_lod: f32,
+    ) -> Result<B::ImageView, image::ViewCreationError> {
+        let level = (min_lod.max(0.0) as image::Level).max(range.level_start);
+        let skipped = level - range.level_start;
+        let range = image::SubresourceRange {
+            level_start: level,
+            level_count: range.level_count.map(|count| count.saturating_sub(skipped).max(1)),
+            ..range
+        };
+        self.create_image_view(image, view_kind, format, swizzle, range)
+    }
+
     /// Destroy an image view object
     unsafe fn destroy_image_view(&self, view: B::ImageView);
 
@@ -472,6 +798,40 @@ pub trait Device<B: Backend>: fmt::Debug + Any + Send + Sync {
     /// Destroy a sampler object
     unsafe fn destroy_sampler(&self, sampler: B::Sampler);
 
+    /// Create a new sampler Y'CbCr conversion object.
+    ///
+    /// Only valid to call if `Features::SAMPLER_YCBCR_CONVERSION` is enabled.
+    /// The backends without Y'CbCr conversions fail with `image::CreationError::Format`.
+    unsafe fn create_sampler_ycbcr_conversion(
+        &self,
+        desc: &image::SamplerYcbcrConversionDesc,
+    ) -> Result<B::SamplerYcbcrConversion, image::CreationError>;
+
+    /// Destroy a sampler Y'CbCr conversion object.
+    unsafe fn destroy_sampler_ycbcr_conversion(&self, conversion: B::SamplerYcbcrConversion);
+
+    /// Create a new sampler object, applying the given Y'CbCr conversion.
+    ///
+    /// Such samplers can only be used as immutable samplers of combined image samplers,
+    /// paired with image views created by [`create_ycbcr_image_view`][Device::create_ycbcr_image_view]
+    /// with the same conversion.
+    unsafe fn create_ycbcr_sampler(
+        &self,
+        desc: &image::SamplerDesc,
+        conversion: &B::SamplerYcbcrConversion,
+    ) -> Result<B::Sampler, AllocationError>;
+
+    /// Create a new image view object, applying the given Y'CbCr conversion.
+    ///
+    /// The view takes the format of the conversion, with no swizzling.
+    unsafe fn create_ycbcr_image_view(
+        &self,
+        image: &B::Image,
+        view_kind: image::ViewKind,
+        range: image::SubresourceRange,
+        conversion: &B::SamplerYcbcrConversion,
+    ) -> Result<B::ImageView, image::ViewCreationError>;
+
     /// Create a descriptor pool.
     ///
     /// Descriptor pools allow allocation of descriptor sets.
@@ -540,6 +900,56 @@ pub trait Device<B: Backend>: fmt::Debug + Any + Send + Sync {
     /// Unmap a memory object once host access to it is no longer needed by the application
     unsafe fn unmap_memory(&self, memory: &mut B::Memory);
 
+    /// Copy the host memory into an image, without a staging buffer
+    /// and a command buffer.
+    ///
+    /// The regions are interpreted as in `CommandBuffer::copy_buffer_to_image`,
+    /// with `buffer_offset` being the offset into `data`. If `layout` is
+    /// `Layout::Undefined`, the written subresources are transitioned
+    /// into `Layout::General`, discarding the previous contents.
+    ///
+    /// Requires `Features::HOST_IMAGE_COPY`.
+    ///
+    /// # Safety
+    /// - `image` must be created with `image::Usage::HOST_TRANSFER` and bound to memory.
+    /// - `image` must not be used by the device during the copy.
+    /// - `layout` must be either `Layout::Undefined`, or the current layout of the written
+    ///   subresources, which can only be `Layout::General` or `Layout::TransferDstOptimal`.
+    /// - `data` must contain all of the regions.
+    unsafe fn copy_memory_to_image<I>(
+        &self,
+        data: &[u8],
+        image: &B::Image,
+        layout: image::Layout,
+        regions: I,
+    ) -> Result<(), MapError>
+    where
+        I: Iterator<Item = command::BufferImageCopy>;
+
+    /// Copy the contents of an image into the host memory, without a staging buffer
+    /// and a command buffer.
+    ///
+    /// The regions are interpreted as in `CommandBuffer::copy_image_to_buffer`,
+    /// with `buffer_offset` being the offset into `data`.
+    ///
+    /// Requires `Features::HOST_IMAGE_COPY`.
+    ///
+    /// # Safety
+    /// - `image` must be created with `image::Usage::HOST_TRANSFER` and bound to memory.
+    /// - The device writes to `image` must be finished, as observed by a fence wait.
+    /// - `layout` must be the current layout of the read subresources, which can only be
+    ///   `Layout::General` or `Layout::TransferSrcOptimal`.
+    /// - `data` must contain all of the regions.
+    unsafe fn copy_image_to_memory<I>(
+        &self,
+        image: &B::Image,
+        layout: image::Layout,
+        data: &mut [u8],
+        regions: I,
+    ) -> Result<(), MapError>
+    where
+        I: Iterator<Item = command::BufferImageCopy>;
+
     /// Create a new semaphore object.
     fn create_semaphore(&self) -> Result<B::Semaphore, OutOfMemory>;
 
@@ -677,7 +1087,18 @@ pub trait Device<B: Backend>: fmt::Debug + Any + Send + Sync {
     /// Wait for all queues associated with this device to idle.
     ///
     /// Host access to all queues needs to be **externally** sycnhronized!
-    fn wait_idle(&self) -> Result<(), OutOfMemory>;
+    fn wait_idle(&self) -> Result<(), WaitError>;
+
+    /// Get the diagnostics of the device loss, after [`DeviceLost`] has been reported.
+    ///
+    /// Returns `None` if the device isn't lost, or the backend can't tell anything about it.
+    /// The diagnostics come from DRED on DX12, `VK_EXT_device_fault` on Vulkan,
+    /// and the errors of the failed command buffers on Metal. The checkpoints
+    /// are only reported by the backends that support them, see
+    /// [`CommandBuffer::set_checkpoint`][crate::command::CommandBuffer::set_checkpoint].
+    fn get_device_lost_reason(&self) -> Option<DeviceLostReason> {
+        None
+    }
 
     /// Associate a name with an image, for easier debugging in external tools or with validation
     /// layers that can print a friendly name when referring to objects in error messages
@@ -24,6 +24,12 @@ bitflags!(
         const DEPTH = 0x2;
         /// Stencil aspect.
         const STENCIL = 0x4;
+        /// First plane of a multi-planar format.
+        const PLANE_0 = 0x10;
+        /// Second plane of a multi-planar format.
+        const PLANE_1 = 0x20;
+        /// Third plane of a multi-planar format.
+        const PLANE_2 = 0x40;
     }
 );
 
@@ -329,6 +335,9 @@ surface_types! {
     ASTC_10x10          { 128, COLOR, (10, 10) },
     ASTC_12x10          { 128, COLOR, (12, 10) },
     ASTC_12x12          { 128, COLOR, (12, 12) },
+    // Multi-planar 4:2:0 formats, described per 2x2 block of pixels
+    NV12                {  48, COLOR, (2, 2) },
+    P010                {  96, COLOR, (2, 2) },
 }
 
 /// Generic run-time base format.
@@ -588,6 +597,12 @@ formats! {
     Astc12x10Srgb = (ASTC_12x10, Srgb),
     Astc12x12Unorm = (ASTC_12x12, Unorm),
     Astc12x12Srgb = (ASTC_12x12, Srgb),
+    // Multi-planar formats are not in the Vulkan core range and have to be mapped explicitly.
+    // Two-plane 4:2:0 Y'CbCr, with Y' in the first plane and interleaved CbCr in the second one:
+    // 8 bits per value
+    Nv12Unorm = (NV12, Unorm),
+    // 10 bits per value, stored in the high bits of 16-bit words
+    P010Unorm = (P010, Unorm),
 }
 
 impl Format {
@@ -618,6 +633,112 @@ impl Format {
     pub fn is_stencil(self) -> bool {
         self.surface_desc().aspects.contains(Aspects::STENCIL)
     }
+
+    /// Returns the number of planes of a multi-planar format, or `1` for other formats.
+    pub fn plane_count(self) -> usize {
+        match self {
+            Format::Nv12Unorm | Format::P010Unorm => 2,
+            _ => 1,
+        }
+    }
+
+    /// Returns the format of a single plane of a multi-planar format, together
+    /// with the horizontal and vertical subsampling factors of the plane.
+    ///
+    /// This is the format to use when viewing or copying the plane with
+    /// the corresponding `Aspects::PLANE_*` aspect.
+    /// Returns `None` if the format doesn't have the given plane.
+    pub fn plane_format(self, plane: usize) -> Option<(Format, (u8, u8))> {
+        match (self, plane) {
+            (Format::Nv12Unorm, 0) => Some((Format::R8Unorm, (1, 1))),
+            (Format::Nv12Unorm, 1) => Some((Format::Rg8Unorm, (2, 2))),
+            (Format::P010Unorm, 0) => Some((Format::R16Unorm, (1, 1))),
+            (Format::P010Unorm, 1) => Some((Format::Rg16Unorm, (2, 2))),
+            _ => None,
+        }
+    }
+
+    /// Packs a floating-point clear color into a single texel of a packed
+    /// unsigned float format.
+    ///
+    /// This is used by the backends to clear images of these formats with
+    /// a copy, when the format is not renderable on the device.
+    /// Negative and NaN channels are cleared to zero, and the alpha channel is ignored.
+    /// Returns `None` for formats other than `B10g11r11Ufloat` and `E5b9g9r9Ufloat`.
+    pub fn pack_ufloat_color(self, color: [f32; 4]) -> Option<u32> {
+        match self {
+            Format::B10g11r11Ufloat => Some(
+                pack_ufloat(color[0], 6)
+                    | pack_ufloat(color[1], 6) << 11
+                    | pack_ufloat(color[2], 5) << 22,
+            ),
+            Format::E5b9g9r9Ufloat => Some(pack_shared_exponent(color)),
+            _ => None,
+        }
+    }
+}
+
+/// Converts a value into an unsigned float with a 5-bit exponent.
+fn pack_ufloat(value: f32, mantissa_bits: u32) -> u32 {
+    const MAX_EXPONENT: i32 = 0x1F;
+    if !(value > 0.0) {
+        return 0;
+    }
+    if value.is_infinite() {
+        return (MAX_EXPONENT as u32) << mantissa_bits;
+    }
+    let bits = value.to_bits();
+    let exponent = ((bits >> 23) & 0xFF) as i32 - 127 + 15;
+    let mantissa = bits & 0x7F_FFFF;
+    if exponent >= MAX_EXPONENT {
+        // clamp to the largest finite value
+        ((MAX_EXPONENT as u32 - 1) << mantissa_bits) | ((1 << mantissa_bits) - 1)
+    } else if exponent <= 0 {
+        let shift = (23 - mantissa_bits as i32 + 1 - exponent) as u32;
+        if shift >= 32 {
+            0
+        } else {
+            (mantissa | 0x80_0000) >> shift
+        }
+    } else {
+        (exponent as u32) << mantissa_bits | mantissa >> (23 - mantissa_bits)
+    }
+}
+
+/// Converts a color into the `E5B9G9R9` shared exponent representation,
+/// following the `EXT_texture_shared_exponent` specification.
+fn pack_shared_exponent(color: [f32; 4]) -> u32 {
+    const MANTISSA_BITS: i32 = 9;
+    const EXPONENT_BIAS: i32 = 15;
+    const MAX_VALUE: f32 = 65408.0; // (2^9 - 1) / 2^9 * 2^(31 - 15)
+
+    let clamp = |value: f32| {
+        if value > 0.0 {
+            value.min(MAX_VALUE)
+        } else {
+            0.0
+        }
+    };
+    let rgb = [clamp(color[0]), clamp(color[1]), clamp(color[2])];
+    let max = rgb[0].max(rgb[1]).max(rgb[2]);
+
+    // The float math is done on the bits, so that it doesn't require `std`.
+    // The exponent field gives `floor(log2(max))` for the normal values,
+    // while the others are below the smallest shared exponent anyway.
+    let max_exponent = ((max.to_bits() >> 23) & 0xFF) as i32 - 127;
+    let mut exponent = (-EXPONENT_BIAS - 1).max(max_exponent) + 1 + EXPONENT_BIAS;
+    let scale = |exponent: i32| {
+        f32::from_bits(((exponent - EXPONENT_BIAS - MANTISSA_BITS + 127) as u32) << 23)
+    };
+    // the values are non-negative, so rounding is truncating
+    if (max / scale(exponent) + 0.5) as u32 == 1 << MANTISSA_BITS {
+        exponent += 1;
+    }
+
+    let divisor = scale(exponent);
+    let [r, g, b] = rgb;
+    let quantize = |value: f32| (value / divisor + 0.5) as u32;
+    quantize(r) | quantize(g) << 9 | quantize(b) << 18 | (exponent as u32) << 27
 }
 
 // Common vertex attribute formats
@@ -2,12 +2,14 @@
 //!
 //! An image is a block of GPU memory representing a grid of texels.
 
+#[cfg(feature = "std")]
+use crate::device;
 use crate::{
     buffer::Offset as RawOffset,
-    device, format,
+    format,
     pso::{Comparison, Rect},
 };
-use std::{f32, hash, ops::Range};
+use core::{f32, hash, ops::Range};
 
 /// Dimension size.
 pub type Size = u32;
@@ -102,7 +104,57 @@ pub enum Tiling {
     Linear = 1,
 }
 
+/// A DRM format modifier, as defined by the Linux `drm_fourcc.h`.
+///
+/// Describes a vendor-specific memory layout of an image, which allows
+/// sharing it with other processes and APIs.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DrmModifier(pub u64);
+
+impl DrmModifier {
+    /// Row-major layout, without any tiling or compression.
+    pub const LINEAR: Self = DrmModifier(0);
+}
+
+/// Properties of a DRM format modifier supported for a format.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct DrmModifierProperties {
+    /// The modifier itself.
+    pub modifier: DrmModifier,
+    /// Number of memory planes of an image using this modifier.
+    pub plane_count: u32,
+    /// Image features supported with this modifier.
+    pub features: format::ImageFeature,
+}
+
+/// Layout of a single memory plane of an image with a DRM format modifier.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DrmPlaneLayout {
+    /// Offset of the plane from the start of the bound memory.
+    pub offset: RawOffset,
+    /// Byte distance between rows.
+    pub row_pitch: RawOffset,
+}
+
+/// Selection of the DRM format modifier of a new image.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum DrmTiling<'a> {
+    /// Let the implementation pick one of the listed modifiers.
+    List(&'a [DrmModifier]),
+    /// Use the given modifier and plane layout, typically when importing
+    /// an image allocated by another process.
+    Explicit {
+        /// Modifier of the image.
+        modifier: DrmModifier,
+        /// Layout of each memory plane.
+        planes: &'a [DrmPlaneLayout],
+    },
+}
+
 /// Pure image object creation error.
+#[cfg(feature = "std")]
 #[derive(Clone, Debug, PartialEq, thiserror::Error)]
 pub enum CreationError {
     /// Out of either host or device memory.
@@ -126,9 +178,13 @@ pub enum CreationError {
     /// The mentioned usage mode is not supported
     #[error("Unsupported usage: {0:?}")]
     Usage(Usage),
+    /// None of the requested DRM format modifiers is supported.
+    #[error("Unsupported DRM format modifier")]
+    DrmModifier,
 }
 
 /// Error creating an `ImageView`.
+#[cfg(feature = "std")]
 #[derive(Clone, Debug, PartialEq, thiserror::Error)]
 pub enum ViewCreationError {
     /// Out of either Host or Device memory
@@ -155,6 +211,7 @@ pub enum ViewCreationError {
 }
 
 /// An error associated with selected image layer.
+#[cfg(feature = "std")]
 #[derive(Clone, Debug, PartialEq, thiserror::Error)]
 pub enum LayerError {
     /// The source image kind doesn't support array slices.
@@ -244,7 +301,7 @@ impl Kind {
 
     /// Get the extent of a particular mipmap level.
     pub fn level_extent(&self, level: Level) -> Extent {
-        use std::cmp::{max, min};
+        use core::cmp::{max, min};
         // must be at least 1
         let map = |val| max(min(val, 1), val >> min(level, MAX_LEVEL));
         match *self {
@@ -268,7 +325,7 @@ impl Kind {
 
     /// Count the number of mipmap levels.
     pub fn compute_num_levels(&self) -> Level {
-        use std::cmp::max;
+        use core::cmp::max;
         match *self {
             Kind::D2(_, _, _, s) if s > 1 => {
                 // anti-aliased images can't have mipmaps
@@ -336,6 +393,13 @@ bitflags!(
         const KIND_CUBE      = 0x0000_0010;
         /// Support creation of `D2Array` kind of view.
         const KIND_2D_ARRAY  = 0x0000_0020;
+        /// The image can share memory with other images created with the same parameters,
+        /// and see their contents. Images created without it may alias each other's memory,
+        /// but their contents are undefined after an `Aliasing` barrier.
+        const ALIAS          = 0x0000_0400;
+        /// The image is protected, and must be bound to protected memory.
+        /// Requires `Features::PROTECTED_MEMORY`.
+        const PROTECTED      = 0x0000_0800;
     }
 );
 
@@ -352,6 +416,8 @@ bitflags!(
         /// The image is a [sampled image](https://www.khronos.org/registry/vulkan/specs/1.0/html/vkspec.html#descriptorsets-sampledimage)
         const SAMPLED = 0x4;
         /// The image is a [storage image](https://www.khronos.org/registry/vulkan/specs/1.0/html/vkspec.html#descriptorsets-storageimage)
+        ///
+        /// Multisampled storage images require `Features::SHADER_STORAGE_IMAGE_MULTISAMPLE`.
         const STORAGE = 0x8;
         /// The image is used as a color attachment -- that is, color input to a rendering pass.
         const COLOR_ATTACHMENT = 0x10;
@@ -361,6 +427,11 @@ bitflags!(
         const TRANSIENT_ATTACHMENT = 0x40;
         ///
         const INPUT_ATTACHMENT = 0x80;
+        /// The image can be used as a shading rate attachment.
+        const SHADING_RATE = 0x100;
+        /// The image can be copied to and from the host memory by the device methods.
+        /// Requires `Features::HOST_IMAGE_COPY`.
+        const HOST_TRANSFER = 0x40_0000;
 
     }
 );
@@ -517,6 +588,65 @@ impl SamplerDesc {
     }
 }
 
+/// Color model used to convert the Y'CbCr encoded values into RGB.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum YcbcrModel {
+    /// Values are passed through, without range expansion.
+    RgbIdentity,
+    /// Values are passed through, with range expansion only.
+    YcbcrIdentity,
+    /// BT.709 color model.
+    Ycbcr709,
+    /// BT.601 color model.
+    Ycbcr601,
+    /// BT.2020 color model.
+    Ycbcr2020,
+}
+
+/// Numerical range of the encoded Y'CbCr values.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum YcbcrRange {
+    /// The full range of the encoding bits is used.
+    Full,
+    /// Headroom and footroom are reserved, as done by the ITU "narrow range" encoding.
+    Narrow,
+}
+
+/// Location of the downsampled chroma samples relative to the luma samples.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ChromaLocation {
+    /// Chroma samples are aligned with the even luma samples.
+    CositedEven,
+    /// Chroma samples are located half way between the luma samples.
+    Midpoint,
+}
+
+/// Description of a sampler Y'CbCr conversion, which is applied when sampling
+/// from a multi-planar image, such as a decoded video frame.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SamplerYcbcrConversionDesc {
+    /// Format of the images sampled with this conversion.
+    pub format: format::Format,
+    /// Color model of the conversion.
+    pub model: YcbcrModel,
+    /// Numerical range of the encoded values.
+    pub range: YcbcrRange,
+    /// Swizzle applied to the encoded values before the conversion.
+    pub swizzle: format::Swizzle,
+    /// Horizontal location of the chroma samples.
+    pub x_chroma_offset: ChromaLocation,
+    /// Vertical location of the chroma samples.
+    pub y_chroma_offset: ChromaLocation,
+    /// Filter used for reconstructing the chroma values.
+    pub chroma_filter: Filter,
+    /// Forces the chroma reconstruction to be done explicitly, per sample.
+    pub force_explicit_reconstruction: bool,
+}
+
 /// Specifies options for how memory for an image is arranged.
 /// These are hints to the GPU driver and may or may not have actual
 /// performance effects, but describe constraints on how the data
@@ -537,6 +667,34 @@ pub enum Layout {
     /// Must only be used as a depth attachment in a framebuffer,
     /// or as a read-only depth or stencil buffer in a shader.
     DepthStencilReadOnlyOptimal,
+    /// Depth aspect is read-only, stencil aspect can be used as an attachment.
+    ///
+    /// Requires `Features::SEPARATE_DEPTH_STENCIL_LAYOUTS`.
+    DepthReadOnlyStencilAttachmentOptimal,
+    /// Depth aspect can be used as an attachment, stencil aspect is read-only.
+    ///
+    /// Requires `Features::SEPARATE_DEPTH_STENCIL_LAYOUTS`.
+    DepthAttachmentStencilReadOnlyOptimal,
+    /// Layout of the depth aspect alone, used as an attachment.
+    ///
+    /// Only valid for depth-only formats, or in barriers affecting just the depth aspect.
+    /// Requires `Features::SEPARATE_DEPTH_STENCIL_LAYOUTS`.
+    DepthAttachmentOptimal,
+    /// Layout of the depth aspect alone, read-only.
+    ///
+    /// Only valid for depth-only formats, or in barriers affecting just the depth aspect.
+    /// Requires `Features::SEPARATE_DEPTH_STENCIL_LAYOUTS`.
+    DepthReadOnlyOptimal,
+    /// Layout of the stencil aspect alone, used as an attachment.
+    ///
+    /// Only valid for stencil-only formats, or in barriers affecting just the stencil aspect.
+    /// Requires `Features::SEPARATE_DEPTH_STENCIL_LAYOUTS`.
+    StencilAttachmentOptimal,
+    /// Layout of the stencil aspect alone, read-only.
+    ///
+    /// Only valid for stencil-only formats, or in barriers affecting just the stencil aspect.
+    /// Requires `Features::SEPARATE_DEPTH_STENCIL_LAYOUTS`.
+    StencilReadOnlyOptimal,
     /// Must only be used as a read-only image in a shader.
     ShaderReadOnlyOptimal,
     /// Must only be used as the source for a transfer command.
@@ -594,6 +752,8 @@ bitflags!(
         const MEMORY_READ = 0x8000;
         /// Write access for memory to be accessed by a non-specific entity.
         const MEMORY_WRITE = 0x10000;
+        /// Read access to a shading rate attachment during rasterization.
+        const SHADING_RATE_READ = 0x80_0000;
     }
 );
 
@@ -604,7 +764,7 @@ pub type State = (Access, Layout);
 #[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Subresource {
-    /// Included aspects: color/depth/stencil
+    /// Included aspects: color/depth/stencil, or a single plane of a multi-planar format
     pub aspects: format::Aspects,
     /// Selected mipmap level
     pub level: Level,
@@ -616,7 +776,7 @@ pub struct Subresource {
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct SubresourceLayers {
-    /// Included aspects: color/depth/stencil
+    /// Included aspects: color/depth/stencil, or a single plane of a multi-planar format
     pub aspects: format::Aspects,
     /// Selected mipmap level
     pub level: Level,
@@ -628,7 +788,7 @@ pub struct SubresourceLayers {
 #[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct SubresourceRange {
-    /// Included aspects: color/depth/stencil
+    /// Included aspects: color/depth/stencil, or a single plane of a multi-planar format
     pub aspects: format::Aspects,
     /// First mipmap level in this subresource
     pub level_start: Level,
@@ -701,6 +861,9 @@ pub struct SubresourceFootprint {
 }
 
 /// Description of a framebuffer attachment.
+///
+/// The image views used with the framebuffer must be created from images
+/// with the same usage and view capabilities, and have the same format.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct FramebufferAttachment {
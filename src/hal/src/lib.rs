@@ -1,3 +1,4 @@
+#![cfg_attr(not(feature = "std"), no_std)]
 #![warn(
     trivial_casts,
     trivial_numeric_casts,
@@ -38,31 +39,51 @@
 //! [`PhysicalDevice`][adapter::PhysicalDevice] to get a [logical device
 //! handle][device::Device], from which you can manage all the other device-specific
 //! resources.
+//!
+//! # Features
+//!
+//! The `std` feature is enabled by default. Without it, the crate is `no_std`
+//! and only contains the data types, such as the [formats][format], the
+//! [pipeline states][pso], the [`Limits`] and the [`Features`], which lets asset
+//! pipelines and shader tools share them without depending on the backend interface.
 
 #[macro_use]
 extern crate bitflags;
+#[cfg(not(feature = "std"))]
+extern crate alloc;
 
 #[cfg(feature = "serde")]
 #[macro_use]
 extern crate serde;
 
+#[cfg(feature = "std")]
 use std::{any::Any, fmt, hash::Hash};
 
+#[cfg(feature = "std")]
 pub mod adapter;
 pub mod buffer;
+#[cfg(feature = "std")]
 pub mod command;
+#[cfg(feature = "std")]
 pub mod device;
 pub mod format;
 pub mod image;
+#[cfg(feature = "std")]
 pub mod memory;
+#[cfg(feature = "std")]
 pub mod pass;
+#[cfg(feature = "std")]
 pub mod pool;
 pub mod pso;
+#[cfg(feature = "std")]
 pub mod query;
+#[cfg(feature = "std")]
 pub mod queue;
+#[cfg(feature = "std")]
 pub mod window;
 
 /// Prelude module re-exports all the traits necessary to use `gfx-hal`.
+#[cfg(feature = "std")]
 pub mod prelude {
     pub use crate::{
         adapter::PhysicalDevice,
@@ -178,7 +199,8 @@ bitflags! {
         const SHADER_IMAGE_GATHER_EXTENDED = 0x0000_0000_1000_0000;
         ///
         const SHADER_STORAGE_IMAGE_EXTENDED_FORMATS = 0x0000_0000_2000_0000;
-        ///
+        /// Supports `image::Usage::STORAGE` on multisampled images, with the sample
+        /// counts of `Limits::storage_image_sample_counts`.
         const SHADER_STORAGE_IMAGE_MULTISAMPLE = 0x0000_0000_4000_0000;
         ///
         const SHADER_STORAGE_IMAGE_READ_WITHOUT_FORMAT = 0x0000_0000_8000_0000;
@@ -240,6 +262,9 @@ bitflags! {
         const UNSIZED_DESCRIPTOR_ARRAY = 0x0800_0000_0000_0000;
         /// Enable draw_indirect_count and draw_indexed_indirect_count
         const DRAW_INDIRECT_COUNT = 0x1000_0000_0000_0000;
+        /// Support rendering to multiple views of the attachments within a single
+        /// subpass, driven by `SubpassDesc::view_mask`.
+        const MULTIVIEW = 0x2000_0000_0000_0000;
 
         /// Support triangle fan primitive topology.
         const TRIANGLE_FAN = 0x0001 << 64;
@@ -263,6 +288,79 @@ bitflags! {
         const TASK_SHADER = 0x0001 << 96;
         /// Supports mesh shader stage.
         const MESH_SHADER = 0x0002 << 96;
+        /// Supports `DescriptorType::InlineUniformBlock` bindings in descriptor sets.
+        const INLINE_UNIFORM_BLOCK = 0x0004 << 96;
+        /// Supports setting the fragment shading rate with `CommandBuffer::set_shading_rate`.
+        const SHADING_RATE = 0x0008 << 96;
+        /// Supports the shading rate attachments in `SubpassDesc::shading_rate`.
+        const SHADING_RATE_ATTACHMENT = 0x0010 << 96;
+        /// Supports sampler Y'CbCr conversion objects and multi-planar formats.
+        const SAMPLER_YCBCR_CONVERSION = 0x0020 << 96;
+        /// Supports creating images with DRM format modifiers.
+        const DRM_FORMAT_MODIFIER = 0x0040 << 96;
+        /// Supports importing and exporting memory as Linux dma-buf file descriptors.
+        const EXTERNAL_MEMORY_DMA_BUF = 0x0080 << 96;
+        /// Supports application control over exclusive full screen of swapchains.
+        const FULL_SCREEN_EXCLUSIVE = 0x0100 << 96;
+        /// Supports image layouts that treat the depth and stencil aspects separately.
+        const SEPARATE_DEPTH_STENCIL_LAYOUTS = 0x0200 << 96;
+        /// Supports querying the display refresh duration and the timing of past presents.
+        const PRESENT_TIMING = 0x0400 << 96;
+        /// Supports protected memory, command buffers, queue submissions and swapchains,
+        /// for content that must not be read back by the host or unprotected commands.
+        const PROTECTED_MEMORY = 0x0800 << 96;
+        /// Supports clamping the level of detail of image views
+        /// with [`Device::create_image_view_with_min_lod`][device::Device::create_image_view_with_min_lod].
+        const IMAGE_VIEW_MIN_LOD = 0x1000 << 96;
+        /// Supports `PipelineCreationFlags::FAIL_ON_PIPELINE_COMPILE_REQUIRED`.
+        const PIPELINE_CREATION_CACHE_CONTROL = 0x2000 << 96;
+        /// Supports programmable sample locations, enabled with `Multisampling::sample_locations`
+        /// and set by [`CommandBuffer::set_sample_locations`][command::CommandBuffer::set_sample_locations].
+        const SAMPLE_LOCATIONS = 0x4000 << 96;
+        /// Supports rendering without render pass and framebuffer objects with
+        /// [`CommandBuffer::begin_rendering`][command::CommandBuffer::begin_rendering].
+        const DYNAMIC_RENDERING = 0x8000 << 96;
+        /// Supports copying between the host memory and the images created with
+        /// `image::Usage::HOST_TRANSFER`, with
+        /// [`Device::copy_memory_to_image`][device::Device::copy_memory_to_image] and
+        /// [`Device::copy_image_to_memory`][device::Device::copy_image_to_memory].
+        const HOST_IMAGE_COPY = 0x0001_0000 << 96;
+        /// Supports the cooperative matrix (tensor core) operations in the shaders,
+        /// with the configurations listed by
+        /// [`PhysicalDevice::cooperative_matrix_properties`][adapter::PhysicalDevice::cooperative_matrix_properties].
+        const COOPERATIVE_MATRIX = 0x0002_0000 << 96;
+        /// Supports switching the present mode of a configured swapchain with
+        /// [`PresentationSurface::set_present_mode`][window::PresentationSurface::set_present_mode].
+        const PRESENT_MODE_SWITCH = 0x0004_0000 << 96;
+        /// Supports viewport depth ranges and depth clear values outside of [0, 1].
+        const DEPTH_RANGE_UNRESTRICTED = 0x0008_0000 << 96;
+        /// Supports setting the HDR metadata of the presented content with
+        /// [`PresentationSurface::set_hdr_metadata`][window::PresentationSurface::set_hdr_metadata].
+        const HDR_METADATA = 0x0010_0000 << 96;
+        /// Supports the advanced blend operations of [`pso::BlendOp::Advanced`],
+        /// with the overlapping primitives blended coherently in order.
+        const BLEND_OPERATION_ADVANCED = 0x0020_0000 << 96;
+        /// Supports `pso::LineRasterizationMode::Rectangular`.
+        const LINE_RASTERIZATION_RECTANGULAR = 0x0040_0000 << 96;
+        /// Supports `pso::LineRasterizationMode::Bresenham`.
+        const LINE_RASTERIZATION_BRESENHAM = 0x0080_0000 << 96;
+        /// Supports `pso::LineRasterizationMode::Smooth`.
+        const LINE_RASTERIZATION_SMOOTH = 0x0100_0000 << 96;
+        /// Supports `pso::LineRasterization::stipple` with all the line rasterization
+        /// modes supported by the device.
+        const LINE_STIPPLE = 0x0200_0000 << 96;
+        /// Supports controlling the depth clipping separately from the depth clamping
+        /// with `pso::Rasterizer::depth_clip_enable`.
+        const DEPTH_CLIP_ENABLE = 0x0400_0000 << 96;
+        /// Supports `pso::ConservativeRasterization::Overestimate`.
+        const CONSERVATIVE_RASTERIZATION = 0x0800_0000 << 96;
+        /// Supports `pso::ConservativeRasterization::Underestimate`.
+        const CONSERVATIVE_RASTERIZATION_UNDERESTIMATE = 0x1000_0000 << 96;
+        /// Supports the `FragmentShaderPixelInterlockEXT` capability in the shaders, ordering
+        /// the critical sections of the fragment invocations covering the same pixel.
+        const FRAGMENT_SHADER_INTERLOCK = 0x2000_0000 << 96;
+        /// Support for writing `pso::Descriptor::Null` into descriptor sets.
+        const NULL_DESCRIPTOR = 0x4000_0000 << 96;
     }
 }
 
@@ -335,7 +433,11 @@ pub struct Limits {
     pub max_uniform_buffer_range: buffer::Offset,
     ///
     pub max_storage_buffer_range: buffer::Offset,
+    /// Maximum size of the push constants, in bytes.
     ///
+    /// All the backends expose 256 bytes, backing the push constants by a buffer
+    /// when they don't fit the native storage, except for the Vulkan devices with
+    /// a lower native limit, which is at least 128 bytes.
     pub max_push_constants_size: usize,
     ///
     pub max_memory_allocation_count: usize,
@@ -376,6 +478,10 @@ pub struct Limits {
     pub max_descriptor_set_storage_images: usize,
     ///
     pub max_descriptor_set_input_attachments: usize,
+    /// Maximum size in bytes of an inline uniform block binding.
+    pub max_inline_uniform_block_size: usize,
+    /// Maximum number of inline uniform block bindings in a pipeline layout.
+    pub max_descriptor_set_inline_uniform_blocks: usize,
 
     /// Maximum number of vertex input attributes that can be specified for a graphics pipeline.
     pub max_vertex_input_attributes: usize,
@@ -451,12 +557,40 @@ pub struct Limits {
     pub framebuffer_depth_sample_counts: image::NumSamples,
     /// Number of samples supported for stencil attachments of framebuffers.
     pub framebuffer_stencil_sample_counts: image::NumSamples,
+    /// Mask of the sample counts supported by the images with `image::Usage::STORAGE`,
+    /// which only includes 1 without `Features::SHADER_STORAGE_IMAGE_MULTISAMPLE`.
+    pub storage_image_sample_counts: image::NumSamples,
     /// Timestamp queries are supported on all compute and graphics queues.
     pub timestamp_compute_and_graphics: bool,
     /// Maximum number of color attachments that can be used by a subpass in a render pass.
     pub max_color_attachments: usize,
-    ///
+    /// Maximum number of color attachments that can be used by a subpass
+    /// with [`pso::BlendOp::Advanced`] blending.
+    pub max_advanced_blend_color_attachments: usize,
+    /// Maximum number of views in a multiview subpass, i.e. the highest bit
+    /// that can be set in `SubpassDesc::view_mask` plus one.
+    pub max_multiview_view_count: u32,
+    /// Maximum instance index of a draw recorded in a multiview subpass.
+    pub max_multiview_instance_index: u32,
+    /// Size of the framebuffer area controlled by a single texel of a shading rate attachment.
+    pub shading_rate_attachment_texel_size: [image::Size; 2],
+    /// The coarsest fragment shading rate supported.
+    pub max_shading_rate: pso::ShadingRate,
+    /// If `true`, the multisampled images are rasterized with the sample locations
+    /// returned by [`pso::standard_sample_locations`].
     pub standard_sample_locations: bool,
+    /// Mask of the sample counts supporting programmable sample locations.
+    pub sample_location_sample_counts: image::NumSamples,
+    /// Number of bits of sub-pixel precision for the programmable sample locations.
+    pub sample_location_sub_pixel_bits: u32,
+    /// Size in pixels by which the conservatively overestimated primitives are
+    /// implicitly extended, to account for the rasterization precision.
+    pub primitive_overestimation_size: f32,
+    /// Maximum extra size in pixels of `pso::ConservativeRasterization::Overestimate`.
+    pub max_extra_primitive_overestimation_size: f32,
+    /// Protected queue operations that break the protected memory rules only leave
+    /// undefined contents in the written memory, instead of possibly losing the device.
+    pub protected_no_fault: bool,
     /// The alignment of the start of the buffer used as a GPU copy source, in bytes, non-zero.
     pub optimal_buffer_copy_offset_alignment: buffer::Offset,
     /// The alignment of the row pitch of the texture data stored in a buffer that is
@@ -464,6 +598,12 @@ pub struct Limits {
     pub optimal_buffer_copy_pitch_alignment: buffer::Offset,
     /// Size and alignment in bytes that bounds concurrent access to host-mapped device memory.
     pub non_coherent_atom_size: usize,
+    /// Total size in bytes of the memory heaps that have a memory type both
+    /// `DEVICE_LOCAL` and `CPU_VISIBLE`, or 0 if there are none.
+    ///
+    /// This memory, like the resizable BAR of discrete GPUs or the unified memory
+    /// of integrated ones, can be written by the host without a staging copy.
+    pub device_local_host_visible_heap_size: u64,
 
     /// The alignment of the vertex buffer stride.
     pub min_vertex_input_binding_stride_alignment: buffer::Offset,
@@ -520,6 +660,7 @@ pub enum IndexType {
 
 /// Error creating an instance of a backend on the platform that
 /// doesn't support this backend.
+#[cfg(feature = "std")]
 #[derive(Copy, Clone, Debug, Eq, PartialEq, thiserror::Error)]
 #[error("Backend is not supported on this platform")]
 pub struct UnsupportedBackend;
@@ -547,6 +688,7 @@ pub struct UnsupportedBackend;
 ///     println!("Adapter {}: {:?}", idx, adapter.info);
 /// }
 /// ```
+#[cfg(feature = "std")]
 pub trait Instance<B: Backend>: Any + Send + Sync + Sized {
     /// Create a new instance.
     ///
@@ -579,6 +721,25 @@ pub trait Instance<B: Backend>: Any + Send + Sync + Sized {
         raw_window_handle: &impl raw_window_handle::HasRawWindowHandle,
     ) -> Result<B::Surface, window::InitError>;
 
+    /// Create a new [surface][window::Surface] on a window of the given display connection.
+    ///
+    /// Unlike `create_surface`, this honors the display connection even when it's not
+    /// the default one of the process, e.g. with several Wayland or XCB connections.
+    ///
+    /// # Safety
+    ///
+    /// This method can cause undefined behavior if `raw_window_handle` isn't
+    /// a handle to a valid window of the `display` connection.
+    unsafe fn create_surface_with_display(
+        &self,
+        display: window::DisplayHandle,
+        raw_window_handle: &impl raw_window_handle::HasRawWindowHandle,
+    ) -> Result<B::Surface, window::InitError> {
+        let window =
+            window::RawWindow::with_display(raw_window_handle.raw_window_handle(), display)?;
+        self.create_surface(&window)
+    }
+
     /// Destroy a surface, freeing the resources associated with it and
     /// releasing it from this graphics API.
     ///
@@ -598,8 +759,10 @@ impl From<usize> for MemoryTypeId {
     }
 }
 
+#[cfg(feature = "std")]
 struct PseudoVec<T>(Option<T>);
 
+#[cfg(feature = "std")]
 impl<T> Extend<T> for PseudoVec<T> {
     fn extend<I: IntoIterator<Item = T>>(&mut self, into_iter: I) {
         let mut iter = into_iter.into_iter();
@@ -612,6 +775,7 @@ impl<T> Extend<T> for PseudoVec<T> {
 ///
 /// Each backend module, such as OpenGL or Metal, will implement this trait
 /// with its own concrete types.
+#[cfg(feature = "std")]
 pub trait Backend: 'static + Sized + Eq + Clone + Hash + fmt::Debug + Any + Send + Sync {
     /// The corresponding [instance][Instance] type for this backend.
     type Instance: Instance<Self>;
@@ -651,6 +815,8 @@ pub trait Backend: 'static + Sized + Eq + Clone + Hash + fmt::Debug + Any + Send
     type ImageView: fmt::Debug + Any + Send + Sync;
     /// The corresponding sampler type for this backend.
     type Sampler: fmt::Debug + Any + Send + Sync;
+    /// The corresponding sampler Y'CbCr conversion type for this backend.
+    type SamplerYcbcrConversion: fmt::Debug + Any + Send + Sync;
 
     /// The corresponding compute pipeline type for this backend.
     type ComputePipeline: fmt::Debug + Any + Send + Sync;
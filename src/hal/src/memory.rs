@@ -1,7 +1,7 @@
 //! Types to describe the properties of memory allocated for graphics resources.
 
-use crate::{buffer, image, queue, Backend};
-use std::ops::Range;
+use crate::{buffer, command::CommandBuffer, image, pso::PipelineStage, queue, Backend};
+use std::{iter, ops::Range};
 
 bitflags!(
     /// Memory property flags.
@@ -26,6 +26,12 @@ bitflags!(
         /// Memory that may be lazily allocated as needed on the GPU
         /// and *must not* be visible to the CPU.
         const LAZILY_ALLOCATED = 0x10;
+
+        /// Protected memory, only accessible by protected command buffers
+        /// and *must not* be visible to the CPU.
+        ///
+        /// Requires `Features::PROTECTED_MEMORY`.
+        const PROTECTED = 0x20;
     }
 );
 
@@ -86,6 +92,24 @@ pub enum Barrier<'a, B: Backend> {
         /// Can be `None` to indicate no ownership transfer.
         families: Option<Range<queue::QueueFamilyId>>,
     },
+    /// A barrier between the uses of two resources bound to overlapping memory.
+    ///
+    /// The contents of the `after` resource are undefined past the barrier, and images
+    /// still need to be transitioned from `Layout::Undefined` by an `Image` barrier.
+    Aliasing {
+        /// The resource using the memory before the barrier, or any resource if `None`.
+        before: Option<AliasedResource<'a, B>>,
+        /// The resource using the memory after the barrier, or any resource if `None`.
+        after: Option<AliasedResource<'a, B>>,
+    },
+}
+
+/// A resource taking part in an aliasing barrier.
+#[allow(missing_docs)]
+#[derive(Clone, Debug)]
+pub enum AliasedResource<'a, B: Backend> {
+    Buffer(&'a B::Buffer),
+    Image(&'a B::Image),
 }
 
 impl<'a, B: Backend> Barrier<'a, B> {
@@ -100,6 +124,208 @@ impl<'a, B: Backend> Barrier<'a, B> {
     }
 }
 
+/// Error creating an [`OwnershipTransfer`].
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+pub enum OwnershipTransferError {
+    /// The source and destination families are the same.
+    #[error("Ownership transfer within the same queue family {0:?}")]
+    SameFamily(queue::QueueFamilyId),
+}
+
+#[derive(Debug)]
+enum TransferTarget<'a, B: Backend> {
+    Buffer {
+        states: Range<buffer::State>,
+        target: &'a B::Buffer,
+        range: buffer::SubRange,
+    },
+    Image {
+        states: Range<image::State>,
+        target: &'a B::Image,
+        range: image::SubresourceRange,
+    },
+}
+
+impl<'a, B: Backend> Clone for TransferTarget<'a, B> {
+    fn clone(&self) -> Self {
+        match *self {
+            TransferTarget::Buffer {
+                ref states,
+                target,
+                ref range,
+            } => TransferTarget::Buffer {
+                states: states.clone(),
+                target,
+                range: range.clone(),
+            },
+            TransferTarget::Image {
+                ref states,
+                target,
+                ref range,
+            } => TransferTarget::Image {
+                states: states.clone(),
+                target,
+                range: range.clone(),
+            },
+        }
+    }
+}
+
+/// A [queue family ownership transfer](https://www.khronos.org/registry/vulkan/specs/1.0/html/vkspec.html#synchronization-queue-transfers)
+/// of a resource between two queue families.
+///
+/// The transfer consists of two mirrored barriers: the release is recorded into a
+/// command buffer of the source family, and the acquire into a command buffer of
+/// the destination family. The submission containing the release has to complete
+/// before the acquire executes, typically by signaling a semaphore the destination
+/// queue waits on. Any layout transition of an image happens once, between the two.
+#[derive(Clone, Debug)]
+pub struct OwnershipTransfer<'a, B: Backend> {
+    resource: TransferTarget<'a, B>,
+    stages: Range<PipelineStage>,
+    families: Range<queue::QueueFamilyId>,
+}
+
+impl<'a, B: Backend> OwnershipTransfer<'a, B> {
+    fn new(
+        resource: TransferTarget<'a, B>,
+        stages: Range<PipelineStage>,
+        families: Range<queue::QueueFamilyId>,
+    ) -> Result<Self, OwnershipTransferError> {
+        if families.start == families.end {
+            return Err(OwnershipTransferError::SameFamily(families.start));
+        }
+        Ok(OwnershipTransfer {
+            resource,
+            stages,
+            families,
+        })
+    }
+
+    /// Create a transfer of a buffer range, accessed with `states.start` by `stages.start`
+    /// in the source family, and with `states.end` by `stages.end` in the destination family.
+    pub fn buffer(
+        target: &'a B::Buffer,
+        range: buffer::SubRange,
+        states: Range<buffer::State>,
+        stages: Range<PipelineStage>,
+        families: Range<queue::QueueFamilyId>,
+    ) -> Result<Self, OwnershipTransferError> {
+        Self::new(
+            TransferTarget::Buffer {
+                states,
+                target,
+                range,
+            },
+            stages,
+            families,
+        )
+    }
+
+    /// Create a transfer of an image subresource range, accessed with `states.start` by
+    /// `stages.start` in the source family, and with `states.end` by `stages.end` in the
+    /// destination family.
+    pub fn image(
+        target: &'a B::Image,
+        range: image::SubresourceRange,
+        states: Range<image::State>,
+        stages: Range<PipelineStage>,
+        families: Range<queue::QueueFamilyId>,
+    ) -> Result<Self, OwnershipTransferError> {
+        Self::new(
+            TransferTarget::Image {
+                states,
+                target,
+                range,
+            },
+            stages,
+            families,
+        )
+    }
+
+    /// Returns the source and destination queue families.
+    pub fn families(&self) -> Range<queue::QueueFamilyId> {
+        self.families.clone()
+    }
+
+    /// Returns the release barrier, making the accesses of the source family available.
+    pub fn release_barrier(&self) -> Barrier<'a, B> {
+        let families = Some(self.families.clone());
+        match self.resource {
+            TransferTarget::Buffer {
+                ref states,
+                target,
+                ref range,
+            } => Barrier::Buffer {
+                states: states.start..buffer::Access::empty(),
+                target,
+                range: range.clone(),
+                families,
+            },
+            TransferTarget::Image {
+                ref states,
+                target,
+                ref range,
+            } => Barrier::Image {
+                states: states.start..(image::Access::empty(), states.end.1),
+                target,
+                range: range.clone(),
+                families,
+            },
+        }
+    }
+
+    /// Returns the acquire barrier, making the resource visible to the destination family.
+    pub fn acquire_barrier(&self) -> Barrier<'a, B> {
+        let families = Some(self.families.clone());
+        match self.resource {
+            TransferTarget::Buffer {
+                ref states,
+                target,
+                ref range,
+            } => Barrier::Buffer {
+                states: buffer::Access::empty()..states.end,
+                target,
+                range: range.clone(),
+                families,
+            },
+            TransferTarget::Image {
+                ref states,
+                target,
+                ref range,
+            } => Barrier::Image {
+                states: (image::Access::empty(), states.start.1)..states.end,
+                target,
+                range: range.clone(),
+                families,
+            },
+        }
+    }
+
+    /// Record the release barrier into a command buffer of the source family.
+    pub unsafe fn release(&self, command_buffer: &mut B::CommandBuffer) {
+        command_buffer.pipeline_barrier(
+            self.stages.start..PipelineStage::BOTTOM_OF_PIPE,
+            Dependencies::empty(),
+            iter::once(self.release_barrier()),
+        );
+    }
+
+    /// Record the acquire barrier into a command buffer of the destination family.
+    pub unsafe fn acquire(&self, command_buffer: &mut B::CommandBuffer) {
+        command_buffer.pipeline_barrier(
+            PipelineStage::TOP_OF_PIPE..self.stages.end,
+            Dependencies::empty(),
+            iter::once(self.acquire_barrier()),
+        );
+    }
+}
+
+/// Hint for devices about the priority of a memory allocation, used to decide
+/// what to evict first under memory pressure. Values range from `0.0` (low) to `1.0` (high),
+/// with `0.5` being the priority of regular allocations.
+pub type Priority = f32;
+
 /// Memory requirements for a certain resource (buffer/image).
 #[derive(Clone, Copy, Debug)]
 pub struct Requirements {
@@ -109,6 +335,21 @@ pub struct Requirements {
     pub alignment: u64,
     /// Supported memory types.
     pub type_mask: u32,
+    /// The resource performs better when bound to a dedicated allocation,
+    /// made by `Device::allocate_dedicated_memory`.
+    pub prefers_dedicated: bool,
+    /// The resource can only be bound to a dedicated allocation,
+    /// made by `Device::allocate_dedicated_memory`.
+    pub requires_dedicated: bool,
+}
+
+/// Resource that a dedicated memory allocation is made for.
+#[derive(Clone, Debug)]
+pub enum DedicatedResource<'a, B: Backend> {
+    /// The allocation is dedicated to a buffer.
+    Buffer(&'a B::Buffer),
+    /// The allocation is dedicated to an image.
+    Image(&'a B::Image),
 }
 
 /// A linear segment within a memory block.
@@ -83,7 +83,7 @@ impl AttachmentOps {
 ///
 /// It includes things such as render targets, images that were produced from
 /// previous subpasses, etc.
-#[derive(Clone, Debug, Hash, PartialEq)]
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Attachment {
     /// Format of this attachment.
@@ -167,6 +167,22 @@ pub struct SubpassDesc<'a> {
     /// Attachments that are not used by the subpass but must be preserved to be
     /// passed on to subsequent passes.
     pub preserves: &'a [AttachmentId],
+    /// Bit mask of the views this subpass renders to, where bit `i` corresponds to
+    /// the array layer `i` of every attachment.
+    ///
+    /// Zero disables multiview rendering. Either all subpasses of a render pass
+    /// have a zero mask, or none of them.
+    ///
+    /// Requires the [`MULTIVIEW`](crate::Features::MULTIVIEW) feature if non-zero.
+    pub view_mask: u32,
+    /// Which attachment will be used as the shading rate image.
+    ///
+    /// Each texel of the attachment controls the shading rate of the framebuffer
+    /// region covered by `Limits::shading_rate_attachment_texel_size`.
+    /// The attachment has to be created with `image::Usage::SHADING_RATE`.
+    ///
+    /// Requires the [`SHADING_RATE_ATTACHMENT`](crate::Features::SHADING_RATE_ATTACHMENT) feature.
+    pub shading_rate: Option<&'a AttachmentRef>,
 }
 
 /// A sub-pass borrow of a pass.
@@ -13,6 +13,11 @@ bitflags!(
         const TRANSIENT = 0x1;
         /// Allow command buffers to be reset individually.
         const RESET_INDIVIDUAL = 0x2;
+        /// Allocate protected command buffers, which can access protected
+        /// resources and only be submitted with `Queue::submit_protected`.
+        ///
+        /// Requires `Features::PROTECTED_MEMORY`.
+        const PROTECTED = 0x4;
     }
 );
 
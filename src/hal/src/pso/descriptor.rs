@@ -16,10 +16,11 @@
 //! [`DescriptorSetWrite`]: struct.DescriptorSetWrite.html
 //! [`DescriptorSetCopy`]: struct.DescriptorSetWrite.html
 
-use crate::{
-    buffer::SubRange, device::OutOfMemory, image::Layout, pso::ShaderStageFlags, Backend, PseudoVec,
-};
+use crate::pso::ShaderStageFlags;
+#[cfg(feature = "std")]
+use crate::{buffer::SubRange, device::OutOfMemory, image::Layout, Backend, PseudoVec};
 
+#[cfg(feature = "std")]
 use std::{fmt, iter};
 
 ///
@@ -94,6 +95,14 @@ pub enum DescriptorType {
     },
     /// A descriptor associated with an input attachment.
     InputAttachment,
+    /// A block of uniform data stored directly in the descriptor set.
+    ///
+    /// For bindings of this type the `count` field is interpreted as the size
+    /// of the block in bytes, and has to be a multiple of 4.
+    ///
+    /// Requires the [`INLINE_UNIFORM_BLOCK`](crate::Features::INLINE_UNIFORM_BLOCK) feature.
+    /// The backends without it fail to create set layouts with these bindings.
+    InlineUniformBlock,
 }
 
 /// Information about the contents of and in which stages descriptors may be bound to a descriptor
@@ -133,10 +142,14 @@ pub struct DescriptorRangeDesc {
     /// Type of the stored descriptors.
     pub ty: DescriptorType,
     /// Amount of space.
+    ///
+    /// *Note*: for `DescriptorType::InlineUniformBlock` this is the total
+    /// number of bytes, and each range accounts for one inline block binding per set.
     pub count: usize,
 }
 
 /// An error allocating descriptor sets from a pool.
+#[cfg(feature = "std")]
 #[derive(Clone, Debug, PartialEq, thiserror::Error)]
 pub enum AllocationError {
     /// OutOfMemory::Host: Memory allocation on the host side failed.
@@ -157,6 +170,7 @@ pub enum AllocationError {
 }
 
 /// A descriptor pool is a collection of memory from which descriptor sets are allocated.
+#[cfg(feature = "std")]
 pub trait DescriptorPool<B: Backend>: Send + Sync + fmt::Debug {
     /// Allocate a descriptor set from the pool.
     ///
@@ -217,6 +231,7 @@ pub trait DescriptorPool<B: Backend>: Send + Sync + fmt::Debug {
 /// Writes the actual descriptors to be bound into a descriptor set.
 ///
 /// Should be provided to the `write_descriptor_sets` method of a `Device`.
+#[cfg(feature = "std")]
 #[derive(Debug)]
 pub struct DescriptorSetWrite<'a, B: Backend, I>
 where
@@ -241,6 +256,7 @@ where
 /// Usually provided in a [`DescriptorSetWrite`]
 ///
 /// [`DescriptorSetWrite`]: struct.DescriptorSetWrite.html
+#[cfg(feature = "std")]
 #[allow(missing_docs)]
 #[derive(Clone, Debug)]
 pub enum Descriptor<'a, B: Backend> {
@@ -249,11 +265,24 @@ pub enum Descriptor<'a, B: Backend> {
     CombinedImageSampler(&'a B::ImageView, Layout, &'a B::Sampler),
     Buffer(&'a B::Buffer, SubRange),
     TexelBuffer(&'a B::BufferView),
+    /// Raw data for an inline uniform block binding.
+    ///
+    /// The `array_offset` of the write is interpreted as the byte offset into
+    /// the block, and the data is not spilled onto the following bindings.
+    InlineUniformBlock(&'a [u8]),
+    /// No resource, requires `Features::NULL_DESCRIPTOR`.
+    ///
+    /// Reads from the descriptor return zeros, with an alpha of either zero or one
+    /// for the images, and writes are discarded. Combined image samplers need to
+    /// use immutable samplers, and sampler descriptors can't be null.
+    /// DX12 doesn't support null dynamic buffer descriptors.
+    Null,
 }
 
 /// Copies a range of descriptors to be bound from one descriptor set to another.
 ///
 /// Should be provided to the `copy_descriptor_sets` method of a `Device`.
+#[cfg(feature = "std")]
 #[derive(Debug)]
 pub struct DescriptorSetCopy<'a, B: Backend> {
     /// Descriptor set to copy from.
@@ -284,6 +313,7 @@ pub struct DescriptorSetCopy<'a, B: Backend> {
 
 bitflags! {
     /// Descriptor pool creation flags.
+    #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
     pub struct DescriptorPoolCreateFlags: u32 {
         /// Specifies that descriptor sets are allowed to be freed from the pool
         /// individually.
@@ -1,16 +1,23 @@
 //! Graphics pipeline descriptor.
 
 use crate::{
-    image, pass,
+    image,
     pso::{
         input_assembler::{AttributeDesc, InputAssemblerDesc, VertexBufferDesc},
         output_merger::{ColorBlendDesc, DepthStencilDesc, Face},
-        BasePipeline, EntryPoint, PipelineCreationFlags, State,
+        PipelineCreationFlags, State,
     },
+};
+#[cfg(feature = "std")]
+use crate::{
+    pass,
+    pso::{input_assembler::Primitive, BasePipeline, EntryPoint},
     Backend,
 };
 
-use std::ops::Range;
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+use core::ops::Range;
 
 /// A simple struct describing a rect with integer coordinates.
 #[derive(Clone, Copy, Debug, Hash, PartialEq, PartialOrd)]
@@ -43,6 +50,8 @@ pub struct Viewport {
     /// The viewport boundaries.
     pub rect: Rect,
     /// The viewport depth limits.
+    ///
+    /// They have to be within [0, 1], unless `Features::DEPTH_RANGE_UNRESTRICTED` is enabled.
     pub depth: Range<f32>,
 }
 
@@ -65,12 +74,16 @@ pub struct BakedStates {
     /// Static depth bounds.
     pub depth_bounds: Option<Range<f32>>,
 }
+#[cfg(feature = "std")]
 #[derive(Debug)]
 /// Primitive Assembler describes how input data are fetched in the pipeline and formed into primitives before being sent into the fragment shader.
 pub enum PrimitiveAssemblerDesc<'a, B: Backend> {
     /// Vertex based pipeline
     Vertex {
         /// Vertex buffers (IA)
+        ///
+        /// May be empty, together with `attributes`, for pipelines that generate
+        /// their vertices from the vertex index, such as full-screen passes.
         buffers: &'a [VertexBufferDesc],
         /// Vertex attributes (IA)
         attributes: &'a [AttributeDesc],
@@ -106,6 +119,7 @@ pub enum PrimitiveAssemblerDesc<'a, B: Backend> {
 }
 /// A description of all the settings that can be altered
 /// when creating a graphics pipeline.
+#[cfg(feature = "std")]
 #[derive(Debug)]
 pub struct GraphicsPipelineDesc<'a, B: Backend> {
     /// Pipeline label
@@ -143,6 +157,7 @@ pub struct GraphicsPipelineDesc<'a, B: Backend> {
     pub parent: BasePipeline<'a, B::GraphicsPipeline>,
 }
 
+#[cfg(feature = "std")]
 impl<'a, B: Backend> GraphicsPipelineDesc<'a, B> {
     /// Create a new empty PSO descriptor.
     pub fn new(
@@ -167,6 +182,95 @@ impl<'a, B: Backend> GraphicsPipelineDesc<'a, B> {
             parent: BasePipeline::None,
         }
     }
+
+    /// Create a vertex based PSO descriptor from the stored fixed-function `state`.
+    pub fn from_state(
+        state: &'a GraphicsPipelineState,
+        vertex: EntryPoint<'a, B>,
+        fragment: Option<EntryPoint<'a, B>>,
+        layout: &'a B::PipelineLayout,
+        subpass: pass::Subpass<'a, B>,
+    ) -> Self {
+        GraphicsPipelineDesc {
+            label: None,
+            primitive_assembler: PrimitiveAssemblerDesc::Vertex {
+                buffers: &state.buffers,
+                attributes: &state.attributes,
+                input_assembler: state.input_assembler.clone(),
+                vertex,
+                tessellation: None,
+                geometry: None,
+            },
+            rasterizer: state.rasterizer,
+            fragment,
+            blender: state.blender.clone(),
+            depth_stencil: state.depth_stencil,
+            multisampling: state.multisampling.clone(),
+            baked_states: state.baked_states.clone(),
+            layout,
+            subpass,
+            flags: state.flags,
+            parent: BasePipeline::None,
+        }
+    }
+
+    /// Extract the fixed-function state of this descriptor.
+    ///
+    /// Mesh shading pipelines have no vertex input, so the returned state
+    /// has no buffers nor attributes, and lists triangles.
+    pub fn state(&self) -> GraphicsPipelineState {
+        let (buffers, attributes, input_assembler) = match self.primitive_assembler {
+            PrimitiveAssemblerDesc::Vertex {
+                buffers,
+                attributes,
+                ref input_assembler,
+                ..
+            } => (buffers.to_vec(), attributes.to_vec(), input_assembler.clone()),
+            PrimitiveAssemblerDesc::Mesh { .. } => (
+                Vec::new(),
+                Vec::new(),
+                InputAssemblerDesc::new(Primitive::TriangleList),
+            ),
+        };
+        GraphicsPipelineState {
+            buffers,
+            attributes,
+            input_assembler,
+            rasterizer: self.rasterizer,
+            blender: self.blender.clone(),
+            depth_stencil: self.depth_stencil,
+            multisampling: self.multisampling.clone(),
+            baked_states: self.baked_states.clone(),
+            flags: self.flags,
+        }
+    }
+}
+
+/// Fixed-function state of a graphics pipeline, owning all of its data.
+///
+/// Unlike `GraphicsPipelineDesc`, it doesn't refer to the shaders, the layout
+/// or the render pass, so it can be stored as data, e.g. by material systems.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GraphicsPipelineState {
+    /// Vertex buffers (IA)
+    pub buffers: Vec<VertexBufferDesc>,
+    /// Vertex attributes (IA)
+    pub attributes: Vec<AttributeDesc>,
+    /// Input assembler attributes.
+    pub input_assembler: InputAssemblerDesc,
+    /// Rasterizer setup
+    pub rasterizer: Rasterizer,
+    /// Description of how blend operations should be performed.
+    pub blender: BlendDesc,
+    /// Depth stencil (DSV)
+    pub depth_stencil: DepthStencilDesc,
+    /// Multisampling.
+    pub multisampling: Option<Multisampling>,
+    /// Static pipeline states.
+    pub baked_states: BakedStates,
+    /// Options that may be set to alter pipeline properties.
+    pub flags: PipelineCreationFlags,
 }
 
 /// Methods for rasterizing polygons, ie, turning the mesh
@@ -212,6 +316,118 @@ pub struct DepthBias {
     pub slope_factor: f32,
 }
 
+/// Size of the fragment area covered by a single fragment shader invocation,
+/// in pixels. Both dimensions are powers of two.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ShadingRate {
+    /// Width of the fragment area.
+    pub width: u8,
+    /// Height of the fragment area.
+    pub height: u8,
+}
+
+impl ShadingRate {
+    /// Regular shading, one invocation per pixel.
+    pub const FULL: Self = ShadingRate {
+        width: 1,
+        height: 1,
+    };
+}
+
+impl Default for ShadingRate {
+    fn default() -> Self {
+        Self::FULL
+    }
+}
+
+/// Operation combining the shading rate accumulated so far with the rate
+/// provided by the next source.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ShadingRateCombiner {
+    /// Keep the accumulated rate, ignoring the new one.
+    Keep,
+    /// Replace the accumulated rate with the new one.
+    Replace,
+    /// Take the finer of the two rates, per dimension.
+    Min,
+    /// Take the coarser of the two rates, per dimension.
+    Max,
+    /// Multiply the two rates, per dimension.
+    Mul,
+}
+
+/// Algorithm used to rasterize line segments.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum LineRasterizationMode {
+    /// Implementation-defined line rasterization.
+    Default,
+    /// Lines are rasterized as parallelograms, extruded perpendicular to the segment.
+    /// Requires `Features::LINE_RASTERIZATION_RECTANGULAR`.
+    Rectangular,
+    /// Lines are rasterized with the "diamond-exit" rule, like the aliased lines of OpenGL.
+    /// Requires `Features::LINE_RASTERIZATION_BRESENHAM`.
+    Bresenham,
+    /// Lines are rasterized as antialiased rectangles, with the coverage going to the alpha.
+    /// Requires `Features::LINE_RASTERIZATION_SMOOTH`.
+    Smooth,
+}
+
+/// Stipple pattern applied to the rasterized line segments.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LineStipple {
+    /// Number of consecutive fragments covered by each bit of the pattern, from 1 to 256.
+    pub factor: u32,
+    /// Bit pattern of the stipple, starting with the least significant bit.
+    /// Fragments corresponding to the zero bits are discarded.
+    pub pattern: u16,
+}
+
+/// Line rasterization state.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LineRasterization {
+    /// How the line segments are rasterized.
+    pub mode: LineRasterizationMode,
+    /// Stipple pattern, if any, to apply to the line segments.
+    /// Requires `Features::LINE_STIPPLE`, and a mode other than `Default`.
+    pub stipple: Option<LineStipple>,
+}
+
+impl LineRasterization {
+    /// Implementation-defined line rasterization without stipple.
+    pub const DEFAULT: Self = LineRasterization {
+        mode: LineRasterizationMode::Default,
+        stipple: None,
+    };
+}
+
+impl Default for LineRasterization {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Conservative rasterization mode, generating the fragments based on the coverage
+/// of the whole pixel instead of the coverage of its sample locations.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ConservativeRasterization {
+    /// Generate the fragments for all the pixels partially covered by the primitive,
+    /// after extending its edges by the given extra size, in pixels.
+    ///
+    /// Requires `Features::CONSERVATIVE_RASTERIZATION`, and the extra size to be
+    /// at most `Limits::max_extra_primitive_overestimation_size`.
+    Overestimate(f32),
+    /// Only generate the fragments for the pixels fully covered by the primitive.
+    ///
+    /// Requires `Features::CONSERVATIVE_RASTERIZATION_UNDERESTIMATE`.
+    Underestimate,
+}
+
 /// Rasterization state.
 #[derive(Copy, Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -226,12 +442,22 @@ pub struct Rasterizer {
     /// fragments being omitted when they are outside the bounds of the z-plane,
     /// they will be clamped to the min or max z value.
     pub depth_clamping: bool,
+    /// Whether or not to clip the fragments outside the bounds of the z-plane,
+    /// independently of `depth_clamping`. When `None`, the fragments are only
+    /// clipped if depth clamping is disabled.
+    ///
+    /// Requires `Features::DEPTH_CLIP_ENABLE` if not `None`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub depth_clip_enable: Option<bool>,
     /// What depth bias, if any, to use for the drawn primitives.
     pub depth_bias: Option<State<DepthBias>>,
     /// Controls how triangles will be rasterized depending on their overlap with pixels.
-    pub conservative: bool,
+    pub conservative: Option<ConservativeRasterization>,
     /// Controls width of rasterized line segments.
     pub line_width: State<f32>,
+    /// Controls the rasterization of line segments.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub line_rasterization: LineRasterization,
 }
 
 impl Rasterizer {
@@ -241,9 +467,11 @@ impl Rasterizer {
         cull_face: Face::NONE,
         front_face: FrontFace::CounterClockwise,
         depth_clamping: false,
+        depth_clip_enable: None,
         depth_bias: None,
-        conservative: false,
+        conservative: None,
         line_width: State::Static(1.0),
+        line_rasterization: LineRasterization::DEFAULT,
     };
 }
 
@@ -285,10 +513,14 @@ pub type SampleMask = u64;
 
 ///
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Multisampling {
     ///
     pub rasterization_samples: image::NumSamples,
+    /// Enables the sample shading, with the minimum fraction of the samples,
+    /// between 0 and 1, that are shaded independently for each pixel.
     ///
+    /// Requires `Features::SAMPLE_RATE_SHADING`.
     pub sample_shading: Option<f32>,
     ///
     pub sample_mask: SampleMask,
@@ -298,4 +530,78 @@ pub struct Multisampling {
     pub alpha_coverage: bool,
     ///
     pub alpha_to_one: bool,
+    /// Rasterize with the sample locations set by
+    /// [`CommandBuffer::set_sample_locations`][crate::command::CommandBuffer::set_sample_locations]
+    /// instead of the default ones.
+    ///
+    /// Requires `Features::SAMPLE_LOCATIONS`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub sample_locations: bool,
+}
+
+/// Location of a sample within a pixel, where `(0.0, 0.0)` is the top-left corner
+/// of the pixel and `(0.5, 0.5)` is its center.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SampleLocation {
+    /// Horizontal coordinate, in the `[0.0, 1.0)` range.
+    pub x: f32,
+    /// Vertical coordinate, in the `[0.0, 1.0)` range.
+    pub y: f32,
+}
+
+const fn sl(x: f32, y: f32) -> SampleLocation {
+    SampleLocation { x, y }
+}
+
+const STANDARD_SAMPLE_LOCATIONS_1: [SampleLocation; 1] = [sl(0.5, 0.5)];
+const STANDARD_SAMPLE_LOCATIONS_2: [SampleLocation; 2] = [sl(0.75, 0.75), sl(0.25, 0.25)];
+const STANDARD_SAMPLE_LOCATIONS_4: [SampleLocation; 4] = [
+    sl(0.375, 0.125),
+    sl(0.875, 0.375),
+    sl(0.125, 0.625),
+    sl(0.625, 0.875),
+];
+const STANDARD_SAMPLE_LOCATIONS_8: [SampleLocation; 8] = [
+    sl(0.5625, 0.3125),
+    sl(0.4375, 0.6875),
+    sl(0.8125, 0.5625),
+    sl(0.3125, 0.1875),
+    sl(0.1875, 0.8125),
+    sl(0.0625, 0.4375),
+    sl(0.6875, 0.9375),
+    sl(0.9375, 0.0625),
+];
+const STANDARD_SAMPLE_LOCATIONS_16: [SampleLocation; 16] = [
+    sl(0.5625, 0.5625),
+    sl(0.4375, 0.3125),
+    sl(0.3125, 0.625),
+    sl(0.75, 0.4375),
+    sl(0.1875, 0.375),
+    sl(0.625, 0.8125),
+    sl(0.8125, 0.6875),
+    sl(0.6875, 0.1875),
+    sl(0.375, 0.875),
+    sl(0.5, 0.0625),
+    sl(0.25, 0.125),
+    sl(0.125, 0.75),
+    sl(0.0, 0.5),
+    sl(0.9375, 0.25),
+    sl(0.875, 0.9375),
+    sl(0.0625, 0.0),
+];
+
+/// Returns the standard sample locations for the given number of samples,
+/// shared by Vulkan and D3D, or `None` if there is no standard pattern for it.
+///
+/// Only valid for the devices reporting `Limits::standard_sample_locations`.
+pub fn standard_sample_locations(samples: image::NumSamples) -> Option<&'static [SampleLocation]> {
+    match samples {
+        1 => Some(&STANDARD_SAMPLE_LOCATIONS_1),
+        2 => Some(&STANDARD_SAMPLE_LOCATIONS_2),
+        4 => Some(&STANDARD_SAMPLE_LOCATIONS_4),
+        8 => Some(&STANDARD_SAMPLE_LOCATIONS_8),
+        16 => Some(&STANDARD_SAMPLE_LOCATIONS_16),
+        _ => None,
+    }
 }
@@ -2,20 +2,26 @@
 //!
 //! This module contains items used to create and manage Pipelines.
 
+#[cfg(feature = "std")]
 use crate::{device, pass, Backend};
 
+#[cfg(feature = "std")]
 mod compute;
 mod descriptor;
 mod graphics;
 mod input_assembler;
 mod output_merger;
+#[cfg(feature = "std")]
 mod specialization;
+#[cfg(feature = "std")]
+mod task;
 
-pub use self::{
-    compute::*, descriptor::*, graphics::*, input_assembler::*, output_merger::*, specialization::*,
-};
+#[cfg(feature = "std")]
+pub use self::{compute::*, specialization::*, task::*};
+pub use self::{descriptor::*, graphics::*, input_assembler::*, output_merger::*};
 
 /// Error types happening upon PSO creation on the device side.
+#[cfg(feature = "std")]
 #[derive(Clone, Debug, PartialEq, thiserror::Error)]
 pub enum CreationError {
     /// Unknown other error.
@@ -36,6 +42,13 @@ pub enum CreationError {
     /// Out of either host or device memory.
     #[error(transparent)]
     OutOfMemory(#[from] device::OutOfMemory),
+    /// The pipeline has to be compiled, but the creation used
+    /// `PipelineCreationFlags::FAIL_ON_PIPELINE_COMPILE_REQUIRED`.
+    #[error("Pipeline compilation is required")]
+    CompileRequired,
+    /// The interfaces of the shader stages, or the vertex attributes, don't match.
+    #[error("Shader interface mismatch: {0:}")]
+    InterfaceMismatch(String),
 }
 
 bitflags!(
@@ -80,6 +93,8 @@ bitflags!(
         const TASK_SHADER = 0x80000;
         /// Mesh shader stage.
         const MESH_SHADER = 0x100000;
+        /// Stage where the shading rate attachment is read.
+        const SHADING_RATE = 0x400000;
     }
 );
 
@@ -112,6 +127,7 @@ bitflags!(
     }
 );
 
+#[cfg(feature = "std")]
 impl From<naga::ShaderStage> for ShaderStageFlags {
     fn from(stage: naga::ShaderStage) -> Self {
         use naga::ShaderStage as Ss;
@@ -124,6 +140,7 @@ impl From<naga::ShaderStage> for ShaderStageFlags {
 }
 
 /// Shader entry point.
+#[cfg(feature = "std")]
 #[derive(Debug)]
 pub struct EntryPoint<'a, B: Backend> {
     /// Entry point name.
@@ -134,6 +151,7 @@ pub struct EntryPoint<'a, B: Backend> {
     pub specialization: Specialization<'a>,
 }
 
+#[cfg(feature = "std")]
 impl<'a, B: Backend> Clone for EntryPoint<'a, B> {
     fn clone(&self) -> Self {
         EntryPoint {
@@ -156,6 +174,11 @@ bitflags!(
         ///
         /// Must be set when pipelines set the pipeline as base.
         const ALLOW_DERIVATIVES = 0x2;
+        /// Fail with `CreationError::CompileRequired` instead of compiling the pipeline,
+        /// unless it can be created quickly, e.g. from the pipeline cache.
+        ///
+        /// Requires `Features::PIPELINE_CREATION_CACHE_CONTROL`.
+        const FAIL_ON_PIPELINE_COMPILE_REQUIRED = 0x4;
     }
 );
 
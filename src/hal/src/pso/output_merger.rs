@@ -99,6 +99,50 @@ pub enum BlendOp {
     Min,
     /// Component-wise maximum value of source and destination.
     Max,
+    /// Advanced blend operation, combining the color and the alpha of source and destination.
+    ///
+    /// The color and the alpha of a `BlendState` have to use the same advanced operation,
+    /// and the blending is done on premultiplied colors.
+    ///
+    /// Requires `Features::BLEND_OPERATION_ADVANCED`.
+    Advanced(AdvancedBlendOp),
+}
+
+/// Advanced blend operations, following the separable and non-separable
+/// blend modes of the PDF specification.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum AdvancedBlendOp {
+    /// Product of source and destination.
+    Multiply,
+    /// Inverse of the product of the inverted source and destination.
+    Screen,
+    /// `Multiply` or `Screen`, depending on the destination.
+    Overlay,
+    /// Minimum of source and destination.
+    Darken,
+    /// Maximum of source and destination.
+    Lighten,
+    /// Brightens the destination to reflect the source.
+    ColorDodge,
+    /// Darkens the destination to reflect the source.
+    ColorBurn,
+    /// `Multiply` or `Screen`, depending on the source.
+    HardLight,
+    /// Darkens or lightens the destination, depending on the source.
+    SoftLight,
+    /// Absolute difference of source and destination.
+    Difference,
+    /// Similar to `Difference`, with a lower contrast.
+    Exclusion,
+    /// Hue of the source, with the saturation and luminosity of the destination.
+    HslHue,
+    /// Saturation of the source, with the hue and luminosity of the destination.
+    HslSaturation,
+    /// Hue and saturation of the source, with the luminosity of the destination.
+    HslColor,
+    /// Luminosity of the source, with the hue and saturation of the destination.
+    HslLuminosity,
 }
 
 impl BlendOp {
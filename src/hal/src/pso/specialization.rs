@@ -4,6 +4,7 @@ use std::{borrow::Cow, ops::Range, slice};
 
 /// Description of a specialization constant for the pipeline.
 #[derive(Debug, Clone, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct SpecializationConstant {
     /// Constant identifier in shader source.
     pub id: u32,
@@ -23,6 +24,7 @@ pub struct SpecializationConstant {
 /// More importantly, they are fast to execute, since the driver
 /// can optimize out the branch on that other PSO creation.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Specialization<'a> {
     /// Array of descriptors of specialization constants to override.
     pub constants: Cow<'a, [SpecializationConstant]>,
@@ -0,0 +1,66 @@
+//! Pipeline creation tasks.
+
+use crate::pso::CreationError;
+
+use std::{cell::Cell, sync::mpsc};
+
+/// Sending side of a [`PipelineTask`], used by the backends
+/// to deliver the created pipeline.
+pub type PipelineTaskSender<P> = mpsc::Sender<Result<P, CreationError>>;
+
+/// Handle to a pipeline being created in the background.
+///
+/// Dropping the task before it completes doesn't cancel the creation,
+/// but the resulting pipeline is leaked.
+#[derive(Debug)]
+pub struct PipelineTask<P> {
+    receiver: mpsc::Receiver<Result<P, CreationError>>,
+    consumed: Cell<bool>,
+}
+
+impl<P> PipelineTask<P> {
+    /// Create a pending task, along with the sender completing it.
+    pub fn new() -> (PipelineTaskSender<P>, Self) {
+        let (sender, receiver) = mpsc::channel();
+        (
+            sender,
+            PipelineTask {
+                receiver,
+                consumed: Cell::new(false),
+            },
+        )
+    }
+
+    /// Create a task that is already complete.
+    pub fn ready(result: Result<P, CreationError>) -> Self {
+        let (sender, task) = Self::new();
+        let _ = sender.send(result);
+        task
+    }
+
+    /// Return the result of the creation if it's complete, without blocking.
+    ///
+    /// The result is only returned once, polling the task after that returns `None`.
+    pub fn poll(&self) -> Option<Result<P, CreationError>> {
+        if self.consumed.get() {
+            return None;
+        }
+        let result = match self.receiver.try_recv() {
+            Ok(result) => result,
+            Err(mpsc::TryRecvError::Empty) => return None,
+            Err(mpsc::TryRecvError::Disconnected) => Err(CreationError::Other),
+        };
+        self.consumed.set(true);
+        Some(result)
+    }
+
+    /// Block until the creation is complete and return its result.
+    ///
+    /// Returns `CreationError::Other` if the result was already returned by `poll`.
+    pub fn wait(self) -> Result<P, CreationError> {
+        if self.consumed.get() {
+            return Err(CreationError::Other);
+        }
+        self.receiver.recv().unwrap_or(Err(CreationError::Other))
+    }
+}
@@ -59,6 +59,7 @@ bitflags!(
 
 /// Type of queries in a query pool.
 #[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Type {
     /// Occlusion query. Count the number of drawn samples between
     /// the start and end of the query command.
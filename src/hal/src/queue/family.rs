@@ -17,6 +17,11 @@ pub trait QueueFamily: Debug + Any + Send + Sync {
     fn max_queues(&self) -> usize;
     /// Returns the queue family ID.
     fn id(&self) -> QueueFamilyId;
+    /// Returns true if the queues of this family accept protected submissions,
+    /// when `Features::PROTECTED_MEMORY` is enabled.
+    fn supports_protected(&self) -> bool {
+        false
+    }
 }
 
 /// Identifier for a queue family of a physical device.
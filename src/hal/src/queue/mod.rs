@@ -7,16 +7,22 @@
 //! `Queue<B, C>` has the capability defined by `C`: graphics, compute and transfer.
 
 pub mod family;
+mod schedule;
+mod submission;
 
 use crate::{
-    device::OutOfMemory,
+    device::{DeviceLost, OutOfMemory, WaitError},
     pso,
-    window::{PresentError, PresentationSurface, Suboptimal},
+    window::{PresentError, PresentTime, PresentationSurface, Suboptimal},
     Backend,
 };
 use std::{any::Any, fmt};
 
-pub use self::family::{QueueFamily, QueueFamilyId, QueueGroup};
+pub use self::{
+    family::{QueueFamily, QueueFamilyId, QueueGroup},
+    schedule::{Schedule, SemaphorePool, SubmissionId},
+    submission::{FrameToken, OwnedSubmissions, OwnedSubmitError, SubmissionIndex},
+};
 
 /// The type of the queue, an enum encompassing `queue::Capability`
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -53,6 +59,17 @@ impl QueueType {
     }
 }
 
+/// Error submitting the work to a queue.
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+pub enum SubmitError {
+    /// Out of either host or device memory.
+    #[error(transparent)]
+    OutOfMemory(#[from] OutOfMemory),
+    /// Device is lost.
+    #[error(transparent)]
+    DeviceLost(#[from] DeviceLost),
+}
+
 /// Scheduling hint for devices about the priority of a queue.  Values range from `0.0` (low) to
 /// `1.0` (high).
 pub type QueuePriority = f32;
@@ -76,23 +93,65 @@ pub trait Queue<B: Backend>: fmt::Debug + Any + Send + Sync {
     /// * `fence` - must be in unsignaled state, and will be signaled after
     ///   all command buffers in the submission have finished execution.
     ///
+    /// # Ordering
+    ///
+    /// Submissions to a queue start executing in submission order, but are not
+    /// otherwise ordered without pipeline barriers, whose synchronization scopes
+    /// include the previously submitted work. Submissions to different queues are
+    /// only ordered by semaphores. See [`Schedule`] for a way to insert those
+    /// semaphores according to the dependencies between the submissions.
+    ///
     /// # Safety
     ///
     /// It's not checked that the queue can process the submitted command buffers.
     ///
     /// For example, trying to submit compute commands to a graphics queue
     /// will result in undefined behavior.
+    ///
+    /// See [`OwnedSubmissions`] for a way to hand over the ownership of
+    /// the command buffers for the duration of their execution.
+    ///
+    /// # Errors
+    ///
+    /// If the device is lost, the submission is dropped, and [`SubmitError::DeviceLost`]
+    /// is returned. Some backends only detect the loss later, when waiting on the queue,
+    /// the device, or the fences.
     unsafe fn submit<'a, Ic, Iw, Is>(
         &mut self,
         command_buffers: Ic,
         wait_semaphores: Iw,
         signal_semaphores: Is,
         fence: Option<&mut B::Fence>,
-    ) where
+    ) -> Result<(), SubmitError>
+    where
         Ic: Iterator<Item = &'a B::CommandBuffer>,
         Iw: Iterator<Item = (&'a B::Semaphore, pso::PipelineStage)>,
         Is: Iterator<Item = &'a B::Semaphore>;
 
+    /// Submit protected command buffers to queue for execution, like [`submit`][Queue::submit].
+    ///
+    /// Requires `Features::PROTECTED_MEMORY`, and a queue from a family
+    /// that [supports protected submissions][QueueFamily::supports_protected].
+    ///
+    /// # Safety
+    ///
+    /// All the command buffers must be allocated from a pool created with
+    /// `CommandPoolCreateFlags::PROTECTED`.
+    unsafe fn submit_protected<'a, Ic, Iw, Is>(
+        &mut self,
+        command_buffers: Ic,
+        wait_semaphores: Iw,
+        signal_semaphores: Is,
+        fence: Option<&mut B::Fence>,
+    ) -> Result<(), SubmitError>
+    where
+        Ic: Iterator<Item = &'a B::CommandBuffer>,
+        Iw: Iterator<Item = (&'a B::Semaphore, pso::PipelineStage)>,
+        Is: Iterator<Item = &'a B::Semaphore>,
+    {
+        self.submit(command_buffers, wait_semaphores, signal_semaphores, fence)
+    }
+
     /// Present a swapchain image directly to a surface, after waiting on `wait_semaphore`.
     ///
     /// # Safety
@@ -106,8 +165,28 @@ pub trait Queue<B: Backend>: fmt::Debug + Any + Send + Sync {
         wait_semaphore: Option<&mut B::Semaphore>,
     ) -> Result<Option<Suboptimal>, PresentError>;
 
+    /// Present a swapchain image like [`present`][Queue::present], with a timing request.
+    ///
+    /// The timing of the present can later be queried with
+    /// `PresentationSurface::get_past_presentation_timing`.
+    /// Requires `Features::PRESENT_TIMING`, otherwise the timing request is ignored.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe for the same reasons as [`present`][Queue::present].
+    unsafe fn present_with_time(
+        &mut self,
+        surface: &mut B::Surface,
+        image: <B::Surface as PresentationSurface<B>>::SwapchainImage,
+        wait_semaphore: Option<&mut B::Semaphore>,
+        time: PresentTime,
+    ) -> Result<Option<Suboptimal>, PresentError> {
+        let _ = time;
+        self.present(surface, image, wait_semaphore)
+    }
+
     /// Wait for the queue to be idle.
-    fn wait_idle(&mut self) -> Result<(), OutOfMemory>;
+    fn wait_idle(&mut self) -> Result<(), WaitError>;
 
     /// The amount of nanoseconds that causes a timestamp query value to increment by one.
     fn timestamp_period(&self) -> f32;
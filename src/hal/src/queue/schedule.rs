@@ -0,0 +1,257 @@
+//! Ordered submissions across queues.
+//!
+//! Submissions to different queues execute independently of each other, and the only
+//! way to order them is to signal a semaphore in one submission and wait on it in the other.
+//! Within a single queue, submissions start in submission order but may overlap,
+//! and are ordered by the pipeline barriers recorded into the command buffers,
+//! whose synchronization scopes extend to the previously submitted work.
+//!
+//! A [`Schedule`] collects submissions to multiple queues together with their dependencies,
+//! and inserts the semaphores between the queues when submitted. The semaphores are taken
+//! from a [`SemaphorePool`], and recycled once the scheduled work is done.
+
+use crate::{
+    device::{Device, OutOfMemory},
+    pso,
+    queue::{Queue, SubmitError},
+    Backend,
+};
+
+use std::{fmt, ops::Range};
+
+/// Identifier of a submission in a [`Schedule`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SubmissionId(usize);
+
+struct ScheduledSubmission<'a, B: Backend> {
+    queue: usize,
+    command_buffers: Vec<&'a B::CommandBuffer>,
+    dependencies: Vec<(SubmissionId, pso::PipelineStage)>,
+    wait_semaphores: Vec<(&'a B::Semaphore, pso::PipelineStage)>,
+    signal_semaphores: Vec<&'a B::Semaphore>,
+    fence: Option<&'a mut B::Fence>,
+}
+
+/// A list of submissions to multiple queues, ordered by their dependencies.
+///
+/// Submissions are issued in the order they are added, which makes every dependency
+/// precede its dependents. Dependencies between submissions to different queues are
+/// fulfilled by semaphores, while the ones within a queue have to be covered by pipeline
+/// barriers at the start of the dependent command buffers, like for any other submission.
+pub struct Schedule<'a, B: Backend> {
+    submissions: Vec<ScheduledSubmission<'a, B>>,
+}
+
+impl<B: Backend> fmt::Debug for Schedule<'_, B> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Schedule")
+            .field("submissions", &self.submissions.len())
+            .finish()
+    }
+}
+
+impl<B: Backend> Default for Schedule<'_, B> {
+    fn default() -> Self {
+        Schedule {
+            submissions: Vec::new(),
+        }
+    }
+}
+
+impl<'a, B: Backend> Schedule<'a, B> {
+    /// Create an empty schedule.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a submission of command buffers to the queue with the given index
+    /// in the list passed to [`submit`][Schedule::submit].
+    ///
+    /// The submission waits at the given stages for each of the `dependencies` to finish.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any of the dependencies is not from this schedule.
+    pub fn add<I>(
+        &mut self,
+        queue: usize,
+        command_buffers: I,
+        dependencies: &[(SubmissionId, pso::PipelineStage)],
+    ) -> SubmissionId
+    where
+        I: IntoIterator<Item = &'a B::CommandBuffer>,
+    {
+        let id = SubmissionId(self.submissions.len());
+        for &(dependency, _) in dependencies {
+            assert!(
+                dependency < id,
+                "Dependency {:?} is not from this schedule",
+                dependency
+            );
+        }
+        self.submissions.push(ScheduledSubmission {
+            queue,
+            command_buffers: command_buffers.into_iter().collect(),
+            dependencies: dependencies.to_vec(),
+            wait_semaphores: Vec::new(),
+            signal_semaphores: Vec::new(),
+            fence: None,
+        });
+        id
+    }
+
+    /// Make a submission wait on an external semaphore at the given stage,
+    /// e.g. the one signaled by acquiring a swapchain image.
+    pub fn wait_semaphore(
+        &mut self,
+        id: SubmissionId,
+        semaphore: &'a B::Semaphore,
+        stage: pso::PipelineStage,
+    ) {
+        self.submissions[id.0]
+            .wait_semaphores
+            .push((semaphore, stage));
+    }
+
+    /// Make a submission signal an external semaphore,
+    /// e.g. the one waited on by presenting a swapchain image.
+    pub fn signal_semaphore(&mut self, id: SubmissionId, semaphore: &'a B::Semaphore) {
+        self.submissions[id.0].signal_semaphores.push(semaphore);
+    }
+
+    /// Make a submission signal a fence once finished.
+    ///
+    /// The fence must be in the unsignaled state.
+    pub fn signal_fence(&mut self, id: SubmissionId, fence: &'a mut B::Fence) {
+        self.submissions[id.0].fence = Some(fence);
+    }
+
+    /// Submit all the scheduled work.
+    ///
+    /// A semaphore is allocated from the `semaphores` pool for every dependency
+    /// on a submission to another queue. When several dependencies are on the
+    /// same queue, only the last of them is waited on, since a semaphore signal
+    /// covers all the work previously submitted to the queue.
+    ///
+    /// # Safety
+    ///
+    /// Same as [`Queue::submit`] for each of the submissions. The `queues` have to
+    /// belong to the `device`.
+    pub unsafe fn submit(
+        self,
+        device: &B::Device,
+        queues: &mut [&mut B::Queue],
+        semaphores: &mut SemaphorePool<B>,
+    ) -> Result<(), SubmitError> {
+        // (source submission, destination submission, destination stages)
+        let mut edges = Vec::<(usize, usize, pso::PipelineStage)>::new();
+        for (index, submission) in self.submissions.iter().enumerate() {
+            let first_edge = edges.len();
+            for &(SubmissionId(source), stage) in &submission.dependencies {
+                let queue = self.submissions[source].queue;
+                if queue == submission.queue {
+                    continue;
+                }
+                match edges[first_edge..]
+                    .iter_mut()
+                    .find(|&&mut (other, _, _)| self.submissions[other].queue == queue)
+                {
+                    Some(edge) => {
+                        edge.0 = edge.0.max(source);
+                        edge.2 |= stage;
+                    }
+                    None => edges.push((source, index, stage)),
+                }
+            }
+        }
+
+        let range = semaphores.allocate(device, edges.len())?;
+        let edge_semaphores = &semaphores.in_use[range];
+
+        for (index, submission) in self.submissions.into_iter().enumerate() {
+            let waits = edges
+                .iter()
+                .zip(edge_semaphores)
+                .filter(|&(&(_, dst, _), _)| dst == index)
+                .map(|(&(_, _, stage), semaphore)| (semaphore, stage))
+                .chain(submission.wait_semaphores.into_iter());
+            let signals = edges
+                .iter()
+                .zip(edge_semaphores)
+                .filter(|&(&(src, _, _), _)| src == index)
+                .map(|(_, semaphore)| semaphore)
+                .chain(submission.signal_semaphores.into_iter());
+            queues[submission.queue].submit(
+                submission.command_buffers.into_iter(),
+                waits,
+                signals,
+                submission.fence,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Pool of the semaphores inserted between the queues by a [`Schedule`].
+pub struct SemaphorePool<B: Backend> {
+    free: Vec<B::Semaphore>,
+    in_use: Vec<B::Semaphore>,
+}
+
+impl<B: Backend> fmt::Debug for SemaphorePool<B> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("SemaphorePool")
+            .field("free", &self.free.len())
+            .field("in_use", &self.in_use.len())
+            .finish()
+    }
+}
+
+impl<B: Backend> Default for SemaphorePool<B> {
+    fn default() -> Self {
+        SemaphorePool {
+            free: Vec::new(),
+            in_use: Vec::new(),
+        }
+    }
+}
+
+impl<B: Backend> SemaphorePool<B> {
+    /// Create an empty pool.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn allocate(&mut self, device: &B::Device, count: usize) -> Result<Range<usize>, OutOfMemory> {
+        let start = self.in_use.len();
+        for _ in 0..count {
+            let semaphore = match self.free.pop() {
+                Some(semaphore) => semaphore,
+                None => device.create_semaphore()?,
+            };
+            self.in_use.push(semaphore);
+        }
+        Ok(start..self.in_use.len())
+    }
+
+    /// Make the semaphores used by the previous schedules available again.
+    ///
+    /// # Safety
+    ///
+    /// All the work submitted with the schedules using this pool must be finished,
+    /// e.g. by waiting on the fences signaled by their last submissions.
+    pub unsafe fn recycle(&mut self) {
+        self.free.extend(self.in_use.drain(..));
+    }
+
+    /// Destroy the pool with all the semaphores.
+    ///
+    /// # Safety
+    ///
+    /// All the work submitted with the schedules using this pool must be finished.
+    pub unsafe fn destroy(self, device: &B::Device) {
+        for semaphore in self.free.into_iter().chain(self.in_use) {
+            device.destroy_semaphore(semaphore);
+        }
+    }
+}
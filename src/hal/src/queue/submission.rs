@@ -0,0 +1,380 @@
+//! Owned submissions.
+//!
+//! The regular [`Queue::submit`][super::Queue::submit] only borrows the command buffers,
+//! leaving it up to the user to keep them alive until the GPU is done executing them.
+//! [`OwnedSubmissions`] takes the ownership of the submitted command buffers instead,
+//! and hands them back once the associated fence is signaled.
+//!
+//! Submissions and presents can also be tagged with a user-provided [`FrameToken`],
+//! so that the engines can recycle per-frame resources by looking at the last
+//! completed token, instead of maintaining a parallel array of fences.
+
+use crate::{
+    device::{Device, OutOfMemory, WaitError},
+    pso,
+    queue::{Queue, SubmitError},
+    window::{PresentError, PresentationSurface, Suboptimal},
+    Backend,
+};
+
+use std::{collections::VecDeque, fmt};
+
+/// Monotonically increasing index of an owned submission.
+pub type SubmissionIndex = u64;
+
+/// User-provided tag of the work belonging to a frame.
+///
+/// Tokens are expected to be increasing over the submissions of a queue.
+pub type FrameToken = u64;
+
+/// Error of an owned submission, handing back the command buffers that failed to be submitted.
+#[derive(Debug, thiserror::Error)]
+#[error("{error}")]
+pub struct OwnedSubmitError<B: Backend> {
+    /// The cause of the failure.
+    #[source]
+    pub error: SubmitError,
+    /// Command buffers of the submission, which are not owned by the tracker.
+    pub command_buffers: Vec<B::CommandBuffer>,
+}
+
+struct PendingSubmission<B: Backend> {
+    index: SubmissionIndex,
+    token: Option<FrameToken>,
+    fence: B::Fence,
+    command_buffers: Vec<B::CommandBuffer>,
+}
+
+#[derive(Debug)]
+struct PendingPresent {
+    /// Index of the last submission issued before the present.
+    after: Option<SubmissionIndex>,
+    token: FrameToken,
+}
+
+/// Tracker of the command buffers owned by in-flight submissions.
+///
+/// Each submission is guarded by a fence, which is managed internally
+/// and recycled once the submission is retired.
+pub struct OwnedSubmissions<B: Backend> {
+    pending: VecDeque<PendingSubmission<B>>,
+    free_fences: Vec<B::Fence>,
+    pending_presents: VecDeque<PendingPresent>,
+    next_index: SubmissionIndex,
+    last_completed: Option<SubmissionIndex>,
+    last_completed_token: Option<FrameToken>,
+    last_presented_token: Option<FrameToken>,
+}
+
+impl<B: Backend> fmt::Debug for OwnedSubmissions<B> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("OwnedSubmissions")
+            .field("pending", &self.pending.len())
+            .field("next_index", &self.next_index)
+            .field("last_completed", &self.last_completed)
+            .field("last_completed_token", &self.last_completed_token)
+            .field("last_presented_token", &self.last_presented_token)
+            .finish()
+    }
+}
+
+impl<B: Backend> Default for OwnedSubmissions<B> {
+    fn default() -> Self {
+        OwnedSubmissions {
+            pending: VecDeque::new(),
+            free_fences: Vec::new(),
+            pending_presents: VecDeque::new(),
+            next_index: 0,
+            last_completed: None,
+            last_completed_token: None,
+            last_presented_token: None,
+        }
+    }
+}
+
+impl<B: Backend> OwnedSubmissions<B> {
+    /// Create an empty tracker.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Submit command buffers to the queue, transferring their ownership to the tracker.
+    ///
+    /// The command buffers are returned by [`maintain`][OwnedSubmissions::maintain]
+    /// once the GPU has finished executing them, or with the error if the submission fails.
+    ///
+    /// # Safety
+    ///
+    /// Same as [`Queue::submit`]. The queue has to belong to the `device`.
+    pub unsafe fn submit<I>(
+        &mut self,
+        device: &B::Device,
+        queue: &mut B::Queue,
+        command_buffers: I,
+        wait_semaphores: &[(&B::Semaphore, pso::PipelineStage)],
+        signal_semaphores: &[&B::Semaphore],
+    ) -> Result<SubmissionIndex, OwnedSubmitError<B>>
+    where
+        I: Iterator<Item = B::CommandBuffer>,
+    {
+        self.submit_impl(
+            device,
+            queue,
+            command_buffers,
+            wait_semaphores,
+            signal_semaphores,
+            None,
+        )
+    }
+
+    /// Submit command buffers to the queue, like [`submit`][OwnedSubmissions::submit],
+    /// and tag the submission with a frame token.
+    ///
+    /// The token is reported by [`last_completed_token`][OwnedSubmissions::last_completed_token]
+    /// once the submission is retired.
+    ///
+    /// # Safety
+    ///
+    /// Same as [`Queue::submit`]. The queue has to belong to the `device`.
+    pub unsafe fn submit_tagged<I>(
+        &mut self,
+        device: &B::Device,
+        queue: &mut B::Queue,
+        command_buffers: I,
+        wait_semaphores: &[(&B::Semaphore, pso::PipelineStage)],
+        signal_semaphores: &[&B::Semaphore],
+        token: FrameToken,
+    ) -> Result<SubmissionIndex, OwnedSubmitError<B>>
+    where
+        I: Iterator<Item = B::CommandBuffer>,
+    {
+        self.submit_impl(
+            device,
+            queue,
+            command_buffers,
+            wait_semaphores,
+            signal_semaphores,
+            Some(token),
+        )
+    }
+
+    /// Present a swapchain image to the surface, tagging it with a frame token.
+    ///
+    /// The presentation engine doesn't report the completion of the presents,
+    /// so the token is considered presented once all the submissions made before
+    /// this call are retired, at which point the image is owned by the presentation
+    /// engine and no longer accessed by the submitted work.
+    ///
+    /// # Safety
+    ///
+    /// Same as [`Queue::present`].
+    pub unsafe fn present(
+        &mut self,
+        queue: &mut B::Queue,
+        surface: &mut B::Surface,
+        image: <B::Surface as PresentationSurface<B>>::SwapchainImage,
+        wait_semaphore: Option<&mut B::Semaphore>,
+        token: FrameToken,
+    ) -> Result<Option<Suboptimal>, PresentError> {
+        let result = queue.present(surface, image, wait_semaphore);
+        self.pending_presents.push_back(PendingPresent {
+            after: self.next_index.checked_sub(1),
+            token,
+        });
+        self.retire_presents();
+        result
+    }
+
+    unsafe fn submit_impl<I>(
+        &mut self,
+        device: &B::Device,
+        queue: &mut B::Queue,
+        command_buffers: I,
+        wait_semaphores: &[(&B::Semaphore, pso::PipelineStage)],
+        signal_semaphores: &[&B::Semaphore],
+        token: Option<FrameToken>,
+    ) -> Result<SubmissionIndex, OwnedSubmitError<B>>
+    where
+        I: Iterator<Item = B::CommandBuffer>,
+    {
+        let command_buffers = command_buffers.collect::<Vec<_>>();
+        let mut fence = match self.free_fences.pop() {
+            Some(fence) => fence,
+            None => match device.create_fence(false) {
+                Ok(fence) => fence,
+                Err(err) => {
+                    return Err(OwnedSubmitError {
+                        error: err.into(),
+                        command_buffers,
+                    })
+                }
+            },
+        };
+        if let Err(error) = queue.submit(
+            command_buffers.iter(),
+            wait_semaphores.iter().cloned(),
+            signal_semaphores.iter().cloned(),
+            Some(&mut fence),
+        ) {
+            self.free_fences.push(fence);
+            return Err(OwnedSubmitError {
+                error,
+                command_buffers,
+            });
+        }
+
+        let index = self.next_index;
+        self.next_index += 1;
+        self.pending.push_back(PendingSubmission {
+            index,
+            token,
+            fence,
+            command_buffers,
+        });
+        Ok(index)
+    }
+
+    /// Retire all the submissions that have finished execution, in submission order.
+    ///
+    /// Command buffers of the retired submissions are moved into `retired`,
+    /// from where they can be reset and reused, or freed back to their pools.
+    ///
+    /// Returns the index of the last retired submission, if any.
+    ///
+    /// # Safety
+    ///
+    /// The `device` has to be the one used for the submissions.
+    pub unsafe fn maintain<E>(
+        &mut self,
+        device: &B::Device,
+        retired: &mut E,
+    ) -> Result<Option<SubmissionIndex>, WaitError>
+    where
+        E: Extend<B::CommandBuffer>,
+    {
+        while let Some(submission) = self.pending.front() {
+            if !device.get_fence_status(&submission.fence)? {
+                break;
+            }
+            self.retire_front(device, retired)?;
+        }
+        self.retire_presents();
+        Ok(self.last_completed)
+    }
+
+    /// Block until the submission with the given index is finished,
+    /// and retire it together with all the preceding submissions.
+    ///
+    /// Returns `false` if the timeout has expired before that, or if no submission
+    /// has been made with this index yet.
+    ///
+    /// # Safety
+    ///
+    /// The `device` has to be the one used for the submissions.
+    pub unsafe fn wait<E>(
+        &mut self,
+        device: &B::Device,
+        index: SubmissionIndex,
+        timeout_ns: u64,
+        retired: &mut E,
+    ) -> Result<bool, WaitError>
+    where
+        E: Extend<B::CommandBuffer>,
+    {
+        if index >= self.next_index {
+            return Ok(false);
+        }
+        if let Some(submission) = self.pending.iter().find(|s| s.index == index) {
+            if !device.wait_for_fence(&submission.fence, timeout_ns)? {
+                return Ok(false);
+            }
+        }
+        while self.pending.front().map_or(false, |s| s.index <= index) {
+            self.retire_front(device, retired)?;
+        }
+        self.retire_presents();
+        Ok(true)
+    }
+
+    /// Index of the most recent submission known to be finished.
+    pub fn last_completed(&self) -> Option<SubmissionIndex> {
+        self.last_completed
+    }
+
+    /// Token of the most recent tagged submission known to be finished.
+    pub fn last_completed_token(&self) -> Option<FrameToken> {
+        self.last_completed_token
+    }
+
+    /// Token of the most recent tagged present known to be handed over
+    /// to the presentation engine.
+    pub fn last_presented_token(&self) -> Option<FrameToken> {
+        self.last_presented_token
+    }
+
+    /// Returns `true` if there are no submissions in flight.
+    pub fn is_idle(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Destroy the tracker, returning the command buffers of the submissions still in flight.
+    ///
+    /// # Safety
+    ///
+    /// All the submissions must be finished, e.g. by waiting for the queue to become idle.
+    pub unsafe fn destroy<E>(self, device: &B::Device, retired: &mut E)
+    where
+        E: Extend<B::CommandBuffer>,
+    {
+        for submission in self.pending {
+            retired.extend(submission.command_buffers);
+            device.destroy_fence(submission.fence);
+        }
+        for fence in self.free_fences {
+            device.destroy_fence(fence);
+        }
+    }
+
+    unsafe fn retire_front<E>(
+        &mut self,
+        device: &B::Device,
+        retired: &mut E,
+    ) -> Result<(), OutOfMemory>
+    where
+        E: Extend<B::CommandBuffer>,
+    {
+        let PendingSubmission {
+            index,
+            token,
+            mut fence,
+            command_buffers,
+        } = self.pending.pop_front().unwrap();
+        retired.extend(command_buffers);
+        self.last_completed = Some(index);
+        if token.is_some() {
+            self.last_completed_token = token;
+        }
+        // The submission is retired regardless, only the fence is lost on failure.
+        if let Err(err) = device.reset_fence(&mut fence) {
+            device.destroy_fence(fence);
+            return Err(err);
+        }
+        self.free_fences.push(fence);
+        Ok(())
+    }
+
+    fn retire_presents(&mut self) {
+        let first_pending = self.pending.front().map(|s| s.index);
+        while let Some(present) = self.pending_presents.front() {
+            let in_flight = match (present.after, first_pending) {
+                (Some(after), Some(first)) => after >= first,
+                _ => false,
+            };
+            if in_flight {
+                break;
+            }
+            self.last_presented_token = Some(present.token);
+            self.pending_presents.pop_front();
+        }
+    }
+}
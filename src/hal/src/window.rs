@@ -54,7 +54,7 @@
 //!
 //! DOC TODO
 
-use crate::{device, format::Format, image, Backend};
+use crate::{device, format::Format, image, queue::QueueFamilyId, Backend};
 
 use std::{
     any::Any,
@@ -62,6 +62,7 @@ use std::{
     cmp::{max, min},
     fmt,
     ops::RangeInclusive,
+    os::raw::c_void,
 };
 
 /// Default image usage for the swapchain.
@@ -91,6 +92,34 @@ pub enum SwapchainError {
     WindowInUse,
 }
 
+/// Error on entering or leaving exclusive full screen.
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+pub enum FullScreenExclusiveError {
+    /// Out of either host or device memory.
+    #[error(transparent)]
+    OutOfMemory(#[from] device::OutOfMemory),
+    /// Surface is lost
+    #[error(transparent)]
+    SurfaceLost(#[from] SurfaceLost),
+    /// The swapchain isn't configured with `FullScreenExclusive::ApplicationControlled`.
+    #[error("Exclusive full screen is not controlled by the application")]
+    NotApplicationControlled,
+    /// Exclusive full screen can't be entered at the moment,
+    /// for example when the window is occluded or another application owns the display.
+    #[error("Exclusive full screen is not available")]
+    NotAvailable,
+}
+
+/// Error on switching the present mode of a configured swapchain.
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+#[error("Present mode can't be switched without re-configuring the swapchain")]
+pub struct PresentModeIncompatible;
+
+/// Error on setting the HDR metadata of a swapchain.
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+#[error("HDR metadata can't be set on the swapchain")]
+pub struct HdrMetadataUnsupported;
+
 /// An extent describes the size of a rectangle, such as
 /// a window or texture. It is not used for referring to a
 /// sub-rectangle; for that see `command::Rect`.
@@ -123,6 +152,23 @@ impl Extent2D {
     }
 }
 
+/// Extended dynamic range (EDR) state of the display showing a surface.
+///
+/// Applications can use it to decide whether to tone map their HDR content.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ExtendedDynamicRange {
+    /// Maximum value of the linear color components the display can currently show,
+    /// relative to the reference white of 1.0.
+    ///
+    /// It's 1.0 when the display has no headroom above the reference white,
+    /// and it may change over time, for example with the display brightness.
+    pub max_component_value: f32,
+    /// Whether the surface requests the extended dynamic range content,
+    /// without clamping the color components above 1.0.
+    pub enabled: bool,
+}
+
 /// Describes information about what a `Surface`'s properties are.
 /// Fetch this with [Surface::capabilities].
 #[derive(Debug, Clone)]
@@ -158,6 +204,23 @@ pub struct SurfaceCapabilities {
 
     /// A bitmask of supported alpha composition modes.
     pub composite_alpha_modes: CompositeAlphaMode,
+
+    /// Transform currently applied by the presentation engine, relative to
+    /// the natural orientation of the display.
+    pub current_transform: SurfaceTransform,
+
+    /// A bitmask of supported pre-transforms.
+    pub transforms: SurfaceTransform,
+
+    /// A bitmask of supported color spaces.
+    ///
+    /// Always contains `ColorSpace::SRGB_NONLINEAR`.
+    pub color_spaces: ColorSpace,
+
+    /// Extended dynamic range of the display showing the surface.
+    ///
+    /// `None` if it can't be queried.
+    pub extended_dynamic_range: Option<ExtendedDynamicRange>,
 }
 
 impl SurfaceCapabilities {
@@ -227,10 +290,74 @@ pub trait PresentationSurface<B: Backend>: Surface<B> {
     /// # Synchronization
     ///
     /// The acquired image is available to render. No synchronization is required.
+    /// The backends wait for the image to become available before returning it,
+    /// e.g. for the next drawable on Metal, so there is no fence or semaphore
+    /// to wait on for the acquisition.
     unsafe fn acquire_image(
         &mut self,
         timeout_ns: u64,
     ) -> Result<(Self::SwapchainImage, Option<Suboptimal>), AcquireError>;
+
+    /// Switch the present mode of the configured swapchain, taking effect
+    /// with the next present, without re-creating the swapchain.
+    ///
+    /// Requires `Features::PRESENT_MODE_SWITCH`. Depending on the platform,
+    /// only some of the modes can be switched to from the one the swapchain
+    /// is configured with. Otherwise, or without the feature, `PresentModeIncompatible`
+    /// is returned and the swapchain has to be re-configured instead.
+    unsafe fn set_present_mode(
+        &mut self,
+        mode: PresentMode,
+    ) -> Result<(), PresentModeIncompatible> {
+        let _ = mode;
+        Err(PresentModeIncompatible)
+    }
+
+    /// Enter exclusive full screen with the configured swapchain.
+    ///
+    /// The swapchain has to be configured with `FullScreenExclusive::ApplicationControlled`.
+    /// Depending on the platform, the swapchain may become out of date,
+    /// and needs to be re-configured afterwards.
+    unsafe fn acquire_full_screen_exclusive(&mut self) -> Result<(), FullScreenExclusiveError> {
+        Err(FullScreenExclusiveError::NotApplicationControlled)
+    }
+
+    /// Leave exclusive full screen, previously entered with `acquire_full_screen_exclusive`.
+    unsafe fn release_full_screen_exclusive(&mut self) -> Result<(), FullScreenExclusiveError> {
+        Err(FullScreenExclusiveError::NotApplicationControlled)
+    }
+
+    /// Get the duration of a refresh cycle of the display the swapchain is presented to,
+    /// in nanoseconds.
+    ///
+    /// Returns `None` if the swapchain isn't configured, or the information isn't available.
+    /// Requires `Features::PRESENT_TIMING`.
+    unsafe fn get_refresh_duration(&mut self) -> Option<u64> {
+        None
+    }
+
+    /// Get the timing of images presented with `Queue::present_with_time`
+    /// that have been displayed since the last call.
+    ///
+    /// Presents that are still pending are reported by later calls, while
+    /// the ones the platform doesn't have information about are dropped.
+    /// Requires `Features::PRESENT_TIMING`.
+    unsafe fn get_past_presentation_timing(&mut self) -> Vec<PresentationTiming> {
+        Vec::new()
+    }
+
+    /// Set the HDR metadata of the content presented with the configured swapchain,
+    /// so that the display can map it to its own luminance range.
+    ///
+    /// Requires `Features::HDR_METADATA`, and is only meaningful with `ColorSpace::HDR10`.
+    /// The metadata has to be set again after re-configuring the swapchain.
+    unsafe fn set_hdr_metadata(
+        &mut self,
+        metadata: &HdrMetadata,
+    ) -> Result<(), HdrMetadataUnsupported> {
+        let _ = metadata;
+        Err(HdrMetadataUnsupported)
+    }
 }
 
 /// Index of an image in the swapchain.
@@ -256,6 +383,74 @@ bitflags!(
     }
 );
 
+bitflags!(
+    /// Transform applied to the swapchain images before presentation.
+    ///
+    /// When the pre-transform of a swapchain matches the current transform of
+    /// the surface, the presentation engine can skip rotating the images itself,
+    /// which saves a full screen pass on mobile devices.
+    #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+    pub struct SurfaceTransform: u32 {
+        /// Images are presented as they are.
+        const IDENTITY = 0x1;
+        /// Images are rotated by 90 degrees clockwise.
+        const ROTATE_90 = 0x2;
+        /// Images are rotated by 180 degrees.
+        const ROTATE_180 = 0x4;
+        /// Images are rotated by 270 degrees clockwise.
+        const ROTATE_270 = 0x8;
+        /// Images are mirrored horizontally.
+        const HORIZONTAL_MIRROR = 0x10;
+        /// Images are mirrored horizontally, then rotated by 90 degrees clockwise.
+        const HORIZONTAL_MIRROR_ROTATE_90 = 0x20;
+        /// Images are mirrored horizontally, then rotated by 180 degrees.
+        const HORIZONTAL_MIRROR_ROTATE_180 = 0x40;
+        /// Images are mirrored horizontally, then rotated by 270 degrees clockwise.
+        const HORIZONTAL_MIRROR_ROTATE_270 = 0x80;
+        /// The transform is specified through native window system commands.
+        const INHERIT = 0x100;
+    }
+);
+
+impl SurfaceTransform {
+    /// Check if the transform swaps the width and height of the images.
+    ///
+    /// The swapchain extent is expected in the natural orientation of the display,
+    /// so the current extent needs to be swapped when pre-transforming by 90 or 270 degrees.
+    pub fn swaps_extent(self) -> bool {
+        self.intersects(
+            SurfaceTransform::ROTATE_90
+                | SurfaceTransform::ROTATE_270
+                | SurfaceTransform::HORIZONTAL_MIRROR_ROTATE_90
+                | SurfaceTransform::HORIZONTAL_MIRROR_ROTATE_270,
+        )
+    }
+
+    /// Return the column-major matrix to multiply the projection by,
+    /// so that rendering matches the pre-transform of the swapchain.
+    ///
+    /// Only the clip space X and Y coordinates are affected.
+    /// `IDENTITY`, `INHERIT` and combinations of flags return the identity matrix.
+    pub fn projection_matrix(self) -> [[f32; 4]; 4] {
+        let (cos, sin, mirror) = match self {
+            SurfaceTransform::ROTATE_90 => (0.0, 1.0, 1.0),
+            SurfaceTransform::ROTATE_180 => (-1.0, 0.0, 1.0),
+            SurfaceTransform::ROTATE_270 => (0.0, -1.0, 1.0),
+            SurfaceTransform::HORIZONTAL_MIRROR => (1.0, 0.0, -1.0),
+            SurfaceTransform::HORIZONTAL_MIRROR_ROTATE_90 => (0.0, 1.0, -1.0),
+            SurfaceTransform::HORIZONTAL_MIRROR_ROTATE_180 => (-1.0, 0.0, -1.0),
+            SurfaceTransform::HORIZONTAL_MIRROR_ROTATE_270 => (0.0, -1.0, -1.0),
+            _ => (1.0, 0.0, 1.0),
+        };
+        [
+            [cos * mirror, sin * mirror, 0.0, 0.0],
+            [-sin, cos, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]
+    }
+}
+
 bitflags!(
     /// Specifies how the alpha channel of the images should be handled during
     /// compositing.
@@ -286,6 +481,110 @@ bitflags!(
     }
 );
 
+bitflags!(
+    /// Color space the presentation engine interprets the swapchain images in.
+    ///
+    /// The color space is independent from the format of the images: an `Srgb` format
+    /// only means that the values are encoded when rendering into the images,
+    /// and decoded when sampling them.
+    #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+    pub struct ColorSpace: u32 {
+        /// The images hold sRGB encoded values, which are displayed as they are.
+        ///
+        /// With a `Unorm` format, the shaders have to output encoded values.
+        const SRGB_NONLINEAR = 0x1;
+        /// The images hold linear values with the sRGB primaries,
+        /// which are encoded by the presentation engine.
+        ///
+        /// Values outside of [0, 1] are preserved with floating-point formats.
+        const LINEAR = 0x2;
+        /// The images hold values with the BT.2020 primaries encoded with
+        /// the SMPTE ST 2084 (PQ) transfer function, as used by HDR10 displays.
+        const HDR10 = 0x4;
+    }
+);
+
+/// HDR metadata of the presented content, as defined by SMPTE ST 2086 and CTA-861.3.
+///
+/// Chromaticities are CIE 1931 xy coordinates, and light levels are in nits (cd/m²).
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct HdrMetadata {
+    /// Red primary of the display the content was mastered on.
+    pub red_primary: [f32; 2],
+    /// Green primary of the mastering display.
+    pub green_primary: [f32; 2],
+    /// Blue primary of the mastering display.
+    pub blue_primary: [f32; 2],
+    /// White point of the mastering display.
+    pub white_point: [f32; 2],
+    /// Maximum luminance of the mastering display.
+    pub max_luminance: f32,
+    /// Minimum luminance of the mastering display.
+    pub min_luminance: f32,
+    /// Maximum light level of any pixel of the content.
+    pub max_content_light_level: f32,
+    /// Maximum average light level of any frame of the content.
+    pub max_frame_average_light_level: f32,
+}
+
+/// Specifies how the swapchain may use exclusive full screen on the display.
+///
+/// Exclusive full screen bypasses the desktop compositor, which reduces
+/// the presentation latency. Only has an effect on Windows,
+/// if `Features::FULL_SCREEN_EXCLUSIVE` is enabled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum FullScreenExclusive {
+    /// The platform decides whether to use exclusive full screen.
+    Default,
+    /// The platform may enter exclusive full screen whenever it deems appropriate.
+    Allowed,
+    /// Exclusive full screen is never used.
+    Disallowed,
+    /// Exclusive full screen is only entered and left with
+    /// `PresentationSurface::acquire_full_screen_exclusive`
+    /// and `PresentationSurface::release_full_screen_exclusive`.
+    ApplicationControlled,
+}
+
+/// Refresh rate of the display, in Hz, expressed as a fraction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RefreshRate {
+    /// Numerator of the rate.
+    pub numerator: u32,
+    /// Denominator of the rate.
+    pub denominator: u32,
+}
+
+/// Timing request attached to a present operation.
+///
+/// Times are in nanoseconds, in the same time domain as the timings
+/// reported by `PresentationSurface::get_past_presentation_timing`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PresentTime {
+    /// Application-provided identifier of the present, reported back in `PresentationTiming`.
+    pub present_id: u32,
+    /// Earliest time the image should be displayed at, or 0 for no constraint.
+    ///
+    /// Backends without native support treat this as a hint and ignore it.
+    pub desired_present_time: u64,
+}
+
+/// Timing information about an image that has been displayed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PresentationTiming {
+    /// Identifier given in `PresentTime::present_id`.
+    pub present_id: u32,
+    /// Time that was requested in `PresentTime::desired_present_time`.
+    pub desired_present_time: u64,
+    /// Time the image was actually displayed at, in nanoseconds.
+    pub actual_present_time: u64,
+}
+
 /// Contains all the data necessary to create a new `Swapchain`:
 /// color, depth, and number of images.
 ///
@@ -303,6 +602,7 @@ bitflags!(
 /// # }
 /// ```
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct SwapchainConfig {
     /// Presentation mode.
     pub present_mode: PresentMode,
@@ -321,6 +621,29 @@ pub struct SwapchainConfig {
     pub image_layers: image::Layer,
     /// Image usage of the backbuffer images.
     pub image_usage: image::Usage,
+    /// Exclusive full screen behavior.
+    pub full_screen_exclusive: FullScreenExclusive,
+    /// Refresh rate to switch the display to, when in exclusive full screen.
+    ///
+    /// Ignored by the backends that can't change the display mode.
+    pub refresh_rate: Option<RefreshRate>,
+    /// Transform applied to the images before presentation. Must be one of
+    /// `SurfaceCapabilities::transforms`.
+    ///
+    /// Rendering needs to be adjusted to match, see `SurfaceTransform::projection_matrix`.
+    pub pre_transform: SurfaceTransform,
+    /// Queue families accessing the images concurrently.
+    ///
+    /// If fewer than two distinct families are listed, the images are owned
+    /// exclusively by one family at a time, and presenting from another family
+    /// requires an ownership transfer through the `families` of an image barrier.
+    pub queue_families: Vec<QueueFamilyId>,
+    /// Whether the images are protected, and can only be written by protected
+    /// command buffers. Requires `Features::PROTECTED_MEMORY`.
+    pub protected: bool,
+    /// Color space the images are presented in. Must be one of
+    /// `SurfaceCapabilities::color_spaces`.
+    pub color_space: ColorSpace,
 }
 
 impl SwapchainConfig {
@@ -334,6 +657,12 @@ impl SwapchainConfig {
             image_count,
             image_layers: 1,
             image_usage: DEFAULT_USAGE,
+            full_screen_exclusive: FullScreenExclusive::Default,
+            refresh_rate: None,
+            pre_transform: SurfaceTransform::IDENTITY,
+            queue_families: Vec::new(),
+            protected: false,
+            color_space: ColorSpace::SRGB_NONLINEAR,
         }
     }
 
@@ -383,6 +712,12 @@ impl SwapchainConfig {
                 .min(*caps.image_count.end()),
             image_layers: 1,
             image_usage: DEFAULT_USAGE,
+            full_screen_exclusive: FullScreenExclusive::Default,
+            refresh_rate: None,
+            pre_transform: SurfaceTransform::IDENTITY,
+            queue_families: Vec::new(),
+            protected: false,
+            color_space: ColorSpace::SRGB_NONLINEAR,
         }
     }
 
@@ -410,6 +745,45 @@ impl SwapchainConfig {
         self
     }
 
+    /// Specify the exclusive full screen behavior.
+    pub fn with_full_screen_exclusive(mut self, mode: FullScreenExclusive) -> Self {
+        self.full_screen_exclusive = mode;
+        self
+    }
+
+    /// Specify the refresh rate used in exclusive full screen.
+    pub fn with_refresh_rate(mut self, rate: RefreshRate) -> Self {
+        self.refresh_rate = Some(rate);
+        self
+    }
+
+    /// Specify the pre-transform of the images.
+    pub fn with_pre_transform(mut self, transform: SurfaceTransform) -> Self {
+        self.pre_transform = transform;
+        self
+    }
+
+    /// Specify the queue families accessing the images concurrently.
+    pub fn with_queue_families<I>(mut self, families: I) -> Self
+    where
+        I: IntoIterator<Item = QueueFamilyId>,
+    {
+        self.queue_families = families.into_iter().collect();
+        self
+    }
+
+    /// Specify whether the images are protected.
+    pub fn with_protected(mut self, protected: bool) -> Self {
+        self.protected = protected;
+        self
+    }
+
+    /// Specify the color space the images are presented in.
+    pub fn with_color_space(mut self, color_space: ColorSpace) -> Self {
+        self.color_space = color_space;
+        self
+    }
+
     // TODO: depth-only, stencil-only, swapchain size, present modes, etc.
 }
 
@@ -470,3 +844,266 @@ pub enum InitError {
     #[error("Specified window handle is unsupported")]
     UnsupportedWindowHandle,
 }
+
+/// Connection to the display server that a window belongs to.
+///
+/// Pairing it with a window allows creating surfaces on windows that don't belong
+/// to the default display connection of the process, see
+/// [`Instance::create_surface_with_display`][crate::Instance::create_surface_with_display].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DisplayHandle {
+    /// Xlib `Display`.
+    Xlib(*mut c_void),
+    /// XCB `xcb_connection_t`.
+    Xcb(*mut c_void),
+    /// Wayland `wl_display`.
+    Wayland(*mut c_void),
+    /// Connection implied by the window, on the platforms without explicit ones.
+    Default,
+}
+
+#[cfg(feature = "rwh_05")]
+impl From<rwh_05::RawDisplayHandle> for DisplayHandle {
+    fn from(handle: rwh_05::RawDisplayHandle) -> Self {
+        match handle {
+            rwh_05::RawDisplayHandle::Xlib(display) => DisplayHandle::Xlib(display.display),
+            rwh_05::RawDisplayHandle::Xcb(display) => DisplayHandle::Xcb(display.connection),
+            rwh_05::RawDisplayHandle::Wayland(display) => DisplayHandle::Wayland(display.display),
+            _ => DisplayHandle::Default,
+        }
+    }
+}
+
+/// Raw window handle, as accepted by [`Instance::create_surface`][crate::Instance::create_surface].
+///
+/// It's also the conversion point from the newer versions of `raw-window-handle`,
+/// behind the `rwh_04` and `rwh_05` features.
+#[derive(Clone, Copy, Debug)]
+pub struct RawWindow(pub raw_window_handle::RawWindowHandle);
+
+unsafe impl raw_window_handle::HasRawWindowHandle for RawWindow {
+    fn raw_window_handle(&self) -> raw_window_handle::RawWindowHandle {
+        self.0
+    }
+}
+
+impl RawWindow {
+    /// Pair a window handle with the display connection it belongs to.
+    ///
+    /// Fails if the connection isn't of the same windowing system as the window.
+    pub fn with_display(
+        handle: raw_window_handle::RawWindowHandle,
+        display: DisplayHandle,
+    ) -> Result<Self, InitError> {
+        #[allow(unused_imports)]
+        use raw_window_handle::RawWindowHandle as Rwh;
+
+        match (handle, display) {
+            (handle, DisplayHandle::Default) => Ok(RawWindow(handle)),
+            #[cfg(any(
+                target_os = "linux",
+                target_os = "dragonfly",
+                target_os = "freebsd",
+                target_os = "netbsd",
+                target_os = "openbsd"
+            ))]
+            (Rwh::Xlib(mut window), DisplayHandle::Xlib(display)) => {
+                window.display = display;
+                Ok(RawWindow(Rwh::Xlib(window)))
+            }
+            #[cfg(any(
+                target_os = "linux",
+                target_os = "dragonfly",
+                target_os = "freebsd",
+                target_os = "netbsd",
+                target_os = "openbsd"
+            ))]
+            (Rwh::Xcb(mut window), DisplayHandle::Xcb(connection)) => {
+                window.connection = connection;
+                Ok(RawWindow(Rwh::Xcb(window)))
+            }
+            #[cfg(any(
+                target_os = "linux",
+                target_os = "dragonfly",
+                target_os = "freebsd",
+                target_os = "netbsd",
+                target_os = "openbsd"
+            ))]
+            (Rwh::Wayland(mut window), DisplayHandle::Wayland(display)) => {
+                window.display = display;
+                Ok(RawWindow(Rwh::Wayland(window)))
+            }
+            _ => Err(InitError::UnsupportedWindowHandle),
+        }
+    }
+
+    /// Convert a window handle of `raw-window-handle` 0.4,
+    /// returning `None` if it's not supported on this platform.
+    #[cfg(feature = "rwh_04")]
+    pub fn from_rwh_04(handle: rwh_04::RawWindowHandle) -> Option<Self> {
+        #[allow(unused_imports)]
+        use raw_window_handle as rwh;
+
+        Some(RawWindow(match handle {
+            #[cfg(any(
+                target_os = "linux",
+                target_os = "dragonfly",
+                target_os = "freebsd",
+                target_os = "netbsd",
+                target_os = "openbsd"
+            ))]
+            rwh_04::RawWindowHandle::Xlib(window) => {
+                let mut raw = rwh::unix::XlibHandle::empty();
+                raw.window = window.window;
+                raw.display = window.display;
+                rwh::RawWindowHandle::Xlib(raw)
+            }
+            #[cfg(any(
+                target_os = "linux",
+                target_os = "dragonfly",
+                target_os = "freebsd",
+                target_os = "netbsd",
+                target_os = "openbsd"
+            ))]
+            rwh_04::RawWindowHandle::Xcb(window) => {
+                let mut raw = rwh::unix::XcbHandle::empty();
+                raw.window = window.window;
+                raw.connection = window.connection;
+                rwh::RawWindowHandle::Xcb(raw)
+            }
+            #[cfg(any(
+                target_os = "linux",
+                target_os = "dragonfly",
+                target_os = "freebsd",
+                target_os = "netbsd",
+                target_os = "openbsd"
+            ))]
+            rwh_04::RawWindowHandle::Wayland(window) => {
+                let mut raw = rwh::unix::WaylandHandle::empty();
+                raw.surface = window.surface;
+                raw.display = window.display;
+                rwh::RawWindowHandle::Wayland(raw)
+            }
+            #[cfg(target_os = "windows")]
+            rwh_04::RawWindowHandle::Win32(window) => {
+                let mut raw = rwh::windows::WindowsHandle::empty();
+                raw.hwnd = window.hwnd;
+                raw.hinstance = window.hinstance;
+                rwh::RawWindowHandle::Windows(raw)
+            }
+            #[cfg(target_os = "macos")]
+            rwh_04::RawWindowHandle::AppKit(window) => {
+                let mut raw = rwh::macos::MacOSHandle::empty();
+                raw.ns_window = window.ns_window;
+                raw.ns_view = window.ns_view;
+                rwh::RawWindowHandle::MacOS(raw)
+            }
+            #[cfg(target_os = "ios")]
+            rwh_04::RawWindowHandle::UiKit(window) => {
+                let mut raw = rwh::ios::IOSHandle::empty();
+                raw.ui_window = window.ui_window;
+                raw.ui_view = window.ui_view;
+                raw.ui_view_controller = window.ui_view_controller;
+                rwh::RawWindowHandle::IOS(raw)
+            }
+            #[cfg(target_os = "android")]
+            rwh_04::RawWindowHandle::AndroidNdk(window) => {
+                let mut raw = rwh::android::AndroidHandle::empty();
+                raw.a_native_window = window.a_native_window;
+                rwh::RawWindowHandle::Android(raw)
+            }
+            #[cfg(target_arch = "wasm32")]
+            rwh_04::RawWindowHandle::Web(window) => {
+                let mut raw = rwh::web::WebHandle::empty();
+                raw.id = window.id;
+                rwh::RawWindowHandle::Web(raw)
+            }
+            _ => return None,
+        }))
+    }
+
+    /// Convert a window handle of `raw-window-handle` 0.5,
+    /// returning `None` if it's not supported on this platform.
+    ///
+    /// The display connection is a separate handle in this version, so the result
+    /// needs to be paired with it by [`RawWindow::with_display`] on Xlib, XCB and Wayland.
+    #[cfg(feature = "rwh_05")]
+    pub fn from_rwh_05(handle: rwh_05::RawWindowHandle) -> Option<Self> {
+        #[allow(unused_imports)]
+        use raw_window_handle as rwh;
+
+        Some(RawWindow(match handle {
+            #[cfg(any(
+                target_os = "linux",
+                target_os = "dragonfly",
+                target_os = "freebsd",
+                target_os = "netbsd",
+                target_os = "openbsd"
+            ))]
+            rwh_05::RawWindowHandle::Xlib(window) => {
+                let mut raw = rwh::unix::XlibHandle::empty();
+                raw.window = window.window;
+                rwh::RawWindowHandle::Xlib(raw)
+            }
+            #[cfg(any(
+                target_os = "linux",
+                target_os = "dragonfly",
+                target_os = "freebsd",
+                target_os = "netbsd",
+                target_os = "openbsd"
+            ))]
+            rwh_05::RawWindowHandle::Xcb(window) => {
+                let mut raw = rwh::unix::XcbHandle::empty();
+                raw.window = window.window;
+                rwh::RawWindowHandle::Xcb(raw)
+            }
+            #[cfg(any(
+                target_os = "linux",
+                target_os = "dragonfly",
+                target_os = "freebsd",
+                target_os = "netbsd",
+                target_os = "openbsd"
+            ))]
+            rwh_05::RawWindowHandle::Wayland(window) => {
+                let mut raw = rwh::unix::WaylandHandle::empty();
+                raw.surface = window.surface;
+                rwh::RawWindowHandle::Wayland(raw)
+            }
+            #[cfg(target_os = "windows")]
+            rwh_05::RawWindowHandle::Win32(window) => {
+                let mut raw = rwh::windows::WindowsHandle::empty();
+                raw.hwnd = window.hwnd;
+                raw.hinstance = window.hinstance;
+                rwh::RawWindowHandle::Windows(raw)
+            }
+            #[cfg(target_os = "macos")]
+            rwh_05::RawWindowHandle::AppKit(window) => {
+                let mut raw = rwh::macos::MacOSHandle::empty();
+                raw.ns_window = window.ns_window;
+                raw.ns_view = window.ns_view;
+                rwh::RawWindowHandle::MacOS(raw)
+            }
+            #[cfg(target_os = "ios")]
+            rwh_05::RawWindowHandle::UiKit(window) => {
+                let mut raw = rwh::ios::IOSHandle::empty();
+                raw.ui_window = window.ui_window;
+                raw.ui_view = window.ui_view;
+                raw.ui_view_controller = window.ui_view_controller;
+                rwh::RawWindowHandle::IOS(raw)
+            }
+            #[cfg(target_os = "android")]
+            rwh_05::RawWindowHandle::AndroidNdk(window) => {
+                let mut raw = rwh::android::AndroidHandle::empty();
+                raw.a_native_window = window.a_native_window;
+                rwh::RawWindowHandle::Android(raw)
+            }
+            #[cfg(target_arch = "wasm32")]
+            rwh_05::RawWindowHandle::Web(window) => {
+                let mut raw = rwh::web::WebHandle::empty();
+                raw.id = window.id;
+                rwh::RawWindowHandle::Web(raw)
+            }
+            _ => return None,
+        }))
+    }
+}
@@ -0,0 +1,182 @@
+//! Ordering of the owned submissions, checked on the software backend,
+//! which executes the submissions immediately.
+
+use gfx_backend_software as back;
+use gfx_hal::{
+    adapter::PhysicalDevice as _,
+    command::{self, CommandBuffer as _},
+    device::Device as _,
+    pool::{CommandPool as _, CommandPoolCreateFlags},
+    queue::{OwnedSubmissions, QueueGroup},
+    Backend, Features, Instance as _,
+};
+
+struct Context {
+    device: <back::Backend as Backend>::Device,
+    queue_group: QueueGroup<back::Backend>,
+    pool: <back::Backend as Backend>::CommandPool,
+    submissions: OwnedSubmissions<back::Backend>,
+}
+
+impl Context {
+    fn new() -> Self {
+        let instance = back::Instance::create("owned_submissions", 1).unwrap();
+        let adapter = instance.enumerate_adapters().remove(0);
+        let mut gpu = unsafe {
+            adapter
+                .physical_device
+                .open(&[(&adapter.queue_families[0], &[1.0])], Features::empty())
+                .unwrap()
+        };
+        let queue_group = gpu.queue_groups.pop().unwrap();
+        let pool = unsafe {
+            gpu.device
+                .create_command_pool(queue_group.family, CommandPoolCreateFlags::empty())
+                .unwrap()
+        };
+        Context {
+            device: gpu.device,
+            queue_group,
+            pool,
+            submissions: OwnedSubmissions::new(),
+        }
+    }
+
+    /// Submit `count` empty command buffers, optionally tagged with `token`.
+    fn submit(&mut self, count: usize, token: Option<u64>) -> u64 {
+        unsafe {
+            let command_buffers = (0..count)
+                .map(|_| {
+                    let mut cmd_buffer = self.pool.allocate_one(command::Level::Primary);
+                    cmd_buffer.begin_primary(command::CommandBufferFlags::ONE_TIME_SUBMIT);
+                    cmd_buffer.finish();
+                    cmd_buffer
+                })
+                .collect::<Vec<_>>();
+            let queue = &mut self.queue_group.queues[0];
+            match token {
+                Some(token) => self.submissions.submit_tagged(
+                    &self.device,
+                    queue,
+                    command_buffers.into_iter(),
+                    &[],
+                    &[],
+                    token,
+                ),
+                None => self.submissions.submit(
+                    &self.device,
+                    queue,
+                    command_buffers.into_iter(),
+                    &[],
+                    &[],
+                ),
+            }
+            .unwrap()
+        }
+    }
+}
+
+#[test]
+fn submission_indices() {
+    let mut context = Context::new();
+    assert!(context.submissions.is_idle());
+    assert_eq!(context.submit(1, None), 0);
+    assert_eq!(context.submit(2, None), 1);
+    assert_eq!(context.submit(0, None), 2);
+    assert!(!context.submissions.is_idle());
+    assert_eq!(context.submissions.last_completed(), None);
+}
+
+#[test]
+fn maintain_retires_in_order() {
+    let mut context = Context::new();
+    context.submit(1, Some(10));
+    context.submit(2, None);
+    context.submit(1, Some(11));
+
+    let mut retired = Vec::new();
+    let last = unsafe { context.submissions.maintain(&context.device, &mut retired) };
+    assert_eq!(last, Ok(Some(2)));
+    assert_eq!(retired.len(), 4);
+    assert_eq!(context.submissions.last_completed_token(), Some(11));
+    assert!(context.submissions.is_idle());
+
+    // Nothing left to retire.
+    let last = unsafe { context.submissions.maintain(&context.device, &mut retired) };
+    assert_eq!(last, Ok(Some(2)));
+    assert_eq!(retired.len(), 4);
+}
+
+#[test]
+fn wait_retires_preceding_submissions() {
+    let mut context = Context::new();
+    context.submit(1, Some(1));
+    context.submit(2, Some(2));
+    context.submit(3, Some(3));
+
+    let mut retired = Vec::new();
+    let done = unsafe {
+        context
+            .submissions
+            .wait(&context.device, 1, !0, &mut retired)
+    };
+    assert_eq!(done, Ok(true));
+    // The later submission is finished too, but only the waited ones are retired.
+    assert_eq!(retired.len(), 3);
+    assert_eq!(context.submissions.last_completed(), Some(1));
+    assert_eq!(context.submissions.last_completed_token(), Some(2));
+    assert!(!context.submissions.is_idle());
+
+    // Waiting again on a retired submission is a no-op.
+    let done = unsafe {
+        context
+            .submissions
+            .wait(&context.device, 0, !0, &mut retired)
+    };
+    assert_eq!(done, Ok(true));
+    assert_eq!(retired.len(), 3);
+
+    let last = unsafe { context.submissions.maintain(&context.device, &mut retired) };
+    assert_eq!(last, Ok(Some(2)));
+    assert_eq!(retired.len(), 6);
+    assert_eq!(context.submissions.last_completed_token(), Some(3));
+}
+
+#[test]
+fn wait_for_unknown_submission() {
+    let mut context = Context::new();
+    context.submit(1, None);
+
+    let mut retired = Vec::new();
+    let done = unsafe {
+        context
+            .submissions
+            .wait(&context.device, 1, !0, &mut retired)
+    };
+    assert_eq!(done, Ok(false));
+    assert!(retired.is_empty());
+    assert!(!context.submissions.is_idle());
+}
+
+#[test]
+fn retire_every_frame() {
+    let mut context = Context::new();
+    let mut retired = Vec::new();
+    for frame in 0..4 {
+        let index = context.submit(1, Some(frame));
+        let done = unsafe {
+            context
+                .submissions
+                .wait(&context.device, index, !0, &mut retired)
+        };
+        assert_eq!(done, Ok(true));
+        assert_eq!(context.submissions.last_completed_token(), Some(frame));
+    }
+    assert_eq!(retired.len(), 4);
+    unsafe {
+        context.pool.free(retired.drain(..));
+        let submissions = std::mem::replace(&mut context.submissions, OwnedSubmissions::new());
+        submissions.destroy(&context.device, &mut retired);
+    }
+    assert!(retired.is_empty());
+}
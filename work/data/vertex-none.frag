@@ -0,0 +1,8 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+
+layout(location = 0) out uvec4 o_Color;
+
+void main() {
+    o_Color = uvec4(1, 2, 3, 4);
+}